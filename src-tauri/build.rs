@@ -7,16 +7,42 @@
 //!
 //! Creates `$OUT_DIR/embedded_schemas.rs` containing:
 //! - `MESSAGES_TOML: &str` - the full messages.toml content
+//! - `TABLES_TOML: &str` - the full tables.toml content
 //! - `SEGMENT_SCHEMAS: &[(&str, &str)]` - array of (segment_name, toml_content) tuples
+//! - `VERSION_OVERLAYS: &[(&str, &str)]` - array of (hl7_version, toml_content) tuples,
+//!   one per file in `data/versions/`
+//! - `SCHEMA_HASH: u64` - hash of all of the above, used to tell whether a binary
+//!   schema cache on disk was built from the same content
 //!
 //! # Rebuild Triggers
 //!
-//! The build script emits `cargo:rerun-if-changed` for messages.toml and all segment
-//! files, so modifying any schema file triggers recompilation.
+//! The build script emits `cargo:rerun-if-changed` for messages.toml, tables.toml, and
+//! all segment files, so modifying any schema file triggers recompilation. The versions
+//! directory is also watched so adding a new version overlay is picked up.
 
 use serde::Deserialize;
 use std::{collections::HashMap, env, fs, path::Path};
 
+/// FNV-1a hash, used instead of `DefaultHasher` because it must stay stable
+/// across Rust releases for binary schema caches to remain valid.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.0 ^= u64::from(*byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+        // separate consecutive values so ("ab", "c") and ("a", "bc") differ
+        self.0 ^= 0xff;
+        self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+    }
+}
+
 /// Partial deserialisation of messages.toml to extract segment file mappings.
 #[derive(Deserialize)]
 struct MessagesConfig {
@@ -35,6 +61,14 @@ fn main() {
     let config: MessagesConfig =
         toml::from_str(&messages_content).expect("can parse data/messages.toml");
 
+    let tables_path = data_dir.join("tables.toml");
+    println!("cargo:rerun-if-changed={}", tables_path.display());
+    let tables_content = fs::read_to_string(&tables_path).expect("can read data/tables.toml");
+
+    let mut hash = Fnv1a::new();
+    hash.write(messages_content.as_bytes());
+    hash.write(tables_content.as_bytes());
+
     // collect segment files and emit rerun-if-changed
     let mut segment_entries = Vec::new();
     for (segment_name, filename) in &config.segments {
@@ -63,11 +97,18 @@ fn main() {
         "pub const MESSAGES_TOML: &str = include_str!(\"{}\");\n\n",
         messages_path.display().to_string().replace('\\', "/")
     ));
+    generated.push_str("/// Embedded tables.toml content.\n");
+    generated.push_str(&format!(
+        "pub const TABLES_TOML: &str = include_str!(\"{}\");\n\n",
+        tables_path.display().to_string().replace('\\', "/")
+    ));
     generated.push_str("/// Embedded segment schema TOML content, keyed by segment name.\n");
     generated.push_str("pub const SEGMENT_SCHEMAS: &[(&str, &str)] = &[\n");
 
     for (segment_name, filename) in &segment_entries {
         let segment_path = data_dir.join(filename);
+        hash.write(segment_name.as_bytes());
+        hash.write(&fs::read(&segment_path).expect("can read segment schema file"));
         generated.push_str(&format!(
             "    (\"{}\", include_str!(\"{}\")),\n",
             segment_name,
@@ -75,7 +116,45 @@ fn main() {
         ));
     }
 
-    generated.push_str("];\n");
+    generated.push_str("];\n\n");
+
+    // collect version overlays, named after the version they describe (e.g. 2.3.1.toml)
+    let versions_dir = data_dir.join("versions");
+    println!("cargo:rerun-if-changed={}", versions_dir.display());
+
+    let mut version_entries = Vec::new();
+    if versions_dir.exists() {
+        for entry in fs::read_dir(&versions_dir).expect("can read data/versions") {
+            let path = entry.expect("can read data/versions entry").path();
+            if path.extension().and_then(|e| e.to_str()) != Some("toml") {
+                continue;
+            }
+            println!("cargo:rerun-if-changed={}", path.display());
+            let version = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .expect("version overlay file names are valid UTF-8")
+                .to_string();
+            version_entries.push((version, path));
+        }
+    }
+    version_entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    generated.push_str("/// Embedded version overlay TOML content, keyed by HL7 version.\n");
+    generated.push_str("pub const VERSION_OVERLAYS: &[(&str, &str)] = &[\n");
+    for (version, path) in &version_entries {
+        hash.write(version.as_bytes());
+        hash.write(&fs::read(path).expect("can read version overlay file"));
+        generated.push_str(&format!(
+            "    (\"{}\", include_str!(\"{}\")),\n",
+            version,
+            path.display().to_string().replace('\\', "/")
+        ));
+    }
+    generated.push_str("];\n\n");
+
+    generated.push_str("/// Hash of all embedded schema content, for validating binary caches.\n");
+    generated.push_str(&format!("pub const SCHEMA_HASH: u64 = {};\n", hash.0));
 
     let out_path = Path::new(&out_dir).join("embedded_schemas.rs");
     fs::write(&out_path, &generated).expect("can write embedded_schemas.rs");
@@ -0,0 +1,708 @@
+//! Export of communication audit events to a syslog or OTLP collector.
+//!
+//! When Hermes is shared across a test environment, it's useful to see what each
+//! instance sent and received alongside the rest of the environment's logs. With
+//! an exporter configured, every send, receive, and acknowledgement is recorded
+//! as an [`AuditEvent`] and forwarded to a central collector:
+//!
+//! * **Syslog** - RFC 5424 messages over UDP, or over TCP with octet-counting
+//!   framing (RFC 6587)
+//! * **OTLP** - OpenTelemetry log records as JSON over HTTP (`/v1/logs`)
+//!
+//! Events carry who and what (peer address, control ID, message type, ACK code),
+//! never message content, so no patient data leaves the machine this way.
+//! Scheduled sends aren't audited, as a soak test would swamp the collector.
+//!
+//! # Batching and Retry
+//!
+//! Events are queued and exported in batches: a batch goes out once it's full or
+//! the oldest queued event has waited for the flush interval. A failed export is
+//! retried with exponential backoff, and the batch is dropped (with an error in
+//! the application log) once the retries run out, so an unreachable collector
+//! never holds up sending or listening.
+//!
+//! # Lifecycle
+//!
+//! The frontend calls `configure_audit_export` at startup and whenever the
+//! setting changes. Replacing or disabling an exporter lets the old one export
+//! whatever it has queued before it finishes. On shutdown the app waits for
+//! the queue to drain before exiting.
+
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio::sync::mpsc;
+use tokio::time::{sleep, sleep_until, timeout, Instant};
+
+use super::control_ids::remember_control_id;
+use super::send::Environment;
+use crate::AppData;
+
+/// How long a single export attempt may take before it counts as failed.
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest wait between retries of a failed export.
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
+
+/// Structured data ID for syslog parameters (private enterprise number 32473 is
+/// reserved for documentation, per RFC 5612).
+const SYSLOG_SD_ID: &str = "hermes@32473";
+
+/// Collector protocol to export audit events with.
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditProtocol {
+    /// RFC 5424 syslog
+    Syslog,
+    /// OpenTelemetry logs over HTTP/JSON
+    Otlp,
+}
+
+/// Transport for syslog messages.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SyslogTransport {
+    /// One datagram per event
+    #[default]
+    Udp,
+    /// Octet-counted messages over a TCP connection per batch
+    Tcp,
+}
+
+/// Where and how to export audit events.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditExportConfig {
+    /// Collector protocol
+    pub protocol: AuditProtocol,
+    /// `host:port` for syslog, or an `http://` URL for OTLP (the path defaults
+    /// to `/v1/logs`)
+    pub endpoint: String,
+    /// Transport for syslog (ignored for OTLP)
+    #[serde(default)]
+    pub syslog_transport: SyslogTransport,
+    /// Maximum number of events per export
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+    /// Longest an event waits before its batch is exported, in seconds
+    #[serde(default = "default_flush_interval_seconds")]
+    pub flush_interval_seconds: f32,
+    /// Retries of a failed export before its batch is dropped
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+}
+
+fn default_batch_size() -> usize {
+    50
+}
+
+fn default_flush_interval_seconds() -> f32 {
+    5.0
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+/// What happened in an audited exchange.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditEventKind {
+    /// A message was handed to a remote server
+    MessageSent,
+    /// A message couldn't be delivered (connection or write failure)
+    SendFailed,
+    /// The remote server answered a sent message
+    AckReceived,
+    /// No answer to a sent message arrived within the wait timeout
+    AckTimeout,
+    /// The listener received a message
+    MessageReceived,
+    /// The listener acknowledged a received message
+    AckSent,
+}
+
+impl AuditEventKind {
+    /// Identifier used as the syslog MSGID and the OTLP `hermes.event` attribute.
+    fn name(self) -> &'static str {
+        match self {
+            AuditEventKind::MessageSent => "message-sent",
+            AuditEventKind::SendFailed => "send-failed",
+            AuditEventKind::AckReceived => "ack-received",
+            AuditEventKind::AckTimeout => "ack-timeout",
+            AuditEventKind::MessageReceived => "message-received",
+            AuditEventKind::AckSent => "ack-sent",
+        }
+    }
+
+    /// Whether the event records something going wrong.
+    fn is_failure(self) -> bool {
+        match self {
+            AuditEventKind::SendFailed | AuditEventKind::AckTimeout => true,
+            AuditEventKind::MessageSent
+            | AuditEventKind::AckReceived
+            | AuditEventKind::MessageReceived
+            | AuditEventKind::AckSent => false,
+        }
+    }
+}
+
+/// A single audited send, receive, or acknowledgement.
+#[derive(Debug, Clone)]
+pub struct AuditEvent {
+    /// When it happened
+    pub timestamp: jiff::Timestamp,
+    /// What happened
+    pub kind: AuditEventKind,
+    /// Address of the other side of the connection
+    pub peer: String,
+    /// MSH.10 of the message concerned
+    pub control_id: Option<String>,
+    /// MSH.9 of the message concerned (e.g. "ADT^A01")
+    pub message_type: Option<String>,
+    /// Connection preset label, for sends made through a preset
+    pub endpoint: Option<String>,
+    /// Environment of the connection preset, for sends made through one
+    pub environment: Option<Environment>,
+    /// ACK code (MSA.1) or error description
+    pub detail: Option<String>,
+}
+
+impl AuditEvent {
+    /// Create an event that happened now, with no optional details.
+    #[must_use]
+    pub fn new(kind: AuditEventKind, peer: impl ToString) -> Self {
+        AuditEvent {
+            timestamp: jiff::Timestamp::now(),
+            kind,
+            peer: peer.to_string(),
+            control_id: None,
+            message_type: None,
+            endpoint: None,
+            environment: None,
+            detail: None,
+        }
+    }
+
+    /// One-line human-readable description of the event.
+    fn summary(&self) -> String {
+        let control_id = self.control_id.as_deref().unwrap_or("<none>");
+        let detail = self.detail.as_deref().unwrap_or("unknown");
+        let peer = &self.peer;
+        match self.kind {
+            AuditEventKind::MessageSent => format!("Sent message {control_id} to {peer}"),
+            AuditEventKind::SendFailed => {
+                format!("Failed to send message {control_id} to {peer}: {detail}")
+            }
+            AuditEventKind::AckReceived => {
+                format!("Received {detail} acknowledgement for {control_id} from {peer}")
+            }
+            AuditEventKind::AckTimeout => {
+                format!("No acknowledgement for {control_id} from {peer}")
+            }
+            AuditEventKind::MessageReceived => {
+                format!("Received message {control_id} from {peer}")
+            }
+            AuditEventKind::AckSent => {
+                format!("Sent {detail} acknowledgement for {control_id} to {peer}")
+            }
+        }
+    }
+
+    /// Named details of the event, skipping those that aren't set.
+    fn attributes(&self) -> Vec<(&'static str, &str)> {
+        [
+            ("peer", Some(self.peer.as_str())),
+            ("controlId", self.control_id.as_deref()),
+            ("messageType", self.message_type.as_deref()),
+            ("endpoint", self.endpoint.as_deref()),
+            ("detail", self.detail.as_deref()),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|value| (name, value)))
+        .collect()
+    }
+}
+
+/// A running exporter: the queue feeding its background task.
+pub struct AuditExporter {
+    /// Queue of events waiting to be exported.
+    events: mpsc::UnboundedSender<AuditEvent>,
+    /// Handle to the exporting task.
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl AuditExporter {
+    /// Close the queue, and wait for the events already in it to be exported.
+    pub(crate) async fn finish(self) {
+        drop(self.events);
+        if let Err(e) = self.task.await {
+            log::warn!("Audit exporter task ended abnormally: {e}");
+        }
+    }
+}
+
+/// Start, replace, or stop exporting audit events.
+///
+/// # Arguments
+/// * `config` - Exporter settings, or none to stop exporting
+/// * `state` - Application state holding the running exporter
+///
+/// # Returns
+/// * `Ok(())` - The exporter was started or stopped
+/// * `Err(String)` - The endpoint isn't valid for the protocol
+#[tauri::command]
+pub async fn configure_audit_export(
+    config: Option<AuditExportConfig>,
+    state: State<'_, AppData>,
+) -> Result<(), String> {
+    let exporter = match config {
+        Some(config) => {
+            let sink = AuditSink::from_config(&config)?;
+            log::info!(
+                "Exporting audit events to {} ({:?})",
+                config.endpoint,
+                config.protocol
+            );
+            let (events, queue) = mpsc::unbounded_channel();
+            let task = tokio::spawn(run_exporter(queue, sink, config));
+            Some(AuditExporter { events, task })
+        }
+        None => None,
+    };
+
+    // dropping the old exporter's queue lets it export what it has and finish
+    *state.audit_export.lock().await = exporter;
+    Ok(())
+}
+
+/// Queue an event for export, if an exporter is configured.
+///
+/// Sends and receives are also remembered in the control ID history (see
+/// [`super::control_ids`]), whether or not they're exported.
+pub(crate) async fn record_audit_event(app: &AppHandle, event: AuditEvent) {
+    remember_control_id(app, &event);
+    let state = app.state::<AppData>();
+    let exporter = state.audit_export.lock().await;
+    if let Some(exporter) = exporter.as_ref() {
+        if exporter.events.send(event).is_err() {
+            log::warn!("Audit exporter has stopped; event not exported");
+        }
+    }
+}
+
+/// MSH.9 of a message, as written (e.g. "ADT^A01^ADT_A01").
+pub(crate) fn message_type_of(message: &hl7_parser::Message) -> Option<String> {
+    message
+        .query("MSH.9")
+        .map(|value| value.raw_value().to_string())
+        .filter(|value| !value.is_empty())
+}
+
+/// A validated export destination.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum AuditSink {
+    Syslog {
+        addr: String,
+        transport: SyslogTransport,
+    },
+    Otlp {
+        host: String,
+        port: u16,
+        path: String,
+    },
+}
+
+impl AuditSink {
+    /// Check the endpoint suits the protocol.
+    fn from_config(config: &AuditExportConfig) -> Result<Self, String> {
+        let endpoint = config.endpoint.trim();
+        match config.protocol {
+            AuditProtocol::Syslog => {
+                let valid = endpoint
+                    .rsplit_once(':')
+                    .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok());
+                if !valid {
+                    return Err(format!(
+                        "Syslog endpoint must be host:port, got `{endpoint}`"
+                    ));
+                }
+                Ok(AuditSink::Syslog {
+                    addr: endpoint.to_string(),
+                    transport: config.syslog_transport,
+                })
+            }
+            AuditProtocol::Otlp => {
+                let url = url::Url::parse(endpoint)
+                    .map_err(|e| format!("Invalid OTLP endpoint `{endpoint}`: {e}"))?;
+                if url.scheme() != "http" {
+                    return Err(format!(
+                        "OTLP endpoint must be an http:// URL, got `{endpoint}`"
+                    ));
+                }
+                let host = url
+                    .host_str()
+                    .ok_or_else(|| format!("OTLP endpoint `{endpoint}` has no host"))?;
+                let path = match url.path() {
+                    "" | "/" => "/v1/logs".to_string(),
+                    path => path.to_string(),
+                };
+                Ok(AuditSink::Otlp {
+                    host: host.to_string(),
+                    port: url.port().unwrap_or(4318),
+                    path,
+                })
+            }
+        }
+    }
+
+    /// Export one batch of events.
+    async fn export(&self, batch: &[AuditEvent]) -> Result<(), String> {
+        let export = async {
+            match self {
+                AuditSink::Syslog { addr, transport } => {
+                    let lines: Vec<String> = batch.iter().map(syslog_line).collect();
+                    send_syslog(addr, *transport, &lines).await
+                }
+                AuditSink::Otlp { host, port, path } => {
+                    post_json(host, *port, path, &otlp_body(batch).to_string()).await
+                }
+            }
+        };
+        timeout(EXPORT_TIMEOUT, export)
+            .await
+            .map_err(|_| format!("timed out after {EXPORT_TIMEOUT:?}"))?
+    }
+}
+
+/// Export queued events in batches until the queue is closed.
+async fn run_exporter(
+    mut queue: mpsc::UnboundedReceiver<AuditEvent>,
+    sink: AuditSink,
+    config: AuditExportConfig,
+) {
+    let batch_size = config.batch_size.max(1);
+    let flush_interval = Duration::from_secs_f32(config.flush_interval_seconds.max(0.1));
+    let mut batch = Vec::new();
+    // when the oldest queued event has waited long enough
+    let mut deadline: Option<Instant> = None;
+
+    loop {
+        let event = match deadline {
+            Some(flush_at) => tokio::select! {
+                event = queue.recv() => event,
+                () = sleep_until(flush_at) => {
+                    export_with_retry(&sink, &batch, config.max_retries).await;
+                    batch.clear();
+                    deadline = None;
+                    continue;
+                }
+            },
+            None => queue.recv().await,
+        };
+        let Some(event) = event else {
+            break;
+        };
+
+        batch.push(event);
+        deadline.get_or_insert_with(|| Instant::now() + flush_interval);
+        if batch.len() >= batch_size {
+            export_with_retry(&sink, &batch, config.max_retries).await;
+            batch.clear();
+            deadline = None;
+        }
+    }
+
+    if !batch.is_empty() {
+        export_with_retry(&sink, &batch, config.max_retries).await;
+    }
+    log::info!("Stopped exporting audit events to {}", config.endpoint);
+}
+
+/// Export a batch, retrying with exponential backoff, and drop it if every attempt fails.
+async fn export_with_retry(sink: &AuditSink, batch: &[AuditEvent], max_retries: u32) {
+    let mut delay = Duration::from_secs(1);
+    for attempt in 0..=max_retries {
+        match sink.export(batch).await {
+            Ok(()) => return,
+            Err(e) if attempt < max_retries => {
+                log::warn!(
+                    "Failed to export {} audit events: {e}; retrying in {delay:?}",
+                    batch.len()
+                );
+                sleep(delay).await;
+                delay = (delay * 2).min(MAX_RETRY_DELAY);
+            }
+            Err(e) => log::error!(
+                "Dropping {} audit events after {} failed exports: {e}",
+                batch.len(),
+                attempt + 1
+            ),
+        }
+    }
+}
+
+/// Name of this machine for the collector, if the environment says.
+fn hostname() -> Option<String> {
+    std::env::var("HOSTNAME")
+        .or_else(|_| std::env::var("COMPUTERNAME"))
+        .ok()
+        .filter(|name| !name.is_empty() && !name.contains(char::is_whitespace))
+}
+
+/// Format an event as an RFC 5424 syslog message.
+///
+/// Events use facility local0, with severity warning for failures and
+/// informational otherwise. Details go in structured data, and the summary is
+/// the free-form message.
+fn syslog_line(event: &AuditEvent) -> String {
+    const LOCAL0: u8 = 16;
+    let severity = if event.kind.is_failure() { 4 } else { 6 };
+    let priority = LOCAL0 * 8 + severity;
+
+    // RFC 5424 allows at most microsecond precision
+    let timestamp = event
+        .timestamp
+        .round(jiff::Unit::Microsecond)
+        .unwrap_or(event.timestamp);
+    let hostname = hostname().unwrap_or_else(|| "-".to_string());
+
+    let params: String = event
+        .attributes()
+        .into_iter()
+        .map(|(name, value)| format!(" {name}=\"{}\"", escape_sd_value(value)))
+        .collect();
+
+    format!(
+        "<{priority}>1 {timestamp} {hostname} hermes - {msgid} [{SYSLOG_SD_ID}{params}] {summary}",
+        msgid = event.kind.name(),
+        summary = event.summary(),
+    )
+}
+
+/// Escape a structured data parameter value (`"`, `\` and `]` must be escaped).
+fn escape_sd_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Send syslog messages over UDP (one datagram each) or TCP (octet-counted).
+async fn send_syslog(
+    addr: &str,
+    transport: SyslogTransport,
+    lines: &[String],
+) -> Result<(), String> {
+    match transport {
+        SyslogTransport::Udp => {
+            let target: SocketAddr = tokio::net::lookup_host(addr)
+                .await
+                .map_err(|e| format!("failed to resolve {addr}: {e}"))?
+                .next()
+                .ok_or_else(|| format!("no address found for {addr}"))?;
+            let bind: SocketAddr = if target.is_ipv4() {
+                ([0, 0, 0, 0], 0).into()
+            } else {
+                ([0u16; 8], 0).into()
+            };
+            let socket = UdpSocket::bind(bind)
+                .await
+                .map_err(|e| format!("failed to open UDP socket: {e}"))?;
+            for line in lines {
+                socket
+                    .send_to(line.as_bytes(), target)
+                    .await
+                    .map_err(|e| format!("failed to send to {addr}: {e}"))?;
+            }
+        }
+        SyslogTransport::Tcp => {
+            let mut stream = TcpStream::connect(addr)
+                .await
+                .map_err(|e| format!("failed to connect to {addr}: {e}"))?;
+            for line in lines {
+                let framed = format!("{} {line}", line.len());
+                stream
+                    .write_all(framed.as_bytes())
+                    .await
+                    .map_err(|e| format!("failed to send to {addr}: {e}"))?;
+            }
+            stream
+                .flush()
+                .await
+                .map_err(|e| format!("failed to send to {addr}: {e}"))?;
+        }
+    }
+    Ok(())
+}
+
+/// Build an OTLP `ExportLogsServiceRequest` for a batch of events.
+fn otlp_body(batch: &[AuditEvent]) -> serde_json::Value {
+    let string_attribute = |key: &str, value: &str| serde_json::json!({ "key": key, "value": { "stringValue": value } });
+
+    let mut resource_attributes = vec![
+        string_attribute("service.name", "hermes"),
+        string_attribute("service.version", env!("CARGO_PKG_VERSION")),
+    ];
+    if let Some(hostname) = hostname() {
+        resource_attributes.push(string_attribute("host.name", &hostname));
+    }
+
+    let records: Vec<serde_json::Value> = batch
+        .iter()
+        .map(|event| {
+            let (severity_number, severity_text) = if event.kind.is_failure() {
+                (13, "WARN")
+            } else {
+                (9, "INFO")
+            };
+            let mut attributes = vec![string_attribute("hermes.event", event.kind.name())];
+            attributes.extend(
+                event
+                    .attributes()
+                    .into_iter()
+                    .map(|(name, value)| string_attribute(&format!("hermes.{name}"), value)),
+            );
+            serde_json::json!({
+                "timeUnixNano": event.timestamp.as_nanosecond().to_string(),
+                "severityNumber": severity_number,
+                "severityText": severity_text,
+                "body": { "stringValue": event.summary() },
+                "attributes": attributes,
+            })
+        })
+        .collect();
+
+    serde_json::json!({
+        "resourceLogs": [{
+            "resource": { "attributes": resource_attributes },
+            "scopeLogs": [{
+                "scope": { "name": "hermes.audit" },
+                "logRecords": records,
+            }],
+        }],
+    })
+}
+
+/// POST a JSON body over plain HTTP/1.1, succeeding on any 2xx status.
+async fn post_json(host: &str, port: u16, path: &str, body: &str) -> Result<(), String> {
+    let mut stream = TcpStream::connect((host, port))
+        .await
+        .map_err(|e| format!("failed to connect to {host}:{port}: {e}"))?;
+    let request = format!(
+        "POST {path} HTTP/1.1\r\nHost: {host}:{port}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .map_err(|e| format!("failed to send to {host}:{port}: {e}"))?;
+
+    let mut response = Vec::new();
+    stream
+        .read_to_end(&mut response)
+        .await
+        .map_err(|e| format!("failed to read response from {host}:{port}: {e}"))?;
+    match http_status(&response) {
+        Some(status) if (200..300).contains(&status) => Ok(()),
+        Some(status) => Err(format!("collector answered with HTTP {status}")),
+        None => Err("collector sent an invalid HTTP response".to_string()),
+    }
+}
+
+/// Status code from the status line of an HTTP response.
+fn http_status(response: &[u8]) -> Option<u16> {
+    let status_line = response.split(|&b| b == b'\n').next()?;
+    let status_line = std::str::from_utf8(status_line).ok()?;
+    let mut parts = status_line.split_whitespace();
+    parts
+        .next()
+        .filter(|version| version.starts_with("HTTP/"))?;
+    parts.next()?.parse().ok()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    fn event() -> AuditEvent {
+        AuditEvent {
+            timestamp: "2025-03-01T12:30:45.123456789Z".parse().unwrap(),
+            control_id: Some("ABC123".to_string()),
+            message_type: Some("ADT^A01".to_string()),
+            detail: Some("AA".to_string()),
+            ..AuditEvent::new(AuditEventKind::AckReceived, "10.0.0.5:2575")
+        }
+    }
+
+    fn config(protocol: AuditProtocol, endpoint: &str) -> AuditExportConfig {
+        AuditExportConfig {
+            protocol,
+            endpoint: endpoint.to_string(),
+            syslog_transport: SyslogTransport::Udp,
+            batch_size: default_batch_size(),
+            flush_interval_seconds: default_flush_interval_seconds(),
+            max_retries: default_max_retries(),
+        }
+    }
+
+    #[test]
+    fn formats_rfc5424_syslog_lines() {
+        let line = syslog_line(&event());
+        assert!(
+            line.starts_with("<134>1 2025-03-01T12:30:45.123457Z "),
+            "{line}"
+        );
+        assert!(line.contains(" hermes - ack-received [hermes@32473 peer=\"10.0.0.5:2575\" controlId=\"ABC123\" messageType=\"ADT^A01\" detail=\"AA\"] "), "{line}");
+        assert!(line.ends_with("Received AA acknowledgement for ABC123 from 10.0.0.5:2575"));
+
+        let failed = AuditEvent::new(AuditEventKind::SendFailed, "host:1");
+        assert!(syslog_line(&failed).starts_with("<132>1 "));
+        assert_eq!(escape_sd_value(r#"a"b\c]"#), r#"a\"b\\c\]"#);
+    }
+
+    #[test]
+    fn builds_otlp_log_records() {
+        let body = otlp_body(&[event()]);
+        let record = &body["resourceLogs"][0]["scopeLogs"][0]["logRecords"][0];
+        assert_eq!(record["severityText"], "INFO");
+        assert_eq!(record["timeUnixNano"], "1740832245123456789");
+        let attributes = record["attributes"].as_array().unwrap();
+        assert!(attributes.contains(&serde_json::json!({
+            "key": "hermes.controlId",
+            "value": { "stringValue": "ABC123" }
+        })));
+    }
+
+    #[test]
+    fn validates_endpoints() {
+        assert_eq!(
+            AuditSink::from_config(&config(AuditProtocol::Otlp, "http://collector:4318")),
+            Ok(AuditSink::Otlp {
+                host: "collector".to_string(),
+                port: 4318,
+                path: "/v1/logs".to_string(),
+            })
+        );
+        assert!(AuditSink::from_config(&config(AuditProtocol::Otlp, "https://collector")).is_err());
+        assert!(AuditSink::from_config(&config(AuditProtocol::Syslog, "logs:514")).is_ok());
+        assert!(AuditSink::from_config(&config(AuditProtocol::Syslog, "logs")).is_err());
+    }
+
+    #[test]
+    fn reads_http_status() {
+        assert_eq!(http_status(b"HTTP/1.1 200 OK\r\n\r\n"), Some(200));
+        assert_eq!(
+            http_status(b"HTTP/1.1 503 Service Unavailable\r\n"),
+            Some(503)
+        );
+        assert_eq!(http_status(b"garbage"), None);
+    }
+}
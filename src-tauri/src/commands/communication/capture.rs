@@ -0,0 +1,846 @@
+//! Import of HL7 messages from network captures.
+//!
+//! When a production interface misbehaves, a packet capture taken on the
+//! engine or a span port is often all there is to go on. This module reads a
+//! pcap or pcapng file, puts the TCP streams in it back together, and pulls
+//! out the MLLP-framed messages, each with when it was sent and which
+//! endpoints it passed between.
+//!
+//! # Supported Captures
+//!
+//! * **pcap** - Microsecond and nanosecond resolution, in either byte order
+//! * **pcapng** - Enhanced and simple packet blocks, with each interface's
+//!   link type and timestamp resolution
+//!
+//! Packets are read from Ethernet (with 802.1Q VLAN tags), Linux cooked
+//! captures (SLL and SLL2), BSD loopback, and raw IP link types, over IPv4 or
+//! IPv6. Other link types, fragmented IP packets, and non-TCP traffic are
+//! skipped.
+//!
+//! # Reassembly
+//!
+//! Each direction of a TCP connection is a separate stream, so a message and
+//! the ACK answering it are both found. Segments are put in sequence order,
+//! with retransmitted and overlapping data dropped. A stream starts at its SYN
+//! if the capture has it, or at the first segment with data otherwise, and a
+//! new SYN on the same addresses and ports starts a new stream.
+//!
+//! Data missing from a stream (a gap in the sequence numbers, usually from
+//! packets the capture dropped) is reported as a warning; the framing of the
+//! next message lets extraction carry on after it.
+//!
+//! # Timestamps
+//!
+//! A message's timestamp is when the packet carrying its end block was
+//! captured, i.e. when the whole message had arrived.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::PathBuf;
+
+use serde::Serialize;
+
+use super::encoding::MessageEncoding;
+use super::framing::Framing;
+
+/// Magic number of a pcap file written in its reader's byte order, with
+/// microsecond timestamps.
+const PCAP_MICROS: u32 = 0xa1b2_c3d4;
+
+/// Magic number of a pcap file with nanosecond timestamps.
+const PCAP_NANOS: u32 = 0xa1b2_3c4d;
+
+/// Block type of a pcapng section header, which starts the file.
+const PCAPNG_SECTION_HEADER: u32 = 0x0a0d_0d0a;
+
+/// Byte-order magic of a pcapng section header.
+const PCAPNG_BYTE_ORDER: u32 = 0x1a2b_3c4d;
+
+/// TCP flags.
+const TCP_SYN: u8 = 0x02;
+
+/// A message found in a capture.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CapturedMessage {
+    /// The message, decoded and without its MLLP framing
+    pub message: String,
+    /// When the packet completing the message was captured
+    pub timestamp: jiff::Timestamp,
+    /// Address and port the message was sent from
+    pub source: String,
+    /// Address and port the message was sent to
+    pub destination: String,
+    /// MSH.9, if present
+    pub message_type: Option<String>,
+    /// MSH.10, if present
+    pub control_id: Option<String>,
+}
+
+/// The messages found in a capture.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureImport {
+    /// The messages, in the order they were completed
+    pub messages: Vec<CapturedMessage>,
+    /// Number of TCP streams carrying data
+    pub streams: usize,
+    /// Problems that didn't stop the import, such as missing data
+    pub warnings: Vec<String>,
+}
+
+/// Extract the HL7 messages from a pcap or pcapng capture.
+///
+/// # Arguments
+/// * `path` - Path to the capture file
+/// * `encoding` - Character encoding of the messages (UTF-8 if not given)
+/// * `framing` - MLLP framing of the messages (standard framing if not given)
+///
+/// # Returns
+/// * `Ok(CaptureImport)` - The messages found, and any warnings
+/// * `Err(String)` - If the file can't be read, isn't a capture, or the
+///   framing is invalid
+#[tauri::command]
+pub async fn import_capture(
+    path: PathBuf,
+    encoding: Option<MessageEncoding>,
+    framing: Option<Framing>,
+) -> Result<CaptureImport, String> {
+    let framing = framing.unwrap_or_default();
+    framing.validate()?;
+    let bytes = tokio::fs::read(&path)
+        .await
+        .map_err(|e| format!("Failed to read capture file: {e}"))?;
+    tokio::task::spawn_blocking(move || {
+        read_capture(&bytes, encoding.unwrap_or_default(), &framing)
+    })
+    .await
+    .map_err(|e| format!("Failed to read capture: {e}"))?
+}
+
+/// Extract the HL7 messages from the bytes of a capture.
+fn read_capture(
+    bytes: &[u8],
+    encoding: MessageEncoding,
+    framing: &Framing,
+) -> Result<CaptureImport, String> {
+    let mut import = CaptureImport::default();
+    let packets = read_packets(bytes, &mut import.warnings)?;
+
+    let mut streams = Streams::default();
+    let mut skipped_fragments = 0usize;
+    for packet in &packets {
+        match tcp_segment(packet) {
+            Some(Ok(segment)) => streams.add(segment, packet.timestamp),
+            Some(Err(Fragmented)) => skipped_fragments += 1,
+            None => {}
+        }
+    }
+    if skipped_fragments > 0 {
+        import.warnings.push(format!(
+            "Skipped {skipped_fragments} fragmented IP packet(s)"
+        ));
+    }
+
+    let streams = streams.finish();
+    import.streams = streams.len();
+    for stream in streams {
+        stream.extract(encoding, framing, &mut import);
+    }
+    // stable, so messages completed by the same packet keep their order
+    import.messages.sort_by_key(|message| message.timestamp);
+    Ok(import)
+}
+
+/// A captured packet.
+struct Packet<'a> {
+    /// When the packet was captured
+    timestamp: jiff::Timestamp,
+    /// Link-layer header type of the interface it was captured on
+    link_type: u32,
+    /// The packet, from its link-layer header
+    data: &'a [u8],
+}
+
+/// Read the packets of a pcap or pcapng file.
+fn read_packets<'a>(
+    bytes: &'a [u8],
+    warnings: &mut Vec<String>,
+) -> Result<Vec<Packet<'a>>, String> {
+    let magic = read_u32(bytes, 0, false).ok_or("The file is too short to be a capture")?;
+    if magic == PCAPNG_SECTION_HEADER {
+        return Ok(read_pcapng(bytes, warnings));
+    }
+    let (big_endian, nanos) = match (magic, magic.swap_bytes()) {
+        (PCAP_MICROS, _) => (false, false),
+        (PCAP_NANOS, _) => (false, true),
+        (_, PCAP_MICROS) => (true, false),
+        (_, PCAP_NANOS) => (true, true),
+        _ => return Err("The file isn't a pcap or pcapng capture".to_string()),
+    };
+    Ok(read_pcap(bytes, big_endian, nanos, warnings))
+}
+
+/// Read the packets of a pcap file.
+fn read_pcap<'a>(
+    bytes: &'a [u8],
+    big_endian: bool,
+    nanos: bool,
+    warnings: &mut Vec<String>,
+) -> Vec<Packet<'a>> {
+    let link_type = read_u32(bytes, 20, big_endian).unwrap_or_default();
+    let mut packets = Vec::new();
+    let mut offset = 24;
+    while offset < bytes.len() {
+        let header = (
+            read_u32(bytes, offset, big_endian),
+            read_u32(bytes, offset + 4, big_endian),
+            read_u32(bytes, offset + 8, big_endian),
+        );
+        let (Some(seconds), Some(fraction), Some(length)) = header else {
+            warnings.push("The capture ends partway through a packet".to_string());
+            break;
+        };
+        let start = offset + 16;
+        let Some(data) = bytes.get(start..start + length as usize) else {
+            warnings.push("The capture ends partway through a packet".to_string());
+            break;
+        };
+        let nanoseconds = if nanos {
+            i128::from(fraction)
+        } else {
+            i128::from(fraction) * 1_000
+        };
+        packets.push(Packet {
+            timestamp: timestamp(i128::from(seconds) * 1_000_000_000 + nanoseconds),
+            link_type,
+            data,
+        });
+        offset = start + length as usize;
+    }
+    packets
+}
+
+/// An interface described in a pcapng section.
+struct Interface {
+    link_type: u32,
+    /// How long a timestamp tick is
+    resolution: Resolution,
+}
+
+/// Length of a pcapng timestamp tick.
+#[derive(Clone, Copy)]
+enum Resolution {
+    /// Each tick is this many nanoseconds
+    Nanoseconds(i128),
+    /// Each nanosecond is this many ticks
+    PerNanosecond(i128),
+}
+
+/// Read the packets of a pcapng file.
+fn read_pcapng<'a>(bytes: &'a [u8], warnings: &mut Vec<String>) -> Vec<Packet<'a>> {
+    let mut packets = Vec::new();
+    let mut interfaces: Vec<Interface> = Vec::new();
+    let mut big_endian = false;
+    let mut last_timestamp = jiff::Timestamp::UNIX_EPOCH;
+    let mut offset = 0;
+
+    while offset < bytes.len() {
+        let Some(block_type) = read_u32(bytes, offset, big_endian) else {
+            break;
+        };
+        if block_type == PCAPNG_SECTION_HEADER {
+            // each section sets its own byte order and interfaces
+            big_endian = read_u32(bytes, offset + 8, false) != Some(PCAPNG_BYTE_ORDER);
+            interfaces.clear();
+        }
+        let length = read_u32(bytes, offset + 4, big_endian).unwrap_or_default() as usize;
+        let Some(block) = bytes.get(offset..offset + length).filter(|_| length >= 12) else {
+            warnings.push("The capture ends partway through a block".to_string());
+            break;
+        };
+        let body = block.get(8..length - 4).unwrap_or_default();
+
+        match block_type {
+            // interface description
+            1 => interfaces.push(Interface {
+                link_type: u32::from(read_u16(body, 0, big_endian).unwrap_or_default()),
+                resolution: interface_resolution(body.get(8..).unwrap_or_default(), big_endian),
+            }),
+            // enhanced packet
+            6 => {
+                let interface =
+                    read_u32(body, 0, big_endian).and_then(|id| interfaces.get(id as usize));
+                let high = read_u32(body, 4, big_endian).unwrap_or_default();
+                let low = read_u32(body, 8, big_endian).unwrap_or_default();
+                let captured = read_u32(body, 12, big_endian).unwrap_or_default() as usize;
+                match (interface, body.get(20..20 + captured)) {
+                    (Some(interface), Some(data)) => {
+                        let ticks = (i128::from(high) << 32) | i128::from(low);
+                        let nanoseconds = match interface.resolution {
+                            Resolution::Nanoseconds(tick) => ticks * tick,
+                            Resolution::PerNanosecond(ticks_per) => ticks / ticks_per,
+                        };
+                        last_timestamp = timestamp(nanoseconds);
+                        packets.push(Packet {
+                            timestamp: last_timestamp,
+                            link_type: interface.link_type,
+                            data,
+                        });
+                    }
+                    (None, _) | (_, None) => {
+                        warnings.push("Skipped a malformed packet block".to_string());
+                    }
+                }
+            }
+            // simple packet, which has no timestamp and is always on the first interface
+            3 => {
+                let captured = body.len().saturating_sub(4);
+                let original = read_u32(body, 0, big_endian).unwrap_or_default() as usize;
+                if let (Some(interface), Some(data)) =
+                    (interfaces.first(), body.get(4..4 + captured.min(original)))
+                {
+                    packets.push(Packet {
+                        timestamp: last_timestamp,
+                        link_type: interface.link_type,
+                        data,
+                    });
+                }
+            }
+            // section headers, statistics, name resolution, and so on
+            _ => {}
+        }
+        offset += length;
+    }
+    packets
+}
+
+/// Read the timestamp resolution from an interface description's options
+/// (microseconds unless it has an `if_tsresol`).
+fn interface_resolution(mut options: &[u8], big_endian: bool) -> Resolution {
+    const IF_TSRESOL: u16 = 9;
+    while let (Some(code), Some(length)) = (
+        read_u16(options, 0, big_endian),
+        read_u16(options, 2, big_endian),
+    ) {
+        if code == 0 {
+            break;
+        }
+        if code == IF_TSRESOL {
+            if let Some(&resolution) = options.get(4) {
+                let exponent = u32::from(resolution & 0x7f);
+                let base: i128 = if resolution & 0x80 == 0 { 10 } else { 2 };
+                let ticks_per_second = base.checked_pow(exponent).unwrap_or(i128::MAX);
+                return if ticks_per_second <= 1_000_000_000 {
+                    Resolution::Nanoseconds(1_000_000_000 / ticks_per_second.max(1))
+                } else {
+                    Resolution::PerNanosecond(ticks_per_second / 1_000_000_000)
+                };
+            }
+        }
+        let padded = (usize::from(length) + 3) & !3;
+        options = options.get(4 + padded..).unwrap_or_default();
+    }
+    Resolution::Nanoseconds(1_000)
+}
+
+/// The data of a TCP segment and where it went.
+struct TcpSegment<'a> {
+    source: SocketAddr,
+    destination: SocketAddr,
+    sequence: u32,
+    syn: bool,
+    data: &'a [u8],
+}
+
+/// Marks an IP packet that is one fragment of a larger one.
+struct Fragmented;
+
+/// Read the TCP segment in a packet, if it carries one.
+fn tcp_segment<'a>(packet: &Packet<'a>) -> Option<Result<TcpSegment<'a>, Fragmented>> {
+    let ip = link_payload(packet.link_type, packet.data)?;
+    let (source, destination, tcp) = match ip.first()? >> 4 {
+        4 => {
+            let header_length = usize::from(ip.first()? & 0x0f) * 4;
+            let total_length = usize::from(read_u16(ip, 2, true)?);
+            let fragment = read_u16(ip, 6, true)?;
+            if *ip.get(9)? != 6 {
+                return None;
+            }
+            // more fragments, or a fragment offset
+            if fragment & 0x3fff != 0 {
+                return Some(Err(Fragmented));
+            }
+            let source: [u8; 4] = ip.get(12..16)?.try_into().ok()?;
+            let destination: [u8; 4] = ip.get(16..20)?.try_into().ok()?;
+            (
+                IpAddr::V4(Ipv4Addr::from(source)),
+                IpAddr::V4(Ipv4Addr::from(destination)),
+                // trims link-layer padding off short packets
+                ip.get(header_length..total_length.min(ip.len()))?,
+            )
+        }
+        6 => {
+            let payload_length = usize::from(read_u16(ip, 4, true)?);
+            let source: [u8; 16] = ip.get(8..24)?.try_into().ok()?;
+            let destination: [u8; 16] = ip.get(24..40)?.try_into().ok()?;
+            let mut next_header = *ip.get(6)?;
+            let mut payload = ip.get(40..(40 + payload_length).min(ip.len()))?;
+            loop {
+                match next_header {
+                    6 => break,
+                    // hop-by-hop, routing, and destination options
+                    0 | 43 | 60 => {
+                        next_header = *payload.first()?;
+                        let length = (usize::from(*payload.get(1)?) + 1) * 8;
+                        payload = payload.get(length..)?;
+                    }
+                    44 => return Some(Err(Fragmented)),
+                    _ => return None,
+                }
+            }
+            (
+                IpAddr::V6(Ipv6Addr::from(source)),
+                IpAddr::V6(Ipv6Addr::from(destination)),
+                payload,
+            )
+        }
+        _ => return None,
+    };
+
+    let header_length = usize::from(tcp.get(12)? >> 4) * 4;
+    Some(Ok(TcpSegment {
+        source: SocketAddr::new(source, read_u16(tcp, 0, true)?),
+        destination: SocketAddr::new(destination, read_u16(tcp, 2, true)?),
+        sequence: read_u32(tcp, 4, true)?,
+        syn: tcp.get(13)? & TCP_SYN != 0,
+        data: tcp.get(header_length..)?,
+    }))
+}
+
+/// The IP packet in a link-layer frame.
+fn link_payload(link_type: u32, frame: &[u8]) -> Option<&[u8]> {
+    match link_type {
+        // BSD loopback, whose 4-byte address family is in the capturing host's
+        // byte order, so the IP version is checked instead
+        0 | 109 => frame.get(4..),
+        // Ethernet, skipping any VLAN tags
+        1 => {
+            let mut offset = 12;
+            while matches!(read_u16(frame, offset, true)?, 0x8100 | 0x88a8) {
+                offset += 4;
+            }
+            match read_u16(frame, offset, true)? {
+                0x0800 | 0x86dd => frame.get(offset + 2..),
+                _ => None,
+            }
+        }
+        // raw IP
+        12 | 14 | 101 | 228 | 229 => Some(frame),
+        // Linux cooked capture
+        113 => frame.get(16..),
+        // Linux cooked capture v2
+        276 => frame.get(20..),
+        _ => None,
+    }
+}
+
+/// One direction of a TCP connection.
+struct Stream {
+    source: SocketAddr,
+    destination: SocketAddr,
+    /// Sequence number of the stream's first byte, once known
+    start: Option<u32>,
+    /// Data segments, as (sequence number, capture time, data)
+    segments: Vec<(u32, jiff::Timestamp, Vec<u8>)>,
+}
+
+/// The TCP streams in a capture, by source and destination.
+#[derive(Default)]
+struct Streams {
+    open: HashMap<(SocketAddr, SocketAddr), Stream>,
+    finished: Vec<Stream>,
+}
+
+impl Streams {
+    /// Add a segment to its stream, starting a new one on a SYN.
+    fn add(&mut self, segment: TcpSegment, timestamp: jiff::Timestamp) {
+        let key = (segment.source, segment.destination);
+        if segment.syn {
+            if let Some(stream) = self.open.remove(&key) {
+                self.finished.push(stream);
+            }
+        }
+        let stream = self.open.entry(key).or_insert_with(|| Stream {
+            source: segment.source,
+            destination: segment.destination,
+            start: None,
+            segments: Vec::new(),
+        });
+        if segment.syn {
+            stream.start = Some(segment.sequence.wrapping_add(1));
+        }
+        if !segment.data.is_empty() {
+            stream.start.get_or_insert(segment.sequence);
+            stream
+                .segments
+                .push((segment.sequence, timestamp, segment.data.to_vec()));
+        }
+    }
+
+    /// The streams that carried data, in the order they started.
+    fn finish(mut self) -> Vec<Stream> {
+        self.finished.extend(self.open.into_values());
+        self.finished.retain(|stream| !stream.segments.is_empty());
+        self.finished.sort_by_key(|stream| {
+            stream
+                .segments
+                .iter()
+                .map(|(_, timestamp, _)| *timestamp)
+                .min()
+        });
+        self.finished
+    }
+}
+
+impl Stream {
+    /// Reassemble the stream and add the messages in it to an import.
+    fn extract(self, encoding: MessageEncoding, framing: &Framing, import: &mut CaptureImport) {
+        let start = self.start.unwrap_or_default();
+        let label = format!("{} → {}", self.source, self.destination);
+
+        // put segments in order by their offset into the stream; data from
+        // before the start (retransmitted from an earlier capture) wraps
+        // around to a huge offset and is dropped below
+        let mut segments: Vec<(u64, jiff::Timestamp, Vec<u8>)> = self
+            .segments
+            .into_iter()
+            .map(|(sequence, timestamp, data)| {
+                (u64::from(sequence.wrapping_sub(start)), timestamp, data)
+            })
+            .filter(|(offset, _, _)| *offset < u64::from(u32::MAX / 2))
+            .collect();
+        segments.sort_by_key(|(offset, _, _)| *offset);
+
+        let mut data = Vec::new();
+        // (offset into `data`, capture time) of each segment's first new byte
+        let mut times: Vec<(usize, jiff::Timestamp)> = Vec::new();
+        let mut missing = 0u64;
+        for (offset, timestamp, bytes) in segments {
+            let next = data.len() as u64;
+            let end = offset + bytes.len() as u64;
+            if end <= next {
+                continue;
+            }
+            if offset > next {
+                missing += offset - next;
+            }
+            let skip = usize::try_from(next.saturating_sub(offset)).unwrap_or_default();
+            times.push((data.len(), timestamp));
+            data.extend_from_slice(bytes.get(skip..).unwrap_or_default());
+        }
+        if missing > 0 {
+            import.warnings.push(format!(
+                "{missing} byte(s) missing from {label}; messages around the gap may be incomplete"
+            ));
+        }
+
+        let time_at = |offset: usize| {
+            let index = times.partition_point(|(start, _)| *start <= offset);
+            times
+                .get(index.saturating_sub(1))
+                .map(|(_, timestamp)| *timestamp)
+                .unwrap_or(jiff::Timestamp::UNIX_EPOCH)
+        };
+
+        let start_block = framing.start_block.as_slice();
+        let end_block = framing.end_block.as_slice();
+        let mut position = 0;
+        while let Some(frame_start) = find(data.get(position..).unwrap_or_default(), start_block) {
+            let mut body_start = position + frame_start + start_block.len();
+            let Some(length) = find(data.get(body_start..).unwrap_or_default(), end_block) else {
+                import
+                    .warnings
+                    .push(format!("{label} ends partway through a message"));
+                break;
+            };
+            let end = body_start + length + end_block.len();
+            let mut body = data
+                .get(body_start..body_start + length)
+                .unwrap_or_default();
+            // another start block before the end block means the end of the
+            // message before it is missing, so only the last one is kept
+            if let Some(restart) = rfind(body, start_block) {
+                import
+                    .warnings
+                    .push(format!("Skipped an incomplete message from {label}"));
+                body_start += restart + start_block.len();
+                body = data
+                    .get(body_start..end - end_block.len())
+                    .unwrap_or_default();
+            }
+            match encoding.decode(body) {
+                Ok(message) => {
+                    let message_type = msh_field(&message, 9);
+                    let control_id = msh_field(&message, 10);
+                    import.messages.push(CapturedMessage {
+                        message,
+                        timestamp: time_at(end - 1),
+                        source: self.source.to_string(),
+                        destination: self.destination.to_string(),
+                        message_type,
+                        control_id,
+                    });
+                }
+                Err(e) => import
+                    .warnings
+                    .push(format!("Skipped a message from {label}: {e}")),
+            }
+            position = end;
+        }
+    }
+}
+
+/// A field of a message's MSH segment, if present and not empty.
+fn msh_field(message: &str, number: usize) -> Option<String> {
+    let msh = message.split(['\r', '\n']).next()?;
+    let separator = msh.chars().nth(3)?;
+    msh.starts_with("MSH")
+        .then(|| msh.split(separator).nth(number - 1))
+        .flatten()
+        .filter(|value| !value.is_empty())
+        .map(ToString::to_string)
+}
+
+/// A capture time from nanoseconds since the Unix epoch.
+fn timestamp(nanoseconds: i128) -> jiff::Timestamp {
+    jiff::Timestamp::from_nanosecond(nanoseconds).unwrap_or(jiff::Timestamp::UNIX_EPOCH)
+}
+
+/// Find the first occurrence of `needle` in `haystack`.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return None;
+    }
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// Find the last occurrence of `needle` in `haystack`.
+fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return None;
+    }
+    haystack
+        .windows(needle.len())
+        .rposition(|window| window == needle)
+}
+
+/// Read a `u16` at an offset.
+fn read_u16(bytes: &[u8], offset: usize, big_endian: bool) -> Option<u16> {
+    let bytes: [u8; 2] = bytes.get(offset..offset + 2)?.try_into().ok()?;
+    Some(if big_endian {
+        u16::from_be_bytes(bytes)
+    } else {
+        u16::from_le_bytes(bytes)
+    })
+}
+
+/// Read a `u32` at an offset.
+fn read_u32(bytes: &[u8], offset: usize, big_endian: bool) -> Option<u32> {
+    let bytes: [u8; 4] = bytes.get(offset..offset + 4)?.try_into().ok()?;
+    Some(if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const ADT: &[u8] = b"MSH|^~\\&|ADT|HOSP|LAB|HOSP|20250114||ADT^A01|MSG001|P|2.5.1\rPID|1||12345^^^MRN||DOE^JOHN";
+    const ACK: &[u8] =
+        b"MSH|^~\\&|LAB|HOSP|ADT|HOSP|20250114||ACK^A01|ACK001|P|2.5.1\rMSA|AA|MSG001";
+
+    /// An Ethernet frame holding an IPv4 TCP segment.
+    fn frame(
+        source: ([u8; 4], u16),
+        destination: ([u8; 4], u16),
+        seq: u32,
+        syn: bool,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&[0x08, 0x00]);
+        let total = 20 + 20 + data.len();
+        frame.extend_from_slice(&[
+            0x45,
+            0,
+            (total >> 8) as u8,
+            total as u8,
+            0,
+            0,
+            0x40,
+            0,
+            64,
+            6,
+            0,
+            0,
+        ]);
+        frame.extend_from_slice(&source.0);
+        frame.extend_from_slice(&destination.0);
+        frame.extend_from_slice(&source.1.to_be_bytes());
+        frame.extend_from_slice(&destination.1.to_be_bytes());
+        frame.extend_from_slice(&seq.to_be_bytes());
+        frame.extend_from_slice(&[
+            0,
+            0,
+            0,
+            0,
+            0x50,
+            if syn { 0x02 } else { 0x18 },
+            0xff,
+            0xff,
+            0,
+            0,
+            0,
+            0,
+        ]);
+        frame.extend_from_slice(data);
+        frame
+    }
+
+    /// A little-endian microsecond pcap file holding Ethernet frames.
+    fn pcap(packets: &[(u32, Vec<u8>)]) -> Vec<u8> {
+        let mut file = Vec::new();
+        file.extend_from_slice(&PCAP_MICROS.to_le_bytes());
+        file.extend_from_slice(&[
+            2, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff, 0, 0, 1, 0, 0, 0,
+        ]);
+        for (seconds, frame) in packets {
+            file.extend_from_slice(&seconds.to_le_bytes());
+            file.extend_from_slice(&0u32.to_le_bytes());
+            file.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            file.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            file.extend_from_slice(frame);
+        }
+        file
+    }
+
+    fn framed(message: &[u8]) -> Vec<u8> {
+        [b"\x0b".as_slice(), message, b"\x1c\r"].concat()
+    }
+
+    const CLIENT: ([u8; 4], u16) = ([10, 0, 0, 1], 40000);
+    const SERVER: ([u8; 4], u16) = ([10, 0, 0, 2], 2575);
+
+    #[test]
+    fn reassembles_messages_split_across_packets() {
+        let adt = framed(ADT);
+        let (first, second) = adt.split_at(20);
+        let file = pcap(&[
+            (100, frame(CLIENT, SERVER, 999, true, &[])),
+            // out of order, with a retransmission of the first half
+            (
+                102,
+                frame(CLIENT, SERVER, 1000 + first.len() as u32, false, second),
+            ),
+            (101, frame(CLIENT, SERVER, 1000, false, first)),
+            (103, frame(CLIENT, SERVER, 1000, false, first)),
+            (104, frame(SERVER, CLIENT, 5000, false, &framed(ACK))),
+        ]);
+
+        let import = read_capture(&file, MessageEncoding::Utf8, &Framing::default()).unwrap();
+        assert!(import.warnings.is_empty(), "{:?}", import.warnings);
+        assert_eq!(import.streams, 2);
+        assert_eq!(import.messages.len(), 2);
+
+        let adt = &import.messages[0];
+        assert_eq!(adt.message.as_bytes(), ADT);
+        assert_eq!(adt.source, "10.0.0.1:40000");
+        assert_eq!(adt.destination, "10.0.0.2:2575");
+        assert_eq!(adt.timestamp.as_second(), 102);
+        assert_eq!(adt.message_type.as_deref(), Some("ADT^A01"));
+        assert_eq!(adt.control_id.as_deref(), Some("MSG001"));
+
+        let ack = &import.messages[1];
+        assert_eq!(ack.message.as_bytes(), ACK);
+        assert_eq!(ack.source, "10.0.0.2:2575");
+        assert_eq!(ack.timestamp.as_second(), 104);
+    }
+
+    #[test]
+    fn reports_missing_data() {
+        let adt = framed(ADT);
+        let ack = framed(ACK);
+        let file = pcap(&[
+            (100, frame(CLIENT, SERVER, 1000, false, &adt[..10])),
+            // the rest of the ADT was never captured
+            (
+                101,
+                frame(CLIENT, SERVER, 1000 + adt.len() as u32, false, &ack),
+            ),
+        ]);
+
+        let import = read_capture(&file, MessageEncoding::Utf8, &Framing::default()).unwrap();
+        assert_eq!(import.warnings.len(), 2);
+        assert!(import.warnings[0].contains("missing from 10.0.0.1:40000"));
+        assert!(import.warnings[1].contains("incomplete message"));
+        // the ADT never ends, but the ACK after it is still found
+        assert_eq!(import.messages.len(), 1);
+        assert_eq!(import.messages[0].message.as_bytes(), ACK);
+    }
+
+    #[test]
+    fn reads_pcapng_captures() {
+        fn block(block_type: u32, body: &[u8]) -> Vec<u8> {
+            let padded = (body.len() + 3) & !3;
+            let length = (12 + padded) as u32;
+            let mut block = Vec::new();
+            block.extend_from_slice(&block_type.to_le_bytes());
+            block.extend_from_slice(&length.to_le_bytes());
+            block.extend_from_slice(body);
+            block.resize(8 + padded, 0);
+            block.extend_from_slice(&length.to_le_bytes());
+            block
+        }
+
+        let mut section = PCAPNG_BYTE_ORDER.to_le_bytes().to_vec();
+        section.extend_from_slice(&[1, 0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+        // Ethernet, with nanosecond timestamps (if_tsresol = 9)
+        let mut interface = vec![1, 0, 0, 0, 0, 0, 0, 0];
+        interface.extend_from_slice(&[9, 0, 1, 0, 9, 0, 0, 0, 0, 0, 0, 0]);
+        let packet = frame(CLIENT, SERVER, 1000, false, &framed(ADT));
+        let ticks: u64 = 1_700_000_000_500_000_000;
+        let mut enhanced = 0u32.to_le_bytes().to_vec();
+        enhanced.extend_from_slice(&((ticks >> 32) as u32).to_le_bytes());
+        enhanced.extend_from_slice(&(ticks as u32).to_le_bytes());
+        enhanced.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        enhanced.extend_from_slice(&(packet.len() as u32).to_le_bytes());
+        enhanced.extend_from_slice(&packet);
+
+        let file = [
+            block(PCAPNG_SECTION_HEADER, &section),
+            block(1, &interface),
+            block(6, &enhanced),
+        ]
+        .concat();
+        let import = read_capture(&file, MessageEncoding::Utf8, &Framing::default()).unwrap();
+        assert_eq!(import.messages.len(), 1);
+        assert_eq!(import.messages[0].message.as_bytes(), ADT);
+        assert_eq!(
+            import.messages[0].timestamp.as_nanosecond(),
+            i128::from(ticks)
+        );
+    }
+
+    #[test]
+    fn rejects_files_that_are_not_captures() {
+        assert!(read_capture(b"MSH|^~\\&|", MessageEncoding::Utf8, &Framing::default()).is_err());
+        assert!(read_capture(b"", MessageEncoding::Utf8, &Framing::default()).is_err());
+    }
+}
@@ -0,0 +1,319 @@
+//! Named connection profiles, managed by the backend.
+//!
+//! Connection presets live in the frontend's settings and only the Send tab
+//! knows about them. A connection profile instead names a host, port, TLS
+//! settings, framing, and encoding once, and is looked up by name wherever a
+//! connection is needed: [`send_message`](super::send_message) and
+//! [`start_listening`](super::start_listening) both take a profile name in place
+//! of the individual settings. Re-typing addresses for a dozen environments is
+//! how messages end up somewhere they shouldn't.
+//!
+//! # Storage
+//!
+//! Profiles are kept in `connections.json` under the data root, through the
+//! store plugin, so they're checked and backed up at startup like the settings
+//! and follow the data root when it's switched. The frontend never writes the
+//! store itself; every change goes through [`save_connection_profile`] and
+//! [`delete_connection_profile`], which validate it first.
+//!
+//! # TLS
+//!
+//! Sends to a profile with TLS settings wrap the MLLP connection in TLS,
+//! verifying the server's certificate against the system's trusted roots
+//! unless told not to. Listening with TLS would need a certificate the sender
+//! trusts, so a TLS profile can't be listened on, and TLS connections can't be
+//! kept open in the connection pool.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_store::StoreExt;
+use tokio::net::TcpStream;
+use tokio_native_tls::{native_tls, TlsConnector, TlsStream};
+
+use super::encoding::MessageEncoding;
+use super::framing::Framing;
+use crate::AppData;
+
+/// Store holding the profiles, relative to the data root.
+pub const CONNECTIONS_STORE: &str = "connections.json";
+
+/// Key the profiles are kept under in the store.
+const PROFILES_KEY: &str = "profiles";
+
+/// A named connection to a remote system.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConnectionProfile {
+    /// Name the profile is referred to by
+    pub name: String,
+    /// Hostname or IP address to send to
+    pub host: String,
+    /// Port to send to, or listen on
+    pub port: u16,
+    /// TLS settings, if the connection is wrapped in TLS
+    #[serde(default)]
+    pub tls: Option<TlsSettings>,
+    /// MLLP start and end blocks
+    #[serde(default)]
+    pub framing: Framing,
+    /// Encoding messages are sent and received in
+    #[serde(default)]
+    pub encoding: MessageEncoding,
+}
+
+/// How a connection is wrapped in TLS.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TlsSettings {
+    /// Name to verify the server's certificate against, if not the host
+    #[serde(rename = "serverName", default)]
+    pub server_name: Option<String>,
+    /// Accept certificates that don't verify, such as self-signed ones on test
+    /// systems
+    #[serde(rename = "acceptInvalidCerts", default)]
+    pub accept_invalid_certs: bool,
+}
+
+impl ConnectionProfile {
+    /// Check the profile is complete and usable.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Connection profiles need a name".to_string());
+        }
+        if self.name.trim() != self.name {
+            return Err(format!(
+                "Profile name `{}` can't start or end with spaces",
+                self.name
+            ));
+        }
+        let host = self.host.trim();
+        if host.is_empty() || host.contains(char::is_whitespace) {
+            return Err(format!("`{}` isn't a valid host", self.host));
+        }
+        if self.port == 0 {
+            return Err("Port must be between 1 and 65535".to_string());
+        }
+        if let Some(server_name) = self.tls.as_ref().and_then(|tls| tls.server_name.as_ref()) {
+            if server_name.trim().is_empty() {
+                return Err("TLS server name can't be blank".to_string());
+            }
+        }
+        self.framing.validate()
+    }
+}
+
+/// Path of the profiles store under the data root.
+async fn store_path(state: &AppData) -> PathBuf {
+    state.data_root.lock().await.path.join(CONNECTIONS_STORE)
+}
+
+/// Read the profiles from the store.
+async fn load_profiles(app: &AppHandle, state: &AppData) -> Result<Vec<ConnectionProfile>, String> {
+    let store = app
+        .store(store_path(state).await)
+        .map_err(|e| format!("Failed to open connection profiles: {e}"))?;
+    match store.get(PROFILES_KEY) {
+        Some(profiles) => serde_json::from_value(profiles)
+            .map_err(|e| format!("Failed to read connection profiles: {e}")),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Write the profiles to the store.
+async fn store_profiles(
+    app: &AppHandle,
+    state: &AppData,
+    profiles: &[ConnectionProfile],
+) -> Result<(), String> {
+    let store = app
+        .store(store_path(state).await)
+        .map_err(|e| format!("Failed to open connection profiles: {e}"))?;
+    let profiles = serde_json::to_value(profiles)
+        .map_err(|e| format!("Failed to serialize connection profiles: {e}"))?;
+    store.set(PROFILES_KEY, profiles);
+    store
+        .save()
+        .map_err(|e| format!("Failed to save connection profiles: {e}"))
+}
+
+/// Look up a profile by name.
+///
+/// # Returns
+/// * `Err(String)` - If there's no profile by that name, or the store can't be read
+pub(super) async fn find_profile(app: &AppHandle, name: &str) -> Result<ConnectionProfile, String> {
+    let state = app.state::<AppData>();
+    load_profiles(app, &state)
+        .await?
+        .into_iter()
+        .find(|profile| profile.name == name)
+        .ok_or_else(|| format!("No connection profile named `{name}`"))
+}
+
+/// Replace or add a profile in a list, keeping names unique.
+///
+/// `replacing` names the profile being edited, so it can be renamed. Without
+/// it, the profile is new and its name can't already be taken.
+fn upsert(
+    profiles: &mut Vec<ConnectionProfile>,
+    profile: ConnectionProfile,
+    replacing: Option<&str>,
+) -> Result<(), String> {
+    let taken = profiles
+        .iter()
+        .any(|existing| existing.name == profile.name && Some(existing.name.as_str()) != replacing);
+    if taken {
+        return Err(format!(
+            "A connection profile named `{}` already exists",
+            profile.name
+        ));
+    }
+    match replacing.and_then(|name| profiles.iter_mut().find(|existing| existing.name == name)) {
+        Some(existing) => *existing = profile,
+        None => profiles.push(profile),
+    }
+    Ok(())
+}
+
+/// Wrap a connection in TLS.
+///
+/// # Arguments
+/// * `stream` - The connected TCP stream
+/// * `tls` - The profile's TLS settings
+/// * `host` - The host connected to, verified against the certificate unless
+///   the settings name another server
+pub(super) async fn connect_tls(
+    stream: TcpStream,
+    tls: &TlsSettings,
+    host: &str,
+) -> Result<TlsStream<TcpStream>, String> {
+    let connector = native_tls::TlsConnector::builder()
+        .danger_accept_invalid_certs(tls.accept_invalid_certs)
+        .danger_accept_invalid_hostnames(tls.accept_invalid_certs)
+        .build()
+        .map_err(|e| format!("Failed to set up TLS: {e}"))?;
+    let server_name = tls.server_name.as_deref().unwrap_or(host);
+    TlsConnector::from(connector)
+        .connect(server_name, stream)
+        .await
+        .map_err(|e| format!("TLS handshake with {server_name} failed: {e}"))
+}
+
+/// List the saved connection profiles, in the order they were added.
+#[tauri::command]
+pub async fn list_connection_profiles(
+    app: AppHandle,
+    state: State<'_, AppData>,
+) -> Result<Vec<ConnectionProfile>, String> {
+    load_profiles(&app, &state).await
+}
+
+/// Save a connection profile, adding it or replacing the one it was edited from.
+///
+/// # Arguments
+/// * `profile` - The profile to save
+/// * `replacing` - Name of the profile being edited, if it isn't new
+///
+/// # Returns
+/// * `Ok(Vec<ConnectionProfile>)` - The profiles after saving
+/// * `Err(String)` - If the profile isn't valid, its name is taken, or the
+///   store can't be written
+#[tauri::command]
+pub async fn save_connection_profile(
+    profile: ConnectionProfile,
+    replacing: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppData>,
+) -> Result<Vec<ConnectionProfile>, String> {
+    let profile = ConnectionProfile {
+        host: profile.host.trim().to_string(),
+        ..profile
+    };
+    profile.validate()?;
+    let mut profiles = load_profiles(&app, &state).await?;
+    upsert(&mut profiles, profile, replacing.as_deref())?;
+    store_profiles(&app, &state, &profiles).await?;
+    Ok(profiles)
+}
+
+/// Delete a connection profile.
+///
+/// # Returns
+/// * `Ok(Vec<ConnectionProfile>)` - The profiles left
+/// * `Err(String)` - If there's no profile by that name, or the store can't be
+///   written
+#[tauri::command]
+pub async fn delete_connection_profile(
+    name: String,
+    app: AppHandle,
+    state: State<'_, AppData>,
+) -> Result<Vec<ConnectionProfile>, String> {
+    let mut profiles = load_profiles(&app, &state).await?;
+    let count = profiles.len();
+    profiles.retain(|profile| profile.name != name);
+    if profiles.len() == count {
+        return Err(format!("No connection profile named `{name}`"));
+    }
+    store_profiles(&app, &state, &profiles).await?;
+    Ok(profiles)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    fn profile(name: &str, port: u16) -> ConnectionProfile {
+        ConnectionProfile {
+            name: name.to_string(),
+            host: "lab.example.org".to_string(),
+            port,
+            tls: None,
+            framing: Framing::default(),
+            encoding: MessageEncoding::default(),
+        }
+    }
+
+    #[test]
+    fn rejects_incomplete_profiles() {
+        assert!(profile("Lab", 2575).validate().is_ok());
+        assert!(profile("", 2575).validate().is_err());
+        assert!(profile(" Lab", 2575).validate().is_err());
+        assert!(profile("Lab", 0).validate().is_err());
+
+        let mut spaced = profile("Lab", 2575);
+        spaced.host = "lab example".to_string();
+        assert!(spaced.validate().is_err());
+
+        let mut blank_server = profile("Lab", 2575);
+        blank_server.tls = Some(TlsSettings {
+            server_name: Some(" ".to_string()),
+            accept_invalid_certs: false,
+        });
+        assert!(blank_server.validate().is_err());
+    }
+
+    #[test]
+    fn upsert_keeps_names_unique() {
+        let mut profiles = vec![profile("Lab", 2575), profile("ADT", 2576)];
+
+        assert!(upsert(&mut profiles, profile("ADT", 1), None).is_err());
+        assert!(upsert(&mut profiles, profile("ADT", 1), Some("Lab")).is_err());
+
+        upsert(&mut profiles, profile("Lab (test)", 3000), Some("Lab")).unwrap();
+        upsert(&mut profiles, profile("ADT", 3001), Some("ADT")).unwrap();
+        upsert(&mut profiles, profile("Pharmacy", 3002), None).unwrap();
+        let names: Vec<_> = profiles.iter().map(|p| (p.name.as_str(), p.port)).collect();
+        assert_eq!(
+            names,
+            [("Lab (test)", 3000), ("ADT", 3001), ("Pharmacy", 3002)]
+        );
+    }
+
+    #[test]
+    fn reads_profiles_without_optional_settings() {
+        let profile: ConnectionProfile =
+            serde_json::from_str(r#"{"name": "Lab", "host": "10.0.0.5", "port": 2575}"#).unwrap();
+        assert_eq!(profile.tls, None);
+        assert_eq!(profile.framing, Framing::default());
+        assert_eq!(profile.encoding, MessageEncoding::default());
+    }
+}
@@ -0,0 +1,295 @@
+//! Control IDs already transmitted, for catching duplicates before they're sent.
+//!
+//! Receivers commonly drop a message whose control ID (MSH.10) they've seen
+//! before, treating it as a resend, and often without saying so in the ACK. A
+//! message edited and sent again without a fresh control ID is therefore one
+//! of the easiest ways to lose a message without noticing.
+//!
+//! Every message Hermes sends or receives is remembered by its control ID,
+//! along with when, which way, and with whom (including the environment of the
+//! connection preset a message was sent through), and validation warns when the
+//! message in the editor has a control ID already in the history (see
+//! [`flag_duplicate_control_id`](crate::commands::flag_duplicate_control_id)).
+//!
+//! # Storage
+//!
+//! The history is kept in memory and written to `history.json` under the data
+//! root a few seconds after messages arrive ([`SAVE_DELAY`]), so a busy
+//! listener writes it once per burst rather than once per message. It's also
+//! written on shutdown and before the data root is switched, so it carries over
+//! between sessions, and is read again from the new folder once switched. Only the most
+//! recent [`MAX_CONTROL_IDS`] messages are remembered, and the history can be
+//! cleared from the Tools menu. Like the audit trail, it records who and what
+//! but never message content. Scheduled sends aren't remembered, as a soak
+//! test would crowd everything else out.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, State};
+use tauri_plugin_store::StoreExt;
+
+use super::audit::{AuditEvent, AuditEventKind};
+use super::send::Environment;
+use crate::AppData;
+
+/// Store holding the history, relative to the data root.
+pub const HISTORY_STORE: &str = "history.json";
+
+/// Key the control IDs are kept under in the store.
+const CONTROL_IDS_KEY: &str = "controlIds";
+
+/// Most messages remembered; the oldest are forgotten first.
+const MAX_CONTROL_IDS: usize = 10_000;
+
+/// How long after a message is remembered the history is written, gathering
+/// the messages that arrive meanwhile into one write.
+const SAVE_DELAY: Duration = Duration::from_secs(5);
+
+/// Which way a message went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ControlIdDirection {
+    Sent,
+    Received,
+}
+
+/// A message sent or received with a control ID.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ControlIdUse {
+    /// MSH.10 of the message
+    #[serde(rename = "controlId")]
+    pub control_id: String,
+    /// Whether the message was sent or received
+    pub direction: ControlIdDirection,
+    /// When it was sent or received
+    pub timestamp: jiff::Timestamp,
+    /// Where it was sent to, or who it was received from
+    pub peer: String,
+    /// MSH.9 of the message
+    #[serde(rename = "messageType")]
+    pub message_type: Option<String>,
+    /// Environment of the connection preset it was sent to, if it was sent
+    /// through one
+    #[serde(default)]
+    pub environment: Option<Environment>,
+}
+
+/// Control IDs sent and received, oldest first.
+#[derive(Debug, Default)]
+pub struct ControlIdHistory {
+    uses: Mutex<VecDeque<ControlIdUse>>,
+    /// Set when messages have been remembered since the history was written
+    unsaved: AtomicBool,
+    /// Set while a write is waiting for [`SAVE_DELAY`] to pass
+    save_scheduled: AtomicBool,
+}
+
+impl ControlIdHistory {
+    /// Read the history saved under a data root, starting empty if there's none
+    /// or it can't be read.
+    pub fn load(app: &AppHandle, data_root: &Path) -> Self {
+        ControlIdHistory {
+            uses: Mutex::new(read_history(app, data_root)),
+            ..ControlIdHistory::default()
+        }
+    }
+
+    /// Replace the history with the one saved under another data root.
+    pub fn reload(&self, app: &AppHandle, data_root: &Path) {
+        *self.uses.lock().expect("can write control ID history") = read_history(app, data_root);
+        self.unsaved.store(false, Ordering::Release);
+    }
+
+    /// Earlier messages with a control ID, oldest first.
+    #[must_use]
+    pub fn uses_of(&self, control_id: &str) -> Vec<ControlIdUse> {
+        self.uses
+            .lock()
+            .expect("can read control ID history")
+            .iter()
+            .filter(|used| used.control_id == control_id)
+            .cloned()
+            .collect()
+    }
+
+    /// Remember a message, forgetting the oldest if the history is full.
+    pub(crate) fn push(&self, used: ControlIdUse) {
+        let mut uses = self.uses.lock().expect("can write control ID history");
+        uses.push_back(used);
+        while uses.len() > MAX_CONTROL_IDS {
+            uses.pop_front();
+        }
+        self.unsaved.store(true, Ordering::Release);
+    }
+
+    /// Write the history to its store under a data root, if messages have been
+    /// remembered since it was last written.
+    pub(crate) fn save(&self, app: &AppHandle, data_root: &Path) -> Result<(), String> {
+        if !self.unsaved.swap(false, Ordering::AcqRel) {
+            return Ok(());
+        }
+        let written = self
+            .to_value()
+            .and_then(|value| write_history(app, data_root, value));
+        if written.is_err() {
+            self.unsaved.store(true, Ordering::Release);
+        }
+        written
+    }
+
+    /// The history as it's saved in the store.
+    fn to_value(&self) -> Result<serde_json::Value, String> {
+        let uses = self.uses.lock().expect("can read control ID history");
+        serde_json::to_value(&*uses)
+            .map_err(|e| format!("Failed to serialize control ID history: {e}"))
+    }
+
+    /// Forget every control ID.
+    fn clear(&self) {
+        self.uses
+            .lock()
+            .expect("can write control ID history")
+            .clear();
+        self.unsaved.store(true, Ordering::Release);
+    }
+}
+
+/// Read the history saved under a data root, or an empty one if it can't be read.
+fn read_history(app: &AppHandle, data_root: &Path) -> VecDeque<ControlIdUse> {
+    app.store(data_root.join(HISTORY_STORE))
+        .map_err(|e| e.to_string())
+        .and_then(|store| match store.get(CONTROL_IDS_KEY) {
+            Some(uses) => serde_json::from_value(uses).map_err(|e| e.to_string()),
+            None => Ok(VecDeque::new()),
+        })
+        .unwrap_or_else(|e| {
+            log::warn!("Failed to read control ID history: {e}");
+            VecDeque::new()
+        })
+}
+
+/// Write a history to the store under a data root.
+fn write_history(app: &AppHandle, data_root: &Path, uses: serde_json::Value) -> Result<(), String> {
+    let store = app
+        .store(data_root.join(HISTORY_STORE))
+        .map_err(|e| format!("Failed to open control ID history: {e}"))?;
+    store.set(CONTROL_IDS_KEY, uses);
+    store
+        .save()
+        .map_err(|e| format!("Failed to save control ID history: {e}"))
+}
+
+/// Write the history to the store under the current data root.
+async fn save_history(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<AppData>();
+    let root = state.data_root.lock().await.path.clone();
+    state.control_ids.save(app, &root)
+}
+
+/// Write the history once [`SAVE_DELAY`] has passed, unless a write is already
+/// waiting.
+fn schedule_save(app: &AppHandle) {
+    let history = &app.state::<AppData>().control_ids;
+    if history.save_scheduled.swap(true, Ordering::AcqRel) {
+        return;
+    }
+    let app = app.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(SAVE_DELAY).await;
+        // messages remembered from here on schedule another write
+        app.state::<AppData>()
+            .control_ids
+            .save_scheduled
+            .store(false, Ordering::Release);
+        if let Err(e) = save_history(&app).await {
+            log::error!("{e}");
+        }
+    });
+}
+
+/// Remember the control ID of a message an audit event records sending or
+/// receiving.
+///
+/// Other events, and messages without a control ID, are ignored.
+pub(crate) fn remember_control_id(app: &AppHandle, event: &AuditEvent) {
+    let direction = match event.kind {
+        AuditEventKind::MessageSent => ControlIdDirection::Sent,
+        AuditEventKind::MessageReceived => ControlIdDirection::Received,
+        AuditEventKind::SendFailed
+        | AuditEventKind::AckReceived
+        | AuditEventKind::AckTimeout
+        | AuditEventKind::AckSent => return,
+    };
+    let Some(control_id) = event.control_id.clone().filter(|id| !id.is_empty()) else {
+        return;
+    };
+    app.state::<AppData>().control_ids.push(ControlIdUse {
+        control_id,
+        direction,
+        timestamp: event.timestamp,
+        peer: event.peer.clone(),
+        message_type: event.message_type.clone(),
+        environment: event.environment,
+    });
+    schedule_save(app);
+}
+
+/// Forget every control ID sent or received, so none are reported as
+/// duplicates.
+#[tauri::command]
+pub async fn clear_control_id_history(
+    app: AppHandle,
+    state: State<'_, AppData>,
+) -> Result<(), String> {
+    state.control_ids.clear();
+    save_history(&app).await
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    fn used(control_id: &str) -> ControlIdUse {
+        ControlIdUse {
+            control_id: control_id.to_string(),
+            direction: ControlIdDirection::Sent,
+            timestamp: jiff::Timestamp::UNIX_EPOCH,
+            peer: "127.0.0.1:2575".to_string(),
+            message_type: Some("ADT^A01".to_string()),
+            environment: None,
+        }
+    }
+
+    #[test]
+    fn finds_every_use_of_a_control_id() {
+        let history = ControlIdHistory::default();
+        history.push(used("a"));
+        history.push(used("b"));
+        history.push(used("a"));
+        assert_eq!(history.uses_of("a").len(), 2);
+        assert_eq!(history.uses_of("b").len(), 1);
+        assert!(history.uses_of("c").is_empty());
+
+        history.clear();
+        assert!(history.uses_of("a").is_empty());
+    }
+
+    #[test]
+    fn forgets_the_oldest_once_full() {
+        let history = ControlIdHistory::default();
+        for n in 0..=MAX_CONTROL_IDS {
+            history.push(used(&n.to_string()));
+        }
+        assert!(history.uses_of("0").is_empty());
+        assert_eq!(history.uses_of("1").len(), 1);
+        assert_eq!(
+            history.uses_of(&MAX_CONTROL_IDS.to_string())[0].control_id,
+            MAX_CONTROL_IDS.to_string()
+        );
+    }
+}
@@ -0,0 +1,221 @@
+//! Character encodings for messages on the wire.
+//!
+//! HL7 doesn't mandate UTF-8, and many older systems send and expect ISO-8859-1
+//! (Latin-1) or Windows-1252. Decoding their messages as UTF-8 either fails
+//! outright or mangles accented names, so the encoding used by the sender and
+//! listener can be chosen per connection.
+//!
+//! # MSH-18
+//!
+//! With [`MessageEncoding::Msh18`], each message is encoded or decoded using the
+//! character set named in its own MSH-18 (HL7 table 0211), falling back to UTF-8
+//! when MSH-18 is empty. Only the first repetition of MSH-18 is used, since
+//! further repetitions name alternate character sets for escape sequences.
+//! Supported values are:
+//! * `ASCII`, `ISO IR6`, `UNICODE`, `UNICODE UTF-8` - UTF-8 (a superset of ASCII)
+//! * `8859/1` to `8859/9`, `8859/15` - The matching ISO-8859 part
+//! * `ISO IR100` to `ISO IR148` - The ISO-8859 part with that registration number
+//! * Any WHATWG encoding label, such as `windows-1252`
+//!
+//! # Unrepresentable Characters
+//!
+//! Encoding fails rather than substituting characters that don't exist in the
+//! target character set, so a message is never silently altered on its way out.
+
+use encoding_rs::Encoding;
+use serde::{Deserialize, Serialize};
+
+/// Character encoding used for messages sent or received over MLLP.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageEncoding {
+    /// UTF-8
+    #[default]
+    Utf8,
+    /// ISO-8859-1
+    Latin1,
+    /// Windows-1252, the common superset of Latin-1
+    Windows1252,
+    /// The character set named in each message's MSH-18
+    Msh18,
+}
+
+/// A resolved character set.
+#[derive(Debug, Clone, Copy)]
+enum Charset {
+    Utf8,
+    /// ISO-8859-1 proper; `encoding_rs` treats the label as Windows-1252, which
+    /// maps 0x80-0x9F to printable characters instead of control codes
+    Latin1,
+    Other(&'static Encoding),
+}
+
+impl MessageEncoding {
+    /// Decode a received message.
+    ///
+    /// # Arguments
+    /// * `bytes` - Message bytes, without MLLP framing
+    ///
+    /// # Returns
+    /// * `Ok(String)` - The decoded message
+    /// * `Err(String)` - If the bytes aren't valid in the encoding, or MSH-18 names an unsupported character set
+    pub fn decode(self, bytes: &[u8]) -> Result<String, String> {
+        let msh18 = msh18(bytes);
+        match self.charset(msh18.as_deref())? {
+            Charset::Utf8 => std::str::from_utf8(bytes)
+                .map(str::to_string)
+                .map_err(|e| format!("Invalid UTF-8: {e}")),
+            Charset::Latin1 => Ok(bytes.iter().copied().map(char::from).collect()),
+            Charset::Other(encoding) => encoding
+                .decode_without_bom_handling_and_without_replacement(bytes)
+                .map(|decoded| decoded.into_owned())
+                .ok_or_else(|| format!("Invalid {}", encoding.name())),
+        }
+    }
+
+    /// Encode a message for sending.
+    ///
+    /// # Arguments
+    /// * `message` - The message to encode
+    ///
+    /// # Returns
+    /// * `Ok(Vec<u8>)` - The encoded message
+    /// * `Err(String)` - If the message contains characters the encoding can't represent, or MSH-18 names an unsupported character set
+    pub fn encode(self, message: &str) -> Result<Vec<u8>, String> {
+        let msh18 = msh18(message.as_bytes());
+        match self.charset(msh18.as_deref())? {
+            Charset::Utf8 => Ok(message.as_bytes().to_vec()),
+            Charset::Latin1 => message
+                .chars()
+                .map(|c| {
+                    u8::try_from(c).map_err(|_| format!("'{c}' can't be represented in ISO-8859-1"))
+                })
+                .collect(),
+            Charset::Other(encoding) => {
+                let (encoded, used, had_errors) = encoding.encode(message);
+                if had_errors || used != encoding {
+                    return Err(format!(
+                        "Message contains characters that can't be represented in {}",
+                        encoding.name()
+                    ));
+                }
+                Ok(encoded.into_owned())
+            }
+        }
+    }
+
+    /// Resolve the character set to use for a message.
+    fn charset(self, msh18: Option<&str>) -> Result<Charset, String> {
+        match self {
+            MessageEncoding::Utf8 => Ok(Charset::Utf8),
+            MessageEncoding::Latin1 => Ok(Charset::Latin1),
+            MessageEncoding::Windows1252 => Ok(Charset::Other(encoding_rs::WINDOWS_1252)),
+            MessageEncoding::Msh18 => charset_for_msh18(msh18.unwrap_or_default()),
+        }
+    }
+}
+
+/// Map an MSH-18 value (HL7 table 0211) to a character set.
+fn charset_for_msh18(value: &str) -> Result<Charset, String> {
+    let value = value.trim().to_ascii_uppercase();
+    let part = match value.as_str() {
+        "" | "ASCII" | "ISO IR6" | "UNICODE" | "UNICODE UTF-8" => return Ok(Charset::Utf8),
+        "8859/1" | "ISO IR100" => return Ok(Charset::Latin1),
+        "ISO IR101" => "2",
+        "ISO IR109" => "3",
+        "ISO IR110" => "4",
+        "ISO IR144" => "5",
+        "ISO IR127" => "6",
+        "ISO IR126" => "7",
+        "ISO IR138" => "8",
+        "ISO IR148" => "9",
+        other => other.strip_prefix("8859/").unwrap_or_default(),
+    };
+
+    let label = if part.is_empty() {
+        value.clone()
+    } else {
+        format!("ISO-8859-{part}")
+    };
+    Encoding::for_label(label.as_bytes())
+        .map(Charset::Other)
+        .ok_or_else(|| format!("Unsupported MSH-18 character set '{value}'"))
+}
+
+/// Read the first repetition of MSH-18 from raw message bytes.
+///
+/// The MSH segment is always ASCII, so this works before the message has been
+/// decoded.
+fn msh18(bytes: &[u8]) -> Option<String> {
+    let start = bytes.windows(3).position(|window| window == b"MSH")?;
+    let msh = bytes.get(start..)?;
+    let segment = msh
+        .split(|b| *b == b'\r' || *b == b'\n')
+        .next()
+        .unwrap_or_default();
+    let field_separator = *segment.get(3)?;
+    let repetition_separator = *segment.get(5)?;
+
+    // splitting on the field separator puts MSH-1 between "MSH" and MSH-2, so
+    // MSH-n is at index n - 1
+    let value = segment.split(|b| *b == field_separator).nth(17)?;
+    let value = value
+        .split(|b| *b == repetition_separator)
+        .next()
+        .unwrap_or_default();
+    Some(String::from_utf8_lossy(value).into_owned())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const MSH: &str = "MSH|^~\\&|APP|FAC|||20250101||ADT^A01|1|P|2.5.1||||||";
+
+    #[test]
+    fn can_round_trip_latin1() {
+        let message = format!("{MSH}8859/1\rPID|||1||Müller^José");
+        for encoding in [MessageEncoding::Latin1, MessageEncoding::Msh18] {
+            let encoded = encoding.encode(&message).unwrap();
+            assert!(encoded.contains(&0xFC));
+            assert_eq!(encoding.decode(&encoded).unwrap(), message);
+        }
+    }
+
+    #[test]
+    fn latin1_keeps_c1_control_codes() {
+        assert_eq!(MessageEncoding::Latin1.decode(&[0x80]).unwrap(), "\u{80}");
+        assert_eq!(MessageEncoding::Windows1252.decode(&[0x80]).unwrap(), "€");
+    }
+
+    #[test]
+    fn unrepresentable_characters_are_rejected() {
+        assert!(MessageEncoding::Latin1.encode("PID|||1||€").is_err());
+        assert!(MessageEncoding::Windows1252.encode("PID|||1||Ł").is_err());
+        assert!(MessageEncoding::Utf8.decode(&[0xFC]).is_err());
+    }
+
+    #[test]
+    fn msh18_selects_charset() {
+        assert_eq!(
+            msh18(format!("{MSH}8859/2~UNICODE").as_bytes()).as_deref(),
+            Some("8859/2")
+        );
+        assert_eq!(msh18(b"MSH|^~\\&|APP"), None);
+
+        let message = format!("{MSH}ISO IR101\rPID|||1||Łukasz");
+        let encoded = MessageEncoding::Msh18.encode(&message).unwrap();
+        assert_eq!(MessageEncoding::Msh18.decode(&encoded).unwrap(), message);
+
+        // without MSH-18, UTF-8 is assumed
+        let encoded = MessageEncoding::Msh18
+            .encode("MSH|^~\\&\rPID|||Łukasz")
+            .unwrap();
+        assert_eq!(encoded, "MSH|^~\\&\rPID|||Łukasz".as_bytes());
+
+        assert!(MessageEncoding::Msh18
+            .encode(&format!("{MSH}EBCDIC"))
+            .is_err());
+    }
+}
@@ -0,0 +1,655 @@
+//! Import of endpoint settings from integration engine exports.
+//!
+//! Setting Hermes up to target (or stand in for) an existing interface means
+//! finding the host, port, framing, and encoding buried in the engine's channel
+//! configuration. This module reads those exports directly and turns each TCP
+//! connector into an endpoint the frontend can save as a connection preset.
+//!
+//! # Supported Formats
+//!
+//! * **Mirth Connect** - Channel exports (`<channel>`) and full server
+//!   configuration exports (`<serverConfiguration>`). Each channel's TCP
+//!   Listener source and TCP Sender destinations are imported; other connector
+//!   types are skipped.
+//! * **Rhapsody** - Communication point definitions exported as XML
+//!   (`<CommunicationPoint>` elements with named `<Property>` children). TCP
+//!   communication points are imported; other types are skipped. Rhapsody
+//!   `.rlc` archives need to be extracted first.
+//!
+//! # Roles
+//!
+//! Endpoints are described from the engine's point of view: a *listener* is a
+//! port the engine accepts messages on (so Hermes can send to it), and a
+//! *sender* is a remote system the engine delivers to (so Hermes can target it
+//! directly, or listen on its port to stand in for it). Listeners bound to all
+//! interfaces are given a host of `localhost`, with a warning to replace it with
+//! the engine's address.
+//!
+//! # Unresolvable Settings
+//!
+//! Anything that can't be mapped (a port taken from a channel variable, an
+//! unsupported character set, unparseable framing bytes) is reported as a
+//! warning rather than failing the whole import.
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+
+use super::encoding::MessageEncoding;
+use super::framing::Framing;
+
+/// Integration engine an export came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Engine {
+    Mirth,
+    Rhapsody,
+}
+
+/// What an endpoint does in the engine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EndpointRole {
+    /// The engine listens on this port for incoming messages
+    Listener,
+    /// The engine sends messages to this remote host and port
+    Sender,
+}
+
+/// A TCP endpoint found in an engine export.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportedEndpoint {
+    /// Channel and connector (or communication point) name
+    pub name: String,
+    /// Host to connect to
+    pub hostname: String,
+    /// Port to connect to
+    pub port: u16,
+    /// What the endpoint does in the engine
+    pub role: EndpointRole,
+    /// Framing bytes, if not standard MLLP
+    pub framing: Option<Framing>,
+    /// Character encoding, if not UTF-8
+    pub encoding: Option<MessageEncoding>,
+    /// Where in the export the endpoint came from
+    pub notes: String,
+}
+
+/// Result of importing an engine export.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportedEndpoints {
+    /// Engine the export came from
+    pub engine: Engine,
+    /// TCP endpoints found in the export
+    pub endpoints: Vec<ImportedEndpoint>,
+    /// Settings that couldn't be imported
+    pub warnings: Vec<String>,
+}
+
+/// Import TCP endpoints from an integration engine export file.
+///
+/// # Arguments
+/// * `path` - Path to a Mirth Connect channel/server export or a Rhapsody communication point export
+///
+/// # Returns
+/// * `Ok(ImportedEndpoints)` - The endpoints found, and warnings for anything that couldn't be mapped
+/// * `Err(String)` - If the file can't be read, isn't XML, or isn't a recognised export
+#[tauri::command]
+pub fn import_engine_config(path: &str) -> Result<ImportedEndpoints, String> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| format!("Failed to read export: {e}"))?;
+    parse_engine_config(&content)
+}
+
+/// Parse an engine export, detecting which engine it came from.
+fn parse_engine_config(content: &str) -> Result<ImportedEndpoints, String> {
+    let document = parse_xml(content)?;
+    let mut warnings = Vec::new();
+
+    let channels: Vec<&Element> = document
+        .descendants("channel")
+        .into_iter()
+        .filter(|channel| channel.child("sourceConnector").is_some())
+        .collect();
+    if !channels.is_empty() {
+        let endpoints = channels
+            .into_iter()
+            .flat_map(|channel| mirth_channel_endpoints(channel, &mut warnings))
+            .collect();
+        return Ok(ImportedEndpoints {
+            engine: Engine::Mirth,
+            endpoints,
+            warnings,
+        });
+    }
+
+    let communication_points = document.descendants("CommunicationPoint");
+    if !communication_points.is_empty() {
+        let endpoints = communication_points
+            .into_iter()
+            .filter_map(|point| rhapsody_endpoint(point, &mut warnings))
+            .collect();
+        return Ok(ImportedEndpoints {
+            engine: Engine::Rhapsody,
+            endpoints,
+            warnings,
+        });
+    }
+
+    Err("Not a recognised Mirth Connect or Rhapsody export".to_string())
+}
+
+/// Extract the TCP endpoints of a Mirth channel.
+fn mirth_channel_endpoints(channel: &Element, warnings: &mut Vec<String>) -> Vec<ImportedEndpoint> {
+    let channel_name = channel.child_text("name").unwrap_or("Unnamed channel");
+
+    let source = channel
+        .child("sourceConnector")
+        .map(|connector| (connector, EndpointRole::Listener));
+    let destinations = channel
+        .child("destinationConnectors")
+        .map(|connectors| connectors.children_named("connector"))
+        .unwrap_or_default()
+        .into_iter()
+        .map(|connector| (connector, EndpointRole::Sender));
+
+    source
+        .into_iter()
+        .chain(destinations)
+        .filter_map(|(connector, role)| {
+            let properties = connector.child("properties")?;
+            let class = properties.attribute("class").unwrap_or_default();
+            if !class.contains(".tcp.") {
+                return None;
+            }
+
+            let (name, host, port) = match role {
+                EndpointRole::Listener => {
+                    let listener = properties.child("listenerConnectorProperties");
+                    (
+                        channel_name.to_string(),
+                        listener.and_then(|l| l.child_text("host")),
+                        listener.and_then(|l| l.child_text("port")),
+                    )
+                }
+                EndpointRole::Sender => (
+                    format!(
+                        "{channel_name} - {}",
+                        connector.child_text("name").unwrap_or("Destination")
+                    ),
+                    properties.child_text("remoteAddress"),
+                    properties.child_text("remotePort"),
+                ),
+            };
+
+            let framing = properties
+                .child("transmissionModeProperties")
+                .and_then(|mode| {
+                    mirth_framing(
+                        mode.child_text("startOfMessageBytes"),
+                        mode.child_text("endOfMessageBytes"),
+                        &name,
+                        warnings,
+                    )
+                });
+            let encoding = properties
+                .child_text("charsetEncoding")
+                .and_then(|charset| encoding_for_charset(charset, &name, warnings));
+
+            endpoint(
+                name,
+                host,
+                port,
+                role,
+                framing,
+                encoding,
+                format!(
+                    "Imported from Mirth channel '{channel_name}' ({})",
+                    match role {
+                        EndpointRole::Listener => "TCP Listener source",
+                        EndpointRole::Sender => "TCP Sender destination",
+                    }
+                ),
+                warnings,
+            )
+        })
+        .collect()
+}
+
+/// Read Mirth's hex start/end of message bytes.
+fn mirth_framing(
+    start: Option<&str>,
+    end: Option<&str>,
+    name: &str,
+    warnings: &mut Vec<String>,
+) -> Option<Framing> {
+    let standard = Framing::default();
+    let start_block = match start {
+        Some(start) => parse_hex_bytes(start),
+        None => Some(standard.start_block.clone()),
+    };
+    let end_block = match end {
+        Some(end) => parse_hex_bytes(end),
+        None => Some(standard.end_block.clone()),
+    };
+
+    match (start_block, end_block) {
+        (Some(start_block), Some(end_block)) => {
+            let framing = Framing {
+                start_block,
+                end_block,
+            };
+            (framing != standard).then_some(framing)
+        }
+        _ => {
+            warnings.push(format!(
+                "{name}: framing bytes couldn't be read; standard MLLP framing will be used"
+            ));
+            None
+        }
+    }
+}
+
+/// Extract a TCP endpoint from a Rhapsody communication point.
+fn rhapsody_endpoint(point: &Element, warnings: &mut Vec<String>) -> Option<ImportedEndpoint> {
+    let name = point
+        .attribute("name")
+        .or_else(|| point.child_text("name"))
+        .unwrap_or("Unnamed communication point")
+        .to_string();
+    let point_type = point
+        .attribute("type")
+        .or_else(|| point.child_text("type"))
+        .unwrap_or_default();
+    if !point_type.to_ascii_lowercase().contains("tcp") {
+        return None;
+    }
+
+    // property names vary between versions, so match them loosely
+    let properties: Vec<(String, &str)> = point
+        .descendants("Property")
+        .into_iter()
+        .filter_map(|property| {
+            let key = property.attribute("name")?;
+            let value = property.attribute("value").unwrap_or(property.text.trim());
+            Some((normalise_key(key), value))
+        })
+        .collect();
+    let property = |keys: &[&str]| {
+        properties
+            .iter()
+            .find(|(key, _)| keys.contains(&key.as_str()))
+            .map(|(_, value)| *value)
+            .filter(|value| !value.is_empty())
+    };
+
+    let mode = property(&["mode", "connectionmode"])
+        .unwrap_or(point_type)
+        .to_ascii_lowercase();
+    let role = if mode.contains("server") || mode.contains("listen") || mode.contains("input") {
+        EndpointRole::Listener
+    } else {
+        EndpointRole::Sender
+    };
+
+    let framing = match (
+        property(&["startofmessage", "startbytes", "startblock", "messagestart"]),
+        property(&["endofmessage", "endbytes", "endblock", "messageend"]),
+    ) {
+        (None, None) => None,
+        (start, end) => mirth_framing(start, end, &name, warnings),
+    };
+    let encoding = property(&["encoding", "characterencoding", "charset", "characterset"])
+        .and_then(|charset| encoding_for_charset(charset, &name, warnings));
+
+    endpoint(
+        name,
+        property(&["host", "hostname", "remotehost", "address", "remoteaddress"]),
+        property(&["port", "remoteport", "localport", "listenport"]),
+        role,
+        framing,
+        encoding,
+        format!("Imported from Rhapsody communication point ({point_type})"),
+        warnings,
+    )
+}
+
+/// Build an endpoint, warning about and skipping any without a usable port.
+#[allow(clippy::too_many_arguments)]
+fn endpoint(
+    name: String,
+    host: Option<&str>,
+    port: Option<&str>,
+    role: EndpointRole,
+    framing: Option<Framing>,
+    encoding: Option<MessageEncoding>,
+    notes: String,
+    warnings: &mut Vec<String>,
+) -> Option<ImportedEndpoint> {
+    let Some(port) = port.and_then(|port| port.trim().parse::<u16>().ok()) else {
+        warnings.push(format!(
+            "{name}: skipped because its port '{}' isn't a number",
+            port.unwrap_or_default()
+        ));
+        return None;
+    };
+
+    let hostname = match host.map(str::trim) {
+        Some(host) if !host.is_empty() && host != "0.0.0.0" && host != "::" => host.to_string(),
+        _ => {
+            if role == EndpointRole::Listener {
+                warnings.push(format!(
+                    "{name}: listens on all interfaces; replace 'localhost' with the engine's address"
+                ));
+            } else {
+                warnings.push(format!("{name}: has no remote host; using 'localhost'"));
+            }
+            "localhost".to_string()
+        }
+    };
+
+    Some(ImportedEndpoint {
+        name,
+        hostname,
+        port,
+        role,
+        framing,
+        encoding,
+        notes,
+    })
+}
+
+/// Map a Java/IANA charset name to a message encoding.
+///
+/// # Returns
+/// `None` for UTF-8 (and compatible defaults) or for unsupported charsets, which are warned about.
+fn encoding_for_charset(
+    charset: &str,
+    name: &str,
+    warnings: &mut Vec<String>,
+) -> Option<MessageEncoding> {
+    match normalise_key(charset).as_str() {
+        "" | "defaultencoding" | "utf8" | "usascii" | "ascii" => None,
+        "iso88591" | "latin1" | "iso885911987" => Some(MessageEncoding::Latin1),
+        "windows1252" | "cp1252" => Some(MessageEncoding::Windows1252),
+        _ => {
+            warnings.push(format!(
+                "{name}: character set '{charset}' isn't supported; UTF-8 will be used"
+            ));
+            None
+        }
+    }
+}
+
+/// Lowercase a name and strip anything that isn't a letter or digit, so
+/// "Remote Host", "remote-host", and "remoteHost" all compare equal.
+fn normalise_key(key: &str) -> String {
+    key.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+/// Parse hex bytes such as `0B`, `1C0D`, `0x1C 0x0D`, or `1C,0D`.
+fn parse_hex_bytes(text: &str) -> Option<Vec<u8>> {
+    let hex: String = text
+        .replace("0x", "")
+        .replace("0X", "")
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != ',')
+        .collect();
+    if hex.is_empty() || hex.len() % 2 == 1 {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+/// A parsed XML element.
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn from_start(start: &BytesStart) -> Result<Self, String> {
+        let attributes = start
+            .attributes()
+            .map(|attribute| {
+                let attribute = attribute.map_err(|e| format!("Invalid XML attribute: {e}"))?;
+                let key = String::from_utf8_lossy(attribute.key.local_name().as_ref()).to_string();
+                let value = attribute
+                    .unescape_value()
+                    .map_err(|e| format!("Invalid XML attribute: {e}"))?
+                    .to_string();
+                Ok((key, value))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Element {
+            name: String::from_utf8_lossy(start.local_name().as_ref()).to_string(),
+            attributes,
+            ..Default::default()
+        })
+    }
+
+    /// First child element with the given name.
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    /// Trimmed text of the first child element with the given name.
+    fn child_text(&self, name: &str) -> Option<&str> {
+        self.child(name).map(|child| child.text.trim())
+    }
+
+    /// All child elements with the given name.
+    fn children_named(&self, name: &str) -> Vec<&Element> {
+        self.children
+            .iter()
+            .filter(|child| child.name == name)
+            .collect()
+    }
+
+    /// All descendant elements with the given name (case-insensitive), in document order.
+    fn descendants(&self, name: &str) -> Vec<&Element> {
+        let mut found = Vec::new();
+        for child in &self.children {
+            if child.name.eq_ignore_ascii_case(name) {
+                found.push(child);
+            }
+            found.extend(child.descendants(name));
+        }
+        found
+    }
+
+    /// Value of the attribute with the given name (case-insensitive).
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// Parse an XML document into a tree of elements under an unnamed root.
+fn parse_xml(content: &str) -> Result<Element, String> {
+    let mut reader = Reader::from_str(content);
+    reader.config_mut().trim_text(true);
+
+    let mut stack = vec![Element::default()];
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| format!("Invalid XML at position {}: {e}", reader.error_position()))?;
+        match event {
+            Event::Start(start) => stack.push(Element::from_start(&start)?),
+            Event::Empty(start) => {
+                let element = Element::from_start(&start)?;
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(element);
+                }
+            }
+            Event::End(_) => {
+                let element = stack.pop().ok_or("Invalid XML: unbalanced tags")?;
+                let parent = stack.last_mut().ok_or("Invalid XML: unbalanced tags")?;
+                parent.children.push(element);
+            }
+            Event::Text(text) => {
+                let text = text
+                    .unescape()
+                    .map_err(|e| format!("Invalid XML text: {e}"))?;
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&text);
+                }
+            }
+            Event::CData(data) => {
+                if let Some(element) = stack.last_mut() {
+                    element
+                        .text
+                        .push_str(&String::from_utf8_lossy(&data.into_inner()));
+                }
+            }
+            Event::Eof => break,
+            Event::Comment(_) | Event::Decl(_) | Event::PI(_) | Event::DocType(_) => {}
+        }
+    }
+
+    let document = stack.pop().ok_or("Invalid XML: unbalanced tags")?;
+    if !stack.is_empty() {
+        return Err("Invalid XML: unclosed tags".to_string());
+    }
+    Ok(document)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const MIRTH_CHANNEL: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<channel version="4.4.0">
+  <id>8f1c</id>
+  <name>ADT Inbound</name>
+  <sourceConnector version="4.4.0">
+    <name>sourceConnector</name>
+    <properties class="com.mirth.connect.connectors.tcp.TcpReceiverProperties" version="4.4.0">
+      <listenerConnectorProperties version="4.4.0">
+        <host>0.0.0.0</host>
+        <port>6661</port>
+      </listenerConnectorProperties>
+      <transmissionModeProperties class="com.mirth.connect.plugins.mllpmode.MLLPModeProperties">
+        <pluginPointName>MLLP</pluginPointName>
+        <startOfMessageBytes>0B</startOfMessageBytes>
+        <endOfMessageBytes>1C0D</endOfMessageBytes>
+      </transmissionModeProperties>
+      <charsetEncoding>ISO-8859-1</charsetEncoding>
+    </properties>
+  </sourceConnector>
+  <destinationConnectors>
+    <connector version="4.4.0">
+      <name>To Lab</name>
+      <properties class="com.mirth.connect.connectors.tcp.TcpDispatcherProperties" version="4.4.0">
+        <remoteAddress>lab.example.org</remoteAddress>
+        <remotePort>2575</remotePort>
+        <transmissionModeProperties class="com.mirth.connect.plugins.mllpmode.MLLPModeProperties">
+          <startOfMessageBytes>02</startOfMessageBytes>
+          <endOfMessageBytes>03</endOfMessageBytes>
+        </transmissionModeProperties>
+        <charsetEncoding>DEFAULT_ENCODING</charsetEncoding>
+      </properties>
+    </connector>
+    <connector version="4.4.0">
+      <name>To Archive</name>
+      <properties class="com.mirth.connect.connectors.file.FileDispatcherProperties" version="4.4.0"/>
+    </connector>
+    <connector version="4.4.0">
+      <name>To Billing</name>
+      <properties class="com.mirth.connect.connectors.tcp.TcpDispatcherProperties" version="4.4.0">
+        <remoteAddress>billing</remoteAddress>
+        <remotePort>${billingPort}</remotePort>
+      </properties>
+    </connector>
+  </destinationConnectors>
+</channel>"#;
+
+    #[test]
+    fn can_import_mirth_channel() {
+        let imported = parse_engine_config(MIRTH_CHANNEL).unwrap();
+        assert_eq!(imported.engine, Engine::Mirth);
+        assert_eq!(imported.endpoints.len(), 2);
+
+        let source = &imported.endpoints[0];
+        assert_eq!(source.name, "ADT Inbound");
+        assert_eq!(source.hostname, "localhost");
+        assert_eq!(source.port, 6661);
+        assert_eq!(source.role, EndpointRole::Listener);
+        assert_eq!(source.framing, None);
+        assert_eq!(source.encoding, Some(MessageEncoding::Latin1));
+
+        let destination = &imported.endpoints[1];
+        assert_eq!(destination.name, "ADT Inbound - To Lab");
+        assert_eq!(destination.hostname, "lab.example.org");
+        assert_eq!(destination.role, EndpointRole::Sender);
+        assert_eq!(
+            destination.framing,
+            Some(Framing {
+                start_block: vec![0x02],
+                end_block: vec![0x03],
+            })
+        );
+        assert_eq!(destination.encoding, None);
+
+        // the listener's wildcard host and the billing port variable are both reported
+        assert_eq!(imported.warnings.len(), 2);
+        assert!(imported.warnings[1].contains("${billingPort}"));
+    }
+
+    #[test]
+    fn can_import_rhapsody_communication_points() {
+        let export = r#"<RhapsodyExport>
+  <CommunicationPoint name="Pharmacy Out" type="TCP Client">
+    <Property name="Remote Host" value="10.0.0.7"/>
+    <Property name="Remote Port">5000</Property>
+    <Property name="Character Encoding" value="windows-1252"/>
+  </CommunicationPoint>
+  <CommunicationPoint name="Orders In" type="TCP Server">
+    <Property name="Port" value="7000"/>
+    <Property name="Start of Message" value="0x0B"/>
+    <Property name="End of Message" value="0x1C"/>
+  </CommunicationPoint>
+  <CommunicationPoint name="Drop Folder" type="Directory"/>
+</RhapsodyExport>"#;
+        let imported = parse_engine_config(export).unwrap();
+        assert_eq!(imported.engine, Engine::Rhapsody);
+        assert_eq!(imported.endpoints.len(), 2);
+
+        let client = &imported.endpoints[0];
+        assert_eq!(client.hostname, "10.0.0.7");
+        assert_eq!(client.port, 5000);
+        assert_eq!(client.role, EndpointRole::Sender);
+        assert_eq!(client.encoding, Some(MessageEncoding::Windows1252));
+
+        let server = &imported.endpoints[1];
+        assert_eq!(server.role, EndpointRole::Listener);
+        assert_eq!(server.framing.as_ref().unwrap().end_block, vec![0x1c]);
+    }
+
+    #[test]
+    fn unrecognised_exports_are_rejected() {
+        assert!(parse_engine_config("<config><port>1</port></config>").is_err());
+        assert!(parse_engine_config("<channel>").is_err());
+    }
+
+    #[test]
+    fn can_parse_hex_bytes() {
+        assert_eq!(parse_hex_bytes("1C0D"), Some(vec![0x1c, 0x0d]));
+        assert_eq!(parse_hex_bytes("0x1C 0x0D"), Some(vec![0x1c, 0x0d]));
+        assert_eq!(parse_hex_bytes("1C0"), None);
+        assert_eq!(parse_hex_bytes("ZZ"), None);
+    }
+}
@@ -0,0 +1,784 @@
+//! File-drop transport: messages written to, and picked up from, directories.
+//!
+//! Plenty of interfaces still exchange messages as files dropped into a shared
+//! or remote directory rather than over a connection. A connection preset can
+//! "send" by writing the message into a directory, and a drop watch picks up
+//! files appearing in a directory and surfaces them as received messages, like
+//! the listener does.
+//!
+//! # Sending
+//! The message is written to the preset's directory under a name built from its
+//! filename pattern (see [`file_name`]). It's first written under a hidden
+//! temporary name and then renamed, so a watcher on the other side never reads
+//! a half-written file. An existing file with the same name fails the send
+//! rather than being overwritten. File drops have no response, so a successful
+//! send always finishes without one.
+//!
+//! # SFTP
+//! A preset with an SFTP login writes to the directory on its host and port
+//! instead, using the system's OpenSSH `sftp` client in batch mode. Batch mode
+//! can't prompt for a password, so the login has to authenticate with a key,
+//! either from the SSH agent or an identity file, and the host has to be in
+//! `known_hosts` already.
+//!
+//! # Watching
+//! A drop watch polls a local directory once a second. A file is picked up once
+//! its size and modification time have stopped changing between two polls, so
+//! a file still being written isn't read early. Files already in the directory
+//! when the watch starts are picked up too, as an interface would. Hidden files
+//! are ignored, and an extension filter can limit the watch to e.g. `.hl7`
+//! files. A file holding several messages (MLLP-framed, blank-line separated,
+//! or a batch) is surfaced as one received message each.
+//!
+//! Picked-up files can be left in place, deleted, or moved to another
+//! directory. A file left in place is picked up again only if it changes.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::{Duration, SystemTime};
+
+use jiff::Zoned;
+use rand::distr::{Alphanumeric, SampleString};
+use serde::{Deserialize, Deserializer};
+use tauri::{AppHandle, Emitter, State};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+
+use super::audit::{message_type_of, record_audit_event, AuditEvent, AuditEventKind};
+use super::encoding::MessageEncoding;
+use super::listen::{control_id_of, ReceivedMessage};
+use super::send::{emit_log, emit_response, EndpointLabel, SendConfirmation, SendResponse};
+use crate::commands::split_messages;
+use crate::events;
+use crate::AppData;
+
+/// Filename pattern used when a preset doesn't give one.
+pub const DEFAULT_FILENAME_PATTERN: &str = "{controlId}.hl7";
+
+/// How often a drop watch looks for new files.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Settings for sending by writing a file instead of over MLLP, chosen in a
+/// connection preset.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FileDropTransport {
+    /// Directory to write the message into
+    pub directory: String,
+    /// Name of the file to write ([`DEFAULT_FILENAME_PATTERN`] if empty)
+    #[serde(rename = "filenamePattern", default)]
+    pub filename_pattern: String,
+    /// Login to write the file over SFTP to the preset's host and port, rather
+    /// than to a local directory
+    #[serde(default)]
+    pub sftp: Option<SftpLogin>,
+}
+
+/// How an SFTP file drop logs in.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SftpLogin {
+    /// User to log in as
+    #[serde(deserialize_with = "deserialize_ssh_user")]
+    pub username: String,
+    /// Private key to authenticate with, if not one from the SSH agent
+    #[serde(rename = "identityFile", default)]
+    pub identity_file: Option<String>,
+}
+
+fn deserialize_ssh_user<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    let username = String::deserialize(deserializer)?;
+    check_ssh_name(&username, "username").map_err(serde::de::Error::custom)?;
+    Ok(username)
+}
+
+/// Check a user or host name given to `sftp`, which would take one starting
+/// with `-` as an option and one with spaces as several arguments.
+pub(super) fn check_ssh_name(name: &str, what: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err(format!("SFTP {what} can't be empty"));
+    }
+    if name.starts_with('-') {
+        return Err(format!("SFTP {what} can't start with '-'"));
+    }
+    if name.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(format!(
+            "SFTP {what} can't contain spaces or control characters"
+        ));
+    }
+    Ok(())
+}
+
+/// What a drop watch does with a file once it's been picked up.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum AfterPickup {
+    /// Leave the file where it is
+    #[default]
+    Leave,
+    /// Delete the file
+    Delete,
+    /// Move the file into another directory
+    Move { directory: String },
+}
+
+/// Values filled into a filename pattern.
+#[derive(Debug, Clone)]
+pub struct FileNameFields {
+    /// MSH.10 of the message
+    pub control_id: Option<String>,
+    /// MSH.9 of the message (e.g. "ADT^A01")
+    pub message_type: Option<String>,
+    /// When the file is written
+    pub timestamp: Zoned,
+}
+
+/// Build a file name from a filename pattern.
+///
+/// The pattern can contain these placeholders:
+/// * `{controlId}` - MSH.10, or a random ID if the message has none
+/// * `{messageType}` - MSH.9 with components joined by `_` (e.g. `ADT_A01`)
+/// * `{timestamp}` - When the file is written, as `YYYYMMDDHHMMSSfff`
+/// * `{random}` - 8 random letters and digits
+///
+/// Characters in the filled-in values that don't belong in a file name are
+/// replaced with `_`.
+///
+/// # Returns
+/// * `Ok(String)` - The file name
+/// * `Err(String)` - The pattern has an unknown or unclosed placeholder, or
+///   doesn't make a plain, visible file name
+pub fn file_name(pattern: &str, fields: &FileNameFields) -> Result<String, String> {
+    let pattern = match pattern.trim() {
+        "" => DEFAULT_FILENAME_PATTERN,
+        pattern => pattern,
+    };
+
+    let mut name = String::new();
+    let mut rest = pattern;
+    while let Some(open) = rest.find('{') {
+        name.push_str(rest.get(..open).unwrap_or_default());
+        let after = rest.get(open + 1..).unwrap_or_default();
+        let close = after
+            .find('}')
+            .ok_or_else(|| format!("Unclosed placeholder in filename pattern `{pattern}`"))?;
+        let value = match after.get(..close).unwrap_or_default() {
+            "controlId" => fields
+                .control_id
+                .clone()
+                .filter(|id| !id.is_empty())
+                .unwrap_or_else(|| random_id(20)),
+            "messageType" => fields
+                .message_type
+                .clone()
+                .unwrap_or_else(|| "UNKNOWN".to_string())
+                .replace('^', "_"),
+            "timestamp" => fields.timestamp.strftime("%Y%m%d%H%M%S%3f").to_string(),
+            "random" => random_id(8),
+            placeholder => {
+                return Err(format!(
+                    "Unknown placeholder `{{{placeholder}}}` in filename pattern"
+                ))
+            }
+        };
+        name.extend(value.chars().map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '_'
+            }
+        }));
+        rest = after.get(close + 1..).unwrap_or_default();
+    }
+    name.push_str(rest);
+
+    if name.is_empty() || name.starts_with('.') || name.contains(['/', '\\']) {
+        return Err(format!(
+            "Filename pattern `{pattern}` must make a visible file name without directories"
+        ));
+    }
+    Ok(name)
+}
+
+fn random_id(len: usize) -> String {
+    Alphanumeric.sample_string(&mut rand::rng(), len)
+}
+
+/// Details of a prepared message being written as a file.
+pub(super) struct FileDropSend {
+    pub file_drop: FileDropTransport,
+    pub host: String,
+    pub port: u16,
+    pub message: String,
+    pub body: Vec<u8>,
+    pub control_id: Option<String>,
+    pub message_type: Option<String>,
+    pub endpoint: Option<EndpointLabel>,
+    pub wait_timeout: Duration,
+}
+
+/// Write a message into a local or SFTP directory and report the result.
+///
+/// Emits the same events as an MLLP send: `Sent` once the file is in place,
+/// then `Final(None)`, as a file drop has no response.
+pub(super) async fn send_over_file_drop(app: AppHandle, send: FileDropSend) {
+    let FileDropSend {
+        file_drop,
+        host,
+        port,
+        message,
+        body,
+        control_id,
+        message_type,
+        endpoint,
+        wait_timeout,
+    } = send;
+
+    let fields = FileNameFields {
+        control_id: control_id.clone(),
+        message_type: message_type.clone(),
+        timestamp: Zoned::now(),
+    };
+    let name = match file_name(&file_drop.filename_pattern, &fields) {
+        Ok(name) => name,
+        Err(e) => {
+            log::error!("{e}");
+            emit_response(&app, SendResponse::FailedToSend(e));
+            return;
+        }
+    };
+    let destination = match &file_drop.sftp {
+        Some(login) => format!(
+            "sftp://{user}@{host}:{port}/{path}",
+            user = login.username,
+            path = remote_path(&file_drop.directory, &name).trim_start_matches('/'),
+        ),
+        None => Path::new(&file_drop.directory)
+            .join(&name)
+            .display()
+            .to_string(),
+    };
+    let target = match &endpoint {
+        Some(endpoint) => format!("{endpoint} ({destination})"),
+        None => destination.clone(),
+    };
+    let audit_base = AuditEvent {
+        control_id: control_id.clone(),
+        message_type,
+        endpoint: endpoint.as_ref().map(ToString::to_string),
+        environment: endpoint.as_ref().and_then(|e| e.environment),
+        ..AuditEvent::new(AuditEventKind::MessageSent, &destination)
+    };
+
+    emit_log(
+        &app,
+        format!(
+            "[{now}] Writing message to {target}:\n{message}",
+            now = Zoned::now()
+        ),
+    );
+
+    let written = match &file_drop.sftp {
+        Some(login) => {
+            let upload = upload_over_sftp(login, &host, port, &file_drop.directory, &name, &body);
+            match tokio::time::timeout(wait_timeout, upload).await {
+                Ok(written) => written,
+                Err(_) => Err(format!("Timed out after {wait_timeout:?}")),
+            }
+        }
+        None => match write_local(Path::new(&file_drop.directory), &name, &body).await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err("A file with that name already exists".to_string()),
+            Err(e) => Err(e),
+        },
+    };
+    if let Err(e) = written {
+        log::error!("Failed to write message to {destination}: {e}");
+        record_audit_event(
+            &app,
+            AuditEvent {
+                timestamp: jiff::Timestamp::now(),
+                kind: AuditEventKind::SendFailed,
+                detail: Some(e.clone()),
+                ..audit_base
+            },
+        )
+        .await;
+        emit_response(
+            &app,
+            SendResponse::FailedToSend(format!("{destination}: {e}")),
+        );
+        return;
+    }
+
+    log::info!(
+        "Sent message {control_id} to {target}",
+        control_id = control_id.as_deref().unwrap_or("<none>")
+    );
+    record_audit_event(
+        &app,
+        AuditEvent {
+            timestamp: jiff::Timestamp::now(),
+            ..audit_base
+        },
+    )
+    .await;
+    emit_response(
+        &app,
+        SendResponse::Sent(SendConfirmation {
+            addr: destination,
+            control_id,
+            endpoint,
+        }),
+    );
+    emit_log(
+        &app,
+        format!(
+            "[{now}] Message written; file drops have no response",
+            now = Zoned::now()
+        ),
+    );
+    emit_response(&app, SendResponse::Final(None));
+}
+
+/// Write a file into a local directory under a temporary name of its own, then
+/// link it to its real name so it appears complete. Returns `false`, leaving
+/// nothing behind, if a file with that name already exists.
+pub(super) async fn write_local(directory: &Path, name: &str, body: &[u8]) -> Result<bool, String> {
+    let path = directory.join(name);
+    let partial = directory.join(format!(".{name}.{}.part", uuid::Uuid::new_v4()));
+    tokio::fs::write(&partial, body)
+        .await
+        .map_err(|e| format!("Failed to write file: {e}"))?;
+    // unlike a rename, linking fails rather than replacing a file that's
+    // appeared since the name was chosen
+    let linked = tokio::fs::hard_link(&partial, &path).await;
+    let _ = tokio::fs::remove_file(&partial).await;
+    match linked {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => Ok(false),
+        Err(e) => Err(format!("Failed to move file into place: {e}")),
+    }
+}
+
+/// Path of a file in a remote directory, which always uses `/`.
+fn remote_path(directory: &str, name: &str) -> String {
+    match directory.trim_end_matches('/') {
+        "" if directory.starts_with('/') => format!("/{name}"),
+        "" => name.to_string(),
+        directory => format!("{directory}/{name}"),
+    }
+}
+
+/// Upload a file with the system `sftp` client, under a temporary name first.
+async fn upload_over_sftp(
+    login: &SftpLogin,
+    host: &str,
+    port: u16,
+    directory: &str,
+    name: &str,
+    body: &[u8],
+) -> Result<(), String> {
+    if directory.contains(['"', '\n', '\r']) {
+        return Err("SFTP directory can't contain quotes or line breaks".to_string());
+    }
+
+    // sftp only uploads from a file, so stage the message locally
+    let local = std::env::temp_dir().join(format!("hermes-drop-{}", uuid::Uuid::new_v4()));
+    tokio::fs::write(&local, body)
+        .await
+        .map_err(|e| format!("Failed to stage file for upload: {e}"))?;
+
+    let path = remote_path(directory, name);
+    let partial = remote_path(directory, &format!(".{name}.part"));
+    let script = format!(
+        "put \"{local}\" \"{partial}\"\nrename \"{partial}\" \"{path}\"\n",
+        local = local.display(),
+    );
+    let result = run_sftp(login, host, port, &script).await;
+    let _ = tokio::fs::remove_file(&local).await;
+    result
+}
+
+async fn run_sftp(login: &SftpLogin, host: &str, port: u16, script: &str) -> Result<(), String> {
+    let mut command = Command::new("sftp");
+    command
+        .args(["-b", "-", "-o", "BatchMode=yes", "-P"])
+        .arg(port.to_string());
+    if let Some(identity_file) = login.identity_file.as_deref().filter(|f| !f.is_empty()) {
+        command.arg("-i").arg(identity_file);
+    }
+    command
+        .arg("--")
+        .arg(format!("{user}@{host}", user = login.username))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let mut child = command
+        .spawn()
+        .map_err(|e| format!("Failed to run sftp (is OpenSSH installed?): {e}"))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(script.as_bytes())
+            .await
+            .map_err(|e| format!("Failed to pass commands to sftp: {e}"))?;
+    }
+    let output = child
+        .wait_with_output()
+        .await
+        .map_err(|e| format!("Failed to run sftp: {e}"))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        Err(format!(
+            "sftp failed ({}): {}",
+            output.status,
+            stderr.trim()
+        ))
+    }
+}
+
+/// A running drop watch, held in [`AppData`].
+pub struct DropWatch {
+    /// Directory being watched
+    pub directory: PathBuf,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl DropWatch {
+    /// Stop polling the directory.
+    pub fn stop(self) {
+        self.task.abort();
+    }
+}
+
+/// Size and modification time of a file, to tell when it has stopped changing.
+type FileState = (u64, Option<SystemTime>);
+
+/// Finds files in a directory that are ready to be picked up.
+#[derive(Debug, Default)]
+struct DropScanner {
+    /// Extension files must have to be picked up, lowercase and without the dot
+    extension: Option<String>,
+    /// Files seen on the last poll that haven't been picked up yet
+    pending: HashMap<PathBuf, FileState>,
+    /// Files picked up and left in place, as they were when picked up
+    picked_up: HashMap<PathBuf, FileState>,
+}
+
+impl DropScanner {
+    fn new(extension: Option<String>) -> Self {
+        DropScanner {
+            extension: extension
+                .map(|extension| extension.trim().trim_start_matches('.').to_lowercase())
+                .filter(|extension| !extension.is_empty()),
+            ..DropScanner::default()
+        }
+    }
+
+    /// List the files that were unchanged since the last poll, and haven't been
+    /// picked up as they are now.
+    async fn poll(&mut self, directory: &Path) -> std::io::Result<Vec<PathBuf>> {
+        let mut entries = tokio::fs::read_dir(directory).await?;
+        let mut seen = HashSet::new();
+        let mut ready = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if !self.wants(&path) {
+                continue;
+            }
+            let Ok(metadata) = entry.metadata().await else {
+                continue;
+            };
+            if !metadata.is_file() {
+                continue;
+            }
+            let state = (metadata.len(), metadata.modified().ok());
+            seen.insert(path.clone());
+            if self.picked_up.get(&path) == Some(&state) {
+                continue;
+            }
+            if self.pending.insert(path.clone(), state) == Some(state) {
+                self.pending.remove(&path);
+                ready.push(path);
+            }
+        }
+        // forget files that have gone, so one recreated with the same name is
+        // picked up again
+        self.pending.retain(|path, _| seen.contains(path));
+        self.picked_up.retain(|path, _| seen.contains(path));
+        ready.sort();
+        Ok(ready)
+    }
+
+    fn wants(&self, path: &Path) -> bool {
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            return false;
+        };
+        if name.starts_with('.') {
+            return false;
+        }
+        match &self.extension {
+            Some(extension) => path
+                .extension()
+                .and_then(|e| e.to_str())
+                .is_some_and(|e| e.eq_ignore_ascii_case(extension)),
+            None => true,
+        }
+    }
+
+    /// Remember a file left in place after being picked up.
+    async fn leave(&mut self, path: PathBuf) {
+        if let Ok(metadata) = tokio::fs::metadata(&path).await {
+            self.picked_up
+                .insert(path, (metadata.len(), metadata.modified().ok()));
+        }
+    }
+}
+
+/// Start picking up files dropped into a local directory.
+///
+/// Replaces any drop watch already running. Each message in a picked-up file
+/// is emitted as a `received-message` event, as if the listener had received
+/// it, and recorded as an audit event.
+///
+/// # Arguments
+/// * `directory` - Directory to watch
+/// * `extension` - Only pick up files with this extension, if given
+/// * `encoding` - Encoding to decode the files with (UTF-8 if not given)
+/// * `after_pickup` - What to do with files once picked up (leave them if not
+///   given)
+/// * `app` - Tauri app handle for emitting events
+/// * `state` - Application state holding the watch
+///
+/// # Returns
+/// * `Ok(())` - The watch started
+/// * `Err(String)` - The directory, or the directory to move files to, doesn't
+///   exist
+#[tauri::command]
+pub async fn start_drop_watch(
+    directory: String,
+    extension: Option<String>,
+    encoding: Option<MessageEncoding>,
+    after_pickup: Option<AfterPickup>,
+    app: AppHandle,
+    state: State<'_, AppData>,
+) -> Result<(), String> {
+    let directory = PathBuf::from(directory);
+    let after_pickup = after_pickup.unwrap_or_default();
+    let encoding = encoding.unwrap_or_default();
+    if !tokio::fs::metadata(&directory)
+        .await
+        .is_ok_and(|metadata| metadata.is_dir())
+    {
+        return Err(format!("{} is not a directory", directory.display()));
+    }
+    if let AfterPickup::Move { directory: archive } = &after_pickup {
+        if !tokio::fs::metadata(archive)
+            .await
+            .is_ok_and(|metadata| metadata.is_dir())
+        {
+            return Err(format!("{archive} is not a directory"));
+        }
+    }
+
+    let mut drop_watch = state.drop_watch.lock().await;
+    if let Some(watch) = drop_watch.take() {
+        watch.stop();
+    }
+
+    log::info!("Watching {} for dropped files", directory.display());
+    let task = tokio::spawn({
+        let directory = directory.clone();
+        let mut scanner = DropScanner::new(extension);
+        async move {
+            loop {
+                let ready = match scanner.poll(&directory).await {
+                    Ok(ready) => ready,
+                    Err(e) => {
+                        log::debug!(
+                            "Watched directory {} is unavailable: {e}",
+                            directory.display()
+                        );
+                        Vec::new()
+                    }
+                };
+                for path in ready {
+                    pick_up(&app, &path, encoding).await;
+                    if let Err(e) = dispose(&path, &after_pickup).await {
+                        log::warn!("Failed to clear picked-up file {}: {e}", path.display());
+                    }
+                    if after_pickup == AfterPickup::Leave {
+                        scanner.leave(path).await;
+                    }
+                }
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        }
+    });
+
+    *drop_watch = Some(DropWatch { directory, task });
+    Ok(())
+}
+
+/// Stop the running drop watch.
+///
+/// # Returns
+/// * `Ok(())` - Always succeeds, even if no directory was being watched
+#[tauri::command]
+pub async fn stop_drop_watch(state: State<'_, AppData>) -> Result<(), String> {
+    if let Some(watch) = state.drop_watch.lock().await.take() {
+        log::info!("Stopped watching {}", watch.directory.display());
+        watch.stop();
+    }
+    Ok(())
+}
+
+/// Read a picked-up file and report each message in it as received.
+async fn pick_up(app: &AppHandle, path: &Path, encoding: MessageEncoding) {
+    let contents = match tokio::fs::read(path).await {
+        Ok(contents) => contents,
+        Err(e) => {
+            log::warn!("Failed to read dropped file {}: {e}", path.display());
+            return;
+        }
+    };
+    let contents = match encoding.decode(&contents) {
+        Ok(contents) => contents,
+        Err(e) => {
+            log::warn!("Failed to decode dropped file {}: {e:#}", path.display());
+            return;
+        }
+    };
+
+    let mut received = 0;
+    for message in split_messages(&contents).messages {
+        let message = match hl7_parser::parse_message_with_lenient_newlines(&message.text) {
+            Ok(message) => message,
+            Err(e) => {
+                log::warn!("Skipping unparseable message in {}: {e:#}", path.display());
+                continue;
+            }
+        };
+        received += 1;
+        if let Err(e) = app.emit(
+            events::RECEIVED_MESSAGE,
+            ReceivedMessage {
+                message: message.raw_value().replace('\r', "\n"),
+                saved_path: None,
+                reply_rule: None,
+            },
+        ) {
+            log::error!("Failed to emit received-message event: {e:#}");
+        }
+        let control_id = Some(control_id_of(&message)).filter(|id| !id.is_empty());
+        record_audit_event(
+            app,
+            AuditEvent {
+                control_id,
+                message_type: message_type_of(&message),
+                ..AuditEvent::new(AuditEventKind::MessageReceived, path.display())
+            },
+        )
+        .await;
+    }
+    log::info!("Picked up {received} message(s) from {}", path.display());
+}
+
+/// Delete or move a picked-up file, as the watch was asked to.
+async fn dispose(path: &Path, after_pickup: &AfterPickup) -> std::io::Result<()> {
+    match after_pickup {
+        AfterPickup::Leave => Ok(()),
+        AfterPickup::Delete => tokio::fs::remove_file(path).await,
+        AfterPickup::Move { directory } => {
+            let Some(name) = path.file_name() else {
+                return Ok(());
+            };
+            tokio::fs::rename(path, Path::new(directory).join(name)).await
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use crate::commands::temp_dir;
+
+    fn fields() -> FileNameFields {
+        FileNameFields {
+            control_id: Some("MSG/001".to_string()),
+            message_type: Some("ADT^A01".to_string()),
+            timestamp: "2024-03-05T14:30:15.250+00:00[UTC]".parse().unwrap(),
+        }
+    }
+
+    #[test]
+    fn file_name_fills_placeholders() {
+        assert_eq!(
+            file_name("{messageType}-{controlId}-{timestamp}.hl7", &fields()).unwrap(),
+            "ADT_A01-MSG_001-20240305143015250.hl7"
+        );
+        assert_eq!(file_name("", &fields()).unwrap(), "MSG_001.hl7");
+        assert_eq!(file_name("{random}.txt", &fields()).unwrap().len(), 12);
+
+        assert!(file_name("{controlId", &fields()).is_err());
+        assert!(file_name("{patient}.hl7", &fields()).is_err());
+        assert!(file_name("out/{controlId}.hl7", &fields()).is_err());
+        assert!(file_name(".{controlId}", &fields()).is_err());
+    }
+
+    #[test]
+    fn sftp_names_that_look_like_options_are_rejected() {
+        assert!(check_ssh_name("hl7feed", "username").is_ok());
+        assert!(check_ssh_name("sftp.example.org", "host").is_ok());
+        assert!(check_ssh_name("", "host").is_err());
+        assert!(check_ssh_name("-oProxyCommand=sh", "host").is_err());
+        assert!(check_ssh_name("hl7 feed", "username").is_err());
+        assert!(check_ssh_name("hl7\nfeed", "username").is_err());
+
+        let login: Result<SftpLogin, _> =
+            serde_json::from_str(r#"{"username": "-oProxyCommand=sh"}"#);
+        assert!(login.is_err());
+    }
+
+    #[tokio::test]
+    async fn scanner_waits_for_files_to_settle() {
+        let dir = temp_dir("drop");
+        let mut scanner = DropScanner::new(Some(".HL7".to_string()));
+
+        std::fs::write(dir.join("a.hl7"), "MSH|^~\\&|").unwrap();
+        std::fs::write(dir.join("b.txt"), "MSH|^~\\&|").unwrap();
+        std::fs::write(dir.join(".c.hl7.part"), "MSH|^~\\&|").unwrap();
+        // seen once, not yet known to be settled
+        assert!(scanner.poll(&dir).await.unwrap().is_empty());
+        let ready = scanner.poll(&dir).await.unwrap();
+        assert_eq!(ready, vec![dir.join("a.hl7")]);
+
+        // a file left in place isn't picked up again until it changes
+        scanner.leave(dir.join("a.hl7")).await;
+        assert!(scanner.poll(&dir).await.unwrap().is_empty());
+        std::fs::write(dir.join("a.hl7"), "MSH|^~\\&|changed").unwrap();
+        assert!(scanner.poll(&dir).await.unwrap().is_empty());
+        assert_eq!(scanner.poll(&dir).await.unwrap(), vec![dir.join("a.hl7")]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn local_drop_moves_into_place() {
+        let dir = temp_dir("drop");
+
+        assert!(write_local(&dir, "out.hl7", b"MSH|^~\\&|").await.unwrap());
+        assert_eq!(std::fs::read(dir.join("out.hl7")).unwrap(), b"MSH|^~\\&|");
+        // an existing file isn't overwritten
+        assert!(!write_local(&dir, "out.hl7", b"other").await.unwrap());
+        assert_eq!(std::fs::read(dir.join("out.hl7")).unwrap(), b"MSH|^~\\&|");
+        // and no partial files are left behind
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,253 @@
+//! Configurable MLLP framing.
+//!
+//! Standard MLLP wraps each message in a start block (`0x0B`) and an end block
+//! followed by a carriage return (`0x1C 0x0D`). Some legacy systems use other
+//! bytes, or leave off the trailing carriage return, so the framing used by the
+//! sender and listener can be changed per connection.
+//!
+//! # Decoding
+//!
+//! Bytes before a start block are discarded, so a peer that sends stray bytes
+//! between frames doesn't break the stream. A frame is only returned once its
+//! end block has arrived; the codec remembers how far it has already looked so
+//! a frame arriving in many small reads isn't searched from the start each
+//! time. A frame longer than the codec's maximum is an error rather than being
+//! buffered forever.
+
+use std::io;
+
+use bytes::{Buf, BufMut, BytesMut};
+use serde::{Deserialize, Serialize};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// Bytes marking the start and end of each message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Framing {
+    /// Bytes sent before each message
+    #[serde(rename = "startBlock")]
+    pub start_block: Vec<u8>,
+    /// Bytes sent after each message
+    #[serde(rename = "endBlock")]
+    pub end_block: Vec<u8>,
+}
+
+impl Default for Framing {
+    /// Standard MLLP framing.
+    fn default() -> Self {
+        Framing {
+            start_block: vec![0x0b],
+            end_block: vec![0x1c, 0x0d],
+        }
+    }
+}
+
+impl Framing {
+    /// Check that the framing can delimit messages.
+    ///
+    /// # Returns
+    /// * `Ok(())` - Both blocks are non-empty
+    /// * `Err(String)` - If either block is empty
+    pub fn validate(&self) -> Result<(), String> {
+        if self.start_block.is_empty() {
+            return Err("MLLP start block cannot be empty".to_string());
+        }
+        if self.end_block.is_empty() {
+            return Err("MLLP end block cannot be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Longest message a [`FramingCodec`] accepts unless told otherwise.
+pub const DEFAULT_MAX_FRAME_LEN: usize = 64 * 1024 * 1024;
+
+/// Codec that frames messages with configurable start and end blocks.
+pub struct FramingCodec {
+    framing: Framing,
+    /// Longest message body to buffer before giving up on the stream
+    max_frame_len: usize,
+    /// How much of the body of the frame at the front of the buffer has
+    /// already been searched for an end block
+    scanned: usize,
+}
+
+impl FramingCodec {
+    #[must_use]
+    pub fn new(framing: Framing) -> Self {
+        FramingCodec {
+            framing,
+            max_frame_len: DEFAULT_MAX_FRAME_LEN,
+            scanned: 0,
+        }
+    }
+
+    /// Set the longest message the codec decodes, in bytes.
+    #[must_use]
+    pub fn with_max_frame_len(mut self, max_frame_len: usize) -> Self {
+        self.max_frame_len = max_frame_len;
+        self
+    }
+
+    fn too_long(&self) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Message is longer than the {} byte limit",
+                self.max_frame_len
+            ),
+        )
+    }
+}
+
+impl Decoder for FramingCodec {
+    type Item = BytesMut;
+    type Error = io::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let start_block = self.framing.start_block.as_slice();
+        let end_block = self.framing.end_block.as_slice();
+
+        // once a start block has been found it stays at the front of the
+        // buffer, so this is immediate on later calls
+        let Some(start) = find(src, start_block) else {
+            // keep anything that could be the beginning of a start block
+            let keep = start_block.len().saturating_sub(1).min(src.len());
+            src.advance(src.len() - keep);
+            return Ok(None);
+        };
+        src.advance(start);
+
+        let body = src.get(start_block.len()..).unwrap_or_default();
+        let unscanned = body.get(self.scanned..).unwrap_or_default();
+        let Some(length) = find(unscanned, end_block).map(|at| self.scanned + at) else {
+            if body.len() > self.max_frame_len {
+                return Err(self.too_long());
+            }
+            // an end block may have started arriving at the end of the body
+            self.scanned = body.len().saturating_sub(end_block.len().saturating_sub(1));
+            return Ok(None);
+        };
+        if length > self.max_frame_len {
+            return Err(self.too_long());
+        }
+
+        self.scanned = 0;
+        let mut frame = src.split_to(start_block.len() + length + end_block.len());
+        frame.advance(start_block.len());
+        frame.truncate(length);
+        Ok(Some(frame))
+    }
+}
+
+impl Encoder<BytesMut> for FramingCodec {
+    type Error = io::Error;
+
+    fn encode(&mut self, item: BytesMut, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        dst.reserve(self.framing.start_block.len() + item.len() + self.framing.end_block.len());
+        dst.put_slice(&self.framing.start_block);
+        dst.put(item);
+        dst.put_slice(&self.framing.end_block);
+        Ok(())
+    }
+}
+
+/// Find the first occurrence of `needle` in `haystack`.
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return None;
+    }
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_round_trip_standard_framing() {
+        let mut codec = FramingCodec::new(Framing::default());
+        let mut buffer = BytesMut::new();
+        codec
+            .encode(BytesMut::from(&b"MSH|^~\\&"[..]), &mut buffer)
+            .unwrap();
+        assert_eq!(&buffer[..], b"\x0bMSH|^~\\&\x1c\x0d");
+        assert_eq!(
+            &codec.decode(&mut buffer).unwrap().unwrap()[..],
+            b"MSH|^~\\&"
+        );
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn waits_for_end_block() {
+        let mut codec = FramingCodec::new(Framing::default());
+        let mut buffer = BytesMut::from(&b"junk\x0bMSH|^~\\&\x1c"[..]);
+        assert!(codec.decode(&mut buffer).unwrap().is_none());
+        buffer.put_slice(b"\x0d\x0bMSH");
+        assert_eq!(
+            &codec.decode(&mut buffer).unwrap().unwrap()[..],
+            b"MSH|^~\\&"
+        );
+        assert!(codec.decode(&mut buffer).unwrap().is_none());
+        assert_eq!(&buffer[..], b"\x0bMSH");
+    }
+
+    #[test]
+    fn can_use_custom_framing() {
+        let framing = Framing {
+            start_block: vec![0x02],
+            end_block: vec![0x03],
+        };
+        let mut codec = FramingCodec::new(framing);
+        let mut buffer = BytesMut::from(&b"\x02MSH|1\x03\x02MSH|2\x03"[..]);
+        assert_eq!(&codec.decode(&mut buffer).unwrap().unwrap()[..], b"MSH|1");
+        assert_eq!(&codec.decode(&mut buffer).unwrap().unwrap()[..], b"MSH|2");
+    }
+
+    #[test]
+    fn resumes_searching_where_it_left_off() {
+        let mut codec = FramingCodec::new(Framing::default());
+        let mut buffer = BytesMut::from(&b"\x0bMSH|^~"[..]);
+        assert!(codec.decode(&mut buffer).unwrap().is_none());
+        assert_eq!(codec.scanned, 5);
+        // an end block split across reads is still found
+        buffer.put_slice(b"\\&\x1c");
+        assert!(codec.decode(&mut buffer).unwrap().is_none());
+        assert_eq!(codec.scanned, 8);
+        buffer.put_slice(b"\x0d\x0bMSH|2\x1c\x0d");
+        assert_eq!(
+            &codec.decode(&mut buffer).unwrap().unwrap()[..],
+            b"MSH|^~\\&"
+        );
+        assert_eq!(codec.scanned, 0);
+        assert_eq!(&codec.decode(&mut buffer).unwrap().unwrap()[..], b"MSH|2");
+    }
+
+    #[test]
+    fn frames_over_the_limit_are_rejected() {
+        let mut codec = FramingCodec::new(Framing::default()).with_max_frame_len(8);
+        let mut buffer = BytesMut::from(&b"\x0bMSH|1\x1c\x0d\x0bMSH|^~\\&|"[..]);
+        assert_eq!(&codec.decode(&mut buffer).unwrap().unwrap()[..], b"MSH|1");
+        // still waiting for the end block, but already too long
+        let error = codec.decode(&mut buffer).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+
+        let mut codec = FramingCodec::new(Framing::default()).with_max_frame_len(8);
+        let mut buffer = BytesMut::from(&b"\x0bMSH|^~\\&|ADT\x1c\x0d"[..]);
+        let error = codec.decode(&mut buffer).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn empty_blocks_are_invalid() {
+        let framing = Framing {
+            start_block: vec![],
+            end_block: vec![0x1c],
+        };
+        assert!(framing.validate().is_err());
+        assert!(Framing::default().validate().is_ok());
+    }
+}
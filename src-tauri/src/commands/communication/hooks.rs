@@ -0,0 +1,242 @@
+//! Extension hooks around sent and received messages.
+//!
+//! Extensions subscribe to these events in their `initialize` capabilities:
+//!
+//! * `message/beforeSend` - A request made before each send, answered with a
+//!   message to send instead (e.g., with a control ID stamped in), a veto, or
+//!   nothing to let the send go ahead unchanged
+//! * `message/ackReceived` - A notification of each response to an MLLP send
+//! * `message/received` - A notification of each message the listener receives
+//!
+//! # Ordering
+//!
+//! `message/beforeSend` hooks are asked one after another, in order of
+//! extension name, each seeing the message as the hooks before it left it. A
+//! hook that fails or doesn't answer within `BEFORE_SEND_TIMEOUT` is skipped
+//! with a line in the send log, rather than failing the send.
+//!
+//! # Notifications
+//!
+//! The notifications are sent from their own task, so a send or the listener
+//! is never held up waiting for the extension host.
+
+use jiff::Zoned;
+use std::time::Duration;
+use tauri::{AppHandle, Manager};
+use tokio::time::timeout;
+
+use super::audit::message_type_of;
+use super::listen::control_id_of;
+use super::schedule::ack_code;
+use super::send::{emit_log, EndpointLabel};
+use crate::extensions::types::{
+    AckReceivedParams, BeforeSendParams, BeforeSendResult, MessageReceivedParams,
+};
+use crate::AppData;
+
+/// How long a `message/beforeSend` hook has to answer.
+const BEFORE_SEND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Run a prepared message through the extensions' `message/beforeSend` hooks.
+///
+/// # Arguments
+/// * `app` - Tauri app handle, for the extension host and send log
+/// * `message` - The message about to be sent, with placeholders filled in
+/// * `control_id` - MSH.10 of the message
+/// * `endpoint` - Label of the connection preset being sent to, if any
+///
+/// # Returns
+/// * `Ok((String, Option<String>))` - The message to send and its control ID
+/// * `Err(String)` - If a hook vetoed the send, or changed the message into
+///   one that can't be parsed
+pub(super) async fn run_before_send_hooks(
+    app: &AppHandle,
+    message: String,
+    control_id: Option<String>,
+    endpoint: Option<&EndpointLabel>,
+) -> Result<(String, Option<String>), String> {
+    let hooks = app
+        .state::<AppData>()
+        .extension_host
+        .lock()
+        .await
+        .before_send_hooks()
+        .await;
+
+    let mut message = message;
+    let mut control_id = control_id;
+    for (name, sender) in hooks {
+        let params = BeforeSendParams {
+            message_type: hl7_parser::parse_message_with_lenient_newlines(&message)
+                .ok()
+                .and_then(|parsed| message_type_of(&parsed)),
+            message: message.clone(),
+            control_id: control_id.clone(),
+            endpoint: endpoint.map(ToString::to_string),
+        };
+        let params = match serde_json::to_value(params) {
+            Ok(params) => params,
+            Err(e) => {
+                log::error!("Failed to serialise message/beforeSend params: {e:#}");
+                continue;
+            }
+        };
+
+        let result = match timeout(
+            BEFORE_SEND_TIMEOUT,
+            sender.send("message/beforeSend", params),
+        )
+        .await
+        {
+            Ok(Ok(response)) if response.result.is_null() => Ok(BeforeSendResult::default()),
+            Ok(Ok(response)) => serde_json::from_value(response.result)
+                .map_err(|e| format!("returned an invalid result: {e}")),
+            Ok(Err(e)) => Err(e.to_string()),
+            Err(_) => Err(format!(
+                "didn't answer within {} seconds",
+                BEFORE_SEND_TIMEOUT.as_secs()
+            )),
+        };
+        let result = match result {
+            Ok(result) => result,
+            Err(e) => {
+                log::warn!("message/beforeSend hook {name} failed: {e}");
+                emit_log(
+                    app,
+                    format!(
+                        "[{now}] Skipped {name}'s check before sending: it {e}",
+                        now = Zoned::now()
+                    ),
+                );
+                continue;
+            }
+        };
+
+        if let Some((changed, changed_id)) = apply_before_send(&name, result)? {
+            emit_log(
+                app,
+                format!("[{now}] {name} changed the message", now = Zoned::now()),
+            );
+            message = changed;
+            control_id = changed_id;
+        }
+    }
+    Ok((message, control_id))
+}
+
+/// Apply one hook's answer to `message/beforeSend`.
+///
+/// # Returns
+/// * `Ok(Some((String, Option<String>)))` - The changed message and its control ID
+/// * `Ok(None)` - If the hook left the message as it was
+/// * `Err(String)` - If the hook vetoed the send, or the changed message can't
+///   be parsed
+fn apply_before_send(
+    name: &str,
+    result: BeforeSendResult,
+) -> Result<Option<(String, Option<String>)>, String> {
+    if result.veto {
+        return Err(match result.reason {
+            Some(reason) => format!("{name} cancelled the send: {reason}"),
+            None => format!("{name} cancelled the send"),
+        });
+    }
+    let Some(message) = result.message else {
+        return Ok(None);
+    };
+    let control_id = {
+        let parsed = hl7_parser::parse_message_with_lenient_newlines(&message).map_err(|e| {
+            format!("{name} changed the message into one that can't be parsed: {e:#}")
+        })?;
+        Some(control_id_of(&parsed)).filter(|id| !id.is_empty())
+    };
+    Ok(Some((message, control_id)))
+}
+
+/// Notify subscribed extensions of a response to a send.
+///
+/// # Arguments
+/// * `app` - Tauri app handle, for the extension host
+/// * `response` - The response, decoded
+/// * `control_id` - MSH.10 of the message that was sent
+/// * `target` - Where the message was sent, as shown in the send log
+pub(super) fn notify_ack_received(
+    app: &AppHandle,
+    response: String,
+    control_id: Option<String>,
+    target: String,
+) {
+    let app = app.clone();
+    tokio::spawn(async move {
+        let params = AckReceivedParams {
+            message: None,
+            format: None,
+            control_id,
+            ack_code: ack_code(&response),
+            target,
+        };
+        let state = app.state::<AppData>();
+        let mut host = state.extension_host.lock().await;
+        host.notify_ack_received(&response, params).await;
+    });
+}
+
+/// Notify subscribed extensions of a message the listener received.
+///
+/// # Arguments
+/// * `app` - Tauri app handle, for the extension host
+/// * `message` - The message received
+/// * `params` - The notification, without the message's content
+pub(super) fn notify_message_received(
+    app: &AppHandle,
+    message: String,
+    params: MessageReceivedParams,
+) {
+    let app = app.clone();
+    tokio::spawn(async move {
+        let state = app.state::<AppData>();
+        let mut host = state.extension_host.lock().await;
+        host.notify_message_received(&message, params).await;
+    });
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changed_messages_take_their_control_id() {
+        let result = BeforeSendResult {
+            message: Some("MSH|^~\\&|APP|FAC|||20250101||ADT^A01|SITE-0042|P|2.5.1".to_string()),
+            ..BeforeSendResult::default()
+        };
+        let (message, control_id) = apply_before_send("Stamper", result).unwrap().unwrap();
+        assert!(message.contains("|SITE-0042|"));
+        assert_eq!(control_id.as_deref(), Some("SITE-0042"));
+
+        // an empty answer leaves the message as it was
+        assert!(apply_before_send("Stamper", BeforeSendResult::default())
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn vetoes_cancel_the_send() {
+        let result = BeforeSendResult {
+            veto: true,
+            reason: Some("PID-3 is missing".to_string()),
+            ..BeforeSendResult::default()
+        };
+        assert_eq!(
+            apply_before_send("Site Rules", result).unwrap_err(),
+            "Site Rules cancelled the send: PID-3 is missing"
+        );
+
+        let result = BeforeSendResult {
+            message: Some("not a message".to_string()),
+            ..BeforeSendResult::default()
+        };
+        assert!(apply_before_send("Site Rules", result).is_err());
+    }
+}
@@ -0,0 +1,663 @@
+//! HL7 v2 over HTTP, as an alternative to MLLP.
+//!
+//! Several modern interface engines accept v2 messages POSTed over HTTP(S)
+//! rather than (or as well as) over MLLP, with the ACK returned as the response
+//! body. A connection preset can send that way instead of over MLLP, and the
+//! listener can receive that way (see [`super::listen`]).
+//!
+//! # Sending
+//! The message is POSTed to `http(s)://host:port/path` with the preset's
+//! content type, extra headers, and basic or bearer authentication. Any 2xx
+//! response is a successful send, and its body, if there is one, is the
+//! response message; an empty body is treated like an MLLP send that got no
+//! response. Other statuses fail the send. The send's encoding is used for the
+//! body and to decode the response, and the wait timeout covers the whole
+//! request.
+//!
+//! # Receiving
+//! The listener takes HTTP/1.1 POSTs to any path over plain HTTP, acknowledging
+//! each message in a 200 response with the same content type it was sent with.
+//! Requests that aren't POSTs, or whose bodies aren't HL7, are answered with an
+//! error status. Connections are kept open between requests unless the client
+//! asks to close them. HTTPS isn't offered by the listener, as it would need a
+//! certificate the sender trusts.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use jiff::Zoned;
+use serde::Deserialize;
+use tauri::AppHandle;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+use super::audit::{record_audit_event, AuditEvent, AuditEventKind};
+use super::encoding::MessageEncoding;
+use super::listen::{Connection, ConnectionEventKind};
+use super::schedule::ack_code;
+use super::send::{emit_log, emit_response, EndpointLabel, SendConfirmation, SendResponse};
+
+/// Content type sent when a preset doesn't name one.
+pub const DEFAULT_HTTP_CONTENT_TYPE: &str = "x-application/hl7-v2+er7";
+
+/// Largest request the listener accepts, headers and body together.
+const MAX_REQUEST_SIZE: usize = 16 * 1024 * 1024;
+
+/// How requests to an HTTP endpoint authenticate.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum HttpAuth {
+    /// No authentication
+    #[default]
+    None,
+    /// HTTP basic authentication
+    Basic { username: String, password: String },
+    /// A bearer token in the `Authorization` header
+    Bearer { token: String },
+}
+
+/// Settings for sending over HTTP instead of MLLP, chosen in a connection preset.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HttpTransport {
+    /// Send over HTTPS rather than plain HTTP
+    #[serde(default)]
+    pub https: bool,
+    /// Path to POST to
+    #[serde(default)]
+    pub path: String,
+    /// `Content-Type` of the request ([`DEFAULT_HTTP_CONTENT_TYPE`] if empty)
+    #[serde(rename = "contentType", default)]
+    pub content_type: String,
+    /// Extra request headers, by name
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// How the request authenticates
+    #[serde(default)]
+    pub auth: HttpAuth,
+}
+
+impl HttpTransport {
+    /// The URL to POST to on a host and port.
+    pub fn url(&self, host: &str, port: u16) -> Result<url::Url, String> {
+        let scheme = if self.https { "https" } else { "http" };
+        // IPv6 addresses need brackets in a URL
+        let host = if host.contains(':') && !host.starts_with('[') {
+            format!("[{host}]")
+        } else {
+            host.to_string()
+        };
+        let path = self.path.trim();
+        let separator = if path.starts_with('/') { "" } else { "/" };
+        url::Url::parse(&format!("{scheme}://{host}:{port}{separator}{path}"))
+            .map_err(|e| format!("Invalid HTTP endpoint: {e}"))
+    }
+
+    fn content_type(&self) -> &str {
+        match self.content_type.trim() {
+            "" => DEFAULT_HTTP_CONTENT_TYPE,
+            content_type => content_type,
+        }
+    }
+}
+
+/// Details of a prepared message being sent over HTTP.
+pub(super) struct HttpSend {
+    pub http: HttpTransport,
+    pub host: String,
+    pub port: u16,
+    pub message: String,
+    pub body: Vec<u8>,
+    pub encoding: MessageEncoding,
+    pub control_id: Option<String>,
+    pub message_type: Option<String>,
+    pub endpoint: Option<EndpointLabel>,
+    pub wait_timeout: Duration,
+}
+
+/// POST a message to an HTTP endpoint and report the result.
+///
+/// Emits the same events as an MLLP send: `Sent` once the endpoint accepts the
+/// message, then `Final` with the response body, or `Final(None)` if the body
+/// is empty or the request times out.
+pub(super) async fn send_over_http(app: AppHandle, send: HttpSend) {
+    let HttpSend {
+        http,
+        host,
+        port,
+        message,
+        body,
+        encoding,
+        control_id,
+        message_type,
+        endpoint,
+        wait_timeout,
+    } = send;
+
+    let url = match http.url(&host, port) {
+        Ok(url) => url,
+        Err(e) => {
+            log::error!("{e}");
+            emit_response(&app, SendResponse::FailedToConnect(e));
+            return;
+        }
+    };
+    let target = match &endpoint {
+        Some(endpoint) => format!("{endpoint} ({url})"),
+        None => url.to_string(),
+    };
+    let audit_base = AuditEvent {
+        control_id: control_id.clone(),
+        message_type,
+        endpoint: endpoint.as_ref().map(ToString::to_string),
+        environment: endpoint.as_ref().and_then(|e| e.environment),
+        ..AuditEvent::new(AuditEventKind::MessageSent, &url)
+    };
+    let audit_event = move |kind, detail| AuditEvent {
+        timestamp: jiff::Timestamp::now(),
+        kind,
+        detail,
+        ..audit_base.clone()
+    };
+
+    emit_log(
+        &app,
+        format!(
+            "[{now}] POSTing message to {target}:\n{message}",
+            now = Zoned::now()
+        ),
+    );
+
+    let client = match reqwest::Client::builder().timeout(wait_timeout).build() {
+        Ok(client) => client,
+        Err(e) => {
+            log::error!("Failed to create HTTP client: {e:#}");
+            emit_response(&app, SendResponse::FailedToConnect(format!("{e:#}")));
+            return;
+        }
+    };
+    let mut request = client
+        .post(url.clone())
+        .header(reqwest::header::CONTENT_TYPE, http.content_type());
+    for (name, value) in &http.headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+    request = match &http.auth {
+        HttpAuth::None => request,
+        HttpAuth::Basic { username, password } => request.basic_auth(username, Some(password)),
+        HttpAuth::Bearer { token } => request.bearer_auth(token),
+    };
+
+    let response = match request.body(body).send().await {
+        Ok(response) => response,
+        Err(e) if e.is_timeout() => {
+            log::warn!("Timeout waiting for response from {url}");
+            record_audit_event(&app, audit_event(AuditEventKind::AckTimeout, None)).await;
+            emit_log(
+                &app,
+                format!(
+                    "[{now}] Timeout waiting for response after {wait_timeout:?}",
+                    now = Zoned::now()
+                ),
+            );
+            emit_response(&app, SendResponse::Final(None));
+            return;
+        }
+        Err(e) => {
+            log::error!("Failed to POST message to {url}: {e:#}");
+            let detail = format!("{e:#}");
+            record_audit_event(
+                &app,
+                audit_event(AuditEventKind::SendFailed, Some(detail.clone())),
+            )
+            .await;
+            let response = if e.is_connect() {
+                SendResponse::FailedToConnect(format!("{url}: {detail}"))
+            } else {
+                SendResponse::FailedToSend(detail)
+            };
+            emit_response(&app, response);
+            return;
+        }
+    };
+
+    let status = response.status();
+    let response = match response.bytes().await {
+        Ok(response) => response,
+        Err(e) => {
+            log::error!("Failed to receive response from {url}: {e:#}");
+            emit_response(&app, SendResponse::FailedToReceive(format!("{e:#}")));
+            return;
+        }
+    };
+    if !status.is_success() {
+        let detail = format!("{url} answered with HTTP {status}");
+        log::error!("{detail}");
+        record_audit_event(
+            &app,
+            audit_event(AuditEventKind::SendFailed, Some(detail.clone())),
+        )
+        .await;
+        let body = String::from_utf8_lossy(&response);
+        let detail = match body.trim() {
+            "" => detail,
+            body => format!("{detail}:\n{body}"),
+        };
+        emit_response(&app, SendResponse::FailedToSend(detail));
+        return;
+    }
+
+    log::info!(
+        "Sent message {control_id} to {target}",
+        control_id = control_id.as_deref().unwrap_or("<none>")
+    );
+    record_audit_event(&app, audit_event(AuditEventKind::MessageSent, None)).await;
+    emit_response(
+        &app,
+        SendResponse::Sent(SendConfirmation {
+            addr: url.to_string(),
+            control_id,
+            endpoint,
+        }),
+    );
+
+    if response.is_empty() {
+        emit_log(
+            &app,
+            format!(
+                "[{now}] {target} answered with HTTP {status} and no response body",
+                now = Zoned::now()
+            ),
+        );
+        emit_response(&app, SendResponse::Final(None));
+        return;
+    }
+
+    let response = match encoding.decode(&response) {
+        Ok(response) => response,
+        Err(e) => {
+            log::error!("Failed to decode response: {e:#}");
+            emit_response(&app, SendResponse::FailedToDecode(format!("{e:#}")));
+            return;
+        }
+    };
+    emit_log(
+        &app,
+        format!("[{now}] Response:\n{response}", now = Zoned::now()),
+    );
+    record_audit_event(
+        &app,
+        audit_event(AuditEventKind::AckReceived, ack_code(&response)),
+    )
+    .await;
+
+    let event = match hl7_parser::parse_message_with_lenient_newlines(&response) {
+        Ok(parsed) => SendResponse::Final(Some(parsed.raw_value().to_string())),
+        Err(e) => {
+            log::error!("Failed to parse response message: {e:#}");
+            SendResponse::FailedToParse {
+                message: response.clone(),
+                error: format!("{e:#}"),
+            }
+        }
+    };
+    emit_response(&app, event);
+}
+
+/// The parts of a request's head the listener needs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RequestHead {
+    method: String,
+    content_type: Option<String>,
+    content_length: Option<usize>,
+    /// The client asked to close the connection after this request
+    close: bool,
+}
+
+/// Why a request couldn't be read.
+#[derive(Debug)]
+enum RequestError {
+    /// The connection failed
+    Io(std::io::Error),
+    /// The request can't be served; it's answered with this status and the
+    /// connection closed
+    Invalid(u16, &'static str),
+}
+
+/// Receive and acknowledge messages POSTed on one connection until it closes.
+pub(super) async fn serve_http_connection(
+    connection: Connection,
+    stream: TcpStream,
+    encoding: MessageEncoding,
+    app: AppHandle,
+) {
+    let remote = connection.remote;
+    log::info!("Accepted HTTP connection from {remote}");
+    connection.emit(&app, ConnectionEventKind::Connected, 0, None, None);
+
+    let mut stream = BufReader::new(stream);
+    let mut received = 0;
+    let mut error = None;
+    loop {
+        let (head, body) = match read_request(&mut stream).await {
+            Ok(Some(request)) => request,
+            Ok(None) => break,
+            Err(RequestError::Io(e)) => {
+                log::error!("Failed to receive request from {remote}: {e:#}");
+                error = Some(format!("{e:#}"));
+                break;
+            }
+            Err(RequestError::Invalid(status, reason)) => {
+                log::warn!("Refused request from {remote}: {reason}");
+                // the connection is closed either way
+                let _ = write_response(&mut stream, status, "text/plain", reason.as_bytes(), true)
+                    .await;
+                error = Some(reason.to_string());
+                break;
+            }
+        };
+        let content_type = head
+            .content_type
+            .as_deref()
+            .unwrap_or(DEFAULT_HTTP_CONTENT_TYPE);
+
+        let (status, content_type, response) = if head.method != "POST" {
+            (405, "text/plain", b"Messages must be POSTed".to_vec())
+        } else {
+            match receive(&connection, &app, &mut received, &body, encoding).await {
+                Ok((ack, ack_event)) => {
+                    if let Err(e) =
+                        write_response(&mut stream, 200, content_type, &ack, head.close).await
+                    {
+                        log::error!("Failed to send ACK to {remote}: {e:#}");
+                        error = Some(format!("{e:#}"));
+                        break;
+                    }
+                    record_audit_event(
+                        &app,
+                        AuditEvent {
+                            timestamp: jiff::Timestamp::now(),
+                            ..ack_event
+                        },
+                    )
+                    .await;
+                    if head.close {
+                        break;
+                    }
+                    continue;
+                }
+                Err((status, reason)) => (status, "text/plain", reason.into_bytes()),
+            }
+        };
+        if let Err(e) =
+            write_response(&mut stream, status, content_type, &response, head.close).await
+        {
+            log::error!("Failed to send response to {remote}: {e:#}");
+            error = Some(format!("{e:#}"));
+            break;
+        }
+        if head.close {
+            break;
+        }
+    }
+
+    log::info!("HTTP connection from {remote} closed after {received} message(s)");
+    connection.emit(
+        &app,
+        ConnectionEventKind::Disconnected,
+        received,
+        None,
+        error,
+    );
+}
+
+/// Decode and accept a POSTed message, returning its encoded ACK and the audit
+/// event to record once it's sent, or the status and reason to refuse it with.
+async fn receive(
+    connection: &Connection,
+    app: &AppHandle,
+    received: &mut usize,
+    body: &[u8],
+    encoding: MessageEncoding,
+) -> Result<(Vec<u8>, AuditEvent), (u16, String)> {
+    let message = encoding
+        .decode(body)
+        .map_err(|e| (400, format!("Failed to decode message: {e}")))?;
+    let message = hl7_parser::parse_message_with_lenient_newlines(&message)
+        .map_err(|e| (400, format!("Failed to parse HL7 message: {e:#}")))?;
+    *received += 1;
+    let (ack, ack_event) = connection.accept(app, *received, &message, body).await;
+    let ack = encoding
+        .encode(&ack)
+        .map_err(|e| (500, format!("Failed to encode ACK: {e}")))?;
+    Ok((ack, ack_event))
+}
+
+/// Read one request from a connection, or `None` if the client closed it
+/// between requests.
+async fn read_request(
+    stream: &mut BufReader<TcpStream>,
+) -> Result<Option<(RequestHead, Vec<u8>)>, RequestError> {
+    let mut head = Vec::new();
+    loop {
+        let start = head.len();
+        let read = stream
+            .read_until(b'\n', &mut head)
+            .await
+            .map_err(RequestError::Io)?;
+        if read == 0 {
+            if head.is_empty() {
+                return Ok(None);
+            }
+            return Err(RequestError::Io(std::io::ErrorKind::UnexpectedEof.into()));
+        }
+        if head.len() > MAX_REQUEST_SIZE {
+            return Err(RequestError::Invalid(431, "Request headers are too large"));
+        }
+        // a blank line ends the head; stray blank lines before a request are skipped
+        if matches!(head.get(start..), Some(b"\r\n" | b"\n")) {
+            if start == 0 {
+                head.clear();
+                continue;
+            }
+            break;
+        }
+    }
+
+    let head = parse_request_head(&String::from_utf8_lossy(&head))?;
+    let length = match (head.method.as_str(), head.content_length) {
+        (_, Some(length)) => length,
+        ("POST", None) => return Err(RequestError::Invalid(411, "Content-Length is required")),
+        (_, None) => 0,
+    };
+    if length > MAX_REQUEST_SIZE {
+        return Err(RequestError::Invalid(413, "Message is too large"));
+    }
+    let mut body = vec![0; length];
+    stream
+        .read_exact(&mut body)
+        .await
+        .map_err(RequestError::Io)?;
+    Ok(Some((head, body)))
+}
+
+/// Parse the request line and headers of a request.
+fn parse_request_head(head: &str) -> Result<RequestHead, RequestError> {
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let (Some(method), Some(_target), Some(version)) = (
+        request_line.next(),
+        request_line.next(),
+        request_line.next(),
+    ) else {
+        return Err(malformed());
+    };
+    if !version.starts_with("HTTP/1.") {
+        return Err(RequestError::Invalid(505, "Only HTTP/1.x is supported"));
+    }
+
+    let mut parsed = RequestHead {
+        method: method.to_ascii_uppercase(),
+        content_type: None,
+        content_length: None,
+        // HTTP/1.0 closes after each request unless asked not to
+        close: version == "HTTP/1.0",
+    };
+    for line in lines.filter(|line| !line.trim().is_empty()) {
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(malformed());
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-type" => parsed.content_type = Some(value.to_string()),
+            "content-length" => {
+                parsed.content_length = Some(value.parse().map_err(|_| malformed())?);
+            }
+            "transfer-encoding" if !value.eq_ignore_ascii_case("identity") => {
+                return Err(RequestError::Invalid(411, "Content-Length is required"));
+            }
+            "connection" => {
+                let value = value.to_ascii_lowercase();
+                if value.contains("close") {
+                    parsed.close = true;
+                } else if value.contains("keep-alive") {
+                    parsed.close = false;
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(parsed)
+}
+
+fn malformed() -> RequestError {
+    RequestError::Invalid(400, "Malformed HTTP request")
+}
+
+/// Write a complete response to a request.
+async fn write_response(
+    stream: &mut BufReader<TcpStream>,
+    status: u16,
+    content_type: &str,
+    body: &[u8],
+    close: bool,
+) -> std::io::Result<()> {
+    let mut response = response_head(status, content_type, body.len(), close).into_bytes();
+    response.extend_from_slice(body);
+    let stream = stream.get_mut();
+    stream.write_all(&response).await?;
+    stream.flush().await
+}
+
+/// The status line and headers of a response.
+fn response_head(status: u16, content_type: &str, length: usize, close: bool) -> String {
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        405 => "Method Not Allowed",
+        411 => "Length Required",
+        413 => "Content Too Large",
+        431 => "Request Header Fields Too Large",
+        505 => "HTTP Version Not Supported",
+        _ => "Internal Server Error",
+    };
+    let mut head = format!(
+        "HTTP/1.1 {status} {reason}\r\nContent-Type: {content_type}\r\nContent-Length: {length}\r\n"
+    );
+    if status == 405 {
+        head.push_str("Allow: POST\r\n");
+    }
+    if close {
+        head.push_str("Connection: close\r\n");
+    }
+    head.push_str("\r\n");
+    head
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    fn transport(https: bool, path: &str) -> HttpTransport {
+        HttpTransport {
+            https,
+            path: path.to_string(),
+            content_type: String::new(),
+            headers: HashMap::new(),
+            auth: HttpAuth::None,
+        }
+    }
+
+    #[test]
+    fn builds_endpoint_urls() {
+        assert_eq!(
+            transport(false, "").url("engine", 8080).unwrap().as_str(),
+            "http://engine:8080/"
+        );
+        assert_eq!(
+            transport(true, "hl7/v2").url("::1", 443).unwrap().as_str(),
+            "https://[::1]:443/hl7/v2"
+        );
+        assert_eq!(
+            transport(false, "").content_type(),
+            DEFAULT_HTTP_CONTENT_TYPE
+        );
+
+        let http: HttpTransport = serde_json::from_str(
+            r#"{"https": true, "path": "/in", "contentType": "text/plain",
+                "auth": {"type": "bearer", "token": "abc"}}"#,
+        )
+        .unwrap();
+        assert_eq!(http.content_type(), "text/plain");
+        assert_eq!(
+            http.auth,
+            HttpAuth::Bearer {
+                token: "abc".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn parses_request_heads() {
+        let head = parse_request_head(
+            "post /hl7 HTTP/1.1\r\nHost: x\r\ncontent-type: text/plain\r\nContent-Length: 12\r\n\r\n",
+        )
+        .unwrap();
+        assert_eq!(
+            head,
+            RequestHead {
+                method: "POST".to_string(),
+                content_type: Some("text/plain".to_string()),
+                content_length: Some(12),
+                close: false,
+            }
+        );
+
+        let head = parse_request_head("GET / HTTP/1.0\r\n\r\n").unwrap();
+        assert!(head.close);
+        let head = parse_request_head("POST / HTTP/1.1\r\nConnection: close\r\n\r\n").unwrap();
+        assert!(head.close);
+
+        let refused = |head: &str| match parse_request_head(head).unwrap_err() {
+            RequestError::Invalid(status, _) => status,
+            RequestError::Io(e) => panic!("{e}"),
+        };
+        assert_eq!(refused("nonsense\r\n\r\n"), 400);
+        assert_eq!(refused("POST / HTTP/2\r\n\r\n"), 505);
+        assert_eq!(
+            refused("POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n"),
+            411
+        );
+        assert_eq!(refused("POST / HTTP/1.1\r\nContent-Length: x\r\n\r\n"), 400);
+    }
+
+    #[test]
+    fn writes_response_heads() {
+        assert_eq!(
+            response_head(200, "text/plain", 3, false),
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nContent-Length: 3\r\n\r\n"
+        );
+        assert!(response_head(405, "text/plain", 0, true)
+            .ends_with("Allow: POST\r\nConnection: close\r\n\r\n"));
+    }
+}
@@ -18,28 +18,242 @@
 //! sending system to know that the message was not just received, but also processed
 //! or committed to storage.
 //!
+//! # Framing and Encoding
+//! Like the sender, the listener can use non-standard MLLP start and end blocks
+//! and decode messages in encodings other than UTF-8. ACKs are sent back using
+//! the same framing and encoding, and echo the received message's MSH-18 so the
+//! sender knows which character set they're in.
+//!
+//! # Auditing
+//! Each received message and each ACK sent is recorded as an audit event,
+//! exported if an audit exporter is configured (see [`super::audit`]).
+//!
+//! # Connections
+//! Each accepted connection is served by its own task, so any number of clients
+//! can send at once, and a connection stays open for as many messages as the
+//! client sends (a persistent MLLP session, as interface engines use). Each
+//! connection's lifecycle is reported with the `listener-connection` event:
+//! when it's accepted, for each message received on it, and when it closes.
+//!
+//! # HTTP
+//! The listener can take messages as HTTP POSTs instead of MLLP, for senders
+//! that deliver v2 over HTTP (see [`super::http`]). Each request is
+//! acknowledged in its response, and connections are reported the same way.
+//!
+//! # Saving Received Messages
+//! The listener can write each received message to a directory as it arrives,
+//! named with a filename pattern (see [`super::file_name`]), as an audit trail
+//! of what a sender actually transmitted. The bytes are saved exactly as they
+//! arrived, before decoding, and the saved path is included in the
+//! `received-message` event.
+//!
+//! # Extension Hooks
+//! Extensions subscribed to `message/received` are told of each message as
+//! it's received (see [`super::hooks`]).
+//!
+//! # Reply Rules
+//! Instead of an ACK, the listener can answer messages matching a reply rule
+//! with a templated response, such as a canned RSP for a query (see
+//! [`super::reply_rules`]). The rule that answered a message is included in
+//! the `received-message` event.
+//!
+//! # Binding
+//! The listener binds to all IPv4 interfaces unless given a host, which can be
+//! an IPv4 or IPv6 address (`::` for every interface over both), a host name,
+//! or the name of a network interface (see [`list_listen_interfaces`]). The
+//! port is bound before the listener task is spawned, so a port already in use
+//! is reported as the command's error, and once bound the address is reported
+//! with the `listener-started` event, along with the addresses peers can reach
+//! it on.
+//!
 //! # Lifecycle Management
 //! Only one listener can be active at a time. Starting a new listener automatically
 //! aborts any existing listener. The listener task handle is stored in AppData state
-//! and can be stopped via the `stop_listening` command.
+//! and can be stopped via the `stop_listening` command, which also closes every
+//! open connection.
 
-use core::str;
-use std::net::ToSocketAddrs;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use bytes::BytesMut;
 use futures::{SinkExt, StreamExt};
-use hl7_mllp_codec::MllpCodec;
 use hl7_parser::{
     builder::{FieldBuilder, MessageBuilder, SegmentBuilder},
     message::Separators,
+    Message,
 };
 use rand::distr::{Alphanumeric, SampleString};
+use serde::{Deserialize, Serialize};
 use tauri::{AppHandle, Emitter, State};
-use tokio::net::TcpListener;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinSet;
 use tokio_util::codec::Framed;
 
+use super::audit::{message_type_of, record_audit_event, AuditEvent, AuditEventKind};
+use super::connection_profiles::find_profile;
+use super::encoding::MessageEncoding;
+use super::file_drop::{file_name, write_local, FileNameFields};
+use super::framing::{Framing, FramingCodec};
+use super::hooks::notify_message_received;
+use super::http::serve_http_connection;
+use super::reply_rules::ListenReplies;
+use crate::events;
+use crate::extensions::types::MessageReceivedParams;
 use crate::AppData;
 
+/// Protocol the listener receives messages over.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListenProtocol {
+    /// MLLP-framed messages over TCP
+    #[default]
+    Mllp,
+    /// Messages POSTed over plain HTTP
+    Http,
+}
+
+/// What happened on a listener connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConnectionEventKind {
+    /// A client connected
+    Connected,
+    /// A message was received and acknowledged
+    Message,
+    /// The connection closed
+    Disconnected,
+}
+
+/// Payload of the `listener-connection` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct ListenerConnectionEvent {
+    /// Identifies the connection among those accepted since the listener started
+    pub connection: u64,
+    /// Address of the client
+    pub peer: String,
+    /// What happened
+    pub kind: ConnectionEventKind,
+    /// Messages received on the connection so far
+    pub messages: usize,
+    /// MSH.9 of the message, for `message` events
+    #[serde(rename = "messageType")]
+    pub message_type: Option<String>,
+    /// Why the connection closed, if it was an error
+    pub error: Option<String>,
+}
+
+/// Payload of the `listener-started` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct ListenerStarted {
+    /// Address and port the listener is bound to, e.g. `0.0.0.0:2575`
+    pub address: String,
+    /// Protocol the listener receives messages over
+    pub protocol: ListenProtocol,
+    /// Addresses and ports peers can send to: the bound address, or for a
+    /// listener on every interface, each interface's address of the same family
+    pub reachable: Vec<String>,
+}
+
+/// A network interface address the listener can bind to.
+#[derive(Debug, Clone, Serialize)]
+pub struct ListenInterface {
+    /// Name of the interface, e.g. `eth0`
+    pub name: String,
+    /// The interface's address
+    pub address: String,
+    /// Whether the address is IPv6
+    pub ipv6: bool,
+    /// Whether the interface is a loopback interface, only reachable from this
+    /// computer
+    pub loopback: bool,
+}
+
+/// Payload of the `received-message` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReceivedMessage {
+    /// The message, with segments separated by `\n`
+    pub message: String,
+    /// Where the message was saved, if the listener saves received messages
+    #[serde(rename = "savedPath")]
+    pub saved_path: Option<String>,
+    /// Name of the reply rule that answered the message, if one did
+    #[serde(rename = "replyRule")]
+    pub reply_rule: Option<String>,
+}
+
+/// Where the listener saves received messages.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ListenAutoSave {
+    /// Directory to save messages in, created if it doesn't exist
+    pub directory: PathBuf,
+    /// Pattern for file names (see [`file_name`]); `{controlId}.hl7` if empty
+    #[serde(rename = "filenamePattern", default)]
+    pub filename_pattern: String,
+}
+
+/// Most files tried for one message before giving up on a free name.
+const MAX_SAVE_ATTEMPTS: usize = 100;
+
+impl ListenAutoSave {
+    /// Check the directory can be written to and the pattern makes file names.
+    async fn prepare(&self) -> Result<(), String> {
+        file_name(
+            &self.filename_pattern,
+            &FileNameFields {
+                control_id: Some("CHECK".to_string()),
+                message_type: None,
+                timestamp: jiff::Zoned::now(),
+            },
+        )?;
+        tokio::fs::create_dir_all(&self.directory)
+            .await
+            .map_err(|e| {
+                format!(
+                    "Failed to create save directory {}: {e}",
+                    self.directory.display()
+                )
+            })
+    }
+
+    /// Save a received message, exactly as it arrived.
+    ///
+    /// If a file by the pattern's name already exists (a resent message with
+    /// the same control ID, say), `-2`, `-3`, and so on is added before the
+    /// extension rather than overwriting it.
+    async fn save(&self, raw: &[u8], fields: &FileNameFields) -> Result<PathBuf, String> {
+        let name = file_name(&self.filename_pattern, fields)?;
+        for attempt in 1..=MAX_SAVE_ATTEMPTS {
+            let candidate = numbered(&name, attempt);
+            if write_local(&self.directory, &candidate, raw).await? {
+                return Ok(self.directory.join(candidate));
+            }
+        }
+        Err(format!(
+            "Failed to find a free file name for {name} in {}",
+            self.directory.display()
+        ))
+    }
+}
+
+/// A file name with `-n` added before its extension, unless `n` is 1.
+fn numbered(name: &str, n: usize) -> String {
+    if n <= 1 {
+        return name.to_string();
+    }
+    match Path::new(name).extension().and_then(|e| e.to_str()) {
+        Some(extension) => {
+            let stem = name
+                .strip_suffix(extension)
+                .and_then(|stem| stem.strip_suffix('.'))
+                .unwrap_or(name);
+            format!("{stem}-{n}.{extension}")
+        }
+        None => format!("{name}-{n}"),
+    }
+}
+
 /// Start listening for incoming HL7 messages via MLLP.
 ///
 /// This command starts a TCP listener that accepts incoming connections and processes
@@ -49,8 +263,8 @@ use crate::AppData;
 /// # Listener Lifecycle
 /// * If a listener is already running, it will be aborted before starting the new one
 /// * The listener runs in a background task that continues until explicitly stopped
-/// * Each incoming connection is handled sequentially within the task
-/// * Multiple messages can be received over a single connection
+/// * Each incoming connection is handled concurrently in its own task
+/// * A connection stays open across messages until the client closes it
 ///
 /// # ACK Generation Algorithm
 /// For each received message, the listener:
@@ -68,174 +282,532 @@ use crate::AppData;
 ///
 /// # Event Emission
 /// Received messages are emitted to the frontend via the `received-message` event,
-/// allowing the UI to display incoming messages in real-time.
+/// allowing the UI to display incoming messages in real-time. Connections
+/// opening, receiving, and closing are emitted via the `listener-connection` event.
+///
+/// # Binding
+/// The port is bound before the listener task is spawned, so a port in use is
+/// reported here rather than in the log. The listener running before is
+/// stopped first, and its port released, so restarting on the same port works.
+/// Once bound, the `listener-started` event reports the address.
 ///
 /// # Arguments
-/// * `host` - Host to bind to (defaults to "0.0.0.0" for all interfaces)
+/// * `host` - Address, host name, or interface name to bind to (defaults to
+///   "0.0.0.0" for all IPv4 interfaces; "::" binds every interface over IPv6 and,
+///   where the system allows, IPv4)
 /// * `port` - Port number to listen on
+/// * `framing` - MLLP start and end blocks (defaults to standard MLLP)
+/// * `encoding` - Encoding of received messages and sent ACKs (defaults to UTF-8)
+/// * `protocol` - Whether messages arrive over MLLP or HTTP (defaults to MLLP);
+///   framing doesn't apply to HTTP
+/// * `auto_save` - Directory and filename pattern to save each received message
+///   with, if they should be saved
+/// * `replies` - Rules answering matching messages with templated responses
+///   instead of ACKs
+/// * `profile` - Name of a connection profile supplying the port, framing, and
+///   encoding in place of the arguments, if any. Profiles with TLS settings
+///   can't be listened on.
 /// * `app` - Tauri app handle for emitting events
 /// * `state` - Application state containing the listener task handle
 ///
 /// # Returns
 /// * `Ok(())` - Listener started successfully
-/// * `Err(String)` - Failed to resolve address or bind to port
+/// * `Err(String)` - Failed to resolve the host, the port is in use or can't be
+///   bound, the framing is invalid, the profile doesn't exist or uses TLS, the
+///   save directory can't be created or the filename pattern is invalid, or a
+///   reply rule is incomplete
 #[tauri::command]
 pub async fn start_listening(
     host: Option<&str>,
     port: u16,
+    framing: Option<Framing>,
+    encoding: Option<MessageEncoding>,
+    protocol: Option<ListenProtocol>,
+    auto_save: Option<ListenAutoSave>,
+    replies: Option<ListenReplies>,
+    profile: Option<String>,
     app: AppHandle,
     state: State<'_, AppData>,
 ) -> Result<(), String> {
-    let host = host.unwrap_or("0.0.0.0");
-
-    let addr = format!("{host}:{port}")
-        .to_socket_addrs()
-        .map_err(|_| format!("Failed to resolve address for {}:{}", host, port))?
-        .next()
-        .ok_or_else(|| format!("No host found in `{host}:{port}`"))?;
+    let (port, framing, encoding) = match profile {
+        Some(name) => {
+            let profile = find_profile(&app, &name).await?;
+            if profile.tls.is_some() {
+                return Err(format!(
+                    "Connection profile `{name}` uses TLS, which the listener doesn't offer"
+                ));
+            }
+            (profile.port, Some(profile.framing), Some(profile.encoding))
+        }
+        None => (port, framing, encoding),
+    };
+    let framing = framing.unwrap_or_default();
+    framing.validate()?;
+    let encoding = encoding.unwrap_or_default();
+    let protocol = protocol.unwrap_or_default();
+    if let Some(auto_save) = &auto_save {
+        auto_save.prepare().await?;
+    }
+    let auto_save = auto_save.map(Arc::new);
+    let replies = Arc::new(replies.unwrap_or_default());
+    replies.validate()?;
 
-    log::info!("Listening on {addr}");
+    let addr = listen_address(host.unwrap_or_default(), port)?;
 
-    // Abort any existing listener before starting a new one
+    // Stop any existing listener before starting a new one, waiting for it to
+    // end so the port it held is free again
     let mut listen_join = state.listen_join.lock().await;
-    if let Some(listener) = listen_join.as_mut() {
+    if let Some(listener) = listen_join.take() {
         listener.abort();
-        *listen_join = None;
+        let _ = listener.await;
     }
     drop(listen_join);
 
     let listener = TcpListener::bind(&addr)
         .await
-        .map_err(|e| format!("Failed to start listening on {addr}: {e:#}"))?;
+        .map_err(|e| bind_error(addr, &e))?;
+    let bound = listener.local_addr().unwrap_or(addr);
+    log::info!("Listening on {bound} ({protocol:?})");
+    let started = ListenerStarted {
+        address: bound.to_string(),
+        protocol,
+        reachable: reachable_addresses(bound),
+    };
+    if let Err(e) = app.emit(events::LISTENER_STARTED, started) {
+        log::warn!("Failed to emit listener started event: {e}");
+    }
 
     let handle = tokio::spawn(async move {
-        'accept: loop {
-            let (stream, remote) = match listener.accept().await {
-                Ok((stream, remote)) => (stream, remote),
-                Err(e) => {
-                    log::error!("Failed to accept connection: {e:#}");
-                    continue 'accept;
+        // dropping the set when the listener is aborted aborts every connection
+        let mut connections = JoinSet::new();
+        let mut next_id: u64 = 0;
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let (stream, remote) = match accepted {
+                        Ok((stream, remote)) => (stream, remote),
+                        Err(e) => {
+                            log::error!("Failed to accept connection: {e:#}");
+                            continue;
+                        }
+                    };
+                    next_id += 1;
+                    let connection = Connection {
+                        id: next_id,
+                        remote,
+                        auto_save: auto_save.clone(),
+                        replies: replies.clone(),
+                    };
+                    match protocol {
+                        ListenProtocol::Mllp => connections.spawn(serve_connection(
+                            connection,
+                            stream,
+                            framing.clone(),
+                            encoding,
+                            app.clone(),
+                        )),
+                        ListenProtocol::Http => connections.spawn(serve_http_connection(
+                            connection,
+                            stream,
+                            encoding,
+                            app.clone(),
+                        )),
+                    };
                 }
-            };
-            log::info!("Accepted connection from {remote}");
+                // reap connections that have closed
+                Some(_) = connections.join_next(), if !connections.is_empty() => {}
+            }
+        }
+    });
 
-            let mut transport = Framed::new(stream, MllpCodec::new());
-            'messages: while let Some(result) = transport.next().await {
-                let message = match result {
-                    Ok(message) => message,
-                    Err(e) => {
-                        log::error!("Failed to receive message: {e:#}");
-                        continue 'messages;
-                    }
-                };
-                let Ok(message) = str::from_utf8(&message) else {
-                    log::error!("Failed to decode message: invalid UTF-8");
-                    continue 'messages;
-                };
+    let mut listen_join = state.listen_join.lock().await;
+    *listen_join = Some(handle);
+    drop(listen_join);
+
+    Ok(())
+}
+
+/// List the network interface addresses the listener can bind to.
+///
+/// # Returns
+/// * `Ok(Vec<ListenInterface>)` - Each interface address, IPv4 before IPv6
+/// * `Err(String)` - If the interfaces can't be read
+#[tauri::command]
+pub fn list_listen_interfaces() -> Result<Vec<ListenInterface>, String> {
+    let mut interfaces: Vec<ListenInterface> = if_addrs::get_if_addrs()
+        .map_err(|e| format!("Failed to list network interfaces: {e}"))?
+        .into_iter()
+        .map(|interface| ListenInterface {
+            address: interface.ip().to_string(),
+            ipv6: interface.ip().is_ipv6(),
+            loopback: interface.is_loopback(),
+            name: interface.name,
+        })
+        .collect();
+    interfaces.sort_by_key(|interface| interface.ipv6);
+    Ok(interfaces)
+}
+
+/// Resolve the address to listen on.
+///
+/// `host` can be empty (every IPv4 interface), an IPv4 or IPv6 address (with
+/// or without brackets), the name of a network interface (its first IPv4
+/// address, or IPv6 if it has none), or a host name.
+fn listen_address(host: &str, port: u16) -> Result<SocketAddr, String> {
+    let host = host.trim();
+    if host.is_empty() {
+        return Ok(SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), port));
+    }
+    let literal = host
+        .strip_prefix('[')
+        .and_then(|host| host.strip_suffix(']'))
+        .unwrap_or(host);
+    if let Ok(ip) = literal.parse::<IpAddr>() {
+        return Ok(SocketAddr::new(ip, port));
+    }
+
+    let mut addresses: Vec<IpAddr> = if_addrs::get_if_addrs()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|interface| interface.name == host)
+        .map(|interface| interface.ip())
+        .collect();
+    addresses.sort_by_key(IpAddr::is_ipv6);
+    if let Some(ip) = addresses.first() {
+        return Ok(SocketAddr::new(*ip, port));
+    }
 
-                let message = match hl7_parser::parse_message(message) {
-                    Ok(message) => message,
+    (host, port)
+        .to_socket_addrs()
+        .map_err(|e| format!("Failed to resolve `{host}`: {e}"))?
+        .next()
+        .ok_or_else(|| format!("No address found for `{host}`"))
+}
+
+/// Explain why the listener couldn't bind to an address.
+fn bind_error(addr: SocketAddr, e: &io::Error) -> String {
+    let kind = e.kind();
+    if kind == io::ErrorKind::AddrInUse {
+        format!(
+            "Port {} is already in use on {}. Stop the program using it, or listen on another port.",
+            addr.port(),
+            addr.ip()
+        )
+    } else if kind == io::ErrorKind::AddrNotAvailable {
+        format!(
+            "{} isn't an address of this computer. Choose one of its interfaces, or listen on all of them.",
+            addr.ip()
+        )
+    } else if kind == io::ErrorKind::PermissionDenied {
+        format!(
+            "Not allowed to listen on port {}. Ports below 1024 usually need administrator rights; try a higher port.",
+            addr.port()
+        )
+    } else {
+        format!("Failed to start listening on {addr}: {e:#}")
+    }
+}
+
+/// The addresses peers can reach a listener bound to `bound` on.
+fn reachable_addresses(bound: SocketAddr) -> Vec<String> {
+    if !bound.ip().is_unspecified() {
+        return vec![bound.to_string()];
+    }
+    // `::` accepts IPv4 too on most systems
+    let any_family = bound.is_ipv6();
+    let mut addresses: Vec<SocketAddr> = if_addrs::get_if_addrs()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|interface| any_family || interface.ip().is_ipv4())
+        .map(|interface| SocketAddr::new(interface.ip(), bound.port()))
+        .collect();
+    // addresses other computers can reach first
+    addresses.sort_by_key(|address| (address.ip().is_loopback(), address.is_ipv6()));
+    if addresses.is_empty() {
+        return vec![bound.to_string()];
+    }
+    addresses.iter().map(ToString::to_string).collect()
+}
+
+/// A client connected to the listener.
+#[derive(Debug, Clone)]
+pub(super) struct Connection {
+    pub id: u64,
+    pub remote: SocketAddr,
+    /// Where received messages are saved, if they are
+    pub auto_save: Option<Arc<ListenAutoSave>>,
+    /// Rules answering messages with something other than an ACK
+    pub replies: Arc<ListenReplies>,
+}
+
+impl Connection {
+    /// Report a message received on the connection and build its response.
+    ///
+    /// Saves the message as it arrived (`raw`) if the listener saves messages,
+    /// emits it to the frontend, and records it as an audit event. Returns the
+    /// response, an ACK unless a reply rule matches, with the audit event to
+    /// record once it's been sent.
+    pub(super) async fn accept(
+        &self,
+        app: &AppHandle,
+        received: usize,
+        message: &Message<'_>,
+        raw: &[u8],
+    ) -> (String, AuditEvent) {
+        let message_type = message_type_of(message);
+        let control_id = control_id_of(message);
+
+        let saved_path = match &self.auto_save {
+            Some(auto_save) => {
+                let fields = FileNameFields {
+                    control_id: Some(control_id.clone()),
+                    message_type: message_type.clone(),
+                    timestamp: jiff::Zoned::now(),
+                };
+                match auto_save.save(raw, &fields).await {
+                    Ok(path) => Some(path.display().to_string()),
                     Err(e) => {
-                        log::error!("Failed to parse HL7 message: {e:#}");
-                        continue 'messages;
+                        log::error!("Failed to save received message: {e}");
+                        None
                     }
-                };
-
-                // emit the message
-                if let Err(e) =
-                    app.emit("received-message", message.raw_value().replace('\r', "\n"))
-                {
-                    log::error!("Failed to emit received-message event: {e:#}");
                 }
+            }
+            None => None,
+        };
 
-                // extract info from the message
-                let msh = message
-                    .segment("MSH")
-                    .expect("Valid messages have MSH segments");
-
-                let sending_app = msh
-                    .field(3)
-                    .map(|f| message.separators.decode(f.raw_value()).to_string())
-                    .unwrap_or_default();
-                let sending_facility = msh
-                    .field(4)
-                    .map(|f| message.separators.decode(f.raw_value()).to_string())
-                    .unwrap_or_default();
-                let receiving_app = msh
-                    .field(5)
-                    .map(|f| message.separators.decode(f.raw_value()).to_string())
-                    .unwrap_or_default();
-                let receiving_facility = msh
-                    .field(6)
-                    .map(|f| message.separators.decode(f.raw_value()).to_string())
-                    .unwrap_or_default();
-                let trigger_event = msh
-                    .field(9)
-                    .and_then(|f| f.component(2))
-                    .map(|f| message.separators.decode(f.raw_value()).to_string())
-                    .unwrap_or_default();
-                let control_id = msh
-                    .field(10)
-                    .map(|f| message.separators.decode(f.raw_value()).to_string())
-                    .unwrap_or_default();
-                let processing_id = msh
-                    .field(11)
-                    .map(|f| message.separators.decode(f.raw_value()).to_string())
-                    .unwrap_or_default();
-                let version_id = msh
-                    .field(12)
-                    .map(|f| message.separators.decode(f.raw_value()).to_string())
-                    .unwrap_or_else(|| "2.5.1".to_string());
-
-                let accept_ack = msh.field(15);
-                let application_ack = msh.field(16);
-
-                // Enhanced acknowledgment mode is indicated by the presence of MSH.15 or MSH.16
-                // Enhanced mode uses 'C' (Commit) level ACKs, original mode uses 'A' (Application) level
-                let is_enhanced_mode = accept_ack.is_some() || application_ack.is_some();
-                let ack_level = if is_enhanced_mode { 'C' } else { 'A' };
-
-                let new_cid = Alphanumeric.sample_string(&mut rand::rng(), 20);
-
-                let ack = MessageBuilder::new(Separators::default())
-                    .with_segment(
-                        SegmentBuilder::new("MSH")
-                            .with_field_value(3, receiving_app)
-                            .with_field_value(4, receiving_facility)
-                            .with_field_value(5, sending_app)
-                            .with_field_value(6, sending_facility)
-                            .with_field(
-                                9,
-                                FieldBuilder::default()
-                                    .with_component_value(1, "ACK")
-                                    .with_component_value(2, trigger_event)
-                                    .with_component_value(3, "ACK"),
-                            )
-                            .with_field_value(10, new_cid)
-                            .with_field_value(11, processing_id)
-                            .with_field_value(12, version_id),
-                    )
-                    .with_segment(
-                        SegmentBuilder::new("MSA")
-                            .with_field_value(1, format!("{ack_level}A"))
-                            .with_field_value(2, control_id)
-                            .with_field_value(3, "Message accepted"),
+        let reply = self.replies.find(message).and_then(|rule| {
+            match rule.render(message, &self.replies.variables) {
+                Ok(response) => Some((response, rule.name.clone())),
+                Err(e) => {
+                    log::error!(
+                        "Failed to fill in reply rule `{}`, sending an ACK: {e}",
+                        rule.name
                     );
-                let ack = ack.to_string();
-
-                if let Err(e) = transport.send(BytesMut::from(ack.as_bytes())).await {
-                    log::error!("Failed to send ACK: {e:#}");
-                    continue 'messages;
+                    None
                 }
             }
+        });
+
+        notify_message_received(
+            app,
+            message.raw_value().to_string(),
+            MessageReceivedParams {
+                message: None,
+                format: None,
+                control_id: Some(control_id.clone()).filter(|id| !id.is_empty()),
+                message_type: message_type.clone(),
+                peer: self.remote.to_string(),
+                saved_path: saved_path.clone(),
+            },
+        );
+        if let Err(e) = app.emit(
+            events::RECEIVED_MESSAGE,
+            ReceivedMessage {
+                message: message.raw_value().replace('\r', "\n"),
+                saved_path,
+                reply_rule: reply.as_ref().map(|(_, rule)| rule.clone()),
+            },
+        ) {
+            log::error!("Failed to emit received-message event: {e:#}");
         }
-    });
+        self.emit(
+            app,
+            ConnectionEventKind::Message,
+            received,
+            message_type.clone(),
+            None,
+        );
 
-    let mut listen_join = state.listen_join.lock().await;
-    *listen_join = Some(handle);
-    drop(listen_join);
+        let received_event = AuditEvent {
+            control_id: Some(control_id).filter(|id| !id.is_empty()),
+            message_type,
+            ..AuditEvent::new(AuditEventKind::MessageReceived, self.remote)
+        };
+        record_audit_event(app, received_event.clone()).await;
 
-    Ok(())
+        let (ack, ack_code) = match reply {
+            Some((response, rule)) => (response, format!("Reply rule `{rule}`")),
+            None => build_ack(message),
+        };
+        let ack_event = AuditEvent {
+            kind: AuditEventKind::AckSent,
+            detail: Some(ack_code),
+            ..received_event
+        };
+        (ack, ack_event)
+    }
+
+    /// Report something that happened on the connection to the frontend.
+    pub(super) fn emit(
+        &self,
+        app: &AppHandle,
+        kind: ConnectionEventKind,
+        messages: usize,
+        message_type: Option<String>,
+        error: Option<String>,
+    ) {
+        let event = ListenerConnectionEvent {
+            connection: self.id,
+            peer: self.remote.to_string(),
+            kind,
+            messages,
+            message_type,
+            error,
+        };
+        if let Err(e) = app.emit(events::LISTENER_CONNECTION, event) {
+            log::error!("Failed to emit listener-connection event: {e:#}");
+        }
+    }
+}
+
+/// Receive and acknowledge messages on one connection until the client closes it.
+async fn serve_connection(
+    connection: Connection,
+    stream: TcpStream,
+    framing: Framing,
+    encoding: MessageEncoding,
+    app: AppHandle,
+) {
+    let remote = connection.remote;
+    log::info!("Accepted connection from {remote}");
+    connection.emit(&app, ConnectionEventKind::Connected, 0, None, None);
+
+    let mut received = 0;
+    let mut error = None;
+    let mut transport = Framed::new(stream, FramingCodec::new(framing));
+    'messages: while let Some(result) = transport.next().await {
+        let raw = match result {
+            Ok(raw) => raw,
+            Err(e) => {
+                // an I/O error leaves the connection unusable
+                log::error!("Failed to receive message from {remote}: {e:#}");
+                error = Some(format!("{e:#}"));
+                break 'messages;
+            }
+        };
+        let message = match encoding.decode(&raw) {
+            Ok(message) => message,
+            Err(e) => {
+                log::error!("Failed to decode message: {e}");
+                continue 'messages;
+            }
+        };
+
+        let message = match hl7_parser::parse_message(&message) {
+            Ok(message) => message,
+            Err(e) => {
+                log::error!("Failed to parse HL7 message: {e:#}");
+                continue 'messages;
+            }
+        };
+        received += 1;
+
+        let (ack, ack_event) = connection.accept(&app, received, &message, &raw).await;
+        let ack = match encoding.encode(&ack) {
+            Ok(ack) => ack,
+            Err(e) => {
+                log::error!("Failed to encode ACK: {e}");
+                continue 'messages;
+            }
+        };
+
+        if let Err(e) = transport.send(BytesMut::from(ack.as_slice())).await {
+            log::error!("Failed to send ACK to {remote}: {e:#}");
+            error = Some(format!("{e:#}"));
+            break 'messages;
+        }
+        record_audit_event(
+            &app,
+            AuditEvent {
+                timestamp: jiff::Timestamp::now(),
+                ..ack_event
+            },
+        )
+        .await;
+    }
+
+    log::info!("Connection from {remote} closed after {received} message(s)");
+    connection.emit(
+        &app,
+        ConnectionEventKind::Disconnected,
+        received,
+        None,
+        error,
+    );
+}
+
+/// MSH.10 of a message, decoded.
+pub(super) fn control_id_of(message: &Message) -> String {
+    message
+        .segment("MSH")
+        .and_then(|msh| msh.field(10))
+        .map(|f| message.separators.decode(f.raw_value()).to_string())
+        .unwrap_or_default()
+}
+
+/// Build the ACK for a received message, and its MSA.1 code.
+fn build_ack(message: &Message) -> (String, String) {
+    let field = |n: usize| {
+        message
+            .segment("MSH")
+            .and_then(|msh| msh.field(n))
+            .map(|f| message.separators.decode(f.raw_value()).to_string())
+    };
+
+    let sending_app = field(3).unwrap_or_default();
+    let sending_facility = field(4).unwrap_or_default();
+    let receiving_app = field(5).unwrap_or_default();
+    let receiving_facility = field(6).unwrap_or_default();
+    let trigger_event = message
+        .segment("MSH")
+        .and_then(|msh| msh.field(9))
+        .and_then(|f| f.component(2))
+        .map(|f| message.separators.decode(f.raw_value()).to_string())
+        .unwrap_or_default();
+    let control_id = control_id_of(message);
+    let processing_id = field(11).unwrap_or_default();
+    let version_id = field(12).unwrap_or_else(|| "2.5.1".to_string());
+    let character_set = message
+        .segment("MSH")
+        .and_then(|msh| msh.field(18))
+        .map(|f| f.raw_value().to_string())
+        .unwrap_or_default();
+
+    // Enhanced acknowledgment mode is indicated by the presence of MSH.15 or MSH.16
+    // Enhanced mode uses 'C' (Commit) level ACKs, original mode uses 'A' (Application) level
+    let is_enhanced_mode = field(15).is_some() || field(16).is_some();
+    let ack_code = if is_enhanced_mode { "CA" } else { "AA" };
+
+    let new_cid = Alphanumeric.sample_string(&mut rand::rng(), 20);
+
+    let mut ack_msh = SegmentBuilder::new("MSH")
+        .with_field_value(3, receiving_app)
+        .with_field_value(4, receiving_facility)
+        .with_field_value(5, sending_app)
+        .with_field_value(6, sending_facility)
+        .with_field(
+            9,
+            FieldBuilder::default()
+                .with_component_value(1, "ACK")
+                .with_component_value(2, trigger_event)
+                .with_component_value(3, "ACK"),
+        )
+        .with_field_value(10, new_cid)
+        .with_field_value(11, processing_id)
+        .with_field_value(12, version_id);
+    if !character_set.is_empty() {
+        ack_msh.set_field_value(18, character_set);
+    }
+
+    let ack = MessageBuilder::new(Separators::default())
+        .with_segment(ack_msh)
+        .with_segment(
+            SegmentBuilder::new("MSA")
+                .with_field_value(1, ack_code)
+                .with_field_value(2, control_id)
+                .with_field_value(3, "Message accepted"),
+        );
+    (ack.to_string(), ack_code.to_string())
 }
 
 /// Stop the currently running MLLP listener.
@@ -260,3 +832,89 @@ pub async fn stop_listening(state: State<'_, AppData>) -> Result<(), String> {
     }
     Ok(())
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn acks_swap_sender_and_receiver() {
+        let message = hl7_parser::parse_message(
+            "MSH|^~\\&|LAB|HOSP|HERMES|DEV|20250101||ORU^R01|abc123|P|2.3",
+        )
+        .unwrap();
+        let (ack, code) = build_ack(&message);
+        assert_eq!(code, "AA");
+
+        let ack = hl7_parser::parse_message(&ack).unwrap();
+        assert_eq!(ack.query("MSH.3").unwrap().raw_value(), "HERMES");
+        assert_eq!(ack.query("MSH.5").unwrap().raw_value(), "LAB");
+        assert_eq!(ack.query("MSH.9").unwrap().raw_value(), "ACK^R01^ACK");
+        assert_eq!(ack.query("MSH.12").unwrap().raw_value(), "2.3");
+        assert_eq!(ack.query("MSA.2").unwrap().raw_value(), "abc123");
+    }
+
+    #[test]
+    fn enhanced_mode_commits() {
+        let message = hl7_parser::parse_message(
+            "MSH|^~\\&|LAB|HOSP|HERMES|DEV|20250101||ORU^R01|abc123|P|2.5.1|||AL|NE",
+        )
+        .unwrap();
+        let (ack, code) = build_ack(&message);
+        assert_eq!(code, "CA");
+        let ack = hl7_parser::parse_message(&ack).unwrap();
+        assert_eq!(ack.query("MSA.1").unwrap().raw_value(), "CA");
+    }
+
+    #[test]
+    fn listen_addresses_accept_ipv4_and_ipv6() {
+        assert_eq!(
+            listen_address("", 2575).unwrap(),
+            "0.0.0.0:2575".parse::<SocketAddr>().unwrap()
+        );
+        assert_eq!(
+            listen_address(" 127.0.0.1 ", 2575).unwrap(),
+            "127.0.0.1:2575".parse::<SocketAddr>().unwrap()
+        );
+        assert_eq!(
+            listen_address("::", 2575).unwrap(),
+            "[::]:2575".parse::<SocketAddr>().unwrap()
+        );
+        assert_eq!(
+            listen_address("[::1]", 2575).unwrap(),
+            "[::1]:2575".parse::<SocketAddr>().unwrap()
+        );
+        assert!(listen_address("no such host.invalid", 2575).is_err());
+    }
+
+    #[tokio::test]
+    async fn ports_in_use_are_explained() {
+        let taken = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = taken.local_addr().unwrap();
+        let e = TcpListener::bind(addr).await.unwrap_err();
+        let message = bind_error(addr, &e);
+        assert!(
+            message.starts_with(&format!("Port {} is already in use", addr.port())),
+            "{message}"
+        );
+    }
+
+    #[test]
+    fn listeners_on_one_address_are_reached_there() {
+        let bound: SocketAddr = "192.0.2.7:2575".parse().unwrap();
+        assert_eq!(reachable_addresses(bound), ["192.0.2.7:2575"]);
+        let everywhere: SocketAddr = "0.0.0.0:2575".parse().unwrap();
+        assert!(reachable_addresses(everywhere)
+            .iter()
+            .all(|address| address.ends_with(":2575") && !address.starts_with('[')));
+    }
+
+    #[test]
+    fn saved_names_are_numbered_before_the_extension() {
+        assert_eq!(numbered("abc123.hl7", 1), "abc123.hl7");
+        assert_eq!(numbered("abc123.hl7", 2), "abc123-2.hl7");
+        assert_eq!(numbered("ADT^A01.abc.hl7", 3), "ADT^A01.abc-3.hl7");
+        assert_eq!(numbered("abc123", 2), "abc123-2");
+    }
+}
@@ -0,0 +1,248 @@
+//! Export and import of listener configurations as shareable files.
+//!
+//! A team simulating the same receiving system wants every copy of Hermes
+//! listening the same way, without copying settings across from screenshots.
+//! The listener's configuration can be written to a single TOML file, checked
+//! into a repository or passed around, and imported on another machine.
+//!
+//! # File Format
+//!
+//! ```toml
+//! format = "hermes-listener"  # identifies the file, so any TOML won't do
+//! version = 1                 # format version, see below
+//! name = "Lab receiver"       # optional description
+//!
+//! [listener]
+//! port = 2575
+//! encoding = "utf8"           # "utf8", "latin1", "windows1252", or "msh18"
+//!
+//! [listener.framing]          # optional, standard MLLP if left out
+//! startBlock = [11]
+//! endBlock = [28, 13]
+//! ```
+//!
+//! # Versioning
+//!
+//! [`LISTENER_CONFIG_VERSION`] is raised whenever a setting is added to the
+//! format. Files of an older version import with the new settings at their
+//! defaults; files of a newer version are refused, rather than silently
+//! dropping settings this version doesn't know about. Unknown keys in a file of
+//! a known version are refused too, as they're most likely typos.
+
+use serde::{Deserialize, Serialize};
+
+use super::encoding::MessageEncoding;
+use super::framing::Framing;
+
+/// Value of the `format` key identifying a listener configuration file.
+const LISTENER_CONFIG_FORMAT: &str = "hermes-listener";
+
+/// Current version of the listener configuration format.
+pub const LISTENER_CONFIG_VERSION: u32 = 1;
+
+/// Settings the listener is started with.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ListenerConfig {
+    /// Port to listen on
+    pub port: u16,
+    /// Encoding of received messages and sent ACKs
+    #[serde(default)]
+    pub encoding: MessageEncoding,
+    /// MLLP start and end blocks
+    #[serde(default)]
+    pub framing: Framing,
+}
+
+/// A listener configuration file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ListenerConfigFile {
+    /// Always [`LISTENER_CONFIG_FORMAT`]
+    format: String,
+    /// Format version the file was written in
+    version: u32,
+    /// Optional description of the configuration
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    /// The listener settings
+    listener: ListenerConfig,
+}
+
+/// A listener configuration read from a file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportedListenerConfig {
+    /// Description given when the configuration was exported, if any
+    pub name: Option<String>,
+    /// Format version the file was written in
+    pub version: u32,
+    /// The listener settings
+    pub config: ListenerConfig,
+}
+
+/// Write a listener configuration to a file.
+///
+/// # Arguments
+/// * `path` - Where to write the file
+/// * `config` - The listener settings
+/// * `name` - Optional description, shown when the file is imported
+///
+/// # Returns
+/// * `Ok(())` - The file was written
+/// * `Err(String)` - If the settings are invalid or the file can't be written
+#[tauri::command]
+pub fn export_listener_config(
+    path: &str,
+    config: ListenerConfig,
+    name: Option<String>,
+) -> Result<(), String> {
+    let content = serialise_listener_config(config, name)?;
+    std::fs::write(path, content)
+        .map_err(|e| format!("Failed to write listener configuration: {e}"))
+}
+
+/// Read a listener configuration from a file.
+///
+/// # Arguments
+/// * `path` - The file to read
+///
+/// # Returns
+/// * `Ok(ImportedListenerConfig)` - The settings, and the file's name and version
+/// * `Err(String)` - If the file can't be read, isn't a listener configuration,
+///   was written by a newer version of Hermes, or holds invalid settings
+#[tauri::command]
+pub fn import_listener_config(path: &str) -> Result<ImportedListenerConfig, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read listener configuration: {e}"))?;
+    parse_listener_config(&content)
+}
+
+/// Serialise a listener configuration in the current format version.
+fn serialise_listener_config(
+    config: ListenerConfig,
+    name: Option<String>,
+) -> Result<String, String> {
+    validate_listener_config(&config)?;
+    let file = ListenerConfigFile {
+        format: LISTENER_CONFIG_FORMAT.to_string(),
+        version: LISTENER_CONFIG_VERSION,
+        name: name.filter(|name| !name.trim().is_empty()),
+        listener: config,
+    };
+    toml::to_string_pretty(&file)
+        .map_err(|e| format!("Failed to serialise listener configuration: {e}"))
+}
+
+/// Parse and check a listener configuration file.
+fn parse_listener_config(content: &str) -> Result<ImportedListenerConfig, String> {
+    // check what the file is before its settings, so a file from a newer
+    // version is reported as such rather than as having unknown keys
+    let value: toml::Table = toml::from_str(content)
+        .map_err(|e| format!("Failed to parse listener configuration: {e}"))?;
+    if value.get("format").and_then(toml::Value::as_str) != Some(LISTENER_CONFIG_FORMAT) {
+        return Err("Not a Hermes listener configuration file".to_string());
+    }
+    let version = value
+        .get("version")
+        .and_then(toml::Value::as_integer)
+        .ok_or_else(|| "Listener configuration has no format version".to_string())?;
+    match u32::try_from(version) {
+        Ok(version) if (1..=LISTENER_CONFIG_VERSION).contains(&version) => {}
+        Ok(version) if version > LISTENER_CONFIG_VERSION => {
+            return Err(format!(
+                "Listener configuration is format version {version}, but this version of \
+                 Hermes only reads up to version {LISTENER_CONFIG_VERSION}; update Hermes to \
+                 import it"
+            ));
+        }
+        _ => return Err(format!("Invalid listener configuration version {version}")),
+    }
+
+    let file: ListenerConfigFile = toml::from_str(content)
+        .map_err(|e| format!("Failed to parse listener configuration: {e}"))?;
+    validate_listener_config(&file.listener)?;
+
+    Ok(ImportedListenerConfig {
+        name: file.name,
+        version: file.version,
+        config: file.listener,
+    })
+}
+
+/// Check that the listener could be started with a configuration.
+fn validate_listener_config(config: &ListenerConfig) -> Result<(), String> {
+    if config.port == 0 {
+        return Err("Listener port must be between 1 and 65535".to_string());
+    }
+    config.framing.validate()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    fn config() -> ListenerConfig {
+        ListenerConfig {
+            port: 6661,
+            encoding: MessageEncoding::Windows1252,
+            framing: Framing {
+                start_block: vec![0x0b],
+                end_block: vec![0x1c],
+            },
+        }
+    }
+
+    #[test]
+    fn round_trips_a_configuration() {
+        let content = serialise_listener_config(config(), Some("Lab".to_string())).unwrap();
+        assert!(content.contains("format = \"hermes-listener\""));
+
+        let imported = parse_listener_config(&content).unwrap();
+        assert_eq!(imported.name.as_deref(), Some("Lab"));
+        assert_eq!(imported.version, LISTENER_CONFIG_VERSION);
+        assert_eq!(imported.config, config());
+    }
+
+    #[test]
+    fn fills_in_defaults() {
+        let imported = parse_listener_config(
+            "format = \"hermes-listener\"\nversion = 1\n\n[listener]\nport = 2575\n",
+        )
+        .unwrap();
+        assert_eq!(imported.name, None);
+        assert_eq!(imported.config.encoding, MessageEncoding::Utf8);
+        assert_eq!(imported.config.framing, Framing::default());
+    }
+
+    #[test]
+    fn rejects_files_it_cannot_trust() {
+        let cases = [
+            ("[listener]\nport = 2575", "Not a Hermes listener"),
+            (
+                "format = \"hermes-listener\"\nversion = 2\n[listener]\nport = 2575\nack = \"AE\"",
+                "only reads up to version 1",
+            ),
+            (
+                "format = \"hermes-listener\"\nversion = 0\n[listener]\nport = 2575",
+                "Invalid listener configuration version 0",
+            ),
+            (
+                "format = \"hermes-listener\"\nversion = 1\n[listener]\nprot = 2575",
+                "unknown field",
+            ),
+            (
+                "format = \"hermes-listener\"\nversion = 1\n[listener]\nport = 0",
+                "between 1 and 65535",
+            ),
+        ];
+        for (content, expected) in cases {
+            let error = parse_listener_config(content).unwrap_err();
+            assert!(error.contains(expected), "{error}");
+        }
+
+        let mut invalid = config();
+        invalid.framing.end_block.clear();
+        assert!(serialise_listener_config(invalid, None).is_err());
+    }
+}
@@ -6,18 +6,70 @@
 //! # Modules
 //!
 //! - [`send`] - MLLP client for sending messages and receiving ACKs
+//! - [`retry`] - Retry policy for MLLP sends, with backoff
+//! - [`connection_profiles`] - Named host/port/TLS/encoding profiles, stored by the backend
+//! - [`pool`] - Named connections kept open across sends
+//! - [`http`] - HL7 v2 over HTTP(S), for sending and listening
+//! - [`file_drop`] - Sends written to local or SFTP directories, and watches picking files up
 //! - [`listen`] - MLLP server for receiving messages and sending ACKs
+//! - [`reply_rules`] - Templated responses the listener sends instead of ACKs
+//! - [`relay`] - MLLP proxy between two systems, showing and optionally editing the traffic
+//! - [`schedule`] - Repeated sends on a timer, for load and soak testing
+//! - [`transport`] - Sends over transports provided by extensions
+//! - [`hooks`] - Extension hooks before sends and on acknowledgements and received messages
+//! - [`listener_config`] - Export and import of listener settings as shareable files
+//! - [`framing`] - Configurable MLLP start and end blocks
+//! - [`encoding`] - Character encodings for messages on the wire
+//! - [`engine_import`] - Import of endpoint settings from Mirth Connect and Rhapsody exports
+//! - [`capture`] - Messages extracted from pcap and pcapng network captures
+//! - [`audit`] - Export of send/receive/ACK audit events to a syslog or OTLP collector
+//! - [`control_ids`] - Control IDs already sent or received, for duplicate warnings
 //!
 //! # Event-Driven Architecture
 //!
 //! Both send and listen operations use Tauri events to communicate progress:
 //! - `send-log` / `send-response` - Progress and results from send operations
-//! - `received-message` - Incoming messages from the listener
+//! - `received-message` - Incoming messages from the listener or a drop watch
+//! - `relay-traffic` - Connections and messages passing through the relay
+//! - `schedule-progress` / `schedule-finished` - Results of scheduled sends
 //!
 //! This allows the UI to show real-time feedback while async operations run.
 
+mod audit;
+mod capture;
+mod connection_profiles;
+mod control_ids;
+mod encoding;
+mod engine_import;
+mod file_drop;
+mod framing;
+mod hooks;
+mod http;
 mod listen;
+mod listener_config;
+mod pool;
+mod relay;
+mod reply_rules;
+mod retry;
+mod schedule;
 mod send;
+mod transport;
 
+pub use audit::*;
+pub use capture::*;
+pub use connection_profiles::*;
+pub use control_ids::*;
+pub use encoding::*;
+pub use engine_import::*;
+pub use file_drop::*;
+pub use framing::*;
+pub use http::*;
 pub use listen::*;
+pub use listener_config::*;
+pub use pool::*;
+pub use relay::*;
+pub use reply_rules::*;
+pub use retry::*;
+pub use schedule::*;
 pub use send::*;
+pub use transport::*;
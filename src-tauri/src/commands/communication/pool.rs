@@ -0,0 +1,273 @@
+//! Named MLLP connections kept open across sends.
+//!
+//! Normally each send connects, sends, waits for the ACK, and disconnects. Some
+//! receivers treat every new connection as a new session, resetting sequence
+//! number expectations or logging a session start for each message, so testing
+//! them needs a client that holds one connection open like an interface engine
+//! does.
+//!
+//! A connection is opened under a name with [`open_connection`], and a send
+//! naming it in [`SendRequest::connection`](super::SendRequest) goes over that
+//! connection instead of a new one. Sends over the same connection take turns,
+//! so a response is always read by the send it answers.
+//!
+//! # Framing
+//! The connection's MLLP start and end blocks are fixed when it's opened; a
+//! send over it uses them rather than its own. The encoding is still the
+//! send's.
+//!
+//! # Dropped Connections
+//! A pooled connection can be closed by the receiver at any time, which is only
+//! noticed when the next send over it fails. A send that fails to write, or
+//! reads the end of the connection instead of a response, removes it from the
+//! pool, and it has to be opened again.
+
+use std::collections::HashMap;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+
+use serde::Serialize;
+use tauri::State;
+use tokio::net::TcpStream;
+use tokio::sync::{Mutex, OwnedMutexGuard};
+use tokio_util::codec::Framed;
+
+use super::framing::{Framing, FramingCodec};
+use crate::AppData;
+
+/// An open connection, taken by one send at a time.
+pub struct PooledConnection {
+    /// Messages sent over it so far
+    sends: usize,
+    transport: Framed<TcpStream, FramingCodec>,
+}
+
+impl PooledConnection {
+    /// The framed stream to send over, counting the send.
+    pub(super) fn transport_for_send(&mut self) -> &mut Framed<TcpStream, FramingCodec> {
+        self.sends += 1;
+        &mut self.transport
+    }
+}
+
+/// A connection in the pool, with what's known about it without taking it.
+struct PoolEntry {
+    addr: SocketAddr,
+    opened: jiff::Timestamp,
+    connection: Arc<Mutex<PooledConnection>>,
+}
+
+/// Connections opened by name, held in [`AppData`].
+#[derive(Default)]
+pub struct ConnectionPool {
+    connections: Mutex<HashMap<String, PoolEntry>>,
+}
+
+impl ConnectionPool {
+    /// Add a connected stream to the pool, replacing any connection of the same
+    /// name.
+    async fn insert(
+        &self,
+        name: String,
+        addr: SocketAddr,
+        stream: TcpStream,
+        framing: Framing,
+    ) -> ConnectionInfo {
+        let entry = PoolEntry {
+            addr,
+            opened: jiff::Timestamp::now(),
+            connection: Arc::new(Mutex::new(PooledConnection {
+                sends: 0,
+                transport: Framed::new(stream, FramingCodec::new(framing)),
+            })),
+        };
+        let info = connection_info(&name, &entry);
+        self.connections.lock().await.insert(name, entry);
+        info
+    }
+
+    /// The open connections, sorted by name.
+    async fn list(&self) -> Vec<ConnectionInfo> {
+        let connections = self.connections.lock().await;
+        let mut infos: Vec<ConnectionInfo> = connections
+            .iter()
+            .map(|(name, entry)| connection_info(name, entry))
+            .collect();
+        infos.sort_by(|a, b| a.name.cmp(&b.name));
+        infos
+    }
+
+    /// Address of a named connection, if it's open.
+    pub(super) async fn addr_of(&self, name: &str) -> Option<SocketAddr> {
+        self.connections
+            .lock()
+            .await
+            .get(name)
+            .map(|entry| entry.addr)
+    }
+
+    /// Take a named connection for a send, waiting for any send already using it.
+    pub(super) async fn checkout(&self, name: &str) -> Option<OwnedMutexGuard<PooledConnection>> {
+        let connection = self
+            .connections
+            .lock()
+            .await
+            .get(name)
+            .map(|entry| entry.connection.clone())?;
+        Some(connection.lock_owned().await)
+    }
+
+    /// Remove a connection, returning whether it was open.
+    ///
+    /// The stream closes once any send holding it finishes.
+    pub(super) async fn remove(&self, name: &str) -> bool {
+        self.connections.lock().await.remove(name).is_some()
+    }
+}
+
+/// A named connection, as listed to the frontend.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConnectionInfo {
+    /// Name the connection was opened under
+    pub name: String,
+    /// Address it's connected to
+    pub addr: String,
+    /// When it was opened
+    pub opened: String,
+    /// Messages sent over it so far, or `None` if a send is using it right now
+    pub sends: Option<usize>,
+}
+
+/// Open a connection and keep it in the pool under a name.
+///
+/// Opening a name that's already open replaces it; the old connection closes
+/// once any send using it finishes.
+///
+/// # Arguments
+/// * `name` - Name to send over the connection with
+/// * `host` - Host to connect to
+/// * `port` - Port to connect to
+/// * `framing` - MLLP start and end blocks for every send over the connection
+///   (standard MLLP if not given)
+/// * `state` - Application state holding the pool
+///
+/// # Returns
+/// * `Ok(ConnectionInfo)` - The opened connection
+/// * `Err(String)` - The name is empty, the framing is invalid, or the address
+///   can't be resolved or connected to
+#[tauri::command]
+pub async fn open_connection(
+    name: String,
+    host: String,
+    port: u16,
+    framing: Option<Framing>,
+    state: State<'_, AppData>,
+) -> Result<ConnectionInfo, String> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err("Connection name cannot be empty".to_string());
+    }
+    let framing = framing.unwrap_or_default();
+    framing.validate()?;
+
+    let addr = format!("{host}:{port}")
+        .to_socket_addrs()
+        .map_err(|_| format!("Failed to resolve address for {host}:{port}"))?
+        .next()
+        .ok_or_else(|| format!("No host found in `{host}:{port}`"))?;
+    let stream = TcpStream::connect(addr)
+        .await
+        .map_err(|e| format!("Failed to connect to {addr}: {e}"))?;
+
+    log::info!("Opened pooled connection {name} to {addr}");
+    Ok(state.connections.insert(name, addr, stream, framing).await)
+}
+
+/// Close a named connection.
+///
+/// A send using the connection finishes first.
+///
+/// # Arguments
+/// * `name` - Name the connection was opened under
+/// * `state` - Application state holding the pool
+///
+/// # Returns
+/// * `Ok(())` - Always succeeds, even if no connection had the name
+#[tauri::command]
+pub async fn close_connection(name: String, state: State<'_, AppData>) -> Result<(), String> {
+    if state.connections.remove(&name).await {
+        log::info!("Closed pooled connection {name}");
+    }
+    Ok(())
+}
+
+/// List the open connections, by name.
+///
+/// # Arguments
+/// * `state` - Application state holding the pool
+///
+/// # Returns
+/// The connections, sorted by name
+#[tauri::command]
+pub async fn list_connections(state: State<'_, AppData>) -> Result<Vec<ConnectionInfo>, String> {
+    Ok(state.connections.list().await)
+}
+
+fn connection_info(name: &str, entry: &PoolEntry) -> ConnectionInfo {
+    ConnectionInfo {
+        name: name.to_string(),
+        addr: entry.addr.to_string(),
+        opened: entry.opened.to_string(),
+        sends: entry
+            .connection
+            .try_lock()
+            .ok()
+            .map(|connection| connection.sends),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use bytes::BytesMut;
+    use futures::{SinkExt, StreamExt};
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn sends_share_one_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let pool = ConnectionPool::default();
+        let stream = TcpStream::connect(addr).await.unwrap();
+        pool.insert("lab".to_string(), addr, stream, Framing::default())
+            .await;
+        let (server, _) = listener.accept().await.unwrap();
+        let mut server = Framed::new(server, FramingCodec::new(Framing::default()));
+
+        for message in ["MSH|1", "MSH|2"] {
+            let mut connection = pool.checkout("lab").await.unwrap();
+            // the connection is busy while a send holds it
+            assert_eq!(pool.list().await[0].sends, None);
+            connection
+                .transport_for_send()
+                .send(BytesMut::from(message))
+                .await
+                .unwrap();
+            assert_eq!(
+                &server.next().await.unwrap().unwrap()[..],
+                message.as_bytes()
+            );
+        }
+
+        let infos = pool.list().await;
+        assert_eq!(infos[0].addr, addr.to_string());
+        assert_eq!(infos[0].sends, Some(2));
+
+        assert!(pool.remove("lab").await);
+        assert!(pool.checkout("lab").await.is_none());
+        assert!(!pool.remove("lab").await);
+        // dropping the last reference closed the stream
+        assert!(server.next().await.is_none());
+    }
+}
@@ -0,0 +1,412 @@
+//! MLLP relay: a proxy between two systems that shows everything passing through.
+//!
+//! When two systems exchange messages and something goes wrong between them,
+//! it helps to see exactly what each one sent. The relay listens on a port and
+//! forwards each connection to a target host and port, so a sender pointed at
+//! Hermes instead of its real receiver has its messages relayed on, and the
+//! receiver's ACKs relayed back, while every message in either direction is
+//! shown in the UI.
+//!
+//! # Connections
+//! Each connection accepted by the relay gets its own connection to the target,
+//! and the two are relayed until either side closes, when the other is closed
+//! too. A target that can't be connected to closes the accepted connection
+//! straight away. Connections opening, each message relayed, and connections
+//! closing are reported with the `relay-traffic` event.
+//!
+//! # Modifying Messages
+//! Field edits (see [`FieldEdit`]) can be applied to messages on their way to
+//! the target, and separately to the responses on their way back, e.g. to
+//! rewrite MSH-5 for a test receiver or force an ACK code to check the
+//! sender's error handling. A message the edits can't be applied to, such as
+//! one without the segment an edit names, is relayed unchanged and the failure
+//! reported alongside it, so the relay never holds traffic up.
+//!
+//! # Framing and Encoding
+//! Both sides use the same MLLP start and end blocks. The encoding is only
+//! used to show messages and to apply edits; messages that aren't edited are
+//! relayed byte for byte.
+//!
+//! Relayed messages aren't recorded as audit events, as Hermes neither sends
+//! nor receives them itself.
+
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Arc;
+
+use bytes::BytesMut;
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinSet;
+use tokio_util::codec::Framed;
+
+use super::encoding::MessageEncoding;
+use super::framing::{Framing, FramingCodec};
+use crate::commands::{set_fields, FieldEdit};
+use crate::events;
+use crate::AppData;
+
+/// Settings the relay is started with.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RelayConfig {
+    /// Port to accept connections on
+    #[serde(rename = "listenPort")]
+    pub listen_port: u16,
+    /// Host to relay connections to
+    #[serde(rename = "targetHost")]
+    pub target_host: String,
+    /// Port to relay connections to
+    #[serde(rename = "targetPort")]
+    pub target_port: u16,
+    /// MLLP start and end blocks on both sides (standard MLLP if not given)
+    #[serde(default)]
+    pub framing: Framing,
+    /// Encoding messages are shown and edited in
+    #[serde(default)]
+    pub encoding: MessageEncoding,
+    /// Edits applied to messages on their way to the target
+    #[serde(rename = "messageEdits", default)]
+    pub message_edits: Vec<FieldEdit>,
+    /// Edits applied to responses on their way back from the target
+    #[serde(rename = "responseEdits", default)]
+    pub response_edits: Vec<FieldEdit>,
+}
+
+/// Which way a relayed message was going.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RelayDirection {
+    /// From the connected client to the target
+    ToTarget,
+    /// From the target back to the client
+    ToClient,
+}
+
+/// Payload of the `relay-traffic` event.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum RelayTraffic {
+    /// A client connected and the relay connected to the target for it
+    Connected {
+        /// Identifies the connection among those accepted since the relay started
+        connection: u64,
+        /// Address of the client
+        peer: String,
+    },
+    /// A message was relayed
+    Message {
+        connection: u64,
+        direction: RelayDirection,
+        /// The message as relayed, with segments separated by `\n`
+        message: String,
+        /// The message before it was edited, if the edits changed it
+        original: Option<String>,
+        /// Why the edits couldn't be applied, if they couldn't
+        error: Option<String>,
+    },
+    /// The connection closed, on either side
+    Disconnected {
+        connection: u64,
+        /// Why it closed, if it was an error
+        error: Option<String>,
+    },
+}
+
+/// Start relaying connections on a port to a target host and port.
+///
+/// Replaces any relay already running.
+///
+/// # Arguments
+/// * `config` - Port to listen on, target, framing, encoding, and edits
+/// * `app` - Tauri app handle for emitting events
+/// * `state` - Application state holding the relay task handle
+///
+/// # Returns
+/// * `Ok(())` - The relay is accepting connections
+/// * `Err(String)` - The framing is invalid, the target can't be resolved, or
+///   the port can't be listened on
+#[tauri::command]
+pub async fn start_relay(
+    config: RelayConfig,
+    app: AppHandle,
+    state: State<'_, AppData>,
+) -> Result<(), String> {
+    config.framing.validate()?;
+    let target = format!("{}:{}", config.target_host, config.target_port)
+        .to_socket_addrs()
+        .map_err(|_| {
+            format!(
+                "Failed to resolve address for {}:{}",
+                config.target_host, config.target_port
+            )
+        })?
+        .next()
+        .ok_or_else(|| {
+            format!(
+                "No host found in `{}:{}`",
+                config.target_host, config.target_port
+            )
+        })?;
+
+    let mut relay_join = state.relay_join.lock().await;
+    if let Some(relay) = relay_join.take() {
+        relay.abort();
+    }
+
+    let addr = SocketAddr::from(([0, 0, 0, 0], config.listen_port));
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(|e| format!("Failed to start relay on {addr}: {e:#}"))?;
+    log::info!("Relaying {addr} to {target}");
+
+    let config = Arc::new(config);
+    let handle = tokio::spawn(async move {
+        // dropping the set when the relay is aborted aborts every connection
+        let mut connections = JoinSet::new();
+        let mut next_id: u64 = 0;
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => {
+                    let (stream, peer) = match accepted {
+                        Ok(accepted) => accepted,
+                        Err(e) => {
+                            log::error!("Failed to accept relay connection: {e:#}");
+                            continue;
+                        }
+                    };
+                    next_id += 1;
+                    connections.spawn(relay_connection(
+                        next_id,
+                        stream,
+                        peer,
+                        target,
+                        config.clone(),
+                        app.clone(),
+                    ));
+                }
+                // reap connections that have closed
+                Some(_) = connections.join_next(), if !connections.is_empty() => {}
+            }
+        }
+    });
+
+    *relay_join = Some(handle);
+    Ok(())
+}
+
+/// Stop the relay, closing every connection through it.
+///
+/// # Returns
+/// * `Ok(())` - Always succeeds, even if the relay wasn't running
+#[tauri::command]
+pub async fn stop_relay(state: State<'_, AppData>) -> Result<(), String> {
+    if let Some(relay) = state.relay_join.lock().await.take() {
+        log::info!("Stopped relay");
+        relay.abort();
+    }
+    Ok(())
+}
+
+/// Relay one accepted connection to the target until either side closes.
+async fn relay_connection(
+    connection: u64,
+    client: TcpStream,
+    peer: SocketAddr,
+    target: SocketAddr,
+    config: Arc<RelayConfig>,
+    app: AppHandle,
+) {
+    let server = match TcpStream::connect(target).await {
+        Ok(server) => server,
+        Err(e) => {
+            log::warn!("Failed to connect relay from {peer} to {target}: {e:#}");
+            emit_traffic(
+                &app,
+                RelayTraffic::Disconnected {
+                    connection,
+                    error: Some(format!("Failed to connect to {target}: {e}")),
+                },
+            );
+            return;
+        }
+    };
+    log::info!("Relaying connection from {peer} to {target}");
+    emit_traffic(
+        &app,
+        RelayTraffic::Connected {
+            connection,
+            peer: peer.to_string(),
+        },
+    );
+
+    let (mut to_client, mut from_client) =
+        Framed::new(client, FramingCodec::new(config.framing.clone())).split();
+    let (mut to_target, mut from_target) =
+        Framed::new(server, FramingCodec::new(config.framing.clone())).split();
+
+    let upstream = forward(
+        &app,
+        connection,
+        RelayDirection::ToTarget,
+        &mut from_client,
+        &mut to_target,
+        &config,
+    );
+    let downstream = forward(
+        &app,
+        connection,
+        RelayDirection::ToClient,
+        &mut from_target,
+        &mut to_client,
+        &config,
+    );
+    // whichever side closes first ends the connection; dropping the other
+    // closes it
+    let error = tokio::select! {
+        result = upstream => result.err(),
+        result = downstream => result.err(),
+    };
+
+    log::info!("Relay connection from {peer} closed");
+    emit_traffic(&app, RelayTraffic::Disconnected { connection, error });
+}
+
+/// Relay messages from one side to the other until the sending side closes.
+async fn forward<S, K>(
+    app: &AppHandle,
+    connection: u64,
+    direction: RelayDirection,
+    from: &mut S,
+    to: &mut K,
+    config: &RelayConfig,
+) -> Result<(), String>
+where
+    S: Stream<Item = std::io::Result<BytesMut>> + Unpin,
+    K: Sink<BytesMut, Error = std::io::Error> + Unpin,
+{
+    let (edits, source, destination) = match direction {
+        RelayDirection::ToTarget => (&config.message_edits, "client", "target"),
+        RelayDirection::ToClient => (&config.response_edits, "target", "client"),
+    };
+    while let Some(frame) = from.next().await {
+        let frame = frame.map_err(|e| format!("Failed to receive from {source}: {e}"))?;
+        let relayed = relay_frame(&frame, edits, config.encoding);
+        emit_traffic(
+            app,
+            RelayTraffic::Message {
+                connection,
+                direction,
+                message: relayed.message,
+                original: relayed.original,
+                error: relayed.error,
+            },
+        );
+        to.send(relayed.bytes)
+            .await
+            .map_err(|e| format!("Failed to relay to {destination}: {e}"))?;
+    }
+    Ok(())
+}
+
+/// A message ready to relay, with what to show of it.
+#[derive(Debug)]
+struct Relayed {
+    bytes: BytesMut,
+    message: String,
+    original: Option<String>,
+    error: Option<String>,
+}
+
+/// Apply edits to a received frame, falling back to relaying it unchanged.
+fn relay_frame(frame: &[u8], edits: &[FieldEdit], encoding: MessageEncoding) -> Relayed {
+    let unchanged = |message: String, error: Option<String>| Relayed {
+        bytes: BytesMut::from(frame),
+        message: message.replace('\r', "\n"),
+        original: None,
+        error,
+    };
+
+    let text = match encoding.decode(frame) {
+        Ok(text) => text,
+        Err(e) => {
+            let error = (!edits.is_empty()).then(|| format!("Failed to decode message: {e}"));
+            return unchanged(String::from_utf8_lossy(frame).into_owned(), error);
+        }
+    };
+    if edits.is_empty() {
+        return unchanged(text, None);
+    }
+
+    let edited = match set_fields(&text, edits.to_vec()) {
+        Ok(edited) => edited.replace('\n', "\r"),
+        Err(e) => return unchanged(text, Some(e)),
+    };
+    if edited == text.trim_end_matches(['\r', '\n']) {
+        return unchanged(text, None);
+    }
+    match encoding.encode(&edited) {
+        Ok(bytes) => Relayed {
+            bytes: BytesMut::from(bytes.as_slice()),
+            message: edited.replace('\r', "\n"),
+            original: Some(text.replace('\r', "\n")),
+            error: None,
+        },
+        Err(e) => unchanged(text, Some(format!("Failed to encode edited message: {e}"))),
+    }
+}
+
+fn emit_traffic(app: &AppHandle, traffic: RelayTraffic) {
+    if let Err(e) = app.emit(events::RELAY_TRAFFIC, traffic) {
+        log::error!("Failed to emit relay-traffic event: {e:#}");
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str = "MSH|^~\\&|LAB|HOSP|EHR|HOSP|20240101||ORU^R01|1|P|2.5.1\rPID|1||123";
+
+    fn edit(path: &str, value: &str) -> FieldEdit {
+        FieldEdit {
+            path: path.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn unedited_frames_relay_byte_for_byte() {
+        let relayed = relay_frame(MESSAGE.as_bytes(), &[], MessageEncoding::default());
+        assert_eq!(&relayed.bytes[..], MESSAGE.as_bytes());
+        assert_eq!(relayed.message, MESSAGE.replace('\r', "\n"));
+        assert_eq!(relayed.original, None);
+        assert_eq!(relayed.error, None);
+    }
+
+    #[test]
+    fn edits_rewrite_the_relayed_message() {
+        let relayed = relay_frame(
+            MESSAGE.as_bytes(),
+            &[edit("MSH.5", "TEST")],
+            MessageEncoding::default(),
+        );
+        let expected = MESSAGE.replace("|EHR|", "|TEST|");
+        assert_eq!(&relayed.bytes[..], expected.as_bytes());
+        assert_eq!(relayed.original, Some(MESSAGE.replace('\r', "\n")));
+        assert_eq!(relayed.error, None);
+    }
+
+    #[test]
+    fn failed_edits_relay_unchanged() {
+        let relayed = relay_frame(
+            MESSAGE.as_bytes(),
+            &[edit("OBX.5", "1")],
+            MessageEncoding::default(),
+        );
+        assert_eq!(&relayed.bytes[..], MESSAGE.as_bytes());
+        assert_eq!(relayed.original, None);
+        assert!(relayed.error.is_some());
+    }
+}
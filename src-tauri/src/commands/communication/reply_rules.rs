@@ -0,0 +1,361 @@
+//! Reply rules: answering received messages with templated responses.
+//!
+//! By default the listener answers every message with an ACK. Reply rules let
+//! it answer some messages with a message of their own instead, such as a
+//! canned RSP for a QBP query, so Hermes can stand in for the system at the
+//! other end of a query workflow.
+//!
+//! Rules are tried in order and the first one that matches answers the
+//! message. A rule matches on the message type (MSH-9) and, optionally, on the
+//! values of other fields. Messages no rule matches are ACKed as usual.
+//!
+//! # Query Parameters
+//!
+//! For QBP and legacy QRY queries, a condition's path can also name part of the
+//! query as `query.NAME` (see [`query_parameters`]): `query.tag`, `query.name`,
+//! `query.limit`, or the path of a parameter, e.g. `query.PID.5.1` for the
+//! family name a Q22 searches for. A rule can then answer a search for one
+//! patient differently from a search for another.
+//!
+//! # Templates
+//!
+//! A rule's response is a template, filled in for each message it answers:
+//!
+//! 1. `{request.PATH}` is replaced by the raw value at `PATH` in the received
+//!    message (e.g. `{request.MSH.10}` for its control ID, or
+//!    `{request.QPD.2}` for a query tag), or by nothing if it isn't present
+//! 2. `{query.NAME}` is replaced by part of the query, as in conditions (e.g.
+//!    `{query.tag}`, or `{query.PID.5.1}`), escaped, or by nothing if the
+//!    message isn't a query or doesn't have it
+//! 3. Placeholders such as `{now}` and `{uuid}`, and the variables sent with
+//!    the rules, are filled in (see [`expand_placeholders`])
+//! 4. `{=...}` formulas are evaluated (see [`evaluate_formulas`])
+//!
+//! ```text
+//! MSH|^~\&|{request.MSH.5}|{request.MSH.6}|{request.MSH.3}|{request.MSH.4}|{now}||RSP^K22^RSP_K21|{uuid}|P|2.5.1
+//! MSA|AA|{request.MSH.10}
+//! QAK|{query.tag}|OK|{request.QPD.1}
+//! QPD|{request.QPD.1}|{request.QPD.2}|{request.QPD.3}
+//! PID|1||12345^^^HOSP^MR||{query.PID.5.1}^JANE
+//! ```
+//!
+//! If a template can't be filled in, or doesn't fill in to a valid message,
+//! the error is logged and the message is ACKed instead, so a broken rule
+//! never leaves a sender waiting.
+
+use hl7_parser::query::LocationQuery;
+use hl7_parser::Message;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+use crate::commands::{
+    escape_text, evaluate_formulas, expand_placeholders, query_parameters, QueryParameters,
+};
+
+/// Marks a reference to a field of the received message in a template.
+const REQUEST_FIELD: &str = "{request.";
+
+/// Marks a reference to part of the received query in a template.
+const QUERY_FIELD: &str = "{query.";
+
+/// Prefix of a condition path naming part of the received query.
+const QUERY_PATH: &str = "query.";
+
+/// Reply rules the listener is started with.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ListenReplies {
+    /// Rules, tried in order
+    #[serde(default)]
+    pub rules: Vec<ReplyRule>,
+    /// User-defined variables for the templates, by name
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+}
+
+/// A rule answering matching messages with a templated response.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReplyRule {
+    /// Name the rule is shown and logged with
+    pub name: String,
+    /// MSH-9 to match, by component (e.g. `QBP` for any QBP, or `QBP^Q22`);
+    /// empty matches any message
+    #[serde(rename = "messageType", default)]
+    pub message_type: String,
+    /// Other field values the message must have
+    #[serde(default)]
+    pub conditions: Vec<ReplyCondition>,
+    /// The response, with segments separated by newlines or carriage returns
+    pub template: String,
+}
+
+/// A field value a message must have for a rule to match.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReplyCondition {
+    /// Path of the field, e.g. `QPD.1.1`, or part of the query, e.g.
+    /// `query.PID.5.1`
+    pub path: String,
+    /// Value the field must have, after unescaping
+    pub value: String,
+}
+
+impl ListenReplies {
+    /// Check every rule can be used.
+    pub(super) fn validate(&self) -> Result<(), String> {
+        self.rules.iter().try_for_each(ReplyRule::validate)
+    }
+
+    /// The first rule matching a message, if any.
+    pub(super) fn find(&self, message: &Message) -> Option<&ReplyRule> {
+        self.rules.iter().find(|rule| rule.matches(message))
+    }
+}
+
+impl ReplyRule {
+    /// Check the rule is complete and its paths are valid.
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Reply rules need a name".to_string());
+        }
+        if !self.template.trim_start().starts_with("MSH") {
+            return Err(format!(
+                "The response for reply rule `{}` must start with an MSH segment",
+                self.name
+            ));
+        }
+        for condition in &self.conditions {
+            let path = condition.path.as_str();
+            let valid = match path.strip_prefix(QUERY_PATH) {
+                Some(name) => check_query_name(name),
+                None => LocationQuery::parse(path).map(|_| ()),
+            };
+            valid
+                .map_err(|e| format!("Invalid path `{path}` in reply rule `{}`: {e}", self.name))?;
+        }
+        for path in references(&self.template, REQUEST_FIELD)? {
+            LocationQuery::parse(path).map_err(|e| {
+                format!(
+                    "Invalid path `{path}` in the response for reply rule `{}`: {e}",
+                    self.name
+                )
+            })?;
+        }
+        for name in references(&self.template, QUERY_FIELD)? {
+            check_query_name(name).map_err(|e| {
+                format!(
+                    "Invalid query reference `{name}` in the response for reply rule `{}`: {e}",
+                    self.name
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Whether a message has the rule's message type and field values.
+    fn matches(&self, message: &Message) -> bool {
+        let message_type = message
+            .query("MSH.9")
+            .map(|value| value.raw_value())
+            .unwrap_or_default();
+        let expected_type = self.message_type.trim();
+        let mut actual = message_type.split(message.separators.component);
+        let type_matches = expected_type.is_empty()
+            || expected_type.split('^').all(|expected| {
+                let actual = actual.next().unwrap_or_default();
+                expected.is_empty() || expected.eq_ignore_ascii_case(actual)
+            });
+
+        // only read when a condition asks about the query
+        let mut query: Option<Option<QueryParameters>> = None;
+        type_matches
+            && self.conditions.iter().all(|condition| {
+                let value = match condition.path.strip_prefix(QUERY_PATH) {
+                    Some(name) => query
+                        .get_or_insert_with(|| query_parameters(message))
+                        .as_ref()
+                        .and_then(|query| query.get(name))
+                        .unwrap_or_default()
+                        .to_string(),
+                    None => message
+                        .query(condition.path.as_str())
+                        .map(|value| message.separators.decode(value.raw_value()).to_string())
+                        .unwrap_or_default(),
+                };
+                value == condition.value
+            })
+    }
+
+    /// Fill in the rule's response for a received message.
+    ///
+    /// # Returns
+    /// * `Ok(String)` - The response, with segments separated by `\r`
+    /// * `Err(String)` - If a placeholder or formula is malformed, or the
+    ///   response isn't a valid message
+    pub(super) fn render(
+        &self,
+        message: &Message,
+        variables: &HashMap<String, String>,
+    ) -> Result<String, String> {
+        let response = fill_references(&self.template, REQUEST_FIELD, |path| {
+            message
+                .query(path)
+                .map(|value| value.raw_value().to_string())
+        })?;
+        let query = query_parameters(message);
+        let response = fill_references(&response, QUERY_FIELD, |name| {
+            query
+                .as_ref()
+                .and_then(|query| query.get(name))
+                .map(|value| escape_text(value, &message.separators))
+        })?;
+        let response = expand_placeholders(&response, variables)?;
+        let response = evaluate_formulas(&response)?;
+        let response = response.trim().replace("\r\n", "\r").replace('\n', "\r");
+        hl7_parser::parse_message(&response).map_err(|e| {
+            format!(
+                "The response for reply rule `{}` isn't a valid message: {e}",
+                self.name
+            )
+        })?;
+        Ok(response)
+    }
+}
+
+/// What the references starting with `marker` (`{request.` or `{query.`) in a
+/// template refer to.
+fn references<'t>(template: &'t str, marker: &str) -> Result<Vec<&'t str>, String> {
+    let mut names = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find(marker) {
+        let after = rest.get(start + marker.len()..).unwrap_or_default();
+        let end = after
+            .find('}')
+            .ok_or_else(|| format!("Unterminated `{marker}` reference"))?;
+        names.push(after.get(..end).unwrap_or_default());
+        rest = after.get(end + 1..).unwrap_or_default();
+    }
+    Ok(names)
+}
+
+/// Replace each reference starting with `marker` in a template with what
+/// `resolve` gives for it, or nothing if it gives nothing.
+fn fill_references(
+    template: &str,
+    marker: &str,
+    resolve: impl Fn(&str) -> Option<String>,
+) -> Result<String, String> {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find(marker) {
+        filled.push_str(rest.get(..start).unwrap_or_default());
+        let after = rest.get(start + marker.len()..).unwrap_or_default();
+        let end = after
+            .find('}')
+            .ok_or_else(|| format!("Unterminated `{marker}` reference"))?;
+        if let Some(value) = resolve(after.get(..end).unwrap_or_default()) {
+            filled.push_str(&value);
+        }
+        rest = after.get(end + 1..).unwrap_or_default();
+    }
+    filled.push_str(rest);
+    Ok(filled)
+}
+
+/// Check a reference to part of a query names one: `tag`, `name`, `limit`, or
+/// a field path.
+fn check_query_name(name: &str) -> Result<(), String> {
+    match name {
+        "tag" | "name" | "limit" => Ok(()),
+        path => LocationQuery::parse(path.strip_prefix('@').unwrap_or(path))
+            .map(|_| ())
+            .map_err(|e| e.to_string()),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const QUERY: &str = "MSH|^~\\&|EMR|HOSP|HERMES|LAB|20250101||QBP^Q22^QBP_Q21|q1|P|2.5.1\rQPD|IHE PDQ Query^IHE^HL7|tag42|@PID.5.1^SMITH";
+
+    fn rule(message_type: &str, conditions: &[(&str, &str)]) -> ReplyRule {
+        ReplyRule {
+            name: "PDQ".to_string(),
+            message_type: message_type.to_string(),
+            conditions: conditions
+                .iter()
+                .map(|(path, value)| ReplyCondition {
+                    path: path.to_string(),
+                    value: value.to_string(),
+                })
+                .collect(),
+            template: "MSH|^~\\&|{request.MSH.5}|||||RSP^K22^RSP_K21|r1|P|2.5.1\nMSA|AA|{request.MSH.10}\nQAK|{request.QPD.2}|OK".to_string(),
+        }
+    }
+
+    #[test]
+    fn matches_on_type_and_fields() {
+        let message = hl7_parser::parse_message(QUERY).unwrap();
+        assert!(rule("", &[]).matches(&message));
+        assert!(rule("QBP", &[]).matches(&message));
+        assert!(rule("qbp^Q22", &[]).matches(&message));
+        assert!(rule("^Q22", &[]).matches(&message));
+        assert!(!rule("QBP^Q23", &[]).matches(&message));
+        assert!(!rule("ADT", &[]).matches(&message));
+        assert!(rule("QBP", &[("QPD.1.1", "IHE PDQ Query")]).matches(&message));
+        assert!(!rule("QBP", &[("QPD.2", "other")]).matches(&message));
+    }
+
+    #[test]
+    fn fills_in_request_fields() {
+        let message = hl7_parser::parse_message(QUERY).unwrap();
+        let response = rule("QBP", &[]).render(&message, &HashMap::new()).unwrap();
+        assert_eq!(
+            response,
+            "MSH|^~\\&|HERMES|||||RSP^K22^RSP_K21|r1|P|2.5.1\rMSA|AA|q1\rQAK|tag42|OK"
+        );
+    }
+
+    #[test]
+    fn matches_and_fills_in_query_parameters() {
+        let message = hl7_parser::parse_message(QUERY).unwrap();
+        assert!(rule("QBP", &[("query.PID.5.1", "SMITH")]).matches(&message));
+        assert!(rule("QBP", &[("query.tag", "tag42")]).matches(&message));
+        assert!(!rule("QBP", &[("query.PID.5.1", "JONES")]).matches(&message));
+        assert!(!rule("QBP", &[("query.PID.7", "19800101")]).matches(&message));
+
+        let mut rule = rule("QBP", &[]);
+        rule.template =
+            "MSH|^~\\&|HERMES|||||RSP^K22|r1|P|2.5.1\nQAK|{query.tag}|OK\nPID|1||||{query.@PID.5.1}~{query.PID.7}".to_string();
+        assert!(rule.validate().is_ok());
+        let response = rule.render(&message, &HashMap::new()).unwrap();
+        assert_eq!(
+            response,
+            "MSH|^~\\&|HERMES|||||RSP^K22|r1|P|2.5.1\rQAK|tag42|OK\rPID|1||||SMITH~"
+        );
+    }
+
+    #[test]
+    fn rejects_unusable_rules() {
+        assert!(rule("QBP", &[]).validate().is_ok());
+        assert!(rule("QBP", &[("PID.x", "x")]).validate().is_err());
+
+        let mut unnamed = rule("QBP", &[]);
+        unnamed.name = " ".to_string();
+        assert!(unnamed.validate().is_err());
+
+        let mut headless = rule("QBP", &[]);
+        headless.template = "MSA|AA|{request.MSH.10}".to_string();
+        assert!(headless.validate().is_err());
+
+        let mut unterminated = rule("QBP", &[]);
+        unterminated.template = "MSH|^~\\&|{request.MSH.5".to_string();
+        assert!(unterminated.validate().is_err());
+
+        assert!(rule("QBP", &[("query.limit", "10")]).validate().is_ok());
+        assert!(rule("QBP", &[("query.PID.x", "x")]).validate().is_err());
+        let mut unknown = rule("QBP", &[]);
+        unknown.template = "MSH|^~\\&|{query.PID.x}".to_string();
+        assert!(unknown.validate().is_err());
+    }
+}
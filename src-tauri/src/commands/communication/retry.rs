@@ -0,0 +1,151 @@
+//! Retry policy for MLLP sends.
+//!
+//! Integration engines rarely give up on the first failed attempt: a refused
+//! connection, a missing acknowledgement, or a negative acknowledgement is
+//! usually retried a few times with a growing delay between attempts. A
+//! [`RetryPolicy`] on a send request mimics that, so a receiver's behaviour
+//! under retries can be tested from Hermes.
+//!
+//! Each attempt resends the same bytes, with the same control ID, the way an
+//! engine resends a queued message. Failures that leave the message in an
+//! unknown state, like a connection dropping mid-send, aren't retried.
+
+use serde::Deserialize;
+use std::time::Duration;
+
+/// Longest delay between attempts, however the backoff grows.
+const MAX_BACKOFF: Duration = Duration::from_secs(5 * 60);
+
+/// How an MLLP send is retried when an attempt fails.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RetryPolicy {
+    /// Number of attempts after the first
+    #[serde(default)]
+    pub retries: u32,
+    /// Delay before the first retry, in milliseconds
+    #[serde(rename = "backoffMs", default = "default_backoff_ms")]
+    pub backoff_ms: u64,
+    /// Factor the delay is multiplied by after each retry (1 for a fixed delay)
+    #[serde(rename = "backoffMultiplier", default = "default_backoff_multiplier")]
+    pub backoff_multiplier: f64,
+    /// Retry when no response arrives within the wait timeout
+    #[serde(rename = "onTimeout", default = "enabled")]
+    pub on_timeout: bool,
+    /// Retry when the connection is refused or can't be made
+    #[serde(rename = "onRefused", default = "enabled")]
+    pub on_refused: bool,
+    /// Retry when the response is an AE, AR, CE, or CR acknowledgement
+    #[serde(rename = "onNak", default)]
+    pub on_nak: bool,
+}
+
+fn default_backoff_ms() -> u64 {
+    1000
+}
+
+fn default_backoff_multiplier() -> f64 {
+    1.0
+}
+
+fn enabled() -> bool {
+    true
+}
+
+impl Default for RetryPolicy {
+    /// A single attempt, with no retries.
+    fn default() -> Self {
+        Self {
+            retries: 0,
+            backoff_ms: default_backoff_ms(),
+            backoff_multiplier: default_backoff_multiplier(),
+            on_timeout: enabled(),
+            on_refused: enabled(),
+            on_nak: false,
+        }
+    }
+}
+
+/// Ways an attempt can fail that a [`RetryPolicy`] may retry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) enum RetryableFailure {
+    /// The connection was refused or couldn't be made
+    Refused,
+    /// No response arrived within the wait timeout
+    TimedOut,
+    /// The response was a negative acknowledgement
+    Nak,
+}
+
+impl RetryPolicy {
+    /// Total number of attempts, including the first.
+    pub(super) fn attempts(&self) -> u32 {
+        self.retries.saturating_add(1)
+    }
+
+    /// Whether this policy retries a failure of the given kind.
+    pub(super) fn retries_on(&self, failure: RetryableFailure) -> bool {
+        match failure {
+            RetryableFailure::Refused => self.on_refused,
+            RetryableFailure::TimedOut => self.on_timeout,
+            RetryableFailure::Nak => self.on_nak,
+        }
+    }
+
+    /// Delay before the given retry, counting the first retry as 1.
+    ///
+    /// The delay starts at `backoff_ms` and is multiplied by
+    /// `backoff_multiplier` for each retry after the first, up to five minutes.
+    pub(super) fn delay_before(&self, retry: u32) -> Duration {
+        if self.backoff_ms == 0 {
+            return Duration::ZERO;
+        }
+        let exponent = i32::try_from(retry.saturating_sub(1)).unwrap_or(i32::MAX);
+        let factor = self.backoff_multiplier.max(0.0).powi(exponent);
+        let seconds = self.backoff_ms as f64 / 1000.0 * factor;
+        Duration::try_from_secs_f64(seconds)
+            .unwrap_or(MAX_BACKOFF)
+            .min(MAX_BACKOFF)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    fn policy(backoff_ms: u64, backoff_multiplier: f64) -> RetryPolicy {
+        RetryPolicy {
+            retries: 3,
+            backoff_ms,
+            backoff_multiplier,
+            on_timeout: true,
+            on_refused: true,
+            on_nak: false,
+        }
+    }
+
+    #[test]
+    fn backoff_grows_by_the_multiplier() {
+        let policy = policy(500, 2.0);
+        assert_eq!(policy.delay_before(1), Duration::from_millis(500));
+        assert_eq!(policy.delay_before(2), Duration::from_millis(1000));
+        assert_eq!(policy.delay_before(3), Duration::from_millis(2000));
+    }
+
+    #[test]
+    fn backoff_is_capped() {
+        assert_eq!(policy(1000, 10.0).delay_before(10), MAX_BACKOFF);
+        assert_eq!(policy(1000, f64::INFINITY).delay_before(2), MAX_BACKOFF);
+        assert_eq!(policy(1000, f64::NAN).delay_before(2), Duration::ZERO);
+    }
+
+    #[test]
+    fn missing_fields_get_defaults() {
+        let policy: RetryPolicy = serde_json::from_str(r#"{"retries": 2}"#).unwrap();
+        assert_eq!(policy.attempts(), 3);
+        assert_eq!(policy.delay_before(2), Duration::from_secs(1));
+        assert!(policy.retries_on(RetryableFailure::TimedOut));
+        assert!(policy.retries_on(RetryableFailure::Refused));
+        assert!(!policy.retries_on(RetryableFailure::Nak));
+    }
+}
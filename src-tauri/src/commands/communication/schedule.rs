@@ -0,0 +1,536 @@
+//! Scheduled, repeated sends for load and soak testing.
+//!
+//! A schedule sends the same message to one endpoint over and over from a
+//! background task, at a fixed interval with optional random jitter, until it
+//! has sent a given number of messages or is stopped. It's meant for soak-testing
+//! interface engines: leaving a steady trickle of traffic running for hours, or
+//! pushing a burst through to see how the engine copes.
+//!
+//! # Fresh Control IDs and Timestamps
+//!
+//! Engines commonly reject or de-duplicate repeated control IDs, so by default
+//! MSH.7 and MSH.10 are replaced on every iteration. With `regenerateIds` off,
+//! only the `{auto}`/`{now}`/`{random}` placeholders are replaced (see
+//! [`send_message`](super::send_message)), which still yields fresh values each
+//! time. Other placeholders, such as `{seq}` or `{now-2h}`, are filled in anew
+//! on every iteration either way.
+//!
+//! # Connections
+//!
+//! One connection is kept open and reused for every send. It's dropped and
+//! re-opened after any error or timeout, so a late acknowledgement is never
+//! mistaken for the response to the next message.
+//!
+//! # Timing
+//!
+//! The interval is measured between the starts of consecutive sends, so a slow
+//! acknowledgement doesn't stretch the schedule. If a send takes longer than the
+//! interval, the next one starts as soon as it's done. Jitter adds or removes up
+//! to the given number of milliseconds from each interval.
+//!
+//! # Events
+//!
+//! - `schedule-progress` - Emitted after every send, with a [`ScheduleProgress`]
+//!   payload holding the outcome and running totals
+//! - `schedule-finished` - Emitted once when the schedule ends, with the final
+//!   [`ScheduleStats`] and whether it was stopped early
+
+use bytes::BytesMut;
+use futures::{sink::SinkExt, StreamExt};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+use tokio::{
+    net::TcpStream,
+    time::{sleep_until, timeout, Instant},
+};
+use tokio_util::{codec::Framed, sync::CancellationToken};
+
+use super::encoding::MessageEncoding;
+use super::framing::{Framing, FramingCodec};
+use super::send::{check_prod_confirmed, prepare_message, EndpointLabel};
+use crate::events;
+use crate::AppData;
+
+/// Request parameters for a scheduled send.
+#[derive(Deserialize)]
+pub struct ScheduleRequest {
+    /// Target hostname or IP address
+    pub host: String,
+    /// Target port number
+    pub port: u16,
+    /// The HL7 message to send on every iteration (may contain placeholder values)
+    pub message: String,
+    /// Time between the starts of consecutive sends, in milliseconds
+    #[serde(rename = "intervalMs")]
+    pub interval_ms: u64,
+    /// Maximum random variation of each interval, in milliseconds
+    #[serde(rename = "jitterMs", default)]
+    pub jitter_ms: u64,
+    /// Number of messages to send, or none to send until stopped
+    #[serde(default)]
+    pub count: Option<u32>,
+    /// How long to wait for each acknowledgement before counting it as timed out (in seconds)
+    #[serde(rename = "waitTimeoutSeconds")]
+    pub wait_timeout_seconds: f32,
+    /// Replace MSH.7 and MSH.10 on every send, even if they don't hold placeholders
+    #[serde(rename = "regenerateIds", default = "default_regenerate_ids")]
+    pub regenerate_ids: bool,
+    /// Label of the connection preset being sent to, if any
+    #[serde(default)]
+    pub endpoint: Option<EndpointLabel>,
+    /// MLLP start and end blocks (standard MLLP if not given)
+    #[serde(default)]
+    pub framing: Framing,
+    /// Encoding to send messages in and decode acknowledgements with
+    #[serde(default)]
+    pub encoding: MessageEncoding,
+    /// User-defined placeholder values, by name
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+}
+
+fn default_regenerate_ids() -> bool {
+    true
+}
+
+/// The schedule currently running.
+pub struct ScheduledSend {
+    /// Cancelled to stop the schedule after the current send.
+    cancel: CancellationToken,
+    /// Handle to the sending task.
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl ScheduledSend {
+    /// Stop the schedule, and wait for the send in progress to finish.
+    pub(crate) async fn finish(self) {
+        self.cancel.cancel();
+        if let Err(e) = self.task.await {
+            log::warn!("Scheduled send task ended abnormally: {e}");
+        }
+    }
+}
+
+/// Outcome of a single send in a schedule.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SendOutcome {
+    /// An acknowledgement other than a reject or error was received
+    Accepted,
+    /// The acknowledgement was an application or commit reject or error
+    Rejected,
+    /// No response arrived within the wait timeout
+    TimedOut,
+    /// The message couldn't be sent, or the response couldn't be read
+    Failed,
+}
+
+/// Running totals for a schedule.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ScheduleStats {
+    /// Number of sends attempted
+    pub sent: u32,
+    /// Number of sends that were acknowledged
+    pub accepted: u32,
+    /// Number of sends that were rejected
+    pub rejected: u32,
+    /// Number of sends with no response in time
+    #[serde(rename = "timedOut")]
+    pub timed_out: u32,
+    /// Number of sends that failed outright
+    pub failed: u32,
+    /// Mean time from sending to receiving a response, in milliseconds
+    #[serde(rename = "averageLatencyMs")]
+    pub average_latency_ms: Option<f64>,
+    /// Longest time from sending to receiving a response, in milliseconds
+    #[serde(rename = "maxLatencyMs")]
+    pub max_latency_ms: Option<f64>,
+    /// Number of sends that received a response, for averaging latency
+    #[serde(skip)]
+    responses: u32,
+}
+
+impl ScheduleStats {
+    /// Record the outcome of one send.
+    fn record(&mut self, outcome: SendOutcome, latency: Option<Duration>) {
+        self.sent += 1;
+        match outcome {
+            SendOutcome::Accepted => self.accepted += 1,
+            SendOutcome::Rejected => self.rejected += 1,
+            SendOutcome::TimedOut => self.timed_out += 1,
+            SendOutcome::Failed => self.failed += 1,
+        }
+
+        if let Some(latency) = latency {
+            let latency = latency.as_secs_f64() * 1000.0;
+            let total = self.average_latency_ms.unwrap_or_default() * f64::from(self.responses);
+            self.responses += 1;
+            self.average_latency_ms = Some((total + latency) / f64::from(self.responses));
+            self.max_latency_ms = Some(self.max_latency_ms.unwrap_or_default().max(latency));
+        }
+    }
+}
+
+/// Payload of the `schedule-progress` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduleProgress {
+    /// Number of this send (1-based)
+    pub iteration: u32,
+    /// Number of sends the schedule will make, if limited
+    pub total: Option<u32>,
+    /// MSH.10 of the message sent
+    #[serde(rename = "controlId")]
+    pub control_id: Option<String>,
+    /// What happened to the message
+    pub outcome: SendOutcome,
+    /// MSA.1 of the acknowledgement, if one was received
+    #[serde(rename = "ackCode")]
+    pub ack_code: Option<String>,
+    /// Error details, for failed sends
+    pub error: Option<String>,
+    /// Running totals, including this send
+    pub stats: ScheduleStats,
+}
+
+/// Payload of the `schedule-finished` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduleFinished {
+    /// Final totals
+    pub stats: ScheduleStats,
+    /// Whether the schedule was stopped before sending every message
+    pub cancelled: bool,
+}
+
+/// Result of a single send.
+struct Attempt {
+    outcome: SendOutcome,
+    ack_code: Option<String>,
+    error: Option<String>,
+    latency: Option<Duration>,
+}
+
+impl Attempt {
+    fn failed(error: String) -> Self {
+        Attempt {
+            outcome: SendOutcome::Failed,
+            ack_code: None,
+            error: Some(error),
+            latency: None,
+        }
+    }
+}
+
+/// Start sending a message repeatedly.
+///
+/// Validates the request, then spawns a background task that sends the message
+/// on the requested schedule. Any schedule already running is stopped first.
+/// Progress is reported through `schedule-progress` and `schedule-finished`
+/// events.
+///
+/// # Arguments
+/// * `request` - Target, message, interval, count, and jitter
+/// * `confirmed_prod` - Whether the user confirmed sending to a production
+///   endpoint, which is refused otherwise
+/// * `app` - Tauri app handle for emitting events to the frontend
+/// * `state` - Application state holding the running schedule
+///
+/// # Returns
+/// * `Ok(())` - The schedule started (does not indicate that any send succeeded)
+/// * `Err(String)` - If the address can't be resolved, the message can't be parsed, the interval or framing is invalid, or a production send wasn't confirmed
+#[tauri::command]
+pub async fn start_scheduled_send(
+    request: ScheduleRequest,
+    confirmed_prod: bool,
+    app: AppHandle,
+    state: State<'_, AppData>,
+) -> Result<(), String> {
+    check_prod_confirmed(request.endpoint.as_ref(), confirmed_prod)?;
+    request.framing.validate()?;
+    if request.interval_ms == 0 && request.count.is_none() {
+        return Err("An interval is required when sending until stopped".to_string());
+    }
+
+    let addr = format!("{}:{}", request.host, request.port)
+        .to_socket_addrs()
+        .map_err(|_| {
+            format!(
+                "Failed to resolve address for {}:{}",
+                request.host, request.port
+            )
+        })?
+        .next()
+        .ok_or_else(|| format!("No host found in `{}:{}`", request.host, request.port))?;
+
+    // fail up front rather than on every iteration
+    let (message, _) =
+        prepare_message(&request.message, request.regenerate_ids, &request.variables)?;
+    request
+        .encoding
+        .encode(&message)
+        .map_err(|e| format!("Failed to encode message: {e}"))?;
+
+    let mut schedule = state.schedule.lock().await;
+    if let Some(running) = schedule.take() {
+        running.cancel.cancel();
+        running.task.abort();
+    }
+
+    let cancel = CancellationToken::new();
+    let task = tokio::spawn(run_schedule(request, addr, app, cancel.clone()));
+    *schedule = Some(ScheduledSend { cancel, task });
+
+    Ok(())
+}
+
+/// Stop the running schedule, if any.
+///
+/// The send in progress is abandoned, and `schedule-finished` is emitted with the
+/// totals so far.
+///
+/// # Arguments
+/// * `state` - Application state holding the running schedule
+///
+/// # Returns
+/// * `Ok(())` - Always succeeds, even if no schedule was running
+#[tauri::command]
+pub async fn stop_scheduled_send(state: State<'_, AppData>) -> Result<(), String> {
+    if let Some(running) = state.schedule.lock().await.take() {
+        running.cancel.cancel();
+    }
+    Ok(())
+}
+
+/// Send messages until the count is reached or the schedule is cancelled.
+async fn run_schedule(
+    request: ScheduleRequest,
+    addr: SocketAddr,
+    app: AppHandle,
+    cancel: CancellationToken,
+) {
+    let target = match &request.endpoint {
+        Some(endpoint) => format!("{endpoint} ({addr})"),
+        None => addr.to_string(),
+    };
+    log::info!(
+        "Starting scheduled send to {target}: every {}ms (±{}ms), {}",
+        request.interval_ms,
+        request.jitter_ms,
+        request
+            .count
+            .map_or("until stopped".to_string(), |count| format!(
+                "{count} messages"
+            ))
+    );
+
+    let wait_timeout = Duration::from_secs_f32(request.wait_timeout_seconds);
+    let mut connection = None;
+    let mut stats = ScheduleStats::default();
+    let mut iteration = 0;
+    let mut cancelled = false;
+
+    while request.count.is_none_or(|count| iteration < count) {
+        iteration += 1;
+        let started = Instant::now();
+
+        let (attempt, control_id) = match prepare_message(
+            &request.message,
+            request.regenerate_ids,
+            &request.variables,
+        ) {
+            Ok((message, control_id)) => {
+                let attempt = tokio::select! {
+                    () = cancel.cancelled() => {
+                        cancelled = true;
+                        break;
+                    }
+                    attempt = send_once(&mut connection, addr, &request, &message, wait_timeout) => attempt,
+                };
+                (attempt, control_id)
+            }
+            Err(e) => (Attempt::failed(e), None),
+        };
+
+        stats.record(attempt.outcome, attempt.latency);
+        if let Some(error) = &attempt.error {
+            log::warn!("Scheduled send {iteration} to {target} failed: {error}");
+        }
+        if let Err(e) = app.emit(
+            events::SCHEDULE_PROGRESS,
+            ScheduleProgress {
+                iteration,
+                total: request.count,
+                control_id,
+                outcome: attempt.outcome,
+                ack_code: attempt.ack_code,
+                error: attempt.error,
+                stats: stats.clone(),
+            },
+        ) {
+            log::error!("Failed to emit schedule-progress event: {e:#}");
+        }
+
+        if request.count.is_some_and(|count| iteration >= count) {
+            break;
+        }
+
+        let next = started + next_interval(request.interval_ms, request.jitter_ms);
+        tokio::select! {
+            () = cancel.cancelled() => {
+                cancelled = true;
+                break;
+            }
+            () = sleep_until(next) => {}
+        }
+    }
+
+    log::info!(
+        "Scheduled send to {target} {}: {} sent, {} accepted, {} rejected, {} timed out, {} failed",
+        if cancelled { "stopped" } else { "finished" },
+        stats.sent,
+        stats.accepted,
+        stats.rejected,
+        stats.timed_out,
+        stats.failed
+    );
+    if let Err(e) = app.emit(
+        events::SCHEDULE_FINISHED,
+        ScheduleFinished { stats, cancelled },
+    ) {
+        log::error!("Failed to emit schedule-finished event: {e:#}");
+    }
+}
+
+/// Send one message and wait for its acknowledgement, (re)connecting if needed.
+///
+/// The connection is dropped after anything other than a clean response, so the
+/// next send starts on a fresh connection.
+async fn send_once(
+    connection: &mut Option<Framed<TcpStream, FramingCodec>>,
+    addr: SocketAddr,
+    request: &ScheduleRequest,
+    message: &str,
+    wait_timeout: Duration,
+) -> Attempt {
+    let encoded = match request.encoding.encode(message) {
+        Ok(encoded) => encoded,
+        Err(e) => return Attempt::failed(format!("Failed to encode message: {e}")),
+    };
+
+    let mut transport = match connection.take() {
+        Some(transport) => transport,
+        None => match TcpStream::connect(addr).await {
+            Ok(stream) => Framed::new(stream, FramingCodec::new(request.framing.clone())),
+            Err(e) => return Attempt::failed(format!("Failed to connect to {addr}: {e}")),
+        },
+    };
+
+    let sent_at = Instant::now();
+    if let Err(e) = transport.send(BytesMut::from(encoded.as_slice())).await {
+        return Attempt::failed(format!("Failed to send message: {e:#}"));
+    }
+
+    let response = match timeout(wait_timeout, transport.next()).await {
+        Err(_) => {
+            return Attempt {
+                outcome: SendOutcome::TimedOut,
+                ack_code: None,
+                error: None,
+                latency: None,
+            }
+        }
+        Ok(None) => return Attempt::failed("Connection closed by remote".to_string()),
+        Ok(Some(Err(e))) => return Attempt::failed(format!("Failed to receive response: {e:#}")),
+        Ok(Some(Ok(response))) => response,
+    };
+    let latency = sent_at.elapsed();
+
+    let response = match request.encoding.decode(&response) {
+        Ok(response) => response,
+        Err(e) => return Attempt::failed(format!("Failed to decode response: {e}")),
+    };
+
+    *connection = Some(transport);
+    let ack_code = ack_code(&response);
+    Attempt {
+        outcome: classify(ack_code.as_deref()),
+        ack_code,
+        error: None,
+        latency: Some(latency),
+    }
+}
+
+/// Read MSA.1 from a response.
+pub(super) fn ack_code(response: &str) -> Option<String> {
+    let response = hl7_parser::parse_message_with_lenient_newlines(response).ok()?;
+    response
+        .query("MSA.1")
+        .map(|value| response.separators.decode(value.raw_value()).to_string())
+        .filter(|code| !code.is_empty())
+}
+
+/// Classify a response by its acknowledgement code.
+///
+/// Responses without an MSA segment count as accepted, since the remote did
+/// answer and didn't reject the message.
+pub(super) fn classify(ack_code: Option<&str>) -> SendOutcome {
+    match ack_code {
+        Some("AE" | "AR" | "CE" | "CR") => SendOutcome::Rejected,
+        Some(_) | None => SendOutcome::Accepted,
+    }
+}
+
+/// Pick the delay before the next send, varying the interval by up to `jitter_ms` either way.
+fn next_interval(interval_ms: u64, jitter_ms: u64) -> Duration {
+    if jitter_ms == 0 {
+        return Duration::from_millis(interval_ms);
+    }
+    let low = interval_ms.saturating_sub(jitter_ms);
+    let high = interval_ms.saturating_add(jitter_ms);
+    Duration::from_millis(rand::rng().random_range(low..=high))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stats_track_outcomes_and_latency() {
+        let mut stats = ScheduleStats::default();
+        stats.record(SendOutcome::Accepted, Some(Duration::from_millis(10)));
+        stats.record(SendOutcome::Rejected, Some(Duration::from_millis(30)));
+        stats.record(SendOutcome::TimedOut, None);
+        stats.record(SendOutcome::Failed, None);
+
+        assert_eq!(stats.sent, 4);
+        assert_eq!(stats.accepted, 1);
+        assert_eq!(stats.rejected, 1);
+        assert_eq!(stats.timed_out, 1);
+        assert_eq!(stats.failed, 1);
+        assert_eq!(stats.average_latency_ms, Some(20.0));
+        assert_eq!(stats.max_latency_ms, Some(30.0));
+    }
+
+    #[test]
+    fn acks_are_classified_by_msa1() {
+        assert_eq!(
+            ack_code("MSH|^~\\&|A|B|C|D|20250101||ACK|1|P|2.5.1\rMSA|AR|1").as_deref(),
+            Some("AR")
+        );
+        assert_eq!(classify(Some("AR")), SendOutcome::Rejected);
+        assert_eq!(classify(Some("CA")), SendOutcome::Accepted);
+        assert_eq!(classify(None), SendOutcome::Accepted);
+    }
+
+    #[test]
+    fn jitter_stays_within_bounds() {
+        assert_eq!(next_interval(500, 0), Duration::from_millis(500));
+        for _ in 0..100 {
+            let interval = next_interval(100, 150);
+            assert!(interval <= Duration::from_millis(250));
+        }
+    }
+}
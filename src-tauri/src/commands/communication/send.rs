@@ -11,7 +11,9 @@
 //! * End Block (EB): 0x1C (file separator)
 //! * Carriage Return (CR): 0x0D
 //!
-//! The `hl7_mllp_codec` crate handles the framing automatically.
+//! The start and end blocks can be changed for systems that use non-standard
+//! framing (see [`Framing`]), and messages can be sent in encodings other than
+//! UTF-8 (see [`MessageEncoding`]). Responses are decoded with the same encoding.
 //!
 //! # Event-Driven Architecture
 //! The send operation emits two types of events to the frontend:
@@ -20,20 +22,86 @@
 //!
 //! This allows the frontend to show real-time feedback while the async operation
 //! executes in a background task.
+//!
+//! # Endpoint Labels
+//! Sends made through a saved connection preset carry the preset's name,
+//! environment, and tag. The label is included in the log lines, in the `sent`
+//! confirmation event, and in the application log, so it's always clear after
+//! the fact which environment a message went to.
+//!
+//! # Extension Transports
+//! A request naming an [`ExtensionTransport`] skips MLLP entirely: the prepared
+//! message is handed to the extension providing that transport (see
+//! [`super::transport`]), and its result is reported with the same events.
+//!
+//! # HTTP
+//! A request with HTTP settings is POSTed to the host and port over HTTP(S)
+//! instead of MLLP (see [`super::http`]), and its result reported with the same
+//! events.
+//!
+//! # File Drops
+//! A request with file-drop settings writes the message into a local or SFTP
+//! directory instead (see [`super::file_drop`]), and reports the result with
+//! the same events.
+//!
+//! # Connection Profiles
+//! A request naming a connection profile takes its host, port, framing, and
+//! encoding from the profile, and wraps the MLLP connection in TLS if the
+//! profile says to (see [`super::connection_profiles`]).
+//!
+//! # Persistent Connections
+//! A request naming a connection opened with [`super::open_connection`] is sent
+//! over that connection, which stays open afterwards, instead of over a new one
+//! (see [`super::pool`]).
+//!
+//! # Retries
+//! An MLLP send can be retried when an attempt is refused, times out, or is
+//! negatively acknowledged, with a growing delay between attempts (see
+//! [`super::retry`]). Each failed attempt is reported with its own event.
+//!
+//! # Auditing
+//! Each send, failure, acknowledgement, and timeout is also recorded as an
+//! audit event, exported if an audit exporter is configured (see [`super::audit`]).
+//!
+//! # Extension Hooks
+//! Before a message is sent, extensions subscribed to `message/beforeSend` can
+//! change it or cancel the send, and those subscribed to `message/ackReceived`
+//! are told of each response to an MLLP send (see [`super::hooks`]).
 
 use bytes::BytesMut;
-use core::str;
 use futures::{sink::SinkExt, StreamExt};
-use hl7_mllp_codec::MllpCodec;
 use hl7_parser::{builder::MessageBuilder, datetime::TimeStamp};
 use jiff::Zoned;
 use rand::distr::{Alphanumeric, SampleString};
 use serde::{Deserialize, Serialize};
-use std::net::ToSocketAddrs;
-use tauri::{AppHandle, Emitter};
-use tokio::{net::TcpStream, time::timeout};
+use std::{
+    collections::HashMap,
+    fmt,
+    net::{SocketAddr, ToSocketAddrs},
+    time::Duration,
+};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+    time::timeout,
+};
 use tokio_util::codec::Framed;
 
+use super::audit::{message_type_of, record_audit_event, AuditEvent, AuditEventKind};
+use super::connection_profiles::{connect_tls, find_profile, TlsSettings};
+use super::encoding::MessageEncoding;
+use super::file_drop::{check_ssh_name, send_over_file_drop, FileDropSend, FileDropTransport};
+use super::framing::{Framing, FramingCodec};
+use super::hooks::{notify_ack_received, run_before_send_hooks};
+use super::http::{send_over_http, HttpSend, HttpTransport};
+use super::retry::{RetryPolicy, RetryableFailure};
+use super::schedule::{ack_code, classify, SendOutcome};
+use super::transport::{send_over_extension, ExtensionTransport, TransportSend};
+use crate::commands::{evaluate_formulas, expand_placeholders, strip_document_metadata};
+use crate::events;
+use crate::AppData;
+
 /// Request parameters for sending an HL7 message.
 ///
 /// Passed from the frontend to the `send_message` command.
@@ -47,6 +115,133 @@ pub struct SendRequest {
     pub wait_timeout_seconds: f32,
     /// The HL7 message to send (may contain placeholder values)
     pub message: String,
+    /// Label of the connection preset being sent to, if any
+    #[serde(default)]
+    pub endpoint: Option<EndpointLabel>,
+    /// MLLP start and end blocks (standard MLLP if not given)
+    #[serde(default)]
+    pub framing: Framing,
+    /// Encoding to send the message in and decode the response with
+    #[serde(default)]
+    pub encoding: MessageEncoding,
+    /// Extension transport to send over instead of MLLP, if any
+    #[serde(default)]
+    pub transport: Option<ExtensionTransport>,
+    /// User-defined placeholder values, by name
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+    /// HTTP settings, to POST the message instead of sending over MLLP
+    #[serde(default)]
+    pub http: Option<HttpTransport>,
+    /// Name of an open connection to send over instead of connecting, if any
+    #[serde(default)]
+    pub connection: Option<String>,
+    /// File-drop settings, to write the message to a directory instead of
+    /// sending over MLLP
+    #[serde(default)]
+    pub file_drop: Option<FileDropTransport>,
+    /// How to retry an MLLP send whose attempt is refused, times out, or is
+    /// negatively acknowledged (a single attempt if not given)
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+    /// Name of a connection profile supplying the host, port, TLS settings,
+    /// framing, and encoding in place of the request's, if any
+    #[serde(default)]
+    pub profile: Option<String>,
+}
+
+/// Environment classification of a connection preset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Environment {
+    Dev,
+    Test,
+    Prod,
+}
+
+impl fmt::Display for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Environment::Dev => write!(f, "dev"),
+            Environment::Test => write!(f, "test"),
+            Environment::Prod => write!(f, "prod"),
+        }
+    }
+}
+
+/// Identifies the connection preset a message is being sent to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EndpointLabel {
+    /// Preset name
+    pub name: String,
+    /// Environment the endpoint belongs to
+    #[serde(default)]
+    pub environment: Option<Environment>,
+    /// Freeform tag shown alongside the name
+    #[serde(default)]
+    pub tag: Option<String>,
+}
+
+impl fmt::Display for EndpointLabel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        let details = self
+            .environment
+            .map(|environment| environment.to_string())
+            .into_iter()
+            .chain(self.tag.iter().filter(|tag| !tag.is_empty()).cloned())
+            .collect::<Vec<_>>();
+        if !details.is_empty() {
+            write!(f, " [{}]", details.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Check that the user confirmed a send to a production endpoint.
+///
+/// # Returns
+/// * `Ok(())` - The endpoint isn't production, or the send was confirmed
+/// * `Err(String)` - The endpoint is production and the send wasn't confirmed
+pub(super) fn check_prod_confirmed(
+    endpoint: Option<&EndpointLabel>,
+    confirmed_prod: bool,
+) -> Result<(), String> {
+    match endpoint {
+        Some(endpoint) if endpoint.environment == Some(Environment::Prod) && !confirmed_prod => {
+            Err(format!(
+                "{endpoint} is a production endpoint; sends to it must be confirmed"
+            ))
+        }
+        Some(_) | None => Ok(()),
+    }
+}
+
+/// Confirmation that a message was handed off to the remote server.
+#[derive(Serialize, Clone)]
+pub struct SendConfirmation {
+    /// Resolved address the message was sent to
+    pub addr: String,
+    /// MSH.10 of the sent message, after placeholder transformations
+    #[serde(rename = "controlId")]
+    pub control_id: Option<String>,
+    /// Label of the connection preset, if the send used one
+    pub endpoint: Option<EndpointLabel>,
+}
+
+/// An attempt at an MLLP send that was refused, timed out, or negatively
+/// acknowledged.
+#[derive(Serialize, Clone)]
+pub struct AttemptFailure {
+    /// Which attempt this was, counting from 1
+    pub attempt: u32,
+    /// Total number of attempts the retry policy allows
+    pub attempts: u32,
+    /// Delay before the next attempt, if there is one
+    #[serde(rename = "retryInMs")]
+    pub retry_in_ms: Option<u64>,
+    /// The acknowledgement code, for a negative acknowledgement
+    pub detail: Option<String>,
 }
 
 /// Response events emitted during the send operation.
@@ -54,6 +249,10 @@ pub struct SendRequest {
 /// These variants are serialized to camelCase JSON and emitted to the frontend
 /// via the `send-response` event channel. The `tag` field becomes "event" and
 /// the `content` field becomes "data" in the serialized output.
+///
+/// `refused`, `timedOut`, and `nak` report the end of a single attempt of an
+/// MLLP send. When no retry follows, they're followed by `failedToConnect`, or
+/// by `final` with no response or the negative acknowledgement.
 #[derive(Serialize, Clone)]
 #[serde(rename_all = "camelCase", tag = "event", content = "data")]
 pub enum SendResponse {
@@ -63,7 +262,7 @@ pub enum SendResponse {
     FailedToSend(String),
     /// Failed to receive a response (network error, not timeout)
     FailedToReceive(String),
-    /// Received response but failed to decode as UTF-8
+    /// Received response but failed to decode with the request's encoding
     FailedToDecode(String),
     /// Received response but failed to parse as valid HL7
     FailedToParse {
@@ -72,6 +271,14 @@ pub enum SendResponse {
         /// Parse error details
         error: String,
     },
+    /// Message was sent and the client is now waiting for a response
+    Sent(SendConfirmation),
+    /// An attempt couldn't connect
+    Refused(AttemptFailure),
+    /// An attempt got no response within the wait timeout
+    TimedOut(AttemptFailure),
+    /// An attempt's response was an AE, AR, CE, or CR acknowledgement
+    Nak(AttemptFailure),
     /// Final response (success case, or timeout with None)
     Final(Option<String>),
 }
@@ -94,6 +301,17 @@ pub enum SendResponse {
 /// These placeholders allow users to compose message templates without worrying
 /// about generating unique control IDs or current timestamps.
 ///
+/// Placeholders anywhere in the message, such as `{now-2h}`, `{seq}`, or the
+/// request's variables, are filled in next (see [`expand_placeholders`]). Then
+/// any `{=...}` formulas are evaluated (see [`evaluate_formulas`]), so formulas
+/// can refer to the new timestamp and control ID.
+///
+/// # Extension Hooks
+/// The prepared message is then passed through the extensions subscribed to
+/// `message/beforeSend`, which can change it or cancel the send, whatever it's
+/// sent over. Responses to MLLP sends are passed on to extensions subscribed to
+/// `message/ackReceived`.
+///
 /// # Event Flow
 /// 1. Validate and resolve the target address
 /// 2. Parse the message and apply placeholder transformations
@@ -107,200 +325,658 @@ pub enum SendResponse {
 ///    - Decode and parse response
 ///    - Emit "send-response" with final result
 ///
+/// # Framing and Encoding
+/// The message is framed with the request's start and end blocks and encoded with
+/// its encoding before the background task is spawned, so a message containing
+/// characters the encoding can't represent is rejected up front.
+///
+/// # Extension Transports
+/// If the request names a transport provided by an extension, the host, port,
+/// and transport options are passed to that extension in a `transport/send`
+/// request along with the prepared message. Address resolution, framing, and
+/// encoding are left to the extension.
+///
+/// # HTTP
+/// If the request has HTTP settings, the prepared message is encoded and POSTed
+/// to the host and port instead. Framing and persistent connections don't
+/// apply.
+///
+/// # File Drops
+/// If the request has file-drop settings, the prepared message is encoded and
+/// written into a directory instead, over SFTP to the host and port if the
+/// settings have a login. There's no response to wait for, so the timeout
+/// only limits an SFTP upload.
+///
+/// # Production Endpoints
+/// A send to a preset in the `prod` environment is refused unless
+/// `confirmed_prod` says the user confirmed it, so a caller that skips the
+/// frontend's confirmation can't send to production by accident.
+///
+/// # Connection Profiles
+/// If the request names a connection profile, the profile's host, port,
+/// framing, and encoding are used instead of the request's, and a profile with
+/// TLS settings has its MLLP connections wrapped in TLS. A TLS profile can't be
+/// combined with an open connection.
+///
+/// # Persistent Connections
+/// If the request names an open connection, the message is sent over it and
+/// the connection is left open; the host, port, and framing come from the
+/// connection rather than the request. A connection that fails to send or
+/// receive, or that the remote closed, is removed from the pool. A response
+/// arriving after its send timed out is read by the next send over the
+/// connection.
+///
 /// # Timeout Behavior
 /// If no response is received within `wait_timeout_seconds`, a timeout log is emitted
 /// and a Final(None) response is sent. This is not considered a fatal error, as some
 /// HL7 systems may not send acknowledgments for certain message types.
 ///
+/// # Retries
+/// If the request has a retry policy, an MLLP attempt that's refused, times
+/// out, or gets an AE, AR, CE, or CR acknowledgement is retried as the policy
+/// allows, after the policy's backoff. Each such attempt emits a `refused`,
+/// `timedOut`, or `nak` event saying whether another attempt follows; the
+/// result of the last attempt is reported as it would be without retries. The
+/// same bytes are resent each time, so the control ID doesn't change. Direct
+/// sends reconnect for each attempt, and pooled sends reuse the connection.
+/// Failures to write the message or read the response aren't retried, since
+/// the remote may have received the message.
+///
 /// # Arguments
 /// * `request` - Send parameters including host, port, timeout, and message
+/// * `confirmed_prod` - Whether the user confirmed sending to a production endpoint
 /// * `app` - Tauri app handle for emitting events to the frontend
 ///
 /// # Returns
 /// * `Ok(())` - Background task spawned successfully (does not indicate send success)
-/// * `Err(String)` - Failed to resolve address or parse message (before spawning task)
+/// * `Err(String)` - Failed to resolve address, parse or encode the message, the framing is invalid, the named connection or profile doesn't exist, a production send wasn't confirmed, or an extension cancelled the send (before spawning task)
 #[tauri::command]
-pub async fn send_message(request: SendRequest, app: AppHandle) -> Result<(), String> {
+pub async fn send_message(
+    request: SendRequest,
+    confirmed_prod: bool,
+    app: AppHandle,
+) -> Result<(), String> {
     let SendRequest {
         host,
         port,
         wait_timeout_seconds,
         message,
+        endpoint,
+        framing,
+        encoding,
+        transport,
+        variables,
+        http,
+        connection,
+        file_drop,
+        retry,
+        profile,
     } = request;
+    check_prod_confirmed(endpoint.as_ref(), confirmed_prod)?;
 
-    let addr = format!("{host}:{port}")
-        .to_socket_addrs()
-        .map_err(|_| format!("Failed to resolve address for {}:{}", host, port))?
-        .next()
-        .ok_or_else(|| format!("No host found in `{host}:{port}`"))?;
+    let (host, port, framing, encoding, tls) = match profile {
+        Some(name) => {
+            let profile = find_profile(&app, &name).await?;
+            (
+                profile.host,
+                profile.port,
+                profile.framing,
+                profile.encoding,
+                profile.tls,
+            )
+        }
+        None => (host, port, framing, encoding, None),
+    };
+    if tls.is_some() && connection.is_some() {
+        return Err("TLS connections can't be kept open".to_string());
+    }
+    if file_drop.as_ref().is_some_and(|f| f.sftp.is_some()) {
+        check_ssh_name(&host, "host")?;
+    }
 
-    let message = hl7_parser::parse_message_with_lenient_newlines(&message)
-        .map_err(|e| format!("Failed to parse message: {e:#}"))?;
+    let (message, control_id) = prepare_message(&message, false, &variables)?;
+    let (message, control_id) =
+        run_before_send_hooks(&app, message, control_id, endpoint.as_ref()).await?;
+    let message_type = hl7_parser::parse_message_with_lenient_newlines(&message)
+        .ok()
+        .and_then(|parsed| message_type_of(&parsed));
 
-    let mut message: MessageBuilder = (&message).into();
+    if let Some(transport) = transport {
+        tokio::spawn(send_over_extension(
+            app,
+            TransportSend {
+                transport,
+                host,
+                port,
+                message,
+                control_id,
+                message_type,
+                endpoint,
+                wait_timeout: std::time::Duration::from_secs_f32(wait_timeout_seconds),
+            },
+        ));
+        return Ok(());
+    }
 
-    // Placeholder transformations for auto-generated values
-    // TODO: more general {auto} transformations
-    let msh = message
-        .segment_named_mut("MSH")
-        .expect("messages have MSH segments");
+    if let Some(file_drop) = file_drop {
+        let body = encoding
+            .encode(&message)
+            .map_err(|e| format!("Failed to encode message: {e}"))?;
+        tokio::spawn(send_over_file_drop(
+            app,
+            FileDropSend {
+                file_drop,
+                host,
+                port,
+                message,
+                body,
+                control_id,
+                message_type,
+                endpoint,
+                wait_timeout: std::time::Duration::from_secs_f32(wait_timeout_seconds),
+            },
+        ));
+        return Ok(());
+    }
 
-    // Transform {auto} or {now} in MSH.7 to current timestamp
-    if let Some(timestamp) = msh.field_mut(7) {
-        if let Some(value) = timestamp.value_mut() {
-            if value == "{auto}" || value == "{now}" {
-                let now = jiff::Zoned::now();
-                let now: jiff::civil::DateTime = now.into();
-                let now: TimeStamp = now.into();
-                *value = now.to_string();
-            }
-        }
+    if let Some(http) = http {
+        let body = encoding
+            .encode(&message)
+            .map_err(|e| format!("Failed to encode message: {e}"))?;
+        tokio::spawn(send_over_http(
+            app,
+            HttpSend {
+                http,
+                host,
+                port,
+                message,
+                body,
+                encoding,
+                control_id,
+                message_type,
+                endpoint,
+                wait_timeout: std::time::Duration::from_secs_f32(wait_timeout_seconds),
+            },
+        ));
+        return Ok(());
     }
 
-    // Transform {auto} or {random} in MSH.10 to random control ID
-    if let Some(control_id) = msh.field_mut(10) {
-        if let Some(value) = control_id.value_mut() {
-            if value == "{auto}" || value == "{random}" {
-                *value = Alphanumeric.sample_string(&mut rand::rng(), 20);
-            }
+    let addr = match &connection {
+        Some(name) => app
+            .state::<AppData>()
+            .connections
+            .addr_of(name)
+            .await
+            .ok_or_else(|| format!("No open connection named `{name}`"))?,
+        None => {
+            framing.validate()?;
+            format!("{host}:{port}")
+                .to_socket_addrs()
+                .map_err(|_| format!("Failed to resolve address for {}:{}", host, port))?
+                .next()
+                .ok_or_else(|| format!("No host found in `{host}:{port}`"))?
         }
-    }
+    };
 
-    let message = message.to_string();
+    let encoded = encoding
+        .encode(&message)
+        .map_err(|e| format!("Failed to encode message: {e}"))?;
     let wait_timeout = std::time::Duration::from_secs_f32(wait_timeout_seconds);
 
-    if let Err(e) = app.emit(
-        "send-log",
+    let target = match &endpoint {
+        Some(endpoint) => format!("{endpoint} ({addr})"),
+        None => addr.to_string(),
+    };
+    let target = match &connection {
+        Some(name) => format!("{target} over connection `{name}`"),
+        None => target,
+    };
+
+    emit_log(
+        &app,
         format!(
-            "[{now}] Sending message to {addr}:\n{message}",
+            "[{now}] Sending message to {target}:\n{message}",
             now = Zoned::now()
         ),
-    ) {
-        log::error!("Failed to emit send-response event: {e:#}");
-    }
+    );
+
+    let send = MllpSend {
+        audit: AuditEvent {
+            control_id: control_id.clone(),
+            message_type,
+            endpoint: endpoint.as_ref().map(ToString::to_string),
+            environment: endpoint.as_ref().and_then(|e| e.environment),
+            ..AuditEvent::new(AuditEventKind::MessageSent, addr)
+        },
+        addr,
+        host,
+        tls,
+        target,
+        connection,
+        framing,
+        encoded,
+        control_id,
+        endpoint,
+        wait_timeout,
+    };
+    let retry = retry.unwrap_or_default();
 
     tokio::spawn(async move {
-        let Ok(stream) = TcpStream::connect(addr).await else {
-            log::error!("Failed to connect to {addr}");
-            if let Err(e) = app.emit(
-                "send-response",
-                SendResponse::FailedToConnect(format!("{addr}")),
-            ) {
-                log::error!("Failed to emit send-response event: {e:#}");
-            }
-            return;
-        };
+        let attempts = retry.attempts();
+        let mut attempt = 1;
+        let response = loop {
+            let (failure, detail, response) = match attempt_send(&app, &send).await {
+                Attempt::Refused => {
+                    log::error!("Failed to connect to {addr}");
+                    record_audit_event(
+                        &app,
+                        send.audit_event(
+                            AuditEventKind::SendFailed,
+                            Some("Failed to connect".to_string()),
+                        ),
+                    )
+                    .await;
+                    (RetryableFailure::Refused, None, None)
+                }
+                Attempt::SendFailed(e) => {
+                    record_audit_event(
+                        &app,
+                        send.audit_event(AuditEventKind::SendFailed, Some(e.clone())),
+                    )
+                    .await;
+                    emit_response(&app, SendResponse::FailedToSend(e));
+                    return;
+                }
+                Attempt::TimedOut => {
+                    log::warn!("Timeout waiting for response");
+                    record_audit_event(&app, send.audit_event(AuditEventKind::AckTimeout, None))
+                        .await;
+                    emit_log(
+                        &app,
+                        format!(
+                            "[{now}] Timeout waiting for response after {wait_timeout:?}",
+                            now = Zoned::now()
+                        ),
+                    );
+                    (RetryableFailure::TimedOut, None, None)
+                }
+                Attempt::ReceiveFailed(e) => {
+                    emit_response(&app, SendResponse::FailedToReceive(e));
+                    return;
+                }
+                Attempt::Responded(response) => {
+                    emit_log(
+                        &app,
+                        format!(
+                            "[{now}] Received response from {addr}: {count} bytes. Parsing...",
+                            count = response.len(),
+                            now = Zoned::now()
+                        ),
+                    );
 
-        let mut transport = Framed::new(stream, MllpCodec::new());
+                    let response = match encoding.decode(&response) {
+                        Ok(response) => response,
+                        Err(e) => {
+                            log::error!("Failed to decode response: {e:#}");
+                            emit_response(&app, SendResponse::FailedToDecode(format!("{e:#}")));
+                            return;
+                        }
+                    };
 
-        if let Err(e) = transport.send(BytesMut::from(message.as_bytes())).await {
-            log::error!("Failed to send message: {e:#}");
-            if let Err(ee) = app.emit(
-                "send-response",
-                SendResponse::FailedToSend(format!("{e:#}")),
-            ) {
-                log::error!("Failed to emit send-response event: {ee:#}");
-            }
-            return;
-        }
+                    emit_log(
+                        &app,
+                        format!("[{now}] Response:\n{response}", now = Zoned::now()),
+                    );
+                    notify_ack_received(
+                        &app,
+                        response.clone(),
+                        send.control_id.clone(),
+                        send.target.clone(),
+                    );
+                    let code = ack_code(&response);
+                    record_audit_event(
+                        &app,
+                        send.audit_event(AuditEventKind::AckReceived, code.clone()),
+                    )
+                    .await;
 
-        if let Err(e) = app.emit(
-            "send-log",
-            format!(
-                "[{now}] Message sent to {addr}, awaiting response...",
-                now = Zoned::now()
-            ),
-        ) {
-            log::error!("Failed to emit send-response event: {e:#}");
-        }
+                    if classify(code.as_deref()) != SendOutcome::Rejected {
+                        break Some(response);
+                    }
+                    (RetryableFailure::Nak, code, Some(response))
+                }
+            };
+
+            let retry_in = (attempt < attempts && retry.retries_on(failure))
+                .then(|| retry.delay_before(attempt));
+            let report = AttemptFailure {
+                attempt,
+                attempts,
+                retry_in_ms: retry_in
+                    .map(|delay| u64::try_from(delay.as_millis()).unwrap_or(u64::MAX)),
+                detail,
+            };
+            emit_response(
+                &app,
+                match failure {
+                    RetryableFailure::Refused => SendResponse::Refused(report),
+                    RetryableFailure::TimedOut => SendResponse::TimedOut(report),
+                    RetryableFailure::Nak => SendResponse::Nak(report),
+                },
+            );
 
-        let Some(response) = timeout(wait_timeout, transport.next()).await.ok().flatten() else {
-            log::warn!("Timeout waiting for response");
-            if let Err(e) = app.emit(
-                "send-log",
+            let Some(delay) = retry_in else {
+                match failure {
+                    RetryableFailure::Refused => {
+                        emit_response(&app, SendResponse::FailedToConnect(format!("{addr}")));
+                        return;
+                    }
+                    RetryableFailure::TimedOut | RetryableFailure::Nak => break response,
+                }
+            };
+            attempt += 1;
+            emit_log(
+                &app,
                 format!(
-                    "[{now}] Timeout waiting for response after {wait_timeout:?}",
+                    "[{now}] Retrying in {delay:?} (attempt {attempt} of {attempts})",
                     now = Zoned::now()
                 ),
-            ) {
-                log::error!("Failed to emit send-response event: {e:#}");
-            }
-            if let Err(ee) = app.emit("send-response", SendResponse::Final(None)) {
-                log::error!("Failed to emit send-response event: {ee:#}");
-            }
+            );
+            tokio::time::sleep(delay).await;
+        };
+
+        // no response at all, or the last of the negative acknowledgements
+        let Some(response) = response else {
+            emit_response(&app, SendResponse::Final(None));
             return;
         };
 
-        let response = match response {
+        let response = match hl7_parser::parse_message_with_lenient_newlines(&response) {
             Ok(response) => response,
             Err(e) => {
-                log::error!("Failed to receive message: {e:#}");
-                if let Err(ee) = app.emit(
-                    "send-response",
-                    SendResponse::FailedToReceive(format!("{e:#}")),
-                ) {
-                    log::error!("Failed to emit send-response event: {ee:#}");
-                }
+                log::error!("Failed to parse response message: {e:#}");
+                emit_response(
+                    &app,
+                    SendResponse::FailedToParse {
+                        message: response.to_string(),
+                        error: format!("{e:#}"),
+                    },
+                );
                 return;
             }
         };
 
-        if let Err(e) = app.emit(
-            "send-log",
-            format!(
-                "[{now}] Received response from {addr}: {count} bytes. Parsing...",
-                count = response.len(),
-                now = Zoned::now()
-            ),
-        ) {
-            log::error!("Failed to emit send-response event: {e:#}");
+        emit_response(
+            &app,
+            SendResponse::Final(Some(response.raw_value().to_string())),
+        );
+    });
+
+    Ok(())
+}
+
+/// A prepared MLLP send, sent as is on each attempt.
+struct MllpSend {
+    /// Audit event the send's audit events are based on
+    audit: AuditEvent,
+    addr: SocketAddr,
+    /// Host the address was resolved from, for verifying TLS certificates
+    host: String,
+    /// TLS settings, if the connection is wrapped in TLS
+    tls: Option<TlsSettings>,
+    /// Description of where the message is going, for the log lines
+    target: String,
+    /// Name of the open connection to send over, if any
+    connection: Option<String>,
+    framing: Framing,
+    /// The framed message's content, encoded
+    encoded: Vec<u8>,
+    control_id: Option<String>,
+    endpoint: Option<EndpointLabel>,
+    wait_timeout: Duration,
+}
+
+impl MllpSend {
+    /// Build an audit event about this send.
+    fn audit_event(&self, kind: AuditEventKind, detail: Option<String>) -> AuditEvent {
+        AuditEvent {
+            timestamp: jiff::Timestamp::now(),
+            kind,
+            detail,
+            ..self.audit.clone()
         }
+    }
+}
 
-        let response = match str::from_utf8(&response) {
-            Ok(response) => response,
+/// How a single attempt at an MLLP send ended.
+enum Attempt {
+    /// The connection was refused or couldn't be made, or the named connection
+    /// isn't open
+    Refused,
+    /// The message couldn't be written to the connection
+    SendFailed(String),
+    /// No response arrived within the wait timeout, or the remote closed the
+    /// connection instead of responding
+    TimedOut,
+    /// The response couldn't be read
+    ReceiveFailed(String),
+    /// A response arrived
+    Responded(BytesMut),
+}
+
+/// Connect (or check out the named connection), send the message, and wait for
+/// a response, emitting the `sent` event once the message is written.
+async fn attempt_send(app: &AppHandle, send: &MllpSend) -> Attempt {
+    if let Some(name) = &send.connection {
+        // a pooled connection is held for the whole attempt, so its response
+        // can't be read by another send
+        let Some(mut pooled) = app.state::<AppData>().connections.checkout(name).await else {
+            return Attempt::Refused;
+        };
+        return exchange(app, send, pooled.transport_for_send()).await;
+    }
+
+    let Ok(stream) = TcpStream::connect(send.addr).await else {
+        return Attempt::Refused;
+    };
+    let codec = FramingCodec::new(send.framing.clone());
+    match &send.tls {
+        Some(tls) => match connect_tls(stream, tls, &send.host).await {
+            Ok(stream) => exchange(app, send, &mut Framed::new(stream, codec)).await,
             Err(e) => {
-                log::error!("Failed to decode response as UTF-8: {e:#}");
-                if let Err(ee) = app.emit(
-                    "send-response",
-                    SendResponse::FailedToDecode(format!("{e:#}")),
-                ) {
-                    log::error!("Failed to emit send-response event: {ee:#}");
-                }
-                return;
+                log::error!("{e}");
+                emit_log(app, format!("[{now}] {e}", now = Zoned::now()));
+                Attempt::Refused
             }
-        };
+        },
+        None => exchange(app, send, &mut Framed::new(stream, codec)).await,
+    }
+}
+
+/// Send the message over a connected transport and wait for a response.
+async fn exchange<S>(
+    app: &AppHandle,
+    send: &MllpSend,
+    transport: &mut Framed<S, FramingCodec>,
+) -> Attempt
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    if let Err(e) = transport
+        .send(BytesMut::from(send.encoded.as_slice()))
+        .await
+    {
+        log::error!("Failed to send message: {e:#}");
+        discard_connection(app, send.connection.as_deref()).await;
+        return Attempt::SendFailed(format!("{e:#}"));
+    }
 
-        if let Err(e) = app.emit(
-            "send-log",
-            format!("[{now}] Response:\n{response}", now = Zoned::now()),
-        ) {
-            log::error!("Failed to emit send-response event: {e:#}");
+    log::info!(
+        "Sent message {control_id} to {target}",
+        control_id = send.control_id.as_deref().unwrap_or("<none>"),
+        target = send.target,
+    );
+    record_audit_event(app, send.audit_event(AuditEventKind::MessageSent, None)).await;
+    emit_response(
+        app,
+        SendResponse::Sent(SendConfirmation {
+            addr: send.addr.to_string(),
+            control_id: send.control_id.clone(),
+            endpoint: send.endpoint.clone(),
+        }),
+    );
+    emit_log(
+        app,
+        format!(
+            "[{now}] Message sent to {target}, awaiting response...",
+            target = send.target,
+            now = Zoned::now()
+        ),
+    );
+
+    match timeout(send.wait_timeout, transport.next()).await {
+        Ok(Some(Ok(response))) => Attempt::Responded(response),
+        Ok(Some(Err(e))) => {
+            log::error!("Failed to receive message: {e:#}");
+            discard_connection(app, send.connection.as_deref()).await;
+            Attempt::ReceiveFailed(format!("{e:#}"))
+        }
+        Ok(None) => {
+            // the remote closed the connection instead of responding
+            discard_connection(app, send.connection.as_deref()).await;
+            Attempt::TimedOut
         }
+        Err(_) => Attempt::TimedOut,
+    }
+}
 
-        let response = match hl7_parser::parse_message_with_lenient_newlines(response) {
-            Ok(response) => response,
-            Err(e) => {
-                log::error!("Failed to parse response message: {e:#}");
-                if let Err(ee) = app.emit(
-                    "send-response",
-                    SendResponse::FailedToParse {
-                        message: response.to_string(),
-                        error: format!("{e:#}"),
-                    },
-                ) {
-                    log::error!("Failed to emit send-response event: {ee:#}");
-                }
-                return;
+/// Emit a `send-log` line.
+pub(super) fn emit_log(app: &AppHandle, line: String) {
+    if let Err(e) = app.emit(events::SEND_LOG, line) {
+        log::error!("Failed to emit send-log event: {e:#}");
+    }
+}
+
+/// Emit a `send-response` event.
+pub(super) fn emit_response(app: &AppHandle, response: SendResponse) {
+    if let Err(e) = app.emit(events::SEND_RESPONSE, response) {
+        log::error!("Failed to emit send-response event: {e:#}");
+    }
+}
+
+/// Remove a pooled connection that stopped working from the pool, if the send
+/// used one.
+async fn discard_connection(app: &AppHandle, connection: Option<&str>) {
+    if let Some(name) = connection {
+        if app.state::<AppData>().connections.remove(name).await {
+            log::info!("Removed pooled connection {name}");
+        }
+    }
+}
+
+/// Parse a message, apply the placeholder transformations for MSH.7 and MSH.10,
+/// fill in the remaining placeholders, and evaluate any `{=...}` formulas.
+///
+/// See [`send_message`] for the placeholders. With `regenerate`, MSH.7 and MSH.10
+/// are replaced whatever their value, so repeated sends of the same message each
+/// get a fresh timestamp and control ID.
+///
+/// # Arguments
+/// * `message` - The HL7 message to prepare
+/// * `regenerate` - Replace MSH.7 and MSH.10 even if they don't hold placeholders
+/// * `variables` - User-defined placeholder values, by name
+///
+/// # Returns
+/// * `Ok((String, Option<String>))` - The prepared message and its MSH.10
+/// * `Err(String)` - If the message can't be parsed, or a placeholder or formula
+///   can't be evaluated
+pub(crate) fn prepare_message(
+    message: &str,
+    regenerate: bool,
+    variables: &HashMap<String, String>,
+) -> Result<(String, Option<String>), String> {
+    let (message, _) = strip_document_metadata(message);
+    let message = hl7_parser::parse_message_with_lenient_newlines(message)
+        .map_err(|e| format!("Failed to parse message: {e:#}"))?;
+
+    let mut message: MessageBuilder = (&message).into();
+
+    // Placeholder transformations for auto-generated values
+    let msh = message
+        .segment_named_mut("MSH")
+        .expect("messages have MSH segments");
+
+    // Transform {auto} or {now} in MSH.7 to current timestamp
+    if let Some(timestamp) = msh.field_mut(7) {
+        if let Some(value) = timestamp.value_mut() {
+            if regenerate || value == "{auto}" || value == "{now}" {
+                let now = jiff::Zoned::now();
+                let now: jiff::civil::DateTime = now.into();
+                let now: TimeStamp = now.into();
+                *value = now.to_string();
             }
-        };
+        }
+    }
 
-        if let Err(ee) = app.emit(
-            "send-response",
-            SendResponse::Final(Some(response.raw_value().to_string())),
-        ) {
-            log::error!("Failed to emit send-response event: {ee:#}");
+    // Transform {auto} or {random} in MSH.10 to random control ID
+    if let Some(control_id) = msh.field_mut(10) {
+        if let Some(value) = control_id.value_mut() {
+            if regenerate || value == "{auto}" || value == "{random}" {
+                *value = Alphanumeric.sample_string(&mut rand::rng(), 20);
+            }
         }
-    });
+    }
 
-    Ok(())
+    // formulas can refer to the values filled in above, and can set MSH.10 themselves
+    let message = expand_placeholders(&message.to_string(), variables)?;
+    let message = evaluate_formulas(&message)?;
+    let control_id = hl7_parser::parse_message_with_lenient_newlines(&message)
+        .ok()
+        .and_then(|parsed| {
+            parsed
+                .query("MSH.10")
+                .map(|value| parsed.separators.decode(value.raw_value()).to_string())
+        });
+
+    Ok((message, control_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn endpoint_label_includes_environment_and_tag() {
+        let label = EndpointLabel {
+            name: "Lab Interface".to_string(),
+            environment: Some(Environment::Prod),
+            tag: Some("lab".to_string()),
+        };
+        assert_eq!(label.to_string(), "Lab Interface [prod, lab]");
+    }
+
+    #[test]
+    fn endpoint_label_without_details_is_just_the_name() {
+        let label = EndpointLabel {
+            name: "Local".to_string(),
+            environment: None,
+            tag: Some(String::new()),
+        };
+        assert_eq!(label.to_string(), "Local");
+    }
+
+    #[test]
+    fn production_sends_must_be_confirmed() {
+        let label = |environment| EndpointLabel {
+            name: "Lab Interface".to_string(),
+            environment,
+            tag: None,
+        };
+        let prod = label(Some(Environment::Prod));
+        assert!(check_prod_confirmed(Some(&prod), false).is_err());
+        assert!(check_prod_confirmed(Some(&prod), true).is_ok());
+        assert!(check_prod_confirmed(Some(&label(Some(Environment::Test))), false).is_ok());
+        assert!(check_prod_confirmed(Some(&label(None)), false).is_ok());
+        assert!(check_prod_confirmed(None, false).is_ok());
+    }
 }
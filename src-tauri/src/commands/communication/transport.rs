@@ -0,0 +1,244 @@
+//! Sending messages over transports provided by extensions.
+//!
+//! Extensions can register named transports (e.g., IBM MQ, Kafka, an SFTP
+//! drop) in their `initialize` capabilities. A send to a connection preset
+//! configured with one of those transports is handed to the extension as a
+//! `transport/send` request instead of going out over MLLP. The extension
+//! reports where the message went and the response, if any, and those feed
+//! the same `send-log` / `send-response` events and audit events as an MLLP
+//! send, so the send panel and history treat both alike.
+
+use jiff::Zoned;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::time::timeout;
+
+use super::audit::{record_audit_event, AuditEvent, AuditEventKind};
+use super::schedule::ack_code;
+use super::send::{EndpointLabel, SendConfirmation, SendResponse};
+use crate::events;
+use crate::extensions::types::{TransportSendParams, TransportSendResult};
+use crate::AppData;
+
+/// Transport provided by an extension, chosen in a connection preset.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExtensionTransport {
+    /// Name the extension registered the transport under
+    pub name: String,
+    /// Transport-specific settings passed through to the extension (e.g., queue name)
+    #[serde(default)]
+    pub options: HashMap<String, String>,
+}
+
+/// Details of a prepared message being sent over an extension transport.
+pub(super) struct TransportSend {
+    pub transport: ExtensionTransport,
+    pub host: String,
+    pub port: u16,
+    pub message: String,
+    pub control_id: Option<String>,
+    pub message_type: Option<String>,
+    pub endpoint: Option<EndpointLabel>,
+    pub wait_timeout: Duration,
+}
+
+/// Hand a message to the extension providing its transport and report the result.
+///
+/// Emits the same events as an MLLP send: `Sent` once the extension accepts the
+/// message, then `Final` with the response, or `Final(None)` if the extension
+/// reports none or doesn't answer within the wait timeout.
+pub(super) async fn send_over_extension(app: AppHandle, send: TransportSend) {
+    let TransportSend {
+        transport,
+        host,
+        port,
+        message,
+        control_id,
+        message_type,
+        endpoint,
+        wait_timeout,
+    } = send;
+
+    let peer = format!("{} transport", transport.name);
+    let target = match &endpoint {
+        Some(endpoint) => format!("{endpoint} ({peer})"),
+        None => peer.clone(),
+    };
+    let audit_base = AuditEvent {
+        control_id: control_id.clone(),
+        message_type,
+        endpoint: endpoint.as_ref().map(ToString::to_string),
+        environment: endpoint.as_ref().and_then(|e| e.environment),
+        ..AuditEvent::new(AuditEventKind::MessageSent, &peer)
+    };
+    let audit_event = move |kind, detail| AuditEvent {
+        timestamp: jiff::Timestamp::now(),
+        kind,
+        detail,
+        ..audit_base.clone()
+    };
+
+    if let Err(e) = app.emit(
+        events::SEND_LOG,
+        format!(
+            "[{now}] Sending message to {target}:\n{message}",
+            now = Zoned::now()
+        ),
+    ) {
+        log::error!("Failed to emit send-log event: {e:#}");
+    }
+
+    // take a sender and release the host, so a slow transport doesn't hold it
+    let sender = {
+        let state = app.state::<AppData>();
+        let host = state.extension_host.lock().await;
+        host.transport_sender(&transport.name).await
+    };
+    let Some(sender) = sender else {
+        log::error!(
+            "No running extension provides the {} transport",
+            transport.name
+        );
+        record_audit_event(
+            &app,
+            audit_event(
+                AuditEventKind::SendFailed,
+                Some("Transport unavailable".to_string()),
+            ),
+        )
+        .await;
+        if let Err(e) = app.emit(
+            events::SEND_RESPONSE,
+            SendResponse::FailedToConnect(format!("{peer}: no running extension provides it")),
+        ) {
+            log::error!("Failed to emit send-response event: {e:#}");
+        }
+        return;
+    };
+
+    let params = TransportSendParams {
+        transport: transport.name.clone(),
+        host,
+        port,
+        options: transport.options,
+        message,
+        control_id: control_id.clone(),
+        timeout_seconds: wait_timeout.as_secs_f32(),
+    };
+    let params = match serde_json::to_value(&params) {
+        Ok(params) => params,
+        Err(e) => {
+            log::error!("Failed to serialise transport/send params: {e:#}");
+            if let Err(ee) = app.emit(
+                events::SEND_RESPONSE,
+                SendResponse::FailedToSend(format!("{e:#}")),
+            ) {
+                log::error!("Failed to emit send-response event: {ee:#}");
+            }
+            return;
+        }
+    };
+
+    let result = match timeout(wait_timeout, sender.send("transport/send", params)).await {
+        Ok(Ok(response)) => serde_json::from_value::<TransportSendResult>(response.result)
+            .map_err(|e| format!("Extension returned an invalid transport/send result: {e}")),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => {
+            log::warn!("Timeout waiting for the {peer}");
+            record_audit_event(&app, audit_event(AuditEventKind::AckTimeout, None)).await;
+            if let Err(e) = app.emit(
+                events::SEND_LOG,
+                format!(
+                    "[{now}] Timeout waiting for the {peer} after {wait_timeout:?}",
+                    now = Zoned::now()
+                ),
+            ) {
+                log::error!("Failed to emit send-log event: {e:#}");
+            }
+            if let Err(e) = app.emit(events::SEND_RESPONSE, SendResponse::Final(None)) {
+                log::error!("Failed to emit send-response event: {e:#}");
+            }
+            return;
+        }
+    };
+
+    let result = match result {
+        Ok(result) => result,
+        Err(e) => {
+            log::error!("Failed to send message over the {peer}: {e}");
+            record_audit_event(
+                &app,
+                audit_event(AuditEventKind::SendFailed, Some(e.clone())),
+            )
+            .await;
+            if let Err(ee) = app.emit(events::SEND_RESPONSE, SendResponse::FailedToSend(e)) {
+                log::error!("Failed to emit send-response event: {ee:#}");
+            }
+            return;
+        }
+    };
+
+    let destination = result.destination.unwrap_or_else(|| peer.clone());
+    log::info!(
+        "Sent message {control_id} to {target} ({destination})",
+        control_id = control_id.as_deref().unwrap_or("<none>")
+    );
+    record_audit_event(&app, audit_event(AuditEventKind::MessageSent, None)).await;
+    if let Err(e) = app.emit(
+        events::SEND_RESPONSE,
+        SendResponse::Sent(SendConfirmation {
+            addr: destination.clone(),
+            control_id,
+            endpoint,
+        }),
+    ) {
+        log::error!("Failed to emit send-response event: {e:#}");
+    }
+
+    let Some(response) = result.response else {
+        if let Err(e) = app.emit(
+            events::SEND_LOG,
+            format!(
+                "[{now}] Message sent to {destination}, no response returned",
+                now = Zoned::now()
+            ),
+        ) {
+            log::error!("Failed to emit send-log event: {e:#}");
+        }
+        if let Err(e) = app.emit(events::SEND_RESPONSE, SendResponse::Final(None)) {
+            log::error!("Failed to emit send-response event: {e:#}");
+        }
+        return;
+    };
+
+    if let Err(e) = app.emit(
+        events::SEND_LOG,
+        format!(
+            "[{now}] Response from {destination}:\n{response}",
+            now = Zoned::now()
+        ),
+    ) {
+        log::error!("Failed to emit send-log event: {e:#}");
+    }
+    record_audit_event(
+        &app,
+        audit_event(AuditEventKind::AckReceived, ack_code(&response)),
+    )
+    .await;
+
+    let event = match hl7_parser::parse_message_with_lenient_newlines(&response) {
+        Ok(parsed) => SendResponse::Final(Some(parsed.raw_value().to_string())),
+        Err(e) => {
+            log::error!("Failed to parse response message: {e:#}");
+            SendResponse::FailedToParse {
+                message: response.clone(),
+                error: format!("{e:#}"),
+            }
+        }
+    };
+    if let Err(e) = app.emit(events::SEND_RESPONSE, event) {
+        log::error!("Failed to emit send-response event: {e:#}");
+    }
+}
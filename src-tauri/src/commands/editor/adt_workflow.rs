@@ -0,0 +1,600 @@
+//! Generate the ADT messages for a whole patient encounter.
+//!
+//! Testing an interface's handling of a visit means sending a register, an
+//! admit, a transfer or two, and a discharge that all agree with each other:
+//! the same MRN and visit number, each transfer starting where the last one
+//! left the patient, timestamps in order. A single template gets one of those
+//! messages right; this builds the lot from one set of demographics and a
+//! scenario.
+//!
+//! # Scenarios
+//!
+//! A scenario is the list of events to generate, in order. Without one, the
+//! full lifecycle is generated: A04 (register) → A01 (admit) → A02 (transfer)
+//! → A03 (discharge). The events have to make sense together:
+//!
+//! - A04 can only start the encounter
+//! - A01 starts the encounter or admits a registered patient, once
+//! - A02, A03, and A08 need an encounter that hasn't been discharged
+//!
+//! # Values Shared Between Messages
+//!
+//! - PID-3 holds the MRN, PID-5, 7, and 8 the demographics
+//! - PV1-19 holds the visit number, PV1-44 the admit (or registration) time,
+//!   and PV1-45 the discharge time once discharged
+//! - PV1-2 is `E` until the patient is admitted, then the scenario's patient
+//!   class (`I` by default)
+//! - PV1-3 moves to the next location on an admission after registration and
+//!   on each transfer, with PV1-6 holding where the patient was
+//!
+//! An MRN or visit number left empty is generated, as are locations beyond
+//! those given. The first event happens at the scenario's start time (or now),
+//! and each later one a sensible while after the one before: two hours after
+//! registration for an admission, a day for a transfer, two days for a
+//! discharge, half an hour for an update. A fixed interval replaces those.
+
+use hl7_parser::builder::{FieldBuilder, MessageBuilder, SegmentBuilder};
+use hl7_parser::message::Separators;
+use jiff::civil::DateTime;
+use jiff::SignedDuration;
+use rand::distr::{Alphanumeric, SampleString};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::escape::escape_text;
+
+/// Format of timestamps in the generated messages.
+const TIMESTAMP_FORMAT: &str = "%Y%m%d%H%M%S";
+
+/// Locations used when a scenario doesn't give enough, starting with the
+/// registration desk for encounters that start with A04.
+const DEFAULT_LOCATIONS: &[&str] = &["ER^01^A", "MED^201^A", "SURG^305^B", "ICU^04^A"];
+
+/// An ADT event a scenario can include.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AdtEvent {
+    /// A01: admit an inpatient
+    #[serde(rename = "A01")]
+    Admit,
+    /// A02: transfer to another location
+    #[serde(rename = "A02")]
+    Transfer,
+    /// A03: discharge and end the visit
+    #[serde(rename = "A03")]
+    Discharge,
+    /// A04: register an outpatient or emergency visit
+    #[serde(rename = "A04")]
+    Register,
+    /// A08: update the patient's information
+    #[serde(rename = "A08")]
+    Update,
+}
+
+/// The patient the encounter is for.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WorkflowPatient {
+    /// Family name
+    pub family_name: String,
+    /// Given name
+    pub given_name: String,
+    /// Date of birth (YYYYMMDD), if known
+    pub birth_date: String,
+    /// Administrative sex (e.g., "F", "M", "U"), if known
+    pub sex: String,
+    /// Medical record number; generated if empty
+    pub mrn: String,
+}
+
+/// What happens during the encounter.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct VisitScenario {
+    /// Events to generate, in order (the full lifecycle if empty)
+    pub events: Vec<AdtEvent>,
+    /// Time of the first event as an HL7 timestamp (now if empty)
+    pub start: String,
+    /// Minutes between events, replacing the defaults for each event
+    pub interval_minutes: Option<u32>,
+    /// PV1-2 once the patient is admitted ("I" if empty)
+    pub patient_class: String,
+    /// Locations the patient moves through (e.g., "MED^201^A"), in order
+    pub locations: Vec<String>,
+    /// Attending doctor for PV1-7, as written (e.g., "1234^WELBY^MARCUS")
+    pub attending: String,
+    /// Visit number for PV1-19; generated if empty
+    pub visit_number: String,
+    /// Sending facility for MSH-4 and the MRN's assigning facility ("HOSP" if empty)
+    pub facility: String,
+}
+
+/// One message of a generated encounter.
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkflowMessage {
+    /// The event the message is for
+    pub event: AdtEvent,
+    /// When the event happened, as an HL7 timestamp
+    pub timestamp: String,
+    /// The message
+    pub message: String,
+}
+
+/// A generated encounter.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdtWorkflow {
+    /// The messages, in event order
+    pub messages: Vec<WorkflowMessage>,
+    /// The medical record number used
+    pub mrn: String,
+    /// The visit number used
+    pub visit_number: String,
+    /// Every message, separated by blank lines
+    pub content: String,
+}
+
+impl AdtEvent {
+    /// Message structure for MSH-9.3.
+    fn structure(self) -> &'static str {
+        match self {
+            AdtEvent::Admit | AdtEvent::Register | AdtEvent::Update => "ADT_A01",
+            AdtEvent::Transfer => "ADT_A02",
+            AdtEvent::Discharge => "ADT_A03",
+        }
+    }
+
+    /// Trigger event code.
+    fn code(self) -> &'static str {
+        match self {
+            AdtEvent::Admit => "A01",
+            AdtEvent::Transfer => "A02",
+            AdtEvent::Discharge => "A03",
+            AdtEvent::Register => "A04",
+            AdtEvent::Update => "A08",
+        }
+    }
+
+    /// Time after the previous event this one usually happens.
+    fn default_gap(self) -> SignedDuration {
+        match self {
+            AdtEvent::Admit | AdtEvent::Register => SignedDuration::from_hours(2),
+            AdtEvent::Transfer => SignedDuration::from_hours(24),
+            AdtEvent::Discharge => SignedDuration::from_hours(48),
+            AdtEvent::Update => SignedDuration::from_mins(30),
+        }
+    }
+}
+
+/// Generate the ADT messages for an encounter.
+///
+/// # Arguments
+/// * `patient` - The patient's demographics
+/// * `scenario` - The events to generate and details of the visit
+///
+/// # Returns
+/// * `Ok(AdtWorkflow)` - The messages, in event order
+/// * `Err(String)` - If the patient has no name, the start time isn't a
+///   timestamp, or the events don't make sense in order
+#[tauri::command]
+pub fn generate_adt_workflow(
+    patient: WorkflowPatient,
+    scenario: VisitScenario,
+) -> Result<AdtWorkflow, String> {
+    let start = match scenario.start.trim() {
+        "" => jiff::Zoned::now().datetime(),
+        start => parse_start(start)?,
+    };
+    generate_workflow(&patient, &scenario, start)
+}
+
+/// Generate an encounter with its first event at `start`.
+fn generate_workflow(
+    patient: &WorkflowPatient,
+    scenario: &VisitScenario,
+    start: DateTime,
+) -> Result<AdtWorkflow, String> {
+    if patient.family_name.trim().is_empty() || patient.given_name.trim().is_empty() {
+        return Err("The patient needs a family and a given name".to_string());
+    }
+    let events = if scenario.events.is_empty() {
+        vec![
+            AdtEvent::Register,
+            AdtEvent::Admit,
+            AdtEvent::Transfer,
+            AdtEvent::Discharge,
+        ]
+    } else {
+        scenario.events.clone()
+    };
+    check_events(&events)?;
+
+    let mut rng = rand::rng();
+    let mrn = or_generated(&patient.mrn, || digits(&mut rng, 7));
+    let visit_number = or_generated(&scenario.visit_number, || digits(&mut rng, 8));
+    let control_base = Alphanumeric.sample_string(&mut rng, 12);
+    let facility = or_default(&scenario.facility, "HOSP");
+    let patient_class = or_default(&scenario.patient_class, "I");
+
+    // locations for encounters that start with an admission skip the ER
+    let skip = usize::from(events.first() != Some(&AdtEvent::Register));
+    let location = |index: usize| {
+        scenario
+            .locations
+            .get(index)
+            .cloned()
+            .or_else(|| DEFAULT_LOCATIONS.get(index + skip).map(ToString::to_string))
+            .unwrap_or_else(|| format!("MED^{}^A", 200 + index))
+    };
+
+    let mut visit = Visit {
+        class: "E".to_string(),
+        location_index: 0,
+        prior_location: None,
+        admitted: None,
+        discharged: None,
+    };
+    let mut time = start;
+    let mut messages = Vec::with_capacity(events.len());
+    for (index, event) in events.iter().copied().enumerate() {
+        if index > 0 {
+            let gap = scenario
+                .interval_minutes
+                .map(|minutes| SignedDuration::from_mins(i64::from(minutes)))
+                .unwrap_or_else(|| event.default_gap());
+            time = time
+                .checked_add(gap)
+                .map_err(|e| format!("Failed to work out the time of event {}: {e}", index + 1))?;
+        }
+        let timestamp = time.strftime(TIMESTAMP_FORMAT).to_string();
+
+        visit.prior_location = None;
+        match event {
+            AdtEvent::Register => visit.admitted = Some(timestamp.clone()),
+            AdtEvent::Admit => {
+                if index > 0 {
+                    visit.move_to_next(location(visit.location_index));
+                }
+                visit.class = patient_class.clone();
+                visit.admitted = Some(timestamp.clone());
+            }
+            AdtEvent::Transfer => visit.move_to_next(location(visit.location_index)),
+            AdtEvent::Discharge => visit.discharged = Some(timestamp.clone()),
+            AdtEvent::Update => {}
+        }
+
+        let message = build_message(&MessageParts {
+            event,
+            timestamp: &timestamp,
+            control_id: &format!("{control_base}{:02}", index + 1),
+            facility: &facility,
+            patient,
+            mrn: &mrn,
+            visit: &visit,
+            location: &location(visit.location_index),
+            attending: &scenario.attending,
+            visit_number: &visit_number,
+        });
+        messages.push(WorkflowMessage {
+            event,
+            timestamp,
+            message,
+        });
+    }
+
+    let content = messages
+        .iter()
+        .map(|m| m.message.as_str())
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    Ok(AdtWorkflow {
+        messages,
+        mrn,
+        visit_number,
+        content,
+    })
+}
+
+/// Where the encounter stands after an event.
+struct Visit {
+    /// PV1-2
+    class: String,
+    /// Which of the scenario's locations the patient is in
+    location_index: usize,
+    /// Where the patient was before this event moved them
+    prior_location: Option<String>,
+    /// Admit (or registration) time
+    admitted: Option<String>,
+    /// Discharge time
+    discharged: Option<String>,
+}
+
+impl Visit {
+    /// Move the patient on, remembering where they were.
+    fn move_to_next(&mut self, current: String) {
+        self.prior_location = Some(current);
+        self.location_index += 1;
+    }
+}
+
+/// Everything that goes into one message.
+struct MessageParts<'a> {
+    event: AdtEvent,
+    timestamp: &'a str,
+    control_id: &'a str,
+    facility: &'a str,
+    patient: &'a WorkflowPatient,
+    mrn: &'a str,
+    visit: &'a Visit,
+    location: &'a str,
+    attending: &'a str,
+    visit_number: &'a str,
+}
+
+/// Check that a scenario's events make sense in order.
+fn check_events(events: &[AdtEvent]) -> Result<(), String> {
+    let mut open = false;
+    let mut admitted = false;
+    let mut discharged = false;
+    for (index, event) in events.iter().enumerate() {
+        let number = index + 1;
+        match event {
+            AdtEvent::Register if index > 0 => {
+                return Err(format!("Event {number}: A04 can only start the encounter"));
+            }
+            AdtEvent::Admit if admitted => {
+                return Err(format!("Event {number}: the patient is already admitted"));
+            }
+            AdtEvent::Register | AdtEvent::Admit if discharged => {
+                return Err(format!("Event {number}: the patient was discharged"));
+            }
+            AdtEvent::Transfer | AdtEvent::Discharge | AdtEvent::Update if !open || discharged => {
+                return Err(format!(
+                    "Event {number}: {} needs an encounter that hasn't been discharged",
+                    event.code()
+                ));
+            }
+            AdtEvent::Admit => admitted = true,
+            AdtEvent::Discharge => discharged = true,
+            AdtEvent::Transfer | AdtEvent::Register | AdtEvent::Update => {}
+        }
+        open = true;
+    }
+    Ok(())
+}
+
+/// Build the MSH, EVN, PID, and PV1 segments of one message.
+fn build_message(parts: &MessageParts) -> String {
+    let separators = Separators::default();
+    let escape = |text: &str| escape_text(text.trim(), &separators);
+    let MessageParts {
+        event,
+        timestamp,
+        visit,
+        ..
+    } = parts;
+
+    let msh = SegmentBuilder::new("MSH")
+        .with_field_value(3, "HERMES")
+        .with_field_value(4, &escape(parts.facility))
+        .with_field_value(7, *timestamp)
+        .with_field(
+            9,
+            FieldBuilder::default()
+                .with_component_value(1, "ADT")
+                .with_component_value(2, event.code())
+                .with_component_value(3, event.structure()),
+        )
+        .with_field_value(10, parts.control_id)
+        .with_field_value(11, "P")
+        .with_field_value(12, "2.5.1");
+
+    let evn = SegmentBuilder::new("EVN")
+        .with_field_value(1, event.code())
+        .with_field_value(2, *timestamp)
+        .with_field_value(6, *timestamp);
+
+    let mut pid = SegmentBuilder::new("PID")
+        .with_field_value(1, "1")
+        .with_field(
+            3,
+            FieldBuilder::default()
+                .with_component_value(1, &escape(parts.mrn))
+                .with_component_value(4, &escape(parts.facility))
+                .with_component_value(5, "MR"),
+        )
+        .with_field(
+            5,
+            FieldBuilder::default()
+                .with_component_value(1, &escape(&parts.patient.family_name))
+                .with_component_value(2, &escape(&parts.patient.given_name)),
+        );
+    if !parts.patient.birth_date.trim().is_empty() {
+        pid.set_field_value(7, &escape(&parts.patient.birth_date));
+    }
+    if !parts.patient.sex.trim().is_empty() {
+        pid.set_field_value(8, &escape(&parts.patient.sex));
+    }
+
+    // locations and the attending doctor are written with their components
+    let mut pv1 = SegmentBuilder::new("PV1")
+        .with_field_value(1, "1")
+        .with_field_value(2, visit.class.as_str())
+        .with_field_value(3, parts.location.trim())
+        .with_field_value(19, &escape(parts.visit_number));
+    if let Some(prior) = &visit.prior_location {
+        pv1.set_field_value(6, prior.trim());
+    }
+    if !parts.attending.trim().is_empty() {
+        pv1.set_field_value(7, parts.attending.trim());
+    }
+    if let Some(admitted) = &visit.admitted {
+        pv1.set_field_value(44, admitted.as_str());
+    }
+    if let Some(discharged) = &visit.discharged {
+        pv1.set_field_value(45, discharged.as_str());
+    }
+
+    MessageBuilder::default()
+        .with_segment(msh)
+        .with_segment(evn)
+        .with_segment(pid)
+        .with_segment(pv1)
+        .render_with_newlines()
+        .to_string()
+}
+
+/// Parse a start time written as an HL7 timestamp, to the minute or second.
+fn parse_start(start: &str) -> Result<DateTime, String> {
+    let padded = match start.len() {
+        8 => format!("{start}000000"),
+        12 => format!("{start}00"),
+        _ => start.to_string(),
+    };
+    DateTime::strptime(TIMESTAMP_FORMAT, &padded)
+        .map_err(|_| format!("'{start}' isn't an HL7 timestamp (YYYYMMDD[HHMM[SS]])"))
+}
+
+/// The value, or a generated one if it's empty.
+fn or_generated(value: &str, generate: impl FnOnce() -> String) -> String {
+    match value.trim() {
+        "" => generate(),
+        value => value.to_string(),
+    }
+}
+
+/// The value, or a default if it's empty.
+fn or_default(value: &str, default: &str) -> String {
+    or_generated(value, || default.to_string())
+}
+
+/// A random number with the given count of digits, not starting with zero.
+fn digits(rng: &mut impl Rng, count: usize) -> String {
+    (0..count)
+        .map(|i| char::from(b'0' + rng.random_range(u8::from(i == 0)..10)))
+        .collect()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    fn patient() -> WorkflowPatient {
+        WorkflowPatient {
+            family_name: "DOE".to_string(),
+            given_name: "JANE".to_string(),
+            birth_date: "19800101".to_string(),
+            sex: "F".to_string(),
+            mrn: String::new(),
+        }
+    }
+
+    fn query(message: &str, path: &str) -> String {
+        let parsed = hl7_parser::parse_message_with_lenient_newlines(message).unwrap();
+        parsed
+            .query(path)
+            .map(|value| value.raw_value().to_string())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn generates_a_coherent_lifecycle() {
+        let start = parse_start("202503141000").unwrap();
+        let workflow = generate_workflow(&patient(), &VisitScenario::default(), start).unwrap();
+        let messages: Vec<&str> = workflow
+            .messages
+            .iter()
+            .map(|m| m.message.as_str())
+            .collect();
+        assert_eq!(messages.len(), 4);
+
+        let types: Vec<String> = messages.iter().map(|m| query(m, "MSH.9")).collect();
+        assert_eq!(
+            types,
+            [
+                "ADT^A04^ADT_A01",
+                "ADT^A01^ADT_A01",
+                "ADT^A02^ADT_A02",
+                "ADT^A03^ADT_A03"
+            ]
+        );
+        for message in &messages {
+            assert_eq!(query(message, "PID.3.1"), workflow.mrn);
+            assert_eq!(query(message, "PV1.19"), workflow.visit_number);
+            assert_eq!(query(message, "PID.5"), "DOE^JANE");
+        }
+
+        let times: Vec<&str> = workflow
+            .messages
+            .iter()
+            .map(|m| m.timestamp.as_str())
+            .collect();
+        assert_eq!(
+            times,
+            [
+                "20250314100000",
+                "20250314120000",
+                "20250315120000",
+                "20250317120000"
+            ]
+        );
+
+        // registered in the ER, admitted to a ward, transferred, discharged from there
+        assert_eq!(query(messages[0], "PV1.2"), "E");
+        assert_eq!(query(messages[0], "PV1.3"), "ER^01^A");
+        assert_eq!(query(messages[1], "PV1.2"), "I");
+        assert_eq!(query(messages[1], "PV1.3"), "MED^201^A");
+        assert_eq!(query(messages[1], "PV1.6"), "ER^01^A");
+        assert_eq!(query(messages[1], "PV1.44"), "20250314120000");
+        assert_eq!(query(messages[2], "PV1.3"), "SURG^305^B");
+        assert_eq!(query(messages[2], "PV1.6"), "MED^201^A");
+        assert_eq!(query(messages[3], "PV1.3"), "SURG^305^B");
+        assert_eq!(query(messages[3], "PV1.45"), "20250317120000");
+
+        assert_ne!(query(messages[0], "MSH.10"), query(messages[1], "MSH.10"));
+        assert_eq!(workflow.content.split("\n\n").count(), 4);
+    }
+
+    #[test]
+    fn follows_the_scenario() {
+        let scenario = VisitScenario {
+            events: vec![AdtEvent::Admit, AdtEvent::Update, AdtEvent::Discharge],
+            interval_minutes: Some(90),
+            locations: vec!["CARD^12^B".to_string()],
+            visit_number: "V100".to_string(),
+            ..VisitScenario::default()
+        };
+        let mut patient = patient();
+        patient.mrn = "M^1".to_string();
+        let start = parse_start("20250314").unwrap();
+        let workflow = generate_workflow(&patient, &scenario, start).unwrap();
+
+        let first = &workflow.messages[0].message;
+        assert_eq!(query(first, "PV1.3"), "CARD^12^B");
+        assert_eq!(query(first, "PV1.19"), "V100");
+        assert_eq!(query(first, "PID.3.1"), "M\\S\\1");
+        assert_eq!(workflow.messages[2].timestamp, "20250314030000");
+    }
+
+    #[test]
+    fn rejects_incoherent_scenarios() {
+        for events in [
+            vec![AdtEvent::Admit, AdtEvent::Register],
+            vec![AdtEvent::Admit, AdtEvent::Admit],
+            vec![AdtEvent::Transfer],
+            vec![AdtEvent::Register, AdtEvent::Discharge, AdtEvent::Update],
+        ] {
+            let scenario = VisitScenario {
+                events,
+                ..VisitScenario::default()
+            };
+            assert!(
+                generate_workflow(&patient(), &scenario, parse_start("20250314").unwrap()).is_err()
+            );
+        }
+        assert!(
+            generate_adt_workflow(WorkflowPatient::default(), VisitScenario::default()).is_err()
+        );
+        assert!(parse_start("yesterday").is_err());
+    }
+}
@@ -0,0 +1,386 @@
+//! Notes and bookmarks attached to parts of a message file.
+//!
+//! Vendor sample messages are reviewed by several people at once, and the
+//! notes from a review ("PID.3 carries the MRN in the second repeat", "OBX[4]
+//! is the one the receiver rejects") belong with the message rather than in a
+//! separate document. An annotation attaches a note or a bookmark to a segment,
+//! field, repeat, component, or subcomponent of a message.
+//!
+//! # Sidecar Files
+//!
+//! Annotations are kept in a sidecar file next to the message file, named
+//! after it with `.notes.json` appended (`adt_a01.hl7.notes.json`), so they
+//! travel with the file through shares and version control without touching
+//! the message itself. The sidecar is JSON laid out one property per line, so
+//! it diffs and merges like any other text file. It's deleted when its last
+//! annotation is, and a file without one simply has no annotations.
+//!
+//! # Paths and Ranges
+//!
+//! An annotation is anchored by its query path (`PID.5.1`, `OBX[2].5`) rather
+//! than by offsets, so it stays with its field as the text around it changes.
+//! When annotations are listed, each path is resolved to its current range in
+//! the editor's message, or to none if the message no longer has that part.
+//! An annotation can be added at a path, or at a selection in the editor, in
+//! which case it's anchored to the innermost part holding the whole selection,
+//! named the way the structure view names it (see [`build_tree`]).
+//!
+//! # Resolving
+//!
+//! Like review comments, notes are resolved rather than deleted once they've
+//! been dealt with, so the history of a review stays in the sidecar. Resolved
+//! annotations are still listed, flagged as resolved, and can be reopened.
+
+use hl7_parser::query::LocationQuery;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use super::cursor::CursorRange;
+use super::offsets::Utf16Offsets;
+use super::tree::{build_tree, SegmentNames, TreeNode};
+
+/// Appended to a message file's name to name its sidecar.
+const SIDECAR_SUFFIX: &str = ".notes.json";
+
+/// What an annotation is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnotationKind {
+    /// A remark about the part it's attached to
+    Note,
+    /// A place in the message to come back to
+    Bookmark,
+}
+
+/// A note or bookmark attached to part of a message.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Annotation {
+    /// Identifier of the annotation, unique within its file
+    pub id: String,
+    /// Whether this is a note or a bookmark
+    pub kind: AnnotationKind,
+    /// Query path of the part it's attached to (e.g., "PID.5.1", "OBX[2]")
+    pub path: String,
+    /// Text of the note, or the bookmark's label
+    #[serde(default)]
+    pub text: String,
+    /// Who added it, if known
+    #[serde(default)]
+    pub author: Option<String>,
+    /// When it was added
+    pub created: jiff::Timestamp,
+    /// Whether it has been dealt with
+    #[serde(default)]
+    pub resolved: bool,
+}
+
+/// An annotation and where its part is in the message now.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LocatedAnnotation {
+    #[serde(flatten)]
+    pub annotation: Annotation,
+    /// Range of the annotated part in the message (UTF-16 offsets), or `None`
+    /// if the message no longer has it
+    pub range: Option<CursorRange>,
+}
+
+/// Contents of a sidecar file.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Sidecar {
+    annotations: Vec<Annotation>,
+}
+
+/// Path of the sidecar holding a message file's annotations.
+fn sidecar_path(file_path: &Path) -> PathBuf {
+    let mut name = file_path.as_os_str().to_os_string();
+    name.push(SIDECAR_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Read a message file's annotations, oldest first.
+fn read_annotations(file_path: &Path) -> Result<Vec<Annotation>, String> {
+    let path = sidecar_path(file_path);
+    let contents = match std::fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(format!("Failed to read {}: {e}", path.display())),
+    };
+    serde_json::from_str::<Sidecar>(&contents)
+        .map(|sidecar| sidecar.annotations)
+        .map_err(|e| format!("Failed to parse {}: {e}", path.display()))
+}
+
+/// Write a message file's annotations, deleting the sidecar if there are none.
+fn write_annotations(file_path: &Path, annotations: Vec<Annotation>) -> Result<(), String> {
+    let path = sidecar_path(file_path);
+    if annotations.is_empty() {
+        return match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                Err(format!("Failed to delete {}: {e}", path.display()))
+            }
+            _ => Ok(()),
+        };
+    }
+    let mut contents = serde_json::to_string_pretty(&Sidecar { annotations })
+        .map_err(|e| format!("Failed to serialize annotations: {e}"))?;
+    contents.push('\n');
+    std::fs::write(&path, contents).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+/// Resolve each annotation's path to its range in a message.
+///
+/// If the message can't be parsed, no annotation has a range.
+fn locate(message: &str, annotations: Vec<Annotation>) -> Vec<LocatedAnnotation> {
+    let offsets = Utf16Offsets::new(message);
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(message).ok();
+    annotations
+        .into_iter()
+        .map(|annotation| {
+            let range = parsed
+                .as_ref()
+                .and_then(|parsed| parsed.query(annotation.path.as_str()))
+                .map(|result| offsets.cursor_range(&result.range()));
+            LocatedAnnotation { annotation, range }
+        })
+        .collect()
+}
+
+/// Path of the innermost part of a message holding the whole of a byte range.
+fn path_at(message: &str, range: &std::ops::Range<usize>) -> Result<String, String> {
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(message)
+        .map_err(|e| format!("Failed to parse message: {e}"))?;
+    let tree = build_tree(message, &parsed, |_| SegmentNames::default());
+
+    let holds = |node: &TreeNode| node.range.start <= range.start && range.end <= node.range.end;
+    let mut nodes = tree.as_slice();
+    let mut path = None;
+    while let Some(node) = nodes.iter().find(|node| holds(node)) {
+        path = Some(node.path.clone());
+        nodes = node.children.as_slice();
+    }
+    path.ok_or_else(|| "The selection isn't within a segment".to_string())
+}
+
+/// Change one annotation of a file and write them back.
+fn update_annotation(
+    file_path: &Path,
+    id: &str,
+    update: impl FnOnce(&mut Vec<Annotation>, usize),
+) -> Result<Vec<Annotation>, String> {
+    let mut annotations = read_annotations(file_path)?;
+    let index = annotations
+        .iter()
+        .position(|annotation| annotation.id == id)
+        .ok_or_else(|| format!("No annotation with ID {id}"))?;
+    update(&mut annotations, index);
+    write_annotations(file_path, annotations.clone())?;
+    Ok(annotations)
+}
+
+/// List the annotations of a message file, with where each is in the message.
+///
+/// # Arguments
+/// * `file_path` - Path of the message file
+/// * `message` - The message in the editor, to find the annotated parts in
+///
+/// # Returns
+/// * `Ok(Vec<LocatedAnnotation>)` - The annotations, oldest first
+/// * `Err(String)` - The sidecar exists but couldn't be read
+#[tauri::command]
+pub fn list_annotations(file_path: &str, message: &str) -> Result<Vec<LocatedAnnotation>, String> {
+    Ok(locate(message, read_annotations(Path::new(file_path))?))
+}
+
+/// Attach a note or bookmark to part of a message file.
+///
+/// The part is given either by its query path or by a selection in the
+/// editor; if both are given, the path is used.
+///
+/// # Arguments
+/// * `file_path` - Path of the message file
+/// * `message` - The message in the editor
+/// * `kind` - Whether to add a note or a bookmark
+/// * `text` - Text of the note, or the bookmark's label
+/// * `author` - Who is adding it, if known
+/// * `path` - Query path of the part to annotate (e.g., "PID.5.1")
+/// * `range` - Selection to annotate the innermost enclosing part of (UTF-16
+///   offsets)
+///
+/// # Returns
+/// * `Ok(Vec<LocatedAnnotation>)` - The file's annotations, with the new one last
+/// * `Err(String)` - The path is invalid, the selection isn't within a
+///   segment, or the sidecar couldn't be read or written
+#[tauri::command]
+pub fn add_annotation(
+    file_path: &str,
+    message: &str,
+    kind: AnnotationKind,
+    text: String,
+    author: Option<String>,
+    path: Option<String>,
+    range: Option<CursorRange>,
+) -> Result<Vec<LocatedAnnotation>, String> {
+    let path = match (path.as_deref().map(str::trim), range) {
+        (Some(path), _) if !path.is_empty() => {
+            LocationQuery::parse(path).map_err(|e| format!("Invalid path `{path}`: {e}"))?;
+            path.to_string()
+        }
+        (_, Some(range)) => path_at(message, &Utf16Offsets::new(message).byte_range(&range))?,
+        _ => return Err("An annotation needs a path or a selection".to_string()),
+    };
+
+    let file_path = Path::new(file_path);
+    let mut annotations = read_annotations(file_path)?;
+    annotations.push(Annotation {
+        id: uuid::Uuid::new_v4().to_string(),
+        kind,
+        path,
+        text: text.trim().to_string(),
+        author: author
+            .map(|author| author.trim().to_string())
+            .filter(|author| !author.is_empty()),
+        created: jiff::Timestamp::now(),
+        resolved: false,
+    });
+    write_annotations(file_path, annotations.clone())?;
+    Ok(locate(message, annotations))
+}
+
+/// Mark an annotation as resolved, or reopen it.
+///
+/// # Arguments
+/// * `file_path` - Path of the message file
+/// * `message` - The message in the editor
+/// * `id` - Identifier of the annotation
+/// * `resolved` - Whether the annotation has been dealt with
+///
+/// # Returns
+/// * `Ok(Vec<LocatedAnnotation>)` - The file's annotations after the change
+/// * `Err(String)` - There's no such annotation, or the sidecar couldn't be
+///   read or written
+#[tauri::command]
+pub fn resolve_annotation(
+    file_path: &str,
+    message: &str,
+    id: &str,
+    resolved: bool,
+) -> Result<Vec<LocatedAnnotation>, String> {
+    let annotations = update_annotation(Path::new(file_path), id, |annotations, index| {
+        if let Some(annotation) = annotations.get_mut(index) {
+            annotation.resolved = resolved;
+        }
+    })?;
+    Ok(locate(message, annotations))
+}
+
+/// Delete an annotation.
+///
+/// # Arguments
+/// * `file_path` - Path of the message file
+/// * `message` - The message in the editor
+/// * `id` - Identifier of the annotation
+///
+/// # Returns
+/// * `Ok(Vec<LocatedAnnotation>)` - The file's annotations left
+/// * `Err(String)` - There's no such annotation, or the sidecar couldn't be
+///   read or written
+#[tauri::command]
+pub fn delete_annotation(
+    file_path: &str,
+    message: &str,
+    id: &str,
+) -> Result<Vec<LocatedAnnotation>, String> {
+    let annotations = update_annotation(Path::new(file_path), id, |annotations, index| {
+        annotations.remove(index);
+    })?;
+    Ok(locate(message, annotations))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use crate::commands::temp_dir;
+
+    const MESSAGE: &str = "MSH|^~\\&|APP|FAC|||20250101||ADT^A01|1|P|2.5.1\r\
+        PID|1||123~456^^^HOSP||DOÉ^JANE\r\
+        OBX|1|ST|||first\r\
+        OBX|2|ST|||second";
+
+    #[test]
+    fn anchors_selections_to_the_innermost_part() {
+        let range = |text: &str| {
+            let start = MESSAGE.find(text).unwrap();
+            start..start + text.len()
+        };
+        assert_eq!(path_at(MESSAGE, &range("JANE")).unwrap(), "PID.5.2");
+        assert_eq!(path_at(MESSAGE, &range("DOÉ^JANE")).unwrap(), "PID.5");
+        assert_eq!(path_at(MESSAGE, &range("HOSP")).unwrap(), "PID.3[2].4");
+        assert_eq!(path_at(MESSAGE, &range("second")).unwrap(), "OBX[2].5");
+        assert_eq!(
+            path_at(MESSAGE, &range("first\rOBX")).unwrap_err(),
+            "The selection isn't within a segment"
+        );
+    }
+
+    #[test]
+    fn locates_annotations_in_the_current_message() {
+        let annotation = |path: &str| Annotation {
+            id: path.to_string(),
+            kind: AnnotationKind::Note,
+            path: path.to_string(),
+            text: String::new(),
+            author: None,
+            created: jiff::Timestamp::UNIX_EPOCH,
+            resolved: false,
+        };
+        let located = locate(MESSAGE, vec![annotation("PID.5.2"), annotation("OBX[3].5")]);
+        let start = MESSAGE.find("JANE").unwrap() - 1;
+        assert_eq!(
+            located[0].range,
+            Some(CursorRange {
+                start,
+                end: start + 4
+            })
+        );
+        assert_eq!(located[1].range, None);
+    }
+
+    #[test]
+    fn sidecar_round_trips_and_is_removed_when_empty() {
+        let directory = temp_dir("notes");
+        let file = directory.join("sample.hl7");
+        let file_path = file.to_str().unwrap();
+
+        let added = add_annotation(
+            file_path,
+            MESSAGE,
+            AnnotationKind::Bookmark,
+            " MRN ".to_string(),
+            Some(String::new()),
+            Some("PID.3".to_string()),
+            None,
+        )
+        .unwrap();
+        assert!(directory.join("sample.hl7.notes.json").exists());
+        assert_eq!(added[0].annotation.text, "MRN");
+        assert_eq!(added[0].annotation.author, None);
+
+        let id = added[0].annotation.id.clone();
+        let resolved = resolve_annotation(file_path, MESSAGE, &id, true).unwrap();
+        assert!(resolved[0].annotation.resolved);
+        assert!(
+            list_annotations(file_path, MESSAGE).unwrap()[0]
+                .annotation
+                .resolved
+        );
+
+        assert!(delete_annotation(file_path, MESSAGE, &id)
+            .unwrap()
+            .is_empty());
+        assert!(!directory.join("sample.hl7.notes.json").exists());
+        assert!(delete_annotation(file_path, MESSAGE, &id).is_err());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,402 @@
+//! Completion candidates for the text under the cursor.
+//!
+//! Coded fields take their values from HL7 tables that few people know by
+//! heart. Given the message and the cursor position, this module works out
+//! what kind of text is being typed and offers what could go there, so the
+//! editor can show a completion dropdown.
+//!
+//! # Candidates
+//!
+//! * **Segment names** - At the start of a line, every segment the schema knows,
+//!   including the user's custom segments, that starts with what's been typed
+//! * **Values** - In a field or component with allowed values (its own, or
+//!   those of the HL7 table it's bound to), the codes whose code starts with,
+//!   or whose description contains, what's been typed
+//! * **Timestamps** - In a date or timestamp field, the current time and the
+//!   days around it, formatted for the field
+//!
+//! # Ranges
+//!
+//! Each result carries the range a chosen candidate replaces: the whole
+//! segment name, or the whole cell (field, component, or subcomponent) the
+//! cursor is in, so choosing a value never leaves part of the old one behind.
+
+use serde::Serialize;
+use tauri::State;
+
+use super::cursor::CursorRange;
+use super::data::format_hl7_timestamp;
+use super::offsets::Utf16Offsets;
+use crate::schema::cache::SchemaCache;
+use crate::schema::segment::{DataType, Field};
+use crate::schema::version::{detect_version, DEFAULT_VERSION};
+use crate::spec::std_spec::{
+    is_component_a_timestamp, is_field_a_timestamp, is_valid_version, known_segment_description,
+};
+use crate::AppData;
+
+/// What a completion candidate is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompletionKind {
+    /// A segment name
+    Segment,
+    /// An allowed value of the field
+    Value,
+    /// A date or timestamp
+    Timestamp,
+}
+
+/// A candidate for the text under the cursor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Completion {
+    /// Text to insert in place of the range
+    pub value: String,
+    /// What the text means (e.g., the code's description), if known
+    pub detail: Option<String>,
+    /// What the candidate is
+    pub kind: CompletionKind,
+}
+
+/// Completion candidates for the cursor position, and the text they replace.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Completions {
+    /// Range a chosen candidate replaces
+    pub range: CursorRange,
+    /// Text already typed before the cursor within the range
+    pub prefix: String,
+    /// Candidates, in the order to offer them
+    pub items: Vec<Completion>,
+}
+
+/// Get completion candidates for the cursor position.
+///
+/// # Arguments
+/// * `message` - The HL7 message being edited
+/// * `cursor` - Cursor offset in UTF-16 code units
+/// * `state` - Application state containing the schema cache
+///
+/// # Returns
+/// * `Some(Completions)` - Candidates for the segment name or cell under the cursor
+/// * `None` - There's nothing to offer here (e.g., a free-text field or the MSH
+///   encoding characters)
+#[tauri::command]
+pub fn get_completions(
+    message: &str,
+    cursor: usize,
+    state: State<'_, AppData>,
+) -> Option<Completions> {
+    completions(message, cursor, &state.schema, &jiff::Zoned::now())
+}
+
+/// Find completion candidates without going through Tauri state.
+fn completions(
+    message: &str,
+    cursor: usize,
+    schema: &SchemaCache,
+    now: &jiff::Zoned,
+) -> Option<Completions> {
+    let offsets = Utf16Offsets::new(message);
+    let cursor = offsets.to_byte(cursor);
+    let version = schema.resolve_version(detect_version(message));
+
+    if let Some(completions) = segment_completions(message, cursor, schema, &version, &offsets) {
+        return Some(completions);
+    }
+
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(message).ok()?;
+    let loc = parsed.locate_cursor(cursor)?;
+    let (segment, _, _) = loc.segment?;
+    let (field_i, field) = loc.field?;
+    if segment == "MSH" && field_i <= 2 {
+        // the separator and encoding characters
+        return None;
+    }
+
+    // the innermost cell holding the cursor, and which component it is
+    let mut range = field.range.clone();
+    let mut component_n = None;
+    let mut in_subcomponent = false;
+    if let Some((_, repeat)) = loc.repeat {
+        range = repeat.range.clone();
+        if let Some((component_i, component)) = loc.component {
+            if repeat.has_components() {
+                range = component.range.clone();
+                component_n = Some(component_i);
+            }
+            if let Some((_, subcomponent)) = loc.sub_component {
+                if component.has_subcomponents() {
+                    range = subcomponent.range.clone();
+                    component_n = Some(component_i);
+                    in_subcomponent = true;
+                }
+            }
+        }
+    }
+    let prefix = message.get(range.start..cursor.clamp(range.start, range.end))?;
+
+    let definitions = schema
+        .get_segment_for_version(segment, &version)
+        .unwrap_or_default();
+    let definition = if in_subcomponent {
+        None
+    } else {
+        find_definition(&definitions, field_i, component_n).or_else(|| {
+            component_n
+                .is_none()
+                .then(|| find_definition(&definitions, field_i, Some(1)))
+                .flatten()
+        })
+    };
+
+    let mut items = definition
+        .and_then(|definition| definition.values.as_ref())
+        .map(|values| value_completions(values, prefix))
+        .unwrap_or_default();
+
+    let spec_version = spec_version(&version);
+    let datatype = definition.and_then(|definition| definition.datatype);
+    let is_timestamp = match (component_n, in_subcomponent) {
+        (_, true) => false,
+        (Some(component), false) => {
+            is_component_a_timestamp(spec_version, segment, field_i, component)
+        }
+        (None, false) => is_field_a_timestamp(spec_version, segment, field_i),
+    };
+    if datatype.is_some() || is_timestamp {
+        items.extend(timestamp_completions(
+            now,
+            datatype.unwrap_or(DataType::DateTime),
+        ));
+    }
+
+    if items.is_empty() {
+        return None;
+    }
+    Some(Completions {
+        range: offsets.cursor_range(&range),
+        prefix: prefix.to_string(),
+        items,
+    })
+}
+
+/// Segment names for a cursor at the start of a line, if it's there.
+fn segment_completions(
+    message: &str,
+    cursor: usize,
+    schema: &SchemaCache,
+    version: &str,
+    offsets: &Utf16Offsets,
+) -> Option<Completions> {
+    let before = message.get(..cursor)?;
+    let line_start = before.rfind(['\r', '\n']).map_or(0, |i| i + 1);
+    let prefix = before.get(line_start..)?;
+    if prefix.len() > 3 || !prefix.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return None;
+    }
+
+    // the segment name runs to the first field separator or the end of the line
+    let rest = message.get(line_start..)?;
+    let name_len = rest
+        .find(|c: char| !c.is_ascii_alphanumeric())
+        .unwrap_or(rest.len());
+    let range = line_start..line_start + name_len;
+
+    let spec_version = spec_version(version);
+    let typed = prefix.to_ascii_uppercase();
+    let mut names: Vec<String> = schema
+        .get_messages()
+        .segments
+        .into_keys()
+        .filter(|name| name.starts_with(&typed))
+        .filter(|name| name != "MSH" || line_start == 0)
+        .collect();
+    names.sort();
+
+    let items: Vec<Completion> = names
+        .into_iter()
+        .map(|name| Completion {
+            detail: known_segment_description(spec_version, &name).or_else(|| {
+                schema
+                    .custom_segment(&name)
+                    .map(|custom| custom.description)
+            }),
+            value: name,
+            kind: CompletionKind::Segment,
+        })
+        .collect();
+    if items.is_empty() {
+        return None;
+    }
+    Some(Completions {
+        range: offsets.cursor_range(&range),
+        prefix: prefix.to_string(),
+        items,
+    })
+}
+
+/// Allowed values matching what's been typed, sorted by code.
+///
+/// Template placeholders such as `{auto}` aren't values, so they're left out.
+fn value_completions(
+    values: &std::collections::HashMap<String, String>,
+    prefix: &str,
+) -> Vec<Completion> {
+    let typed = prefix.to_lowercase();
+    let mut items: Vec<Completion> = values
+        .iter()
+        .filter(|(code, _)| !(code.starts_with('{') && code.ends_with('}')))
+        .filter(|(code, description)| {
+            code.to_lowercase().starts_with(&typed) || description.to_lowercase().contains(&typed)
+        })
+        .map(|(code, description)| Completion {
+            value: code.clone(),
+            detail: Some(description.clone()),
+            kind: CompletionKind::Value,
+        })
+        .collect();
+    items.sort_by(|a, b| a.value.cmp(&b.value));
+    items
+}
+
+/// Common dates or timestamps around the current time.
+fn timestamp_completions(now: &jiff::Zoned, datatype: DataType) -> Vec<Completion> {
+    let today = now.date();
+    let date = |date: jiff::civil::Date| date.strftime("%Y%m%d").to_string();
+    let timestamp = |value: String, detail: &str| Completion {
+        value,
+        detail: Some(detail.to_string()),
+        kind: CompletionKind::Timestamp,
+    };
+
+    let mut items = Vec::new();
+    match datatype {
+        DataType::Date => {
+            items.push(timestamp(date(today), "Today"));
+            if let Ok(yesterday) = today.yesterday() {
+                items.push(timestamp(date(yesterday), "Yesterday"));
+            }
+            if let Ok(tomorrow) = today.tomorrow() {
+                items.push(timestamp(date(tomorrow), "Tomorrow"));
+            }
+        }
+        DataType::DateTime => {
+            items.push(timestamp(format_hl7_timestamp(now, false), "Now"));
+            items.push(timestamp(
+                format_hl7_timestamp(now, true),
+                "Now, with UTC offset",
+            ));
+            items.push(timestamp(
+                format!("{}000000", date(today)),
+                "Today, midnight",
+            ));
+            items.push(timestamp(date(today), "Today"));
+        }
+    }
+    items
+}
+
+/// Schema definition of a field or one of its components.
+fn find_definition(
+    definitions: &[Field],
+    field: usize,
+    component: Option<usize>,
+) -> Option<&Field> {
+    definitions.iter().find(|definition| {
+        usize::from(definition.field) == field && definition.component.map(usize::from) == component
+    })
+}
+
+/// The version to look up in the spec, falling back to the default for versions
+/// the spec doesn't have.
+fn spec_version(version: &str) -> &str {
+    if is_valid_version(version) {
+        version
+    } else {
+        DEFAULT_VERSION
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str =
+        "MSH|^~\\&|APP|FAC|||20250101||ADT^A01|1|P|2.5.1\rPID|1||123||Doe^Jane||19800101|F";
+
+    fn now() -> jiff::Zoned {
+        "2025-03-04T05:06:07-05:00[America/New_York]"
+            .parse()
+            .unwrap()
+    }
+
+    fn utf16(text: &str) -> usize {
+        text.encode_utf16().count()
+    }
+
+    #[test]
+    fn offers_segment_names_at_line_start() {
+        let schema = SchemaCache::new().unwrap();
+        let message = format!("{MESSAGE}\rP");
+        let completions = completions(&message, utf16(&message), &schema, &now()).unwrap();
+
+        assert_eq!(completions.prefix, "P");
+        let names: Vec<&str> = completions
+            .items
+            .iter()
+            .map(|item| item.value.as_str())
+            .collect();
+        assert!(names.contains(&"PID"));
+        assert!(names.contains(&"PV1"));
+        assert!(names.iter().all(|name| name.starts_with('P')));
+        assert!(completions
+            .items
+            .iter()
+            .all(|item| item.kind == CompletionKind::Segment));
+        assert_eq!(
+            completions.range.start,
+            utf16(&message) - 1,
+            "the typed name is replaced"
+        );
+    }
+
+    #[test]
+    fn offers_allowed_values_of_the_field() {
+        let schema = SchemaCache::new().unwrap();
+        // just after the "F" in PID.8
+        let cursor = utf16(MESSAGE);
+        let completions = completions(MESSAGE, cursor, &schema, &now()).unwrap();
+
+        assert_eq!(completions.prefix, "F");
+        assert_eq!(completions.items[0].value, "F");
+        assert_eq!(completions.items[0].detail.as_deref(), Some("Female"));
+        assert!(completions
+            .items
+            .iter()
+            .all(|item| item.kind == CompletionKind::Value));
+    }
+
+    #[test]
+    fn offers_dates_in_date_fields() {
+        let schema = SchemaCache::new().unwrap();
+        // in PID.7
+        let cursor = utf16(MESSAGE.split("19800101").next().unwrap()) + 1;
+        let completions = completions(MESSAGE, cursor, &schema, &now()).unwrap();
+
+        assert_eq!(completions.prefix, "1");
+        assert!(completions.items.iter().any(
+            |item| item.kind == CompletionKind::Timestamp && item.value.starts_with("20250304")
+        ));
+    }
+
+    #[test]
+    fn offers_nothing_for_free_text_or_encoding_characters() {
+        let schema = SchemaCache::new().unwrap();
+        assert!(completions(MESSAGE, 5, &schema, &now()).is_none());
+
+        // PID.3 is an identifier
+        let cursor = utf16(MESSAGE.split("123").next().unwrap()) + 1;
+        assert!(completions(MESSAGE, cursor, &schema, &now()).is_none());
+    }
+}
@@ -24,9 +24,34 @@
 //! The `get_range_of_next_field` and `get_range_of_previous_field` commands support
 //! Tab/Shift-Tab navigation by finding the next/previous "cell" in the message.
 //! A "cell" is the smallest navigable unit: a field, component, or subcomponent.
+//!
+//! Both take optional `NavigationOptions` to:
+//! * skip empty cells
+//! * wrap around at the end (or start) of the message
+//! * move by component, field, or segment instead of the smallest cell
+//! * jump to the same field in the next (or previous) occurrence of the segment,
+//!   e.g. from OBX-5 in one OBX to OBX-5 in the next
+//!
+//! # Breadcrumbs
+//! Along with the numbers, `locate_cursor` names each level of the location
+//! (e.g. "Patient Identification > Patient Name > Family Name") from the
+//! schema and the HL7 specification, and describes the innermost one, so the
+//! status bar can show all of it from a single call per cursor move.
 
+use hl7_parser::message::Segment;
 use serde::{Deserialize, Serialize};
 use std::ops::Range;
+use tauri::State;
+
+use super::offsets::Utf16Offsets;
+use crate::commands::{spec_version, std_description};
+use crate::schema::cache::SchemaCache;
+use crate::schema::version::detect_version;
+use crate::spec::std_spec::{
+    component_descriptions, field_descriptions, known_segment_description,
+    subcomponent_descriptions,
+};
+use crate::AppData;
 
 /// Structured representation of a cursor's position within an HL7 message.
 ///
@@ -47,6 +72,40 @@ pub struct CursorLocation {
     component: Option<usize>,
     /// Subcomponent number within the component (1-based, matching HL7 notation)
     subcomponent: Option<usize>,
+    /// Each level of the location, from the segment down, with its name
+    breadcrumb: Vec<BreadcrumbPart>,
+    /// Description of the innermost level from the HL7 specification (see
+    /// `get_std_description`)
+    description: Option<String>,
+}
+
+/// A level of the hierarchy in a cursor's breadcrumb.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BreadcrumbLevel {
+    /// The segment, labelled by its identifier
+    Segment,
+    /// A field within the segment
+    Field,
+    /// A repeat of a repeating field
+    Repeat,
+    /// A component within the field
+    Component,
+    /// A subcomponent within the component
+    Subcomponent,
+}
+
+/// One level of a cursor's breadcrumb.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BreadcrumbPart {
+    /// Which level this is
+    level: BreadcrumbLevel,
+    /// The segment identifier, or the number of the field, repeat, component,
+    /// or subcomponent (all 1-based)
+    label: String,
+    /// What the level is called (e.g. "Patient Identification" for PID,
+    /// "Patient Name" for PID.5), if known; repeats have no name
+    name: Option<String>,
 }
 
 /// Determine the HL7 structural location of a cursor position.
@@ -66,15 +125,33 @@ pub struct CursorLocation {
 /// contains components/subcomponents. For simple fields with no delimiters, only
 /// the field number is reported. This prevents confusion in the UI.
 ///
+/// # Names
+/// The breadcrumb names fields and components from the schema where it has
+/// them (so user overrides and custom segments show up), falling back to the
+/// specification for the message's version.
+///
 /// # Arguments
 /// * `message` - The HL7 message as a string
-/// * `cursor` - Character offset (0-based) within the message
+/// * `cursor` - Offset (0-based, in UTF-16 code units) within the message
+/// * `state` - Application state containing the schema cache
 ///
 /// # Returns
 /// * `Some(CursorLocation)` - Structured location if cursor is within the message
 /// * `None` - If message parsing fails or cursor is out of bounds
 #[tauri::command]
-pub fn locate_cursor(message: &str, cursor: usize) -> Option<CursorLocation> {
+pub fn locate_cursor(
+    message: &str,
+    cursor: usize,
+    state: State<'_, AppData>,
+) -> Option<CursorLocation> {
+    let mut location = locate(message, cursor)?;
+    describe_location(&mut location, message, &state.schema);
+    Some(location)
+}
+
+/// Find the structural location of a cursor, without names or descriptions.
+fn locate(message: &str, cursor: usize) -> Option<CursorLocation> {
+    let cursor = Utf16Offsets::new(message).to_byte(cursor);
     let message = hl7_parser::parse_message_with_lenient_newlines(message).ok()?;
 
     message.locate_cursor(cursor).map(|loc| {
@@ -108,10 +185,71 @@ pub fn locate_cursor(message: &str, cursor: usize) -> Option<CursorLocation> {
     })
 }
 
+/// Fill in the breadcrumb and description of a location.
+fn describe_location(location: &mut CursorLocation, message: &str, schema: &SchemaCache) {
+    let Some(segment) = location.segment.as_deref() else {
+        return;
+    };
+    let schema_version = schema.resolve_version(detect_version(message));
+    let version = spec_version(&schema_version);
+    let definitions = schema
+        .get_segment_for_version(segment, &schema_version)
+        .unwrap_or_default();
+    let schema_name = |field: usize, component: Option<usize>| {
+        definitions
+            .iter()
+            .find(|f| usize::from(f.field) == field && f.component.map(usize::from) == component)
+            .map(|f| f.name.clone())
+    };
+    let nth = |names: Vec<String>, n: usize| names.into_iter().nth(n.checked_sub(1)?);
+    let part = |level: BreadcrumbLevel, number: usize, name: Option<String>| BreadcrumbPart {
+        level,
+        label: number.to_string(),
+        name,
+    };
+
+    let mut breadcrumb = vec![BreadcrumbPart {
+        level: BreadcrumbLevel::Segment,
+        label: segment.to_string(),
+        name: known_segment_description(version, segment)
+            .or_else(|| schema.custom_segment(segment).map(|c| c.description)),
+    }];
+    if let Some(field) = location.field {
+        let name =
+            schema_name(field, None).or_else(|| nth(field_descriptions(version, segment), field));
+        breadcrumb.push(part(BreadcrumbLevel::Field, field, name));
+        if let Some(repeat) = location.repeat {
+            breadcrumb.push(part(BreadcrumbLevel::Repeat, repeat + 1, None));
+        }
+        if let Some(component) = location.component {
+            let name = schema_name(field, Some(component))
+                .or_else(|| nth(component_descriptions(version, segment, field), component));
+            breadcrumb.push(part(BreadcrumbLevel::Component, component, name));
+            if let Some(subcomponent) = location.subcomponent {
+                let names = subcomponent_descriptions(version, segment, field, component);
+                let name = nth(names, subcomponent);
+                breadcrumb.push(part(BreadcrumbLevel::Subcomponent, subcomponent, name));
+            }
+        }
+    }
+
+    location.description = Some(std_description(
+        schema,
+        segment,
+        location.field,
+        location.component,
+        location.subcomponent,
+        Some(message),
+    ));
+    location.breadcrumb = breadcrumb;
+}
+
 /// Character range within the message (start/end offsets).
 ///
 /// Used to communicate field boundaries to the frontend for navigation and selection.
-#[derive(Serialize, Deserialize, Debug)]
+/// Ranges crossing to the frontend are in UTF-16 code units, matching the
+/// textarea's selection; see [`Utf16Offsets`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct CursorRange {
     /// Starting character offset (inclusive)
     pub start: usize,
@@ -119,6 +257,41 @@ pub struct CursorRange {
     pub end: usize,
 }
 
+/// How far a single navigation step moves.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum NavigationGranularity {
+    /// The smallest part that exists: field, repeat, component, or subcomponent
+    #[default]
+    Cell,
+    /// Components, treating subcomponents as part of their component
+    Component,
+    /// Whole fields, including all of their repeats
+    Field,
+    /// Whole segments
+    Segment,
+}
+
+/// Options for field navigation.
+///
+/// Every option is off by default, which gives plain Tab/Shift-Tab behaviour.
+#[derive(Deserialize, Debug, Default, Clone, Copy)]
+pub struct NavigationOptions {
+    /// Skip cells with no content
+    #[serde(default)]
+    pub skip_empty: bool,
+    /// Continue from the other end of the message instead of stopping
+    #[serde(default)]
+    pub wrap: bool,
+    /// How far a single step moves
+    #[serde(default)]
+    pub granularity: NavigationGranularity,
+    /// Move to the same field (component, ...) in the next occurrence of the
+    /// current segment rather than to the adjacent cell
+    #[serde(default)]
+    pub same_path: bool,
+}
+
 /// Flatten an HL7 message into a linear sequence of navigable "cells".
 ///
 /// This function traverses the hierarchical message structure and extracts the character
@@ -134,7 +307,8 @@ pub struct CursorRange {
 /// * If a component has subcomponents, each subcomponent is a cell
 ///
 /// This ensures navigation stops at the most specific level that actually exists,
-/// rather than jumping over fine-grained structure.
+/// rather than jumping over fine-grained structure. Coarser granularities stop
+/// descending at components, fields, or segments.
 ///
 /// # Why Flattening?
 /// The hierarchical structure is perfect for parsing and understanding, but navigation
@@ -143,12 +317,21 @@ pub struct CursorRange {
 ///
 /// # Arguments
 /// * `message` - Parsed HL7 message
+/// * `granularity` - Deepest level to descend to
 ///
 /// # Returns
 /// Vector of character ranges in document order, representing navigable cells.
-fn flatten_message(message: &hl7_parser::Message) -> Vec<Range<usize>> {
+fn flatten_message(
+    message: &hl7_parser::Message,
+    granularity: NavigationGranularity,
+) -> Vec<Range<usize>> {
     let mut cells = Vec::new();
     for segment in message.segments() {
+        if granularity == NavigationGranularity::Segment {
+            cells.push(segment.range.clone());
+            continue;
+        }
+
         // include segment name as a navigable cell
         cells.push(Range {
             start: segment.range.start,
@@ -160,7 +343,7 @@ fn flatten_message(message: &hl7_parser::Message) -> Vec<Range<usize>> {
         }
 
         for field in segment.fields() {
-            if field.repeats.is_empty() {
+            if field.repeats.is_empty() || granularity == NavigationGranularity::Field {
                 cells.push(field.range.clone());
                 continue;
             }
@@ -172,7 +355,9 @@ fn flatten_message(message: &hl7_parser::Message) -> Vec<Range<usize>> {
                 }
 
                 for component in repeat.components.iter() {
-                    if component.subcomponents.is_empty() {
+                    if component.subcomponents.is_empty()
+                        || granularity == NavigationGranularity::Component
+                    {
                         cells.push(component.range.clone());
                         continue;
                     }
@@ -197,6 +382,10 @@ fn flatten_message(message: &hl7_parser::Message) -> Vec<Range<usize>> {
 /// * If the cursor is at the end of the message, returns None
 /// * If the cursor is between cells, returns the next cell
 ///
+/// With `options`, empty cells can be skipped, navigation can wrap to the start
+/// of the message, and steps can be by component, field, or segment, or to the
+/// same field in the next occurrence of the segment (see `NavigationOptions`).
+///
 /// # Frontend Integration
 /// The frontend uses the returned range to:
 /// 1. Move the cursor to the start of the next field
@@ -205,27 +394,19 @@ fn flatten_message(message: &hl7_parser::Message) -> Vec<Range<usize>> {
 ///
 /// # Arguments
 /// * `message` - The HL7 message as a string
-/// * `cursor` - Current cursor position (character offset)
+/// * `cursor` - Current cursor position (UTF-16 offset)
+/// * `options` - Navigation options; plain Tab behaviour if omitted
 ///
 /// # Returns
 /// * `Some(CursorRange)` - Range of the next field
-/// * `None` - If at end of message or parsing fails
+/// * `None` - If at end of message (without wrapping), no cell qualifies, or parsing fails
 #[tauri::command]
-pub fn get_range_of_next_field(message: &str, cursor: usize) -> Option<CursorRange> {
-    let message = hl7_parser::parse_message_with_lenient_newlines(message).ok()?;
-    let cells = flatten_message(&message);
-
-    let mut cells_iter = cells.iter();
-    while let Some(cell) = cells_iter.next() {
-        if cursor >= cell.start && cursor <= cell.end {
-            return cells_iter.next().map(|next_cell| CursorRange {
-                start: next_cell.start,
-                end: next_cell.end,
-            });
-        }
-    }
-
-    None
+pub fn get_range_of_next_field(
+    message: &str,
+    cursor: usize,
+    options: Option<NavigationOptions>,
+) -> Option<CursorRange> {
+    navigate(message, cursor, options.unwrap_or_default(), true)
 }
 
 /// Get the character range of the previous navigable field before the cursor.
@@ -238,33 +419,183 @@ pub fn get_range_of_next_field(message: &str, cursor: usize) -> Option<CursorRan
 /// * If the cursor is at the start of the message, returns None
 /// * If the cursor is between cells, returns the previous cell
 ///
-/// # Implementation Note
-/// Uses a reverse iterator over the flattened cells to efficiently find the
-/// previous cell without building a reversed copy of the entire vector.
+/// Takes the same `options` as `get_range_of_next_field`, wrapping to the end of
+/// the message when `wrap` is set.
 ///
 /// # Arguments
 /// * `message` - The HL7 message as a string
-/// * `cursor` - Current cursor position (character offset)
+/// * `cursor` - Current cursor position (UTF-16 offset)
+/// * `options` - Navigation options; plain Shift-Tab behaviour if omitted
 ///
 /// # Returns
 /// * `Some(CursorRange)` - Range of the previous field
-/// * `None` - If at start of message or parsing fails
+/// * `None` - If at start of message (without wrapping), no cell qualifies, or parsing fails
 #[tauri::command]
-pub fn get_range_of_previous_field(message: &str, cursor: usize) -> Option<CursorRange> {
+pub fn get_range_of_previous_field(
+    message: &str,
+    cursor: usize,
+    options: Option<NavigationOptions>,
+) -> Option<CursorRange> {
+    navigate(message, cursor, options.unwrap_or_default(), false)
+}
+
+/// Shared implementation of next/previous field navigation.
+///
+/// When the cursor touches two cells (an empty cell sits right against its
+/// neighbour), moving forward starts from the first of them and moving
+/// backward from the last, so a step never lands back where it started.
+fn navigate(
+    message: &str,
+    cursor: usize,
+    options: NavigationOptions,
+    forward: bool,
+) -> Option<CursorRange> {
+    let offsets = Utf16Offsets::new(message);
+    let cursor = offsets.to_byte(cursor);
     let message = hl7_parser::parse_message_with_lenient_newlines(message).ok()?;
-    let cells = flatten_message(&message);
-
-    let mut cells_iter = cells.iter().rev();
-    while let Some(cell) = cells_iter.next() {
-        if cursor >= cell.start && cursor <= cell.end {
-            return cells_iter.next().map(|prev_cell| CursorRange {
-                start: prev_cell.start,
-                end: prev_cell.end,
-            });
+    if options.same_path {
+        return navigate_same_path(&message, cursor, options, forward)
+            .map(|range| offsets.cursor_range(&range));
+    }
+
+    let cells = flatten_message(&message, options.granularity);
+    let contains_cursor = |cell: &Range<usize>| cursor >= cell.start && cursor <= cell.end;
+    let current = if forward {
+        cells.iter().position(contains_cursor)?
+    } else {
+        cells.iter().rposition(contains_cursor)?
+    };
+
+    step_indices(cells.len(), current, forward, options.wrap)
+        .filter_map(|index| cells.get(index))
+        .find(|cell| !options.skip_empty || !cell.is_empty())
+        .map(|cell| offsets.cursor_range(cell))
+}
+
+/// Indices visited when stepping away from `current`, in order.
+///
+/// Without wrapping this stops at the end (or start) of the list; with wrapping
+/// it carries on from the other end, stopping before `current` comes round again.
+fn step_indices(
+    len: usize,
+    current: usize,
+    forward: bool,
+    wrap: bool,
+) -> impl Iterator<Item = usize> {
+    let steps = match (wrap, forward) {
+        (true, _) => len.saturating_sub(1),
+        (false, true) => len.saturating_sub(current + 1),
+        (false, false) => current,
+    };
+    (1..=steps).map(move |step| {
+        if forward {
+            (current + step) % len
+        } else {
+            (current + len - step) % len
         }
+    })
+}
+
+/// Move to the same field in another occurrence of the cursor's segment.
+///
+/// The cursor's position is recorded as indices from the field down (field,
+/// repeat, component, subcomponent) and followed in each other occurrence of the
+/// segment. Where an occurrence has less structure (e.g. no components), the
+/// deepest part that exists is used; occurrences too short to have the field at
+/// all are passed over.
+fn navigate_same_path(
+    message: &hl7_parser::Message,
+    cursor: usize,
+    options: NavigationOptions,
+    forward: bool,
+) -> Option<Range<usize>> {
+    let current_segment = message
+        .segments()
+        .find(|segment| cursor >= segment.range.start && cursor <= segment.range.end)?;
+    let path = cell_path(current_segment, cursor);
+
+    let occurrences: Vec<&Segment> = message
+        .segments()
+        .filter(|segment| segment.name == current_segment.name)
+        .collect();
+    let current = occurrences
+        .iter()
+        .position(|segment| segment.range == current_segment.range)?;
+
+    step_indices(occurrences.len(), current, forward, options.wrap)
+        .filter_map(|index| occurrences.get(index))
+        .filter_map(|segment| range_at_path(segment, &path))
+        .find(|range| !options.skip_empty || !range.is_empty())
+}
+
+/// Indices (0-based) of the field, repeat, component and subcomponent holding
+/// the cursor, as deep as the structure goes. Empty on the segment name.
+fn cell_path(segment: &Segment, cursor: usize) -> Vec<usize> {
+    let contains = |range: &Range<usize>| cursor >= range.start && cursor <= range.end;
+    let mut path = Vec::new();
+
+    let Some((field_idx, field)) = segment
+        .fields
+        .iter()
+        .enumerate()
+        .find(|(_, field)| contains(&field.range))
+    else {
+        return path;
+    };
+    path.push(field_idx);
+
+    let Some((repeat_idx, repeat)) = field
+        .repeats
+        .iter()
+        .enumerate()
+        .find(|(_, repeat)| contains(&repeat.range))
+    else {
+        return path;
+    };
+    path.push(repeat_idx);
+
+    let Some((component_idx, component)) = repeat
+        .components
+        .iter()
+        .enumerate()
+        .find(|(_, component)| contains(&component.range))
+    else {
+        return path;
+    };
+    path.push(component_idx);
+
+    if let Some(subcomponent_idx) = component
+        .subcomponents
+        .iter()
+        .position(|subcomponent| contains(&subcomponent.range))
+    {
+        path.push(subcomponent_idx);
     }
+    path
+}
 
-    None
+/// Range of the part of a segment at `path` (see `cell_path`), falling back to
+/// the deepest part that exists. `None` if the segment lacks the field itself.
+fn range_at_path(segment: &Segment, path: &[usize]) -> Option<Range<usize>> {
+    let Some((&field_idx, rest)) = path.split_first() else {
+        return Some(segment.range.start..segment.range.start + segment.name.len());
+    };
+    let field = segment.fields.get(field_idx)?;
+
+    let mut indices = rest.iter();
+    let Some(repeat) = indices.next().and_then(|&idx| field.repeats.get(idx)) else {
+        return Some(field.range.clone());
+    };
+    let Some(component) = indices.next().and_then(|&idx| repeat.components.get(idx)) else {
+        return Some(repeat.range.clone());
+    };
+    let Some(subcomponent) = indices
+        .next()
+        .and_then(|&idx| component.subcomponents.get(idx))
+    else {
+        return Some(component.range.clone());
+    };
+    Some(subcomponent.range.clone())
 }
 
 #[cfg(test)]
@@ -276,7 +607,7 @@ mod tests {
     fn can_get_range_of_next_field_in_component_next_component() {
         let message = r#"MSH|^~\&|a^b"#;
         let cursor = 9;
-        let range = get_range_of_next_field(message, cursor).expect("range exists");
+        let range = get_range_of_next_field(message, cursor, None).expect("range exists");
         assert_eq!(range.start, 11);
         assert_eq!(range.end, 12);
     }
@@ -285,7 +616,7 @@ mod tests {
     fn can_get_range_of_next_field_in_component_next_repeat_component() {
         let message = r#"MSH|^~\&|a~b^c"#;
         let cursor = 9;
-        let range = get_range_of_next_field(message, cursor).expect("range exists");
+        let range = get_range_of_next_field(message, cursor, None).expect("range exists");
         assert_eq!(range.start, 11);
         assert_eq!(range.end, 12);
     }
@@ -294,7 +625,7 @@ mod tests {
     fn can_get_range_of_next_field_in_component_next_repeat() {
         let message = r#"MSH|^~\&|a~bc"#;
         let cursor = 9;
-        let range = get_range_of_next_field(message, cursor).expect("range exists");
+        let range = get_range_of_next_field(message, cursor, None).expect("range exists");
         assert_eq!(range.start, 11);
         assert_eq!(range.end, 13);
     }
@@ -303,7 +634,7 @@ mod tests {
     fn can_get_range_of_next_field_in_field_next_component() {
         let message = r#"MSH|^~\&|a|b^c"#;
         let cursor = 9;
-        let range = get_range_of_next_field(message, cursor).expect("range exists");
+        let range = get_range_of_next_field(message, cursor, None).expect("range exists");
         assert_eq!(range.start, 11);
         assert_eq!(range.end, 12);
     }
@@ -312,8 +643,139 @@ mod tests {
     fn can_get_range_of_next_field_in_field_next_field() {
         let message = r#"MSH|^~\&|a|bc"#;
         let cursor = 9;
-        let range = get_range_of_next_field(message, cursor).expect("range exists");
+        let range = get_range_of_next_field(message, cursor, None).expect("range exists");
         assert_eq!(range.start, 11);
         assert_eq!(range.end, 13);
     }
+
+    fn options(granularity: NavigationGranularity) -> Option<NavigationOptions> {
+        Some(NavigationOptions {
+            granularity,
+            ..NavigationOptions::default()
+        })
+    }
+
+    #[test]
+    fn can_skip_empty_fields() {
+        let message = r#"MSH|^~\&|a|||b"#;
+        let skip = Some(NavigationOptions {
+            skip_empty: true,
+            ..NavigationOptions::default()
+        });
+        let range = get_range_of_next_field(message, 9, skip).expect("range exists");
+        assert_eq!((range.start, range.end), (13, 14));
+        let range = get_range_of_previous_field(message, 13, skip).expect("range exists");
+        assert_eq!((range.start, range.end), (9, 10));
+    }
+
+    #[test]
+    fn wraps_only_when_asked() {
+        let message = "MSH|^~\\&|a\rPID|1";
+        let cursor = message.len();
+        assert!(get_range_of_next_field(message, cursor, None).is_none());
+
+        let wrap = Some(NavigationOptions {
+            wrap: true,
+            ..NavigationOptions::default()
+        });
+        let range = get_range_of_next_field(message, cursor, wrap).expect("range exists");
+        assert_eq!((range.start, range.end), (0, 3));
+        let range = get_range_of_previous_field(message, 0, wrap).expect("range exists");
+        assert_eq!(range.end, message.len());
+    }
+
+    #[test]
+    fn moves_by_field_and_segment() {
+        let message = "MSH|^~\\&|a^b|c\rPID|1";
+        let range = get_range_of_next_field(message, 9, options(NavigationGranularity::Field))
+            .expect("range exists");
+        assert_eq!((range.start, range.end), (13, 14));
+
+        let range = get_range_of_next_field(message, 9, options(NavigationGranularity::Segment))
+            .expect("range exists");
+        assert_eq!(&message[range.start..range.end], "PID|1");
+    }
+
+    #[test]
+    fn jumps_to_same_field_in_next_occurrence() {
+        let message = "MSH|^~\\&\rOBX|1|NM|GLU^Glucose\rNTE|1\rOBX|2|NM|HB^Haemoglobin";
+        let same_path = Some(NavigationOptions {
+            same_path: true,
+            ..NavigationOptions::default()
+        });
+        let cursor = message.find("Glucose").unwrap();
+        let range = get_range_of_next_field(message, cursor, same_path).expect("range exists");
+        assert_eq!(&message[range.start..range.end], "Haemoglobin");
+
+        // no later OBX, and wrapping is off
+        let cursor = message.find("Haemoglobin").unwrap();
+        assert!(get_range_of_next_field(message, cursor, same_path).is_none());
+        let range = get_range_of_previous_field(message, cursor, same_path).expect("range exists");
+        assert_eq!(&message[range.start..range.end], "Glucose");
+    }
+
+    #[test]
+    fn offsets_are_utf16_code_units() {
+        let message = "MSH|^~\\&\rPID|1||MÜLLER^JOSÉ|F";
+        let utf16: Vec<u16> = message.encode_utf16().collect();
+        let text = |range: CursorRange| String::from_utf16(&utf16[range.start..range.end]).unwrap();
+
+        // the cursor after the accented name is in the next field, not drifted into it
+        let last = utf16.len() - 1;
+        let location = locate(message, last).unwrap();
+        assert_eq!(location.field, Some(4));
+
+        let name = message.find("MÜLLER").unwrap();
+        let range = get_range_of_next_field(message, name, None).expect("range exists");
+        assert_eq!(text(range.clone()), "JOSÉ");
+        let range = get_range_of_next_field(message, range.start, None).expect("range exists");
+        assert_eq!(text(range), "F");
+    }
+
+    #[test]
+    fn breadcrumb_names_each_level() {
+        let schema = SchemaCache::new().unwrap();
+        let message = "MSH|^~\\&|APP|FAC|||20250101||ADT^A01|1|P|2.5.1\rPID|1||123~456^^^GH&1.2&ISO||Doe^Jane";
+        let describe = |needle: &str| {
+            let cursor = message.find(needle).unwrap();
+            let mut location = locate(message, cursor).unwrap();
+            describe_location(&mut location, message, &schema);
+            location
+        };
+
+        let location = describe("Jane");
+        let labels: Vec<(BreadcrumbLevel, &str)> = location
+            .breadcrumb
+            .iter()
+            .map(|part| (part.level, part.label.as_str()))
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                (BreadcrumbLevel::Segment, "PID"),
+                (BreadcrumbLevel::Field, "5"),
+                (BreadcrumbLevel::Component, "2"),
+            ]
+        );
+        assert!(location.breadcrumb.iter().all(|part| part.name.is_some()));
+        assert!(location.description.is_some());
+
+        // repeats are counted from 1 and have no name
+        let location = describe("1.2");
+        let levels: Vec<BreadcrumbLevel> =
+            location.breadcrumb.iter().map(|part| part.level).collect();
+        assert_eq!(
+            levels,
+            vec![
+                BreadcrumbLevel::Segment,
+                BreadcrumbLevel::Field,
+                BreadcrumbLevel::Repeat,
+                BreadcrumbLevel::Component,
+                BreadcrumbLevel::Subcomponent,
+            ]
+        );
+        assert_eq!(location.breadcrumb[2].label, "2");
+        assert_eq!(location.breadcrumb[2].name, None);
+        assert_eq!(location.breadcrumb[4].label, "2");
+    }
 }
@@ -15,27 +15,51 @@
 //! * `PID.3` - Patient ID field
 //! * `PID.5.1` - Patient name, last name component
 //! * `MSH.9.1` - Message type code
+//!
+//! # Field Repeats
+//! Fields can repeat (e.g. several patient identifiers in PID.3, separated by
+//! `~`). The first repeat always uses the plain field ID; later repeats carry the
+//! repeat number, as in the query syntax: `PID.3[2]`, `PID.5[2].1`. The number of
+//! repeats of each repeating field travels alongside the values in
+//! `SegmentData::repeats`, so the frontend can add and remove repeats.
+//!
+//! # Batch Files
+//! Batch-oriented systems exchange messages wrapped in envelope segments: BHS/BTS
+//! around each batch, and optionally FHS/FTS around the whole file. The trailers
+//! carry counts (BTS.1 messages in the batch, FTS.1 batches in the file) that
+//! receivers check. `wrap_batch` builds the envelope and `unwrap_batch` removes
+//! it, reporting any envelope or count problems.
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 use color_eyre::eyre::Context;
-use hl7_parser::builder::{FieldBuilder, MessageBuilder, SegmentBuilder};
+use hl7_parser::builder::{FieldBuilder, MessageBuilder, RepeatBuilder, SegmentBuilder};
 use hl7_parser::datetime::{parse_date, parse_timestamp};
+use hl7_parser::Message;
 use rand::distr::{Alphanumeric, SampleString};
 use serde::{Deserialize, Serialize};
 use tauri::State;
 
 use super::cursor::CursorRange;
+use super::formula::evaluate_formulas;
+use super::offsets::Utf16Offsets;
+use super::placeholders::expand_placeholders;
+use crate::schema::message::{MessagesSchema, SegmentMetadata};
+use crate::schema::version::detect_version;
 use crate::AppData;
 
 /// Segment data extracted from an HL7 message.
 ///
-/// Maps field identifiers (e.g., "PID.3", "PID.5.1") to their values.
+/// Maps field identifiers (e.g., "PID.3", "PID.5.1", "PID.3[2]") to their values.
 /// None values indicate the field exists in the schema but is not populated in the message.
 #[derive(Deserialize, Serialize, Debug, Default)]
 pub struct SegmentData {
     /// Map of field IDs to field values
     fields: HashMap<String, Option<String>>,
+    /// Number of repeats of each repeating field, keyed by field number.
+    /// Fields not listed here have a single repeat.
+    #[serde(default)]
+    repeats: HashMap<u8, usize>,
 }
 
 /// Get the list of segment names in a message.
@@ -116,6 +140,17 @@ pub fn get_message_type(message: &str) -> Option<String> {
 /// fields for editing, not just the fields that happen to be populated. This is
 /// important for message composition workflows.
 ///
+/// # Field Repeats
+/// Each repeat of a repeating field gets its own set of field IDs (`PID.3`,
+/// `PID.3[2]`, ...) and the repeat count is recorded in `SegmentData::repeats`.
+/// The first repeat is then read on its own, rather than as the whole `~`
+/// separated field.
+///
+/// # Versions
+/// The segment's fields are those of the selected HL7 version or, when none is
+/// selected, the version in the message's MSH.12, as for validation and field
+/// descriptions.
+///
 /// # Arguments
 /// * `message` - The HL7 message as a string
 /// * `segment` - Segment identifier (e.g., "PID", "PV1")
@@ -132,38 +167,80 @@ pub fn parse_message_segment(
     _segment_repeat: usize,
     state: State<'_, AppData>,
 ) -> Result<SegmentData, String> {
+    let version = state.schema.resolve_version(detect_version(message));
     let schema = state
         .schema
-        .get_segment(segment)
-        .wrap_err_with(|| format!("Failed to load segment {segment} schema"))
+        .get_segment_for_version(segment, &version)
+        .wrap_err_with(|| format!("Failed to load segment {segment} schema for HL7 {version}"))
         .map_err(|e| format!("{e:#}"))?;
 
     let message = hl7_parser::parse_message_with_lenient_newlines(message)
         .wrap_err_with(|| "Failed to parse message")
         .map_err(|e| format!("{e:#}"))?;
 
-    Ok(SegmentData {
-        fields: schema
-            .into_iter()
-            .map(|field| {
-                let field_id = format!(
-                    "{segment}.{field}{component}",
-                    // segment_repeat = segment_repeat + 1,
-                    field = field.field,
-                    component = if let Some(comp) = field.component {
-                        format!(".{comp}")
-                    } else {
-                        String::new()
-                    }
-                );
-                let field_value = message
-                    .query(&field_id)
-                    .map(|value| message.separators.decode(value.raw_value()).to_string());
+    let mut data = SegmentData::default();
+    for field in schema {
+        let component = field
+            .component
+            .map(|comp| format!(".{comp}"))
+            .unwrap_or_default();
+        let repeat_count = field_repeat_count(&message, segment, field.field.into());
+        if repeat_count > 1 {
+            data.repeats.insert(field.field, repeat_count);
+        }
 
-                (field_id, field_value)
-            })
-            .collect(),
-    })
+        for repeat in 1..=repeat_count {
+            let field_id = repeat_field_id(segment, field.field.into(), repeat, &component);
+            // a repeating field is read one repeat at a time so the first repeat
+            // doesn't pick up the others
+            let query = if repeat_count > 1 {
+                format!("{segment}.{}[{repeat}]{component}", field.field)
+            } else {
+                field_id.clone()
+            };
+            let field_value = message
+                .query(&query)
+                .map(|value| message.separators.decode(value.raw_value()).to_string());
+
+            data.fields.insert(field_id, field_value);
+        }
+    }
+
+    Ok(data)
+}
+
+/// Build the field ID for one repeat of a field.
+///
+/// The first repeat keeps the plain ID (`PID.3`) so non-repeating fields look the
+/// same as always; later repeats carry the repeat number (`PID.3[2]`).
+fn repeat_field_id(segment: &str, field: usize, repeat: usize, component: &str) -> String {
+    if repeat > 1 {
+        format!("{segment}.{field}[{repeat}]{component}")
+    } else {
+        format!("{segment}.{field}{component}")
+    }
+}
+
+/// Whether the segment's fields are edited repeat by repeat.
+///
+/// MSH is left alone: MSH.1 and MSH.2 hold the delimiters themselves, so MSH.2
+/// contains a literal `~` that must never be split into repeats.
+fn edits_repeats(segment: &str) -> bool {
+    segment != "MSH"
+}
+
+/// Count the repeats of a field in the first occurrence of a segment.
+///
+/// Empty and missing fields count as a single repeat.
+fn field_repeat_count(message: &Message, segment: &str, field: usize) -> usize {
+    if !edits_repeats(segment) || field < 1 {
+        return 1;
+    }
+    message
+        .segments()
+        .find(|seg| seg.name == segment)
+        .and_then(|seg| seg.fields.get(field - 1))
+        .map_or(1, |field| field.repeats.len().max(1))
 }
 
 /// Apply modified segment data back to a message.
@@ -180,16 +257,26 @@ pub fn parse_message_segment(
 /// For component updates, if the field doesn't exist, it creates an empty field
 /// first, then sets the component. This ensures components can be set independently.
 ///
+/// # Field Repeats
+/// Each field is rebuilt as `SegmentData::repeats` repeats (one if it isn't
+/// listed): extra repeats in the message are dropped and missing ones are added
+/// empty, before the values for each repeat (`PID.3[2]`, `PID.5[2].1`) are set.
+/// Values are set per repeat rather than on the whole field, so editing one
+/// repeat keeps the others. MSH is always edited as single values, see
+/// `edits_repeats`.
+///
 /// # Segment Creation
-/// If the specified segment doesn't exist in the message, a new segment is appended.
-/// Currently, this doesn't handle segment ordering according to HL7 specifications
-/// (see TODO comment in code).
+/// If the specified segment doesn't exist in the message, a new segment is
+/// created where the message structure in messages.toml puts it (see
+/// `segment_insert_position`), e.g. an EVN added to an ADT^A01 lands between MSH
+/// and PID. It's appended when the message type or segment isn't in the schema.
 ///
 /// # Arguments
 /// * `message` - The HL7 message as a string
 /// * `segment` - Segment identifier to update
 /// * `segment_repeat` - Currently unused; reserved for repeating segment support
 /// * `data` - Segment data with field values to apply
+/// * `state` - Application state containing the schema cache
 ///
 /// # Returns
 /// The modified message as a string. If message parsing fails, returns the original message unchanged.
@@ -199,47 +286,156 @@ pub fn render_message_segment(
     segment: &str,
     _segment_repeat: usize,
     data: SegmentData,
+    state: State<'_, AppData>,
 ) -> String {
-    let Ok(message) = hl7_parser::parse_message_with_lenient_newlines(message) else {
+    render_segment(message, segment, data, &state.schema.get_messages())
+}
+
+/// Apply segment data to a message, see `render_message_segment`.
+fn render_segment(
+    message: &str,
+    segment: &str,
+    data: SegmentData,
+    messages_schema: &MessagesSchema,
+) -> String {
+    let Ok(parsed) = hl7_parser::parse_message_with_lenient_newlines(message) else {
         return message.to_string();
     };
 
-    let mut message: MessageBuilder = (&message).into();
-    // ensure the message has at least `segment_repeat + 1` segments of this type
-    // while message.segment_n(segment, segment_repeat + 1).is_none() {
-    //     message.push_segment(SegmentBuilder::new(segment));
-    // }
-    // let seg = message
-    //     .segment_n_mut(segment, segment_repeat + 1)
-    //     .expect("message has segment");
+    let mut message: MessageBuilder = (&parsed).into();
     if message.segment_named(segment).is_none() {
-        message.push_segment(SegmentBuilder::new(segment));
+        let structure = message_structure(&parsed, messages_schema);
+        let segments = message.segments_mut();
+        let position = structure
+            .and_then(|structure| segment_insert_position(segments, segment, structure))
+            .unwrap_or(segments.len());
+        segments.insert(position, SegmentBuilder::new(segment));
     }
     let seg = message
         .segment_named_mut(segment)
         .expect("message has segment");
 
+    if !edits_repeats(segment) {
+        for (field_id, field_value) in data.fields.into_iter() {
+            let Some(FieldId {
+                field: field_id,
+                component: component_id,
+                ..
+            }) = parse_field_id(&field_id, segment)
+            else {
+                continue;
+            };
+
+            if let Some(component_id) = component_id {
+                if !seg.has_field(field_id) {
+                    seg.set_field(field_id, FieldBuilder::default());
+                }
+                let field = seg.field_mut(field_id).expect("field exists");
+                field.set_component(component_id, field_value.unwrap_or_default());
+            } else {
+                seg.set_field_value(field_id, field_value.unwrap_or_default());
+            }
+        }
+        return message.render_with_newlines().to_string();
+    }
+
+    // group the values by field so each field's repeats are rebuilt together
+    let mut values_by_field: BTreeMap<usize, Vec<(usize, Option<usize>, String)>> = BTreeMap::new();
     for (field_id, field_value) in data.fields.into_iter() {
-        let Some((field_id, component_id)) = parse_field_id(&field_id, segment) else {
+        let Some(FieldId {
+            field,
+            repeat,
+            component,
+        }) = parse_field_id(&field_id, segment)
+        else {
             continue;
         };
+        values_by_field.entry(field).or_default().push((
+            repeat,
+            component,
+            field_value.unwrap_or_default(),
+        ));
+    }
+
+    for (field_id, mut values) in values_by_field {
+        let repeat_count = u8::try_from(field_id)
+            .ok()
+            .and_then(|field| data.repeats.get(&field))
+            .copied()
+            .unwrap_or(1)
+            .max(1);
 
-        if let Some(component_id) = component_id {
-            if !seg.has_field(field_id) {
-                seg.set_field(field_id, FieldBuilder::default());
+        if !seg.has_field(field_id) {
+            seg.set_field(field_id, FieldBuilder::default());
+        }
+        let field = seg.field_mut(field_id).expect("field exists");
+        let mut repeats = match std::mem::take(field) {
+            FieldBuilder::Value(value) => vec![RepeatBuilder::with_value(value)],
+            FieldBuilder::Repeats(repeats) => repeats,
+        };
+        repeats.resize_with(repeat_count, RepeatBuilder::default);
+
+        // whole-repeat values go before component values so they don't wipe them
+        values.sort_by_key(|(repeat, component, _)| (*repeat, *component));
+        for (repeat, component, value) in values {
+            let Some(repeat_builder) = repeats.get_mut(repeat - 1) else {
+                // a value for a repeat that has been removed
+                continue;
+            };
+            match component {
+                Some(component) => repeat_builder.set_component_value(component, &value),
+                None => *repeat_builder = RepeatBuilder::with_value(value),
             }
-            let field = seg.field_mut(field_id).expect("field exists");
-            field.set_component(component_id, field_value.unwrap_or_default());
-        } else {
-            seg.set_field_value(field_id, field_value.unwrap_or_default());
         }
+        *field = FieldBuilder::Repeats(repeats);
     }
 
-    // TODO: rearrange the segments if needed
-
     message.render_with_newlines().to_string()
 }
 
+/// Look up the segment structure for a message's type (MSH.9.1 and MSH.9.2).
+fn message_structure<'s>(
+    message: &Message,
+    messages_schema: &'s MessagesSchema,
+) -> Option<&'s [SegmentMetadata]> {
+    let message_type = message.query("MSH.9.1")?.raw_value();
+    let trigger_event = message.query("MSH.9.2")?.raw_value();
+    let key = format!(
+        "{}_{}",
+        message_type.to_lowercase(),
+        trigger_event.to_lowercase()
+    );
+    messages_schema.message.get(&key).map(Vec::as_slice)
+}
+
+/// Where a new segment belongs according to a message structure.
+///
+/// The segment goes straight after the last existing segment that the structure
+/// places at or before it, so segments the structure doesn't list (e.g.
+/// Z-segments) stay after it. Where a segment appears more than once in the
+/// structure, its first position is used.
+///
+/// # Returns
+/// * `Some(index)` - Index in `segments` to insert at
+/// * `None` - The structure doesn't list the segment
+fn segment_insert_position(
+    segments: &[SegmentBuilder],
+    segment: &str,
+    structure: &[SegmentMetadata],
+) -> Option<usize> {
+    let structure_position = |name: &str| structure.iter().position(|meta| meta.name == name);
+    let target = structure_position(segment)?;
+
+    Some(
+        segments
+            .iter()
+            .rposition(|existing| {
+                structure_position(existing.name()).is_some_and(|position| position <= target)
+            })
+            .map_or(0, |index| index + 1),
+    )
+}
+
 /// Get the character range of a field within an HL7 message by query path.
 ///
 /// This command enables "Jump to Field" functionality in the editor. Given a field
@@ -262,30 +458,40 @@ pub fn render_message_segment(
 /// * `None` - If message parsing fails or field path is invalid/not found
 #[tauri::command]
 pub fn get_field_range(message: &str, field_path: &str) -> Option<CursorRange> {
+    let offsets = Utf16Offsets::new(message);
     let message = hl7_parser::parse_message_with_lenient_newlines(message).ok()?;
     let result = message.query(field_path)?;
-    let range = result.range();
-    Some(CursorRange {
-        start: range.start,
-        end: range.end,
-    })
+    Some(offsets.cursor_range(&result.range()))
+}
+
+/// Field, repeat and component numbers decoded from a field identifier.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct FieldId {
+    /// Field number (1-based)
+    pub(super) field: usize,
+    /// Repeat number (1-based), 1 when the identifier doesn't name one
+    pub(super) repeat: usize,
+    /// Component number (1-based), if the identifier names one
+    pub(super) component: Option<usize>,
 }
 
-/// Parse a field identifier string into segment, field, and component numbers.
+/// Parse a field identifier string into field, repeat, and component numbers.
 ///
-/// This helper validates and decomposes field identifiers like "PID.3" or "PID.5.1"
-/// into their numeric components for use with the HL7 parser.
+/// This helper validates and decomposes field identifiers like "PID.3", "PID.5.1"
+/// or "PID.3[2]" into their numeric components for use with the HL7 parser.
 ///
 /// # Format
-/// Expected format: `SEGMENT.FIELD[.COMPONENT]`
+/// Expected format: `SEGMENT.FIELD[.COMPONENT]`, where FIELD may be followed by
+/// a repeat number in brackets (`PID.3[2]`, `PID.5[2].1`)
 /// * SEGMENT must match the provided segment parameter
 /// * FIELD must be a positive integer (1-based)
+/// * REPEAT (optional) must be a positive integer (1-based), defaulting to 1
 /// * COMPONENT (optional) must be a positive integer (1-based)
 ///
 /// # Returns
-/// * `Some((field, component))` - Parsed field and optional component numbers
+/// * `Some(FieldId)` - Parsed field, repeat and optional component numbers
 /// * `None` - Invalid format or validation failure
-fn parse_field_id(field_id: &str, segment: &str) -> Option<(usize, Option<usize>)> {
+pub(super) fn parse_field_id(field_id: &str, segment: &str) -> Option<FieldId> {
     // Split the field_id into segment, field, and component parts
     let parts: Vec<&str> = field_id.split('.').collect();
     if parts.len() < 2 {
@@ -308,6 +514,21 @@ fn parse_field_id(field_id: &str, segment: &str) -> Option<(usize, Option<usize>
         return None;
     }
 
+    // an optional repeat follows the field number in brackets
+    let (field_str, repeat) = match field_str.split_once('[') {
+        Some((field_str, repeat_str)) => {
+            let repeat = repeat_str
+                .strip_suffix(']')
+                .and_then(|repeat| repeat.parse::<usize>().ok());
+            let Some(repeat) = repeat.filter(|repeat| *repeat >= 1) else {
+                log::warn!("Invalid repeat number in field_id: {field_id}");
+                return None;
+            };
+            (field_str, repeat)
+        }
+        None => (field_str, 1),
+    };
+
     let Ok(field) = field_str.parse::<usize>() else {
         log::warn!("Invalid field number in field_id: {field_id}");
         return None;
@@ -331,7 +552,11 @@ fn parse_field_id(field_id: &str, segment: &str) -> Option<(usize, Option<usize>
         None
     };
 
-    Some((field, component))
+    Some(FieldId {
+        field,
+        repeat,
+        component,
+    })
 }
 
 /// Result of generating a new control ID.
@@ -389,12 +614,10 @@ pub fn generate_control_id(message: &str) -> Result<GenerateControlIdResult, Str
         .map(|r| r.range())
         .ok_or("Could not find MSH.10 in updated message")?;
 
+    let range = Utf16Offsets::new(&rendered).cursor_range(&range);
     Ok(GenerateControlIdResult {
         message: rendered,
-        range: CursorRange {
-            start: range.start,
-            end: range.end,
-        },
+        range,
     })
 }
 
@@ -413,62 +636,148 @@ pub fn generate_control_id(message: &str) -> Result<GenerateControlIdResult, Str
 ///
 /// # Arguments
 /// * `message` - The HL7 message as a string
-/// * `cursor` - Current cursor position (character offset)
+/// * `cursor` - Current cursor position (UTF-16 offset)
 ///
 /// # Returns
 /// * `Some(CursorRange)` - Range of the cell containing the cursor
 /// * `None` - If cursor is not within a valid cell (e.g., on segment name, between segments)
 #[tauri::command]
 pub fn get_current_cell_range(message: &str, cursor: usize) -> Option<CursorRange> {
+    let offsets = Utf16Offsets::new(message);
+    let cursor = offsets.to_byte(cursor);
     let message = hl7_parser::parse_message_with_lenient_newlines(message).ok()?;
+    cell_at(&message, cursor).map(|cell| offsets.cursor_range(&cell.range))
+}
+
+/// Get the path of the cell at the cursor, for "Copy Field Path".
+///
+/// The cell is the one `get_current_cell_range` finds. Its path is as short as
+/// it can be while still naming only that cell: the segment occurrence and
+/// field repeat are only given past the first (`OBX[2].5`, `PID.3[2]`), and
+/// component and subcomponent numbers only where the field has them
+/// (`PID.5.1`, `PID.3.4.2`).
+///
+/// # Arguments
+/// * `message` - The HL7 message as a string
+/// * `cursor` - Current cursor position (UTF-16 offset)
+///
+/// # Returns
+/// * `Some(String)` - The cell's path, e.g. `PID.5.1`
+/// * `None` - If cursor is not within a valid cell (e.g., on segment name, between segments)
+#[tauri::command]
+pub fn get_cell_path(message: &str, cursor: usize) -> Option<String> {
+    let cursor = Utf16Offsets::new(message).to_byte(cursor);
+    let message = hl7_parser::parse_message_with_lenient_newlines(message).ok()?;
+    cell_at(&message, cursor).map(|cell| cell.path)
+}
+
+/// Get the decoded value of the cell at the cursor, for "Copy Field Value".
+///
+/// The cell is the one `get_current_cell_range` finds. Escape sequences in its
+/// value are decoded (`\F\` becomes `|`, and so on), except in MSH.1 and MSH.2,
+/// which hold the delimiters themselves.
+///
+/// # Arguments
+/// * `message` - The HL7 message as a string
+/// * `cursor` - Current cursor position (UTF-16 offset)
+///
+/// # Returns
+/// * `Some(String)` - The cell's value, decoded
+/// * `None` - If cursor is not within a valid cell (e.g., on segment name, between segments)
+#[tauri::command]
+pub fn get_cell_value(message: &str, cursor: usize) -> Option<String> {
+    let cursor = Utf16Offsets::new(message).to_byte(cursor);
+    let source = message;
+    let message = hl7_parser::parse_message_with_lenient_newlines(message).ok()?;
+    let cell = cell_at(&message, cursor)?;
+    let raw = source.get(cell.range)?;
+    if cell.path == "MSH.1" || cell.path == "MSH.2" {
+        return Some(raw.to_string());
+    }
+    Some(message.separators.decode(raw).to_string())
+}
+
+/// The navigable cell holding a cursor (see `get_current_cell_range`).
+struct Cell {
+    /// Path of the cell, as `get_cell_path` gives it
+    path: String,
+    /// Byte range of the cell
+    range: std::ops::Range<usize>,
+}
+
+/// Find the navigable cell holding a cursor, given as a byte offset.
+fn cell_at(message: &Message, cursor: usize) -> Option<Cell> {
+    let contains = |range: &std::ops::Range<usize>| cursor >= range.start && cursor <= range.end;
+    let mut occurrences: HashMap<&str, usize> = HashMap::new();
 
     // Flatten message into navigable cells and find the one containing the cursor
     for segment in message.segments() {
+        let occurrence = occurrences.entry(segment.name).or_default();
+        *occurrence += 1;
+
         // Skip segment name - we don't want to replace segment identifiers
         let segment_name_end = segment.range.start + segment.name.len();
         if cursor >= segment.range.start && cursor <= segment_name_end {
             return None; // Cursor is on segment name, not a valid cell for replacement
         }
 
-        for field in segment.fields() {
+        let segment_path = if *occurrence == 1 {
+            segment.name.to_string()
+        } else {
+            format!("{}[{occurrence}]", segment.name)
+        };
+        let cell = |path: String, range: &std::ops::Range<usize>| {
+            Some(Cell {
+                path,
+                range: range.clone(),
+            })
+        };
+
+        for (field_i, field) in segment.fields().enumerate() {
+            let field_path = format!("{segment_path}.{}", field_i + 1);
             if field.repeats.is_empty() {
-                if cursor >= field.range.start && cursor <= field.range.end {
-                    return Some(CursorRange {
-                        start: field.range.start,
-                        end: field.range.end,
-                    });
+                if contains(&field.range) {
+                    return cell(field_path, &field.range);
                 }
                 continue;
             }
 
-            for repeat in field.repeats.iter() {
+            for (repeat_i, repeat) in field.repeats.iter().enumerate() {
+                let repeat_path = if repeat_i == 0 {
+                    field_path.clone()
+                } else {
+                    format!("{field_path}[{}]", repeat_i + 1)
+                };
                 if repeat.components.is_empty() {
-                    if cursor >= repeat.range.start && cursor <= repeat.range.end {
-                        return Some(CursorRange {
-                            start: repeat.range.start,
-                            end: repeat.range.end,
-                        });
+                    if contains(&repeat.range) {
+                        return cell(repeat_path, &repeat.range);
                     }
                     continue;
                 }
 
-                for component in repeat.components.iter() {
+                for (component_i, component) in repeat.components.iter().enumerate() {
+                    let has_subcomponents = component.subcomponents.len() > 1;
+                    let component_path = if repeat.components.len() > 1 || has_subcomponents {
+                        format!("{repeat_path}.{}", component_i + 1)
+                    } else {
+                        repeat_path.clone()
+                    };
                     if component.subcomponents.is_empty() {
-                        if cursor >= component.range.start && cursor <= component.range.end {
-                            return Some(CursorRange {
-                                start: component.range.start,
-                                end: component.range.end,
-                            });
+                        if contains(&component.range) {
+                            return cell(component_path, &component.range);
                         }
                         continue;
                     }
 
-                    for subcomponent in component.subcomponents.iter() {
-                        if cursor >= subcomponent.range.start && cursor <= subcomponent.range.end {
-                            return Some(CursorRange {
-                                start: subcomponent.range.start,
-                                end: subcomponent.range.end,
-                            });
+                    for (subcomponent_i, subcomponent) in component.subcomponents.iter().enumerate()
+                    {
+                        if contains(&subcomponent.range) {
+                            let path = if has_subcomponents {
+                                format!("{component_path}.{}", subcomponent_i + 1)
+                            } else {
+                                component_path
+                            };
+                            return cell(path, &subcomponent.range);
                         }
                     }
                 }
@@ -602,7 +911,7 @@ fn parse_offset(offset_str: &str) -> Result<jiff::tz::Offset, String> {
 /// - mm: 2-digit minute (00-59)
 /// - ss: 2-digit second (00-59)
 /// - ±ZZZZ: UTC offset as ±HHmm (optional)
-fn format_hl7_timestamp(dt: &jiff::Zoned, include_offset: bool) -> String {
+pub(crate) fn format_hl7_timestamp(dt: &jiff::Zoned, include_offset: bool) -> String {
     format_hl7_timestamp_from_zoned(dt, include_offset)
 }
 
@@ -671,6 +980,11 @@ fn format_hl7_offset(base: &str, offset: jiff::tz::Offset) -> String {
 /// - `oru_r01` for Observation Result messages
 /// - `orr_o02` for Order Response messages
 /// - `dft_p03` for Financial Transaction messages
+/// - `mdm_t01`, `mdm_t02` for Medical Document Management messages
+/// - `siu_s12`, ..., `siu_s26` for Scheduling Information messages
+/// - `vxu_v04` for Vaccination Record Update messages
+/// - `qbp_q22`, `rsp_k22` for Query by Parameter messages and their responses
+/// - `qry_a19`, `adr_a19` for original mode patient queries and their responses
 ///
 /// # Message Structure
 /// The generated message includes:
@@ -681,19 +995,25 @@ fn format_hl7_offset(base: &str, offset: jiff::tz::Offset) -> String {
 /// # Template Values
 /// Each field in the segment schema can have a `template` value. Special values:
 /// - `{auto}` - Placeholder for dynamic values (timestamps, control IDs) expanded at send time
+/// - `{now-2h}`, `{uuid}`, `{seq}`, `{name}`, ... - Placeholders filled in once the message
+///   is built, including the user's variables (see [`expand_placeholders`])
+/// - `{=expression}` - Computed value, evaluated after placeholders (see [`evaluate_formulas`])
 /// - Empty string - Field left blank
 /// - Any other value - Used directly
 ///
 /// # Arguments
 /// * `template_name` - Template identifier (e.g., "adt_a01", "orm_o01")
+/// * `variables` - User-defined placeholder values, by name
 /// * `state` - Application state containing the schema cache
 ///
 /// # Returns
 /// * `Ok(String)` - The generated HL7 message
-/// * `Err(String)` - If template not found or schema loading fails
+/// * `Err(String)` - If template not found, schema loading fails, or a placeholder or formula
+///   can't be evaluated
 #[tauri::command]
 pub fn generate_template_message(
     template_name: &str,
+    variables: Option<HashMap<String, String>>,
     state: State<'_, AppData>,
 ) -> Result<String, String> {
     let schema = state.schema.get_messages();
@@ -807,10 +1127,28 @@ pub fn generate_template_message(
             }
         }
 
+        // MDM documents carry text content in OBX rather than a numeric result
+        if message_type == "MDM" && segment_name == "OBX" {
+            seg.set_field_value(2, "TX");
+            seg.set_field(3, {
+                let mut field = FieldBuilder::default();
+                field.set_component(1, "DOC");
+                field.set_component(2, "Document");
+                field
+            });
+            seg.set_field_value(5, "Patient was seen and discharged in good condition.");
+            seg.set_field_value(6, "");
+            seg.set_field_value(7, "");
+        }
+
         builder.push_segment(seg);
     }
 
-    Ok(builder.render_with_newlines().to_string())
+    let message = expand_placeholders(
+        &builder.render_with_newlines().to_string(),
+        &variables.unwrap_or_default(),
+    )?;
+    evaluate_formulas(&message)
 }
 
 /// Result of parsing an HL7 timestamp into ISO components.
@@ -935,6 +1273,252 @@ pub fn parse_hl7_timestamp(value: &str, mode: &str) -> ParsedTimestamp {
     }
 }
 
+/// Messages recovered from an HL7 batch file, with any envelope problems found.
+#[derive(Serialize, Debug)]
+pub struct UnwrappedBatch {
+    /// Messages in file order, without envelope segments
+    pub messages: Vec<String>,
+    /// Number of BHS/BTS batches in the file
+    #[serde(rename = "batchCount")]
+    pub batch_count: usize,
+    /// Whether the file has an FHS/FTS file envelope
+    #[serde(rename = "hasFileHeader")]
+    pub has_file_header: bool,
+    /// Problems with the envelope (missing trailers, wrong counts, etc.)
+    pub issues: Vec<String>,
+}
+
+/// A batch being read by [`unwrap_batch`].
+struct OpenBatch {
+    /// Number of messages seen in the batch so far
+    messages: usize,
+    /// Whether the batch has been closed by a BTS
+    closed: bool,
+}
+
+/// Wrap one or more messages in HL7 batch envelope segments.
+///
+/// The messages are wrapped in a BHS/BTS batch, and optionally an FHS/FTS file
+/// envelope around that. Sending/receiving application and facility are copied
+/// from the first message's MSH, and the trailer counts are filled in. Any
+/// existing envelope segments in the content are replaced, so re-wrapping an
+/// edited batch refreshes its counts.
+///
+/// # Arguments
+/// * `content` - One or more messages, as split by `split_messages`
+/// * `file_header` - Whether to also add FHS/FTS segments
+///
+/// # Returns
+/// * `Ok(String)` - The wrapped batch
+/// * `Err(String)` - If the content contains no messages, or the first can't be parsed
+#[tauri::command]
+pub fn wrap_batch(content: &str, file_header: bool) -> Result<String, String> {
+    let mut file = super::multi_message::split_messages(content);
+    let first = file
+        .messages
+        .first()
+        .ok_or_else(|| "No messages to wrap".to_string())?;
+
+    let message = hl7_parser::parse_message_with_lenient_newlines(&first.text)
+        .map_err(|e| format!("Failed to parse message: {e:#}"))?;
+    let msh = message
+        .segment("MSH")
+        .expect("Valid messages have MSH segments");
+    let msh_field = |n: usize| {
+        msh.field(n)
+            .map(|f| f.raw_value().to_string())
+            .unwrap_or_default()
+    };
+
+    let separators = &message.separators;
+    let field = separators.field.to_string();
+    let encoding = format!(
+        "{}{}{}{}",
+        separators.component, separators.repetition, separators.escape, separators.subcomponent
+    );
+    let now = format_hl7_timestamp(&jiff::Zoned::now(), true);
+
+    // FHS and BHS share the same layout for fields 1-11
+    let envelope = |name: &str| {
+        [
+            name.to_string(),
+            encoding.clone(),
+            msh_field(3),
+            msh_field(4),
+            msh_field(5),
+            msh_field(6),
+            now.clone(),
+            String::new(),
+            String::new(),
+            String::new(),
+            Alphanumeric.sample_string(&mut rand::rng(), 20),
+        ]
+        .join(field.as_str())
+    };
+
+    let mut header = Vec::new();
+    let mut trailer = Vec::new();
+    if file_header {
+        header.push(envelope("FHS"));
+    }
+    header.push(envelope("BHS"));
+    trailer.push(format!("BTS{field}{}", file.messages.len()));
+    if file_header {
+        trailer.push(format!("FTS{field}1"));
+    }
+
+    file.header = header;
+    file.trailer = trailer;
+    file.framing = super::multi_message::MessageFraming::Batch;
+    Ok(super::multi_message::join_messages(file))
+}
+
+/// Unwrap an HL7 batch file into its messages, validating the envelope.
+///
+/// Checks that:
+/// * FHS, if present, is the first segment and is closed by an FTS
+/// * Every BHS is closed by a BTS before the next batch or the file trailer
+/// * BTS.1 (batch message count) matches the number of messages in the batch
+/// * FTS.1 (file batch count) matches the number of batches in the file
+/// * No messages or segments appear outside a batch when batches are used
+///
+/// Content without any envelope segments is returned as-is, split into messages.
+///
+/// # Arguments
+/// * `content` - Raw batch file content
+///
+/// # Returns
+/// The messages found in the file, envelope details, and any problems found
+#[tauri::command]
+pub fn unwrap_batch(content: &str) -> UnwrappedBatch {
+    let line_ending = super::multi_message::detect_line_ending(content);
+    let mut messages: Vec<Vec<&str>> = Vec::new();
+    let mut batches: Vec<OpenBatch> = Vec::new();
+    let mut issues = Vec::new();
+    let mut has_file_header = false;
+    let mut file_closed = false;
+    let mut in_message = false;
+    let mut first_segment = true;
+
+    for (_, line) in super::multi_message::lines_with_offsets(content) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let is_first = std::mem::take(&mut first_segment);
+        let batch_number = batches.len();
+        let open_batch = batches.last_mut().filter(|batch| !batch.closed);
+
+        match line.get(..3) {
+            Some("FHS") => {
+                in_message = false;
+                if has_file_header {
+                    issues.push("File contains more than one FHS segment".to_string());
+                } else if !is_first {
+                    issues.push("FHS must be the first segment in the file".to_string());
+                }
+                has_file_header = true;
+            }
+            Some("BHS") => {
+                in_message = false;
+                if open_batch.is_some() {
+                    issues.push(format!(
+                        "Batch {batch_number} is not closed by a BTS before the next BHS"
+                    ));
+                }
+                batches.push(OpenBatch {
+                    messages: 0,
+                    closed: false,
+                });
+            }
+            Some("BTS") => {
+                in_message = false;
+                match open_batch {
+                    Some(batch) => {
+                        batch.closed = true;
+                        if let Some(count) =
+                            envelope_count(line).filter(|count| *count != batch.messages)
+                        {
+                            issues.push(format!(
+                                "BTS.1 says batch {batch_number} has {count} messages, but it contains {}",
+                                batch.messages
+                            ));
+                        }
+                    }
+                    None => issues.push("BTS segment without a matching BHS".to_string()),
+                }
+            }
+            Some("FTS") => {
+                in_message = false;
+                if open_batch.is_some() {
+                    issues.push(format!(
+                        "Batch {batch_number} is not closed by a BTS before the FTS"
+                    ));
+                }
+                if !has_file_header {
+                    issues.push("FTS segment without a matching FHS".to_string());
+                }
+                if let Some(count) = envelope_count(line).filter(|count| *count != batch_number) {
+                    issues.push(format!(
+                        "FTS.1 says the file has {count} batches, but it contains {batch_number}"
+                    ));
+                }
+                file_closed = true;
+            }
+            Some("MSH") => {
+                in_message = true;
+                match open_batch {
+                    Some(batch) => batch.messages += 1,
+                    None if !batches.is_empty() || has_file_header => {
+                        issues.push(format!(
+                            "Message {} is outside of a BHS/BTS batch",
+                            messages.len() + 1
+                        ));
+                    }
+                    None => {}
+                }
+                messages.push(vec![line]);
+            }
+            _ => match messages.last_mut().filter(|_| in_message) {
+                Some(message) => message.push(line),
+                None => issues.push(format!(
+                    "{} segment is outside of a message",
+                    line.get(..3).unwrap_or(line)
+                )),
+            },
+        }
+    }
+
+    if batches.last().is_some_and(|batch| !batch.closed) {
+        issues.push(format!(
+            "Batch {} is missing its BTS trailer",
+            batches.len()
+        ));
+    }
+    if has_file_header && !file_closed {
+        issues.push("File is missing its FTS trailer".to_string());
+    }
+
+    UnwrappedBatch {
+        messages: messages
+            .into_iter()
+            .map(|segments| segments.join(line_ending))
+            .collect(),
+        batch_count: batches.len(),
+        has_file_header,
+        issues,
+    }
+}
+
+/// Parse the count in field 1 of a BTS or FTS segment, if present.
+///
+/// The count is optional, so a bare `BTS` or `FTS` is never reported as wrong.
+fn envelope_count(line: &str) -> Option<usize> {
+    let separator = line.chars().nth(3)?;
+    line.split(separator)
+        .nth(1)
+        .and_then(|count| count.trim().parse().ok())
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used, clippy::indexing_slicing)]
 mod tests {
@@ -982,4 +1566,175 @@ mod tests {
         let offset = parse_offset("+00:00").unwrap();
         assert_eq!(offset.seconds(), 0);
     }
+
+    const MSG1: &str = "MSH|^~\\&|LAB|HOSP|EHR|CLINIC|20250101||ORU^R01|1|P|2.5.1\rPID|||111";
+    const MSG2: &str = "MSH|^~\\&|LAB|HOSP|EHR|CLINIC|20250101||ORU^R01|2|P|2.5.1\rPID|||222";
+
+    #[test]
+    fn can_wrap_messages_in_batch() {
+        let wrapped = wrap_batch(&format!("{MSG1}\r{MSG2}"), true).unwrap();
+        let lines: Vec<&str> = wrapped.split('\r').collect();
+        assert!(lines[0].starts_with("FHS|^~\\&|LAB|HOSP|EHR|CLINIC|"));
+        assert!(lines[1].starts_with("BHS|^~\\&|LAB|HOSP|EHR|CLINIC|"));
+        assert_eq!(lines[lines.len() - 2], "BTS|2");
+        assert_eq!(lines[lines.len() - 1], "FTS|1");
+
+        let unwrapped = unwrap_batch(&wrapped);
+        assert!(unwrapped.issues.is_empty(), "{:?}", unwrapped.issues);
+        assert_eq!(unwrapped.messages, vec![MSG1, MSG2]);
+        assert_eq!(unwrapped.batch_count, 1);
+        assert!(unwrapped.has_file_header);
+    }
+
+    #[test]
+    fn wrapping_nothing_is_an_error() {
+        assert!(wrap_batch("", false).is_err());
+    }
+
+    #[test]
+    fn unwrap_reports_wrong_counts() {
+        let content = format!("FHS|^~\\&\rBHS|^~\\&\r{MSG1}\r{MSG2}\rBTS|3\rFTS|2");
+        let unwrapped = unwrap_batch(&content);
+        assert_eq!(unwrapped.messages.len(), 2);
+        assert_eq!(unwrapped.issues.len(), 2);
+        assert!(unwrapped.issues[0].contains("BTS.1"));
+        assert!(unwrapped.issues[1].contains("FTS.1"));
+    }
+
+    #[test]
+    fn unwrap_reports_unclosed_envelopes() {
+        let content = format!("FHS|^~\\&\rBHS|^~\\&\r{MSG1}\rBHS|^~\\&\r{MSG2}");
+        let unwrapped = unwrap_batch(&content);
+        assert_eq!(unwrapped.batch_count, 2);
+        assert_eq!(
+            unwrapped.issues,
+            vec![
+                "Batch 1 is not closed by a BTS before the next BHS",
+                "Batch 2 is missing its BTS trailer",
+                "File is missing its FTS trailer",
+            ]
+        );
+    }
+
+    const REPEATING_PID: &str = "MSH|^~\\&|HERMES\rPID|1||111^^^MRN~222^^^SSN||DOE^JOHN";
+
+    fn repeat_data(values: &[(&str, &str)], repeats: &[(u8, usize)]) -> SegmentData {
+        SegmentData {
+            fields: values
+                .iter()
+                .map(|(id, value)| ((*id).to_string(), Some((*value).to_string())))
+                .collect(),
+            repeats: repeats.iter().copied().collect(),
+        }
+    }
+
+    #[test]
+    fn parse_field_id_reads_repeats() {
+        assert_eq!(
+            parse_field_id("PID.3[2].1", "PID"),
+            Some(FieldId {
+                field: 3,
+                repeat: 2,
+                component: Some(1)
+            })
+        );
+        assert_eq!(
+            parse_field_id("PID.3", "PID"),
+            Some(FieldId {
+                field: 3,
+                repeat: 1,
+                component: None
+            })
+        );
+        assert_eq!(parse_field_id("PID.3[0]", "PID"), None);
+        assert_eq!(parse_field_id("PID.3[2", "PID"), None);
+    }
+
+    #[test]
+    fn counts_field_repeats() {
+        let message = hl7_parser::parse_message_with_lenient_newlines(REPEATING_PID).unwrap();
+        assert_eq!(field_repeat_count(&message, "PID", 3), 2);
+        assert_eq!(field_repeat_count(&message, "PID", 5), 1);
+        assert_eq!(field_repeat_count(&message, "PID", 30), 1);
+        // MSH.2 holds the repetition separator itself
+        assert_eq!(field_repeat_count(&message, "MSH", 2), 1);
+    }
+
+    #[test]
+    fn render_edits_one_repeat_and_keeps_the_other() {
+        let data = repeat_data(&[("PID.3[2].1", "333")], &[(3, 2)]);
+        let rendered = render_segment(REPEATING_PID, "PID", data, &messages_schema());
+        assert!(rendered.contains("|111^^^MRN~333^^^SSN|"), "{rendered}");
+    }
+
+    #[test]
+    fn render_adds_and_removes_repeats() {
+        let data = repeat_data(&[("PID.3[3]", "444")], &[(3, 3)]);
+        let rendered = render_segment(REPEATING_PID, "PID", data, &messages_schema());
+        assert!(rendered.contains("|111^^^MRN~222^^^SSN~444|"), "{rendered}");
+
+        let data = repeat_data(&[("PID.3.1", "111")], &[]);
+        let rendered = render_segment(REPEATING_PID, "PID", data, &messages_schema());
+        assert!(rendered.contains("|111^^^MRN||"), "{rendered}");
+    }
+
+    fn messages_schema() -> MessagesSchema {
+        crate::schema::cache::SchemaCache::new()
+            .expect("can create cache")
+            .get_messages()
+    }
+
+    #[test]
+    fn render_inserts_new_segment_in_structure_order() {
+        let message = "MSH|^~\\&|HERMES||||||ADT^A01\rPID|1\rPV1|1\rZPI|custom";
+        let data = repeat_data(&[("EVN.1", "A01")], &[]);
+        let rendered = render_segment(message, "EVN", data, &messages_schema());
+        let names = get_message_segment_names(&rendered);
+        assert_eq!(names, vec!["MSH", "EVN", "PID", "PV1", "ZPI"]);
+
+        // DG1 follows PV1 in ADT^A01, and the Z-segment stays last
+        let rendered = render_segment(message, "DG1", SegmentData::default(), &messages_schema());
+        let names = get_message_segment_names(&rendered);
+        assert_eq!(names, vec!["MSH", "PID", "PV1", "DG1", "ZPI"]);
+    }
+
+    #[test]
+    fn render_appends_segments_the_structure_does_not_list() {
+        let message = "MSH|^~\\&|HERMES||||||ADT^A01\rPID|1\rPV1|1";
+        let rendered = render_segment(message, "OBX", SegmentData::default(), &messages_schema());
+        let names = get_message_segment_names(&rendered);
+        assert_eq!(names, vec!["MSH", "PID", "PV1", "OBX"]);
+
+        // unknown message types keep appending
+        let message = "MSH|^~\\&|HERMES||||||ZZZ^Z01\rPV1|1";
+        let rendered = render_segment(message, "PID", SegmentData::default(), &messages_schema());
+        let names = get_message_segment_names(&rendered);
+        assert_eq!(names, vec!["MSH", "PV1", "PID"]);
+    }
+
+    #[test]
+    fn cell_paths_name_only_what_the_field_has() {
+        let message = "MSH|^~\\&|HERMES\rPID|1||123^^^MRN~456||DOE^JANE\rOBX|1\rOBX|2|CE|A&B^X";
+        let path = |needle: &str| get_cell_path(message, message.find(needle).unwrap());
+
+        assert_eq!(path("HERMES").as_deref(), Some("MSH.3"));
+        assert_eq!(path("123").as_deref(), Some("PID.3.1"));
+        assert_eq!(path("MRN").as_deref(), Some("PID.3.4"));
+        assert_eq!(path("456").as_deref(), Some("PID.3[2]"));
+        assert_eq!(path("JANE").as_deref(), Some("PID.5.2"));
+        assert_eq!(path("CE").as_deref(), Some("OBX[2].2"));
+        assert_eq!(path("B^").as_deref(), Some("OBX[2].3.1.2"));
+        assert_eq!(path("PID"), None);
+    }
+
+    #[test]
+    fn cell_values_are_decoded() {
+        let message = "MSH|^~\\&|HERMES\rPID|1||||DOE\\T\\SMITH^JANE";
+        let value = |needle: &str| get_cell_value(message, message.find(needle).unwrap());
+
+        assert_eq!(value("DOE").as_deref(), Some("DOE&SMITH"));
+        assert_eq!(value("JANE").as_deref(), Some("JANE"));
+        assert_eq!(value("^~").as_deref(), Some("^~\\&"));
+        assert_eq!(value("MSH"), None);
+    }
 }
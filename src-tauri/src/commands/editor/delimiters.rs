@@ -0,0 +1,223 @@
+//! Rewriting a message from one set of delimiters to another.
+//!
+//! Some systems send messages with non-standard delimiters (e.g., `*` as the
+//! component separator), or with an MSH-2 that doesn't match the delimiters
+//! actually used, which Hermes then splits in the wrong places. This rewrites
+//! every delimiter and escape sequence for a new set of delimiters, and
+//! escapes characters that were plain text before but are delimiters now.
+//!
+//! # Delimiters
+//!
+//! A set of delimiters is written as MSH-1 followed by MSH-2: the field,
+//! component, repetition, escape, and subcomponent characters, as in `|^~\&`.
+//!
+//! The MSH, BHS, and FHS headers are rewritten to declare the new delimiters.
+//! Characters of MSH-2 beyond the fourth (such as the truncation character of
+//! HL7 2.7) are kept.
+
+use hl7_parser::message::Separators;
+
+use super::metadata::strip_document_metadata;
+
+/// Segments whose first two fields declare the delimiters.
+const HEADER_SEGMENTS: [&str; 3] = ["MSH", "BHS", "FHS"];
+
+/// Rewrite a message for a different set of delimiters.
+///
+/// # Arguments
+/// * `message` - The HL7 message as a string
+/// * `from` - The delimiters the message actually uses (e.g., `|*~\&`), or None
+///   to take them from its MSH header
+/// * `to` - The delimiters to rewrite the message with (e.g., `|^~\&`)
+///
+/// # Returns
+/// * `Ok(String)` - The rewritten message
+/// * `Err(String)` - If a set of delimiters is malformed, or `from` is None and
+///   the message doesn't start with a header declaring its delimiters
+#[tauri::command]
+pub fn remap_delimiters(message: &str, from: Option<String>, to: &str) -> Result<String, String> {
+    let (body, header_length) = strip_document_metadata(message);
+    let from = match from {
+        Some(from) => parse_delimiters(&from)?,
+        None => declared_delimiters(body).ok_or_else(|| {
+            "The message doesn't start with a header declaring its delimiters".to_string()
+        })?,
+    };
+    let to = parse_delimiters(to)?;
+
+    let mut remapped = message.get(..header_length).unwrap_or_default().to_string();
+    for line in body.split_inclusive(['\r', '\n']) {
+        let content = line.trim_end_matches(['\r', '\n']);
+        remapped.push_str(&remap_segment(content, &from, &to));
+        remapped.push_str(line.get(content.len()..).unwrap_or_default());
+    }
+    Ok(remapped)
+}
+
+/// Parse a set of delimiters written as MSH-1 and MSH-2 (e.g., `|^~\&`).
+fn parse_delimiters(delimiters: &str) -> Result<Separators, String> {
+    let chars: Vec<char> = delimiters.chars().collect();
+    let [field, component, repetition, escape, subcomponent] = chars.as_slice() else {
+        return Err(format!(
+            "Delimiters are written as five characters, like |^~\\&, not '{delimiters}'"
+        ));
+    };
+    if let Some(c) = chars
+        .iter()
+        .find(|c| c.is_alphanumeric() || c.is_whitespace() || c.is_control())
+    {
+        return Err(format!("'{c}' can't be used as a delimiter"));
+    }
+    if let Some(c) = chars
+        .iter()
+        .enumerate()
+        .find_map(|(i, c)| chars.get(i + 1..)?.contains(c).then_some(c))
+    {
+        return Err(format!("'{c}' is used as more than one delimiter"));
+    }
+    Ok(Separators {
+        field: *field,
+        component: *component,
+        repetition: *repetition,
+        escape: *escape,
+        subcomponent: *subcomponent,
+        lenient_newlines: false,
+    })
+}
+
+/// The delimiters declared by the header segment a message starts with.
+fn declared_delimiters(message: &str) -> Option<Separators> {
+    let name = HEADER_SEGMENTS
+        .iter()
+        .find(|name| message.starts_with(*name))?;
+    let mut chars = message.get(name.len()..)?.chars();
+    let field = chars.next()?;
+    let encoding: String = chars.take_while(|c| *c != field).take(4).collect();
+    parse_delimiters(&format!("{field}{encoding}")).ok()
+}
+
+/// Rewrite one segment (without its line ending) for the new delimiters.
+fn remap_segment(segment: &str, from: &Separators, to: &Separators) -> String {
+    let mut remapped = String::with_capacity(segment.len());
+    let mut rest = segment;
+
+    let header = HEADER_SEGMENTS.iter().find(|name| {
+        segment.starts_with(*name)
+            && segment
+                .get(name.len()..)
+                .is_some_and(|after| after.starts_with(from.field))
+    });
+    if let Some(name) = header {
+        let after = segment
+            .get(name.len() + from.field.len_utf8()..)
+            .unwrap_or_default();
+        let encoding_length = after.find(from.field).unwrap_or(after.len());
+        let extra: String = after
+            .get(..encoding_length)
+            .unwrap_or_default()
+            .chars()
+            .skip(4)
+            .collect();
+        remapped.push_str(name);
+        remapped.extend([
+            to.field,
+            to.component,
+            to.repetition,
+            to.escape,
+            to.subcomponent,
+        ]);
+        remapped.push_str(&extra);
+        rest = after.get(encoding_length..).unwrap_or_default();
+    }
+
+    let mut chars = rest.chars();
+    while let Some(c) = chars.next() {
+        if c == from.escape {
+            // escape sequences keep their meaning, written with the new escape character
+            let after = chars.as_str();
+            if let Some(length) = after.find(from.escape) {
+                remapped.push(to.escape);
+                remapped.push_str(after.get(..length).unwrap_or_default());
+                remapped.push(to.escape);
+                chars = after
+                    .get(length + from.escape.len_utf8()..)
+                    .unwrap_or_default()
+                    .chars();
+            } else {
+                remapped.push(to.escape);
+            }
+        } else if c == from.field {
+            remapped.push(to.field);
+        } else if c == from.component {
+            remapped.push(to.component);
+        } else if c == from.repetition {
+            remapped.push(to.repetition);
+        } else if c == from.subcomponent {
+            remapped.push(to.subcomponent);
+        } else {
+            push_text(&mut remapped, c, to);
+        }
+    }
+    remapped
+}
+
+/// Write a character of text, escaping it if it's one of the delimiters.
+fn push_text(remapped: &mut String, c: char, delimiters: &Separators) {
+    let code = if c == delimiters.field {
+        'F'
+    } else if c == delimiters.component {
+        'S'
+    } else if c == delimiters.subcomponent {
+        'T'
+    } else if c == delimiters.repetition {
+        'R'
+    } else if c == delimiters.escape {
+        'E'
+    } else {
+        remapped.push(c);
+        return;
+    };
+    remapped.extend([delimiters.escape, code, delimiters.escape]);
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrites_non_standard_delimiters() {
+        let message = "MSH|*~\\&|APP\rPID|1||123*MR^X~456*MR\r";
+        assert_eq!(
+            remap_delimiters(message, None, "|^~\\&").unwrap(),
+            "MSH|^~\\&|APP\rPID|1||123^MR\\S\\X~456^MR\r"
+        );
+    }
+
+    #[test]
+    fn trusts_the_given_delimiters_over_the_header() {
+        // the header claims the standard delimiters, but components are split by *
+        let message = "MSH|^~\\&|APP\nPID|1||123*MR";
+        assert_eq!(
+            remap_delimiters(message, Some("|*~\\&".to_string()), "|^~\\&").unwrap(),
+            "MSH|^~\\&|APP\nPID|1||123^MR"
+        );
+    }
+
+    #[test]
+    fn rewrites_escape_sequences() {
+        let message = "MSH|^~\\&|APP\rNTE|1||A\\T\\B! \\.br\\";
+        assert_eq!(
+            remap_delimiters(message, None, "#^~!&").unwrap(),
+            "MSH#^~!&#APP\rNTE#1##A!T!B!E! !.br!"
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_delimiters() {
+        assert!(parse_delimiters("|^~\\").is_err());
+        assert!(parse_delimiters("|^^\\&").is_err());
+        assert!(parse_delimiters("|^~a&").is_err());
+        assert!(remap_delimiters("PID|1", None, "|^~\\&").is_err());
+    }
+}
@@ -0,0 +1,239 @@
+//! Document attachment helpers for MDM and ORU messages.
+//!
+//! Document feeds (MDM^T02, and ORU results with attached reports) carry their
+//! content in OBX segments, either embedded as encapsulated data (ED) or as a
+//! reference pointer (RP) to where the document lives. Building these by hand
+//! means base64-encoding a file and getting five ED components in the right
+//! order, so these commands do it instead.
+//!
+//! # OBX Layout
+//!
+//! Attachments are appended to the end of the message as a new OBX segment:
+//! * OBX.1 - Next set ID after any existing OBX segments
+//! * OBX.2 - `ED` or `RP`
+//! * OBX.3 - `DOC^Document`
+//! * OBX.5 - The encapsulated data or reference pointer
+//! * OBX.11 - `F` (final)
+//!
+//! # Data Types
+//!
+//! The ED/RP type of data (HL7 table 0191) and subtype (table 0291) are derived
+//! from the document's file extension, falling back to `AP` (other application
+//! data) for anything unrecognised.
+
+use std::path::Path;
+
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use hl7_parser::builder::{FieldBuilder, MessageBuilder, SegmentBuilder};
+
+use super::cursor::CursorRange;
+use super::segment::{SegmentChange, SegmentOperation, SegmentOperationResult};
+
+/// How a document is attached to a message.
+enum Attachment<'a> {
+    /// Document content embedded as base64.
+    Embedded { data: String },
+    /// Pointer to a document stored elsewhere.
+    Reference {
+        pointer: &'a str,
+        application: &'a str,
+    },
+}
+
+/// Attach a file to a message as an embedded (ED) OBX.
+///
+/// # Arguments
+/// * `message` - The HL7 message to attach the document to
+/// * `path` - Path of the document to embed
+///
+/// # Returns
+/// * `Ok(SegmentOperationResult)` - The message with the new OBX, and the cursor at its start
+/// * `Err(String)` - If the file can't be read or the message can't be parsed
+#[tauri::command]
+pub fn attach_document(message: &str, path: &str) -> Result<SegmentOperationResult, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read document: {e}"))?;
+    append_attachment(
+        message,
+        path,
+        Attachment::Embedded {
+            data: STANDARD.encode(bytes),
+        },
+    )
+    .map(|result| result.into_utf16(message))
+}
+
+/// Attach a reference to an externally stored document as an RP OBX.
+///
+/// # Arguments
+/// * `message` - The HL7 message to attach the reference to
+/// * `pointer` - Where the document can be found (e.g. a URL or document ID)
+/// * `application` - Application that can resolve the pointer
+///
+/// # Returns
+/// * `Ok(SegmentOperationResult)` - The message with the new OBX, and the cursor at its start
+/// * `Err(String)` - If the pointer is empty or the message can't be parsed
+#[tauri::command]
+pub fn attach_document_reference(
+    message: &str,
+    pointer: &str,
+    application: &str,
+) -> Result<SegmentOperationResult, String> {
+    if pointer.trim().is_empty() {
+        return Err("Document reference pointer cannot be empty".to_string());
+    }
+    append_attachment(
+        message,
+        pointer,
+        Attachment::Reference {
+            pointer,
+            application,
+        },
+    )
+    .map(|result| result.into_utf16(message))
+}
+
+/// Append an OBX carrying the attachment to the end of the message.
+fn append_attachment(
+    message: &str,
+    name: &str,
+    attachment: Attachment,
+) -> Result<SegmentOperationResult, String> {
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(message)
+        .map_err(|e| format!("Failed to parse message: {e:#}"))?;
+    let set_id = parsed.segments().filter(|s| s.name == "OBX").count() + 1;
+    let segment_count = parsed.segments().count();
+    let (type_of_data, subtype) = data_type_for(name);
+
+    let mut obx = SegmentBuilder::new("OBX");
+    obx.set_field_value(1, set_id.to_string());
+    obx.set_field(3, {
+        let mut field = FieldBuilder::default();
+        field.set_component(1, "DOC");
+        field.set_component(2, "Document");
+        field
+    });
+    match attachment {
+        Attachment::Embedded { data } => {
+            obx.set_field_value(2, "ED");
+            obx.set_field(5, {
+                let mut field = FieldBuilder::default();
+                field.set_component(2, type_of_data);
+                field.set_component(3, subtype);
+                field.set_component(4, "Base64");
+                field.set_component(5, data);
+                field
+            });
+        }
+        Attachment::Reference {
+            pointer,
+            application,
+        } => {
+            obx.set_field_value(2, "RP");
+            obx.set_field(5, {
+                let mut field = FieldBuilder::default();
+                field.set_component(1, pointer);
+                field.set_component(2, application);
+                field.set_component(3, type_of_data);
+                field.set_component(4, subtype);
+                field
+            });
+        }
+    }
+    obx.set_field_value(11, "F");
+
+    let mut builder: MessageBuilder = (&parsed).into();
+    builder.push_segment(obx);
+    let message = builder.render_with_newlines().to_string();
+
+    // the new OBX is always the last segment
+    let end = message.trim_end_matches(['\r', '\n']).len();
+    let cursor = message
+        .get(..end)
+        .and_then(|content| content.rfind(['\r', '\n']))
+        .map(|i| i + 1)
+        .unwrap_or(0);
+
+    Ok(SegmentOperationResult {
+        message,
+        cursor,
+        changes: vec![SegmentChange {
+            operation: SegmentOperation::Insert,
+            index_before: None,
+            index_after: Some(segment_count),
+            range_before: None,
+            range_after: Some(CursorRange { start: cursor, end }),
+        }],
+    })
+}
+
+/// Derive the ED/RP type of data and subtype from a document's file extension.
+pub(super) fn data_type_for(name: &str) -> (&'static str, &'static str) {
+    let extension = Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "pdf" => ("AP", "PDF"),
+        "rtf" => ("TEXT", "RTF"),
+        "txt" => ("TEXT", "PLAIN"),
+        "htm" | "html" => ("TEXT", "HTML"),
+        "xml" => ("TEXT", "XML"),
+        "jpg" | "jpeg" => ("IM", "JPEG"),
+        "png" => ("IM", "PNG"),
+        "gif" => ("IM", "GIF"),
+        "tif" | "tiff" => ("IM", "TIFF"),
+        "wav" => ("AU", "WAV"),
+        _ => ("AP", "OCTET-STREAM"),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use crate::commands::temp_dir;
+
+    const MESSAGE: &str = "MSH|^~\\&|APP|FAC|||20250101||MDM^T02|1|P|2.5.1\nTXA|1|DS|TX";
+
+    #[test]
+    fn can_embed_document() {
+        let dir = temp_dir("attach");
+        let path = dir.join("report.pdf");
+        std::fs::write(&path, b"%PDF-1.4").unwrap();
+
+        let result = attach_document(MESSAGE, path.to_str().unwrap()).unwrap();
+        let obx = &result.message[result.cursor..];
+        assert!(obx.starts_with("OBX|1|ED|DOC^Document||^AP^PDF^Base64^JVBERi0xLjQ="));
+        assert!(obx.trim_end().ends_with("|F"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn can_reference_document() {
+        let message = format!("{MESSAGE}\nOBX|1|TX|DOC^Document||Hello");
+        let result = attach_document_reference(&message, "docs/scan.tiff", "DOCSTORE").unwrap();
+        let obx = &result.message[result.cursor..];
+        assert!(obx.starts_with("OBX|2|RP|DOC^Document||docs/scan.tiff^DOCSTORE^IM^TIFF"));
+
+        let change = &result.changes[0];
+        assert_eq!(change.operation, SegmentOperation::Insert);
+        assert_eq!(change.index_after, Some(3));
+        let range = change.range_after.clone().unwrap();
+        assert_eq!(range.start, result.cursor);
+        assert!(result.message[range.start..range.end].ends_with("|F"));
+    }
+
+    #[test]
+    fn empty_reference_is_rejected() {
+        assert!(attach_document_reference(MESSAGE, "  ", "DOCSTORE").is_err());
+    }
+
+    #[test]
+    fn unknown_extensions_are_application_data() {
+        assert_eq!(data_type_for("notes.docx"), ("AP", "OCTET-STREAM"));
+        assert_eq!(data_type_for("Scan.JPG"), ("IM", "JPEG"));
+    }
+}
@@ -0,0 +1,287 @@
+//! Documents open in the editor.
+//!
+//! The editor can hold several messages at once, each a *document* with its
+//! own contents, file path, undo history, and file watch. Documents are
+//! identified by a [`DocumentId`] handed out when they're created, and the
+//! commands that keep per-message state (syncing the editor contents, undo and
+//! redo, watching the file) take the ID of the document they act on. Commands
+//! that only look at the message they're given, such as validation and the
+//! cursor commands, don't need one.
+//!
+//! # The Active Document
+//!
+//! One document is *active*: the one in front of the user. Extensions see the
+//! active document (`editor/getMessage` reads it and `editor/setMessage`
+//! replaces it), they're told a message was opened when the active document
+//! changes, and the Undo and Redo menu items follow its history. Closing the
+//! active document activates the one after it, or the one before it if it was
+//! the last.
+
+use indexmap::IndexMap;
+use serde::Serialize;
+use tauri::State;
+
+use crate::AppData;
+
+/// Identifier of a document open in the editor.
+pub type DocumentId = u64;
+
+/// A message open in the editor.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Document {
+    /// Current editor contents, synced from the frontend
+    pub message: String,
+    /// Path of the file the message was opened from or saved to, if any
+    pub file_path: Option<String>,
+}
+
+/// A document as listed for the frontend.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DocumentInfo {
+    /// Identifier of the document
+    pub id: DocumentId,
+    /// Path of its file, if it has one
+    #[serde(rename = "filePath")]
+    pub file_path: Option<String>,
+    /// Whether it's the active document
+    pub active: bool,
+}
+
+/// The documents open in the editor, in the order they were opened.
+#[derive(Debug, Default)]
+pub struct Documents {
+    open: IndexMap<DocumentId, Document>,
+    active: Option<DocumentId>,
+    next_id: DocumentId,
+}
+
+impl Documents {
+    /// Open a document and make it the active one.
+    pub fn create(&mut self, message: String, file_path: Option<String>) -> DocumentId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.open.insert(id, Document { message, file_path });
+        self.active = Some(id);
+        id
+    }
+
+    /// Close a document, activating a neighbour if it was the active one.
+    ///
+    /// # Returns
+    /// The closed document, or `None` if it wasn't open
+    pub fn close(&mut self, id: DocumentId) -> Option<Document> {
+        let index = self.open.get_index_of(&id)?;
+        let document = self.open.shift_remove(&id);
+        if self.active == Some(id) {
+            self.active = self
+                .open
+                .get_index(index)
+                .or_else(|| index.checked_sub(1).and_then(|i| self.open.get_index(i)))
+                .map(|(id, _)| *id);
+        }
+        document
+    }
+
+    /// Make a document the active one.
+    ///
+    /// # Returns
+    /// `false` if the document isn't open
+    pub fn activate(&mut self, id: DocumentId) -> bool {
+        let open = self.open.contains_key(&id);
+        if open {
+            self.active = Some(id);
+        }
+        open
+    }
+
+    /// An open document.
+    #[must_use]
+    pub fn get(&self, id: DocumentId) -> Option<&Document> {
+        self.open.get(&id)
+    }
+
+    /// An open document, to change.
+    pub fn get_mut(&mut self, id: DocumentId) -> Option<&mut Document> {
+        self.open.get_mut(&id)
+    }
+
+    /// The identifier of the active document, if any are open.
+    #[must_use]
+    pub fn active_id(&self) -> Option<DocumentId> {
+        self.active
+    }
+
+    /// The active document, if any are open.
+    #[must_use]
+    pub fn active(&self) -> Option<&Document> {
+        self.active.and_then(|id| self.open.get(&id))
+    }
+
+    /// The active document, to change.
+    pub fn active_mut(&mut self) -> Option<&mut Document> {
+        self.active.and_then(|id| self.open.get_mut(&id))
+    }
+
+    /// The open documents, in the order they were opened.
+    #[must_use]
+    pub fn list(&self) -> Vec<DocumentInfo> {
+        self.open
+            .iter()
+            .map(|(id, document)| DocumentInfo {
+                id: *id,
+                file_path: document.file_path.clone(),
+                active: self.active == Some(*id),
+            })
+            .collect()
+    }
+}
+
+/// Open a document in the editor and make it the active one.
+///
+/// # Arguments
+/// * `message` - The document's contents
+/// * `file_path` - Path of the file it was opened from, if any
+/// * `state` - Application state holding the documents
+///
+/// # Returns
+/// The new document's identifier
+#[tauri::command]
+pub async fn create_document(
+    message: String,
+    file_path: Option<String>,
+    state: State<'_, AppData>,
+) -> Result<DocumentId, String> {
+    let id = state.documents.lock().await.create(message, file_path);
+    log::debug!("opened document {id}");
+    Ok(id)
+}
+
+/// Close a document, forgetting its undo history and stopping its file watch.
+///
+/// # Arguments
+/// * `document_id` - The document to close
+/// * `state` - Application state holding the documents
+///
+/// # Returns
+/// * `Ok(Option<DocumentId>)` - The active document afterwards, if any are open
+/// * `Err(String)` - If the document isn't open
+#[tauri::command]
+pub async fn close_document(
+    document_id: DocumentId,
+    state: State<'_, AppData>,
+) -> Result<Option<DocumentId>, String> {
+    let active = {
+        let mut documents = state.documents.lock().await;
+        documents
+            .close(document_id)
+            .ok_or_else(|| format!("Document {document_id} isn't open"))?;
+        documents.active_id()
+    };
+    state.undo_history.forget(document_id);
+    if let Some(watch) = state.file_watch.lock().await.remove(&document_id) {
+        watch.stop();
+    }
+    if let Some(active) = active {
+        show_document(&state, active).await;
+    }
+    Ok(active)
+}
+
+/// Make a document the active one.
+///
+/// # Arguments
+/// * `document_id` - The document to bring to the front
+/// * `state` - Application state holding the documents
+///
+/// # Returns
+/// * `Ok(())` - The document is now active
+/// * `Err(String)` - If the document isn't open
+#[tauri::command]
+pub async fn switch_document(
+    document_id: DocumentId,
+    state: State<'_, AppData>,
+) -> Result<(), String> {
+    if !state.documents.lock().await.activate(document_id) {
+        return Err(format!("Document {document_id} isn't open"));
+    }
+    show_document(&state, document_id).await;
+    Ok(())
+}
+
+/// List the documents open in the editor.
+///
+/// # Returns
+/// The documents, in the order they were opened
+#[tauri::command]
+pub async fn list_documents(state: State<'_, AppData>) -> Result<Vec<DocumentInfo>, String> {
+    Ok(state.documents.lock().await.list())
+}
+
+/// Bring the rest of the app up to date with a newly active document: the
+/// undo menu shows its history, and extensions are told it was opened.
+async fn show_document(state: &AppData, id: DocumentId) {
+    state.undo_history.show(state, id);
+    let file_path = state
+        .documents
+        .lock()
+        .await
+        .get(id)
+        .and_then(|document| document.file_path.clone());
+    let is_new = file_path.is_none();
+    state
+        .extension_host
+        .lock()
+        .await
+        .notify_message_opened(file_path.as_deref(), is_new)
+        .await;
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closing_the_active_document_activates_a_neighbour() {
+        let mut documents = Documents::default();
+        let a = documents.create("A".to_string(), None);
+        let b = documents.create("B".to_string(), Some("b.hl7".to_string()));
+        let c = documents.create("C".to_string(), None);
+        assert_eq!(documents.active_id(), Some(c));
+
+        assert!(documents.activate(b));
+        assert_eq!(documents.active().unwrap().message, "B");
+        assert_eq!(
+            documents.close(b).unwrap().file_path.as_deref(),
+            Some("b.hl7")
+        );
+        assert_eq!(documents.active_id(), Some(c));
+
+        assert!(documents.close(c).is_some());
+        assert_eq!(documents.active_id(), Some(a));
+        assert!(documents.close(c).is_none());
+        assert!(!documents.activate(c));
+
+        assert!(documents.close(a).is_some());
+        assert_eq!(documents.active_id(), None);
+        assert!(documents.list().is_empty());
+    }
+
+    #[test]
+    fn closing_another_document_keeps_the_active_one() {
+        let mut documents = Documents::default();
+        let a = documents.create(String::new(), None);
+        let b = documents.create(String::new(), None);
+        assert!(documents.close(a).is_some());
+        assert_eq!(documents.active_id(), Some(b));
+        assert_eq!(
+            documents.list(),
+            [DocumentInfo {
+                id: b,
+                file_path: None,
+                active: true
+            }]
+        );
+        assert_ne!(documents.create(String::new(), None), a);
+    }
+}
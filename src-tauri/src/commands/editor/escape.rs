@@ -0,0 +1,314 @@
+//! Encoding and decoding HL7 escape sequences.
+//!
+//! A value containing one of the message's delimiters has to be escaped, or the
+//! delimiter splits the value: pasting "Smith & Jones" into a field quietly
+//! turns it into two subcomponents. These commands escape a selection (or the
+//! field at the cursor) so its text survives as written, and decode it again
+//! to read or edit the text behind the escapes.
+//!
+//! # Escape Sequences
+//!
+//! The sequences use the message's own delimiters, shown here with the usual
+//! `|^~\&`:
+//!
+//! * `\F\` - Field separator (`|`)
+//! * `\S\` - Component separator (`^`)
+//! * `\T\` - Subcomponent separator (`&`)
+//! * `\R\` - Repetition separator (`~`)
+//! * `\E\` - Escape character (`\`)
+//! * `\.br\` - Line break
+//! * `\Xdd...\` - Bytes written in hexadecimal (e.g., `\X09\` for a tab)
+//!
+//! Encoding writes line breaks as `\.br\` and other control characters as
+//! `\Xdd\`. Decoding reads `\Xdd...\` as UTF-8, and leaves sequences it doesn't
+//! know (such as `\H\` highlighting) as they are.
+
+use hl7_parser::message::Separators;
+use hl7_parser::Message;
+use serde::Serialize;
+
+use super::cursor::CursorRange;
+use super::offsets::Utf16Offsets;
+
+/// A message after escaping or unescaping part of it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EscapeEdit {
+    /// The whole message, with the range replaced
+    pub message: String,
+    /// Where the replaced text is in the new message, for selecting it
+    pub range: CursorRange,
+}
+
+/// Escape the delimiters in a range of a message.
+///
+/// # Arguments
+/// * `message` - The HL7 message as a string
+/// * `range` - Text to escape (UTF-16 offsets); an empty range escapes the
+///   whole field it's in
+///
+/// # Returns
+/// * `Ok(EscapeEdit)` - The message with the text escaped
+/// * `Err(String)` - If the message can't be parsed, or the range is empty and
+///   not in a field
+#[tauri::command]
+pub fn encode_escapes(message: &str, range: CursorRange) -> Result<EscapeEdit, String> {
+    replace_range(message, range, escape_text)
+}
+
+/// Replace the escape sequences in a range of a message with the text they
+/// stand for.
+///
+/// Decoding puts delimiters back into the message, so decoded text containing
+/// them changes the message's structure until it's escaped again.
+///
+/// # Arguments
+/// * `message` - The HL7 message as a string
+/// * `range` - Text to decode (UTF-16 offsets); an empty range decodes the
+///   whole field it's in
+///
+/// # Returns
+/// * `Ok(EscapeEdit)` - The message with the text decoded
+/// * `Err(String)` - If the message can't be parsed, or the range is empty and
+///   not in a field
+#[tauri::command]
+pub fn decode_escapes(message: &str, range: CursorRange) -> Result<EscapeEdit, String> {
+    replace_range(message, range, unescape_text)
+}
+
+fn replace_range(
+    message: &str,
+    range: CursorRange,
+    convert: impl Fn(&str, &Separators) -> String,
+) -> Result<EscapeEdit, String> {
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(message)
+        .map_err(|e| format!("Failed to parse message: {e}"))?;
+
+    let bytes = Utf16Offsets::new(message).byte_range(&range);
+    let range = if bytes.is_empty() {
+        field_at(&parsed, bytes.start)
+            .ok_or_else(|| "Select some text or place the cursor in a field".to_string())?
+    } else {
+        CursorRange {
+            start: bytes.start,
+            end: bytes.end,
+        }
+    };
+    let text = message
+        .get(range.start..range.end)
+        .ok_or_else(|| format!("Invalid range {}..{}", range.start, range.end))?;
+
+    let converted = convert(text, &parsed.separators);
+    let end = range.start + converted.len();
+    let message = format!(
+        "{}{converted}{}",
+        message.get(..range.start).unwrap_or_default(),
+        message.get(range.end..).unwrap_or_default()
+    );
+    let range = Utf16Offsets::new(&message).cursor_range(&(range.start..end));
+    Ok(EscapeEdit { message, range })
+}
+
+/// The range of the field containing a position, skipping the MSH fields that
+/// hold the delimiters.
+fn field_at(message: &Message, position: usize) -> Option<CursorRange> {
+    message.segments().find_map(|segment| {
+        let skip = if segment.name == "MSH" { 2 } else { 0 };
+        segment
+            .fields
+            .iter()
+            .skip(skip)
+            .find(|field| field.range.start <= position && position <= field.range.end)
+            .map(|field| CursorRange {
+                start: field.range.start,
+                end: field.range.end,
+            })
+    })
+}
+
+/// Escape every delimiter, line break, and control character in some text.
+pub fn escape_text(text: &str, separators: &Separators) -> String {
+    let escape = separators.escape;
+    let mut encoded = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        let code = if c == separators.field {
+            "F"
+        } else if c == separators.component {
+            "S"
+        } else if c == separators.subcomponent {
+            "T"
+        } else if c == separators.repetition {
+            "R"
+        } else if c == escape {
+            "E"
+        } else if c == '\r' || c == '\n' {
+            // a \r\n pair is one line break
+            if c == '\r' && chars.peek() == Some(&'\n') {
+                chars.next();
+            }
+            ".br"
+        } else if c.is_control() {
+            encoded.push_str(&format!("{escape}X{:02X}{escape}", u32::from(c)));
+            continue;
+        } else {
+            encoded.push(c);
+            continue;
+        };
+        encoded.push(escape);
+        encoded.push_str(code);
+        encoded.push(escape);
+    }
+    encoded
+}
+
+/// Replace every escape sequence in some text with the text it stands for.
+pub fn unescape_text(text: &str, separators: &Separators) -> String {
+    let escape = separators.escape;
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(escape) {
+        decoded.push_str(rest.get(..start).unwrap_or_default());
+        let after = rest.get(start + escape.len_utf8()..).unwrap_or_default();
+        let Some(length) = after.find(escape) else {
+            // an unterminated escape is kept as written
+            decoded.push_str(rest.get(start..).unwrap_or_default());
+            return decoded;
+        };
+        let code = after.get(..length).unwrap_or_default();
+        match decode_sequence(code, separators) {
+            Some(text) => decoded.push_str(&text),
+            None => {
+                decoded.push(escape);
+                decoded.push_str(code);
+                decoded.push(escape);
+            }
+        }
+        rest = after.get(length + escape.len_utf8()..).unwrap_or_default();
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// The text a single escape sequence (without its escape characters) stands
+/// for, or `None` if it isn't one this module decodes.
+fn decode_sequence(code: &str, separators: &Separators) -> Option<String> {
+    let c = match code {
+        "F" => separators.field,
+        "S" => separators.component,
+        "T" => separators.subcomponent,
+        "R" => separators.repetition,
+        "E" => separators.escape,
+        ".br" => '\n',
+        _ => {
+            let hex = code.strip_prefix('X')?;
+            if hex.is_empty() || hex.len() % 2 == 1 {
+                return None;
+            }
+            let bytes = (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+                .collect::<Option<Vec<u8>>>()?;
+            return Some(String::from_utf8_lossy(&bytes).into_owned());
+        }
+    };
+    Some(c.to_string())
+}
+
+/// Ranges of the escape sequences in some text, relative to its start.
+///
+/// Used by syntax highlighting to mark escaped content.
+pub(crate) fn escape_sequence_ranges(text: &str, escape: char) -> Vec<std::ops::Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut search = 0;
+    while let Some(offset) = text.get(search..).and_then(|rest| rest.find(escape)) {
+        let start = search + offset;
+        let body = start + escape.len_utf8();
+        let Some(length) = text.get(body..).and_then(|rest| rest.find(escape)) else {
+            break;
+        };
+        let end = body + length + escape.len_utf8();
+        if length > 0 {
+            ranges.push(start..end);
+        }
+        search = end;
+    }
+    ranges
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str = "MSH|^~\\&|APP|FAC|||20250101||ADT^A01|1|P|2.5.1\r\
+        NTE|1||Smith & Jones|x";
+
+    fn separators() -> Separators {
+        hl7_parser::parse_message(MESSAGE).unwrap().separators
+    }
+
+    #[test]
+    fn round_trips_delimiters_and_control_characters() {
+        let separators = separators();
+        let text = "a|b^c~d\\e&f\r\ng\th";
+        let encoded = escape_text(text, &separators);
+        assert_eq!(encoded, "a\\F\\b\\S\\c\\R\\d\\E\\e\\T\\f\\.br\\g\\X09\\h");
+        assert_eq!(unescape_text(&encoded, &separators), "a|b^c~d\\e&f\ng\th");
+
+        // multi-byte hex, unknown sequences, and unterminated escapes
+        assert_eq!(
+            unescape_text("\\XC3A9\\t\\H\\x\\N\\", &separators),
+            "ét\\H\\x\\N\\"
+        );
+        assert_eq!(unescape_text("tail\\F", &separators), "tail\\F");
+    }
+
+    #[test]
+    fn escapes_a_selection_or_the_field_at_the_cursor() {
+        let field = MESSAGE.find("Smith").unwrap();
+        let selected = encode_escapes(
+            MESSAGE,
+            CursorRange {
+                start: field + 6,
+                end: field + 7,
+            },
+        )
+        .unwrap();
+        assert!(selected.message.ends_with("|Smith \\T\\ Jones|x"));
+        assert_eq!(
+            &selected.message[selected.range.start..selected.range.end],
+            "\\T\\"
+        );
+
+        let cursor = CursorRange {
+            start: field + 2,
+            end: field + 2,
+        };
+        let whole = encode_escapes(MESSAGE, cursor.clone()).unwrap();
+        assert_eq!(whole.message, selected.message);
+        assert_eq!(
+            &whole.message[whole.range.start..whole.range.end],
+            "Smith \\T\\ Jones"
+        );
+        let decoded = decode_escapes(&whole.message, cursor).unwrap();
+        assert_eq!(decoded.message, MESSAGE);
+
+        // the cursor on a segment name isn't in a field
+        assert!(encode_escapes(
+            MESSAGE,
+            CursorRange {
+                start: field - 7,
+                end: field - 7
+            }
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn finds_escape_sequences() {
+        assert_eq!(
+            escape_sequence_ranges("a\\F\\b\\\\c\\X0D\\d\\", '\\'),
+            [1..4, 8..13]
+        );
+    }
+}
@@ -203,7 +203,7 @@ fn component_to_value(component: &Component) -> Value {
 }
 
 /// Checks if a JSON value is considered empty (null or empty object/array/string).
-fn is_empty_value(value: &Value) -> bool {
+pub(super) fn is_empty_value(value: &Value) -> bool {
     match value {
         Value::Null => true,
         Value::String(s) => s.is_empty(),
@@ -0,0 +1,1344 @@
+//! FHIR R4 conversion commands.
+//!
+//! Converts HL7 v2 messages to FHIR R4 JSON bundles and back, for testing
+//! hybrid environments where some systems speak v2 and others FHIR.
+//!
+//! # Why a Separate Module?
+//!
+//! The JSON/YAML/TOML exporters are lossless structural dumps of the message.
+//! FHIR conversion is a semantic mapping instead: segments become resources,
+//! and only the fields with a clear FHIR counterpart survive the trip. Keeping
+//! it apart from `export`/`import` makes that difference obvious.
+//!
+//! # Mapping
+//!
+//! Resources are produced from the segments present rather than from the
+//! message type, so any message carrying these segments converts:
+//!
+//! | Segment | Resource            | Typical message |
+//! |---------|---------------------|-----------------|
+//! | PID     | `Patient`           | ADT, ORU        |
+//! | PV1     | `Encounter`         | ADT             |
+//! | OBR     | `DiagnosticReport`  | ORU             |
+//! | OBX     | `Observation`       | ORU             |
+//!
+//! OBX segments following an OBR are linked to its report through
+//! `DiagnosticReport.result`. Every resource references the patient.
+//!
+//! Importing reverses the mapping: bundles with reports or observations become
+//! `ORU^R01`, bundles with an encounter become an ADT message whose trigger
+//! event follows the encounter status, and anything else becomes `ADT^A08`.
+//!
+//! # Limitations
+//!
+//! Only the commonly used fields of each segment are mapped, so a round trip
+//! through FHIR is not lossless. Use the JSON exporter when the goal is to
+//! preserve the message exactly.
+
+use super::export::is_empty_value;
+use super::import::{tree_to_message, MessageImport, SegmentImport};
+use hl7_parser::message::{Message, Segment, Separators};
+use indexmap::IndexMap;
+use rand::distr::{Alphanumeric, SampleString};
+use serde_json::{json, Map, Value};
+
+/// Code system for v2 identifier types (PID.3.5).
+const V2_IDENTIFIER_TYPE: &str = "http://terminology.hl7.org/CodeSystem/v2-0203";
+
+/// Code system for v2 patient classes (PV1.2) that have no v3 equivalent.
+const V2_PATIENT_CLASS: &str = "http://terminology.hl7.org/CodeSystem/v2-0004";
+
+/// Code system for v2 abnormal flags (OBX.8).
+const V2_ABNORMAL_FLAGS: &str = "http://terminology.hl7.org/CodeSystem/v2-0078";
+
+/// Code system for encounter classes.
+const V3_ACT_CODE: &str = "http://terminology.hl7.org/CodeSystem/v3-ActCode";
+
+/// Code system used when the encounter class is missing.
+const V3_NULL_FLAVOR: &str = "http://terminology.hl7.org/CodeSystem/v3-NullFlavor";
+
+/// Well-known coding systems, as (v2 name, FHIR URI).
+const CODING_SYSTEMS: &[(&str, &str)] = &[
+    ("LN", "http://loinc.org"),
+    ("SCT", "http://snomed.info/sct"),
+    ("UCUM", "http://unitsofmeasure.org"),
+    ("I10", "http://hl7.org/fhir/sid/icd-10"),
+];
+
+/// Decoded values of a segment, indexed by field, repetition, and component.
+///
+/// Field numbering follows HL7 conventions, including for MSH where field 1
+/// is the field separator.
+struct SegmentValues(Vec<Vec<Vec<String>>>);
+
+impl SegmentValues {
+    fn new(separators: &Separators, segment: &Segment) -> Self {
+        Self(
+            segment
+                .fields
+                .iter()
+                .map(|field| {
+                    field
+                        .repeats
+                        .iter()
+                        .map(|repeat| {
+                            repeat
+                                .components
+                                .iter()
+                                .map(|component| {
+                                    separators.decode(component.raw_value()).to_string()
+                                })
+                                .collect()
+                        })
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    /// All repetitions of a field, each as a list of component values.
+    fn repeats(&self, field: usize) -> &[Vec<String>] {
+        field
+            .checked_sub(1)
+            .and_then(|i| self.0.get(i))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// A component of the first repetition of a field, if it isn't empty.
+    fn get(&self, field: usize, component: usize) -> Option<&str> {
+        self.repeats(field)
+            .first()
+            .and_then(|repeat| component_of(repeat, component))
+    }
+}
+
+/// A component of a field repetition, if it isn't empty.
+fn component_of(repeat: &[String], component: usize) -> Option<&str> {
+    component
+        .checked_sub(1)
+        .and_then(|i| repeat.get(i))
+        .map(String::as_str)
+        .filter(|value| !value.is_empty())
+}
+
+/// Exports an HL7 message to a FHIR R4 bundle in JSON format.
+#[tauri::command]
+pub fn export_to_fhir(message: &str) -> Result<String, String> {
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(message)
+        .map_err(|e| format!("Failed to parse message: {e}"))?;
+    let bundle = message_to_bundle(&parsed)?;
+    serde_json::to_string_pretty(&bundle).map_err(|e| format!("Failed to serialise to JSON: {e}"))
+}
+
+/// Imports an HL7 message from a FHIR R4 bundle in JSON format.
+#[tauri::command]
+pub fn import_from_fhir(content: &str) -> Result<String, String> {
+    let bundle: Value =
+        serde_json::from_str(content).map_err(|e| format!("Failed to parse JSON: {e}"))?;
+    let import = bundle_to_import(&bundle)?;
+    tree_to_message(&import)
+}
+
+// ============================================================================
+// HL7 v2 -> FHIR
+// ============================================================================
+
+/// Converts a parsed HL7 message to a FHIR collection bundle.
+fn message_to_bundle(message: &Message) -> Result<Value, String> {
+    let segments: Vec<(&str, SegmentValues)> = message
+        .segments()
+        .map(|segment| {
+            (
+                segment.name,
+                SegmentValues::new(&message.separators, segment),
+            )
+        })
+        .collect();
+
+    let msh = segments
+        .iter()
+        .find(|(name, _)| *name == "MSH")
+        .map(|(_, values)| values);
+    let trigger_event = msh.and_then(|msh| msh.get(9, 2));
+
+    let mut entries: Vec<(String, Value)> = Vec::new();
+
+    // patient first, since everything else references it
+    let patient_url = segments
+        .iter()
+        .find(|(name, _)| *name == "PID")
+        .map(|(_, pid)| {
+            let url = new_full_url();
+            entries.push((url.clone(), pid_to_patient(pid)));
+            url
+        });
+
+    if let Some((_, pv1)) = segments.iter().find(|(name, _)| *name == "PV1") {
+        let encounter = pv1_to_encounter(pv1, trigger_event, patient_url.as_deref());
+        entries.push((new_full_url(), encounter));
+    }
+
+    // reports collect the observations that follow them
+    let mut report: Option<(String, Value, Option<String>)> = None;
+    for (name, values) in &segments {
+        match *name {
+            "OBR" => {
+                if let Some((url, resource, _)) = report.take() {
+                    entries.push((url, resource));
+                }
+                let effective = values.get(7, 1).and_then(hl7_to_fhir_datetime);
+                report = Some((
+                    new_full_url(),
+                    obr_to_report(values, patient_url.as_deref()),
+                    effective,
+                ));
+            }
+            "OBX" => {
+                let report_effective = report.as_ref().and_then(|(_, _, e)| e.as_deref());
+                let observation =
+                    obx_to_observation(values, patient_url.as_deref(), report_effective);
+                let url = new_full_url();
+                if let Some((_, resource, _)) = report.as_mut() {
+                    push_to_array(resource, "result", json!({ "reference": url }));
+                }
+                entries.push((url, observation));
+            }
+            _ => {}
+        }
+    }
+    if let Some((url, resource, _)) = report.take() {
+        entries.push((url, resource));
+    }
+
+    if entries.is_empty() {
+        return Err(
+            "Message has no segments that map to FHIR resources (PID, PV1, OBR, or OBX)"
+                .to_string(),
+        );
+    }
+
+    let mut bundle = Map::new();
+    bundle.insert("resourceType".to_string(), json!("Bundle"));
+    if let Some(control_id) = msh.and_then(|msh| msh.get(10, 1)) {
+        bundle.insert("identifier".to_string(), json!({ "value": control_id }));
+    }
+    bundle.insert("type".to_string(), json!("collection"));
+    insert_opt(
+        &mut bundle,
+        "timestamp",
+        msh.and_then(|msh| msh.get(7, 1))
+            .and_then(hl7_to_fhir_datetime)
+            .map(Value::String),
+    );
+    bundle.insert(
+        "entry".to_string(),
+        Value::Array(
+            entries
+                .into_iter()
+                .map(|(url, resource)| json!({ "fullUrl": url, "resource": resource }))
+                .collect(),
+        ),
+    );
+
+    Ok(Value::Object(bundle))
+}
+
+/// Maps a PID segment to a Patient resource.
+fn pid_to_patient(pid: &SegmentValues) -> Value {
+    let mut patient = Map::new();
+    patient.insert("resourceType".to_string(), json!("Patient"));
+
+    let identifiers: Vec<Value> = pid
+        .repeats(3)
+        .iter()
+        .filter_map(|cx| {
+            let mut identifier = Map::new();
+            identifier.insert("value".to_string(), json!(component_of(cx, 1)?));
+            if let Some(code) = component_of(cx, 5) {
+                identifier.insert(
+                    "type".to_string(),
+                    json!({ "coding": [{ "system": V2_IDENTIFIER_TYPE, "code": code }] }),
+                );
+            }
+            if let Some(assigner) = component_of(cx, 4) {
+                identifier.insert("assigner".to_string(), json!({ "display": assigner }));
+            }
+            Some(Value::Object(identifier))
+        })
+        .collect();
+    insert_array(&mut patient, "identifier", identifiers);
+
+    let names: Vec<Value> = pid
+        .repeats(5)
+        .iter()
+        .filter_map(|xpn| xpn_to_name(xpn))
+        .collect();
+    insert_array(&mut patient, "name", names);
+
+    let telecoms: Vec<Value> = pid
+        .repeats(13)
+        .iter()
+        .filter_map(|xtn| component_of(xtn, 1))
+        .map(|number| json!({ "system": "phone", "value": number }))
+        .collect();
+    insert_array(&mut patient, "telecom", telecoms);
+
+    insert_opt(
+        &mut patient,
+        "gender",
+        pid.get(8, 1).map(|sex| json!(v2_sex_to_gender(sex))),
+    );
+    insert_opt(
+        &mut patient,
+        "birthDate",
+        pid.get(7, 1)
+            .map(|dob| dob.get(..8).unwrap_or(dob))
+            .and_then(hl7_to_fhir_datetime)
+            .map(Value::String),
+    );
+
+    let addresses: Vec<Value> = pid
+        .repeats(11)
+        .iter()
+        .filter_map(|xad| xad_to_address(xad))
+        .collect();
+    insert_array(&mut patient, "address", addresses);
+
+    Value::Object(patient)
+}
+
+/// Maps an XPN repetition to a HumanName.
+fn xpn_to_name(xpn: &[String]) -> Option<Value> {
+    let mut name = Map::new();
+    insert_opt(&mut name, "family", component_of(xpn, 1).map(|v| json!(v)));
+    let given: Vec<Value> = [2, 3]
+        .into_iter()
+        .filter_map(|i| component_of(xpn, i))
+        .map(|v| json!(v))
+        .collect();
+    insert_array(&mut name, "given", given);
+    insert_opt(
+        &mut name,
+        "suffix",
+        component_of(xpn, 4).map(|v| json!([v])),
+    );
+    insert_opt(
+        &mut name,
+        "prefix",
+        component_of(xpn, 5).map(|v| json!([v])),
+    );
+    (!name.is_empty()).then_some(Value::Object(name))
+}
+
+/// Maps an XAD repetition to an Address.
+fn xad_to_address(xad: &[String]) -> Option<Value> {
+    let mut address = Map::new();
+    let lines: Vec<Value> = [1, 2]
+        .into_iter()
+        .filter_map(|i| component_of(xad, i))
+        .map(|v| json!(v))
+        .collect();
+    insert_array(&mut address, "line", lines);
+    for (component, key) in [(3, "city"), (4, "state"), (5, "postalCode"), (6, "country")] {
+        insert_opt(
+            &mut address,
+            key,
+            component_of(xad, component).map(|v| json!(v)),
+        );
+    }
+    (!address.is_empty()).then_some(Value::Object(address))
+}
+
+/// Maps a PV1 segment to an Encounter resource.
+fn pv1_to_encounter(
+    pv1: &SegmentValues,
+    trigger_event: Option<&str>,
+    patient_url: Option<&str>,
+) -> Value {
+    let mut encounter = Map::new();
+    encounter.insert("resourceType".to_string(), json!("Encounter"));
+
+    if let Some(visit_number) = pv1.get(19, 1) {
+        encounter.insert("identifier".to_string(), json!([{ "value": visit_number }]));
+    }
+
+    let status = match trigger_event {
+        Some("A03") => "finished",
+        Some("A05" | "A14") => "planned",
+        Some("A11" | "A27") => "cancelled",
+        _ if pv1.get(45, 1).is_some() => "finished",
+        _ => "in-progress",
+    };
+    encounter.insert("status".to_string(), json!(status));
+
+    let class = match pv1.get(2, 1) {
+        Some("E") => json!({ "system": V3_ACT_CODE, "code": "EMER", "display": "emergency" }),
+        Some("I") => {
+            json!({ "system": V3_ACT_CODE, "code": "IMP", "display": "inpatient encounter" })
+        }
+        Some("O") => json!({ "system": V3_ACT_CODE, "code": "AMB", "display": "ambulatory" }),
+        Some("P") => json!({ "system": V3_ACT_CODE, "code": "PRENC", "display": "pre-admission" }),
+        Some(other) => json!({ "system": V2_PATIENT_CLASS, "code": other }),
+        None => json!({ "system": V3_NULL_FLAVOR, "code": "UNK", "display": "unknown" }),
+    };
+    encounter.insert("class".to_string(), class);
+
+    insert_opt(&mut encounter, "subject", reference(patient_url));
+
+    if let Some(location) = pv1.get(3, 1) {
+        encounter.insert(
+            "location".to_string(),
+            json!([{ "location": { "display": location } }]),
+        );
+    }
+
+    let mut period = Map::new();
+    for (field, key) in [(44, "start"), (45, "end")] {
+        insert_opt(
+            &mut period,
+            key,
+            pv1.get(field, 1)
+                .and_then(hl7_to_fhir_datetime)
+                .map(Value::String),
+        );
+    }
+    if !period.is_empty() {
+        encounter.insert("period".to_string(), Value::Object(period));
+    }
+
+    Value::Object(encounter)
+}
+
+/// Maps an OBR segment to a DiagnosticReport resource, without results.
+fn obr_to_report(obr: &SegmentValues, patient_url: Option<&str>) -> Value {
+    let mut report = Map::new();
+    report.insert("resourceType".to_string(), json!("DiagnosticReport"));
+
+    // prefer the filler order number, as it identifies the report itself
+    if let Some(order_number) = obr.get(3, 1).or_else(|| obr.get(2, 1)) {
+        report.insert("identifier".to_string(), json!([{ "value": order_number }]));
+    }
+
+    let status = match obr.get(25, 1) {
+        Some("F") => "final",
+        Some("P" | "R") => "preliminary",
+        Some("C") => "corrected",
+        Some("X") => "cancelled",
+        Some("A") => "partial",
+        Some("I" | "O" | "S") => "registered",
+        _ => "unknown",
+    };
+    report.insert("status".to_string(), json!(status));
+    report.insert(
+        "code".to_string(),
+        obr.repeats(4)
+            .first()
+            .map(|ce| coded_to_concept(ce))
+            .unwrap_or_else(|| json!({ "text": "unknown" })),
+    );
+    insert_opt(&mut report, "subject", reference(patient_url));
+    insert_opt(
+        &mut report,
+        "effectiveDateTime",
+        obr.get(7, 1)
+            .and_then(hl7_to_fhir_datetime)
+            .map(Value::String),
+    );
+
+    Value::Object(report)
+}
+
+/// Maps an OBX segment to an Observation resource.
+fn obx_to_observation(
+    obx: &SegmentValues,
+    patient_url: Option<&str>,
+    report_effective: Option<&str>,
+) -> Value {
+    let mut observation = Map::new();
+    observation.insert("resourceType".to_string(), json!("Observation"));
+
+    let status = match obx.get(11, 1) {
+        Some("F") => "final",
+        Some("P") => "preliminary",
+        Some("C") => "corrected",
+        Some("X") => "cancelled",
+        Some("R") => "registered",
+        Some("W" | "D") => "entered-in-error",
+        _ => "unknown",
+    };
+    observation.insert("status".to_string(), json!(status));
+    observation.insert(
+        "code".to_string(),
+        obx.repeats(3)
+            .first()
+            .map(|ce| coded_to_concept(ce))
+            .unwrap_or_else(|| json!({ "text": "unknown" })),
+    );
+    insert_opt(&mut observation, "subject", reference(patient_url));
+    insert_opt(
+        &mut observation,
+        "effectiveDateTime",
+        obx.get(14, 1)
+            .and_then(hl7_to_fhir_datetime)
+            .or_else(|| report_effective.map(str::to_string))
+            .map(Value::String),
+    );
+
+    if let Some(value) = obx.repeats(5).first() {
+        let (key, value) = match obx.get(2, 1) {
+            Some("NM") => match component_of(value, 1).and_then(|v| v.parse::<f64>().ok()) {
+                Some(number) => {
+                    let mut quantity = Map::new();
+                    quantity.insert("value".to_string(), json!(number));
+                    insert_opt(&mut quantity, "unit", obx.get(6, 1).map(|u| json!(u)));
+                    ("valueQuantity", Value::Object(quantity))
+                }
+                None => ("valueString", json!(join_components(value))),
+            },
+            Some("CE" | "CWE" | "CNE") => ("valueCodeableConcept", coded_to_concept(value)),
+            Some("DT" | "DTM" | "TS") => {
+                match component_of(value, 1).and_then(hl7_to_fhir_datetime) {
+                    Some(datetime) => ("valueDateTime", json!(datetime)),
+                    None => ("valueString", json!(join_components(value))),
+                }
+            }
+            _ => ("valueString", json!(join_components(value))),
+        };
+        if !is_empty_value(&value) {
+            observation.insert(key.to_string(), value);
+        }
+    }
+
+    if let Some(range) = obx.get(7, 1) {
+        observation.insert("referenceRange".to_string(), json!([{ "text": range }]));
+    }
+    if let Some(flag) = obx.get(8, 1) {
+        observation.insert(
+            "interpretation".to_string(),
+            json!([{ "coding": [{ "system": V2_ABNORMAL_FLAGS, "code": flag }] }]),
+        );
+    }
+
+    Value::Object(observation)
+}
+
+/// Maps a CE/CWE repetition to a CodeableConcept.
+fn coded_to_concept(ce: &[String]) -> Value {
+    let mut coding = Map::new();
+    if let Some(system) = component_of(ce, 3) {
+        let uri = CODING_SYSTEMS
+            .iter()
+            .find(|(name, _)| *name == system)
+            .map_or(system, |(_, uri)| uri);
+        coding.insert("system".to_string(), json!(uri));
+    }
+    insert_opt(&mut coding, "code", component_of(ce, 1).map(|v| json!(v)));
+    insert_opt(
+        &mut coding,
+        "display",
+        component_of(ce, 2).map(|v| json!(v)),
+    );
+
+    let mut concept = Map::new();
+    if !coding.is_empty() {
+        concept.insert("coding".to_string(), json!([coding]));
+    }
+    insert_opt(
+        &mut concept,
+        "text",
+        component_of(ce, 2)
+            .or_else(|| component_of(ce, 1))
+            .map(|v| json!(v)),
+    );
+    Value::Object(concept)
+}
+
+/// Maps a v2 administrative sex (PID.8) to a FHIR gender.
+fn v2_sex_to_gender(sex: &str) -> &'static str {
+    match sex {
+        "M" => "male",
+        "F" => "female",
+        "O" | "A" => "other",
+        _ => "unknown",
+    }
+}
+
+/// Converts an HL7 DTM value to a FHIR date or dateTime.
+///
+/// Values with a time but no offset are interpreted in the local timezone,
+/// since FHIR requires an offset whenever a time is present.
+///
+/// # Arguments
+/// * `value` - HL7 timestamp (e.g., "20231215", "20231215103000-0500")
+///
+/// # Returns
+/// The FHIR value (e.g., "2023-12-15", "2023-12-15T10:30:00-05:00"), or `None`
+/// if the value isn't a valid HL7 timestamp
+fn hl7_to_fhir_datetime(value: &str) -> Option<String> {
+    let (datetime, offset) = match value.find(['+', '-']) {
+        Some(i) => (value.get(..i)?, value.get(i..)),
+        None => (value, None),
+    };
+    // fractional seconds aren't worth carrying across
+    let digits = datetime.split('.').next()?;
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let part = |range: std::ops::Range<usize>| digits.get(range);
+    let date = match digits.len() {
+        4 => return Some(digits.to_string()),
+        6 => return Some(format!("{}-{}", part(0..4)?, part(4..6)?)),
+        8 | 10 | 12 | 14 => format!("{}-{}-{}", part(0..4)?, part(4..6)?, part(6..8)?),
+        _ => return None,
+    };
+    if digits.len() == 8 {
+        return Some(date);
+    }
+
+    let hour = part(8..10)?;
+    let minute = part(10..12).unwrap_or("00");
+    let second = part(12..14).unwrap_or("00");
+
+    let offset = match offset {
+        Some(offset) => {
+            let sign = offset.get(..1)?;
+            format!("{sign}{}:{}", offset.get(1..3)?, offset.get(3..5)?)
+        }
+        None => {
+            let civil = jiff::civil::DateTime::new(
+                part(0..4)?.parse().ok()?,
+                part(4..6)?.parse().ok()?,
+                part(6..8)?.parse().ok()?,
+                hour.parse().ok()?,
+                minute.parse().ok()?,
+                second.parse().ok()?,
+                0,
+            )
+            .ok()?;
+            let zoned = civil.to_zoned(jiff::tz::TimeZone::system()).ok()?;
+            zoned.strftime("%:z").to_string()
+        }
+    };
+
+    Some(format!("{date}T{hour}:{minute}:{second}{offset}"))
+}
+
+/// Joins the components of a repetition back into a single display string.
+fn join_components(repeat: &[String]) -> String {
+    let last = repeat
+        .iter()
+        .rposition(|c| !c.is_empty())
+        .map_or(0, |i| i + 1);
+    repeat
+        .iter()
+        .take(last)
+        .cloned()
+        .collect::<Vec<_>>()
+        .join("^")
+}
+
+/// Builds a reference to a bundle entry.
+fn reference(url: Option<&str>) -> Option<Value> {
+    url.map(|url| json!({ "reference": url }))
+}
+
+/// Generates a unique `fullUrl` for a bundle entry.
+fn new_full_url() -> String {
+    format!("urn:uuid:{}", uuid::Uuid::new_v4())
+}
+
+/// Inserts a value into a JSON object if it is present.
+fn insert_opt(object: &mut Map<String, Value>, key: &str, value: Option<Value>) {
+    if let Some(value) = value {
+        object.insert(key.to_string(), value);
+    }
+}
+
+/// Inserts an array into a JSON object if it isn't empty.
+fn insert_array(object: &mut Map<String, Value>, key: &str, values: Vec<Value>) {
+    if !values.is_empty() {
+        object.insert(key.to_string(), Value::Array(values));
+    }
+}
+
+/// Appends a value to an array property of a JSON object, creating it if needed.
+fn push_to_array(resource: &mut Value, key: &str, value: Value) {
+    if let Some(object) = resource.as_object_mut() {
+        if let Some(array) = object
+            .entry(key)
+            .or_insert_with(|| Value::Array(Vec::new()))
+            .as_array_mut()
+        {
+            array.push(value);
+        }
+    }
+}
+
+// ============================================================================
+// FHIR -> HL7 v2
+// ============================================================================
+
+/// Converts a FHIR bundle to the import structure used by the JSON importer.
+fn bundle_to_import(bundle: &Value) -> Result<MessageImport, String> {
+    if bundle.get("resourceType").and_then(Value::as_str) != Some("Bundle") {
+        return Err("Expected a FHIR Bundle resource".to_string());
+    }
+
+    let entries: Vec<(Option<&str>, &Value)> = bundle
+        .get("entry")
+        .and_then(Value::as_array)
+        .map(|entries| {
+            entries
+                .iter()
+                .filter_map(|entry| {
+                    let resource = entry.get("resource")?;
+                    Some((entry.get("fullUrl").and_then(Value::as_str), resource))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    let of_type = |resource_type: &str| {
+        entries
+            .iter()
+            .filter(|(_, resource)| {
+                resource.get("resourceType").and_then(Value::as_str) == Some(resource_type)
+            })
+            .copied()
+            .collect()
+    };
+    let patient = of_type("Patient").first().map(|(_, resource)| *resource);
+    let encounter = of_type("Encounter").first().map(|(_, resource)| *resource);
+    let reports = of_type("DiagnosticReport");
+    let observations = of_type("Observation");
+
+    if patient.is_none() && encounter.is_none() && reports.is_empty() && observations.is_empty() {
+        return Err(
+            "Bundle has no resources that map to HL7 segments (Patient, Encounter, DiagnosticReport, or Observation)"
+                .to_string(),
+        );
+    }
+
+    let (message_type, trigger_event, structure) =
+        if !reports.is_empty() || !observations.is_empty() {
+            ("ORU", "R01", "ORU_R01")
+        } else if let Some(encounter) = encounter {
+            let event = match encounter.get("status").and_then(Value::as_str) {
+                Some("finished") => "A03",
+                Some("planned") => "A05",
+                Some("cancelled") => "A11",
+                _ => "A01",
+            };
+            (
+                "ADT",
+                event,
+                if event == "A05" { "ADT_A05" } else { "ADT_A01" },
+            )
+        } else {
+            ("ADT", "A08", "ADT_A01")
+        };
+
+    let timestamp = bundle
+        .get("timestamp")
+        .and_then(Value::as_str)
+        .and_then(fhir_to_hl7_datetime)
+        .unwrap_or_else(|| jiff::Zoned::now().strftime("%Y%m%d%H%M%S%z").to_string());
+    let control_id = bundle
+        .get("identifier")
+        .and_then(|identifier| identifier.get("value"))
+        .and_then(Value::as_str)
+        .map_or_else(
+            || Alphanumeric.sample_string(&mut rand::rng(), 20),
+            str::to_string,
+        );
+
+    let mut segments = vec![segment(
+        "MSH",
+        [
+            ("1", json!("|")),
+            ("2", json!("^~\\&")),
+            ("3", json!("HERMES")),
+            ("7", json!(timestamp)),
+            (
+                "9",
+                json!({ "1": message_type, "2": trigger_event, "3": structure }),
+            ),
+            ("10", json!(control_id)),
+            ("11", json!("P")),
+            ("12", json!("2.5.1")),
+        ],
+    )];
+
+    if message_type == "ADT" {
+        segments.push(segment(
+            "EVN",
+            [("1", json!(trigger_event)), ("2", json!(timestamp))],
+        ));
+    }
+    if let Some(patient) = patient {
+        segments.push(patient_to_pid(patient));
+    }
+    if let Some(encounter) = encounter {
+        segments.push(encounter_to_pv1(encounter));
+    }
+
+    // observations are emitted under the report that references them, and any
+    // left over are emitted on their own at the end
+    let mut emitted: Vec<&Value> = Vec::new();
+    let mut obx_set_id = 0;
+    for (report_index, (_, report)) in reports.iter().enumerate() {
+        segments.push(report_to_obr(report, report_index + 1));
+        let results = report
+            .get("result")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        for reference in results.iter().filter_map(|r| r.get("reference")?.as_str()) {
+            if let Some((_, observation)) =
+                observations.iter().find(|(url, _)| *url == Some(reference))
+            {
+                obx_set_id += 1;
+                segments.push(observation_to_obx(observation, obx_set_id));
+                emitted.push(*observation);
+            }
+        }
+    }
+    for (_, observation) in &observations {
+        if !emitted.iter().any(|e| std::ptr::eq(*e, *observation)) {
+            obx_set_id += 1;
+            segments.push(observation_to_obx(observation, obx_set_id));
+        }
+    }
+
+    Ok(MessageImport { segments })
+}
+
+/// Maps a Patient resource to a PID segment.
+fn patient_to_pid(patient: &Value) -> SegmentImport {
+    let mut fields = IndexMap::new();
+    fields.insert("1".to_string(), json!("1"));
+
+    let identifiers: Vec<Value> = array(patient, "identifier")
+        .iter()
+        .filter_map(|identifier| {
+            let mut cx = Map::new();
+            cx.insert("1".to_string(), json!(string(identifier, "value")?));
+            insert_opt(
+                &mut cx,
+                "4",
+                identifier
+                    .get("assigner")
+                    .and_then(|a| string(a, "display"))
+                    .map(|v| json!(v)),
+            );
+            insert_opt(
+                &mut cx,
+                "5",
+                identifier
+                    .get("type")
+                    .and_then(first_coding)
+                    .and_then(|c| string(c, "code"))
+                    .map(|v| json!(v)),
+            );
+            Some(Value::Object(cx))
+        })
+        .collect();
+    insert_field(&mut fields, 3, repeats(identifiers));
+
+    let names: Vec<Value> = array(patient, "name")
+        .iter()
+        .map(|name| {
+            let given: Vec<&str> = array(name, "given")
+                .iter()
+                .filter_map(Value::as_str)
+                .collect();
+            let mut xpn = Map::new();
+            insert_opt(&mut xpn, "1", string(name, "family").map(|v| json!(v)));
+            insert_opt(&mut xpn, "2", given.first().map(|v| json!(v)));
+            insert_opt(&mut xpn, "3", given.get(1).map(|v| json!(v)));
+            insert_opt(&mut xpn, "4", array(name, "suffix").first().cloned());
+            insert_opt(&mut xpn, "5", array(name, "prefix").first().cloned());
+            Value::Object(xpn)
+        })
+        .collect();
+    insert_field(&mut fields, 5, repeats(names));
+
+    insert_field(
+        &mut fields,
+        7,
+        string(patient, "birthDate")
+            .and_then(fhir_to_hl7_datetime)
+            .map(Value::String),
+    );
+    insert_field(
+        &mut fields,
+        8,
+        string(patient, "gender").map(|gender| {
+            json!(match gender {
+                "male" => "M",
+                "female" => "F",
+                "other" => "O",
+                _ => "U",
+            })
+        }),
+    );
+
+    let addresses: Vec<Value> = array(patient, "address")
+        .iter()
+        .map(|address| {
+            let lines = array(address, "line");
+            let mut xad = Map::new();
+            insert_opt(&mut xad, "1", lines.first().cloned());
+            insert_opt(&mut xad, "2", lines.get(1).cloned());
+            for (component, key) in [
+                ("3", "city"),
+                ("4", "state"),
+                ("5", "postalCode"),
+                ("6", "country"),
+            ] {
+                insert_opt(&mut xad, component, string(address, key).map(|v| json!(v)));
+            }
+            Value::Object(xad)
+        })
+        .collect();
+    insert_field(&mut fields, 11, repeats(addresses));
+
+    let phones: Vec<Value> = array(patient, "telecom")
+        .iter()
+        .filter(|telecom| matches!(string(telecom, "system"), None | Some("phone")))
+        .filter_map(|telecom| string(telecom, "value").map(|v| json!(v)))
+        .collect();
+    insert_field(&mut fields, 13, repeats(phones));
+
+    SegmentImport {
+        segment: "PID".to_string(),
+        fields,
+    }
+}
+
+/// Maps an Encounter resource to a PV1 segment.
+fn encounter_to_pv1(encounter: &Value) -> SegmentImport {
+    let mut fields = IndexMap::new();
+    fields.insert("1".to_string(), json!("1"));
+
+    let class = encounter.get("class");
+    let patient_class = match class.and_then(|c| string(c, "code")) {
+        Some("EMER") => Some("E"),
+        Some("IMP" | "ACUTE" | "NONAC") => Some("I"),
+        Some("AMB" | "VR" | "HH") => Some("O"),
+        Some("PRENC") => Some("P"),
+        Some(code) if class.and_then(|c| string(c, "system")) == Some(V2_PATIENT_CLASS) => {
+            Some(code)
+        }
+        _ => None,
+    };
+    insert_field(&mut fields, 2, patient_class.map(|v| json!(v)));
+
+    insert_field(
+        &mut fields,
+        3,
+        array(encounter, "location")
+            .first()
+            .and_then(|l| l.get("location"))
+            .and_then(|l| string(l, "display"))
+            .map(|v| json!(v)),
+    );
+    insert_field(
+        &mut fields,
+        19,
+        array(encounter, "identifier")
+            .first()
+            .and_then(|i| string(i, "value"))
+            .map(|v| json!(v)),
+    );
+
+    let period = encounter.get("period");
+    for (field, key) in [(44, "start"), (45, "end")] {
+        insert_field(
+            &mut fields,
+            field,
+            period
+                .and_then(|p| string(p, key))
+                .and_then(fhir_to_hl7_datetime)
+                .map(Value::String),
+        );
+    }
+
+    SegmentImport {
+        segment: "PV1".to_string(),
+        fields,
+    }
+}
+
+/// Maps a DiagnosticReport resource to an OBR segment.
+fn report_to_obr(report: &Value, set_id: usize) -> SegmentImport {
+    let mut fields = IndexMap::new();
+    fields.insert("1".to_string(), json!(set_id.to_string()));
+    insert_field(
+        &mut fields,
+        3,
+        array(report, "identifier")
+            .first()
+            .and_then(|i| string(i, "value"))
+            .map(|v| json!(v)),
+    );
+    insert_field(
+        &mut fields,
+        4,
+        report.get("code").and_then(concept_to_coded),
+    );
+    insert_field(
+        &mut fields,
+        7,
+        string(report, "effectiveDateTime")
+            .and_then(fhir_to_hl7_datetime)
+            .map(Value::String),
+    );
+
+    let status = match string(report, "status") {
+        Some("final") => Some("F"),
+        Some("preliminary") => Some("P"),
+        Some("corrected" | "amended") => Some("C"),
+        Some("cancelled") => Some("X"),
+        Some("partial") => Some("A"),
+        Some("registered") => Some("I"),
+        _ => None,
+    };
+    insert_field(&mut fields, 25, status.map(|v| json!(v)));
+
+    SegmentImport {
+        segment: "OBR".to_string(),
+        fields,
+    }
+}
+
+/// Maps an Observation resource to an OBX segment.
+fn observation_to_obx(observation: &Value, set_id: usize) -> SegmentImport {
+    let mut fields = IndexMap::new();
+    fields.insert("1".to_string(), json!(set_id.to_string()));
+
+    let (value_type, value, unit) = if let Some(quantity) = observation.get("valueQuantity") {
+        (
+            "NM",
+            quantity.get("value").map(|v| json!(v.to_string())),
+            string(quantity, "unit").map(|v| json!(v)),
+        )
+    } else if let Some(concept) = observation.get("valueCodeableConcept") {
+        ("CWE", concept_to_coded(concept), None)
+    } else if let Some(datetime) = string(observation, "valueDateTime") {
+        (
+            "DTM",
+            fhir_to_hl7_datetime(datetime).map(Value::String),
+            None,
+        )
+    } else {
+        (
+            "ST",
+            string(observation, "valueString").map(|v| json!(v)),
+            None,
+        )
+    };
+    insert_field(&mut fields, 2, value.is_some().then(|| json!(value_type)));
+    insert_field(
+        &mut fields,
+        3,
+        observation.get("code").and_then(concept_to_coded),
+    );
+    insert_field(&mut fields, 5, value);
+    insert_field(&mut fields, 6, unit);
+    insert_field(
+        &mut fields,
+        7,
+        array(observation, "referenceRange")
+            .first()
+            .and_then(|r| string(r, "text"))
+            .map(|v| json!(v)),
+    );
+    insert_field(
+        &mut fields,
+        8,
+        array(observation, "interpretation")
+            .first()
+            .and_then(first_coding)
+            .and_then(|c| string(c, "code"))
+            .map(|v| json!(v)),
+    );
+
+    let status = match string(observation, "status") {
+        Some("final") => Some("F"),
+        Some("preliminary") => Some("P"),
+        Some("corrected" | "amended") => Some("C"),
+        Some("cancelled") => Some("X"),
+        Some("registered") => Some("R"),
+        Some("entered-in-error") => Some("W"),
+        _ => None,
+    };
+    insert_field(&mut fields, 11, status.map(|v| json!(v)));
+    insert_field(
+        &mut fields,
+        14,
+        string(observation, "effectiveDateTime")
+            .and_then(fhir_to_hl7_datetime)
+            .map(Value::String),
+    );
+
+    SegmentImport {
+        segment: "OBX".to_string(),
+        fields,
+    }
+}
+
+/// Maps a CodeableConcept to a CWE field value.
+fn concept_to_coded(concept: &Value) -> Option<Value> {
+    let mut cwe = Map::new();
+    if let Some(coding) = first_coding(concept) {
+        insert_opt(&mut cwe, "1", string(coding, "code").map(|v| json!(v)));
+        insert_opt(&mut cwe, "2", string(coding, "display").map(|v| json!(v)));
+        if let Some(system) = string(coding, "system") {
+            let name = CODING_SYSTEMS
+                .iter()
+                .find(|(_, uri)| *uri == system)
+                .map(|(name, _)| *name);
+            insert_opt(&mut cwe, "3", name.map(|v| json!(v)));
+        }
+    }
+    if !cwe.contains_key("2") {
+        insert_opt(&mut cwe, "2", string(concept, "text").map(|v| json!(v)));
+    }
+    (!cwe.is_empty()).then_some(Value::Object(cwe))
+}
+
+/// Converts a FHIR date, dateTime, or instant to an HL7 DTM value.
+///
+/// # Arguments
+/// * `value` - FHIR value (e.g., "2023-12-15", "2023-12-15T10:30:00-05:00")
+///
+/// # Returns
+/// The HL7 timestamp (e.g., "20231215", "20231215103000-0500"), or `None` if
+/// the value isn't a valid FHIR date or dateTime
+fn fhir_to_hl7_datetime(value: &str) -> Option<String> {
+    let (date, time) = match value.split_once('T') {
+        Some((date, time)) => (date, Some(time)),
+        None => (value, None),
+    };
+    let date_digits: String = date.chars().filter(|c| *c != '-').collect();
+    if date_digits.is_empty() || !date_digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let Some(time) = time else {
+        return Some(date_digits);
+    };
+
+    let (clock, offset) = if let Some(clock) = time.strip_suffix('Z') {
+        (clock, "+0000".to_string())
+    } else {
+        match time.rfind(['+', '-']) {
+            Some(i) => (
+                time.get(..i)?,
+                time.get(i..)?.chars().filter(|c| *c != ':').collect(),
+            ),
+            None => (time, String::new()),
+        }
+    };
+    // drop fractional seconds
+    let clock = clock.split('.').next()?;
+    let clock_digits: String = clock.chars().filter(|c| *c != ':').collect();
+    if !clock_digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    Some(format!("{date_digits}{clock_digits}{offset}"))
+}
+
+/// Builds an import segment from field index/value pairs.
+fn segment<const N: usize>(name: &str, fields: [(&str, Value); N]) -> SegmentImport {
+    SegmentImport {
+        segment: name.to_string(),
+        fields: fields
+            .into_iter()
+            .map(|(index, value)| (index.to_string(), value))
+            .collect(),
+    }
+}
+
+/// Inserts a field into an import segment if it is present.
+fn insert_field(fields: &mut IndexMap<String, Value>, index: usize, value: Option<Value>) {
+    if let Some(value) = value {
+        fields.insert(index.to_string(), value);
+    }
+}
+
+/// Collapses a list of repetitions into a field value.
+fn repeats(mut values: Vec<Value>) -> Option<Value> {
+    match values.len() {
+        0 => None,
+        1 => values.pop(),
+        _ => Some(Value::Array(values)),
+    }
+}
+
+/// Gets an array property of a JSON object, or an empty slice.
+fn array<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+/// Gets a string property of a JSON object.
+fn string<'a>(value: &'a Value, key: &str) -> Option<&'a str> {
+    value.get(key).and_then(Value::as_str)
+}
+
+/// Gets the first coding of a CodeableConcept.
+fn first_coding(concept: &Value) -> Option<&Value> {
+    array(concept, "coding").first()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const ADT: &str = "MSH|^~\\&|APP|FAC|||20231215120000||ADT^A01|MSG001|P|2.5.1\r\
+                       EVN|A01|20231215120000\r\
+                       PID|1||12345^^^HOSP^MR||DOE^JOHN^A||19800101|M|||123 MAIN ST^^SPRINGFIELD^IL^62701\r\
+                       PV1|1|I|WARD1^101^A||||||||||||||||V001";
+
+    const ORU: &str = "MSH|^~\\&|LAB|FACILITY|APP|DEST|20231215120000||ORU^R01|MSG002|P|2.5.1\r\
+                       PID|||12345^^^HOSP^MR||DOE^JOHN\r\
+                       OBR|1||ORD001|CBC^Complete Blood Count|||20231215100000-0500\r\
+                       OBX|1|NM|WBC^White Blood Count^LN||7.5|10*3/uL|4.5-11.0|N|||F\r\
+                       OBX|2|ST|NOTE^Comment||Sample haemolysed||||||P";
+
+    fn resources<'a>(bundle: &'a Value, resource_type: &str) -> Vec<&'a Value> {
+        bundle["entry"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| &entry["resource"])
+            .filter(|resource| resource["resourceType"] == resource_type)
+            .collect()
+    }
+
+    #[test]
+    fn can_export_adt_to_patient_and_encounter() {
+        let bundle: Value = serde_json::from_str(&export_to_fhir(ADT).unwrap()).unwrap();
+        assert_eq!(bundle["resourceType"], "Bundle");
+        assert_eq!(bundle["identifier"]["value"], "MSG001");
+
+        let patient = resources(&bundle, "Patient")[0];
+        assert_eq!(patient["identifier"][0]["value"], "12345");
+        assert_eq!(patient["identifier"][0]["type"]["coding"][0]["code"], "MR");
+        assert_eq!(patient["name"][0]["family"], "DOE");
+        assert_eq!(patient["name"][0]["given"], json!(["JOHN", "A"]));
+        assert_eq!(patient["gender"], "male");
+        assert_eq!(patient["birthDate"], "1980-01-01");
+        assert_eq!(patient["address"][0]["city"], "SPRINGFIELD");
+
+        let encounter = resources(&bundle, "Encounter")[0];
+        assert_eq!(encounter["status"], "in-progress");
+        assert_eq!(encounter["class"]["code"], "IMP");
+        assert_eq!(encounter["identifier"][0]["value"], "V001");
+        assert_eq!(
+            encounter["subject"]["reference"],
+            bundle["entry"][0]["fullUrl"]
+        );
+    }
+
+    #[test]
+    fn can_export_oru_to_report_and_observations() {
+        let bundle: Value = serde_json::from_str(&export_to_fhir(ORU).unwrap()).unwrap();
+
+        let observations = resources(&bundle, "Observation");
+        assert_eq!(observations.len(), 2);
+        assert_eq!(observations[0]["valueQuantity"]["value"], 7.5);
+        assert_eq!(observations[0]["valueQuantity"]["unit"], "10*3/uL");
+        assert_eq!(
+            observations[0]["code"]["coding"][0]["system"],
+            "http://loinc.org"
+        );
+        assert_eq!(observations[0]["status"], "final");
+        assert_eq!(
+            observations[0]["effectiveDateTime"],
+            "2023-12-15T10:00:00-05:00"
+        );
+        assert_eq!(observations[1]["valueString"], "Sample haemolysed");
+        assert_eq!(observations[1]["status"], "preliminary");
+
+        let report = resources(&bundle, "DiagnosticReport")[0];
+        assert_eq!(report["identifier"][0]["value"], "ORD001");
+        assert_eq!(report["result"].as_array().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn export_rejects_messages_without_mappable_segments() {
+        let message = "MSH|^~\\&|APP|FAC|||20231215||ACK^A01|123|P|2.5.1\rMSA|AA|123";
+        assert!(export_to_fhir(message).is_err());
+    }
+
+    #[test]
+    fn can_roundtrip_oru_through_fhir() {
+        let fhir = export_to_fhir(ORU).unwrap();
+        let message = import_from_fhir(&fhir).unwrap();
+
+        assert!(message.contains("ORU^R01^ORU_R01|MSG002|P|2.5.1"));
+        assert!(message.contains("PID|1||12345^^^HOSP^MR||DOE^JOHN"));
+        assert!(message.contains("OBR|1||ORD001|CBC^Complete Blood Count|||20231215100000-0500"));
+        assert!(message.contains("OBX|1|NM|WBC^White Blood Count^LN||7.5|10*3/uL|4.5-11.0|N|||F"));
+        assert!(message.contains("OBX|2|ST|NOTE^Comment||Sample haemolysed||||||P"));
+    }
+
+    #[test]
+    fn import_picks_adt_event_from_encounter_status() {
+        let bundle = json!({
+            "resourceType": "Bundle",
+            "type": "collection",
+            "entry": [
+                { "resource": { "resourceType": "Patient", "gender": "female" } },
+                { "resource": { "resourceType": "Encounter", "status": "finished" } }
+            ]
+        });
+        let message = import_from_fhir(&bundle.to_string()).unwrap();
+        assert!(message.contains("|ADT^A03^ADT_A01|"));
+        assert!(message.contains("EVN|A03|"));
+    }
+
+    #[test]
+    fn import_rejects_non_bundles() {
+        let patient = json!({ "resourceType": "Patient" });
+        assert!(import_from_fhir(&patient.to_string()).is_err());
+    }
+
+    #[test]
+    fn can_convert_datetimes() {
+        assert_eq!(hl7_to_fhir_datetime("2023"), Some("2023".to_string()));
+        assert_eq!(
+            hl7_to_fhir_datetime("20231215"),
+            Some("2023-12-15".to_string())
+        );
+        assert_eq!(
+            hl7_to_fhir_datetime("20231215103000.123+0100"),
+            Some("2023-12-15T10:30:00+01:00".to_string())
+        );
+        assert_eq!(hl7_to_fhir_datetime("2023121"), None);
+        assert_eq!(hl7_to_fhir_datetime("{now}"), None);
+
+        assert_eq!(
+            fhir_to_hl7_datetime("2023-12-15"),
+            Some("20231215".to_string())
+        );
+        assert_eq!(
+            fhir_to_hl7_datetime("2023-12-15T10:30:00-05:00"),
+            Some("20231215103000-0500".to_string())
+        );
+        assert_eq!(
+            fhir_to_hl7_datetime("2023-12-15T10:30:00.5Z"),
+            Some("20231215103000+0000".to_string())
+        );
+    }
+}
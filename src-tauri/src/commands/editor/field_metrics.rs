@@ -0,0 +1,154 @@
+//! Length and size of a field's value, measured against its schema limits.
+//!
+//! Length limits are otherwise only checked by full validation, so an over-long
+//! value goes unnoticed until validation is run. The editor asks for the
+//! metrics of the field under the cursor as the user types, and shows a
+//! counter such as "23/50" for fields with a maximum length.
+//!
+//! # Measurements
+//!
+//! * **Length** - Characters in the decoded value (escape sequences such as
+//!   `\F\` count as the one character they stand for), which is what the
+//!   schema's `minlength` and `maxlength` limit
+//! * **Bytes** - Size of the decoded value in UTF-8, for receivers that limit
+//!   storage rather than characters; accented names take more bytes than
+//!   characters
+
+use hl7_parser::query::LocationQuery;
+use serde::Serialize;
+use tauri::State;
+
+use crate::schema::cache::SchemaCache;
+use crate::schema::segment::Field;
+use crate::schema::version::detect_version;
+use crate::AppData;
+
+/// Length and size of a field's value, and the limits the schema sets on it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldMetrics {
+    /// Number of characters in the decoded value
+    pub length: usize,
+    /// Number of bytes in the decoded value, encoded as UTF-8
+    pub bytes: usize,
+    /// Minimum length from the schema, if the field has one
+    pub min_length: Option<u16>,
+    /// Maximum length from the schema, if the field has one
+    pub max_length: Option<u16>,
+    /// Characters left before the maximum length, negative once past it
+    pub remaining: Option<i64>,
+}
+
+/// Measure the value at a path against the schema's length limits.
+///
+/// Limits are looked up for the field, or the component when the path names
+/// one. A field path falls back to the limits of the field's first component,
+/// as validation does, since a field holding no components is its first
+/// component. Subcomponents have no limits of their own.
+///
+/// # Arguments
+/// * `message` - The HL7 message
+/// * `path` - Query path of the field, e.g. "PID.5.1" or "OBX[2].5"
+/// * `state` - Application state containing the schema cache
+///
+/// # Returns
+/// * `Some(FieldMetrics)` - The value's length and size, and its limits
+/// * `None` - If the message can't be parsed, or the path doesn't name a field
+///   in it
+#[tauri::command]
+pub fn get_field_metrics(
+    message: &str,
+    path: &str,
+    state: State<'_, AppData>,
+) -> Option<FieldMetrics> {
+    field_metrics(message, path, &state.schema)
+}
+
+/// Measure a field without going through Tauri state.
+fn field_metrics(message: &str, path: &str, schema: &SchemaCache) -> Option<FieldMetrics> {
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(message).ok()?;
+    let query = LocationQuery::parse(path).ok()?;
+    let field = query.field?;
+    let value = parsed
+        .query(path)
+        .map(|value| parsed.separators.decode(value.raw_value()).to_string())?;
+
+    let version = schema.resolve_version(detect_version(message));
+    let definitions = schema
+        .get_segment_for_version(&query.segment, &version)
+        .unwrap_or_default();
+    let definition = match (query.component, query.subcomponent) {
+        (_, Some(_)) => None,
+        (Some(component), None) => find_definition(&definitions, field, Some(component)),
+        (None, None) => find_definition(&definitions, field, None)
+            .or_else(|| find_definition(&definitions, field, Some(1))),
+    };
+
+    let length = value.chars().count();
+    let max_length = definition.and_then(|definition| definition.maxlength);
+    Some(FieldMetrics {
+        length,
+        bytes: value.len(),
+        min_length: definition.and_then(|definition| definition.minlength),
+        max_length,
+        remaining: max_length.map(|max| i64::from(max) - i64::try_from(length).unwrap_or(i64::MAX)),
+    })
+}
+
+/// Schema definition of a field or one of its components.
+fn find_definition(
+    definitions: &[Field],
+    field: usize,
+    component: Option<usize>,
+) -> Option<&Field> {
+    definitions.iter().find(|definition| {
+        usize::from(definition.field) == field && definition.component.map(usize::from) == component
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str = "MSH|^~\\&|APP|FAC|||20250101||ADT^A01|1|P|2.5.1\rNK1|1|Doe^Zoë^^^MR|SPO\rNK1|2|Whitfield-Montgomery-Fairbanks-Worthington-Smythe-Jones^Al\rZPD|custom";
+
+    #[test]
+    fn measures_characters_and_bytes() {
+        let schema = SchemaCache::new().unwrap();
+        let metrics = field_metrics(MESSAGE, "NK1.2.2", &schema).unwrap();
+
+        assert_eq!(metrics.length, 3);
+        assert_eq!(metrics.bytes, 4);
+        assert_eq!(metrics.max_length, Some(30));
+        assert_eq!(metrics.remaining, Some(27));
+    }
+
+    #[test]
+    fn reports_budget_past_the_maximum() {
+        let schema = SchemaCache::new().unwrap();
+        let metrics = field_metrics(MESSAGE, "NK1[2].2.1", &schema).unwrap();
+
+        assert_eq!(metrics.length, 55);
+        assert_eq!(metrics.max_length, Some(50));
+        assert_eq!(metrics.remaining, Some(-5));
+    }
+
+    #[test]
+    fn looks_up_limits_by_path() {
+        let schema = SchemaCache::new().unwrap();
+
+        // segments without a schema have no limits
+        let metrics = field_metrics(MESSAGE, "ZPD.1", &schema).unwrap();
+        assert_eq!(metrics.length, 6);
+        assert_eq!(metrics.max_length, None);
+        assert_eq!(metrics.remaining, None);
+
+        // a limit on the whole field
+        let metrics = field_metrics(MESSAGE, "NK1.3", &schema).unwrap();
+        assert_eq!(metrics.max_length, Some(1));
+        assert_eq!(metrics.remaining, Some(-2));
+
+        assert!(field_metrics(MESSAGE, "NK1", &schema).is_none());
+        assert!(field_metrics(MESSAGE, "PID.5", &schema).is_none());
+    }
+}
@@ -0,0 +1,328 @@
+//! Find fields by name rather than by path.
+//!
+//! Remembering that the attending doctor is PV1.7 is the kind of thing nobody
+//! should have to do. This module matches a search against the schema's field
+//! names and the HL7 specification's field descriptions for every segment in
+//! the message, and returns the matching paths along with where they are, so
+//! the "Jump to Field" palette can take "attending" as readily as "PV1.7".
+//!
+//! # Matching
+//!
+//! Matching ignores case. A name that equals the search ranks highest, then one
+//! that starts with it, then one with a word starting with it, then one that
+//! contains it, and finally one containing every word of the search or its
+//! letters in order (so "pat nm" finds "Patient Name"). Ties keep message order.
+//!
+//! A word of the search naming a segment in the message narrows the search to
+//! that segment: "pv1 doctor" only finds PV1's doctors, and "pv1" on its own
+//! lists every field of PV1.
+
+use std::collections::{BTreeMap, HashMap};
+
+use hl7_parser::Message;
+use serde::Serialize;
+use tauri::State;
+
+use super::cursor::CursorRange;
+use super::offsets::Utf16Offsets;
+use crate::schema::segment::Field;
+use crate::schema::version::{detect_version, DEFAULT_VERSION};
+use crate::spec::std_spec::{field_descriptions, is_valid_version};
+use crate::AppData;
+
+/// Most matches returned for a single search.
+const MAX_MATCHES: usize = 25;
+
+/// A field whose name or description matches a search.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FieldMatch {
+    /// Query path of the field (e.g., "PV1.7", "OBX[2].5", "PID.5.1")
+    pub path: String,
+    /// Name of the field from the schema, or the spec description if the
+    /// schema doesn't name it
+    pub name: String,
+    /// Description from the HL7 specification, if it differs from the name
+    pub description: Option<String>,
+    /// Where the field is in the message, or `None` if the segment is too short
+    /// to have it (UTF-16 offsets from the command)
+    pub range: Option<CursorRange>,
+}
+
+/// Search the fields of a message by name and description.
+///
+/// # Arguments
+/// * `message` - The HL7 message as a string
+/// * `query` - Text to search for (e.g., "attending", "pid birth")
+/// * `state` - Application state containing the schema cache
+///
+/// # Returns
+/// * `Ok(Vec<FieldMatch>)` - The best matches, best first (empty for an empty search)
+/// * `Err(String)` - If the message can't be parsed
+#[tauri::command]
+pub fn find_fields_by_name(
+    message: &str,
+    query: &str,
+    state: State<'_, AppData>,
+) -> Result<Vec<FieldMatch>, String> {
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(message)
+        .map_err(|e| format!("Failed to parse message: {e}"))?;
+
+    let version = state.schema.resolve_version(detect_version(message));
+    let spec_version = if is_valid_version(&version) {
+        version.as_str()
+    } else {
+        DEFAULT_VERSION
+    };
+
+    let mut matches = search_fields(
+        &parsed,
+        query,
+        |segment| {
+            state
+                .schema
+                .get_segment_for_version(segment, &version)
+                .unwrap_or_default()
+        },
+        |segment| field_descriptions(spec_version, segment),
+    );
+    let offsets = Utf16Offsets::new(message);
+    for found in &mut matches {
+        found.range = found
+            .range
+            .as_ref()
+            .map(|range| offsets.cursor_range(&(range.start..range.end)));
+    }
+    Ok(matches)
+}
+
+/// A field being considered, keyed by field and component number.
+#[derive(Default)]
+struct Candidate {
+    name: Option<String>,
+    description: Option<String>,
+    score: u32,
+}
+
+/// Match a search against the fields of every segment in a message.
+///
+/// `schema_fields` gives the schema's field definitions for a segment, and
+/// `spec_descriptions` the specification's description of each field in order.
+pub fn search_fields(
+    message: &Message,
+    query: &str,
+    schema_fields: impl Fn(&str) -> Vec<Field>,
+    spec_descriptions: impl Fn(&str) -> Vec<String>,
+) -> Vec<FieldMatch> {
+    let mut words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    let segment_filter = words
+        .iter()
+        .position(|word| {
+            message
+                .segments()
+                .any(|segment| segment.name.eq_ignore_ascii_case(word))
+        })
+        .map(|index| words.remove(index).to_uppercase());
+    if words.is_empty() && segment_filter.is_none() {
+        return Vec::new();
+    }
+
+    let mut lookups: HashMap<&str, (Vec<Field>, Vec<String>)> = HashMap::new();
+    let mut occurrences: HashMap<&str, usize> = HashMap::new();
+    let mut matches: Vec<(u32, FieldMatch)> = Vec::new();
+
+    for segment in message.segments() {
+        let occurrence = occurrences.entry(segment.name).or_default();
+        *occurrence += 1;
+        if segment_filter
+            .as_deref()
+            .is_some_and(|filter| filter != segment.name)
+        {
+            continue;
+        }
+        let prefix = if *occurrence == 1 {
+            segment.name.to_string()
+        } else {
+            format!("{}[{occurrence}]", segment.name)
+        };
+
+        let (fields, descriptions) = lookups
+            .entry(segment.name)
+            .or_insert_with(|| (schema_fields(segment.name), spec_descriptions(segment.name)));
+
+        let mut candidates: BTreeMap<(usize, Option<u8>), Candidate> = BTreeMap::new();
+        for field in fields.iter() {
+            let candidate = candidates
+                .entry((usize::from(field.field), field.component))
+                .or_default();
+            candidate.score = candidate.score.max(match_score(&words, &field.name));
+            candidate.name = Some(field.name.clone());
+        }
+        for (index, description) in descriptions.iter().enumerate() {
+            let candidate = candidates.entry((index + 1, None)).or_default();
+            candidate.score = candidate.score.max(match_score(&words, description));
+            candidate.description = Some(description.clone());
+        }
+
+        for ((field, component), candidate) in candidates {
+            if candidate.score == 0 {
+                continue;
+            }
+            let path = match component {
+                Some(component) => format!("{prefix}.{field}.{component}"),
+                None => format!("{prefix}.{field}"),
+            };
+            let range = message.query(&path).map(|result| {
+                let range = result.range();
+                CursorRange {
+                    start: range.start,
+                    end: range.end,
+                }
+            });
+            let (name, description) = match (candidate.name, candidate.description) {
+                (Some(name), Some(description)) if name.eq_ignore_ascii_case(&description) => {
+                    (name, None)
+                }
+                (Some(name), description) => (name, description),
+                (None, Some(description)) => (description, None),
+                (None, None) => continue,
+            };
+            matches.push((
+                candidate.score,
+                FieldMatch {
+                    path,
+                    name,
+                    description,
+                    range,
+                },
+            ));
+        }
+    }
+
+    // the sort is stable, so equal scores stay in message order
+    matches.sort_by(|a, b| b.0.cmp(&a.0));
+    matches
+        .into_iter()
+        .take(MAX_MATCHES)
+        .map(|(_, found)| found)
+        .collect()
+}
+
+/// How well a name matches the words of a search, or 0 if it doesn't.
+///
+/// With no words (a search for a segment alone), every name matches equally.
+fn match_score(words: &[String], name: &str) -> u32 {
+    if words.is_empty() {
+        return 1;
+    }
+    let name = name.to_lowercase();
+    let query = words.join(" ");
+
+    if name == query {
+        100
+    } else if name.starts_with(&query) {
+        80
+    } else if name
+        .split(|c: char| !c.is_alphanumeric())
+        .any(|word| word.starts_with(&query))
+    {
+        60
+    } else if name.contains(&query) {
+        40
+    } else if words.iter().all(|word| name.contains(word.as_str())) {
+        20
+    } else if is_subsequence(&query.replace(' ', ""), &name) {
+        10
+    } else {
+        0
+    }
+}
+
+/// Whether the letters of `needle` appear in `haystack` in order.
+fn is_subsequence(needle: &str, haystack: &str) -> bool {
+    let mut letters = haystack.chars();
+    needle.chars().all(|c| letters.any(|h| h == c))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str = "MSH|^~\\&|APP|FAC|||20250101||ADT^A01|1|P|2.5.1\r\
+        PID|1||123||DOE^JANE||19800101\r\
+        PV1|1|I|||||1234^WELBY^MARCUS\r\
+        PV1|2|O";
+
+    fn search(query: &str) -> Vec<FieldMatch> {
+        let message = hl7_parser::parse_message(MESSAGE).unwrap();
+        search_fields(
+            &message,
+            query,
+            |segment| match segment {
+                "PID" => Field::parse(
+                    "[[fields]]\nfield = 5\nname = \"Patient Name\"\n\
+                     [[fields]]\nfield = 5\ncomponent = 1\nname = \"Family Name\"\n\
+                     [[fields]]\nfield = 7\nname = \"Date of Birth\"",
+                )
+                .unwrap(),
+                _ => Vec::new(),
+            },
+            |segment| match segment {
+                "PV1" => [
+                    "Set ID - PV1",
+                    "Patient Class",
+                    "Assigned Patient Location",
+                    "Admission Type",
+                    "Preadmit Number",
+                    "Prior Patient Location",
+                    "Attending Doctor",
+                    "Referring Doctor",
+                ]
+                .map(String::from)
+                .to_vec(),
+                _ => Vec::new(),
+            },
+        )
+    }
+
+    fn paths(matches: &[FieldMatch]) -> Vec<&str> {
+        matches.iter().map(|found| found.path.as_str()).collect()
+    }
+
+    #[test]
+    fn finds_fields_by_schema_name_and_spec_description() {
+        let attending = search("attending");
+        assert_eq!(paths(&attending), ["PV1.7", "PV1[2].7"]);
+        assert_eq!(attending[0].name, "Attending Doctor");
+        assert!(attending[0].range.is_some());
+        // the second visit is too short to have the field
+        assert_eq!(attending[1].range, None);
+
+        let birth = search("BIRTH");
+        assert_eq!(paths(&birth), ["PID.7"]);
+        let range = birth[0].range.clone().unwrap();
+        assert_eq!(range.end - range.start, "19800101".len());
+    }
+
+    #[test]
+    fn ranks_closer_matches_first() {
+        // names starting with the search come before names with a later word starting with it
+        assert_eq!(
+            paths(&search("patient")),
+            ["PID.5", "PV1.2", "PV1[2].2", "PV1.3", "PV1.6", "PV1[2].3", "PV1[2].6"]
+        );
+        // letters in order
+        assert_eq!(paths(&search("pat nm")), ["PID.5", "PV1.5", "PV1[2].5"]);
+        assert!(search("   ").is_empty());
+        assert!(search("xyzzy").is_empty());
+    }
+
+    #[test]
+    fn segment_names_narrow_the_search() {
+        assert_eq!(
+            paths(&search("pv1 doctor")),
+            ["PV1.7", "PV1.8", "PV1[2].7", "PV1[2].8"]
+        );
+        assert_eq!(search("pid").len(), 3);
+    }
+}
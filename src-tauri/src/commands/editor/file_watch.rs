@@ -0,0 +1,565 @@
+//! Detection of external changes to the file open in the editor.
+//!
+//! Other tools in an integration pipeline sometimes rewrite message files while
+//! they're open in Hermes. Without a guard, the next save silently throws their
+//! changes away. This module watches the file of each document open in the
+//! editor and tells the frontend when its contents change underneath it.
+//!
+//! # Watching
+//!
+//! The watcher polls the file's modification time and only reads the file when
+//! that changes, so watching costs a `stat` call per interval. A change is only
+//! reported when the contents on disk differ from both the last version Hermes
+//! read or wrote (the *base*) and the current editor contents, so Hermes' own
+//! saves never trigger it. A missing file is logged and ignored until it comes
+//! back, since many tools write files by deleting and recreating them.
+//!
+//! # Events
+//!
+//! External changes are emitted as `file-changed-externally` events carrying the
+//! new disk contents and a diff of the editor contents against them (computed
+//! with [`compare_messages`](crate::commands::compare_messages)).
+//!
+//! # Comparing With Disk
+//!
+//! [`compare_with_disk`] diffs a document against its file on demand, whether
+//! or not a change has been reported, so the user can check what they'd
+//! overwrite before saving.
+//!
+//! # Resolving Changes
+//!
+//! The frontend resolves a change with one of three choices:
+//! * **Reload** - Replace the editor contents with the file on disk
+//! * **Overwrite** - Write the editor contents over the file on disk
+//! * **Merge** - Three-way merge the editor and disk versions against the base,
+//!   segment by segment. Segments changed on only one side are taken from that
+//!   side; segments changed differently on both sides keep the editor's version
+//!   and are counted as conflicts.
+
+use std::{sync::Arc, time::SystemTime};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, State};
+use tokio::sync::Mutex;
+
+use super::documents::DocumentId;
+use super::multi_message::{detect_line_ending, lines_with_offsets};
+use crate::commands::{compare_messages, strip_document_metadata, MessageDiff, SegmentMatching};
+use crate::events;
+use crate::AppData;
+
+/// How often the watched file is checked for changes.
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+/// A document's file being watched.
+pub struct FileWatch {
+    /// Path of the watched file.
+    path: String,
+    /// Last version of the file Hermes read or wrote.
+    base: Arc<Mutex<String>>,
+    /// Handle to the polling task.
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl FileWatch {
+    /// Stop polling the file.
+    pub(crate) fn stop(self) {
+        self.task.abort();
+    }
+}
+
+/// Payload of the `file-changed-externally` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExternalChange {
+    /// Document the file is open in.
+    #[serde(rename = "documentId")]
+    pub document_id: DocumentId,
+    /// Path of the file that changed.
+    pub path: String,
+    /// New contents of the file on disk.
+    pub disk: String,
+    /// Differences from the editor contents (left) to the disk contents (right),
+    /// or `None` if either version can't be parsed.
+    pub diff: Option<MessageDiff>,
+}
+
+/// How to resolve an external change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeResolution {
+    /// Discard the editor contents and use the file on disk.
+    Reload,
+    /// Write the editor contents over the file on disk.
+    Overwrite,
+    /// Merge the editor and disk versions.
+    Merge,
+}
+
+/// Result of resolving an external change.
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolvedChange {
+    /// New editor contents.
+    pub message: String,
+    /// Contents of the file on disk after resolving.
+    pub disk: String,
+    /// Number of segments changed differently in both versions. The editor's
+    /// version is kept for each of these.
+    pub conflicts: usize,
+}
+
+/// A document compared with its file on disk.
+#[derive(Debug, Clone, Serialize)]
+pub struct DiskComparison {
+    /// Path of the document's file.
+    pub path: String,
+    /// Contents of the file on disk.
+    pub disk: String,
+    /// Whether the file changed since Hermes last read or wrote it. Always
+    /// `false` if the file isn't being watched.
+    #[serde(rename = "changedExternally")]
+    pub changed_externally: bool,
+    /// Differences from the editor contents (left) to the disk contents (right),
+    /// or `None` if either version can't be parsed.
+    pub diff: Option<MessageDiff>,
+}
+
+/// Start watching a document's file for external changes.
+///
+/// Replaces any existing watch for the document. Call this whenever a file is
+/// opened or saved so the watcher knows what the file is expected to contain.
+///
+/// # Arguments
+/// * `document_id` - The document the file is open in
+/// * `path` - Path of the file to watch
+/// * `content` - Contents of the file as last read or written by Hermes
+/// * `app` - Tauri app handle for emitting events
+/// * `state` - Application state holding the watch and editor contents
+///
+/// # Returns
+/// * `Ok(())` - Always succeeds; a file that doesn't exist yet is watched for creation
+#[tauri::command]
+pub async fn watch_file(
+    document_id: DocumentId,
+    path: String,
+    content: String,
+    app: AppHandle,
+    state: State<'_, AppData>,
+) -> Result<(), String> {
+    // the file may not exist yet if it's about to be saved for the first time
+    let mut modified = modified_time(&path).await.ok();
+
+    let mut file_watch = state.file_watch.lock().await;
+    if let Some(watch) = file_watch.remove(&document_id) {
+        watch.stop();
+    }
+
+    let base = Arc::new(Mutex::new(content));
+    let documents = state.documents.clone();
+    let task = tokio::spawn({
+        let path = path.clone();
+        let base = base.clone();
+        async move {
+            // the last disk contents reported, so each change is only reported once
+            let mut reported: Option<String> = None;
+            loop {
+                tokio::time::sleep(POLL_INTERVAL).await;
+
+                let current = match modified_time(&path).await {
+                    Ok(current) => current,
+                    Err(e) => {
+                        log::debug!("Watched file {path} is unavailable: {e}");
+                        continue;
+                    }
+                };
+                if modified == Some(current) {
+                    continue;
+                }
+                modified = Some(current);
+
+                let disk = match tokio::fs::read_to_string(&path).await {
+                    Ok(disk) => disk,
+                    Err(e) => {
+                        log::warn!("Failed to read watched file {path}: {e}");
+                        continue;
+                    }
+                };
+                let Some(editor) = documents
+                    .lock()
+                    .await
+                    .get(document_id)
+                    .map(|document| document.message.clone())
+                else {
+                    // the document was closed
+                    break;
+                };
+                // the editor holds the message without its metadata header
+                let (disk_message, _) = strip_document_metadata(&disk);
+
+                let mut base = base.lock().await;
+                if disk == *base || reported.as_ref() == Some(&disk) {
+                    continue;
+                }
+                if disk_message == editor {
+                    // the file now matches the editor, most likely because we just saved it
+                    *base = disk;
+                    continue;
+                }
+                drop(base);
+
+                log::info!("Detected external change to {path}");
+                let change = ExternalChange {
+                    document_id,
+                    path: path.clone(),
+                    diff: compare_messages(&editor, disk_message, None).ok(),
+                    disk: disk.clone(),
+                };
+                if let Err(e) = app.emit(events::FILE_CHANGED_EXTERNALLY, change) {
+                    log::error!("Failed to emit file-changed-externally event: {e:#}");
+                }
+                reported = Some(disk);
+            }
+        }
+    });
+
+    file_watch.insert(document_id, FileWatch { path, base, task });
+    Ok(())
+}
+
+/// Stop watching a document's file.
+///
+/// # Arguments
+/// * `document_id` - The document the file is open in
+///
+/// # Returns
+/// * `Ok(())` - Always succeeds, even if no file was being watched
+#[tauri::command]
+pub async fn unwatch_file(
+    document_id: DocumentId,
+    state: State<'_, AppData>,
+) -> Result<(), String> {
+    if let Some(watch) = state.file_watch.lock().await.remove(&document_id) {
+        watch.stop();
+    }
+    Ok(())
+}
+
+/// Resolve an external change to a document's watched file.
+///
+/// # Arguments
+/// * `document_id` - The document the file is open in
+/// * `resolution` - How to resolve the change
+/// * `message` - Current editor contents
+/// * `state` - Application state holding the watch
+///
+/// # Returns
+/// * `Ok(ResolvedChange)` - The new editor and disk contents
+/// * `Err(String)` - If the document's file isn't being watched, or it can't be
+///   read or written
+#[tauri::command]
+pub async fn resolve_external_change(
+    document_id: DocumentId,
+    resolution: ChangeResolution,
+    message: String,
+    state: State<'_, AppData>,
+) -> Result<ResolvedChange, String> {
+    let file_watch = state.file_watch.lock().await;
+    let Some(watch) = file_watch.get(&document_id) else {
+        return Err(format!(
+            "No file is being watched for document {document_id}"
+        ));
+    };
+    let path = watch.path.as_str();
+
+    let resolved = match resolution {
+        ChangeResolution::Reload => {
+            let disk = read_file(path).await?;
+            ResolvedChange {
+                message: disk.clone(),
+                disk,
+                conflicts: 0,
+            }
+        }
+        ChangeResolution::Overwrite => {
+            tokio::fs::write(path, &message)
+                .await
+                .map_err(|e| format!("Failed to write {path}: {e}"))?;
+            ResolvedChange {
+                message: message.clone(),
+                disk: message,
+                conflicts: 0,
+            }
+        }
+        ChangeResolution::Merge => {
+            let disk = read_file(path).await?;
+            let base = watch.base.lock().await.clone();
+            let (merged, conflicts) = merge_messages(&base, &message, &disk);
+            ResolvedChange {
+                message: merged,
+                disk,
+                conflicts,
+            }
+        }
+    };
+
+    log::info!("Resolved external change to {path} ({resolution:?})");
+    *watch.base.lock().await = resolved.disk.clone();
+    Ok(resolved)
+}
+
+/// Compare a document with its file on disk.
+///
+/// # Arguments
+/// * `document_id` - The document to compare
+/// * `matching` - How to pair repeating segments (defaults to content)
+/// * `state` - Application state holding the documents and watches
+///
+/// # Returns
+/// * `Ok(DiskComparison)` - The disk contents and their differences from the editor
+/// * `Err(String)` - If the document isn't open, has no file, or the file can't be read
+#[tauri::command]
+pub async fn compare_with_disk(
+    document_id: DocumentId,
+    matching: Option<SegmentMatching>,
+    state: State<'_, AppData>,
+) -> Result<DiskComparison, String> {
+    let (editor, path) = {
+        let documents = state.documents.lock().await;
+        let document = documents
+            .get(document_id)
+            .ok_or_else(|| format!("Document {document_id} isn't open"))?;
+        let path = document
+            .file_path
+            .clone()
+            .ok_or_else(|| format!("Document {document_id} hasn't been saved to a file"))?;
+        (document.message.clone(), path)
+    };
+
+    let disk = read_file(&path).await?;
+    let changed_externally = match state.file_watch.lock().await.get(&document_id) {
+        Some(watch) if watch.path == path => *watch.base.lock().await != disk,
+        _ => false,
+    };
+    // the editor holds the message without its metadata header
+    let (disk_message, _) = strip_document_metadata(&disk);
+    let diff = compare_messages(&editor, disk_message, matching).ok();
+
+    Ok(DiskComparison {
+        path,
+        disk,
+        changed_externally,
+        diff,
+    })
+}
+
+/// Read the watched file.
+async fn read_file(path: &str) -> Result<String, String> {
+    tokio::fs::read_to_string(path)
+        .await
+        .map_err(|e| format!("Failed to read {path}: {e}"))
+}
+
+/// Get a file's modification time.
+async fn modified_time(path: &str) -> std::io::Result<SystemTime> {
+    tokio::fs::metadata(path).await?.modified()
+}
+
+/// Three-way merge two versions of a message against their common base.
+///
+/// Messages are merged segment by segment: segments are aligned with the base
+/// using their longest common subsequence, and each run of segments between
+/// aligned ones is taken from whichever side changed it. Runs changed
+/// differently on both sides keep the editor's (`ours`) version.
+///
+/// # Returns
+/// The merged message, using the line endings of `ours`, and the number of
+/// conflicting runs.
+fn merge_messages(base: &str, ours: &str, theirs: &str) -> (String, usize) {
+    let (base_lines, our_lines, their_lines) = (
+        segment_lines(base),
+        segment_lines(ours),
+        segment_lines(theirs),
+    );
+    let our_matches = matching_lines(&base_lines, &our_lines);
+    let their_matches = matching_lines(&base_lines, &their_lines);
+
+    let mut merged: Vec<&str> = Vec::new();
+    let mut conflicts = 0;
+    let (mut b, mut o, mut t) = (0, 0, 0);
+    loop {
+        // the next base segment kept unchanged in both versions
+        let stable = (b..base_lines.len()).find_map(|i| {
+            match (our_matches.get(i).copied()?, their_matches.get(i).copied()?) {
+                (Some(oi), Some(ti)) => Some((i, oi, ti)),
+                _ => None,
+            }
+        });
+        let (bi, oi, ti) = stable.unwrap_or((base_lines.len(), our_lines.len(), their_lines.len()));
+
+        conflicts += merge_run(
+            base_lines.get(b..bi).unwrap_or_default(),
+            our_lines.get(o..oi).unwrap_or_default(),
+            their_lines.get(t..ti).unwrap_or_default(),
+            &mut merged,
+        );
+
+        let Some(line) = stable.and_then(|_| base_lines.get(bi)) else {
+            break;
+        };
+        merged.push(*line);
+        (b, o, t) = (bi + 1, oi + 1, ti + 1);
+    }
+
+    let line_ending = detect_line_ending(ours);
+    let mut message = merged.join(line_ending);
+    if ours.ends_with(['\r', '\n']) {
+        message.push_str(line_ending);
+    }
+    (message, conflicts)
+}
+
+/// Merge a run of segments that differs from the base in at least one version.
+///
+/// When all three runs have the same number of segments (the usual case when
+/// fields are edited in place), they're merged segment by segment so edits to
+/// neighbouring segments don't conflict.
+///
+/// # Returns
+/// The number of conflicts in the run.
+fn merge_run<'a>(
+    base: &[&'a str],
+    ours: &[&'a str],
+    theirs: &[&'a str],
+    merged: &mut Vec<&'a str>,
+) -> usize {
+    if ours == base {
+        merged.extend_from_slice(theirs);
+        return 0;
+    }
+    if theirs == base || theirs == ours {
+        merged.extend_from_slice(ours);
+        return 0;
+    }
+    if base.len() == ours.len() && base.len() == theirs.len() {
+        return base
+            .iter()
+            .zip(ours)
+            .zip(theirs)
+            .map(|((base, ours), theirs)| {
+                merge_run(
+                    std::slice::from_ref(base),
+                    std::slice::from_ref(ours),
+                    std::slice::from_ref(theirs),
+                    merged,
+                )
+            })
+            .sum();
+    }
+    merged.extend_from_slice(ours);
+    1
+}
+
+/// Split a message into its segment lines.
+fn segment_lines(message: &str) -> Vec<&str> {
+    lines_with_offsets(message)
+        .into_iter()
+        .map(|(_, line)| line)
+        .collect()
+}
+
+/// Match lines of `a` to lines of `b` using their longest common subsequence.
+///
+/// # Returns
+/// For each line of `a`, the index of the line in `b` it matches, if any.
+fn matching_lines(a: &[&str], b: &[&str]) -> Vec<Option<usize>> {
+    // lengths[i][j] is the length of the LCS of a[i..] and b[j..]
+    let mut lengths = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, line_a) in a.iter().enumerate().rev() {
+        for (j, line_b) in b.iter().enumerate().rev() {
+            let length = |i: usize, j: usize| {
+                lengths
+                    .get(i)
+                    .and_then(|row| row.get(j))
+                    .copied()
+                    .unwrap_or_default()
+            };
+            let value = if line_a == line_b {
+                length(i + 1, j + 1) + 1
+            } else {
+                length(i + 1, j).max(length(i, j + 1))
+            };
+            if let Some(cell) = lengths.get_mut(i).and_then(|row| row.get_mut(j)) {
+                *cell = value;
+            }
+        }
+    }
+
+    let length = |i: usize, j: usize| {
+        lengths
+            .get(i)
+            .and_then(|row| row.get(j))
+            .copied()
+            .unwrap_or_default()
+    };
+    let mut matches = vec![None; a.len()];
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if a.get(i) == b.get(j) {
+            if let Some(m) = matches.get_mut(i) {
+                *m = Some(j);
+            }
+            i += 1;
+            j += 1;
+        } else if length(i + 1, j) >= length(i, j + 1) {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    matches
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "MSH|^~\\&|APP|FAC\nPID|||111\nPV1||I\nOBX|1|TX|||a";
+
+    #[test]
+    fn changes_on_one_side_are_taken() {
+        let ours = BASE.replace("PV1||I", "PV1||O");
+        let theirs = BASE.replace("PID|||111", "PID|||222");
+        let (merged, conflicts) = merge_messages(BASE, &ours, &theirs);
+        assert_eq!(conflicts, 0);
+        assert_eq!(merged, "MSH|^~\\&|APP|FAC\nPID|||222\nPV1||O\nOBX|1|TX|||a");
+    }
+
+    #[test]
+    fn added_and_removed_segments_are_merged() {
+        let ours = format!("{BASE}\nOBX|2|TX|||b");
+        let theirs = BASE.replace("PV1||I\n", "");
+        let (merged, conflicts) = merge_messages(BASE, &ours, &theirs);
+        assert_eq!(conflicts, 0);
+        assert_eq!(
+            merged,
+            "MSH|^~\\&|APP|FAC\nPID|||111\nOBX|1|TX|||a\nOBX|2|TX|||b"
+        );
+    }
+
+    #[test]
+    fn conflicts_keep_editor_version() {
+        let ours = BASE.replace("PID|||111", "PID|||222");
+        let theirs = BASE.replace("PID|||111", "PID|||333");
+        let (merged, conflicts) = merge_messages(BASE, &ours, &theirs);
+        assert_eq!(conflicts, 1);
+        assert_eq!(merged, ours);
+    }
+
+    #[test]
+    fn merge_keeps_editor_line_endings() {
+        let base = BASE.replace('\n', "\r");
+        let ours = format!("{}\r", base.replace("PV1||I", "PV1||E"));
+        let (merged, _) = merge_messages(&base, &ours, BASE);
+        assert_eq!(merged, ours);
+    }
+}
@@ -0,0 +1,890 @@
+//! Computed fields: formulas embedded in field values.
+//!
+//! A value written as `{=expression}` is replaced by the result of the
+//! expression. Formulas are evaluated when a template is generated, when a
+//! message is sent (after the `{auto}` placeholders have been filled in), and
+//! on demand from the Tools menu, so a template can derive values from other
+//! fields instead of repeating them.
+//!
+//! ```text
+//! PV1|1|I|||||||||||||||||{=PID.3.1 & luhn(PID.3.1)}
+//! EVN|A01|{=MSH.7 - 2h}
+//! NTE|1||{=concat(upper(PID.5.1), ", ", PID.5.2)}
+//! ```
+//!
+//! # Syntax
+//!
+//! * `"text"` - A string; `\"` and `\\` escape a quote or backslash
+//! * `42`, `1.5` - A number
+//! * `30s`, `15m`, `2h`, `1d`, `1w`, `3mo`, `1y` - A duration
+//! * `PID.5.1`, `OBX[2].5`, `PID.3[2].1` - The value of a field in the message
+//! * `name(arg, ...)` - A function call (see below)
+//! * `a & b` - Concatenation
+//! * `a + b`, `a - b` - Addition and subtraction of numbers, or a duration
+//!   added to or subtracted from an HL7 timestamp
+//!
+//! # Functions
+//!
+//! * `concat(a, ...)` - Join values together
+//! * `substr(text, start, [length])` - Part of a value; `start` counts from 1
+//! * `left(text, n)`, `right(text, n)` - The first or last `n` characters
+//! * `upper(text)`, `lower(text)`, `trim(text)` - Change case or strip whitespace
+//! * `len(text)` - Number of characters
+//! * `pad(text, width, [char])` - Left-pad to a width, with `0` by default
+//! * `replace(text, from, to)` - Replace every occurrence of `from`
+//! * `coalesce(a, ...)` - The first non-empty value
+//! * `now()`, `today()` - The current local time (`YYYYMMDDHHMMSS`) or date (`YYYYMMDD`)
+//! * `luhn(digits)` - Mod 10 (Luhn) check digit
+//! * `mod11(digits)` - Mod 11 check digit, with weights 2 to 7 from the right; `X` for 10
+//! * `random(n)`, `random_digits(n)` - `n` random letters and digits, or digits only
+//!
+//! # Timestamps
+//!
+//! Adding a duration to a timestamp keeps its precision and UTC offset, so
+//! `20250101 + 1d` is `20250102` and `202501011200-0500 + 90m` is
+//! `202501011330-0500`. A field holding `{now}` or `{auto}` counts as the
+//! current time.
+//!
+//! # Evaluation Order
+//!
+//! Formulas are evaluated in document order. Field references see the results
+//! of earlier formulas, while fields holding formulas that haven't been evaluated
+//! yet read as empty. Results are inserted as-is, so a formula can produce
+//! components by including the component separator.
+
+use std::ops::Range;
+
+use hl7_parser::Message;
+use jiff::{civil::DateTime, SignedDuration, Span};
+use rand::distr::{Alphanumeric, SampleString};
+use rand::Rng;
+
+/// Marks the start of a formula; the formula runs to the next `}` outside a string.
+const FORMULA_START: &str = "{=";
+
+/// Evaluate every formula in a message.
+///
+/// # Arguments
+/// * `message` - The HL7 message, which may contain `{=...}` formulas
+///
+/// # Returns
+/// * `Ok(String)` - The message with each formula replaced by its result
+/// * `Err(String)` - If a formula is malformed or can't be evaluated
+#[tauri::command]
+pub fn evaluate_formulas(message: &str) -> Result<String, String> {
+    let spans = find_formulas(message)?;
+    if spans.is_empty() {
+        return Ok(message.to_string());
+    }
+
+    let mut results: Vec<String> = Vec::with_capacity(spans.len());
+    for span in &spans {
+        // earlier formulas are filled in and later ones blanked, so delimiters
+        // inside unevaluated formulas can't throw off field references
+        let reference = splice(message, &spans, &results);
+        let parsed = hl7_parser::parse_message_with_lenient_newlines(&reference).ok();
+
+        let expression = message
+            .get(span.start + FORMULA_START.len()..span.end - 1)
+            .unwrap_or_default();
+        let value = evaluate(expression, parsed.as_ref())
+            .map_err(|e| format!("Failed to evaluate formula `{expression}`: {e}"))?;
+        results.push(value);
+    }
+
+    Ok(splice(message, &spans, &results))
+}
+
+/// Find the byte ranges of every `{=...}` formula, including the braces.
+pub(super) fn find_formulas(text: &str) -> Result<Vec<Range<usize>>, String> {
+    let mut spans = Vec::new();
+    let mut search = 0;
+
+    while let Some(offset) = text.get(search..).and_then(|rest| rest.find(FORMULA_START)) {
+        let start = search + offset;
+        let body = start + FORMULA_START.len();
+
+        let mut in_string = false;
+        let mut escaped = false;
+        let mut end = None;
+        for (i, c) in text.get(body..).unwrap_or_default().char_indices() {
+            if in_string {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == '"' {
+                    in_string = false;
+                }
+            } else if c == '"' {
+                in_string = true;
+            } else if c == '}' {
+                end = Some(body + i + 1);
+                break;
+            } else if c == '\r' || c == '\n' {
+                break;
+            }
+        }
+
+        let Some(end) = end else {
+            let line = text
+                .get(start..)
+                .unwrap_or_default()
+                .lines()
+                .next()
+                .unwrap_or_default();
+            return Err(format!("Unterminated formula `{line}`"));
+        };
+        spans.push(start..end);
+        search = end;
+    }
+
+    Ok(spans)
+}
+
+/// Replace each span with its result, or with nothing if it has no result yet.
+fn splice(text: &str, spans: &[Range<usize>], results: &[String]) -> String {
+    let mut spliced = String::with_capacity(text.len());
+    let mut last = 0;
+    for (i, span) in spans.iter().enumerate() {
+        spliced.push_str(text.get(last..span.start).unwrap_or_default());
+        if let Some(result) = results.get(i) {
+            spliced.push_str(result);
+        }
+        last = span.end;
+    }
+    spliced.push_str(text.get(last..).unwrap_or_default());
+    spliced
+}
+
+/// Evaluate a single expression against a message.
+fn evaluate(expression: &str, message: Option<&Message>) -> Result<String, String> {
+    let mut parser = Parser {
+        tokens: tokenize(expression)?,
+        position: 0,
+        message,
+    };
+    let value = parser.concatenation()?;
+    if let Some(token) = parser.tokens.get(parser.position) {
+        return Err(format!(
+            "Unexpected {token:?} after the end of the expression"
+        ));
+    }
+    value.into_text()
+}
+
+/// A calendar offset: months are added first, then seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(super) struct Shift {
+    months: i64,
+    seconds: i64,
+}
+
+impl Shift {
+    /// Parse a duration literal such as `2h` or `3mo`.
+    pub(super) fn parse(amount: &str, unit: &str) -> Result<Self, String> {
+        let amount: i64 = amount
+            .parse()
+            .map_err(|_| format!("Durations must be whole numbers, not '{amount}{unit}'"))?;
+        let (months, seconds) = match unit {
+            "s" => (0, 1),
+            "m" | "min" => (0, 60),
+            "h" => (0, 3_600),
+            "d" => (0, 86_400),
+            "w" => (0, 604_800),
+            "mo" => (1, 0),
+            "y" => (12, 0),
+            _ => return Err(format!("Unknown duration unit '{unit}'")),
+        };
+        let overflow = || format!("Duration '{amount}{unit}' is too large");
+        Ok(Shift {
+            months: amount.checked_mul(months).ok_or_else(overflow)?,
+            seconds: amount.checked_mul(seconds).ok_or_else(overflow)?,
+        })
+    }
+
+    /// A shift of a number of seconds, which may be negative.
+    pub(super) fn from_seconds(seconds: i64) -> Self {
+        Shift { months: 0, seconds }
+    }
+
+    pub(super) fn negate(self) -> Self {
+        Shift {
+            months: -self.months,
+            seconds: -self.seconds,
+        }
+    }
+
+    pub(super) fn plus(self, other: Shift) -> Result<Self, String> {
+        Ok(Shift {
+            months: self
+                .months
+                .checked_add(other.months)
+                .ok_or("Duration is too large")?,
+            seconds: self
+                .seconds
+                .checked_add(other.seconds)
+                .ok_or("Duration is too large")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Duration(Shift),
+    Text(String),
+    Name(String),
+    LeftParen,
+    RightParen,
+    Comma,
+    Plus,
+    Minus,
+    Ampersand,
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.char_indices().peekable();
+
+    while let Some((start, c)) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '(' => tokens.push(Token::LeftParen),
+            ')' => tokens.push(Token::RightParen),
+            ',' => tokens.push(Token::Comma),
+            '+' => tokens.push(Token::Plus),
+            '-' => tokens.push(Token::Minus),
+            '&' => tokens.push(Token::Ampersand),
+            '"' => {
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, escaped)) => text.push(escaped),
+                            None => return Err("Unterminated string".to_string()),
+                        },
+                        Some((_, c)) => text.push(c),
+                        None => return Err("Unterminated string".to_string()),
+                    }
+                }
+                tokens.push(Token::Text(text));
+            }
+            '0'..='9' => {
+                let mut end = start + 1;
+                while let Some(&(i, c)) = chars.peek() {
+                    if !(c.is_ascii_digit() || c == '.') {
+                        break;
+                    }
+                    end = i + 1;
+                    chars.next();
+                }
+                let mut unit_end = end;
+                while let Some(&(i, c)) = chars.peek() {
+                    if !c.is_ascii_alphabetic() {
+                        break;
+                    }
+                    unit_end = i + 1;
+                    chars.next();
+                }
+
+                let amount = expression.get(start..end).unwrap_or_default();
+                let unit = expression.get(end..unit_end).unwrap_or_default();
+                if unit.is_empty() {
+                    let number = amount
+                        .parse()
+                        .map_err(|_| format!("'{amount}' isn't a number"))?;
+                    tokens.push(Token::Number(number));
+                } else {
+                    tokens.push(Token::Duration(Shift::parse(amount, unit)?));
+                }
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut end = start + 1;
+                while let Some(&(i, c)) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '[' | ']')) {
+                        break;
+                    }
+                    end = i + 1;
+                    chars.next();
+                }
+                tokens.push(Token::Name(
+                    expression.get(start..end).unwrap_or_default().to_string(),
+                ));
+            }
+            other => return Err(format!("Unexpected character '{other}'")),
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// An intermediate result.
+#[derive(Debug, Clone)]
+enum Value {
+    Text(String),
+    Number(f64),
+    Duration(Shift),
+}
+
+impl Value {
+    fn into_text(self) -> Result<String, String> {
+        match self {
+            Value::Text(text) => Ok(text),
+            Value::Number(number) => Ok(format_number(number)),
+            Value::Duration(_) => {
+                Err("A duration can only be added to or subtracted from a timestamp".to_string())
+            }
+        }
+    }
+
+    fn into_number(self) -> Result<f64, String> {
+        match self {
+            Value::Number(number) => Ok(number),
+            Value::Text(text) => text
+                .trim()
+                .parse()
+                .map_err(|_| format!("'{text}' isn't a number")),
+            Value::Duration(_) => Err("A duration can't be used as a number".to_string()),
+        }
+    }
+
+    fn negate(self) -> Result<Value, String> {
+        match self {
+            Value::Duration(shift) => Ok(Value::Duration(shift.negate())),
+            Value::Number(_) | Value::Text(_) => Ok(Value::Number(-self.into_number()?)),
+        }
+    }
+}
+
+/// Format a number without a trailing `.0` when it's whole.
+fn format_number(number: f64) -> String {
+    if number.fract() == 0.0 && number.abs() < 1e15 {
+        format!("{}", number as i64)
+    } else {
+        number.to_string()
+    }
+}
+
+fn add(lhs: Value, rhs: Value) -> Result<Value, String> {
+    match (lhs, rhs) {
+        (Value::Duration(a), Value::Duration(b)) => Ok(Value::Duration(a.plus(b)?)),
+        (Value::Text(timestamp), Value::Duration(shift))
+        | (Value::Duration(shift), Value::Text(timestamp)) => {
+            Ok(Value::Text(shift_timestamp(&timestamp, shift)?))
+        }
+        (Value::Number(_), Value::Duration(_)) | (Value::Duration(_), Value::Number(_)) => {
+            Err("A duration can only be added to or subtracted from a timestamp".to_string())
+        }
+        (lhs @ (Value::Text(_) | Value::Number(_)), rhs @ (Value::Text(_) | Value::Number(_))) => {
+            Ok(Value::Number(lhs.into_number()? + rhs.into_number()?))
+        }
+    }
+}
+
+/// Recursive-descent parser that evaluates as it goes.
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    message: Option<&'a Message<'a>>,
+}
+
+impl Parser<'_> {
+    /// Consume the next token if it's the given one.
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.tokens.get(self.position) == Some(token) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        if token.is_some() {
+            self.position += 1;
+        }
+        token
+    }
+
+    /// `additive ('&' additive)*`
+    fn concatenation(&mut self) -> Result<Value, String> {
+        let mut value = self.additive()?;
+        while self.eat(&Token::Ampersand) {
+            let rhs = self.additive()?;
+            value = Value::Text(value.into_text()? + &rhs.into_text()?);
+        }
+        Ok(value)
+    }
+
+    /// `unary (('+' | '-') unary)*`
+    fn additive(&mut self) -> Result<Value, String> {
+        let mut value = self.unary()?;
+        loop {
+            if self.eat(&Token::Plus) {
+                value = add(value, self.unary()?)?;
+            } else if self.eat(&Token::Minus) {
+                value = add(value, self.unary()?.negate()?)?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    /// `'-' unary | primary`
+    fn unary(&mut self) -> Result<Value, String> {
+        if self.eat(&Token::Minus) {
+            self.unary()?.negate()
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Result<Value, String> {
+        match self.next() {
+            Some(Token::Number(number)) => Ok(Value::Number(number)),
+            Some(Token::Duration(shift)) => Ok(Value::Duration(shift)),
+            Some(Token::Text(text)) => Ok(Value::Text(text)),
+            Some(Token::LeftParen) => {
+                let value = self.concatenation()?;
+                if !self.eat(&Token::RightParen) {
+                    return Err("Expected ')'".to_string());
+                }
+                Ok(value)
+            }
+            Some(Token::Name(name)) => {
+                if !self.eat(&Token::LeftParen) {
+                    return self.field(&name);
+                }
+                let mut args = Vec::new();
+                if !self.eat(&Token::RightParen) {
+                    loop {
+                        args.push(self.concatenation()?);
+                        if self.eat(&Token::RightParen) {
+                            break;
+                        }
+                        if !self.eat(&Token::Comma) {
+                            return Err(format!("Expected ',' or ')' in {name}()"));
+                        }
+                    }
+                }
+                call(&name, args)
+            }
+            Some(
+                token @ (Token::RightParen
+                | Token::Comma
+                | Token::Plus
+                | Token::Minus
+                | Token::Ampersand),
+            ) => Err(format!("Unexpected {token:?}")),
+            None => Err("Unexpected end of expression".to_string()),
+        }
+    }
+
+    /// Look up the value of a field reference such as `PID.5.1`.
+    fn field(&self, path: &str) -> Result<Value, String> {
+        let is_field = path.len() >= 3
+            && path
+                .chars()
+                .take(3)
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+        if !is_field {
+            return Err(format!("Unknown name '{path}'"));
+        }
+
+        let value = self
+            .message
+            .and_then(|message| {
+                message
+                    .query(path)
+                    .map(|value| message.separators.decode(value.raw_value()).to_string())
+            })
+            .unwrap_or_default();
+        Ok(Value::Text(value))
+    }
+}
+
+/// Positional arguments to a function call.
+struct Args<'n> {
+    name: &'n str,
+    values: std::vec::IntoIter<Value>,
+}
+
+impl Args<'_> {
+    fn value(&mut self) -> Result<Value, String> {
+        self.values
+            .next()
+            .ok_or_else(|| format!("{}() needs more arguments", self.name))
+    }
+
+    fn text(&mut self) -> Result<String, String> {
+        self.value()?.into_text()
+    }
+
+    fn count(&mut self) -> Result<usize, String> {
+        let number = self.value()?.into_number()?;
+        if number < 0.0 || number.fract() != 0.0 {
+            return Err(format!(
+                "{}() needs a whole number, not {}",
+                self.name,
+                format_number(number)
+            ));
+        }
+        Ok(number as usize)
+    }
+
+    fn has_more(&self) -> bool {
+        !self.values.as_slice().is_empty()
+    }
+
+    fn finish(mut self) -> Result<(), String> {
+        match self.values.next() {
+            Some(_) => Err(format!("{}() has too many arguments", self.name)),
+            None => Ok(()),
+        }
+    }
+}
+
+fn call(name: &str, values: Vec<Value>) -> Result<Value, String> {
+    let mut args = Args {
+        name,
+        values: values.into_iter(),
+    };
+
+    let value = match name {
+        "concat" => {
+            let mut text = String::new();
+            while args.has_more() {
+                text.push_str(&args.text()?);
+            }
+            Value::Text(text)
+        }
+        "coalesce" => {
+            let mut first = String::new();
+            while args.has_more() {
+                let text = args.text()?;
+                if first.is_empty() {
+                    first = text;
+                }
+            }
+            Value::Text(first)
+        }
+        "substr" => {
+            let text = args.text()?;
+            let start = args.count()?.saturating_sub(1);
+            let length = if args.has_more() {
+                args.count()?
+            } else {
+                usize::MAX
+            };
+            Value::Text(text.chars().skip(start).take(length).collect())
+        }
+        "left" => {
+            let text = args.text()?;
+            let count = args.count()?;
+            Value::Text(text.chars().take(count).collect())
+        }
+        "right" => {
+            let text = args.text()?;
+            let count = args.count()?;
+            let skip = text.chars().count().saturating_sub(count);
+            Value::Text(text.chars().skip(skip).collect())
+        }
+        "upper" => Value::Text(args.text()?.to_uppercase()),
+        "lower" => Value::Text(args.text()?.to_lowercase()),
+        "trim" => Value::Text(args.text()?.trim().to_string()),
+        "len" => Value::Number(args.text()?.chars().count() as f64),
+        "pad" => {
+            let text = args.text()?;
+            let width = args.count()?;
+            let fill = if args.has_more() {
+                args.text()?
+            } else {
+                "0".to_string()
+            };
+            let mut fill = fill.chars();
+            let (Some(fill), None) = (fill.next(), fill.next()) else {
+                return Err("pad() needs a single fill character".to_string());
+            };
+            let padding = width.saturating_sub(text.chars().count());
+            Value::Text(
+                std::iter::repeat_n(fill, padding)
+                    .chain(text.chars())
+                    .collect(),
+            )
+        }
+        "replace" => {
+            let text = args.text()?;
+            let from = args.text()?;
+            let to = args.text()?;
+            if from.is_empty() {
+                return Err("replace() needs something to replace".to_string());
+            }
+            Value::Text(text.replace(&from, &to))
+        }
+        "now" => Value::Text(
+            jiff::Zoned::now()
+                .datetime()
+                .strftime("%Y%m%d%H%M%S")
+                .to_string(),
+        ),
+        "today" => Value::Text(jiff::Zoned::now().date().strftime("%Y%m%d").to_string()),
+        "luhn" => Value::Text(luhn(&args.text()?)?),
+        "mod11" => Value::Text(mod11(&args.text()?)?),
+        "random" => Value::Text(Alphanumeric.sample_string(&mut rand::rng(), args.count()?)),
+        "random_digits" => {
+            let count = args.count()?;
+            let mut rng = rand::rng();
+            Value::Text(
+                (0..count)
+                    .map(|_| char::from(b'0' + rng.random_range(0..10u8)))
+                    .collect(),
+            )
+        }
+        _ => return Err(format!("Unknown function {name}()")),
+    };
+
+    args.finish()?;
+    Ok(value)
+}
+
+/// Add a calendar offset to an HL7 timestamp, keeping its precision and offset.
+pub(super) fn shift_timestamp(timestamp: &str, shift: Shift) -> Result<String, String> {
+    let timestamp = timestamp.trim();
+    let now;
+    let timestamp = if timestamp == "{now}" || timestamp == "{auto}" {
+        now = jiff::Zoned::now()
+            .datetime()
+            .strftime("%Y%m%d%H%M%S")
+            .to_string();
+        now.as_str()
+    } else {
+        timestamp
+    };
+
+    let TimestampParts {
+        datetime,
+        whole,
+        fraction,
+        offset,
+    } = TimestampParts::parse(timestamp)?;
+
+    let shifted = Span::new()
+        .try_months(shift.months)
+        .and_then(|months| datetime.checked_add(months))
+        .and_then(|shifted| shifted.checked_add(SignedDuration::from_secs(shift.seconds)))
+        .map_err(|e| format!("Failed to shift '{timestamp}': {e}"))?;
+
+    let mut result = shifted.strftime("%Y%m%d%H%M%S").to_string();
+    result.truncate(whole.len());
+    if let Some(fraction) = fraction {
+        let nanoseconds = format!("{:09}", shifted.subsec_nanosecond());
+        result.push('.');
+        result.push_str(nanoseconds.get(..fraction.len().min(9)).unwrap_or_default());
+    }
+    result.push_str(offset);
+    Ok(result)
+}
+
+/// An HL7 timestamp split into the parts shifting it has to keep.
+struct TimestampParts<'a> {
+    /// The date and time it stands for, ignoring its UTC offset
+    datetime: DateTime,
+    /// The digits before any fraction of a second, as precise as written
+    whole: &'a str,
+    /// Fraction of a second, if it has one
+    fraction: Option<&'a str>,
+    /// UTC offset as written (e.g., `-0500`), or empty
+    offset: &'a str,
+}
+
+impl<'a> TimestampParts<'a> {
+    fn parse(timestamp: &'a str) -> Result<Self, String> {
+        let invalid = || format!("'{timestamp}' isn't an HL7 timestamp");
+        let (digits, offset) = match timestamp.find(['+', '-']) {
+            Some(index) => timestamp.split_at(index),
+            None => (timestamp, ""),
+        };
+        let (whole, fraction) = match digits.split_once('.') {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (digits, None),
+        };
+        let all_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+        if ![4, 6, 8, 10, 12, 14].contains(&whole.len())
+            || !all_digits(whole)
+            || fraction.is_some_and(|fraction| fraction.is_empty() || !all_digits(fraction))
+            || (fraction.is_some() && whole.len() != 14)
+        {
+            return Err(invalid());
+        }
+
+        let part = |range: Range<usize>, default: i8| -> Result<i8, String> {
+            whole
+                .get(range)
+                .map_or(Ok(default), |part| part.parse().map_err(|_| invalid()))
+        };
+        let year: i16 = whole
+            .get(0..4)
+            .unwrap_or_default()
+            .parse()
+            .map_err(|_| invalid())?;
+        let nanoseconds = fraction.map_or(Ok(0), |fraction| {
+            format!("{fraction:0<9}")
+                .get(..9)
+                .unwrap_or_default()
+                .parse::<i32>()
+                .map_err(|_| invalid())
+        })?;
+        let datetime = DateTime::new(
+            year,
+            part(4..6, 1)?,
+            part(6..8, 1)?,
+            part(8..10, 0)?,
+            part(10..12, 0)?,
+            part(12..14, 0)?,
+            nanoseconds,
+        )
+        .map_err(|e| format!("'{timestamp}' isn't a valid timestamp: {e}"))?;
+
+        Ok(TimestampParts {
+            datetime,
+            whole,
+            fraction,
+            offset,
+        })
+    }
+}
+
+/// Read an HL7 timestamp as the local date and time it was written in,
+/// ignoring its UTC offset.
+pub(super) fn timestamp_datetime(timestamp: &str) -> Result<DateTime, String> {
+    TimestampParts::parse(timestamp.trim()).map(|parts| parts.datetime)
+}
+
+/// Parse a string of decimal digits.
+fn digits(text: &str) -> Result<Vec<u32>, String> {
+    let digits: Option<Vec<u32>> = text.trim().chars().map(|c| c.to_digit(10)).collect();
+    match digits {
+        Some(digits) if !digits.is_empty() => Ok(digits),
+        _ => Err(format!("'{text}' must contain only digits")),
+    }
+}
+
+/// Mod 10 (Luhn) check digit for a string of digits.
+fn luhn(text: &str) -> Result<String, String> {
+    let sum: u32 = digits(text)?
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, digit)| {
+            if i % 2 == 0 {
+                let doubled = digit * 2;
+                if doubled > 9 {
+                    doubled - 9
+                } else {
+                    doubled
+                }
+            } else {
+                *digit
+            }
+        })
+        .sum();
+    Ok(((10 - sum % 10) % 10).to_string())
+}
+
+/// Mod 11 check digit for a string of digits, with weights 2 to 7 from the right.
+fn mod11(text: &str) -> Result<String, String> {
+    let sum: u32 = digits(text)?
+        .iter()
+        .rev()
+        .zip((2..=7).cycle())
+        .map(|(digit, weight)| digit * weight)
+        .sum();
+    match (11 - sum % 11) % 11 {
+        10 => Ok("X".to_string()),
+        check => Ok(check.to_string()),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str =
+        "MSH|^~\\&|APP|FAC|||20250101120000||ADT^A01|1|P|2.5.1\rPID|1||7992739871||Doe^John";
+
+    fn evaluate_in(segment: &str) -> Result<String, String> {
+        let message = format!("{MESSAGE}\r{segment}");
+        evaluate_formulas(&message)
+            .map(|result| result.rsplit('\r').next().unwrap_or_default().to_string())
+    }
+
+    #[test]
+    fn can_reference_fields_and_concatenate() {
+        assert_eq!(
+            evaluate_in("NTE|1||{=PID.5.2 & \" \" & upper(PID.5.1)}").unwrap(),
+            "NTE|1||John DOE"
+        );
+        assert_eq!(
+            evaluate_in("NTE|1||{=concat(substr(PID.3, 1, 3), \"}\", right(PID.3, 2))}").unwrap(),
+            "NTE|1||799}71"
+        );
+        assert_eq!(
+            evaluate_in("NTE|1||{=pad(len(PID.5.1), 4)}").unwrap(),
+            "NTE|1||0003"
+        );
+    }
+
+    #[test]
+    fn later_formulas_see_earlier_results() {
+        assert_eq!(
+            evaluate_in("NTE|{=\"A\" & \"B\"}||{=NTE.1 & \"C\"}").unwrap(),
+            "NTE|AB||ABC"
+        );
+    }
+
+    #[test]
+    fn can_shift_timestamps() {
+        assert_eq!(
+            evaluate_in("EVN|A01|{=MSH.7 - 2h}").unwrap(),
+            "EVN|A01|20250101100000"
+        );
+        let shift = |amount, unit| Shift::parse(amount, unit).unwrap();
+        assert_eq!(
+            shift_timestamp("20250131", shift("1", "mo")).unwrap(),
+            "20250228"
+        );
+        assert_eq!(
+            shift_timestamp("202501011200-0500", shift("90", "m")).unwrap(),
+            "202501011330-0500"
+        );
+        assert_eq!(
+            shift_timestamp("20250101000000.25", shift("1", "s").negate()).unwrap(),
+            "20241231235959.25"
+        );
+        assert!(shift_timestamp("2025010", shift("1", "d")).is_err());
+    }
+
+    #[test]
+    fn can_compute_check_digits() {
+        assert_eq!(luhn("7992739871").unwrap(), "3");
+        assert_eq!(mod11("123").unwrap(), "6");
+        assert!(luhn("12a").is_err());
+        assert_eq!(
+            evaluate_in("PV1|{=PID.3 & luhn(PID.3)}").unwrap(),
+            "PV1|79927398713"
+        );
+    }
+
+    #[test]
+    fn malformed_formulas_are_errors() {
+        assert!(evaluate_formulas("PID|{=concat(\"a\"").is_err());
+        assert!(evaluate_formulas("PID|{=nope(1)}").is_err());
+        assert!(evaluate_formulas("PID|{=1 +}").is_err());
+        assert!(evaluate_formulas("PID|{=2h}").is_err());
+        assert_eq!(evaluate_formulas("PID|{auto}|x").unwrap(), "PID|{auto}|x");
+    }
+}
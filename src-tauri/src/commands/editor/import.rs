@@ -77,20 +77,20 @@ use std::collections::HashMap;
 
 /// A single segment in the import format.
 #[derive(Deserialize)]
-struct SegmentImport {
-    segment: String,
+pub(super) struct SegmentImport {
+    pub(super) segment: String,
     #[serde(default)]
-    fields: IndexMap<String, Value>,
+    pub(super) fields: IndexMap<String, Value>,
 }
 
 /// Root structure for imported messages.
 #[derive(Deserialize)]
-struct MessageImport {
-    segments: Vec<SegmentImport>,
+pub(super) struct MessageImport {
+    pub(super) segments: Vec<SegmentImport>,
 }
 
 /// Converts an import structure back to an HL7 message using MessageBuilder.
-fn tree_to_message(import: &MessageImport) -> Result<String, String> {
+pub(super) fn tree_to_message(import: &MessageImport) -> Result<String, String> {
     let separators = extract_separators(import);
     let mut builder = MessageBuilder::new(separators);
 
@@ -119,7 +119,7 @@ fn extract_separators(import: &MessageImport) -> Separators {
 }
 
 /// Parses separators from MSH.2 encoding characters string.
-fn separators_from_encoding_chars(encoding_chars: &str) -> Separators {
+pub(super) fn separators_from_encoding_chars(encoding_chars: &str) -> Separators {
     let chars: Vec<char> = encoding_chars.chars().collect();
     Separators {
         field: '|',
@@ -0,0 +1,273 @@
+//! Carve HL7 messages out of interface engine logs and other free text.
+//!
+//! Engines log messages surrounded by timestamps, log levels, and their own
+//! chatter, often with a prefix on the line holding MSH:
+//!
+//! ```text
+//! 2025-01-14 09:12:44,118 INFO  [inbound-adt] Received: MSH|^~\&|ADT|HOSP|...
+//! EVN|A01|20250114091244
+//! PID|1||12345^^^MRN||DOE^JOHN
+//! 2025-01-14 09:12:44,131 INFO  [inbound-adt] Sent ACK
+//! ```
+//!
+//! Unlike [`multi_message`](super::multi_message), which splits files that hold
+//! nothing but messages, extraction assumes most of the text is noise.
+//!
+//! # Message Boundaries
+//!
+//! - A message starts at `MSH` followed by a field separator and encoding
+//!   characters, anywhere on a line; whatever precedes it is dropped
+//! - It continues over the following lines that look like segments: a segment
+//!   name (`PID`, `ZP1`) followed by the message's field separator
+//! - It ends at the first other line, including blank lines, batch segments
+//!   (FHS, BHS, BTS, FTS), and the next MSH
+//!
+//! MLLP start and end block bytes around segments are ignored, as is trailing
+//! whitespace. Segments are joined with `\r` in the extracted message.
+//!
+//! # Offsets
+//!
+//! Offsets are byte offsets into the scanned text, like those of
+//! [`split_messages`](super::split_messages); a log file is scanned as UTF-8,
+//! with invalid bytes replaced.
+
+use super::multi_message::lines_with_offsets;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// MLLP start block byte.
+const START_BLOCK: char = '\x0b';
+
+/// MLLP end block byte.
+const END_BLOCK: char = '\x1c';
+
+/// Segments that wrap batches of messages rather than belonging to one.
+const BATCH_SEGMENTS: &[&str] = &["FHS", "BHS", "BTS", "FTS"];
+
+/// A message found in a log.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExtractedMessage {
+    /// The message, with segments separated by `\r`
+    pub message: String,
+    /// Byte offset of the start of `MSH` in the text
+    pub start: usize,
+    /// Byte offset of the end of the last segment in the text
+    pub end: usize,
+    /// Line the message starts on (1-based)
+    pub line: usize,
+    /// Number of segments in the message
+    pub segments: usize,
+    /// MSH.9, if present
+    pub message_type: Option<String>,
+    /// MSH.10, if present
+    pub control_id: Option<String>,
+}
+
+/// Extract the HL7 messages embedded in some text.
+///
+/// # Arguments
+/// * `text` - Log output or other text holding messages
+///
+/// # Returns
+/// The messages found, in the order they appear
+#[tauri::command]
+pub fn extract_messages(text: &str) -> Vec<ExtractedMessage> {
+    let mut messages = Vec::new();
+    let mut current: Option<Extraction> = None;
+
+    for (number, (offset, line)) in lines_with_offsets(text).into_iter().enumerate() {
+        if let Some(position) = find_header(line) {
+            if let Some(extraction) = current.take() {
+                messages.push(extraction.finish());
+            }
+            let segment = clean_segment(line.get(position..).unwrap_or_default());
+            current = Some(Extraction::new(segment, offset + position, number + 1));
+            continue;
+        }
+
+        let segment = clean_segment(line);
+        match current.as_mut() {
+            Some(extraction) if extraction.accepts(segment) => {
+                extraction.push(segment, offset + leading_framing(line) + segment.len());
+            }
+            Some(_) => {
+                if let Some(extraction) = current.take() {
+                    messages.push(extraction.finish());
+                }
+            }
+            None => {}
+        }
+    }
+    if let Some(extraction) = current {
+        messages.push(extraction.finish());
+    }
+
+    messages
+}
+
+/// Extract the HL7 messages embedded in a log file.
+///
+/// # Arguments
+/// * `path` - Path to the log file
+///
+/// # Returns
+/// * `Ok(Vec<ExtractedMessage>)` - The messages found, in the order they appear
+/// * `Err(String)` - If the file can't be read
+#[tauri::command]
+pub async fn extract_messages_from_file(path: PathBuf) -> Result<Vec<ExtractedMessage>, String> {
+    let bytes = tokio::fs::read(&path)
+        .await
+        .map_err(|e| format!("Failed to read log file: {e}"))?;
+    let text = String::from_utf8_lossy(&bytes);
+    Ok(extract_messages(&text))
+}
+
+/// A message being collected.
+struct Extraction {
+    segments: Vec<String>,
+    field_separator: char,
+    start: usize,
+    end: usize,
+    line: usize,
+}
+
+impl Extraction {
+    /// Start a message at its MSH segment.
+    fn new(msh: &str, start: usize, line: usize) -> Self {
+        Self {
+            field_separator: msh.chars().nth(3).unwrap_or('|'),
+            segments: vec![msh.to_string()],
+            start,
+            end: start + msh.len(),
+            line,
+        }
+    }
+
+    /// Whether a line continues the message.
+    fn accepts(&self, segment: &str) -> bool {
+        let mut chars = segment.chars();
+        let name: String = chars.by_ref().take(3).collect();
+        name.len() == 3
+            && name.starts_with(|c: char| c.is_ascii_uppercase())
+            && name
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+            && !BATCH_SEGMENTS.contains(&name.as_str())
+            && chars.next().is_none_or(|c| c == self.field_separator)
+    }
+
+    /// Add a segment ending at byte offset `end` of the text.
+    fn push(&mut self, segment: &str, end: usize) {
+        self.segments.push(segment.to_string());
+        self.end = end;
+    }
+
+    /// Finish the message, reading its type and control ID from MSH.
+    fn finish(self) -> ExtractedMessage {
+        let msh_field = |number: usize| {
+            self.segments
+                .first()
+                .and_then(|msh| msh.split(self.field_separator).nth(number - 1))
+                .filter(|value| !value.is_empty())
+                .map(ToString::to_string)
+        };
+        ExtractedMessage {
+            message_type: msh_field(9),
+            control_id: msh_field(10),
+            segments: self.segments.len(),
+            message: self.segments.join("\r"),
+            start: self.start,
+            end: self.end,
+            line: self.line,
+        }
+    }
+}
+
+/// Find where an MSH segment starts in a line, if it holds one.
+///
+/// `MSH` has to stand on its own (not be the end of a longer word) and be
+/// followed by a field separator and at least two encoding characters, which
+/// keeps log text that merely mentions MSH from starting a message.
+fn find_header(line: &str) -> Option<usize> {
+    line.match_indices("MSH").map(|(i, _)| i).find(|&i| {
+        let before = line.get(..i).and_then(|before| before.chars().next_back());
+        let mut after = line.get(i + 3..).unwrap_or_default().chars();
+        let separator = after.next();
+        let encoding: Vec<char> = after.take(2).collect();
+        before.is_none_or(|c| !c.is_ascii_alphanumeric())
+            && separator.is_some_and(|c| c.is_ascii_punctuation())
+            && encoding.len() == 2
+            && encoding
+                .iter()
+                .all(|c| c.is_ascii_punctuation() && Some(*c) != separator)
+    })
+}
+
+/// Strip MLLP framing bytes and trailing whitespace from a segment line.
+fn clean_segment(line: &str) -> &str {
+    line.trim_start_matches(START_BLOCK)
+        .trim_end_matches(|c: char| c == END_BLOCK || c.is_whitespace())
+}
+
+/// Length in bytes of the MLLP start block bytes at the start of a line.
+fn leading_framing(line: &str) -> usize {
+    line.len() - line.trim_start_matches(START_BLOCK).len()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const LOG: &str = "2025-01-14 09:12:44,118 INFO  [inbound-adt] Received: MSH|^~\\&|ADT|HOSP|||20250114||ADT^A01|MSG001|P|2.5.1\n\
+EVN|A01|20250114091244\n\
+PID|1||12345^^^MRN||DOE^JOHN   \n\
+2025-01-14 09:12:44,131 INFO  [inbound-adt] Sent ACK, MSH segment had 12 fields\n\
+\x0bMSH|^~\\&|LAB|HOSP|||20250114||ORU^R01|MSG002|P|2.5.1\r\
+OBX|1|NM|GLU||5.5\r\
+\x1c\r\
+trailing noise";
+
+    #[test]
+    fn extracts_messages_from_log_noise() {
+        let messages = extract_messages(LOG);
+        assert_eq!(messages.len(), 2);
+
+        let adt = &messages[0];
+        assert_eq!(
+            adt.message,
+            "MSH|^~\\&|ADT|HOSP|||20250114||ADT^A01|MSG001|P|2.5.1\rEVN|A01|20250114091244\rPID|1||12345^^^MRN||DOE^JOHN"
+        );
+        assert_eq!(adt.line, 1);
+        assert_eq!(adt.segments, 3);
+        assert_eq!(adt.message_type.as_deref(), Some("ADT^A01"));
+        assert_eq!(adt.control_id.as_deref(), Some("MSG001"));
+        assert!(LOG[adt.start..].starts_with("MSH|^~\\&|ADT"));
+        assert!(LOG[..adt.end].ends_with("DOE^JOHN"));
+
+        let oru = &messages[1];
+        assert_eq!(
+            oru.message,
+            "MSH|^~\\&|LAB|HOSP|||20250114||ORU^R01|MSG002|P|2.5.1\rOBX|1|NM|GLU||5.5"
+        );
+        assert_eq!(oru.line, 5);
+        assert!(LOG[oru.start..].starts_with("MSH|^~\\&|LAB"));
+        assert!(LOG[..oru.end].ends_with("GLU||5.5"));
+    }
+
+    #[test]
+    fn back_to_back_messages_are_separate() {
+        let text = "MSH|^~\\&|A||||||ADT^A08|1\nPID|1\nMSH|^~\\&|A||||||ADT^A08|2\nPID|2\n\nPV1|1";
+        let messages = extract_messages(text);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].message, "MSH|^~\\&|A||||||ADT^A08|1\rPID|1");
+        assert_eq!(messages[1].message, "MSH|^~\\&|A||||||ADT^A08|2\rPID|2");
+    }
+
+    #[test]
+    fn mentions_of_msh_are_not_messages() {
+        let text = "Checking MSH: ok\nXMSH|^~\\&|nope\nMSH|field|x";
+        assert!(extract_messages(text).is_empty());
+    }
+}
@@ -0,0 +1,293 @@
+//! Metadata headers: `#` comment lines at the top of a message file.
+//!
+//! Teams annotate sample and test files with a title, an author, and notes on
+//! the scenario they exercise. HL7 has nowhere to put these, so they're written
+//! as comment lines before the MSH segment:
+//!
+//! ```text
+//! # title: Admission with two next of kin
+//! # author: Integration team
+//! # ticket: INT-1042
+//! #
+//! # Checks the receiver keeps both NK1 segments in order.
+//! MSH|^~\&|...
+//! ```
+//!
+//! A comment line is no part of the message, so the header is split off when
+//! a file is opened and put back when it's saved; the editor, validation, and
+//! sending only ever see the message itself. Validation and sending also strip
+//! a header left in a message, so a pasted file doesn't fail to parse.
+//!
+//! # Format
+//!
+//! Leading lines of `key: value` pairs are properties; `title` and `author`
+//! are recognised, and any other keys are kept as they are. The first line that
+//! isn't a pair starts the notes, which run to the end of the header. Blank
+//! lines between the header and the MSH segment belong to the header.
+//!
+//! Headers are rewritten in a standard layout on save (properties, a blank
+//! comment line, then the notes), which can reorder a hand-written header but
+//! never loses any of it.
+
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+use super::multi_message::{detect_line_ending, lines_with_offsets};
+
+/// Metadata carried in a message file's comment header.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentMetadata {
+    /// Short title of the message
+    pub title: Option<String>,
+    /// Who wrote or maintains the message
+    pub author: Option<String>,
+    /// Any other `key: value` properties, in header order
+    #[serde(default)]
+    pub properties: IndexMap<String, String>,
+    /// Free-form notes, one line per comment line
+    pub notes: Option<String>,
+}
+
+/// A message file split into its metadata header and the message itself.
+#[derive(Debug, Clone, Serialize)]
+pub struct DocumentContent {
+    /// Metadata from the header (empty if the file has no header)
+    pub metadata: DocumentMetadata,
+    /// The message, without the header
+    pub message: String,
+    /// Length of the header in bytes, i.e. the offset of the message in the file
+    pub header_length: usize,
+}
+
+impl DocumentMetadata {
+    /// Whether there's nothing to write in a header.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.author.is_none()
+            && self.properties.is_empty()
+            && self.notes.is_none()
+    }
+}
+
+/// Split a message file into its metadata header and message.
+///
+/// # Arguments
+/// * `content` - Raw file content
+///
+/// # Returns
+/// The metadata, the message, and where the message starts in the file. A file
+/// without a header returns empty metadata and the content unchanged.
+#[tauri::command]
+pub fn get_document_metadata(content: &str) -> DocumentContent {
+    let header_length = header_length(content);
+    let header = content.get(..header_length).unwrap_or_default();
+
+    let mut metadata = DocumentMetadata::default();
+    let mut notes: Vec<&str> = Vec::new();
+    for (_, line) in lines_with_offsets(header) {
+        let Some(text) = line.strip_prefix('#') else {
+            // blank lines before the message
+            continue;
+        };
+        let text = text.strip_prefix(' ').unwrap_or(text);
+
+        match property(text).filter(|_| notes.is_empty()) {
+            Some((key, value)) if key.eq_ignore_ascii_case("title") => {
+                metadata.title = Some(value.to_string());
+            }
+            Some((key, value)) if key.eq_ignore_ascii_case("author") => {
+                metadata.author = Some(value.to_string());
+            }
+            Some((key, value)) => {
+                metadata
+                    .properties
+                    .insert(key.to_string(), value.to_string());
+            }
+            // a blank comment line between the properties and the notes
+            None if notes.is_empty() && text.trim().is_empty() => {}
+            None => notes.push(text.trim_end()),
+        }
+    }
+    while notes.last().is_some_and(|line| line.is_empty()) {
+        notes.pop();
+    }
+    if !notes.is_empty() {
+        metadata.notes = Some(notes.join("\n"));
+    }
+
+    DocumentContent {
+        metadata,
+        message: content.get(header_length..).unwrap_or_default().to_string(),
+        header_length,
+    }
+}
+
+/// Write a metadata header onto a message.
+///
+/// Any header already on the message is replaced. Empty metadata removes the
+/// header altogether.
+///
+/// # Arguments
+/// * `message` - The message, with or without a header
+/// * `metadata` - Metadata to write
+///
+/// # Returns
+/// The file content: the header followed by the message
+#[tauri::command]
+pub fn set_document_metadata(message: &str, metadata: DocumentMetadata) -> String {
+    let existing = header_length(message);
+    let body = message.get(existing..).unwrap_or_default();
+    if metadata.is_empty() {
+        return body.to_string();
+    }
+
+    // keep the line ending of the existing header, or else of the message
+    let line_ending = match existing {
+        0 => detect_line_ending(body),
+        _ => detect_line_ending(message),
+    };
+
+    // values are single lines; a newline would start a note
+    let single_line = |value: &str| value.replace(['\r', '\n'], " ");
+    let mut lines = Vec::new();
+    if let Some(title) = &metadata.title {
+        lines.push(format!("# title: {}", single_line(title)));
+    }
+    if let Some(author) = &metadata.author {
+        lines.push(format!("# author: {}", single_line(author)));
+    }
+    for (key, value) in &metadata.properties {
+        lines.push(format!("# {}: {}", single_line(key), single_line(value)));
+    }
+    if let Some(notes) = &metadata.notes {
+        if !lines.is_empty() {
+            lines.push("#".to_string());
+        }
+        for line in notes.lines() {
+            match line.trim_end() {
+                "" => lines.push("#".to_string()),
+                line => lines.push(format!("# {line}")),
+            }
+        }
+    }
+
+    let mut content = lines.join(line_ending);
+    content.push_str(line_ending);
+    content.push_str(body);
+    content
+}
+
+/// Strip any metadata header from a message.
+///
+/// # Returns
+/// The message without its header, and the header's length in bytes so offsets
+/// into the message can be mapped back onto the original text
+#[must_use]
+pub fn strip_document_metadata(content: &str) -> (&str, usize) {
+    let header_length = header_length(content);
+    (
+        content.get(header_length..).unwrap_or_default(),
+        header_length,
+    )
+}
+
+/// Length of the comment header at the start of the content, in bytes.
+fn header_length(content: &str) -> usize {
+    if !content.starts_with('#') {
+        return 0;
+    }
+    lines_with_offsets(content)
+        .into_iter()
+        .find(|(_, line)| !line.starts_with('#') && !line.trim().is_empty())
+        .map_or(content.len(), |(start, _)| start)
+}
+
+/// Split a comment into a `key: value` property, if it is one.
+///
+/// Keys are single words of letters, digits, `-`, and `_`, so a sentence that
+/// happens to contain a colon isn't mistaken for a property.
+fn property(text: &str) -> Option<(&str, &str)> {
+    let (key, value) = text.split_once(':')?;
+    let valid_key = key.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    valid_key.then(|| (key, value.trim()))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str = "MSH|^~\\&|APP|FAC|||20250101||ADT^A01|1|P|2.5.1\rPID|1||123";
+
+    #[test]
+    fn reads_properties_and_notes() {
+        let content = format!(
+            "# title: Admission\n# author: Integration team\n# ticket: INT-1042\n#\n# Checks NK1 order.\n# Note: both are kept.\n\n{MESSAGE}"
+        );
+        let document = get_document_metadata(&content);
+
+        assert_eq!(document.message, MESSAGE);
+        assert_eq!(&content[document.header_length..], MESSAGE);
+        assert_eq!(document.metadata.title.as_deref(), Some("Admission"));
+        assert_eq!(
+            document.metadata.author.as_deref(),
+            Some("Integration team")
+        );
+        assert_eq!(document.metadata.properties["ticket"], "INT-1042");
+        // once the notes start, everything is a note
+        assert_eq!(
+            document.metadata.notes.as_deref(),
+            Some("Checks NK1 order.\nNote: both are kept.")
+        );
+    }
+
+    #[test]
+    fn files_without_a_header_are_unchanged() {
+        let document = get_document_metadata(MESSAGE);
+        assert_eq!(document.message, MESSAGE);
+        assert_eq!(document.header_length, 0);
+        assert!(document.metadata.is_empty());
+        assert_eq!(
+            set_document_metadata(MESSAGE, DocumentMetadata::default()),
+            MESSAGE
+        );
+    }
+
+    #[test]
+    fn headers_round_trip() {
+        let mut metadata = DocumentMetadata {
+            title: Some("Admission".to_string()),
+            author: None,
+            properties: IndexMap::new(),
+            notes: Some("First line\n\nSecond paragraph".to_string()),
+        };
+        metadata
+            .properties
+            .insert("scenario".to_string(), "happy path".to_string());
+
+        let content = set_document_metadata(MESSAGE, metadata.clone());
+        assert!(content.starts_with("# title: Admission\r# scenario: happy path\r#\r# First"));
+        let document = get_document_metadata(&content);
+        assert_eq!(document.metadata, metadata);
+        assert_eq!(document.message, MESSAGE);
+
+        // writing again replaces the header rather than adding another
+        metadata.title = Some("Renamed".to_string());
+        let rewritten = set_document_metadata(&content, metadata.clone());
+        assert_eq!(get_document_metadata(&rewritten).metadata, metadata);
+        assert_eq!(rewritten.matches("# title:").count(), 1);
+    }
+
+    #[test]
+    fn strips_headers() {
+        let content = format!("# just a note\n{MESSAGE}");
+        let (message, offset) = strip_document_metadata(&content);
+        assert_eq!(message, MESSAGE);
+        assert_eq!(offset, "# just a note\n".len());
+        assert_eq!(strip_document_metadata(MESSAGE), (MESSAGE, 0));
+    }
+}
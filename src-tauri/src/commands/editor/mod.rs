@@ -5,11 +5,41 @@
 //!
 //! # Modules
 //!
+//! - [`adt_workflow`] - ADT messages for a whole encounter, sharing MRN, visit, and timeline
+//! - [`annotations`] - Notes and bookmarks on parts of a message, kept in a sidecar file
+//! - [`completions`] - Completion candidates for the segment name or field under the cursor
 //! - [`cursor`] - Cursor position tracking and field navigation (Tab/Shift-Tab)
 //! - [`data`] - Segment parsing/rendering, field queries, timestamps, templates
+//! - [`delimiters`] - Rewrite a message from one set of delimiters to another
+//! - [`document`] - Attach documents to messages as ED or RP observations
+//! - [`documents`] - The messages open in the editor, each with its own state
+//! - [`escape`] - Encode and decode `\F\`, `\S\`, and the other escape sequences
 //! - [`export`] - Export messages to JSON, YAML, TOML formats
+//! - [`fhir`] - Convert messages to and from FHIR R4 bundles
+//! - [`field_search`] - Find fields by schema name or spec description
+//! - [`field_metrics`] - Length and byte size of a field against its schema limits
+//! - [`file_watch`] - Detect and resolve external changes to the open file
+//! - [`formula`] - Computed field values written as `{=expression}`
 //! - [`import`] - Import messages from JSON, YAML, TOML formats
+//! - [`log_extract`] - Find the messages embedded in interface engine logs
+//! - [`metadata`] - `#` comment headers carrying a file's title, author, and notes
+//! - [`multi_edit`] - Field and segment edits at several places, applied in one step
+//! - [`multi_message`] - Split files holding several messages and join them on save
+//! - [`name_format`] - Convert names and addresses between formats
+//! - [`normalize`] - Strip trailing delimiters and empty repetitions, and unify line endings
+//! - [`offsets`] - Convert between byte offsets and the editor's UTF-16 offsets
+//! - [`payload`] - Find, open, and replace documents embedded in OBX-5
+//! - [`placeholders`] - `{now-2h}`, `{seq}`, and user variables in templates and sent messages
+//! - [`query`] - QPD segments built from query criteria, and the parameters of received queries
+//! - [`result_block`] - OBR/OBX result blocks built from a list of observations
+//! - [`script`] - Short Rhai scripts that transform the message, run from the scripting console
+//! - [`snippets`] - Reusable segments saved under the data root and inserted at the cursor
 //! - [`syntax_highlight`] - HTML generation with CSS classes for HL7 elements
+//! - [`time_shift`] - Move every timestamp by an offset, or so MSH-7 is now
+//! - [`tree`] - The message as a tree of named parts with their ranges, for the structure view
+//! - [`undo`] - Undo and redo history of the message, with checkpoints, kept across sessions
+//! - [`user_templates`] - Messages saved as named templates under the data root
+//! - [`v2xml`] - Convert messages to and from HL7 v2.xml
 //!
 //! # Editing Flow
 //!
@@ -18,17 +48,80 @@
 //! 2. Frontend calls `syntax_highlight` to get styled HTML
 //! 3. HTML overlay renders on top of the textarea
 //! 4. Cursor position tracked via `locate_cursor` for context display
+//!
+//! Positions and ranges exchanged with the editor are in UTF-16 code units, as
+//! the textarea counts them; see [`offsets`].
 
+mod adt_workflow;
+mod annotations;
+mod completions;
 mod cursor;
 mod data;
+mod delimiters;
+mod document;
+mod documents;
+mod escape;
 pub mod export;
+pub mod fhir;
+mod field_metrics;
+mod field_search;
+mod file_watch;
+mod formula;
 pub mod import;
+mod log_extract;
+mod metadata;
+mod multi_edit;
+mod multi_message;
+mod name_format;
+mod normalize;
+mod offsets;
+mod payload;
+mod placeholders;
+mod query;
+mod result_block;
+mod script;
 mod segment;
+mod snippets;
 mod syntax_highlight;
+mod time_shift;
+mod tree;
+mod undo;
+mod user_templates;
+mod v2xml;
 
+pub use adt_workflow::*;
+pub use annotations::*;
+pub use completions::*;
 pub use cursor::*;
 pub use data::*;
+pub use delimiters::*;
+pub use document::*;
+pub use documents::*;
+pub use escape::*;
 pub use export::*;
+pub use fhir::*;
+pub use field_metrics::*;
+pub use field_search::*;
+pub use file_watch::*;
+pub use formula::*;
 pub use import::*;
+pub use log_extract::*;
+pub use metadata::*;
+pub use multi_edit::*;
+pub use multi_message::*;
+pub use name_format::*;
+pub use normalize::*;
+pub use offsets::*;
+pub use payload::*;
+pub use placeholders::*;
+pub use query::*;
+pub use result_block::*;
+pub use script::*;
 pub use segment::*;
+pub use snippets::*;
 pub use syntax_highlight::*;
+pub use time_shift::*;
+pub use tree::*;
+pub use undo::*;
+pub use user_templates::*;
+pub use v2xml::*;
@@ -0,0 +1,312 @@
+//! Edits spanning several places in a message, applied in one step.
+//!
+//! Multi-cursor and multi-select workflows (filling the same field in every
+//! OBX, clearing a handful of fields, dropping all the NTE segments in a
+//! selection) would otherwise take one command round-trip per place, each
+//! producing an intermediate message and its own undo entry. These commands
+//! take the whole batch and return a single resulting message, so the frontend
+//! records one undo entry for it.
+//!
+//! # Atomicity
+//!
+//! Every edit is checked before anything is changed: if any path or index is
+//! invalid, the command fails and the message is left as it was, rather than
+//! applying the edits that happened to be valid.
+
+use hl7_parser::builder::{FieldBuilder, MessageBuilder, RepeatBuilder, SegmentBuilder};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+
+use super::data::{parse_field_id, FieldId};
+use super::segment::{SegmentChange, SegmentOperationResult};
+
+/// A value to write to a field, repeat, or component.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FieldEdit {
+    /// Path in query syntax, optionally naming the segment occurrence and
+    /// repeat (e.g., "PID.5.1", "OBX[2].5", "PID.3[2].1")
+    pub path: String,
+    /// Value to write, with any delimiters already escaped
+    pub value: String,
+}
+
+/// Set several fields at once.
+///
+/// Fields, repeats, and components that don't exist yet are created. Edits are
+/// applied in order, so a later edit to the same place wins.
+///
+/// # Arguments
+/// * `message` - The HL7 message as a string
+/// * `edits` - Values to write, with their paths
+///
+/// # Returns
+/// * `Ok(String)` - The message with every edit applied
+/// * `Err(String)` - The message couldn't be parsed, or an edit's path is
+///   invalid or names a segment the message doesn't have
+#[tauri::command]
+pub fn set_fields(message: &str, edits: Vec<FieldEdit>) -> Result<String, String> {
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(message)
+        .map_err(|e| format!("Failed to parse message: {e}"))?;
+    let mut builder: MessageBuilder = (&parsed).into();
+
+    // resolve every path first so a bad edit doesn't leave a partial result
+    let mut resolved = Vec::with_capacity(edits.len());
+    for edit in &edits {
+        let (segment_index, field_id) = resolve_path(builder.segments_mut(), &edit.path)?;
+        resolved.push((segment_index, field_id, edit.value.as_str()));
+    }
+
+    let segments = builder.segments_mut();
+    for (segment_index, field_id, value) in resolved {
+        let segment = segments
+            .get_mut(segment_index)
+            .expect("resolved segment exists");
+        set_value(segment, &field_id, value);
+    }
+
+    Ok(builder.render_with_newlines().to_string())
+}
+
+/// Delete several segments at once.
+///
+/// The cursor is positioned where the first deleted segment was, or at the
+/// start of the last remaining segment if nothing followed the deleted ones,
+/// matching `delete_segment`.
+///
+/// # Constraints
+/// - Cannot delete the MSH segment (index 0)
+/// - Returns None if any index is out of bounds, or no indices are given
+#[tauri::command]
+pub fn delete_segments(message: &str, indices: Vec<usize>) -> Option<SegmentOperationResult> {
+    let indices: BTreeSet<usize> = indices.into_iter().collect();
+    let first = *indices.first()?;
+    if first == 0 {
+        return None;
+    }
+
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(message).ok()?;
+    let segments: Vec<_> = parsed.segments().collect();
+    if *indices.last()? >= segments.len() {
+        return None;
+    }
+
+    // each deletion takes the newline before its segment with it
+    let mut ranges = Vec::with_capacity(indices.len());
+    for &index in &indices {
+        let segment = segments.get(index)?;
+        let preceding = message.get(..segment.range.start).unwrap_or_default();
+        let start = if preceding.ends_with("\r\n") {
+            segment.range.start - 2
+        } else if preceding.ends_with(['\r', '\n']) {
+            segment.range.start - 1
+        } else {
+            segment.range.start
+        };
+        ranges.push((start, segment.range.end));
+    }
+
+    // splice from the end so earlier ranges stay valid
+    let mut new_message = message.to_string();
+    for &(start, end) in ranges.iter().rev() {
+        new_message.replace_range(start..end, "");
+    }
+
+    let first_start = ranges.first().map_or(0, |(start, _)| *start);
+    let anything_follows = (first + 1..segments.len()).any(|index| !indices.contains(&index));
+    let cursor = if anything_follows {
+        first_start
+    } else {
+        // text before the first deleted segment is unchanged
+        (1..first)
+            .rev()
+            .find(|index| !indices.contains(index))
+            .and_then(|index| segments.get(index))
+            .map_or(0, |segment| segment.range.start)
+    };
+
+    let changes = indices
+        .iter()
+        .filter_map(|&index| {
+            let segment = segments.get(index)?;
+            Some(SegmentChange::deleted(index, &segment.range))
+        })
+        .collect();
+
+    Some(
+        SegmentOperationResult {
+            message: new_message,
+            cursor,
+            changes,
+        }
+        .into_utf16(message),
+    )
+}
+
+/// A field path naming a segment occurrence, e.g. "OBX[2].5.1".
+pub(super) struct OccurrencePath<'a> {
+    /// Segment name
+    pub(super) segment: &'a str,
+    /// Segment occurrence (1-based), 1 when the path doesn't name one
+    pub(super) occurrence: usize,
+    /// Field, repeat, and component within the segment
+    pub(super) field_id: FieldId,
+}
+
+/// Parse a field path that may name a segment occurrence and repeat.
+///
+/// # Returns
+/// * `Ok(OccurrencePath)` - The parsed path
+/// * `Err(String)` - The path is invalid
+pub(super) fn parse_occurrence_path(path: &str) -> Result<OccurrencePath<'_>, String> {
+    let invalid = || format!("Invalid field path: {path}");
+    let (segment_part, field_part) = path.split_once('.').ok_or_else(invalid)?;
+
+    // an optional occurrence follows the segment name in brackets
+    let (segment, occurrence) = match segment_part.split_once('[') {
+        Some((name, occurrence)) => {
+            let occurrence = occurrence
+                .strip_suffix(']')
+                .and_then(|occurrence| occurrence.parse::<usize>().ok())
+                .filter(|occurrence| *occurrence >= 1)
+                .ok_or_else(invalid)?;
+            (name, occurrence)
+        }
+        None => (segment_part, 1),
+    };
+
+    let field_id =
+        parse_field_id(&format!("{segment}.{field_part}"), segment).ok_or_else(invalid)?;
+    Ok(OccurrencePath {
+        segment,
+        occurrence,
+        field_id,
+    })
+}
+
+/// Resolve an edit path to a segment index and field identifier.
+fn resolve_path(segments: &[SegmentBuilder], path: &str) -> Result<(usize, FieldId), String> {
+    let OccurrencePath {
+        segment: name,
+        occurrence,
+        field_id,
+    } = parse_occurrence_path(path)?;
+    let segment_part = path.split('.').next().unwrap_or(path);
+    if name == "MSH" && field_id.field <= 2 {
+        return Err(format!(
+            "{path} holds the message delimiters and can't be set"
+        ));
+    }
+
+    let segment_index = segments
+        .iter()
+        .enumerate()
+        .filter(|(_, segment)| segment.name() == name)
+        .nth(occurrence - 1)
+        .map(|(index, _)| index)
+        .ok_or_else(|| format!("Message has no {segment_part} segment for {path}"))?;
+
+    Ok((segment_index, field_id))
+}
+
+/// Write a value to a field, repeat, or component, creating it if needed.
+fn set_value(segment: &mut SegmentBuilder, field_id: &FieldId, value: &str) {
+    if !segment.has_field(field_id.field) {
+        segment.set_field(field_id.field, FieldBuilder::default());
+    }
+    let field = segment.field_mut(field_id.field).expect("field exists");
+
+    let mut repeats = match std::mem::take(field) {
+        FieldBuilder::Value(value) => vec![RepeatBuilder::with_value(value)],
+        FieldBuilder::Repeats(repeats) => repeats,
+    };
+    if repeats.len() < field_id.repeat {
+        repeats.resize_with(field_id.repeat, RepeatBuilder::default);
+    }
+    if let Some(repeat) = repeats.get_mut(field_id.repeat - 1) {
+        match field_id.component {
+            Some(component) => repeat.set_component_value(component, value),
+            None => *repeat = RepeatBuilder::with_value(value.to_string()),
+        }
+    }
+    *field = FieldBuilder::Repeats(repeats);
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str = "MSH|^~\\&|APP|FAC\rPID|1||123||Doe^John\rOBX|1|NM|GLU||5.5\rNTE|1||first\rOBX|2|NM|HGB||13\rNTE|1||second";
+
+    fn edit(path: &str, value: &str) -> FieldEdit {
+        FieldEdit {
+            path: path.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    fn query(message: &str, path: &str) -> String {
+        let parsed = hl7_parser::parse_message_with_lenient_newlines(message).unwrap();
+        parsed
+            .query(path)
+            .map(|value| value.raw_value().to_string())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn sets_fields_across_segments() {
+        let result = set_fields(
+            MESSAGE,
+            vec![
+                edit("PID.5.2", "Jane"),
+                edit("OBX.11", "F"),
+                edit("OBX[2].11", "F"),
+                edit("PID.3[2]", "456"),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(query(&result, "PID.5"), "Doe^Jane");
+        assert_eq!(query(&result, "OBX.11"), "F");
+        assert_eq!(query(&result, "OBX[2].11"), "F");
+        assert_eq!(query(&result, "PID.3"), "123~456");
+        assert_eq!(query(&result, "OBX[2].5"), "13");
+    }
+
+    #[test]
+    fn rejects_the_whole_batch_on_a_bad_edit() {
+        let result = set_fields(
+            MESSAGE,
+            vec![edit("PID.5.2", "Jane"), edit("OBX[3].11", "F")],
+        );
+        assert!(result.is_err());
+        assert!(set_fields(MESSAGE, vec![edit("MSH.2", "^~")]).is_err());
+        assert!(set_fields(MESSAGE, vec![edit("PID", "x")]).is_err());
+    }
+
+    #[test]
+    fn deletes_several_segments() {
+        let result = delete_segments(MESSAGE, vec![5, 3]).unwrap();
+        assert_eq!(
+            result.message,
+            "MSH|^~\\&|APP|FAC\rPID|1||123||Doe^John\rOBX|1|NM|GLU||5.5\rOBX|2|NM|HGB||13"
+        );
+
+        // nothing follows the deleted segments, so the cursor moves back
+        let result = delete_segments(MESSAGE, vec![4, 5]).unwrap();
+        assert_eq!(&result.message[result.cursor..result.cursor + 5], "NTE|1");
+        let deleted: Vec<_> = result
+            .changes
+            .iter()
+            .map(|change| change.index_before)
+            .collect();
+        assert_eq!(deleted, vec![Some(4), Some(5)]);
+    }
+
+    #[test]
+    fn cannot_delete_msh_or_missing_segments() {
+        assert!(delete_segments(MESSAGE, vec![0, 2]).is_none());
+        assert!(delete_segments(MESSAGE, vec![2, 9]).is_none());
+        assert!(delete_segments(MESSAGE, vec![]).is_none());
+    }
+}
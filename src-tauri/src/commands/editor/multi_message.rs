@@ -0,0 +1,374 @@
+//! Support for files containing more than one HL7 message.
+//!
+//! Integration engines commonly export many messages into a single file, either
+//! back to back, separated by blank lines, wrapped in an FHS/BHS batch envelope,
+//! or still carrying their MLLP framing bytes. Parsed as one message, such a
+//! file looks like a single broken message with hundreds of MSH segments.
+//!
+//! This module splits such files into individual messages so they can be
+//! edited one at a time, and joins them back together on save using the same
+//! framing the file was read with.
+//!
+//! # Message Boundaries
+//!
+//! A new message starts at:
+//! - Every MSH segment
+//! - The first segment after one or more blank lines
+//! - Every MLLP start block (`0x0B`), if the file contains framing bytes
+//!
+//! Batch envelope segments (FHS, BHS, BTS, FTS) are never part of a message;
+//! they're kept aside as the file's header and trailer.
+//!
+//! # Offsets
+//!
+//! Message spans are byte offsets into the original file content. The cursor
+//! given to [`find_message_at`] is a UTF-16 offset, like the cursors of the
+//! other editor commands, as that's what the editor reports.
+
+use serde::{Deserialize, Serialize};
+
+use super::offsets::Utf16Offsets;
+
+/// MLLP start block byte.
+const START_BLOCK: char = '\x0b';
+
+/// MLLP end block byte.
+const END_BLOCK: char = '\x1c';
+
+/// How messages were separated in a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MessageFraming {
+    /// Messages directly follow one another (or there is only one message).
+    Newline,
+    /// Messages are separated by blank lines.
+    Blank,
+    /// Messages are wrapped in an FHS/BHS batch envelope.
+    Batch,
+    /// Messages are wrapped in MLLP start/end block bytes.
+    Mllp,
+}
+
+/// A single message within a multi-message file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileMessage {
+    /// Message text, without surrounding blank lines or framing bytes.
+    pub text: String,
+    /// Byte offset of the start of the message in the file.
+    pub start: usize,
+    /// Byte offset of the end of the message in the file.
+    pub end: usize,
+}
+
+/// A file split into individual messages.
+///
+/// Passed back to [`join_messages`] after editing to rebuild the file with its
+/// original framing.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageFile {
+    /// Messages in file order.
+    pub messages: Vec<FileMessage>,
+    /// How the messages were separated.
+    pub framing: MessageFraming,
+    /// Line ending used between segments ("\r", "\n", or "\r\n").
+    #[serde(rename = "lineEnding")]
+    pub line_ending: String,
+    /// Batch header segments (FHS, BHS), in file order.
+    pub header: Vec<String>,
+    /// Batch trailer segments (BTS, FTS), in file order.
+    pub trailer: Vec<String>,
+}
+
+/// Split file content into individual messages.
+///
+/// # Arguments
+/// * `content` - Raw file content
+///
+/// # Returns
+/// The messages in the file along with the framing needed to rejoin them. A
+/// file with a single message returns one message with `newline` framing.
+#[tauri::command]
+pub fn split_messages(content: &str) -> MessageFile {
+    let line_ending = detect_line_ending(content).to_string();
+
+    if content.contains(START_BLOCK) {
+        return MessageFile {
+            messages: split_mllp(content),
+            framing: MessageFraming::Mllp,
+            line_ending,
+            header: Vec::new(),
+            trailer: Vec::new(),
+        };
+    }
+
+    let mut messages = Vec::new();
+    let mut header = Vec::new();
+    let mut trailer = Vec::new();
+    let mut separated_by_blank = false;
+
+    // span of the message currently being collected
+    let mut current: Option<(usize, usize)> = None;
+    let mut after_blank = false;
+
+    for (start, line) in lines_with_offsets(content) {
+        if line.trim().is_empty() {
+            after_blank = true;
+            continue;
+        }
+
+        let end = start + line.len();
+        match line.get(..3) {
+            Some("FHS" | "BHS") => {
+                close_message(content, &mut current, &mut messages);
+                header.push(line.to_string());
+            }
+            Some("BTS" | "FTS") => {
+                close_message(content, &mut current, &mut messages);
+                trailer.push(line.to_string());
+            }
+            _ => {
+                let starts_message = line.starts_with("MSH") || after_blank;
+                if starts_message || current.is_none() {
+                    if current.is_some() {
+                        separated_by_blank |= after_blank;
+                    }
+                    close_message(content, &mut current, &mut messages);
+                    current = Some((start, end));
+                } else if let Some(span) = current.as_mut() {
+                    span.1 = end;
+                }
+            }
+        }
+        after_blank = false;
+    }
+    close_message(content, &mut current, &mut messages);
+
+    let framing = if !header.is_empty() || !trailer.is_empty() {
+        MessageFraming::Batch
+    } else if separated_by_blank {
+        MessageFraming::Blank
+    } else {
+        MessageFraming::Newline
+    };
+
+    MessageFile {
+        messages,
+        framing,
+        line_ending,
+        header,
+        trailer,
+    }
+}
+
+/// Find which message in a file contains a cursor position.
+///
+/// Positions between messages (on blank lines or framing bytes) belong to the
+/// preceding message, so navigating with the cursor never lands on nothing.
+///
+/// # Arguments
+/// * `content` - Raw file content
+/// * `cursor` - UTF-16 offset of the cursor
+///
+/// # Returns
+/// The index of the message, or `None` if the file contains no messages
+#[tauri::command]
+pub fn find_message_at(content: &str, cursor: usize) -> Option<usize> {
+    let cursor = Utf16Offsets::new(content).to_byte(cursor);
+    let file = split_messages(content);
+    file.messages
+        .iter()
+        .rposition(|message| message.start <= cursor)
+        .or_else(|| (!file.messages.is_empty()).then_some(0))
+}
+
+/// Join messages back into file content using the framing they were split with.
+///
+/// # Arguments
+/// * `file` - Split file, typically from [`split_messages`] with edited message text
+///
+/// # Returns
+/// The file content to save
+#[tauri::command]
+pub fn join_messages(file: MessageFile) -> String {
+    let le = file.line_ending.as_str();
+    let texts = file.messages.iter().map(|message| message.text.as_str());
+
+    match file.framing {
+        MessageFraming::Newline => texts.collect::<Vec<_>>().join(le),
+        MessageFraming::Blank => texts.collect::<Vec<_>>().join(&format!("{le}{le}")),
+        MessageFraming::Batch => file
+            .header
+            .iter()
+            .map(String::as_str)
+            .chain(texts)
+            .chain(file.trailer.iter().map(String::as_str))
+            .collect::<Vec<_>>()
+            .join(le),
+        MessageFraming::Mllp => texts
+            .map(|text| format!("{START_BLOCK}{text}{le}{END_BLOCK}\r"))
+            .collect(),
+    }
+}
+
+/// Split MLLP-framed content into messages.
+fn split_mllp(content: &str) -> Vec<FileMessage> {
+    content
+        .match_indices(START_BLOCK)
+        .filter_map(|(block_start, _)| {
+            let start = block_start + START_BLOCK.len_utf8();
+            let rest = content.get(start..)?;
+            // a frame ends at its end block, or at the next start block if it's missing
+            let len = rest.find([END_BLOCK, START_BLOCK]).unwrap_or(rest.len());
+            let text = rest.get(..len)?.trim_end_matches(['\r', '\n']);
+            (!text.trim().is_empty()).then(|| FileMessage {
+                text: text.to_string(),
+                start,
+                end: start + text.len(),
+            })
+        })
+        .collect()
+}
+
+/// Push the message being collected, if any, onto the list of messages.
+fn close_message(
+    content: &str,
+    current: &mut Option<(usize, usize)>,
+    messages: &mut Vec<FileMessage>,
+) {
+    if let Some((start, end)) = current.take() {
+        if let Some(text) = content.get(start..end) {
+            messages.push(FileMessage {
+                text: text.to_string(),
+                start,
+                end,
+            });
+        }
+    }
+}
+
+/// Split content into lines, keeping the byte offset of each line.
+///
+/// Accepts `\r`, `\n`, and `\r\n` line endings, since HL7 files use all three.
+pub(super) fn lines_with_offsets(content: &str) -> Vec<(usize, &str)> {
+    let mut lines = Vec::new();
+    let mut start = 0;
+    let mut chars = content.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        if c == '\r' || c == '\n' {
+            lines.push((start, content.get(start..i).unwrap_or_default()));
+            if c == '\r' && chars.peek().is_some_and(|(_, next)| *next == '\n') {
+                chars.next();
+                start = i + 2;
+            } else {
+                start = i + 1;
+            }
+        }
+    }
+    if start < content.len() {
+        lines.push((start, content.get(start..).unwrap_or_default()));
+    }
+
+    lines
+}
+
+/// Detect the line ending used between segments.
+pub(super) fn detect_line_ending(content: &str) -> &'static str {
+    match content.find(['\r', '\n']) {
+        Some(i) if content.get(i..i + 2) == Some("\r\n") => "\r\n",
+        Some(i) if content.get(i..i + 1) == Some("\n") => "\n",
+        _ => "\r",
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const MSG1: &str = "MSH|^~\\&|APP|FAC|||20231215||ADT^A01|1|P|2.5.1\nPID|||111";
+    const MSG2: &str = "MSH|^~\\&|APP|FAC|||20231215||ADT^A01|2|P|2.5.1\nPID|||222";
+
+    #[test]
+    fn single_message_is_not_split() {
+        let file = split_messages(MSG1);
+        assert_eq!(file.messages.len(), 1);
+        assert_eq!(file.messages[0].text, MSG1);
+        assert_eq!(file.framing, MessageFraming::Newline);
+    }
+
+    #[test]
+    fn can_split_back_to_back_messages() {
+        let content = format!("{MSG1}\n{MSG2}\n");
+        let file = split_messages(&content);
+        assert_eq!(file.messages.len(), 2);
+        assert_eq!(file.messages[1].text, MSG2);
+        assert_eq!(file.framing, MessageFraming::Newline);
+        assert_eq!(&content[file.messages[1].start..file.messages[1].end], MSG2);
+    }
+
+    #[test]
+    fn can_split_blank_line_separated_messages() {
+        let content = format!("{MSG1}\n\n\n{MSG2}");
+        let file = split_messages(&content);
+        assert_eq!(file.messages.len(), 2);
+        assert_eq!(file.framing, MessageFraming::Blank);
+        assert_eq!(join_messages(file), format!("{MSG1}\n\n{MSG2}"));
+    }
+
+    #[test]
+    fn can_split_batch_files() {
+        let content = format!(
+            "FHS|^~\\&|ENGINE\rBHS|^~\\&|ENGINE\r{}\r{}\rBTS|2\rFTS|1",
+            MSG1.replace('\n', "\r"),
+            MSG2.replace('\n', "\r")
+        );
+        let file = split_messages(&content);
+        assert_eq!(file.framing, MessageFraming::Batch);
+        assert_eq!(file.line_ending, "\r");
+        assert_eq!(file.header, vec!["FHS|^~\\&|ENGINE", "BHS|^~\\&|ENGINE"]);
+        assert_eq!(file.trailer, vec!["BTS|2", "FTS|1"]);
+        assert_eq!(file.messages.len(), 2);
+        assert_eq!(join_messages(file), content);
+    }
+
+    #[test]
+    fn can_split_mllp_framed_messages() {
+        let content = format!("\x0b{MSG1}\r\x1c\r\n\x0b{MSG2}\r\x1c\r\n");
+        let file = split_messages(&content);
+        assert_eq!(file.framing, MessageFraming::Mllp);
+        assert_eq!(file.messages.len(), 2);
+        assert_eq!(file.messages[0].text, MSG1);
+        assert_eq!(file.messages[1].text, MSG2);
+    }
+
+    #[test]
+    fn can_find_message_at_cursor() {
+        let content = format!("{MSG1}\n\n{MSG2}");
+        let second_start = MSG1.len() + 2;
+        assert_eq!(find_message_at(&content, 0), Some(0));
+        assert_eq!(find_message_at(&content, MSG1.len() + 1), Some(0));
+        assert_eq!(find_message_at(&content, second_start), Some(1));
+        assert_eq!(find_message_at("", 0), None);
+    }
+
+    #[test]
+    fn find_message_at_takes_a_utf16_cursor() {
+        let first = MSG1.replace("PID|||111", "PID|||111||MÜLLER^JOSÉ");
+        let content = format!("{first}\n\n{MSG2}");
+        let second_start = first.encode_utf16().count() + 2;
+        // as a byte offset this would still be inside the first message
+        assert!(second_start < first.len() + 2);
+        assert_eq!(find_message_at(&content, second_start), Some(1));
+        assert_eq!(find_message_at(&content, second_start - 1), Some(0));
+    }
+
+    #[test]
+    fn join_preserves_edited_text() {
+        let content = format!("{MSG1}\n{MSG2}");
+        let mut file = split_messages(&content);
+        file.messages[0].text = file.messages[0].text.replace("111", "999");
+        let joined = join_messages(file);
+        assert!(joined.contains("PID|||999\nMSH"));
+    }
+}
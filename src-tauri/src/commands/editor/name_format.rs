@@ -0,0 +1,480 @@
+//! Conversions between name and address formats.
+//!
+//! Mapping names and addresses between systems is a frequent small task that's
+//! easy to get wrong by one component: a person name (XPN, as in PID-5) starts
+//! with the family name, while a participant (XCN, as in PV1-7) puts an ID
+//! first and moves every name component along by one. Other systems send the
+//! whole name as a single "DOE, JOHN" string, or shout everything in upper
+//! case. These conversions do the rearranging in one step.
+//!
+//! # Styles
+//!
+//! * `split-name` - "FAMILY, GIVEN MIDDLE" (or "GIVEN MIDDLE FAMILY") into
+//!   family, given, and middle name components
+//! * `join-name` - family, given, and middle name components into a single
+//!   "FAMILY, GIVEN MIDDLE" string
+//! * `upper-case` / `title-case` - the family, given, and middle names
+//! * `person-to-participant` - XPN into XCN, adding an empty ID first and moving
+//!   the name type and later components to their XCN positions
+//! * `participant-to-person` - XCN into XPN, dropping the ID and the other
+//!   components that identify the participant rather than name them
+//! * `split-address` - "STREET, CITY, STATE ZIP[, COUNTRY]" into address (XAD)
+//!   components
+//! * `drop-subcomponents` - keep only the first subcomponent of each component.
+//!   v2.5 split family names (FN) and street addresses (SAD) into
+//!   subcomponents whose first is the whole value, which v2.3 and earlier
+//!   systems expect on its own
+//!
+//! # Paths
+//!
+//! Paths use the query syntax, optionally naming a segment occurrence and a
+//! repeat ("PID.5", "NK1[2].2", "PID.5[2]"). Without a repeat, every repeat of
+//! the field is converted. The name and address styles start at the component
+//! the path names, so "PV1.7.2" works on a participant's name; without one they
+//! start at component 1. The whole-field styles don't take a component.
+//!
+//! Values are converted as written, escape sequences included; case changes
+//! leave escape sequences alone.
+
+use serde::{Deserialize, Serialize};
+
+use super::multi_edit::{parse_occurrence_path, OccurrencePath};
+
+/// A name or address conversion.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NameStyle {
+    /// "FAMILY, GIVEN MIDDLE" or "GIVEN MIDDLE FAMILY" into name components
+    SplitName,
+    /// Name components into "FAMILY, GIVEN MIDDLE"
+    JoinName,
+    /// Upper-case the family, given, and middle names
+    UpperCase,
+    /// Capitalise each word of the family, given, and middle names
+    TitleCase,
+    /// Person name (XPN) into participant (XCN)
+    PersonToParticipant,
+    /// Participant (XCN) into person name (XPN)
+    ParticipantToPerson,
+    /// "STREET, CITY, STATE ZIP[, COUNTRY]" into address components
+    SplitAddress,
+    /// Keep only the first subcomponent of each component
+    DropSubcomponents,
+}
+
+impl NameStyle {
+    /// Whether the style converts whole fields rather than starting at a component.
+    fn whole_field(self) -> bool {
+        match self {
+            NameStyle::PersonToParticipant | NameStyle::ParticipantToPerson => true,
+            NameStyle::SplitName
+            | NameStyle::JoinName
+            | NameStyle::UpperCase
+            | NameStyle::TitleCase
+            | NameStyle::SplitAddress
+            | NameStyle::DropSubcomponents => false,
+        }
+    }
+}
+
+/// XPN component positions (1-based) and where they go in an XCN.
+///
+/// Family name through degree move along by one for the ID; name type code and
+/// the components after it sit further along, after the XCN's identifier
+/// components.
+const PERSON_TO_PARTICIPANT: [(usize, usize); 14] = [
+    (1, 2),
+    (2, 3),
+    (3, 4),
+    (4, 5),
+    (5, 6),
+    (6, 7),
+    (7, 10),
+    (8, 15),
+    (9, 16),
+    (10, 17),
+    (11, 18),
+    (12, 19),
+    (13, 20),
+    (14, 21),
+];
+
+/// Convert the name or address at a path to another format.
+///
+/// # Arguments
+/// * `message` - The HL7 message as a string
+/// * `path` - Field to convert, optionally naming a segment occurrence, repeat,
+///   and the component the name or address starts at (e.g., "PID.5", "PV1.7.2")
+/// * `style` - Conversion to apply
+///
+/// # Returns
+/// * `Ok(String)` - The message with the field converted
+/// * `Err(String)` - The message couldn't be parsed, the path is invalid or
+///   names a field the message doesn't have, or the value isn't in the form
+///   the style expects
+#[tauri::command]
+pub fn convert_name_format(message: &str, path: &str, style: NameStyle) -> Result<String, String> {
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(message)
+        .map_err(|e| format!("Failed to parse message: {e}"))?;
+    let OccurrencePath {
+        segment: name,
+        occurrence,
+        field_id,
+    } = parse_occurrence_path(path)?;
+    if name == "MSH" && field_id.field <= 2 {
+        return Err(format!("{path} holds the message delimiters"));
+    }
+    if style.whole_field() && field_id.component.is_some() {
+        return Err(format!(
+            "{path} names a component, but this converts whole fields"
+        ));
+    }
+
+    let field = parsed
+        .segments()
+        .filter(|segment| segment.name == name)
+        .nth(occurrence - 1)
+        .and_then(|segment| segment.fields.get(field_id.field - 1))
+        .filter(|field| !field.raw_value().is_empty())
+        .ok_or_else(|| format!("{path} is empty"))?;
+
+    let separators = &parsed.separators;
+    let start = field_id.component.unwrap_or(1) - 1;
+    // the path only names a repeat if it has brackets after the segment
+    let one_repeat = path
+        .split_once('.')
+        .is_some_and(|(_, field_part)| field_part.contains('['));
+
+    let mut repeats = Vec::new();
+    for (index, repeat) in field.raw_value().split(separators.repetition).enumerate() {
+        if one_repeat && index + 1 != field_id.repeat {
+            repeats.push(repeat.to_string());
+            continue;
+        }
+        let components = repeat
+            .split(separators.component)
+            .map(str::to_string)
+            .collect();
+        let converted = convert(
+            components,
+            start,
+            style,
+            separators.subcomponent,
+            separators.escape,
+        )
+        .map_err(|e| format!("{path}: {e}"))?;
+        repeats.push(join_components(converted, separators.component));
+    }
+    if one_repeat && field_id.repeat > repeats.len() {
+        return Err(format!("{path} is empty"));
+    }
+
+    let repeat_separator = separators.repetition.to_string();
+    Ok(format!(
+        "{}{}{}",
+        message.get(..field.range.start).unwrap_or_default(),
+        repeats.join(&repeat_separator),
+        message.get(field.range.end..).unwrap_or_default()
+    ))
+}
+
+/// Apply a style to one repeat's components.
+///
+/// # Arguments
+/// * `components` - The repeat's components, as written
+/// * `start` - Index of the component the name or address starts at
+/// * `style` - Conversion to apply
+/// * `subcomponent` - Subcomponent separator
+/// * `escape` - Escape character
+fn convert(
+    mut components: Vec<String>,
+    start: usize,
+    style: NameStyle,
+    subcomponent: char,
+    escape: char,
+) -> Result<Vec<String>, String> {
+    match style {
+        NameStyle::SplitName => {
+            let text = component(&components, start).trim().to_string();
+            if text.is_empty() {
+                return Ok(components);
+            }
+            let (family, given, middle) = split_name(&text);
+            set_component(&mut components, start, family);
+            set_component(&mut components, start + 1, given);
+            set_component(&mut components, start + 2, middle);
+        }
+        NameStyle::JoinName => {
+            let family = component(&components, start).to_string();
+            let forenames = [start + 1, start + 2]
+                .map(|index| component(&components, index).to_string())
+                .into_iter()
+                .filter(|name| !name.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+            let joined = match forenames.is_empty() {
+                true => family,
+                false => format!("{family}, {forenames}"),
+            };
+            set_component(&mut components, start, joined);
+            set_component(&mut components, start + 1, String::new());
+            set_component(&mut components, start + 2, String::new());
+        }
+        NameStyle::UpperCase | NameStyle::TitleCase => {
+            for index in start..start + 3 {
+                if let Some(value) = components.get_mut(index) {
+                    *value = change_case(value, style, escape);
+                }
+            }
+        }
+        NameStyle::PersonToParticipant => {
+            let mut participant = Vec::new();
+            for (from, to) in PERSON_TO_PARTICIPANT {
+                set_component(
+                    &mut participant,
+                    to - 1,
+                    component(&components, from - 1).to_string(),
+                );
+            }
+            components = participant;
+        }
+        NameStyle::ParticipantToPerson => {
+            let mut person = Vec::new();
+            for (to, from) in PERSON_TO_PARTICIPANT {
+                set_component(
+                    &mut person,
+                    to - 1,
+                    component(&components, from - 1).to_string(),
+                );
+            }
+            components = person;
+        }
+        NameStyle::SplitAddress => {
+            let text = component(&components, start).trim().to_string();
+            if text.is_empty() {
+                return Ok(components);
+            }
+            let (street, city, state, zip, country) = split_address(&text)?;
+            set_component(&mut components, start, street);
+            set_component(&mut components, start + 2, city);
+            set_component(&mut components, start + 3, state);
+            set_component(&mut components, start + 4, zip);
+            if let Some(country) = country {
+                set_component(&mut components, start + 5, country);
+            }
+        }
+        NameStyle::DropSubcomponents => {
+            for (index, value) in components.iter_mut().enumerate() {
+                if index >= start {
+                    if let Some((first, _)) = value.split_once(subcomponent) {
+                        *value = first.to_string();
+                    }
+                }
+            }
+        }
+    }
+    Ok(components)
+}
+
+/// A component's value, or an empty string if the repeat doesn't have it.
+fn component(components: &[String], index: usize) -> &str {
+    components.get(index).map_or("", String::as_str)
+}
+
+/// Set a component's value, adding empty components before it if needed.
+fn set_component(components: &mut Vec<String>, index: usize, value: String) {
+    if index >= components.len() {
+        if value.is_empty() {
+            return;
+        }
+        components.resize(index + 1, String::new());
+    }
+    if let Some(component) = components.get_mut(index) {
+        *component = value;
+    }
+}
+
+/// Join components, dropping empty ones from the end.
+fn join_components(mut components: Vec<String>, separator: char) -> String {
+    while components.last().is_some_and(String::is_empty) {
+        components.pop();
+    }
+    components.join(&separator.to_string())
+}
+
+/// Split a name string into family, given, and middle names.
+///
+/// "FAMILY, GIVEN MIDDLE" is split at the comma; without one, the last word is
+/// the family name and the first the given name.
+fn split_name(text: &str) -> (String, String, String) {
+    if let Some((family, forenames)) = text.split_once(',') {
+        let mut words = forenames.split_whitespace();
+        let given = words.next().unwrap_or_default().to_string();
+        let middle = words.collect::<Vec<_>>().join(" ");
+        return (family.trim().to_string(), given, middle);
+    }
+
+    let words: Vec<&str> = text.split_whitespace().collect();
+    match words.as_slice() {
+        [] => (String::new(), String::new(), String::new()),
+        [family] => ((*family).to_string(), String::new(), String::new()),
+        [given, middle @ .., family] => (
+            (*family).to_string(),
+            (*given).to_string(),
+            middle.join(" "),
+        ),
+    }
+}
+
+/// An address split into street, city, state, postal code, and country.
+type AddressParts = (String, String, String, String, Option<String>);
+
+/// Split "STREET, CITY, STATE ZIP[, COUNTRY]" into its parts.
+fn split_address(text: &str) -> Result<AddressParts, String> {
+    let parts: Vec<&str> = text.split(',').map(str::trim).collect();
+    let (street, city, region, country) = match parts.as_slice() {
+        [street, city, region] => (street, city, region, None),
+        [street, city, region, country] => (street, city, region, Some((*country).to_string())),
+        _ => return Err("expected an address like 'STREET, CITY, STATE ZIP'".to_string()),
+    };
+
+    // the postal code is the last word if it has a digit in it
+    let (state, zip) = match region.rsplit_once(' ') {
+        Some((state, zip)) if zip.chars().any(|c| c.is_ascii_digit()) => {
+            (state.trim().to_string(), zip.to_string())
+        }
+        _ => ((*region).to_string(), String::new()),
+    };
+    Ok((
+        (*street).to_string(),
+        (*city).to_string(),
+        state,
+        zip,
+        country,
+    ))
+}
+
+/// Change the case of a value, leaving escape sequences alone.
+fn change_case(value: &str, style: NameStyle, escape: char) -> String {
+    // escape sequences are every other part between escape characters
+    value
+        .split(escape)
+        .enumerate()
+        .map(|(index, part)| match (index % 2, style) {
+            (0, NameStyle::UpperCase) => part.to_uppercase(),
+            (0, NameStyle::TitleCase) => title_case(part),
+            _ => part.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(&escape.to_string())
+}
+
+/// Capitalise the first letter of each word, lower-casing the rest.
+///
+/// Words are broken at anything that isn't a letter, so hyphenated names and
+/// names like "O'Brien" come out right.
+fn title_case(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut start_of_word = true;
+    for c in text.chars() {
+        if c.is_alphabetic() {
+            match start_of_word {
+                true => result.extend(c.to_uppercase()),
+                false => result.extend(c.to_lowercase()),
+            }
+            start_of_word = false;
+        } else {
+            result.push(c);
+            start_of_word = true;
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const MSH: &str = "MSH|^~\\&|APP|FAC|||20250101||ADT^A01|1|P|2.5.1";
+
+    fn field(message: &str, path: &str) -> String {
+        let parsed = hl7_parser::parse_message_with_lenient_newlines(message).unwrap();
+        parsed
+            .query(path)
+            .map(|value| value.raw_value().to_string())
+            .unwrap_or_default()
+    }
+
+    #[test]
+    fn splits_and_joins_names() {
+        let message = format!("{MSH}\rPID|1||123||DOE, JOHN QUINCY~SMITH JANE");
+        let split = convert_name_format(&message, "PID.5", NameStyle::SplitName).unwrap();
+        assert_eq!(field(&split, "PID.5"), "DOE^JOHN^QUINCY~JANE^SMITH");
+
+        let joined = convert_name_format(&split, "PID.5[1]", NameStyle::JoinName).unwrap();
+        assert_eq!(field(&joined, "PID.5"), "DOE, JOHN QUINCY~JANE^SMITH");
+    }
+
+    #[test]
+    fn changes_case_of_names_only() {
+        let message = format!("{MSH}\rPID|1||123||O'BRIEN-SMITH^MARY^ANN^JR^DR");
+        let converted = convert_name_format(&message, "PID.5", NameStyle::TitleCase).unwrap();
+        assert_eq!(field(&converted, "PID.5"), "O'Brien-Smith^Mary^Ann^JR^DR");
+
+        // escape sequences are left as they are
+        let message = format!("{MSH}\rPID|1||123||doe\\T\\sons^john");
+        let converted = convert_name_format(&message, "PID.5", NameStyle::UpperCase).unwrap();
+        assert_eq!(field(&converted, "PID.5"), "DOE\\T\\SONS^JOHN");
+    }
+
+    #[test]
+    fn converts_between_person_and_participant() {
+        let message = format!(
+            "{MSH}\rPID|1||123||Doe^John^Q^^^^L\rPV1|1|I|||||1234^Smith^Jane^^^Dr^MD^^NPI^^^^^^A"
+        );
+        let participant =
+            convert_name_format(&message, "PID.5", NameStyle::PersonToParticipant).unwrap();
+        assert_eq!(field(&participant, "PID.5"), "^Doe^John^Q^^^^^^L");
+
+        let person =
+            convert_name_format(&message, "PV1.7", NameStyle::ParticipantToPerson).unwrap();
+        assert_eq!(field(&person, "PV1.7"), "Smith^Jane^^^Dr^MD^^A");
+
+        // a participant's name starts at component 2
+        let message = format!("{MSH}\rPV1|1|I|||||1234^SMITH, JANE");
+        let split = convert_name_format(&message, "PV1.7.2", NameStyle::SplitName).unwrap();
+        assert_eq!(field(&split, "PV1.7"), "1234^SMITH^JANE");
+    }
+
+    #[test]
+    fn splits_addresses_and_drops_subcomponents() {
+        let message =
+            format!("{MSH}\rPID|1||123||Doe^John|||||123 Main St, Springfield, IL 62701^^^^^^H");
+        let converted = convert_name_format(&message, "PID.11", NameStyle::SplitAddress).unwrap();
+        assert_eq!(
+            field(&converted, "PID.11"),
+            "123 Main St^^Springfield^IL^62701^^H"
+        );
+        assert!(convert_name_format(&message, "PID.5", NameStyle::SplitAddress).is_err());
+
+        let message = format!(
+            "{MSH}\rPID|1||123||van der Berg&van der&Berg^Anna|||||12 High St&High St&12^^Leeds"
+        );
+        let converted =
+            convert_name_format(&message, "PID.5", NameStyle::DropSubcomponents).unwrap();
+        assert_eq!(field(&converted, "PID.5"), "van der Berg^Anna");
+        let converted =
+            convert_name_format(&converted, "PID.11", NameStyle::DropSubcomponents).unwrap();
+        assert_eq!(field(&converted, "PID.11"), "12 High St^^Leeds");
+    }
+
+    #[test]
+    fn rejects_bad_paths() {
+        let message = format!("{MSH}\rPID|1||123||Doe^John");
+        assert!(convert_name_format(&message, "PID.9", NameStyle::SplitName).is_err());
+        assert!(convert_name_format(&message, "NK1.2", NameStyle::SplitName).is_err());
+        assert!(convert_name_format(&message, "PID.5.1", NameStyle::PersonToParticipant).is_err());
+        assert!(convert_name_format(&message, "PID", NameStyle::SplitName).is_err());
+    }
+}
@@ -0,0 +1,201 @@
+//! Normalizing the delimiters and line endings of a message.
+//!
+//! Engines differ on whether `PID|1|||` is the same segment as `PID|1`, or
+//! `123^^^` the same identifier as `123`; in HL7 they are, as trailing empty
+//! fields, components, and subcomponents carry nothing. Normalizing two
+//! messages before comparing them keeps those differences out of the diff.
+//!
+//! # Options
+//!
+//! * **Strip trailing** - Remove empty fields at the end of a segment, and
+//!   empty repetitions, components, and subcomponents at the end of a field
+//! * **Collapse repeats** - Remove empty repetitions anywhere in a field (e.g.,
+//!   `A~~B` to `A~B`)
+//! * **Line ending** - Separate segments with `\r`, `\n`, or `\r\n`
+//!
+//! MSH-1 and MSH-2 hold the delimiters and are never changed, and neither are
+//! a file's `#` comment header or blank lines.
+
+use hl7_parser::message::Separators;
+use serde::Deserialize;
+
+use super::metadata::strip_document_metadata;
+use super::multi_message::lines_with_offsets;
+
+/// Line ending to separate segments with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    /// `\r`, as the HL7 standard specifies
+    Cr,
+    /// `\n`
+    Lf,
+    /// `\r\n`
+    CrLf,
+}
+
+impl LineEnding {
+    fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Cr => "\r",
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// What to normalize in a message.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NormalizeOptions {
+    /// Remove trailing empty fields, repetitions, components, and subcomponents
+    pub strip_trailing: bool,
+    /// Remove empty repetitions between other repetitions
+    pub collapse_repeats: bool,
+    /// Line ending to use between segments, or None to keep the message's own
+    pub line_ending: Option<LineEnding>,
+}
+
+/// Normalize the delimiters and line endings of a message.
+///
+/// # Arguments
+/// * `message` - The HL7 message as a string
+/// * `options` - What to normalize
+///
+/// # Returns
+/// * `Ok(String)` - The normalized message
+/// * `Err(String)` - If the message can't be parsed
+#[tauri::command]
+pub fn normalize_message(message: &str, options: NormalizeOptions) -> Result<String, String> {
+    let (body, header_length) = strip_document_metadata(message);
+    let separators = hl7_parser::parse_message_with_lenient_newlines(body)
+        .map_err(|e| format!("Failed to parse message: {e}"))?
+        .separators;
+
+    let mut normalized = message.get(..header_length).unwrap_or_default().to_string();
+    let lines = lines_with_offsets(body);
+    for (i, (start, line)) in lines.iter().enumerate() {
+        normalized.push_str(&normalize_segment(line, &separators, options));
+
+        let next = lines.get(i + 1).map_or(body.len(), |(next, _)| *next);
+        let ending = body.get(start + line.len()..next).unwrap_or_default();
+        match options.line_ending {
+            Some(line_ending) if !ending.is_empty() => normalized.push_str(line_ending.as_str()),
+            _ => normalized.push_str(ending),
+        }
+    }
+    Ok(normalized)
+}
+
+/// Normalize the delimiters of one segment.
+fn normalize_segment(segment: &str, separators: &Separators, options: NormalizeOptions) -> String {
+    if segment.trim().is_empty() {
+        return segment.to_string();
+    }
+    let mut fields: Vec<String> = segment
+        .split(separators.field)
+        .map(str::to_string)
+        .collect();
+    // the segment name, and MSH-2 (MSH-1 being the separator itself)
+    let fixed = if segment.starts_with("MSH") { 2 } else { 1 };
+    for field in fields.iter_mut().skip(fixed) {
+        *field = normalize_field(field, separators, options);
+    }
+    if options.strip_trailing {
+        strip_empty(&mut fields, fixed);
+    }
+    fields.join(&separators.field.to_string())
+}
+
+/// Normalize the repetitions, components, and subcomponents of one field.
+fn normalize_field(field: &str, separators: &Separators, options: NormalizeOptions) -> String {
+    let mut repeats: Vec<String> = field
+        .split(separators.repetition)
+        .map(|repeat| {
+            if !options.strip_trailing {
+                return repeat.to_string();
+            }
+            let mut components: Vec<String> = repeat
+                .split(separators.component)
+                .map(|component| {
+                    let mut subcomponents: Vec<String> = component
+                        .split(separators.subcomponent)
+                        .map(str::to_string)
+                        .collect();
+                    strip_empty(&mut subcomponents, 1);
+                    subcomponents.join(&separators.subcomponent.to_string())
+                })
+                .collect();
+            strip_empty(&mut components, 1);
+            components.join(&separators.component.to_string())
+        })
+        .collect();
+
+    if options.collapse_repeats {
+        repeats.retain(|repeat| !repeat.is_empty());
+    } else if options.strip_trailing {
+        strip_empty(&mut repeats, 1);
+    }
+    repeats.join(&separators.repetition.to_string())
+}
+
+/// Remove empty parts from the end, keeping at least `keep` parts.
+fn strip_empty(parts: &mut Vec<String>, keep: usize) {
+    while parts.len() > keep && parts.last().is_some_and(String::is_empty) {
+        parts.pop();
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const STRIP: NormalizeOptions = NormalizeOptions {
+        strip_trailing: true,
+        collapse_repeats: false,
+        line_ending: None,
+    };
+
+    #[test]
+    fn strips_trailing_delimiters() {
+        let message = "MSH|^~\\&|APP||\rPID|1||123^^^~^&|||\rPV1|";
+        assert_eq!(
+            normalize_message(message, STRIP).unwrap(),
+            "MSH|^~\\&|APP\rPID|1||123\rPV1"
+        );
+        // MSH-2 ends with the subcomponent separator, and stays as it is
+        assert_eq!(normalize_message("MSH|^~\\&|", STRIP).unwrap(), "MSH|^~\\&");
+    }
+
+    #[test]
+    fn collapses_empty_repeats() {
+        let options = NormalizeOptions {
+            collapse_repeats: true,
+            ..NormalizeOptions::default()
+        };
+        let message = "MSH|^~\\&\rPID|1||A~~B~|";
+        assert_eq!(
+            normalize_message(message, options).unwrap(),
+            "MSH|^~\\&\rPID|1||A~B|"
+        );
+    }
+
+    #[test]
+    fn normalizes_line_endings() {
+        let options = NormalizeOptions {
+            line_ending: Some(LineEnding::CrLf),
+            ..NormalizeOptions::default()
+        };
+        let message = "# title: test\nMSH|^~\\&\nPID|1\rPV1|1\n";
+        assert_eq!(
+            normalize_message(message, options).unwrap(),
+            "# title: test\nMSH|^~\\&\r\nPID|1\r\nPV1|1\r\n"
+        );
+        // nothing asked for, nothing changed
+        assert_eq!(
+            normalize_message(message, NormalizeOptions::default()).unwrap(),
+            message
+        );
+    }
+}
@@ -0,0 +1,126 @@
+//! Converting between byte offsets and editor offsets.
+//!
+//! The parser, and everything built on it, works in byte offsets into the
+//! message string. The editor is a textarea, whose `selectionStart` and
+//! `selectionEnd` count UTF-16 code units. The two agree for ASCII, but an
+//! accented name (`MÜLLER`, `JOSÉ`) is two bytes for each one code unit, and an
+//! emoji is four bytes for two, so every offset after one drifts.
+//!
+//! Commands take positions from the editor and hand ranges back to it in UTF-16
+//! code units, converting at their boundary with [`Utf16Offsets`]; their inner
+//! functions keep working in bytes.
+
+use std::ops::Range;
+
+use super::cursor::CursorRange;
+
+/// A character whose UTF-8 and UTF-16 lengths differ.
+#[derive(Debug, Clone, Copy)]
+struct WideChar {
+    byte_start: usize,
+    utf16_start: usize,
+    byte_len: usize,
+    utf16_len: usize,
+}
+
+/// Maps offsets in a string between bytes and UTF-16 code units.
+///
+/// Only non-ASCII characters are recorded, so for a plain ASCII message the
+/// mapping is empty and every conversion returns its input.
+#[derive(Debug, Clone, Default)]
+pub struct Utf16Offsets {
+    wide: Vec<WideChar>,
+}
+
+impl Utf16Offsets {
+    /// Build the mapping for a string.
+    pub fn new(text: &str) -> Self {
+        let mut utf16_start = 0;
+        let wide = text
+            .char_indices()
+            .filter_map(|(byte_start, c)| {
+                let start = utf16_start;
+                utf16_start += c.len_utf16();
+                (!c.is_ascii()).then_some(WideChar {
+                    byte_start,
+                    utf16_start: start,
+                    byte_len: c.len_utf8(),
+                    utf16_len: c.len_utf16(),
+                })
+            })
+            .collect();
+        Utf16Offsets { wide }
+    }
+
+    /// The UTF-16 offset of a byte offset.
+    ///
+    /// An offset inside a multi-byte character maps to the start of it.
+    pub fn to_utf16(&self, byte: usize) -> usize {
+        let index = self.wide.partition_point(|c| c.byte_start < byte);
+        match index.checked_sub(1).and_then(|i| self.wide.get(i)) {
+            None => byte,
+            Some(c) if byte < c.byte_start + c.byte_len => c.utf16_start,
+            Some(c) => c.utf16_start + c.utf16_len + (byte - c.byte_start - c.byte_len),
+        }
+    }
+
+    /// The byte offset of a UTF-16 offset.
+    ///
+    /// An offset between the halves of a surrogate pair maps to the start of
+    /// the character.
+    pub fn to_byte(&self, utf16: usize) -> usize {
+        let index = self.wide.partition_point(|c| c.utf16_start < utf16);
+        match index.checked_sub(1).and_then(|i| self.wide.get(i)) {
+            None => utf16,
+            Some(c) if utf16 < c.utf16_start + c.utf16_len => c.byte_start,
+            Some(c) => c.byte_start + c.byte_len + (utf16 - c.utf16_start - c.utf16_len),
+        }
+    }
+
+    /// A byte range as an editor range.
+    pub fn cursor_range(&self, bytes: &Range<usize>) -> CursorRange {
+        CursorRange {
+            start: self.to_utf16(bytes.start),
+            end: self.to_utf16(bytes.end),
+        }
+    }
+
+    /// An editor range as a byte range.
+    pub fn byte_range(&self, range: &CursorRange) -> Range<usize> {
+        self.to_byte(range.start)..self.to_byte(range.end)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ascii_offsets_are_unchanged() {
+        let offsets = Utf16Offsets::new("PID|1||123");
+        assert_eq!(offsets.to_utf16(7), 7);
+        assert_eq!(offsets.to_byte(7), 7);
+    }
+
+    #[test]
+    fn converts_around_multi_byte_characters() {
+        let text = "PID|JOSÉ^MÜLLER|😀|x";
+        let offsets = Utf16Offsets::new(text);
+        for (byte, _) in text.char_indices().chain([(text.len(), ' ')]) {
+            let utf16 = text[..byte].encode_utf16().count();
+            assert_eq!(offsets.to_utf16(byte), utf16);
+            assert_eq!(offsets.to_byte(utf16), byte);
+        }
+
+        // inside a character snaps to its start
+        let e = text.find('É').unwrap();
+        assert_eq!(offsets.to_utf16(e + 1), offsets.to_utf16(e));
+        let emoji = text.find('😀').unwrap();
+        assert_eq!(offsets.to_byte(offsets.to_utf16(emoji) + 1), emoji);
+
+        let x = text.rfind('x').unwrap();
+        let range = offsets.cursor_range(&(x..x + 1));
+        assert_eq!(offsets.byte_range(&range), x..x + 1);
+    }
+}
@@ -0,0 +1,484 @@
+//! Documents embedded in OBX-5, found, opened, and replaced.
+//!
+//! MDM and ORU messages carry documents in their observations: a base64 PDF in
+//! an encapsulated data (ED) value, an RTF report written straight into a text
+//! observation, or a CDA document as XML. In the editor these are just very long
+//! strings; these commands find them, decode one to a temporary file and open it
+//! in the system's viewer (or save it somewhere), and swap a file back in.
+//!
+//! # Detection
+//!
+//! Every ED observation is a payload. Its data (ED.5) is decoded according to
+//! its encoding (ED.4): `Base64`, `Hex`, or `A` (text as written). Other
+//! observations are payloads when their value is a document: RTF, XML, or
+//! HTML text, or base64 that decodes to a PDF or an image. Escape sequences are
+//! decoded first, so data split into lines with `\.br\` decodes as a whole.
+//!
+//! The format comes from the decoded bytes' signature where there is one
+//! (`%PDF`, `{\rtf`, PNG, JPEG, GIF, TIFF, `<?xml`), otherwise from the ED
+//! subtype (ED.3).
+//!
+//! # Replacing
+//!
+//! Replacing a payload turns the observation into ED: OBX-2 becomes `ED`, and
+//! OBX-5 the file's type of data and subtype (from its extension, as when
+//! attaching a document), `Base64`, and the file's content.
+
+use base64::alphabet;
+use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig, STANDARD};
+use base64::engine::DecodePaddingMode;
+use base64::Engine as _;
+use hl7_parser::message::{Segment, Separators};
+use hl7_parser::Message;
+use serde::Serialize;
+
+use super::cursor::CursorRange;
+use super::document::data_type_for;
+use super::escape::unescape_text;
+use super::offsets::Utf16Offsets;
+
+/// Base64 decoding that doesn't mind missing padding, which some senders drop.
+const LENIENT_BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Shortest base64 value in a non-ED observation worth checking for a document.
+const MIN_BASE64_LENGTH: usize = 16;
+
+/// What kind of document a payload is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadFormat {
+    Pdf,
+    Rtf,
+    Xml,
+    Html,
+    Text,
+    Png,
+    Jpeg,
+    Gif,
+    Tiff,
+    /// Bytes that aren't a recognised format
+    Binary,
+}
+
+impl PayloadFormat {
+    /// File extension for a decoded payload, so the system opens it in the
+    /// right viewer.
+    fn extension(self) -> &'static str {
+        match self {
+            PayloadFormat::Pdf => "pdf",
+            PayloadFormat::Rtf => "rtf",
+            PayloadFormat::Xml => "xml",
+            PayloadFormat::Html => "html",
+            PayloadFormat::Text => "txt",
+            PayloadFormat::Png => "png",
+            PayloadFormat::Jpeg => "jpg",
+            PayloadFormat::Gif => "gif",
+            PayloadFormat::Tiff => "tif",
+            PayloadFormat::Binary => "bin",
+        }
+    }
+
+    /// Whether the format is a document worth reporting outside an ED value.
+    fn is_document(self) -> bool {
+        match self {
+            PayloadFormat::Text | PayloadFormat::Binary => false,
+            PayloadFormat::Pdf
+            | PayloadFormat::Rtf
+            | PayloadFormat::Xml
+            | PayloadFormat::Html
+            | PayloadFormat::Png
+            | PayloadFormat::Jpeg
+            | PayloadFormat::Gif
+            | PayloadFormat::Tiff => true,
+        }
+    }
+}
+
+/// How a payload's data is written in the message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PayloadEncoding {
+    Base64,
+    Hex,
+    /// Written as text, escape sequences aside
+    Text,
+}
+
+/// A document embedded in an observation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EmbeddedPayload {
+    /// Which OBX segment holds it (1 for the first OBX in the message)
+    pub occurrence: usize,
+    /// Query path of the value (e.g., "OBX[2].5")
+    pub path: String,
+    /// OBX-1 set ID
+    pub set_id: String,
+    /// OBX-2 value type (e.g., "ED", "TX")
+    pub value_type: String,
+    /// What kind of document it is
+    pub format: PayloadFormat,
+    /// How the data is written
+    pub encoding: PayloadEncoding,
+    /// Size of the decoded document in bytes
+    pub size: usize,
+    /// Where OBX-5 is in the message, in UTF-16 offsets
+    pub range: CursorRange,
+}
+
+/// Find the documents embedded in a message's observations.
+///
+/// # Arguments
+/// * `message` - The HL7 message as a string
+///
+/// # Returns
+/// * `Ok(Vec<EmbeddedPayload>)` - Each payload, in message order
+/// * `Err(String)` - If the message can't be parsed
+#[tauri::command]
+pub fn find_embedded_payloads(message: &str) -> Result<Vec<EmbeddedPayload>, String> {
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(message)
+        .map_err(|e| format!("Failed to parse message: {e}"))?;
+    let offsets = Utf16Offsets::new(message);
+
+    Ok(observations(&parsed)
+        .filter_map(|(occurrence, segment)| {
+            let (bytes, encoding) = decode_observation(segment, &parsed.separators).ok()??;
+            let format = detect_format(&bytes, ed_subtype(segment, &parsed.separators));
+            let value = segment.fields.get(4)?;
+            Some(EmbeddedPayload {
+                occurrence,
+                path: format!("OBX[{occurrence}].5"),
+                set_id: field_value(segment, 1).to_string(),
+                value_type: field_value(segment, 2).to_string(),
+                format,
+                encoding,
+                size: bytes.len(),
+                range: offsets.cursor_range(&value.range),
+            })
+        })
+        .collect())
+}
+
+/// Decode a payload to a temporary file and open it with the system's viewer.
+///
+/// # Arguments
+/// * `message` - The HL7 message as a string
+/// * `occurrence` - Which OBX holds the payload (1 for the first)
+///
+/// # Returns
+/// * `Ok(String)` - Path of the temporary file
+/// * `Err(String)` - If there's no payload there, it can't be decoded, or the
+///   file can't be written or opened
+#[tauri::command]
+pub fn open_embedded_payload(message: &str, occurrence: usize) -> Result<String, String> {
+    let (bytes, format) = decode_payload(message, occurrence)?;
+    let dir = std::env::temp_dir().join("hermes-payloads");
+    std::fs::create_dir_all(&dir).map_err(|e| format!("Failed to create payload folder: {e}"))?;
+    let path = dir.join(format!(
+        "obx-{occurrence}-{}.{}",
+        uuid::Uuid::new_v4(),
+        format.extension()
+    ));
+    std::fs::write(&path, bytes).map_err(|e| format!("Failed to write payload: {e}"))?;
+    tauri_plugin_opener::open_path(&path, None::<&str>)
+        .map_err(|e| format!("Failed to open payload: {e}"))?;
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Decode a payload and save it to a file.
+///
+/// # Arguments
+/// * `message` - The HL7 message as a string
+/// * `occurrence` - Which OBX holds the payload (1 for the first)
+/// * `path` - Where to save the document
+///
+/// # Returns
+/// * `Ok(())` - The document was saved
+/// * `Err(String)` - If there's no payload there, it can't be decoded, or the
+///   file can't be written
+#[tauri::command]
+pub fn save_embedded_payload(message: &str, occurrence: usize, path: &str) -> Result<(), String> {
+    let (bytes, _) = decode_payload(message, occurrence)?;
+    std::fs::write(path, bytes).map_err(|e| format!("Failed to save payload: {e}"))
+}
+
+/// Replace an observation's value with a file, base64-encoded as ED.
+///
+/// # Arguments
+/// * `message` - The HL7 message as a string
+/// * `occurrence` - Which OBX to replace the value of (1 for the first)
+/// * `path` - The file to embed
+///
+/// # Returns
+/// * `Ok(String)` - The message with the new value
+/// * `Err(String)` - If the file can't be read, the message can't be parsed,
+///   or it has no such OBX
+#[tauri::command]
+pub fn replace_embedded_payload(
+    message: &str,
+    occurrence: usize,
+    path: &str,
+) -> Result<String, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("Failed to read document: {e}"))?;
+    replace_payload(message, occurrence, path, &bytes)
+}
+
+fn replace_payload(
+    message: &str,
+    occurrence: usize,
+    name: &str,
+    bytes: &[u8],
+) -> Result<String, String> {
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(message)
+        .map_err(|e| format!("Failed to parse message: {e}"))?;
+    let segment = nth_observation(&parsed, occurrence)?;
+    let (Some(value_type), Some(value)) = (segment.fields.get(1), segment.fields.get(4)) else {
+        return Err(format!("OBX[{occurrence}] has no OBX-5 to replace"));
+    };
+
+    let (type_of_data, subtype) = data_type_for(name);
+    let separator = parsed.separators.component;
+    let data = format!(
+        "{separator}{type_of_data}{separator}{subtype}{separator}Base64{separator}{}",
+        STANDARD.encode(bytes)
+    );
+    Ok(format!(
+        "{}ED{}{data}{}",
+        message.get(..value_type.range.start).unwrap_or_default(),
+        message
+            .get(value_type.range.end..value.range.start)
+            .unwrap_or_default(),
+        message.get(value.range.end..).unwrap_or_default()
+    ))
+}
+
+/// Decode the payload in an OBX, with its format.
+fn decode_payload(message: &str, occurrence: usize) -> Result<(Vec<u8>, PayloadFormat), String> {
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(message)
+        .map_err(|e| format!("Failed to parse message: {e}"))?;
+    let segment = nth_observation(&parsed, occurrence)?;
+    let (bytes, _) = decode_observation(segment, &parsed.separators)?
+        .ok_or_else(|| format!("OBX[{occurrence}] doesn't hold a document"))?;
+    let format = detect_format(&bytes, ed_subtype(segment, &parsed.separators));
+    Ok((bytes, format))
+}
+
+/// The message's OBX segments, numbered from 1.
+fn observations<'a>(message: &'a Message<'a>) -> impl Iterator<Item = (usize, &'a Segment<'a>)> {
+    message
+        .segments()
+        .filter(|segment| segment.name == "OBX")
+        .enumerate()
+        .map(|(index, segment)| (index + 1, segment))
+}
+
+fn nth_observation<'a>(
+    message: &'a Message<'a>,
+    occurrence: usize,
+) -> Result<&'a Segment<'a>, String> {
+    observations(message)
+        .find(|(number, _)| *number == occurrence)
+        .map(|(_, segment)| segment)
+        .ok_or_else(|| format!("The message has no OBX[{occurrence}]"))
+}
+
+/// Raw value of a field by number, or "" if the segment is too short.
+fn field_value<'a>(segment: &'a Segment, number: usize) -> &'a str {
+    segment
+        .fields
+        .get(number - 1)
+        .map(|field| field.raw_value())
+        .unwrap_or_default()
+}
+
+/// The ED subtype (ED.3) of an ED observation.
+fn ed_subtype<'a>(segment: &'a Segment, separators: &Separators) -> Option<&'a str> {
+    if !field_value(segment, 2).eq_ignore_ascii_case("ED") {
+        return None;
+    }
+    field_value(segment, 5)
+        .split(separators.component)
+        .nth(2)
+        .filter(|subtype| !subtype.is_empty())
+}
+
+/// Decode an observation's value if it holds a document.
+///
+/// # Returns
+/// * `Ok(Some(..))` - The decoded bytes and how they were written
+/// * `Ok(None)` - The observation doesn't hold a document
+/// * `Err(String)` - It's an ED value whose data can't be decoded
+fn decode_observation(
+    segment: &Segment,
+    separators: &Separators,
+) -> Result<Option<(Vec<u8>, PayloadEncoding)>, String> {
+    let value = field_value(segment, 5);
+    if value.is_empty() {
+        return Ok(None);
+    }
+
+    if field_value(segment, 2).eq_ignore_ascii_case("ED") {
+        let mut components = value.split(separators.component);
+        let encoding = components.nth(3).unwrap_or_default();
+        let data = unescape_text(components.next().unwrap_or_default(), separators);
+        return match encoding.to_ascii_lowercase().as_str() {
+            "base64" => decode_base64(&data)
+                .map(|bytes| Some((bytes, PayloadEncoding::Base64)))
+                .ok_or_else(|| "The ED data isn't valid base64".to_string()),
+            "hex" => decode_hex(&data)
+                .map(|bytes| Some((bytes, PayloadEncoding::Hex)))
+                .ok_or_else(|| "The ED data isn't valid hexadecimal".to_string()),
+            _ => Ok(Some((data.into_bytes(), PayloadEncoding::Text))),
+        };
+    }
+
+    let text = unescape_text(value, separators);
+    let bytes = text.as_bytes();
+    if detect_format(bytes, None).is_document() {
+        return Ok(Some((text.into_bytes(), PayloadEncoding::Text)));
+    }
+    if text.len() >= MIN_BASE64_LENGTH {
+        if let Some(bytes) = decode_base64(&text) {
+            if detect_format(&bytes, None).is_document() {
+                return Ok(Some((bytes, PayloadEncoding::Base64)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Decode base64, ignoring whitespace and line breaks within it.
+fn decode_base64(data: &str) -> Option<Vec<u8>> {
+    let compact: String = data.chars().filter(|c| !c.is_whitespace()).collect();
+    LENIENT_BASE64.decode(compact).ok()
+}
+
+/// Decode hexadecimal, ignoring whitespace and line breaks within it.
+fn decode_hex(data: &str) -> Option<Vec<u8>> {
+    let digits: Vec<u8> = data.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if digits.len() % 2 == 1 {
+        return None;
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+        })
+        .collect()
+}
+
+/// Work out a payload's format from its bytes, falling back to the ED subtype.
+fn detect_format(bytes: &[u8], subtype: Option<&str>) -> PayloadFormat {
+    const SIGNATURES: &[(&[u8], PayloadFormat)] = &[
+        (b"%PDF", PayloadFormat::Pdf),
+        (b"{\\rtf", PayloadFormat::Rtf),
+        (b"\x89PNG", PayloadFormat::Png),
+        (b"\xFF\xD8\xFF", PayloadFormat::Jpeg),
+        (b"GIF8", PayloadFormat::Gif),
+        (b"II*\0", PayloadFormat::Tiff),
+        (b"MM\0*", PayloadFormat::Tiff),
+    ];
+    if let Some((_, format)) = SIGNATURES
+        .iter()
+        .find(|(signature, _)| bytes.starts_with(signature))
+    {
+        return *format;
+    }
+
+    if let Ok(text) = std::str::from_utf8(bytes) {
+        let start: String = text
+            .trim_start_matches('\u{feff}')
+            .trim_start()
+            .chars()
+            .take(16)
+            .collect::<String>()
+            .to_ascii_lowercase();
+        if start.starts_with("<?xml") || start.starts_with("<clinicaldocument") {
+            return PayloadFormat::Xml;
+        }
+        if start.starts_with("<!doctype html") || start.starts_with("<html") {
+            return PayloadFormat::Html;
+        }
+    }
+
+    match subtype.map(str::to_ascii_uppercase).as_deref() {
+        Some("PDF") => PayloadFormat::Pdf,
+        Some("RTF") => PayloadFormat::Rtf,
+        Some("XML") => PayloadFormat::Xml,
+        Some("HTML") => PayloadFormat::Html,
+        Some("PNG") => PayloadFormat::Png,
+        Some("JPEG") => PayloadFormat::Jpeg,
+        Some("GIF") => PayloadFormat::Gif,
+        Some("TIFF") => PayloadFormat::Tiff,
+        _ if std::str::from_utf8(bytes).is_ok() => PayloadFormat::Text,
+        _ => PayloadFormat::Binary,
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str = "MSH|^~\\&|APP|FAC|||20250101||MDM^T02|1|P|2.5.1\r\
+        TXA|1|DS|TX\r\
+        OBX|1|ED|DOC^Document||^AP^PDF^Base64^JVBERi0x\\.br\\LjQ=||||||F\r\
+        OBX|2|TX|NOTE||Just a note||||||F\r\
+        OBX|3|TX|RPT||{\\E\\rtf1 Report}||||||F\r\
+        OBX|4|ED|IMG||^IM^PNG^Hex^89504E47||||||F";
+
+    #[test]
+    fn finds_ed_and_text_documents() {
+        let payloads = find_embedded_payloads(MESSAGE).unwrap();
+        let found: Vec<(usize, PayloadFormat, PayloadEncoding, usize)> = payloads
+            .iter()
+            .map(|p| (p.occurrence, p.format, p.encoding, p.size))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (1, PayloadFormat::Pdf, PayloadEncoding::Base64, 8),
+                (3, PayloadFormat::Rtf, PayloadEncoding::Text, 14),
+                (4, PayloadFormat::Png, PayloadEncoding::Hex, 4),
+            ]
+        );
+        assert_eq!(payloads[0].path, "OBX[1].5");
+        assert_eq!(payloads[0].value_type, "ED");
+        let range = &payloads[1].range;
+        assert_eq!(&MESSAGE[range.start..range.end], "{\\E\\rtf1 Report}");
+
+        let (bytes, format) = decode_payload(MESSAGE, 1).unwrap();
+        assert_eq!(bytes, b"%PDF-1.4");
+        assert_eq!(format, PayloadFormat::Pdf);
+        assert!(decode_payload(MESSAGE, 2).is_err());
+        assert!(decode_payload(MESSAGE, 9).is_err());
+    }
+
+    #[test]
+    fn finds_base64_documents_outside_ed() {
+        let message = format!(
+            "{MESSAGE}\rOBX|5|TX|PDF||{}||||||F",
+            STANDARD.encode(b"%PDF-1.7 and then some")
+        );
+        let payloads = find_embedded_payloads(&message).unwrap();
+        let last = payloads.last().unwrap();
+        assert_eq!(last.occurrence, 5);
+        assert_eq!(last.format, PayloadFormat::Pdf);
+        assert_eq!(last.encoding, PayloadEncoding::Base64);
+    }
+
+    #[test]
+    fn replaces_a_value_with_a_file() {
+        let replaced = replace_payload(MESSAGE, 3, "scan.jpg", b"\xFF\xD8\xFF\xE0").unwrap();
+        assert!(replaced.contains("\rOBX|3|ED|RPT||^IM^JPEG^Base64^/9j/4A==||||||F\r"));
+
+        let (bytes, format) = decode_payload(&replaced, 3).unwrap();
+        assert_eq!(bytes, b"\xFF\xD8\xFF\xE0");
+        assert_eq!(format, PayloadFormat::Jpeg);
+        assert!(replace_payload(MESSAGE, 9, "scan.jpg", b"").is_err());
+    }
+}
@@ -0,0 +1,288 @@
+//! Placeholder substitution: named values written as `{name}` in field values.
+//!
+//! Placeholders are filled in when a template is generated and again when a
+//! message is sent, so a template can carry values that should be fresh each
+//! time without spelling out a formula for them.
+//!
+//! ```text
+//! MSH|^~\&|HERMES|LAB|||{now}||ADT^A01|{auto}|P|2.5.1
+//! EVN|A01|{now-2h}
+//! PID|1||{seq}^^^{facility}||{random_name}
+//! ```
+//!
+//! # Built-in Placeholders
+//!
+//! * `{now}` - The current local time (`YYYYMMDDHHMMSS`)
+//! * `{today}` - The current local date (`YYYYMMDD`)
+//! * `{uuid}` - A random UUID
+//! * `{seq}` - The next number in a sequence shared by every message generated
+//!   or sent since the app started
+//! * `{random_name}` - A random `FAMILY^GIVEN` name, using the message's
+//!   component separator
+//!
+//! `{now}` and `{today}` take offsets made of the same durations formulas use,
+//! so `{now-2h}` is two hours ago and `{today+1w-1d}` is six days from now.
+//!
+//! # User Variables
+//!
+//! Any other `{name}` is looked up in the variables configured in the settings
+//! (letters, digits, and underscores, not starting with a digit). Built-in
+//! placeholders take precedence over variables of the same name.
+//!
+//! # Left Alone
+//!
+//! Braces that don't name a built-in placeholder or a variable are kept as they
+//! are. That includes `{auto}` and `{random}`, which the send command fills in
+//! for MSH.7 and MSH.10 (see [`send_message`](crate::commands::send_message)),
+//! and `{=...}` formulas, which are evaluated after placeholders (see
+//! [`evaluate_formulas`](super::evaluate_formulas)), so formulas can refer to
+//! the values placeholders produce.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use rand::seq::IndexedRandom;
+
+use super::formula::{find_formulas, shift_timestamp, Shift};
+
+/// Next value of `{seq}`.
+static SEQUENCE: AtomicU64 = AtomicU64::new(1);
+
+const FAMILY_NAMES: &[&str] = &[
+    "SMITH", "JOHNSON", "WILLIAMS", "BROWN", "JONES", "GARCIA", "MILLER", "DAVIS", "MARTIN", "LEE",
+    "THOMPSON", "WHITE", "CLARK", "LEWIS", "WALKER", "NGUYEN",
+];
+
+const GIVEN_NAMES: &[&str] = &[
+    "JAMES",
+    "MARY",
+    "ROBERT",
+    "PATRICIA",
+    "JOHN",
+    "JENNIFER",
+    "MICHAEL",
+    "LINDA",
+    "DAVID",
+    "ELIZABETH",
+    "WILLIAM",
+    "SUSAN",
+    "OLIVIA",
+    "NOAH",
+    "EMMA",
+    "LIAM",
+];
+
+/// Fill in every placeholder in a message.
+///
+/// # Arguments
+/// * `message` - The HL7 message, which may contain `{name}` placeholders
+/// * `variables` - User-defined variables, by name
+///
+/// # Returns
+/// * `Ok(String)` - The message with each known placeholder replaced
+/// * `Err(String)` - If an offset on `{now}` or `{today}` is malformed, or a
+///   formula is unterminated
+pub fn expand_placeholders(
+    message: &str,
+    variables: &HashMap<String, String>,
+) -> Result<String, String> {
+    let formulas = find_formulas(message)?;
+    let component_separator = message
+        .strip_prefix("MSH")
+        .and_then(|rest| rest.chars().nth(1))
+        .unwrap_or('^');
+
+    let mut expanded = String::with_capacity(message.len());
+    let mut last = 0;
+    let mut search = 0;
+    while let Some(offset) = message.get(search..).and_then(|rest| rest.find('{')) {
+        let start = search + offset;
+        // formulas are evaluated later, and may contain braces of their own
+        if let Some(formula) = formulas.iter().find(|formula| formula.start == start) {
+            search = formula.end;
+            continue;
+        }
+
+        let body = start + 1;
+        let Some(length) = message
+            .get(body..)
+            .and_then(|rest| rest.find(['}', '{', '\r', '\n']))
+            .filter(|&length| {
+                message
+                    .get(body + length..)
+                    .is_some_and(|rest| rest.starts_with('}'))
+            })
+        else {
+            search = body;
+            continue;
+        };
+        let end = body + length + 1;
+        let placeholder = message.get(body..end - 1).unwrap_or_default();
+
+        if let Some(value) = expand(placeholder, variables, component_separator)? {
+            expanded.push_str(message.get(last..start).unwrap_or_default());
+            expanded.push_str(&value);
+            last = end;
+        }
+        search = end;
+    }
+    expanded.push_str(message.get(last..).unwrap_or_default());
+
+    Ok(expanded)
+}
+
+/// The value of a single placeholder (without its braces), or `None` if it
+/// isn't one this module fills in.
+fn expand(
+    placeholder: &str,
+    variables: &HashMap<String, String>,
+    component_separator: char,
+) -> Result<Option<String>, String> {
+    let name_length = placeholder
+        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .unwrap_or(placeholder.len());
+    let (name, offset) = placeholder.split_at(name_length);
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        return Ok(None);
+    }
+
+    let value = match name {
+        "now" | "today" => {
+            let format = if name == "now" {
+                "%Y%m%d%H%M%S"
+            } else {
+                "%Y%m%d"
+            };
+            let now = jiff::Zoned::now().datetime().strftime(format).to_string();
+            if offset.is_empty() {
+                now
+            } else {
+                let shift = parse_offset(offset)
+                    .map_err(|e| format!("Invalid placeholder {{{placeholder}}}: {e}"))?;
+                shift_timestamp(&now, shift)?
+            }
+        }
+        _ if !offset.is_empty() => return Ok(None),
+        "uuid" => uuid::Uuid::new_v4().to_string(),
+        "seq" => SEQUENCE.fetch_add(1, Ordering::Relaxed).to_string(),
+        "random_name" => {
+            let mut rng = rand::rng();
+            format!(
+                "{}{component_separator}{}",
+                FAMILY_NAMES.choose(&mut rng).unwrap_or(&"DOE"),
+                GIVEN_NAMES.choose(&mut rng).unwrap_or(&"JANE"),
+            )
+        }
+        _ => match variables.get(name) {
+            Some(value) => value.clone(),
+            None => return Ok(None),
+        },
+    };
+    Ok(Some(value))
+}
+
+/// Parse an offset such as `-2h` or `+1d-30m` into a single shift.
+pub(super) fn parse_offset(offset: &str) -> Result<Shift, String> {
+    let mut total = Shift::parse("0", "s")?;
+    let mut rest = offset;
+    while !rest.is_empty() {
+        let negative = match rest.chars().next() {
+            Some('+') => false,
+            Some('-') => true,
+            _ => return Err(format!("Expected + or - before '{rest}'")),
+        };
+        rest = rest.get(1..).unwrap_or_default();
+
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let (amount, after) = rest.split_at(digits);
+        let letters = after
+            .find(|c: char| !c.is_ascii_alphabetic())
+            .unwrap_or(after.len());
+        let (unit, after) = after.split_at(letters);
+        if amount.is_empty() || unit.is_empty() {
+            return Err("Offsets are written like +2h or -30m".to_string());
+        }
+
+        let shift = Shift::parse(amount, unit)?;
+        total = total.plus(if negative { shift.negate() } else { shift })?;
+        rest = after;
+    }
+    Ok(total)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str = "MSH|^~\\&|APP|FAC|||{auto}||ADT^A01|{random}|P|2.5.1";
+
+    fn expand_in(segment: &str, variables: &[(&str, &str)]) -> Result<String, String> {
+        let variables = variables
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        let message = format!("{MESSAGE}\r{segment}");
+        expand_placeholders(&message, &variables)
+            .map(|result| result.rsplit('\r').next().unwrap_or_default().to_string())
+    }
+
+    #[test]
+    fn fills_in_built_ins_and_variables() {
+        let expanded = expand_in(
+            "PID|1||{mrn}^^^{facility}||{random_name}||{today}|{uuid}",
+            &[("mrn", "12345"), ("facility", "GEN")],
+        )
+        .unwrap();
+        let fields: Vec<&str> = expanded.split('|').collect();
+        assert_eq!(fields.get(3), Some(&"12345^^^GEN"));
+        assert!(fields
+            .get(5)
+            .is_some_and(|name| name.split('^').count() == 2));
+        assert_eq!(
+            fields.get(7),
+            Some(&jiff::Zoned::now().strftime("%Y%m%d").to_string().as_str())
+        );
+        assert!(fields
+            .get(8)
+            .is_some_and(|uuid| uuid::Uuid::parse_str(uuid).is_ok()));
+
+        let first: u64 = expand_in("{seq}", &[]).unwrap().parse().unwrap();
+        let second: u64 = expand_in("{seq}", &[]).unwrap().parse().unwrap();
+        assert!(second > first);
+    }
+
+    #[test]
+    fn shifts_the_current_time() {
+        let before = jiff::Zoned::now().datetime();
+        let expanded = expand_in("EVN|A01|{now-2h}|{today+1d-1d}", &[]).unwrap();
+        let fields: Vec<&str> = expanded.split('|').collect();
+        let shifted =
+            jiff::civil::DateTime::strptime("%Y%m%d%H%M%S", fields.get(2).unwrap()).unwrap();
+        let hours = before.duration_since(shifted).as_secs() / 3_600;
+        assert!((1..=2).contains(&hours));
+        assert_eq!(
+            fields.get(3),
+            Some(&before.strftime("%Y%m%d").to_string().as_str())
+        );
+
+        assert!(expand_in("EVN|A01|{now+2}", &[]).is_err());
+        assert!(expand_in("EVN|A01|{now+2parsecs}", &[]).is_err());
+    }
+
+    #[test]
+    fn leaves_other_braces_alone() {
+        let segment = "NTE|1||{auto}|{unknown}|{=concat(\"{seq}\", \"}\")}|{not a placeholder}|{";
+        assert_eq!(expand_in(segment, &[]).unwrap(), segment);
+        assert_eq!(
+            expand_in("NTE|1||{now}", &[]).unwrap().len(),
+            "NTE|1||".len() + 14
+        );
+        // the send-time placeholders in MSH survive
+        assert!(expand_placeholders(MESSAGE, &HashMap::new())
+            .unwrap()
+            .starts_with(MESSAGE));
+    }
+}
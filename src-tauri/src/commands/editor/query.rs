@@ -0,0 +1,461 @@
+//! Build the QPD segment of a query, and read the parameters of a received one.
+//!
+//! A QBP^Q22 (find candidates) asks for patients matching a set of field
+//! values, written in QPD-3 as `@PATH^value` pairs, one per repetition:
+//!
+//! ```text
+//! QPD|Q22^Find Candidates^HL70471|tag42|@PID.5.1^SMITH~@PID.7^19800101
+//! RCP|I|10^RD
+//! ```
+//!
+//! Queries are described here as a query name, a tag, and the parameters, and
+//! the QPD (and an RCP, when the number of records is limited) is written from
+//! them with the message's delimiters.
+//!
+//! # Reading Queries
+//!
+//! [`query_parameters`] reads the same back out of a received query, so reply
+//! rules can answer on what was asked for (see
+//! [`crate::commands::ListenReplies`]). QPD-3 repetitions written as
+//! `@PATH^value` become parameters named by their path; other QPD fields,
+//! as used by queries such as the Q23 identifier query, become parameters named
+//! by their position (`QPD.3`, `QPD.4`, ...).
+//!
+//! Legacy QRY queries (e.g., QRY^A19) carry their parameters in the QRD and
+//! QRF segments instead: the query ID (QRD-4) is read as the tag, the what
+//! subject filter (QRD-9) as the query name, the quantity limit (QRD-7) as the
+//! limit, and each who subject filter (QRD-8) and where subject filter (QRF-1)
+//! as a parameter.
+
+use hl7_parser::message::Separators;
+use hl7_parser::query::LocationQuery;
+use hl7_parser::Message;
+use serde::{Deserialize, Serialize};
+
+use super::escape::escape_text;
+use super::multi_message::{detect_line_ending, lines_with_offsets};
+
+/// Coding system of a query name without one (HL7's table of query names).
+const QUERY_NAME_SYSTEM: &str = "HL70471";
+
+/// A query to build a QPD segment for.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct QueryCriteria {
+    /// Identifier of the query for QPD-1 (e.g., "Q22")
+    pub query_name: String,
+    /// Text of the query name (e.g., "Find Candidates")
+    pub query_text: String,
+    /// Coding system of the query name ("HL70471" if empty)
+    pub coding_system: String,
+    /// Query tag for QPD-2, echoed in the response's QAK ("{uuid}" if empty)
+    pub query_tag: String,
+    /// Field values to match, written to QPD-3
+    pub parameters: Vec<QueryParameter>,
+    /// Most records to return, for RCP-2 (no RCP if empty)
+    pub limit: String,
+}
+
+/// A parameter of a query.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct QueryParameter {
+    /// Path of the field the value is for (e.g., "PID.5.1"), or the position
+    /// of a positional parameter (e.g., "QPD.3")
+    pub path: String,
+    /// The value, unescaped
+    pub value: String,
+}
+
+/// The parameters of a received query.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueryParameters {
+    /// Message type of the query (e.g., "QBP^Q22")
+    pub message_type: String,
+    /// Identifier of the query name (QPD-1), or the what subject filter (QRD-9)
+    pub query_name: String,
+    /// Query tag (QPD-2), or query ID (QRD-4)
+    pub query_tag: String,
+    /// Parameters, in the order they appear
+    pub parameters: Vec<QueryParameter>,
+    /// Most records to return (RCP-2 or QRD-7), if limited
+    pub limit: Option<String>,
+}
+
+impl QueryParameters {
+    /// Look up part of the query by name.
+    ///
+    /// `tag`, `name`, and `limit` are the query tag, query name, and limit;
+    /// anything else is the path of a parameter (e.g., `PID.5.1`, with or
+    /// without a leading `@`), compared without regard to case.
+    pub(crate) fn get(&self, key: &str) -> Option<&str> {
+        match key {
+            "tag" => Some(self.query_tag.as_str()),
+            "name" => Some(self.query_name.as_str()),
+            "limit" => self.limit.as_deref(),
+            path => {
+                let path = normalize_path(path);
+                self.parameters
+                    .iter()
+                    .find(|parameter| parameter.path.eq_ignore_ascii_case(path))
+                    .map(|parameter| parameter.value.as_str())
+            }
+        }
+    }
+}
+
+/// Build the QPD segment, and an RCP if the query is limited, for a query.
+///
+/// # Arguments
+/// * `criteria` - The query name, tag, parameters, and limit
+///
+/// # Returns
+/// * `Ok(String)` - The segments, one per line
+/// * `Err(String)` - If the query has no name, or a parameter's path is invalid
+#[tauri::command]
+pub fn build_query_segment(criteria: QueryCriteria) -> Result<String, String> {
+    Ok(query_segments(&criteria, &Separators::default())?.join("\n"))
+}
+
+/// Replace the QPD and RCP segments of a message with those built for a query.
+///
+/// A message without them gets them straight after its MSH.
+///
+/// # Arguments
+/// * `message` - The HL7 message
+/// * `criteria` - The query name, tag, parameters, and limit
+///
+/// # Returns
+/// * `Ok(String)` - The message with the new query
+/// * `Err(String)` - If the message can't be parsed, the query has no name, or
+///   a parameter's path is invalid
+#[tauri::command]
+pub fn apply_query(message: &str, criteria: QueryCriteria) -> Result<String, String> {
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(message)
+        .map_err(|e| format!("Failed to parse message: {e}"))?;
+    let query = query_segments(&criteria, &parsed.separators)?;
+    let field = parsed.separators.field;
+
+    let name_of = |line: &str| {
+        line.split(field)
+            .next()
+            .map(|name| name.trim().to_string())
+            .unwrap_or_default()
+    };
+    let lines: Vec<&str> = lines_with_offsets(message)
+        .into_iter()
+        .map(|(_, line)| line)
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    let start = lines
+        .iter()
+        .position(|line| matches!(name_of(line).as_str(), "QPD" | "RCP"))
+        .or_else(|| {
+            lines
+                .iter()
+                .position(|line| name_of(line) == "MSH")
+                .map(|msh| msh + 1)
+        })
+        .unwrap_or(0);
+
+    let mut segments: Vec<String> = Vec::with_capacity(lines.len() + query.len());
+    segments.extend(lines.iter().take(start).map(ToString::to_string));
+    segments.extend(query);
+    segments.extend(
+        lines
+            .iter()
+            .skip(start)
+            .filter(|line| !matches!(name_of(line).as_str(), "QPD" | "RCP"))
+            .map(ToString::to_string),
+    );
+    Ok(segments.join(detect_line_ending(message)))
+}
+
+/// Read the parameters of a query message.
+///
+/// # Arguments
+/// * `message` - A QBP or QRY message
+///
+/// # Returns
+/// * `Ok(QueryParameters)` - The query name, tag, parameters, and limit
+/// * `Err(String)` - If the message can't be parsed, or has neither a QPD nor a
+///   QRD segment
+#[tauri::command]
+pub fn parse_query_parameters(message: &str) -> Result<QueryParameters, String> {
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(message)
+        .map_err(|e| format!("Failed to parse message: {e}"))?;
+    query_parameters(&parsed).ok_or_else(|| "The message has no QPD or QRD segment".to_string())
+}
+
+/// Read the parameters of a parsed query message, from its QPD or, failing
+/// that, its QRD and QRF.
+pub(crate) fn query_parameters(message: &Message) -> Option<QueryParameters> {
+    let separators = &message.separators;
+    let decode = |raw: &str| separators.decode(raw).to_string();
+    let value = |path: &str| {
+        message
+            .query(path)
+            .map(|value| decode(value.raw_value()))
+            .unwrap_or_default()
+    };
+    let message_type = message
+        .query("MSH.9")
+        .map(|value| value.raw_value())
+        .unwrap_or_default()
+        .split(separators.component)
+        .take(2)
+        .collect::<Vec<_>>()
+        .join("^");
+    let non_empty = |value: String| (!value.is_empty()).then_some(value);
+
+    if let Some(qpd) = message.segment("QPD") {
+        let mut parameters = Vec::new();
+        for (index, field) in qpd.fields().enumerate().skip(2) {
+            let position = format!("QPD.{}", index + 1);
+            for repeat in &field.repeats {
+                let raw = repeat.raw_value();
+                let first = repeat
+                    .components
+                    .first()
+                    .map(|c| c.raw_value())
+                    .unwrap_or_default();
+                if index == 2 && first.starts_with('@') {
+                    parameters.push(QueryParameter {
+                        path: normalize_path(first).to_string(),
+                        value: repeat
+                            .components
+                            .get(1)
+                            .map(|c| decode(c.raw_value()))
+                            .unwrap_or_default(),
+                    });
+                } else if !raw.is_empty() {
+                    parameters.push(QueryParameter {
+                        path: position.clone(),
+                        value: decode(raw),
+                    });
+                }
+            }
+        }
+        return Some(QueryParameters {
+            message_type,
+            query_name: value("QPD.1.1"),
+            query_tag: value("QPD.2"),
+            parameters,
+            limit: non_empty(value("RCP.2.1")),
+        });
+    }
+
+    let qrd = message.segment("QRD")?;
+    let mut parameters = Vec::new();
+    let mut push_repeats = |segment: &hl7_parser::message::Segment, field: usize| {
+        let Some(repeats) = segment.field(field).map(|f| &f.repeats) else {
+            return;
+        };
+        for repeat in repeats {
+            let first = repeat
+                .components
+                .first()
+                .map(|c| c.raw_value())
+                .unwrap_or_else(|| repeat.raw_value());
+            if !first.is_empty() {
+                parameters.push(QueryParameter {
+                    path: format!("{}.{field}", segment.name),
+                    value: decode(first),
+                });
+            }
+        }
+    };
+    push_repeats(qrd, 8);
+    if let Some(qrf) = message.segment("QRF") {
+        push_repeats(qrf, 1);
+    }
+    Some(QueryParameters {
+        message_type,
+        query_name: value("QRD.9.1"),
+        query_tag: value("QRD.4"),
+        parameters,
+        limit: non_empty(value("QRD.7.1")),
+    })
+}
+
+/// Build the QPD, and RCP if limited, for a query with the given delimiters.
+fn query_segments(
+    criteria: &QueryCriteria,
+    separators: &Separators,
+) -> Result<Vec<String>, String> {
+    let name = criteria.query_name.trim();
+    if name.is_empty() {
+        return Err("The query needs a name (e.g., Q22)".to_string());
+    }
+    let escape = |text: &str| escape_text(text.trim(), separators);
+    let component = separators.component.to_string();
+    let field = separators.field.to_string();
+
+    let query_name = [
+        escape(name),
+        escape(&criteria.query_text),
+        escape(match criteria.coding_system.trim() {
+            "" => QUERY_NAME_SYSTEM,
+            system => system,
+        }),
+    ]
+    .join(component.as_str());
+    let query_tag = match criteria.query_tag.trim() {
+        "" => "{uuid}".to_string(),
+        tag => escape(tag),
+    };
+
+    let mut parameters = Vec::with_capacity(criteria.parameters.len());
+    for parameter in &criteria.parameters {
+        let path = normalize_path(parameter.path.trim());
+        if path.is_empty() {
+            continue;
+        }
+        LocationQuery::parse(path)
+            .map_err(|e| format!("Invalid query parameter `{}`: {e}", parameter.path))?;
+        parameters.push(format!(
+            "@{}{component}{}",
+            path.to_uppercase(),
+            escape(&parameter.value)
+        ));
+    }
+
+    let mut segments = vec![[
+        "QPD".to_string(),
+        query_name,
+        query_tag,
+        parameters.join(separators.repetition.to_string().as_str()),
+    ]
+    .join(field.as_str())
+    .trim_end_matches(separators.field)
+    .to_string()];
+    let limit = criteria.limit.trim();
+    if !limit.is_empty() {
+        segments.push(format!("RCP{field}I{field}{}{component}RD", escape(limit)));
+    }
+    Ok(segments)
+}
+
+/// A parameter path without its leading `@`.
+fn normalize_path(path: &str) -> &str {
+    path.strip_prefix('@').unwrap_or(path)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    fn criteria() -> QueryCriteria {
+        QueryCriteria {
+            query_name: "Q22".to_string(),
+            query_text: "Find Candidates".to_string(),
+            query_tag: "tag42".to_string(),
+            parameters: vec![
+                QueryParameter {
+                    path: "pid.5.1".to_string(),
+                    value: "O'NEIL^SMITH".to_string(),
+                },
+                QueryParameter {
+                    path: "@PID.7".to_string(),
+                    value: "19800101".to_string(),
+                },
+            ],
+            limit: "10".to_string(),
+            ..QueryCriteria::default()
+        }
+    }
+
+    #[test]
+    fn builds_query_segments() {
+        assert_eq!(
+            build_query_segment(criteria()).unwrap(),
+            "QPD|Q22^Find Candidates^HL70471|tag42|@PID.5.1^O'NEIL\\S\\SMITH~@PID.7^19800101\nRCP|I|10^RD"
+        );
+
+        let bare = QueryCriteria {
+            query_name: "Q22".to_string(),
+            ..QueryCriteria::default()
+        };
+        assert_eq!(
+            build_query_segment(bare).unwrap(),
+            "QPD|Q22^^HL70471|{uuid}"
+        );
+
+        assert!(build_query_segment(QueryCriteria::default()).is_err());
+        let mut invalid = criteria();
+        invalid.parameters[0].path = "PID.x".to_string();
+        assert!(build_query_segment(invalid).is_err());
+    }
+
+    #[test]
+    fn replaces_the_query_in_a_message() {
+        let message =
+            "MSH|^~\\&|EMR|HOSP|||20250101||QBP^Q22^QBP_Q21|1|P|2.5.1\rQPD|Q22|old\rRCP|I|5^RD";
+        assert_eq!(
+            apply_query(message, criteria()).unwrap(),
+            "MSH|^~\\&|EMR|HOSP|||20250101||QBP^Q22^QBP_Q21|1|P|2.5.1\rQPD|Q22^Find Candidates^HL70471|tag42|@PID.5.1^O'NEIL\\S\\SMITH~@PID.7^19800101\rRCP|I|10^RD"
+        );
+
+        let message = "MSH|^~\\&|EMR|HOSP|||20250101||QBP^Q22|1|P|2.5.1\nZQA|1";
+        let applied = apply_query(message, criteria()).unwrap();
+        let names: Vec<&str> = applied.lines().map(|line| &line[..3]).collect();
+        assert_eq!(names, ["MSH", "QPD", "RCP", "ZQA"]);
+    }
+
+    #[test]
+    fn reads_query_parameters() {
+        let message = format!(
+            "MSH|^~\\&|EMR|HOSP|||20250101||QBP^Q22^QBP_Q21|1|P|2.5.1\r{}",
+            build_query_segment(criteria()).unwrap().replace('\n', "\r")
+        );
+        let query = parse_query_parameters(&message).unwrap();
+        assert_eq!(query.message_type, "QBP^Q22");
+        assert_eq!(query.query_name, "Q22");
+        assert_eq!(query.query_tag, "tag42");
+        assert_eq!(query.limit.as_deref(), Some("10"));
+        assert_eq!(query.get("PID.5.1"), Some("O'NEIL^SMITH"));
+        assert_eq!(query.get("@pid.7"), Some("19800101"));
+        assert_eq!(query.get("tag"), Some("tag42"));
+        assert_eq!(query.get("PID.8"), None);
+
+        let pix = "MSH|^~\\&|EMR|HOSP|||20250101||QBP^Q23|1|P|2.5.1\rQPD|IHE PIX Query|q9|12345^^^HOSP&1.2.3&ISO^MR|^^^CLINIC";
+        let query = parse_query_parameters(pix).unwrap();
+        assert_eq!(query.query_name, "IHE PIX Query");
+        assert_eq!(query.get("QPD.3"), Some("12345^^^HOSP&1.2.3&ISO^MR"));
+        assert_eq!(query.get("QPD.4"), Some("^^^CLINIC"));
+        assert_eq!(query.limit, None);
+    }
+
+    #[test]
+    fn reads_legacy_queries() {
+        let message = "MSH|^~\\&|EMR|HOSP|||20250101||QRY^A19|1|P|2.3\rQRD|20250101|R|I|Q77|||25^RD|12345^DOE~67890|DEM\rQRF|MAIN";
+        let query = parse_query_parameters(message).unwrap();
+        assert_eq!(query.message_type, "QRY^A19");
+        assert_eq!(query.query_name, "DEM");
+        assert_eq!(query.query_tag, "Q77");
+        assert_eq!(query.limit.as_deref(), Some("25"));
+        assert_eq!(
+            query.parameters,
+            [
+                QueryParameter {
+                    path: "QRD.8".to_string(),
+                    value: "12345".to_string(),
+                },
+                QueryParameter {
+                    path: "QRD.8".to_string(),
+                    value: "67890".to_string(),
+                },
+                QueryParameter {
+                    path: "QRF.1".to_string(),
+                    value: "MAIN".to_string(),
+                },
+            ]
+        );
+
+        assert!(
+            parse_query_parameters("MSH|^~\\&|EMR|HOSP|||20250101||ADT^A01|1|P|2.5.1").is_err()
+        );
+    }
+}
@@ -0,0 +1,486 @@
+//! Build the OBR/OBX hierarchy of a result message from a list of observations.
+//!
+//! Writing an ORU^R01 by hand means keeping a lot of bookkeeping straight: OBR
+//! and OBX set IDs that count up, OBX-2 matching what's actually in OBX-5,
+//! abnormal flags that agree with the reference range. Add or remove an
+//! observation in the middle and the set IDs after it drift. Here, results are
+//! described as orders holding observations, and the segments are generated.
+//!
+//! # Generated Segments
+//!
+//! Each order becomes an OBR followed by an OBX per observation, and an NTE
+//! after any observation with a note:
+//!
+//! ```text
+//! OBR|1|PL123|FL456|24323-8^Comprehensive metabolic panel^LN|||20250314083000|...|F
+//! OBX|1|NM|2345-7^Glucose^LN||182|mg/dL|70-99|H|||F|||20250314083000
+//! OBX|2|SN|2160-0^Creatinine^LN||<^0.5|mg/dL|0.6-1.2|L|||F|||20250314083000
+//! NTE|1||Below the reportable range
+//! ```
+//!
+//! OBR set IDs count up through the block; OBX set IDs start again at 1 under
+//! each OBR, as do NTE set IDs under each OBX.
+//!
+//! # Value Types
+//!
+//! An observation without a value type gets one from its value:
+//!
+//! - `NM` for a number (`5.4`, `-2`)
+//! - `SN` for a comparison or ratio (`<0.5`, `>=10`, `1:40`), written with its
+//!   components (`<^0.5`, `^1^:^40`)
+//! - `ST` for anything else, or `TX` past 200 characters
+//!
+//! Text values are escaped. A value with an explicit type is written as given,
+//! so coded values (`CWE`) can carry their components.
+//!
+//! # Abnormal Flags
+//!
+//! An `NM` observation without abnormal flags is flagged against its reference
+//! range when the range is `low-high`, `<high`, or `>low`: `L` below it, `H`
+//! above it, and `N` within it.
+
+use hl7_parser::message::Separators;
+use serde::Deserialize;
+
+use super::escape::escape_text;
+use super::multi_message::{detect_line_ending, lines_with_offsets};
+
+/// Segments replaced when a block is applied to a message, starting at its first OBR.
+const BLOCK_SEGMENTS: &[&str] = &["OBR", "OBX", "NTE", "ORC", "TQ1", "TQ2", "SPM"];
+
+/// Length past which a text value is `TX` rather than `ST`.
+const MAX_ST_LENGTH: usize = 200;
+
+/// Comparators a structured numeric value can start with, longest first.
+const COMPARATORS: &[&str] = &[">=", "<=", "<>", ">", "<", "="];
+
+/// An order and the observations reported for it.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ResultOrder {
+    /// Code of the ordered service for OBR-4
+    pub code: String,
+    /// Name of the ordered service
+    pub text: String,
+    /// Coding system of the code ("LN" if empty)
+    pub coding_system: String,
+    /// Placer order number for OBR-2
+    pub placer_order_number: String,
+    /// Filler order number for OBR-3
+    pub filler_order_number: String,
+    /// When the specimen was collected or the observation made, for OBR-7 and OBX-14
+    pub observed_at: String,
+    /// Result status for OBR-25 and the observations' default ("F" if empty)
+    pub status: String,
+    /// The observations, in order
+    pub observations: Vec<ResultObservation>,
+}
+
+/// A single observation.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ResultObservation {
+    /// Code of the observation for OBX-3
+    pub code: String,
+    /// Name of the observation
+    pub text: String,
+    /// Coding system of the code ("LN" if empty)
+    pub coding_system: String,
+    /// The value for OBX-5
+    pub value: String,
+    /// OBX-2, worked out from the value if empty
+    pub value_type: String,
+    /// Units for OBX-6
+    pub units: String,
+    /// Reference range for OBX-7 (e.g., "70-99")
+    pub reference_range: String,
+    /// Abnormal flags for OBX-8, worked out from the range if empty
+    pub abnormal_flags: String,
+    /// Result status for OBX-11 (the order's if empty)
+    pub status: String,
+    /// Comment, written as an NTE after the observation
+    pub note: String,
+}
+
+/// Build the OBR, OBX, and NTE segments for a list of orders.
+///
+/// # Arguments
+/// * `orders` - The orders, each with its observations
+///
+/// # Returns
+/// * `Ok(String)` - The segments, one per line
+/// * `Err(String)` - If there are no orders, or an order or observation has no code
+#[tauri::command]
+pub fn build_result_block(orders: Vec<ResultOrder>) -> Result<String, String> {
+    Ok(block_segments(&orders, &Separators::default())?.join("\n"))
+}
+
+/// Replace the results in a message with the segments built for a list of orders.
+///
+/// The segments from the message's first OBR through the OBX, NTE, ORC, and
+/// other order segments that follow it are replaced; a message without an OBR
+/// gets the block at its end. An ORC before the first OBR is kept.
+///
+/// # Arguments
+/// * `message` - The HL7 message
+/// * `orders` - The orders, each with its observations
+///
+/// # Returns
+/// * `Ok(String)` - The message with the new results
+/// * `Err(String)` - If the message can't be parsed, there are no orders, or an
+///   order or observation has no code
+#[tauri::command]
+pub fn apply_result_block(message: &str, orders: Vec<ResultOrder>) -> Result<String, String> {
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(message)
+        .map_err(|e| format!("Failed to parse message: {e}"))?;
+    let block = block_segments(&orders, &parsed.separators)?;
+    let field = parsed.separators.field;
+
+    let name_of = |line: &str| {
+        line.split(field)
+            .next()
+            .map(|name| name.trim().to_string())
+            .unwrap_or_default()
+    };
+    let lines: Vec<&str> = lines_with_offsets(message)
+        .into_iter()
+        .map(|(_, line)| line)
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    let start = lines
+        .iter()
+        .position(|line| name_of(line) == "OBR")
+        .unwrap_or(lines.len());
+    let end = lines
+        .iter()
+        .skip(start)
+        .position(|line| !BLOCK_SEGMENTS.contains(&name_of(line).as_str()))
+        .map_or(lines.len(), |count| start + count);
+
+    let mut segments: Vec<String> = Vec::with_capacity(lines.len() + block.len());
+    segments.extend(lines.iter().take(start).map(ToString::to_string));
+    segments.extend(block);
+    segments.extend(lines.iter().skip(end).map(ToString::to_string));
+    Ok(segments.join(detect_line_ending(message)))
+}
+
+/// Build the segments for a list of orders with the given delimiters.
+fn block_segments(orders: &[ResultOrder], separators: &Separators) -> Result<Vec<String>, String> {
+    if orders.is_empty() {
+        return Err("There are no orders to build results for".to_string());
+    }
+
+    let writer = Writer { separators };
+    let mut segments = Vec::new();
+    for (order_index, order) in orders.iter().enumerate() {
+        let order_number = order_index + 1;
+        if order.code.trim().is_empty() {
+            return Err(format!("Order {order_number} needs a code"));
+        }
+        let status = or_default(&order.status, "F");
+
+        segments.push(writer.segment(
+            "OBR",
+            &[
+                (1, order_number.to_string()),
+                (2, writer.escape(&order.placer_order_number)),
+                (3, writer.escape(&order.filler_order_number)),
+                (
+                    4,
+                    writer.coded(&order.code, &order.text, &order.coding_system),
+                ),
+                (7, writer.escape(&order.observed_at)),
+                (25, writer.escape(status)),
+            ],
+        ));
+
+        for (index, observation) in order.observations.iter().enumerate() {
+            if observation.code.trim().is_empty() {
+                return Err(format!(
+                    "Observation {} of order {order_number} needs a code",
+                    index + 1
+                ));
+            }
+            let (value_type, value) = writer.value(observation);
+            let flags = match observation.abnormal_flags.trim() {
+                "" if value_type == "NM" => {
+                    abnormal_flag(&observation.value, &observation.reference_range)
+                        .unwrap_or_default()
+                        .to_string()
+                }
+                flags => writer.escape(flags),
+            };
+
+            segments.push(writer.segment(
+                "OBX",
+                &[
+                    (1, (index + 1).to_string()),
+                    (2, value_type),
+                    (
+                        3,
+                        writer.coded(
+                            &observation.code,
+                            &observation.text,
+                            &observation.coding_system,
+                        ),
+                    ),
+                    (5, value),
+                    (6, writer.escape(&observation.units)),
+                    (7, writer.escape(&observation.reference_range)),
+                    (8, flags),
+                    (11, writer.escape(or_default(&observation.status, status))),
+                    (14, writer.escape(&order.observed_at)),
+                ],
+            ));
+
+            if !observation.note.trim().is_empty() {
+                segments.push(writer.segment(
+                    "NTE",
+                    &[(1, "1".to_string()), (3, writer.escape(&observation.note))],
+                ));
+            }
+        }
+    }
+    Ok(segments)
+}
+
+/// Writes values with a message's delimiters.
+struct Writer<'a> {
+    separators: &'a Separators,
+}
+
+impl Writer<'_> {
+    /// Escape text for a field.
+    fn escape(&self, text: &str) -> String {
+        escape_text(text.trim(), self.separators)
+    }
+
+    /// A coded element: `code^text^system`.
+    fn coded(&self, code: &str, text: &str, system: &str) -> String {
+        let component = self.separators.component.to_string();
+        [
+            self.escape(code),
+            self.escape(text),
+            self.escape(or_default(system, "LN")),
+        ]
+        .join(component.as_str())
+    }
+
+    /// An observation's value type and its value as written in OBX-5.
+    fn value(&self, observation: &ResultObservation) -> (String, String) {
+        let value = observation.value.trim();
+        let explicit = observation.value_type.trim();
+        if !explicit.is_empty() {
+            return (explicit.to_uppercase(), value.to_string());
+        }
+        if value.is_empty() {
+            return (String::new(), String::new());
+        }
+        if is_number(value) {
+            return ("NM".to_string(), value.to_string());
+        }
+        if let Some(parts) = structured_numeric(value) {
+            let component = self.separators.component.to_string();
+            return ("SN".to_string(), parts.join(component.as_str()));
+        }
+        let value_type = if value.chars().count() > MAX_ST_LENGTH {
+            "TX"
+        } else {
+            "ST"
+        };
+        (value_type.to_string(), self.escape(value))
+    }
+
+    /// A segment with the given fields set, without trailing empty fields.
+    fn segment(&self, name: &str, fields: &[(usize, String)]) -> String {
+        let count = fields
+            .iter()
+            .filter(|(_, value)| !value.is_empty())
+            .map(|(number, _)| *number)
+            .max()
+            .unwrap_or(0);
+        let mut values = vec![String::new(); count];
+        for (number, value) in fields {
+            if let Some(slot) = number.checked_sub(1).and_then(|i| values.get_mut(i)) {
+                slot.clone_from(value);
+            }
+        }
+        std::iter::once(name.to_string())
+            .chain(values)
+            .collect::<Vec<_>>()
+            .join(self.separators.field.to_string().as_str())
+    }
+}
+
+/// Whether a value is a plain decimal number.
+fn is_number(value: &str) -> bool {
+    let digits = value.strip_prefix(['+', '-']).unwrap_or(value);
+    let (whole, fraction) = digits.split_once('.').unwrap_or((digits, "0"));
+    !whole.is_empty()
+        && whole.chars().all(|c| c.is_ascii_digit())
+        && !fraction.is_empty()
+        && fraction.chars().all(|c| c.is_ascii_digit())
+}
+
+/// The SN components of a comparison (`<0.5`) or ratio (`1:40`), if it's one.
+fn structured_numeric(value: &str) -> Option<Vec<String>> {
+    if let Some(comparator) = COMPARATORS.iter().find(|c| value.starts_with(**c)) {
+        let number = value.get(comparator.len()..)?.trim();
+        return is_number(number).then(|| vec![comparator.to_string(), number.to_string()]);
+    }
+    let separator = [':', '/'].into_iter().find(|c| value.contains(*c))?;
+    let (first, second) = value.split_once(separator)?;
+    let (first, second) = (first.trim(), second.trim());
+    (is_number(first) && is_number(second)).then(|| {
+        vec![
+            String::new(),
+            first.to_string(),
+            separator.to_string(),
+            second.to_string(),
+        ]
+    })
+}
+
+/// Flag a numeric value against a reference range, if the range can be read.
+fn abnormal_flag(value: &str, range: &str) -> Option<&'static str> {
+    let value: f64 = value.trim().parse().ok()?;
+    let range = range.trim();
+    let (low, high) = if let Some(high) = range.strip_prefix('<') {
+        (
+            None,
+            Some(high.trim_start_matches('=').trim().parse::<f64>().ok()?),
+        )
+    } else if let Some(low) = range.strip_prefix('>') {
+        (
+            Some(low.trim_start_matches('=').trim().parse::<f64>().ok()?),
+            None,
+        )
+    } else {
+        // skip a leading sign so a negative lower bound isn't taken for the dash
+        let dash = range.get(1..)?.find('-')? + 1;
+        let low = range.get(..dash)?.trim().parse::<f64>().ok()?;
+        let high = range.get(dash + 1..)?.trim().parse::<f64>().ok()?;
+        (Some(low), Some(high))
+    };
+
+    if low.is_some_and(|low| value < low) {
+        Some("L")
+    } else if high.is_some_and(|high| value > high) {
+        Some("H")
+    } else {
+        Some("N")
+    }
+}
+
+/// The value, or a default if it's empty.
+fn or_default<'a>(value: &'a str, default: &'a str) -> &'a str {
+    match value.trim() {
+        "" => default,
+        value => value,
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    fn observation(code: &str, value: &str, range: &str) -> ResultObservation {
+        ResultObservation {
+            code: code.to_string(),
+            text: format!("Test {code}"),
+            value: value.to_string(),
+            units: "mg/dL".to_string(),
+            reference_range: range.to_string(),
+            ..ResultObservation::default()
+        }
+    }
+
+    fn orders() -> Vec<ResultOrder> {
+        vec![
+            ResultOrder {
+                code: "24323-8".to_string(),
+                text: "Metabolic panel".to_string(),
+                placer_order_number: "PL1".to_string(),
+                observed_at: "20250314083000".to_string(),
+                observations: vec![
+                    observation("2345-7", "182", "70-99"),
+                    ResultObservation {
+                        note: "Below the reportable range".to_string(),
+                        ..observation("2160-0", "<0.5", "0.6-1.2")
+                    },
+                    observation("1975-2", "0.8", "<1.2"),
+                ],
+                ..ResultOrder::default()
+            },
+            ResultOrder {
+                code: "5048-4".to_string(),
+                status: "P".to_string(),
+                observations: vec![
+                    observation("5048-4", "1:40", ""),
+                    ResultObservation {
+                        value_type: "CWE".to_string(),
+                        ..observation("600-7", "264^E. coli^SCT", "")
+                    },
+                    observation("NOTE", "No growth | yet", ""),
+                ],
+                ..ResultOrder::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn builds_numbered_segments() {
+        let block = build_result_block(orders()).unwrap();
+        let lines: Vec<&str> = block.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "OBR|1|PL1||24323-8^Metabolic panel^LN|||20250314083000||||||||||||||||||F",
+                "OBX|1|NM|2345-7^Test 2345-7^LN||182|mg/dL|70-99|H|||F|||20250314083000",
+                "OBX|2|SN|2160-0^Test 2160-0^LN||<^0.5|mg/dL|0.6-1.2||||F|||20250314083000",
+                "NTE|1||Below the reportable range",
+                "OBX|3|NM|1975-2^Test 1975-2^LN||0.8|mg/dL|<1.2|N|||F|||20250314083000",
+                "OBR|2|||5048-4^^LN|||||||||||||||||||||P",
+                "OBX|1|SN|5048-4^Test 5048-4^LN||^1^:^40|mg/dL|||||P",
+                "OBX|2|CWE|600-7^Test 600-7^LN||264^E. coli^SCT|mg/dL|||||P",
+                "OBX|3|ST|NOTE^Test NOTE^LN||No growth \\F\\ yet|mg/dL|||||P",
+            ]
+        );
+    }
+
+    #[test]
+    fn flags_values_against_ranges() {
+        assert_eq!(abnormal_flag("5", "3.5-5.0"), Some("N"));
+        assert_eq!(abnormal_flag("2", "3.5-5.0"), Some("L"));
+        assert_eq!(abnormal_flag("-3", "-2-2"), Some("L"));
+        assert_eq!(abnormal_flag("12", ">=10"), Some("N"));
+        assert_eq!(abnormal_flag("12", "<10"), Some("H"));
+        assert_eq!(abnormal_flag("12", "negative"), None);
+        assert!(is_number("-2.50"));
+        assert!(!is_number("1."));
+        assert!(!is_number(""));
+    }
+
+    #[test]
+    fn replaces_results_in_a_message() {
+        let message = "MSH|^~\\&|LAB|HOSP|||20250314||ORU^R01|1|P|2.5.1\rPID|1||123\rORC|RE\rOBR|1|||OLD\rOBX|1|ST|OLD||x\rOBX|3|ST|OLD||y\rNTE|1||note\rZLB|1";
+        let orders = vec![ResultOrder {
+            code: "GLU".to_string(),
+            observations: vec![observation("GLU", "5.5", "")],
+            ..ResultOrder::default()
+        }];
+        let applied = apply_result_block(message, orders.clone()).unwrap();
+        assert_eq!(
+            applied,
+            "MSH|^~\\&|LAB|HOSP|||20250314||ORU^R01|1|P|2.5.1\rPID|1||123\rORC|RE\rOBR|1|||GLU^^LN|||||||||||||||||||||F\rOBX|1|NM|GLU^Test GLU^LN||5.5|mg/dL|||||F\rZLB|1"
+        );
+
+        // without results, the block goes at the end
+        let appended = apply_result_block("MSH|^~\\&|LAB\nPID|1", orders).unwrap();
+        assert!(appended.starts_with("MSH|^~\\&|LAB\nPID|1\nOBR|1|"));
+
+        assert!(build_result_block(Vec::new()).is_err());
+        assert!(build_result_block(vec![ResultOrder::default()]).is_err());
+    }
+}
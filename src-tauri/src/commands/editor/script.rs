@@ -0,0 +1,258 @@
+//! Scripting console: short scripts that transform the message.
+//!
+//! Some edits are too fiddly for find and replace but too small to be worth an
+//! extension: renumbering the OBX set IDs, copying PID-3 into a Z-segment,
+//! giving every message in a test a fresh control ID. The console runs a
+//! [Rhai](https://rhai.rs) script against the message in the editor, and the
+//! frontend applies the result as a single undo entry.
+//!
+//! ```text
+//! for i in 1..=count("OBX") {
+//!     set(`OBX[${i}].1`, `${i}`);
+//! }
+//! set("PV1.19", get("PID.18"));
+//! new_control_id();
+//! ```
+//!
+//! # API
+//!
+//! * `get(path)` - The value at a path (e.g., `PID.5.1`, `OBX[2].5`,
+//!   `PID.3[2].1`), or `""` if the message doesn't have it
+//! * `set(path, value)` - Write a value, creating the field or component if
+//!   needed
+//! * `count(segment)` - Number of segments with a name
+//! * `segments()` - Names of every segment, in order
+//! * `new_control_id()` - Give the message a new random control ID in MSH-10,
+//!   and return it
+//! * `message()` / `set_message(text)` - The whole message, and replace it
+//! * `print(value)` - Add a line to the console's output
+//!
+//! Values are read and written as they appear in the message, with escape
+//! sequences and any component separators, so `set("PID.5", "Doe^John")` sets
+//! both name components.
+//!
+//! # Sandboxing
+//!
+//! Scripts can only see and change the message: Rhai has no access to files,
+//! the network, or other processes. Each run is limited to `MAX_OPERATIONS`
+//! operations, so a runaway loop fails instead of hanging Hermes, and runs on a
+//! blocking thread so the editor stays responsive meanwhile.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use rhai::{Array, Dynamic, Engine, EvalAltResult};
+use serde::Serialize;
+
+use super::data::generate_control_id;
+use super::multi_edit::{set_fields, FieldEdit};
+
+/// Most operations a script may perform before it's stopped.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// Longest string a script may build, in bytes; large enough for messages with
+/// embedded documents.
+const MAX_STRING_SIZE: usize = 16 * 1024 * 1024;
+
+/// Most elements an array in a script may hold.
+const MAX_ARRAY_SIZE: usize = 100_000;
+
+/// Deepest a script's function calls may nest.
+const MAX_CALL_LEVELS: usize = 32;
+
+/// The outcome of running a script.
+#[derive(Debug, Clone, Serialize)]
+pub struct ScriptResult {
+    /// The message after the script ran
+    pub message: String,
+    /// Whether the script changed the message
+    pub changed: bool,
+    /// Lines printed by the script
+    pub output: Vec<String>,
+}
+
+/// Run a script against a message.
+///
+/// # Arguments
+/// * `message` - The HL7 message as a string
+/// * `script` - Rhai source of the script
+///
+/// # Returns
+/// * `Ok(ScriptResult)` - The message as the script left it, with its output
+/// * `Err(String)` - If the script doesn't compile, fails, or runs too long;
+///   the message is left as it was
+#[tauri::command]
+pub async fn run_script(message: String, script: String) -> Result<ScriptResult, String> {
+    tokio::task::spawn_blocking(move || run(&message, &script))
+        .await
+        .map_err(|e| format!("Failed to run script: {e}"))?
+}
+
+/// Run a script against a message, on the current thread.
+fn run(message: &str, script: &str) -> Result<ScriptResult, String> {
+    let current = Rc::new(RefCell::new(message.to_string()));
+    let output = Rc::new(RefCell::new(Vec::new()));
+    let engine = engine(&current, &output);
+
+    engine
+        .run(script)
+        .map_err(|e| format!("Script failed: {e}"))?;
+
+    let result = current.borrow().clone();
+    let output = output.borrow().clone();
+    Ok(ScriptResult {
+        changed: result != message,
+        message: result,
+        output,
+    })
+}
+
+/// Build an engine whose API reads and writes `current`, printing to `output`.
+fn engine(current: &Rc<RefCell<String>>, output: &Rc<RefCell<Vec<String>>>) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_max_operations(MAX_OPERATIONS)
+        .set_max_string_size(MAX_STRING_SIZE)
+        .set_max_array_size(MAX_ARRAY_SIZE)
+        .set_max_call_levels(MAX_CALL_LEVELS);
+
+    let lines = Rc::clone(output);
+    engine.on_print(move |line| lines.borrow_mut().push(line.to_string()));
+    let lines = Rc::clone(output);
+    engine.on_debug(move |line, _, position| {
+        lines.borrow_mut().push(format!("[{position}] {line}"));
+    });
+
+    let message = Rc::clone(current);
+    engine.register_fn(
+        "get",
+        move |path: &str| -> Result<String, Box<EvalAltResult>> {
+            let message = message.borrow();
+            let parsed = parse(&message)?;
+            Ok(parsed
+                .query(path)
+                .map(|value| value.raw_value().to_string())
+                .unwrap_or_default())
+        },
+    );
+
+    let message = Rc::clone(current);
+    engine.register_fn(
+        "set",
+        move |path: &str, value: &str| -> Result<(), Box<EvalAltResult>> {
+            let mut message = message.borrow_mut();
+            let edit = FieldEdit {
+                path: path.to_string(),
+                value: value.to_string(),
+            };
+            *message = set_fields(&message, vec![edit])?;
+            Ok(())
+        },
+    );
+
+    let message = Rc::clone(current);
+    engine.register_fn(
+        "count",
+        move |segment: &str| -> Result<i64, Box<EvalAltResult>> {
+            let message = message.borrow();
+            let parsed = parse(&message)?;
+            let count = parsed.segments().filter(|s| s.name == segment).count();
+            Ok(i64::try_from(count).unwrap_or(i64::MAX))
+        },
+    );
+
+    let message = Rc::clone(current);
+    engine.register_fn("segments", move || -> Result<Array, Box<EvalAltResult>> {
+        let message = message.borrow();
+        let parsed = parse(&message)?;
+        Ok(parsed
+            .segments()
+            .map(|segment| Dynamic::from(segment.name.to_string()))
+            .collect())
+    });
+
+    let message = Rc::clone(current);
+    engine.register_fn(
+        "new_control_id",
+        move || -> Result<String, Box<EvalAltResult>> {
+            let mut message = message.borrow_mut();
+            *message = generate_control_id(&message)?.message;
+            let parsed = parse(&message)?;
+            Ok(parsed
+                .query("MSH.10")
+                .map(|value| value.raw_value().to_string())
+                .unwrap_or_default())
+        },
+    );
+
+    let message = Rc::clone(current);
+    engine.register_fn("message", move || message.borrow().clone());
+
+    let message = Rc::clone(current);
+    engine.register_fn(
+        "set_message",
+        move |text: &str| -> Result<(), Box<EvalAltResult>> {
+            parse(text)?;
+            *message.borrow_mut() = text.to_string();
+            Ok(())
+        },
+    );
+
+    engine
+}
+
+/// Parse the message for a script, failing the script if it can't be parsed.
+fn parse(message: &str) -> Result<hl7_parser::Message<'_>, Box<EvalAltResult>> {
+    hl7_parser::parse_message_with_lenient_newlines(message)
+        .map_err(|e| format!("Failed to parse message: {e}").into())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str = "MSH|^~\\&|APP|FAC|||20250101||ORU^R01|ABC123|P|2.5.1\rPID|1||123||Doe^John\rOBX|7|NM|GLU||5.5\rOBX|7|NM|HGB||13";
+
+    #[test]
+    fn scripts_read_and_write_fields() {
+        let script = r#"
+            for i in 1..=count("OBX") {
+                set(`OBX[${i}].1`, `${i}`);
+            }
+            set("PID.5.1", get("PID.5.1") + "-Smith");
+            print(segments().len());
+            print(segments()[2]);
+        "#;
+        let result = run(MESSAGE, script).unwrap();
+        assert!(result.changed);
+        assert!(result.message.contains("OBX|1|NM|GLU"));
+        assert!(result.message.contains("OBX|2|NM|HGB"));
+        assert!(result.message.contains("|Doe-Smith^John"));
+        assert_eq!(result.output, ["4", "OBX"]);
+    }
+
+    #[test]
+    fn scripts_can_regenerate_the_control_id() {
+        let result = run(MESSAGE, "print(new_control_id());").unwrap();
+        let control_id = &result.output[0];
+        assert_eq!(control_id.len(), 20);
+        assert!(result.message.contains(&format!("|{control_id}|")));
+        assert!(!result.message.contains("|ABC123|"));
+    }
+
+    #[test]
+    fn failing_scripts_leave_the_message_alone() {
+        assert!(run(MESSAGE, "set(\"PID\", \"oops\");").is_err());
+        assert!(run(MESSAGE, "set(\"ZZZ.1\", \"x\");").is_err());
+        assert!(run(MESSAGE, "let x = ;").is_err());
+
+        // runaway loops are stopped rather than hanging
+        let error = run(MESSAGE, "loop { }").unwrap_err();
+        assert!(error.contains("Too many operations"), "{error}");
+
+        let result = run(MESSAGE, "print(get(\"PID.99\").len());").unwrap();
+        assert!(!result.changed);
+        assert_eq!(result.output, ["0"]);
+    }
+}
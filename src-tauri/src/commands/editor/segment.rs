@@ -13,6 +13,14 @@
 //! These commands operate at the segment level, handling delimiter boundaries correctly
 //! and preserving the message's line ending style.
 //!
+//! # Choosing a Segment
+//!
+//! Segments are chosen by their 0-based index in the message, as the editor does
+//! for the segment at the cursor, or by name and 1-based occurrence, as in
+//! `OBX[3]` (`OBX` alone is the first OBX), so extensions and the jump-to-field
+//! palette can name the segment they mean without counting lines. See
+//! [`SegmentTarget`].
+//!
 //! # MSH Protection
 //!
 //! The MSH segment is protected from all operations. Every valid HL7 message must have
@@ -23,18 +31,155 @@
 //!
 //! Each operation returns a cursor position to maintain editing flow:
 //! - **Delete**: Cursor moves to the next segment, or previous if deleting the last
-//! - **Move**: Cursor follows the moved segment to its new position
+//! - **Move**: Cursor follows the moved segment to its new position, whether it
+//!   moved a line up or down or to any other position ([`move_segment_to`])
 //! - **Duplicate**: Cursor moves to the start of the new copy
+//!
+//! # Change Descriptions
+//!
+//! Each result also lists the segments the operation touched, with their index
+//! and range before and after the operation. Consumers that track segments,
+//! such as extensions subscribed to `message/changed`, can use these to update
+//! incrementally instead of re-reading the whole message.
+
+use hl7_parser::query::LocationQuery;
+use serde::{Deserialize, Serialize};
 
-use serde::Serialize;
+use super::cursor::CursorRange;
+use super::offsets::Utf16Offsets;
 
 /// Result of a segment operation containing the new message and cursor position.
+///
+/// The cursor and ranges are in UTF-16 code units, as the editor counts them.
 #[derive(Serialize)]
 pub struct SegmentOperationResult {
     /// The modified message content
     pub message: String,
     /// Where to position the cursor after the operation
     pub cursor: usize,
+    /// Segments the operation added, removed, or moved
+    pub changes: Vec<SegmentChange>,
+}
+
+impl SegmentOperationResult {
+    /// Convert the cursor and ranges, worked out as byte offsets, to UTF-16
+    /// offsets. `before` is the message the operation started from.
+    pub(super) fn into_utf16(mut self, before: &str) -> Self {
+        let before = Utf16Offsets::new(before);
+        let after = Utf16Offsets::new(&self.message);
+        let convert = |range: &CursorRange, offsets: &Utf16Offsets| {
+            offsets.cursor_range(&(range.start..range.end))
+        };
+        self.cursor = after.to_utf16(self.cursor);
+        for change in &mut self.changes {
+            change.range_before = change.range_before.as_ref().map(|r| convert(r, &before));
+            change.range_after = change.range_after.as_ref().map(|r| convert(r, &after));
+        }
+        self
+    }
+}
+
+/// Kind of segment operation that produced a change.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SegmentOperation {
+    /// The segment was removed
+    Delete,
+    /// The segment moved to another position
+    Move,
+    /// The segment is a new copy of the segment before it
+    Duplicate,
+    /// The segment is new, e.g. an attached document's OBX
+    Insert,
+}
+
+/// How a single segment changed in a segment operation.
+///
+/// Indices are 0-based segment indices; ranges are offsets of the segment's
+/// content, without its line ending. The "before" side is empty for segments
+/// that were added, and the "after" side for segments that were removed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SegmentChange {
+    /// Operation that made the change
+    pub operation: SegmentOperation,
+    /// Index of the segment before the operation
+    pub index_before: Option<usize>,
+    /// Index of the segment after the operation
+    pub index_after: Option<usize>,
+    /// Range of the segment before the operation
+    pub range_before: Option<CursorRange>,
+    /// Range of the segment after the operation
+    pub range_after: Option<CursorRange>,
+}
+
+impl SegmentChange {
+    /// A segment that was removed.
+    pub(super) fn deleted(index: usize, range: &std::ops::Range<usize>) -> Self {
+        SegmentChange {
+            operation: SegmentOperation::Delete,
+            index_before: Some(index),
+            index_after: None,
+            range_before: Some(CursorRange {
+                start: range.start,
+                end: range.end,
+            }),
+            range_after: None,
+        }
+    }
+}
+
+/// A segment to operate on.
+///
+/// Deserializes from a number, taken as the segment's 0-based index, or a
+/// string naming the segment and its 1-based occurrence (`"OBX[3]"`, or
+/// `"OBX"` for the first).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum SegmentTarget {
+    /// 0-based index of the segment in the message
+    Index(usize),
+    /// Segment name with an optional occurrence, e.g. `OBX[3]`
+    Named(String),
+}
+
+impl From<usize> for SegmentTarget {
+    fn from(index: usize) -> Self {
+        SegmentTarget::Index(index)
+    }
+}
+
+impl From<&str> for SegmentTarget {
+    fn from(name: &str) -> Self {
+        SegmentTarget::Named(name.to_string())
+    }
+}
+
+impl SegmentTarget {
+    /// The 0-based index of the segment in the message.
+    ///
+    /// Returns None if the segment isn't in the message, or the name isn't a
+    /// segment name with an optional occurrence.
+    #[must_use]
+    pub fn resolve(&self, message: &str) -> Option<usize> {
+        let parsed = hl7_parser::parse_message_with_lenient_newlines(message).ok()?;
+        match self {
+            SegmentTarget::Index(index) => (*index < parsed.segments().count()).then_some(*index),
+            SegmentTarget::Named(name) => {
+                let query = LocationQuery::parse(name.trim()).ok()?;
+                if query.field.is_some() {
+                    return None;
+                }
+                let occurrence = query.segment_index.unwrap_or(1);
+                parsed
+                    .segments()
+                    .enumerate()
+                    .filter(|(_, segment)| segment.name.eq_ignore_ascii_case(&query.segment))
+                    .nth(occurrence.checked_sub(1)?)
+                    .map(|(index, _)| index)
+            }
+        }
+    }
 }
 
 /// Get the absolute segment index at the given cursor position.
@@ -43,6 +188,7 @@ pub struct SegmentOperationResult {
 /// the message cannot be parsed or the cursor is outside any segment.
 #[tauri::command]
 pub fn get_segment_index_at_cursor(message: &str, cursor: usize) -> Option<usize> {
+    let cursor = Utf16Offsets::new(message).to_byte(cursor);
     let parsed = hl7_parser::parse_message_with_lenient_newlines(message).ok()?;
 
     for (index, segment) in parsed.segments().enumerate() {
@@ -54,16 +200,21 @@ pub fn get_segment_index_at_cursor(message: &str, cursor: usize) -> Option<usize
     None
 }
 
-/// Delete the segment at the given index.
+/// Delete a segment.
 ///
 /// Returns the modified message and new cursor position. The cursor is positioned
 /// at the start of the next segment, or the previous segment if deleting the last one.
 ///
 /// # Constraints
 /// - Cannot delete MSH segment (index 0) as it's required for valid HL7
-/// - Returns None if the segment index is out of bounds
+/// - Returns None if the segment isn't in the message
 #[tauri::command]
-pub fn delete_segment(message: &str, segment_index: usize) -> Option<SegmentOperationResult> {
+pub fn delete_segment(message: &str, segment: SegmentTarget) -> Option<SegmentOperationResult> {
+    delete_segment_at(message, segment.resolve(message)?)
+}
+
+/// Delete the segment at the given index.
+fn delete_segment_at(message: &str, segment_index: usize) -> Option<SegmentOperationResult> {
     // prevent deleting MSH
     if segment_index == 0 {
         return None;
@@ -106,10 +257,14 @@ pub fn delete_segment(message: &str, segment_index: usize) -> Option<SegmentOper
         0
     };
 
-    Some(SegmentOperationResult {
-        message: new_message,
-        cursor: new_cursor,
-    })
+    Some(
+        SegmentOperationResult {
+            message: new_message,
+            cursor: new_cursor,
+            changes: vec![SegmentChange::deleted(segment_index, &segment.range)],
+        }
+        .into_utf16(message),
+    )
 }
 
 /// Direction to move a segment.
@@ -120,7 +275,7 @@ pub enum MoveDirection {
     Down,
 }
 
-/// Move the segment at the given index up or down.
+/// Move a segment up or down.
 ///
 /// Returns the modified message and new cursor position at the moved segment.
 ///
@@ -131,6 +286,15 @@ pub enum MoveDirection {
 /// - Returns None if the operation is invalid
 #[tauri::command]
 pub fn move_segment(
+    message: &str,
+    segment: SegmentTarget,
+    direction: MoveDirection,
+) -> Option<SegmentOperationResult> {
+    move_segment_at(message, segment.resolve(message)?, direction)
+}
+
+/// Move the segment at the given index up or down.
+fn move_segment_at(
     message: &str,
     segment_index: usize,
     direction: MoveDirection,
@@ -199,22 +363,139 @@ pub fn move_segment(
         first_segment.range.start + second_content.len() + between.len()
     };
 
-    Some(SegmentOperationResult {
-        message: new_message,
-        cursor: new_cursor,
-    })
+    // the two segments swap places, so both move
+    let second_start = first_segment.range.start;
+    let first_start = second_start + second_content.len() + between.len();
+    let moved =
+        |index_before, index_after, range: &std::ops::Range<usize>, start: usize| SegmentChange {
+            operation: SegmentOperation::Move,
+            index_before: Some(index_before),
+            index_after: Some(index_after),
+            range_before: Some(CursorRange {
+                start: range.start,
+                end: range.end,
+            }),
+            range_after: Some(CursorRange {
+                start,
+                end: start + range.len(),
+            }),
+        };
+    let first_change = moved(first_idx, second_idx, &first_segment.range, first_start);
+    let second_change = moved(second_idx, first_idx, &second_segment.range, second_start);
+    // the requested segment's change comes first
+    let changes = if segment_index == first_idx {
+        vec![first_change, second_change]
+    } else {
+        vec![second_change, first_change]
+    };
+
+    Some(
+        SegmentOperationResult {
+            message: new_message,
+            cursor: new_cursor,
+            changes,
+        }
+        .into_utf16(message),
+    )
 }
 
-/// Duplicate the segment at the given index.
+/// Move a segment to another position in the message.
+///
+/// The segment ends up where `position` is now, and the segments in between
+/// shift by one to make room: moving `NTE[1]` to `OBX[2]` puts the NTE just
+/// before that OBX when moving up, and just after it when moving down. The line
+/// endings stay where they are, so a message with mixed line endings keeps them.
+/// The cursor follows the moved segment.
+///
+/// # Constraints
+/// - Cannot move MSH segment (index 0), or another segment into its position
+/// - Returns None if either segment isn't in the message, or they're the same
+#[tauri::command]
+pub fn move_segment_to(
+    message: &str,
+    segment: SegmentTarget,
+    position: SegmentTarget,
+) -> Option<SegmentOperationResult> {
+    let from = segment.resolve(message)?;
+    let to = position.resolve(message)?;
+    if from == 0 || to == 0 || from == to {
+        return None;
+    }
+
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(message).ok()?;
+    let segments: Vec<_> = parsed.segments().collect();
+    let first = segments.first()?;
+    let last = segments.last()?;
+
+    // the segments' new order, as indices into their old order
+    let mut order: Vec<usize> = (0..segments.len()).collect();
+    order.remove(from);
+    order.insert(to, from);
+
+    let mut new_message = String::with_capacity(message.len());
+    new_message.push_str(&message[..first.range.start]);
+    let mut changes = Vec::new();
+    let mut cursor = 0;
+    for (index_after, &index_before) in order.iter().enumerate() {
+        let segment = segments.get(index_before)?;
+        let start = new_message.len();
+        new_message.push_str(&message[segment.range.start..segment.range.end]);
+        // the separator after each position stays with the position
+        if let Some(next) = segments.get(index_after + 1) {
+            let current = segments.get(index_after)?;
+            new_message.push_str(&message[current.range.end..next.range.start]);
+        }
+
+        if index_before != index_after {
+            let change = SegmentChange {
+                operation: SegmentOperation::Move,
+                index_before: Some(index_before),
+                index_after: Some(index_after),
+                range_before: Some(CursorRange {
+                    start: segment.range.start,
+                    end: segment.range.end,
+                }),
+                range_after: Some(CursorRange {
+                    start,
+                    end: start + segment.range.len(),
+                }),
+            };
+            // the requested segment's change comes first
+            if index_before == from {
+                cursor = start;
+                changes.insert(0, change);
+            } else {
+                changes.push(change);
+            }
+        }
+    }
+    new_message.push_str(&message[last.range.end..]);
+
+    Some(
+        SegmentOperationResult {
+            message: new_message,
+            cursor,
+            changes,
+        }
+        .into_utf16(message),
+    )
+}
+
+/// Duplicate a segment.
 ///
 /// Creates a copy of the segment immediately after the original. The cursor is
 /// positioned at the start of the new duplicate segment.
 ///
 /// # Constraints
 /// - Cannot duplicate MSH segment (would create invalid message)
-/// - Returns None if the segment index is out of bounds
+/// - Returns None if the segment isn't in the message
 #[tauri::command]
-pub fn duplicate_segment(message: &str, segment_index: usize) -> Option<SegmentOperationResult> {
+pub fn duplicate_segment(message: &str, segment: SegmentTarget) -> Option<SegmentOperationResult> {
+    duplicate_segment_at(message, segment.resolve(message)?)
+}
+
+/// Duplicate the segment at the given index.
+fn duplicate_segment_at(message: &str, segment_index: usize) -> Option<SegmentOperationResult> {
     // prevent duplicating MSH
     if segment_index == 0 {
         return None;
@@ -247,10 +528,23 @@ pub fn duplicate_segment(message: &str, segment_index: usize) -> Option<SegmentO
     // cursor at start of the new duplicate segment
     let new_cursor = segment.range.end + line_ending.len();
 
-    Some(SegmentOperationResult {
-        message: new_message,
-        cursor: new_cursor,
-    })
+    Some(
+        SegmentOperationResult {
+            message: new_message,
+            cursor: new_cursor,
+            changes: vec![SegmentChange {
+                operation: SegmentOperation::Duplicate,
+                index_before: None,
+                index_after: Some(segment_index + 1),
+                range_before: None,
+                range_after: Some(CursorRange {
+                    start: new_cursor,
+                    end: new_cursor + segment_content.len(),
+                }),
+            }],
+        }
+        .into_utf16(message),
+    )
 }
 
 #[cfg(test)]
@@ -283,13 +577,13 @@ mod tests {
 
     #[test]
     fn cannot_delete_msh_segment() {
-        let result = delete_segment(TEST_MESSAGE, 0);
+        let result = delete_segment(TEST_MESSAGE, 0.into());
         assert!(result.is_none());
     }
 
     #[test]
     fn can_delete_pid_segment() {
-        let result = delete_segment(TEST_MESSAGE, 1).expect("should delete PID");
+        let result = delete_segment(TEST_MESSAGE, 1.into()).expect("should delete PID");
         assert!(!result.message.contains("PID"));
         assert!(result.message.contains("MSH"));
         assert!(result.message.contains("PV1"));
@@ -297,32 +591,33 @@ mod tests {
 
     #[test]
     fn can_delete_last_segment() {
-        let result = delete_segment(TEST_MESSAGE, 2).expect("should delete PV1");
+        let result = delete_segment(TEST_MESSAGE, 2.into()).expect("should delete PV1");
         assert!(!result.message.contains("PV1"));
         assert!(result.message.contains("PID"));
     }
 
     #[test]
     fn cannot_move_msh_segment() {
-        let result = move_segment(TEST_MESSAGE, 0, MoveDirection::Down);
+        let result = move_segment(TEST_MESSAGE, 0.into(), MoveDirection::Down);
         assert!(result.is_none());
     }
 
     #[test]
     fn cannot_move_segment_into_msh_position() {
-        let result = move_segment(TEST_MESSAGE, 1, MoveDirection::Up);
+        let result = move_segment(TEST_MESSAGE, 1.into(), MoveDirection::Up);
         assert!(result.is_none());
     }
 
     #[test]
     fn cannot_move_last_segment_down() {
-        let result = move_segment(TEST_MESSAGE, 2, MoveDirection::Down);
+        let result = move_segment(TEST_MESSAGE, 2.into(), MoveDirection::Down);
         assert!(result.is_none());
     }
 
     #[test]
     fn can_move_segment_down() {
-        let result = move_segment(TEST_MESSAGE, 1, MoveDirection::Down).expect("should move");
+        let result =
+            move_segment(TEST_MESSAGE, 1.into(), MoveDirection::Down).expect("should move");
         // PV1 should now come before PID
         let pv1_pos = result.message.find("PV1").unwrap();
         let pid_pos = result.message.find("PID").unwrap();
@@ -331,7 +626,7 @@ mod tests {
 
     #[test]
     fn can_move_segment_up() {
-        let result = move_segment(TEST_MESSAGE, 2, MoveDirection::Up).expect("should move");
+        let result = move_segment(TEST_MESSAGE, 2.into(), MoveDirection::Up).expect("should move");
         // PV1 should now come before PID
         let pv1_pos = result.message.find("PV1").unwrap();
         let pid_pos = result.message.find("PID").unwrap();
@@ -340,13 +635,13 @@ mod tests {
 
     #[test]
     fn cannot_duplicate_msh_segment() {
-        let result = duplicate_segment(TEST_MESSAGE, 0);
+        let result = duplicate_segment(TEST_MESSAGE, 0.into());
         assert!(result.is_none());
     }
 
     #[test]
     fn can_duplicate_segment() {
-        let result = duplicate_segment(TEST_MESSAGE, 1).expect("should duplicate");
+        let result = duplicate_segment(TEST_MESSAGE, 1.into()).expect("should duplicate");
         // should have two PID segments
         let first_pid = result.message.find("PID").unwrap();
         let second_pid = result.message[first_pid + 3..].find("PID");
@@ -355,9 +650,115 @@ mod tests {
 
     #[test]
     fn duplicate_preserves_content() {
-        let result = duplicate_segment(TEST_MESSAGE, 1).expect("should duplicate");
+        let result = duplicate_segment(TEST_MESSAGE, 1.into()).expect("should duplicate");
         let pid_content = "PID|1||12345^^^MRN||DOE^JOHN";
         let count = result.message.matches(pid_content).count();
         assert_eq!(count, 2, "should have two identical PID segments");
     }
+
+    const RESULTS_MESSAGE: &str = "MSH|^~\\&|LAB|FAC|||20240101||ORU^R01|1|P|2.5.1\rPID|1||123\rOBR|1\rOBX|1|ST|A\rOBX|2|ST|B\rNTE|1||note\rOBX|3|ST|C";
+
+    #[test]
+    fn resolves_segments_by_name_and_occurrence() {
+        let resolve = |target: &str| SegmentTarget::from(target).resolve(RESULTS_MESSAGE);
+        assert_eq!(resolve("OBX"), Some(3));
+        assert_eq!(resolve("OBX[3]"), Some(6));
+        assert_eq!(resolve("obx[2]"), Some(4));
+        assert_eq!(resolve("OBX[4]"), None);
+        assert_eq!(resolve("OBX[0]"), None);
+        assert_eq!(resolve("OBX.5"), None);
+        assert_eq!(SegmentTarget::from(6).resolve(RESULTS_MESSAGE), Some(6));
+        assert_eq!(SegmentTarget::from(7).resolve(RESULTS_MESSAGE), None);
+
+        let target: SegmentTarget = serde_json::from_str("\"NTE[1]\"").unwrap();
+        assert_eq!(target, SegmentTarget::Named("NTE[1]".to_string()));
+        let target: SegmentTarget = serde_json::from_str("2").unwrap();
+        assert_eq!(target, SegmentTarget::Index(2));
+
+        let result = delete_segment(RESULTS_MESSAGE, "OBX[2]".into()).unwrap();
+        assert!(!result.message.contains("OBX|2|"));
+        let result = duplicate_segment(RESULTS_MESSAGE, "OBX[3]".into()).unwrap();
+        assert!(result.message.ends_with("OBX|3|ST|C\rOBX|3|ST|C"));
+        assert!(delete_segment(RESULTS_MESSAGE, "MSH".into()).is_none());
+    }
+
+    #[test]
+    fn moves_segments_to_any_position() {
+        // up: the NTE takes the first OBX's place
+        let result = move_segment_to(RESULTS_MESSAGE, "NTE".into(), "OBX[1]".into()).unwrap();
+        assert_eq!(
+            result.message,
+            "MSH|^~\\&|LAB|FAC|||20240101||ORU^R01|1|P|2.5.1\rPID|1||123\rOBR|1\rNTE|1||note\rOBX|1|ST|A\rOBX|2|ST|B\rOBX|3|ST|C"
+        );
+        assert_eq!(&result.message[result.cursor..result.cursor + 3], "NTE");
+        assert_eq!(result.changes.len(), 3);
+        assert_eq!(
+            (
+                result.changes[0].index_before,
+                result.changes[0].index_after
+            ),
+            (Some(5), Some(3))
+        );
+        for change in &result.changes {
+            let range = change.range_after.clone().unwrap();
+            let before = change.range_before.clone().unwrap();
+            assert_eq!(
+                &result.message[range.start..range.end],
+                &RESULTS_MESSAGE[before.start..before.end]
+            );
+        }
+
+        // down: the PID goes to the end
+        let result = move_segment_to(RESULTS_MESSAGE, 1.into(), "OBX[3]".into()).unwrap();
+        assert!(result.message.ends_with("\rOBX|3|ST|C\rPID|1||123"));
+        assert!(result
+            .message
+            .starts_with("MSH|^~\\&|LAB|FAC|||20240101||ORU^R01|1|P|2.5.1\rOBR|1\r"));
+
+        assert!(move_segment_to(RESULTS_MESSAGE, "PID".into(), "MSH".into()).is_none());
+        assert!(move_segment_to(RESULTS_MESSAGE, "MSH".into(), "PID".into()).is_none());
+        assert!(move_segment_to(RESULTS_MESSAGE, "PID".into(), "PID".into()).is_none());
+        assert!(move_segment_to(RESULTS_MESSAGE, "PID".into(), "ZZZ".into()).is_none());
+    }
+
+    #[test]
+    fn results_describe_the_segments_changed() {
+        let pid = TEST_MESSAGE.find("PID").unwrap();
+        let pv1 = TEST_MESSAGE.find("PV1").unwrap();
+
+        let result = delete_segment(TEST_MESSAGE, 1.into()).unwrap();
+        assert_eq!(result.changes.len(), 1);
+        assert_eq!(result.changes[0].operation, SegmentOperation::Delete);
+        assert_eq!(result.changes[0].index_before, Some(1));
+        assert_eq!(result.changes[0].index_after, None);
+        let range = result.changes[0].range_before.clone().unwrap();
+        assert_eq!(
+            &TEST_MESSAGE[range.start..range.end],
+            "PID|1||12345^^^MRN||DOE^JOHN"
+        );
+
+        let result = move_segment(TEST_MESSAGE, 2.into(), MoveDirection::Up).unwrap();
+        assert_eq!(result.changes.len(), 2);
+        let moved = &result.changes[0];
+        assert_eq!((moved.index_before, moved.index_after), (Some(2), Some(1)));
+        assert_eq!(moved.range_before.clone().unwrap().start, pv1);
+        let range = moved.range_after.clone().unwrap();
+        assert_eq!(&result.message[range.start..range.end], "PV1|1|I|ROOM^BED");
+        let swapped = result.changes[1].range_after.clone().unwrap();
+        assert_eq!(
+            &result.message[swapped.start..swapped.end],
+            &TEST_MESSAGE[pid..pv1 - 1]
+        );
+
+        let result = duplicate_segment(TEST_MESSAGE, 1.into()).unwrap();
+        let copy = &result.changes[0];
+        assert_eq!(copy.operation, SegmentOperation::Duplicate);
+        assert_eq!((copy.index_before, copy.index_after), (None, Some(2)));
+        let range = copy.range_after.clone().unwrap();
+        assert_eq!(range.start, result.cursor);
+        assert_eq!(
+            &result.message[range.start..range.end],
+            &TEST_MESSAGE[pid..pv1 - 1]
+        );
+    }
 }
@@ -0,0 +1,385 @@
+//! Reusable segment snippets kept in the data root.
+//!
+//! Templates cover whole messages, but much of the typing in a test message
+//! goes into the same few segments: a fully populated IN1, a standard block of
+//! vitals OBXs, the NK1s of a test family. A snippet saves one or more such
+//! segments under a name so they can be dropped into any message.
+//!
+//! # Storage
+//!
+//! Snippets are stored like user templates (see [`super::user_templates`]):
+//! one file per snippet in the `snippets` folder of the data root, named after
+//! the snippet, with its name and description in a metadata header. The
+//! segments are kept one per line.
+//!
+//! # Insertion
+//!
+//! Segments can't be split, so a snippet is inserted after the segment holding
+//! the cursor (or after the last segment if the cursor is past it), using the
+//! message's line endings. MSH can't be part of a snippet, as a message has
+//! only one.
+
+use serde::Serialize;
+use std::path::Path;
+use tauri::State;
+
+use super::cursor::CursorRange;
+use super::metadata::{get_document_metadata, set_document_metadata, DocumentMetadata};
+use super::multi_message::detect_line_ending;
+use super::offsets::Utf16Offsets;
+use super::segment::{SegmentChange, SegmentOperation, SegmentOperationResult};
+use super::user_templates::{library_id, library_path, LIBRARY_EXTENSION};
+use crate::AppData;
+
+/// Folder under the data root that holds snippets.
+pub const SNIPPETS_DIR: &str = "snippets";
+
+/// A saved snippet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SnippetInfo {
+    /// Identifier (the file name without extension)
+    pub id: String,
+    /// Name the snippet was saved with
+    pub name: String,
+    /// What the snippet is for, if given
+    pub description: Option<String>,
+    /// Names of the snippet's segments, in order
+    pub segments: Vec<String>,
+    /// The snippet's segments, one per line
+    pub content: String,
+}
+
+/// List the saved snippets, sorted by name.
+///
+/// # Arguments
+/// * `query` - Only list snippets whose name, description, or segment names
+///   contain this text, ignoring case
+/// * `state` - Application state containing the data root
+///
+/// # Returns
+/// * `Ok(Vec<SnippetInfo>)` - The matching snippets
+/// * `Err(String)` - The snippets folder couldn't be read
+#[tauri::command]
+pub async fn list_snippets(
+    query: Option<String>,
+    state: State<'_, AppData>,
+) -> Result<Vec<SnippetInfo>, String> {
+    let directory = state.data_root.lock().await.path.join(SNIPPETS_DIR);
+    let snippets = list_snippets_in(&directory)?;
+    Ok(match query.as_deref().map(str::trim) {
+        Some(query) if !query.is_empty() => snippets
+            .into_iter()
+            .filter(|snippet| snippet.matches(query))
+            .collect(),
+        _ => snippets,
+    })
+}
+
+/// Save segments as a snippet, replacing any snippet of the same name.
+///
+/// # Arguments
+/// * `name` - Name of the snippet
+/// * `content` - The segments, one per line
+/// * `description` - Optional description of what the snippet is for
+/// * `state` - Application state containing the data root
+///
+/// # Returns
+/// * `Ok(SnippetInfo)` - The saved snippet
+/// * `Err(String)` - The name is empty, the content isn't a list of segments,
+///   or the file couldn't be written
+#[tauri::command]
+pub async fn save_snippet(
+    name: String,
+    content: String,
+    description: Option<String>,
+    state: State<'_, AppData>,
+) -> Result<SnippetInfo, String> {
+    let directory = state.data_root.lock().await.path.join(SNIPPETS_DIR);
+    save_snippet_in(&directory, &name, &content, description)
+}
+
+/// Delete a snippet.
+///
+/// # Arguments
+/// * `id` - Identifier of the snippet
+/// * `state` - Application state containing the data root
+///
+/// # Returns
+/// * `Ok(())` - The snippet was deleted
+/// * `Err(String)` - The snippet doesn't exist or couldn't be deleted
+#[tauri::command]
+pub async fn delete_snippet(id: String, state: State<'_, AppData>) -> Result<(), String> {
+    let directory = state.data_root.lock().await.path.join(SNIPPETS_DIR);
+    std::fs::remove_file(library_path(&directory, &id)?)
+        .map_err(|e| format!("Failed to delete snippet {id}: {e}"))
+}
+
+/// Insert a snippet's segments after the segment holding the cursor.
+///
+/// # Arguments
+/// * `message` - The HL7 message to insert into
+/// * `cursor` - Cursor position in the message (UTF-16 offset)
+/// * `id` - Identifier of the snippet
+/// * `state` - Application state containing the data root
+///
+/// # Returns
+/// * `Ok(SegmentOperationResult)` - The modified message, with the cursor at
+///   the start of the first inserted segment
+/// * `Err(String)` - The snippet can't be read or the message can't be parsed
+#[tauri::command]
+pub async fn insert_snippet(
+    message: String,
+    cursor: usize,
+    id: String,
+    state: State<'_, AppData>,
+) -> Result<SegmentOperationResult, String> {
+    let directory = state.data_root.lock().await.path.join(SNIPPETS_DIR);
+    let file = std::fs::read_to_string(library_path(&directory, &id)?)
+        .map_err(|e| format!("Failed to read snippet {id}: {e}"))?;
+    let segments = snippet_segments(&get_document_metadata(&file).message)?;
+    let cursor = Utf16Offsets::new(&message).to_byte(cursor);
+    insert_segments(&message, cursor, &segments).map(|result| result.into_utf16(&message))
+}
+
+impl SnippetInfo {
+    /// Whether the snippet's name, description, or segment names contain the
+    /// query, ignoring case.
+    fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+        self.name.to_lowercase().contains(&query)
+            || self
+                .description
+                .as_ref()
+                .is_some_and(|description| description.to_lowercase().contains(&query))
+            || self
+                .segments
+                .iter()
+                .any(|segment| segment.to_lowercase().contains(&query))
+    }
+}
+
+/// Read the snippets in a folder, sorted by name.
+///
+/// A folder that doesn't exist yet holds no snippets, and files that can't be
+/// read or don't hold segments are skipped.
+fn list_snippets_in(directory: &Path) -> Result<Vec<SnippetInfo>, String> {
+    if !directory.exists() {
+        return Ok(Vec::new());
+    }
+    let entries =
+        std::fs::read_dir(directory).map_err(|e| format!("Failed to read snippets folder: {e}"))?;
+
+    let mut snippets: Vec<SnippetInfo> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == LIBRARY_EXTENSION))
+        .filter_map(|path| {
+            let id = path.file_stem()?.to_str()?.to_string();
+            let info = std::fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|content| snippet_info(id, &content));
+            match info {
+                Ok(info) => Some(info),
+                Err(e) => {
+                    log::warn!("skipping snippet {}: {e}", path.display());
+                    None
+                }
+            }
+        })
+        .collect();
+    snippets.sort_by(|a, b| {
+        a.name
+            .to_lowercase()
+            .cmp(&b.name.to_lowercase())
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    Ok(snippets)
+}
+
+/// Write a snippet file into a folder, creating the folder if needed.
+fn save_snippet_in(
+    directory: &Path,
+    name: &str,
+    content: &str,
+    description: Option<String>,
+) -> Result<SnippetInfo, String> {
+    let name = name.trim();
+    let id = library_id(name)?;
+    let segments = snippet_segments(content)?;
+    std::fs::create_dir_all(directory)
+        .map_err(|e| format!("Failed to create snippets folder: {e}"))?;
+
+    let metadata = DocumentMetadata {
+        title: Some(name.to_string()),
+        notes: description.filter(|description| !description.trim().is_empty()),
+        ..DocumentMetadata::default()
+    };
+    let file = set_document_metadata(&format!("{}\n", segments.join("\n")), metadata);
+    std::fs::write(library_path(directory, &id)?, &file)
+        .map_err(|e| format!("Failed to write snippet {name}: {e}"))?;
+    snippet_info(id, &file)
+}
+
+/// Describe a snippet from its file content.
+fn snippet_info(id: String, file: &str) -> Result<SnippetInfo, String> {
+    let document = get_document_metadata(file);
+    let segments = snippet_segments(&document.message)?;
+    Ok(SnippetInfo {
+        name: document.metadata.title.unwrap_or_else(|| id.clone()),
+        description: document.metadata.notes,
+        segments: segments
+            .iter()
+            .filter_map(|segment| segment.get(..3))
+            .map(str::to_string)
+            .collect(),
+        content: segments.join("\n"),
+        id,
+    })
+}
+
+/// Split snippet content into its segments, checking each is one.
+///
+/// Blank lines are dropped; every other line must start with a segment name
+/// other than MSH.
+fn snippet_segments(content: &str) -> Result<Vec<&str>, String> {
+    let segments: Vec<&str> = content
+        .split(['\r', '\n'])
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    if segments.is_empty() {
+        return Err("A snippet needs at least one segment".to_string());
+    }
+    for segment in &segments {
+        let name = segment.get(..3).unwrap_or_default();
+        let is_name = name.len() == 3
+            && name
+                .chars()
+                .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+            && segment
+                .chars()
+                .nth(3)
+                .is_none_or(|c| !c.is_ascii_alphanumeric());
+        if !is_name {
+            return Err(format!("Not a segment: \"{segment}\""));
+        }
+        if name == "MSH" {
+            return Err("A snippet can't contain an MSH segment".to_string());
+        }
+    }
+    Ok(segments)
+}
+
+/// Insert segments after the segment holding the cursor.
+fn insert_segments(
+    message: &str,
+    cursor: usize,
+    segments: &[&str],
+) -> Result<SegmentOperationResult, String> {
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(message)
+        .map_err(|e| format!("Failed to parse message: {e:#}"))?;
+    let existing: Vec<_> = parsed.segments().collect();
+    let index = existing
+        .iter()
+        .position(|segment| cursor <= segment.range.end)
+        .unwrap_or(existing.len().saturating_sub(1));
+    let after = existing
+        .get(index)
+        .map(|segment| segment.range.end)
+        .unwrap_or_default();
+
+    let line_ending = detect_line_ending(message);
+    let mut inserted = String::new();
+    let mut changes = Vec::new();
+    for (offset, segment) in segments.iter().enumerate() {
+        inserted.push_str(line_ending);
+        let start = after + inserted.len();
+        inserted.push_str(segment);
+        changes.push(SegmentChange {
+            operation: SegmentOperation::Insert,
+            index_before: None,
+            index_after: Some(index + 1 + offset),
+            range_before: None,
+            range_after: Some(CursorRange {
+                start,
+                end: start + segment.len(),
+            }),
+        });
+    }
+
+    Ok(SegmentOperationResult {
+        message: format!(
+            "{}{inserted}{}",
+            message.get(..after).unwrap_or_default(),
+            message.get(after..).unwrap_or_default()
+        ),
+        cursor: after + line_ending.len(),
+        changes,
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use crate::commands::temp_dir;
+
+    const MESSAGE: &str =
+        "MSH|^~\\&|HERMES|TEST|||20250101||ADT^A01|1|P|2.5.1\rPID|1||123||JONES^ALICE\rPV1|1|I";
+
+    #[test]
+    fn checks_snippet_segments() {
+        assert_eq!(
+            snippet_segments("OBX|1|NM|HR||72\n\nOBX|2|NM|RR||16\n").unwrap(),
+            ["OBX|1|NM|HR||72", "OBX|2|NM|RR||16"]
+        );
+        assert!(snippet_segments("  \n").is_err());
+        assert!(snippet_segments("MSH|^~\\&|X").unwrap_err().contains("MSH"));
+        assert!(snippet_segments("ins|1").is_err());
+        assert!(snippet_segments("IN12|1").is_err());
+    }
+
+    #[test]
+    fn inserts_after_the_segment_at_the_cursor() {
+        let pid = MESSAGE.find("PID").unwrap();
+        let result = insert_segments(MESSAGE, pid + 4, &["NK1|1|JONES^BOB", "NK1|2"]).unwrap();
+        assert_eq!(
+            result.message,
+            MESSAGE.replace("ALICE\r", "ALICE\rNK1|1|JONES^BOB\rNK1|2\r")
+        );
+        assert!(result.message[result.cursor..].starts_with("NK1|1"));
+        assert_eq!(result.changes.len(), 2);
+        let second = result.changes[1].range_after.clone().unwrap();
+        assert_eq!(&result.message[second.start..second.end], "NK1|2");
+        assert_eq!(result.changes[1].index_after, Some(3));
+
+        // past the last segment, the snippet goes at the end
+        let crlf = format!("{}\r\n", MESSAGE.replace('\r', "\r\n"));
+        let result = insert_segments(&crlf, crlf.len(), &["AL1|1"]).unwrap();
+        assert!(result.message.ends_with("PV1|1|I\r\nAL1|1\r\n"));
+    }
+
+    #[test]
+    fn saves_searches_and_lists_snippets() {
+        let directory = temp_dir("snippets");
+        save_snippet_in(
+            &directory,
+            "Vitals",
+            "OBX|1|NM|HR||72\rOBX|2|NM|RR||16",
+            Some("Heart and breathing rate".to_string()),
+        )
+        .unwrap();
+        let insurance = save_snippet_in(&directory, "Blue Cross", "IN1|1|BC01", None).unwrap();
+        assert_eq!(insurance.segments, ["IN1"]);
+
+        let snippets = list_snippets_in(&directory).unwrap();
+        let names: Vec<_> = snippets.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["Blue Cross", "Vitals"]);
+        assert_eq!(snippets[1].content, "OBX|1|NM|HR||72\nOBX|2|NM|RR||16");
+
+        assert!(snippets[1].matches("breathing"));
+        assert!(snippets[1].matches("obx"));
+        assert!(!snippets[1].matches("in1"));
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -29,6 +29,7 @@
 //! * `cell` - Regular field/component/subcomponent values
 //! * `temp` - Templated placeholders (e.g., "{now}", "{random}")
 //! * `ts` - Timestamp fields (detected via HL7 spec)
+//! * `esc` - Escape sequences (e.g., `\F\`, `\X0D\`), when asked for
 //! * `err` - Parse errors or unparsed content
 //! * `search-match` - Search result matches (find/replace feature)
 //! * `search-match-current` - Currently selected search match
@@ -43,6 +44,10 @@
 //! if the value is wrapped in curly braces. These placeholders are automatically
 //! transformed during message sending (see send_receive.rs).
 //!
+//! Escape sequences are only marked when the caller asks for them, since they
+//! otherwise read as ordinary cell content (see [`escape`](super::escape) for
+//! the sequences themselves).
+//!
 //! # Search Match Highlighting
 //!
 //! The syntax highlighting command accepts optional match ranges to highlight search
@@ -52,6 +57,8 @@
 use hl7_parser::{parser::ParseError, Message};
 use std::{borrow::Cow, ops::Range};
 
+use super::escape::escape_sequence_ranges;
+use super::offsets::Utf16Offsets;
 use crate::spec::std_spec::{
     get_version_with_fallback, is_component_a_timestamp, is_field_a_timestamp,
 };
@@ -59,9 +66,9 @@ use crate::spec::std_spec::{
 /// A range representing a search match for highlighting.
 #[derive(Debug, Clone, serde::Deserialize)]
 pub struct SearchMatch {
-    /// Start position of the match (byte offset)
+    /// Start position of the match (UTF-16 offset)
     pub start: usize,
-    /// End position of the match (byte offset, exclusive)
+    /// End position of the match (UTF-16 offset, exclusive)
     pub end: usize,
 }
 
@@ -88,9 +95,9 @@ impl DiffType {
 /// A range representing a diff highlight with its type.
 #[derive(Debug, Clone, serde::Deserialize)]
 pub struct DiffMatch {
-    /// Start position of the diff (byte offset)
+    /// Start position of the diff (UTF-16 offset)
     pub start: usize,
-    /// End position of the diff (byte offset, exclusive)
+    /// End position of the diff (UTF-16 offset, exclusive)
     pub end: usize,
     /// Type of difference (added, removed, modified)
     pub diff_type: DiffType,
@@ -119,9 +126,9 @@ impl ValidationSeverity {
 /// A range representing a validation highlight with its severity.
 #[derive(Debug, Clone, serde::Deserialize)]
 pub struct ValidationMatch {
-    /// Start position of the validation issue (byte offset)
+    /// Start position of the validation issue (UTF-16 offset)
     pub start: usize,
-    /// End position of the validation issue (byte offset, exclusive)
+    /// End position of the validation issue (UTF-16 offset, exclusive)
     pub end: usize,
     /// Severity of the validation issue
     pub severity: ValidationSeverity,
@@ -153,6 +160,10 @@ pub struct ValidationMatch {
 /// * `current_match_index` - Optional index of the currently selected match (0-based)
 /// * `diff_matches` - Optional list of diff highlight ranges with their types
 /// * `validation_matches` - Optional list of validation highlight ranges with their severities
+/// * `highlight_escapes` - Whether to mark escape sequences (defaults to false)
+///
+/// Match ranges are in UTF-16 code units, as the editor counts them, and are
+/// converted to byte offsets before highlighting.
 ///
 /// # Returns
 /// HTML string with syntax highlighting, safe for insertion into the DOM
@@ -164,7 +175,39 @@ pub fn syntax_highlight(
     current_match_index: Option<usize>,
     diff_matches: Option<Vec<DiffMatch>>,
     validation_matches: Option<Vec<ValidationMatch>>,
+    highlight_escapes: Option<bool>,
 ) -> String {
+    let offsets = Utf16Offsets::new(message);
+    let search_matches = search_matches.map(|matches| {
+        matches
+            .into_iter()
+            .map(|m| SearchMatch {
+                start: offsets.to_byte(m.start),
+                end: offsets.to_byte(m.end),
+            })
+            .collect::<Vec<_>>()
+    });
+    let diff_matches = diff_matches.map(|matches| {
+        matches
+            .into_iter()
+            .map(|m| DiffMatch {
+                start: offsets.to_byte(m.start),
+                end: offsets.to_byte(m.end),
+                ..m
+            })
+            .collect::<Vec<_>>()
+    });
+    let validation_matches = validation_matches.map(|matches| {
+        matches
+            .into_iter()
+            .map(|m| ValidationMatch {
+                start: offsets.to_byte(m.start),
+                end: offsets.to_byte(m.end),
+                ..m
+            })
+            .collect::<Vec<_>>()
+    });
+
     match hl7_parser::parse_message_with_lenient_newlines(message) {
         Ok(msg) => {
             let mut highlighted = do_syntax_highlight(
@@ -173,6 +216,7 @@ pub fn syntax_highlight(
                 current_match_index,
                 diff_matches.as_deref(),
                 validation_matches.as_deref(),
+                highlight_escapes.unwrap_or(false),
             );
             if msg.raw_value().len() != message.len() {
                 // the delivered message extends beyond the parsed message
@@ -220,6 +264,8 @@ enum RangeType {
     TemplatedValue,
     /// Timestamp fields (detected via HL7 spec)
     Timestamp,
+    /// Escape sequences within a value, like `\F\` or `\X0D\`
+    Escape,
 }
 
 impl RangeType {
@@ -236,6 +282,7 @@ impl RangeType {
             RangeType::Cell => "cell",
             RangeType::TemplatedValue => "temp",
             RangeType::Timestamp => "ts",
+            RangeType::Escape => "esc",
         }
     }
 }
@@ -251,6 +298,7 @@ impl RangeType {
 /// * `current_match_index` - Optional index of the currently selected match
 /// * `diff_matches` - Optional slice of diff highlight ranges with their types
 /// * `validation_matches` - Optional slice of validation highlight ranges with their severities
+/// * `highlight_escapes` - Whether to mark escape sequences
 ///
 /// # Returns
 /// HTML-formatted string with syntax highlighting
@@ -260,8 +308,9 @@ fn do_syntax_highlight(
     current_match_index: Option<usize>,
     diff_matches: Option<&[DiffMatch]>,
     validation_matches: Option<&[ValidationMatch]>,
+    highlight_escapes: bool,
 ) -> String {
-    let ranges = collect_ranges(message);
+    let ranges = collect_ranges(message, highlight_escapes);
     // ranges will already be sorted by their start position because of the
     // structure of the message
     let position_types = create_position_mapping(ranges, message.raw_value().len());
@@ -289,6 +338,8 @@ fn do_syntax_highlight(
 /// * **Timestamp detection**: Uses the HL7 spec to identify fields that should contain
 ///   timestamps, enabling special formatting
 /// * **Templated values**: Detects placeholders like "{now}" by checking for curly braces
+/// * **Escape sequences**: When asked for, each sequence within a subcomponent is
+///   pushed after the subcomponent itself, so it takes precedence in the mapping
 ///
 /// # Return Value Order
 ///
@@ -297,10 +348,11 @@ fn do_syntax_highlight(
 ///
 /// # Arguments
 /// * `message` - Parsed HL7 message
+/// * `highlight_escapes` - Whether to mark escape sequences
 ///
 /// # Returns
 /// Vector of (character range, type) tuples, sorted by start position
-fn collect_ranges(message: &Message) -> Vec<(Range<usize>, RangeType)> {
+fn collect_ranges(message: &Message, highlight_escapes: bool) -> Vec<(Range<usize>, RangeType)> {
     let mut ranges = Vec::new();
     let version = get_version_with_fallback(message);
 
@@ -341,6 +393,19 @@ fn collect_ranges(message: &Message) -> Vec<(Range<usize>, RangeType)> {
                                 RangeType::Cell
                             },
                         ));
+                        if highlight_escapes {
+                            let start = subcomponent.range.start;
+                            ranges.extend(
+                                escape_sequence_ranges(
+                                    subcomponent.raw_value(),
+                                    message.separators.escape,
+                                )
+                                .into_iter()
+                                .map(|range| {
+                                    (start + range.start..start + range.end, RangeType::Escape)
+                                }),
+                            );
+                        }
                     }
                 }
             }
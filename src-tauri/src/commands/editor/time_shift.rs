@@ -0,0 +1,267 @@
+//! Shifting every timestamp in a message at once.
+//!
+//! Replaying an old test message often needs current dates: a receiver may
+//! reject a visit that ended years ago, or file results under the wrong day.
+//! Rather than editing each timestamp by hand, this moves all of them by the
+//! same amount, so the time between them is kept.
+//!
+//! # Shifts
+//!
+//! * **Offset** - Move every timestamp by an offset written like placeholder
+//!   offsets (e.g., `+1d`, `-2h30m`, `+1y-1w`)
+//! * **Rebase** - Move every timestamp by however long ago MSH-7 was, so MSH-7
+//!   becomes the current time
+//!
+//! # Fields
+//!
+//! The fields and components the schema gives the `datetime` datatype are
+//! shifted, in every repetition. Fields with the `date` datatype, which are
+//! mostly birth dates, are only shifted on request. Timestamps keep their
+//! precision and UTC offset, and are compared as the local times they're
+//! written in. Placeholders such as `{now}` are left for the send to fill in.
+
+use hl7_parser::Message;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use super::formula::{shift_timestamp, timestamp_datetime, Shift};
+use super::metadata::strip_document_metadata;
+use super::placeholders::parse_offset;
+use crate::schema::cache::SchemaCache;
+use crate::schema::segment::DataType;
+use crate::schema::version::detect_version;
+use crate::AppData;
+
+/// How far to move the timestamps of a message.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum TimestampShift {
+    /// Move every timestamp by an offset such as `+1d` or `-2h30m`
+    Offset { offset: String },
+    /// Move every timestamp so MSH-7 becomes the current time
+    Rebase,
+}
+
+/// A message after its timestamps were shifted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ShiftedTimestamps {
+    /// The message with its timestamps shifted
+    pub message: String,
+    /// Number of timestamps shifted
+    pub shifted: usize,
+    /// Paths of timestamp fields whose values couldn't be read, left as they were
+    pub unreadable: Vec<String>,
+}
+
+/// Shift every timestamp in a message by the same amount.
+///
+/// # Arguments
+/// * `message` - The HL7 message as a string
+/// * `shift` - An offset to shift by, or a rebase onto the current time
+/// * `include_dates` - Whether to shift date fields (e.g., birth dates) too
+/// * `state` - Application state containing the schema cache
+///
+/// # Returns
+/// * `Ok(ShiftedTimestamps)` - The shifted message
+/// * `Err(String)` - If the message can't be parsed, the offset is malformed,
+///   or MSH-7 has no timestamp to rebase from
+#[tauri::command]
+pub fn shift_timestamps(
+    message: &str,
+    shift: TimestampShift,
+    include_dates: bool,
+    state: State<'_, AppData>,
+) -> Result<ShiftedTimestamps, String> {
+    shift_message_timestamps(
+        message,
+        &shift,
+        include_dates,
+        &state.schema,
+        jiff::Zoned::now().datetime(),
+    )
+}
+
+fn shift_message_timestamps(
+    message: &str,
+    shift: &TimestampShift,
+    include_dates: bool,
+    schema: &SchemaCache,
+    now: jiff::civil::DateTime,
+) -> Result<ShiftedTimestamps, String> {
+    let (body, header_length) = strip_document_metadata(message);
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(body)
+        .map_err(|e| format!("Failed to parse message: {e}"))?;
+    let shift = match shift {
+        TimestampShift::Offset { offset } => {
+            parse_offset(offset.trim()).map_err(|e| format!("Invalid offset '{offset}': {e}"))?
+        }
+        TimestampShift::Rebase => rebase_shift(&parsed, now)?,
+    };
+
+    let mut shifted = message.get(..header_length).unwrap_or_default().to_string();
+    let mut copied = 0;
+    let mut count = 0;
+    let mut unreadable = Vec::new();
+    for (path, range) in timestamp_ranges(&parsed, body, include_dates, schema) {
+        let Some(value) = body.get(range.clone()) else {
+            continue;
+        };
+        if value.is_empty() || value.starts_with('{') && value.ends_with('}') {
+            continue;
+        }
+        match shift_timestamp(value, shift) {
+            Ok(value) => {
+                shifted.push_str(body.get(copied..range.start).unwrap_or_default());
+                shifted.push_str(&value);
+                copied = range.end;
+                count += 1;
+            }
+            Err(_) => unreadable.push(path),
+        }
+    }
+    shifted.push_str(body.get(copied..).unwrap_or_default());
+
+    Ok(ShiftedTimestamps {
+        message: shifted,
+        shifted: count,
+        unreadable,
+    })
+}
+
+/// The shift that moves MSH-7 to the current time.
+fn rebase_shift(message: &Message, now: jiff::civil::DateTime) -> Result<Shift, String> {
+    let sent = message
+        .query("MSH.7.1")
+        .map(|sent| sent.raw_value())
+        .filter(|sent| !sent.is_empty())
+        .ok_or_else(|| "MSH-7 has no timestamp to rebase from".to_string())?;
+    let sent = timestamp_datetime(sent).map_err(|e| format!("Can't rebase from MSH-7: {e}"))?;
+    Ok(Shift::from_seconds(now.duration_since(sent).as_secs()))
+}
+
+/// Paths and byte ranges of the timestamps in a message, in message order.
+///
+/// A field with a timestamp datatype holds the time in its first component
+/// (as in a TS), and a component in its first subcomponent.
+fn timestamp_ranges(
+    message: &Message,
+    text: &str,
+    include_dates: bool,
+    schema: &SchemaCache,
+) -> Vec<(String, std::ops::Range<usize>)> {
+    let version = schema.resolve_version(detect_version(text));
+    let shifts = |datatype: Option<DataType>| match datatype {
+        Some(DataType::DateTime) => true,
+        Some(DataType::Date) => include_dates,
+        None => false,
+    };
+
+    let mut ranges = Vec::new();
+    for segment in message.segments() {
+        let Ok(definitions) = schema.get_segment_for_version(segment.name, &version) else {
+            continue;
+        };
+        let datatype = |field: usize, component: Option<usize>| {
+            definitions
+                .iter()
+                .find(|definition| {
+                    usize::from(definition.field) == field
+                        && definition.component.map(usize::from) == component
+                })
+                .and_then(|definition| definition.datatype)
+        };
+
+        // MSH-1 and MSH-2 hold the delimiters
+        let skip = if segment.name == "MSH" { 2 } else { 0 };
+        for (i, field) in segment.fields.iter().enumerate().skip(skip) {
+            let field_n = i + 1;
+            let field_is_timestamp = shifts(datatype(field_n, None));
+            for repeat in &field.repeats {
+                if field_is_timestamp {
+                    let range = repeat
+                        .components
+                        .first()
+                        .map_or(repeat.range.clone(), |component| component.range.clone());
+                    ranges.push((format!("{}.{field_n}", segment.name), range));
+                    continue;
+                }
+                for (c, component) in repeat.components.iter().enumerate() {
+                    if shifts(datatype(field_n, Some(c + 1))) {
+                        let range = component
+                            .subcomponents
+                            .first()
+                            .map_or(component.range.clone(), |sub| sub.range.clone());
+                        ranges.push((format!("{}.{field_n}.{}", segment.name, c + 1), range));
+                    }
+                }
+            }
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str = "MSH|^~\\&|APP|FAC|||20250101120000||ADT^A01|1|P|2.5.1\r\
+        EVN|A01|202501011100-0500\r\
+        PID|1||123||Doe^Jane||19800101";
+
+    fn shift(shift: TimestampShift, include_dates: bool) -> ShiftedTimestamps {
+        let schema = SchemaCache::new().unwrap();
+        let now = jiff::civil::date(2026, 3, 15).at(9, 30, 0, 0);
+        shift_message_timestamps(MESSAGE, &shift, include_dates, &schema, now).unwrap()
+    }
+
+    #[test]
+    fn shifts_by_an_offset() {
+        let result = shift(
+            TimestampShift::Offset {
+                offset: "+1d-1h".to_string(),
+            },
+            false,
+        );
+        assert_eq!(result.shifted, 2);
+        assert!(result.message.contains("|20250102110000|"));
+        assert!(result.message.contains("EVN|A01|202501021000-0500\r"));
+        assert!(result.message.ends_with("||19800101"), "dates are kept");
+
+        let result = shift(
+            TimestampShift::Offset {
+                offset: "-1y".to_string(),
+            },
+            true,
+        );
+        assert_eq!(result.shifted, 3);
+        assert!(result.message.ends_with("||19790101"));
+    }
+
+    #[test]
+    fn rebases_onto_the_current_time() {
+        let result = shift(TimestampShift::Rebase, false);
+        assert!(result.message.contains("|20260315093000|"));
+        // an hour before MSH-7, as it was
+        assert!(result.message.contains("EVN|A01|202603150830-0500\r"));
+        assert!(result.unreadable.is_empty());
+    }
+
+    #[test]
+    fn reports_unreadable_timestamps() {
+        let schema = SchemaCache::new().unwrap();
+        let message = "MSH|^~\\&|APP|FAC|||yesterday||ADT^A01|1|P|2.5.1\rEVN|A01|{now}";
+        let offset = TimestampShift::Offset {
+            offset: "+1h".to_string(),
+        };
+        let now = jiff::civil::date(2026, 3, 15).at(9, 30, 0, 0);
+        let result = shift_message_timestamps(message, &offset, false, &schema, now).unwrap();
+        assert_eq!(result.message, message);
+        assert_eq!(result.unreadable, ["MSH.7"]);
+
+        assert!(
+            shift_message_timestamps(message, &TimestampShift::Rebase, false, &schema, now)
+                .is_err()
+        );
+    }
+}
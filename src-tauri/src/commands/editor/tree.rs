@@ -0,0 +1,381 @@
+//! The whole message as a tree, for the structure view.
+//!
+//! The editor shows the message as text; the structure view shows the same
+//! message as a collapsible tree of segments, fields, repeats, components, and
+//! subcomponents, each named from the schema or the HL7 specification and
+//! carrying its character range so selecting a node can select the text, and
+//! moving the cursor can reveal the node.
+//!
+//! # Levels That Exist
+//!
+//! Like [`locate_cursor`](super::locate_cursor), the tree only has the levels
+//! a value actually uses. A field without repeats has its components directly
+//! beneath it (`PID.5` → `PID.5.1`), a field without components has no children
+//! at all, and a component only has children if it has subcomponents. Paths are
+//! written the same way, so every node's path can be given to
+//! [`get_field_range`](super::get_field_range).
+//!
+//! # Names
+//!
+//! Segments are named from the specification. Fields and components are named
+//! from the schema where it has a name for them, otherwise from the
+//! specification for the message's version. Subcomponents aren't named.
+
+use std::collections::HashMap;
+
+use hl7_parser::message::{Component, Field, Repeat, Segment};
+use hl7_parser::Message;
+use serde::Serialize;
+use tauri::State;
+
+use super::cursor::CursorRange;
+use super::offsets::Utf16Offsets;
+use crate::schema::version::{detect_version, DEFAULT_VERSION};
+use crate::spec::std_spec::{
+    component_descriptions, field_descriptions, is_valid_version, known_segment_description,
+};
+use crate::AppData;
+
+/// What part of the message a node is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeKind {
+    Segment,
+    Field,
+    Repeat,
+    Component,
+    Subcomponent,
+}
+
+/// A part of the message and the parts within it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TreeNode {
+    /// What part of the message this is
+    pub kind: NodeKind,
+    /// Query path of the part (e.g., "OBX[2]", "PID.5", "PID.3[2].1", "PID.5.1.2")
+    pub path: String,
+    /// Name of the part from the schema or spec, if known
+    pub name: Option<String>,
+    /// Raw text of the part, delimiters and escape sequences included
+    pub value: String,
+    /// Where the part is in the message (UTF-16 offsets from the command)
+    pub range: CursorRange,
+    /// The parts within this one, empty if it has none
+    pub children: Vec<TreeNode>,
+}
+
+/// Names for the parts of one kind of segment.
+#[derive(Debug, Default, Clone)]
+pub struct SegmentNames {
+    /// What the segment is (e.g., "Patient Identification")
+    pub description: Option<String>,
+    /// Field names, by field number
+    pub fields: HashMap<usize, String>,
+    /// Component names, by field and component number
+    pub components: HashMap<(usize, usize), String>,
+}
+
+/// Parse a message into a tree of its segments, fields, repeats, components,
+/// and subcomponents.
+///
+/// # Arguments
+/// * `message` - The HL7 message as a string
+/// * `state` - Application state containing the schema cache
+///
+/// # Returns
+/// * `Ok(Vec<TreeNode>)` - One node per segment, in message order
+/// * `Err(String)` - If the message can't be parsed
+#[tauri::command]
+pub fn parse_message_tree(
+    message: &str,
+    state: State<'_, AppData>,
+) -> Result<Vec<TreeNode>, String> {
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(message)
+        .map_err(|e| format!("Failed to parse message: {e}"))?;
+
+    let version = state.schema.resolve_version(detect_version(message));
+    let spec_version = if is_valid_version(&version) {
+        version.as_str()
+    } else {
+        DEFAULT_VERSION
+    };
+
+    let mut tree = build_tree(message, &parsed, |segment| {
+        let mut names = SegmentNames {
+            description: known_segment_description(spec_version, segment).or_else(|| {
+                state
+                    .schema
+                    .custom_segment(segment)
+                    .map(|custom| custom.description)
+            }),
+            ..SegmentNames::default()
+        };
+        for field in state
+            .schema
+            .get_segment_for_version(segment, &version)
+            .unwrap_or_default()
+        {
+            let number = usize::from(field.field);
+            match field.component {
+                Some(component) => {
+                    names
+                        .components
+                        .insert((number, usize::from(component)), field.name);
+                }
+                None => {
+                    names.fields.insert(number, field.name);
+                }
+            }
+        }
+        for (index, description) in field_descriptions(spec_version, segment)
+            .into_iter()
+            .enumerate()
+        {
+            let number = index + 1;
+            for (component, description) in component_descriptions(spec_version, segment, number)
+                .into_iter()
+                .enumerate()
+            {
+                names
+                    .components
+                    .entry((number, component + 1))
+                    .or_insert(description);
+            }
+            names.fields.entry(number).or_insert(description);
+        }
+        names
+    });
+    ranges_to_utf16(&mut tree, &Utf16Offsets::new(message));
+    Ok(tree)
+}
+
+/// Convert the nodes' byte ranges to the UTF-16 offsets the editor uses.
+fn ranges_to_utf16(nodes: &mut [TreeNode], offsets: &Utf16Offsets) {
+    for node in nodes {
+        node.range = offsets.cursor_range(&(node.range.start..node.range.end));
+        ranges_to_utf16(&mut node.children, offsets);
+    }
+}
+
+/// Build the tree for a parsed message.
+///
+/// `names` gives the names for a kind of segment; it's called once for each
+/// segment name in the message.
+pub fn build_tree(
+    source: &str,
+    message: &Message,
+    names: impl Fn(&str) -> SegmentNames,
+) -> Vec<TreeNode> {
+    let mut lookups: HashMap<&str, SegmentNames> = HashMap::new();
+    let mut occurrences: HashMap<&str, usize> = HashMap::new();
+
+    message
+        .segments()
+        .map(|segment| {
+            let occurrence = occurrences.entry(segment.name).or_default();
+            *occurrence += 1;
+            let path = if *occurrence == 1 {
+                segment.name.to_string()
+            } else {
+                format!("{}[{occurrence}]", segment.name)
+            };
+            let segment_names = lookups
+                .entry(segment.name)
+                .or_insert_with(|| names(segment.name));
+            segment_node(source, segment, path, segment_names)
+        })
+        .collect()
+}
+
+fn segment_node(source: &str, segment: &Segment, path: String, names: &SegmentNames) -> TreeNode {
+    let children = segment
+        .fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| field_node(source, field, &path, index + 1, names))
+        .collect();
+    node(
+        source,
+        NodeKind::Segment,
+        path,
+        names.description.clone(),
+        &segment.range,
+        children,
+    )
+}
+
+fn field_node(
+    source: &str,
+    field: &Field,
+    segment_path: &str,
+    number: usize,
+    names: &SegmentNames,
+) -> TreeNode {
+    let path = format!("{segment_path}.{number}");
+    let children = match field.repeats.as_slice() {
+        [repeat] => repeat_children(source, repeat, &path, number, names),
+        repeats => repeats
+            .iter()
+            .enumerate()
+            .map(|(index, repeat)| {
+                let repeat_path = format!("{path}[{}]", index + 1);
+                let children = repeat_children(source, repeat, &repeat_path, number, names);
+                node(
+                    source,
+                    NodeKind::Repeat,
+                    repeat_path,
+                    None,
+                    &repeat.range,
+                    children,
+                )
+            })
+            .collect(),
+    };
+    node(
+        source,
+        NodeKind::Field,
+        path,
+        names.fields.get(&number).cloned(),
+        &field.range,
+        children,
+    )
+}
+
+/// The components of a repeat, or the subcomponents of its only component.
+fn repeat_children(
+    source: &str,
+    repeat: &Repeat,
+    path: &str,
+    field: usize,
+    names: &SegmentNames,
+) -> Vec<TreeNode> {
+    match repeat.components.as_slice() {
+        [component] => subcomponent_nodes(source, component, &format!("{path}.1")),
+        components => components
+            .iter()
+            .enumerate()
+            .map(|(index, component)| {
+                let number = index + 1;
+                let component_path = format!("{path}.{number}");
+                let children = subcomponent_nodes(source, component, &component_path);
+                node(
+                    source,
+                    NodeKind::Component,
+                    component_path,
+                    names.components.get(&(field, number)).cloned(),
+                    &component.range,
+                    children,
+                )
+            })
+            .collect(),
+    }
+}
+
+/// The subcomponents of a component, or nothing if it has only one.
+fn subcomponent_nodes(source: &str, component: &Component, path: &str) -> Vec<TreeNode> {
+    if component.subcomponents.len() < 2 {
+        return Vec::new();
+    }
+    component
+        .subcomponents
+        .iter()
+        .enumerate()
+        .map(|(index, subcomponent)| {
+            node(
+                source,
+                NodeKind::Subcomponent,
+                format!("{path}.{}", index + 1),
+                None,
+                &subcomponent.range,
+                Vec::new(),
+            )
+        })
+        .collect()
+}
+
+fn node(
+    source: &str,
+    kind: NodeKind,
+    path: String,
+    name: Option<String>,
+    range: &std::ops::Range<usize>,
+    children: Vec<TreeNode>,
+) -> TreeNode {
+    TreeNode {
+        kind,
+        path,
+        name,
+        value: source.get(range.clone()).unwrap_or_default().to_string(),
+        range: CursorRange {
+            start: range.start,
+            end: range.end,
+        },
+        children,
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str = "MSH|^~\\&|APP|FAC|||20250101||ADT^A01|1|P|2.5.1\r\
+        PID|1||123~456^^^HOSP||DOE^JANE&Q|F\r\
+        NTE|1\r\
+        NTE|2";
+
+    fn tree() -> Vec<TreeNode> {
+        let message = hl7_parser::parse_message(MESSAGE).unwrap();
+        build_tree(MESSAGE, &message, |segment| match segment {
+            "PID" => SegmentNames {
+                description: Some("Patient Identification".to_string()),
+                fields: HashMap::from([(5, "Patient Name".to_string())]),
+                components: HashMap::from([((5, 2), "Given Name".to_string())]),
+            },
+            _ => SegmentNames::default(),
+        })
+    }
+
+    #[test]
+    fn nests_only_the_levels_a_value_uses() {
+        let tree = tree();
+        let paths: Vec<&str> = tree.iter().map(|node| node.path.as_str()).collect();
+        assert_eq!(paths, ["MSH", "PID", "NTE", "NTE[2]"]);
+
+        let pid = &tree[1];
+        assert_eq!(pid.name.as_deref(), Some("Patient Identification"));
+
+        // a plain field has no children
+        assert!(pid.children[0].children.is_empty());
+
+        // repeats, the second with components
+        let identifiers = &pid.children[2];
+        assert_eq!(identifiers.children.len(), 2);
+        assert_eq!(identifiers.children[0].kind, NodeKind::Repeat);
+        assert_eq!(identifiers.children[0].path, "PID.3[1]");
+        assert!(identifiers.children[0].children.is_empty());
+        assert_eq!(identifiers.children[1].children[3].path, "PID.3[2].4");
+        assert_eq!(identifiers.children[1].children[3].value, "HOSP");
+
+        // components straight beneath the field, and subcomponents beneath them
+        let name = &pid.children[4];
+        assert_eq!(name.name.as_deref(), Some("Patient Name"));
+        assert_eq!(name.children[1].kind, NodeKind::Component);
+        assert_eq!(name.children[1].path, "PID.5.2");
+        assert_eq!(name.children[1].name.as_deref(), Some("Given Name"));
+        assert_eq!(name.children[1].children[1].path, "PID.5.2.2");
+        assert_eq!(name.children[1].children[1].value, "Q");
+    }
+
+    #[test]
+    fn ranges_cover_each_value() {
+        fn check(node: &TreeNode) {
+            assert_eq!(&MESSAGE[node.range.start..node.range.end], node.value);
+            node.children.iter().for_each(check);
+        }
+        let tree = tree();
+        tree.iter().for_each(check);
+        assert_eq!(tree[1].children[4].value, "DOE^JANE&Q");
+        assert_eq!(tree[3].value, "NTE|2");
+    }
+}
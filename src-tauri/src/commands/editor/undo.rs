@@ -0,0 +1,755 @@
+//! Undo and redo for the message in the editor.
+//!
+//! The backend owns the editor's undo history. Every change to the message is
+//! recorded as an edit (the range it replaced, the text it removed, and the
+//! text it put in), and edits are grouped into steps, so typing a name is one
+//! step to undo rather than one per keystroke. Undo applies a step's edits in
+//! reverse; redo applies them again. Each document open in the editor has a
+//! history of its own (see [`super::documents`]).
+//!
+//! # Recording Changes
+//!
+//! The frontend sends the whole message after each change, along with an
+//! optional label for the step ("Typing", "Delete Segment") and whether it may
+//! be merged with the step before. The edit is worked out from what changed
+//! between the message the history last saw and the new one. A change merges
+//! with the step before while the changes keep coming within
+//! [`COALESCE_WINDOW`] of each other, and every merging change is a new step
+//! once the window has passed.
+//!
+//! Every command takes the message as the editor has it, and any difference
+//! from what the history last saw (a change made without going through
+//! [`record_edit`]) is recorded as a step of its own first, so the history
+//! never undoes into text the editor never had.
+//!
+//! # Menu
+//!
+//! The Undo and Redo menu items are kept in step with the active document's
+//! history by the backend, enabled only when there's a step to undo or redo,
+//! and named after the step they'd undo or redo ("Undo Delete Segment").
+//!
+//! # Checkpoints
+//!
+//! A checkpoint remembers the message under a name, such as the last version
+//! that passed validation, so it can be returned to however many steps ago it
+//! was. Returning to a checkpoint is itself a step, so it can be undone.
+//!
+//! # Persistence
+//!
+//! When a file is saved, its history is saved with it in the history store
+//! under the data root, and it's restored when the file is opened again, as
+//! long as the file hasn't changed since. The histories of the last
+//! [`MAX_SAVED_HISTORIES`] files saved are kept.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, State};
+use tauri_plugin_store::StoreExt;
+
+use super::documents::DocumentId;
+use crate::commands::HISTORY_STORE;
+use crate::AppData;
+
+/// Key the saved undo histories are kept under in the history store.
+const UNDO_KEY: &str = "undo";
+
+/// How soon a change must follow the one before to be merged with it.
+const COALESCE_WINDOW: Duration = Duration::from_millis(500);
+
+/// Most steps kept; the oldest are forgotten first.
+const MAX_STEPS: usize = 500;
+
+/// Most files whose histories are saved; the least recently saved are
+/// forgotten first.
+const MAX_SAVED_HISTORIES: usize = 20;
+
+/// A change to the message: `removed` at byte offset `start` was replaced by
+/// `inserted`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Edit {
+    start: usize,
+    removed: String,
+    inserted: String,
+}
+
+impl Edit {
+    /// The edit turning one text into another, or `None` if they're the same.
+    ///
+    /// The edit covers everything between the longest common prefix and the
+    /// longest common suffix, split on character boundaries.
+    fn between(before: &str, after: &str) -> Option<Edit> {
+        if before == after {
+            return None;
+        }
+        let start = before
+            .char_indices()
+            .zip(after.chars())
+            .find(|((_, a), b)| a != b)
+            .map_or_else(|| before.len().min(after.len()), |((i, _), _)| i);
+        let before_rest = before.get(start..)?;
+        let after_rest = after.get(start..)?;
+        let suffix: usize = before_rest
+            .chars()
+            .rev()
+            .zip(after_rest.chars().rev())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a.len_utf8())
+            .sum();
+        Some(Edit {
+            start,
+            removed: before_rest.get(..before_rest.len() - suffix)?.to_string(),
+            inserted: after_rest.get(..after_rest.len() - suffix)?.to_string(),
+        })
+    }
+
+    /// Apply the edit to a text, or `None` if the text doesn't have the
+    /// removed text where the edit expects it.
+    fn apply(&self, text: &str) -> Option<String> {
+        let end = self.start + self.removed.len();
+        if text.get(self.start..end)? != self.removed {
+            return None;
+        }
+        Some(format!(
+            "{}{}{}",
+            text.get(..self.start)?,
+            self.inserted,
+            text.get(end..)?
+        ))
+    }
+
+    /// The edit undoing this one.
+    fn inverse(&self) -> Edit {
+        Edit {
+            start: self.start,
+            removed: self.inserted.clone(),
+            inserted: self.removed.clone(),
+        }
+    }
+}
+
+/// Edits undone and redone together.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct UndoStep {
+    /// What the step did, for the menu
+    label: Option<String>,
+    /// The edits, in the order they were made
+    edits: Vec<Edit>,
+}
+
+/// The message under a name, to return to later.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Checkpoint {
+    name: String,
+    message: String,
+}
+
+/// The undo history of one message.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct DocumentHistory {
+    /// The message as of the last change recorded
+    current: String,
+    /// Steps to undo, oldest first
+    undo: Vec<UndoStep>,
+    /// Steps undone that can be redone, most recently undone last
+    redo: Vec<UndoStep>,
+    /// Named versions of the message
+    #[serde(default)]
+    checkpoints: Vec<Checkpoint>,
+    /// When the last change was merged into the step on top of the undo stack,
+    /// if the next one may be merged into it too
+    #[serde(skip)]
+    merging_since: Option<Instant>,
+}
+
+impl DocumentHistory {
+    /// A history starting from a message, with nothing to undo.
+    fn new(message: &str) -> Self {
+        DocumentHistory {
+            current: message.to_string(),
+            ..DocumentHistory::default()
+        }
+    }
+
+    /// Record a change to the message.
+    ///
+    /// A merging change is added to the step on top of the undo stack if the
+    /// change before it merged too and was less than [`COALESCE_WINDOW`] ago.
+    fn record(&mut self, message: &str, label: Option<String>, coalesce: bool, now: Instant) {
+        let Some(edit) = Edit::between(&self.current, message) else {
+            return;
+        };
+        self.redo.clear();
+        let merges = coalesce
+            && self
+                .merging_since
+                .is_some_and(|since| now.duration_since(since) < COALESCE_WINDOW);
+        match self.undo.last_mut() {
+            Some(step) if merges => step.edits.push(edit),
+            _ => self.undo.push(UndoStep {
+                label,
+                edits: vec![edit],
+            }),
+        }
+        self.merging_since = coalesce.then_some(now);
+        if self.undo.len() > MAX_STEPS {
+            self.undo.drain(..self.undo.len() - MAX_STEPS);
+        }
+        self.current = message.to_string();
+    }
+
+    /// Undo the last step.
+    ///
+    /// # Returns
+    /// * `Ok(Some(String))` - The message with the step undone
+    /// * `Ok(None)` - There's nothing to undo
+    /// * `Err(String)` - The step doesn't fit the message; the history is
+    ///   cleared, as the rest of it can't be trusted either
+    fn undo(&mut self) -> Result<Option<String>, String> {
+        let Some(step) = self.undo.pop() else {
+            return Ok(None);
+        };
+        let undone = step
+            .edits
+            .iter()
+            .rev()
+            .try_fold(self.current.clone(), |text, edit| {
+                edit.inverse().apply(&text)
+            });
+        self.finish_step(undone, step, |history, step| history.redo.push(step))
+    }
+
+    /// Redo the last step undone.
+    ///
+    /// # Returns
+    /// * `Ok(Some(String))` - The message with the step redone
+    /// * `Ok(None)` - There's nothing to redo
+    /// * `Err(String)` - The step doesn't fit the message; the history is
+    ///   cleared
+    fn redo(&mut self) -> Result<Option<String>, String> {
+        let Some(step) = self.redo.pop() else {
+            return Ok(None);
+        };
+        let redone = step
+            .edits
+            .iter()
+            .try_fold(self.current.clone(), |text, edit| edit.apply(&text));
+        self.finish_step(redone, step, |history, step| history.undo.push(step))
+    }
+
+    /// Take the message an undo or redo produced, moving its step to the
+    /// other stack, or clear the history if it couldn't be applied.
+    fn finish_step(
+        &mut self,
+        message: Option<String>,
+        step: UndoStep,
+        keep: impl FnOnce(&mut Self, UndoStep),
+    ) -> Result<Option<String>, String> {
+        self.merging_since = None;
+        match message {
+            Some(message) => {
+                self.current.clone_from(&message);
+                keep(self, step);
+                Ok(Some(message))
+            }
+            None => {
+                self.undo.clear();
+                self.redo.clear();
+                Err("The undo history no longer matches the message and was cleared".to_string())
+            }
+        }
+    }
+
+    /// Remember the message under a name, replacing any checkpoint of that name.
+    fn checkpoint(&mut self, name: &str) {
+        self.checkpoints
+            .retain(|checkpoint| checkpoint.name != name);
+        self.checkpoints.push(Checkpoint {
+            name: name.to_string(),
+            message: self.current.clone(),
+        });
+    }
+
+    /// Return to a checkpoint, as a step of its own.
+    ///
+    /// # Returns
+    /// * `Some(String)` - The message at the checkpoint
+    /// * `None` - There's no checkpoint of that name
+    fn revert_to(&mut self, name: &str, now: Instant) -> Option<String> {
+        let message = self
+            .checkpoints
+            .iter()
+            .find(|checkpoint| checkpoint.name == name)?
+            .message
+            .clone();
+        self.record(&message, Some(format!("Revert to {name}")), false, now);
+        Some(message)
+    }
+
+    /// What can be undone and redone.
+    fn state(&self) -> HistoryState {
+        HistoryState {
+            undo_depth: self.undo.len(),
+            redo_depth: self.redo.len(),
+            undo_label: self.undo.last().and_then(|step| step.label.clone()),
+            redo_label: self.redo.last().and_then(|step| step.label.clone()),
+            checkpoints: self
+                .checkpoints
+                .iter()
+                .map(|checkpoint| checkpoint.name.clone())
+                .collect(),
+        }
+    }
+}
+
+/// What can be undone and redone in the editor.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct HistoryState {
+    /// Steps that can be undone
+    #[serde(rename = "undoDepth")]
+    pub undo_depth: usize,
+    /// Steps that can be redone
+    #[serde(rename = "redoDepth")]
+    pub redo_depth: usize,
+    /// What the next undo would undo, if the step has a label
+    #[serde(rename = "undoLabel")]
+    pub undo_label: Option<String>,
+    /// What the next redo would redo, if the step has a label
+    #[serde(rename = "redoLabel")]
+    pub redo_label: Option<String>,
+    /// Names of the checkpoints that can be returned to, oldest first
+    pub checkpoints: Vec<String>,
+}
+
+/// The result of undoing, redoing, or returning to a checkpoint.
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryChange {
+    /// The message to show, or `None` if there was nothing to do
+    pub message: Option<String>,
+    /// What can be undone and redone now
+    pub state: HistoryState,
+}
+
+/// The undo histories of the documents open in the editor, kept in the app
+/// state.
+#[derive(Debug, Default)]
+pub struct UndoHistory {
+    histories: Mutex<HashMap<DocumentId, DocumentHistory>>,
+}
+
+impl UndoHistory {
+    /// Run something against a document's history and show the result in the
+    /// menu. A document without a history yet starts one from `message`.
+    fn update<T>(
+        &self,
+        state: &AppData,
+        document_id: DocumentId,
+        message: &str,
+        f: impl FnOnce(&mut DocumentHistory) -> T,
+    ) -> (T, HistoryState) {
+        let mut histories = self.histories.lock().expect("can write undo history");
+        let history = histories
+            .entry(document_id)
+            .or_insert_with(|| DocumentHistory::new(message));
+        let result = f(history);
+        let current = history.state();
+        drop(histories);
+        show_in_menu(state, &current);
+        (result, current)
+    }
+
+    /// Run something against a document's history, catching it up with the
+    /// editor's message first, and show the result in the menu.
+    fn with_history<T>(
+        &self,
+        state: &AppData,
+        document_id: DocumentId,
+        message: &str,
+        f: impl FnOnce(&mut DocumentHistory) -> T,
+    ) -> (T, HistoryState) {
+        self.update(state, document_id, message, |history| {
+            history.record(message, None, false, Instant::now());
+            f(history)
+        })
+    }
+
+    /// Show a document's history in the menu, for when it becomes the active
+    /// document.
+    pub(crate) fn show(&self, state: &AppData, document_id: DocumentId) {
+        let current = self
+            .histories
+            .lock()
+            .expect("can read undo history")
+            .get(&document_id)
+            .map(DocumentHistory::state)
+            .unwrap_or_default();
+        show_in_menu(state, &current);
+    }
+
+    /// Forget the history of a closed document.
+    pub(crate) fn forget(&self, document_id: DocumentId) {
+        self.histories
+            .lock()
+            .expect("can write undo history")
+            .remove(&document_id);
+    }
+}
+
+/// A file's undo history, as saved in the history store.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SavedHistory {
+    path: String,
+    #[serde(flatten)]
+    history: DocumentHistory,
+}
+
+/// Read the saved undo histories, most recently saved last.
+fn read_saved(app: &AppHandle, data_root: &std::path::Path) -> Result<Vec<SavedHistory>, String> {
+    let store = app
+        .store(data_root.join(HISTORY_STORE))
+        .map_err(|e| format!("Failed to open undo history: {e}"))?;
+    match store.get(UNDO_KEY) {
+        Some(saved) => serde_json::from_value(saved)
+            .map_err(|e| format!("Failed to parse saved undo history: {e}")),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// Enable the Undo and Redo menu items and name them after their steps.
+fn show_in_menu(state: &AppData, history: &HistoryState) {
+    let items = [
+        (
+            &state.undo_menu_item,
+            "&Undo",
+            history.undo_depth,
+            &history.undo_label,
+        ),
+        (
+            &state.redo_menu_item,
+            "&Redo",
+            history.redo_depth,
+            &history.redo_label,
+        ),
+    ];
+    for (item, text, depth, label) in items {
+        let text = match label {
+            Some(label) => format!("{text} {label}"),
+            None => text.to_string(),
+        };
+        if let Err(e) = item
+            .set_text(text)
+            .and_then(|()| item.set_enabled(depth > 0))
+        {
+            log::warn!("Failed to update the undo menu: {e}");
+        }
+    }
+}
+
+/// Start a new undo history for a message just opened or created.
+///
+/// If the message was opened from a file whose history was saved, and the
+/// file hasn't changed since, that history is restored.
+///
+/// # Arguments
+/// * `document_id` - The document whose history this is
+/// * `message` - The message now in the editor
+/// * `file_path` - Path of the file it was opened from, if any
+///
+/// # Returns
+/// * `Ok(HistoryState)` - What can be undone and redone
+/// * `Err(String)` - Never; a saved history that can't be read is logged and
+///   ignored
+#[tauri::command]
+pub async fn reset_undo_history(
+    document_id: DocumentId,
+    message: String,
+    file_path: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppData>,
+) -> Result<HistoryState, String> {
+    let restored = match file_path.as_deref() {
+        Some(path) => {
+            let data_root = state.data_root.lock().await.path.clone();
+            read_saved(&app, &data_root)
+                .unwrap_or_else(|e| {
+                    log::warn!("{e}");
+                    Vec::new()
+                })
+                .into_iter()
+                .rev()
+                .find(|saved| saved.path == path && saved.history.current == message)
+                .map(|saved| saved.history)
+        }
+        None => None,
+    };
+
+    let ((), current) = state
+        .undo_history
+        .update(&state, document_id, &message, |history| {
+            *history = restored.unwrap_or_else(|| DocumentHistory::new(&message));
+        });
+    Ok(current)
+}
+
+/// Record a change to the message in the editor.
+///
+/// # Arguments
+/// * `document_id` - The document whose history this is
+/// * `message` - The message after the change
+/// * `label` - What the change did (e.g., "Typing", "Delete Segment")
+/// * `coalesce` - Whether the change may be merged with the step before, for
+///   typing
+///
+/// # Returns
+/// What can be undone and redone after the change
+#[tauri::command]
+pub fn record_edit(
+    document_id: DocumentId,
+    message: &str,
+    label: Option<String>,
+    coalesce: bool,
+    state: State<'_, AppData>,
+) -> HistoryState {
+    let ((), current) = state
+        .undo_history
+        .update(&state, document_id, message, |history| {
+            history.record(message, label, coalesce, Instant::now());
+        });
+    current
+}
+
+/// Undo the last step.
+///
+/// # Arguments
+/// * `document_id` - The document whose history this is
+/// * `message` - The message as the editor has it
+///
+/// # Returns
+/// * `Ok(HistoryChange)` - The message with the step undone, if there was one
+/// * `Err(String)` - The history didn't match the message and was cleared
+#[tauri::command]
+pub fn undo_edit(
+    document_id: DocumentId,
+    message: &str,
+    state: State<'_, AppData>,
+) -> Result<HistoryChange, String> {
+    let (message, state) =
+        state
+            .undo_history
+            .with_history(&state, document_id, message, DocumentHistory::undo);
+    Ok(HistoryChange {
+        message: message?,
+        state,
+    })
+}
+
+/// Redo the last step undone.
+///
+/// # Arguments
+/// * `document_id` - The document whose history this is
+/// * `message` - The message as the editor has it
+///
+/// # Returns
+/// * `Ok(HistoryChange)` - The message with the step redone, if there was one
+/// * `Err(String)` - The history didn't match the message and was cleared
+#[tauri::command]
+pub fn redo_edit(
+    document_id: DocumentId,
+    message: &str,
+    state: State<'_, AppData>,
+) -> Result<HistoryChange, String> {
+    let (message, state) =
+        state
+            .undo_history
+            .with_history(&state, document_id, message, DocumentHistory::redo);
+    Ok(HistoryChange {
+        message: message?,
+        state,
+    })
+}
+
+/// Remember the message under a name, to return to later.
+///
+/// # Arguments
+/// * `document_id` - The document whose history this is
+/// * `name` - Name of the checkpoint (e.g., "validated"); an earlier
+///   checkpoint of the same name is replaced
+/// * `message` - The message as the editor has it
+///
+/// # Returns
+/// What can be undone and redone, and the checkpoints
+#[tauri::command]
+pub fn checkpoint_history(
+    document_id: DocumentId,
+    name: &str,
+    message: &str,
+    state: State<'_, AppData>,
+) -> HistoryState {
+    let ((), current) = state
+        .undo_history
+        .with_history(&state, document_id, message, |history| {
+            history.checkpoint(name);
+        });
+    current
+}
+
+/// Return the message to a checkpoint, as a step that can be undone.
+///
+/// # Arguments
+/// * `document_id` - The document whose history this is
+/// * `name` - Name of the checkpoint
+/// * `message` - The message as the editor has it
+///
+/// # Returns
+/// * `Ok(HistoryChange)` - The message at the checkpoint
+/// * `Err(String)` - There's no checkpoint of that name
+#[tauri::command]
+pub fn revert_to_checkpoint(
+    document_id: DocumentId,
+    name: &str,
+    message: &str,
+    state: State<'_, AppData>,
+) -> Result<HistoryChange, String> {
+    let (message, state) =
+        state
+            .undo_history
+            .with_history(&state, document_id, message, |history| {
+                history.revert_to(name, Instant::now())
+            });
+    let message = message.ok_or_else(|| format!("No checkpoint named {name}"))?;
+    Ok(HistoryChange {
+        message: Some(message),
+        state,
+    })
+}
+
+/// Save the undo history of a file just saved, to restore when it's reopened.
+///
+/// # Arguments
+/// * `document_id` - The document whose history this is
+/// * `file_path` - Path the message was saved to
+/// * `message` - The message as saved
+///
+/// # Returns
+/// * `Ok(())` - The history was saved
+/// * `Err(String)` - The history store couldn't be read or written
+#[tauri::command]
+pub async fn save_undo_history(
+    document_id: DocumentId,
+    file_path: String,
+    message: String,
+    app: AppHandle,
+    state: State<'_, AppData>,
+) -> Result<(), String> {
+    let (history, _) = state
+        .undo_history
+        .with_history(&state, document_id, &message, |history| history.clone());
+
+    let data_root = state.data_root.lock().await.path.clone();
+    let mut saved = read_saved(&app, &data_root).unwrap_or_else(|e| {
+        log::warn!("{e}");
+        Vec::new()
+    });
+    saved.retain(|saved| saved.path != file_path);
+    saved.push(SavedHistory {
+        path: file_path,
+        history,
+    });
+    if saved.len() > MAX_SAVED_HISTORIES {
+        saved.drain(..saved.len() - MAX_SAVED_HISTORIES);
+    }
+
+    let store = app
+        .store(data_root.join(HISTORY_STORE))
+        .map_err(|e| format!("Failed to open undo history: {e}"))?;
+    store.set(
+        UNDO_KEY,
+        serde_json::to_value(&saved)
+            .map_err(|e| format!("Failed to serialize undo history: {e}"))?,
+    );
+    store
+        .save()
+        .map_err(|e| format!("Failed to save undo history: {e}"))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edits_cover_only_what_changed() {
+        let edit = Edit::between("PID|1||DOE^JOHN", "PID|1||DOE^JANE").unwrap();
+        assert_eq!(edit.start, 12);
+        assert_eq!(edit.removed, "OH");
+        assert_eq!(edit.inserted, "AN");
+
+        let edit = Edit::between("NAME^MÜLLER", "NAME^MÖLLER").unwrap();
+        assert_eq!(edit.removed, "Ü");
+        assert_eq!(edit.inserted, "Ö");
+        assert_eq!(edit.apply("NAME^MÜLLER").unwrap(), "NAME^MÖLLER");
+        assert_eq!(edit.inverse().apply("NAME^MÖLLER").unwrap(), "NAME^MÜLLER");
+        assert_eq!(edit.apply("NAME^MILLER"), None);
+
+        let edit = Edit::between("aaa", "aaaa").unwrap();
+        assert_eq!(edit.apply("aaa").unwrap(), "aaaa");
+        assert!(Edit::between("same", "same").is_none());
+    }
+
+    #[test]
+    fn merges_typing_into_one_step() {
+        let start = Instant::now();
+        let mut history = DocumentHistory::new("PID|");
+        history.record("PID|D", Some("Typing".to_string()), true, start);
+        history.record("PID|DO", None, true, start + Duration::from_millis(200));
+        history.record("PID|DOE", None, true, start + Duration::from_millis(400));
+        history.record("PID|DOE^", None, true, start + Duration::from_secs(2));
+        history.record("PID|DOE^J", Some("Paste".to_string()), false, start);
+        assert_eq!(history.state().undo_depth, 3);
+        assert_eq!(history.state().undo_label.as_deref(), Some("Paste"));
+
+        assert_eq!(history.undo().unwrap().as_deref(), Some("PID|DOE^"));
+        assert_eq!(history.undo().unwrap().as_deref(), Some("PID|DOE"));
+        assert_eq!(history.undo().unwrap().as_deref(), Some("PID|"));
+        assert_eq!(history.undo().unwrap(), None);
+        assert_eq!(history.state().redo_depth, 3);
+        assert_eq!(history.state().redo_label.as_deref(), Some("Typing"));
+
+        assert_eq!(history.redo().unwrap().as_deref(), Some("PID|DOE"));
+        history.record("PID|DOE|F", None, false, start);
+        assert_eq!(history.state().redo_depth, 0);
+        assert_eq!(history.redo().unwrap(), None);
+    }
+
+    #[test]
+    fn reverting_to_a_checkpoint_can_be_undone() {
+        let now = Instant::now();
+        let mut history = DocumentHistory::new("MSH|^~\\&|A");
+        history.record("MSH|^~\\&|B", None, false, now);
+        history.checkpoint("validated");
+        history.record("MSH|^~\\&|C", None, false, now);
+        history.record("MSH|^~\\&|D", None, false, now);
+
+        assert_eq!(
+            history.revert_to("validated", now).as_deref(),
+            Some("MSH|^~\\&|B")
+        );
+        assert_eq!(
+            history.state().undo_label.as_deref(),
+            Some("Revert to validated")
+        );
+        assert_eq!(history.undo().unwrap().as_deref(), Some("MSH|^~\\&|D"));
+        assert_eq!(history.revert_to("missing", now), None);
+        assert_eq!(history.state().checkpoints, ["validated"]);
+    }
+
+    #[test]
+    fn clears_a_history_that_no_longer_fits() {
+        let mut history = DocumentHistory::new("abc");
+        history.record("abcd", None, false, Instant::now());
+        history.current = "xyz".to_string();
+        assert!(history.undo().is_err());
+        assert_eq!(history.state(), HistoryState::default());
+    }
+}
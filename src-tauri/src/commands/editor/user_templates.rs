@@ -0,0 +1,296 @@
+//! User-defined message templates kept in the data root.
+//!
+//! The built-in templates give a bare message of each type, but teams recreate
+//! the same test patients and orders over and over. Any message can be saved
+//! as a named template, which then shows up under "New from Template" >
+//! "My Templates" and in the template library.
+//!
+//! # Storage
+//!
+//! Templates are plain HL7 files in the `templates` folder of the data root,
+//! one per template, named after the template (`Test Patient Jones` is saved as
+//! `test-patient-jones.hl7`). The template's name and description are kept in
+//! the file's metadata header, so a template can be opened, edited, or copied
+//! to another machine like any other message file. Saving a template under an
+//! existing name replaces it.
+
+use serde::Serialize;
+use std::path::Path;
+use tauri::{AppHandle, State};
+
+use super::data::{get_message_trigger_event, get_message_type};
+use super::metadata::{get_document_metadata, set_document_metadata, DocumentMetadata};
+use crate::AppData;
+
+/// Folder under the data root that holds user templates.
+pub const TEMPLATES_DIR: &str = "templates";
+
+/// Extension of template and snippet files.
+pub(super) const LIBRARY_EXTENSION: &str = "hl7";
+
+/// A user template, as listed in the menu and the template library.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UserTemplateInfo {
+    /// Identifier (the file name without extension)
+    pub id: String,
+    /// Name the template was saved with
+    pub name: String,
+    /// What the template is for, if given
+    pub description: Option<String>,
+    /// Message type and trigger event (e.g., "ADT^A01"), if the message has them
+    pub message_type: Option<String>,
+}
+
+/// List the saved user templates, sorted by name.
+///
+/// # Arguments
+/// * `state` - Application state containing the data root
+///
+/// # Returns
+/// * `Ok(Vec<UserTemplateInfo>)` - The templates (empty if none have been saved)
+/// * `Err(String)` - The templates folder couldn't be read
+#[tauri::command]
+pub async fn list_user_templates(
+    state: State<'_, AppData>,
+) -> Result<Vec<UserTemplateInfo>, String> {
+    let directory = state.data_root.lock().await.path.join(TEMPLATES_DIR);
+    list_templates_in(&directory)
+}
+
+/// Save a message as a user template, replacing any template of the same name.
+///
+/// # Arguments
+/// * `name` - Name of the template
+/// * `message` - The message, with or without a metadata header
+/// * `description` - Optional description of what the template is for
+/// * `app` - Tauri app handle, for refreshing the menu
+/// * `state` - Application state containing the data root
+///
+/// # Returns
+/// * `Ok(UserTemplateInfo)` - The saved template
+/// * `Err(String)` - The name is empty or the file couldn't be written
+#[tauri::command]
+pub async fn save_user_template(
+    name: String,
+    message: String,
+    description: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppData>,
+) -> Result<UserTemplateInfo, String> {
+    let directory = state.data_root.lock().await.path.join(TEMPLATES_DIR);
+    let saved = save_template_in(&directory, &name, &message, description)?;
+    crate::menu::refresh_user_templates_menu(&app, &state, &directory)?;
+    Ok(saved)
+}
+
+/// Read a user template's message.
+///
+/// # Arguments
+/// * `id` - Identifier of the template
+/// * `state` - Application state containing the data root
+///
+/// # Returns
+/// * `Ok(String)` - The message, without the template's metadata header
+/// * `Err(String)` - The template doesn't exist or couldn't be read
+#[tauri::command]
+pub async fn load_user_template(id: String, state: State<'_, AppData>) -> Result<String, String> {
+    let directory = state.data_root.lock().await.path.join(TEMPLATES_DIR);
+    read_user_template(&directory, &id)
+}
+
+/// Delete a user template.
+///
+/// # Arguments
+/// * `id` - Identifier of the template
+/// * `app` - Tauri app handle, for refreshing the menu
+/// * `state` - Application state containing the data root
+///
+/// # Returns
+/// * `Ok(())` - The template was deleted
+/// * `Err(String)` - The template doesn't exist or couldn't be deleted
+#[tauri::command]
+pub async fn delete_user_template(
+    id: String,
+    app: AppHandle,
+    state: State<'_, AppData>,
+) -> Result<(), String> {
+    let directory = state.data_root.lock().await.path.join(TEMPLATES_DIR);
+    std::fs::remove_file(library_path(&directory, &id)?)
+        .map_err(|e| format!("Failed to delete template {id}: {e}"))?;
+    crate::menu::refresh_user_templates_menu(&app, &state, &directory)
+}
+
+/// Read a template's message from a templates folder, without its metadata header.
+pub fn read_user_template(directory: &Path, id: &str) -> Result<String, String> {
+    let content = std::fs::read_to_string(library_path(directory, id)?)
+        .map_err(|e| format!("Failed to read template {id}: {e}"))?;
+    Ok(get_document_metadata(&content).message)
+}
+
+/// Read the templates in a folder, sorted by name.
+///
+/// A folder that doesn't exist yet holds no templates.
+pub fn list_templates_in(directory: &Path) -> Result<Vec<UserTemplateInfo>, String> {
+    if !directory.exists() {
+        return Ok(Vec::new());
+    }
+    let entries = std::fs::read_dir(directory)
+        .map_err(|e| format!("Failed to read templates folder: {e}"))?;
+
+    let mut templates: Vec<UserTemplateInfo> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == LIBRARY_EXTENSION))
+        .filter_map(|path| {
+            let id = path.file_stem()?.to_str()?.to_string();
+            let content = match std::fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(e) => {
+                    log::warn!("skipping unreadable template {}: {e}", path.display());
+                    return None;
+                }
+            };
+            Some(template_info(id, &content))
+        })
+        .collect();
+    templates.sort_by(|a, b| {
+        a.name
+            .to_lowercase()
+            .cmp(&b.name.to_lowercase())
+            .then_with(|| a.id.cmp(&b.id))
+    });
+    Ok(templates)
+}
+
+/// Write a template file into a folder, creating the folder if needed.
+fn save_template_in(
+    directory: &Path,
+    name: &str,
+    message: &str,
+    description: Option<String>,
+) -> Result<UserTemplateInfo, String> {
+    let name = name.trim();
+    let id = library_id(name)?;
+    std::fs::create_dir_all(directory)
+        .map_err(|e| format!("Failed to create templates folder: {e}"))?;
+
+    // the template's own header replaces any the message carried
+    let body = get_document_metadata(message).message;
+    let metadata = DocumentMetadata {
+        title: Some(name.to_string()),
+        notes: description.filter(|description| !description.trim().is_empty()),
+        ..DocumentMetadata::default()
+    };
+    let content = set_document_metadata(&body, metadata);
+    std::fs::write(library_path(directory, &id)?, &content)
+        .map_err(|e| format!("Failed to write template {name}: {e}"))?;
+    Ok(template_info(id, &content))
+}
+
+/// Describe a template from its file content.
+fn template_info(id: String, content: &str) -> UserTemplateInfo {
+    let document = get_document_metadata(content);
+    let message_type = get_message_type(&document.message).map(|message_type| {
+        match get_message_trigger_event(&document.message) {
+            Some(trigger_event) => format!("{message_type}^{trigger_event}"),
+            None => message_type,
+        }
+    });
+    UserTemplateInfo {
+        name: document.metadata.title.unwrap_or_else(|| id.clone()),
+        description: document.metadata.notes,
+        message_type,
+        id,
+    }
+}
+
+/// Derive a template's or snippet's identifier from its name.
+///
+/// Letters and digits are kept, lowercased, and every other run of characters
+/// becomes a single dash, so the identifier is safe as a file name and a menu id.
+pub(super) fn library_id(name: &str) -> Result<String, String> {
+    let mut id = String::new();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            id.push(c.to_ascii_lowercase());
+        } else if !id.is_empty() && !id.ends_with('-') {
+            id.push('-');
+        }
+    }
+    let id = id.trim_end_matches('-').to_string();
+    if id.is_empty() {
+        return Err(format!(
+            "Name must contain at least one letter or digit, got \"{name}\""
+        ));
+    }
+    Ok(id)
+}
+
+/// Path of a template or snippet file, refusing identifiers that could leave
+/// the folder.
+pub(super) fn library_path(directory: &Path, id: &str) -> Result<std::path::PathBuf, String> {
+    if id.is_empty()
+        || !id
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+    {
+        return Err(format!("Invalid identifier \"{id}\""));
+    }
+    Ok(directory.join(format!("{id}.{LIBRARY_EXTENSION}")))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use crate::commands::temp_dir;
+
+    const MESSAGE: &str =
+        "MSH|^~\\&|HERMES|TEST|||20250101||ADT^A01|1|P|2.5.1\rPID|1||123||JONES^ALICE";
+
+    #[test]
+    fn derives_identifiers_from_names() {
+        assert_eq!(
+            library_id("Test Patient: Jones (2)").unwrap(),
+            "test-patient-jones-2"
+        );
+        assert_eq!(library_id("  ORU / glucose ").unwrap(), "oru-glucose");
+        assert!(library_id("  ---  ").is_err());
+        assert!(library_path(Path::new("."), "../settings").is_err());
+    }
+
+    #[test]
+    fn saves_lists_and_replaces_templates() {
+        let directory = temp_dir("templates");
+        assert!(list_templates_in(&directory).unwrap().is_empty());
+
+        let saved = save_template_in(
+            &directory,
+            "Test Patient Jones",
+            &format!("# title: Old\n{MESSAGE}"),
+            Some("Admits Alice Jones".to_string()),
+        )
+        .unwrap();
+        assert_eq!(saved.id, "test-patient-jones");
+        assert_eq!(saved.message_type.as_deref(), Some("ADT^A01"));
+        save_template_in(&directory, "Another", MESSAGE, None).unwrap();
+
+        // saving under the same name replaces the template
+        let replaced = MESSAGE.replace("ALICE", "BOB");
+        save_template_in(&directory, "test patient jones", &replaced, None).unwrap();
+
+        let templates = list_templates_in(&directory).unwrap();
+        let names: Vec<_> = templates.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["Another", "test patient jones"]);
+        assert_eq!(templates[1].description, None);
+
+        // the template's header is stripped again when it's used
+        let content =
+            std::fs::read_to_string(library_path(&directory, "test-patient-jones").unwrap())
+                .unwrap();
+        assert!(content.starts_with("# title: test patient jones"));
+        assert_eq!(get_document_metadata(&content).message, replaced);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
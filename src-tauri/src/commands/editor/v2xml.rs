@@ -0,0 +1,511 @@
+//! HL7 v2.xml conversion commands.
+//!
+//! Converts messages to and from the XML encoding of HL7 v2 defined by the
+//! v2.xml schemas, for systems and tools that exchange v2 messages as XML.
+//!
+//! # Encoding
+//!
+//! Element names come from the message and the spec rather than from indices:
+//!
+//! ```xml
+//! <ADT_A01 xmlns="urn:hl7-org:v2xml">
+//!   <MSH>
+//!     <MSH.1>|</MSH.1>
+//!     <MSH.2>^~\&amp;</MSH.2>
+//!     <MSH.9>
+//!       <MSG.1>ADT</MSG.1>
+//!       <MSG.2>A01</MSG.2>
+//!       <MSG.3>ADT_A01</MSG.3>
+//!     </MSH.9>
+//!   </MSH>
+//!   <PID>
+//!     <PID.5>
+//!       <XPN.1>
+//!         <FN.1>DOE</FN.1>
+//!       </XPN.1>
+//!       <XPN.2>JOHN</XPN.2>
+//!     </PID.5>
+//!   </PID>
+//! </ADT_A01>
+//! ```
+//!
+//! - The root element is the message structure (MSH.9.3), or the message type
+//!   and trigger event joined with `_` when MSH.9.3 is empty.
+//! - Fields are `SEG.n`, and each repetition is another element with the same
+//!   name.
+//! - Components of a composite field are named after its data type (`XPN.2`),
+//!   and subcomponents after the component's data type (`FN.1`). Where the spec
+//!   doesn't know the data type, as in Z-segments, the field name is extended
+//!   instead (`ZPI.2.1`).
+//! - Text holds the decoded value: escape sequences are resolved when exporting
+//!   and delimiters are escaped again when importing.
+//!
+//! # Limitations
+//!
+//! Exported segments sit directly under the root element; the segment groups
+//! of the message structure (`PATIENT`, `ORDER_OBSERVATION`, ...) are not
+//! produced. Importing accepts documents with groups, and any namespace prefix,
+//! by walking down to the segment elements.
+
+use super::escape::{escape_text, unescape_text};
+use super::import::{
+    separators_from_encoding_chars, tree_to_message, MessageImport, SegmentImport,
+};
+use crate::spec::std_spec::{
+    component_datatype, field_datatype, get_version_with_fallback, is_composite_datatype,
+};
+use hl7_parser::message::{Component, Message, Repeat, Segment, Separators};
+use indexmap::IndexMap;
+use quick_xml::escape::escape;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde_json::{Map, Value};
+
+/// Namespace of v2.xml documents.
+const V2XML_NAMESPACE: &str = "urn:hl7-org:v2xml";
+
+/// Root element used when the message type can't name one.
+const FALLBACK_ROOT: &str = "HL7Message";
+
+/// Exports an HL7 message to HL7 v2.xml.
+#[tauri::command]
+pub fn export_to_xml(message: &str) -> Result<String, String> {
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(message)
+        .map_err(|e| format!("Failed to parse message: {e}"))?;
+    Ok(message_to_xml(&parsed))
+}
+
+/// Imports an HL7 message from HL7 v2.xml.
+#[tauri::command]
+pub fn import_from_xml(content: &str) -> Result<String, String> {
+    let document = parse_xml(content)?;
+    let import = document_to_import(&document)?;
+    tree_to_message(&import)
+}
+
+// ============================================================================
+// HL7 v2 -> XML
+// ============================================================================
+
+/// Converts a parsed HL7 message to a v2.xml document.
+fn message_to_xml(message: &Message) -> String {
+    let version = get_version_with_fallback(message);
+    let root = root_element(message);
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!("<{root} xmlns=\"{V2XML_NAMESPACE}\">\n"));
+    for segment in message.segments() {
+        write_segment(&mut xml, version, &message.separators, segment);
+    }
+    xml.push_str(&format!("</{root}>\n"));
+    xml
+}
+
+/// Name of the root element: the message structure, or type and trigger event.
+fn root_element(message: &Message) -> String {
+    let query = |path: &str| {
+        message
+            .query(path)
+            .map(|value| value.raw_value().trim().to_string())
+            .unwrap_or_default()
+    };
+
+    let structure = query("MSH.9.3");
+    let name = if structure.is_empty() {
+        [query("MSH.9.1"), query("MSH.9.2")]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("_")
+    } else {
+        structure
+    };
+
+    let name: String = name
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect();
+    if name.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        name
+    } else {
+        FALLBACK_ROOT.to_string()
+    }
+}
+
+/// Writes a segment element with its non-empty fields.
+fn write_segment(xml: &mut String, version: &str, separators: &Separators, segment: &Segment) {
+    let name = segment.name;
+    xml.push_str(&format!("  <{name}>\n"));
+
+    for (idx, field) in segment.fields.iter().enumerate() {
+        let number = idx + 1;
+        let element = format!("{name}.{number}");
+
+        // MSH.1 and MSH.2 are the delimiters themselves, not encoded values
+        if name == "MSH" && number <= 2 {
+            write_leaf(xml, 2, &element, field.raw_value());
+            continue;
+        }
+        if field.raw_value().is_empty() {
+            continue;
+        }
+
+        let datatype = field_datatype(version, name, number)
+            .filter(|datatype| is_composite_datatype(version, datatype));
+        for repeat in &field.repeats {
+            write_repeat(
+                xml,
+                version,
+                separators,
+                &element,
+                datatype.as_deref(),
+                repeat,
+            );
+        }
+    }
+
+    xml.push_str(&format!("  </{name}>\n"));
+}
+
+/// Writes one repetition of a field, breaking composites into components.
+fn write_repeat(
+    xml: &mut String,
+    version: &str,
+    separators: &Separators,
+    element: &str,
+    datatype: Option<&str>,
+    repeat: &Repeat,
+) {
+    if repeat.raw_value().is_empty() || (datatype.is_none() && repeat.components.len() <= 1) {
+        write_leaf(
+            xml,
+            2,
+            element,
+            &unescape_text(repeat.raw_value(), separators),
+        );
+        return;
+    }
+
+    xml.push_str(&format!("    <{element}>\n"));
+    for (idx, component) in repeat.components.iter().enumerate() {
+        if component.raw_value().is_empty() {
+            continue;
+        }
+        let number = idx + 1;
+        let (name, component_type) = match datatype {
+            Some(datatype) => (
+                format!("{datatype}.{number}"),
+                component_datatype(version, datatype, number)
+                    .filter(|datatype| is_composite_datatype(version, datatype)),
+            ),
+            None => (format!("{element}.{number}"), None),
+        };
+        write_component(xml, separators, &name, component_type.as_deref(), component);
+    }
+    xml.push_str(&format!("    </{element}>\n"));
+}
+
+/// Writes a component, breaking composites into subcomponents.
+fn write_component(
+    xml: &mut String,
+    separators: &Separators,
+    element: &str,
+    datatype: Option<&str>,
+    component: &Component,
+) {
+    if datatype.is_none() && component.subcomponents.len() <= 1 {
+        write_leaf(
+            xml,
+            3,
+            element,
+            &unescape_text(component.raw_value(), separators),
+        );
+        return;
+    }
+
+    xml.push_str(&format!("      <{element}>\n"));
+    for (idx, subcomponent) in component.subcomponents.iter().enumerate() {
+        if subcomponent.value.is_empty() {
+            continue;
+        }
+        let name = match datatype {
+            Some(datatype) => format!("{datatype}.{}", idx + 1),
+            None => format!("{element}.{}", idx + 1),
+        };
+        write_leaf(
+            xml,
+            4,
+            &name,
+            &unescape_text(subcomponent.value, separators),
+        );
+    }
+    xml.push_str(&format!("      </{element}>\n"));
+}
+
+/// Writes an element holding only text, indented by `depth` levels.
+fn write_leaf(xml: &mut String, depth: usize, element: &str, text: &str) {
+    let indent = "  ".repeat(depth);
+    let text = escape(text);
+    xml.push_str(&format!("{indent}<{element}>{text}</{element}>\n"));
+}
+
+// ============================================================================
+// XML -> HL7 v2
+// ============================================================================
+
+/// A parsed XML element.
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    children: Vec<Element>,
+    text: String,
+}
+
+/// Parse an XML document into a tree of elements under an unnamed root.
+///
+/// Text is kept untrimmed so leading and trailing spaces in values survive;
+/// only the text of elements without children is used.
+fn parse_xml(content: &str) -> Result<Element, String> {
+    let mut reader = Reader::from_str(content);
+    reader.config_mut().trim_text(false);
+
+    let mut stack = vec![Element::default()];
+    loop {
+        let event = reader
+            .read_event()
+            .map_err(|e| format!("Invalid XML at position {}: {e}", reader.error_position()))?;
+        match event {
+            Event::Start(start) => stack.push(Element {
+                name: String::from_utf8_lossy(start.local_name().as_ref()).to_string(),
+                ..Default::default()
+            }),
+            Event::Empty(start) => {
+                let element = Element {
+                    name: String::from_utf8_lossy(start.local_name().as_ref()).to_string(),
+                    ..Default::default()
+                };
+                if let Some(parent) = stack.last_mut() {
+                    parent.children.push(element);
+                }
+            }
+            Event::End(_) => {
+                let element = stack.pop().ok_or("Invalid XML: unbalanced tags")?;
+                let parent = stack.last_mut().ok_or("Invalid XML: unbalanced tags")?;
+                parent.children.push(element);
+            }
+            Event::Text(text) => {
+                let text = text
+                    .unescape()
+                    .map_err(|e| format!("Invalid XML text: {e}"))?;
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&text);
+                }
+            }
+            Event::CData(data) => {
+                if let Some(element) = stack.last_mut() {
+                    element
+                        .text
+                        .push_str(&String::from_utf8_lossy(&data.into_inner()));
+                }
+            }
+            Event::Eof => break,
+            Event::Comment(_) | Event::Decl(_) | Event::PI(_) | Event::DocType(_) => {}
+        }
+    }
+
+    let document = stack.pop().ok_or("Invalid XML: unbalanced tags")?;
+    if !stack.is_empty() {
+        return Err("Invalid XML: unclosed tags".to_string());
+    }
+    Ok(document)
+}
+
+/// Converts a v2.xml document to the import structure.
+fn document_to_import(document: &Element) -> Result<MessageImport, String> {
+    let mut segments = Vec::new();
+    collect_segments(document, &mut segments);
+    if segments.is_empty() {
+        return Err("The XML doesn't contain any HL7 segments".to_string());
+    }
+
+    // delimiters in values are escaped with the message's own encoding characters
+    let separators = segments
+        .iter()
+        .find(|segment| segment.name == "MSH")
+        .and_then(|msh| msh.children.iter().find(|child| child.name == "MSH.2"))
+        .map(|encoding| separators_from_encoding_chars(&encoding.text))
+        .unwrap_or_default();
+
+    Ok(MessageImport {
+        segments: segments
+            .into_iter()
+            .map(|segment| segment_to_import(segment, &separators))
+            .collect::<Result<_, _>>()?,
+    })
+}
+
+/// Collects segment elements in document order, looking inside groups.
+fn collect_segments<'e>(element: &'e Element, segments: &mut Vec<&'e Element>) {
+    for child in &element.children {
+        if is_segment_name(&child.name) {
+            segments.push(child);
+        } else {
+            collect_segments(child, segments);
+        }
+    }
+}
+
+/// Whether an element name is a segment name like `PID` or `ZP1`.
+fn is_segment_name(name: &str) -> bool {
+    name.len() == 3
+        && name.starts_with(|c: char| c.is_ascii_uppercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+/// Converts a segment element to the import structure.
+fn segment_to_import(segment: &Element, separators: &Separators) -> Result<SegmentImport, String> {
+    let prefix = format!("{}.", segment.name);
+    let mut fields: IndexMap<String, Value> = IndexMap::new();
+
+    for child in &segment.children {
+        let number = child
+            .name
+            .strip_prefix(&prefix)
+            .and_then(|number| number.parse::<usize>().ok())
+            .ok_or_else(|| {
+                format!(
+                    "Unexpected element <{}> in segment {}",
+                    child.name, segment.name
+                )
+            })?;
+
+        let value = if segment.name == "MSH" && number <= 2 {
+            Value::String(child.text.clone())
+        } else {
+            element_to_value(child, separators, 2)?
+        };
+
+        let key = number.to_string();
+        match fields.get_mut(&key) {
+            Some(Value::Array(repeats)) => repeats.push(value),
+            Some(existing) => {
+                let first = existing.take();
+                *existing = Value::Array(vec![first, value]);
+            }
+            None => {
+                fields.insert(key, value);
+            }
+        }
+    }
+
+    Ok(SegmentImport {
+        segment: segment.name.clone(),
+        fields,
+    })
+}
+
+/// Converts a field, component, or subcomponent element to an import value.
+///
+/// `levels` is how many more levels of nesting the element may have below it:
+/// two for fields (components and subcomponents), one for components.
+fn element_to_value(
+    element: &Element,
+    separators: &Separators,
+    levels: usize,
+) -> Result<Value, String> {
+    if element.children.is_empty() {
+        return Ok(if element.text.is_empty() {
+            Value::Null
+        } else {
+            Value::String(escape_text(&element.text, separators))
+        });
+    }
+    let levels = levels
+        .checked_sub(1)
+        .ok_or_else(|| format!("Element <{}> is nested too deeply", element.name))?;
+
+    let mut parts = Map::new();
+    for child in &element.children {
+        let number = child
+            .name
+            .rsplit_once('.')
+            .and_then(|(_, number)| number.parse::<usize>().ok())
+            .ok_or_else(|| format!("Unexpected element <{}> in <{}>", child.name, element.name))?;
+        parts.insert(
+            number.to_string(),
+            element_to_value(child, separators, levels)?,
+        );
+    }
+    Ok(Value::Object(parts))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str = "MSH|^~\\&|APP|FAC|||20231215||ADT^A01^ADT_A01|123|P|2.5.1\rPID|1||12345^^^MRN~67890^^^SSN||DOE^JOHN||19800101|M|||1 MAIN ST\\T\\APT 2^^CITY\rZPI|1|A^B";
+
+    #[test]
+    fn exports_schema_element_names() {
+        let xml = export_to_xml(MESSAGE).unwrap();
+        assert!(
+            xml.contains("<ADT_A01 xmlns=\"urn:hl7-org:v2xml\">"),
+            "{xml}"
+        );
+        assert!(xml.contains("<MSH.2>^~\\&amp;</MSH.2>"), "{xml}");
+        assert!(xml.contains("<MSG.1>ADT</MSG.1>"), "{xml}");
+        assert!(xml.contains("<FN.1>DOE</FN.1>"), "{xml}");
+        assert!(xml.contains("<XPN.2>JOHN</XPN.2>"), "{xml}");
+        assert!(xml.contains("<PID.8>M</PID.8>"), "{xml}");
+        // escape sequences are decoded, then escaped for XML
+        assert!(xml.contains("1 MAIN ST&amp;APT 2"), "{xml}");
+        // segments the spec doesn't know fall back to positional names
+        assert!(xml.contains("<ZPI.2.1>A</ZPI.2.1>"), "{xml}");
+        assert_eq!(xml.matches("<PID.3>").count(), 2);
+    }
+
+    #[test]
+    fn roundtrips_through_xml() {
+        let xml = export_to_xml(MESSAGE).unwrap();
+        let imported = import_from_xml(&xml).unwrap();
+        assert_eq!(imported, MESSAGE);
+    }
+
+    #[test]
+    fn imports_grouped_and_prefixed_documents() {
+        let xml = r#"<?xml version="1.0"?>
+<v2:ORU_R01 xmlns:v2="urn:hl7-org:v2xml">
+  <v2:MSH>
+    <v2:MSH.1>|</v2:MSH.1>
+    <v2:MSH.2>^~\&amp;</v2:MSH.2>
+    <v2:MSH.9><v2:MSG.1>ORU</v2:MSG.1><v2:MSG.2>R01</v2:MSG.2></v2:MSH.9>
+  </v2:MSH>
+  <v2:ORU_R01.PATIENT_RESULT>
+    <v2:ORU_R01.PATIENT>
+      <v2:PID><v2:PID.3><v2:CX.1>123</v2:CX.1></v2:PID.3></v2:PID>
+    </v2:ORU_R01.PATIENT>
+    <v2:ORU_R01.ORDER_OBSERVATION>
+      <v2:OBX>
+        <v2:OBX.1>1</v2:OBX.1>
+        <v2:OBX.5>5 &lt; 6 | 7</v2:OBX.5>
+      </v2:OBX>
+    </v2:ORU_R01.ORDER_OBSERVATION>
+  </v2:ORU_R01.PATIENT_RESULT>
+</v2:ORU_R01>"#;
+        let message = import_from_xml(xml).unwrap();
+        assert_eq!(
+            message,
+            "MSH|^~\\&|||||||ORU^R01\rPID|||123\rOBX|1||||5 < 6 \\F\\ 7"
+        );
+    }
+
+    #[test]
+    fn rejects_documents_without_segments() {
+        assert!(import_from_xml("<root><child/></root>").is_err());
+        assert!(import_from_xml("<ADT_A01><MSH>").is_err());
+        assert!(import_from_xml("<ADT_A01><PID><XYZ>1</XYZ></PID></ADT_A01>").is_err());
+    }
+}
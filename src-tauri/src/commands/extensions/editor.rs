@@ -4,13 +4,14 @@
 //!
 //! - `editor/getMessage` - Get the current message in various formats
 //! - `editor/setMessage` - Replace the entire message
-//! - `editor/patchMessage` - Apply targeted patches to specific fields
+//! - `editor/patchMessage` - Apply targeted patches to specific fields and segments
 //!
 //! The handlers reuse existing export/import functionality where possible and
 //! communicate with the frontend via Tauri events.
 
 use crate::commands::editor::export::{export_to_json, export_to_toml, export_to_yaml};
 use crate::commands::editor::import::{import_from_json, import_from_toml, import_from_yaml};
+use crate::commands::{duplicate_segment, move_segment_to};
 use crate::extensions::protocol::RpcError;
 use crate::extensions::types::{
     GetMessageResult, MessageFormat, Patch, PatchError, PatchMessageResult, SetMessageParams,
@@ -87,7 +88,8 @@ pub fn handle_set_message(
 /// Handle `editor/patchMessage` request from an extension.
 ///
 /// Applies a list of patches to the HL7 message. Each patch targets a specific
-/// HL7 path (e.g., "PID.5.1", "OBX[2].5") and can set, clear, or remove fields.
+/// HL7 path (e.g., "PID.5.1", "OBX[2].5") and can set, clear, or remove fields,
+/// or create, remove, duplicate, or move segments.
 ///
 /// Uses best-effort semantics: patches are applied in order, failures are recorded
 /// but don't stop subsequent patches from being attempted.
@@ -164,6 +166,22 @@ fn apply_patch(message: &str, patch: &Patch) -> Result<String, String> {
         return remove_segment(message, &query.segment, query.segment_index);
     }
 
+    // handle segment duplication and moves, by the segment's name and occurrence
+    if patch.duplicate == Some(true) && query.field.is_none() {
+        return duplicate_segment(message, patch.path.as_str().into())
+            .map(|result| result.message)
+            .ok_or_else(|| format!("Cannot duplicate segment '{}'", patch.path));
+    }
+    if let (Some(position), None) = (&patch.move_to, query.field) {
+        return move_segment_to(
+            message,
+            patch.path.as_str().into(),
+            position.as_str().into(),
+        )
+        .map(|result| result.message)
+        .ok_or_else(|| format!("Cannot move segment '{}' to '{position}'", patch.path));
+    }
+
     // for field operations, we need a field number
     let field_num = query.field.ok_or_else(|| {
         format!(
@@ -543,6 +561,8 @@ mod tests {
             value: Some("SMITH".to_string()),
             remove: None,
             create: None,
+            duplicate: None,
+            move_to: None,
         }];
         let message = "MSH|^~\\&|APP|FAC|||20231215||ADT^A01|123|P|2.5.1\rPID|||12345||DOE^JOHN";
         let (new_msg, result) = handle_patch_message(message, patches);
@@ -559,12 +579,16 @@ mod tests {
                 value: Some("SMITH".to_string()),
                 remove: None,
                 create: None,
+                duplicate: None,
+                move_to: None,
             },
             Patch {
                 path: "XYZ.1".to_string(), // invalid segment
                 value: Some("VALUE".to_string()),
                 remove: None,
                 create: None,
+                duplicate: None,
+                move_to: None,
             },
         ];
         let message = "MSH|^~\\&|APP|FAC|||20231215||ADT^A01|123|P|2.5.1\rPID|||12345||DOE^JOHN";
@@ -576,6 +600,32 @@ mod tests {
         assert!(new_msg.contains("SMITH")); // first patch still applied
     }
 
+    #[test]
+    fn test_patch_duplicates_and_moves_segments() {
+        let patch = |path: &str, duplicate: Option<bool>, move_to: Option<&str>| Patch {
+            path: path.to_string(),
+            value: None,
+            remove: None,
+            create: None,
+            duplicate,
+            move_to: move_to.map(ToString::to_string),
+        };
+        let message = "MSH|^~\\&|APP|FAC|||20231215||ORU^R01|123|P|2.5.1\rOBX|1|ST|A\rOBX|2|ST|B\rNTE|1||note";
+        let patches = vec![
+            patch("OBX[2]", Some(true), None),
+            patch("NTE", None, Some("OBX[1]")),
+            patch("MSH", Some(true), None),
+        ];
+        let (new_msg, result) = handle_patch_message(message, patches);
+        assert_eq!(result.patches_applied, 2);
+        assert_eq!(
+            new_msg,
+            "MSH|^~\\&|APP|FAC|||20231215||ORU^R01|123|P|2.5.1\rNTE|1||note\rOBX|1|ST|A\rOBX|2|ST|B\rOBX|2|ST|B"
+        );
+        let errors = result.errors.unwrap();
+        assert_eq!(errors[0].index, 2);
+    }
+
     #[test]
     fn test_validate_hl7_structure() {
         assert!(validate_hl7_structure("MSH|^~\\&|APP").is_ok());
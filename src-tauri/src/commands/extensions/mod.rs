@@ -3,21 +3,39 @@
 //! These commands provide the interface between the frontend and the extension
 //! host, allowing the UI to:
 //!
-//! - Query extension status and toolbar buttons
+//! - Query extension status, toolbar buttons, and transports
+//! - Read the protocol trace of an extension in development mode
 //! - Execute extension commands (triggered by toolbar button clicks)
+//! - Generate messages from the templates extensions contribute
+//! - Change the values of the settings extensions declare
 //! - Reload extensions after configuration changes
+//! - Run the conformance suite against an extension under development
+//! - Install, update, and uninstall extensions from a registry ([`registry`])
 //! - Provide responses from the frontend for async editor operations
 //!
 //! Extension-to-Hermes requests (editor/*, ui/*) are handled internally by
 //! the extension host and don't require separate Tauri commands.
 
 pub mod editor;
+mod registry;
 pub mod ui;
 
-use crate::extensions::host::{ExtensionStatus, ToolbarButtonInfo};
-use crate::extensions::types::{ExtensionConfig, ExtensionLog, MessageEvent};
+pub use registry::*;
+
+use crate::commands::DocumentId;
+use crate::extensions::conformance::{run_conformance_suite, ConformanceReport};
+use crate::extensions::host::{ExtensionStatus, ToolbarButtonInfo, TransportInfo};
+use crate::extensions::types::{
+    ExtensionConfig, ExtensionLogPage, LogLevel, MessageEvent, TemplateGenerateParams,
+    TemplateGenerateResult, TraceEntry,
+};
 use crate::AppData;
-use tauri::State;
+use std::time::Duration;
+use tauri::{AppHandle, State};
+use tokio::time::timeout;
+
+/// How long an extension has to generate a message from a template.
+const TEMPLATE_TIMEOUT: Duration = Duration::from_secs(30);
 
 /// Get status information for all extensions.
 ///
@@ -41,24 +59,62 @@ pub async fn get_extension_toolbar_buttons(
     Ok(host.get_toolbar_buttons().to_vec())
 }
 
+/// Get the transports provided by running extensions.
+///
+/// Returns each transport with the extension that provides it, for choosing a
+/// transport in a connection preset.
+#[tauri::command]
+pub async fn get_extension_transports(
+    state: State<'_, AppData>,
+) -> Result<Vec<TransportInfo>, String> {
+    let host = state.extension_host.lock().await;
+    Ok(host.get_transports().await)
+}
+
 /// Get log entries for a specific extension.
 ///
-/// Returns the recent log entries (up to 100) for the specified extension.
+/// Returns a page of the extension's recent log entries, newest first, with
+/// the number of entries matching the filter across all pages.
+///
+/// # Arguments
+///
+/// * `extension_id` - ID of the extension
+/// * `level` - Least severe level to include (e.g., `warn` for warnings and
+///   errors), or None for every level
+/// * `offset` - Number of the newest matching entries to skip
+/// * `limit` - Most entries to return, or None for all of them
 #[tauri::command]
 pub async fn get_extension_logs(
     extension_id: String,
+    level: Option<LogLevel>,
+    offset: Option<usize>,
+    limit: Option<usize>,
     state: State<'_, AppData>,
-) -> Result<Vec<ExtensionLog>, String> {
+) -> Result<ExtensionLogPage, String> {
     let host = state.extension_host.lock().await;
-    host.get_extension_logs(&extension_id)
+    host.get_extension_logs(&extension_id, level, offset.unwrap_or(0), limit)
         .await
         .ok_or_else(|| format!("extension not found: {extension_id}"))
 }
 
+/// Get the protocol trace of an extension in development mode.
+///
+/// Returns the latest messages (up to 1000) exchanged with the extension,
+/// oldest first. Later messages arrive in `extension-trace` events.
+#[tauri::command]
+pub async fn get_extension_trace(
+    extension_id: String,
+    state: State<'_, AppData>,
+) -> Result<Vec<TraceEntry>, String> {
+    let host = state.extension_host.lock().await;
+    host.get_extension_trace(&extension_id).await
+}
+
 /// Reload all extensions.
 ///
 /// Shuts down existing extensions and restarts them with the provided configuration.
-/// After reloading, merges extension schema overrides and updates the SchemaCache.
+/// After reloading, merges extension schema overrides and updates the SchemaCache,
+/// and lists the templates the extensions contribute under "New from Template".
 /// This is typically called after the user modifies extension settings, or on app
 /// startup when settings are loaded from disk.
 ///
@@ -70,12 +126,43 @@ pub async fn get_extension_logs(
 #[tauri::command]
 pub async fn reload_extensions(
     configs: Vec<ExtensionConfig>,
+    app: AppHandle,
     state: State<'_, AppData>,
 ) -> Result<(), String> {
-    let mut host = state.extension_host.lock().await;
-    host.reload(configs, &state.window_manager, &state.schema)
-        .await
-        .map_err(|e| e.to_string())
+    let templates = {
+        let mut host = state.extension_host.lock().await;
+        host.reload(configs, &state.window_manager, &state.schema)
+            .await
+            .map_err(|e| e.to_string())?;
+        host.get_templates().await
+    };
+    crate::menu::refresh_extension_templates_menu(&app, &state, templates).await
+}
+
+/// Run the conformance suite against an extension executable.
+///
+/// The extension is spawned separately from the running extensions, so it can
+/// be tested without adding it to the settings first. The host is only locked
+/// long enough to read the data directory and version, since the suite can take
+/// several seconds against a misbehaving extension.
+///
+/// # Arguments
+///
+/// * `path` - Extension command, as it would be entered in the extension settings
+#[tauri::command]
+pub async fn test_extension(
+    path: String,
+    state: State<'_, AppData>,
+) -> Result<ConformanceReport, String> {
+    let (data_dir, hermes_version) = {
+        let host = state.extension_host.lock().await;
+        (host.extension_data_dir(), host.hermes_version().to_string())
+    };
+
+    std::fs::create_dir_all(&data_dir)
+        .map_err(|e| format!("failed to create extension data directory: {e}"))?;
+
+    Ok(run_conformance_suite(&path, &data_dir, &hermes_version).await)
 }
 
 /// Send a command notification to an extension.
@@ -92,30 +179,105 @@ pub async fn send_extension_command(
         .map_err(|e| e.to_string())
 }
 
-/// Sync the current editor message content from frontend to backend.
+/// Generate a message from a template an extension contributes.
+///
+/// Sends the extension a `template/generate` request with the template's
+/// command. The host is only locked long enough to find the extension, so one
+/// slow to generate the message doesn't block other extension traffic.
+///
+/// # Arguments
+///
+/// * `extension_id` - ID of the extension contributing the template
+/// * `command` - Command of the template, as declared by the extension
+///
+/// # Returns
+///
+/// * `Ok(String)` - The generated message, to open in the editor
+/// * `Err(String)` - If the extension isn't running or has no such template,
+///   or fails to generate the message in time
+#[tauri::command]
+pub async fn generate_extension_template(
+    extension_id: String,
+    command: String,
+    state: State<'_, AppData>,
+) -> Result<String, String> {
+    let sender = {
+        let host = state.extension_host.lock().await;
+        host.template_sender(&extension_id, &command).await
+    }
+    .ok_or_else(|| format!("No running extension provides the template '{command}'"))?;
+
+    let params = serde_json::to_value(TemplateGenerateParams { command })
+        .map_err(|e| format!("Failed to serialise template/generate params: {e}"))?;
+    let response = timeout(TEMPLATE_TIMEOUT, sender.send("template/generate", params))
+        .await
+        .map_err(|_| {
+            format!(
+                "The extension didn't generate the message within {} seconds",
+                TEMPLATE_TIMEOUT.as_secs()
+            )
+        })?
+        .map_err(|e| format!("Failed to generate the message: {e}"))?;
+    let result: TemplateGenerateResult = serde_json::from_value(response.result)
+        .map_err(|e| format!("Extension returned an invalid template/generate result: {e}"))?;
+    Ok(result.message)
+}
+
+/// Change the values of an extension's settings.
+///
+/// The values are checked against the settings the extension declared, and
+/// sent to it in a `configuration/changed` notification if it's running.
+///
+/// # Arguments
+/// * `extension_id` - ID of the extension
+/// * `settings` - Every value set, by key; a null value clears the setting
+///
+/// # Returns
+/// * `Ok(Map)` - The values as stored, to save in the extension's configuration
+/// * `Err(String)` - If the extension isn't loaded, or a value doesn't match
+///   the setting it's for
+#[tauri::command]
+pub async fn update_extension_settings(
+    extension_id: String,
+    settings: serde_json::Map<String, serde_json::Value>,
+    state: State<'_, AppData>,
+) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    let mut host = state.extension_host.lock().await;
+    host.update_settings(&extension_id, settings)
+        .await
+        .map_err(|e| format!("Failed to update extension settings: {e}"))
+}
+
+/// Sync a document's message content from frontend to backend.
 ///
-/// Called by the frontend whenever the message changes. Updates the stored editor
-/// state and triggers extension notifications based on the event type:
+/// Called by the frontend whenever the message changes. Updates the stored
+/// document and, if it's the active document, triggers extension notifications
+/// based on the event type:
 /// - No event: schedules a debounced `message/changed` notification
 /// - `opened`: sends immediate `message/opened` notification
 /// - `saved`: sends immediate `message/saved` notification
+/// - `segments_changed`: schedules a debounced `message/changed` notification
+///   that describes the segments changed
 #[tauri::command]
 pub async fn sync_editor_message(
+    document_id: DocumentId,
     message: String,
     file_path: Option<String>,
     event: Option<MessageEvent>,
     state: State<'_, AppData>,
 ) -> Result<(), String> {
-    // update stored message
-    {
-        let mut editor_msg = state.editor_message.lock().await;
-        *editor_msg = message;
-    }
-
-    // update stored file path
+    // update the stored document
     {
-        let mut path = state.editor_file_path.lock().await;
-        *path = file_path.clone();
+        let mut documents = state.documents.lock().await;
+        let document = documents
+            .get_mut(document_id)
+            .ok_or_else(|| format!("Document {document_id} isn't open"))?;
+        document.message = message;
+        document.file_path.clone_from(&file_path);
+        if documents.active_id() != Some(document_id) {
+            // extensions only follow the active document
+            return Ok(());
+        }
     }
 
     // notify extensions based on event type
@@ -130,9 +292,12 @@ pub async fn sync_editor_message(
                 host.notify_message_saved(path, save_as).await;
             }
         }
+        Some(MessageEvent::SegmentsChanged { changes }) => {
+            host.schedule_message_changed_notification(Some(changes));
+        }
         None => {
             // normal message change - schedule debounced notification
-            host.schedule_message_changed_notification();
+            host.schedule_message_changed_notification(None);
         }
     }
 
@@ -143,6 +308,7 @@ pub async fn sync_editor_message(
 #[allow(clippy::unwrap_used, clippy::indexing_slicing)]
 mod tests {
     use super::*;
+    use crate::extensions::types::ExtensionTransport;
 
     /// Test that ExtensionConfig deserialises correctly from the format
     /// produced by the TypeScript frontend (matching settings.ts).
@@ -180,6 +346,20 @@ mod tests {
         assert!(!config.enabled);
     }
 
+    /// Test that socket transports and their port deserialise, defaulting to stdio.
+    #[test]
+    fn test_extension_config_transport() {
+        let json = r#"{"path": "/usr/bin/ext", "transport": "websocket", "port": 9400}"#;
+        let config: ExtensionConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.transport, ExtensionTransport::WebSocket);
+        assert_eq!(config.port, Some(9400));
+
+        let json = r#"{"path": "/usr/bin/ext"}"#;
+        let config: ExtensionConfig = serde_json::from_str(json).unwrap();
+        assert_eq!(config.transport, ExtensionTransport::Stdio);
+        assert_eq!(config.port, None);
+    }
+
     /// Test deserialisation of an array of configs (as stored in settings.json).
     #[test]
     fn test_extension_config_array() {
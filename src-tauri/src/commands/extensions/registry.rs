@@ -0,0 +1,532 @@
+//! Installing extensions from a registry.
+//!
+//! Sharing an extension used to mean copying an executable to each machine and
+//! typing its path into the settings. A registry is a JSON index served over
+//! HTTPS, listing extensions and where to download them; Hermes downloads the
+//! one chosen into the data root, checks it against the index's checksum, and
+//! returns the configuration to add to the settings.
+//!
+//! # Index
+//!
+//! ```json
+//! {
+//!   "extensions": [
+//!     {
+//!       "id": "site-rules",
+//!       "name": "Site Rules",
+//!       "version": "1.2.0",
+//!       "description": "Checks messages against our interface spec",
+//!       "command": "python3 {path}",
+//!       "downloads": [
+//!         { "url": "https://example.org/site_rules.py", "sha256": "9f86d08..." }
+//!       ]
+//!     }
+//!   ]
+//! }
+//! ```
+//!
+//! A download with a `platform` (e.g., `linux-x86_64`, `macos-aarch64`,
+//! `windows-x86_64`) is only used on that platform; one without is used on any
+//! platform that has no download of its own, as for scripts. `command` runs the
+//! downloaded file, with `{path}` replaced by its path; without it, the file is
+//! run directly.
+//!
+//! # Storage
+//!
+//! Each extension is downloaded to its own folder under `installed-extensions`
+//! in the data root, keeping the same path across updates so the settings
+//! don't change. `installed.json` in that folder records what was installed
+//! from where, for updates and uninstalls.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::State;
+
+use crate::extensions::types::{ExtensionConfig, ExtensionTransport};
+use crate::AppData;
+
+/// Folder under the data root that holds installed extensions.
+pub const INSTALLED_EXTENSIONS_DIR: &str = "installed-extensions";
+
+/// File in [`INSTALLED_EXTENSIONS_DIR`] recording the installed extensions.
+const MANIFEST_FILE: &str = "installed.json";
+
+/// How long fetching the index or a download may take.
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Largest download accepted, in bytes.
+const MAX_DOWNLOAD_SIZE: usize = 200 * 1024 * 1024;
+
+/// An extension registry's index.
+#[derive(Debug, Clone, Deserialize)]
+struct RegistryIndex {
+    extensions: Vec<RegistryEntry>,
+}
+
+/// An extension listed in a registry's index.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RegistryEntry {
+    id: String,
+    name: String,
+    version: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    homepage: Option<String>,
+    /// Command to run the downloaded file, with `{path}` for its path
+    #[serde(default)]
+    command: Option<String>,
+    downloads: Vec<RegistryDownload>,
+}
+
+/// Where to download an extension for a platform.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RegistryDownload {
+    /// Platform the download is for (e.g., `linux-x86_64`), or None for any
+    #[serde(default)]
+    platform: Option<String>,
+    url: String,
+    /// SHA-256 of the file, in hex
+    sha256: String,
+    /// Name to save the file as, if not the last part of the URL
+    #[serde(default)]
+    file_name: Option<String>,
+}
+
+/// An extension available from a registry.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AvailableExtension {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    pub description: Option<String>,
+    pub homepage: Option<String>,
+    /// Version installed, if the extension is installed
+    pub installed_version: Option<String>,
+    /// Whether the registry has a download for this platform
+    pub supported: bool,
+}
+
+/// An extension installed from a registry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstalledExtension {
+    pub id: String,
+    pub name: String,
+    pub version: String,
+    /// Index the extension was installed from, for updates
+    pub index_url: String,
+    /// SHA-256 of the installed file
+    pub sha256: String,
+    /// Configuration to run the extension with
+    pub config: ExtensionConfig,
+}
+
+/// List the extensions a registry offers.
+///
+/// # Arguments
+/// * `index_url` - HTTPS URL of the registry's index
+/// * `state` - Application state containing the data root
+///
+/// # Returns
+/// * `Ok(Vec<AvailableExtension>)` - The extensions, sorted by name
+/// * `Err(String)` - If the index can't be fetched or read
+#[tauri::command]
+pub async fn list_available_extensions(
+    index_url: String,
+    state: State<'_, AppData>,
+) -> Result<Vec<AvailableExtension>, String> {
+    let index = fetch_index(&index_url).await?;
+    let directory = installed_dir(&state).await;
+    let installed: HashMap<String, String> = read_manifest(&directory)?
+        .into_iter()
+        .map(|extension| (extension.id, extension.version))
+        .collect();
+
+    let platform = current_platform();
+    let mut available: Vec<AvailableExtension> = index
+        .extensions
+        .into_iter()
+        .map(|entry| AvailableExtension {
+            supported: select_download(&entry.downloads, &platform).is_some(),
+            installed_version: installed.get(&entry.id).cloned(),
+            id: entry.id,
+            name: entry.name,
+            version: entry.version,
+            description: entry.description,
+            homepage: entry.homepage,
+        })
+        .collect();
+    available.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    Ok(available)
+}
+
+/// List the extensions installed from registries.
+///
+/// # Returns
+/// * `Ok(Vec<InstalledExtension>)` - The installed extensions
+/// * `Err(String)` - If the record of installed extensions can't be read
+#[tauri::command]
+pub async fn list_installed_extensions(
+    state: State<'_, AppData>,
+) -> Result<Vec<InstalledExtension>, String> {
+    read_manifest(&installed_dir(&state).await)
+}
+
+/// Download and install an extension from a registry.
+///
+/// Installing an extension that's already installed replaces it with the
+/// registry's version.
+///
+/// # Arguments
+/// * `index_url` - HTTPS URL of the registry's index
+/// * `extension_id` - ID of the extension in the index
+/// * `state` - Application state containing the data root
+///
+/// # Returns
+/// * `Ok(InstalledExtension)` - The installed extension, whose configuration
+///   is to be added to the settings
+/// * `Err(String)` - If the extension isn't in the index, has no download for
+///   this platform, or fails to download or verify
+#[tauri::command]
+pub async fn install_extension(
+    index_url: String,
+    extension_id: String,
+    state: State<'_, AppData>,
+) -> Result<InstalledExtension, String> {
+    let index = fetch_index(&index_url).await?;
+    let entry = index
+        .extensions
+        .into_iter()
+        .find(|entry| entry.id == extension_id)
+        .ok_or_else(|| format!("The registry has no extension '{extension_id}'"))?;
+    install(&installed_dir(&state).await, &index_url, entry).await
+}
+
+/// Update an installed extension from the registry it was installed from.
+///
+/// # Returns
+/// * `Ok(Some(InstalledExtension))` - The updated extension
+/// * `Ok(None)` - If the installed version is the registry's version
+/// * `Err(String)` - If the extension isn't installed, or the update fails
+#[tauri::command]
+pub async fn update_extension(
+    extension_id: String,
+    state: State<'_, AppData>,
+) -> Result<Option<InstalledExtension>, String> {
+    let directory = installed_dir(&state).await;
+    let installed = read_manifest(&directory)?
+        .into_iter()
+        .find(|extension| extension.id == extension_id)
+        .ok_or_else(|| format!("Extension '{extension_id}' isn't installed"))?;
+
+    let index = fetch_index(&installed.index_url).await?;
+    let entry = index
+        .extensions
+        .into_iter()
+        .find(|entry| entry.id == extension_id)
+        .ok_or_else(|| {
+            format!(
+                "The registry at {} no longer lists '{extension_id}'",
+                installed.index_url
+            )
+        })?;
+    if entry.version == installed.version {
+        return Ok(None);
+    }
+    install(&directory, &installed.index_url, entry)
+        .await
+        .map(Some)
+}
+
+/// Uninstall an extension installed from a registry, deleting its files.
+///
+/// # Returns
+/// * `Ok(InstalledExtension)` - The removed extension, whose configuration is
+///   to be removed from the settings
+/// * `Err(String)` - If the extension isn't installed or can't be deleted
+#[tauri::command]
+pub async fn uninstall_extension(
+    extension_id: String,
+    state: State<'_, AppData>,
+) -> Result<InstalledExtension, String> {
+    let directory = installed_dir(&state).await;
+    let mut manifest = read_manifest(&directory)?;
+    let position = manifest
+        .iter()
+        .position(|extension| extension.id == extension_id)
+        .ok_or_else(|| format!("Extension '{extension_id}' isn't installed"))?;
+    let removed = manifest.remove(position);
+
+    let folder = directory.join(&removed.id);
+    if folder.exists() {
+        std::fs::remove_dir_all(&folder)
+            .map_err(|e| format!("Failed to delete {}: {e}", folder.display()))?;
+    }
+    write_manifest(&directory, &manifest)?;
+    Ok(removed)
+}
+
+async fn installed_dir(state: &AppData) -> PathBuf {
+    state
+        .data_root
+        .lock()
+        .await
+        .path
+        .join(INSTALLED_EXTENSIONS_DIR)
+}
+
+/// Download an extension, verify it, and record it as installed.
+async fn install(
+    directory: &Path,
+    index_url: &str,
+    entry: RegistryEntry,
+) -> Result<InstalledExtension, String> {
+    check_id(&entry.id)?;
+    let download = select_download(&entry.downloads, &current_platform()).ok_or_else(|| {
+        format!(
+            "{} has no download for this platform ({})",
+            entry.name,
+            current_platform()
+        )
+    })?;
+    let file_name = match &download.file_name {
+        Some(file_name) => file_name.clone(),
+        None => url::Url::parse(&download.url)
+            .ok()
+            .and_then(|url| url.path_segments()?.next_back().map(str::to_string))
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| entry.id.clone()),
+    };
+    check_id(&file_name)?;
+
+    let bytes = fetch(&download.url).await?;
+    verify_checksum(&bytes, &download.sha256)
+        .map_err(|e| format!("{} failed verification: {e}", entry.name))?;
+
+    let folder = directory.join(&entry.id);
+    std::fs::create_dir_all(&folder)
+        .map_err(|e| format!("Failed to create {}: {e}", folder.display()))?;
+    let path = folder.join(&file_name);
+    // write beside the old file and rename, so a failed write keeps the old version
+    let partial = folder.join(format!("{file_name}.partial"));
+    std::fs::write(&partial, &bytes)
+        .map_err(|e| format!("Failed to write {}: {e}", partial.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&partial, std::fs::Permissions::from_mode(0o755))
+            .map_err(|e| format!("Failed to make {} executable: {e}", partial.display()))?;
+    }
+    std::fs::rename(&partial, &path)
+        .map_err(|e| format!("Failed to install {}: {e}", path.display()))?;
+
+    let installed = InstalledExtension {
+        config: ExtensionConfig {
+            path: launch_command(entry.command.as_deref(), &path),
+            args: Vec::new(),
+            env: HashMap::new(),
+            enabled: true,
+            settings: serde_json::Map::new(),
+            transport: ExtensionTransport::default(),
+            port: None,
+            dev_mode: false,
+            log_capacity: None,
+        },
+        id: entry.id,
+        name: entry.name,
+        version: entry.version,
+        index_url: index_url.to_string(),
+        sha256: download.sha256.to_lowercase(),
+    };
+
+    let mut manifest = read_manifest(directory)?;
+    manifest.retain(|extension| extension.id != installed.id);
+    manifest.push(installed.clone());
+    write_manifest(directory, &manifest)?;
+    log::info!(
+        "installed extension {} {} from {index_url}",
+        installed.id,
+        installed.version
+    );
+    Ok(installed)
+}
+
+async fn fetch_index(index_url: &str) -> Result<RegistryIndex, String> {
+    let bytes = fetch(index_url).await?;
+    serde_json::from_slice(&bytes)
+        .map_err(|e| format!("The registry index at {index_url} isn't valid: {e}"))
+}
+
+/// Fetch a file over HTTPS.
+async fn fetch(url: &str) -> Result<Vec<u8>, String> {
+    let parsed = url::Url::parse(url).map_err(|e| format!("Invalid URL '{url}': {e}"))?;
+    if parsed.scheme() != "https" {
+        return Err(format!(
+            "Registries are only fetched over HTTPS, not '{url}'"
+        ));
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(DOWNLOAD_TIMEOUT)
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {e}"))?;
+    let response = client
+        .get(parsed)
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .map_err(|e| format!("Failed to download {url}: {e}"))?;
+    if response
+        .content_length()
+        .is_some_and(|length| length > MAX_DOWNLOAD_SIZE as u64)
+    {
+        return Err(format!("{url} is too large to download"));
+    }
+    let bytes = response
+        .bytes()
+        .await
+        .map_err(|e| format!("Failed to download {url}: {e}"))?;
+    if bytes.len() > MAX_DOWNLOAD_SIZE {
+        return Err(format!("{url} is too large to download"));
+    }
+    Ok(bytes.to_vec())
+}
+
+fn read_manifest(directory: &Path) -> Result<Vec<InstalledExtension>, String> {
+    let path = directory.join(MANIFEST_FILE);
+    match std::fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to read {}: {e}", path.display())),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(format!("Failed to read {}: {e}", path.display())),
+    }
+}
+
+fn write_manifest(directory: &Path, manifest: &[InstalledExtension]) -> Result<(), String> {
+    std::fs::create_dir_all(directory)
+        .map_err(|e| format!("Failed to create {}: {e}", directory.display()))?;
+    let path = directory.join(MANIFEST_FILE);
+    let json = serde_json::to_string_pretty(manifest)
+        .map_err(|e| format!("Failed to serialise installed extensions: {e}"))?;
+    std::fs::write(&path, json).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+}
+
+/// The platform downloads are chosen for (e.g., `linux-x86_64`).
+fn current_platform() -> String {
+    format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH)
+}
+
+/// The download for a platform, falling back to one for any platform.
+fn select_download<'a>(
+    downloads: &'a [RegistryDownload],
+    platform: &str,
+) -> Option<&'a RegistryDownload> {
+    downloads
+        .iter()
+        .find(|download| download.platform.as_deref() == Some(platform))
+        .or_else(|| {
+            downloads
+                .iter()
+                .find(|download| download.platform.is_none())
+        })
+}
+
+/// Check a file's SHA-256 against the hex digest in the index.
+fn verify_checksum(bytes: &[u8], expected: &str) -> Result<(), String> {
+    let actual: String = Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    if actual.eq_ignore_ascii_case(expected.trim()) {
+        Ok(())
+    } else {
+        Err(format!("checksum {actual} doesn't match {expected}"))
+    }
+}
+
+/// Check an ID or file name from the index is safe to use as a file name.
+fn check_id(name: &str) -> Result<(), String> {
+    // each extension's folder sits beside the manifest, so an ID can't take
+    // its name (in any case, for case-insensitive file systems)
+    let valid = !name.is_empty()
+        && !name.starts_with('.')
+        && !name.eq_ignore_ascii_case(MANIFEST_FILE)
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("'{name}' can't be used as a file name"))
+    }
+}
+
+/// The command that runs a downloaded extension.
+fn launch_command(template: Option<&str>, path: &Path) -> String {
+    let quoted = shell_words::quote(&path.to_string_lossy()).into_owned();
+    match template {
+        Some(template) if template.contains("{path}") => template.replace("{path}", &quoted),
+        Some(template) => format!("{template} {quoted}"),
+        None => quoted,
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn selects_the_platform_download() {
+        let downloads: Vec<RegistryDownload> = serde_json::from_str(
+            r#"[
+                {"url": "https://example.org/ext", "sha256": "00"},
+                {"platform": "linux-x86_64", "url": "https://example.org/ext-linux", "sha256": "01"}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            select_download(&downloads, "linux-x86_64").unwrap().sha256,
+            "01"
+        );
+        assert_eq!(
+            select_download(&downloads, "windows-x86_64")
+                .unwrap()
+                .sha256,
+            "00"
+        );
+        assert!(select_download(&downloads[1..], "macos-aarch64").is_none());
+    }
+
+    #[test]
+    fn verifies_checksums() {
+        let sha256 = "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824";
+        assert!(verify_checksum(b"hello", sha256).is_ok());
+        assert!(verify_checksum(b"hello", &sha256.to_uppercase()).is_ok());
+        assert!(verify_checksum(b"hello!", sha256).is_err());
+    }
+
+    #[test]
+    fn builds_safe_paths_and_commands() {
+        assert!(check_id("site-rules_1.2").is_ok());
+        assert!(check_id("../evil").is_err());
+        assert!(check_id("a/b").is_err());
+        assert!(check_id(".hidden").is_err());
+        assert!(check_id("installed.json").is_err());
+        assert!(check_id("Installed.JSON").is_err());
+
+        let path = Path::new("/data/installed-extensions/site rules/rules.py");
+        assert_eq!(
+            launch_command(Some("python3 {path}"), path),
+            "python3 '/data/installed-extensions/site rules/rules.py'"
+        );
+        assert_eq!(launch_command(None, Path::new("/data/ext")), "/data/ext");
+    }
+}
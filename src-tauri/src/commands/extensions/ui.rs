@@ -10,18 +10,23 @@
 //! - `ui/openFiles` - Show file open dialog (multiple files)
 //! - `ui/saveFile` - Show file save dialog
 //! - `ui/selectDirectory` - Show directory selection dialog
+//! - `ui/setStatusBarItem` - Show, change, or remove text in the status bar
+//! - `ui/progress` - Report the progress of a long-running operation
 //!
 //! Also provides the `window/closed` notification for informing extensions
 //! when their windows are closed.
 
+use crate::events;
 use crate::extensions::protocol::RpcError;
 use crate::extensions::types::{
     CloseWindowParams, CloseWindowResult, ConfirmButtons, MessageKind, OpenFileParams,
     OpenFileResult, OpenFilesParams, OpenFilesResult, OpenWindowParams, OpenWindowResult,
-    SaveFileParams, SaveFileResult, SelectDirectoryParams, SelectDirectoryResult,
-    ShowConfirmParams, ShowConfirmResult, ShowMessageParams, ShowMessageResult, WindowClosedParams,
+    ProgressParams, ProgressResult, SaveFileParams, SaveFileResult, SelectDirectoryParams,
+    SelectDirectoryResult, SetStatusBarItemParams, SetStatusBarItemResult, ShowConfirmParams,
+    ShowConfirmResult, ShowMessageParams, ShowMessageResult, WindowClosedParams,
     WindowClosedReason,
 };
+use serde::Serialize;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tauri::{AppHandle, Emitter, Manager, WebviewUrl, WebviewWindowBuilder};
@@ -211,7 +216,7 @@ pub async fn handle_close_window(
             window_id: params.window_id,
             reason: WindowClosedReason::Extension,
         };
-        if let Err(e) = app.emit("extension-window-closed", (&ext_id, &params)) {
+        if let Err(e) = app.emit(events::EXTENSION_WINDOW_CLOSED, (&ext_id, &params)) {
             log::warn!("Failed to emit window-closed event: {e}");
         }
     }
@@ -243,7 +248,7 @@ pub async fn handle_window_closed(
         };
 
         // emit event that the host can listen for
-        if let Err(e) = app.emit("extension-window-closed", (&ext_id, &params)) {
+        if let Err(e) = app.emit(events::EXTENSION_WINDOW_CLOSED, (&ext_id, &params)) {
             log::warn!("Failed to emit window-closed event: {e}");
         }
     }
@@ -482,11 +487,106 @@ pub async fn handle_select_directory(
     })
 }
 
+// ============================================================================
+// Status bar handlers
+// ============================================================================
+
+/// A status bar item change, as emitted to the frontend.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusBarItemEvent {
+    /// ID of the extension that owns the item.
+    pub extension_id: String,
+    #[serde(flatten)]
+    pub item: SetStatusBarItemParams,
+}
+
+/// A progress report, as emitted to the frontend.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProgressEvent {
+    /// ID of the extension running the operation.
+    pub extension_id: String,
+    #[serde(flatten)]
+    pub progress: ProgressParams,
+}
+
+/// Handle `ui/setStatusBarItem` request from an extension.
+///
+/// Shows the item in the status bar, replacing the extension's item with the
+/// same ID, or removes it if it has no text.
+pub fn handle_set_status_bar_item(
+    app: &AppHandle,
+    ext_id: &str,
+    params: SetStatusBarItemParams,
+) -> Result<SetStatusBarItemResult, RpcError> {
+    if params.id.is_empty() {
+        return Err(RpcError::invalid_params("id must not be empty"));
+    }
+
+    let event = StatusBarItemEvent {
+        extension_id: ext_id.to_string(),
+        item: params,
+    };
+    app.emit(events::EXTENSION_STATUS_BAR_ITEM, &event)
+        .map_err(|e| RpcError::internal(format!("failed to emit event: {e}")))?;
+
+    Ok(SetStatusBarItemResult { success: true })
+}
+
+/// Handle `ui/progress` request from an extension.
+///
+/// Shows the operation's progress in the status bar until it reports `done`.
+pub fn handle_progress(
+    app: &AppHandle,
+    ext_id: &str,
+    params: ProgressParams,
+) -> Result<ProgressResult, RpcError> {
+    check_progress(&params)?;
+
+    let event = ProgressEvent {
+        extension_id: ext_id.to_string(),
+        progress: params,
+    };
+    app.emit(events::EXTENSION_PROGRESS, &event)
+        .map_err(|e| RpcError::internal(format!("failed to emit event: {e}")))?;
+
+    Ok(ProgressResult { success: true })
+}
+
+/// Check a progress report before showing it.
+fn check_progress(params: &ProgressParams) -> Result<(), RpcError> {
+    if params.id.is_empty() {
+        return Err(RpcError::invalid_params("id must not be empty"));
+    }
+    if let Some(percentage) = params.percentage.filter(|p| *p > 100) {
+        return Err(RpcError::invalid_params(format!(
+            "percentage must be between 0 and 100, not {percentage}"
+        )));
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used, clippy::indexing_slicing)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_check_progress() {
+        let progress: ProgressParams =
+            serde_json::from_str(r#"{"id": "import", "title": "Importing", "percentage": 40}"#)
+                .unwrap();
+        assert!(check_progress(&progress).is_ok());
+        assert!(!progress.done);
+
+        let progress: ProgressParams =
+            serde_json::from_str(r#"{"id": "import", "percentage": 140}"#).unwrap();
+        assert!(check_progress(&progress).is_err());
+        let progress: ProgressParams = serde_json::from_str(r#"{"id": ""}"#).unwrap();
+        assert!(check_progress(&progress).is_err());
+    }
+
     #[test]
     fn test_window_manager_track_untrack() {
         let mut manager = WindowManager::new();
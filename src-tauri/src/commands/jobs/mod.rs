@@ -0,0 +1,19 @@
+//! Declarative batch jobs.
+//!
+//! This module provides a job runner that executes a job file describing a batch
+//! of work (validate a folder, convert the messages, write a report) by reusing
+//! the existing validation and export subsystems.
+//!
+//! # Modules
+//!
+//! - [`runner`] - Job file parsing, execution, and progress reporting
+//!
+//! # Why Jobs?
+//!
+//! Jobs sit between interactive use and a full CLI: repetitive batch work can be
+//! captured in a file once and re-run from the Tools menu, without having to
+//! open and validate every message by hand.
+
+mod runner;
+
+pub use runner::*;
@@ -0,0 +1,579 @@
+//! Job file parsing and execution.
+//!
+//! A job file is a small TOML document describing a batch of work to run over a
+//! folder of HL7 messages. Each step reuses an existing subsystem: validation
+//! goes through [`validate_message`], conversion through the export commands.
+//!
+//! # Job File Format
+//!
+//! ```toml
+//! name = "Nightly ADT check"
+//!
+//! [validate]
+//! input = "inbox"          # folder of messages, relative to the job file
+//! mode = "full"            # "light" or "full" (default: "full")
+//! profile = "acme-adt"     # optional validation profile, used instead of mode
+//! extensions = ["hl7"]     # file extensions to pick up (default: hl7, txt)
+//!
+//! [convert]                # optional
+//! format = "json"          # "json", "yaml", or "toml"
+//! output = "converted"     # folder to write converted messages into
+//! skip_invalid = true      # don't convert messages with errors (default: false)
+//!
+//! [report]                 # optional
+//! output = "report.json"   # where to write the final report as JSON
+//! ```
+//!
+//! Relative paths are resolved against the directory containing the job file,
+//! so a job folder can be moved around or checked into version control. A
+//! profile is looked up by identifier in the data root's validation profiles,
+//! as with [`crate::commands::validate_with_profile`], and its own `base` mode
+//! applies.
+//!
+//! # Events
+//!
+//! - `job-progress` - Emitted after each message is processed, with a
+//!   [`JobProgress`] payload so the UI can show a progress bar
+//! - `job-finished` - Emitted once the job has run, with a [`JobSummary`] of
+//!   how many files passed, failed, and were converted, and where the report
+//!   was written
+//!
+//! The final [`JobReport`] is returned from the command once all files have been
+//! processed. The job runs on a blocking thread, as reading, validating, and
+//! writing a folder of messages can take a while.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::commands::editor::export::{export_to_json, export_to_toml, export_to_yaml};
+use crate::commands::strip_document_metadata;
+use crate::commands::validation::{
+    load_profile, validate_message, Severity, ValidationIssue, ValidationMode, ValidationResult,
+    ValidationSummary,
+};
+use crate::events;
+use crate::schema::cache::SchemaCache;
+use crate::AppData;
+
+/// A parsed job file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct JobFile {
+    /// Optional human-readable name, echoed back in the report
+    pub name: Option<String>,
+    /// Which messages to process and how to validate them
+    pub validate: ValidateStep,
+    /// Optional conversion of each message into another format
+    pub convert: Option<ConvertStep>,
+    /// Optional location to write the final report to
+    pub report: Option<ReportStep>,
+}
+
+/// The validation step of a job.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ValidateStep {
+    /// Folder containing the messages to process
+    pub input: PathBuf,
+    /// Validation mode to apply to each message
+    #[serde(default = "default_mode")]
+    pub mode: ValidationMode,
+    /// Validation profile to check each message against instead of `mode`
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// File extensions (without the dot) to include from the input folder
+    #[serde(default = "default_extensions")]
+    pub extensions: Vec<String>,
+}
+
+/// The conversion step of a job.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConvertStep {
+    /// Format to convert each message into
+    pub format: ConvertFormat,
+    /// Folder to write converted messages into (created if missing)
+    pub output: PathBuf,
+    /// Skip conversion of messages that have validation errors
+    #[serde(default)]
+    pub skip_invalid: bool,
+}
+
+/// The report step of a job.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReportStep {
+    /// File to write the JSON report to
+    pub output: PathBuf,
+}
+
+/// Structured formats a job can convert messages into.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConvertFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl ConvertFormat {
+    /// File extension used for converted output.
+    fn extension(self) -> &'static str {
+        match self {
+            ConvertFormat::Json => "json",
+            ConvertFormat::Yaml => "yaml",
+            ConvertFormat::Toml => "toml",
+        }
+    }
+
+    /// Convert a raw HL7 message into this format.
+    fn convert(self, message: &str) -> Result<String, String> {
+        match self {
+            ConvertFormat::Json => export_to_json(message),
+            ConvertFormat::Yaml => export_to_yaml(message),
+            ConvertFormat::Toml => export_to_toml(message),
+        }
+    }
+}
+
+fn default_mode() -> ValidationMode {
+    ValidationMode::Full
+}
+
+fn default_extensions() -> Vec<String> {
+    vec!["hl7".to_string(), "txt".to_string()]
+}
+
+impl JobFile {
+    /// Parse a job file from its TOML content.
+    pub fn parse(content: &str) -> Result<Self, String> {
+        toml::from_str(content).map_err(|e| format!("Failed to parse job file: {e}"))
+    }
+}
+
+/// Progress payload emitted as `job-progress` after each file is processed.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobProgress {
+    /// Number of files processed so far (1-based)
+    pub current: usize,
+    /// Total number of files the job will process
+    pub total: usize,
+    /// Path of the file that was just processed
+    pub file: String,
+}
+
+/// Outcome of processing a single message file.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobFileResult {
+    /// Path of the input file
+    pub file: String,
+    /// Validation summary counts (absent if the file couldn't be read)
+    pub summary: Option<ValidationSummary>,
+    /// Validation issues found in the message
+    pub issues: Vec<ValidationIssue>,
+    /// Path of the converted output, if the message was converted
+    pub output: Option<String>,
+    /// Error that stopped this file from being fully processed
+    pub error: Option<String>,
+}
+
+impl JobFileResult {
+    /// A file passes if it was processed without errors and has no error-level issues.
+    fn passed(&self) -> bool {
+        self.error.is_none() && self.summary.as_ref().is_some_and(|s| s.errors == 0)
+    }
+}
+
+/// Final report for a job run.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobReport {
+    /// Name of the job, from the job file
+    pub name: Option<String>,
+    /// Per-file results, in processing order
+    pub files: Vec<JobFileResult>,
+    /// Number of files processed
+    pub total: usize,
+    /// Number of files with no errors
+    pub passed: usize,
+    /// Number of files with errors
+    pub failed: usize,
+    /// Number of files converted
+    pub converted: usize,
+    /// Where the report was written, if the job has a report step
+    pub written: Option<String>,
+}
+
+impl JobReport {
+    /// The counts of the report, without the per-file results.
+    #[must_use]
+    pub fn summary(&self) -> JobSummary {
+        JobSummary {
+            name: self.name.clone(),
+            total: self.total,
+            passed: self.passed,
+            failed: self.failed,
+            converted: self.converted,
+            written: self.written.clone(),
+        }
+    }
+}
+
+/// Summary payload emitted as `job-finished` once a job has run.
+#[derive(Debug, Clone, Serialize)]
+pub struct JobSummary {
+    /// Name of the job, from the job file
+    pub name: Option<String>,
+    /// Number of files processed
+    pub total: usize,
+    /// Number of files with no errors
+    pub passed: usize,
+    /// Number of files with errors
+    pub failed: usize,
+    /// Number of files converted
+    pub converted: usize,
+    /// Where the report was written, if the job has a report step
+    pub written: Option<String>,
+}
+
+/// Run a job file.
+///
+/// Reads and parses the job file, processes every matching message in the input
+/// folder, and returns a report. Progress is emitted as `job-progress` events,
+/// and a summary as `job-finished` once done. The job runs on a blocking
+/// thread, off the async runtime.
+///
+/// # Arguments
+/// * `path` - Path to the job file
+/// * `app` - Tauri app handle for emitting events and reaching the schema cache
+///
+/// # Returns
+/// * `Ok(JobReport)` - The job ran to completion (individual files may have failed)
+/// * `Err(String)` - The job file couldn't be read or parsed, or the input folder is missing
+#[tauri::command]
+pub async fn run_job(path: String, app: AppHandle) -> Result<JobReport, String> {
+    let running = (app.clone(), path.clone());
+    let report = tokio::task::spawn_blocking(move || {
+        let (app, path) = running;
+        let job_path = PathBuf::from(&path);
+        let content = std::fs::read_to_string(&job_path)
+            .map_err(|e| format!("Failed to read job file {path}: {e}"))?;
+        let job = JobFile::parse(&content)?;
+
+        let base_dir = job_path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+        log::info!("running job {path}");
+        let state = app.state::<AppData>();
+        let data_root = state.data_root.blocking_lock().path.clone();
+        execute_job(&job, &base_dir, &data_root, &state.schema, |progress| {
+            if let Err(e) = app.emit(events::JOB_PROGRESS, progress) {
+                log::error!("Failed to emit job-progress event: {e:#}");
+            }
+        })
+    })
+    .await
+    .map_err(|e| format!("Failed to run job: {e}"))??;
+    if let Err(e) = app.emit(events::JOB_FINISHED, report.summary()) {
+        log::error!("Failed to emit job-finished event: {e:#}");
+    }
+    log::info!(
+        "job {path} finished: {passed}/{total} passed",
+        passed = report.passed,
+        total = report.total
+    );
+
+    Ok(report)
+}
+
+/// Execute a parsed job.
+///
+/// Separated from the command so it can run without a Tauri runtime.
+///
+/// # Arguments
+/// * `job` - The parsed job file
+/// * `base_dir` - Directory that relative paths in the job are resolved against
+/// * `data_root` - Data root holding the job's validation profile, if it has one
+/// * `schema` - Schema cache used for validation
+/// * `on_progress` - Called after each file is processed
+pub fn execute_job(
+    job: &JobFile,
+    base_dir: &Path,
+    data_root: &Path,
+    schema: &SchemaCache,
+    mut on_progress: impl FnMut(&JobProgress),
+) -> Result<JobReport, String> {
+    let profile = match &job.validate.profile {
+        Some(id) => Some(load_profile(data_root, id)?),
+        None => None,
+    };
+    let input_dir = base_dir.join(&job.validate.input);
+    let inputs = collect_input_files(&input_dir, &job.validate.extensions)?;
+
+    let output_dir = match &job.convert {
+        Some(convert) => {
+            let dir = base_dir.join(&convert.output);
+            std::fs::create_dir_all(&dir)
+                .map_err(|e| format!("Failed to create output folder {}: {e}", dir.display()))?;
+            Some(dir)
+        }
+        None => None,
+    };
+
+    let validate = |message: &str| match &profile {
+        Some((name, profile)) => profile.validate(message, name, schema),
+        None => validate_message(message, schema, job.validate.mode),
+    };
+    let total = inputs.len();
+    let mut files = Vec::with_capacity(total);
+
+    for (index, input) in inputs.iter().enumerate() {
+        let result = process_file(input, job, output_dir.as_deref(), &validate);
+        files.push(result);
+
+        on_progress(&JobProgress {
+            current: index + 1,
+            total,
+            file: input.display().to_string(),
+        });
+    }
+
+    let passed = files.iter().filter(|f| f.passed()).count();
+    let converted = files.iter().filter(|f| f.output.is_some()).count();
+    let mut report = JobReport {
+        name: job.name.clone(),
+        total: files.len(),
+        passed,
+        failed: files.len() - passed,
+        converted,
+        files,
+        written: None,
+    };
+
+    if let Some(report_step) = &job.report {
+        let report_path = base_dir.join(&report_step.output);
+        let json = serde_json::to_string_pretty(&report)
+            .map_err(|e| format!("Failed to serialise job report: {e}"))?;
+        std::fs::write(&report_path, json)
+            .map_err(|e| format!("Failed to write job report {}: {e}", report_path.display()))?;
+        report.written = Some(report_path.display().to_string());
+    }
+
+    Ok(report)
+}
+
+/// Validate (and optionally convert) a single message file.
+fn process_file(
+    input: &Path,
+    job: &JobFile,
+    output_dir: Option<&Path>,
+    validate: impl Fn(&str) -> ValidationResult,
+) -> JobFileResult {
+    let mut result = JobFileResult {
+        file: input.display().to_string(),
+        summary: None,
+        issues: Vec::new(),
+        output: None,
+        error: None,
+    };
+
+    let content = match std::fs::read_to_string(input) {
+        Ok(content) => content,
+        Err(e) => {
+            result.error = Some(format!("Failed to read file: {e}"));
+            return result;
+        }
+    };
+    // trailing newlines from editors on disk and metadata headers aren't part
+    // of the message
+    let (message, _) = strip_document_metadata(content.trim_end());
+
+    let validation = validate(message);
+    let has_errors = validation
+        .issues
+        .iter()
+        .any(|i| i.severity == Severity::Error);
+    result.summary = Some(validation.summary);
+    result.issues = validation.issues;
+
+    let (Some(convert), Some(output_dir)) = (&job.convert, output_dir) else {
+        return result;
+    };
+    if convert.skip_invalid && has_errors {
+        return result;
+    }
+
+    let converted = match convert.format.convert(message) {
+        Ok(converted) => converted,
+        Err(e) => {
+            result.error = Some(e);
+            return result;
+        }
+    };
+
+    let stem = input
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "message".to_string());
+    let output_path = output_dir.join(format!("{stem}.{}", convert.format.extension()));
+    match std::fs::write(&output_path, converted) {
+        Ok(()) => result.output = Some(output_path.display().to_string()),
+        Err(e) => result.error = Some(format!("Failed to write {}: {e}", output_path.display())),
+    }
+
+    result
+}
+
+/// List the files in a folder that match one of the given extensions, sorted by path.
+fn collect_input_files(dir: &Path, extensions: &[String]) -> Result<Vec<PathBuf>, String> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read input folder {}: {e}", dir.display()))?;
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| extensions.iter().any(|e| e.eq_ignore_ascii_case(ext)))
+        })
+        .collect();
+    files.sort();
+
+    Ok(files)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use crate::commands::temp_dir;
+    use crate::commands::validation::PROFILES_DIR;
+
+    #[test]
+    fn can_parse_job_with_defaults() {
+        let job = JobFile::parse(
+            r#"
+[validate]
+input = "inbox"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(job.validate.input, PathBuf::from("inbox"));
+        assert_eq!(job.validate.mode, ValidationMode::Full);
+        assert_eq!(job.validate.extensions, vec!["hl7", "txt"]);
+        assert!(job.convert.is_none());
+        assert!(job.report.is_none());
+    }
+
+    #[test]
+    fn rejects_unknown_convert_format() {
+        let result = JobFile::parse(
+            r#"
+[validate]
+input = "inbox"
+
+[convert]
+format = "xml"
+output = "out"
+"#,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn can_execute_job() {
+        let dir = temp_dir("job");
+        std::fs::create_dir_all(dir.join("inbox")).unwrap();
+        std::fs::write(
+            dir.join("inbox").join("a.hl7"),
+            "MSH|^~\\&|APP|FAC|||20250101120000||ADT^A01|123|P|2.5.1\rPID|||12345||DOE^JOHN\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("inbox").join("ignored.dat"), "not a message").unwrap();
+
+        let job = JobFile::parse(
+            r#"
+name = "test"
+
+[validate]
+input = "inbox"
+mode = "light"
+
+[convert]
+format = "json"
+output = "out"
+
+[report]
+output = "report.json"
+"#,
+        )
+        .unwrap();
+
+        let schema = SchemaCache::new().unwrap();
+        let mut progress = Vec::new();
+        let report = execute_job(&job, &dir, &dir, &schema, |p| progress.push(p.current)).unwrap();
+
+        assert_eq!(report.total, 1);
+        assert_eq!(report.converted, 1);
+        assert_eq!(progress, vec![1]);
+        assert!(dir.join("out").join("a.json").exists());
+        assert!(dir.join("report.json").exists());
+        assert_eq!(
+            report.summary().written,
+            Some(dir.join("report.json").display().to_string())
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+    #[test]
+    fn can_validate_against_a_profile() {
+        let dir = temp_dir("job");
+        std::fs::create_dir_all(dir.join("inbox")).unwrap();
+        std::fs::write(
+            dir.join("inbox").join("a.hl7"),
+            "MSH|^~\\&|APP|FAC|||20250101120000||ADT^A01|123|P|2.5.1\rPID|||12345||DOE^JOHN\n",
+        )
+        .unwrap();
+        let root = dir.join("data");
+        std::fs::create_dir_all(root.join(PROFILES_DIR)).unwrap();
+        std::fs::write(
+            root.join(PROFILES_DIR).join("acme.toml"),
+            r#"
+base = "light"
+
+[[rules]]
+path = "PID.18"
+required = true
+message = "Acme needs an account number"
+"#,
+        )
+        .unwrap();
+
+        let job = JobFile::parse(
+            r#"
+[validate]
+input = "inbox"
+profile = "acme"
+"#,
+        )
+        .unwrap();
+        let schema = SchemaCache::new().unwrap();
+        let report = execute_job(&job, &dir, &root, &schema, |_| {}).unwrap();
+
+        assert_eq!(report.failed, 1);
+        assert!(report.files[0]
+            .issues
+            .iter()
+            .any(|issue| issue.message == "Acme needs an account number"));
+
+        // a missing profile stops the job before any file is processed
+        let job = JobFile::parse(
+            r#"
+[validate]
+input = "inbox"
+profile = "missing"
+"#,
+        )
+        .unwrap();
+        assert!(execute_job(&job, &dir, &root, &schema, |_| {}).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -6,6 +6,9 @@
 //!
 //! - [`communication`] - MLLP send/receive over TCP
 //! - [`editor`] - Cursor tracking, data manipulation, syntax highlighting
+//! - [`jobs`] - Declarative batch jobs run from job files
+//! - [`testing`] - Test cases run against pipelines and interfaces
+//! - [`transform`] - Transformation pipelines run on messages and folders
 //! - [`validation`] - Message validation and semantic comparison
 //! - [`support`] - Field descriptions and schema queries
 //!
@@ -21,11 +24,17 @@
 mod communication;
 mod editor;
 pub mod extensions;
+mod jobs;
 mod support;
+mod testing;
+mod transform;
 mod validation;
 
 pub use communication::*;
 pub use editor::*;
 pub use extensions::*;
+pub use jobs::*;
 pub use support::*;
+pub use testing::*;
+pub use transform::*;
 pub use validation::*;
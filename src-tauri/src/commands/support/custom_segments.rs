@@ -0,0 +1,73 @@
+//! Commands for defining custom segment (Z-segment) schemas.
+//!
+//! Custom segments give a site's own segments names, descriptions, and
+//! validation rules, the same as the embedded schemas give the standard ones.
+//! They're saved in the data folder (see [`crate::schema::custom`]) and take
+//! effect straight away: each change reloads the folder into the schema cache,
+//! so segment tabs, field names, descriptions, and validation all pick it up.
+
+use tauri::State;
+
+use crate::schema::custom::{CustomSegment, CUSTOM_SEGMENTS_DIR};
+use crate::AppData;
+
+/// List the custom segments, sorted by name.
+///
+/// # Arguments
+/// * `state` - Application state containing the schema cache
+///
+/// # Returns
+/// The custom segments (empty if none have been defined)
+#[tauri::command]
+pub fn list_custom_segments(state: State<'_, AppData>) -> Vec<CustomSegment> {
+    state.schema.custom_segments()
+}
+
+/// Define a custom segment, replacing any earlier definition of it.
+///
+/// # Arguments
+/// * `segment` - The segment's name, description, and fields
+/// * `state` - Application state containing the data root and schema cache
+///
+/// # Returns
+/// * `Ok(Vec<CustomSegment>)` - The custom segments after the change
+/// * `Err(String)` - The segment isn't valid or couldn't be saved
+#[tauri::command]
+pub async fn save_custom_segment(
+    segment: CustomSegment,
+    state: State<'_, AppData>,
+) -> Result<Vec<CustomSegment>, String> {
+    let directory = state.data_root.lock().await.path.join(CUSTOM_SEGMENTS_DIR);
+    segment
+        .save(&directory)
+        .map_err(|e| format!("Failed to save custom segment {}: {e:#}", segment.name))?;
+    log::info!("saved custom segment {}", segment.name);
+    state
+        .schema
+        .set_custom_segments(CustomSegment::load_all(&directory));
+    Ok(state.schema.custom_segments())
+}
+
+/// Delete a custom segment.
+///
+/// # Arguments
+/// * `name` - Name of the segment (e.g., "ZPI")
+/// * `state` - Application state containing the data root and schema cache
+///
+/// # Returns
+/// * `Ok(Vec<CustomSegment>)` - The custom segments left
+/// * `Err(String)` - The segment isn't defined or couldn't be deleted
+#[tauri::command]
+pub async fn delete_custom_segment(
+    name: String,
+    state: State<'_, AppData>,
+) -> Result<Vec<CustomSegment>, String> {
+    let directory = state.data_root.lock().await.path.join(CUSTOM_SEGMENTS_DIR);
+    CustomSegment::delete(&directory, &name)
+        .map_err(|e| format!("Failed to delete custom segment {name}: {e:#}"))?;
+    log::info!("deleted custom segment {name}");
+    state
+        .schema
+        .set_custom_segments(CustomSegment::load_all(&directory));
+    Ok(state.schema.custom_segments())
+}
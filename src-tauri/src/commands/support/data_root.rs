@@ -0,0 +1,424 @@
+//! Location of persisted data, and portable mode.
+//!
+//! Everything Hermes persists (settings, connection presets, extension data, and
+//! any store added alongside them) lives under a single data root. By default
+//! that's the platform's app data directory, but some workstations forbid
+//! writing there, and some teams want their settings next to a project under
+//! version control.
+//!
+//! # Resolution Order
+//!
+//! The data root is resolved once at startup:
+//!
+//! 1. The `HERMES_DATA_ROOT` environment variable, if set
+//! 2. A `hermes-data` directory next to the executable (portable mode), so
+//!    Hermes can run from a USB stick without touching the machine it's on
+//! 3. A folder chosen in the settings, recorded in `data-root.txt` in the app
+//!    config directory
+//! 4. The app data directory
+//!
+//! The first two are set up outside the app, so they can't be changed from
+//! inside it. A chosen folder can be changed at any time with [`set_data_root`].
+//!
+//! # Live Switching
+//!
+//! Switching folders doesn't need a restart. The extension host is pointed at
+//! the new folder, and "data-root-changed" is emitted so the frontend reloads
+//! its stores from there, which in turn reloads the extensions with the
+//! configuration found in the new folder.
+
+use serde::Serialize;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+use crate::commands::{
+    apply_schema_directory, check_stores, load_schema_directory, load_schema_overrides,
+    CONNECTIONS_STORE, HISTORY_STORE, MAPPING_TABLES_DIR, PIPELINES_DIR, PROFILES_DIR,
+    SETTINGS_STORE, SNIPPETS_DIR, TEMPLATES_DIR, TEST_CASES_DIR,
+};
+use crate::events;
+use crate::schema::custom::{CustomSegment, CUSTOM_SEGMENTS_DIR};
+use crate::AppData;
+
+/// Environment variable that fixes the data root.
+pub const DATA_ROOT_ENV: &str = "HERMES_DATA_ROOT";
+
+/// Directory next to the executable that switches on portable mode.
+const PORTABLE_DIR: &str = "hermes-data";
+
+/// File in the app config directory recording a chosen data root.
+const POINTER_FILE: &str = "data-root.txt";
+
+/// Stores under the data root, copied when moving to a new folder.
+const STORED_ENTRIES: &[&str] = &[
+    SETTINGS_STORE,
+    CONNECTIONS_STORE,
+    HISTORY_STORE,
+    "extensions",
+    PROFILES_DIR,
+    PIPELINES_DIR,
+    MAPPING_TABLES_DIR,
+    TEST_CASES_DIR,
+    TEMPLATES_DIR,
+    SNIPPETS_DIR,
+    CUSTOM_SEGMENTS_DIR,
+];
+
+/// Where the data root came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DataRootSource {
+    /// The platform's app data directory.
+    Default,
+    /// The `HERMES_DATA_ROOT` environment variable.
+    Environment,
+    /// A `hermes-data` directory next to the executable.
+    Portable,
+    /// A folder chosen in the settings.
+    Configured,
+}
+
+/// The directory holding all persisted data.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DataRoot {
+    /// Absolute path of the directory.
+    pub path: PathBuf,
+
+    /// Where the path came from.
+    pub source: DataRootSource,
+}
+
+/// Resolve the data root at startup.
+///
+/// # Arguments
+/// * `app` - Tauri app handle, for the platform's app data and config directories
+///
+/// # Returns
+/// * `Ok(DataRoot)` - The directory to persist data in; it may not exist yet
+/// * `Err(String)` - The platform directories couldn't be determined
+pub fn resolve_data_root(app: &AppHandle) -> Result<DataRoot, String> {
+    let default = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| format!("Failed to get app data directory: {e}"))?;
+    let config_dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to get app config directory: {e}"))?;
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(Path::to_path_buf));
+
+    Ok(resolve(
+        std::env::var_os(DATA_ROOT_ENV),
+        exe_dir.as_deref(),
+        read_pointer(&config_dir),
+        default,
+    ))
+}
+
+/// Pick the data root from each possible source, in priority order.
+fn resolve(
+    env: Option<OsString>,
+    exe_dir: Option<&Path>,
+    configured: Option<PathBuf>,
+    default: PathBuf,
+) -> DataRoot {
+    if let Some(path) = env.filter(|path| !path.is_empty()) {
+        return DataRoot {
+            path: PathBuf::from(path),
+            source: DataRootSource::Environment,
+        };
+    }
+
+    if let Some(portable) = exe_dir
+        .map(|dir| dir.join(PORTABLE_DIR))
+        .filter(|dir| dir.is_dir())
+    {
+        return DataRoot {
+            path: portable,
+            source: DataRootSource::Portable,
+        };
+    }
+
+    match configured {
+        Some(path) => DataRoot {
+            path,
+            source: DataRootSource::Configured,
+        },
+        None => DataRoot {
+            path: default,
+            source: DataRootSource::Default,
+        },
+    }
+}
+
+/// Read the chosen data root from the pointer file, if there is one.
+fn read_pointer(config_dir: &Path) -> Option<PathBuf> {
+    let contents = std::fs::read_to_string(config_dir.join(POINTER_FILE)).ok()?;
+    let path = PathBuf::from(contents.trim());
+    path.is_absolute().then_some(path)
+}
+
+/// Record the chosen data root, or remove the record to go back to the default.
+fn write_pointer(config_dir: &Path, path: Option<&Path>) -> std::io::Result<()> {
+    let pointer = config_dir.join(POINTER_FILE);
+    match path {
+        Some(path) => {
+            std::fs::create_dir_all(config_dir)?;
+            std::fs::write(pointer, path.to_string_lossy().as_bytes())
+        }
+        None => match std::fs::remove_file(pointer) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            Ok(()) | Err(_) => Ok(()),
+        },
+    }
+}
+
+/// Make sure a directory exists and can be written to.
+///
+/// Locked-down machines often let a directory be created but not written, so
+/// this writes and removes a probe file rather than trusting the permissions.
+fn check_writable(dir: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let probe = dir.join(".hermes-write-test");
+    std::fs::write(&probe, b"")?;
+    std::fs::remove_file(probe)
+}
+
+/// Copy the stores from one data root to another, keeping any already there.
+fn copy_stores(from: &Path, to: &Path) -> std::io::Result<()> {
+    for entry in STORED_ENTRIES {
+        let source = from.join(entry);
+        if source.exists() {
+            copy_missing(&source, &to.join(entry))?;
+        }
+    }
+    Ok(())
+}
+
+/// Recursively copy a file or directory, skipping files that already exist.
+fn copy_missing(from: &Path, to: &Path) -> std::io::Result<()> {
+    if from.is_dir() {
+        std::fs::create_dir_all(to)?;
+        for entry in std::fs::read_dir(from)? {
+            let entry = entry?;
+            copy_missing(&entry.path(), &to.join(entry.file_name()))?;
+        }
+    } else if !to.exists() {
+        std::fs::copy(from, to)?;
+    }
+    Ok(())
+}
+
+/// Get the current data root.
+///
+/// # Returns
+/// * `Ok(DataRoot)` - The directory data is persisted in, and where that came from
+#[tauri::command]
+pub async fn get_data_root(state: State<'_, AppData>) -> Result<DataRoot, String> {
+    Ok(state.data_root.lock().await.clone())
+}
+
+/// Move persisted data to a different folder, or back to the default.
+///
+/// The folder is checked for write access before anything changes. The choice
+/// is recorded so it's used on the next launch, the stores in the new folder
+/// are checked for damage (see [`check_stores`]), the extension host is pointed
+/// at the new folder, and "data-root-changed" is emitted so the frontend can
+/// reload its stores.
+///
+/// # Arguments
+/// * `path` - Absolute path of the new folder, or `None` for the app data directory
+/// * `copy_existing` - Copy the current stores into the new folder, without
+///   overwriting anything already there
+/// * `app` - Tauri app handle, for the platform directories and emitting events
+/// * `state` - Application state holding the current data root
+///
+/// # Returns
+/// * `Ok(DataRoot)` - The new data root
+/// * `Err(String)` - The data root is fixed outside the app, or the folder
+///   couldn't be written to
+#[tauri::command]
+pub async fn set_data_root(
+    path: Option<String>,
+    copy_existing: bool,
+    app: AppHandle,
+    state: State<'_, AppData>,
+) -> Result<DataRoot, String> {
+    let mut data_root = state.data_root.lock().await;
+    match data_root.source {
+        DataRootSource::Environment => {
+            return Err(format!(
+                "The data folder is set by the {DATA_ROOT_ENV} environment variable and can't be changed here"
+            ));
+        }
+        DataRootSource::Portable => {
+            return Err(format!(
+                "Hermes is running in portable mode, using the {PORTABLE_DIR} folder next to the executable"
+            ));
+        }
+        DataRootSource::Default | DataRootSource::Configured => {}
+    }
+
+    let new_root = match path {
+        Some(path) => {
+            let path = PathBuf::from(path);
+            if !path.is_absolute() {
+                return Err(format!(
+                    "Data folder must be an absolute path: {}",
+                    path.display()
+                ));
+            }
+            DataRoot {
+                path,
+                source: DataRootSource::Configured,
+            }
+        }
+        None => DataRoot {
+            path: app
+                .path()
+                .app_data_dir()
+                .map_err(|e| format!("Failed to get app data directory: {e}"))?,
+            source: DataRootSource::Default,
+        },
+    };
+
+    check_writable(&new_root.path).map_err(|e| {
+        format!(
+            "Failed to write to data folder {}: {e}",
+            new_root.path.display()
+        )
+    })?;
+
+    if copy_existing && new_root.path != data_root.path {
+        copy_stores(&data_root.path, &new_root.path)
+            .map_err(|e| format!("Failed to copy existing data: {e}"))?;
+    }
+    *state.startup_health.lock().await = check_stores(&new_root.path);
+
+    let config_dir = app
+        .path()
+        .app_config_dir()
+        .map_err(|e| format!("Failed to get app config directory: {e}"))?;
+    let pointer = match new_root.source {
+        DataRootSource::Configured => Some(new_root.path.as_path()),
+        DataRootSource::Default | DataRootSource::Environment | DataRootSource::Portable => None,
+    };
+    write_pointer(&config_dir, pointer)
+        .map_err(|e| format!("Failed to record data folder: {e}"))?;
+
+    state
+        .extension_host
+        .lock()
+        .await
+        .set_data_dir(new_root.path.clone());
+    if let Err(e) = state.control_ids.save(&app, &data_root.path) {
+        log::error!("{e}");
+    }
+    *data_root = new_root.clone();
+    state.control_ids.reload(&app, &new_root.path);
+    state
+        .schema
+        .set_user_overrides(load_schema_overrides(&app, &new_root.path));
+    state.schema.set_custom_segments(CustomSegment::load_all(
+        &new_root.path.join(CUSTOM_SEGMENTS_DIR),
+    ));
+    let schema_directory = load_schema_directory(&app, &new_root.path);
+    if let Err(e) = apply_schema_directory(&app, &state, schema_directory).await {
+        log::warn!("{e}");
+    }
+
+    if let Err(e) =
+        crate::menu::refresh_user_templates_menu(&app, &state, &new_root.path.join(TEMPLATES_DIR))
+    {
+        log::warn!("failed to list user templates: {e}");
+    }
+
+    if let Err(e) = app.emit(events::DATA_ROOT_CHANGED, &new_root) {
+        log::error!("failed to emit data-root-changed event: {e}");
+    }
+
+    Ok(new_root)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use crate::commands::temp_dir;
+
+    #[test]
+    fn resolves_in_priority_order() {
+        let exe_dir = temp_dir("root");
+        let default = PathBuf::from("/default");
+        let configured = Some(PathBuf::from("/configured"));
+
+        let root = resolve(None, Some(&exe_dir), configured.clone(), default.clone());
+        assert_eq!(root.source, DataRootSource::Configured);
+        assert_eq!(root.path, PathBuf::from("/configured"));
+
+        std::fs::create_dir(exe_dir.join(PORTABLE_DIR)).unwrap();
+        let root = resolve(None, Some(&exe_dir), configured.clone(), default.clone());
+        assert_eq!(root.source, DataRootSource::Portable);
+        assert_eq!(root.path, exe_dir.join(PORTABLE_DIR));
+
+        let root = resolve(
+            Some(OsString::from("/from-env")),
+            Some(&exe_dir),
+            configured,
+            default.clone(),
+        );
+        assert_eq!(root.source, DataRootSource::Environment);
+
+        let root = resolve(Some(OsString::new()), None, None, default.clone());
+        assert_eq!(root.source, DataRootSource::Default);
+        assert_eq!(root.path, default);
+
+        std::fs::remove_dir_all(exe_dir).unwrap();
+    }
+
+    #[test]
+    fn pointer_round_trips_and_resets() {
+        let config_dir = temp_dir("root");
+        assert_eq!(read_pointer(&config_dir), None);
+
+        let chosen = config_dir.join("project data");
+        write_pointer(&config_dir, Some(&chosen)).unwrap();
+        assert_eq!(read_pointer(&config_dir), Some(chosen));
+
+        write_pointer(&config_dir, None).unwrap();
+        assert_eq!(read_pointer(&config_dir), None);
+        // removing a pointer that isn't there is fine
+        write_pointer(&config_dir, None).unwrap();
+
+        std::fs::write(config_dir.join(POINTER_FILE), "relative/path").unwrap();
+        assert_eq!(read_pointer(&config_dir), None);
+
+        std::fs::remove_dir_all(config_dir).unwrap();
+    }
+
+    #[test]
+    fn copies_stores_without_overwriting() {
+        let from = temp_dir("root");
+        let to = temp_dir("root");
+        std::fs::write(from.join("settings.json"), "{\"old\":true}").unwrap();
+        std::fs::create_dir_all(from.join("extensions").join("ext")).unwrap();
+        std::fs::write(from.join("extensions").join("ext").join("state"), "x").unwrap();
+        std::fs::write(from.join("unrelated.log"), "x").unwrap();
+        std::fs::write(to.join("settings.json"), "{\"new\":true}").unwrap();
+
+        copy_stores(&from, &to).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(to.join("settings.json")).unwrap(),
+            "{\"new\":true}"
+        );
+        assert!(to.join("extensions").join("ext").join("state").exists());
+        assert!(!to.join("unrelated.log").exists());
+
+        std::fs::remove_dir_all(from).unwrap();
+        std::fs::remove_dir_all(to).unwrap();
+    }
+}
@@ -0,0 +1,159 @@
+//! Folders of named definitions, each a TOML or JSON file.
+//!
+//! Pipelines, validation profiles, and test cases are all kept one per file in
+//! a folder under the data root, identified by the file's name without its
+//! extension (`acme.toml` is `acme`). This reads them the same way for each:
+//! listing a folder, and finding one by identifier without letting the
+//! identifier name a path out of the folder.
+
+use std::path::{Path, PathBuf};
+
+/// A kind of definition kept one per file in a folder.
+pub(crate) trait Definition: Sized {
+    /// What a definition is called in messages, e.g. "pipeline"
+    const KIND: &'static str;
+
+    /// Parse a definition, from JSON if `json` is set, otherwise TOML.
+    fn parse_definition(content: &str, json: bool) -> Result<Self, String>;
+}
+
+/// File formats a definition can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DefinitionFormat {
+    Toml,
+    Json,
+}
+
+/// Check that an identifier is a bare file name, never a path out of the
+/// folder.
+///
+/// `kind` is what the identifier names, for the error (e.g. "mapping table").
+pub(crate) fn check_definition_id(id: &str, kind: &str) -> Result<(), String> {
+    if id.is_empty() || Path::new(id).file_name().and_then(|name| name.to_str()) != Some(id) {
+        return Err(format!("Invalid {kind} name: {id}"));
+    }
+    Ok(())
+}
+
+/// Read the definitions in a folder, sorted by identifier.
+///
+/// Each is paired with its identifier, and is the error reading it if it can't
+/// be read, so broken files can still be listed.
+pub(crate) fn list_definitions<T: Definition>(
+    directory: &Path,
+) -> Result<Vec<(String, Result<T, String>)>, String> {
+    let entries = std::fs::read_dir(directory)
+        .map_err(|e| format!("Failed to read {}s folder: {e}", T::KIND))?;
+
+    let mut definitions: Vec<(String, Result<T, String>)> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| definition_format(path).is_some())
+        .filter_map(|path| {
+            let id = path.file_stem()?.to_str()?.to_string();
+            Some((id, read_definition(&path)))
+        })
+        .collect();
+    definitions.sort_by(|(a, _), (b, _)| a.cmp(b));
+    Ok(definitions)
+}
+
+/// Find and load a definition by identifier.
+pub(crate) fn load_definition<T: Definition>(
+    directory: &Path,
+    id: &str,
+) -> Result<(PathBuf, T), String> {
+    check_definition_id(id, T::KIND)?;
+
+    ["toml", "json"]
+        .iter()
+        .map(|extension| directory.join(format!("{id}.{extension}")))
+        .find(|path| path.is_file())
+        .ok_or_else(|| format!("{} not found: {id}", capitalized(T::KIND)))
+        .and_then(|path| read_definition(&path).map(|definition| (path, definition)))
+}
+
+/// Read and parse a definition file.
+fn read_definition<T: Definition>(path: &Path) -> Result<T, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {} {}: {e}", T::KIND, path.display()))?;
+    T::parse_definition(
+        &content,
+        definition_format(path) == Some(DefinitionFormat::Json),
+    )
+}
+
+/// Format implied by a file's extension, if it's a definition at all.
+fn definition_format(path: &Path) -> Option<DefinitionFormat> {
+    let extension = path.extension()?.to_str()?;
+    if extension.eq_ignore_ascii_case("toml") {
+        Some(DefinitionFormat::Toml)
+    } else if extension.eq_ignore_ascii_case("json") {
+        Some(DefinitionFormat::Json)
+    } else {
+        None
+    }
+}
+
+/// `text` with its first letter in upper case.
+fn capitalized(text: &str) -> String {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use crate::commands::temp_dir;
+
+    #[derive(Debug)]
+    struct Named(String);
+
+    impl Definition for Named {
+        const KIND: &'static str = "test definition";
+
+        fn parse_definition(content: &str, json: bool) -> Result<Self, String> {
+            let value: toml::Value = if json {
+                serde_json::from_str(content).map_err(|e| e.to_string())?
+            } else {
+                toml::from_str(content).map_err(|e| e.to_string())?
+            };
+            value
+                .get("name")
+                .and_then(|name| name.as_str())
+                .map(|name| Named(name.to_string()))
+                .ok_or_else(|| "No name".to_string())
+        }
+    }
+
+    #[test]
+    fn lists_and_loads_definitions_by_identifier() {
+        let dir = temp_dir("definitions");
+        std::fs::write(dir.join("b.json"), r#"{"name": "Bee"}"#).unwrap();
+        std::fs::write(dir.join("a.toml"), "name = \"Ay\"").unwrap();
+        std::fs::write(dir.join("broken.toml"), "name =").unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a definition").unwrap();
+
+        let listed = list_definitions::<Named>(&dir).unwrap();
+        let ids: Vec<&str> = listed.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(ids, ["a", "b", "broken"]);
+        assert!(listed[2].1.is_err());
+
+        let (path, loaded) = load_definition::<Named>(&dir, "b").unwrap();
+        assert_eq!(path, dir.join("b.json"));
+        assert_eq!(loaded.0, "Bee");
+        assert_eq!(
+            load_definition::<Named>(&dir, "missing").unwrap_err(),
+            "Test definition not found: missing"
+        );
+        assert!(load_definition::<Named>(&dir, "../a").is_err());
+        assert!(check_definition_id("", "test definition").is_err());
+        assert!(check_definition_id("a/b", "test definition").is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -5,24 +5,49 @@
 //!
 //! # Description Hierarchy
 //!
-//! Descriptions can be requested at three levels of granularity:
+//! Descriptions can be requested at four levels of granularity:
 //! 1. **Segment**: What this segment represents (e.g., "Patient Identification")
 //! 2. **Field**: What this field represents (e.g., "Patient ID")
 //! 3. **Component**: What a specific component represents (e.g., "ID Number")
+//! 4. **Subcomponent**: What a part of a composite component represents
+//!    (e.g., PID.3.4.1 "Namespace ID" of the assigning authority)
+//!
+//! Fields, components, and subcomponents are described with their data type,
+//! and, where the schema binds them to an HL7 table, the table. Data types can
+//! also be described on their own, with the components of composite types.
 //!
 //! # Version Handling
 //!
-//! Currently hard-coded to version "2.5.1" for consistency. This version is widely
-//! used in healthcare systems and provides comprehensive field definitions.
+//! Descriptions follow the same version resolution as the schema: a version
+//! selected by the user wins, otherwise the version declared in the message's
+//! MSH.12 is used, falling back to the default version ("2.5.1"). Field meanings
+//! are largely compatible across HL7 v2.x versions, but lengths, datatypes and
+//! deprecations differ, so matching the message's version avoids misleading hints.
+//!
+//! # Custom Segments
 //!
-//! # Why Hard-Coded Version?
+//! Segments the standard doesn't define are described from the user's custom
+//! segment schemas (see [`crate::schema::custom`]), if there's one for them.
 //!
-//! While the message itself may specify a different HL7 version (in MSH.12), using
-//! a consistent version for descriptions ensures predictable behaviour. The field
-//! definitions are largely compatible across HL7 v2.x versions, and 2.5.1 provides
-//! a good balance of completeness and compatibility.
+//! # Missing Descriptions
+//!
+//! Segments described by neither the specification (including its supplement,
+//! see [`crate::spec::supplement`]) nor a custom segment schema show as
+//! "Unknown segment". [`get_undescribed_segments`] lists those segments in a
+//! message, so gaps in the descriptions can be found and filled.
+
+use tauri::State;
 
-use crate::spec::std_spec::{describe_component, describe_field, segment_description};
+use crate::schema::cache::SchemaCache;
+use crate::schema::custom::CustomSegment;
+use crate::schema::segment::Field;
+use crate::schema::table::Table;
+use crate::schema::version::{detect_version, DEFAULT_VERSION};
+use crate::spec::std_spec::{
+    describe_component, describe_datatype, describe_field, describe_subcomponent, is_valid_version,
+    known_segment_description, segment_description,
+};
+use crate::AppData;
 
 /// Get a description for a segment, field, or component from the HL7 standard.
 ///
@@ -32,7 +57,9 @@ use crate::spec::std_spec::{describe_component, describe_field, segment_descript
 ///
 /// # Description Sources
 ///
-/// Descriptions come from the standard HL7 v2.5.1 specification (`std_spec.rs`).
+/// Descriptions come from the standard HL7 specification (`std_spec.rs`) for the
+/// resolved version of the message, or from the user's custom segment schema for
+/// segments the specification doesn't define.
 ///
 /// # Granularity Selection
 ///
@@ -40,11 +67,19 @@ use crate::spec::std_spec::{describe_component, describe_field, segment_descript
 /// * **segment only**: Returns segment-level description (e.g., "PID - Patient Identification")
 /// * **segment + field**: Returns field-level description (e.g., "PID.3 - Patient Identifier List")
 /// * **segment + field + component**: Returns component-level description (e.g., "PID.3.1 - ID Number")
+/// * **segment + field + component + subcomponent**: Returns subcomponent-level
+///   description (e.g., "PID.3.4.1 - Namespace ID")
+///
+/// A field or component bound to an HL7 table in the schema has the table's
+/// number and name added.
 ///
 /// # Arguments
 /// * `segment` - Segment identifier (e.g., "PID", "MSH", "OBX")
 /// * `field` - Optional field number (1-based, matching HL7 notation)
 /// * `component` - Optional component number (1-based, requires field to be set)
+/// * `subcomponent` - Optional subcomponent number (1-based, requires component to be set)
+/// * `message` - Optional message being edited, used to detect the version from MSH.12
+/// * `state` - Application state containing the schema cache (for the selected version)
 ///
 /// # Returns
 /// Human-readable description string, or an empty string if no description is available
@@ -53,11 +88,272 @@ pub fn get_std_description(
     segment: &str,
     field: Option<usize>,
     component: Option<usize>,
+    subcomponent: Option<usize>,
+    message: Option<&str>,
+    state: State<'_, AppData>,
+) -> String {
+    std_description(
+        &state.schema,
+        segment,
+        field,
+        component,
+        subcomponent,
+        message,
+    )
+}
+
+/// Describe a segment, field, or component as [`get_std_description`] does,
+/// without going through Tauri state.
+pub(crate) fn std_description(
+    schema: &SchemaCache,
+    segment: &str,
+    field: Option<usize>,
+    component: Option<usize>,
+    subcomponent: Option<usize>,
+    message: Option<&str>,
+) -> String {
+    let schema_version = schema.resolve_version(message.and_then(detect_version));
+    let version = spec_version(&schema_version);
+    if known_segment_description(version, segment).is_none() {
+        if let Some(custom) = schema.custom_segment(segment) {
+            return describe_custom(&custom, field, component);
+        }
+    }
+    let description = match (field, component, subcomponent) {
+        (Some(field), Some(component), Some(subcomponent)) => {
+            return describe_subcomponent(version, segment, field, component, subcomponent);
+        }
+        (Some(field), Some(component), None) => {
+            describe_component(version, segment, field, component)
+        }
+        (Some(field), None, _) => describe_field(version, segment, field),
+        _ => return segment_description(version, segment),
+    };
+
+    // the spec doesn't say which table a field takes its values from, but the schema does
+    let table = field.and_then(|field| {
+        schema
+            .get_segment_for_version(segment, &schema_version)
+            .ok()?
+            .into_iter()
+            .find(|f| usize::from(f.field) == field && f.component.map(usize::from) == component)?
+            .table
+            .and_then(|id| schema.get_table(&id))
+    });
+    with_table(description, table.as_ref())
+}
+
+/// Get a description of an HL7 data type from the standard.
+///
+/// Composite types list their components, e.g. "Hierarchic Designator:
+/// Namespace ID ^ Universal ID ^ Universal ID Type" for HD.
+///
+/// # Arguments
+/// * `datatype` - Data type code (e.g., "XPN", "CX", "TS")
+/// * `message` - Optional message being edited, used to detect the version from MSH.12
+/// * `state` - Application state containing the schema cache (for the selected version)
+///
+/// # Returns
+/// Human-readable description string, or an empty string if the type isn't known
+#[tauri::command]
+pub fn get_datatype_description(
+    datatype: &str,
+    message: Option<&str>,
+    state: State<'_, AppData>,
 ) -> String {
-    let version = "2.5.1";
-    match (field, component) {
-        (Some(field), Some(component)) => describe_component(version, segment, field, component),
-        (Some(field), None) => describe_field(version, segment, field),
-        _ => segment_description(version, segment),
+    let version = state
+        .schema
+        .resolve_version(message.and_then(detect_version));
+    describe_datatype(
+        spec_version(&version),
+        &datatype.trim().to_ascii_uppercase(),
+    )
+    .unwrap_or_default()
+}
+
+/// List the segments in a message that have no description.
+///
+/// # Arguments
+/// * `message` - The message being edited; its MSH.12 picks the version
+/// * `state` - Application state containing the schema cache (for the selected
+///   version and custom segments)
+///
+/// # Returns
+/// The names of segments described by neither the specification nor a custom
+/// segment schema, each once, in the order they first appear
+#[tauri::command]
+pub fn get_undescribed_segments(message: &str, state: State<'_, AppData>) -> Vec<String> {
+    undescribed_segments(&state.schema, message)
+}
+
+/// List the segments in a message without a description, as
+/// [`get_undescribed_segments`] does, without going through Tauri state.
+pub(crate) fn undescribed_segments(schema: &SchemaCache, message: &str) -> Vec<String> {
+    let Ok(parsed) = hl7_parser::parse_message_with_lenient_newlines(message) else {
+        return Vec::new();
+    };
+    let schema_version = schema.resolve_version(detect_version(message));
+    let version = spec_version(&schema_version);
+
+    let mut undescribed: Vec<String> = Vec::new();
+    for segment in parsed.segments() {
+        let name = segment.name;
+        if undescribed.iter().any(|u| u == name)
+            || known_segment_description(version, name).is_some()
+            || schema.custom_segment(name).is_some()
+        {
+            continue;
+        }
+        undescribed.push(name.to_string());
+    }
+    undescribed
+}
+
+/// The version to look up in the spec, falling back to the default for versions
+/// the spec doesn't have.
+pub(crate) fn spec_version(version: &str) -> &str {
+    if is_valid_version(version) {
+        version
+    } else {
+        DEFAULT_VERSION
+    }
+}
+
+/// Add the HL7 table a field takes its values from to its description.
+fn with_table(description: String, table: Option<&Table>) -> String {
+    match table {
+        Some(table) => format!("{description}, table {} ({})", table.id, table.name),
+        None => description,
+    }
+}
+
+/// Describe part of a custom segment, in the same form as the standard's descriptions.
+fn describe_custom(
+    segment: &CustomSegment,
+    field: Option<usize>,
+    component: Option<usize>,
+) -> String {
+    let find = |field: usize, component: Option<usize>| {
+        segment
+            .fields
+            .iter()
+            .find(|f| usize::from(f.field) == field && f.component.map(usize::from) == component)
+    };
+    let Some(field) = field else {
+        return segment.description.clone();
+    };
+    let Some(field_def) = find(field, None) else {
+        return "Unknown field".to_string();
+    };
+    match component {
+        None => describe_custom_field(&field_def.name, field_def),
+        Some(component) => match find(field, Some(component)) {
+            Some(component_def) => describe_custom_field(
+                &format!("{} / {}", field_def.name, component_def.name),
+                component_def,
+            ),
+            None => "Unknown component".to_string(),
+        },
+    }
+}
+
+/// Describe a field or component of a custom segment by its name, length, and optionality.
+fn describe_custom_field(description: &str, field: &Field) -> String {
+    let len = field
+        .maxlength
+        .map(|l| l.to_string())
+        .unwrap_or_else(|| "∞".to_string());
+    let optional = if field.required == Some(true) {
+        "*required*"
+    } else {
+        "*optional*"
+    };
+    format!("{description}, len: {len} [{optional}]")
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn descriptions_name_the_table() {
+        let table: Table = Table::parse(
+            r#"
+            [[tables]]
+            id = "0001"
+            name = "Administrative Sex"
+            kind = "user"
+            values = { F = "Female", M = "Male" }
+            "#,
+        )
+        .unwrap()
+        .remove(0);
+
+        assert_eq!(
+            with_table("Administrative Sex, len: 1".to_string(), Some(&table)),
+            "Administrative Sex, len: 1, table 0001 (Administrative Sex)"
+        );
+        assert_eq!(with_table("Set ID".to_string(), None), "Set ID");
+    }
+
+    #[test]
+    fn describes_custom_segments() {
+        let segment: CustomSegment = toml::from_str(
+            r#"
+            name = "ZPI"
+            description = "Patient Insurance Extension"
+
+            [[fields]]
+            field = 2
+            name = "Member"
+            required = true
+
+            [[fields]]
+            field = 2
+            component = 1
+            name = "Member ID"
+            maxlength = 12
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            describe_custom(&segment, None, None),
+            "Patient Insurance Extension"
+        );
+        assert_eq!(
+            describe_custom(&segment, Some(2), None),
+            "Member, len: ∞ [*required*]"
+        );
+        assert_eq!(
+            describe_custom(&segment, Some(2), Some(1)),
+            "Member / Member ID, len: 12 [*optional*]"
+        );
+        assert_eq!(describe_custom(&segment, Some(3), None), "Unknown field");
+        assert_eq!(
+            describe_custom(&segment, Some(2), Some(2)),
+            "Unknown component"
+        );
+    }
+
+    #[test]
+    fn supplemented_segments_are_described() {
+        let schema = SchemaCache::new().unwrap();
+        let message = "MSH|^~\\&|HIS|HOSP|RCV|FAC|20250101120000||SIU^S12|1|P|2.5.1\r\
+            SCH|1234|5678||||ROUTINE\r\
+            AIS|1||CHECKUP\r\
+            ZXY|1\r\
+            ZXY|2";
+
+        assert_eq!(
+            std_description(&schema, "SCH", None, None, None, Some(message)),
+            "Scheduling Activity Information"
+        );
+        assert!(
+            std_description(&schema, "AIS", Some(3), None, None, Some(message))
+                .starts_with("Universal Service Identifier, len: ")
+        );
+        assert_eq!(undescribed_segments(&schema, message), vec!["ZXY"]);
     }
 }
@@ -5,9 +5,17 @@
 //!
 //! # Modules
 //!
+//! - [`custom_segments`] - Schemas for the user's own Z-segments
+//! - [`data_root`] - Location of persisted data and portable mode
+//! - [`definitions`] - Folders of named TOML or JSON definitions
 //! - [`field_description`] - Human-readable descriptions from HL7 specs
 //! - [`open_url`] - Open URLs in OS default browser
+//! - [`samples`] - Built-in gallery of example messages
 //! - [`schema`] - Message and segment schema queries
+//! - [`schema_directory`] - Loading the schema from a folder at runtime
+//! - [`schema_overrides`] - The user's own overrides of the schema
+//! - [`shutdown`] - Coordinated shutdown of background work when the app closes
+//! - [`store_health`] - Integrity check and repair of persisted stores at startup
 //!
 //! # Usage
 //!
@@ -15,10 +23,30 @@
 //! - Field descriptions appear in tooltips when cursor moves
 //! - Schema data populates segment editing forms and validates structure
 
+mod custom_segments;
+mod data_root;
+mod definitions;
 mod field_description;
 mod open_url;
+mod samples;
 mod schema;
+mod schema_directory;
+mod schema_overrides;
+mod shutdown;
+mod store_health;
+#[cfg(test)]
+mod test_dirs;
 
+pub use custom_segments::*;
+pub use data_root::*;
+pub use definitions::*;
 pub use field_description::*;
 pub use open_url::*;
+pub use samples::*;
 pub use schema::*;
+pub use schema_directory::*;
+pub use schema_overrides::*;
+pub use shutdown::*;
+pub use store_health::*;
+#[cfg(test)]
+pub(crate) use test_dirs::temp_dir;
@@ -0,0 +1,178 @@
+//! Built-in sample gallery.
+//!
+//! New team members shouldn't have to hunt down real (and likely PHI-laden)
+//! messages before they can try Hermes out. The gallery is a small set of
+//! curated, fictional messages embedded in the binary: common message types,
+//! edge cases such as escape sequences and batch files, and messages with
+//! deliberate mistakes for learning what validation reports.
+//!
+//! # Data
+//!
+//! Samples live in `data/samples.toml`, one `[[samples]]` table each, with the
+//! message as a TOML literal string so backslashes survive as written. The
+//! gallery is listed in the Help menu, grouped by category, in file order.
+
+use serde::{Deserialize, Serialize};
+
+/// Embedded sample gallery.
+const SAMPLES_TOML: &str = include_str!("../../../data/samples.toml");
+
+/// What a sample is meant to show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SampleCategory {
+    /// A well-formed message of a common type
+    Example,
+    /// A valid message exercising a less common part of the standard
+    EdgeCase,
+    /// A message with deliberate mistakes, for learning validation
+    Broken,
+}
+
+/// A sample as listed in the gallery, without its message.
+#[derive(Debug, Clone, Serialize)]
+pub struct SampleInfo {
+    /// Identifier used to load the sample (e.g., "adt-a01-admit")
+    pub id: String,
+    /// Display name
+    pub name: String,
+    /// What the sample is meant to show
+    pub category: SampleCategory,
+    /// What the sample contains and what to try with it
+    pub description: String,
+}
+
+/// A sample and its message.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Sample {
+    /// Identifier used to load the sample (e.g., "adt-a01-admit")
+    pub id: String,
+    /// Display name
+    pub name: String,
+    /// What the sample is meant to show
+    pub category: SampleCategory,
+    /// What the sample contains and what to try with it
+    pub description: String,
+    /// The HL7 message, one segment per line
+    pub message: String,
+}
+
+/// Wrapper for deserializing the samples file.
+#[derive(Debug, Deserialize)]
+struct Samples {
+    samples: Vec<Sample>,
+}
+
+/// Every sample in the gallery, in file order.
+///
+/// Trailing newlines are trimmed from messages, so a sample loads exactly as a
+/// message would be typed.
+#[must_use]
+pub fn gallery() -> Vec<Sample> {
+    let samples: Samples = toml::from_str(SAMPLES_TOML).expect("embedded samples.toml is valid");
+    samples
+        .samples
+        .into_iter()
+        .map(|mut sample| {
+            sample.message = sample.message.trim_end().to_string();
+            sample
+        })
+        .collect()
+}
+
+/// List the samples in the gallery.
+///
+/// # Returns
+/// Every sample's identifier, name, category, and description, in gallery order
+#[tauri::command]
+pub fn list_samples() -> Vec<SampleInfo> {
+    gallery()
+        .into_iter()
+        .map(|sample| SampleInfo {
+            id: sample.id,
+            name: sample.name,
+            category: sample.category,
+            description: sample.description,
+        })
+        .collect()
+}
+
+/// Load a sample from the gallery.
+///
+/// # Arguments
+/// * `id` - Identifier of the sample, as returned by [`list_samples`]
+///
+/// # Returns
+/// * `Ok(Sample)` - The sample and its message
+/// * `Err(String)` - No sample has that identifier
+#[tauri::command]
+pub fn load_sample(id: &str) -> Result<Sample, String> {
+    gallery()
+        .into_iter()
+        .find(|sample| sample.id == id)
+        .ok_or_else(|| format!("Sample '{id}' not found"))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn sample_ids_are_unique_and_kebab_case() {
+        let samples = gallery();
+        assert!(!samples.is_empty());
+
+        let mut ids = HashSet::new();
+        for sample in &samples {
+            assert!(ids.insert(sample.id.as_str()), "duplicate id {}", sample.id);
+            assert!(
+                sample
+                    .id
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-'),
+                "{} isn't kebab-case",
+                sample.id
+            );
+        }
+    }
+
+    #[test]
+    fn every_category_has_samples() {
+        let samples = gallery();
+        for category in [
+            SampleCategory::Example,
+            SampleCategory::EdgeCase,
+            SampleCategory::Broken,
+        ] {
+            assert!(samples.iter().any(|sample| sample.category == category));
+        }
+    }
+
+    #[test]
+    fn sample_messages_parse() {
+        for sample in gallery() {
+            assert!(!sample.message.ends_with('\n'));
+            // batch files start with their envelope rather than a message header
+            if !sample.message.starts_with("MSH") {
+                assert!(
+                    sample.message.contains("\nMSH|"),
+                    "{} has no MSH",
+                    sample.id
+                );
+                continue;
+            }
+            let parsed = hl7_parser::parse_message_with_lenient_newlines(&sample.message);
+            assert!(parsed.is_ok(), "{} doesn't parse", sample.id);
+        }
+    }
+
+    #[test]
+    fn loads_samples_by_id() {
+        let sample = load_sample("adt-a01-admit").unwrap();
+        assert!(sample.message.starts_with("MSH|^~\\&|"));
+        assert!(load_sample("no-such-sample").is_err());
+    }
+}
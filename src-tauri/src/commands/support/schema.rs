@@ -19,14 +19,54 @@
 //! Schemas are embedded at compile time from TOML files in `data/`. The SchemaCache
 //! parses these once at startup and caches them in memory. Extension overrides can
 //! modify the effective schema at runtime.
+//!
+//! # HL7 Tables
+//!
+//! Coded fields are bound to HL7 tables, whose values already appear in the
+//! field's schema. `list_tables` and `get_table` expose the tables themselves,
+//! for dropdowns on fields the schema doesn't cover and for browsing a table.
+//!
+//! # HL7 Versions
+//!
+//! One schema is cached per supported HL7 version. The frontend can pin a version
+//! with `set_schema_version`, or leave it unset so the version is detected from
+//! each message's MSH.12.
 
 use crate::{
-    schema::{message::MessagesSchema, segment::Field},
+    schema::{
+        message::MessagesSchema,
+        segment::Field,
+        table::{Table, TableKind},
+        version::{detect_version, DEFAULT_VERSION, SUPPORTED_VERSIONS},
+    },
     AppData,
 };
 use color_eyre::eyre::Context;
+use serde::Serialize;
 use tauri::State;
 
+/// HL7 versions available for schema selection.
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaVersions {
+    /// Versions with schema data, oldest first
+    pub supported: Vec<String>,
+    /// Version used when a message doesn't declare a supported version
+    pub default: String,
+    /// Version pinned by the user, or None when auto-detecting from MSH.12
+    pub selected: Option<String>,
+}
+
+/// An HL7 table as listed, without its values.
+#[derive(Debug, Clone, Serialize)]
+pub struct TableSummary {
+    /// Table number (e.g., "0001")
+    pub id: String,
+    /// Table name (e.g., "Administrative Sex")
+    pub name: String,
+    /// Who defines the table's values
+    pub kind: TableKind,
+}
+
 /// Retrieve the schema definition for a specific segment.
 ///
 /// This command loads the segment's field definitions from the schema cache.
@@ -41,16 +81,22 @@ use tauri::State;
 ///
 /// # Arguments
 /// * `segment` - Segment identifier (e.g., "PID", "PV1", "OBX")
+/// * `version` - Optional HL7 version declared by the message (MSH.12)
 /// * `state` - Application state containing the schema cache
 ///
 /// # Returns
 /// * `Ok(Vec<Field>)` - Field definitions for the segment
 /// * `Err(String)` - Segment not found or failed to load schema file
 #[tauri::command]
-pub fn get_segment_schema(segment: &str, state: State<'_, AppData>) -> Result<Vec<Field>, String> {
+pub fn get_segment_schema(
+    segment: &str,
+    version: Option<&str>,
+    state: State<'_, AppData>,
+) -> Result<Vec<Field>, String> {
+    let version = state.schema.resolve_version(version);
     state
         .schema
-        .get_segment(segment)
+        .get_segment_for_version(segment, &version)
         .wrap_err_with(|| format!("Failed to load segment {segment} data"))
         .map_err(|e| format!("{e:#}"))
 }
@@ -79,3 +125,89 @@ pub fn get_segment_schema(segment: &str, state: State<'_, AppData>) -> Result<Ve
 pub fn get_messages_schema(state: State<'_, AppData>) -> Result<MessagesSchema, String> {
     Ok(state.schema.get_messages())
 }
+
+/// List the HL7 tables fields can be bound to.
+///
+/// # Arguments
+/// * `state` - Application state containing the schema cache
+///
+/// # Returns
+/// Each table's number, name, and kind, in table file order
+#[tauri::command]
+pub fn list_tables(state: State<'_, AppData>) -> Vec<TableSummary> {
+    state
+        .schema
+        .get_tables()
+        .iter()
+        .map(|table| TableSummary {
+            id: table.id.clone(),
+            name: table.name.clone(),
+            kind: table.kind,
+        })
+        .collect()
+}
+
+/// Retrieve an HL7 table and its values, e.g. to populate a dropdown.
+///
+/// # Arguments
+/// * `table` - Table number, with or without leading zeros (e.g., "0001" or "1")
+/// * `state` - Application state containing the schema cache
+///
+/// # Returns
+/// * `Ok(Table)` - The table, with its codes and descriptions in table order
+/// * `Err(String)` - There's no table with that number
+#[tauri::command]
+pub fn get_table(table: &str, state: State<'_, AppData>) -> Result<Table, String> {
+    state
+        .schema
+        .get_table(table)
+        .ok_or_else(|| format!("Table {table} not found"))
+}
+
+/// List the HL7 versions available for schema selection.
+///
+/// # Arguments
+/// * `state` - Application state containing the schema cache
+///
+/// # Returns
+/// The supported versions, the default version, and the currently selected version
+#[tauri::command]
+pub fn get_schema_versions(state: State<'_, AppData>) -> SchemaVersions {
+    SchemaVersions {
+        supported: SUPPORTED_VERSIONS.iter().map(|v| v.to_string()).collect(),
+        default: DEFAULT_VERSION.to_string(),
+        selected: state.schema.selected_version(),
+    }
+}
+
+/// Pin the HL7 version used for schemas, validation, and field descriptions.
+///
+/// # Arguments
+/// * `version` - Version to use, or None to auto-detect from each message's MSH.12
+/// * `state` - Application state containing the schema cache
+///
+/// # Returns
+/// * `Ok(())` - Version selected
+/// * `Err(String)` - Version is not supported
+#[tauri::command]
+pub fn set_schema_version(
+    version: Option<String>,
+    state: State<'_, AppData>,
+) -> Result<(), String> {
+    state
+        .schema
+        .set_selected_version(version)
+        .map_err(|e| format!("{e:#}"))
+}
+
+/// Detect which supported HL7 version a message declares in MSH.12.
+///
+/// # Arguments
+/// * `message` - Raw HL7 message text
+///
+/// # Returns
+/// The matching supported version, or None if MSH.12 is missing or unrecognised
+#[tauri::command]
+pub fn detect_message_version(message: &str) -> Option<String> {
+    detect_version(message).map(str::to_string)
+}
@@ -0,0 +1,231 @@
+//! Commands for loading the schema from a folder of TOML files at runtime.
+//!
+//! The embedded schemas can only be changed by rebuilding Hermes. These
+//! commands let the user point Hermes at a folder laid out like `data/` (see
+//! [`crate::schema::external`]) whose files replace the embedded ones, and
+//! reload it without restarting.
+//!
+//! # Storage
+//!
+//! The folder is kept under the `schemaDirectory` key of `settings.json` in the
+//! data root. It's loaded at startup and when the data folder is switched.
+//!
+//! # Watching
+//!
+//! While a folder is set, its TOML files are polled for changes and the schema
+//! is reloaded when any of them changes, so a schema can be edited alongside
+//! Hermes. A reload that fails, at startup, on request, or from the watch,
+//! leaves the schema that was in use in place.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_store::StoreExt;
+
+use super::SETTINGS_STORE;
+use crate::events;
+use crate::schema::external::snapshot;
+use crate::AppData;
+
+/// Key of the schema folder in the settings store.
+const SCHEMA_DIRECTORY_KEY: &str = "schemaDirectory";
+
+/// How often the schema folder is checked for changes.
+const POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// The schema in use after a reload.
+#[derive(Debug, Clone, Serialize)]
+pub struct SchemaReload {
+    /// Folder the schema files were loaded from, or None for the embedded schemas
+    pub directory: Option<String>,
+    /// Files loaded from the folder, relative to it
+    pub files: Vec<String>,
+}
+
+/// A schema folder being polled for changes.
+pub struct SchemaWatch {
+    task: tauri::async_runtime::JoinHandle<()>,
+}
+
+impl SchemaWatch {
+    /// Stop polling the folder.
+    pub fn stop(self) {
+        self.task.abort();
+    }
+}
+
+/// Get the folder the schema is loaded from.
+///
+/// # Returns
+/// The folder set by the user, or None if the embedded schemas are used
+#[tauri::command]
+pub async fn get_schema_directory(
+    app: AppHandle,
+    state: State<'_, AppData>,
+) -> Result<Option<String>, String> {
+    let data_root = state.data_root.lock().await.path.clone();
+    Ok(load_schema_directory(&app, &data_root).map(|dir| dir.display().to_string()))
+}
+
+/// Set the folder to load the schema from, and load it.
+///
+/// The folder is only saved if it loads, so a typo never replaces a working
+/// schema.
+///
+/// # Arguments
+/// * `path` - Folder of schema files, or None to go back to the embedded schemas
+/// * `app` - Tauri app handle, for the settings store and events
+/// * `state` - Application state containing the data root and schema cache
+///
+/// # Returns
+/// * `Ok(SchemaReload)` - The schema now in use
+/// * `Err(String)` - The folder couldn't be loaded, or the setting couldn't be saved
+#[tauri::command]
+pub async fn set_schema_directory(
+    path: Option<String>,
+    app: AppHandle,
+    state: State<'_, AppData>,
+) -> Result<SchemaReload, String> {
+    let directory = path
+        .map(|path| path.trim().to_string())
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from);
+    let reload = reload_from(&app, &state, directory.as_deref())?;
+    watch_schema_directory(&app, &state, directory.clone()).await;
+
+    let store_path = state.data_root.lock().await.path.join(SETTINGS_STORE);
+    let store = app
+        .store(store_path)
+        .map_err(|e| format!("Failed to open settings: {e}"))?;
+    match &directory {
+        Some(directory) => store.set(
+            SCHEMA_DIRECTORY_KEY,
+            serde_json::Value::String(directory.display().to_string()),
+        ),
+        None => {
+            store.delete(SCHEMA_DIRECTORY_KEY);
+        }
+    }
+    store
+        .save()
+        .map_err(|e| format!("Failed to save schema folder: {e}"))?;
+    Ok(reload)
+}
+
+/// Reload the schema from the folder set by the user.
+///
+/// # Arguments
+/// * `app` - Tauri app handle, for the settings store and events
+/// * `state` - Application state containing the data root and schema cache
+///
+/// # Returns
+/// * `Ok(SchemaReload)` - The schema now in use
+/// * `Err(String)` - The folder couldn't be loaded; the previous schema is still in use
+#[tauri::command]
+pub async fn reload_schema(
+    app: AppHandle,
+    state: State<'_, AppData>,
+) -> Result<SchemaReload, String> {
+    let data_root = state.data_root.lock().await.path.clone();
+    let directory = load_schema_directory(&app, &data_root);
+    apply_schema_directory(&app, &state, directory).await
+}
+
+/// Read the schema folder from the settings store in a data root.
+///
+/// # Returns
+/// The folder, or None if none is set or the settings can't be read
+pub fn load_schema_directory(app: &AppHandle, data_root: &Path) -> Option<PathBuf> {
+    let store = match app.store(data_root.join(SETTINGS_STORE)) {
+        Ok(store) => store,
+        Err(e) => {
+            log::warn!("failed to open settings to read the schema folder: {e}");
+            return None;
+        }
+    };
+    store
+        .get(SCHEMA_DIRECTORY_KEY)?
+        .as_str()
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+}
+
+/// Load the schema from a folder and watch it for changes, replacing any
+/// folder watched before.
+///
+/// The folder is watched even if it fails to load, so fixing the file at
+/// fault picks the folder up.
+///
+/// # Arguments
+/// * `app` - Tauri app handle, for emitting events
+/// * `state` - Application state containing the schema cache and watch
+/// * `directory` - Folder of schema files, or None to go back to the embedded schemas
+///
+/// # Returns
+/// * `Ok(SchemaReload)` - The schema now in use
+/// * `Err(String)` - The folder couldn't be loaded; the previous schema is still in use
+pub async fn apply_schema_directory(
+    app: &AppHandle,
+    state: &AppData,
+    directory: Option<PathBuf>,
+) -> Result<SchemaReload, String> {
+    watch_schema_directory(app, state, directory.clone()).await;
+    reload_from(app, state, directory.as_deref())
+}
+
+/// Watch a schema folder for changes, replacing any folder watched before.
+async fn watch_schema_directory(app: &AppHandle, state: &AppData, directory: Option<PathBuf>) {
+    let mut schema_watch = state.schema_watch.lock().await;
+    if let Some(watch) = schema_watch.take() {
+        watch.stop();
+    }
+    *schema_watch = directory.map(|directory| start_schema_watch(app.clone(), directory));
+}
+
+/// Load the schema from a folder into the cache and tell the frontend.
+fn reload_from(
+    app: &AppHandle,
+    state: &AppData,
+    directory: Option<&Path>,
+) -> Result<SchemaReload, String> {
+    let files = state
+        .schema
+        .load_external_schema(directory)
+        .map_err(|e| format!("Failed to load schema: {e:#}"))?;
+    let reload = SchemaReload {
+        directory: directory.map(|dir| dir.display().to_string()),
+        files,
+    };
+    log::info!(
+        "using schema from {} ({} file(s) replaced)",
+        reload.directory.as_deref().unwrap_or("embedded data"),
+        reload.files.len()
+    );
+    if let Err(e) = app.emit(events::SCHEMA_RELOADED, &reload) {
+        log::error!("failed to emit schema-reloaded event: {e}");
+    }
+    Ok(reload)
+}
+
+/// Poll a schema folder, reloading the schema when a file in it changes.
+fn start_schema_watch(app: AppHandle, directory: PathBuf) -> SchemaWatch {
+    let task = tauri::async_runtime::spawn(async move {
+        let mut last = snapshot(&directory);
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+
+            let current = snapshot(&directory);
+            if current == last {
+                continue;
+            }
+            last = current;
+
+            log::info!("schema folder {} changed, reloading", directory.display());
+            let state = app.state::<AppData>();
+            if let Err(e) = reload_from(&app, &state, Some(&directory)) {
+                log::warn!("{e}");
+            }
+        }
+    });
+    SchemaWatch { task }
+}
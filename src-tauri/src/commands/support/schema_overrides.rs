@@ -0,0 +1,291 @@
+//! Commands for the user's own schema overrides.
+//!
+//! Extensions can override the schema, but writing an extension just to rename
+//! PID-19 or make a field required for one site is overkill. These commands
+//! let the frontend edit a user-level [`SchemaOverride`] directly, in the same
+//! format extensions use, so anything an extension's schema can say, the user
+//! can say too.
+//!
+//! # Storage
+//!
+//! The overrides are kept under the `schemaOverrides` key of `settings.json` in
+//! the data root. They're loaded into the schema cache at startup and when the
+//! data folder is switched, and every change takes effect straight away.
+//!
+//! # Precedence
+//!
+//! User overrides are merged over the built-in schema and beneath extension
+//! overrides (see [`crate::schema::cache`]), so an extension that defines a
+//! field still has the final say over it.
+
+use std::path::Path;
+
+use tauri::{AppHandle, State};
+use tauri_plugin_store::StoreExt;
+
+use crate::extensions::types::{FieldOverride, Nullable, SchemaOverride, SegmentOverride};
+use crate::AppData;
+
+/// Store holding the user's settings.
+pub const SETTINGS_STORE: &str = "settings.json";
+
+/// Key of the user's schema overrides in the settings store.
+const SCHEMA_OVERRIDES_KEY: &str = "schemaOverrides";
+
+/// Get the user's schema overrides.
+///
+/// # Returns
+/// The overrides, with no segments if none have been set
+#[tauri::command]
+pub fn get_schema_overrides(state: State<'_, AppData>) -> SchemaOverride {
+    state.schema.user_overrides().unwrap_or_default()
+}
+
+/// Replace the user's schema overrides.
+///
+/// # Arguments
+/// * `overrides` - The new overrides; an override with no segments clears them
+/// * `app` - Tauri app handle, for the settings store
+/// * `state` - Application state containing the data root and schema cache
+///
+/// # Returns
+/// * `Ok(())` - The overrides were saved and applied
+/// * `Err(String)` - An override isn't valid, or the settings couldn't be saved
+#[tauri::command]
+pub async fn set_schema_overrides(
+    overrides: SchemaOverride,
+    app: AppHandle,
+    state: State<'_, AppData>,
+) -> Result<(), String> {
+    save_schema_overrides(&app, &state, overrides).await
+}
+
+/// Add or replace the user's override of one field or component.
+///
+/// An existing override of the same field and component is replaced.
+///
+/// # Arguments
+/// * `segment` - Segment name (e.g., "PID")
+/// * `field_override` - The override, naming the field and component it applies to
+/// * `app` - Tauri app handle, for the settings store
+/// * `state` - Application state containing the data root and schema cache
+///
+/// # Returns
+/// * `Ok(SchemaOverride)` - The user's overrides after the change
+/// * `Err(String)` - The override isn't valid, or the settings couldn't be saved
+#[tauri::command]
+pub async fn set_field_override(
+    segment: String,
+    field_override: FieldOverride,
+    app: AppHandle,
+    state: State<'_, AppData>,
+) -> Result<SchemaOverride, String> {
+    let mut overrides = state.schema.user_overrides().unwrap_or_default();
+    let fields = overrides
+        .segments
+        .get_or_insert_with(Default::default)
+        .entry(segment)
+        .or_insert_with(SegmentOverride::default)
+        .fields
+        .get_or_insert_with(Vec::new);
+    match fields
+        .iter_mut()
+        .find(|f| f.field == field_override.field && f.component == field_override.component)
+    {
+        Some(existing) => *existing = field_override,
+        None => fields.push(field_override),
+    }
+
+    save_schema_overrides(&app, &state, overrides).await?;
+    Ok(get_schema_overrides(state))
+}
+
+/// Remove the user's override of one field or component.
+///
+/// # Arguments
+/// * `segment` - Segment name (e.g., "PID")
+/// * `field` - 1-based field number
+/// * `component` - 1-based component number, or None for the whole field
+/// * `app` - Tauri app handle, for the settings store
+/// * `state` - Application state containing the data root and schema cache
+///
+/// # Returns
+/// * `Ok(SchemaOverride)` - The user's overrides after the change
+/// * `Err(String)` - The settings couldn't be saved
+#[tauri::command]
+pub async fn remove_field_override(
+    segment: String,
+    field: u32,
+    component: Option<u32>,
+    app: AppHandle,
+    state: State<'_, AppData>,
+) -> Result<SchemaOverride, String> {
+    let mut overrides = state.schema.user_overrides().unwrap_or_default();
+    if let Some(segments) = overrides.segments.as_mut() {
+        if let Some(fields) = segments
+            .get_mut(&segment)
+            .and_then(|segment_override| segment_override.fields.as_mut())
+        {
+            fields.retain(|f| f.field != field || f.component != component);
+        }
+    }
+
+    save_schema_overrides(&app, &state, overrides).await?;
+    Ok(get_schema_overrides(state))
+}
+
+/// Read the user's schema overrides from the settings store in a data root.
+///
+/// Overrides that can't be read are logged and ignored, so a bad setting never
+/// stops Hermes from starting.
+///
+/// # Returns
+/// The overrides, or None if there are none
+pub fn load_schema_overrides(app: &AppHandle, data_root: &Path) -> Option<SchemaOverride> {
+    let store = match app.store(data_root.join(SETTINGS_STORE)) {
+        Ok(store) => store,
+        Err(e) => {
+            log::warn!("failed to open settings to read schema overrides: {e}");
+            return None;
+        }
+    };
+    let value = store.get(SCHEMA_OVERRIDES_KEY)?;
+    match serde_json::from_value::<SchemaOverride>(value) {
+        Ok(overrides) => prune(overrides),
+        Err(e) => {
+            log::warn!("ignoring unreadable schema overrides: {e}");
+            None
+        }
+    }
+}
+
+/// Check, store, and apply the user's schema overrides.
+async fn save_schema_overrides(
+    app: &AppHandle,
+    state: &AppData,
+    overrides: SchemaOverride,
+) -> Result<(), String> {
+    validate(&overrides)?;
+    let overrides = prune(overrides);
+
+    let path = state.data_root.lock().await.path.join(SETTINGS_STORE);
+    let store = app
+        .store(path)
+        .map_err(|e| format!("Failed to open settings: {e}"))?;
+    match &overrides {
+        Some(overrides) => {
+            let value = serde_json::to_value(overrides)
+                .map_err(|e| format!("Failed to serialize schema overrides: {e}"))?;
+            store.set(SCHEMA_OVERRIDES_KEY, value);
+        }
+        None => {
+            store.delete(SCHEMA_OVERRIDES_KEY);
+        }
+    }
+    store
+        .save()
+        .map_err(|e| format!("Failed to save schema overrides: {e}"))?;
+
+    state.schema.set_user_overrides(overrides);
+    Ok(())
+}
+
+/// Check that every field override can be applied.
+fn validate(overrides: &SchemaOverride) -> Result<(), String> {
+    for (segment, segment_override) in overrides.segments.iter().flatten() {
+        for field in segment_override.fields.iter().flatten() {
+            let path = match field.component {
+                Some(component) => format!("{segment}.{}.{component}", field.field),
+                None => format!("{segment}.{}", field.field),
+            };
+            if field.field == 0 || field.component == Some(0) {
+                return Err(format!("{path}: field and component numbers start at 1"));
+            }
+            if let Some(Nullable::Value(pattern)) = &field.pattern {
+                regex::Regex::new(pattern)
+                    .map_err(|e| format!("{path}: invalid pattern {pattern}: {e}"))?;
+            }
+            if let Some(Nullable::Value(datatype)) = &field.datatype {
+                if !matches!(datatype.as_str(), "date" | "datetime") {
+                    return Err(format!(
+                        "{path}: unknown datatype {datatype} (expected date or datetime)"
+                    ));
+                }
+            }
+            if let Some(Nullable::Value(classification)) = &field.classification {
+                if !matches!(classification.as_str(), "phi" | "not-phi") {
+                    return Err(format!(
+                        "{path}: unknown classification {classification} (expected phi or not-phi)"
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Drop segments left with no field overrides.
+///
+/// # Returns
+/// The overrides, or None if no segment has any left
+fn prune(mut overrides: SchemaOverride) -> Option<SchemaOverride> {
+    let segments = overrides.segments.as_mut()?;
+    segments.retain(|_, segment_override| {
+        segment_override
+            .fields
+            .as_ref()
+            .is_some_and(|fields| !fields.is_empty())
+    });
+    if segments.is_empty() {
+        None
+    } else {
+        Some(overrides)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    fn overrides(value: serde_json::Value) -> SchemaOverride {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn invalid_overrides_are_rejected() {
+        assert!(validate(&overrides(serde_json::json!({
+            "segments": { "PID": { "fields": [ { "field": 19, "name": "Member", "pattern": "^[0-9]+$" } ] } }
+        })))
+        .is_ok());
+        assert!(validate(&overrides(serde_json::json!({
+            "segments": { "PID": { "fields": [ { "field": 19, "pattern": "[" } ] } }
+        })))
+        .is_err());
+        assert!(validate(&overrides(serde_json::json!({
+            "segments": { "PID": { "fields": [ { "field": 7, "datatype": "time" } ] } }
+        })))
+        .is_err());
+        assert!(validate(&overrides(serde_json::json!({
+            "segments": { "PID": { "fields": [ { "field": 0 } ] } }
+        })))
+        .is_err());
+    }
+
+    #[test]
+    fn empty_segments_are_pruned() {
+        let pruned = prune(overrides(serde_json::json!({
+            "segments": {
+                "PID": { "fields": [ { "field": 19, "required": true } ] },
+                "PV1": { "fields": [] },
+                "OBX": {}
+            }
+        })))
+        .unwrap();
+        let segments = pruned.segments.unwrap();
+        assert_eq!(segments.keys().collect::<Vec<_>>(), ["PID"]);
+
+        assert!(prune(overrides(serde_json::json!({ "segments": { "PV1": {} } }))).is_none());
+        assert!(prune(SchemaOverride::default()).is_none());
+    }
+}
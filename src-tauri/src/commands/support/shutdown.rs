@@ -0,0 +1,331 @@
+//! Coordinated shutdown of background work when the app closes.
+//!
+//! Quitting used to end the process wherever it was: mid-way through a
+//! scheduled send, with audit events still queued, extensions killed without
+//! being asked to stop, and the last settings change possibly not yet written.
+//! Closing the main window or quitting now runs through a fixed sequence of
+//! [`ShutdownStage`]s first, and only exits once they're done.
+//!
+//! # Stages
+//!
+//! 1. Stop the listener, relay, and any drop watch, so no new messages arrive
+//! 2. Stop a scheduled send, letting the send in progress finish
+//! 3. Stop watching the open file
+//! 4. Ask the frontend to save its session (auto-saving the open file, if
+//!    auto-save is on), and wait for it to answer through [`session_saved`]
+//! 5. Export the audit events still queued
+//! 6. Shut extensions down gracefully
+//! 7. Write the control ID history and the persisted stores to disk
+//!
+//! A `shutdown-progress` event is emitted as each stage starts, so the
+//! frontend can show what's being waited on.
+//!
+//! # Timeout
+//!
+//! The whole sequence is bounded by a timeout the frontend configures through
+//! [`configure_shutdown`] (ten seconds unless changed). A stage still running
+//! when it expires is abandoned and the remaining stages are given one chance
+//! to finish without waiting, so a hung collector or extension can't keep the
+//! app open. The frontend gets at most half the timeout to save its session,
+//! leaving the rest for the stages after it. Asking to quit a second time while
+//! shutting down exits at once.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_store::StoreExt;
+use tokio::sync::{oneshot, Mutex};
+use tokio::time::{timeout, Instant};
+
+use super::store_health::STORES;
+use crate::events;
+use crate::AppData;
+
+/// How long shutdown waits for background work unless configured otherwise.
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest shutdown timeout that can be configured.
+const MAX_TIMEOUT_SECONDS: f32 = 300.0;
+
+/// A step of the shutdown sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShutdownStage {
+    /// Stopping the MLLP listener, relay, and any drop watch
+    StopListener,
+    /// Stopping a scheduled send after the send in progress
+    StopSchedule,
+    /// Stopping the watch on the open file
+    StopFileWatch,
+    /// Waiting for the frontend to save its session
+    SaveSession,
+    /// Exporting queued audit events
+    FlushAuditEvents,
+    /// Shutting extensions down
+    StopExtensions,
+    /// Writing persisted stores to disk
+    FlushStores,
+}
+
+impl ShutdownStage {
+    /// Every stage, in the order they run.
+    const ALL: [ShutdownStage; 7] = [
+        ShutdownStage::StopListener,
+        ShutdownStage::StopSchedule,
+        ShutdownStage::StopFileWatch,
+        ShutdownStage::SaveSession,
+        ShutdownStage::FlushAuditEvents,
+        ShutdownStage::StopExtensions,
+        ShutdownStage::FlushStores,
+    ];
+
+    /// What the stage is doing, for showing while it runs.
+    fn description(self) -> &'static str {
+        match self {
+            ShutdownStage::StopListener => "Stopping the listener",
+            ShutdownStage::StopSchedule => "Finishing the scheduled send",
+            ShutdownStage::StopFileWatch => "Stopping the file watch",
+            ShutdownStage::SaveSession => "Saving the session",
+            ShutdownStage::FlushAuditEvents => "Exporting queued audit events",
+            ShutdownStage::StopExtensions => "Shutting down extensions",
+            ShutdownStage::FlushStores => "Saving settings",
+        }
+    }
+}
+
+/// Payload of the `shutdown-progress` event.
+#[derive(Debug, Clone, Serialize)]
+pub struct ShutdownProgress {
+    /// Stage now running
+    pub stage: ShutdownStage,
+    /// What the stage is doing
+    pub description: &'static str,
+    /// Number of the stage, from 1
+    pub step: usize,
+    /// Number of stages
+    pub total: usize,
+}
+
+/// State of the shutdown sequence, held in [`AppData`].
+pub struct Shutdown {
+    /// Set once shutdown has started.
+    started: AtomicBool,
+    /// Set once the app may exit.
+    finished: AtomicBool,
+    /// How long to wait for the whole sequence.
+    timeout: Mutex<Duration>,
+    /// Answered by the frontend once it has saved its session.
+    session_saved: Mutex<Option<oneshot::Sender<()>>>,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Shutdown {
+            started: AtomicBool::new(false),
+            finished: AtomicBool::new(false),
+            timeout: Mutex::new(DEFAULT_TIMEOUT),
+            session_saved: Mutex::new(None),
+        }
+    }
+}
+
+/// Set how long shutdown waits for background work before exiting anyway.
+///
+/// # Arguments
+/// * `timeout_seconds` - The timeout, in seconds
+/// * `state` - Application state holding the shutdown settings
+///
+/// # Returns
+/// * `Ok(())` - The timeout was set
+/// * `Err(String)` - If the timeout isn't a positive number of at most 300 seconds
+#[tauri::command]
+pub async fn configure_shutdown(
+    timeout_seconds: f32,
+    state: State<'_, AppData>,
+) -> Result<(), String> {
+    *state.shutdown.timeout.lock().await = timeout_from_seconds(timeout_seconds)?;
+    Ok(())
+}
+
+/// Tell shutdown that the frontend has saved its session.
+///
+/// Called in answer to the `shutdown-save-session` event.
+#[tauri::command]
+pub async fn session_saved(state: State<'_, AppData>) -> Result<(), String> {
+    if let Some(saved) = state.shutdown.session_saved.lock().await.take() {
+        // shutdown may have stopped waiting already
+        let _ = saved.send(());
+    }
+    Ok(())
+}
+
+/// Handle a request to close the main window or quit.
+///
+/// Starts the shutdown sequence the first time it's called; the app exits by
+/// itself once the sequence is done. A second request while shutting down
+/// exits at once.
+///
+/// # Returns
+/// Whether the app may exit now; if not, the request should be prevented
+pub fn request_exit(app: &AppHandle) -> bool {
+    let Some(state) = app.try_state::<AppData>() else {
+        return true;
+    };
+    let shutdown = &state.shutdown;
+    if shutdown.finished.load(Ordering::SeqCst) {
+        return true;
+    }
+    if shutdown.started.swap(true, Ordering::SeqCst) {
+        log::warn!("exit requested again during shutdown, exiting without waiting");
+        shutdown.finished.store(true, Ordering::SeqCst);
+        app.exit(0);
+        return true;
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move { shut_down(&app).await });
+    false
+}
+
+/// Run every shutdown stage within the timeout, then exit.
+async fn shut_down(app: &AppHandle) {
+    let state = app.state::<AppData>();
+    let total_timeout = *state.shutdown.timeout.lock().await;
+    let deadline = Instant::now() + total_timeout;
+    log::info!("shutting down (timeout {total_timeout:?})");
+
+    for (index, stage) in ShutdownStage::ALL.into_iter().enumerate() {
+        let progress = ShutdownProgress {
+            stage,
+            description: stage.description(),
+            step: index + 1,
+            total: ShutdownStage::ALL.len(),
+        };
+        if let Err(e) = app.emit(events::SHUTDOWN_PROGRESS, &progress) {
+            log::warn!("failed to emit shutdown progress: {e}");
+        }
+
+        let mut remaining = deadline.saturating_duration_since(Instant::now());
+        if stage == ShutdownStage::SaveSession {
+            remaining = remaining.min(total_timeout / 2);
+        }
+        if timeout(remaining, run_stage(app, &state, stage))
+            .await
+            .is_err()
+        {
+            log::warn!("shutdown stage {stage:?} timed out, moving on");
+        }
+    }
+
+    log::info!("shutdown complete");
+    state.shutdown.finished.store(true, Ordering::SeqCst);
+    app.exit(0);
+}
+
+/// Run a single shutdown stage.
+async fn run_stage(app: &AppHandle, state: &AppData, stage: ShutdownStage) {
+    match stage {
+        ShutdownStage::StopListener => {
+            if let Some(listener) = state.listen_join.lock().await.take() {
+                listener.abort();
+            }
+            if let Some(relay) = state.relay_join.lock().await.take() {
+                relay.abort();
+            }
+            if let Some(watch) = state.drop_watch.lock().await.take() {
+                watch.stop();
+            }
+        }
+        ShutdownStage::StopSchedule => {
+            let running = state.schedule.lock().await.take();
+            if let Some(running) = running {
+                running.finish().await;
+            }
+        }
+        ShutdownStage::StopFileWatch => {
+            for (_, watch) in state.file_watch.lock().await.drain() {
+                watch.stop();
+            }
+            if let Some(watch) = state.schema_watch.lock().await.take() {
+                watch.stop();
+            }
+        }
+        ShutdownStage::SaveSession => {
+            let (saved, answer) = oneshot::channel();
+            *state.shutdown.session_saved.lock().await = Some(saved);
+            match app.emit(events::SHUTDOWN_SAVE_SESSION, ()) {
+                Ok(()) => {
+                    let _ = answer.await;
+                }
+                Err(e) => log::warn!("failed to ask the frontend to save its session: {e}"),
+            }
+        }
+        ShutdownStage::FlushAuditEvents => {
+            let exporter = state.audit_export.lock().await.take();
+            if let Some(exporter) = exporter {
+                exporter.finish().await;
+            }
+        }
+        ShutdownStage::StopExtensions => {
+            state
+                .extension_host
+                .lock()
+                .await
+                .shutdown_all(&state.window_manager)
+                .await;
+        }
+        ShutdownStage::FlushStores => {
+            let root = state.data_root.lock().await.path.clone();
+            if let Err(e) = state.control_ids.save(app, &root) {
+                log::error!("failed to save control ID history on shutdown: {e}");
+            }
+            for name in STORES {
+                let Some(store) = app.get_store(root.join(name)) else {
+                    continue;
+                };
+                if let Err(e) = store.save() {
+                    log::error!("failed to save {name} on shutdown: {e}");
+                }
+            }
+        }
+    }
+}
+
+/// Check a configured shutdown timeout.
+fn timeout_from_seconds(seconds: f32) -> Result<Duration, String> {
+    if !seconds.is_finite() || seconds <= 0.0 || seconds > MAX_TIMEOUT_SECONDS {
+        return Err(format!(
+            "Shutdown timeout must be between 0 and {MAX_TIMEOUT_SECONDS} seconds, got {seconds}"
+        ));
+    }
+    Ok(Duration::from_secs_f32(seconds))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_the_timeout() {
+        assert_eq!(
+            timeout_from_seconds(2.5).unwrap(),
+            Duration::from_millis(2500)
+        );
+        for invalid in [0.0, -1.0, f32::NAN, f32::INFINITY, 301.0] {
+            assert!(timeout_from_seconds(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn stops_incoming_work_before_flushing() {
+        let position = |stage| ShutdownStage::ALL.iter().position(|s| *s == stage).unwrap();
+
+        // nothing new should be queued once the queues are being flushed
+        assert!(position(ShutdownStage::StopListener) < position(ShutdownStage::FlushAuditEvents));
+        assert!(position(ShutdownStage::StopSchedule) < position(ShutdownStage::FlushAuditEvents));
+        // the frontend's session save writes settings the last stage flushes
+        assert!(position(ShutdownStage::SaveSession) < position(ShutdownStage::FlushStores));
+    }
+}
@@ -0,0 +1,331 @@
+//! Integrity check and repair of persisted stores at startup.
+//!
+//! Stores under the data root are written whole, so a power cut or crash during
+//! a write can leave one empty or truncated. Left alone, the store then fails to
+//! deserialize and the feature relying on it quietly starts from nothing, or
+//! stops working altogether.
+//!
+//! # Startup Pass
+//!
+//! Before the frontend loads anything, each store is parsed:
+//!
+//! * **Healthy** stores are copied to a `.bak` file next to them, so there's
+//!   always a last-known-good copy to recover from
+//! * **Corrupt** stores are moved to the `quarantine` folder under the data root,
+//!   where they can be inspected or recovered by hand, and the `.bak` copy is
+//!   restored in their place if it parses
+//! * **Leftover partial files** from interrupted writes are removed
+//!
+//! The findings are kept for [`get_startup_health`], so the frontend can tell the
+//! user what was repaired instead of the store silently reverting to defaults.
+//! The pass runs again when the data root is switched.
+//!
+//! # Checked Stores
+//!
+//! Only stores Hermes writes itself are listed in [`STORES`]. Validation
+//! profiles are written by hand, and their loader already reports a profile
+//! that doesn't parse, so quarantining one mid-edit would only get in the way.
+//! Extension data folders belong to the extensions.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use crate::commands::{CONNECTIONS_STORE, HISTORY_STORE, SETTINGS_STORE};
+use crate::AppData;
+
+/// Folder under the data root that corrupt stores are moved to.
+pub const QUARANTINE_DIR: &str = "quarantine";
+
+/// Stores written by Hermes, relative to the data root, checked at startup.
+pub(super) const STORES: &[&str] = &[SETTINGS_STORE, CONNECTIONS_STORE, HISTORY_STORE];
+
+/// Suffix of the last-known-good copy of a store.
+const BACKUP_SUFFIX: &str = "bak";
+
+/// Suffixes of files left behind by interrupted writes.
+const PARTIAL_SUFFIXES: &[&str] = &["partial", "tmp"];
+
+/// What was done about a store that failed its check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreRepair {
+    /// The store was quarantined and its backup restored in its place.
+    Restored,
+    /// The store was quarantined and had no usable backup, so it starts empty.
+    Quarantined,
+    /// A partial file from an interrupted write was removed.
+    Removed,
+}
+
+/// A problem found with a persisted store.
+#[derive(Debug, Clone, Serialize)]
+pub struct StoreIssue {
+    /// Path of the store, relative to the data root
+    pub store: String,
+    /// What was wrong with it
+    pub problem: String,
+    /// What was done about it
+    pub repair: StoreRepair,
+    /// Where the corrupt store was moved to, if it was quarantined
+    pub quarantined_to: Option<PathBuf>,
+}
+
+/// Result of the startup integrity pass.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StartupHealth {
+    /// Stores checked, relative to the data root
+    pub checked: Vec<String>,
+    /// Problems found and repaired
+    pub issues: Vec<StoreIssue>,
+}
+
+/// Get the findings of the integrity pass run on the current data root.
+///
+/// # Returns
+/// * `Ok(StartupHealth)` - The stores checked, and any problems found and repaired
+#[tauri::command]
+pub async fn get_startup_health(state: State<'_, AppData>) -> Result<StartupHealth, String> {
+    Ok(state.startup_health.lock().await.clone())
+}
+
+/// Check every store under a data root, repairing any that are corrupt.
+///
+/// # Arguments
+/// * `root` - The data root
+///
+/// # Returns
+/// The stores checked, and any problems found and repaired
+pub fn check_stores(root: &Path) -> StartupHealth {
+    check_listed_stores(root, STORES)
+}
+
+/// Check the given stores under a data root.
+fn check_listed_stores(root: &Path, stores: &[&str]) -> StartupHealth {
+    let mut health = StartupHealth::default();
+
+    for store in stores {
+        let path = root.join(store);
+
+        for suffix in PARTIAL_SUFFIXES {
+            let partial = sibling_with_suffix(&path, suffix);
+            if !partial.exists() {
+                continue;
+            }
+            match std::fs::remove_file(&partial) {
+                Ok(()) => health.issues.push(StoreIssue {
+                    store: relative_name(root, &partial),
+                    problem: "left behind by an interrupted write".to_string(),
+                    repair: StoreRepair::Removed,
+                    quarantined_to: None,
+                }),
+                Err(e) => log::warn!("failed to remove partial file {}: {e}", partial.display()),
+            }
+        }
+
+        if !path.exists() {
+            continue;
+        }
+        let Some(format) = StoreFormat::of(&path) else {
+            log::warn!("don't know how to check store {store}");
+            continue;
+        };
+        let store = relative_name(root, &path);
+
+        health.checked.push(store.clone());
+        match format.check(&path) {
+            Ok(()) => {
+                if let Err(e) = std::fs::copy(&path, sibling_with_suffix(&path, BACKUP_SUFFIX)) {
+                    log::warn!("failed to back up {}: {e}", path.display());
+                }
+            }
+            Err(problem) => {
+                log::warn!("store {store} is corrupt: {problem}");
+                if let Some(issue) = repair(root, &path, &store, format, problem) {
+                    health.issues.push(issue);
+                }
+            }
+        }
+    }
+
+    health
+}
+
+/// How a store is serialised.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StoreFormat {
+    Json,
+    Toml,
+}
+
+impl StoreFormat {
+    /// Format of a store, from its file extension.
+    fn of(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?;
+        if extension.eq_ignore_ascii_case("json") {
+            Some(StoreFormat::Json)
+        } else if extension.eq_ignore_ascii_case("toml") {
+            Some(StoreFormat::Toml)
+        } else {
+            None
+        }
+    }
+
+    /// Check that a file parses in this format.
+    fn check(self, path: &Path) -> Result<(), String> {
+        let contents = std::fs::read(path).map_err(|e| format!("unreadable: {e}"))?;
+        if contents.iter().all(u8::is_ascii_whitespace) {
+            return Err("the file is empty".to_string());
+        }
+        match self {
+            StoreFormat::Json => serde_json::from_slice::<serde_json::Value>(&contents)
+                .map(|_| ())
+                .map_err(|e| format!("invalid JSON: {e}")),
+            StoreFormat::Toml => {
+                let contents =
+                    std::str::from_utf8(&contents).map_err(|e| format!("invalid UTF-8: {e}"))?;
+                toml::from_str::<toml::Value>(contents)
+                    .map(|_| ())
+                    .map_err(|e| format!("invalid TOML: {e}"))
+            }
+        }
+    }
+}
+
+/// Quarantine a corrupt store, and restore its backup if that's usable.
+fn repair(
+    root: &Path,
+    path: &Path,
+    store: &str,
+    format: StoreFormat,
+    problem: String,
+) -> Option<StoreIssue> {
+    let quarantine_dir = root.join(QUARANTINE_DIR);
+    let timestamp = jiff::Zoned::now().strftime("%Y%m%d-%H%M%S");
+    let quarantined = quarantine_dir.join(format!("{}.{timestamp}", store.replace('/', "_")));
+    let moved =
+        std::fs::create_dir_all(&quarantine_dir).and_then(|()| std::fs::rename(path, &quarantined));
+    if let Err(e) = moved {
+        log::error!("failed to quarantine {}: {e}", path.display());
+        return None;
+    }
+
+    let backup = sibling_with_suffix(path, BACKUP_SUFFIX);
+    let restored =
+        backup.exists() && format.check(&backup).is_ok() && std::fs::copy(&backup, path).is_ok();
+    if restored {
+        log::info!("restored {store} from its backup");
+    }
+
+    Some(StoreIssue {
+        store: store.to_string(),
+        problem,
+        repair: if restored {
+            StoreRepair::Restored
+        } else {
+            StoreRepair::Quarantined
+        },
+        quarantined_to: Some(quarantined),
+    })
+}
+
+/// Path of a file next to a store with a suffix added, e.g. its backup.
+fn sibling_with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Path of a store relative to the data root, with forward slashes.
+fn relative_name(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use crate::commands::temp_dir;
+
+    const TEST_STORES: &[&str] = &["settings.json", "history/sent.toml"];
+
+    #[test]
+    fn backs_up_healthy_stores() {
+        let root = temp_dir("health");
+        std::fs::create_dir_all(root.join("history")).unwrap();
+        std::fs::write(root.join("settings.json"), "{\"theme\":\"dark\"}").unwrap();
+        std::fs::write(root.join("history/sent.toml"), "count = 1").unwrap();
+
+        let health = check_listed_stores(&root, TEST_STORES);
+        assert!(health.issues.is_empty());
+        assert_eq!(health.checked, vec!["settings.json", "history/sent.toml"]);
+        assert_eq!(
+            std::fs::read_to_string(root.join("settings.json.bak")).unwrap(),
+            "{\"theme\":\"dark\"}"
+        );
+        assert!(root.join("history/sent.toml.bak").exists());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn restores_corrupt_stores_from_backup() {
+        let root = temp_dir("health");
+        std::fs::create_dir_all(root.join("history")).unwrap();
+        std::fs::write(root.join("settings.json"), "{\"theme\":\"dark\"}").unwrap();
+        check_listed_stores(&root, TEST_STORES);
+
+        // a write cut short
+        std::fs::write(root.join("settings.json"), "{\"theme\":\"da").unwrap();
+        let health = check_listed_stores(&root, TEST_STORES);
+
+        assert_eq!(health.issues.len(), 1);
+        let issue = &health.issues[0];
+        assert_eq!(issue.store, "settings.json");
+        assert_eq!(issue.repair, StoreRepair::Restored);
+        assert_eq!(
+            std::fs::read_to_string(issue.quarantined_to.as_ref().unwrap()).unwrap(),
+            "{\"theme\":\"da"
+        );
+        assert_eq!(
+            std::fs::read_to_string(root.join("settings.json")).unwrap(),
+            "{\"theme\":\"dark\"}"
+        );
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn quarantines_corrupt_stores_without_backup() {
+        let root = temp_dir("health");
+        std::fs::create_dir_all(root.join("history")).unwrap();
+        std::fs::write(root.join("settings.json.partial"), "{").unwrap();
+        std::fs::write(root.join("history/sent.toml"), "  \n").unwrap();
+
+        let health = check_listed_stores(&root, TEST_STORES);
+        let repairs: Vec<_> = health
+            .issues
+            .iter()
+            .map(|issue| (issue.store.as_str(), issue.repair))
+            .collect();
+        assert_eq!(
+            repairs,
+            vec![
+                ("settings.json.partial", StoreRepair::Removed),
+                ("history/sent.toml", StoreRepair::Quarantined),
+            ]
+        );
+        assert_eq!(health.issues[1].problem, "the file is empty");
+        assert!(!root.join("settings.json.partial").exists());
+        assert!(!root.join("history/sent.toml").exists());
+        assert!(health.issues[1].quarantined_to.as_ref().unwrap().exists());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
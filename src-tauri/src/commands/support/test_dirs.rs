@@ -0,0 +1,12 @@
+//! Scratch directories for tests that read and write files.
+
+use std::path::PathBuf;
+
+/// Create an empty directory under the system's temporary directory, named
+/// with `prefix` and a random suffix so tests running in parallel don't share
+/// one.
+pub(crate) fn temp_dir(prefix: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("hermes-{prefix}-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).expect("can create a temporary directory");
+    dir
+}
@@ -0,0 +1,591 @@
+//! Test case files and running them.
+//!
+//! A test case describes a message, what happens to it, and what should come
+//! out the other end. Running it produces a pass or fail, with the parts of the
+//! output that didn't meet the expectation.
+//!
+//! # Test Case Files
+//!
+//! Test cases are TOML or JSON files in the `test-cases` folder of the data
+//! root, identified by their file name without the extension:
+//!
+//! ```toml
+//! name = "Acme ADT is acknowledged"
+//! description = "A01 from the template, through the inbound pipeline, to Acme"
+//! pipeline = "acme-inbound"   # optional, run before sending or comparing
+//!
+//! [input]
+//! template = "adt-a01"        # a user template, or:
+//! # message = "MSH|..."       # the message itself, or:
+//! # file = "inputs/a01.hl7"   # a message file, relative to the test-cases folder
+//!
+//! [send]                      # optional
+//! host = "localhost"
+//! port = 2575
+//! timeout = 10                # seconds to wait for a response (default: 10)
+//! encoding = "utf8"           # default: utf8
+//!
+//! [expect]
+//! message = """
+//! MSH|^~\&|ACME|HOSP|||{datetime}||ACK^A01|{*}|P|2.5.1
+//! MSA|AA|{any}
+//! """                         # or file = "expected/a01-ack.hl7"
+//! ```
+//!
+//! # Running
+//!
+//! The input is prepared the way it would be sent from the editor: `{now}`,
+//! `{auto}`, and the other placeholders are filled in and formulas evaluated.
+//! The pipeline, if any, is then run on it. With `[send]`, the result is sent
+//! once over MLLP and the response is checked against the expectation;
+//! otherwise the result itself is. Expectations may hold placeholders, see
+//! [`match_expectation`].
+//!
+//! Sends don't go through extensions, connection profiles, or send hooks, so a
+//! test case checks the interface and nothing configured in Hermes.
+
+use bytes::BytesMut;
+use futures::{sink::SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tauri::State;
+use tokio::{net::TcpStream, time::timeout};
+use tokio_util::codec::Framed;
+
+use crate::commands::{
+    list_definitions, load_definition, match_expectation, prepare_message, read_user_template,
+    strip_document_metadata, Definition, ExpectationMismatch, Framing, FramingCodec,
+    MessageEncoding, Pipeline, MAPPING_TABLES_DIR, PIPELINES_DIR, TEMPLATES_DIR,
+};
+use crate::AppData;
+
+/// Folder under the data root that holds test cases.
+pub const TEST_CASES_DIR: &str = "test-cases";
+
+/// Seconds to wait for a response when a test case doesn't say.
+const DEFAULT_TIMEOUT_SECONDS: u64 = 10;
+
+/// A parsed test case.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TestCase {
+    /// Human-readable name (defaults to the file name)
+    pub name: Option<String>,
+    /// What the test case checks, shown alongside its name
+    pub description: Option<String>,
+    /// Pipeline to run on the input, by identifier
+    pub pipeline: Option<String>,
+    /// The message to start from
+    pub input: TestInput,
+    /// Where to send the message, if the response is what's checked
+    pub send: Option<TestSend>,
+    /// What the output should look like
+    pub expect: TestExpectation,
+}
+
+/// Where a test case's input message comes from; exactly one is set.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TestInput {
+    /// The message itself
+    pub message: Option<String>,
+    /// A message file, relative to the test cases folder
+    pub file: Option<String>,
+    /// A user template, by identifier
+    pub template: Option<String>,
+}
+
+/// Where a test case sends its message.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TestSend {
+    /// Host to connect to
+    pub host: String,
+    /// Port to connect to
+    pub port: u16,
+    /// Seconds to wait for the connection and for the response
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+    /// Character encoding of the message and response
+    #[serde(default)]
+    pub encoding: MessageEncoding,
+}
+
+/// A test case's expected output; exactly one is set.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TestExpectation {
+    /// The expected message, with placeholders where values vary
+    pub message: Option<String>,
+    /// A file holding the expected message, relative to the test cases folder
+    pub file: Option<String>,
+}
+
+/// A test case found in the test cases folder.
+#[derive(Debug, Clone, Serialize)]
+pub struct TestCaseInfo {
+    /// Identifier used to select the test case (file name without extension)
+    pub id: String,
+    /// Name from the test case, or the identifier if it has none
+    pub name: String,
+    /// Description from the test case, if any
+    pub description: Option<String>,
+    /// Why the test case couldn't be loaded, if it couldn't
+    pub error: Option<String>,
+}
+
+/// The test cases folder and the test cases in it.
+#[derive(Debug, Clone, Serialize)]
+pub struct TestCases {
+    /// Absolute path of the test cases folder
+    pub directory: String,
+    /// Test cases found in the folder, sorted by identifier
+    pub tests: Vec<TestCaseInfo>,
+}
+
+/// Outcome of running a test case.
+#[derive(Debug, Clone, Serialize)]
+pub struct TestCaseResult {
+    /// Identifier of the test case
+    pub id: String,
+    /// Name of the test case
+    pub name: String,
+    /// Whether the output met the expectation
+    pub passed: bool,
+    /// The message that was sent, if the test case sends one
+    pub sent: Option<String>,
+    /// The message checked against the expectation, if the run got that far
+    pub output: Option<String>,
+    /// The expectation, if it could be read
+    pub expected: Option<String>,
+    /// Parts of the output that don't meet the expectation
+    pub mismatches: Vec<ExpectationMismatch>,
+    /// Why the test case couldn't be run, if it couldn't
+    pub error: Option<String>,
+    /// How long the run took, in milliseconds
+    pub duration: u64,
+}
+
+/// Outcome of running every test case.
+#[derive(Debug, Clone, Serialize)]
+pub struct TestRunReport {
+    /// Per-test results, sorted by identifier
+    pub results: Vec<TestCaseResult>,
+    /// Number of test cases that passed
+    pub passed: usize,
+    /// Number of test cases that failed or couldn't be run
+    pub failed: usize,
+}
+
+fn default_timeout() -> u64 {
+    DEFAULT_TIMEOUT_SECONDS
+}
+
+impl TestCase {
+    /// Parse a test case from TOML or JSON content.
+    ///
+    /// # Arguments
+    /// * `content` - File content
+    /// * `json` - Whether the content is JSON rather than TOML
+    ///
+    /// # Returns
+    /// * `Ok(TestCase)` - The test case
+    /// * `Err(String)` - The content isn't a valid test case
+    pub fn parse(content: &str, json: bool) -> Result<Self, String> {
+        let case: Self = if json {
+            serde_json::from_str(content).map_err(|e| format!("Failed to parse test case: {e}"))?
+        } else {
+            toml::from_str(content).map_err(|e| format!("Failed to parse test case: {e}"))?
+        };
+
+        let TestInput {
+            message,
+            file,
+            template,
+        } = &case.input;
+        if [message.is_some(), file.is_some(), template.is_some()]
+            .iter()
+            .filter(|set| **set)
+            .count()
+            != 1
+        {
+            return Err("The input needs exactly one of message, file, or template".to_string());
+        }
+        if case.expect.message.is_some() == case.expect.file.is_some() {
+            return Err("The expectation needs exactly one of message or file".to_string());
+        }
+        if let Some(send) = &case.send {
+            if send.host.trim().is_empty() || send.port == 0 {
+                return Err("Sending needs a host and a port".to_string());
+            }
+        }
+
+        Ok(case)
+    }
+}
+
+impl Definition for TestCase {
+    const KIND: &'static str = "test case";
+
+    fn parse_definition(content: &str, json: bool) -> Result<Self, String> {
+        TestCase::parse(content, json)
+    }
+}
+
+impl TestInput {
+    /// Read the input message.
+    fn read(&self, root: &Path, directory: &Path) -> Result<String, String> {
+        if let Some(message) = &self.message {
+            return Ok(message.trim().to_string());
+        }
+        if let Some(template) = &self.template {
+            return read_user_template(&root.join(TEMPLATES_DIR), template);
+        }
+        read_message_file(directory, self.file.as_deref().unwrap_or_default())
+    }
+}
+
+impl TestExpectation {
+    /// Read the expected message.
+    fn read(&self, directory: &Path) -> Result<String, String> {
+        match &self.message {
+            Some(message) => Ok(message.trim().to_string()),
+            None => read_message_file(directory, self.file.as_deref().unwrap_or_default()),
+        }
+    }
+}
+
+impl TestSend {
+    /// Send a message once and wait for the response.
+    async fn send(&self, message: &str) -> Result<String, String> {
+        let encoded = self
+            .encoding
+            .encode(message)
+            .map_err(|e| format!("Failed to encode message: {e}"))?;
+        let address = format!("{}:{}", self.host, self.port);
+        let wait = Duration::from_secs(self.timeout);
+
+        let stream = timeout(wait, TcpStream::connect(&address))
+            .await
+            .map_err(|_| format!("Timed out connecting to {address}"))?
+            .map_err(|e| format!("Failed to connect to {address}: {e}"))?;
+        let mut transport = Framed::new(stream, FramingCodec::new(Framing::default()));
+        transport
+            .send(BytesMut::from(encoded.as_slice()))
+            .await
+            .map_err(|e| format!("Failed to send message: {e:#}"))?;
+
+        let response = match timeout(wait, transport.next()).await {
+            Err(_) => return Err(format!("No response within {} seconds", self.timeout)),
+            Ok(None) => return Err("Connection closed by remote".to_string()),
+            Ok(Some(Err(e))) => return Err(format!("Failed to receive response: {e:#}")),
+            Ok(Some(Ok(response))) => response,
+        };
+        self.encoding
+            .decode(&response)
+            .map_err(|e| format!("Failed to decode response: {e}"))
+    }
+}
+
+/// List the test cases in the test cases folder, creating the folder if needed.
+///
+/// Test cases that fail to load are still listed, with the reason.
+///
+/// # Arguments
+/// * `state` - Application state containing the data root
+///
+/// # Returns
+/// * `Ok(TestCases)` - The folder and the test cases in it
+/// * `Err(String)` - The folder couldn't be created or read
+#[tauri::command]
+pub async fn list_test_cases(state: State<'_, AppData>) -> Result<TestCases, String> {
+    let directory = state.data_root.lock().await.path.join(TEST_CASES_DIR);
+    std::fs::create_dir_all(&directory)
+        .map_err(|e| format!("Failed to create test cases folder: {e}"))?;
+    let tests = list_case_files(&directory)?;
+    Ok(TestCases {
+        directory: directory.display().to_string(),
+        tests,
+    })
+}
+
+/// Run a test case.
+///
+/// A test case that can't be run (its pipeline is missing, the connection is
+/// refused) fails with the reason rather than returning an error.
+///
+/// # Arguments
+/// * `id` - Identifier of the test case
+/// * `state` - Application state containing the data root
+///
+/// # Returns
+/// The outcome of the run
+#[tauri::command]
+pub async fn run_test_case(
+    id: String,
+    state: State<'_, AppData>,
+) -> Result<TestCaseResult, String> {
+    let root = state.data_root.lock().await.path.clone();
+    Ok(run_case(&root, &id).await)
+}
+
+/// Run every test case in the test cases folder, one after another.
+///
+/// # Arguments
+/// * `state` - Application state containing the data root
+///
+/// # Returns
+/// * `Ok(TestRunReport)` - The outcome of each test case
+/// * `Err(String)` - The folder couldn't be created or read
+#[tauri::command]
+pub async fn run_all_test_cases(state: State<'_, AppData>) -> Result<TestRunReport, String> {
+    let root = state.data_root.lock().await.path.clone();
+    let directory = root.join(TEST_CASES_DIR);
+    std::fs::create_dir_all(&directory)
+        .map_err(|e| format!("Failed to create test cases folder: {e}"))?;
+
+    let mut results = Vec::new();
+    for case in list_case_files(&directory)? {
+        results.push(run_case(&root, &case.id).await);
+    }
+    let passed = results.iter().filter(|r| r.passed).count();
+    log::info!("ran {} test cases, {passed} passed", results.len());
+    Ok(TestRunReport {
+        failed: results.len() - passed,
+        passed,
+        results,
+    })
+}
+
+/// Load and run a test case from the data root.
+async fn run_case(root: &Path, id: &str) -> TestCaseResult {
+    let started = Instant::now();
+    let mut result = TestCaseResult {
+        id: id.to_string(),
+        name: id.to_string(),
+        passed: false,
+        sent: None,
+        output: None,
+        expected: None,
+        mismatches: Vec::new(),
+        error: None,
+        duration: 0,
+    };
+
+    let directory = root.join(TEST_CASES_DIR);
+    let outcome = match load_definition::<TestCase>(&directory, id) {
+        Ok((_, case)) => {
+            if let Some(name) = &case.name {
+                result.name = name.clone();
+            }
+            check(root, &directory, &case, &mut result).await
+        }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = outcome {
+        result.passed = false;
+        result.error = Some(e);
+    }
+    result.duration = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+    result
+}
+
+/// Run a test case, recording what it produced as it goes.
+async fn check(
+    root: &Path,
+    directory: &Path,
+    case: &TestCase,
+    result: &mut TestCaseResult,
+) -> Result<(), String> {
+    let expected = case.expect.read(directory)?;
+    result.expected = Some(expected.clone());
+
+    let input = case.input.read(root, directory)?;
+    let (mut message, _) = prepare_message(&input, false, &HashMap::new())?;
+    if let Some(pipeline) = &case.pipeline {
+        let (_, mut loaded) = load_definition::<Pipeline>(&root.join(PIPELINES_DIR), pipeline)?;
+        loaded.load_tables(&root.join(MAPPING_TABLES_DIR))?;
+        message = loaded
+            .apply(&message)
+            .map_err(|e| format!("Pipeline {pipeline} failed: {e}"))?;
+    }
+
+    let output = match &case.send {
+        Some(target) => {
+            result.sent = Some(message.clone());
+            target.send(&message).await?
+        }
+        None => message,
+    };
+    result.output = Some(output.clone());
+
+    let matched = match_expectation(&output, &expected)?;
+    result.passed = matched.matched;
+    result.mismatches = matched.mismatches;
+    Ok(())
+}
+
+/// Read the test cases in a folder, sorted by identifier.
+fn list_case_files(directory: &Path) -> Result<Vec<TestCaseInfo>, String> {
+    Ok(list_definitions::<TestCase>(directory)?
+        .into_iter()
+        .map(|(id, case)| match case {
+            Ok(case) => TestCaseInfo {
+                name: case.name.unwrap_or_else(|| id.clone()),
+                description: case.description,
+                error: None,
+                id,
+            },
+            Err(error) => TestCaseInfo {
+                name: id.clone(),
+                description: None,
+                error: Some(error),
+                id,
+            },
+        })
+        .collect())
+}
+
+/// Read a message file named in a test case, without any metadata header.
+fn read_message_file(directory: &Path, file: &str) -> Result<String, String> {
+    let path = directory.join(file);
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+    let (message, _) = strip_document_metadata(content.trim_end());
+    Ok(message.to_string())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use crate::commands::temp_dir;
+    use std::path::PathBuf;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    const PIPELINE: &str = r#"
+[[steps]]
+op = "set"
+path = "MSH.5"
+value = "ACME"
+"#;
+
+    const TRANSFORM_CASE: &str = r#"
+name = "Stamps the receiving application"
+pipeline = "acme"
+
+[input]
+message = "MSH|^~\\&|ADT|HOSP|||20250114||ADT^A01|MSG001|P|2.5.1\rPID|1||123^^^MRN||DOE^JOHN"
+
+[expect]
+message = "MSH|^~\\&|ADT|HOSP|{/AC.E/}||{date}||ADT^A01|{*}|P|2.5.1\rPID|1||{number}^^^MRN||DOE^JOHN"
+"#;
+
+    fn data_root() -> PathBuf {
+        let root = temp_dir("tests");
+        std::fs::create_dir_all(root.join(TEST_CASES_DIR)).unwrap();
+        std::fs::create_dir_all(root.join(PIPELINES_DIR)).unwrap();
+        std::fs::write(root.join(PIPELINES_DIR).join("acme.toml"), PIPELINE).unwrap();
+        root
+    }
+
+    #[test]
+    fn rejects_invalid_test_cases() {
+        assert!(TestCase::parse(TRANSFORM_CASE, false).is_ok());
+        // no input
+        assert!(TestCase::parse("[input]\n[expect]\nmessage = \"MSH|^~\\\\&\"", false).is_err());
+        // two inputs
+        assert!(TestCase::parse(
+            r#"{"input": {"message": "MSH", "template": "a"}, "expect": {"file": "b.hl7"}}"#,
+            true
+        )
+        .is_err());
+        // no expectation
+        assert!(TestCase::parse(r#"{"input": {"file": "a.hl7"}, "expect": {}}"#, true).is_err());
+        // nowhere to send
+        assert!(TestCase::parse(
+            r#"{"input": {"file": "a.hl7"}, "send": {"host": "", "port": 2575}, "expect": {"file": "b.hl7"}}"#,
+            true
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn runs_transform_test_cases() {
+        let root = data_root();
+        let directory = root.join(TEST_CASES_DIR);
+        std::fs::write(directory.join("stamp.toml"), TRANSFORM_CASE).unwrap();
+        std::fs::write(
+            directory.join("wrong.toml"),
+            TRANSFORM_CASE.replace("{/AC.E/}", "OTHER"),
+        )
+        .unwrap();
+        std::fs::write(directory.join("broken.toml"), "[input]").unwrap();
+
+        let passing = run_case(&root, "stamp").await;
+        assert!(passing.passed, "{passing:?}");
+        assert_eq!(passing.name, "Stamps the receiving application");
+        assert!(passing.output.unwrap().contains("|ACME|"));
+
+        let failing = run_case(&root, "wrong").await;
+        assert!(!failing.passed);
+        assert!(failing.error.is_none());
+        assert_eq!(failing.mismatches.len(), 1);
+        assert_eq!(failing.mismatches[0].path, "MSH.5");
+
+        let broken = run_case(&root, "broken").await;
+        assert!(!broken.passed);
+        assert!(broken.error.is_some());
+        assert!(run_case(&root, "../stamp").await.error.is_some());
+
+        let listed = list_case_files(&directory).unwrap();
+        let ids: Vec<&str> = listed.iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, ["broken", "stamp", "wrong"]);
+        assert!(listed[0].error.is_some());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn checks_responses_to_sent_messages() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buffer = [0u8; 1024];
+            let _ = socket.read(&mut buffer).await.unwrap();
+            socket
+                .write_all(b"\x0bMSH|^~\\&|ACME|HOSP|||20250114120000||ACK^A01|ACK1|P|2.5.1\rMSA|AA|MSG001\x1c\r")
+                .await
+                .unwrap();
+        });
+
+        let root = data_root();
+        let case = format!(
+            r#"
+pipeline = "acme"
+
+[input]
+message = "MSH|^~\\&|ADT|HOSP|||20250114||ADT^A01|MSG001|P|2.5.1"
+
+[send]
+host = "127.0.0.1"
+port = {port}
+
+[expect]
+message = "MSH|^~\\&|ACME|HOSP|||{{datetime}}||ACK^A01|{{any}}|P|2.5.1\rMSA|AA|MSG001"
+"#
+        );
+        std::fs::write(root.join(TEST_CASES_DIR).join("ack.toml"), case).unwrap();
+
+        let result = run_case(&root, "ack").await;
+        assert!(result.passed, "{result:?}");
+        assert!(result.sent.unwrap().contains("|ACME|"));
+        assert!(result.output.unwrap().contains("MSA|AA|MSG001"));
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
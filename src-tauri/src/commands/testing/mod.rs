@@ -0,0 +1,20 @@
+//! Interface regression tests.
+//!
+//! This module provides a test runner that checks what happens to a message on
+//! its way through an interface, by reusing the transformation pipelines, the
+//! MLLP framing used for sending, and expectation matching.
+//!
+//! # Modules
+//!
+//! - [`cases`] - Test case files, running them, and their results
+//!
+//! # Why Test Cases?
+//!
+//! Interface changes are usually checked by hand: send a message, eyeball the
+//! ACK, diff the engine's output against last week's. Capturing those checks in
+//! files turns them into a regression suite that can be re-run after every
+//! change to a pipeline or an engine's configuration.
+
+mod cases;
+
+pub use cases::*;
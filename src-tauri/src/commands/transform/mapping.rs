@@ -0,0 +1,398 @@
+//! Code mapping tables.
+//!
+//! Every interface project has crosswalks from a sending system's local codes
+//! to the codes the receiving system expects: sexes, patient classes, order
+//! codes, units. Mapping tables hold those crosswalks so pipelines can
+//! translate values through them and validation profiles can check that a
+//! value has a mapping at all.
+//!
+//! # Table Files
+//!
+//! Tables are CSV files in the `mapping-tables` folder of the data root,
+//! identified by their file name without the extension. They're usually
+//! exported from a spreadsheet and imported here, which normalises them to
+//! three columns:
+//!
+//! ```text
+//! source,target,description
+//! 1,M,Male
+//! 2,F,Female
+//! 9,U,
+//! ```
+//!
+//! When importing, the first row is taken as the header. Columns named
+//! `source` (or `local`, `from`) and `target` (or `standard`, `to`) are used if
+//! there are any, and the first two columns otherwise; a `description` column
+//! is kept if present. Blank rows are skipped, and a source code listed twice
+//! is an error rather than a guess at which mapping was meant.
+//!
+//! Tables are read each time they're used, so a table edited by hand takes
+//! effect on the next run.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use crate::commands::check_definition_id;
+use crate::AppData;
+
+/// Folder under the data root that holds mapping tables.
+pub const MAPPING_TABLES_DIR: &str = "mapping-tables";
+
+/// Header names accepted for the source column when importing.
+const SOURCE_HEADERS: &[&str] = &["source", "local", "from"];
+
+/// Header names accepted for the target column when importing.
+const TARGET_HEADERS: &[&str] = &["target", "standard", "to"];
+
+/// A single mapping from a local code to a standard one.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MappingEntry {
+    /// Code as the sending system has it
+    pub source: String,
+    /// Code it maps to
+    pub target: String,
+    /// What the code means, if the table says
+    pub description: Option<String>,
+}
+
+/// A mapping table and its entries.
+#[derive(Debug, Clone, Serialize)]
+pub struct MappingTable {
+    /// Identifier of the table (file name without extension)
+    pub id: String,
+    /// Entries in the order they appear in the file
+    pub entries: Vec<MappingEntry>,
+}
+
+/// A table found in the mapping tables folder.
+#[derive(Debug, Clone, Serialize)]
+pub struct MappingTableInfo {
+    /// Identifier of the table (file name without extension)
+    pub id: String,
+    /// Number of entries in the table
+    pub entries: usize,
+    /// Why the table couldn't be loaded, if it couldn't
+    pub error: Option<String>,
+}
+
+/// The mapping tables folder and the tables in it.
+#[derive(Debug, Clone, Serialize)]
+pub struct MappingTables {
+    /// Absolute path of the mapping tables folder
+    pub directory: String,
+    /// Tables found in the folder, sorted by identifier
+    pub tables: Vec<MappingTableInfo>,
+}
+
+impl MappingTable {
+    /// Parse a table from CSV, using its header row to find the columns.
+    ///
+    /// # Arguments
+    /// * `id` - Identifier to give the table
+    /// * `content` - CSV content, with a header row
+    ///
+    /// # Returns
+    /// * `Ok(MappingTable)` - The table
+    /// * `Err(String)` - The CSV can't be read, has fewer than two columns, or
+    ///   lists a source code twice
+    pub fn parse_csv(id: &str, content: &str) -> Result<Self, String> {
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .trim(csv::Trim::All)
+            .from_reader(content.as_bytes());
+
+        let headers = reader
+            .headers()
+            .map_err(|e| format!("Failed to read mapping table {id}: {e}"))?
+            .clone();
+        let column = |names: &[&str]| {
+            headers
+                .iter()
+                .position(|header| names.iter().any(|name| header.eq_ignore_ascii_case(name)))
+        };
+        let source_column = column(SOURCE_HEADERS).unwrap_or(0);
+        let target_column = column(TARGET_HEADERS).unwrap_or(1);
+        let description_column = column(&["description"]);
+        if headers.len() < 2 || source_column == target_column {
+            return Err(format!(
+                "Mapping table {id} needs a source and a target column"
+            ));
+        }
+
+        let mut entries = Vec::new();
+        let mut seen = HashSet::new();
+        for (index, record) in reader.records().enumerate() {
+            // the header is line 1
+            let line = index + 2;
+            let record = record.map_err(|e| format!("Failed to read mapping table {id}: {e}"))?;
+            if record.iter().all(str::is_empty) {
+                continue;
+            }
+            let source = record.get(source_column).unwrap_or_default().to_string();
+            if !seen.insert(source.clone()) {
+                return Err(format!(
+                    "Mapping table {id} lists {source:?} more than once (line {line})"
+                ));
+            }
+            entries.push(MappingEntry {
+                source,
+                target: record.get(target_column).unwrap_or_default().to_string(),
+                description: description_column
+                    .and_then(|column| record.get(column))
+                    .filter(|description| !description.is_empty())
+                    .map(str::to_string),
+            });
+        }
+
+        Ok(Self {
+            id: id.to_string(),
+            entries,
+        })
+    }
+
+    /// Render the table as CSV in the normalised three-column form.
+    pub fn to_csv(&self) -> Result<String, String> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        let write_error = |e: csv::Error| format!("Failed to write mapping table: {e}");
+        writer
+            .write_record(["source", "target", "description"])
+            .map_err(write_error)?;
+        for entry in &self.entries {
+            writer
+                .write_record([
+                    entry.source.as_str(),
+                    entry.target.as_str(),
+                    entry.description.as_deref().unwrap_or_default(),
+                ])
+                .map_err(write_error)?;
+        }
+        let bytes = writer
+            .into_inner()
+            .map_err(|e| format!("Failed to write mapping table: {e}"))?;
+        String::from_utf8(bytes).map_err(|e| format!("Failed to write mapping table: {e}"))
+    }
+
+    /// Target code for each source code.
+    #[must_use]
+    pub fn lookup(&self) -> BTreeMap<String, String> {
+        self.entries
+            .iter()
+            .map(|entry| (entry.source.clone(), entry.target.clone()))
+            .collect()
+    }
+}
+
+/// List the tables in the mapping tables folder, creating the folder if needed.
+///
+/// # Arguments
+/// * `state` - Application state containing the data root
+///
+/// # Returns
+/// * `Ok(MappingTables)` - The folder and the tables in it, including any that
+///   failed to load
+/// * `Err(String)` - The folder couldn't be created or read
+#[tauri::command]
+pub async fn list_mapping_tables(state: State<'_, AppData>) -> Result<MappingTables, String> {
+    let directory = mapping_tables_dir(&state).await?;
+    let entries = std::fs::read_dir(&directory)
+        .map_err(|e| format!("Failed to read mapping tables folder: {e}"))?;
+
+    let mut tables: Vec<MappingTableInfo> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| is_csv(path))
+        .filter_map(|path| {
+            let id = path.file_stem()?.to_str()?.to_string();
+            let info = match load_mapping_table(&directory, &id) {
+                Ok(table) => MappingTableInfo {
+                    entries: table.entries.len(),
+                    error: None,
+                    id,
+                },
+                Err(error) => MappingTableInfo {
+                    entries: 0,
+                    error: Some(error),
+                    id,
+                },
+            };
+            Some(info)
+        })
+        .collect();
+    tables.sort_by(|a, b| a.id.cmp(&b.id));
+
+    Ok(MappingTables {
+        directory: directory.display().to_string(),
+        tables,
+    })
+}
+
+/// Get a mapping table's entries.
+///
+/// # Arguments
+/// * `id` - Identifier of the table
+/// * `state` - Application state containing the data root
+///
+/// # Returns
+/// * `Ok(MappingTable)` - The table
+/// * `Err(String)` - The table doesn't exist or couldn't be loaded
+#[tauri::command]
+pub async fn get_mapping_table(
+    id: String,
+    state: State<'_, AppData>,
+) -> Result<MappingTable, String> {
+    let directory = mapping_tables_dir(&state).await?;
+    load_mapping_table(&directory, &id)
+}
+
+/// Import a mapping table from a CSV file.
+///
+/// The file is read, checked, and saved to the mapping tables folder in the
+/// normalised form, replacing any table with the same identifier.
+///
+/// # Arguments
+/// * `path` - CSV file to import
+/// * `id` - Identifier to save the table as; the file name without its
+///   extension if not given
+/// * `state` - Application state containing the data root
+///
+/// # Returns
+/// * `Ok(MappingTableInfo)` - The imported table
+/// * `Err(String)` - The file couldn't be read or isn't a valid table, or the
+///   identifier is invalid
+#[tauri::command]
+pub async fn import_mapping_table(
+    path: String,
+    id: Option<String>,
+    state: State<'_, AppData>,
+) -> Result<MappingTableInfo, String> {
+    let source = PathBuf::from(&path);
+    let id = match id {
+        Some(id) => id.trim().to_string(),
+        None => source
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .map(str::to_string)
+            .ok_or_else(|| format!("Invalid mapping table file: {path}"))?,
+    };
+    check_definition_id(&id, "mapping table")?;
+
+    let content =
+        std::fs::read_to_string(&source).map_err(|e| format!("Failed to read {path}: {e}"))?;
+    let table = MappingTable::parse_csv(&id, &content)?;
+
+    let directory = mapping_tables_dir(&state).await?;
+    let destination = directory.join(format!("{id}.csv"));
+    std::fs::write(&destination, table.to_csv()?)
+        .map_err(|e| format!("Failed to save mapping table {id}: {e}"))?;
+    log::info!(
+        "imported mapping table {id} with {} entries from {path}",
+        table.entries.len()
+    );
+
+    Ok(MappingTableInfo {
+        id,
+        entries: table.entries.len(),
+        error: None,
+    })
+}
+
+/// Delete a mapping table.
+///
+/// # Arguments
+/// * `id` - Identifier of the table
+/// * `state` - Application state containing the data root
+///
+/// # Returns
+/// * `Ok(())` - The table was deleted
+/// * `Err(String)` - The table doesn't exist or couldn't be deleted
+#[tauri::command]
+pub async fn delete_mapping_table(id: String, state: State<'_, AppData>) -> Result<(), String> {
+    check_definition_id(&id, "mapping table")?;
+    let directory = mapping_tables_dir(&state).await?;
+    std::fs::remove_file(directory.join(format!("{id}.csv")))
+        .map_err(|e| format!("Failed to delete mapping table {id}: {e}"))
+}
+
+/// Find and load a mapping table by identifier.
+///
+/// # Arguments
+/// * `directory` - The mapping tables folder
+/// * `id` - Identifier of the table
+pub fn load_mapping_table(directory: &Path, id: &str) -> Result<MappingTable, String> {
+    check_definition_id(id, "mapping table")?;
+    let path = directory.join(format!("{id}.csv"));
+    if !path.is_file() {
+        return Err(format!("Mapping table not found: {id}"));
+    }
+    let content = std::fs::read_to_string(&path)
+        .map_err(|e| format!("Failed to read mapping table {id}: {e}"))?;
+    MappingTable::parse_csv(id, &content)
+}
+
+/// The mapping tables folder, created if needed.
+async fn mapping_tables_dir(state: &AppData) -> Result<PathBuf, String> {
+    let directory = state.data_root.lock().await.path.join(MAPPING_TABLES_DIR);
+    std::fs::create_dir_all(&directory)
+        .map_err(|e| format!("Failed to create mapping tables folder: {e}"))?;
+    Ok(directory)
+}
+
+/// Whether a file is a CSV file.
+fn is_csv(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_columns_by_header() {
+        let csv = "Meaning,Local Code,Code\nMale,1,M\n";
+        // neither header is recognised, so the first two columns are used
+        let table = MappingTable::parse_csv("sex", csv).unwrap();
+        assert_eq!(table.entries[0].source, "Male");
+
+        let csv = "description,local,standard\nMale,1,M\n,,\nFemale, 2 ,F\n";
+        let table = MappingTable::parse_csv("sex", csv).unwrap();
+        assert_eq!(
+            table.entries,
+            vec![
+                MappingEntry {
+                    source: "1".to_string(),
+                    target: "M".to_string(),
+                    description: Some("Male".to_string()),
+                },
+                MappingEntry {
+                    source: "2".to_string(),
+                    target: "F".to_string(),
+                    description: Some("Female".to_string()),
+                },
+            ]
+        );
+        assert_eq!(table.lookup().get("2").map(String::as_str), Some("F"));
+    }
+
+    #[test]
+    fn round_trips_through_the_normalised_form() {
+        let csv = "source,target,description\n1,M,\"Male, adult\"\n2,F,\n";
+        let table = MappingTable::parse_csv("sex", csv).unwrap();
+        let normalised = table.to_csv().unwrap();
+        let reparsed = MappingTable::parse_csv("sex", &normalised).unwrap();
+        assert_eq!(reparsed.entries, table.entries);
+        assert_eq!(reparsed.entries[1].description, None);
+    }
+
+    #[test]
+    fn rejects_ambiguous_tables() {
+        let error = MappingTable::parse_csv("sex", "source,target\n1,M\n2,F\n1,U\n").unwrap_err();
+        assert!(error.contains("line 4"), "{error}");
+        assert!(MappingTable::parse_csv("sex", "code\n1\n").is_err());
+        assert!(check_definition_id("../sex", "mapping table").is_err());
+    }
+}
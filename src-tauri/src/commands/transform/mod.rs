@@ -0,0 +1,25 @@
+//! Message transformations.
+//!
+//! This module provides named pipelines of operations (set a field, map a
+//! value, copy a field, delete a segment, replace with a regular expression)
+//! that are run on the message in the editor or on a folder of messages, the
+//! way an interface engine transforms messages on their way through, and the
+//! code mapping tables they translate values with.
+//!
+//! # Modules
+//!
+//! - [`mapping`] - Code mapping tables imported from CSV
+//! - [`pipeline`] - Pipeline file parsing, listing, and execution
+//!
+//! # Why Pipelines?
+//!
+//! Testing an interface means checking what the engine's transformations do to
+//! real messages. Writing those transformations down as pipelines lets them be
+//! reproduced in Hermes, repeated over a folder of samples, and compared with
+//! the engine's output, without setting up the engine itself.
+
+mod mapping;
+mod pipeline;
+
+pub use mapping::*;
+pub use pipeline::*;
@@ -0,0 +1,808 @@
+//! Named transformation pipelines.
+//!
+//! Interface work is mostly about what an engine does to a message on its way
+//! through: stamping the receiving facility, translating local codes, moving a
+//! value from one field to another, dropping segments the downstream system
+//! doesn't take. A pipeline captures a sequence of those operations in a file,
+//! so the same transformation can be run on the message in the editor or on a
+//! whole folder of messages and the results compared with what the engine
+//! produced.
+//!
+//! # Pipeline Files
+//!
+//! Pipelines are TOML or JSON files in the `pipelines` folder of the data root.
+//! Like validation profiles, they're read each time they're listed or run, and
+//! are identified by their file name without the extension.
+//!
+//! ```toml
+//! name = "Acme inbound ADT"
+//! description = "What the engine does to ADT before it reaches Acme"
+//!
+//! [[steps]]
+//! op = "set"
+//! path = "MSH.5"
+//! value = "ACME"
+//!
+//! [[steps]]
+//! op = "map"
+//! path = "PID.8"
+//! values = { "1" = "M", "2" = "F" }
+//! default = "U"
+//!
+//! [[steps]]
+//! op = "map"
+//! path = "PV1.2"
+//! table = "acme-patient-class"   # a mapping table, see below
+//!
+//! [[steps]]
+//! op = "copy"
+//! from = "PID.3.1"
+//! to = "PID.2"
+//!
+//! [[steps]]
+//! op = "delete_segment"
+//! segment = "NK1"
+//!
+//! [[steps]]
+//! op = "regex"
+//! path = "PID.13"
+//! pattern = "[^0-9]"
+//! replacement = ""
+//! ```
+//!
+//! # Operations
+//!
+//! * `set` - write `value` to `path`
+//! * `map` - replace the value at `path` with its entry in `values` or in the
+//!   mapping `table` named (`values` win where both have an entry); values
+//!   without an entry are replaced with `default`, or left alone without one
+//! * `copy` - write the value at `from` to `to`
+//! * `delete_segment` - remove every occurrence of `segment`
+//! * `regex` - replace matches of `pattern` with `replacement` (which may refer
+//!   to groups as `$1`) in the value at `path`, or in the whole message without
+//!   a path
+//!
+//! Steps run in order, each on the result of the one before. A path that
+//! doesn't name a segment occurrence applies to every occurrence of the
+//! segment, so `OBX.11` sets the result status of every observation while
+//! `OBX[2].11` only sets the second; `copy` reads `from` at its first
+//! occurrence. Values are read and written as they appear in the message, with
+//! escape sequences and any component separators.
+//!
+//! Mapping tables are the CSV crosswalks in the `mapping-tables` folder (see
+//! [`super::mapping`]), loaded when the pipeline is run.
+//!
+//! If any step fails (a path naming a segment occurrence the message doesn't
+//! have, say), the whole run fails and the message is left as it was.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tauri::State;
+
+use super::mapping::{load_mapping_table, MAPPING_TABLES_DIR};
+use crate::commands::{
+    delete_segments, list_definitions, load_definition, set_fields, strip_document_metadata,
+    Definition, FieldEdit,
+};
+use crate::AppData;
+
+/// Folder under the data root that holds pipelines.
+pub const PIPELINES_DIR: &str = "pipelines";
+
+/// File extensions picked up when running a pipeline on a folder.
+const MESSAGE_EXTENSIONS: &[&str] = &["hl7", "txt"];
+
+/// A parsed pipeline.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Pipeline {
+    /// Human-readable name (defaults to the file name)
+    pub name: Option<String>,
+    /// What the pipeline is for, shown alongside its name
+    pub description: Option<String>,
+    /// Operations to apply, in order
+    #[serde(default)]
+    pub steps: Vec<PipelineStep>,
+}
+
+/// A single operation in a pipeline.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case", deny_unknown_fields)]
+pub enum PipelineStep {
+    /// Write a value to a field, repeat, or component
+    Set {
+        /// Path to write to (e.g., "MSH.5", "OBX[2].11")
+        path: String,
+        /// Value to write
+        value: String,
+    },
+    /// Translate the value at a path through a table
+    Map {
+        /// Path of the value to translate
+        path: String,
+        /// Replacement for each value
+        #[serde(default)]
+        values: BTreeMap<String, String>,
+        /// Mapping table to translate through, merged into `values` when loaded
+        table: Option<String>,
+        /// Replacement for values not in `values`; they're left alone without one
+        default: Option<String>,
+    },
+    /// Copy the value at one path to another
+    Copy {
+        /// Path to read from (first occurrence unless it names one)
+        from: String,
+        /// Path to write to
+        to: String,
+    },
+    /// Remove every occurrence of a segment
+    DeleteSegment {
+        /// Segment name (e.g., "NK1")
+        segment: String,
+    },
+    /// Replace matches of a regular expression
+    Regex {
+        /// Path of the value to search, or the whole message without one
+        path: Option<String>,
+        /// Regular expression to search for
+        pattern: String,
+        /// Replacement, which may refer to groups as `$1` or `${name}`
+        #[serde(default)]
+        replacement: String,
+        /// `pattern`, compiled when the pipeline is parsed
+        #[serde(skip)]
+        regex: Option<Regex>,
+    },
+}
+
+/// A pipeline found in the pipelines folder.
+#[derive(Debug, Clone, Serialize)]
+pub struct PipelineInfo {
+    /// Identifier used to select the pipeline (file name without extension)
+    pub id: String,
+    /// Name from the pipeline, or the identifier if it has none
+    pub name: String,
+    /// Description from the pipeline, if any
+    pub description: Option<String>,
+    /// Number of steps in the pipeline
+    pub steps: usize,
+    /// Why the pipeline couldn't be loaded, if it couldn't
+    pub error: Option<String>,
+}
+
+/// The pipelines folder and the pipelines in it.
+#[derive(Debug, Clone, Serialize)]
+pub struct Pipelines {
+    /// Absolute path of the pipelines folder
+    pub directory: String,
+    /// Pipelines found in the folder, sorted by identifier
+    pub pipelines: Vec<PipelineInfo>,
+}
+
+/// Outcome of running a pipeline on one file of a folder.
+#[derive(Debug, Clone, Serialize)]
+pub struct PipelineFileResult {
+    /// Path of the input file
+    pub file: String,
+    /// Path the transformed message was written to, if it was
+    pub output: Option<String>,
+    /// Whether the pipeline changed the message
+    pub changed: bool,
+    /// Why the file couldn't be transformed, if it couldn't
+    pub error: Option<String>,
+}
+
+/// Outcome of running a pipeline on a folder.
+#[derive(Debug, Clone, Serialize)]
+pub struct PipelineFolderReport {
+    /// Per-file results, sorted by path
+    pub files: Vec<PipelineFileResult>,
+    /// Number of files transformed and written
+    pub transformed: usize,
+    /// Number of files that failed
+    pub failed: usize,
+}
+
+impl Pipeline {
+    /// Parse a pipeline from TOML or JSON content.
+    ///
+    /// # Arguments
+    /// * `content` - File content
+    /// * `json` - Whether the content is JSON rather than TOML
+    ///
+    /// # Returns
+    /// * `Ok(Pipeline)` - The pipeline, with its patterns compiled
+    /// * `Err(String)` - The content isn't a valid pipeline
+    pub fn parse(content: &str, json: bool) -> Result<Self, String> {
+        let mut pipeline: Self = if json {
+            serde_json::from_str(content).map_err(|e| format!("Failed to parse pipeline: {e}"))?
+        } else {
+            toml::from_str(content).map_err(|e| format!("Failed to parse pipeline: {e}"))?
+        };
+
+        for (index, step) in pipeline.steps.iter_mut().enumerate() {
+            let number = index + 1;
+            match step {
+                PipelineStep::Set { path, .. } => {
+                    check_path(path, number)?;
+                }
+                PipelineStep::Map {
+                    path,
+                    values,
+                    table,
+                    default,
+                } => {
+                    check_path(path, number)?;
+                    if values.is_empty() && table.is_none() && default.is_none() {
+                        return Err(format!(
+                            "Step {number} needs values or a table to map through"
+                        ));
+                    }
+                }
+                PipelineStep::Copy { from, to } => {
+                    check_path(from, number)?;
+                    check_path(to, number)?;
+                }
+                PipelineStep::DeleteSegment { segment } => {
+                    if !is_segment_name(segment) {
+                        return Err(format!("Invalid segment name in step {number}: {segment}"));
+                    }
+                    if segment == "MSH" {
+                        return Err(format!("Step {number} can't delete the MSH segment"));
+                    }
+                }
+                PipelineStep::Regex {
+                    path,
+                    pattern,
+                    regex,
+                    ..
+                } => {
+                    if let Some(path) = path {
+                        check_path(path, number)?;
+                    }
+                    let compiled = Regex::new(pattern)
+                        .map_err(|e| format!("Invalid pattern in step {number}: {e}"))?;
+                    *regex = Some(compiled);
+                }
+            }
+        }
+
+        Ok(pipeline)
+    }
+
+    /// Load the mapping tables the pipeline's `map` steps name.
+    ///
+    /// Each table's entries are merged into the step's `values`, with the
+    /// step's own values taking precedence.
+    ///
+    /// # Arguments
+    /// * `directory` - The mapping tables folder
+    ///
+    /// # Returns
+    /// * `Ok(())` - Every table was loaded
+    /// * `Err(String)` - A table doesn't exist or couldn't be loaded
+    pub fn load_tables(&mut self, directory: &Path) -> Result<(), String> {
+        for (index, step) in self.steps.iter_mut().enumerate() {
+            let PipelineStep::Map {
+                values,
+                table: Some(table),
+                ..
+            } = step
+            else {
+                continue;
+            };
+            let mut merged = load_mapping_table(directory, table)
+                .map_err(|e| format!("Step {} failed: {e}", index + 1))?
+                .lookup();
+            merged.append(values);
+            *values = merged;
+        }
+        Ok(())
+    }
+
+    /// Run the pipeline on a message.
+    ///
+    /// # Returns
+    /// * `Ok(String)` - The message after every step
+    /// * `Err(String)` - A step failed; the message is left as it was
+    pub fn apply(&self, message: &str) -> Result<String, String> {
+        let mut current = message.to_string();
+        for (index, step) in self.steps.iter().enumerate() {
+            current = step
+                .apply(&current)
+                .map_err(|e| format!("Step {} failed: {e}", index + 1))?;
+        }
+        Ok(current)
+    }
+}
+
+impl Definition for Pipeline {
+    const KIND: &'static str = "pipeline";
+
+    fn parse_definition(content: &str, json: bool) -> Result<Self, String> {
+        Pipeline::parse(content, json)
+    }
+}
+
+impl PipelineStep {
+    /// Apply the step to a message.
+    fn apply(&self, message: &str) -> Result<String, String> {
+        let parsed = hl7_parser::parse_message_with_lenient_newlines(message)
+            .map_err(|e| format!("Failed to parse message: {e}"))?;
+
+        let edits = match self {
+            PipelineStep::Set { path, value } => occurrences(&parsed, path)
+                .into_iter()
+                .map(|path| edit(path, value.clone()))
+                .collect(),
+            PipelineStep::Map {
+                path,
+                values,
+                default,
+            } => occurrences(&parsed, path)
+                .into_iter()
+                .filter_map(|path| {
+                    let value = raw_value(&parsed, &path);
+                    let mapped = values.get(&value).or(default.as_ref())?;
+                    (*mapped != value).then(|| edit(path, mapped.clone()))
+                })
+                .collect(),
+            PipelineStep::Copy { from, to } => {
+                let value = raw_value(&parsed, from);
+                occurrences(&parsed, to)
+                    .into_iter()
+                    .map(|path| edit(path, value.clone()))
+                    .collect()
+            }
+            PipelineStep::DeleteSegment { segment } => {
+                let indices: Vec<usize> = parsed
+                    .segments()
+                    .enumerate()
+                    .filter(|(_, s)| s.name == segment)
+                    .map(|(index, _)| index)
+                    .collect();
+                if indices.is_empty() {
+                    return Ok(message.to_string());
+                }
+                return delete_segments(message, indices)
+                    .map(|result| result.message)
+                    .ok_or_else(|| format!("Failed to delete the {segment} segments"));
+            }
+            PipelineStep::Regex {
+                path,
+                replacement,
+                regex,
+                ..
+            } => {
+                let Some(regex) = regex else {
+                    return Err("Pattern wasn't compiled".to_string());
+                };
+                let Some(path) = path else {
+                    let replaced = regex.replace_all(message, replacement.as_str()).to_string();
+                    // make sure the replacement left something Hermes can still read
+                    hl7_parser::parse_message_with_lenient_newlines(&replaced)
+                        .map_err(|e| format!("Replacement broke the message: {e}"))?;
+                    return Ok(replaced);
+                };
+                occurrences(&parsed, path)
+                    .into_iter()
+                    .filter_map(|path| {
+                        let value = raw_value(&parsed, &path);
+                        let replaced = regex.replace_all(&value, replacement.as_str());
+                        (replaced != value).then(|| edit(path, replaced.to_string()))
+                    })
+                    .collect()
+            }
+        };
+
+        if edits.is_empty() {
+            return Ok(message.to_string());
+        }
+        set_fields(message, edits)
+    }
+}
+
+/// List the pipelines in the pipelines folder, creating the folder if needed.
+///
+/// Pipelines that fail to load are still listed, with the reason.
+///
+/// # Arguments
+/// * `state` - Application state containing the data root
+///
+/// # Returns
+/// * `Ok(Pipelines)` - The folder and the pipelines in it
+/// * `Err(String)` - The folder couldn't be created or read
+#[tauri::command]
+pub async fn list_pipelines(state: State<'_, AppData>) -> Result<Pipelines, String> {
+    let directory = state.data_root.lock().await.path.join(PIPELINES_DIR);
+    std::fs::create_dir_all(&directory)
+        .map_err(|e| format!("Failed to create pipelines folder: {e}"))?;
+    let pipelines = list_definitions::<Pipeline>(&directory)?
+        .into_iter()
+        .map(|(id, pipeline)| match pipeline {
+            Ok(pipeline) => PipelineInfo {
+                name: pipeline.name.unwrap_or_else(|| id.clone()),
+                description: pipeline.description,
+                steps: pipeline.steps.len(),
+                error: None,
+                id,
+            },
+            Err(error) => PipelineInfo {
+                name: id.clone(),
+                description: None,
+                steps: 0,
+                error: Some(error),
+                id,
+            },
+        })
+        .collect();
+    Ok(Pipelines {
+        directory: directory.display().to_string(),
+        pipelines,
+    })
+}
+
+/// Run a pipeline on a message.
+///
+/// # Arguments
+/// * `message` - Raw HL7 message text
+/// * `pipeline` - Identifier of the pipeline (its file name without extension)
+/// * `state` - Application state containing the data root
+///
+/// # Returns
+/// * `Ok(String)` - The transformed message
+/// * `Err(String)` - The pipeline couldn't be loaded, or one of its steps failed
+#[tauri::command]
+pub async fn run_pipeline(
+    message: String,
+    pipeline: String,
+    state: State<'_, AppData>,
+) -> Result<String, String> {
+    let root = state.data_root.lock().await.path.clone();
+    let (path, mut loaded) = load_definition::<Pipeline>(&root.join(PIPELINES_DIR), &pipeline)?;
+    loaded.load_tables(&root.join(MAPPING_TABLES_DIR))?;
+    log::debug!("running pipeline {}", path.display());
+    loaded.apply(&message)
+}
+
+/// Run a pipeline on every message in a folder, writing the results to another.
+///
+/// Each `.hl7` or `.txt` file in the input folder is transformed and written to
+/// the output folder under the same name. A file that fails is reported and
+/// skipped rather than stopping the run.
+///
+/// # Arguments
+/// * `pipeline` - Identifier of the pipeline
+/// * `input` - Folder of messages to transform
+/// * `output` - Folder to write the transformed messages to (created if missing)
+/// * `state` - Application state containing the data root
+///
+/// # Returns
+/// * `Ok(PipelineFolderReport)` - What happened to each file
+/// * `Err(String)` - The pipeline couldn't be loaded, or a folder couldn't be
+///   read or created
+#[tauri::command]
+pub async fn run_pipeline_on_folder(
+    pipeline: String,
+    input: String,
+    output: String,
+    state: State<'_, AppData>,
+) -> Result<PipelineFolderReport, String> {
+    let root = state.data_root.lock().await.path.clone();
+    let (path, mut loaded) = load_definition::<Pipeline>(&root.join(PIPELINES_DIR), &pipeline)?;
+    loaded.load_tables(&root.join(MAPPING_TABLES_DIR))?;
+    log::info!("running pipeline {} on {input}", path.display());
+    tokio::task::spawn_blocking(move || {
+        transform_folder(&loaded, Path::new(&input), Path::new(&output))
+    })
+    .await
+    .map_err(|e| format!("Failed to run pipeline: {e}"))?
+}
+
+/// Transform every message in a folder, writing the results to another.
+fn transform_folder(
+    pipeline: &Pipeline,
+    input: &Path,
+    output: &Path,
+) -> Result<PipelineFolderReport, String> {
+    if input == output {
+        return Err("The output folder must differ from the input folder".to_string());
+    }
+    let entries = std::fs::read_dir(input)
+        .map_err(|e| format!("Failed to read input folder {}: {e}", input.display()))?;
+    let mut inputs: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| {
+                    MESSAGE_EXTENSIONS
+                        .iter()
+                        .any(|e| e.eq_ignore_ascii_case(ext))
+                })
+        })
+        .collect();
+    inputs.sort();
+
+    std::fs::create_dir_all(output)
+        .map_err(|e| format!("Failed to create output folder {}: {e}", output.display()))?;
+
+    let files: Vec<PipelineFileResult> = inputs
+        .iter()
+        .map(|file| transform_file(pipeline, file, output))
+        .collect();
+    let transformed = files.iter().filter(|f| f.output.is_some()).count();
+    Ok(PipelineFolderReport {
+        failed: files.len() - transformed,
+        transformed,
+        files,
+    })
+}
+
+/// Transform one message file into the output folder.
+fn transform_file(pipeline: &Pipeline, file: &Path, output: &Path) -> PipelineFileResult {
+    let mut result = PipelineFileResult {
+        file: file.display().to_string(),
+        output: None,
+        changed: false,
+        error: None,
+    };
+
+    let content = match std::fs::read_to_string(file) {
+        Ok(content) => content,
+        Err(e) => {
+            result.error = Some(format!("Failed to read file: {e}"));
+            return result;
+        }
+    };
+    let (message, _) = strip_document_metadata(content.trim_end());
+
+    let transformed = match pipeline.apply(message) {
+        Ok(transformed) => transformed,
+        Err(e) => {
+            result.error = Some(e);
+            return result;
+        }
+    };
+    result.changed = transformed != message;
+
+    let Some(name) = file.file_name() else {
+        result.error = Some("File has no name".to_string());
+        return result;
+    };
+    let output_path = output.join(name);
+    match std::fs::write(&output_path, transformed) {
+        Ok(()) => result.output = Some(output_path.display().to_string()),
+        Err(e) => result.error = Some(format!("Failed to write {}: {e}", output_path.display())),
+    }
+    result
+}
+
+/// Whether a name looks like a segment name (e.g., "PID", "ZX1").
+fn is_segment_name(name: &str) -> bool {
+    name.len() == 3
+        && name
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+/// Check that a step's path names a segment and a field.
+fn check_path(path: &str, step: usize) -> Result<(), String> {
+    let valid = path.split_once('.').is_some_and(|(segment, rest)| {
+        let name = segment.split('[').next().unwrap_or(segment);
+        is_segment_name(name) && !rest.is_empty()
+    });
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid field path in step {step}: {path}"))
+    }
+}
+
+/// Paths for every occurrence of a path's segment, or the path itself if it
+/// names an occurrence.
+fn occurrences(msg: &hl7_parser::Message, path: &str) -> Vec<String> {
+    let Some((segment, rest)) = path.split_once('.') else {
+        return vec![path.to_string()];
+    };
+    if segment.contains('[') {
+        return vec![path.to_string()];
+    }
+    let count = msg.segments().filter(|s| s.name == segment).count();
+    (1..=count)
+        .map(|occurrence| format!("{segment}[{occurrence}].{rest}"))
+        .collect()
+}
+
+/// Raw value at a path, or an empty string if it isn't present.
+fn raw_value(msg: &hl7_parser::Message, path: &str) -> String {
+    msg.query(path)
+        .map(|value| value.raw_value().to_string())
+        .unwrap_or_default()
+}
+
+/// An edit writing a value to a path.
+fn edit(path: String, value: String) -> FieldEdit {
+    FieldEdit { path, value }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use crate::commands::temp_dir;
+
+    const MESSAGE: &str = "MSH|^~\\&|APP|FAC|||20250101||ADT^A01|1|P|2.5.1\r\
+        PID|1||123^^^MRN||Doe^John|||1|||||(555) 123-4567\r\
+        NK1|1|Doe^Jane\r\
+        OBX|1|NM|GLU||5.5||||||P\r\
+        OBX|2|NM|HGB||13||||||P";
+
+    const PIPELINE: &str = r#"
+name = "Acme inbound"
+
+[[steps]]
+op = "set"
+path = "MSH.5"
+value = "ACME"
+
+[[steps]]
+op = "map"
+path = "PID.8"
+values = { "1" = "M", "2" = "F" }
+default = "U"
+
+[[steps]]
+op = "copy"
+from = "PID.3.1"
+to = "PID.2"
+
+[[steps]]
+op = "delete_segment"
+segment = "NK1"
+
+[[steps]]
+op = "regex"
+path = "PID.13"
+pattern = "[^0-9]"
+
+[[steps]]
+op = "set"
+path = "OBX.11"
+value = "F"
+"#;
+
+    fn query(message: &str, path: &str) -> String {
+        let parsed = hl7_parser::parse_message_with_lenient_newlines(message).unwrap();
+        raw_value(&parsed, path)
+    }
+
+    #[test]
+    fn applies_steps_in_order() {
+        let pipeline = Pipeline::parse(PIPELINE, false).unwrap();
+        let result = pipeline.apply(MESSAGE).unwrap();
+
+        assert_eq!(query(&result, "MSH.5"), "ACME");
+        assert_eq!(query(&result, "PID.8"), "M");
+        assert_eq!(query(&result, "PID.2"), "123");
+        assert_eq!(query(&result, "PID.13"), "5551234567");
+        assert!(!result.contains("NK1"));
+        // paths without an occurrence apply to every segment
+        assert_eq!(query(&result, "OBX[1].11"), "F");
+        assert_eq!(query(&result, "OBX[2].11"), "F");
+    }
+
+    #[test]
+    fn maps_unknown_values_to_the_default() {
+        let pipeline = Pipeline::parse(
+            r#"{"steps": [{"op": "map", "path": "PID.8", "values": {"2": "F"}}]}"#,
+            true,
+        )
+        .unwrap();
+        // without a default, unmapped values are left alone
+        assert_eq!(pipeline.apply(MESSAGE).unwrap(), MESSAGE);
+
+        let pipeline = Pipeline::parse(
+            r#"{"steps": [{"op": "map", "path": "PID.8", "values": {"2": "F"}, "default": "U"}]}"#,
+            true,
+        )
+        .unwrap();
+        assert_eq!(query(&pipeline.apply(MESSAGE).unwrap(), "PID.8"), "U");
+    }
+
+    #[test]
+    fn maps_through_tables() {
+        let dir = temp_dir("tables");
+        std::fs::write(dir.join("sex.csv"), "source,target\n1,M\n2,F\n").unwrap();
+
+        let mut pipeline = Pipeline::parse(
+            "[[steps]]\nop = \"map\"\npath = \"PID.8\"\ntable = \"sex\"",
+            false,
+        )
+        .unwrap();
+        pipeline.load_tables(&dir).unwrap();
+        assert_eq!(query(&pipeline.apply(MESSAGE).unwrap(), "PID.8"), "M");
+
+        // the step's own values win over the table's
+        let mut pipeline = Pipeline::parse(
+            "[[steps]]\nop = \"map\"\npath = \"PID.8\"\ntable = \"sex\"\nvalues = { \"1\" = \"Z\" }",
+            false,
+        )
+        .unwrap();
+        pipeline.load_tables(&dir).unwrap();
+        assert_eq!(query(&pipeline.apply(MESSAGE).unwrap(), "PID.8"), "Z");
+
+        let mut pipeline = Pipeline::parse(
+            "[[steps]]\nop = \"map\"\npath = \"PID.8\"\ntable = \"missing\"",
+            false,
+        )
+        .unwrap();
+        assert!(pipeline.load_tables(&dir).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn failing_steps_stop_the_run() {
+        let pipeline = Pipeline::parse(
+            "[[steps]]\nop = \"set\"\npath = \"OBX[3].5\"\nvalue = \"x\"",
+            false,
+        )
+        .unwrap();
+        let error = pipeline.apply(MESSAGE).unwrap_err();
+        assert!(error.starts_with("Step 1 failed"), "{error}");
+    }
+
+    #[test]
+    fn rejects_invalid_pipelines() {
+        assert!(Pipeline::parse(
+            "[[steps]]\nop = \"set\"\npath = \"PID\"\nvalue = \"x\"",
+            false
+        )
+        .is_err());
+        assert!(Pipeline::parse("[[steps]]\nop = \"regex\"\npattern = \"(\"", false).is_err());
+        assert!(Pipeline::parse(
+            "[[steps]]\nop = \"delete_segment\"\nsegment = \"MSH\"",
+            false
+        )
+        .is_err());
+        assert!(Pipeline::parse("[[steps]]\nop = \"map\"\npath = \"PID.8\"", false).is_err());
+        assert!(Pipeline::parse("[[steps]]\nop = \"rename\"\npath = \"PID.3\"", false).is_err());
+        assert!(Pipeline::parse(
+            "[[steps]]\nop = \"set\"\npath = \"PID.3\"\nvaule = \"x\"",
+            false
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn transforms_folders() {
+        let dir = temp_dir("pipelines");
+        let input = dir.join("in");
+        let output = dir.join("out");
+        std::fs::create_dir_all(&input).unwrap();
+        std::fs::write(input.join("a.hl7"), MESSAGE).unwrap();
+        std::fs::write(input.join("b.hl7"), "not a message").unwrap();
+        std::fs::write(input.join("notes.md"), "ignored").unwrap();
+
+        let pipeline = Pipeline::parse(PIPELINE, false).unwrap();
+        let report = transform_folder(&pipeline, &input, &output).unwrap();
+        assert_eq!(report.files.len(), 2);
+        assert_eq!(report.transformed, 1);
+        assert_eq!(report.failed, 1);
+        assert!(report.files[0].changed);
+        assert!(report.files[1].error.is_some());
+
+        let written = std::fs::read_to_string(output.join("a.hl7")).unwrap();
+        assert_eq!(query(&written, "MSH.5"), "ACME");
+
+        std::fs::write(input.join("acme.toml"), PIPELINE).unwrap();
+        assert!(load_definition::<Pipeline>(&input, "acme").is_ok());
+        assert!(load_definition::<Pipeline>(&input, "../in/acme").is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
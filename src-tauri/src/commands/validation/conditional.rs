@@ -0,0 +1,257 @@
+//! Conditional (co-constraint) field rules.
+//!
+//! A flat `required` flag can't express most real conformance statements:
+//! OBX units are required for numeric results but meaningless for documents,
+//! and a parent document number only belongs on addenda, edits, and
+//! replacements. Marking such fields required flags every message that
+//! legitimately leaves them out, and leaving them optional misses the messages
+//! that should have them. Schema fields can instead carry conditions:
+//!
+//! * `required_if` - the field is required when the condition holds
+//! * `forbidden_unless` - the field must be empty unless the condition holds
+//!
+//! Conditions are checked against each occurrence of the segment separately, so
+//! a numeric OBX and a document OBX in the same message are each judged by
+//! their own value type.
+
+use hl7_parser::message::Segment;
+use hl7_parser::Message;
+
+use super::validate::{get_field_value, get_message_type, matches_trigger_filter};
+use super::{Severity, ValidationIssue, ValidationRule};
+use crate::schema::cache::SchemaCache;
+use crate::schema::segment::FieldCondition;
+
+/// Check `required_if` and `forbidden_unless` rules.
+///
+/// Fields marked `required = true` are left to the required field check, even
+/// if they also have a `required_if` condition.
+///
+/// # Arguments
+/// * `msg` - Parsed message
+/// * `schema` - Schema cache to validate against
+/// * `version` - HL7 version whose schema applies
+/// * `issues` - Collected validation issues
+pub(super) fn validate_conditional_fields(
+    msg: &Message,
+    schema: &SchemaCache,
+    version: &str,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    let (_msg_type, trigger_event) = get_message_type(msg);
+
+    for segment in msg.segments() {
+        check_segment_conditions(msg, segment, schema, version, &trigger_event, issues);
+    }
+}
+
+/// Check `required_if` and `forbidden_unless` rules for one segment occurrence.
+///
+/// # Arguments
+/// * `msg` - Parsed message
+/// * `segment` - Segment occurrence to check
+/// * `schema` - Schema cache to validate against
+/// * `version` - HL7 version whose schema applies
+/// * `trigger_event` - Trigger event of the message (MSH.9.2)
+/// * `issues` - Collected validation issues
+pub(super) fn check_segment_conditions(
+    msg: &Message,
+    segment: &Segment,
+    schema: &SchemaCache,
+    version: &str,
+    trigger_event: &str,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    let Ok(segment_schema) = schema.get_segment_for_version(segment.name, version) else {
+        return;
+    };
+
+    for field_def in segment_schema
+        .iter()
+        .filter(|f| matches_trigger_filter(f, trigger_event))
+    {
+        let required_if = field_def
+            .required_if
+            .as_ref()
+            .filter(|_| field_def.required != Some(true));
+        if required_if.is_none() && field_def.forbidden_unless.is_none() {
+            continue;
+        }
+
+        let value = get_field_value(segment, field_def.field, field_def.component, msg);
+        let populated = value.as_ref().is_some_and(|(v, _)| !v.is_empty());
+        let path = match field_def.component {
+            Some(c) => format!("{}.{}.{}", segment.name, field_def.field, c),
+            None => format!("{}.{}", segment.name, field_def.field),
+        };
+        let range = value
+            .as_ref()
+            .and_then(|(_, r)| *r)
+            .or(Some((segment.range.start, segment.range.end)));
+
+        if let Some(condition) = required_if {
+            if !populated && condition_holds(msg, segment, condition) {
+                issues.push(ValidationIssue {
+                    path: path.clone(),
+                    range,
+                    severity: Severity::Error,
+                    message: format!(
+                        "{} ({}) is required when {}",
+                        path,
+                        field_def.name,
+                        describe(condition)
+                    ),
+                    rule: ValidationRule::RequiredField,
+                    actual_value: None,
+                    fix: None,
+                });
+            }
+        }
+
+        if let Some(condition) = &field_def.forbidden_unless {
+            if populated && !condition_holds(msg, segment, condition) {
+                issues.push(ValidationIssue {
+                    path: path.clone(),
+                    range,
+                    severity: Severity::Error,
+                    message: format!(
+                        "{} ({}) must be empty unless {}",
+                        path,
+                        field_def.name,
+                        describe(condition)
+                    ),
+                    rule: ValidationRule::ForbiddenField,
+                    actual_value: value.map(|(v, _)| v),
+                    fix: None,
+                });
+            }
+        }
+    }
+}
+
+/// Whether the condition holds for a segment occurrence.
+///
+/// Paths into the segment being checked are read from that occurrence; paths
+/// into other segments are read from their first occurrence.
+fn condition_holds(msg: &Message, segment: &Segment, condition: &FieldCondition) -> bool {
+    let value = condition_value(msg, segment, &condition.path);
+    if condition.equals.is_none() && condition.one_of.is_none() {
+        return !value.is_empty();
+    }
+    condition
+        .equals
+        .as_ref()
+        .is_none_or(|equals| *equals == value)
+        && condition
+            .one_of
+            .as_ref()
+            .is_none_or(|values| values.contains(&value))
+}
+
+/// Decoded value at a condition's path, or an empty string if it isn't present.
+fn condition_value(msg: &Message, segment: &Segment, path: &str) -> String {
+    let mut parts = path.split('.');
+    let in_segment = parts.next() == Some(segment.name);
+    let field = parts.next().and_then(|part| part.parse::<u8>().ok());
+    let component = parts.next().and_then(|part| part.parse::<u8>().ok());
+
+    match (field, component) {
+        // a whole field, as a message query would return it
+        (Some(field), None) if in_segment && field >= 1 => segment
+            .fields
+            .get(usize::from(field) - 1)
+            .map(|f| msg.separators.decode(f.raw_value()).to_string())
+            .unwrap_or_default(),
+        (Some(field), Some(component)) if in_segment && field >= 1 && component >= 1 => {
+            get_field_value(segment, field, Some(component), msg)
+                .map(|(value, _)| value)
+                .unwrap_or_default()
+        }
+        _ => msg
+            .query(path)
+            .map(|value| msg.separators.decode(value.raw_value()).to_string())
+            .unwrap_or_default(),
+    }
+}
+
+/// Describe a condition for an issue message, e.g. "OBX.2 is one of 'NM', 'SN'".
+fn describe(condition: &FieldCondition) -> String {
+    match (&condition.equals, &condition.one_of) {
+        (Some(equals), _) => format!("{} is '{}'", condition.path, equals),
+        (None, Some(values)) => format!(
+            "{} is one of {}",
+            condition.path,
+            values
+                .iter()
+                .map(|value| format!("'{value}'"))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        (None, None) => format!("{} is populated", condition.path),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const MSH: &str = "MSH|^~\\&|LAB|FAC|||20250101||ORU^R01|1|P|2.5.1";
+
+    fn check(message: &str) -> Vec<ValidationIssue> {
+        let msg = hl7_parser::parse_message_with_lenient_newlines(message).unwrap();
+        let cache = SchemaCache::new().expect("can create cache");
+        let mut issues = Vec::new();
+        validate_conditional_fields(&msg, &cache, "2.5.1", &mut issues);
+        issues
+    }
+
+    #[test]
+    fn units_are_required_for_numeric_observations_only() {
+        let message =
+            format!("{MSH}\rOBX|1|NM|GLU||5.5|||N\rOBX|2|ED|DOC||^TEXT^^Base64^SGVsbG8=|||N");
+        let issues = check(&message);
+        let paths: Vec<_> = issues.iter().map(|i| i.path.as_str()).collect();
+        assert_eq!(paths.len(), 3, "{issues:?}");
+        assert!(paths.contains(&"OBX.6.1"));
+        assert!(paths.contains(&"OBX.6.2"));
+        assert!(paths.contains(&"OBX.7"));
+
+        // every issue points at the numeric OBX
+        let numeric_end = message.find("\rOBX|2").unwrap();
+        for issue in &issues {
+            assert_eq!(issue.rule, ValidationRule::RequiredField);
+            assert!(issue.range.unwrap().0 < numeric_end);
+        }
+    }
+
+    #[test]
+    fn complete_numeric_observations_pass() {
+        let message = format!("{MSH}\rOBX|1|NM|GLU||5.5|mmol/L^Millimoles per litre|3.9-5.5|N");
+        assert!(check(&message).is_empty(), "{:?}", check(&message));
+    }
+
+    #[test]
+    fn parent_document_is_forbidden_for_original_documents() {
+        let txa = "TXA|1|HP|TX|20250101||||||||DOC002|DOC001";
+        let message = format!("MSH|^~\\&|APP|FAC|||20250101||MDM^T01|1|P|2.5.1\r{txa}");
+        let issues = check(&message);
+        assert_eq!(issues.len(), 1, "{issues:?}");
+        assert_eq!(issues[0].rule, ValidationRule::ForbiddenField);
+        assert_eq!(issues[0].path, "TXA.13.1");
+        assert_eq!(issues[0].actual_value.as_deref(), Some("DOC001"));
+
+        let message = format!("MSH|^~\\&|APP|FAC|||20250101||MDM^T09|1|P|2.5.1\r{txa}");
+        assert!(check(&message).is_empty(), "{:?}", check(&message));
+    }
+
+    #[test]
+    fn conditions_describe_themselves() {
+        let condition = FieldCondition {
+            path: "OBX.2".to_string(),
+            equals: None,
+            one_of: Some(vec!["NM".to_string(), "SN".to_string()]),
+        };
+        assert_eq!(describe(&condition), "OBX.2 is one of 'NM', 'SN'");
+    }
+}
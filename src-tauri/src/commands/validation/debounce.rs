@@ -0,0 +1,97 @@
+//! Debounced light validation in the background.
+//!
+//! The editor light-validates its message as it changes. Issuing a validation
+//! per keystroke from the frontend made typing lag on large messages, as each
+//! one held up the command handler. Instead, the frontend schedules a
+//! validation on every edit with [`schedule_validation`], and:
+//!
+//! 1. Scheduling cancels the validation scheduled before, if it hasn't finished,
+//!    so a burst of edits is validated once
+//! 2. The validation starts once no edit has come in for the debounce delay
+//!    (500ms unless given)
+//! 3. It runs on a blocking thread, off the async runtime and the UI
+//! 4. Its result is emitted as a `validation-result` event, carrying the ticket
+//!    [`schedule_validation`] returned, so the frontend can tell which message
+//!    a result is for
+//!
+//! This follows the extension host's debounced `message/changed` notifications.
+
+use serde::Serialize;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::task::JoinHandle;
+
+use super::validate::{light_validation, ValidationResult};
+use crate::events;
+use crate::AppData;
+
+/// Delay after the last edit before validating, unless the frontend gives one.
+const DEFAULT_DELAY_MS: u64 = 500;
+
+/// The light validation scheduled last.
+#[derive(Default)]
+pub struct ValidationSchedule {
+    /// Ticket of the validation scheduled last
+    ticket: u64,
+    /// Handle to the task waiting out the delay or validating, if it hasn't finished
+    task: Option<JoinHandle<()>>,
+}
+
+/// The result of a validation scheduled with [`schedule_validation`].
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduledValidation {
+    /// Ticket returned when the validation was scheduled
+    pub ticket: u64,
+    /// Light validation result, with UTF-16 ranges
+    pub result: ValidationResult,
+}
+
+/// Light-validate a message once edits have stopped for a moment.
+///
+/// Cancels the validation scheduled before, whether it's still waiting or
+/// already running, so only the latest message's result is emitted.
+///
+/// # Arguments
+/// * `message` - The message to validate
+/// * `delay_ms` - How long to wait for further edits, in milliseconds (500 if not given)
+/// * `app` - Handle for emitting the result
+/// * `state` - Application state holding the schedule
+///
+/// # Returns
+/// The ticket the `validation-result` event for this message will carry
+#[tauri::command]
+pub async fn schedule_validation(
+    message: String,
+    delay_ms: Option<u64>,
+    app: AppHandle,
+    state: State<'_, AppData>,
+) -> Result<u64, String> {
+    let mut schedule = state.validation_schedule.lock().await;
+    if let Some(task) = schedule.task.take() {
+        task.abort();
+    }
+    schedule.ticket = schedule.ticket.wrapping_add(1);
+    let ticket = schedule.ticket;
+
+    let delay = Duration::from_millis(delay_ms.unwrap_or(DEFAULT_DELAY_MS));
+    schedule.task = Some(tokio::spawn(async move {
+        tokio::time::sleep(delay).await;
+
+        let validating = app.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            light_validation(&message, &validating.state::<AppData>())
+        })
+        .await;
+        match result {
+            Ok(result) => {
+                let payload = ScheduledValidation { ticket, result };
+                if let Err(e) = app.emit(events::VALIDATION_RESULT, payload) {
+                    log::warn!("Failed to emit validation result: {e}");
+                }
+            }
+            Err(e) => log::warn!("Scheduled validation failed: {e}"),
+        }
+    }));
+
+    Ok(ticket)
+}
@@ -1,14 +1,18 @@
 //! HL7 message comparison commands.
 //!
 //! This module provides commands for comparing two HL7 messages and identifying
-//! differences at the segment, field, component, and subcomponent levels.
+//! differences at the segment, field, component, and subcomponent levels, and for
+//! comparing two occurrences of a segment within the same message (e.g. two OBX
+//! rows) using the same field-level comparison.
 
-use std::collections::{BTreeMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet};
 
 use hl7_parser::message::{Component, Field, Repeat, Segment};
 use hl7_parser::Message;
 use serde::{Deserialize, Serialize};
 
+use crate::commands::Utf16Offsets;
+
 /// Type of difference detected between two message elements.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -36,9 +40,9 @@ pub struct FieldDiff {
     pub left_value: Option<String>,
     /// Value in the right message (None if removed)
     pub right_value: Option<String>,
-    /// Character range in left message for highlighting
+    /// Character range in left message for highlighting, in UTF-16 code units
     pub left_range: Option<(usize, usize)>,
-    /// Character range in right message for highlighting
+    /// Character range in right message for highlighting, in UTF-16 code units
     pub right_range: Option<(usize, usize)>,
 }
 
@@ -53,9 +57,9 @@ pub struct SegmentDiff {
     pub diff_type: DiffType,
     /// Field-level differences within this segment
     pub fields: Vec<FieldDiff>,
-    /// Character range in left message for the entire segment
+    /// Character range in left message for the entire segment, in UTF-16 code units
     pub left_range: Option<(usize, usize)>,
-    /// Character range in right message for the entire segment
+    /// Character range in right message for the entire segment, in UTF-16 code units
     pub right_range: Option<(usize, usize)>,
 }
 
@@ -81,6 +85,23 @@ pub struct DiffSummary {
     pub total_field_changes: usize,
 }
 
+/// How occurrences of a repeating segment are paired up between two messages.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SegmentMatching {
+    /// Pair occurrences by position: the first OBX with the first OBX, the
+    /// second with the second, and so on
+    Occurrence,
+    /// Pair occurrences by how alike their fields are, so an inserted, removed,
+    /// or reordered repeat doesn't show every later one as modified
+    #[default]
+    Content,
+}
+
+/// Smallest share of fields two occurrences must have in common to be paired
+/// when matching by content.
+const MIN_SIMILARITY: f64 = 0.5;
+
 /// Compare two HL7 messages and return structured differences.
 ///
 /// This command performs a semantic comparison of two HL7 messages at multiple levels:
@@ -90,33 +111,46 @@ pub struct DiffSummary {
 ///
 /// # Comparison Strategy
 ///
-/// Segments are matched by name and occurrence index. For example, if both messages
-/// have two PID segments, PID[0] is compared to PID[0] and PID[1] to PID[1].
+/// Segments are matched by name, and occurrences of a repeating segment are
+/// paired up according to `matching`:
+///
+/// * **Content** (the default) - Each occurrence is paired with the most alike
+///   occurrence on the other side, judged by the share of filled-in fields
+///   with the same value. Occurrences with less than half their fields in
+///   common aren't paired, and show as removed and added instead. Inserting
+///   an OBX early then shows one added OBX, rather than every later OBX as
+///   modified. A segment that appears once in both messages is always paired.
+/// * **Occurrence** - OBX[0] is compared to OBX[0], OBX[1] to OBX[1], and so on.
+///
+/// Segments are listed by name, then by occurrence. `occurrence` is that in
+/// the left message, or in the right message for added segments.
 ///
 /// # Arguments
 /// * `left` - The "original" or "before" message
 /// * `right` - The "new" or "after" message
+/// * `matching` - How to pair repeating segments (defaults to content)
 ///
 /// # Returns
 /// * `Ok(MessageDiff)` - Structured diff result with all differences
 /// * `Err(String)` - If either message cannot be parsed
 #[tauri::command]
-pub fn compare_messages(left: &str, right: &str) -> Result<MessageDiff, String> {
+pub fn compare_messages(
+    left: &str,
+    right: &str,
+    matching: Option<SegmentMatching>,
+) -> Result<MessageDiff, String> {
     let left_msg = hl7_parser::parse_message_with_lenient_newlines(left)
         .map_err(|e| format!("Failed to parse left message: {e}"))?;
     let right_msg = hl7_parser::parse_message_with_lenient_newlines(right)
         .map_err(|e| format!("Failed to parse right message: {e}"))?;
+    let matching = matching.unwrap_or_default();
 
-    // Build maps of segments by (name, occurrence)
-    let left_segments = build_segment_map(&left_msg);
-    let right_segments = build_segment_map(&right_msg);
+    // Group the occurrences of each segment, in message order
+    let left_segments = group_segments(&left_msg);
+    let right_segments = group_segments(&right_msg);
 
-    // Collect all unique segment keys
-    let all_keys: HashSet<_> = left_segments
-        .keys()
-        .chain(right_segments.keys())
-        .cloned()
-        .collect();
+    // Collect all unique segment names, sorted for consistent output
+    let all_names: BTreeSet<&String> = left_segments.keys().chain(right_segments.keys()).collect();
 
     let mut segment_diffs = Vec::new();
     let mut summary = DiffSummary {
@@ -126,81 +160,323 @@ pub fn compare_messages(left: &str, right: &str) -> Result<MessageDiff, String>
         total_field_changes: 0,
     };
 
-    // Sort keys for consistent output (by name then occurrence)
-    let mut sorted_keys: Vec<_> = all_keys.into_iter().collect();
-    sorted_keys.sort();
-
-    for (name, occurrence) in sorted_keys {
-        let left_seg = left_segments.get(&(name.clone(), occurrence));
-        let right_seg = right_segments.get(&(name.clone(), occurrence));
-
-        match (left_seg, right_seg) {
-            (Some(ls), Some(rs)) => {
-                // Segment exists in both - compare fields
-                let (fields, has_changes) =
-                    compare_segment_fields(ls, rs, &name, &left_msg, &right_msg);
-
-                let diff_type = if has_changes {
-                    summary.segments_modified += 1;
-                    DiffType::Modified
-                } else {
-                    DiffType::Unchanged
-                };
-
-                summary.total_field_changes += fields
-                    .iter()
-                    .filter(|f| f.diff_type != DiffType::Unchanged)
-                    .count();
-
-                segment_diffs.push(SegmentDiff {
-                    name: name.clone(),
-                    occurrence,
-                    diff_type,
-                    fields,
-                    left_range: Some((ls.range.start, ls.range.end)),
-                    right_range: Some((rs.range.start, rs.range.end)),
-                });
+    for name in all_names {
+        let left_occurrences = left_segments
+            .get(name)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let right_occurrences = right_segments
+            .get(name)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let pairs = match matching {
+            SegmentMatching::Occurrence => {
+                pair_by_occurrence(left_occurrences.len(), right_occurrences.len())
             }
-            (Some(ls), None) => {
-                // Segment removed
-                summary.segments_removed += 1;
-                let fields = extract_segment_fields(ls, &name, &left_msg, DiffType::Removed, true);
-                summary.total_field_changes += fields.len();
-
-                segment_diffs.push(SegmentDiff {
-                    name: name.clone(),
-                    occurrence,
-                    diff_type: DiffType::Removed,
-                    fields,
-                    left_range: Some((ls.range.start, ls.range.end)),
-                    right_range: None,
-                });
+            SegmentMatching::Content => {
+                pair_by_content(left_occurrences, right_occurrences, &left_msg, &right_msg)
             }
-            (None, Some(rs)) => {
-                // Segment added
-                summary.segments_added += 1;
-                let fields = extract_segment_fields(rs, &name, &right_msg, DiffType::Added, false);
-                summary.total_field_changes += fields.len();
-
-                segment_diffs.push(SegmentDiff {
-                    name: name.clone(),
-                    occurrence,
-                    diff_type: DiffType::Added,
-                    fields,
-                    left_range: None,
-                    right_range: Some((rs.range.start, rs.range.end)),
-                });
+        };
+
+        for (left_index, right_index) in pairs {
+            let left_seg = left_index.and_then(|i| left_occurrences.get(i).copied());
+            let right_seg = right_index.and_then(|i| right_occurrences.get(i).copied());
+
+            match (left_seg, right_seg) {
+                (Some(ls), Some(rs)) => {
+                    // Segment exists in both - compare fields
+                    let (fields, has_changes) =
+                        compare_segment_fields(ls, rs, name, &left_msg, &right_msg);
+
+                    let diff_type = if has_changes {
+                        summary.segments_modified += 1;
+                        DiffType::Modified
+                    } else {
+                        DiffType::Unchanged
+                    };
+
+                    summary.total_field_changes += fields
+                        .iter()
+                        .filter(|f| f.diff_type != DiffType::Unchanged)
+                        .count();
+
+                    segment_diffs.push(SegmentDiff {
+                        name: name.clone(),
+                        occurrence: left_index.unwrap_or_default(),
+                        diff_type,
+                        fields,
+                        left_range: Some((ls.range.start, ls.range.end)),
+                        right_range: Some((rs.range.start, rs.range.end)),
+                    });
+                }
+                (Some(ls), None) => {
+                    // Segment removed
+                    summary.segments_removed += 1;
+                    let fields =
+                        extract_segment_fields(ls, name, &left_msg, DiffType::Removed, true);
+                    summary.total_field_changes += fields.len();
+
+                    segment_diffs.push(SegmentDiff {
+                        name: name.clone(),
+                        occurrence: left_index.unwrap_or_default(),
+                        diff_type: DiffType::Removed,
+                        fields,
+                        left_range: Some((ls.range.start, ls.range.end)),
+                        right_range: None,
+                    });
+                }
+                (None, Some(rs)) => {
+                    // Segment added
+                    summary.segments_added += 1;
+                    let fields =
+                        extract_segment_fields(rs, name, &right_msg, DiffType::Added, false);
+                    summary.total_field_changes += fields.len();
+
+                    segment_diffs.push(SegmentDiff {
+                        name: name.clone(),
+                        occurrence: right_index.unwrap_or_default(),
+                        diff_type: DiffType::Added,
+                        fields,
+                        left_range: None,
+                        right_range: Some((rs.range.start, rs.range.end)),
+                    });
+                }
+                (None, None) => {}
             }
-            (None, None) => unreachable!(),
         }
     }
 
+    let (left_offsets, right_offsets) = (Utf16Offsets::new(left), Utf16Offsets::new(right));
+    for segment in &mut segment_diffs {
+        ranges_to_utf16(segment, &left_offsets, &right_offsets);
+    }
+
     Ok(MessageDiff {
         segments: segment_diffs,
         summary,
     })
 }
 
+/// Convert a segment diff's byte ranges to the UTF-16 offsets the editor uses.
+fn ranges_to_utf16(segment: &mut SegmentDiff, left: &Utf16Offsets, right: &Utf16Offsets) {
+    fn convert(range: &mut Option<(usize, usize)>, offsets: &Utf16Offsets) {
+        if let Some((start, end)) = range {
+            *start = offsets.to_utf16(*start);
+            *end = offsets.to_utf16(*end);
+        }
+    }
+    convert(&mut segment.left_range, left);
+    convert(&mut segment.right_range, right);
+    for field in &mut segment.fields {
+        convert(&mut field.left_range, left);
+        convert(&mut field.right_range, right);
+    }
+}
+
+/// Pair occurrences of a segment by position.
+///
+/// Returns (left index, right index) pairs, with `None` on the side an
+/// occurrence is missing from.
+fn pair_by_occurrence(left: usize, right: usize) -> Vec<(Option<usize>, Option<usize>)> {
+    (0..left.max(right))
+        .map(|i| ((i < left).then_some(i), (i < right).then_some(i)))
+        .collect()
+}
+
+/// Pair occurrences of a segment by content.
+///
+/// The most alike pairs are taken first, preferring occurrences at nearby
+/// positions when several are equally alike (e.g. identical NTE lines), until
+/// no remaining pair has at least [`MIN_SIMILARITY`] of its fields in common.
+/// The pairs are returned ordered by their left occurrence, with unpaired
+/// right occurrences placed after the pair preceding them in the right message.
+fn pair_by_content<'a>(
+    left: &[&'a Segment<'a>],
+    right: &[&'a Segment<'a>],
+    left_msg: &'a Message<'a>,
+    right_msg: &'a Message<'a>,
+) -> Vec<(Option<usize>, Option<usize>)> {
+    if left.len() == 1 && right.len() == 1 {
+        return vec![(Some(0), Some(0))];
+    }
+
+    let mut candidates = Vec::new();
+    for (l, left_seg) in left.iter().enumerate() {
+        for (r, right_seg) in right.iter().enumerate() {
+            let score = similarity(left_seg, right_seg, left_msg, right_msg);
+            if score >= MIN_SIMILARITY {
+                candidates.push((score, l.abs_diff(r), l, r));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(&b.1)).then(a.2.cmp(&b.2)));
+
+    let mut left_pairs: Vec<Option<usize>> = vec![None; left.len()];
+    let mut right_paired = vec![false; right.len()];
+    for (_, _, l, r) in candidates {
+        let (Some(left_pair), Some(right_taken)) = (left_pairs.get_mut(l), right_paired.get_mut(r))
+        else {
+            continue;
+        };
+        if left_pair.is_none() && !*right_taken {
+            *left_pair = Some(r);
+            *right_taken = true;
+        }
+    }
+
+    // walk the left occurrences in order, slotting unpaired right occurrences
+    // in after the last pair that came before them on the right
+    let mut pairs = Vec::with_capacity(left.len().max(right.len()));
+    let mut next_right = 0;
+    for (l, paired) in left_pairs.into_iter().enumerate() {
+        if let Some(r) = paired {
+            while next_right < r {
+                if !right_paired.get(next_right).copied().unwrap_or(true) {
+                    pairs.push((None, Some(next_right)));
+                }
+                next_right += 1;
+            }
+            next_right = next_right.max(r + 1);
+        }
+        pairs.push((Some(l), paired));
+    }
+    for (r, paired) in right_paired.into_iter().enumerate().skip(next_right) {
+        if !paired {
+            pairs.push((None, Some(r)));
+        }
+    }
+    pairs
+}
+
+/// Share of fields two segments have in common, from 0.0 to 1.0.
+///
+/// Only fields filled in on at least one side count, so two segments that
+/// leave most fields empty aren't judged alike on that alone.
+fn similarity<'a>(
+    left: &'a Segment<'a>,
+    right: &'a Segment<'a>,
+    left_msg: &'a Message<'a>,
+    right_msg: &'a Message<'a>,
+) -> f64 {
+    let mut compared = 0u32;
+    let mut equal = 0u32;
+    for field_idx in 0..left.fields.len().max(right.fields.len()) {
+        let left_val = left
+            .fields
+            .get(field_idx)
+            .map(|f| left_msg.separators.decode(f.raw_value()).to_string())
+            .unwrap_or_default();
+        let right_val = right
+            .fields
+            .get(field_idx)
+            .map(|f| right_msg.separators.decode(f.raw_value()).to_string())
+            .unwrap_or_default();
+        if left_val.is_empty() && right_val.is_empty() {
+            continue;
+        }
+        compared += 1;
+        if left_val == right_val {
+            equal += 1;
+        }
+    }
+
+    if compared == 0 {
+        1.0
+    } else {
+        f64::from(equal) / f64::from(compared)
+    }
+}
+
+/// Compare two occurrences of a segment within the same message.
+///
+/// Uses the same field, component, and subcomponent comparison as
+/// `compare_messages()`, so repeated segments such as OBX rows or IN1 insurance
+/// entries can be reviewed side by side without copying them into separate
+/// documents.
+///
+/// # Segment Paths
+/// * `OBX` - First OBX segment
+/// * `OBX[2]` - Second OBX segment (1-based, as in field queries)
+///
+/// # Arguments
+/// * `message` - The HL7 message containing both segments
+/// * `path_a` - Path of the "left" segment
+/// * `path_b` - Path of the "right" segment
+///
+/// # Returns
+/// * `Ok(SegmentDiff)` - Field differences; `occurrence` is that of the left
+///   segment, and both ranges refer to `message`
+/// * `Err(String)` - If the message can't be parsed, a path is invalid or not
+///   found, or the segments have different names
+#[tauri::command]
+pub fn compare_segments(message: &str, path_a: &str, path_b: &str) -> Result<SegmentDiff, String> {
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(message)
+        .map_err(|e| format!("Failed to parse message: {e}"))?;
+
+    let (name_a, occurrence_a) =
+        parse_segment_path(path_a).ok_or_else(|| format!("Invalid segment path: {path_a}"))?;
+    let (name_b, occurrence_b) =
+        parse_segment_path(path_b).ok_or_else(|| format!("Invalid segment path: {path_b}"))?;
+    if name_a != name_b {
+        return Err(format!(
+            "Can only compare segments of the same type, not {name_a} and {name_b}"
+        ));
+    }
+
+    let segments = build_segment_map(&parsed);
+    let left = segments
+        .get(&(name_a.clone(), occurrence_a))
+        .ok_or_else(|| format!("Segment {path_a} not found"))?;
+    let right = segments
+        .get(&(name_b, occurrence_b))
+        .ok_or_else(|| format!("Segment {path_b} not found"))?;
+
+    let (fields, has_changes) = compare_segment_fields(left, right, &name_a, &parsed, &parsed);
+
+    let mut diff = SegmentDiff {
+        name: name_a,
+        occurrence: occurrence_a,
+        diff_type: if has_changes {
+            DiffType::Modified
+        } else {
+            DiffType::Unchanged
+        },
+        fields,
+        left_range: Some((left.range.start, left.range.end)),
+        right_range: Some((right.range.start, right.range.end)),
+    };
+    let offsets = Utf16Offsets::new(message);
+    ranges_to_utf16(&mut diff, &offsets, &offsets);
+    Ok(diff)
+}
+
+/// Parse a segment path like "OBX" or "OBX[2]" into its name and 0-based occurrence.
+fn parse_segment_path(path: &str) -> Option<(String, usize)> {
+    let path = path.trim();
+    let (name, occurrence) = match path.split_once('[') {
+        Some((name, rest)) => {
+            let number: usize = rest.strip_suffix(']')?.trim().parse().ok()?;
+            (name, number.checked_sub(1)?)
+        }
+        None => (path, 0),
+    };
+
+    let valid_name = name.len() == 3 && name.chars().all(|c| c.is_ascii_alphanumeric());
+    valid_name.then(|| (name.to_ascii_uppercase(), occurrence))
+}
+
+/// Group a message's segments by name, each in message order.
+fn group_segments<'a>(message: &'a Message<'a>) -> BTreeMap<String, Vec<&'a Segment<'a>>> {
+    let mut groups: BTreeMap<String, Vec<&'a Segment<'a>>> = BTreeMap::new();
+    for segment in message.segments() {
+        groups
+            .entry(segment.name.to_string())
+            .or_default()
+            .push(segment);
+    }
+    groups
+}
+
 /// Build a map of segments keyed by (name, occurrence_index).
 fn build_segment_map<'a>(message: &'a Message<'a>) -> BTreeMap<(String, usize), &'a Segment<'a>> {
     let mut map = BTreeMap::new();
@@ -644,7 +920,7 @@ mod tests {
     #[test]
     fn test_identical_messages() {
         let msg = "MSH|^~\\&|SEND|FAC|RCV|FAC|20250101120000||ADT^A01|12345|P|2.3\rPID|1||12345^^^MRN||Doe^John|||M";
-        let result = compare_messages(msg, msg).unwrap();
+        let result = compare_messages(msg, msg, None).unwrap();
 
         assert_eq!(result.summary.segments_added, 0);
         assert_eq!(result.summary.segments_removed, 0);
@@ -656,7 +932,7 @@ mod tests {
     fn test_field_modification() {
         let left = "MSH|^~\\&|SEND|FAC|RCV|FAC|20250101120000||ADT^A01|12345|P|2.3\rPID|1||12345^^^MRN||Doe^John|||M";
         let right = "MSH|^~\\&|SEND|FAC|RCV|FAC|20250101120000||ADT^A01|12345|P|2.3\rPID|1||67890^^^MRN||Doe^John|||M";
-        let result = compare_messages(left, right).unwrap();
+        let result = compare_messages(left, right, None).unwrap();
 
         assert_eq!(result.summary.segments_modified, 1);
         assert!(result.summary.total_field_changes > 0);
@@ -677,7 +953,7 @@ mod tests {
     fn test_segment_added() {
         let left = "MSH|^~\\&|SEND|FAC|RCV|FAC|20250101120000||ADT^A01|12345|P|2.3";
         let right = "MSH|^~\\&|SEND|FAC|RCV|FAC|20250101120000||ADT^A01|12345|P|2.3\rPID|1||12345^^^MRN||Doe^John|||M";
-        let result = compare_messages(left, right).unwrap();
+        let result = compare_messages(left, right, None).unwrap();
 
         assert_eq!(result.summary.segments_added, 1);
 
@@ -685,11 +961,102 @@ mod tests {
         assert_eq!(pid_segment.diff_type, DiffType::Added);
     }
 
+    #[test]
+    fn test_inserted_repeat_matched_by_content() {
+        let left = "MSH|^~\\&|SEND|FAC|RCV|FAC|20250101120000||ORU^R01|12345|P|2.5\rOBX|1|NM|GLU^Glucose||5.4|mmol/L\rOBX|2|NM|NA^Sodium||140|mmol/L\rOBX|3|NM|K^Potassium||4.1|mmol/L";
+        let right = "MSH|^~\\&|SEND|FAC|RCV|FAC|20250101120000||ORU^R01|12345|P|2.5\rOBX|1|NM|HB^Haemoglobin||135|g/L\rOBX|2|NM|GLU^Glucose||5.4|mmol/L\rOBX|3|NM|NA^Sodium||140|mmol/L\rOBX|4|NM|K^Potassium||4.1|mmol/L";
+
+        let result = compare_messages(left, right, None).unwrap();
+        assert_eq!(result.summary.segments_added, 1);
+        assert_eq!(result.summary.segments_removed, 0);
+        // only the set IDs of the shifted rows changed
+        assert_eq!(result.summary.segments_modified, 3);
+        let obx: Vec<_> = result.segments.iter().filter(|s| s.name == "OBX").collect();
+        assert_eq!(obx[0].diff_type, DiffType::Added);
+        assert_eq!(obx[0].occurrence, 0);
+        assert!(obx[1..]
+            .iter()
+            .all(|s| s.fields.iter().all(|f| f.path.starts_with("OBX.1"))));
+
+        // by occurrence, every row after the insertion is modified
+        let result = compare_messages(left, right, Some(SegmentMatching::Occurrence)).unwrap();
+        assert_eq!(result.summary.segments_added, 1);
+        assert_eq!(result.summary.segments_modified, 3);
+        assert!(result
+            .segments
+            .iter()
+            .filter(|s| s.name == "OBX")
+            .any(|s| s.fields.iter().any(|f| f.path.starts_with("OBX.5"))));
+    }
+
+    #[test]
+    fn test_reordered_repeats_matched_by_content() {
+        let left = "MSH|^~\\&|SEND|FAC|RCV|FAC|20250101120000||ADT^A01|12345|P|2.5\rNK1|1|DOE^JANE|SPO\rNK1|2|DOE^JIM|CHD\rNK1|3|ROE^ANN|FND";
+        let right = "MSH|^~\\&|SEND|FAC|RCV|FAC|20250101120000||ADT^A01|12345|P|2.5\rNK1|1|DOE^JIM|CHD\rNK1|2|DOE^JANE|SPO\rNK1|3|ROE^ANN|FRD";
+
+        let result = compare_messages(left, right, None).unwrap();
+        assert_eq!(result.summary.segments_added, 0);
+        assert_eq!(result.summary.segments_removed, 0);
+        let nk1: Vec<_> = result.segments.iter().filter(|s| s.name == "NK1").collect();
+        assert_eq!(nk1.len(), 3);
+        // JANE and JIM swapped places, so only their set IDs differ
+        let (start, end) = nk1[0].right_range.unwrap();
+        assert!(right[start..end].contains("JANE"));
+        assert!(nk1[0].fields.iter().all(|f| f.path.starts_with("NK1.1")));
+        assert!(nk1[2].fields.iter().any(|f| f.path.starts_with("NK1.3")));
+    }
+
+    #[test]
+    fn test_compare_segments_within_message() {
+        let msg = "MSH|^~\\&|SEND|FAC|RCV|FAC|20250101120000||ORU^R01|12345|P|2.5\rOBX|1|NM|GLU^Glucose||5.4|mmol/L\rOBX|2|NM|GLU^Glucose||7.1|mmol/L";
+        let result = compare_segments(msg, "OBX[1]", "obx[2]").unwrap();
+
+        assert_eq!(result.name, "OBX");
+        assert_eq!(result.diff_type, DiffType::Modified);
+        let changed: Vec<_> = result.fields.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(changed.len(), 2);
+        assert!(changed.iter().any(|p| p.starts_with("OBX.1")));
+        assert!(changed.iter().any(|p| p.starts_with("OBX.5")));
+
+        // both ranges point into the same message
+        let obx5 = result
+            .fields
+            .iter()
+            .find(|f| f.path.starts_with("OBX.5"))
+            .unwrap();
+        let (start, end) = obx5.right_range.unwrap();
+        assert_eq!(&msg[start..end], "7.1");
+
+        let same = compare_segments(msg, "OBX", "OBX[1]").unwrap();
+        assert_eq!(same.diff_type, DiffType::Unchanged);
+    }
+
+    #[test]
+    fn test_ranges_are_utf16_offsets() {
+        let left = "MSH|^~\\&|SEND|FAC|RCV|FAC|20250101120000||ADT^A01|12345|P|2.5\rPID|1||123||MÜLLER^JOSÉ||19800101";
+        let right = "MSH|^~\\&|SEND|FAC|RCV|FAC|20250101120000||ADT^A01|12345|P|2.5\rPID|1||123||MÜLLER^JOSÉ||19800102";
+
+        let result = compare_messages(left, right, None).unwrap();
+        let pid = result.segments.iter().find(|s| s.name == "PID").unwrap();
+        let (start, end) = pid.fields[0].right_range.unwrap();
+        let utf16: Vec<u16> = right.encode_utf16().collect();
+        assert_eq!(String::from_utf16(&utf16[start..end]).unwrap(), "19800102");
+    }
+
+    #[test]
+    fn test_compare_segments_rejects_bad_paths() {
+        let msg = "MSH|^~\\&|SEND|FAC|RCV|FAC|20250101120000||ORU^R01|12345|P|2.5\rOBX|1|NM|GLU^Glucose||5.4|mmol/L";
+        assert!(compare_segments(msg, "OBX[1]", "OBX[2]").is_err());
+        assert!(compare_segments(msg, "OBX[0]", "OBX[1]").is_err());
+        assert!(compare_segments(msg, "MSH", "OBX").is_err());
+        assert!(compare_segments(msg, "OBX.5", "OBX").is_err());
+    }
+
     #[test]
     fn test_segment_removed() {
         let left = "MSH|^~\\&|SEND|FAC|RCV|FAC|20250101120000||ADT^A01|12345|P|2.3\rPID|1||12345^^^MRN||Doe^John|||M";
         let right = "MSH|^~\\&|SEND|FAC|RCV|FAC|20250101120000||ADT^A01|12345|P|2.3";
-        let result = compare_messages(left, right).unwrap();
+        let result = compare_messages(left, right, None).unwrap();
 
         assert_eq!(result.summary.segments_removed, 1);
 
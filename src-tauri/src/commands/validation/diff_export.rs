@@ -0,0 +1,248 @@
+//! Message comparison export.
+//!
+//! Renders a `MessageDiff` from `compare_messages()` as a side-by-side report
+//! for change-control documentation: each changed segment gets a table of its
+//! changed fields, with the value before and after the change next to each
+//! other. Unchanged segments and fields are left out.
+//!
+//! # Formats
+//!
+//! * **HTML** - A standalone page with changes colour-coded as in the compare
+//!   window: green for added values, red for removed, and amber for modified
+//! * **Markdown** - Tables with the kind of change spelled out, as Markdown
+//!   has no colour
+
+use serde::{Deserialize, Serialize};
+
+use super::diff::{DiffType, FieldDiff, MessageDiff, SegmentDiff};
+use super::report::markdown_cell;
+
+/// Output format for an exported comparison.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffExportFormat {
+    /// Standalone HTML page
+    Html,
+    /// Markdown document with tables
+    Markdown,
+}
+
+/// Render a message comparison as a side-by-side report.
+///
+/// # Arguments
+/// * `diff` - Result of `compare_messages()`
+/// * `format` - Report format
+/// * `left_label` - Name for the left message, e.g. its file name (defaults to "Original")
+/// * `right_label` - Name for the right message (defaults to "Modified")
+///
+/// # Returns
+/// The report's content, ready to be written to a file
+#[tauri::command]
+pub fn export_diff(
+    diff: MessageDiff,
+    format: DiffExportFormat,
+    left_label: Option<String>,
+    right_label: Option<String>,
+) -> String {
+    let left_label = left_label.unwrap_or_else(|| "Original".to_string());
+    let right_label = right_label.unwrap_or_else(|| "Modified".to_string());
+    match format {
+        DiffExportFormat::Html => html_diff(&diff, &left_label, &right_label),
+        DiffExportFormat::Markdown => markdown_diff(&diff, &left_label, &right_label),
+    }
+}
+
+/// Render a comparison as a standalone HTML page.
+fn html_diff(diff: &MessageDiff, left_label: &str, right_label: &str) -> String {
+    let escape = |text: &str| quick_xml::escape::escape(text).into_owned();
+
+    let mut report = String::from(concat!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n",
+        "<title>Message Comparison</title>\n<style>\n",
+        "body { font-family: sans-serif; margin: 2em; }\n",
+        "table { border-collapse: collapse; margin-bottom: 1.5em; width: 100%; }\n",
+        "th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; vertical-align: top; }\n",
+        "th { background: #f4f4f4; }\n",
+        "td.value { font-family: monospace; white-space: pre-wrap; word-break: break-all; width: 35%; }\n",
+        ".added { background: #e6f4ea; }\n.removed { background: #fce8e6; }\n.modified { background: #fef7e0; }\n",
+        "</style>\n</head>\n<body>\n<h1>Message Comparison</h1>\n",
+    ));
+    report.push_str(&format!(
+        "<p><strong>Left:</strong> {}<br>\n<strong>Right:</strong> {}</p>\n",
+        escape(left_label),
+        escape(right_label)
+    ));
+    report.push_str(&format!("<p>{}</p>\n", summary_line(diff)));
+
+    for segment in changed_segments(diff) {
+        report.push_str(&format!(
+            "<h2 class=\"{kind}\">{} ({kind})</h2>\n<table>\n",
+            segment_label(segment),
+            kind = kind_label(segment.diff_type),
+        ));
+        report.push_str(&format!(
+            "<tr><th>Path</th><th>Field</th><th>{}</th><th>{}</th></tr>\n",
+            escape(left_label),
+            escape(right_label)
+        ));
+        for field in changed_fields(segment) {
+            let kind = kind_label(change_kind(field));
+            report.push_str(&format!(
+                "<tr class=\"{kind}\"><td>{}</td><td>{}</td><td class=\"value\">{}</td><td class=\"value\">{}</td></tr>\n",
+                escape(&field.path),
+                escape(field.description.as_deref().unwrap_or_default()),
+                escape(field.left_value.as_deref().unwrap_or_default()),
+                escape(field.right_value.as_deref().unwrap_or_default())
+            ));
+        }
+        report.push_str("</table>\n");
+    }
+    report.push_str("</body>\n</html>\n");
+    report
+}
+
+/// Render a comparison as Markdown.
+fn markdown_diff(diff: &MessageDiff, left_label: &str, right_label: &str) -> String {
+    let mut report = String::from("# Message Comparison\n\n");
+    report.push_str(&format!(
+        "**Left:** {}  \n**Right:** {}\n\n{}\n",
+        markdown_cell(left_label),
+        markdown_cell(right_label),
+        summary_line(diff)
+    ));
+
+    for segment in changed_segments(diff) {
+        report.push_str(&format!(
+            "\n## {} ({})\n\n| Path | Field | Change | {} | {} |\n|---|---|---|---|---|\n",
+            segment_label(segment),
+            kind_label(segment.diff_type),
+            markdown_cell(left_label),
+            markdown_cell(right_label)
+        ));
+        for field in changed_fields(segment) {
+            report.push_str(&format!(
+                "| {} | {} | {} | {} | {} |\n",
+                markdown_cell(&field.path),
+                markdown_cell(field.description.as_deref().unwrap_or_default()),
+                kind_label(change_kind(field)),
+                markdown_cell(field.left_value.as_deref().unwrap_or_default()),
+                markdown_cell(field.right_value.as_deref().unwrap_or_default())
+            ));
+        }
+    }
+    report
+}
+
+/// Segments with any changes.
+fn changed_segments(diff: &MessageDiff) -> impl Iterator<Item = &SegmentDiff> {
+    diff.segments
+        .iter()
+        .filter(|segment| segment.diff_type != DiffType::Unchanged)
+}
+
+/// Fields of a segment with any changes.
+fn changed_fields(segment: &SegmentDiff) -> impl Iterator<Item = &FieldDiff> {
+    segment
+        .fields
+        .iter()
+        .filter(|field| field.diff_type != DiffType::Unchanged)
+}
+
+/// How a field changed, as the compare window shows it.
+///
+/// A field whose value went from empty to filled in is shown as added, and
+/// from filled in to empty as removed, even when the segment is in both
+/// messages.
+fn change_kind(field: &FieldDiff) -> DiffType {
+    let is_empty = |value: &Option<String>| value.as_deref().unwrap_or_default().trim().is_empty();
+    match (is_empty(&field.left_value), is_empty(&field.right_value)) {
+        (true, false) => DiffType::Added,
+        (false, true) => DiffType::Removed,
+        (true, true) | (false, false) => DiffType::Modified,
+    }
+}
+
+/// Segment name with its occurrence, e.g. "OBX[2]"; the first has none.
+fn segment_label(segment: &SegmentDiff) -> String {
+    match segment.occurrence {
+        0 => segment.name.clone(),
+        occurrence => format!("{}[{}]", segment.name, occurrence + 1),
+    }
+}
+
+/// Lower-case name for a kind of change, also used as its CSS class.
+fn kind_label(diff_type: DiffType) -> &'static str {
+    match diff_type {
+        DiffType::Added => "added",
+        DiffType::Removed => "removed",
+        DiffType::Modified => "modified",
+        DiffType::Unchanged => "unchanged",
+    }
+}
+
+/// Summary counts as a sentence.
+fn summary_line(diff: &MessageDiff) -> String {
+    let summary = &diff.summary;
+    if summary.total_field_changes == 0 {
+        return "The messages are identical.".to_string();
+    }
+    format!(
+        "{} field change{}: {} segment{} added, {} removed, {} modified.",
+        summary.total_field_changes,
+        if summary.total_field_changes == 1 {
+            ""
+        } else {
+            "s"
+        },
+        summary.segments_added,
+        if summary.segments_added == 1 { "" } else { "s" },
+        summary.segments_removed,
+        summary.segments_modified
+    )
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use crate::commands::compare_messages;
+
+    const LEFT: &str =
+        "MSH|^~\\&|APP|FAC|||20250101||ADT^A01|1|P|2.5.1\rPID|1||123||DOE^JOHN\rNK1|1|DOE^JANE";
+    const RIGHT: &str = "MSH|^~\\&|APP|FAC|||20250101||ADT^A01|1|P|2.5.1\rPID|1||123||DOE^JOHNNY|||M\rOBX|1|ST|CODE||<b>";
+
+    #[test]
+    fn renders_html_side_by_side() {
+        let diff = compare_messages(LEFT, RIGHT, None).unwrap();
+        let html = export_diff(
+            diff,
+            DiffExportFormat::Html,
+            Some("before.hl7".to_string()),
+            None,
+        );
+
+        assert!(html.contains("<th>before.hl7</th><th>Modified</th>"));
+        assert!(html.contains("<h2 class=\"modified\">PID (modified)</h2>"));
+        assert!(html.contains("<h2 class=\"removed\">NK1 (removed)</h2>"));
+        assert!(html.contains("<td class=\"value\">JOHN</td><td class=\"value\">JOHNNY</td>"));
+        // values are escaped
+        assert!(html.contains("&lt;b&gt;"));
+        // unchanged segments are left out
+        assert!(!html.contains("<h2 class=\"unchanged\">"));
+    }
+
+    #[test]
+    fn renders_markdown_tables() {
+        let diff = compare_messages(LEFT, RIGHT, None).unwrap();
+        let markdown = export_diff(diff, DiffExportFormat::Markdown, None, None);
+
+        assert!(markdown.contains("## PID (modified)"));
+        assert!(markdown.contains("| Path | Field | Change | Original | Modified |"));
+        assert!(markdown.contains("| added |  | M |"));
+        assert!(!markdown.contains("## MSH"));
+
+        let same = compare_messages(LEFT, LEFT, None).unwrap();
+        assert!(export_diff(same, DiffExportFormat::Markdown, None, None)
+            .contains("The messages are identical."));
+    }
+}
@@ -0,0 +1,499 @@
+//! Bulk comparison of two folders of HL7 messages.
+//!
+//! Regression-testing an interface engine upgrade usually means re-running a
+//! test corpus through the new engine and checking which outputs changed.
+//! Opening every pair of files in the compare dialog doesn't scale past a
+//! handful, so this pairs up the files of two folders and compares each pair
+//! with [`compare_messages`], returning a summary per file.
+//!
+//! # Pairing Files
+//!
+//! * **File name** (the default) - `adt_001.hl7` on the left is compared with
+//!   `adt_001.hl7` on the right, for engines that keep the input file name
+//! * **Control ID** - Files are paired by the message control ID in MSH.10,
+//!   for engines that name their output files after a sequence or timestamp
+//!
+//! Files with no partner on the other side are listed as only on the left or
+//! only on the right. Only `.hl7` files directly inside each folder are read,
+//! and a metadata header at the top of a file is ignored.
+//!
+//! # Ignored Fields
+//!
+//! Fields such as MSH.7 (date/time of message) are restamped on every run, so
+//! changes to them can be ignored; a pair whose only differences are in ignored
+//! fields is reported as identical.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use super::diff::{compare_messages, DiffSummary, DiffType, SegmentMatching};
+use crate::commands::strip_document_metadata;
+
+/// File extension of the messages compared.
+const MESSAGE_EXTENSION: &str = "hl7";
+
+/// How files in the two folders are paired up.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DirectoryPairing {
+    /// Pair files with the same name
+    #[default]
+    FileName,
+    /// Pair files holding messages with the same control ID (MSH.10)
+    ControlId,
+}
+
+/// Outcome of comparing one pair of files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    /// Both messages are the same, ignored fields aside
+    Identical,
+    /// The messages differ
+    Changed,
+    /// The file has no partner in the right folder
+    OnlyLeft,
+    /// The file has no partner in the left folder
+    OnlyRight,
+    /// A file couldn't be read, parsed, or paired
+    Error,
+}
+
+/// Comparison of one pair of files.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileComparison {
+    /// What the files were paired by: the file name or the control ID
+    pub key: String,
+    /// Name of the file in the left folder, if there is one
+    pub left_file: Option<String>,
+    /// Name of the file in the right folder, if there is one
+    pub right_file: Option<String>,
+    /// Outcome of the comparison
+    pub status: FileStatus,
+    /// Summary of the differences, if both files were compared
+    pub summary: Option<DiffSummary>,
+    /// Paths of the fields that differ, e.g. "PID.5.1"
+    pub changed_fields: Vec<String>,
+    /// Why the files couldn't be compared, if they couldn't
+    pub error: Option<String>,
+}
+
+/// Number of files with each outcome.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirectoryComparisonSummary {
+    /// Pairs whose messages are the same
+    pub identical: usize,
+    /// Pairs whose messages differ
+    pub changed: usize,
+    /// Files only in the left folder
+    pub only_left: usize,
+    /// Files only in the right folder
+    pub only_right: usize,
+    /// Files that couldn't be compared
+    pub errors: usize,
+}
+
+/// Result of comparing two folders.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryComparison {
+    /// Comparison of each pair of files, ordered by key
+    pub files: Vec<FileComparison>,
+    /// Number of files with each outcome
+    pub summary: DirectoryComparisonSummary,
+}
+
+/// Compare every message in one folder with its partner in another.
+///
+/// # Arguments
+/// * `left_dir` - Folder of the "original" or "before" messages
+/// * `right_dir` - Folder of the "new" or "after" messages
+/// * `pair_by` - How to pair files up (defaults to file name)
+/// * `matching` - How to pair repeating segments within each message (defaults
+///   to content)
+/// * `ignore_fields` - Field paths whose changes don't count, e.g. "MSH.7";
+///   a path also covers the repeats and components under it
+///
+/// # Returns
+/// * `Ok(DirectoryComparison)` - A comparison per file, and the totals
+/// * `Err(String)` - If either folder can't be read
+#[tauri::command]
+pub async fn compare_directories(
+    left_dir: String,
+    right_dir: String,
+    pair_by: Option<DirectoryPairing>,
+    matching: Option<SegmentMatching>,
+    ignore_fields: Option<Vec<String>>,
+) -> Result<DirectoryComparison, String> {
+    compare_directory_files(
+        Path::new(&left_dir),
+        Path::new(&right_dir),
+        pair_by.unwrap_or_default(),
+        matching,
+        &ignore_fields.unwrap_or_default(),
+    )
+}
+
+/// Compare two folders without going through Tauri.
+fn compare_directory_files(
+    left_dir: &Path,
+    right_dir: &Path,
+    pair_by: DirectoryPairing,
+    matching: Option<SegmentMatching>,
+    ignore_fields: &[String],
+) -> Result<DirectoryComparison, String> {
+    let mut files = Vec::new();
+    let (left, left_errors) = key_files(left_dir, pair_by)?;
+    let (right, right_errors) = key_files(right_dir, pair_by)?;
+    for (name, error) in left_errors {
+        files.push(file_error(name.clone(), Some(name), None, error));
+    }
+    for (name, error) in right_errors {
+        files.push(file_error(name.clone(), None, Some(name), error));
+    }
+
+    let mut keys: Vec<&String> = left.keys().chain(right.keys()).collect();
+    keys.sort();
+    keys.dedup();
+
+    for key in keys {
+        let comparison = match (left.get(key), right.get(key)) {
+            (Some(left), Some(right)) => compare_pair(key, left, right, matching, ignore_fields),
+            (Some(left), None) => unpaired(key, Some(left), None, FileStatus::OnlyLeft),
+            (None, Some(right)) => unpaired(key, None, Some(right), FileStatus::OnlyRight),
+            (None, None) => continue,
+        };
+        files.push(comparison);
+    }
+    files.sort_by(|a, b| a.key.cmp(&b.key));
+
+    let mut summary = DirectoryComparisonSummary::default();
+    for file in &files {
+        match file.status {
+            FileStatus::Identical => summary.identical += 1,
+            FileStatus::Changed => summary.changed += 1,
+            FileStatus::OnlyLeft => summary.only_left += 1,
+            FileStatus::OnlyRight => summary.only_right += 1,
+            FileStatus::Error => summary.errors += 1,
+        }
+    }
+
+    Ok(DirectoryComparison { files, summary })
+}
+
+/// A message file read from one of the folders.
+struct MessageFile {
+    /// File name, without the folder
+    name: String,
+    /// Message, without its metadata header
+    message: String,
+}
+
+/// Read the messages in a folder, keyed by what they're paired by.
+///
+/// # Returns
+/// The messages by key, and the names of files that couldn't be read or keyed
+/// with the reason. Of several files with the same control ID, all but the
+/// first are reported as errors.
+fn key_files(
+    dir: &Path,
+    pair_by: DirectoryPairing,
+) -> Result<(BTreeMap<String, MessageFile>, Vec<(String, String)>), String> {
+    let mut keyed: BTreeMap<String, MessageFile> = BTreeMap::new();
+    let mut errors = Vec::new();
+
+    for path in collect_message_files(dir)? {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let message = match std::fs::read_to_string(&path) {
+            Ok(content) => strip_document_metadata(&content).0.to_string(),
+            Err(e) => {
+                errors.push((name, format!("Failed to read file: {e}")));
+                continue;
+            }
+        };
+
+        let key = match pair_by {
+            DirectoryPairing::FileName => name.clone(),
+            DirectoryPairing::ControlId => match control_id(&message) {
+                Some(id) => id,
+                None => {
+                    errors.push((name, "No control ID in MSH.10".to_string()));
+                    continue;
+                }
+            },
+        };
+        if let Some(first) = keyed.get(&key) {
+            let error = format!("Control ID {key} is also used by {}", first.name);
+            errors.push((name, error));
+            continue;
+        }
+        keyed.insert(key, MessageFile { name, message });
+    }
+
+    Ok((keyed, errors))
+}
+
+/// List the message files directly inside a folder, sorted by name.
+fn collect_message_files(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let entries = std::fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read folder {}: {e}", dir.display()))?;
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            path.extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| ext.eq_ignore_ascii_case(MESSAGE_EXTENSION))
+        })
+        .collect();
+    files.sort();
+
+    Ok(files)
+}
+
+/// Control ID (MSH.10) of a message, if it parses and has one.
+fn control_id(message: &str) -> Option<String> {
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(message).ok()?;
+    let id = parsed.query("MSH.10")?.raw_value().trim();
+    (!id.is_empty()).then(|| id.to_string())
+}
+
+/// Compare the messages of a pair of files.
+fn compare_pair(
+    key: &str,
+    left: &MessageFile,
+    right: &MessageFile,
+    matching: Option<SegmentMatching>,
+    ignore_fields: &[String],
+) -> FileComparison {
+    let diff = match compare_messages(&left.message, &right.message, matching) {
+        Ok(diff) => diff,
+        Err(e) => {
+            return file_error(
+                key.to_string(),
+                Some(left.name.clone()),
+                Some(right.name.clone()),
+                e,
+            )
+        }
+    };
+
+    let mut summary = DiffSummary {
+        segments_added: 0,
+        segments_removed: 0,
+        segments_modified: 0,
+        total_field_changes: 0,
+    };
+    let mut changed_fields = Vec::new();
+    for segment in &diff.segments {
+        let fields: Vec<&str> = segment
+            .fields
+            .iter()
+            .filter(|field| field.diff_type != DiffType::Unchanged)
+            .map(|field| field.path.as_str())
+            .filter(|path| !is_ignored(path, ignore_fields))
+            .collect();
+        match segment.diff_type {
+            DiffType::Added => summary.segments_added += 1,
+            DiffType::Removed => summary.segments_removed += 1,
+            DiffType::Modified if !fields.is_empty() => summary.segments_modified += 1,
+            DiffType::Modified | DiffType::Unchanged => {}
+        }
+        summary.total_field_changes += fields.len();
+        changed_fields.extend(fields.into_iter().map(str::to_string));
+    }
+    changed_fields.sort();
+    changed_fields.dedup();
+
+    let changed = summary.segments_added + summary.segments_removed + summary.segments_modified > 0;
+    FileComparison {
+        key: key.to_string(),
+        left_file: Some(left.name.clone()),
+        right_file: Some(right.name.clone()),
+        status: if changed {
+            FileStatus::Changed
+        } else {
+            FileStatus::Identical
+        },
+        summary: Some(summary),
+        changed_fields,
+        error: None,
+    }
+}
+
+/// Whether a field path is, or is under, one of the ignored paths.
+fn is_ignored(path: &str, ignore_fields: &[String]) -> bool {
+    ignore_fields.iter().any(|ignored| {
+        path.strip_prefix(ignored.as_str())
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with(['.', '[']))
+    })
+}
+
+/// Comparison of a file with no partner on the other side.
+fn unpaired(
+    key: &str,
+    left: Option<&MessageFile>,
+    right: Option<&MessageFile>,
+    status: FileStatus,
+) -> FileComparison {
+    FileComparison {
+        key: key.to_string(),
+        left_file: left.map(|file| file.name.clone()),
+        right_file: right.map(|file| file.name.clone()),
+        status,
+        summary: None,
+        changed_fields: Vec::new(),
+        error: None,
+    }
+}
+
+/// Comparison of files that couldn't be compared.
+fn file_error(
+    key: String,
+    left_file: Option<String>,
+    right_file: Option<String>,
+    error: String,
+) -> FileComparison {
+    FileComparison {
+        key,
+        left_file,
+        right_file,
+        status: FileStatus::Error,
+        summary: None,
+        changed_fields: Vec::new(),
+        error: Some(error),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use crate::commands::temp_dir;
+
+    const ADT: &str =
+        "MSH|^~\\&|APP|FAC|||20250101120000||ADT^A01|MSG001|P|2.5.1\rPID|1||123||DOE^JOHN";
+    const ORU: &str =
+        "MSH|^~\\&|LAB|FAC|||20250101120000||ORU^R01|MSG002|P|2.5.1\rOBX|1|NM|GLU||5.5";
+
+    fn temp_dirs() -> (PathBuf, PathBuf) {
+        let root = temp_dir("dircmp");
+        let (left, right) = (root.join("left"), root.join("right"));
+        std::fs::create_dir_all(&left).unwrap();
+        std::fs::create_dir_all(&right).unwrap();
+        (left, right)
+    }
+
+    fn statuses(comparison: &DirectoryComparison) -> Vec<(&str, FileStatus)> {
+        comparison
+            .files
+            .iter()
+            .map(|file| (file.key.as_str(), file.status))
+            .collect()
+    }
+
+    #[test]
+    fn pairs_files_by_name() {
+        let (left, right) = temp_dirs();
+        std::fs::write(left.join("adt.hl7"), ADT).unwrap();
+        std::fs::write(right.join("adt.hl7"), ADT.replace("DOE^JOHN", "DOE^JANE")).unwrap();
+        std::fs::write(left.join("oru.hl7"), ORU).unwrap();
+        std::fs::write(right.join("oru.hl7"), format!("# title: Glucose\n{ORU}")).unwrap();
+        std::fs::write(left.join("gone.hl7"), ADT).unwrap();
+        std::fs::write(right.join("new.hl7"), ORU).unwrap();
+        std::fs::write(right.join("notes.txt"), "not a message").unwrap();
+
+        let comparison =
+            compare_directory_files(&left, &right, DirectoryPairing::FileName, None, &[]).unwrap();
+        assert_eq!(
+            statuses(&comparison),
+            vec![
+                ("adt.hl7", FileStatus::Changed),
+                ("gone.hl7", FileStatus::OnlyLeft),
+                ("new.hl7", FileStatus::OnlyRight),
+                ("oru.hl7", FileStatus::Identical),
+            ]
+        );
+        assert_eq!(comparison.files[0].changed_fields, vec!["PID.5.2"]);
+        assert_eq!(
+            comparison.summary,
+            DirectoryComparisonSummary {
+                identical: 1,
+                changed: 1,
+                only_left: 1,
+                only_right: 1,
+                errors: 0,
+            }
+        );
+
+        std::fs::remove_dir_all(left.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn pairs_files_by_control_id() {
+        let (left, right) = temp_dirs();
+        std::fs::write(left.join("in_1.hl7"), ADT).unwrap();
+        std::fs::write(left.join("in_2.hl7"), ORU).unwrap();
+        std::fs::write(right.join("out_20250102.hl7"), ORU).unwrap();
+        std::fs::write(right.join("out_20250103.hl7"), ADT).unwrap();
+        std::fs::write(right.join("out_20250104.hl7"), ADT).unwrap();
+
+        let comparison =
+            compare_directory_files(&left, &right, DirectoryPairing::ControlId, None, &[]).unwrap();
+        assert_eq!(
+            statuses(&comparison),
+            vec![
+                ("MSG001", FileStatus::Identical),
+                ("MSG002", FileStatus::Identical),
+                ("out_20250104.hl7", FileStatus::Error),
+            ]
+        );
+        assert_eq!(
+            comparison.files[0].right_file.as_deref(),
+            Some("out_20250103.hl7")
+        );
+        assert_eq!(
+            comparison.files[2].error.as_deref(),
+            Some("Control ID MSG001 is also used by out_20250103.hl7")
+        );
+
+        std::fs::remove_dir_all(left.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn ignores_restamped_fields() {
+        let (left, right) = temp_dirs();
+        std::fs::write(left.join("adt.hl7"), ADT).unwrap();
+        std::fs::write(
+            right.join("adt.hl7"),
+            ADT.replace("20250101120000", "20250301080000"),
+        )
+        .unwrap();
+
+        let ignore = vec!["MSH.7".to_string()];
+        let comparison =
+            compare_directory_files(&left, &right, DirectoryPairing::FileName, None, &ignore)
+                .unwrap();
+        assert_eq!(comparison.files[0].status, FileStatus::Identical);
+        assert!(comparison.files[0].changed_fields.is_empty());
+
+        let comparison =
+            compare_directory_files(&left, &right, DirectoryPairing::FileName, None, &[]).unwrap();
+        assert_eq!(comparison.files[0].status, FileStatus::Changed);
+
+        assert!(compare_directory_files(
+            &left.join("missing"),
+            &right,
+            DirectoryPairing::FileName,
+            None,
+            &[]
+        )
+        .is_err());
+
+        std::fs::remove_dir_all(left.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,321 @@
+//! Consistency rules for MDM (Medical Document Management) messages.
+//!
+//! Schema validation checks each TXA field in isolation, but most mistakes in
+//! document feeds are disagreements between fields: an authenticated document
+//! with nobody named as authenticator, a replacement with no parent document, or
+//! a "with content" trigger that carries no OBX. These rules catch those.
+//!
+//! # Trigger Events
+//!
+//! MDM trigger events come in pairs, the even one of each pair carrying the
+//! document content in OBX segments:
+//! * T01/T02 - Original document
+//! * T03/T04 - Document status change
+//! * T05/T06 - Document addendum
+//! * T07/T08 - Document edit
+//! * T09/T10 - Document replacement
+//! * T11 - Document cancel (no content variant)
+//!
+//! # Attachments
+//!
+//! OBX segments carrying documents are also checked: encapsulated data (ED)
+//! must declare a known encoding and include data, and reference pointers (RP)
+//! must include a pointer.
+
+use super::{Severity, ValidationIssue, ValidationRule};
+
+/// Trigger events whose messages carry the document content in OBX segments.
+const CONTENT_TRIGGERS: &[&str] = &["T02", "T04", "T06", "T08", "T10"];
+
+/// Trigger events that refer to an existing (parent) document.
+const PARENT_TRIGGERS: &[&str] = &["T05", "T06", "T09", "T10"];
+
+/// Completion statuses that mean the document has been authenticated.
+const AUTHENTICATED_STATUSES: &[&str] = &["AU", "LA"];
+
+/// Completion statuses that mean the document is still being worked on.
+const UNFINISHED_STATUSES: &[&str] = &["DI", "IN", "IP"];
+
+/// Encodings allowed in ED.4.
+const ED_ENCODINGS: &[&str] = &["A", "Hex", "Base64"];
+
+/// Check document consistency rules for MDM messages.
+///
+/// Messages of any other type are left alone, except that document attachments
+/// in OBX segments are always checked.
+///
+/// # Arguments
+/// * `msg` - Parsed message
+/// * `msg_type` - Message type from MSH.9.1
+/// * `trigger_event` - Trigger event from MSH.9.2
+/// * `issues` - Collected validation issues
+pub(super) fn validate_document_consistency(
+    msg: &hl7_parser::Message,
+    msg_type: &str,
+    trigger_event: &str,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    validate_attachments(msg, issues);
+
+    if !msg_type.eq_ignore_ascii_case("MDM") {
+        return;
+    }
+    let trigger = trigger_event.to_uppercase();
+
+    let txas: Vec<_> = msg.segments().filter(|s| s.name == "TXA").collect();
+    let Some(txa) = txas.first() else {
+        // a missing TXA is reported by the message structure check
+        return;
+    };
+    for extra in txas.iter().skip(1) {
+        issues.push(issue(
+            "TXA",
+            Some((extra.range.start, extra.range.end)),
+            Severity::Error,
+            "MDM messages describe a single document and must contain only one TXA segment"
+                .to_string(),
+            None,
+        ));
+    }
+
+    let segment_range = Some((txa.range.start, txa.range.end));
+    let value = |field: usize| field_value(msg, txa, field);
+
+    // content-bearing triggers need OBX segments, the others must not have any
+    let has_obx = msg.segments().any(|s| s.name == "OBX");
+    let carries_content = CONTENT_TRIGGERS.contains(&trigger.as_str());
+    if carries_content && !has_obx {
+        issues.push(issue(
+            "OBX",
+            None,
+            Severity::Error,
+            format!("MDM^{trigger} messages must carry the document content in OBX segments"),
+            None,
+        ));
+    } else if !carries_content && has_obx {
+        issues.push(issue(
+            "OBX",
+            None,
+            Severity::Warning,
+            format!("MDM^{trigger} messages notify without content; OBX segments will likely be ignored"),
+            None,
+        ));
+    }
+
+    // addenda and replacements must point at the document they modify
+    let (document_number, _) = value(12);
+    let (parent_number, parent_range) = value(13);
+    if PARENT_TRIGGERS.contains(&trigger.as_str()) && parent_number.is_empty() {
+        issues.push(issue(
+            "TXA.13",
+            parent_range.or(segment_range),
+            Severity::Error,
+            format!("TXA.13 (Parent Document Number) is required for MDM^{trigger} messages"),
+            None,
+        ));
+    }
+    if !parent_number.is_empty() && parent_number == document_number {
+        issues.push(issue(
+            "TXA.13",
+            parent_range,
+            Severity::Error,
+            "TXA.13 (Parent Document Number) must differ from TXA.12 (Unique Document Number)"
+                .to_string(),
+            Some(parent_number),
+        ));
+    }
+
+    // authenticated documents must say who authenticated them
+    let (completion_status, completion_range) = value(17);
+    let (authenticator, _) = value(22);
+    if AUTHENTICATED_STATUSES.contains(&completion_status.as_str()) && authenticator.is_empty() {
+        issues.push(issue(
+            "TXA.22",
+            segment_range,
+            Severity::Error,
+            format!(
+                "TXA.22 (Authentication Person) is required when TXA.17 (Document Completion Status) is '{completion_status}'"
+            ),
+            None,
+        ));
+    }
+
+    // documents still being worked on shouldn't be used for patient care
+    let (availability, availability_range) = value(19);
+    if availability == "AV" && UNFINISHED_STATUSES.contains(&completion_status.as_str()) {
+        issues.push(issue(
+            "TXA.19",
+            availability_range.or(completion_range),
+            Severity::Warning,
+            format!(
+                "TXA.19 (Document Availability Status) is 'AV' but TXA.17 (Document Completion Status) is '{completion_status}'; unfinished documents are normally 'UN'"
+            ),
+            Some(availability),
+        ));
+    }
+}
+
+/// Check OBX segments carrying encapsulated data or reference pointers.
+fn validate_attachments(msg: &hl7_parser::Message, issues: &mut Vec<ValidationIssue>) {
+    for (index, obx) in msg.segments().filter(|s| s.name == "OBX").enumerate() {
+        let path = if index == 0 {
+            "OBX.5".to_string()
+        } else {
+            format!("OBX[{}].5", index + 1)
+        };
+        let (value_type, _) = field_value(msg, obx, 2);
+        let Some(value) = obx.fields.get(4).and_then(|f| f.repeats.first()) else {
+            continue;
+        };
+        let range = Some((value.range.start, value.range.end));
+        let component = |n: usize| {
+            value
+                .components
+                .get(n - 1)
+                .map(|c| msg.separators.decode(c.raw_value()).to_string())
+                .unwrap_or_default()
+        };
+
+        match value_type.as_str() {
+            "ED" => {
+                let encoding = component(4);
+                if !ED_ENCODINGS.contains(&encoding.as_str()) {
+                    issues.push(issue(
+                        &format!("{path}.4"),
+                        range,
+                        Severity::Error,
+                        format!(
+                            "Encapsulated data encoding must be one of {}",
+                            ED_ENCODINGS.join(", ")
+                        ),
+                        Some(encoding),
+                    ));
+                }
+                if component(5).is_empty() {
+                    issues.push(issue(
+                        &format!("{path}.5"),
+                        range,
+                        Severity::Error,
+                        "Encapsulated data has no content".to_string(),
+                        None,
+                    ));
+                }
+            }
+            "RP" => {
+                if component(1).is_empty() {
+                    issues.push(issue(
+                        &format!("{path}.1"),
+                        range,
+                        Severity::Error,
+                        "Reference pointer has no pointer".to_string(),
+                        None,
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Get the decoded first component of a field, along with its range.
+fn field_value(
+    msg: &hl7_parser::Message,
+    segment: &hl7_parser::message::Segment,
+    field: usize,
+) -> (String, Option<(usize, usize)>) {
+    segment
+        .fields
+        .get(field - 1)
+        .and_then(|f| f.repeats.first())
+        .and_then(|r| r.components.first())
+        .map(|c| {
+            (
+                msg.separators.decode(c.raw_value()).to_string(),
+                Some((c.range.start, c.range.end)),
+            )
+        })
+        .unwrap_or_default()
+}
+
+fn issue(
+    path: &str,
+    range: Option<(usize, usize)>,
+    severity: Severity,
+    message: String,
+    actual_value: Option<String>,
+) -> ValidationIssue {
+    ValidationIssue {
+        path: path.to_string(),
+        range,
+        severity,
+        message,
+        rule: ValidationRule::DocumentConsistency,
+        actual_value,
+        fix: None,
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    fn check(message: &str) -> Vec<ValidationIssue> {
+        let msg = hl7_parser::parse_message_with_lenient_newlines(message).unwrap();
+        let trigger = if message.contains("MDM^T10") {
+            "T10"
+        } else {
+            "T02"
+        };
+        let mut issues = Vec::new();
+        validate_document_consistency(&msg, "MDM", trigger, &mut issues);
+        issues
+    }
+
+    const MSH_T02: &str = "MSH|^~\\&|APP|FAC|||20250101||MDM^T02|1|P|2.5.1";
+
+    #[test]
+    fn consistent_document_has_no_issues() {
+        let message = format!(
+            "{MSH_T02}\rTXA|1|DS|TX|||||||||DOC1|||||AU||AV|||1234^House^Gregory\rOBX|1|TX|DOC^Document||Hello"
+        );
+        assert!(check(&message).is_empty());
+    }
+
+    #[test]
+    fn content_triggers_require_obx() {
+        let message = format!("{MSH_T02}\rTXA|1|DS|TX|||||||||DOC1|||||DI||UN");
+        let issues = check(&message);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].path, "OBX");
+    }
+
+    #[test]
+    fn authenticated_documents_require_authenticator() {
+        let message = format!("{MSH_T02}\rTXA|1|DS|TX|||||||||DOC1|||||LA||AV\rOBX|1|TX|DOC||x");
+        let issues = check(&message);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].path, "TXA.22");
+    }
+
+    #[test]
+    fn replacements_require_distinct_parent() {
+        let msh = MSH_T02.replace("T02", "T10");
+        let missing = format!("{msh}\rTXA|1|DS|TX|||||||||DOC2|||||DI||UN\rOBX|1|TX|DOC||x");
+        assert_eq!(check(&missing)[0].path, "TXA.13");
+
+        let same = format!("{msh}\rTXA|1|DS|TX|||||||||DOC2|DOC2||||DI||UN\rOBX|1|TX|DOC||x");
+        let issues = check(&same);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].actual_value.as_deref(), Some("DOC2"));
+    }
+
+    #[test]
+    fn attachments_are_checked() {
+        let message = format!(
+            "{MSH_T02}\rTXA|1|DS|TX|||||||||DOC1|||||DI||UN\rOBX|1|ED|DOC||^AP^PDF^Base32^abcd\rOBX|2|RP|DOC||^APP^AP^PDF"
+        );
+        let paths: Vec<_> = check(&message).into_iter().map(|i| i.path).collect();
+        assert_eq!(paths, vec!["OBX.5.4", "OBX[2].5.1"]);
+    }
+}
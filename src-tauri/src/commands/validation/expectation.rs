@@ -0,0 +1,488 @@
+//! Matching a message against a "golden" expectation.
+//!
+//! An exact diff is too strict for asserting what an interface engine produced:
+//! MSH-7 is the time the message was built, MSH-10 a fresh control ID, and
+//! visit numbers come from a counter. An expectation is the message as it
+//! should look, with placeholders where values legitimately vary:
+//!
+//! ```text
+//! MSH|^~\&|ENGINE|HOSP|||{datetime}||ADT^A01|{*}|P|2.5.1
+//! PID|1||{/\d{6}/}^^^MRN||DOE^JOHN||{date}
+//! ```
+//!
+//! # Placeholders
+//!
+//! | Placeholder  | Matches                                             |
+//! |--------------|-----------------------------------------------------|
+//! | `{*}`        | Anything, including nothing                         |
+//! | `{any}`      | Any value that isn't empty                          |
+//! | `{date}`     | A valid HL7 date (`YYYY[MM[DD]]`)                   |
+//! | `{datetime}` | A valid HL7 timestamp (`YYYYMMDDHHMMSS[.S][+ZZZZ]`) |
+//! | `{number}`   | A decimal number                                    |
+//! | `{/regex/}`  | Text matching the regular expression                |
+//!
+//! Placeholders can be mixed with literal text (`MSG{number}`), and anything
+//! else in braces is literal text. A regular expression can't contain the
+//! message's delimiters, since the expectation is parsed as a message first;
+//! use `\x5E` and the like instead.
+//!
+//! # Matching
+//!
+//! Segments are paired by name and occurrence, so the second OBX is checked
+//! against the second expected OBX. Within a segment, each part of the
+//! expectation is checked at the level it's written at: `{*}` in PID-3 accepts
+//! any identifiers, with any number of repetitions, while `{*}^^^MRN` only
+//! leaves the ID itself open. Parts the expectation leaves empty must be empty.
+//!
+//! MSH-1 and MSH-2 aren't compared; values are compared decoded, so the two
+//! messages may use different delimiters.
+
+use hl7_parser::datetime::{parse_date, parse_timestamp};
+use hl7_parser::message::{Segment, Separators};
+use hl7_parser::Message;
+use indexmap::IndexMap;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::commands::Utf16Offsets;
+
+/// A part of the message that doesn't meet the expectation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpectationMismatch {
+    /// Path of the part (e.g., "PID.5.1", "OBX[2].5", "PID.3[2]")
+    pub path: String,
+    /// What the expectation holds there, as written (None if nothing)
+    pub expected: Option<String>,
+    /// What the message holds there (None if nothing)
+    pub actual: Option<String>,
+    /// Character range in the message for highlighting, in UTF-16 code units
+    pub range: Option<(usize, usize)>,
+}
+
+/// The outcome of matching a message against an expectation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExpectationResult {
+    /// Whether the message meets the expectation
+    pub matched: bool,
+    /// Every part that doesn't, in message order
+    pub mismatches: Vec<ExpectationMismatch>,
+}
+
+/// Match a message against an expectation that may contain placeholders.
+///
+/// # Arguments
+/// * `actual` - The message to check, such as an engine's output
+/// * `expected` - The expected message, with placeholders where values vary
+///
+/// # Returns
+/// * `Ok(ExpectationResult)` - The mismatches, if any
+/// * `Err(String)` - If either message can't be parsed, or a placeholder's
+///   regular expression is invalid
+#[tauri::command]
+pub fn match_expectation(actual: &str, expected: &str) -> Result<ExpectationResult, String> {
+    let mut result = check_expectation(actual, expected)?;
+    let offsets = Utf16Offsets::new(actual);
+    for mismatch in &mut result.mismatches {
+        if let Some((start, end)) = mismatch.range.as_mut() {
+            *start = offsets.to_utf16(*start);
+            *end = offsets.to_utf16(*end);
+        }
+    }
+    Ok(result)
+}
+
+/// Match a message against an expectation, with ranges as byte offsets.
+pub fn check_expectation(actual: &str, expected: &str) -> Result<ExpectationResult, String> {
+    let actual_msg = hl7_parser::parse_message_with_lenient_newlines(actual)
+        .map_err(|e| format!("Failed to parse message: {e}"))?;
+    let expected_msg = hl7_parser::parse_message_with_lenient_newlines(expected)
+        .map_err(|e| format!("Failed to parse expected message: {e}"))?;
+
+    let mut matcher = Matcher {
+        expected: &expected_msg.separators,
+        actual: &actual_msg.separators,
+        mismatches: Vec::new(),
+    };
+
+    let expected_segments = by_name(&expected_msg);
+    let actual_segments = by_name(&actual_msg);
+    let count = |name: &str| {
+        let in_expected = expected_segments.get(name).map_or(0, Vec::len);
+        let in_actual = actual_segments.get(name).map_or(0, Vec::len);
+        in_expected.max(in_actual)
+    };
+
+    for (name, segments) in &expected_segments {
+        for (occurrence, expected_segment) in segments.iter().enumerate() {
+            let label = segment_label(name, occurrence, count(name));
+            match actual_segments
+                .get(name)
+                .and_then(|actual| actual.get(occurrence))
+            {
+                Some(actual_segment) => {
+                    matcher.segment(&label, expected_segment, actual_segment)?;
+                }
+                None => matcher.mismatches.push(ExpectationMismatch {
+                    path: label,
+                    expected: Some(expected_segment.raw_value().to_string()),
+                    actual: None,
+                    range: None,
+                }),
+            }
+        }
+    }
+
+    // segments the expectation doesn't have at all
+    for (name, segments) in &actual_segments {
+        let expected_count = expected_segments.get(name).map_or(0, Vec::len);
+        for (occurrence, segment) in segments.iter().enumerate().skip(expected_count) {
+            matcher.mismatches.push(ExpectationMismatch {
+                path: segment_label(name, occurrence, count(name)),
+                expected: None,
+                actual: Some(segment.raw_value().to_string()),
+                range: Some((segment.range.start, segment.range.end)),
+            });
+        }
+    }
+
+    let mut mismatches = matcher.mismatches;
+    mismatches.sort_by_key(|mismatch| mismatch.range.map_or(usize::MAX, |(start, _)| start));
+    Ok(ExpectationResult {
+        matched: mismatches.is_empty(),
+        mismatches,
+    })
+}
+
+/// Segments of a message grouped by name, in order of first appearance.
+fn by_name<'m>(message: &'m Message<'m>) -> IndexMap<&'m str, Vec<&'m Segment<'m>>> {
+    let mut groups: IndexMap<&str, Vec<&Segment>> = IndexMap::new();
+    for segment in message.segments() {
+        groups.entry(segment.name).or_default().push(segment);
+    }
+    groups
+}
+
+/// Path of a segment, with its occurrence if the segment repeats.
+fn segment_label(name: &str, occurrence: usize, count: usize) -> String {
+    if count > 1 {
+        format!("{name}[{}]", occurrence + 1)
+    } else {
+        name.to_string()
+    }
+}
+
+/// Walks the expectation alongside the message, collecting mismatches.
+struct Matcher<'s> {
+    expected: &'s Separators,
+    actual: &'s Separators,
+    mismatches: Vec<ExpectationMismatch>,
+}
+
+impl Matcher<'_> {
+    /// Check the fields of a segment.
+    fn segment(&mut self, label: &str, expected: &Segment, actual: &Segment) -> Result<(), String> {
+        // MSH-1 and MSH-2 are the delimiters, which may differ
+        let skip = if expected.name == "MSH" { 2 } else { 0 };
+        let fields = expected.fields.len().max(actual.fields.len());
+
+        for idx in skip..fields {
+            let expected_field = expected.fields.get(idx).map_or("", |f| f.raw_value());
+            let (actual_field, start) = actual
+                .fields
+                .get(idx)
+                .map_or(("", actual.range.end), |f| (f.raw_value(), f.range.start));
+            self.element(
+                format!("{label}.{}", idx + 1),
+                0,
+                expected_field,
+                actual_field,
+                start,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Check one part of a field, `depth` levels below the field itself
+    /// (0 = field, 1 = repetition, 2 = component, 3 = subcomponent).
+    ///
+    /// A part the expectation doesn't break down any further is checked as a
+    /// whole, however much structure the message has there; otherwise the
+    /// check continues with its children.
+    fn element(
+        &mut self,
+        path: String,
+        depth: usize,
+        expected: &str,
+        actual: &str,
+        start: usize,
+    ) -> Result<(), String> {
+        let expected_below = child_separators(self.expected, depth);
+        if !expected.contains(expected_below.as_slice()) {
+            return self.leaf(path, expected, actual, start);
+        }
+
+        let Some(&expected_sep) = expected_below.first() else {
+            return self.leaf(path, expected, actual, start);
+        };
+        let Some(&actual_sep) = child_separators(self.actual, depth).first() else {
+            return self.leaf(path, expected, actual, start);
+        };
+
+        let expected_parts: Vec<&str> = expected.split(expected_sep).collect();
+        let mut actual_parts = Vec::new();
+        let mut offset = start;
+        for part in actual.split(actual_sep) {
+            actual_parts.push((part, offset));
+            offset += part.len() + actual_sep.len_utf8();
+        }
+
+        let parts = expected_parts.len().max(actual_parts.len());
+        for i in 0..parts {
+            let child_path = match depth {
+                // repetitions are only numbered where there's more than one
+                0 if parts > 1 => format!("{path}[{}]", i + 1),
+                0 => path.clone(),
+                _ => format!("{path}.{}", i + 1),
+            };
+            let expected_part = expected_parts.get(i).copied().unwrap_or_default();
+            let (actual_part, part_start) = actual_parts
+                .get(i)
+                .copied()
+                .unwrap_or(("", start + actual.len()));
+            self.element(
+                child_path,
+                depth + 1,
+                expected_part,
+                actual_part,
+                part_start,
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Check a part of the message against a part of the expectation as a whole.
+    fn leaf(
+        &mut self,
+        path: String,
+        expected: &str,
+        actual: &str,
+        start: usize,
+    ) -> Result<(), String> {
+        let actual_value = self.actual.decode(actual).to_string();
+        let matches = match Pattern::compile(expected, self.expected)
+            .map_err(|e| format!("Invalid placeholder in {path}: {e}"))?
+        {
+            Some(pattern) => pattern.matches(&actual_value),
+            None => self.expected.decode(expected) == actual_value,
+        };
+
+        if !matches {
+            self.mismatches.push(ExpectationMismatch {
+                path,
+                expected: (!expected.is_empty()).then(|| expected.to_string()),
+                actual: (!actual_value.is_empty()).then_some(actual_value),
+                range: Some((start, start + actual.len())),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Separators that divide a part at `depth` into smaller parts, outermost first.
+fn child_separators(separators: &Separators, depth: usize) -> Vec<char> {
+    [
+        separators.repetition,
+        separators.component,
+        separators.subcomponent,
+    ]
+    .into_iter()
+    .skip(depth)
+    .collect()
+}
+
+/// A placeholder in an expectation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Placeholder {
+    /// `{*}`
+    Anything,
+    /// `{any}`
+    NonEmpty,
+    /// `{date}`
+    Date,
+    /// `{datetime}`
+    DateTime,
+    /// `{number}`
+    Number,
+    /// `{/regex/}`
+    Regex(String),
+}
+
+impl Placeholder {
+    /// Read a placeholder at the start of some text, returning it and its length.
+    fn parse(text: &str) -> Option<(Self, usize)> {
+        if let Some(body) = text.strip_prefix("{/") {
+            let end = body.find("/}")?;
+            let regex = body.get(..end)?.to_string();
+            return Some((Placeholder::Regex(regex), end + 4));
+        }
+
+        let close = text.find('}')?;
+        let placeholder = match text.get(1..close)? {
+            "*" => Placeholder::Anything,
+            "any" => Placeholder::NonEmpty,
+            "date" => Placeholder::Date,
+            "datetime" => Placeholder::DateTime,
+            "number" => Placeholder::Number,
+            _ => return None,
+        };
+        Some((placeholder, close + 1))
+    }
+}
+
+/// An expectation value with placeholders, compiled for matching.
+struct Pattern {
+    regex: Regex,
+    /// Capture groups holding dates and timestamps, which are checked for
+    /// validity after the regular expression matches
+    dates: Vec<(String, Placeholder)>,
+}
+
+impl Pattern {
+    /// Compile an expectation value, or `None` if it has no placeholders.
+    fn compile(text: &str, separators: &Separators) -> Result<Option<Self>, String> {
+        let mut pattern = String::from("^(?s:");
+        let mut dates = Vec::new();
+        let mut literal = String::new();
+        let mut found = false;
+        let mut rest = text;
+
+        while let Some(open) = rest.find('{') {
+            literal.push_str(rest.get(..open).unwrap_or_default());
+            let from = rest.get(open..).unwrap_or_default();
+            let Some((placeholder, len)) = Placeholder::parse(from) else {
+                literal.push('{');
+                rest = from.get(1..).unwrap_or_default();
+                continue;
+            };
+
+            found = true;
+            pattern.push_str(&regex::escape(&separators.decode(&literal)));
+            literal.clear();
+            let group = format!("p{}", dates.len());
+            match &placeholder {
+                Placeholder::Anything => pattern.push_str(".*"),
+                Placeholder::NonEmpty => pattern.push_str(".+"),
+                Placeholder::Number => pattern.push_str(r"[+-]?(?:\d+\.?\d*|\.\d+)"),
+                Placeholder::Regex(regex) => {
+                    Regex::new(regex).map_err(|e| e.to_string())?;
+                    pattern.push_str(&format!("(?:{regex})"));
+                }
+                Placeholder::Date => {
+                    pattern.push_str(&format!(r"(?P<{group}>\d{{4,8}})"));
+                    dates.push((group, placeholder.clone()));
+                }
+                Placeholder::DateTime => {
+                    pattern.push_str(&format!(
+                        r"(?P<{group}>\d{{4,14}}(?:\.\d{{1,4}})?(?:[+-]\d{{4}})?)"
+                    ));
+                    dates.push((group, placeholder.clone()));
+                }
+            }
+            rest = from.get(len..).unwrap_or_default();
+        }
+
+        if !found {
+            return Ok(None);
+        }
+        literal.push_str(rest);
+        pattern.push_str(&regex::escape(&separators.decode(&literal)));
+        pattern.push_str(")$");
+
+        let regex = Regex::new(&pattern).map_err(|e| e.to_string())?;
+        Ok(Some(Pattern { regex, dates }))
+    }
+
+    /// Whether a decoded value matches.
+    fn matches(&self, value: &str) -> bool {
+        let Some(captures) = self.regex.captures(value) else {
+            return false;
+        };
+        self.dates.iter().all(|(group, placeholder)| {
+            let Some(text) = captures.name(group).map(|m| m.as_str()) else {
+                return false;
+            };
+            match placeholder {
+                Placeholder::Date => parse_date(text, false).is_ok(),
+                Placeholder::DateTime => parse_timestamp(text, false).is_ok(),
+                Placeholder::Anything
+                | Placeholder::NonEmpty
+                | Placeholder::Number
+                | Placeholder::Regex(_) => true,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const ACTUAL: &str = "MSH|^~\\&|ENGINE|HOSP|||20250114091244||ADT^A01|8F2K1Q|P|2.5.1\rPID|1||123456^^^MRN~999^^^SSN||DOE^JOHN||19800101\rOBX|1|NM|GLU||5.5\rOBX|2|NM|HGB||13";
+
+    fn paths(result: &ExpectationResult) -> Vec<&str> {
+        result.mismatches.iter().map(|m| m.path.as_str()).collect()
+    }
+
+    #[test]
+    fn placeholders_accept_varying_values() {
+        let expected = "MSH|^~\\&|ENGINE|HOSP|||{datetime}||ADT^A01|{any}|P|2.5.1\rPID|1||{*}||DOE^JOHN||{date}\rOBX|1|NM|GLU||{number}\rOBX|2|NM|HGB||{/1[0-9]/}";
+        let result = check_expectation(ACTUAL, expected).unwrap();
+        assert!(result.matched, "{:?}", result.mismatches);
+    }
+
+    #[test]
+    fn only_true_mismatches_are_reported() {
+        let expected = "MSH|^~\\&|ENGINE|HOSP|||{datetime}||ADT^A01|{*}|P|2.5.1\rPID|1||{/\\d{6}/}^^^MRN~{*}||DOE^JANE||{date}\rOBX|1|NM|GLU||5.5\rOBX|2|NM|HGB||14";
+        let result = check_expectation(ACTUAL, expected).unwrap();
+        assert!(!result.matched);
+        assert_eq!(paths(&result), ["PID.5.2", "OBX[2].5"]);
+
+        let name = &result.mismatches[0];
+        assert_eq!(name.expected.as_deref(), Some("JANE"));
+        assert_eq!(name.actual.as_deref(), Some("JOHN"));
+        let (start, end) = name.range.unwrap();
+        assert_eq!(&ACTUAL[start..end], "JOHN");
+    }
+
+    #[test]
+    fn placeholders_check_their_values() {
+        let expected = "MSH|^~\\&|ENGINE|HOSP|||{date}||ADT^A01|{number}|P|2.5.1\rPID|1||{/\\d{3}/}^^^MRN~{*}||{*}||{date}\rOBX|1|NM|GLU||{*}\rOBX|2|NM|HGB||{*}";
+        let result = check_expectation(ACTUAL, expected).unwrap();
+        assert_eq!(paths(&result), ["MSH.7", "MSH.10", "PID.3[1].1"]);
+
+        // not a real date, even though it has the right digits
+        let result = check_expectation(
+            "MSH|^~\\&\rPID|1||||||20251341",
+            "MSH|^~\\&\rPID|1||||||{date}",
+        )
+        .unwrap();
+        assert_eq!(paths(&result), ["PID.7"]);
+    }
+
+    #[test]
+    fn missing_and_extra_segments_are_mismatches() {
+        let expected = "MSH|^~\\&|ENGINE|HOSP|||{*}||ADT^A01|{*}|P|2.5.1\rPID|1||{*}||DOE^JOHN||{*}\rPV1|1|I\rOBX|1|NM|GLU||{*}";
+        let result = check_expectation(ACTUAL, expected).unwrap();
+        assert_eq!(paths(&result), ["OBX[2]", "PV1"]);
+        assert!(result.mismatches[0].expected.is_none());
+        assert!(result.mismatches[1].actual.is_none());
+    }
+
+    #[test]
+    fn unknown_braces_are_literal_and_bad_regexes_fail() {
+        let result = check_expectation("MSH|^~\\&\rZZZ|{x}", "MSH|^~\\&\rZZZ|{x}").unwrap();
+        assert!(result.matched);
+        assert!(check_expectation(ACTUAL, "MSH|^~\\&\rPID|{/(/}").is_err());
+    }
+}
@@ -0,0 +1,352 @@
+//! Machine-applicable fixes for validation issues.
+//!
+//! Many issues have one obvious fix: a sex code typed in lowercase, a date
+//! written as `2025-01-15`, or a segment ending in a run of empty fields.
+//! Validation attaches a [`QuickFix`] to such issues, and the commands here
+//! apply one fix, or every fix in a message at once, so a generated test
+//! corpus doesn't have to be cleaned up by hand.
+//!
+//! # Fixes
+//!
+//! * **Allowed values** - A value matching an allowed code in all but case is
+//!   changed to the code (e.g., `f` to `F`)
+//! * **Dates** - ISO 8601 dates and timestamps are rewritten in HL7 form, dates
+//!   holding a time are cut to the date, and a year or year and month is padded
+//!   to a full date
+//! * **Trailing delimiters** - Separators at the end of a segment, which only
+//!   add empty fields or components, are removed
+//!
+//! A fix replaces the text in its issue's range, so it moves with the issue
+//! wherever the issue's range is converted or shifted. Fixes are only offered
+//! when the result passes the check that raised the issue.
+
+use hl7_parser::datetime::{parse_date, parse_timestamp};
+use hl7_parser::message::Separators;
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+use super::{validate_message, Severity, ValidationIssue, ValidationMode, ValidationRule};
+use crate::commands::{CursorRange, Utf16Offsets};
+use crate::schema::segment::DataType;
+use crate::AppData;
+
+/// A fix for a validation issue, replacing the text in the issue's range.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QuickFix {
+    /// What the fix does, for display (e.g., "Change to 'F'")
+    pub title: String,
+    /// Text to put in place of the issue's range
+    pub replacement: String,
+}
+
+/// A message after fixes were applied to it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FixResult {
+    /// The fixed message
+    pub message: String,
+    /// Where each replacement now is in the fixed message, in message order
+    pub ranges: Vec<CursorRange>,
+    /// Fixable issues left unfixed because they overlapped an applied fix;
+    /// validating again finds them
+    pub skipped: usize,
+}
+
+/// Apply the fix of one validation issue.
+///
+/// # Arguments
+/// * `message` - The message the issue was found in
+/// * `issue` - The issue, as returned by validation, with its UTF-16 range
+///
+/// # Returns
+/// * `Ok(FixResult)` - The fixed message and where the replacement is
+/// * `Err(String)` - The issue has no fix, or its range isn't in the message
+///   (most likely because the message changed since it was validated)
+#[tauri::command]
+pub fn apply_fix(message: &str, issue: ValidationIssue) -> Result<FixResult, String> {
+    let offsets = Utf16Offsets::new(message);
+    let fix = issue
+        .fix
+        .ok_or_else(|| format!("{} has no fix", issue.message))?;
+    let (start, end) = issue
+        .range
+        .ok_or_else(|| format!("{} has no range to fix", issue.message))?;
+    let range = offsets.byte_range(&CursorRange { start, end });
+    apply_fixes(message, vec![(range.start, range.end, fix)])
+}
+
+/// Apply every fix full validation finds in a message.
+///
+/// Fixes whose ranges overlap one already applied are skipped.
+///
+/// # Arguments
+/// * `message` - The message to fix
+/// * `state` - Application state containing the schema cache
+///
+/// # Returns
+/// * `Ok(FixResult)` - The fixed message and where each replacement is
+/// * `Err(String)` - A fix's range isn't in the message
+#[tauri::command]
+pub fn apply_all_fixes(message: &str, state: State<'_, AppData>) -> Result<FixResult, String> {
+    let fixes = validate_message(message, &state.schema, ValidationMode::Full)
+        .issues
+        .into_iter()
+        .filter_map(|issue| {
+            let (start, end) = issue.range?;
+            Some((start, end, issue.fix?))
+        })
+        .collect();
+    apply_fixes(message, fixes)
+}
+
+/// Apply fixes given by byte range, returning the new ranges in UTF-16 offsets.
+fn apply_fixes(
+    message: &str,
+    mut fixes: Vec<(usize, usize, QuickFix)>,
+) -> Result<FixResult, String> {
+    fixes.sort_by_key(|(start, end, _)| (*start, *end));
+
+    let mut fixed = String::with_capacity(message.len());
+    let mut copied = 0;
+    let mut ranges = Vec::new();
+    let mut skipped = 0;
+    for (start, end, fix) in fixes {
+        if start < copied {
+            skipped += 1;
+            continue;
+        }
+        let unchanged = message
+            .get(copied..start)
+            .filter(|_| message.is_char_boundary(end) && end >= start && end <= message.len())
+            .ok_or_else(|| {
+                "The message has changed since it was validated; validate it again".to_string()
+            })?;
+        fixed.push_str(unchanged);
+        ranges.push(fixed.len()..fixed.len() + fix.replacement.len());
+        fixed.push_str(&fix.replacement);
+        copied = end;
+    }
+    fixed.push_str(message.get(copied..).unwrap_or_default());
+
+    let offsets = Utf16Offsets::new(&fixed);
+    Ok(FixResult {
+        ranges: ranges
+            .iter()
+            .map(|range| offsets.cursor_range(range))
+            .collect(),
+        message: fixed,
+        skipped,
+    })
+}
+
+/// Fix for a value that matches an allowed code in all but case.
+pub(super) fn allowed_value_fix(value: &str, allowed: &[&String]) -> Option<QuickFix> {
+    let mut matching = allowed
+        .iter()
+        .filter(|code| code.eq_ignore_ascii_case(value));
+    let code = matching.next()?;
+    if matching.next().is_some() {
+        // e.g. both "a" and "A" are allowed; there's no telling which was meant
+        return None;
+    }
+    Some(QuickFix {
+        title: format!("Change to '{code}'"),
+        replacement: (*code).clone(),
+    })
+}
+
+/// Fix for a date or timestamp that's in the wrong form, if it can be read.
+pub(super) fn date_fix(value: &str, datatype: DataType) -> Option<QuickFix> {
+    let mut digits = iso_to_hl7(value).unwrap_or_else(|| value.to_string());
+    if !digits.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    if datatype == DataType::Date {
+        match digits.len() {
+            4 => digits.push_str("0101"),
+            6 => digits.push_str("01"),
+            len if len > 8 => digits.truncate(8),
+            _ => {}
+        }
+    }
+
+    let valid = match datatype {
+        DataType::Date => parse_date(&digits, false).is_ok(),
+        DataType::DateTime => parse_timestamp(&digits, false).is_ok(),
+    };
+    (valid && digits != value).then(|| QuickFix {
+        title: format!("Change to {digits}"),
+        replacement: digits,
+    })
+}
+
+/// Rewrite an ISO 8601 date or local timestamp (`2025-01-15`, `2025-01-15T10:30`,
+/// `2025/01/15 10:30:00`) as HL7 digits, if it is one.
+fn iso_to_hl7(value: &str) -> Option<String> {
+    let (date, time) = match value.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (value, None),
+    };
+    let date_parts: Vec<&str> = date.split(['-', '/']).collect();
+    let time_parts: Vec<&str> = time.map(|t| t.split(':').collect()).unwrap_or_default();
+    let widths_match = |parts: &[&str], widths: &[usize]| {
+        parts.len() == widths.len()
+            && parts.iter().zip(widths).all(|(part, width)| {
+                part.len() == *width && part.chars().all(|c| c.is_ascii_digit())
+            })
+    };
+    if !widths_match(&date_parts, &[4, 2, 2]) {
+        return None;
+    }
+    if time.is_some()
+        && !widths_match(&time_parts, &[2, 2])
+        && !widths_match(&time_parts, &[2, 2, 2])
+    {
+        return None;
+    }
+    Some(date_parts.concat() + &time_parts.concat())
+}
+
+/// Flag segments that end in field, repeat, component, or subcomponent
+/// separators, with a fix removing them.
+pub(super) fn check_trailing_delimiters(
+    msg: &hl7_parser::Message,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    for segment in msg.segments() {
+        let raw = segment.raw_value();
+        let Some(keep) = trimmed_length(segment.name, raw, &msg.separators) else {
+            continue;
+        };
+        let start = segment.range.start + keep;
+        issues.push(ValidationIssue {
+            path: segment.name.to_string(),
+            range: Some((start, segment.range.start + raw.len())),
+            severity: Severity::Info,
+            message: format!(
+                "{} ends with empty fields or components that can be removed",
+                segment.name
+            ),
+            rule: ValidationRule::TrailingDelimiters,
+            actual_value: raw.get(keep..).map(str::to_string),
+            fix: Some(QuickFix {
+                title: "Remove trailing delimiters".to_string(),
+                replacement: String::new(),
+            }),
+        });
+    }
+}
+
+/// Length of a segment's text without its trailing separators, or None if it
+/// has none.
+///
+/// MSH's encoding characters (MSH.2) end in the subcomponent separator, so
+/// they're never trimmed.
+pub(super) fn trimmed_length(name: &str, raw: &str, separators: &Separators) -> Option<usize> {
+    let is_separator = |c: char| {
+        c == separators.field
+            || c == separators.repetition
+            || c == separators.component
+            || c == separators.subcomponent
+    };
+    let minimum = if name == "MSH" {
+        // "MSH|" followed by the encoding characters up to the next field separator
+        let encoding_start = name.len() + separators.field.len_utf8();
+        match raw.get(encoding_start..)?.find(separators.field) {
+            Some(end) => encoding_start + end,
+            None => return None,
+        }
+    } else {
+        name.len()
+    };
+
+    let trimmed = raw.trim_end_matches(is_separator).len().max(minimum);
+    (trimmed < raw.len()).then_some(trimmed)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use crate::schema::cache::SchemaCache;
+
+    #[test]
+    fn fixes_values_differing_in_case() {
+        let f = "F".to_string();
+        let m = "M".to_string();
+        let fix = allowed_value_fix("f", &[&f, &m]).unwrap();
+        assert_eq!(fix.replacement, "F");
+        assert!(allowed_value_fix("X", &[&f, &m]).is_none());
+
+        let lower = "f".to_string();
+        assert!(allowed_value_fix("f", &[&f, &lower]).is_none());
+    }
+
+    #[test]
+    fn fixes_dates_in_other_forms() {
+        let fixed = |value, datatype| date_fix(value, datatype).map(|fix| fix.replacement);
+
+        assert_eq!(
+            fixed("2025-01-15", DataType::Date).as_deref(),
+            Some("20250115")
+        );
+        assert_eq!(fixed("202501", DataType::Date).as_deref(), Some("20250101"));
+        assert_eq!(
+            fixed("20250115103000", DataType::Date).as_deref(),
+            Some("20250115")
+        );
+        assert_eq!(
+            fixed("2025-01-15T10:30:00", DataType::DateTime).as_deref(),
+            Some("20250115103000")
+        );
+        assert_eq!(fixed("20251301", DataType::Date), None);
+        assert_eq!(fixed("yesterday", DataType::Date), None);
+    }
+
+    #[test]
+    fn trims_trailing_delimiters() {
+        let message = hl7_parser::parse_message_with_lenient_newlines(
+            "MSH|^~\\&|APP\rPID|1||123^^^|||\rPV1|1",
+        )
+        .unwrap();
+        let mut issues = Vec::new();
+        check_trailing_delimiters(&message, &mut issues);
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].path, "PID");
+        assert_eq!(issues[0].actual_value.as_deref(), Some("^^^|||"));
+
+        let bare = hl7_parser::parse_message_with_lenient_newlines("MSH|^~\\&").unwrap();
+        let mut issues = Vec::new();
+        check_trailing_delimiters(&bare, &mut issues);
+        assert!(issues.is_empty(), "MSH.2 is never trimmed");
+    }
+
+    #[test]
+    fn applies_every_fix() {
+        let schema = SchemaCache::new().unwrap();
+        let message =
+            "MSH|^~\\&|APP|FAC|||20250101||ADT^A01|1|P|2.5.1\rPID|1||123||Doe^Jane||1980-01-01|f||";
+        let fixes = validate_message(message, &schema, ValidationMode::Full)
+            .issues
+            .into_iter()
+            .filter_map(|issue| Some((issue.range?.0, issue.range?.1, issue.fix?)))
+            .collect();
+
+        let result = apply_fixes(message, fixes).unwrap();
+        assert_eq!(
+            result.message,
+            "MSH|^~\\&|APP|FAC|||20250101||ADT^A01|1|P|2.5.1\rPID|1||123||Doe^Jane||19800101|F"
+        );
+        assert_eq!(result.ranges.len(), 3);
+        assert_eq!(result.skipped, 0);
+    }
+
+    #[test]
+    fn rejects_ranges_outside_the_message() {
+        let fix = QuickFix {
+            title: "Change".to_string(),
+            replacement: "x".to_string(),
+        };
+        assert!(apply_fixes("PID|1", vec![(3, 10, fix)]).is_err());
+    }
+}
@@ -0,0 +1,236 @@
+//! Light re-validation of only the segments that changed.
+//!
+//! When an extension patches the message, the editor wants the issue
+//! highlights to follow at once rather than after the next debounced
+//! validation. A patch usually touches a field or two, so instead of checking
+//! the whole message again, the segments that differ from the previously
+//! validated version are re-checked and the issues of every other segment are
+//! carried over, moved to where their segment now starts.
+//!
+//! # Falling Back
+//!
+//! The whole message is validated as usual when carrying issues over can't be
+//! trusted:
+//!
+//! * Either version fails to parse, or has unparsed content after the last
+//!   segment
+//! * Segments were added, removed, or reordered, so they can't be paired up
+//! * MSH changed, as the message type, trigger event, and version it declares
+//!   decide which rules apply to every segment
+//!
+//! An unchanged segment is also re-checked if one of its conditional rules
+//! reads a field from a segment that changed.
+
+use std::collections::HashSet;
+use tauri::State;
+
+use super::conditional::check_segment_conditions;
+use super::validate::{
+    check_segment_required_fields, flag_duplicate_control_id, get_message_type, validate_message,
+    ValidationIssue, ValidationMode, ValidationResult,
+};
+use crate::commands::strip_document_metadata;
+use crate::schema::cache::SchemaCache;
+use crate::schema::version::detect_version;
+use crate::AppData;
+
+/// Light-validate a message, re-checking only the segments that changed.
+///
+/// # Arguments
+/// * `previous_message` - The message as it was last light-validated
+/// * `previous` - The light validation result for `previous_message`
+/// * `message` - The message to validate
+/// * `state` - Application state containing the schema cache
+///
+/// # Returns
+/// The same issues light validation of `message` would find, ordered by where
+/// they are in the message
+#[tauri::command]
+pub fn revalidate_light(
+    previous_message: &str,
+    previous: ValidationResult,
+    message: &str,
+    state: State<'_, AppData>,
+) -> ValidationResult {
+    let previous = previous.ranges_to_bytes(previous_message);
+    let result = revalidate_changed_segments(previous_message, &previous, message, &state.schema);
+    flag_duplicate_control_id(result, message, &state.control_ids).ranges_to_utf16(message)
+}
+
+/// Re-check the changed segments without going through Tauri state.
+pub fn revalidate_changed_segments(
+    previous_message: &str,
+    previous: &ValidationResult,
+    message: &str,
+    schema: &SchemaCache,
+) -> ValidationResult {
+    let full = || validate_message(message, schema, ValidationMode::Light);
+
+    let (previous_body, previous_header) = strip_document_metadata(previous_message);
+    let (body, header) = strip_document_metadata(message);
+    let (Ok(previous_msg), Ok(msg)) = (
+        hl7_parser::parse_message_with_lenient_newlines(previous_body),
+        hl7_parser::parse_message_with_lenient_newlines(body),
+    ) else {
+        return full();
+    };
+    if previous_msg.raw_value().len() != previous_body.len() || msg.raw_value().len() != body.len()
+    {
+        return full();
+    }
+
+    let previous_segments: Vec<_> = previous_msg.segments().collect();
+    let segments: Vec<_> = msg.segments().collect();
+    let same_shape = previous_segments.len() == segments.len()
+        && previous_segments
+            .iter()
+            .zip(&segments)
+            .all(|(previous, segment)| previous.name == segment.name);
+    if !same_shape {
+        return full();
+    }
+
+    let changed: Vec<bool> = previous_segments
+        .iter()
+        .zip(&segments)
+        .map(|(previous, segment)| previous.raw_value() != segment.raw_value())
+        .collect();
+    let changed_names: HashSet<&str> = segments
+        .iter()
+        .zip(&changed)
+        .filter(|(_, changed)| **changed)
+        .map(|(segment, _)| segment.name)
+        .collect();
+    if changed_names.contains("MSH") {
+        return full();
+    }
+
+    let version = schema.resolve_version(detect_version(body));
+    let (_msg_type, trigger_event) = get_message_type(&msg);
+    let mut issues = Vec::new();
+
+    for ((previous_segment, segment), changed) in
+        previous_segments.iter().zip(&segments).zip(changed)
+    {
+        if changed || reads_changed_segment(segment.name, &changed_names, schema, &version) {
+            check_segment_required_fields(
+                &msg,
+                segment,
+                schema,
+                &version,
+                &trigger_event,
+                &mut issues,
+            );
+            check_segment_conditions(&msg, segment, schema, &version, &trigger_event, &mut issues);
+            continue;
+        }
+
+        // carry the segment's issues over, moved to where it now starts
+        let was = &previous_segment.range;
+        issues.extend(previous.issues.iter().filter_map(|issue| {
+            let (start, end) = issue.range?;
+            let start = start.checked_sub(previous_header)?;
+            let end = end.checked_sub(previous_header)?;
+            (was.start <= start && end <= was.end).then(|| ValidationIssue {
+                range: Some((
+                    start - was.start + segment.range.start,
+                    end - was.start + segment.range.start,
+                )),
+                ..issue.clone()
+            })
+        }));
+    }
+
+    for issue in &mut issues {
+        if let Some((start, end)) = issue.range.as_mut() {
+            *start += header;
+            *end += header;
+        }
+    }
+    issues.sort_by_key(|issue| issue.range);
+    ValidationResult::new(issues)
+}
+
+/// Whether a segment's conditional rules read a field from a changed segment.
+fn reads_changed_segment(
+    name: &str,
+    changed_names: &HashSet<&str>,
+    schema: &SchemaCache,
+    version: &str,
+) -> bool {
+    if changed_names.is_empty() {
+        return false;
+    }
+    schema
+        .get_segment_for_version(name, version)
+        .unwrap_or_default()
+        .iter()
+        .flat_map(|field| [field.required_if.as_ref(), field.forbidden_unless.as_ref()])
+        .flatten()
+        .filter_map(|condition| condition.path.split('.').next())
+        .any(|segment| segment != name && changed_names.contains(segment))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str = "MSH|^~\\&|LAB|FAC|||20250101||ORU^R01|1|P|2.5.1\rPID|1||123||DOE^JOHN\rOBX|1|NM|GLU||5.5|||N\rOBX|2|NM|NA||140|mmol/L||N";
+
+    fn issues(result: &ValidationResult) -> Vec<(String, (usize, usize))> {
+        let mut issues: Vec<_> = result
+            .issues
+            .iter()
+            .map(|issue| (issue.message.clone(), issue.range.unwrap()))
+            .collect();
+        issues.sort();
+        issues
+    }
+
+    #[test]
+    fn matches_full_light_validation() {
+        let cache = SchemaCache::new().unwrap();
+        let previous = validate_message(MESSAGE, &cache, ValidationMode::Light);
+
+        // a patch filling in units lengthens the first OBX, moving the second
+        let patched = MESSAGE.replace("|5.5|||N", "|5.5|mmol/L^Millimoles per litre||N");
+        let revalidated = revalidate_changed_segments(MESSAGE, &previous, &patched, &cache);
+        let expected = validate_message(&patched, &cache, ValidationMode::Light);
+
+        assert!(revalidated.issues.len() < previous.issues.len());
+        assert_eq!(issues(&revalidated), issues(&expected));
+        assert_eq!(revalidated.summary.errors, expected.summary.errors);
+    }
+
+    #[test]
+    fn carries_issues_over_past_a_metadata_header() {
+        let cache = SchemaCache::new().unwrap();
+        let previous = validate_message(MESSAGE, &cache, ValidationMode::Light);
+
+        let patched = format!(
+            "# title: Glucose\n{}",
+            MESSAGE.replace("DOE^JOHN", "DOE^JANE")
+        );
+        let revalidated = revalidate_changed_segments(MESSAGE, &previous, &patched, &cache);
+        let expected = validate_message(&patched, &cache, ValidationMode::Light);
+
+        assert_eq!(issues(&revalidated), issues(&expected));
+    }
+
+    #[test]
+    fn falls_back_when_segments_change_shape() {
+        let cache = SchemaCache::new().unwrap();
+        let previous = validate_message(MESSAGE, &cache, ValidationMode::Light);
+
+        // removing a segment, and changing MSH, both mean validating everything
+        for patched in [
+            MESSAGE.replace("\rOBX|2|NM|NA||140|mmol/L||N", ""),
+            MESSAGE.replace("ORU^R01", "ADT^A01"),
+        ] {
+            let revalidated = revalidate_changed_segments(MESSAGE, &previous, &patched, &cache);
+            let expected = validate_message(&patched, &cache, ValidationMode::Light);
+            assert_eq!(issues(&revalidated), issues(&expected));
+        }
+    }
+}
@@ -6,19 +6,63 @@
 //! # Modules
 //!
 //! - [`validate`] - Schema-based validation with light/full modes
+//! - [`incremental`] - Light re-validation of only the segments that changed
+//! - [`debounce`] - Debounced light validation in the background, as the message is edited
+//! - [`conditional`] - Conditionally required and forbidden fields
+//! - [`structure`] - Segment order and cardinality against the message structure
+//! - [`document`] - Consistency rules for MDM document messages
 //! - [`diff`] - Semantic comparison at segment/field/component level
+//! - [`dir_compare`] - Bulk comparison of two folders of messages, file by file
+//! - [`expectation`] - Matching against a "golden" message with placeholders for varying values
+//! - [`diff_export`] - Export comparisons as side-by-side HTML or Markdown reports
+//! - [`phi`] - PHI exposure report for a privacy check before sharing
+//! - [`profile`] - Custom validation profiles with site-specific rules
+//! - [`providers`] - Issues from extensions registered as validation providers
+//! - [`report`] - Export validation results as Markdown, HTML, or CSV reports
+//! - [`fixes`] - Machine-applicable fixes for issues, applied one at a time or all at once
 //!
 //! # Validation Modes
 //!
-//! - **Light** - Fast, runs on every edit (500ms debounce). Checks parse errors
-//!   and required fields only.
+//! - **Light** - Fast, runs on every edit (500ms debounce, scheduled in the
+//!   background by [`debounce`]). Checks parse errors
+//!   and required fields only (including conditional requirements). Edits made
+//!   by extensions are re-checked at once, segment by segment.
 //! - **Full** - Comprehensive, triggered on-demand. Adds length limits, patterns,
-//!   allowed values, date formats, and message structure.
+//!   allowed values, date formats, message structure (segment presence, order, and
+//!   cardinality), and document consistency. Runs on a blocking thread, reporting
+//!   progress segment by segment, and can be cancelled.
 //!
-//! Issues include character ranges for inline highlighting via syntax_highlight.
+//! A validation profile adds site-specific rules from a file on top of either
+//! mode, for interface specs the built-in schema can't express. Extensions can
+//! add their own checks to full validation as validation providers.
+//!
+//! Issues include character ranges for inline highlighting via syntax_highlight,
+//! and a fix where there's an obvious one.
 
+mod conditional;
+mod debounce;
 mod diff;
+mod diff_export;
+mod dir_compare;
+mod document;
+mod expectation;
+mod fixes;
+mod incremental;
+mod phi;
+mod profile;
+mod providers;
+mod report;
+mod structure;
 mod validate;
 
+pub use debounce::*;
 pub use diff::*;
+pub use diff_export::*;
+pub use dir_compare::*;
+pub use expectation::*;
+pub use fixes::*;
+pub use incremental::*;
+pub use phi::*;
+pub use profile::*;
+pub use report::*;
 pub use validate::*;
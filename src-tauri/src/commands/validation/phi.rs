@@ -0,0 +1,317 @@
+//! PHI exposure report for a quick privacy check before sharing a message.
+//!
+//! Walks every populated field in a message and looks up its classification in
+//! the schema for the message's version:
+//!
+//! * **PHI** fields are listed with their values, so it's clear what the file
+//!   would disclose.
+//! * **Not PHI** fields are skipped.
+//! * **Unclassified** fields (no schema entry, or an entry with no
+//!   `classification`) are listed as coverage warnings. The report can only be as
+//!   good as the schema, so a gap is flagged rather than assumed safe.
+//!
+//! # Granularity
+//!
+//! A classified field-level entry (e.g. PID.3) covers the whole field. Otherwise
+//! each populated component is checked against its own entry (e.g. PID.5.1), and
+//! each field repeat is reported separately (`PID.3[2]`). Later occurrences of a
+//! segment carry the occurrence in the path (`NK1[2].2.1`). Fields with a single
+//! component are reported by their field path alone.
+//!
+//! MSH.1 and MSH.2 are skipped, as they only hold the delimiters.
+
+use hl7_parser::message::{Field as MessageField, Segment};
+use hl7_parser::Message;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tauri::State;
+
+use crate::commands::Utf16Offsets;
+use crate::schema::cache::SchemaCache;
+use crate::schema::segment::{Classification, Field};
+use crate::schema::version::detect_version;
+use crate::AppData;
+
+/// A populated field or component classified as PHI.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhiElement {
+    /// Field path (e.g., "PID.5.1", "PID.3[2]", "NK1[2].2.1")
+    pub path: String,
+    /// Schema name of the field
+    pub name: String,
+    /// Decoded value found in the message
+    pub value: String,
+    /// Character range in the message for highlighting, in UTF-16 code units
+    pub range: (usize, usize),
+}
+
+/// A populated field or component with no classification in the schema.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnclassifiedField {
+    /// Field path (e.g., "PID.20", "ZPI.1")
+    pub path: String,
+    /// Schema name of the field, if the schema describes it at all
+    pub name: Option<String>,
+    /// Character range in the message for highlighting, in UTF-16 code units
+    pub range: (usize, usize),
+}
+
+/// Everything a message would disclose, as far as the schema can tell.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhiExposureReport {
+    /// Populated PHI fields, in message order
+    pub phi: Vec<PhiElement>,
+    /// Populated fields with no classification, in message order
+    pub unclassified: Vec<UnclassifiedField>,
+}
+
+/// Report which PHI elements a message contains.
+///
+/// # Arguments
+/// * `message` - Raw HL7 message text
+/// * `state` - Application state containing the schema cache
+///
+/// # Returns
+/// * `Ok(PhiExposureReport)` - Populated PHI fields and unclassified fields
+/// * `Err(String)` - The message couldn't be parsed
+#[tauri::command]
+pub fn report_phi_exposure(
+    message: &str,
+    state: State<'_, AppData>,
+) -> Result<PhiExposureReport, String> {
+    phi_exposure(message, &state.schema)
+}
+
+/// Build the PHI exposure report without going through Tauri state.
+fn phi_exposure(message: &str, schema: &SchemaCache) -> Result<PhiExposureReport, String> {
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(message)
+        .map_err(|e| format!("Failed to parse message: {e}"))?;
+    let version = schema.resolve_version(detect_version(message));
+
+    let mut report = PhiExposureReport {
+        phi: Vec::new(),
+        unclassified: Vec::new(),
+    };
+    let mut segment_schemas: HashMap<&str, Vec<Field>> = HashMap::new();
+    let mut occurrences: HashMap<&str, usize> = HashMap::new();
+
+    for segment in parsed.segments() {
+        let occurrence = occurrences.entry(segment.name).or_insert(0);
+        *occurrence += 1;
+        let prefix = if *occurrence > 1 {
+            format!("{}[{occurrence}]", segment.name)
+        } else {
+            segment.name.to_string()
+        };
+
+        // segments without a schema (e.g. Z-segments) leave every field unclassified
+        let segment_schema = segment_schemas.entry(segment.name).or_insert_with(|| {
+            schema
+                .get_segment_for_version(segment.name, &version)
+                .unwrap_or_default()
+        });
+        scan_segment(segment, &prefix, segment_schema, &parsed, &mut report);
+    }
+
+    // ranges go to the editor, which counts UTF-16 code units
+    let offsets = Utf16Offsets::new(message);
+    let ranges = report
+        .phi
+        .iter_mut()
+        .map(|element| &mut element.range)
+        .chain(report.unclassified.iter_mut().map(|field| &mut field.range));
+    for (start, end) in ranges {
+        *start = offsets.to_utf16(*start);
+        *end = offsets.to_utf16(*end);
+    }
+
+    Ok(report)
+}
+
+/// Classify the populated fields of one segment occurrence.
+fn scan_segment(
+    segment: &Segment,
+    prefix: &str,
+    schema: &[Field],
+    message: &Message,
+    report: &mut PhiExposureReport,
+) {
+    for (field_idx, field) in segment.fields.iter().enumerate() {
+        let field_num = field_idx + 1;
+        if segment.name == "MSH" && field_num <= 2 {
+            continue;
+        }
+        if field.raw_value().is_empty() {
+            continue;
+        }
+
+        let field_entry = find_entry(schema, field_num, None);
+        if let Some(classification) = field_entry.and_then(|entry| entry.classification) {
+            // a classified field-level entry covers every component
+            let name = field_entry
+                .map(|entry| entry.name.clone())
+                .unwrap_or_default();
+            report_value(
+                classification,
+                format!("{prefix}.{field_num}"),
+                name,
+                message.separators.decode(field.raw_value()).to_string(),
+                (field.range.start, field.range.end),
+                report,
+            );
+            continue;
+        }
+
+        scan_components(
+            field,
+            field_num,
+            prefix,
+            schema,
+            field_entry,
+            message,
+            report,
+        );
+    }
+}
+
+/// Classify the populated components of a field, repeat by repeat.
+fn scan_components(
+    field: &MessageField,
+    field_num: usize,
+    prefix: &str,
+    schema: &[Field],
+    field_entry: Option<&Field>,
+    message: &Message,
+    report: &mut PhiExposureReport,
+) {
+    for (repeat_idx, repeat) in field.repeats.iter().enumerate() {
+        let field_path = if repeat_idx > 0 {
+            format!("{prefix}.{field_num}[{}]", repeat_idx + 1)
+        } else {
+            format!("{prefix}.{field_num}")
+        };
+
+        // a repeat without separators is a single component
+        let components: Vec<(usize, &str, (usize, usize))> = if repeat.components.is_empty() {
+            vec![(
+                1,
+                repeat.raw_value(),
+                (repeat.range.start, repeat.range.end),
+            )]
+        } else {
+            repeat
+                .components
+                .iter()
+                .enumerate()
+                .map(|(idx, comp)| {
+                    (
+                        idx + 1,
+                        comp.raw_value(),
+                        (comp.range.start, comp.range.end),
+                    )
+                })
+                .collect()
+        };
+
+        // only name the component when there is more than one
+        let single_component = components.len() == 1;
+        for (component_num, raw_value, range) in components {
+            if raw_value.is_empty() {
+                continue;
+            }
+            let entry = find_entry(schema, field_num, Some(component_num));
+            let path = if single_component {
+                field_path.clone()
+            } else {
+                format!("{field_path}.{component_num}")
+            };
+            let name = entry.or(field_entry).map(|entry| entry.name.clone());
+
+            match entry.and_then(|entry| entry.classification) {
+                Some(classification) => report_value(
+                    classification,
+                    path,
+                    name.unwrap_or_default(),
+                    message.separators.decode(raw_value).to_string(),
+                    range,
+                    report,
+                ),
+                None => report
+                    .unclassified
+                    .push(UnclassifiedField { path, name, range }),
+            }
+        }
+    }
+}
+
+/// Record a classified value, keeping only PHI.
+fn report_value(
+    classification: Classification,
+    path: String,
+    name: String,
+    value: String,
+    range: (usize, usize),
+    report: &mut PhiExposureReport,
+) {
+    match classification {
+        Classification::Phi => report.phi.push(PhiElement {
+            path,
+            name,
+            value,
+            range,
+        }),
+        Classification::NotPhi => {}
+    }
+}
+
+/// Find the schema entry for a field or component.
+fn find_entry(schema: &[Field], field: usize, component: Option<usize>) -> Option<&Field> {
+    schema.iter().find(|entry| {
+        usize::from(entry.field) == field && entry.component.map(usize::from) == component
+    })
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str = "MSH|^~\\&|SEND|FAC|RCV|FAC|20250101120000||ADT^A01|12345|P|2.5.1\r\
+        PID|1||12345^^^MRN~67890^^^SSN||Doe^John|||M\r\
+        ZPI|custom";
+
+    fn report() -> PhiExposureReport {
+        let cache = SchemaCache::new().expect("can create cache");
+        phi_exposure(MESSAGE, &cache).unwrap()
+    }
+
+    #[test]
+    fn lists_populated_phi_fields() {
+        let report = report();
+        let paths: Vec<&str> = report.phi.iter().map(|e| e.path.as_str()).collect();
+        assert!(paths.contains(&"PID.3"), "{paths:?}");
+        assert!(paths.contains(&"PID.5.1"), "{paths:?}");
+        assert!(paths.contains(&"PID.5.2"), "{paths:?}");
+
+        let family_name = report.phi.iter().find(|e| e.path == "PID.5.1").unwrap();
+        assert_eq!(family_name.value, "Doe");
+        assert_eq!(&MESSAGE[family_name.range.0..family_name.range.1], "Doe");
+
+        // gender and message header fields are classified as not PHI
+        assert!(!paths.iter().any(|p| p.starts_with("MSH") || *p == "PID.8"));
+    }
+
+    #[test]
+    fn flags_unclassified_populated_fields() {
+        let report = report();
+        let paths: Vec<&str> = report
+            .unclassified
+            .iter()
+            .map(|f| f.path.as_str())
+            .collect();
+        // a Z-segment has no schema, and PID.1 isn't in the base schema
+        assert!(paths.contains(&"ZPI.1"), "{paths:?}");
+        assert!(paths.contains(&"PID.1"), "{paths:?}");
+        assert!(!paths.iter().any(|p| p.starts_with("PID.5")), "{paths:?}");
+    }
+}
@@ -0,0 +1,624 @@
+//! Custom validation profiles with site-specific rules.
+//!
+//! The built-in schema describes the HL7 standard, but every interface spec
+//! tightens it differently: one vendor needs PID.18 on every message, another
+//! only accepts a handful of patient classes, a third wants PV1.2 to be "I" on
+//! admits. A validation profile captures those rules in a file, so messages can
+//! be checked against the spec for the interface they're bound for.
+//!
+//! # Profile Files
+//!
+//! Profiles are TOML or JSON files in the `validation-profiles` folder of the
+//! data root. They're read each time they're listed or used, so edits take
+//! effect without restarting. A profile is identified by its file name without
+//! the extension.
+//!
+//! ```toml
+//! name = "Acme ADT"
+//! description = "Acme's ADT interface spec, v3"
+//! base = "full"                    # built-in checks to run first (default: "full")
+//!
+//! [[rules]]
+//! path = "PID.18"
+//! required = true
+//!
+//! [[rules]]
+//! path = "PV1.2"
+//! allowed_values = ["I", "O", "E"]
+//!
+//! [[rules]]
+//! path = "PV1.2"
+//! allowed_values = ["I"]
+//! when = { path = "MSH.9.2", equals = "A01" }
+//! message = "Admits must be inpatient (PV1.2 = I)"
+//!
+//! [[rules]]
+//! path = "PID.3.1"
+//! pattern = "[0-9]{8}"
+//! severity = "warning"
+//!
+//! [[rules]]
+//! path = "OBR.4.1"
+//! mapping_table = "acme-orders"
+//! ```
+//!
+//! # Rules
+//!
+//! A rule applies to every occurrence of its segment, and can combine:
+//! * `required` - the field must have a value
+//! * `allowed_values` - a populated value must be one of these
+//! * `pattern` - a populated value must match this regular expression in full
+//! * `mapping_table` - a populated value must be a source code in this mapping
+//!   table (see [`crate::commands::MappingTable`]), so it's known to translate
+//!
+//! A `when` condition limits the rule to messages where another field (first
+//! occurrence) `equals` a value or is `one_of` several; with neither, the field
+//! just has to be populated. Issues are errors unless the rule says otherwise,
+//! and a rule's `message` replaces the generated description.
+
+use hl7_parser::Message;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use tauri::State;
+
+use super::{
+    flag_duplicate_control_id, validate_message, Severity, ValidationIssue, ValidationMode,
+    ValidationResult, ValidationRule,
+};
+use crate::commands::{
+    list_definitions, load_definition, load_mapping_table, Definition, MAPPING_TABLES_DIR,
+};
+use crate::schema::cache::SchemaCache;
+use crate::AppData;
+
+/// Folder under the data root that holds validation profiles.
+pub const PROFILES_DIR: &str = "validation-profiles";
+
+/// A parsed validation profile.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ValidationProfile {
+    /// Human-readable name (defaults to the file name)
+    pub name: Option<String>,
+    /// What the profile is for, shown alongside its name
+    pub description: Option<String>,
+    /// Built-in validation to run before the profile's rules
+    #[serde(default = "default_base")]
+    pub base: ValidationMode,
+    /// Site-specific rules
+    #[serde(default)]
+    pub rules: Vec<ProfileRule>,
+}
+
+/// A single site-specific rule for a field or component.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileRule {
+    /// Field path without a segment occurrence (e.g., "PV1.2", "PID.3.1")
+    pub path: String,
+    /// Whether the field must have a value
+    #[serde(default)]
+    pub required: bool,
+    /// Values a populated field may take
+    pub allowed_values: Option<Vec<String>>,
+    /// Regular expression a populated field must match in full
+    pub pattern: Option<String>,
+    /// Mapping table a populated field must have an entry in
+    pub mapping_table: Option<String>,
+    /// Condition limiting which messages the rule applies to
+    pub when: Option<ProfileCondition>,
+    /// Severity of issues raised by the rule
+    #[serde(default = "default_severity")]
+    pub severity: Severity,
+    /// Description to report instead of the generated one
+    pub message: Option<String>,
+    /// `pattern`, anchored and compiled when the profile is parsed
+    #[serde(skip)]
+    regex: Option<Regex>,
+    /// Source codes of `mapping_table`, once the tables are loaded
+    #[serde(skip)]
+    table_codes: Option<HashSet<String>>,
+}
+
+/// Condition on another field that a rule depends on.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ProfileCondition {
+    /// Field path to test (e.g., "MSH.9.2")
+    pub path: String,
+    /// Value the field must equal
+    pub equals: Option<String>,
+    /// Values the field must be one of
+    pub one_of: Option<Vec<String>>,
+}
+
+/// A profile found in the profiles folder.
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationProfileInfo {
+    /// Identifier used to select the profile (file name without extension)
+    pub id: String,
+    /// Name from the profile, or the identifier if it has none
+    pub name: String,
+    /// Description from the profile, if any
+    pub description: Option<String>,
+    /// Why the profile couldn't be loaded, if it couldn't
+    pub error: Option<String>,
+}
+
+/// The profiles folder and the profiles in it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationProfiles {
+    /// Absolute path of the profiles folder
+    pub directory: String,
+    /// Profiles found in the folder, sorted by identifier
+    pub profiles: Vec<ValidationProfileInfo>,
+}
+
+fn default_base() -> ValidationMode {
+    ValidationMode::Full
+}
+
+fn default_severity() -> Severity {
+    Severity::Error
+}
+
+impl ValidationProfile {
+    /// Parse a profile from TOML or JSON content.
+    ///
+    /// # Arguments
+    /// * `content` - File content
+    /// * `json` - Whether the content is JSON rather than TOML
+    ///
+    /// # Returns
+    /// * `Ok(ValidationProfile)` - The profile, with its patterns compiled
+    /// * `Err(String)` - The content isn't a valid profile
+    pub fn parse(content: &str, json: bool) -> Result<Self, String> {
+        let mut profile: Self = if json {
+            serde_json::from_str(content).map_err(|e| format!("Failed to parse profile: {e}"))?
+        } else {
+            toml::from_str(content).map_err(|e| format!("Failed to parse profile: {e}"))?
+        };
+
+        for rule in &mut profile.rules {
+            if split_path(&rule.path).is_none() {
+                return Err(format!("Invalid field path in profile: {}", rule.path));
+            }
+            if let Some(pattern) = &rule.pattern {
+                // anchor the pattern to match the entire value, as the schema does
+                let regex = Regex::new(&format!("^({pattern})$"))
+                    .map_err(|e| format!("Invalid pattern for {}: {e}", rule.path))?;
+                rule.regex = Some(regex);
+            }
+        }
+
+        Ok(profile)
+    }
+
+    /// Load the mapping tables the profile's rules name.
+    ///
+    /// # Arguments
+    /// * `directory` - The mapping tables folder
+    ///
+    /// # Returns
+    /// * `Ok(())` - Every table was loaded
+    /// * `Err(String)` - A table doesn't exist or couldn't be loaded
+    pub fn load_tables(&mut self, directory: &Path) -> Result<(), String> {
+        for rule in &mut self.rules {
+            if let Some(table) = &rule.mapping_table {
+                let table = load_mapping_table(directory, table)
+                    .map_err(|e| format!("Failed to load rule for {}: {e}", rule.path))?;
+                rule.table_codes = Some(table.entries.into_iter().map(|e| e.source).collect());
+            }
+        }
+        Ok(())
+    }
+
+    /// Validate a message against the built-in checks and then the profile's rules.
+    ///
+    /// # Arguments
+    /// * `message` - Raw HL7 message text
+    /// * `name` - Profile name to mention in generated descriptions
+    /// * `schema` - Schema cache for the built-in checks
+    #[must_use]
+    pub fn validate(&self, message: &str, name: &str, schema: &SchemaCache) -> ValidationResult {
+        let mut issues = validate_message(message, schema, self.base).issues;
+
+        // parse errors are already reported by the built-in checks
+        if let Ok(msg) = hl7_parser::parse_message_with_lenient_newlines(message) {
+            for rule in &self.rules {
+                let applies = rule.when.as_ref().is_none_or(|when| when.holds(&msg));
+                if applies {
+                    check_rule(&msg, rule, name, &mut issues);
+                }
+            }
+        }
+
+        ValidationResult::new(issues)
+    }
+}
+
+impl Definition for ValidationProfile {
+    const KIND: &'static str = "validation profile";
+
+    fn parse_definition(content: &str, json: bool) -> Result<Self, String> {
+        ValidationProfile::parse(content, json)
+    }
+}
+
+impl ProfileCondition {
+    /// Whether the message satisfies the condition.
+    fn holds(&self, msg: &Message) -> bool {
+        let value = decoded_value(msg, &self.path);
+        if self.equals.is_none() && self.one_of.is_none() {
+            return !value.is_empty();
+        }
+        self.equals.as_ref().is_none_or(|equals| *equals == value)
+            && self
+                .one_of
+                .as_ref()
+                .is_none_or(|values| values.contains(&value))
+    }
+}
+
+/// List the profiles in the profiles folder, creating the folder if needed.
+///
+/// Profiles that fail to load are still listed, with the reason, so a typo in a
+/// profile doesn't make it silently disappear.
+///
+/// # Arguments
+/// * `state` - Application state containing the data root
+///
+/// # Returns
+/// * `Ok(ValidationProfiles)` - The folder and the profiles in it
+/// * `Err(String)` - The folder couldn't be created or read
+#[tauri::command]
+pub async fn list_validation_profiles(
+    state: State<'_, AppData>,
+) -> Result<ValidationProfiles, String> {
+    let directory = state.data_root.lock().await.path.join(PROFILES_DIR);
+    std::fs::create_dir_all(&directory)
+        .map_err(|e| format!("Failed to create profiles folder: {e}"))?;
+    let profiles = list_profiles(&directory)?;
+    Ok(ValidationProfiles {
+        directory: directory.display().to_string(),
+        profiles,
+    })
+}
+
+/// Validate a message against a validation profile.
+///
+/// # Arguments
+/// * `message` - Raw HL7 message text
+/// * `profile` - Identifier of the profile (its file name without extension)
+/// * `state` - Application state containing the data root and schema cache
+///
+/// # Returns
+/// * `Ok(ValidationResult)` - Issues from the built-in checks and the profile
+/// * `Err(String)` - The profile doesn't exist or couldn't be loaded
+#[tauri::command]
+pub async fn validate_with_profile(
+    message: String,
+    profile: String,
+    state: State<'_, AppData>,
+) -> Result<ValidationResult, String> {
+    let root = state.data_root.lock().await.path.clone();
+    let (name, loaded) = load_profile(&root, &profile)?;
+    let result = loaded.validate(&message, &name, &state.schema);
+    Ok(flag_duplicate_control_id(result, &message, &state.control_ids).ranges_to_utf16(&message))
+}
+
+/// Load a profile and the mapping tables it uses from a data root.
+///
+/// # Arguments
+/// * `root` - The data root
+/// * `id` - Identifier of the profile (its file name without extension)
+///
+/// # Returns
+/// * `Ok((String, ValidationProfile))` - The profile's name and the profile
+/// * `Err(String)` - The profile or one of its tables doesn't exist or couldn't be loaded
+pub fn load_profile(root: &Path, id: &str) -> Result<(String, ValidationProfile), String> {
+    let (path, mut profile) = load_definition::<ValidationProfile>(&root.join(PROFILES_DIR), id)?;
+    profile.load_tables(&root.join(MAPPING_TABLES_DIR))?;
+    log::debug!("loaded validation profile {}", path.display());
+    let name = profile.name.clone().unwrap_or_else(|| id.to_string());
+    Ok((name, profile))
+}
+
+/// Read the profiles in a folder, sorted by identifier.
+fn list_profiles(directory: &Path) -> Result<Vec<ValidationProfileInfo>, String> {
+    Ok(list_definitions::<ValidationProfile>(directory)?
+        .into_iter()
+        .map(|(id, profile)| match profile {
+            Ok(profile) => ValidationProfileInfo {
+                name: profile.name.unwrap_or_else(|| id.clone()),
+                description: profile.description,
+                error: None,
+                id,
+            },
+            Err(error) => ValidationProfileInfo {
+                name: id.clone(),
+                description: None,
+                error: Some(error),
+                id,
+            },
+        })
+        .collect())
+}
+
+/// Split a rule path into its segment name and the rest of the path.
+fn split_path(path: &str) -> Option<(&str, &str)> {
+    let (segment, rest) = path.split_once('.')?;
+    let valid_segment = segment.len() == 3
+        && segment
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+    let valid_rest = !rest.is_empty()
+        && rest
+            .split('.')
+            .all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+    (valid_segment && valid_rest).then_some((segment, rest))
+}
+
+/// Decoded value at a path, or an empty string if it isn't present.
+fn decoded_value(msg: &Message, path: &str) -> String {
+    msg.query(path)
+        .map(|value| msg.separators.decode(value.raw_value()).to_string())
+        .unwrap_or_default()
+}
+
+/// Check one rule against every occurrence of its segment.
+fn check_rule(msg: &Message, rule: &ProfileRule, profile: &str, issues: &mut Vec<ValidationIssue>) {
+    let Some((segment_name, rest)) = split_path(&rule.path) else {
+        return;
+    };
+    let issue = |path: String, range, rule_kind, message: String, actual_value| ValidationIssue {
+        path,
+        range,
+        severity: rule.severity,
+        message: rule.message.clone().unwrap_or(message),
+        rule: rule_kind,
+        actual_value,
+        fix: None,
+    };
+
+    let segments: Vec<_> = msg.segments().filter(|s| s.name == segment_name).collect();
+    if segments.is_empty() {
+        if rule.required {
+            issues.push(issue(
+                rule.path.clone(),
+                None,
+                ValidationRule::RequiredField,
+                format!(
+                    "{} is required by the {profile} profile, but there is no {segment_name} segment",
+                    rule.path
+                ),
+                None,
+            ));
+        }
+        return;
+    }
+
+    for (idx, segment) in segments.iter().enumerate() {
+        let occurrence = idx + 1;
+        let path = if occurrence > 1 {
+            format!("{segment_name}[{occurrence}].{rest}")
+        } else {
+            rule.path.clone()
+        };
+        let result = msg.query(&format!("{segment_name}[{occurrence}].{rest}"));
+        let value = result
+            .as_ref()
+            .map(|value| msg.separators.decode(value.raw_value()).to_string())
+            .unwrap_or_default();
+        // highlight the segment when the field isn't there at all
+        let range = result
+            .as_ref()
+            .map(|value| (value.range().start, value.range().end))
+            .or(Some((segment.range.start, segment.range.end)));
+
+        if value.is_empty() {
+            if rule.required {
+                issues.push(issue(
+                    path,
+                    range,
+                    ValidationRule::RequiredField,
+                    format!("{path} is required by the {profile} profile"),
+                    None,
+                ));
+            }
+            continue;
+        }
+
+        if let Some(allowed) = &rule.allowed_values {
+            if !allowed.contains(&value) {
+                issues.push(issue(
+                    path.clone(),
+                    range,
+                    ValidationRule::AllowedValues,
+                    format!(
+                        "{path} must be one of {} for the {profile} profile",
+                        allowed.join(", ")
+                    ),
+                    Some(value.clone()),
+                ));
+            }
+        }
+
+        if let Some(regex) = &rule.regex {
+            if !regex.is_match(&value) {
+                issues.push(issue(
+                    path.clone(),
+                    range,
+                    ValidationRule::Pattern,
+                    format!("{path} does not match the format required by the {profile} profile"),
+                    Some(value.clone()),
+                ));
+            }
+        }
+
+        if let (Some(table), Some(codes)) = (&rule.mapping_table, &rule.table_codes) {
+            if !codes.contains(&value) {
+                issues.push(issue(
+                    path.clone(),
+                    range,
+                    ValidationRule::AllowedValues,
+                    format!(
+                        "{path} has no entry in the {table} mapping table used by the {profile} profile"
+                    ),
+                    Some(value.clone()),
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use crate::commands::temp_dir;
+
+    const PROFILE: &str = r#"
+name = "Acme ADT"
+base = "light"
+
+[[rules]]
+path = "PID.18"
+required = true
+
+[[rules]]
+path = "PV1.2"
+allowed_values = ["I"]
+when = { path = "MSH.9.2", equals = "A01" }
+
+[[rules]]
+path = "NK1.2.1"
+pattern = "[A-Z][a-z]+"
+severity = "warning"
+"#;
+
+    fn check(profile: &str, message: &str) -> Vec<ValidationIssue> {
+        let cache = SchemaCache::new().expect("can create cache");
+        let profile = ValidationProfile::parse(profile, false).unwrap();
+        profile.validate(message, "Acme ADT", &cache).issues
+    }
+
+    fn profile_issues(issues: &[ValidationIssue]) -> Vec<&ValidationIssue> {
+        issues
+            .iter()
+            .filter(|issue| issue.message.contains("Acme ADT"))
+            .collect()
+    }
+
+    #[test]
+    fn applies_rules_to_every_occurrence() {
+        let message = "MSH|^~\\&|APP|FAC|||20250101||ADT^A08|1|P|2.5.1\r\
+            PID|1||123||Doe^John|||||||||||||ACCT1\r\
+            NK1|1|Doe^Jane\r\
+            NK1|2|DOE^Jim\r\
+            PV1|1|O";
+        let issues = check(PROFILE, message);
+        let issues = profile_issues(&issues);
+        assert_eq!(issues.len(), 1, "{issues:?}");
+        assert_eq!(issues[0].path, "NK1[2].2.1");
+        assert_eq!(issues[0].rule, ValidationRule::Pattern);
+        assert_eq!(issues[0].severity, Severity::Warning);
+        let (start, end) = issues[0].range.unwrap();
+        assert_eq!(&message[start..end], "DOE");
+    }
+
+    #[test]
+    fn conditional_rules_depend_on_other_fields() {
+        let message = "MSH|^~\\&|APP|FAC|||20250101||ADT^A01|1|P|2.5.1\r\
+            PID|1||123||Doe^John\r\
+            PV1|1|O";
+        let issues = check(PROFILE, message);
+        let paths: Vec<&str> = profile_issues(&issues)
+            .iter()
+            .map(|issue| issue.path.as_str())
+            .collect();
+        assert_eq!(paths, vec!["PID.18", "PV1.2"]);
+
+        // the patient class rule only applies to admits
+        let discharge = message.replace("ADT^A01", "ADT^A03");
+        let issues = check(PROFILE, &discharge);
+        let paths: Vec<&str> = profile_issues(&issues)
+            .iter()
+            .map(|issue| issue.path.as_str())
+            .collect();
+        assert_eq!(paths, vec!["PID.18"]);
+    }
+
+    #[test]
+    fn checks_values_against_mapping_tables() {
+        let dir = temp_dir("tables");
+        std::fs::write(dir.join("sex.csv"), "source,target\n1,M\n2,F\n").unwrap();
+
+        let mut profile = ValidationProfile::parse(
+            "[[rules]]\npath = \"PID.8\"\nmapping_table = \"sex\"",
+            false,
+        )
+        .unwrap();
+        profile.load_tables(&dir).unwrap();
+
+        let cache = SchemaCache::new().expect("can create cache");
+        let message = "MSH|^~\\&|APP|FAC|||20250101||ADT^A01|1|P|2.5.1\rPID|1||123||Doe^John|||1";
+        let issues = profile.validate(message, "Acme ADT", &cache).issues;
+        assert!(profile_issues(&issues).is_empty(), "{issues:?}");
+
+        let unmapped = message.replace("|||1", "|||X");
+        let issues = profile.validate(&unmapped, "Acme ADT", &cache).issues;
+        let issues = profile_issues(&issues);
+        assert_eq!(issues.len(), 1, "{issues:?}");
+        assert_eq!(issues[0].path, "PID.8");
+        assert_eq!(issues[0].actual_value.as_deref(), Some("X"));
+
+        let mut missing = ValidationProfile::parse(
+            "[[rules]]\npath = \"PID.8\"\nmapping_table = \"missing\"",
+            false,
+        )
+        .unwrap();
+        assert!(missing.load_tables(&dir).is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_invalid_profiles() {
+        assert!(ValidationProfile::parse("[[rules]]\npath = \"PID\"", false).is_err());
+        assert!(
+            ValidationProfile::parse("[[rules]]\npath = \"PID.3\"\npattern = \"(\"", false)
+                .is_err()
+        );
+        assert!(
+            ValidationProfile::parse("[[rules]]\npath = \"PID.3\"\nrequird = true", false).is_err()
+        );
+        assert!(ValidationProfile::parse(
+            r#"{"rules": [{"path": "PID.3", "required": true}]}"#,
+            true
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn loads_profiles_by_file_name() {
+        let dir = temp_dir("profiles");
+        std::fs::write(dir.join("acme.toml"), PROFILE).unwrap();
+        std::fs::write(dir.join("broken.json"), "{").unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a profile").unwrap();
+
+        let profiles = list_profiles(&dir).unwrap();
+        let ids: Vec<&str> = profiles.iter().map(|p| p.id.as_str()).collect();
+        assert_eq!(ids, vec!["acme", "broken"]);
+        assert_eq!(profiles[0].name, "Acme ADT");
+        assert!(profiles[1].error.is_some());
+
+        assert!(load_definition::<ValidationProfile>(&dir, "acme").is_ok());
+        assert!(load_definition::<ValidationProfile>(&dir, "missing").is_err());
+        assert!(load_definition::<ValidationProfile>(&dir, "../acme").is_err());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
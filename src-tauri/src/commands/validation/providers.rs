@@ -0,0 +1,198 @@
+//! Validation issues from extensions.
+//!
+//! Site conformance rules often live outside Hermes, in scripts written for an
+//! interface spec. Extensions that declare `validationProvider` in their
+//! capabilities are sent the message with a `validation/validate` request on
+//! every full validation, and the issues they return are merged with Hermes'
+//! own, so they're listed and highlighted alike.
+//!
+//! # Ranges
+//!
+//! A provider may give an issue's range in characters of the message it was
+//! sent. Without one, the range is found from the issue's HL7 path, falling
+//! back to no highlight if the path isn't in the message.
+//!
+//! # Failures
+//!
+//! Providers are asked at the same time, and one that fails or takes longer
+//! than `PROVIDER_TIMEOUT` is reported as an info issue rather than failing
+//! the validation.
+
+use std::time::Duration;
+
+use tokio::time::timeout;
+
+use super::{Severity, ValidationIssue, ValidationRule};
+use crate::commands::strip_document_metadata;
+use crate::extensions::types::{ProvidedIssue, ValidateParams, ValidateResult};
+use crate::AppData;
+
+/// How long a provider has to return its issues.
+const PROVIDER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Ask every validation provider for issues in a message.
+///
+/// # Returns
+/// The issues found, with byte ranges in `message`
+pub(super) async fn extension_issues(state: &AppData, message: &str) -> Vec<ValidationIssue> {
+    let providers = state
+        .extension_host
+        .lock()
+        .await
+        .validation_providers()
+        .await;
+    if providers.is_empty() {
+        return Vec::new();
+    }
+
+    let file_path = state
+        .documents
+        .lock()
+        .await
+        .active()
+        .and_then(|document| document.file_path.clone());
+    let params = match serde_json::to_value(ValidateParams {
+        message: message.to_string(),
+        file_path,
+    }) {
+        Ok(params) => params,
+        Err(e) => {
+            log::error!("failed to serialise validation/validate params: {e}");
+            return Vec::new();
+        }
+    };
+
+    let requests = providers.into_iter().map(|(name, sender)| {
+        let params = params.clone();
+        async move {
+            let result =
+                match timeout(PROVIDER_TIMEOUT, sender.send("validation/validate", params)).await {
+                    Ok(Ok(response)) => serde_json::from_value::<ValidateResult>(response.result)
+                        .map_err(|e| format!("returned an invalid result: {e}")),
+                    Ok(Err(e)) => Err(e.to_string()),
+                    Err(_) => Err(format!(
+                        "didn't answer within {} seconds",
+                        PROVIDER_TIMEOUT.as_secs()
+                    )),
+                };
+            (name, result)
+        }
+    });
+
+    let mut issues = Vec::new();
+    for (name, result) in futures::future::join_all(requests).await {
+        match result {
+            Ok(result) => issues.extend(
+                result
+                    .issues
+                    .into_iter()
+                    .map(|issue| provided_issue(&name, issue, message)),
+            ),
+            Err(e) => {
+                log::warn!("validation provider {name} failed: {e}");
+                issues.push(ValidationIssue {
+                    path: String::new(),
+                    range: None,
+                    severity: Severity::Info,
+                    message: format!("{name} couldn't check the message: {e}"),
+                    rule: ValidationRule::Extension,
+                    actual_value: None,
+                    fix: None,
+                });
+            }
+        }
+    }
+    issues
+}
+
+/// Convert an issue returned by a provider, finding its byte range.
+fn provided_issue(provider: &str, issue: ProvidedIssue, message: &str) -> ValidationIssue {
+    let range = issue
+        .range
+        .and_then(|range| {
+            let start = char_to_byte(message, range.start)?;
+            let end = char_to_byte(message, range.end)?;
+            (start <= end).then_some((start, end))
+        })
+        .or_else(|| path_range(message, &issue.path));
+    let source = match &issue.rule {
+        Some(rule) => format!("{provider}, {rule}"),
+        None => provider.to_string(),
+    };
+    ValidationIssue {
+        actual_value: range.and_then(|(start, end)| message.get(start..end).map(str::to_string)),
+        path: issue.path,
+        range,
+        severity: issue.severity,
+        message: format!("{} ({source})", issue.message),
+        rule: ValidationRule::Extension,
+        fix: None,
+    }
+}
+
+/// Byte offset of a character offset, allowing the offset just past the end.
+fn char_to_byte(message: &str, offset: usize) -> Option<usize> {
+    message
+        .char_indices()
+        .map(|(i, _)| i)
+        .chain(std::iter::once(message.len()))
+        .nth(offset)
+}
+
+/// Byte range of an HL7 path (e.g., "PID.3.1") in a message, if it's there.
+fn path_range(message: &str, path: &str) -> Option<(usize, usize)> {
+    let (body, header_length) = strip_document_metadata(message);
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(body).ok()?;
+    let range = parsed.query(path)?.range();
+    Some((range.start + header_length, range.end + header_length))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use crate::extensions::types::IssueRange;
+
+    const MESSAGE: &str =
+        "MSH|^~\\&|APP|FAC|||20250101||ADT^A01|1|P|2.5.1\rPID|1||12345||Müller^Jane";
+
+    fn issue(path: &str, range: Option<IssueRange>) -> ProvidedIssue {
+        ProvidedIssue {
+            path: path.to_string(),
+            severity: Severity::Warning,
+            message: "Looks wrong".to_string(),
+            rule: Some("site-1".to_string()),
+            range,
+        }
+    }
+
+    #[test]
+    fn finds_ranges_from_paths() {
+        let converted = provided_issue("Site Rules", issue("PID.3", None), MESSAGE);
+        assert_eq!(converted.rule, ValidationRule::Extension);
+        assert_eq!(converted.message, "Looks wrong (Site Rules, site-1)");
+        assert_eq!(converted.actual_value.as_deref(), Some("12345"));
+
+        let missing = provided_issue("Site Rules", issue("ZZZ.1", None), MESSAGE);
+        assert_eq!(missing.range, None);
+    }
+
+    #[test]
+    fn converts_character_ranges() {
+        // offsets count characters, and ü is two bytes
+        let start = MESSAGE.chars().count() - "Müller^Jane".chars().count();
+        let range = IssueRange {
+            start,
+            end: start + 6,
+        };
+        let converted = provided_issue("Site Rules", issue("PID.5.1", Some(range)), MESSAGE);
+        assert_eq!(converted.actual_value.as_deref(), Some("Müller"));
+
+        let past_end = IssueRange {
+            start: 1000,
+            end: 1001,
+        };
+        let converted = provided_issue("Site Rules", issue("PID.5.1", Some(past_end)), MESSAGE);
+        assert_eq!(converted.actual_value.as_deref(), Some("Müller"));
+    }
+}
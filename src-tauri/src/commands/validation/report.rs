@@ -0,0 +1,374 @@
+//! Validation report export.
+//!
+//! Turns a validation result into a document that can be attached to a vendor
+//! ticket as evidence, rather than a screenshot of the issues panel. Reports
+//! carry enough about the message to identify it later (type, control ID,
+//! version, sending and receiving applications), the summary counts, and every
+//! issue.
+//!
+//! # Formats
+//!
+//! * **Markdown** - For tickets and wikis that render it
+//! * **HTML** - A standalone page, for email or tickets that take attachments
+//! * **CSV** - One row per issue for spreadsheets. Each row carries the
+//!   message's control ID and type, so reports for several messages can be
+//!   combined; the summary is left to the spreadsheet.
+
+use serde::{Deserialize, Serialize};
+
+use super::validate::{Severity, ValidationIssue, ValidationResult, ValidationRule};
+use crate::commands::get_document_metadata;
+
+/// Output format for a validation report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    /// Markdown document with tables
+    Markdown,
+    /// Standalone HTML page
+    Html,
+    /// Comma-separated values, one row per issue
+    Csv,
+}
+
+/// Details identifying the message a report is about.
+struct MessageDetails {
+    /// Labelled details, in report order, leaving out any the message lacks
+    rows: Vec<(&'static str, String)>,
+    /// Message control ID (MSH.10)
+    control_id: String,
+    /// Message type (MSH.9)
+    message_type: String,
+}
+
+/// Export a validation result as a report.
+///
+/// # Arguments
+/// * `message` - The message that was validated, with or without a metadata header
+/// * `result` - Result of validating it
+/// * `format` - Report format
+/// * `file_name` - Name of the message's file, if it has been saved
+///
+/// # Returns
+/// The report's content, ready to be written to a file
+#[tauri::command]
+pub fn export_validation_report(
+    message: &str,
+    result: ValidationResult,
+    format: ReportFormat,
+    file_name: Option<String>,
+) -> String {
+    let generated = jiff::Zoned::now()
+        .strftime("%Y-%m-%d %H:%M:%S %z")
+        .to_string();
+    let mut details = message_details(message, file_name);
+    details.rows.push(("Report generated", generated));
+
+    match format {
+        ReportFormat::Markdown => markdown_report(&details, &result),
+        ReportFormat::Html => html_report(&details, &result),
+        ReportFormat::Csv => csv_report(&details, &result),
+    }
+}
+
+/// Gather the details identifying a message from its header and MSH segment.
+fn message_details(message: &str, file_name: Option<String>) -> MessageDetails {
+    let document = get_document_metadata(message);
+    let parsed = hl7_parser::parse_message_with_lenient_newlines(&document.message).ok();
+    let query = |path: &str| {
+        parsed
+            .as_ref()
+            .and_then(|parsed| parsed.query(path))
+            .map(|value| value.raw_value().to_string())
+            .unwrap_or_default()
+    };
+    // application and facility, as "APP / FAC"
+    let application = |application: &str, facility: &str| {
+        [query(application), query(facility)]
+            .into_iter()
+            .filter(|value| !value.is_empty())
+            .collect::<Vec<_>>()
+            .join(" / ")
+    };
+
+    let control_id = query("MSH.10");
+    let message_type = query("MSH.9");
+    let rows = [
+        ("File", file_name.unwrap_or_default()),
+        ("Title", document.metadata.title.unwrap_or_default()),
+        ("Message type", message_type.clone()),
+        ("Control ID", control_id.clone()),
+        ("HL7 version", query("MSH.12")),
+        ("Message time", query("MSH.7")),
+        ("Sending application", application("MSH.3", "MSH.4")),
+        ("Receiving application", application("MSH.5", "MSH.6")),
+    ]
+    .into_iter()
+    .filter(|(_, value)| !value.is_empty())
+    .collect();
+
+    MessageDetails {
+        rows,
+        control_id,
+        message_type,
+    }
+}
+
+/// Render a report as Markdown.
+fn markdown_report(details: &MessageDetails, result: &ValidationResult) -> String {
+    let mut report = String::from("# Validation Report\n\n## Message\n\n| | |\n|---|---|\n");
+    for (label, value) in &details.rows {
+        report.push_str(&format!("| {label} | {} |\n", markdown_cell(value)));
+    }
+
+    report.push_str("\n## Summary\n\n");
+    report.push_str(&format!("{}\n", summary_line(result)));
+
+    report.push_str("\n## Issues\n\n");
+    if result.issues.is_empty() {
+        report.push_str("No issues found.\n");
+        return report;
+    }
+    report.push_str("| Severity | Path | Rule | Message | Value |\n|---|---|---|---|---|\n");
+    for issue in &result.issues {
+        report.push_str(&format!(
+            "| {} | {} | {} | {} | {} |\n",
+            severity_label(issue.severity),
+            markdown_cell(issue_path(issue)),
+            rule_label(issue.rule),
+            markdown_cell(&issue.message),
+            markdown_cell(issue.actual_value.as_deref().unwrap_or_default())
+        ));
+    }
+    report
+}
+
+/// Render a report as a standalone HTML page.
+fn html_report(details: &MessageDetails, result: &ValidationResult) -> String {
+    let escape = |text: &str| quick_xml::escape::escape(text).into_owned();
+
+    let mut report = String::from(concat!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n",
+        "<title>Validation Report</title>\n<style>\n",
+        "body { font-family: sans-serif; margin: 2em; }\n",
+        "table { border-collapse: collapse; margin-bottom: 1.5em; }\n",
+        "th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; vertical-align: top; }\n",
+        "th { background: #f4f4f4; }\n",
+        "td.value { font-family: monospace; }\n",
+        ".error { color: #b00020; }\n.warning { color: #a15c00; }\n.info { color: #00579b; }\n",
+        "</style>\n</head>\n<body>\n<h1>Validation Report</h1>\n<h2>Message</h2>\n<table>\n",
+    ));
+    for (label, value) in &details.rows {
+        report.push_str(&format!(
+            "<tr><th>{label}</th><td>{}</td></tr>\n",
+            escape(value)
+        ));
+    }
+    report.push_str("</table>\n<h2>Summary</h2>\n");
+    report.push_str(&format!(
+        "<p>{}</p>\n<h2>Issues</h2>\n",
+        summary_line(result)
+    ));
+
+    if result.issues.is_empty() {
+        report.push_str("<p>No issues found.</p>\n");
+    } else {
+        report.push_str(
+            "<table>\n<tr><th>Severity</th><th>Path</th><th>Rule</th><th>Message</th><th>Value</th></tr>\n",
+        );
+        for issue in &result.issues {
+            let severity = severity_label(issue.severity);
+            report.push_str(&format!(
+                "<tr><td class=\"{}\">{severity}</td><td>{}</td><td>{}</td><td>{}</td><td class=\"value\">{}</td></tr>\n",
+                severity.to_lowercase(),
+                escape(issue_path(issue)),
+                rule_label(issue.rule),
+                escape(&issue.message),
+                escape(issue.actual_value.as_deref().unwrap_or_default())
+            ));
+        }
+        report.push_str("</table>\n");
+    }
+    report.push_str("</body>\n</html>\n");
+    report
+}
+
+/// Render a report as CSV, one row per issue.
+fn csv_report(details: &MessageDetails, result: &ValidationResult) -> String {
+    let mut report = String::from("control_id,message_type,severity,path,rule,message,value\r\n");
+    for issue in &result.issues {
+        let row = [
+            details.control_id.as_str(),
+            details.message_type.as_str(),
+            severity_label(issue.severity),
+            issue_path(issue),
+            rule_label(issue.rule),
+            issue.message.as_str(),
+            issue.actual_value.as_deref().unwrap_or_default(),
+        ];
+        let row: Vec<String> = row.into_iter().map(csv_field).collect();
+        report.push_str(&row.join(","));
+        report.push_str("\r\n");
+    }
+    report
+}
+
+/// Escape text for a Markdown table cell.
+///
+/// Cells can't hold pipes or newlines, and backslashes would escape the
+/// character after them.
+pub(super) fn markdown_cell(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace(['\r', '\n'], " ")
+}
+
+/// Quote a CSV field if it needs it, doubling any quotes inside.
+fn csv_field(value: &str) -> String {
+    match value.contains([',', '"', '\r', '\n']) {
+        true => format!("\"{}\"", value.replace('"', "\"\"")),
+        false => value.to_string(),
+    }
+}
+
+/// Summary counts as a sentence.
+fn summary_line(result: &ValidationResult) -> String {
+    let plural = |count: usize| if count == 1 { "" } else { "s" };
+    let summary = &result.summary;
+    format!(
+        "{} error{}, {} warning{}, {} info.",
+        summary.errors,
+        plural(summary.errors),
+        summary.warnings,
+        plural(summary.warnings),
+        summary.info
+    )
+}
+
+/// Path to show for an issue; message-level issues have none.
+fn issue_path(issue: &ValidationIssue) -> &str {
+    match issue.path.as_str() {
+        "" => "Message",
+        path => path,
+    }
+}
+
+/// Display name for a severity.
+fn severity_label(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Error => "Error",
+        Severity::Warning => "Warning",
+        Severity::Info => "Info",
+    }
+}
+
+/// Display name for a validation rule.
+fn rule_label(rule: ValidationRule) -> &'static str {
+    match rule {
+        ValidationRule::ParseError => "Parse error",
+        ValidationRule::RequiredField => "Required field",
+        ValidationRule::ForbiddenField => "Forbidden field",
+        ValidationRule::MinLength => "Minimum length",
+        ValidationRule::MaxLength => "Maximum length",
+        ValidationRule::Pattern => "Pattern",
+        ValidationRule::AllowedValues => "Allowed values",
+        ValidationRule::RequiredSegment => "Required segment",
+        ValidationRule::InvalidDate => "Invalid date",
+        ValidationRule::DocumentConsistency => "Document consistency",
+        ValidationRule::SegmentOrder => "Segment order",
+        ValidationRule::SegmentCardinality => "Segment cardinality",
+        ValidationRule::UnexpectedSegment => "Unexpected segment",
+        ValidationRule::DuplicateControlId => "Duplicate control ID",
+        ValidationRule::TrailingDelimiters => "Trailing delimiters",
+        ValidationRule::Extension => "Extension",
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    const MESSAGE: &str =
+        "# title: Admission\nMSH|^~\\&|APP|FAC|RECV||20250101||ADT^A01|MSG001|P|2.5.1\rPID|1";
+
+    fn result() -> ValidationResult {
+        ValidationResult::new(vec![
+            ValidationIssue {
+                path: "PID.3".to_string(),
+                range: None,
+                severity: Severity::Error,
+                message: "Patient ID is required".to_string(),
+                rule: ValidationRule::RequiredField,
+                actual_value: None,
+                fix: None,
+            },
+            ValidationIssue {
+                path: "PID.8".to_string(),
+                range: Some((10, 11)),
+                severity: Severity::Warning,
+                message: "Value \"X\" isn't in table 0001, <Administrative Sex>".to_string(),
+                rule: ValidationRule::AllowedValues,
+                actual_value: Some("X|Y".to_string()),
+                fix: None,
+            },
+        ])
+    }
+
+    #[test]
+    fn reports_identify_the_message() {
+        let details = message_details(MESSAGE, Some("admit.hl7".to_string()));
+        assert_eq!(details.control_id, "MSG001");
+        assert_eq!(details.message_type, "ADT^A01");
+        assert_eq!(
+            details.rows,
+            vec![
+                ("File", "admit.hl7".to_string()),
+                ("Title", "Admission".to_string()),
+                ("Message type", "ADT^A01".to_string()),
+                ("Control ID", "MSG001".to_string()),
+                ("HL7 version", "2.5.1".to_string()),
+                ("Message time", "20250101".to_string()),
+                ("Sending application", "APP / FAC".to_string()),
+                ("Receiving application", "RECV".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn renders_markdown_and_html() {
+        let details = message_details(MESSAGE, None);
+        let markdown = markdown_report(&details, &result());
+        assert!(markdown.contains("| Control ID | MSG001 |"));
+        assert!(markdown.contains("1 error, 1 warning, 0 info."));
+        assert!(markdown.contains("| Warning | PID.8 | Allowed values |"));
+        assert!(markdown.contains("| X\\|Y |"));
+
+        let html = html_report(&details, &result());
+        assert!(html.contains("<th>Control ID</th><td>MSG001</td>"));
+        assert!(html.contains("&lt;Administrative Sex&gt;"));
+        assert!(html.contains("<td class=\"error\">Error</td>"));
+
+        let empty = ValidationResult::new(Vec::new());
+        assert!(markdown_report(&details, &empty).contains("No issues found."));
+    }
+
+    #[test]
+    fn renders_csv() {
+        let details = message_details(MESSAGE, None);
+        let csv = csv_report(&details, &result());
+        let rows: Vec<&str> = csv.split("\r\n").collect();
+        assert_eq!(
+            rows[0],
+            "control_id,message_type,severity,path,rule,message,value"
+        );
+        assert_eq!(
+            rows[1],
+            "MSG001,ADT^A01,Error,PID.3,Required field,Patient ID is required,"
+        );
+        assert_eq!(
+            rows[2],
+            "MSG001,ADT^A01,Warning,PID.8,Allowed values,\"Value \"\"X\"\" isn't in table 0001, <Administrative Sex>\",X|Y"
+        );
+    }
+}
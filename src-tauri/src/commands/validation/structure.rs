@@ -0,0 +1,362 @@
+//! Structural rules for segment order and cardinality.
+//!
+//! Checking that required segments are present says nothing about where they
+//! are: an ORU with its OBX before the OBR, or an ADT with two PID segments,
+//! would otherwise validate clean. These rules walk the message's segments
+//! against the structure listed in messages.toml for its type and trigger event.
+//!
+//! # Rules
+//!
+//! * **Order** - segments must follow the order of the structure definition
+//! * **Cardinality** - segments that aren't `repeating` may only appear once,
+//!   and each new occurrence of a `group` must include the group's required
+//!   segments
+//! * **Unexpected segments** - segments not listed for the message type are
+//!   reported as warnings, as receivers will often ignore them
+//!
+//! Z-segments are skipped, since they're site-specific and may appear anywhere.
+//! Missing required segments are left to the required segment check.
+
+use hl7_parser::message::Segment;
+use std::collections::HashMap;
+
+use super::{Severity, ValidationIssue, ValidationRule};
+use crate::schema::message::SegmentMetadata;
+
+/// Check segment order, cardinality, and unexpected segments.
+///
+/// # Arguments
+/// * `msg` - Parsed message
+/// * `msg_type` - Message type from MSH.9.1
+/// * `trigger_event` - Trigger event from MSH.9.2
+/// * `structure` - Segment structure defined for the message type
+/// * `issues` - Collected validation issues
+pub(super) fn validate_segment_structure(
+    msg: &hl7_parser::Message,
+    msg_type: &str,
+    trigger_event: &str,
+    structure: &[SegmentMetadata],
+    issues: &mut Vec<ValidationIssue>,
+) {
+    let segments: Vec<&Segment> = msg
+        .segments()
+        .filter(|segment| !segment.name.starts_with('Z'))
+        .collect();
+    let message_name = format!("{msg_type}^{trigger_event}");
+    let mut occurrences: HashMap<&str, usize> = HashMap::new();
+    // the structure entry matched by the previous in-order segment
+    let mut cursor: Option<usize> = None;
+
+    for (idx, segment) in segments.iter().enumerate() {
+        let occurrence = occurrences.entry(segment.name).or_insert(0);
+        *occurrence += 1;
+        let path = if *occurrence > 1 {
+            format!("{}[{occurrence}]", segment.name)
+        } else {
+            segment.name.to_string()
+        };
+        let range = Some((segment.range.start, segment.range.end));
+        let seen = segments.get(..idx).unwrap_or_default();
+        let remaining = segments.get(idx + 1..).unwrap_or_default();
+
+        let start = cursor.unwrap_or(0);
+        let forward = structure
+            .iter()
+            .enumerate()
+            .skip(start)
+            .find(|(_, entry)| entry.name == segment.name)
+            .map(|(position, _)| position);
+
+        match (forward, cursor) {
+            // the same entry again, so it repeats or starts a new group
+            (Some(position), Some(current)) if position == current => {
+                let Some(entry) = structure.get(position) else {
+                    continue;
+                };
+                if entry.repeating == Some(true) {
+                    continue;
+                }
+                match entry.group.as_deref() {
+                    Some(group) => {
+                        check_group_restart(structure, position, group, path, range, issues);
+                    }
+                    None => issues.push(issue(
+                        path,
+                        range,
+                        Severity::Error,
+                        format!(
+                            "{} segment may only appear once in {message_name} messages",
+                            segment.name
+                        ),
+                        ValidationRule::SegmentCardinality,
+                    )),
+                }
+            }
+            // further along the structure, possibly skipping optional segments
+            (Some(position), _) => {
+                let skipped = structure
+                    .get(cursor.map_or(0, |current| current + 1)..position)
+                    .unwrap_or_default();
+                let out_of_order = skipped.iter().find(|entry| {
+                    entry.required == Some(true)
+                        && remaining.iter().any(|later| later.name == entry.name)
+                });
+                if let Some(missing) = out_of_order {
+                    issues.push(issue(
+                        path,
+                        range,
+                        Severity::Error,
+                        format!(
+                            "{} segment appears before {}, which must come first in {message_name} messages",
+                            segment.name, missing.name
+                        ),
+                        ValidationRule::SegmentOrder,
+                    ));
+                    continue;
+                }
+                check_group_completeness(skipped, seen, &path, range, issues);
+                cursor = Some(position);
+            }
+            // only earlier in the structure, or not listed at all
+            (None, _) => {
+                let earlier = structure
+                    .get(..start)
+                    .unwrap_or_default()
+                    .iter()
+                    .rposition(|entry| entry.name == segment.name);
+                let Some(position) = earlier else {
+                    issues.push(issue(
+                        path,
+                        range,
+                        Severity::Warning,
+                        format!(
+                            "{} segment is not part of the {message_name} message structure",
+                            segment.name
+                        ),
+                        ValidationRule::UnexpectedSegment,
+                    ));
+                    continue;
+                };
+
+                let current = cursor.and_then(|current| structure.get(current));
+                let restarts_group = structure
+                    .get(position)
+                    .and_then(|entry| entry.group.as_deref())
+                    .filter(|group| {
+                        current.is_some_and(|current| within(current.group.as_deref(), group))
+                    });
+                match restarts_group {
+                    Some(group) => {
+                        check_group_restart(structure, position, group, path, range, issues);
+                        cursor = Some(position);
+                    }
+                    None => issues.push(issue(
+                        path,
+                        range,
+                        Severity::Error,
+                        format!(
+                            "{} segment must come before {} in {message_name} messages",
+                            segment.name,
+                            current.map_or("the preceding segments", |entry| entry.name.as_str())
+                        ),
+                        ValidationRule::SegmentOrder,
+                    )),
+                }
+            }
+        }
+    }
+
+    // a final group occurrence may be cut short by the end of the message
+    if let (Some(current), Some(last)) = (cursor, segments.last()) {
+        let skipped = structure.get(current + 1..).unwrap_or_default();
+        let occurrence = occurrences.get(last.name).copied().unwrap_or(1);
+        let path = if occurrence > 1 {
+            format!("{}[{occurrence}]", last.name)
+        } else {
+            last.name.to_string()
+        };
+        let range = Some((last.range.start, last.range.end));
+        check_group_completeness(skipped, &segments, &path, range, issues);
+    }
+}
+
+/// Report required group segments skipped in a repeat of their group.
+///
+/// Only segments that appeared earlier in the message are reported: a segment
+/// that's missing altogether is already caught by the required segment check.
+fn check_group_completeness(
+    skipped: &[SegmentMetadata],
+    seen: &[&Segment],
+    path: &str,
+    range: Option<(usize, usize)>,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    for entry in skipped {
+        let Some(group) = entry.group.as_deref() else {
+            continue;
+        };
+        if entry.required == Some(true) && seen.iter().any(|s| s.name == entry.name) {
+            issues.push(issue(
+                path.to_string(),
+                range,
+                Severity::Error,
+                format!(
+                    "{} group is missing the required {} segment",
+                    group_name(group),
+                    entry.name
+                ),
+                ValidationRule::SegmentCardinality,
+            ));
+        }
+    }
+}
+
+/// Report a group that starts over without its required leading segments.
+///
+/// When a group starts over at `position`, any required segments of the group
+/// listed before `position` have been left out of the new occurrence.
+fn check_group_restart(
+    structure: &[SegmentMetadata],
+    position: usize,
+    group: &str,
+    path: String,
+    range: Option<(usize, usize)>,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    let Some(entry) = structure.get(position) else {
+        return;
+    };
+    let missing = structure
+        .get(..position)
+        .unwrap_or_default()
+        .iter()
+        .find(|earlier| earlier.required == Some(true) && within(earlier.group.as_deref(), group));
+    if let Some(missing) = missing {
+        issues.push(issue(
+            path,
+            range,
+            Severity::Error,
+            format!(
+                "{} segment starts a new {} group without the required {} segment",
+                entry.name,
+                group_name(group),
+                missing.name
+            ),
+            ValidationRule::SegmentCardinality,
+        ));
+    }
+}
+
+/// Whether a segment's group is `group` or nested inside it.
+fn within(segment_group: Option<&str>, group: &str) -> bool {
+    segment_group.is_some_and(|segment_group| {
+        segment_group == group
+            || segment_group
+                .strip_prefix(group)
+                .is_some_and(|rest| rest.starts_with('/'))
+    })
+}
+
+/// Display name of a (possibly nested) group, e.g. "observation".
+fn group_name(group: &str) -> &str {
+    group.rsplit('/').next().unwrap_or(group)
+}
+
+fn issue(
+    path: String,
+    range: Option<(usize, usize)>,
+    severity: Severity,
+    message: String,
+    rule: ValidationRule,
+) -> ValidationIssue {
+    ValidationIssue {
+        path,
+        range,
+        severity,
+        message,
+        rule,
+        actual_value: None,
+        fix: None,
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use crate::schema::cache::SchemaCache;
+
+    fn check(message: &str) -> Vec<ValidationIssue> {
+        let msg = hl7_parser::parse_message_with_lenient_newlines(message).unwrap();
+        let cache = SchemaCache::new().expect("can create cache");
+        let messages = cache.get_messages();
+        let (msg_type, trigger) = if message.contains("ORU^R01") {
+            ("ORU", "R01")
+        } else {
+            ("ADT", "A01")
+        };
+        let key = format!("{}_{}", msg_type.to_lowercase(), trigger.to_lowercase());
+        let mut issues = Vec::new();
+        validate_segment_structure(
+            &msg,
+            msg_type,
+            trigger,
+            &messages.message[&key],
+            &mut issues,
+        );
+        issues
+    }
+
+    const MSH_ORU: &str = "MSH|^~\\&|LAB|FAC|||20250101||ORU^R01|1|P|2.5.1";
+    const MSH_ADT: &str = "MSH|^~\\&|APP|FAC|||20250101||ADT^A01|1|P|2.5.1";
+
+    #[test]
+    fn well_formed_messages_have_no_issues() {
+        let oru = format!(
+            "{MSH_ORU}\rPID|1\rORC|RE\rOBR|1\rNTE|1\rOBX|1\rNTE|1\rOBX|2\rOBR|2\rOBX|1\rZLB|custom"
+        );
+        assert!(check(&oru).is_empty(), "{:?}", check(&oru));
+
+        let adt = format!("{MSH_ADT}\rEVN|A01\rPID|1\rNK1|1\rNK1|2\rPV1|1\rDG1|1\rDG1|2");
+        assert!(check(&adt).is_empty(), "{:?}", check(&adt));
+    }
+
+    #[test]
+    fn observation_before_request_is_out_of_order() {
+        let message = format!("{MSH_ORU}\rPID|1\rOBX|1\rOBR|1");
+        let issues = check(&message);
+        assert_eq!(issues.len(), 1, "{issues:?}");
+        assert_eq!(issues[0].rule, ValidationRule::SegmentOrder);
+        assert_eq!(issues[0].path, "OBX");
+        let (start, end) = issues[0].range.unwrap();
+        assert_eq!(&message[start..end], "OBX|1");
+    }
+
+    #[test]
+    fn non_repeating_segments_appear_once() {
+        let message = format!("{MSH_ADT}\rEVN|A01\rPID|1\rPID|2\rPV1|1");
+        let issues = check(&message);
+        assert_eq!(issues.len(), 1, "{issues:?}");
+        assert_eq!(issues[0].rule, ValidationRule::SegmentCardinality);
+        assert_eq!(issues[0].path, "PID[2]");
+    }
+
+    #[test]
+    fn repeated_groups_need_their_required_segments() {
+        let message = format!("{MSH_ORU}\rPID|1\rOBR|1\rOBX|1\rORC|RE\rOBX|1");
+        let issues = check(&message);
+        assert_eq!(issues.len(), 1, "{issues:?}");
+        assert_eq!(issues[0].rule, ValidationRule::SegmentCardinality);
+        assert_eq!(issues[0].path, "OBX[2]");
+    }
+
+    #[test]
+    fn unlisted_segments_are_warnings() {
+        let message = format!("{MSH_ADT}\rEVN|A01\rPID|1\rPV1|1\rIN1|1\rZPI|custom");
+        let issues = check(&message);
+        assert_eq!(issues.len(), 1, "{issues:?}");
+        assert_eq!(issues[0].rule, ValidationRule::UnexpectedSegment);
+        assert_eq!(issues[0].severity, Severity::Warning);
+        assert_eq!(issues[0].path, "IN1");
+    }
+}
@@ -1,19 +1,40 @@
 //! HL7 message validation commands.
 //!
 //! This module provides validation for HL7 messages against schema definitions,
-//! checking required fields, length limits, patterns, allowed values, and message structure.
+//! checking required fields, length limits, patterns, allowed values, and message structure
+//! (required segments, segment order, and cardinality).
 //!
 //! Two validation modes are provided:
 //! * **Light validation** - Fast checks for passive background validation (required fields, parse errors)
 //! * **Full validation** - Comprehensive checks for on-demand validation (all rules)
+//!
+//! Field rules come from the schema for the message's HL7 version, as declared in
+//! MSH.12 (or the version selected by the user, if any). Both modes also check
+//! conditional rules (`required_if`, `forbidden_unless`), as they decide whether
+//! a field is required at all.
 
 use hl7_parser::datetime::{parse_date, parse_timestamp};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use tauri::State;
+use std::ops::ControlFlow;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio_util::sync::CancellationToken;
 
+use super::conditional::{check_segment_conditions, validate_conditional_fields};
+use super::document::validate_document_consistency;
+use super::fixes::{allowed_value_fix, check_trailing_delimiters, date_fix, QuickFix};
+use super::providers::extension_issues;
+use super::structure::validate_segment_structure;
+use crate::commands::{
+    strip_document_metadata, ControlIdDirection, ControlIdHistory, Utf16Offsets,
+};
+use crate::events;
+use crate::schema::cache::SchemaCache;
 use crate::schema::segment::{DataType, Field};
+use crate::schema::table::TableKind;
+use crate::schema::version::detect_version;
 use crate::AppData;
 
 /// Severity level for validation issues.
@@ -36,6 +57,8 @@ pub enum ValidationRule {
     ParseError,
     /// Required field is missing or empty
     RequiredField,
+    /// Field is populated although its conditions forbid it
+    ForbiddenField,
     /// Value is shorter than minimum length
     MinLength,
     /// Value exceeds maximum length
@@ -48,6 +71,20 @@ pub enum ValidationRule {
     RequiredSegment,
     /// Date/datetime format is invalid
     InvalidDate,
+    /// Document (TXA/OBX) fields disagree with each other or the trigger event
+    DocumentConsistency,
+    /// Segment appears out of order for the message structure
+    SegmentOrder,
+    /// Segment or segment group repeats more than the message structure allows
+    SegmentCardinality,
+    /// Segment is not part of the message structure
+    UnexpectedSegment,
+    /// Control ID (MSH.10) was already used by a message sent or received
+    DuplicateControlId,
+    /// Segment ends with separators that only add empty fields or components
+    TrailingDelimiters,
+    /// Issue reported by an extension registered as a validation provider
+    Extension,
 }
 
 /// A single validation issue found in the message.
@@ -55,7 +92,8 @@ pub enum ValidationRule {
 pub struct ValidationIssue {
     /// HL7 path to the field (e.g., "PID.3", "MSH.9.1")
     pub path: String,
-    /// Character range in the message for highlighting (start, end)
+    /// Character range in the message for highlighting (start, end); byte
+    /// offsets from [`validate_message`], UTF-16 offsets from the commands
     pub range: Option<(usize, usize)>,
     /// Severity of the issue
     pub severity: Severity,
@@ -65,6 +103,9 @@ pub struct ValidationIssue {
     pub rule: ValidationRule,
     /// The actual value that caused the issue (if applicable)
     pub actual_value: Option<String>,
+    /// Machine-applicable fix replacing the text at `range`, if there is one
+    #[serde(default)]
+    pub fix: Option<QuickFix>,
 }
 
 /// Summary of validation results.
@@ -88,7 +129,7 @@ pub struct ValidationResult {
 }
 
 impl ValidationResult {
-    fn new(issues: Vec<ValidationIssue>) -> Self {
+    pub(super) fn new(issues: Vec<ValidationIssue>) -> Self {
         let summary = ValidationSummary {
             errors: issues
                 .iter()
@@ -105,66 +146,70 @@ impl ValidationResult {
         };
         Self { issues, summary }
     }
+
+    /// Convert the issues' byte ranges in `message` to the UTF-16 offsets the
+    /// editor uses.
+    pub(super) fn ranges_to_utf16(self, message: &str) -> Self {
+        let offsets = Utf16Offsets::new(message);
+        self.map_ranges(|offset| offsets.to_utf16(offset))
+    }
+
+    /// Convert the issues' ranges from the editor's UTF-16 offsets back to byte
+    /// ranges in `message`.
+    pub(super) fn ranges_to_bytes(self, message: &str) -> Self {
+        let offsets = Utf16Offsets::new(message);
+        self.map_ranges(|offset| offsets.to_byte(offset))
+    }
+
+    fn map_ranges(mut self, convert: impl Fn(usize) -> usize) -> Self {
+        for issue in &mut self.issues {
+            issue.range = issue
+                .range
+                .map(|(start, end)| (convert(start), convert(end)));
+        }
+        self
+    }
+}
+
+/// How far a full validation has got, emitted as `validation-progress` events.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ValidationProgress {
+    /// Segments checked so far
+    pub validated: usize,
+    /// Segments in the message
+    pub total: usize,
+}
+
+/// Shortest time between `validation-progress` events.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How thoroughly a message should be validated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ValidationMode {
+    /// Parse errors and required fields only
+    Light,
+    /// Every rule the schema knows about
+    Full,
 }
 
 /// Perform light validation (fast, for passive background checking).
 ///
 /// Checks:
 /// * Parse errors
-/// * Required fields
+/// * Required fields, including conditionally required and forbidden fields
 ///
 /// This is designed to run frequently without noticeable performance impact.
 #[tauri::command]
 pub fn validate_light(message: &str, state: State<AppData>) -> ValidationResult {
-    let mut issues = Vec::new();
-
-    // try to parse the message
-    let parsed = match hl7_parser::parse_message_with_lenient_newlines(message) {
-        Ok(msg) => {
-            // check for trailing unparsed content
-            if msg.raw_value().len() != message.len() {
-                issues.push(ValidationIssue {
-                    path: String::new(),
-                    range: Some((msg.raw_value().len(), message.len())),
-                    severity: Severity::Error,
-                    message: "Message contains unparsed content after last segment".to_string(),
-                    rule: ValidationRule::ParseError,
-                    actual_value: Some(message[msg.raw_value().len()..].to_string()),
-                });
-            }
-            Some(msg)
-        }
-        Err(hl7_parser::parser::ParseError::FailedToParse { position, .. }) => {
-            issues.push(ValidationIssue {
-                path: String::new(),
-                range: Some((position, message.len())),
-                severity: Severity::Error,
-                message: "Failed to parse message".to_string(),
-                rule: ValidationRule::ParseError,
-                actual_value: None,
-            });
-            None
-        }
-        Err(hl7_parser::parser::ParseError::IncompleteInput(position)) => {
-            let pos = position.unwrap_or(0);
-            issues.push(ValidationIssue {
-                path: String::new(),
-                range: Some((pos, message.len())),
-                severity: Severity::Error,
-                message: "Incomplete message input".to_string(),
-                rule: ValidationRule::ParseError,
-                actual_value: None,
-            });
-            None
-        }
-    };
-
-    // if parsing succeeded, check required fields
-    if let Some(ref msg) = parsed {
-        validate_required_fields(msg, &state, &mut issues);
-    }
+    light_validation(message, &state)
+}
 
-    ValidationResult::new(issues)
+/// Light-validate a message as [`validate_light`] does, without going through
+/// Tauri state.
+pub(super) fn light_validation(message: &str, state: &AppData) -> ValidationResult {
+    let result = validate_message(message, &state.schema, ValidationMode::Light);
+    flag_duplicate_control_id(result, message, &state.control_ids).ranges_to_utf16(message)
 }
 
 /// Perform full validation (comprehensive, for on-demand checking).
@@ -173,12 +218,202 @@ pub fn validate_light(message: &str, state: State<AppData>) -> ValidationResult
 /// * Length limits (minlength, maxlength)
 /// * Pattern matching
 /// * Allowed values
-/// * Message structure (required segments)
+/// * Message structure (required segments, segment order and cardinality)
 /// * Date/datetime format validation
+/// * Document consistency (TXA fields, OBX attachments)
+///
+/// Issues found by extensions registered as validation providers are merged in.
+///
+/// # Large Messages
+/// Validation runs on a blocking thread, so a batch of thousands of segments
+/// doesn't hold up other commands. Progress is emitted as `validation-progress`
+/// events as segments are checked (at most every 100ms), and the validation can
+/// be stopped with [`cancel_full_validation`]. Starting another full validation
+/// cancels the one running.
+///
+/// # Returns
+/// * `Ok(Some(ValidationResult))` - The result
+/// * `Ok(None)` - The validation was cancelled
+/// * `Err` - The validation thread failed
+#[tauri::command]
+pub async fn validate_full(
+    message: String,
+    app: AppHandle,
+    state: State<'_, AppData>,
+) -> Result<Option<ValidationResult>, String> {
+    let cancel = CancellationToken::new();
+    if let Some(running) = state.full_validation.lock().await.replace(cancel.clone()) {
+        running.cancel();
+    }
+
+    let validating = (app.clone(), cancel.clone(), message.clone());
+    let result = tokio::task::spawn_blocking(move || {
+        let (app, cancel, message) = validating;
+        let mut last_emitted: Option<Instant> = None;
+        validate_message_with_progress(
+            &message,
+            &app.state::<AppData>().schema,
+            ValidationMode::Full,
+            |validated, total| {
+                if cancel.is_cancelled() {
+                    return ControlFlow::Break(());
+                }
+                let due = last_emitted.is_none_or(|last| last.elapsed() >= PROGRESS_INTERVAL);
+                if due || validated == total {
+                    let progress = ValidationProgress { validated, total };
+                    if let Err(e) = app.emit(events::VALIDATION_PROGRESS, progress) {
+                        log::warn!("Failed to emit validation progress: {e}");
+                    }
+                    last_emitted = Some(Instant::now());
+                }
+                ControlFlow::Continue(())
+            },
+        )
+    })
+    .await
+    .map_err(|e| format!("Failed to validate message: {e}"))?;
+
+    let issues = match result {
+        Some(result) => {
+            tokio::select! {
+                issues = extension_issues(&state, &message) => Some((result.issues, issues)),
+                () = cancel.cancelled() => None,
+            }
+        }
+        None => None,
+    };
+
+    // a validation started since has already replaced the token
+    let mut running = state.full_validation.lock().await;
+    if !cancel.is_cancelled() {
+        *running = None;
+    }
+    drop(running);
+
+    let Some((mut issues, provided)) = issues else {
+        return Ok(None);
+    };
+    issues.extend(provided);
+    let result = ValidationResult::new(issues);
+    Ok(Some(
+        flag_duplicate_control_id(result, &message, &state.control_ids).ranges_to_utf16(&message),
+    ))
+}
+
+/// Stop the full validation running, if there is one.
+///
+/// The cancelled [`validate_full`] returns no result.
 #[tauri::command]
-pub fn validate_full(message: &str, state: State<AppData>) -> ValidationResult {
+pub async fn cancel_full_validation(state: State<'_, AppData>) -> Result<(), String> {
+    if let Some(running) = state.full_validation.lock().await.take() {
+        running.cancel();
+    }
+    Ok(())
+}
+
+/// Warn if a message's control ID was already used by a message sent or
+/// received, as receivers often drop such messages as resends.
+///
+/// Replaces any duplicate warning already in `result`, so a result can be
+/// checked again after the history changes. Ranges are byte offsets, as from
+/// [`validate_message`].
+pub fn flag_duplicate_control_id(
+    mut result: ValidationResult,
+    message: &str,
+    history: &ControlIdHistory,
+) -> ValidationResult {
+    result
+        .issues
+        .retain(|issue| issue.rule != ValidationRule::DuplicateControlId);
+
+    let (body, header_length) = strip_document_metadata(message);
+    let Ok(parsed) = hl7_parser::parse_message_with_lenient_newlines(body) else {
+        return ValidationResult::new(result.issues);
+    };
+    let Some(field) = parsed.query("MSH.10") else {
+        return ValidationResult::new(result.issues);
+    };
+    let control_id = field.raw_value();
+    let uses = history.uses_of(control_id);
+    let Some(last) = uses.last() else {
+        return ValidationResult::new(result.issues);
+    };
+
+    let when = last
+        .timestamp
+        .to_zoned(jiff::tz::TimeZone::system())
+        .strftime("%Y-%m-%d %H:%M:%S");
+    let peer = match last.environment {
+        Some(environment) => format!("{} [{environment}]", last.peer),
+        None => last.peer.clone(),
+    };
+    let what = match last.direction {
+        ControlIdDirection::Sent => format!("sent to {peer} at {when}"),
+        ControlIdDirection::Received => format!("received from {peer} at {when}"),
+    };
+    let others = match uses.len() {
+        1 => String::new(),
+        count => format!(" (used {count} times in all)"),
+    };
+    let range = field.range();
+    result.issues.push(ValidationIssue {
+        path: "MSH.10".to_string(),
+        range: Some((range.start + header_length, range.end + header_length)),
+        severity: Severity::Warning,
+        message: format!(
+            "Control ID {control_id} was already {what}{others}; receivers may drop this message as a resend"
+        ),
+        rule: ValidationRule::DuplicateControlId,
+        actual_value: Some(control_id.to_string()),
+        fix: None,
+    });
+    ValidationResult::new(result.issues)
+}
+
+/// Validate a message against the schema without going through Tauri state.
+///
+/// This is the shared implementation behind [`validate_light`] and
+/// [`validate_full`], exposed so other subsystems (e.g. the job runner) can
+/// validate messages that aren't in the editor.
+///
+/// # Arguments
+/// * `message` - Raw HL7 message text, optionally with a `#` metadata header,
+///   which is skipped; issue ranges are relative to the text as given
+/// * `schema` - Schema cache to validate against
+/// * `mode` - Whether to run light or full validation
+pub fn validate_message(
+    message: &str,
+    schema: &SchemaCache,
+    mode: ValidationMode,
+) -> ValidationResult {
+    validate_message_with_progress(message, schema, mode, |_, _| ControlFlow::Continue(()))
+        .unwrap_or_else(|| ValidationResult::new(Vec::new()))
+}
+
+/// Validate a message as [`validate_message`] does, reporting progress through
+/// full validation's field checks segment by segment.
+///
+/// # Arguments
+/// * `message` - Raw HL7 message text, optionally with a `#` metadata header
+/// * `schema` - Schema cache to validate against
+/// * `mode` - Whether to run light or full validation
+/// * `on_segment` - Called with the number of segments checked so far and the
+///   number in all after each segment; breaking stops validation
+///
+/// # Returns
+/// The result, or None if `on_segment` stopped validation
+pub(super) fn validate_message_with_progress(
+    message: &str,
+    schema: &SchemaCache,
+    mode: ValidationMode,
+    mut on_segment: impl FnMut(usize, usize) -> ControlFlow<()>,
+) -> Option<ValidationResult> {
     let mut issues = Vec::new();
 
+    // comment header lines aren't part of the message; ranges are shifted back
+    // onto the full text once validation is done
+    let (message, header_length) = strip_document_metadata(message);
+
     // try to parse the message
     let parsed = match hl7_parser::parse_message_with_lenient_newlines(message) {
         Ok(msg) => {
@@ -191,6 +426,7 @@ pub fn validate_full(message: &str, state: State<AppData>) -> ValidationResult {
                     message: "Message contains unparsed content after last segment".to_string(),
                     rule: ValidationRule::ParseError,
                     actual_value: Some(message[msg.raw_value().len()..].to_string()),
+                    fix: None,
                 });
             }
             Some(msg)
@@ -203,6 +439,7 @@ pub fn validate_full(message: &str, state: State<AppData>) -> ValidationResult {
                 message: "Failed to parse message".to_string(),
                 rule: ValidationRule::ParseError,
                 actual_value: None,
+                fix: None,
             });
             None
         }
@@ -215,25 +452,83 @@ pub fn validate_full(message: &str, state: State<AppData>) -> ValidationResult {
                 message: "Incomplete message input".to_string(),
                 rule: ValidationRule::ParseError,
                 actual_value: None,
+                fix: None,
             });
             None
         }
     };
 
     if let Some(ref msg) = parsed {
-        // validate message structure (required segments)
-        validate_message_structure(msg, &state, &mut issues);
+        // validate against the schema for the message's declared version (MSH.12)
+        let version = schema.resolve_version(detect_version(message));
+
+        match mode {
+            ValidationMode::Light => {
+                validate_required_fields(msg, schema, &version, &mut issues);
+                validate_conditional_fields(msg, schema, &version, &mut issues);
+            }
+            ValidationMode::Full => {
+                // validate message structure (required segments, order, cardinality)
+                validate_message_structure(msg, schema, &mut issues);
+
+                // validate all fields against schema, a segment at a time; issues
+                // are kept grouped by check, as when each check ran over the message
+                let (msg_type, trigger_event) = get_message_type(msg);
+                let total = msg.segments().count();
+                let mut conditional = Vec::new();
+                let mut constraints = Vec::new();
+                for (i, segment) in msg.segments().enumerate() {
+                    let trigger = trigger_event.as_str();
+                    check_segment_required_fields(
+                        msg,
+                        segment,
+                        schema,
+                        &version,
+                        trigger,
+                        &mut issues,
+                    );
+                    check_segment_conditions(
+                        msg,
+                        segment,
+                        schema,
+                        &version,
+                        trigger,
+                        &mut conditional,
+                    );
+                    check_segment_constraints(
+                        msg,
+                        segment,
+                        schema,
+                        &version,
+                        trigger,
+                        &mut constraints,
+                    );
+                    if on_segment(i + 1, total).is_break() {
+                        return None;
+                    }
+                }
+                issues.extend(conditional);
+                issues.extend(constraints);
+
+                // validate rules spanning several fields (e.g. MDM documents)
+                validate_document_consistency(msg, &msg_type, &trigger_event, &mut issues);
 
-        // validate all fields against schema
-        validate_required_fields(msg, &state, &mut issues);
-        validate_field_constraints(msg, &state, &mut issues);
+                check_trailing_delimiters(msg, &mut issues);
+            }
+        }
     }
 
-    ValidationResult::new(issues)
+    for issue in &mut issues {
+        if let Some((start, end)) = issue.range.as_mut() {
+            *start += header_length;
+            *end += header_length;
+        }
+    }
+    Some(ValidationResult::new(issues))
 }
 
 /// Extract message type and trigger event from MSH.9.
-fn get_message_type(msg: &hl7_parser::Message) -> (String, String) {
+pub(super) fn get_message_type(msg: &hl7_parser::Message) -> (String, String) {
     let msh = match msg.segments().find(|s| s.name == "MSH") {
         Some(s) => s,
         None => return (String::new(), String::new()),
@@ -258,7 +553,7 @@ fn get_message_type(msg: &hl7_parser::Message) -> (String, String) {
 }
 
 /// Check if a field's trigger filter matches the current message.
-fn matches_trigger_filter(field_def: &Field, trigger_event: &str) -> bool {
+pub(super) fn matches_trigger_filter(field_def: &Field, trigger_event: &str) -> bool {
     match &field_def.trigger_filter {
         Some(filter) => filter.eq_ignore_ascii_case(trigger_event),
         None => true, // no filter means applies to all messages
@@ -268,183 +563,217 @@ fn matches_trigger_filter(field_def: &Field, trigger_event: &str) -> bool {
 /// Check that required fields have values.
 fn validate_required_fields(
     msg: &hl7_parser::Message,
-    state: &State<AppData>,
+    schema: &SchemaCache,
+    version: &str,
     issues: &mut Vec<ValidationIssue>,
 ) {
     let (_msg_type, trigger_event) = get_message_type(msg);
 
     for segment in msg.segments() {
-        let schema = match state.schema.get_segment(segment.name) {
-            Ok(s) => s,
-            Err(_) => continue, // no schema for this segment
-        };
+        check_segment_required_fields(msg, segment, schema, version, &trigger_event, issues);
+    }
+}
 
-        let required_fields: HashMap<(u8, Option<u8>), &Field> = schema
-            .iter()
-            .filter(|f| f.required == Some(true))
-            .filter(|f| matches_trigger_filter(f, &trigger_event))
-            .map(|f| ((f.field, f.component), f))
-            .collect();
-
-        for ((field_num, component_num), field_def) in required_fields {
-            let value = get_field_value(segment, field_num, component_num, msg);
-            let is_empty = value.as_ref().map(|(v, _)| v.is_empty()).unwrap_or(true);
-
-            if is_empty {
-                let path = match component_num {
-                    Some(c) => format!("{}.{}.{}", segment.name, field_num, c),
-                    None => format!("{}.{}", segment.name, field_num),
-                };
-
-                // find range for highlighting (use segment range if field not present)
-                let range = value
-                    .as_ref()
-                    .and_then(|(_, r)| *r)
-                    .or(Some((segment.range.start, segment.range.end)));
+/// Check that required fields of one segment occurrence have values.
+pub(super) fn check_segment_required_fields(
+    msg: &hl7_parser::Message,
+    segment: &hl7_parser::message::Segment,
+    schema: &SchemaCache,
+    version: &str,
+    trigger_event: &str,
+    issues: &mut Vec<ValidationIssue>,
+) {
+    let segment_schema = match schema.get_segment_for_version(segment.name, version) {
+        Ok(s) => s,
+        Err(_) => return, // no schema for this segment
+    };
 
-                issues.push(ValidationIssue {
-                    path: path.clone(),
-                    range,
-                    severity: Severity::Error,
-                    message: format!("{} ({}) is required", path, field_def.name),
-                    rule: ValidationRule::RequiredField,
-                    actual_value: None,
-                });
-            }
+    let required_fields: HashMap<(u8, Option<u8>), &Field> = segment_schema
+        .iter()
+        .filter(|f| f.required == Some(true))
+        .filter(|f| matches_trigger_filter(f, trigger_event))
+        .map(|f| ((f.field, f.component), f))
+        .collect();
+
+    for ((field_num, component_num), field_def) in required_fields {
+        let value = get_field_value(segment, field_num, component_num, msg);
+        let is_empty = value.as_ref().map(|(v, _)| v.is_empty()).unwrap_or(true);
+
+        if is_empty {
+            let path = match component_num {
+                Some(c) => format!("{}.{}.{}", segment.name, field_num, c),
+                None => format!("{}.{}", segment.name, field_num),
+            };
+
+            // find range for highlighting (use segment range if field not present)
+            let range = value
+                .as_ref()
+                .and_then(|(_, r)| *r)
+                .or(Some((segment.range.start, segment.range.end)));
+
+            issues.push(ValidationIssue {
+                path: path.clone(),
+                range,
+                severity: Severity::Error,
+                message: format!("{} ({}) is required", path, field_def.name),
+                rule: ValidationRule::RequiredField,
+                actual_value: None,
+                fix: None,
+            });
         }
     }
 }
 
-/// Validate field constraints (length, pattern, allowed values, datatypes).
-fn validate_field_constraints(
+/// Validate the field constraints (length, pattern, allowed values, datatypes)
+/// of one segment occurrence.
+fn check_segment_constraints(
     msg: &hl7_parser::Message,
-    state: &State<AppData>,
+    segment: &hl7_parser::message::Segment,
+    schema: &SchemaCache,
+    version: &str,
+    trigger_event: &str,
     issues: &mut Vec<ValidationIssue>,
 ) {
-    let (_msg_type, trigger_event) = get_message_type(msg);
+    let segment_schema = match schema.get_segment_for_version(segment.name, version) {
+        Ok(s) => s,
+        Err(_) => return, // no schema for this segment
+    };
 
-    for segment in msg.segments() {
-        let schema = match state.schema.get_segment(segment.name) {
-            Ok(s) => s,
-            Err(_) => continue,
-        };
+    for field_def in segment_schema
+        .iter()
+        .filter(|f| matches_trigger_filter(f, trigger_event))
+    {
+        let value = get_field_value(segment, field_def.field, field_def.component, msg);
 
-        for field_def in schema
-            .iter()
-            .filter(|f| matches_trigger_filter(f, &trigger_event))
-        {
-            let value = get_field_value(segment, field_def.field, field_def.component, msg);
+        if let Some((value, range)) = value {
+            // skip empty values and template placeholders
+            if value.is_empty() || value.starts_with('{') && value.ends_with('}') {
+                continue;
+            }
+
+            let path = match field_def.component {
+                Some(c) => format!("{}.{}.{}", segment.name, field_def.field, c),
+                None => format!("{}.{}", segment.name, field_def.field),
+            };
 
-            if let Some((value, range)) = value {
-                // skip empty values and template placeholders
-                if value.is_empty() || value.starts_with('{') && value.ends_with('}') {
-                    continue;
+            // lengths are in characters, as the editor's field counter shows them
+            let length = value.chars().count();
+
+            // check minlength
+            if let Some(minlen) = field_def.minlength {
+                if length < minlen as usize {
+                    issues.push(ValidationIssue {
+                        path: path.clone(),
+                        range,
+                        severity: Severity::Warning,
+                        message: format!(
+                            "{} ({}) is too short: {} chars, minimum is {}",
+                            path, field_def.name, length, minlen
+                        ),
+                        rule: ValidationRule::MinLength,
+                        actual_value: Some(value.clone()),
+                        fix: None,
+                    });
                 }
+            }
 
-                let path = match field_def.component {
-                    Some(c) => format!("{}.{}.{}", segment.name, field_def.field, c),
-                    None => format!("{}.{}", segment.name, field_def.field),
-                };
+            // check maxlength
+            if let Some(maxlen) = field_def.maxlength {
+                if length > maxlen as usize {
+                    issues.push(ValidationIssue {
+                        path: path.clone(),
+                        range,
+                        severity: Severity::Warning,
+                        message: format!(
+                            "{} ({}) is too long: {} chars, maximum is {}",
+                            path, field_def.name, length, maxlen
+                        ),
+                        rule: ValidationRule::MaxLength,
+                        actual_value: Some(value.clone()),
+                        fix: None,
+                    });
+                }
+            }
 
-                // check minlength
-                if let Some(minlen) = field_def.minlength {
-                    if value.len() < minlen as usize {
+            // check pattern
+            if let Some(ref pattern) = field_def.pattern {
+                // anchor the pattern to match the entire value
+                let anchored = format!("^({})$", pattern);
+                if let Ok(re) = Regex::new(&anchored) {
+                    if !re.is_match(&value) {
                         issues.push(ValidationIssue {
                             path: path.clone(),
                             range,
                             severity: Severity::Warning,
                             message: format!(
-                                "{} ({}) is too short: {} chars, minimum is {}",
-                                path,
-                                field_def.name,
-                                value.len(),
-                                minlen
+                                "{} ({}) does not match expected format",
+                                path, field_def.name
                             ),
-                            rule: ValidationRule::MinLength,
+                            rule: ValidationRule::Pattern,
                             actual_value: Some(value.clone()),
+                            fix: None,
                         });
                     }
                 }
+            }
+
+            // check allowed values (if no pattern specified)
+            if field_def.pattern.is_none() {
+                if let Some(ref allowed) = field_def.values {
+                    // filter out template placeholders like {auto}, {now} from allowed values
+                    let real_values: Vec<&String> = allowed
+                        .keys()
+                        .filter(|k| !(k.starts_with('{') && k.ends_with('}')))
+                        .collect();
 
-                // check maxlength
-                if let Some(maxlen) = field_def.maxlength {
-                    if value.len() > maxlen as usize {
+                    // only validate if there are non-template allowed values
+                    if !real_values.is_empty() && !real_values.contains(&&value) {
+                        let expected = real_values
+                            .iter()
+                            .take(5)
+                            .map(|s| format!("'{}'", s))
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        let table = field_def
+                            .table
+                            .as_deref()
+                            .and_then(|id| schema.get_table(id));
+                        let (severity, message) = match table {
+                            // sites commonly extend user-defined tables with their own codes
+                            Some(table) => (
+                                match table.kind {
+                                    TableKind::Hl7 => Severity::Warning,
+                                    TableKind::User => Severity::Info,
+                                },
+                                format!(
+                                    "{} ({}) has value '{}', which isn't in table {} ({}). Expected one of: {}",
+                                    path, field_def.name, value, table.id, table.name, expected
+                                ),
+                            ),
+                            None => (
+                                Severity::Warning,
+                                format!(
+                                    "{} ({}) has unexpected value '{}'. Expected one of: {}",
+                                    path, field_def.name, value, expected
+                                ),
+                            ),
+                        };
                         issues.push(ValidationIssue {
                             path: path.clone(),
                             range,
-                            severity: Severity::Warning,
-                            message: format!(
-                                "{} ({}) is too long: {} chars, maximum is {}",
-                                path,
-                                field_def.name,
-                                value.len(),
-                                maxlen
-                            ),
-                            rule: ValidationRule::MaxLength,
+                            severity,
+                            message,
+                            rule: ValidationRule::AllowedValues,
                             actual_value: Some(value.clone()),
+                            fix: allowed_value_fix(&value, &real_values),
                         });
                     }
                 }
+            }
 
-                // check pattern
-                if let Some(ref pattern) = field_def.pattern {
-                    // anchor the pattern to match the entire value
-                    let anchored = format!("^({})$", pattern);
-                    if let Ok(re) = Regex::new(&anchored) {
-                        if !re.is_match(&value) {
-                            issues.push(ValidationIssue {
-                                path: path.clone(),
-                                range,
-                                severity: Severity::Warning,
-                                message: format!(
-                                    "{} ({}) does not match expected format",
-                                    path, field_def.name
-                                ),
-                                rule: ValidationRule::Pattern,
-                                actual_value: Some(value.clone()),
-                            });
-                        }
-                    }
-                }
-
-                // check allowed values (if no pattern specified)
-                if field_def.pattern.is_none() {
-                    if let Some(ref allowed) = field_def.values {
-                        // filter out template placeholders like {auto}, {now} from allowed values
-                        let real_values: Vec<&String> = allowed
-                            .keys()
-                            .filter(|k| !(k.starts_with('{') && k.ends_with('}')))
-                            .collect();
-
-                        // only validate if there are non-template allowed values
-                        if !real_values.is_empty() && !real_values.contains(&&value) {
-                            issues.push(ValidationIssue {
-                                path: path.clone(),
-                                range,
-                                severity: Severity::Warning,
-                                message: format!(
-                                    "{} ({}) has unexpected value '{}'. Expected one of: {}",
-                                    path,
-                                    field_def.name,
-                                    value,
-                                    real_values
-                                        .iter()
-                                        .take(5)
-                                        .map(|s| format!("'{}'", s))
-                                        .collect::<Vec<_>>()
-                                        .join(", ")
-                                ),
-                                rule: ValidationRule::AllowedValues,
-                                actual_value: Some(value.clone()),
-                            });
-                        }
-                    }
-                }
-
-                // check date/datetime format
-                if let Some(datatype) = field_def.datatype {
-                    validate_datetime(&value, datatype, &path, &field_def.name, range, issues);
-                }
+            // check date/datetime format
+            if let Some(datatype) = field_def.datatype {
+                validate_datetime(&value, datatype, &path, &field_def.name, range, issues);
             }
         }
     }
@@ -492,6 +821,7 @@ fn validate_datetime(
             ),
             rule: ValidationRule::InvalidDate,
             actual_value: Some(value.to_string()),
+            fix: date_fix(value, datatype),
         });
     }
 }
@@ -499,7 +829,7 @@ fn validate_datetime(
 /// Validate message structure (required segments).
 fn validate_message_structure(
     msg: &hl7_parser::Message,
-    state: &State<AppData>,
+    schema: &SchemaCache,
     issues: &mut Vec<ValidationIssue>,
 ) {
     // check for MSH segment
@@ -511,6 +841,7 @@ fn validate_message_structure(
             message: "MSH segment is required".to_string(),
             rule: ValidationRule::RequiredSegment,
             actual_value: None,
+            fix: None,
         });
         return;
     }
@@ -526,7 +857,7 @@ fn validate_message_structure(
         msg_type.to_lowercase(),
         trigger_event.to_lowercase()
     );
-    let messages_schema = state.schema.get_messages();
+    let messages_schema = schema.get_messages();
 
     let message_def = match messages_schema.message.get(&message_key) {
         Some(def) => def,
@@ -551,13 +882,17 @@ fn validate_message_structure(
                 ),
                 rule: ValidationRule::RequiredSegment,
                 actual_value: None,
+                fix: None,
             });
         }
     }
+
+    // check segment order, cardinality, and unexpected segments
+    validate_segment_structure(msg, &msg_type, &trigger_event, message_def, issues);
 }
 
 /// Get the value and range of a field or component from a segment.
-fn get_field_value(
+pub(super) fn get_field_value(
     segment: &hl7_parser::message::Segment,
     field_num: u8,
     component_num: Option<u8>,
@@ -593,6 +928,34 @@ fn get_field_value(
 mod tests {
     use super::*;
 
+    #[test]
+    fn flags_control_ids_already_sent() {
+        let message = "MSH|^~\\&|HERMES|LAB|||20250101||ADT^A01|abc123|P|2.5.1";
+        let history = ControlIdHistory::default();
+        let result =
+            flag_duplicate_control_id(ValidationResult::new(Vec::new()), message, &history);
+        assert!(result.issues.is_empty());
+
+        history.push(crate::commands::ControlIdUse {
+            control_id: "abc123".to_string(),
+            direction: ControlIdDirection::Sent,
+            timestamp: jiff::Timestamp::UNIX_EPOCH,
+            peer: "10.0.0.5:2575".to_string(),
+            message_type: None,
+            environment: Some(crate::commands::Environment::Prod),
+        });
+        let result = flag_duplicate_control_id(result, message, &history);
+        let result = flag_duplicate_control_id(result, message, &history);
+        assert_eq!(result.issues.len(), 1);
+        assert_eq!(result.summary.warnings, 1);
+        let issue = &result.issues[0];
+        assert_eq!(issue.rule, ValidationRule::DuplicateControlId);
+        assert_eq!(issue.path, "MSH.10");
+        let (start, end) = issue.range.unwrap();
+        assert_eq!(&message[start..end], "abc123");
+        assert!(issue.message.contains("sent to 10.0.0.5:2575 [prod]"));
+    }
+
     #[test]
     fn test_datetime_validation_date() {
         let mut issues = Vec::new();
@@ -635,4 +998,63 @@ mod tests {
         // template placeholders should be skipped
         assert!(issues.is_empty());
     }
+
+    #[test]
+    fn test_values_outside_bound_tables_are_flagged() {
+        let cache = SchemaCache::new().expect("can create cache");
+        let message = "MSH|^~\\&|APP|FAC|||20250101||ADT^A01|1|X|2.5.1\rPID|1||123||Doe^Jane||19800101|Female";
+        let result = validate_message(message, &cache, ValidationMode::Full);
+
+        let issue = |path: &str| {
+            result
+                .issues
+                .iter()
+                .find(|i| i.path == path && i.rule == ValidationRule::AllowedValues)
+                .unwrap_or_else(|| panic!("no allowed value issue for {path}"))
+        };
+        // MSH.11 is bound to an HL7 table, PID.8 to a user-defined one
+        assert_eq!(issue("MSH.11").severity, Severity::Warning);
+        assert!(issue("MSH.11")
+            .message
+            .contains("table 0103 (Processing ID)"));
+        assert_eq!(issue("PID.8").severity, Severity::Info);
+        assert!(issue("PID.8").message.contains("table 0001"));
+    }
+
+    #[test]
+    fn full_validation_reports_progress_and_stops_when_asked() {
+        let cache = SchemaCache::new().expect("can create cache");
+        let message = "MSH|^~\\&|APP|FAC|||20250101||ORU^R01|1|P|2.5.1\rPID|1||123\rOBR|1\rOBX|1|NM\rOBX|2|NM";
+
+        let mut progress = Vec::new();
+        let result =
+            validate_message_with_progress(message, &cache, ValidationMode::Full, |n, total| {
+                progress.push((n, total));
+                ControlFlow::Continue(())
+            })
+            .unwrap();
+        assert_eq!(progress, vec![(1, 5), (2, 5), (3, 5), (4, 5), (5, 5)]);
+        let expected = validate_message(message, &cache, ValidationMode::Full);
+        let paths = |result: &ValidationResult| {
+            result
+                .issues
+                .iter()
+                .map(|i| i.path.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(paths(&result), paths(&expected));
+
+        let mut checked = 0;
+        let stopped =
+            validate_message_with_progress(message, &cache, ValidationMode::Full, |n, _| {
+                checked = n;
+                if n == 2 {
+                    ControlFlow::Break(())
+                } else {
+                    ControlFlow::Continue(())
+                }
+            });
+        assert!(stopped.is_none());
+        assert_eq!(checked, 2);
+    }
 }
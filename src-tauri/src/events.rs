@@ -0,0 +1,496 @@
+//! Names and payloads of the events the backend emits to the frontend.
+//!
+//! Events used to be named by string literals at each `emit` call and each
+//! frontend `listen`, so nothing tied the two together: a renamed event, or a
+//! payload that changed shape, failed silently at runtime. Every event is now
+//! declared once here, and the frontend's event names, and which payload type
+//! each event carries, are generated from the declarations.
+//!
+//! The payload types themselves aren't generated: each is a TypeScript
+//! interface written by hand next to the frontend code that uses it, and
+//! named by its [`EventContract`]. A renamed event is caught by the type
+//! checker, but a field added to or renamed in a Rust payload struct still has
+//! to be mirrored in its interface by hand.
+//!
+//! # Adding an Event
+//!
+//! 1. Add a constant for its name, and emit it with that constant
+//! 2. Add it to [`EVENTS`] (or, for a menu item without a payload, to
+//!    [`MENU_EVENTS`]) with the TypeScript type of its payload, writing or
+//!    updating that type to match the payload's Rust struct
+//! 3. Regenerate `src/lib/shared/events.ts`:
+//!    `HERMES_UPDATE_EVENTS=1 cargo test events`
+//!
+//! The `typescript_definitions_are_current` test fails until the definitions
+//! are regenerated, so a new event can't be forgotten.
+//!
+//! # Frontend
+//!
+//! The generated file maps each event name to its payload type, and provides
+//! `listenEvent`, a `listen` whose event name is checked and whose payload is
+//! typed from the map.
+
+/// A message received by the MLLP listener or a drop watch, and where it was saved, if it was.
+pub const RECEIVED_MESSAGE: &str = "received-message";
+/// A connection to the MLLP listener opened, received a message, or closed.
+pub const LISTENER_CONNECTION: &str = "listener-connection";
+/// The listener bound to its address and started accepting connections.
+pub const LISTENER_STARTED: &str = "listener-started";
+/// A connection through the MLLP relay opened, relayed a message, or closed.
+pub const RELAY_TRAFFIC: &str = "relay-traffic";
+/// A progress message from a send, for displaying in the UI.
+pub const SEND_LOG: &str = "send-log";
+/// A response, or failure, of a send.
+pub const SEND_RESPONSE: &str = "send-response";
+/// The result of one send of a scheduled send.
+pub const SCHEDULE_PROGRESS: &str = "schedule-progress";
+/// A scheduled send finished or was stopped.
+pub const SCHEDULE_FINISHED: &str = "schedule-finished";
+/// A file of a batch job was processed.
+pub const JOB_PROGRESS: &str = "job-progress";
+/// A batch job finished, with a summary of its results.
+pub const JOB_FINISHED: &str = "job-finished";
+/// A light validation scheduled by the editor finished.
+pub const VALIDATION_RESULT: &str = "validation-result";
+/// A full validation checked more segments.
+pub const VALIDATION_PROGRESS: &str = "validation-progress";
+/// The open file was changed by another program.
+pub const FILE_CHANGED_EXTERNALLY: &str = "file-changed-externally";
+/// The schema was reloaded, from the external schema folder or the embedded data.
+pub const SCHEMA_RELOADED: &str = "schema-reloaded";
+/// The data root moved to another folder.
+pub const DATA_ROOT_CHANGED: &str = "data-root-changed";
+/// A shutdown stage started.
+pub const SHUTDOWN_PROGRESS: &str = "shutdown-progress";
+/// Shutdown is waiting for the frontend to save its session.
+pub const SHUTDOWN_SAVE_SESSION: &str = "shutdown-save-session";
+/// Extensions were started, stopped, or reloaded.
+pub const EXTENSIONS_CHANGED: &str = "extensions-changed";
+/// An extension's status changed.
+pub const EXTENSION_STATUS_CHANGED: &str = "extension-status-changed";
+/// An extension replaced the editor's message.
+pub const EXTENSION_SET_MESSAGE: &str = "extension-set-message";
+/// A window opened by an extension was closed.
+pub const EXTENSION_WINDOW_CLOSED: &str = "extension-window-closed";
+/// An extension set or removed a status bar item.
+pub const EXTENSION_STATUS_BAR_ITEM: &str = "extension-status-bar-item";
+/// An extension reported the progress of an operation.
+pub const EXTENSION_PROGRESS: &str = "extension-progress";
+/// A message was exchanged with an extension in development mode.
+pub const EXTENSION_TRACE: &str = "extension-trace";
+
+/// "Open Recent" item chosen, with the file's path.
+pub const MENU_OPEN_RECENT: &str = "menu-open-recent";
+/// Built-in template chosen, with the template's name.
+pub const MENU_NEW_FROM_TEMPLATE: &str = "menu-new-from-template";
+/// User template chosen, with the template's identifier.
+pub const MENU_NEW_FROM_USER_TEMPLATE: &str = "menu-new-from-user-template";
+/// Extension template chosen, with the template and the extension generating it.
+pub const MENU_NEW_FROM_EXTENSION_TEMPLATE: &str = "menu-new-from-extension-template";
+/// Sample gallery item chosen, with the sample's identifier.
+pub const MENU_OPEN_SAMPLE: &str = "menu-open-sample";
+/// Name conversion chosen, with the style to convert to.
+pub const MENU_TOOLS_CONVERT_NAME: &str = "menu-tools-convert-name";
+/// Delete segment chosen, with the segment to delete (the one at the cursor if null).
+pub const MENU_EDIT_DELETE_SEGMENT: &str = "menu-edit-delete-segment";
+/// Move segment up chosen, with the segment to move (the one at the cursor if null).
+pub const MENU_EDIT_MOVE_SEGMENT_UP: &str = "menu-edit-move-segment-up";
+/// Move segment down chosen, with the segment to move (the one at the cursor if null).
+pub const MENU_EDIT_MOVE_SEGMENT_DOWN: &str = "menu-edit-move-segment-down";
+/// Duplicate segment chosen, with the segment to duplicate (the one at the cursor if null).
+pub const MENU_EDIT_DUPLICATE_SEGMENT: &str = "menu-edit-duplicate-segment";
+
+/// An event the backend emits, as declared to the frontend.
+pub struct EventContract {
+    /// Event name
+    pub name: &'static str,
+    /// TypeScript type of the payload
+    pub payload: &'static str,
+    /// Frontend module exporting the payload type, if it isn't built in
+    pub import: Option<&'static str>,
+}
+
+/// Every event with a payload, or emitted outside the menu.
+pub const EVENTS: &[EventContract] = &[
+    EventContract {
+        name: RECEIVED_MESSAGE,
+        payload: "ReceivedMessage",
+        import: Some("$lib/communication/listen"),
+    },
+    EventContract {
+        name: LISTENER_CONNECTION,
+        payload: "ListenerConnectionEvent",
+        import: Some("$lib/communication/listen"),
+    },
+    EventContract {
+        name: LISTENER_STARTED,
+        payload: "ListenerStarted",
+        import: Some("$lib/communication/listen"),
+    },
+    EventContract {
+        name: RELAY_TRAFFIC,
+        payload: "RelayTraffic",
+        import: Some("$lib/communication/relay"),
+    },
+    EventContract {
+        name: SEND_LOG,
+        payload: "string",
+        import: None,
+    },
+    EventContract {
+        name: SEND_RESPONSE,
+        payload: "SendResponse",
+        import: Some("$lib/communication/send_receive"),
+    },
+    EventContract {
+        name: SCHEDULE_PROGRESS,
+        payload: "ScheduleProgress",
+        import: Some("$lib/communication/schedule"),
+    },
+    EventContract {
+        name: SCHEDULE_FINISHED,
+        payload: "ScheduleFinished",
+        import: Some("$lib/communication/schedule"),
+    },
+    EventContract {
+        name: JOB_PROGRESS,
+        payload: "JobProgress",
+        import: Some("$lib/jobs/jobs"),
+    },
+    EventContract {
+        name: JOB_FINISHED,
+        payload: "JobSummary",
+        import: Some("$lib/jobs/jobs"),
+    },
+    EventContract {
+        name: VALIDATION_RESULT,
+        payload: "ScheduledValidation",
+        import: Some("$lib/validation/validate"),
+    },
+    EventContract {
+        name: VALIDATION_PROGRESS,
+        payload: "ValidationProgress",
+        import: Some("$lib/validation/validate"),
+    },
+    EventContract {
+        name: FILE_CHANGED_EXTERNALLY,
+        payload: "ExternalChange",
+        import: Some("$lib/editor/file_watch"),
+    },
+    EventContract {
+        name: SCHEMA_RELOADED,
+        payload: "SchemaReload",
+        import: Some("$lib/shared/schema"),
+    },
+    EventContract {
+        name: DATA_ROOT_CHANGED,
+        payload: "DataRoot",
+        import: Some("$lib/shared/data_root"),
+    },
+    EventContract {
+        name: SHUTDOWN_PROGRESS,
+        payload: "ShutdownProgress",
+        import: Some("$lib/shared/shutdown"),
+    },
+    EventContract {
+        name: SHUTDOWN_SAVE_SESSION,
+        payload: "null",
+        import: None,
+    },
+    EventContract {
+        name: EXTENSIONS_CHANGED,
+        payload: "null",
+        import: None,
+    },
+    EventContract {
+        name: EXTENSION_STATUS_CHANGED,
+        payload: "ExtensionStatus",
+        import: Some("$lib/extensions/extensions"),
+    },
+    EventContract {
+        name: EXTENSION_SET_MESSAGE,
+        payload: "string",
+        import: None,
+    },
+    EventContract {
+        name: EXTENSION_WINDOW_CLOSED,
+        payload: "[string, { windowId: string; reason: string }]",
+        import: None,
+    },
+    EventContract {
+        name: EXTENSION_STATUS_BAR_ITEM,
+        payload: "ExtensionStatusBarItem",
+        import: Some("$lib/extensions/extensions"),
+    },
+    EventContract {
+        name: EXTENSION_PROGRESS,
+        payload: "ExtensionProgress",
+        import: Some("$lib/extensions/extensions"),
+    },
+    EventContract {
+        name: EXTENSION_TRACE,
+        payload: "ExtensionTraceEntry",
+        import: Some("$lib/extensions/extensions"),
+    },
+    EventContract {
+        name: MENU_OPEN_RECENT,
+        payload: "string",
+        import: None,
+    },
+    EventContract {
+        name: MENU_NEW_FROM_TEMPLATE,
+        payload: "string",
+        import: None,
+    },
+    EventContract {
+        name: MENU_NEW_FROM_USER_TEMPLATE,
+        payload: "string",
+        import: None,
+    },
+    EventContract {
+        name: MENU_NEW_FROM_EXTENSION_TEMPLATE,
+        payload: "TemplateInfo",
+        import: Some("$lib/extensions/extensions"),
+    },
+    EventContract {
+        name: MENU_OPEN_SAMPLE,
+        payload: "string",
+        import: None,
+    },
+    EventContract {
+        name: MENU_TOOLS_CONVERT_NAME,
+        payload: "string",
+        import: None,
+    },
+    EventContract {
+        name: MENU_EDIT_DELETE_SEGMENT,
+        payload: "SegmentEventTarget",
+        import: Some("$lib/editor/segment"),
+    },
+    EventContract {
+        name: MENU_EDIT_MOVE_SEGMENT_UP,
+        payload: "SegmentEventTarget",
+        import: Some("$lib/editor/segment"),
+    },
+    EventContract {
+        name: MENU_EDIT_MOVE_SEGMENT_DOWN,
+        payload: "SegmentEventTarget",
+        import: Some("$lib/editor/segment"),
+    },
+    EventContract {
+        name: MENU_EDIT_DUPLICATE_SEGMENT,
+        payload: "SegmentEventTarget",
+        import: Some("$lib/editor/segment"),
+    },
+];
+
+/// Menu item ids and the segment events they emit to the main window, without
+/// a target so they act on the segment at the cursor.
+///
+/// The events are declared in [`EVENTS`], as anything else emitting them can
+/// name the segment to act on (e.g., `"OBX[3]"`).
+pub const SEGMENT_MENU_EVENTS: &[(&str, &str)] = &[
+    ("edit-delete-segment", MENU_EDIT_DELETE_SEGMENT),
+    ("edit-move-segment-up", MENU_EDIT_MOVE_SEGMENT_UP),
+    ("edit-move-segment-down", MENU_EDIT_MOVE_SEGMENT_DOWN),
+    ("edit-duplicate-segment", MENU_EDIT_DUPLICATE_SEGMENT),
+];
+
+/// Menu item ids and the events they emit to the main window, without a payload.
+pub const MENU_EVENTS: &[(&str, &str)] = &[
+    ("file-new", "menu-file-new"),
+    ("file-open", "menu-file-open"),
+    ("file-save", "menu-file-save"),
+    ("file-save-as", "menu-file-save-as"),
+    ("file-export-json", "menu-file-export-json"),
+    ("file-export-yaml", "menu-file-export-yaml"),
+    ("file-export-toml", "menu-file-export-toml"),
+    ("file-export-xml", "menu-file-export-xml"),
+    ("file-export-fhir", "menu-file-export-fhir"),
+    ("file-import-json", "menu-file-import-json"),
+    ("file-import-yaml", "menu-file-import-yaml"),
+    ("file-import-toml", "menu-file-import-toml"),
+    ("file-import-xml", "menu-file-import-xml"),
+    ("file-import-fhir", "menu-file-import-fhir"),
+    ("file-auto-save", "menu-file-auto-save"),
+    ("file-template-library", "menu-file-template-library"),
+    ("edit-undo", "menu-edit-undo"),
+    ("edit-redo", "menu-edit-redo"),
+    ("edit-find", "menu-edit-find"),
+    ("edit-find-replace", "menu-edit-find-replace"),
+    ("edit-jump-to-field", "menu-edit-jump-to-field"),
+    ("edit-insert-snippet", "menu-edit-insert-snippet"),
+    ("edit-attach-document", "menu-edit-attach-document"),
+    ("edit-paste-escaped", "menu-edit-paste-escaped"),
+    ("edit-encode-escapes", "menu-edit-encode-escapes"),
+    ("edit-decode-escapes", "menu-edit-decode-escapes"),
+    ("edit-copy-field-path", "menu-edit-copy-field-path"),
+    ("edit-copy-field-value", "menu-edit-copy-field-value"),
+    ("view-zoom-in", "menu-view-zoom-in"),
+    ("view-zoom-out", "menu-view-zoom-out"),
+    ("view-reset-zoom", "menu-view-reset-zoom"),
+    ("view-keyboard-shortcuts", "menu-view-keyboard-shortcuts"),
+    ("tools-send", "menu-tools-send"),
+    ("tools-listen", "menu-tools-listen"),
+    (
+        "tools-connection-profiles",
+        "menu-tools-connection-profiles",
+    ),
+    ("tools-forget-control-ids", "menu-tools-forget-control-ids"),
+    ("tools-validate", "menu-tools-validate"),
+    ("tools-compare", "menu-tools-compare"),
+    ("tools-compare-segments", "menu-tools-compare-segments"),
+    ("tools-match-expectation", "menu-tools-match-expectation"),
+    ("tools-phi-report", "menu-tools-phi-report"),
+    ("tools-run-job", "menu-tools-run-job"),
+    ("tools-script-console", "menu-tools-script-console"),
+    ("tools-pipelines", "menu-tools-pipelines"),
+    ("tools-mapping-tables", "menu-tools-mapping-tables"),
+    ("tools-extract-messages", "menu-tools-extract-messages"),
+    ("tools-import-capture", "menu-tools-import-capture"),
+    ("tools-test-cases", "menu-tools-test-cases"),
+    ("tools-adt-workflow", "menu-tools-adt-workflow"),
+    ("tools-result-block", "menu-tools-result-block"),
+    ("tools-query-builder", "menu-tools-query-builder"),
+    (
+        "tools-generate-control-id",
+        "menu-tools-generate-control-id",
+    ),
+    ("tools-evaluate-formulas", "menu-tools-evaluate-formulas"),
+    ("tools-embedded-documents", "menu-tools-embedded-documents"),
+    ("tools-wrap-batch", "menu-tools-wrap-batch"),
+    ("tools-unwrap-batch", "menu-tools-unwrap-batch"),
+    (
+        "tools-insert-timestamp-now",
+        "menu-tools-insert-timestamp-now",
+    ),
+    ("tools-insert-timestamp", "menu-tools-insert-timestamp"),
+    ("recent-clear", "menu-clear-recent"),
+    ("help", "menu-help"),
+];
+
+/// Event emitted by a menu item without a payload, if it emits one.
+#[must_use]
+pub fn menu_event(menu_id: &str) -> Option<&'static str> {
+    MENU_EVENTS
+        .iter()
+        .chain(SEGMENT_MENU_EVENTS)
+        .find(|(id, _)| *id == menu_id)
+        .map(|(_, event)| *event)
+}
+
+/// Render the frontend's event definitions, `src/lib/shared/events.ts`.
+#[must_use]
+pub fn typescript_definitions() -> String {
+    let mut imports: Vec<(&str, Vec<&str>)> = Vec::new();
+    for event in EVENTS {
+        let Some(module) = event.import else {
+            continue;
+        };
+        match imports.iter_mut().find(|(m, _)| *m == module) {
+            Some((_, types)) if !types.contains(&event.payload) => types.push(event.payload),
+            Some(_) => {}
+            None => imports.push((module, vec![event.payload])),
+        }
+    }
+    imports.sort_by_key(|(module, _)| *module);
+
+    let mut ts = String::from(
+        "// Generated from src-tauri/src/events.rs; do not edit by hand.\n\
+         // Regenerate with `HERMES_UPDATE_EVENTS=1 cargo test events` in src-tauri.\n\
+         \n\
+         /**\n \
+         * Names and payload types of the events the backend emits.\n \
+         *\n \
+         * Listen with `listenEvent` rather than `listen`, so a misspelt event name\n \
+         * or a payload of the wrong type is a type error.\n \
+         */\n\
+         \n\
+         import {\n  \
+           listen,\n  \
+           type EventCallback,\n  \
+           type UnlistenFn,\n\
+         } from \"@tauri-apps/api/event\";\n",
+    );
+    for (module, mut types) in imports {
+        types.sort_unstable();
+        ts.push_str(&format!(
+            "import type {{ {} }} from \"{module}\";\n",
+            types.join(", ")
+        ));
+    }
+
+    ts.push_str("\n/** Payload of each event the backend emits, by event name. */\n");
+    ts.push_str("export interface EventPayloads {\n");
+    for event in EVENTS {
+        ts.push_str(&format!("  \"{}\": {};\n", event.name, event.payload));
+    }
+    for (_, event) in MENU_EVENTS {
+        ts.push_str(&format!("  \"{event}\": null;\n"));
+    }
+    ts.push_str("}\n");
+
+    ts.push_str(
+        "\n/** Name of an event the backend emits. */\n\
+         export type EventName = keyof EventPayloads;\n\
+         \n\
+         /**\n \
+         * Listens for a backend event, with its payload typed from `EventPayloads`.\n \
+         *\n \
+         * @param event - Name of the event\n \
+         * @param handler - Called with each occurrence of the event\n \
+         * @returns Function to stop listening\n \
+         */\n\
+         export function listenEvent<K extends EventName>(\n  \
+           event: K,\n  \
+           handler: EventCallback<EventPayloads[K]>,\n\
+         ): Promise<UnlistenFn> {\n  \
+           return listen<EventPayloads[K]>(event, handler);\n\
+         }\n",
+    );
+    ts
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    const DEFINITIONS_PATH: &str =
+        concat!(env!("CARGO_MANIFEST_DIR"), "/../src/lib/shared/events.ts");
+
+    #[test]
+    fn event_names_are_unique() {
+        let mut names = HashSet::new();
+        for name in EVENTS
+            .iter()
+            .map(|event| event.name)
+            .chain(MENU_EVENTS.iter().map(|(_, event)| *event))
+        {
+            assert!(names.insert(name), "{name} is declared twice");
+        }
+        assert_eq!(menu_event("file-save"), Some("menu-file-save"));
+        assert_eq!(menu_event("recent-clear"), Some("menu-clear-recent"));
+        assert_eq!(
+            menu_event("edit-duplicate-segment"),
+            Some(MENU_EDIT_DUPLICATE_SEGMENT)
+        );
+        assert_eq!(menu_event("help-check-updates"), None);
+    }
+
+    #[test]
+    fn typescript_definitions_are_current() {
+        let expected = typescript_definitions();
+        if std::env::var_os("HERMES_UPDATE_EVENTS").is_some() {
+            std::fs::write(DEFINITIONS_PATH, &expected).unwrap();
+            return;
+        }
+        let actual = std::fs::read_to_string(DEFINITIONS_PATH).unwrap_or_default();
+        assert!(
+            actual == expected,
+            "src/lib/shared/events.ts is out of date; regenerate it with \
+             `HERMES_UPDATE_EVENTS=1 cargo test events`"
+        );
+    }
+}
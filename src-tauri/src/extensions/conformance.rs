@@ -0,0 +1,633 @@
+//! Conformance testing for extension developers.
+//!
+//! Runs a scripted suite of protocol checks against an extension executable and
+//! reports how it behaves. The suite exercises the parts of the protocol that
+//! Hermes relies on but which are easy to get wrong when writing an extension
+//! by hand:
+//!
+//! - The `initialize` handshake completes within the timeout and returns usable
+//!   metadata
+//! - Malformed `initialize` params are rejected with `INVALID_PARAMS`
+//! - A second `initialize` is rejected with `ALREADY_INITIALIZED`
+//! - Unknown methods are rejected with `METHOD_NOT_FOUND`
+//! - `shutdown` is answered promptly and the process then exits
+//!
+//! # Isolation
+//!
+//! Extensions under test are spawned outside the [`ExtensionHost`], so they
+//! never receive events, contribute toolbar buttons, or merge schema overrides.
+//! The malformed params check runs against its own throwaway process so that a
+//! crash there doesn't prevent the rest of the suite from running.
+//!
+//! [`ExtensionHost`]: crate::extensions::ExtensionHost
+
+use crate::extensions::host::API_VERSION;
+use crate::extensions::process::{ExtensionError, ExtensionProcess};
+use crate::extensions::protocol::error_codes;
+use crate::extensions::types::{
+    ExtensionConfig, ExtensionLog, ExtensionTransport, ShutdownParams, ShutdownReason,
+};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Instant;
+use tokio::time::{timeout, Duration};
+
+/// How long to wait for a response to any individual probe.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long to wait for the process to exit after answering `shutdown`.
+const EXIT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Names of the checks in the order they run.
+const CHECK_SPAWN: &str = "spawn";
+const CHECK_INITIALIZE: &str = "initialize";
+const CHECK_METADATA: &str = "metadata";
+const CHECK_TOOLBAR_COMMANDS: &str = "toolbar commands";
+const CHECK_DUPLICATE_INITIALIZE: &str = "duplicate initialize";
+const CHECK_UNKNOWN_METHOD: &str = "unknown method";
+const CHECK_MALFORMED_PARAMS: &str = "malformed params";
+const CHECK_SHUTDOWN: &str = "shutdown";
+const CHECK_EXIT: &str = "exit after shutdown";
+
+/// Outcome of a single conformance check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConformanceStatus {
+    /// The extension behaved as the protocol requires.
+    Pass,
+    /// The extension works, but deviates from the protocol in a minor way.
+    Warn,
+    /// The extension violates the protocol in a way Hermes depends on.
+    Fail,
+    /// The check couldn't run because an earlier check failed.
+    Skipped,
+}
+
+/// Result of a single conformance check.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConformanceCheck {
+    /// Short name of the check.
+    pub name: String,
+    /// Outcome of the check.
+    pub status: ConformanceStatus,
+    /// Explanation of the outcome.
+    pub message: String,
+    /// Time taken by the check in milliseconds.
+    #[serde(rename = "durationMs")]
+    pub duration_ms: u64,
+}
+
+impl ConformanceCheck {
+    fn new(
+        name: &str,
+        status: ConformanceStatus,
+        message: impl Into<String>,
+        started: Instant,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            status,
+            message: message.into(),
+            duration_ms: u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX),
+        }
+    }
+
+    fn skipped(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            status: ConformanceStatus::Skipped,
+            message: "skipped because an earlier check failed".to_string(),
+            duration_ms: 0,
+        }
+    }
+}
+
+/// Structured report produced by running the conformance suite.
+#[derive(Debug, Clone, Serialize)]
+pub struct ConformanceReport {
+    /// Extension command that was tested.
+    pub path: String,
+    /// Extension name from the initialize response, if the handshake succeeded.
+    #[serde(rename = "extensionName")]
+    pub extension_name: Option<String>,
+    /// Extension version from the initialize response, if the handshake succeeded.
+    #[serde(rename = "extensionVersion")]
+    pub extension_version: Option<String>,
+    /// Individual check results in the order they ran.
+    pub checks: Vec<ConformanceCheck>,
+    /// Whether every check passed or only warned.
+    pub passed: bool,
+    /// Log entries captured from the extension during the run.
+    pub logs: Vec<ExtensionLog>,
+}
+
+impl ConformanceReport {
+    fn new(path: &str) -> Self {
+        Self {
+            path: path.to_string(),
+            extension_name: None,
+            extension_version: None,
+            checks: Vec::new(),
+            passed: false,
+            logs: Vec::new(),
+        }
+    }
+
+    fn push(&mut self, check: ConformanceCheck) {
+        self.checks.push(check);
+    }
+
+    fn skip(&mut self, names: &[&str]) {
+        self.checks
+            .extend(names.iter().map(|name| ConformanceCheck::skipped(name)));
+    }
+
+    fn finish(mut self) -> Self {
+        self.passed = self
+            .checks
+            .iter()
+            .all(|check| check.status != ConformanceStatus::Fail);
+        self
+    }
+}
+
+/// Outcome of sending a single request to the extension.
+#[derive(Debug)]
+enum ProbeOutcome {
+    /// The extension answered with a result.
+    Success,
+    /// The extension answered with an error response.
+    Rejected { code: i32, message: String },
+    /// The request couldn't be delivered or the response was lost.
+    Failed(String),
+    /// The extension didn't answer within [`PROBE_TIMEOUT`].
+    TimedOut,
+}
+
+/// Run the conformance suite against an extension executable.
+///
+/// # Arguments
+/// * `path` - Extension command, as it would appear in the extension settings
+/// * `data_dir` - Data directory handed to the extension
+/// * `hermes_version` - Hermes version reported to the extension
+///
+/// # Returns
+/// A report of every check; checks that couldn't run are marked as skipped
+pub async fn run_conformance_suite(
+    path: &str,
+    data_dir: &Path,
+    hermes_version: &str,
+) -> ConformanceReport {
+    let config = ExtensionConfig {
+        path: path.to_string(),
+        args: Vec::new(),
+        env: HashMap::new(),
+        enabled: true,
+        settings: serde_json::Map::new(),
+        transport: ExtensionTransport::default(),
+        port: None,
+        dev_mode: false,
+        log_capacity: None,
+    };
+    let mut report = ConformanceReport::new(path);
+
+    // spawn
+    let started = Instant::now();
+    let mut process = match ExtensionProcess::spawn(
+        config.clone(),
+        data_dir,
+        hermes_version,
+        API_VERSION,
+    )
+    .await
+    {
+        Ok(process) => {
+            report.push(ConformanceCheck::new(
+                CHECK_SPAWN,
+                ConformanceStatus::Pass,
+                "process started",
+                started,
+            ));
+            process
+        }
+        Err(e) => {
+            report.push(ConformanceCheck::new(
+                CHECK_SPAWN,
+                ConformanceStatus::Fail,
+                format!("failed to start extension: {e}"),
+                started,
+            ));
+            report.skip(&[
+                CHECK_INITIALIZE,
+                CHECK_METADATA,
+                CHECK_TOOLBAR_COMMANDS,
+                CHECK_DUPLICATE_INITIALIZE,
+                CHECK_UNKNOWN_METHOD,
+                CHECK_MALFORMED_PARAMS,
+                CHECK_SHUTDOWN,
+                CHECK_EXIT,
+            ]);
+            return report.finish();
+        }
+    };
+
+    // initialize handshake
+    let started = Instant::now();
+    match process
+        .initialize(hermes_version, API_VERSION, data_dir)
+        .await
+    {
+        Ok(()) => report.push(ConformanceCheck::new(
+            CHECK_INITIALIZE,
+            ConformanceStatus::Pass,
+            "handshake completed",
+            started,
+        )),
+        Err(e) => {
+            report.push(ConformanceCheck::new(
+                CHECK_INITIALIZE,
+                ConformanceStatus::Fail,
+                format!("handshake failed: {e}"),
+                started,
+            ));
+            report.skip(&[
+                CHECK_METADATA,
+                CHECK_TOOLBAR_COMMANDS,
+                CHECK_DUPLICATE_INITIALIZE,
+                CHECK_UNKNOWN_METHOD,
+            ]);
+            report.logs = process.get_logs().await;
+            process.kill().await;
+
+            // still worth knowing how the extension treats bad input
+            report.push(check_malformed_params(&config, data_dir, hermes_version).await);
+            report.skip(&[CHECK_SHUTDOWN, CHECK_EXIT]);
+            return report.finish();
+        }
+    }
+
+    // metadata
+    let started = Instant::now();
+    let metadata = process.metadata().await;
+    if let Some(metadata) = &metadata {
+        report.extension_name = Some(metadata.name.clone());
+        report.extension_version = Some(metadata.version.clone());
+
+        let mut missing = Vec::new();
+        if metadata.name.trim().is_empty() {
+            missing.push("name");
+        }
+        if metadata.version.trim().is_empty() {
+            missing.push("version");
+        }
+        report.push(if missing.is_empty() {
+            ConformanceCheck::new(
+                CHECK_METADATA,
+                ConformanceStatus::Pass,
+                format!("{} v{}", metadata.name, metadata.version),
+                started,
+            )
+        } else {
+            ConformanceCheck::new(
+                CHECK_METADATA,
+                ConformanceStatus::Fail,
+                format!("missing {}", missing.join(" and ")),
+                started,
+            )
+        });
+
+        // toolbar buttons should only trigger commands the extension declares
+        let started = Instant::now();
+        let undeclared: Vec<&str> = metadata
+            .toolbar_buttons
+            .iter()
+            .map(|button| button.command.as_str())
+            .filter(|command| !metadata.capabilities.commands.iter().any(|c| c == command))
+            .collect();
+        report.push(if undeclared.is_empty() {
+            ConformanceCheck::new(
+                CHECK_TOOLBAR_COMMANDS,
+                ConformanceStatus::Pass,
+                "all toolbar commands are declared in capabilities",
+                started,
+            )
+        } else {
+            ConformanceCheck::new(
+                CHECK_TOOLBAR_COMMANDS,
+                ConformanceStatus::Warn,
+                format!(
+                    "toolbar commands not declared in capabilities: {}",
+                    undeclared.join(", ")
+                ),
+                started,
+            )
+        });
+    } else {
+        report.push(ConformanceCheck::new(
+            CHECK_METADATA,
+            ConformanceStatus::Fail,
+            "no metadata recorded after initialize",
+            started,
+        ));
+        report.skip(&[CHECK_TOOLBAR_COMMANDS]);
+    }
+
+    // a second initialize must be rejected
+    let started = Instant::now();
+    let params = serde_json::json!({
+        "hermesVersion": hermes_version,
+        "apiVersion": API_VERSION,
+        "dataDirectory": data_dir.to_string_lossy(),
+    });
+    let outcome = probe(&mut process, "initialize", params).await;
+    let (status, message) = classify_expected_error(&outcome, error_codes::ALREADY_INITIALIZED);
+    report.push(ConformanceCheck::new(
+        CHECK_DUPLICATE_INITIALIZE,
+        status,
+        message,
+        started,
+    ));
+
+    // unknown methods must be rejected rather than ignored
+    let started = Instant::now();
+    let outcome = probe(
+        &mut process,
+        "hermes/conformanceProbe",
+        serde_json::Value::Null,
+    )
+    .await;
+    let (status, message) = classify_expected_error(&outcome, error_codes::METHOD_NOT_FOUND);
+    report.push(ConformanceCheck::new(
+        CHECK_UNKNOWN_METHOD,
+        status,
+        message,
+        started,
+    ));
+
+    // malformed params, against a separate process
+    report.push(check_malformed_params(&config, data_dir, hermes_version).await);
+
+    // shutdown
+    let started = Instant::now();
+    let params = ShutdownParams {
+        reason: Some(ShutdownReason::Closing),
+    };
+    let outcome = probe(
+        &mut process,
+        "shutdown",
+        serde_json::to_value(&params).expect("can serialize params"),
+    )
+    .await;
+    let shutdown_ok = matches!(outcome, ProbeOutcome::Success);
+    report.push(match outcome {
+        ProbeOutcome::Success => ConformanceCheck::new(
+            CHECK_SHUTDOWN,
+            ConformanceStatus::Pass,
+            "shutdown acknowledged",
+            started,
+        ),
+        ProbeOutcome::Rejected { code, message } => ConformanceCheck::new(
+            CHECK_SHUTDOWN,
+            ConformanceStatus::Fail,
+            format!("shutdown rejected with error {code}: {message}"),
+            started,
+        ),
+        ProbeOutcome::Failed(e) => ConformanceCheck::new(
+            CHECK_SHUTDOWN,
+            ConformanceStatus::Fail,
+            format!("shutdown failed: {e}"),
+            started,
+        ),
+        ProbeOutcome::TimedOut => ConformanceCheck::new(
+            CHECK_SHUTDOWN,
+            ConformanceStatus::Fail,
+            format!(
+                "no response within {}s; Hermes would force kill the extension",
+                PROBE_TIMEOUT.as_secs()
+            ),
+            started,
+        ),
+    });
+
+    // the process should exit by itself once shutdown has been answered
+    if shutdown_ok {
+        let started = Instant::now();
+        report.push(if process.wait_for_exit(EXIT_TIMEOUT).await {
+            ConformanceCheck::new(
+                CHECK_EXIT,
+                ConformanceStatus::Pass,
+                "process exited",
+                started,
+            )
+        } else {
+            ConformanceCheck::new(
+                CHECK_EXIT,
+                ConformanceStatus::Warn,
+                format!(
+                    "process still running {}s after shutdown",
+                    EXIT_TIMEOUT.as_secs()
+                ),
+                started,
+            )
+        });
+    } else {
+        report.skip(&[CHECK_EXIT]);
+    }
+
+    report.logs = process.get_logs().await;
+    process.kill().await;
+
+    report.finish()
+}
+
+/// Check that `initialize` with malformed params is rejected.
+///
+/// Runs against a fresh process, since the only way to probe `initialize`
+/// params is before the extension has been initialised.
+async fn check_malformed_params(
+    config: &ExtensionConfig,
+    data_dir: &Path,
+    hermes_version: &str,
+) -> ConformanceCheck {
+    let started = Instant::now();
+    let mut process = match ExtensionProcess::spawn(
+        config.clone(),
+        data_dir,
+        hermes_version,
+        API_VERSION,
+    )
+    .await
+    {
+        Ok(process) => process,
+        Err(e) => {
+            return ConformanceCheck::new(
+                CHECK_MALFORMED_PARAMS,
+                ConformanceStatus::Fail,
+                format!("failed to start extension: {e}"),
+                started,
+            );
+        }
+    };
+
+    // every field has the wrong type
+    let params = serde_json::json!({
+        "hermesVersion": 42,
+        "apiVersion": [],
+        "dataDirectory": false,
+    });
+    let outcome = probe(&mut process, "initialize", params).await;
+    process.kill().await;
+
+    let (status, message) = if matches!(outcome, ProbeOutcome::Success) {
+        (
+            ConformanceStatus::Fail,
+            "accepted initialize params with invalid types".to_string(),
+        )
+    } else {
+        classify_expected_error(&outcome, error_codes::INVALID_PARAMS)
+    };
+    ConformanceCheck::new(CHECK_MALFORMED_PARAMS, status, message, started)
+}
+
+/// Send a request to the extension, bounded by [`PROBE_TIMEOUT`].
+async fn probe(
+    process: &mut ExtensionProcess,
+    method: &str,
+    params: serde_json::Value,
+) -> ProbeOutcome {
+    match timeout(PROBE_TIMEOUT, process.send_request(method, params)).await {
+        Ok(Ok(_)) => ProbeOutcome::Success,
+        Ok(Err(ExtensionError::Rpc(error))) => ProbeOutcome::Rejected {
+            code: error.code,
+            message: error.message,
+        },
+        Ok(Err(e)) => ProbeOutcome::Failed(e.to_string()),
+        Err(_) => ProbeOutcome::TimedOut,
+    }
+}
+
+/// Classify the outcome of a probe that should have been rejected.
+///
+/// Rejecting with the expected code passes; rejecting with a different code is
+/// only a warning, since Hermes treats any error response as a rejection.
+/// Answering successfully, not answering at all, or losing the connection fail.
+///
+/// # Arguments
+/// * `outcome` - Outcome of the probe
+/// * `expected_code` - JSON-RPC error code the protocol requires
+///
+/// # Returns
+/// The status of the check and a message explaining it
+fn classify_expected_error(
+    outcome: &ProbeOutcome,
+    expected_code: i32,
+) -> (ConformanceStatus, String) {
+    match outcome {
+        ProbeOutcome::Rejected { code, .. } if *code == expected_code => (
+            ConformanceStatus::Pass,
+            format!("rejected with error {code}"),
+        ),
+        ProbeOutcome::Rejected { code, message } => (
+            ConformanceStatus::Warn,
+            format!("rejected with error {code} ({message}), expected {expected_code}"),
+        ),
+        ProbeOutcome::Success => (
+            ConformanceStatus::Fail,
+            format!("answered successfully, expected error {expected_code}"),
+        ),
+        ProbeOutcome::Failed(e) => (ConformanceStatus::Fail, format!("request failed: {e}")),
+        ProbeOutcome::TimedOut => (
+            ConformanceStatus::Fail,
+            format!("no response within {}s", PROBE_TIMEOUT.as_secs()),
+        ),
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_passes_on_expected_code() {
+        let outcome = ProbeOutcome::Rejected {
+            code: error_codes::METHOD_NOT_FOUND,
+            message: "method not found".to_string(),
+        };
+        let (status, _) = classify_expected_error(&outcome, error_codes::METHOD_NOT_FOUND);
+        assert_eq!(status, ConformanceStatus::Pass);
+    }
+
+    #[test]
+    fn classify_warns_on_other_code() {
+        let outcome = ProbeOutcome::Rejected {
+            code: error_codes::INTERNAL_ERROR,
+            message: "boom".to_string(),
+        };
+        let (status, message) = classify_expected_error(&outcome, error_codes::INVALID_PARAMS);
+        assert_eq!(status, ConformanceStatus::Warn);
+        assert!(message.contains("-32602"));
+    }
+
+    #[test]
+    fn classify_fails_on_success_and_timeout() {
+        let (status, _) =
+            classify_expected_error(&ProbeOutcome::Success, error_codes::ALREADY_INITIALIZED);
+        assert_eq!(status, ConformanceStatus::Fail);
+
+        let (status, _) =
+            classify_expected_error(&ProbeOutcome::TimedOut, error_codes::ALREADY_INITIALIZED);
+        assert_eq!(status, ConformanceStatus::Fail);
+    }
+
+    #[test]
+    fn report_passes_with_warnings_but_not_failures() {
+        let started = Instant::now();
+
+        let mut report = ConformanceReport::new("ext");
+        report.push(ConformanceCheck::new(
+            "a",
+            ConformanceStatus::Pass,
+            "",
+            started,
+        ));
+        report.push(ConformanceCheck::new(
+            "b",
+            ConformanceStatus::Warn,
+            "",
+            started,
+        ));
+        report.skip(&["c"]);
+        assert!(report.finish().passed);
+
+        let mut report = ConformanceReport::new("ext");
+        report.push(ConformanceCheck::new(
+            "a",
+            ConformanceStatus::Pass,
+            "",
+            started,
+        ));
+        report.push(ConformanceCheck::new(
+            "b",
+            ConformanceStatus::Fail,
+            "",
+            started,
+        ));
+        assert!(!report.finish().passed);
+    }
+
+    #[tokio::test]
+    async fn missing_executable_fails_spawn_and_skips_the_rest() {
+        let dir = std::env::temp_dir();
+        let report =
+            run_conformance_suite("/nonexistent/hermes-conformance-test", &dir, "0.0.0").await;
+
+        assert!(!report.passed);
+        assert_eq!(report.checks[0].name, CHECK_SPAWN);
+        assert_eq!(report.checks[0].status, ConformanceStatus::Fail);
+        assert!(report.checks[1..]
+            .iter()
+            .all(|check| check.status == ConformanceStatus::Skipped));
+    }
+}
@@ -0,0 +1,159 @@
+//! Connections between Hermes and an extension process.
+//!
+//! Extensions speak JSON-RPC over one of three transports, chosen in their
+//! configuration:
+//!
+//! * **stdio** - Content-Length framed messages over stdin and stdout (the
+//!   default)
+//! * **tcp** - The same framing over a TCP connection the extension makes to
+//!   Hermes
+//! * **websocket** - One message per text frame over a WebSocket connection the
+//!   extension makes to Hermes
+//!
+//! For the socket transports, Hermes listens on a loopback port before starting
+//! the extension and passes the port in the `HERMES_PORT` environment variable.
+//! The extension has `CONNECT_TIMEOUT` to connect; only the first connection is
+//! accepted.
+//!
+//! Every transport is turned into a stream of incoming messages and a sink of
+//! outgoing ones, so the rest of the extension process is the same for all.
+
+use crate::extensions::protocol::{read_message, write_message, Message, ProtocolError};
+use crate::extensions::types::ExtensionTransport;
+use futures::{Sink, SinkExt, Stream, StreamExt};
+use std::net::{Ipv4Addr, SocketAddr};
+use std::pin::Pin;
+use tokio::io::{AsyncBufRead, AsyncWrite, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+/// How long an extension has to connect to Hermes after it's started.
+pub const CONNECT_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Messages received from an extension.
+pub type MessageStream = Pin<Box<dyn Stream<Item = Result<Message, ProtocolError>> + Send>>;
+
+/// Messages sent to an extension.
+pub type MessageSink = Pin<Box<dyn Sink<Message, Error = ProtocolError> + Send>>;
+
+/// Messages framed with Content-Length headers, as over stdio and TCP.
+pub fn framed<R, W>(reader: R, writer: W) -> (MessageStream, MessageSink)
+where
+    R: AsyncBufRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin + Send + 'static,
+{
+    let stream = futures::stream::unfold(reader, |mut reader| async move {
+        let message = read_message(&mut reader).await;
+        Some((message, reader))
+    });
+    let sink = futures::sink::unfold(writer, |mut writer, message: Message| async move {
+        write_message(&mut writer, &message).await?;
+        Ok::<_, ProtocolError>(writer)
+    });
+    (Box::pin(stream), Box::pin(sink))
+}
+
+/// Bind a loopback port for an extension to connect to.
+///
+/// # Arguments
+/// * `port` - Port to listen on, or None for any free port
+///
+/// # Returns
+/// The listener, and the port it's listening on
+pub async fn bind(port: Option<u16>) -> std::io::Result<(TcpListener, u16)> {
+    let listener =
+        TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, port.unwrap_or(0)))).await?;
+    let port = listener.local_addr()?.port();
+    Ok((listener, port))
+}
+
+/// Accept an extension's connection over a socket transport.
+///
+/// # Arguments
+/// * `listener` - Listener returned by [`bind`]
+/// * `transport` - Transport the extension was configured with
+///
+/// # Returns
+/// * `Ok(...)` - The extension's messages, and a sink to send it messages
+/// * `Err(ProtocolError)` - If the connection or WebSocket handshake fails
+pub async fn accept(
+    listener: TcpListener,
+    transport: ExtensionTransport,
+) -> Result<(MessageStream, MessageSink), ProtocolError> {
+    let (stream, peer) = listener.accept().await?;
+    log::debug!("extension connected from {peer} over {transport}");
+    // the listener is dropped here, so later connections are refused
+
+    match transport {
+        ExtensionTransport::Tcp => {
+            let (reader, writer) = stream.into_split();
+            Ok(framed(BufReader::new(reader), writer))
+        }
+        ExtensionTransport::WebSocket => websocket(stream).await,
+        ExtensionTransport::Stdio => Err(ProtocolError::Io(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "stdio extensions don't connect over a socket",
+        ))),
+    }
+}
+
+/// Messages over a WebSocket, one per text frame.
+async fn websocket(stream: TcpStream) -> Result<(MessageStream, MessageSink), ProtocolError> {
+    let socket = tokio_tungstenite::accept_async(stream)
+        .await
+        .map_err(|e| ProtocolError::Io(std::io::Error::other(e)))?;
+    let (sink, stream) = socket.split();
+
+    let stream = stream.filter_map(|frame| async move {
+        match frame {
+            Ok(WsMessage::Text(text)) => {
+                Some(serde_json::from_str(text.as_str()).map_err(ProtocolError::from))
+            }
+            Ok(WsMessage::Binary(bytes)) => {
+                Some(serde_json::from_slice(&bytes).map_err(ProtocolError::from))
+            }
+            Ok(WsMessage::Close(_)) => Some(Err(ProtocolError::Eof)),
+            Ok(WsMessage::Ping(_) | WsMessage::Pong(_) | WsMessage::Frame(_)) => None,
+            Err(e) => Some(Err(ProtocolError::Io(std::io::Error::other(e)))),
+        }
+    });
+    let sink = sink
+        .sink_map_err(|e| ProtocolError::Io(std::io::Error::other(e)))
+        .with(|message: Message| async move {
+            let json = serde_json::to_string(&message)?;
+            Ok::<_, ProtocolError>(WsMessage::text(json))
+        });
+    Ok((Box::pin(stream), Box::pin(sink)))
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use crate::extensions::protocol::Notification;
+    use tokio::io::AsyncWriteExt;
+
+    #[tokio::test]
+    async fn test_tcp_connection() {
+        let (listener, port) = bind(None).await.unwrap();
+        let extension = tokio::spawn(async move {
+            let mut stream = TcpStream::connect((Ipv4Addr::LOCALHOST, port))
+                .await
+                .unwrap();
+            let body = r#"{"jsonrpc":"2.0","method":"ping"}"#;
+            let frame = format!("Content-Length: {}\r\n\r\n{body}", body.len());
+            stream.write_all(frame.as_bytes()).await.unwrap();
+            stream
+        });
+
+        let (mut incoming, mut outgoing) = accept(listener, ExtensionTransport::Tcp).await.unwrap();
+        let message = incoming.next().await.unwrap().unwrap();
+        assert!(matches!(message, Message::Notification(n) if n.method == "ping"));
+
+        let _stream = extension.await.unwrap();
+        outgoing
+            .send(Message::Notification(Notification::new("pong", None)))
+            .await
+            .unwrap();
+    }
+}
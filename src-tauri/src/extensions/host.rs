@@ -3,35 +3,42 @@
 //! The host is responsible for:
 //! - Starting and stopping extensions based on configuration
 //! - Routing commands to the appropriate extension
-//! - Aggregating toolbar buttons from all extensions
-//! - Handling requests from extensions (editor/*, ui/*)
+//! - Aggregating toolbar buttons and templates from all extensions
+//! - Handling requests from extensions (editor/*, ui/*) and `log/message`
+//!   notifications
 //! - Sending event notifications to subscribed extensions
+//! - Forwarding the protocol traces of extensions in development mode
 
 use crate::commands::extensions::editor::{
     handle_get_message, handle_patch_message, handle_set_message,
 };
 use crate::commands::extensions::ui::{
     close_extension_windows, handle_close_window, handle_open_file, handle_open_files,
-    handle_open_window, handle_save_file, handle_select_directory, handle_show_confirm,
-    handle_show_message, SharedWindowManager,
+    handle_open_window, handle_progress, handle_save_file, handle_select_directory,
+    handle_set_status_bar_item, handle_show_confirm, handle_show_message, SharedWindowManager,
 };
+use crate::commands::{Document, Documents, SegmentChange};
+use crate::events;
 use crate::extensions::process::{
-    ExtensionError, ExtensionProcess, InternalMessage, ResponseSender,
+    ExtensionError, ExtensionProcess, InternalMessage, LogBuffer, RequestSender, ResponseSender,
 };
 use crate::extensions::protocol::{ErrorResponse, Request, Response, RpcError};
 use crate::extensions::types::{
-    CloseWindowParams, CommandExecuteParams, EventName, ExtensionConfig, ExtensionState,
-    GetMessageParams, MessageChangedOptions, MessageChangedParams, MessageFormat,
-    MessageOpenedParams, MessageSavedParams, OpenFileParams, OpenFilesParams, OpenWindowParams,
-    PatchMessageParams, SaveFileParams, SchemaOverride, SelectDirectoryParams, SetMessageParams,
-    ShowConfirmParams, ShowMessageParams, ShutdownReason, ToolbarButton,
+    AckReceivedParams, CloseWindowParams, CommandExecuteParams, ConfigurationChangedParams,
+    EventName, ExtensionConfig, ExtensionLogPage, ExtensionState, GetMessageParams, LogLevel,
+    LogMessageParams, MessageChangedOptions, MessageChangedParams, MessageFormat,
+    MessageOpenedParams, MessageReceivedParams, MessageSavedParams, OpenFileParams,
+    OpenFilesParams, OpenWindowParams, PatchMessageParams, ProgressParams, SaveFileParams,
+    SchemaOverride, SelectDirectoryParams, SetMessageParams, SetStatusBarItemParams,
+    SettingDefinition, ShowConfirmParams, ShowMessageParams, ShutdownReason, ToolbarButton,
+    TraceEntry,
 };
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 use tauri::{AppHandle, Emitter, Manager};
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::{broadcast, mpsc, Mutex};
 use tokio::task::JoinHandle;
 
 /// Current extension API version.
@@ -47,6 +54,41 @@ pub struct ToolbarButtonInfo {
     pub button: ToolbarButton,
 }
 
+/// Transport with extension ownership information.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TransportInfo {
+    /// ID of the extension that provides this transport.
+    #[serde(rename = "extensionId")]
+    pub extension_id: String,
+    /// Transport name that presets refer to.
+    pub name: String,
+    /// Human-readable name, falling back to the transport name.
+    pub label: String,
+}
+
+/// Template with extension ownership information.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct TemplateInfo {
+    /// ID of the extension that generates this template.
+    #[serde(rename = "extensionId")]
+    pub extension_id: String,
+    /// Name shown in the menu.
+    pub name: String,
+    /// Command passed back in the `template/generate` request.
+    pub command: String,
+}
+
+/// A message traced from an extension in development mode.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ExtensionTraceEvent {
+    /// ID of the extension the message was exchanged with.
+    #[serde(rename = "extensionId")]
+    pub extension_id: String,
+    /// The traced message.
+    #[serde(flatten)]
+    pub entry: TraceEntry,
+}
+
 /// Status information for an extension.
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct ExtensionStatus {
@@ -63,6 +105,9 @@ pub struct ExtensionStatus {
     /// Error message if in failed state.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// Settings the extension declares.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub settings: Vec<SettingDefinition>,
 }
 
 /// Manages multiple extension processes.
@@ -96,6 +141,12 @@ pub struct ExtensionHost {
 
     /// Handle for the debounced message/changed notification timer.
     message_changed_timer: Option<JoinHandle<()>>,
+
+    /// Segment changes made since the last message/changed notification.
+    ///
+    /// `None` once any other kind of edit has been made, since the changes
+    /// then no longer describe the message fully.
+    pending_segment_changes: Option<Vec<SegmentChange>>,
 }
 
 impl ExtensionHost {
@@ -110,6 +161,7 @@ impl ExtensionHost {
             merged_schema: None,
             request_handler_tasks: HashMap::new(),
             message_changed_timer: None,
+            pending_segment_changes: Some(Vec::new()),
         }
     }
 
@@ -167,6 +219,12 @@ impl ExtensionHost {
 
         let ext_id = process.id.clone();
 
+        // forward the trace from the start, so the handshake is included
+        if let Some(trace) = process.subscribe_trace() {
+            log::info!("extension {ext_id} is in development mode; tracing its messages");
+            Self::spawn_trace_forwarder_task(ext_id.clone(), trace, self.app_handle.clone());
+        }
+
         // insert early so we can emit status updates
         self.extensions.insert(ext_id.clone(), process);
 
@@ -192,17 +250,18 @@ impl ExtensionHost {
                 if let (Some(incoming_rx), Some(response_sender)) =
                     (ext.take_incoming_rx(), ext.response_sender())
                 {
-                    // get editor_message from app data
+                    // extension requests act on the documents open in the editor
                     let state = self.app_handle.state::<crate::AppData>();
-                    let editor_message = state.editor_message.clone();
+                    let documents = state.documents.clone();
 
                     let task = Self::spawn_request_handler_task(
                         ext_id.clone(),
                         incoming_rx,
                         response_sender,
+                        ext.log_buffer(),
                         self.app_handle.clone(),
                         window_manager.clone(),
-                        editor_message,
+                        documents,
                     );
                     self.request_handler_tasks.insert(ext_id.clone(), task);
                     log::debug!("spawned request handler task for {ext_id}");
@@ -319,6 +378,24 @@ impl ExtensionHost {
         self.merged_schema.as_ref()
     }
 
+    /// Get the data directory handed to extensions.
+    pub fn extension_data_dir(&self) -> PathBuf {
+        self.data_dir.join("extensions")
+    }
+
+    /// Change the base data directory.
+    ///
+    /// Running extensions keep the directory they were started with; the new
+    /// one is handed to extensions started after this, e.g. by `reload()`.
+    pub fn set_data_dir(&mut self, data_dir: PathBuf) {
+        self.data_dir = data_dir;
+    }
+
+    /// Get the Hermes version reported to extensions.
+    pub fn hermes_version(&self) -> &str {
+        &self.hermes_version
+    }
+
     /// Get status information for all extensions.
     pub async fn get_extension_statuses(&self) -> Vec<ExtensionStatus> {
         let mut statuses = Vec::new();
@@ -327,10 +404,14 @@ impl ExtensionHost {
             let state = ext.state().await;
             let metadata = ext.metadata().await;
 
-            let (name, version) = if let Some(meta) = &metadata {
-                (meta.name.clone(), meta.version.clone())
+            let (name, version, settings) = if let Some(meta) = &metadata {
+                (
+                    meta.name.clone(),
+                    meta.version.clone(),
+                    meta.settings.clone(),
+                )
             } else {
-                ("Unknown".to_string(), "0.0.0".to_string())
+                ("Unknown".to_string(), "0.0.0".to_string(), Vec::new())
             };
 
             let error = if let ExtensionState::Failed(msg) = &state {
@@ -346,24 +427,214 @@ impl ExtensionHost {
                 version,
                 state,
                 error,
+                settings,
             });
         }
 
         statuses
     }
 
-    /// Get log entries for a specific extension.
+    /// Get a page of log entries for a specific extension, newest first.
+    ///
+    /// # Arguments
+    /// * `ext_id` - ID of the extension
+    /// * `level` - Least severe level to include, or None for every level
+    /// * `offset` - Number of the newest matching entries to skip
+    /// * `limit` - Most entries to return, or None for all of them
     pub async fn get_extension_logs(
         &self,
         ext_id: &str,
-    ) -> Option<Vec<crate::extensions::types::ExtensionLog>> {
+        level: Option<LogLevel>,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> Option<ExtensionLogPage> {
         if let Some(ext) = self.extensions.get(ext_id) {
-            Some(ext.get_logs().await)
+            Some(ext.get_log_page(level, offset, limit).await)
         } else {
             None
         }
     }
 
+    /// Get the protocol trace of an extension in development mode.
+    ///
+    /// # Returns
+    /// * `Ok(Vec<TraceEntry>)` - The messages recorded, oldest first
+    /// * `Err(String)` - If the extension isn't found or isn't in development mode
+    pub async fn get_extension_trace(&self, ext_id: &str) -> Result<Vec<TraceEntry>, String> {
+        let ext = self
+            .extensions
+            .get(ext_id)
+            .ok_or_else(|| format!("extension not found: {ext_id}"))?;
+        ext.get_trace()
+            .await
+            .ok_or_else(|| format!("extension {ext_id} is not in development mode"))
+    }
+
+    /// Change the values of an extension's settings.
+    ///
+    /// The values are checked against the settings the extension declared,
+    /// kept for when the extension is next started, and sent to it in a
+    /// `configuration/changed` notification.
+    ///
+    /// # Arguments
+    /// * `ext_id` - ID of the extension
+    /// * `values` - Every value set, by key; a null value clears the setting
+    ///
+    /// # Returns
+    /// The values as stored, for saving with the extension's configuration
+    pub async fn update_settings(
+        &mut self,
+        ext_id: &str,
+        values: serde_json::Map<String, serde_json::Value>,
+    ) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+        let ext = self
+            .extensions
+            .get_mut(ext_id)
+            .ok_or_else(|| format!("extension not found: {ext_id}"))?;
+        let schema = ext
+            .metadata()
+            .await
+            .map(|metadata| metadata.settings)
+            .unwrap_or_default();
+        let values = checked_settings(&schema, values)?;
+        ext.config.settings.clone_from(&values);
+
+        if ext.state().await.is_running() {
+            let params = ConfigurationChangedParams {
+                settings: values.clone(),
+            };
+            let params_value = serde_json::to_value(&params)
+                .map_err(|e| format!("failed to serialize configuration/changed: {e}"))?;
+            if let Err(e) = ext
+                .send_notification("configuration/changed", params_value)
+                .await
+            {
+                log::debug!("failed to send configuration/changed to {ext_id}: {e}");
+            }
+        }
+
+        Ok(values)
+    }
+
+    /// Get the transports provided by running extensions.
+    pub async fn get_transports(&self) -> Vec<TransportInfo> {
+        let mut transports = Vec::new();
+
+        for (ext_id, ext) in &self.extensions {
+            if ext.state().await != ExtensionState::Running {
+                continue;
+            }
+            if let Some(metadata) = ext.metadata().await {
+                for transport in metadata.capabilities.transports {
+                    transports.push(TransportInfo {
+                        extension_id: ext_id.clone(),
+                        label: transport.label.unwrap_or_else(|| transport.name.clone()),
+                        name: transport.name,
+                    });
+                }
+            }
+        }
+
+        transports.sort_by(|a, b| a.label.cmp(&b.label));
+        transports
+    }
+
+    /// Get a request sender for the running extension providing a transport.
+    ///
+    /// The sender can be used after the host lock is released, so a slow
+    /// `transport/send` doesn't block other extension traffic.
+    pub async fn transport_sender(&self, transport: &str) -> Option<RequestSender> {
+        for ext in self.extensions.values() {
+            if ext.state().await != ExtensionState::Running {
+                continue;
+            }
+            let Some(metadata) = ext.metadata().await else {
+                continue;
+            };
+            if metadata
+                .capabilities
+                .transports
+                .iter()
+                .any(|provider| provider.name == transport)
+            {
+                return ext.request_sender();
+            }
+        }
+        None
+    }
+
+    /// Get the templates contributed by running extensions, sorted by name.
+    pub async fn get_templates(&self) -> Vec<TemplateInfo> {
+        let mut templates = Vec::new();
+
+        for (ext_id, ext) in &self.extensions {
+            if ext.state().await != ExtensionState::Running {
+                continue;
+            }
+            if let Some(metadata) = ext.metadata().await {
+                for template in metadata.templates {
+                    templates.push(TemplateInfo {
+                        extension_id: ext_id.clone(),
+                        name: template.name,
+                        command: template.command,
+                    });
+                }
+            }
+        }
+
+        templates.sort_by(|a, b| a.name.cmp(&b.name));
+        templates
+    }
+
+    /// Get a request sender for a running extension, if it contributes a
+    /// template with the given command.
+    ///
+    /// The sender can be used after the host lock is released, so an extension
+    /// slow to generate a message doesn't block other extension traffic.
+    pub async fn template_sender(&self, ext_id: &str, command: &str) -> Option<RequestSender> {
+        let ext = self.extensions.get(ext_id)?;
+        if ext.state().await != ExtensionState::Running {
+            return None;
+        }
+        let metadata = ext.metadata().await?;
+        if metadata
+            .templates
+            .iter()
+            .any(|template| template.command == command)
+        {
+            ext.request_sender()
+        } else {
+            None
+        }
+    }
+
+    /// Get request senders for the running extensions that provide validation,
+    /// with each extension's name.
+    ///
+    /// The senders can be used after the host lock is released, so a slow
+    /// provider doesn't block other extension traffic.
+    pub async fn validation_providers(&self) -> Vec<(String, RequestSender)> {
+        let mut providers = Vec::new();
+
+        for ext in self.extensions.values() {
+            if ext.state().await != ExtensionState::Running {
+                continue;
+            }
+            let Some(metadata) = ext.metadata().await else {
+                continue;
+            };
+            if !metadata.capabilities.validation_provider {
+                continue;
+            }
+            if let Some(sender) = ext.request_sender() {
+                providers.push((metadata.name, sender));
+            }
+        }
+
+        providers.sort_by(|a, b| a.0.cmp(&b.0));
+        providers
+    }
+
     /// Find the extension that registered a given command.
     async fn find_extension_for_command(&self, command: &str) -> Option<String> {
         for (ext_id, ext) in &self.extensions {
@@ -430,7 +701,7 @@ impl ExtensionHost {
 
     /// Emit an event to notify the frontend that extensions changed.
     fn emit_extensions_changed(&self) {
-        if let Err(e) = self.app_handle.emit("extensions-changed", ()) {
+        if let Err(e) = self.app_handle.emit(events::EXTENSIONS_CHANGED, ()) {
             log::warn!("failed to emit extensions-changed event: {e}");
         }
     }
@@ -441,10 +712,14 @@ impl ExtensionHost {
             let state = ext.state().await;
             let metadata = ext.metadata().await;
 
-            let (name, version) = if let Some(meta) = &metadata {
-                (meta.name.clone(), meta.version.clone())
+            let (name, version, settings) = if let Some(meta) = &metadata {
+                (
+                    meta.name.clone(),
+                    meta.version.clone(),
+                    meta.settings.clone(),
+                )
             } else {
-                ("Unknown".to_string(), "0.0.0".to_string())
+                ("Unknown".to_string(), "0.0.0".to_string(), Vec::new())
             };
 
             let error = if let ExtensionState::Failed(msg) = &state {
@@ -460,9 +735,13 @@ impl ExtensionHost {
                 version,
                 state,
                 error,
+                settings,
             };
 
-            if let Err(e) = self.app_handle.emit("extension-status-changed", status) {
+            if let Err(e) = self
+                .app_handle
+                .emit(events::EXTENSION_STATUS_CHANGED, status)
+            {
                 log::warn!("failed to emit extension-status-changed event: {e}");
             }
         }
@@ -477,7 +756,23 @@ impl ExtensionHost {
     /// Cancels any pending timer and starts a new 500ms timer. When the timer
     /// fires, `send_message_changed_notifications` is called to notify all
     /// subscribed extensions.
-    pub fn schedule_message_changed_notification(&mut self) {
+    ///
+    /// # Arguments
+    /// * `segment_changes` - Segments changed by the edit, if it was a segment
+    ///   operation; `None` for any other edit
+    pub fn schedule_message_changed_notification(
+        &mut self,
+        segment_changes: Option<Vec<SegmentChange>>,
+    ) {
+        match segment_changes {
+            Some(changes) => {
+                if let Some(pending) = &mut self.pending_segment_changes {
+                    pending.extend(changes);
+                }
+            }
+            None => self.pending_segment_changes = None,
+        }
+
         // cancel existing timer
         if let Some(handle) = self.message_changed_timer.take() {
             handle.abort();
@@ -501,8 +796,14 @@ impl ExtensionHost {
     async fn send_message_changed_notifications(&mut self) {
         // get current editor state
         let state = self.app_handle.state::<crate::AppData>();
-        let message = state.editor_message.lock().await.clone();
-        let file_path = state.editor_file_path.lock().await.clone();
+        let Some(Document { message, file_path }) = state.documents.lock().await.active().cloned()
+        else {
+            return;
+        };
+        let segment_changes = self
+            .pending_segment_changes
+            .replace(Vec::new())
+            .filter(|changes| !changes.is_empty());
 
         for (ext_id, ext) in self.extensions.iter_mut() {
             if !ext.state().await.is_running() {
@@ -515,6 +816,7 @@ impl ExtensionHost {
                     &message,
                     file_path.as_deref(),
                     subscription.options.as_ref(),
+                    segment_changes.clone(),
                 );
 
                 if let Ok(params_value) = serde_json::to_value(&params) {
@@ -531,6 +833,9 @@ impl ExtensionHost {
 
     /// Send `message/opened` notification to all subscribed extensions.
     pub async fn notify_message_opened(&mut self, file_path: Option<&str>, is_new: bool) {
+        // segment changes to the previous message don't apply to this one
+        self.pending_segment_changes = None;
+
         for (ext_id, ext) in self.extensions.iter_mut() {
             if !ext.state().await.is_running() {
                 continue;
@@ -581,17 +886,115 @@ impl ExtensionHost {
         }
     }
 
+    /// Get request senders for the running extensions subscribed to
+    /// `message/beforeSend`, with each extension's name, sorted by name.
+    ///
+    /// The senders can be used after the host lock is released, so an
+    /// extension slow to answer doesn't block other extension traffic.
+    pub async fn before_send_hooks(&self) -> Vec<(String, RequestSender)> {
+        let mut hooks = Vec::new();
+
+        for ext in self.extensions.values() {
+            if !ext.state().await.is_running() {
+                continue;
+            }
+            if ext
+                .get_event_subscription(EventName::MessageBeforeSend)
+                .await
+                .is_none()
+            {
+                continue;
+            }
+            if let (Some(metadata), Some(sender)) = (ext.metadata().await, ext.request_sender()) {
+                hooks.push((metadata.name, sender));
+            }
+        }
+
+        hooks.sort_by(|a, b| a.0.cmp(&b.0));
+        hooks
+    }
+
+    /// Send `message/ackReceived` notification to all subscribed extensions.
+    ///
+    /// # Arguments
+    /// * `response` - The response received, included for subscribers that
+    ///   asked for content
+    /// * `params` - The notification, without content
+    pub async fn notify_ack_received(&mut self, response: &str, params: AckReceivedParams) {
+        for (ext_id, ext) in self.extensions.iter_mut() {
+            if !ext.state().await.is_running() {
+                continue;
+            }
+
+            if let Some(subscription) = ext
+                .get_event_subscription(EventName::MessageAckReceived)
+                .await
+            {
+                let (message, format) = message_content(response, subscription.options.as_ref());
+                let params = AckReceivedParams {
+                    message,
+                    format,
+                    ..params.clone()
+                };
+
+                if let Ok(params_value) = serde_json::to_value(&params) {
+                    if let Err(e) = ext
+                        .send_notification("message/ackReceived", params_value)
+                        .await
+                    {
+                        log::debug!("failed to send message/ackReceived to {ext_id}: {e}");
+                    }
+                }
+            }
+        }
+    }
+
+    /// Send `message/received` notification to all subscribed extensions.
+    ///
+    /// # Arguments
+    /// * `received` - The message received, included for subscribers that
+    ///   asked for content
+    /// * `params` - The notification, without content
+    pub async fn notify_message_received(&mut self, received: &str, params: MessageReceivedParams) {
+        for (ext_id, ext) in self.extensions.iter_mut() {
+            if !ext.state().await.is_running() {
+                continue;
+            }
+
+            if let Some(subscription) = ext.get_event_subscription(EventName::MessageReceived).await
+            {
+                let (message, format) = message_content(received, subscription.options.as_ref());
+                let params = MessageReceivedParams {
+                    message,
+                    format,
+                    ..params.clone()
+                };
+
+                if let Ok(params_value) = serde_json::to_value(&params) {
+                    if let Err(e) = ext
+                        .send_notification("message/received", params_value)
+                        .await
+                    {
+                        log::debug!("failed to send message/received to {ext_id}: {e}");
+                    }
+                }
+            }
+        }
+    }
+
     /// Spawn a background task that handles incoming requests from an extension.
     ///
     /// Consumes from the extension's `incoming_rx` channel and routes requests
     /// to the appropriate handlers. Responses are sent back via `ResponseSender`.
+    /// Entries sent in `log/message` notifications are added to `logs`.
     fn spawn_request_handler_task(
         ext_id: String,
         mut incoming_rx: mpsc::Receiver<InternalMessage>,
         response_sender: ResponseSender,
+        logs: LogBuffer,
         app_handle: AppHandle,
         window_manager: SharedWindowManager,
-        editor_message: Arc<Mutex<String>>,
+        documents: Arc<Mutex<Documents>>,
     ) -> JoinHandle<()> {
         tokio::spawn(async move {
             while let Some(msg) = incoming_rx.recv().await {
@@ -605,7 +1008,7 @@ impl ExtensionHost {
                             request,
                             &app_handle,
                             &window_manager,
-                            &editor_message,
+                            &documents,
                         )
                         .await;
 
@@ -636,11 +1039,18 @@ impl ExtensionHost {
                         }
                     }
                     InternalMessage::Notification(notification) => {
-                        // notifications don't need responses - just log for now
-                        log::debug!(
-                            "received notification from {ext_id}: {}",
-                            notification.method
-                        );
+                        if notification.method != "log/message" {
+                            log::debug!(
+                                "received notification from {ext_id}: {}",
+                                notification.method
+                            );
+                            continue;
+                        }
+                        let params = notification.params.unwrap_or_default();
+                        match serde_json::from_value::<LogMessageParams>(params) {
+                            Ok(params) => logs.push(params.level, params.message).await,
+                            Err(e) => log::warn!("invalid log/message from {ext_id}: {e}"),
+                        }
                     }
                     InternalMessage::ReaderError(e) => {
                         log::warn!("reader error for extension {ext_id}: {e}");
@@ -654,6 +1064,38 @@ impl ExtensionHost {
             log::debug!("request handler task for {ext_id} ended");
         })
     }
+
+    /// Spawn a background task that emits an extension's traced messages to the
+    /// frontend as they're recorded.
+    ///
+    /// The task ends by itself once the extension process is dropped, closing
+    /// the trace.
+    fn spawn_trace_forwarder_task(
+        ext_id: String,
+        mut trace: broadcast::Receiver<TraceEntry>,
+        app_handle: AppHandle,
+    ) {
+        tokio::spawn(async move {
+            loop {
+                let entry = match trace.recv().await {
+                    Ok(entry) => entry,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        log::debug!("trace forwarder for {ext_id} skipped {skipped} messages");
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                let event = ExtensionTraceEvent {
+                    extension_id: ext_id.clone(),
+                    entry,
+                };
+                if let Err(e) = app_handle.emit(events::EXTENSION_TRACE, event) {
+                    log::warn!("failed to emit extension-trace event: {e}");
+                }
+            }
+            log::debug!("trace forwarder for {ext_id} ended");
+        });
+    }
 }
 
 impl Drop for ExtensionHost {
@@ -674,7 +1116,7 @@ async fn handle_extension_request_standalone(
     request: Request,
     app_handle: &AppHandle,
     window_manager: &SharedWindowManager,
-    editor_message: &Arc<Mutex<String>>,
+    documents: &Arc<Mutex<Documents>>,
 ) -> Result<Option<Response>, RpcError> {
     log::debug!("handling request from {ext_id}: {}", request.method);
 
@@ -686,8 +1128,12 @@ async fn handle_extension_request_standalone(
             let params: GetMessageParams = serde_json::from_value(params_value)
                 .map_err(|e| RpcError::invalid_params(format!("invalid params: {e}")))?;
 
-            let editor_msg = editor_message.lock().await;
-            let result = handle_get_message(&editor_msg, params.format)
+            let documents = documents.lock().await;
+            let editor_msg = documents
+                .active()
+                .map(|document| document.message.as_str())
+                .unwrap_or_default();
+            let result = handle_get_message(editor_msg, params.format)
                 .map_err(|e| RpcError::internal(e.to_string()))?;
 
             Ok(Some(Response::new(
@@ -705,11 +1151,12 @@ async fn handle_extension_request_standalone(
             let (hl7_message, result) = handle_set_message(params)?;
 
             if result.success {
-                let mut editor_msg = editor_message.lock().await;
-                *editor_msg = hl7_message.clone();
+                if let Some(document) = documents.lock().await.active_mut() {
+                    document.message.clone_from(&hl7_message);
+                }
                 // emit event to frontend with the converted HL7 message
                 app_handle
-                    .emit("extension-set-message", &hl7_message)
+                    .emit(events::EXTENSION_SET_MESSAGE, &hl7_message)
                     .map_err(|e| RpcError::internal(format!("failed to emit event: {e}")))?;
             }
 
@@ -725,14 +1172,17 @@ async fn handle_extension_request_standalone(
             let params: PatchMessageParams = serde_json::from_value(params_value)
                 .map_err(|e| RpcError::invalid_params(format!("invalid params: {e}")))?;
 
-            let mut editor_msg = editor_message.lock().await;
-            let (new_message, result) = handle_patch_message(&editor_msg, params.patches);
+            let mut documents = documents.lock().await;
+            let Some(document) = documents.active_mut() else {
+                return Err(RpcError::internal("no message is open in the editor"));
+            };
+            let (new_message, result) = handle_patch_message(&document.message, params.patches);
 
             // update if any patches were applied (even partial success)
             if result.patches_applied > 0 {
-                *editor_msg = new_message.clone();
+                document.message.clone_from(&new_message);
                 // notify frontend of the new message
-                let _ = app_handle.emit("extension-set-message", &new_message);
+                let _ = app_handle.emit(events::EXTENSION_SET_MESSAGE, &new_message);
             }
 
             Ok(Some(Response::new(
@@ -844,6 +1294,32 @@ async fn handle_extension_request_standalone(
                 serde_json::to_value(result).expect("can serialize result"),
             )))
         }
+        "ui/setStatusBarItem" => {
+            let params_value = request
+                .params
+                .ok_or_else(|| RpcError::invalid_params("missing params"))?;
+            let params: SetStatusBarItemParams = serde_json::from_value(params_value)
+                .map_err(|e| RpcError::invalid_params(format!("invalid params: {e}")))?;
+
+            let result = handle_set_status_bar_item(app_handle, ext_id, params)?;
+            Ok(Some(Response::new(
+                request.id,
+                serde_json::to_value(result).expect("can serialize result"),
+            )))
+        }
+        "ui/progress" => {
+            let params_value = request
+                .params
+                .ok_or_else(|| RpcError::invalid_params("missing params"))?;
+            let params: ProgressParams = serde_json::from_value(params_value)
+                .map_err(|e| RpcError::invalid_params(format!("invalid params: {e}")))?;
+
+            let result = handle_progress(app_handle, ext_id, params)?;
+            Ok(Some(Response::new(
+                request.id,
+                serde_json::to_value(result).expect("can serialize result"),
+            )))
+        }
         _ => Err(RpcError::method_not_found(&request.method)),
     }
 }
@@ -853,36 +1329,70 @@ fn build_message_changed_params(
     message: &str,
     file_path: Option<&str>,
     options: Option<&MessageChangedOptions>,
+    segment_changes: Option<Vec<SegmentChange>>,
 ) -> MessageChangedParams {
-    let (content, format) =
-        if options.is_some_and(|o| o.include_content) {
-            let format = options.and_then(|o| o.format).unwrap_or(MessageFormat::Hl7);
-
-            // convert message to requested format if needed
-            let content =
-                match format {
-                    MessageFormat::Hl7 => message.to_string(),
-                    MessageFormat::Json => crate::commands::export_to_json(message)
-                        .unwrap_or_else(|_| message.to_string()),
-                    MessageFormat::Yaml => crate::commands::export_to_yaml(message)
-                        .unwrap_or_else(|_| message.to_string()),
-                    MessageFormat::Toml => crate::commands::export_to_toml(message)
-                        .unwrap_or_else(|_| message.to_string()),
-                };
-
-            (Some(content), Some(format))
-        } else {
-            (None, None)
-        };
+    let (content, format) = message_content(message, options);
 
     MessageChangedParams {
         message: content,
         format,
         has_file: file_path.is_some(),
         file_path: file_path.map(String::from),
+        segment_changes,
     }
 }
 
+/// Check setting values against the settings an extension declares.
+///
+/// Null values are dropped, so the extension falls back to its default.
+fn checked_settings(
+    schema: &[SettingDefinition],
+    values: serde_json::Map<String, serde_json::Value>,
+) -> Result<serde_json::Map<String, serde_json::Value>, String> {
+    let mut checked = serde_json::Map::new();
+    for (key, value) in values {
+        if value.is_null() {
+            continue;
+        }
+        let definition = schema
+            .iter()
+            .find(|definition| definition.key == key)
+            .ok_or_else(|| format!("the extension has no setting '{key}'"))?;
+        definition.check(&value)?;
+        checked.insert(key, value);
+    }
+    Ok(checked)
+}
+
+/// Message content for a notification, in the format the subscriber asked for.
+///
+/// Returns `(None, None)` unless the subscription asked for `includeContent`.
+fn message_content(
+    message: &str,
+    options: Option<&MessageChangedOptions>,
+) -> (Option<String>, Option<MessageFormat>) {
+    if !options.is_some_and(|o| o.include_content) {
+        return (None, None);
+    }
+    let format = options.and_then(|o| o.format).unwrap_or(MessageFormat::Hl7);
+
+    // convert message to requested format if needed
+    let content = match format {
+        MessageFormat::Hl7 => message.to_string(),
+        MessageFormat::Json => {
+            crate::commands::export_to_json(message).unwrap_or_else(|_| message.to_string())
+        }
+        MessageFormat::Yaml => {
+            crate::commands::export_to_yaml(message).unwrap_or_else(|_| message.to_string())
+        }
+        MessageFormat::Toml => {
+            crate::commands::export_to_toml(message).unwrap_or_else(|_| message.to_string())
+        }
+    };
+
+    (Some(content), Some(format))
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used, clippy::indexing_slicing)]
 mod tests {
@@ -906,6 +1416,24 @@ mod tests {
         assert!(json.contains("\"command\":\"test/action\""));
     }
 
+    #[test]
+    fn test_checked_settings() {
+        let schema: Vec<SettingDefinition> = serde_json::from_str(
+            r#"[{"key": "facility", "type": "string"}, {"key": "retries", "type": "number"}]"#,
+        )
+        .unwrap();
+
+        let values = serde_json::json!({"facility": "EAST", "retries": null});
+        let checked = checked_settings(&schema, values.as_object().unwrap().clone()).unwrap();
+        assert_eq!(checked.len(), 1, "null clears a setting");
+        assert_eq!(checked["facility"], "EAST");
+
+        let values = serde_json::json!({"retries": "three"});
+        assert!(checked_settings(&schema, values.as_object().unwrap().clone()).is_err());
+        let values = serde_json::json!({"unknown": 1});
+        assert!(checked_settings(&schema, values.as_object().unwrap().clone()).is_err());
+    }
+
     #[test]
     fn test_extension_status_serialization() {
         let status = ExtensionStatus {
@@ -915,6 +1443,7 @@ mod tests {
             version: "1.0.0".to_string(),
             state: ExtensionState::Running,
             error: None,
+            settings: Vec::new(),
         };
 
         let json = serde_json::to_string(&status).unwrap();
@@ -931,6 +1460,7 @@ mod tests {
             version: "1.0.0".to_string(),
             state: ExtensionState::Failed("connection lost".to_string()),
             error: Some("connection lost".to_string()),
+            settings: Vec::new(),
         };
 
         let json = serde_json::to_string(&status).unwrap();
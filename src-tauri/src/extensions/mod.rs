@@ -6,13 +6,18 @@
 //! # Architecture
 //!
 //! Extensions are standalone executables that communicate with Hermes using
-//! LSP-style message framing (Content-Length headers) over stdin/stdout.
+//! LSP-style message framing (Content-Length headers) over stdin/stdout, or
+//! over a TCP or WebSocket connection for environments where stdio is awkward.
 //!
 //! - [`protocol`] - JSON-RPC 2.0 message types and framing
+//! - [`connection`] - Stdio, TCP, and WebSocket transports
 //! - [`types`] - Shared type definitions
 //! - [`process`] - Single extension process management
 //! - [`host`] - Multi-extension orchestration
+//! - [`conformance`] - Protocol conformance suite for extension developers
 
+pub mod conformance;
+mod connection;
 pub mod host;
 mod process;
 pub mod protocol;
@@ -2,23 +2,35 @@
 //!
 //! Manages a single extension subprocess, including spawning, lifecycle,
 //! message routing, stderr capture, and graceful shutdown.
+//!
+//! Each extension's log keeps its latest entries, up to the `logCapacity` in
+//! its configuration (`DEFAULT_LOG_CAPACITY` by default): lifecycle events,
+//! lines it writes to stderr, and entries it sends in `log/message`
+//! notifications.
+//!
+//! Extensions configured with `devMode` also have every message read from or
+//! written to them recorded in a trace, which keeps the latest
+//! `MAX_TRACE_ENTRIES` messages and passes each one on to any subscribers as
+//! it's recorded.
 
+use crate::extensions::connection::{self, MessageSink, MessageStream, CONNECT_TIMEOUT};
 use crate::extensions::protocol::{
-    read_message, write_message, ErrorResponse, Message, Notification, ProtocolError, Request,
-    RequestId, Response, RpcError,
+    ErrorResponse, Message, Notification, ProtocolError, Request, RequestId, Response, RpcError,
 };
 use crate::extensions::types::{
-    EventName, EventSubscription, ExtensionConfig, ExtensionLog, ExtensionMetadata, ExtensionState,
-    InitializeParams, InitializeResult, LogLevel, ShutdownParams, ShutdownReason,
+    EventName, EventSubscription, ExtensionConfig, ExtensionLog, ExtensionLogPage,
+    ExtensionMetadata, ExtensionState, ExtensionTransport, InitializeParams, InitializeResult,
+    LogLevel, ShutdownParams, ShutdownReason, TraceDirection, TraceEntry,
 };
+use futures::{SinkExt, StreamExt};
 use jiff::Timestamp;
 use std::collections::{HashMap, VecDeque};
 use std::path::Path;
 use std::process::Stdio;
 use std::sync::Arc;
-use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, BufReader};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader};
 use tokio::process::{Child, Command};
-use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio::sync::{broadcast, mpsc, oneshot, Mutex};
 use tokio::task::JoinHandle;
 use tokio::time::{timeout, Duration};
 
@@ -26,8 +38,15 @@ use tokio::time::{timeout, Duration};
 const INITIALIZE_TIMEOUT: Duration = Duration::from_secs(10);
 const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
 
-/// Maximum number of log entries to keep per extension.
-const MAX_LOG_ENTRIES: usize = 100;
+/// Number of log entries to keep per extension, unless configured otherwise.
+const DEFAULT_LOG_CAPACITY: usize = 1000;
+
+/// Maximum number of messages to keep in an extension's protocol trace.
+const MAX_TRACE_ENTRIES: usize = 1000;
+
+/// How many traced messages a slow subscriber can fall behind by before it
+/// misses some.
+const TRACE_CHANNEL_CAPACITY: usize = 256;
 
 /// Type alias for pending request tracking.
 type PendingRequests =
@@ -60,6 +79,165 @@ impl ResponseSender {
     }
 }
 
+/// Cloneable handle for sending requests to an extension and awaiting responses.
+///
+/// Like `ResponseSender`, this lets a request be awaited without holding the
+/// extension host locked for as long as the extension takes to respond.
+#[derive(Clone)]
+pub struct RequestSender {
+    tx: mpsc::Sender<Message>,
+    pending_requests: PendingRequests,
+    next_request_id: Arc<Mutex<i64>>,
+}
+
+impl RequestSender {
+    /// Send a request to the extension and await the response.
+    pub async fn send(
+        &self,
+        method: &str,
+        params: serde_json::Value,
+    ) -> Result<Response, ExtensionError> {
+        let id = {
+            let mut next_id = self.next_request_id.lock().await;
+            let id = *next_id;
+            *next_id += 1;
+            RequestId::Number(id)
+        };
+
+        let (response_tx, response_rx) = oneshot::channel();
+
+        // register pending request
+        {
+            let mut pending = self.pending_requests.lock().await;
+            pending.insert(id.clone(), response_tx);
+        }
+
+        let request = Request::new(
+            id.clone(),
+            method,
+            if params.is_null() { None } else { Some(params) },
+        );
+
+        // send the request
+        if self.tx.send(Message::Request(request)).await.is_err() {
+            self.pending_requests.lock().await.remove(&id);
+            return Err(ExtensionError::Channel(
+                "failed to send request".to_string(),
+            ));
+        }
+
+        // wait for response
+        match response_rx.await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(error_response)) => Err(ExtensionError::Rpc(error_response.error)),
+            Err(_) => Err(ExtensionError::Channel(
+                "response channel closed".to_string(),
+            )),
+        }
+    }
+}
+
+/// Ring buffer of an extension's log entries.
+///
+/// Cloneable, so the stderr reader and the host's request handler can add
+/// entries alongside the process itself.
+#[derive(Clone)]
+pub struct LogBuffer {
+    entries: Arc<Mutex<VecDeque<ExtensionLog>>>,
+    capacity: usize,
+}
+
+impl LogBuffer {
+    fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            entries: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    /// Add an entry, dropping the oldest if the buffer is full.
+    pub async fn push(&self, level: LogLevel, message: String) {
+        let entry = ExtensionLog {
+            timestamp: Timestamp::now(),
+            level,
+            message,
+        };
+
+        let mut entries = self.entries.lock().await;
+        entries.push_back(entry);
+        if entries.len() > self.capacity {
+            entries.pop_front();
+        }
+    }
+
+    /// Get a page of entries, newest first.
+    ///
+    /// # Arguments
+    /// * `level` - Least severe level to include, or None for every level
+    /// * `offset` - Number of the newest matching entries to skip
+    /// * `limit` - Most entries to return, or None for all of them
+    async fn page(
+        &self,
+        level: Option<LogLevel>,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> ExtensionLogPage {
+        let entries = self.entries.lock().await;
+        let matching = entries
+            .iter()
+            .rev()
+            .filter(|entry| level.is_none_or(|level| entry.level >= level));
+        let total = matching.clone().count();
+        let entries = matching
+            .skip(offset)
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect();
+        ExtensionLogPage { entries, total }
+    }
+}
+
+/// Protocol trace of an extension in development mode.
+///
+/// Cloned into the reader and writer tasks, which record the messages they
+/// read and write.
+#[derive(Clone)]
+struct Trace {
+    /// Ring buffer of the latest messages.
+    entries: Arc<Mutex<VecDeque<TraceEntry>>>,
+    /// Sender of each message as it's recorded.
+    live: broadcast::Sender<TraceEntry>,
+}
+
+impl Trace {
+    fn new() -> Self {
+        let (live, _) = broadcast::channel(TRACE_CHANNEL_CAPACITY);
+        Self {
+            entries: Arc::new(Mutex::new(VecDeque::with_capacity(MAX_TRACE_ENTRIES))),
+            live,
+        }
+    }
+
+    /// Record a message read from or written to the extension.
+    async fn record(&self, direction: TraceDirection, message: &Message) {
+        let entry = TraceEntry {
+            timestamp: Timestamp::now(),
+            direction,
+            message: message.clone(),
+        };
+
+        let mut entries = self.entries.lock().await;
+        entries.push_back(entry.clone());
+        if entries.len() > MAX_TRACE_ENTRIES {
+            entries.pop_front();
+        }
+
+        // fails only when nobody is subscribed, which is fine
+        let _ = self.live.send(entry);
+    }
+}
+
 /// Errors that can occur during extension process operations.
 #[derive(Debug)]
 pub enum ExtensionError {
@@ -178,7 +356,10 @@ pub struct ExtensionProcess {
     incoming_rx: Option<mpsc::Receiver<InternalMessage>>,
 
     /// Ring buffer of recent log entries.
-    logs: Arc<Mutex<VecDeque<ExtensionLog>>>,
+    logs: LogBuffer,
+
+    /// Protocol trace, if the extension is in development mode.
+    trace: Option<Trace>,
 }
 
 impl ExtensionProcess {
@@ -226,16 +407,35 @@ impl ExtensionProcess {
             (config.path.clone(), config.args.clone())
         };
 
+        // socket transports listen before the extension starts, so it can connect
+        let listener = match config.transport {
+            ExtensionTransport::Stdio => None,
+            ExtensionTransport::Tcp | ExtensionTransport::WebSocket => Some(
+                connection::bind(config.port)
+                    .await
+                    .map_err(ExtensionError::SpawnFailed)?,
+            ),
+        };
+        let stdio = if listener.is_some() {
+            Stdio::null
+        } else {
+            Stdio::piped
+        };
+
         // spawn the process
         let mut command = Command::new(&executable);
         command
             .args(&args)
-            .stdin(Stdio::piped())
-            .stdout(Stdio::piped())
+            .stdin(stdio())
+            .stdout(stdio())
             .stderr(Stdio::piped())
             .env("HERMES_VERSION", hermes_version)
             .env("HERMES_API_VERSION", api_version)
-            .env("HERMES_DATA_DIR", data_dir);
+            .env("HERMES_DATA_DIR", data_dir)
+            .env("HERMES_TRANSPORT", config.transport.to_string());
+        if let Some((_, port)) = &listener {
+            command.env("HERMES_PORT", port.to_string());
+        }
 
         // add custom environment variables
         for (key, value) in &config.env {
@@ -243,30 +443,63 @@ impl ExtensionProcess {
         }
 
         let mut child = command.spawn().map_err(ExtensionError::SpawnFailed)?;
-
-        let stdin = child.stdin.take().expect("can take stdin");
-        let stdout = child.stdout.take().expect("can take stdout");
         let stderr = child.stderr.take().expect("can take stderr");
 
+        let (incoming, outgoing) = match listener {
+            None => {
+                let stdin = child.stdin.take().expect("can take stdin");
+                let stdout = child.stdout.take().expect("can take stdout");
+                connection::framed(BufReader::new(stdout), stdin)
+            }
+            Some((listener, port)) => {
+                log::info!("waiting for extension {id} to connect on port {port}");
+                let accept = timeout(
+                    CONNECT_TIMEOUT,
+                    connection::accept(listener, config.transport),
+                );
+                let connected = tokio::select! {
+                    accepted = accept => match accepted {
+                        Ok(Ok(connected)) => Ok(connected),
+                        Ok(Err(e)) => Err(ExtensionError::Protocol(e)),
+                        Err(_) => Err(ExtensionError::Timeout(format!(
+                            "connecting over {} on port {port}",
+                            config.transport
+                        ))),
+                    },
+                    _ = child.wait() => Err(ExtensionError::ProcessExited),
+                };
+                match connected {
+                    Ok(connected) => connected,
+                    Err(e) => {
+                        let _ = child.kill().await;
+                        return Err(e);
+                    }
+                }
+            }
+        };
+
         // set up communication channels
         let (outgoing_tx, outgoing_rx) = mpsc::channel::<Message>(32);
         let (incoming_tx, incoming_rx) = mpsc::channel::<InternalMessage>(32);
         let pending_requests = Arc::new(Mutex::new(HashMap::new()));
 
+        let trace = config.dev_mode.then(Trace::new);
+
         // spawn reader task
         let reader_task = spawn_reader_task(
-            BufReader::new(stdout),
+            incoming,
             incoming_tx.clone(),
             pending_requests.clone(),
+            trace.clone(),
         );
 
         // spawn writer task
-        let writer_task = spawn_writer_task(stdin, outgoing_rx);
+        let writer_task = spawn_writer_task(outgoing, outgoing_rx, trace.clone());
 
         let state = Arc::new(Mutex::new(ExtensionState::Starting));
         let metadata = Arc::new(Mutex::new(None));
         let next_request_id = Arc::new(Mutex::new(1i64));
-        let logs = Arc::new(Mutex::new(VecDeque::with_capacity(MAX_LOG_ENTRIES)));
+        let logs = LogBuffer::new(config.log_capacity.unwrap_or(DEFAULT_LOG_CAPACITY));
 
         // spawn stderr reader task
         let stderr_task = spawn_stderr_reader_task(BufReader::new(stderr), logs.clone());
@@ -285,6 +518,7 @@ impl ExtensionProcess {
             child: Some(child),
             incoming_rx: Some(incoming_rx),
             logs,
+            trace,
         };
 
         // add initial log entry
@@ -319,25 +553,51 @@ impl ExtensionProcess {
 
     /// Add a log entry.
     async fn add_log(&self, level: LogLevel, message: String) {
-        let mut logs = self.logs.lock().await;
-        let entry = ExtensionLog {
-            timestamp: Timestamp::now(),
-            level,
-            message,
-        };
+        self.logs.push(level, message).await;
+    }
 
-        // add to end of deque
-        logs.push_back(entry);
+    /// Get all log entries for this extension, oldest first.
+    pub async fn get_logs(&self) -> Vec<ExtensionLog> {
+        self.logs.entries.lock().await.iter().cloned().collect()
+    }
 
-        // if we exceed capacity, remove oldest entry
-        if logs.len() > MAX_LOG_ENTRIES {
-            logs.pop_front();
-        }
+    /// Get a page of log entries for this extension, newest first.
+    ///
+    /// # Arguments
+    /// * `level` - Least severe level to include, or None for every level
+    /// * `offset` - Number of the newest matching entries to skip
+    /// * `limit` - Most entries to return, or None for all of them
+    pub async fn get_log_page(
+        &self,
+        level: Option<LogLevel>,
+        offset: usize,
+        limit: Option<usize>,
+    ) -> ExtensionLogPage {
+        self.logs.page(level, offset, limit).await
     }
 
-    /// Get all log entries for this extension.
-    pub async fn get_logs(&self) -> Vec<ExtensionLog> {
-        self.logs.lock().await.iter().cloned().collect()
+    /// Get a cloneable handle for adding entries to this extension's log.
+    ///
+    /// Used by the host to record entries the extension sends in `log/message`
+    /// notifications.
+    pub fn log_buffer(&self) -> LogBuffer {
+        self.logs.clone()
+    }
+
+    /// Get the messages recorded in the protocol trace, oldest first.
+    ///
+    /// Returns `None` if the extension isn't in development mode.
+    pub async fn get_trace(&self) -> Option<Vec<TraceEntry>> {
+        let trace = self.trace.as_ref()?;
+        Some(trace.entries.lock().await.iter().cloned().collect())
+    }
+
+    /// Subscribe to messages as they're recorded in the protocol trace.
+    ///
+    /// Returns `None` if the extension isn't in development mode. The receiver
+    /// closes once the extension process is dropped.
+    pub fn subscribe_trace(&self) -> Option<broadcast::Receiver<TraceEntry>> {
+        self.trace.as_ref().map(|trace| trace.live.subscribe())
     }
 
     /// Send the initialize request and await response.
@@ -365,6 +625,7 @@ impl ExtensionProcess {
             hermes_version: hermes_version.to_string(),
             api_version: api_version.to_string(),
             data_directory: data_dir.to_string_lossy().to_string(),
+            settings: self.config.settings.clone(),
         };
 
         let result = timeout(
@@ -421,46 +682,10 @@ impl ExtensionProcess {
         method: &str,
         params: serde_json::Value,
     ) -> Result<Response, ExtensionError> {
-        let id = {
-            let mut next_id = self.next_request_id.lock().await;
-            let id = *next_id;
-            *next_id += 1;
-            RequestId::Number(id)
-        };
-
-        let (response_tx, response_rx) = oneshot::channel();
-
-        // register pending request
-        {
-            let mut pending = self.pending_requests.lock().await;
-            pending.insert(id.clone(), response_tx);
-        }
-
-        let request = Request::new(
-            id.clone(),
-            method,
-            if params.is_null() { None } else { Some(params) },
-        );
-
-        // send the request
-        if let Some(tx) = &self.outgoing_tx {
-            tx.send(Message::Request(request))
-                .await
-                .map_err(|_| ExtensionError::Channel("failed to send request".to_string()))?;
-        } else {
-            return Err(ExtensionError::InvalidState(
-                "extension not connected".to_string(),
-            ));
-        }
-
-        // wait for response
-        match response_rx.await {
-            Ok(Ok(response)) => Ok(response),
-            Ok(Err(error_response)) => Err(ExtensionError::Rpc(error_response.error)),
-            Err(_) => Err(ExtensionError::Channel(
-                "response channel closed".to_string(),
-            )),
-        }
+        let sender = self
+            .request_sender()
+            .ok_or_else(|| ExtensionError::InvalidState("extension not connected".to_string()))?;
+        sender.send(method, params).await
     }
 
     /// Send a notification to the extension (no response expected).
@@ -555,6 +780,17 @@ impl ExtensionProcess {
         }
     }
 
+    /// Wait for the extension process to exit on its own.
+    ///
+    /// Returns `true` if the process exited (or was never running) within the
+    /// given duration, `false` if it is still alive.
+    pub async fn wait_for_exit(&mut self, duration: Duration) -> bool {
+        let Some(child) = self.child.as_mut() else {
+            return true;
+        };
+        timeout(duration, child.wait()).await.is_ok()
+    }
+
     /// Take the incoming message receiver for the host to process.
     ///
     /// The host spawns a task per extension to continuously process this channel,
@@ -573,6 +809,18 @@ impl ExtensionProcess {
             .map(|tx| ResponseSender { tx: tx.clone() })
     }
 
+    /// Get a cloneable handle for sending requests to this extension.
+    ///
+    /// Used for requests that can take a while, such as `transport/send`, so
+    /// they can be awaited without a mutable reference to the `ExtensionProcess`.
+    pub fn request_sender(&self) -> Option<RequestSender> {
+        self.outgoing_tx.as_ref().map(|tx| RequestSender {
+            tx: tx.clone(),
+            pending_requests: Arc::clone(&self.pending_requests),
+            next_request_id: Arc::clone(&self.next_request_id),
+        })
+    }
+
     /// Clean up resources after process termination.
     async fn cleanup(&mut self) {
         // drop outgoing channel to signal writer task to stop
@@ -602,16 +850,20 @@ impl ExtensionProcess {
     }
 }
 
-/// Spawn the reader task that reads messages from the extension's stdout.
-fn spawn_reader_task<R: AsyncBufRead + Unpin + Send + 'static>(
-    mut reader: R,
+/// Spawn the reader task that reads messages from the extension.
+fn spawn_reader_task(
+    mut incoming: MessageStream,
     incoming_tx: mpsc::Sender<InternalMessage>,
     pending_requests: PendingRequests,
+    trace: Option<Trace>,
 ) -> JoinHandle<()> {
     tokio::spawn(async move {
         loop {
-            match read_message(&mut reader).await {
+            match incoming.next().await.unwrap_or(Err(ProtocolError::Eof)) {
                 Ok(msg) => {
+                    if let Some(trace) = &trace {
+                        trace.record(TraceDirection::Incoming, &msg).await;
+                    }
                     match msg {
                         Message::Response(response) => {
                             // route response to waiting request
@@ -659,7 +911,7 @@ fn spawn_reader_task<R: AsyncBufRead + Unpin + Send + 'static>(
                     }
                 }
                 Err(ProtocolError::Eof) => {
-                    log::info!("extension stdout closed (EOF)");
+                    log::info!("extension connection closed (EOF)");
                     let _ = incoming_tx
                         .send(InternalMessage::ReaderError(ProtocolError::Eof))
                         .await;
@@ -675,14 +927,18 @@ fn spawn_reader_task<R: AsyncBufRead + Unpin + Send + 'static>(
     })
 }
 
-/// Spawn the writer task that writes messages to the extension's stdin.
-fn spawn_writer_task<W: AsyncWrite + Unpin + Send + 'static>(
-    mut writer: W,
+/// Spawn the writer task that writes messages to the extension.
+fn spawn_writer_task(
+    mut outgoing: MessageSink,
     mut outgoing_rx: mpsc::Receiver<Message>,
+    trace: Option<Trace>,
 ) -> JoinHandle<()> {
     tokio::spawn(async move {
         while let Some(msg) = outgoing_rx.recv().await {
-            if let Err(e) = write_message(&mut writer, &msg).await {
+            if let Some(trace) = &trace {
+                trace.record(TraceDirection::Outgoing, &msg).await;
+            }
+            if let Err(e) = outgoing.send(msg).await {
                 log::error!("failed to write message to extension: {e}");
                 break;
             }
@@ -693,37 +949,26 @@ fn spawn_writer_task<W: AsyncWrite + Unpin + Send + 'static>(
 /// Spawn the stderr reader task that captures extension stderr output as log entries.
 fn spawn_stderr_reader_task<R: AsyncBufRead + Unpin + Send + 'static>(
     reader: R,
-    logs: Arc<Mutex<VecDeque<ExtensionLog>>>,
+    logs: LogBuffer,
 ) -> JoinHandle<()> {
     tokio::spawn(async move {
         let mut lines = reader.lines();
         while let Ok(Some(line)) = lines.next_line().await {
             let (level, message) = parse_log_line(&line);
-
-            let entry = ExtensionLog {
-                timestamp: Timestamp::now(),
-                level,
-                message,
-            };
-
-            let mut logs = logs.lock().await;
-            logs.push_back(entry);
-            if logs.len() > MAX_LOG_ENTRIES {
-                logs.pop_front();
-            }
+            logs.push(level, message).await;
         }
     })
 }
 
 /// Parse a log line to extract level and message.
 ///
-/// Recognises common log level prefixes like `[ERROR]`, `ERROR:`, etc.
+/// Recognises common log level prefixes like `[ERROR]`, `ERROR:`, `[DEBUG]`, etc.
 /// Returns the parsed level and the message with the prefix stripped.
 /// Defaults to `Info` for lines without a recognised prefix.
 fn parse_log_line(line: &str) -> (LogLevel, String) {
     let line = line.trim();
 
-    // try bracketed format: [ERROR], [WARN], [WARNING], [INFO]
+    // try bracketed format: [ERROR], [WARN], [WARNING], [INFO], [DEBUG]
     if let Some(rest) = line
         .strip_prefix("[ERROR]")
         .or_else(|| line.strip_prefix("[error]"))
@@ -744,8 +989,14 @@ fn parse_log_line(line: &str) -> (LogLevel, String) {
     {
         return (LogLevel::Info, rest.trim().to_string());
     }
+    if let Some(rest) = line
+        .strip_prefix("[DEBUG]")
+        .or_else(|| line.strip_prefix("[debug]"))
+    {
+        return (LogLevel::Debug, rest.trim().to_string());
+    }
 
-    // try colon format: ERROR:, WARN:, WARNING:, INFO:
+    // try colon format: ERROR:, WARN:, WARNING:, INFO:, DEBUG:
     if let Some(rest) = line
         .strip_prefix("ERROR:")
         .or_else(|| line.strip_prefix("error:"))
@@ -768,6 +1019,12 @@ fn parse_log_line(line: &str) -> (LogLevel, String) {
     {
         return (LogLevel::Info, rest.trim().to_string());
     }
+    if let Some(rest) = line
+        .strip_prefix("DEBUG:")
+        .or_else(|| line.strip_prefix("debug:"))
+    {
+        return (LogLevel::Debug, rest.trim().to_string());
+    }
 
     // default to Info for unprefixed lines
     (LogLevel::Info, line.to_string())
@@ -799,6 +1056,62 @@ mod tests {
         assert_ne!(id1, id2);
     }
 
+    #[tokio::test]
+    async fn test_trace_keeps_latest_messages() {
+        let trace = Trace::new();
+        let mut live = trace.live.subscribe();
+
+        let notification =
+            |i: usize| Message::Notification(Notification::new(format!("method/{i}"), None));
+
+        // subscribers see each message as it's recorded
+        trace
+            .record(TraceDirection::Outgoing, &notification(0))
+            .await;
+        let first = live.try_recv().unwrap();
+        assert_eq!(first.direction, TraceDirection::Outgoing);
+        assert!(matches!(first.message, Message::Notification(n) if n.method == "method/0"));
+
+        for i in 1..=MAX_TRACE_ENTRIES {
+            trace
+                .record(TraceDirection::Incoming, &notification(i))
+                .await;
+        }
+
+        let entries = trace.entries.lock().await;
+        assert_eq!(entries.len(), MAX_TRACE_ENTRIES);
+        assert!(matches!(
+            &entries[0].message,
+            Message::Notification(n) if n.method == "method/1"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_log_buffer_pages() {
+        let logs = LogBuffer::new(4);
+        logs.push(LogLevel::Info, "started".to_string()).await;
+        logs.push(LogLevel::Debug, "one".to_string()).await;
+        logs.push(LogLevel::Warn, "two".to_string()).await;
+        logs.push(LogLevel::Error, "three".to_string()).await;
+        logs.push(LogLevel::Debug, "four".to_string()).await;
+
+        // the oldest entry made way for the newest
+        let page = logs.page(None, 0, None).await;
+        assert_eq!(page.total, 4);
+        let messages: Vec<_> = page.entries.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, ["four", "three", "two", "one"]);
+
+        // filtering keeps the level and those more severe
+        let page = logs.page(Some(LogLevel::Warn), 0, None).await;
+        assert_eq!(page.total, 2);
+        assert_eq!(page.entries[0].message, "three");
+
+        let page = logs.page(Some(LogLevel::Debug), 1, Some(2)).await;
+        assert_eq!(page.total, 4);
+        let messages: Vec<_> = page.entries.iter().map(|e| e.message.as_str()).collect();
+        assert_eq!(messages, ["three", "two"]);
+    }
+
     #[test]
     fn test_extension_error_display() {
         let error = ExtensionError::Timeout("initialize".to_string());
@@ -841,6 +1154,10 @@ mod tests {
         let (level, msg) = parse_log_line("[INFO] info message");
         assert_eq!(level, LogLevel::Info);
         assert_eq!(msg, "info message");
+
+        let (level, msg) = parse_log_line("[DEBUG] debug message");
+        assert_eq!(level, LogLevel::Debug);
+        assert_eq!(msg, "debug message");
     }
 
     #[test]
@@ -864,6 +1181,10 @@ mod tests {
         let (level, msg) = parse_log_line("INFO: information");
         assert_eq!(level, LogLevel::Info);
         assert_eq!(msg, "information");
+
+        let (level, msg) = parse_log_line("debug: details");
+        assert_eq!(level, LogLevel::Debug);
+        assert_eq!(msg, "details");
     }
 
     #[test]
@@ -9,6 +9,9 @@ use std::collections::HashMap;
 
 use jiff::Timestamp;
 
+use crate::commands::{SegmentChange, Severity};
+use crate::extensions::protocol::Message;
+
 // ============================================================================
 // Nullable type for schema overrides
 // ============================================================================
@@ -154,12 +157,60 @@ pub struct ExtensionConfig {
     /// Whether the extension is enabled.
     #[serde(default = "default_true")]
     pub enabled: bool,
+
+    /// Values of the settings the extension declares, by key.
+    #[serde(default)]
+    pub settings: serde_json::Map<String, serde_json::Value>,
+
+    /// How the extension exchanges messages with Hermes.
+    #[serde(default)]
+    pub transport: ExtensionTransport,
+
+    /// Port to listen on for socket transports, or None for any free port.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+
+    /// Whether to record every message exchanged with the extension, for
+    /// debugging it.
+    #[serde(default, rename = "devMode")]
+    pub dev_mode: bool,
+
+    /// Number of log entries to keep, or None for the default.
+    #[serde(
+        default,
+        rename = "logCapacity",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub log_capacity: Option<usize>,
 }
 
 fn default_true() -> bool {
     true
 }
 
+/// How an extension exchanges messages with Hermes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExtensionTransport {
+    /// Content-Length framed messages over stdin and stdout.
+    #[default]
+    Stdio,
+    /// Content-Length framed messages over a TCP connection to Hermes.
+    Tcp,
+    /// One message per text frame over a WebSocket connection to Hermes.
+    WebSocket,
+}
+
+impl std::fmt::Display for ExtensionTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExtensionTransport::Stdio => write!(f, "stdio"),
+            ExtensionTransport::Tcp => write!(f, "tcp"),
+            ExtensionTransport::WebSocket => write!(f, "websocket"),
+        }
+    }
+}
+
 /// Extension lifecycle state.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -203,15 +254,59 @@ pub struct ExtensionLog {
     pub message: String,
 }
 
-/// Log level for extension events.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// A page of an extension's log entries, newest first.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExtensionLogPage {
+    /// The entries on this page.
+    pub entries: Vec<ExtensionLog>,
+
+    /// Number of entries matching the filter, across all pages.
+    pub total: usize,
+}
+
+/// Log level for extension events, from least to most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum LogLevel {
+    Debug,
     Info,
     Warn,
     Error,
 }
 
+/// Parameters for the `log/message` notification.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogMessageParams {
+    /// Level of the entry.
+    pub level: LogLevel,
+
+    /// Text of the entry.
+    pub message: String,
+}
+
+/// A message exchanged with an extension in development mode.
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceEntry {
+    /// When the message was read or written.
+    pub timestamp: Timestamp,
+
+    /// Which way the message went.
+    pub direction: TraceDirection,
+
+    /// The message, as sent over the wire.
+    pub message: Message,
+}
+
+/// Which way a traced message went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TraceDirection {
+    /// From the extension to Hermes.
+    Incoming,
+    /// From Hermes to the extension.
+    Outgoing,
+}
+
 /// Metadata returned by an extension during initialization.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExtensionMetadata {
@@ -241,6 +336,14 @@ pub struct ExtensionMetadata {
     #[serde(default, rename = "toolbarButtons")]
     pub toolbar_buttons: Vec<ToolbarButton>,
 
+    /// Templates to list under "New from Template".
+    #[serde(default)]
+    pub templates: Vec<TemplateContribution>,
+
+    /// Settings the user can configure for the extension.
+    #[serde(default)]
+    pub settings: Vec<SettingDefinition>,
+
     /// Schema overrides provided by the extension.
     #[serde(default)]
     pub schema: Option<SchemaOverride>,
@@ -260,6 +363,28 @@ pub struct Capabilities {
     /// Events the extension wants to subscribe to.
     #[serde(default)]
     pub events: Vec<EventSubscription>,
+
+    /// Transports the extension can send messages over.
+    #[serde(default)]
+    pub transports: Vec<TransportProvider>,
+
+    /// Whether the extension checks messages during full validation.
+    #[serde(default, rename = "validationProvider")]
+    pub validation_provider: bool,
+}
+
+/// A named transport an extension provides for sending messages.
+///
+/// Connection presets configured with the transport's name are sent through
+/// the extension with `transport/send` requests instead of over MLLP.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransportProvider {
+    /// Transport name that presets refer to (e.g., "ibm-mq").
+    pub name: String,
+
+    /// Human-readable name shown when choosing a transport.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
 }
 
 /// Toolbar button definition from an extension.
@@ -282,6 +407,83 @@ pub struct ToolbarButton {
     pub group: Option<String>,
 }
 
+/// A template an extension generates messages for.
+///
+/// Listed under "New from Template"; choosing it sends the extension a
+/// `template/generate` request, and the message it returns is opened.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateContribution {
+    /// Name shown in the menu (e.g., "Site ADT^A04").
+    pub name: String,
+
+    /// Command ID passed back in the `template/generate` request.
+    pub command: String,
+}
+
+/// A setting an extension declares, shown in the extension settings.
+///
+/// Values are stored with the extension's configuration, passed in the
+/// `initialize` params, and sent again in `configuration/changed` when the
+/// user changes them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SettingDefinition {
+    /// Key the value is stored under.
+    pub key: String,
+
+    /// Type of value the setting takes.
+    #[serde(rename = "type")]
+    pub kind: SettingKind,
+
+    /// Label shown in the settings, falling back to the key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+
+    /// Explanation shown under the setting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    /// Value used until the user sets one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<serde_json::Value>,
+
+    /// Values to choose from, for `choice` settings.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub choices: Vec<String>,
+}
+
+/// Type of value a setting takes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SettingKind {
+    String,
+    Number,
+    Boolean,
+    /// One of the setting's `choices`.
+    Choice,
+}
+
+impl SettingDefinition {
+    /// Check a value is of the setting's type.
+    pub fn check(&self, value: &serde_json::Value) -> Result<(), String> {
+        let valid = match self.kind {
+            SettingKind::String => value.is_string(),
+            SettingKind::Number => value.is_number(),
+            SettingKind::Boolean => value.is_boolean(),
+            SettingKind::Choice => value
+                .as_str()
+                .is_some_and(|value| self.choices.iter().any(|choice| choice == value)),
+        };
+        if valid {
+            Ok(())
+        } else {
+            Err(format!(
+                "{value} isn't a valid value for setting '{}'",
+                self.key
+            ))
+        }
+    }
+}
+
 /// Schema overrides provided by an extension.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SchemaOverride {
@@ -428,6 +630,18 @@ pub struct FieldOverride {
     )]
     pub values: Option<Nullable<IndexMap<String, String>>>,
 
+    /// Override the HL7 table the field's values come from (e.g., "0001").
+    /// - `None` = inherit from base schema
+    /// - `Some(Nullable::Value(s))` = bind the field to table `s`
+    /// - `Some(Nullable::Null)` = unset inherited table
+    #[serde(
+        default,
+        serialize_with = "option_nullable::serialize",
+        deserialize_with = "option_nullable::deserialize",
+        skip_serializing_if = "option_nullable::is_none"
+    )]
+    pub table: Option<Nullable<String>>,
+
     /// Override template value.
     /// - `None` = inherit from base schema
     /// - `Some(Nullable::Value(s))` = set template to `s`
@@ -439,6 +653,18 @@ pub struct FieldOverride {
         skip_serializing_if = "option_nullable::is_none"
     )]
     pub template: Option<Nullable<String>>,
+
+    /// Override privacy classification ("phi" | "not-phi").
+    /// - `None` = inherit from base schema
+    /// - `Some(Nullable::Value(s))` = set classification to `s`
+    /// - `Some(Nullable::Null)` = unset inherited classification
+    #[serde(
+        default,
+        serialize_with = "option_nullable::serialize",
+        deserialize_with = "option_nullable::deserialize",
+        skip_serializing_if = "option_nullable::is_none"
+    )]
+    pub classification: Option<Nullable<String>>,
 }
 
 // ============================================================================
@@ -459,6 +685,13 @@ pub struct InitializeParams {
     /// Path to the extension's data directory.
     #[serde(rename = "dataDirectory")]
     pub data_directory: String,
+
+    /// Values the user has set for the extension's settings, by key.
+    ///
+    /// Settings the user hasn't set are left out, for the extension to use its
+    /// declared defaults.
+    #[serde(default)]
+    pub settings: serde_json::Map<String, serde_json::Value>,
 }
 
 /// Result of a successful `initialize` response from an extension.
@@ -490,6 +723,14 @@ pub struct InitializeResult {
     #[serde(default, rename = "toolbarButtons")]
     pub toolbar_buttons: Vec<ToolbarButton>,
 
+    /// Templates to list under "New from Template".
+    #[serde(default)]
+    pub templates: Vec<TemplateContribution>,
+
+    /// Settings the user can configure for the extension.
+    #[serde(default)]
+    pub settings: Vec<SettingDefinition>,
+
     /// Schema overrides provided by the extension.
     #[serde(default)]
     pub schema: Option<SchemaOverride>,
@@ -505,6 +746,8 @@ impl From<InitializeResult> for ExtensionMetadata {
             homepage: result.homepage,
             capabilities: result.capabilities,
             toolbar_buttons: result.toolbar_buttons,
+            templates: result.templates,
+            settings: result.settings,
             schema: result.schema,
         }
     }
@@ -547,6 +790,35 @@ pub struct CommandExecuteParams {
     pub command: String,
 }
 
+// ============================================================================
+// Configuration types
+// ============================================================================
+
+/// Parameters for `configuration/changed` notification.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfigurationChangedParams {
+    /// Every value the user has set for the extension's settings, by key.
+    pub settings: serde_json::Map<String, serde_json::Value>,
+}
+
+// ============================================================================
+// Template types
+// ============================================================================
+
+/// Parameters for the `template/generate` request sent by Hermes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateGenerateParams {
+    /// Command of the template that was chosen.
+    pub command: String,
+}
+
+/// Result of a `template/generate` response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TemplateGenerateResult {
+    /// The generated HL7 message.
+    pub message: String,
+}
+
 // ============================================================================
 // Message event types
 // ============================================================================
@@ -561,9 +833,16 @@ pub enum EventName {
     MessageOpened,
     #[serde(rename = "message/saved")]
     MessageSaved,
+    #[serde(rename = "message/beforeSend")]
+    MessageBeforeSend,
+    #[serde(rename = "message/ackReceived")]
+    MessageAckReceived,
+    #[serde(rename = "message/received")]
+    MessageReceived,
 }
 
-/// Options for `message/changed` event subscription.
+/// Options for `message/changed`, `message/ackReceived`, and `message/received`
+/// event subscriptions.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct MessageChangedOptions {
     /// Include message content in notifications.
@@ -581,7 +860,8 @@ pub struct EventSubscription {
     /// Event name to subscribe to.
     pub name: EventName,
 
-    /// Event-specific options (only for `message/changed`).
+    /// Event-specific options (for `message/changed`, `message/ackReceived`,
+    /// and `message/received`).
     #[serde(default)]
     pub options: Option<MessageChangedOptions>,
 }
@@ -604,6 +884,14 @@ pub struct MessageChangedParams {
     /// File path if the message is saved.
     #[serde(rename = "filePath", skip_serializing_if = "Option::is_none")]
     pub file_path: Option<String>,
+
+    /// Segments added, removed, or moved since the last notification, in order.
+    ///
+    /// Only present when every edit since then was a segment operation, so an
+    /// extension tracking segments can apply these instead of re-reading the
+    /// whole message.
+    #[serde(rename = "segmentChanges", skip_serializing_if = "Option::is_none")]
+    pub segment_changes: Option<Vec<SegmentChange>>,
 }
 
 /// Parameters for `message/opened` notification.
@@ -630,6 +918,93 @@ pub struct MessageSavedParams {
     pub save_as: bool,
 }
 
+/// Parameters for the `message/beforeSend` request sent by Hermes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BeforeSendParams {
+    /// The message about to be sent, with placeholders filled in.
+    pub message: String,
+
+    /// MSH.10 of the message.
+    #[serde(rename = "controlId", skip_serializing_if = "Option::is_none")]
+    pub control_id: Option<String>,
+
+    /// MSH.9 of the message (e.g., "ADT^A01").
+    #[serde(rename = "messageType", skip_serializing_if = "Option::is_none")]
+    pub message_type: Option<String>,
+
+    /// Label of the connection preset being sent to, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+}
+
+/// Result of a `message/beforeSend` response.
+///
+/// An empty result lets the send go ahead with the message unchanged.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BeforeSendResult {
+    /// Message to send instead, if the extension changed it.
+    #[serde(default)]
+    pub message: Option<String>,
+
+    /// Whether to cancel the send.
+    #[serde(default)]
+    pub veto: bool,
+
+    /// Why the send was cancelled, shown to the user.
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+/// Parameters for `message/ackReceived` notification.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AckReceivedParams {
+    /// The response (if subscriber requested `includeContent`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+
+    /// Format of the response content.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<MessageFormat>,
+
+    /// MSH.10 of the message that was sent.
+    #[serde(rename = "controlId", skip_serializing_if = "Option::is_none")]
+    pub control_id: Option<String>,
+
+    /// MSA.1 of the response (e.g., "AA", "AE").
+    #[serde(rename = "ackCode", skip_serializing_if = "Option::is_none")]
+    pub ack_code: Option<String>,
+
+    /// Where the message was sent, as shown in the send log.
+    pub target: String,
+}
+
+/// Parameters for `message/received` notification.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageReceivedParams {
+    /// The received message (if subscriber requested `includeContent`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+
+    /// Format of the message content.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<MessageFormat>,
+
+    /// MSH.10 of the message.
+    #[serde(rename = "controlId", skip_serializing_if = "Option::is_none")]
+    pub control_id: Option<String>,
+
+    /// MSH.9 of the message.
+    #[serde(rename = "messageType", skip_serializing_if = "Option::is_none")]
+    pub message_type: Option<String>,
+
+    /// Address of the sender.
+    pub peer: String,
+
+    /// Where the listener saved the message, if it saves received messages.
+    #[serde(rename = "savedPath", skip_serializing_if = "Option::is_none")]
+    pub saved_path: Option<String>,
+}
+
 /// Event type passed from frontend to `sync_editor_message` command.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -644,6 +1019,9 @@ pub enum MessageEvent {
         #[serde(rename = "save_as")]
         save_as: bool,
     },
+    /// Message was changed by segment operations.
+    #[serde(rename = "segments_changed")]
+    SegmentsChanged { changes: Vec<SegmentChange> },
 }
 
 // ============================================================================
@@ -728,6 +1106,15 @@ pub struct Patch {
     /// Fields are auto-created when setting values; this is only for segments.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub create: Option<bool>,
+
+    /// Copy a segment to just after itself (path must be segment name only, e.g., "OBX[2]").
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duplicate: Option<bool>,
+
+    /// Move a segment to where another segment is now, e.g., "OBX[1]"
+    /// (path must be segment name only).
+    #[serde(rename = "moveTo", skip_serializing_if = "Option::is_none")]
+    pub move_to: Option<String>,
 }
 
 /// Result of `editor/patchMessage` response.
@@ -990,6 +1377,157 @@ pub struct SelectDirectoryResult {
     pub path: Option<String>,
 }
 
+// ============================================================================
+// Status bar types
+// ============================================================================
+
+/// Parameters for `ui/setStatusBarItem` request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetStatusBarItemParams {
+    /// Identifier of the item, unique within the extension.
+    pub id: String,
+    /// Text to show, or null to remove the item.
+    #[serde(default)]
+    pub text: Option<String>,
+    /// Tooltip shown on hover.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tooltip: Option<String>,
+    /// Command sent in `command/execute` when the item is clicked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+}
+
+/// Result of `ui/setStatusBarItem` response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SetStatusBarItemResult {
+    /// Whether the item was updated.
+    pub success: bool,
+}
+
+/// Parameters for `ui/progress` request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProgressParams {
+    /// Identifier of the operation, unique within the extension.
+    pub id: String,
+    /// Name of the operation, kept from earlier reports if omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// What the operation is doing now.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// Percentage complete (0-100), or null if it isn't known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percentage: Option<u8>,
+    /// Whether the operation has finished, removing it from the status bar.
+    #[serde(default)]
+    pub done: bool,
+}
+
+/// Result of `ui/progress` response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProgressResult {
+    /// Whether the progress was shown.
+    pub success: bool,
+}
+
+// ============================================================================
+// Transport types
+// ============================================================================
+
+/// Parameters for the `transport/send` request sent by Hermes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransportSendParams {
+    /// Name of the transport to send over.
+    pub transport: String,
+
+    /// Host from the connection preset.
+    pub host: String,
+
+    /// Port from the connection preset.
+    pub port: u16,
+
+    /// Transport-specific settings from the connection preset (queue, topic, path...).
+    #[serde(default)]
+    pub options: HashMap<String, String>,
+
+    /// The message to send, with placeholders and formulas filled in.
+    pub message: String,
+
+    /// MSH.10 of the message.
+    #[serde(rename = "controlId")]
+    pub control_id: Option<String>,
+
+    /// How long Hermes waits for the request to complete.
+    #[serde(rename = "timeoutSeconds")]
+    pub timeout_seconds: f32,
+}
+
+/// Result of `transport/send` response.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TransportSendResult {
+    /// Where the message was delivered, for logs (e.g., "QM1/ADT.IN").
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination: Option<String>,
+
+    /// Response message, or null if the transport got none.
+    #[serde(default)]
+    pub response: Option<String>,
+}
+
+// ============================================================================
+// Validation types
+// ============================================================================
+
+/// Parameters for the `validation/validate` request sent by Hermes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidateParams {
+    /// The message to check, as it is in the editor.
+    pub message: String,
+
+    /// File path if the message is saved.
+    #[serde(rename = "filePath", skip_serializing_if = "Option::is_none")]
+    pub file_path: Option<String>,
+}
+
+/// Result of `validation/validate` response.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ValidateResult {
+    /// Issues the extension found; empty if the message passed its checks.
+    #[serde(default)]
+    pub issues: Vec<ProvidedIssue>,
+}
+
+/// A validation issue found by an extension.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProvidedIssue {
+    /// HL7 path of the problem (e.g., "PID.3", "OBX[2].5.1").
+    pub path: String,
+
+    /// How serious the issue is.
+    pub severity: Severity,
+
+    /// Human-readable description of the issue.
+    pub message: String,
+
+    /// Identifier of the extension's rule, shown with the message.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+
+    /// Where to highlight, in characters of the message; found from `path` if omitted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub range: Option<IssueRange>,
+}
+
+/// A range of characters (Unicode code points) in a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IssueRange {
+    /// First character of the range.
+    pub start: usize,
+
+    /// Character after the end of the range.
+    pub end: usize,
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used, clippy::indexing_slicing)]
 mod tests {
@@ -1032,8 +1570,12 @@ mod tests {
                 commands: vec!["test/command".to_string()],
                 schema_provider: false,
                 events: vec![],
+                transports: vec![],
+                validation_provider: false,
             },
             toolbar_buttons: vec![],
+            templates: vec![],
+            settings: vec![],
             schema: None,
         };
 
@@ -1044,6 +1586,143 @@ mod tests {
         assert_eq!(metadata.capabilities.commands.len(), 1);
     }
 
+    #[test]
+    fn test_initialize_result_templates() {
+        let json = r#"{
+            "name": "Site Templates",
+            "version": "1.0.0",
+            "templates": [{"name": "Site ADT^A04", "command": "site/adt-a04"}]
+        }"#;
+        let result: InitializeResult = serde_json::from_str(json).unwrap();
+        let metadata: ExtensionMetadata = result.into();
+        assert_eq!(metadata.templates.len(), 1);
+        assert_eq!(metadata.templates[0].name, "Site ADT^A04");
+        assert_eq!(metadata.templates[0].command, "site/adt-a04");
+
+        // templates are optional
+        let json = r#"{"name": "Other", "version": "1.0.0"}"#;
+        let result: InitializeResult = serde_json::from_str(json).unwrap();
+        assert!(result.templates.is_empty());
+    }
+
+    #[test]
+    fn test_traffic_event_subscriptions() {
+        let json = r#"{"events": [
+            {"name": "message/beforeSend"},
+            {"name": "message/received", "options": {"includeContent": true, "format": "json"}}
+        ]}"#;
+        let capabilities: Capabilities = serde_json::from_str(json).unwrap();
+        assert_eq!(capabilities.events[0].name, EventName::MessageBeforeSend);
+        assert_eq!(capabilities.events[1].name, EventName::MessageReceived);
+        let options = capabilities.events[1].options.as_ref().unwrap();
+        assert!(options.include_content);
+        assert!(matches!(options.format, Some(MessageFormat::Json)));
+
+        // an empty result lets the send go ahead unchanged
+        let result: BeforeSendResult = serde_json::from_str("{}").unwrap();
+        assert!(result.message.is_none());
+        assert!(!result.veto);
+        let result: BeforeSendResult =
+            serde_json::from_str(r#"{"veto": true, "reason": "No PID segment"}"#).unwrap();
+        assert!(result.veto);
+        assert_eq!(result.reason.as_deref(), Some("No PID segment"));
+    }
+
+    #[test]
+    fn test_setting_definitions() {
+        let json = r#"{
+            "name": "Site Rules",
+            "version": "1.0.0",
+            "settings": [
+                {"key": "facility", "type": "string", "label": "Facility", "default": "MAIN"},
+                {"key": "strict", "type": "boolean"},
+                {"key": "region", "type": "choice", "choices": ["east", "west"]}
+            ]
+        }"#;
+        let result: InitializeResult = serde_json::from_str(json).unwrap();
+        assert_eq!(result.settings.len(), 3);
+        assert_eq!(result.settings[0].kind, SettingKind::String);
+        assert_eq!(result.settings[0].default, Some(serde_json::json!("MAIN")));
+
+        assert!(result.settings[0].check(&serde_json::json!("EAST")).is_ok());
+        assert!(result.settings[0].check(&serde_json::json!(1)).is_err());
+        assert!(result.settings[1].check(&serde_json::json!(true)).is_ok());
+        assert!(result.settings[2].check(&serde_json::json!("west")).is_ok());
+        assert!(result.settings[2]
+            .check(&serde_json::json!("north"))
+            .is_err());
+
+        // settings are optional, in the result and in the stored configuration
+        let json = r#"{"name": "Other", "version": "1.0.0"}"#;
+        let result: InitializeResult = serde_json::from_str(json).unwrap();
+        assert!(result.settings.is_empty());
+        let config: ExtensionConfig =
+            serde_json::from_str(r#"{"path": "/bin/ext", "enabled": true}"#).unwrap();
+        assert!(config.settings.is_empty());
+    }
+
+    #[test]
+    fn test_capabilities_transports() {
+        let json = r#"{"commands": [], "transports": [{"name": "ibm-mq", "label": "IBM MQ"}, {"name": "sftp"}]}"#;
+        let capabilities: Capabilities = serde_json::from_str(json).unwrap();
+        assert_eq!(capabilities.transports.len(), 2);
+        assert_eq!(capabilities.transports[0].name, "ibm-mq");
+        assert_eq!(capabilities.transports[0].label.as_deref(), Some("IBM MQ"));
+        assert_eq!(capabilities.transports[1].label, None);
+
+        // transports are optional
+        let capabilities: Capabilities = serde_json::from_str("{}").unwrap();
+        assert!(capabilities.transports.is_empty());
+    }
+
+    #[test]
+    fn test_segments_changed_event() {
+        let json = r#"{"segments_changed": {"changes": [{"operation": "delete", "indexBefore": 2, "indexAfter": null, "rangeBefore": {"start": 40, "end": 55}, "rangeAfter": null}]}}"#;
+        let event: MessageEvent = serde_json::from_str(json).unwrap();
+        let MessageEvent::SegmentsChanged { changes } = event else {
+            panic!("expected segments_changed event");
+        };
+        assert_eq!(changes[0].index_before, Some(2));
+
+        let params = MessageChangedParams {
+            message: None,
+            format: None,
+            has_file: false,
+            file_path: None,
+            segment_changes: Some(changes),
+        };
+        let value = serde_json::to_value(&params).unwrap();
+        assert_eq!(value["segmentChanges"][0]["rangeBefore"]["end"], 55);
+        assert_eq!(value["segmentChanges"][0]["operation"], "delete");
+    }
+
+    #[test]
+    fn test_validate_result() {
+        let json = r#"{"issues": [
+            {"path": "PID.3", "severity": "error", "message": "MRN must be 8 digits", "rule": "site-mrn"},
+            {"path": "PV1.2", "severity": "info", "message": "Unusual class", "range": {"start": 4, "end": 5}}
+        ]}"#;
+        let result: ValidateResult = serde_json::from_str(json).unwrap();
+        assert_eq!(result.issues.len(), 2);
+        assert_eq!(result.issues[0].severity, Severity::Error);
+        assert_eq!(result.issues[0].rule.as_deref(), Some("site-mrn"));
+        assert_eq!(
+            result.issues[1].range,
+            Some(IssueRange { start: 4, end: 5 })
+        );
+
+        // a provider with nothing to report may return an empty result
+        let result: ValidateResult = serde_json::from_str("{}").unwrap();
+        assert!(result.issues.is_empty());
+    }
+
+    #[test]
+    fn test_transport_send_result_defaults() {
+        let result: TransportSendResult = serde_json::from_str("{}").unwrap();
+        assert_eq!(result.destination, None);
+        assert_eq!(result.response, None);
+    }
+
     #[test]
     fn test_message_format_serialization() {
         assert_eq!(
@@ -1091,6 +1770,8 @@ mod tests {
             value: Some("DOE".to_string()),
             remove: None,
             create: Some(true),
+            duplicate: None,
+            move_to: None,
         };
 
         let json = serde_json::to_string(&patch).unwrap();
@@ -1098,6 +1779,12 @@ mod tests {
         assert!(json.contains("\"value\":\"DOE\""));
         assert!(json.contains("\"create\":true"));
         assert!(!json.contains("\"remove\"")); // should be skipped when None
+        assert!(!json.contains("\"moveTo\""));
+
+        let patch: Patch =
+            serde_json::from_str(r#"{"path": "NTE[1]", "moveTo": "OBX[2]"}"#).unwrap();
+        assert_eq!(patch.move_to.as_deref(), Some("OBX[2]"));
+        assert_eq!(patch.duplicate, None);
     }
 
     // ========================================================================
@@ -1223,7 +1910,9 @@ mod tests {
             datatype: Some(Nullable::Value("string".to_string())),
             placeholder: Some(Nullable::Value("Enter name".to_string())),
             values: Some(Nullable::Value(values_map.clone())),
+            table: None,
             template: Some(Nullable::Null),
+            classification: None,
         };
 
         let json = serde_json::to_string(&override_).unwrap();
@@ -11,6 +11,7 @@
 //! - [`commands`] - Tauri command handlers, grouped by feature:
 //!   - `communication/` - MLLP send/receive
 //!   - `editor/` - Cursor tracking, data manipulation, syntax highlighting
+//!   - `jobs/` - Declarative batch jobs
 //!   - `validation/` - Message validation and comparison
 //!   - `support/` - Field descriptions and schema queries
 //! - [`extensions`] - Extension system for third-party plugins
@@ -22,19 +23,28 @@
 //!
 //! Application state is managed via [`AppData`], which holds:
 //! - Cached HL7 schema
+//! - Location of persisted data
+//! - Findings of the startup integrity check of persisted stores
 //! - MLLP listener task handle
+//! - MLLP relay task handle
+//! - MLLP connections kept open across sends
+//! - Directory watch picking up dropped message files
+//! - Audit event exporter
 //! - Extension host for managing third-party extensions
+//! - Progress and settings of the shutdown sequence
 //! - Menu item references for dynamic enable/disable
 
 use color_eyre::eyre::Context;
 use commands::extensions::ui::SharedWindowManager;
 use schema::cache::SchemaCache;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tauri::menu::{CheckMenuItem, MenuItem, Submenu};
 use tauri::{Manager, Wry};
 use tokio::sync::Mutex;
 
 mod commands;
+mod events;
 mod extensions;
 mod menu;
 mod schema;
@@ -53,15 +63,56 @@ pub struct AppData {
     /// Handle to the MLLP listener background task.
     listen_join: Mutex<Option<tokio::task::JoinHandle<()>>>,
 
+    /// Handle to the MLLP relay background task.
+    relay_join: Mutex<Option<tokio::task::JoinHandle<()>>>,
+
+    /// MLLP connections opened by name and kept open across sends.
+    connections: commands::ConnectionPool,
+
+    /// Control IDs already sent or received, for duplicate warnings.
+    control_ids: commands::ControlIdHistory,
+
+    /// Undo and redo history of the message in the editor.
+    undo_history: commands::UndoHistory,
+
+    /// Watch picking up message files dropped into a directory, if running.
+    drop_watch: Mutex<Option<commands::DropWatch>>,
+
+    /// Directory holding all persisted data.
+    data_root: Mutex<commands::DataRoot>,
+
+    /// Findings of the integrity check of the stores under the data root.
+    startup_health: Mutex<commands::StartupHealth>,
+
+    /// Exporter forwarding communication audit events, if configured.
+    audit_export: Mutex<Option<commands::AuditExporter>>,
+
+    /// Repeated send currently running, if any.
+    schedule: Mutex<Option<commands::ScheduledSend>>,
+
+    /// Light validation scheduled by the editor, debouncing its edits.
+    validation_schedule: Mutex<commands::ValidationSchedule>,
+
+    /// Cancels the full validation running, if any.
+    full_validation: Mutex<Option<tokio_util::sync::CancellationToken>>,
+
+    /// Watches on the files open in the editor, by document, for detecting
+    /// external changes.
+    file_watch: Mutex<HashMap<commands::DocumentId, commands::FileWatch>>,
+
+    /// Watch reloading the schema when the external schema folder changes, if set.
+    schema_watch: Mutex<Option<commands::SchemaWatch>>,
+
+    /// Coordination of background work when the app closes.
+    shutdown: commands::Shutdown,
+
     /// Extension host for managing third-party extensions.
     pub extension_host: Mutex<extensions::ExtensionHost>,
 
-    /// Current editor message content, synced from frontend.
-    /// Wrapped in Arc so it can be shared with extension request handlers.
-    pub editor_message: Arc<Mutex<String>>,
-
-    /// Current editor file path, synced from frontend.
-    pub editor_file_path: Mutex<Option<String>>,
+    /// Documents open in the editor, with their contents synced from the
+    /// frontend. Wrapped in Arc so it can be shared with extension request
+    /// handlers and file watches.
+    pub documents: Arc<Mutex<commands::Documents>>,
 
     /// Reference to the Save menu item for dynamic enable/disable.
     pub save_menu_item: MenuItem<Wry>,
@@ -69,10 +120,10 @@ pub struct AppData {
     /// Reference to the Auto-Save checkable menu item for sync with settings.
     pub auto_save_menu_item: CheckMenuItem<Wry>,
 
-    /// Reference to the Undo menu item for dynamic enable/disable.
+    /// Reference to the Undo menu item, kept in step with the undo history.
     pub undo_menu_item: MenuItem<Wry>,
 
-    /// Reference to the Redo menu item for dynamic enable/disable.
+    /// Reference to the Redo menu item, kept in step with the undo history.
     pub redo_menu_item: MenuItem<Wry>,
 
     /// Reference to the "Open Recent" submenu for dynamic population.
@@ -81,6 +132,15 @@ pub struct AppData {
     /// Current list of recent file paths.
     pub recent_files: Mutex<Vec<String>>,
 
+    /// Reference to the "My Templates" submenu for dynamic population.
+    pub user_templates_submenu: Submenu<Wry>,
+
+    /// Reference to the "Extension Templates" submenu for dynamic population.
+    pub extension_templates_submenu: Submenu<Wry>,
+
+    /// Templates contributed by running extensions, as listed in the menu.
+    pub extension_templates: Mutex<Vec<extensions::host::TemplateInfo>>,
+
     /// Reference to the "Insert Current Timestamp" menu item for dynamic enable/disable.
     pub insert_timestamp_now_menu_item: MenuItem<Wry>,
 
@@ -133,53 +193,197 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             commands::syntax_highlight,
             commands::locate_cursor,
+            commands::get_completions,
             commands::get_range_of_next_field,
             commands::get_range_of_previous_field,
             commands::get_std_description,
+            commands::get_datatype_description,
+            commands::get_undescribed_segments,
             commands::get_messages_schema,
             commands::get_segment_schema,
+            commands::list_tables,
+            commands::get_table,
+            commands::get_schema_overrides,
+            commands::set_schema_overrides,
+            commands::set_field_override,
+            commands::remove_field_override,
+            commands::get_schema_directory,
+            commands::set_schema_directory,
+            commands::reload_schema,
+            commands::list_custom_segments,
+            commands::save_custom_segment,
+            commands::delete_custom_segment,
+            commands::get_schema_versions,
+            commands::set_schema_version,
+            commands::detect_message_version,
             commands::get_message_segment_names,
             commands::get_message_trigger_event,
             commands::get_message_type,
             commands::get_field_range,
+            commands::find_fields_by_name,
+            commands::parse_message_tree,
+            commands::get_field_metrics,
             commands::parse_message_segment,
             commands::render_message_segment,
             commands::generate_control_id,
+            commands::evaluate_formulas,
+            commands::run_script,
             commands::get_current_cell_range,
+            commands::get_cell_path,
+            commands::get_cell_value,
             commands::get_current_hl7_timestamp,
             commands::format_datetime_to_hl7,
+            commands::shift_timestamps,
             commands::parse_hl7_timestamp,
             commands::generate_template_message,
+            commands::generate_adt_workflow,
+            commands::build_result_block,
+            commands::apply_result_block,
+            commands::build_query_segment,
+            commands::apply_query,
+            commands::parse_query_parameters,
             commands::send_message,
+            commands::open_connection,
+            commands::close_connection,
+            commands::list_connections,
+            commands::list_connection_profiles,
+            commands::save_connection_profile,
+            commands::delete_connection_profile,
+            commands::clear_control_id_history,
             commands::start_listening,
             commands::stop_listening,
+            commands::list_listen_interfaces,
+            commands::start_drop_watch,
+            commands::stop_drop_watch,
+            commands::start_relay,
+            commands::stop_relay,
+            commands::export_listener_config,
+            commands::import_listener_config,
+            commands::import_engine_config,
+            commands::start_scheduled_send,
+            commands::stop_scheduled_send,
+            commands::configure_audit_export,
             menu::set_save_enabled,
             menu::set_auto_save_checked,
-            menu::set_undo_enabled,
-            menu::set_redo_enabled,
             menu::update_recent_files_menu,
             menu::set_insert_timestamp_enabled,
             menu::open_help_window,
             commands::compare_messages,
+            commands::compare_segments,
+            commands::match_expectation,
+            commands::export_diff,
+            commands::compare_directories,
+            commands::report_phi_exposure,
             commands::validate_light,
             commands::validate_full,
+            commands::cancel_full_validation,
+            commands::revalidate_light,
+            commands::schedule_validation,
+            commands::apply_fix,
+            commands::apply_all_fixes,
+            commands::list_validation_profiles,
+            commands::validate_with_profile,
+            commands::export_validation_report,
+            commands::run_job,
+            commands::list_pipelines,
+            commands::run_pipeline,
+            commands::run_pipeline_on_folder,
+            commands::list_mapping_tables,
+            commands::get_mapping_table,
+            commands::import_mapping_table,
+            commands::delete_mapping_table,
+            commands::list_test_cases,
+            commands::run_test_case,
+            commands::run_all_test_cases,
             commands::export_to_json,
             commands::export_to_yaml,
             commands::export_to_toml,
+            commands::export_to_fhir,
             commands::import_from_json,
             commands::import_from_yaml,
             commands::import_from_toml,
+            commands::import_from_fhir,
+            commands::export_to_xml,
+            commands::import_from_xml,
+            commands::extract_messages,
+            commands::extract_messages_from_file,
+            commands::import_capture,
+            commands::split_messages,
+            commands::find_message_at,
+            commands::join_messages,
+            commands::wrap_batch,
+            commands::unwrap_batch,
             commands::get_segment_index_at_cursor,
             commands::delete_segment,
             commands::move_segment,
+            commands::move_segment_to,
             commands::duplicate_segment,
+            commands::delete_segments,
+            commands::set_fields,
+            commands::get_document_metadata,
+            commands::list_user_templates,
+            commands::save_user_template,
+            commands::load_user_template,
+            commands::delete_user_template,
+            commands::list_snippets,
+            commands::save_snippet,
+            commands::delete_snippet,
+            commands::insert_snippet,
+            commands::list_annotations,
+            commands::add_annotation,
+            commands::resolve_annotation,
+            commands::delete_annotation,
+            commands::reset_undo_history,
+            commands::record_edit,
+            commands::undo_edit,
+            commands::redo_edit,
+            commands::checkpoint_history,
+            commands::revert_to_checkpoint,
+            commands::save_undo_history,
+            commands::create_document,
+            commands::close_document,
+            commands::switch_document,
+            commands::list_documents,
+            commands::set_document_metadata,
+            commands::convert_name_format,
+            commands::encode_escapes,
+            commands::decode_escapes,
+            commands::remap_delimiters,
+            commands::normalize_message,
+            commands::find_embedded_payloads,
+            commands::open_embedded_payload,
+            commands::save_embedded_payload,
+            commands::replace_embedded_payload,
+            commands::attach_document,
+            commands::attach_document_reference,
+            commands::watch_file,
+            commands::unwatch_file,
+            commands::resolve_external_change,
+            commands::compare_with_disk,
             commands::get_extensions,
             commands::get_extension_toolbar_buttons,
+            commands::get_extension_transports,
             commands::get_extension_logs,
+            commands::get_extension_trace,
             commands::reload_extensions,
             commands::send_extension_command,
+            commands::generate_extension_template,
+            commands::update_extension_settings,
+            commands::list_available_extensions,
+            commands::list_installed_extensions,
+            commands::install_extension,
+            commands::update_extension,
+            commands::uninstall_extension,
+            commands::test_extension,
             commands::sync_editor_message,
             commands::open_url,
+            commands::list_samples,
+            commands::load_sample,
+            commands::get_data_root,
+            commands::set_data_root,
+            commands::get_startup_health,
+            commands::configure_shutdown,
+            commands::session_saved,
         ])
         .setup(|app| {
             let menu_items =
@@ -187,11 +391,20 @@ pub fn run() {
 
             menu::setup_menu_event_handler(app);
 
-            // get app data directory for extensions
-            let data_dir = app
-                .path()
-                .app_data_dir()
-                .wrap_err_with(|| "Failed to get app data directory")?;
+            // resolve the data root (app data directory, portable or chosen folder)
+            let data_root = commands::resolve_data_root(app.handle())
+                .map_err(|e| color_eyre::eyre::eyre!(e))?;
+            log::info!("using data folder {}", data_root.path.display());
+
+            // repair any store left corrupt by an interrupted write before the
+            // frontend tries to load it
+            let startup_health = commands::check_stores(&data_root.path);
+            if !startup_health.issues.is_empty() {
+                log::warn!(
+                    "repaired {} persisted store(s) at startup",
+                    startup_health.issues.len()
+                );
+            }
 
             // get hermes version from cargo package
             let hermes_version = env!("CARGO_PKG_VERSION").to_string();
@@ -199,28 +412,117 @@ pub fn run() {
             // create window manager for extension windows
             let window_manager = commands::extensions::ui::create_window_manager();
 
+            // parse only the messages schema up front; segment schemas come from the
+            // binary cache if it's current, or are parsed on first use
+            let started = std::time::Instant::now();
+            let schema = SchemaCache::new().wrap_err("failed to initialise schema cache")?;
+            let schema_cache_path = data_root.path.join(schema::cache::BINARY_CACHE_FILE);
+            let schema_cache_loaded = schema.load_binary_cache(&schema_cache_path);
+            if schema_cache_loaded {
+                log::info!("loaded schema cache in {:?}", started.elapsed());
+            }
+            schema.set_user_overrides(commands::load_schema_overrides(
+                app.handle(),
+                &data_root.path,
+            ));
+            schema.set_custom_segments(schema::custom::CustomSegment::load_all(
+                &data_root.path.join(schema::custom::CUSTOM_SEGMENTS_DIR),
+            ));
+            let schema_directory = commands::load_schema_directory(app.handle(), &data_root.path);
+
             // create extension host
-            let extension_host =
-                extensions::ExtensionHost::new(app.handle().clone(), data_dir, hermes_version);
+            let extension_host = extensions::ExtensionHost::new(
+                app.handle().clone(),
+                data_root.path.clone(),
+                hermes_version,
+            );
+
+            let templates_dir = data_root.path.join(commands::TEMPLATES_DIR);
+            let control_ids = commands::ControlIdHistory::load(app.handle(), &data_root.path);
 
             let app_data = AppData {
-                schema: SchemaCache::new().wrap_err("failed to initialise schema cache")?,
+                schema,
+                data_root: Mutex::new(data_root),
+                startup_health: Mutex::new(startup_health),
                 listen_join: Mutex::new(None),
+                relay_join: Mutex::new(None),
+                connections: commands::ConnectionPool::default(),
+                control_ids,
+                undo_history: commands::UndoHistory::default(),
+                drop_watch: Mutex::new(None),
+                audit_export: Mutex::new(None),
+                schedule: Mutex::new(None),
+                validation_schedule: Mutex::new(commands::ValidationSchedule::default()),
+                full_validation: Mutex::new(None),
+                file_watch: Mutex::new(HashMap::new()),
+                schema_watch: Mutex::new(None),
+                shutdown: commands::Shutdown::default(),
                 extension_host: Mutex::new(extension_host),
-                editor_message: Arc::new(Mutex::new(String::new())),
-                editor_file_path: Mutex::new(None),
+                documents: Arc::new(Mutex::new(commands::Documents::default())),
                 save_menu_item: menu_items.save_menu_item,
                 auto_save_menu_item: menu_items.auto_save_menu_item,
                 undo_menu_item: menu_items.undo_menu_item,
                 redo_menu_item: menu_items.redo_menu_item,
                 recent_files_submenu: menu_items.recent_files_submenu,
                 recent_files: Mutex::new(Vec::new()),
+                user_templates_submenu: menu_items.user_templates_submenu,
+                extension_templates_submenu: menu_items.extension_templates_submenu,
+                extension_templates: Mutex::new(Vec::new()),
                 insert_timestamp_now_menu_item: menu_items.insert_timestamp_now_menu_item,
                 insert_timestamp_menu_item: menu_items.insert_timestamp_menu_item,
                 window_manager,
             };
             app.manage(app_data);
 
+            // list the user templates saved under the data root in the menu
+            if let Err(e) = menu::refresh_user_templates_menu(
+                app.handle(),
+                &app.state::<AppData>(),
+                &templates_dir,
+            ) {
+                log::warn!("failed to list user templates: {e}");
+            }
+
+            // load the schema folder, if set, before the frontend asks for schemas
+            if let Some(directory) = schema_directory {
+                let handle = app.handle().clone();
+                tauri::async_runtime::block_on(async move {
+                    let state = handle.state::<AppData>();
+                    if let Err(e) =
+                        commands::apply_schema_directory(&handle, &state, Some(directory)).await
+                    {
+                        log::warn!("{e}");
+                    }
+                });
+            }
+
+            // rebuild the binary cache off the startup path so the next launch can use it
+            if !schema_cache_loaded
+                && app
+                    .state::<AppData>()
+                    .schema
+                    .external_schema_directory()
+                    .is_none()
+            {
+                let handle = app.handle().clone();
+                tauri::async_runtime::spawn_blocking(move || {
+                    let state = handle.state::<AppData>();
+                    let started = std::time::Instant::now();
+                    let result = state
+                        .schema
+                        .parse_all()
+                        .and_then(|()| state.schema.save_binary_cache(&schema_cache_path));
+                    match result {
+                        Ok(()) => log::info!(
+                            "wrote schema cache {} in {:?}",
+                            schema_cache_path.display(),
+                            started.elapsed()
+                        ),
+                        Err(e) => log::warn!("failed to write schema cache: {e:#}"),
+                    }
+                });
+            }
+
             #[cfg(debug_assertions)]
             {
                 if let Some(window) = app.get_webview_window("main") {
@@ -233,6 +535,25 @@ pub fn run() {
 
             Ok(())
         })
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // stop background work in order before exiting, rather than
+            // dropping whatever was in flight
+            if let tauri::RunEvent::ExitRequested { api, .. } = &event {
+                if !commands::request_exit(app) {
+                    api.prevent_exit();
+                }
+            }
+            if let tauri::RunEvent::WindowEvent {
+                label,
+                event: tauri::WindowEvent::CloseRequested { api, .. },
+                ..
+            } = &event
+            {
+                if label == "main" && !commands::request_exit(app) {
+                    api.prevent_close();
+                }
+            }
+        });
 }
@@ -16,6 +16,7 @@
 //! 1. User clicks a menu item
 //! 2. `setup_menu_event_handler` receives the event
 //! 3. Event is mapped to a frontend event name (e.g., `file-save` → `menu-file-save`)
+//!    through the table in [`crate::events`]
 //! 4. Frontend event listeners handle the action
 //!
 //! # Dynamic State
@@ -24,6 +25,8 @@
 //! - **Save/Undo/Redo** - Enabled based on document state
 //! - **Auto-Save** - Checked state synced with settings
 //! - **Recent Files** - Rebuilt when files are opened/saved
+//! - **My Templates** - Rebuilt when user templates are saved or deleted
+//! - **Extension Templates** - Rebuilt when extensions are reloaded
 //! - **Timestamp items** - Enabled only when cursor is in a valid field
 //!
 //! The [`state`] submodule provides Tauri commands for these updates.
@@ -38,6 +41,8 @@ use tauri::menu::{
 };
 use tauri::{App, Emitter, Manager, Wry};
 
+use crate::commands::SampleCategory;
+use crate::events;
 use crate::AppData;
 
 /// Menu item references for dynamic state management.
@@ -50,6 +55,8 @@ pub struct MenuItems {
     pub undo_menu_item: MenuItem<Wry>,
     pub redo_menu_item: MenuItem<Wry>,
     pub recent_files_submenu: Submenu<Wry>,
+    pub user_templates_submenu: Submenu<Wry>,
+    pub extension_templates_submenu: Submenu<Wry>,
     pub insert_timestamp_now_menu_item: MenuItem<Wry>,
     pub insert_timestamp_menu_item: MenuItem<Wry>,
 }
@@ -78,8 +85,21 @@ pub fn build_menu(app: &App) -> color_eyre::Result<MenuItems> {
         .enabled(false)
         .build()?;
 
+    // Build the "My Templates" submenu (starts empty, populated from the data root)
+    let user_templates_submenu = SubmenuBuilder::new(app, "&My Templates")
+        .id("file-user-templates")
+        .enabled(false)
+        .build()?;
+
+    // Build the "Extension Templates" submenu (starts empty, populated when extensions load)
+    let extension_templates_submenu = SubmenuBuilder::new(app, "&Extension Templates")
+        .id("file-extension-templates")
+        .enabled(false)
+        .build()?;
+
     // Build the "New from Template" submenu with pre-populated message types
-    let template_submenu = build_template_submenu(app)?;
+    let template_submenu =
+        build_template_submenu(app, &user_templates_submenu, &extension_templates_submenu)?;
 
     // Build the "Export As" submenu for exporting to different formats
     let export_submenu = SubmenuBuilder::new(app, "&Export As")
@@ -99,6 +119,17 @@ pub fn build_menu(app: &App) -> color_eyre::Result<MenuItems> {
                 .id("file-export-toml")
                 .build(app)?,
         )
+        .item(
+            &MenuItemBuilder::new("&XML (v2.xml)...")
+                .id("file-export-xml")
+                .build(app)?,
+        )
+        .separator()
+        .item(
+            &MenuItemBuilder::new("&FHIR Bundle...")
+                .id("file-export-fhir")
+                .build(app)?,
+        )
         .build()?;
 
     // Build the "Import From" submenu for importing from different formats
@@ -119,6 +150,17 @@ pub fn build_menu(app: &App) -> color_eyre::Result<MenuItems> {
                 .id("file-import-toml")
                 .build(app)?,
         )
+        .item(
+            &MenuItemBuilder::new("&XML (v2.xml)...")
+                .id("file-import-xml")
+                .build(app)?,
+        )
+        .separator()
+        .item(
+            &MenuItemBuilder::new("&FHIR Bundle...")
+                .id("file-import-fhir")
+                .build(app)?,
+        )
         .build()?;
 
     // Build the File menu with standard file operations
@@ -212,6 +254,8 @@ pub fn build_menu(app: &App) -> color_eyre::Result<MenuItems> {
         undo_menu_item,
         redo_menu_item,
         recent_files_submenu,
+        user_templates_submenu,
+        extension_templates_submenu,
         insert_timestamp_now_menu_item,
         insert_timestamp_menu_item,
     })
@@ -222,49 +266,14 @@ pub fn setup_menu_event_handler(app: &App) {
     app.on_menu_event(move |app_handle, event| {
         let event_id = event.id().as_ref();
 
-        // handle standard menu events (emit empty payload)
-        let event_name = match event_id {
-            "file-new" => Some("menu-file-new"),
-            "file-open" => Some("menu-file-open"),
-            "file-save" => Some("menu-file-save"),
-            "file-save-as" => Some("menu-file-save-as"),
-            "file-export-json" => Some("menu-file-export-json"),
-            "file-export-yaml" => Some("menu-file-export-yaml"),
-            "file-export-toml" => Some("menu-file-export-toml"),
-            "file-import-json" => Some("menu-file-import-json"),
-            "file-import-yaml" => Some("menu-file-import-yaml"),
-            "file-import-toml" => Some("menu-file-import-toml"),
-            "file-auto-save" => Some("menu-file-auto-save"),
-            "edit-undo" => Some("menu-edit-undo"),
-            "edit-redo" => Some("menu-edit-redo"),
-            "edit-find" => Some("menu-edit-find"),
-            "edit-find-replace" => Some("menu-edit-find-replace"),
-            "edit-jump-to-field" => Some("menu-edit-jump-to-field"),
-            "edit-delete-segment" => Some("menu-edit-delete-segment"),
-            "edit-move-segment-up" => Some("menu-edit-move-segment-up"),
-            "edit-move-segment-down" => Some("menu-edit-move-segment-down"),
-            "edit-duplicate-segment" => Some("menu-edit-duplicate-segment"),
-            "view-zoom-in" => Some("menu-view-zoom-in"),
-            "view-zoom-out" => Some("menu-view-zoom-out"),
-            "view-reset-zoom" => Some("menu-view-reset-zoom"),
-            "view-keyboard-shortcuts" => Some("menu-view-keyboard-shortcuts"),
-            "tools-send" => Some("menu-tools-send"),
-            "tools-listen" => Some("menu-tools-listen"),
-            "tools-validate" => Some("menu-tools-validate"),
-            "tools-compare" => Some("menu-tools-compare"),
-            "tools-generate-control-id" => Some("menu-tools-generate-control-id"),
-            "tools-insert-timestamp-now" => Some("menu-tools-insert-timestamp-now"),
-            "tools-insert-timestamp" => Some("menu-tools-insert-timestamp"),
-            "recent-clear" => Some("menu-clear-recent"),
-            "help" => Some("menu-help"),
-            "help-check-updates" => {
-                crate::updater::handle_check_updates(app_handle);
-                return;
-            }
-            _ => None,
-        };
+        // checking for updates is handled here rather than in the frontend
+        if event_id == "help-check-updates" {
+            crate::updater::handle_check_updates(app_handle);
+            return;
+        }
 
-        if let Some(name) = event_name {
+        // handle standard menu events (emit empty payload)
+        if let Some(name) = events::menu_event(event_id) {
             // emit to main window only so other windows can handle shortcuts independently
             let _ = app_handle.emit_to("main", name, ());
             return;
@@ -276,21 +285,57 @@ pub fn setup_menu_event_handler(app: &App) {
                 if let Some(state) = app_handle.try_state::<AppData>() {
                     let recent_files = state.recent_files.blocking_lock();
                     if let Some(file_path) = recent_files.get(index) {
-                        let _ = app_handle.emit("menu-open-recent", file_path.clone());
+                        let _ = app_handle.emit(events::MENU_OPEN_RECENT, file_path.clone());
                     }
                 }
             }
             return;
         }
 
+        // handle extension template menu items (emit the template as payload)
+        if let Some(index_str) = event_id.strip_prefix("extension-template-") {
+            if let Ok(index) = index_str.parse::<usize>() {
+                if let Some(state) = app_handle.try_state::<AppData>() {
+                    let templates = state.extension_templates.blocking_lock();
+                    if let Some(template) = templates.get(index) {
+                        let _ = app_handle
+                            .emit(events::MENU_NEW_FROM_EXTENSION_TEMPLATE, template.clone());
+                    }
+                }
+            }
+            return;
+        }
+
+        // handle user template menu items (emit template id as payload)
+        if let Some(template_id) = event_id.strip_prefix("user-template-") {
+            let _ = app_handle.emit(events::MENU_NEW_FROM_USER_TEMPLATE, template_id);
+            return;
+        }
+
         // handle template menu items (emit template name as payload)
         if let Some(template_name) = event_id.strip_prefix("template-") {
-            let _ = app_handle.emit("menu-new-from-template", template_name);
+            let _ = app_handle.emit(events::MENU_NEW_FROM_TEMPLATE, template_name);
+            return;
+        }
+
+        // handle sample gallery menu items (emit sample id as payload)
+        if let Some(sample_id) = event_id.strip_prefix("sample-") {
+            let _ = app_handle.emit(events::MENU_OPEN_SAMPLE, sample_id);
+            return;
+        }
+
+        // handle name/address conversion menu items (emit style as payload)
+        if let Some(style) = event_id.strip_prefix("convert-name-") {
+            let _ = app_handle.emit_to("main", events::MENU_TOOLS_CONVERT_NAME, style);
         }
     });
 }
 
-fn build_template_submenu(app: &App) -> color_eyre::Result<Submenu<Wry>> {
+fn build_template_submenu(
+    app: &App,
+    user_templates_submenu: &Submenu<Wry>,
+    extension_templates_submenu: &Submenu<Wry>,
+) -> color_eyre::Result<Submenu<Wry>> {
     let submenu = SubmenuBuilder::new(app, "New from &Template")
         .id("file-new-from-template")
         // ADT messages
@@ -386,11 +431,87 @@ fn build_template_submenu(app: &App) -> color_eyre::Result<Submenu<Wry>> {
                 .id("template-dft_p03")
                 .build(app)?,
         )
+        .separator()
+        // Document messages
+        .item(
+            &MenuItemBuilder::new("MDM^T01 (Document Notification)")
+                .id("template-mdm_t01")
+                .build(app)?,
+        )
+        .item(
+            &MenuItemBuilder::new("MDM^T02 (Document with Content)")
+                .id("template-mdm_t02")
+                .build(app)?,
+        )
+        .separator()
+        // Scheduling and immunization messages
+        .item(&build_siu_template_submenu(app)?)
+        .item(
+            &MenuItemBuilder::new("VXU^V04 (Vaccination Record)")
+                .id("template-vxu_v04")
+                .build(app)?,
+        )
+        .separator()
+        // Query messages
+        .item(
+            &MenuItemBuilder::new("QBP^Q22 (Find Candidates)")
+                .id("template-qbp_q22")
+                .build(app)?,
+        )
+        .item(
+            &MenuItemBuilder::new("QRY^A19 (Patient Query)")
+                .id("template-qry_a19")
+                .build(app)?,
+        )
+        .separator()
+        // User and extension templates
+        .item(user_templates_submenu)
+        .item(extension_templates_submenu)
+        .item(
+            &MenuItemBuilder::new("Template &Library...")
+                .id("file-template-library")
+                .build(app)?,
+        )
         .build()?;
 
     Ok(submenu)
 }
 
+/// Build the submenu of SIU templates, one per trigger event.
+///
+/// Every SIU trigger event shares one message structure, so they're grouped
+/// rather than listed alongside the other templates.
+fn build_siu_template_submenu(app: &App) -> color_eyre::Result<Submenu<Wry>> {
+    let triggers = [
+        ("S12", "New Appointment"),
+        ("S13", "Reschedule"),
+        ("S14", "Modify Appointment"),
+        ("S15", "Cancel Appointment"),
+        ("S16", "Discontinue Appointment"),
+        ("S17", "Delete Appointment"),
+        ("S18", "Add Service/Resource"),
+        ("S19", "Modify Service/Resource"),
+        ("S20", "Cancel Service/Resource"),
+        ("S21", "Discontinue Service/Resource"),
+        ("S22", "Delete Service/Resource"),
+        ("S23", "Block Time Slots"),
+        ("S24", "Open Time Slots"),
+        ("S26", "No-Show"),
+    ];
+
+    let mut submenu =
+        SubmenuBuilder::new(app, "SIU (&Scheduling)").id("file-new-from-template-siu");
+    for (trigger, label) in triggers {
+        submenu = submenu.item(
+            &MenuItemBuilder::new(format!("SIU^{trigger} ({label})"))
+                .id(format!("template-siu_{}", trigger.to_lowercase()))
+                .build(app)?,
+        );
+    }
+
+    Ok(submenu.build()?)
+}
+
 fn build_edit_menu(
     app: &App,
     undo_menu_item: &MenuItem<Wry>,
@@ -431,13 +552,46 @@ fn build_edit_menu(
         .accelerator("CmdOrCtrl+Shift+D")
         .build(app)?;
 
+    let insert_snippet_menu_item = MenuItemBuilder::new("Insert S&nippet...")
+        .id("edit-insert-snippet")
+        .build(app)?;
+
+    let attach_document_menu_item = MenuItemBuilder::new("&Attach Document...")
+        .id("edit-attach-document")
+        .build(app)?;
+
+    let copy_field_path_menu_item = MenuItemBuilder::new("Copy Field Pat&h")
+        .id("edit-copy-field-path")
+        .build(app)?;
+
+    let copy_field_value_menu_item = MenuItemBuilder::new("Copy Field Va&lue")
+        .id("edit-copy-field-value")
+        .build(app)?;
+
+    let paste_escaped_menu_item = MenuItemBuilder::new("Paste E&scaped")
+        .id("edit-paste-escaped")
+        .build(app)?;
+
+    let encode_escapes_menu_item = MenuItemBuilder::new("Escape Deli&miters")
+        .id("edit-encode-escapes")
+        .build(app)?;
+
+    let decode_escapes_menu_item = MenuItemBuilder::new("Decode Escape Se&quences")
+        .id("edit-decode-escapes")
+        .build(app)?;
+
     let menu = SubmenuBuilder::new(app, "&Edit")
         .item(undo_menu_item)
         .item(redo_menu_item)
         .separator()
         .item(&PredefinedMenuItem::cut(app, None)?)
         .item(&PredefinedMenuItem::copy(app, None)?)
+        .item(&copy_field_path_menu_item)
+        .item(&copy_field_value_menu_item)
         .item(&PredefinedMenuItem::paste(app, None)?)
+        .item(&paste_escaped_menu_item)
+        .item(&encode_escapes_menu_item)
+        .item(&decode_escapes_menu_item)
         .separator()
         .item(&find_menu_item)
         .item(&find_replace_menu_item)
@@ -447,6 +601,8 @@ fn build_edit_menu(
         .item(&move_segment_up_menu_item)
         .item(&move_segment_down_menu_item)
         .item(&duplicate_segment_menu_item)
+        .item(&insert_snippet_menu_item)
+        .item(&attach_document_menu_item)
         .separator()
         .item(&PredefinedMenuItem::select_all(app, None)?)
         .build()?;
@@ -464,6 +620,8 @@ fn build_help_menu(app: &App) -> color_eyre::Result<Submenu<Wry>> {
         .id("help-check-updates")
         .build(app)?;
 
+    let sample_gallery_submenu = build_sample_gallery_submenu(app)?;
+
     let about_metadata = AboutMetadata {
         name: Some(env!("CARGO_PKG_NAME").into()),
         version: Some(env!("CARGO_PKG_VERSION").into()),
@@ -480,6 +638,7 @@ fn build_help_menu(app: &App) -> color_eyre::Result<Submenu<Wry>> {
 
     let menu = SubmenuBuilder::new(app, "&Help")
         .item(&help_menu_item)
+        .item(&sample_gallery_submenu)
         .separator()
         .item(&check_updates_menu_item)
         .separator()
@@ -493,6 +652,35 @@ fn build_help_menu(app: &App) -> color_eyre::Result<Submenu<Wry>> {
     Ok(menu)
 }
 
+/// Build the "Sample Gallery" submenu from the embedded samples.
+///
+/// Samples are grouped by category, in gallery order, with a separator between
+/// categories.
+fn build_sample_gallery_submenu(app: &App) -> color_eyre::Result<Submenu<Wry>> {
+    let mut submenu = SubmenuBuilder::new(app, "Sample &Gallery").id("help-sample-gallery");
+
+    let samples = crate::commands::gallery();
+    let categories = [
+        SampleCategory::Example,
+        SampleCategory::EdgeCase,
+        SampleCategory::Broken,
+    ];
+    for (index, category) in categories.into_iter().enumerate() {
+        if index > 0 {
+            submenu = submenu.separator();
+        }
+        for sample in samples.iter().filter(|sample| sample.category == category) {
+            submenu = submenu.item(
+                &MenuItemBuilder::new(&sample.name)
+                    .id(format!("sample-{}", sample.id))
+                    .build(app)?,
+            );
+        }
+    }
+
+    Ok(submenu.build()?)
+}
+
 fn build_view_menu(app: &App) -> color_eyre::Result<Submenu<Wry>> {
     let menu = SubmenuBuilder::new(app, "&View")
         .item(
@@ -543,6 +731,16 @@ fn build_tools_menu(
                 .accelerator("CmdOrCtrl+L")
                 .build(app)?,
         )
+        .item(
+            &MenuItemBuilder::new("Connection &Profiles...")
+                .id("tools-connection-profiles")
+                .build(app)?,
+        )
+        .item(
+            &MenuItemBuilder::new("&Forget Transmitted Control IDs")
+                .id("tools-forget-control-ids")
+                .build(app)?,
+        )
         .separator()
         .item(
             &MenuItemBuilder::new("&Validate Message")
@@ -556,6 +754,71 @@ fn build_tools_menu(
                 .accelerator("CmdOrCtrl+D")
                 .build(app)?,
         )
+        .item(
+            &MenuItemBuilder::new("Compare &Segments...")
+                .id("tools-compare-segments")
+                .build(app)?,
+        )
+        .item(
+            &MenuItemBuilder::new("Match &Expectation...")
+                .id("tools-match-expectation")
+                .build(app)?,
+        )
+        .item(
+            &MenuItemBuilder::new("P&HI Exposure Report...")
+                .id("tools-phi-report")
+                .build(app)?,
+        )
+        .item(
+            &MenuItemBuilder::new("Run &Job...")
+                .id("tools-run-job")
+                .build(app)?,
+        )
+        .item(
+            &MenuItemBuilder::new("Script &Console...")
+                .id("tools-script-console")
+                .build(app)?,
+        )
+        .item(
+            &MenuItemBuilder::new("Pipe&lines...")
+                .id("tools-pipelines")
+                .build(app)?,
+        )
+        .item(
+            &MenuItemBuilder::new("&Mapping Tables...")
+                .id("tools-mapping-tables")
+                .build(app)?,
+        )
+        .item(
+            &MenuItemBuilder::new("E&xtract Messages from Log...")
+                .id("tools-extract-messages")
+                .build(app)?,
+        )
+        .item(
+            &MenuItemBuilder::new("Extract Messages from &Capture...")
+                .id("tools-import-capture")
+                .build(app)?,
+        )
+        .item(
+            &MenuItemBuilder::new("&Test Cases...")
+                .id("tools-test-cases")
+                .build(app)?,
+        )
+        .item(
+            &MenuItemBuilder::new("Generate &ADT Encounter...")
+                .id("tools-adt-workflow")
+                .build(app)?,
+        )
+        .item(
+            &MenuItemBuilder::new("Build &Result Block...")
+                .id("tools-result-block")
+                .build(app)?,
+        )
+        .item(
+            &MenuItemBuilder::new("Build &Query...")
+                .id("tools-query-builder")
+                .build(app)?,
+        )
         .separator()
         .item(
             &MenuItemBuilder::new("&Generate Control ID")
@@ -563,13 +826,61 @@ fn build_tools_menu(
                 .accelerator("CmdOrCtrl+G")
                 .build(app)?,
         )
+        .item(
+            &MenuItemBuilder::new("Evaluate &Formulas")
+                .id("tools-evaluate-formulas")
+                .build(app)?,
+        )
+        .item(&build_convert_name_submenu(app)?)
+        .item(
+            &MenuItemBuilder::new("Embedded &Documents...")
+                .id("tools-embedded-documents")
+                .build(app)?,
+        )
         .item(insert_timestamp_now_menu_item)
         .item(insert_timestamp_menu_item)
+        .separator()
+        .item(
+            &MenuItemBuilder::new("&Wrap in Batch")
+                .id("tools-wrap-batch")
+                .build(app)?,
+        )
+        .item(
+            &MenuItemBuilder::new("&Unwrap Batch")
+                .id("tools-unwrap-batch")
+                .build(app)?,
+        )
         .build()?;
 
     Ok(menu)
 }
 
+/// Build the "Convert Name/Address" submenu.
+///
+/// Item ids are `convert-name-` followed by the conversion's style.
+fn build_convert_name_submenu(app: &App) -> color_eyre::Result<Submenu<Wry>> {
+    let styles = [
+        ("&Split \"Family, Given\" Name", "split-name"),
+        ("&Join Name Components", "join-name"),
+        ("&Upper Case Name", "upper-case"),
+        ("&Title Case Name", "title-case"),
+        ("&Person Name to Participant", "person-to-participant"),
+        ("P&articipant to Person Name", "participant-to-person"),
+        ("Split A&ddress", "split-address"),
+        ("Drop Su&bcomponents", "drop-subcomponents"),
+    ];
+
+    let mut submenu = SubmenuBuilder::new(app, "Convert &Name/Address").id("tools-convert-name");
+    for (label, style) in styles {
+        submenu = submenu.item(
+            &MenuItemBuilder::new(label)
+                .id(format!("convert-name-{style}"))
+                .build(app)?,
+        );
+    }
+    Ok(submenu.build()?)
+}
+
 fn build_window_menu(app: &App) -> color_eyre::Result<Submenu<Wry>> {
     let menu = SubmenuBuilder::new(app, "&Window")
         .item(&PredefinedMenuItem::minimize(app, None)?)
@@ -8,6 +8,7 @@ use tauri::menu::{MenuItemBuilder, PredefinedMenuItem};
 use tauri::webview::WebviewWindowBuilder;
 use tauri::{AppHandle, Manager, State, WebviewUrl};
 
+use crate::extensions::host::TemplateInfo;
 use crate::AppData;
 
 /// Set the enabled state of the Save menu item.
@@ -19,24 +20,6 @@ pub fn set_save_enabled(enabled: bool, state: State<'_, AppData>) -> Result<(),
         .map_err(|e| format!("Failed to set save menu enabled state: {e}"))
 }
 
-/// Set the enabled state of the Undo menu item.
-#[tauri::command]
-pub fn set_undo_enabled(enabled: bool, state: State<'_, AppData>) -> Result<(), String> {
-    state
-        .undo_menu_item
-        .set_enabled(enabled)
-        .map_err(|e| format!("Failed to set undo menu enabled state: {e}"))
-}
-
-/// Set the enabled state of the Redo menu item.
-#[tauri::command]
-pub fn set_redo_enabled(enabled: bool, state: State<'_, AppData>) -> Result<(), String> {
-    state
-        .redo_menu_item
-        .set_enabled(enabled)
-        .map_err(|e| format!("Failed to set redo menu enabled state: {e}"))
-}
-
 /// Set the checked state of the Auto-Save menu item.
 #[tauri::command]
 pub fn set_auto_save_checked(checked: bool, state: State<'_, AppData>) -> Result<(), String> {
@@ -114,6 +97,86 @@ pub async fn update_recent_files_menu(
     Ok(())
 }
 
+/// Rebuild the "My Templates" submenu from the templates in a folder.
+///
+/// Not a command: called at startup, when the data root changes, and after a
+/// template is saved or deleted.
+///
+/// # Arguments
+/// * `app` - Tauri app handle, for building menu items
+/// * `state` - Application state holding the submenu
+/// * `directory` - Folder holding the user templates
+pub fn refresh_user_templates_menu(
+    app: &AppHandle,
+    state: &AppData,
+    directory: &Path,
+) -> Result<(), String> {
+    let templates = crate::commands::list_templates_in(directory)?;
+    let submenu = &state.user_templates_submenu;
+
+    // remove all existing items
+    while let Ok(Some(item)) = submenu.remove_at(0) {
+        drop(item);
+    }
+
+    submenu
+        .set_enabled(!templates.is_empty())
+        .map_err(|e| format!("Failed to set user templates menu enabled state: {e}"))?;
+
+    for template in templates {
+        let menu_item = MenuItemBuilder::new(&template.name)
+            .id(format!("user-template-{}", template.id))
+            .build(app)
+            .map_err(|e| format!("Failed to build user template menu item: {e}"))?;
+
+        submenu
+            .append(&menu_item)
+            .map_err(|e| format!("Failed to append user template to menu: {e}"))?;
+    }
+
+    Ok(())
+}
+
+/// Rebuild the "Extension Templates" submenu from the templates extensions contribute.
+///
+/// Not a command: called after extensions are reloaded. The templates are kept
+/// in `AppData` so a chosen menu item can be looked up by its index.
+///
+/// # Arguments
+/// * `app` - Tauri app handle, for building menu items
+/// * `state` - Application state holding the submenu
+/// * `templates` - Templates contributed by the running extensions
+pub async fn refresh_extension_templates_menu(
+    app: &AppHandle,
+    state: &AppData,
+    templates: Vec<TemplateInfo>,
+) -> Result<(), String> {
+    let submenu = &state.extension_templates_submenu;
+
+    // remove all existing items
+    while let Ok(Some(item)) = submenu.remove_at(0) {
+        drop(item);
+    }
+
+    submenu
+        .set_enabled(!templates.is_empty())
+        .map_err(|e| format!("Failed to set extension templates menu enabled state: {e}"))?;
+
+    for (index, template) in templates.iter().enumerate() {
+        let menu_item = MenuItemBuilder::new(&template.name)
+            .id(format!("extension-template-{index}"))
+            .build(app)
+            .map_err(|e| format!("Failed to build extension template menu item: {e}"))?;
+
+        submenu
+            .append(&menu_item)
+            .map_err(|e| format!("Failed to append extension template to menu: {e}"))?;
+    }
+
+    *state.extension_templates.lock().await = templates;
+    Ok(())
+}
+
 /// Set the enabled state of the timestamp insertion menu items.
 #[tauri::command]
 pub fn set_insert_timestamp_enabled(
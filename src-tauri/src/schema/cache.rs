@@ -7,67 +7,175 @@
 //! Embedding schemas at compile time allows distributing a single binary without external
 //! data files. The trade-off is that schema changes require recompilation.
 //!
+//! # Lazy Parsing
+//! Only messages.toml and the (small) tables.toml are parsed when the cache is
+//! created, since they're needed as soon as the window shows. Segment schemas are parsed the first time each segment is
+//! asked for, and a version overlay the first time its version is asked for, so
+//! startup doesn't pay for schemas that may never be used in the session.
+//!
+//! # Binary Cache
+//! Parsed schemas can be saved to and loaded from a binary file (see
+//! [`SchemaCache::load_binary_cache`]) in the data folder. Loading it is much cheaper
+//! than parsing TOML, so later launches start with every schema already parsed. The
+//! file is stamped with a hash of the embedded schema content and the Hermes version,
+//! and is ignored when either differs, so a schema change or upgrade never serves
+//! stale data.
+//!
+//! # HL7 Versions
+//! Segment schemas are cached per HL7 version. The base schema describes the default
+//! version; other versions are built by merging the embedded version overlays
+//! (`data/versions/*.toml`) over the base, using the same merge semantics as
+//! extension overrides. See [`super::version`] for how the version of a message is
+//! resolved.
+//!
+//! # Tables
+//! Fields bound to an HL7 table (see [`super::table`]) have their values filled in
+//! from the table when a segment schema is returned, after version overlays and
+//! extension overrides are applied, so an override that binds a field to a table
+//! works the same way as the base schema doing so.
+//!
+//! # External Schema Folder
+//! The embedded schemas can be replaced at runtime by the files in a folder (see
+//! [`super::external`]), set via `load_external_schema()`. A file in the folder
+//! replaces the embedded file of the same name, and the rest stay embedded.
+//! External segment schemas aren't kept in the parsed schemas, so they're never
+//! written to the binary cache; it's only saved while no folder is in use.
+//!
+//! # Custom Segments
+//! Segments the user has defined themselves (see [`super::custom`]) are served
+//! for any segment with no embedded schema, in every version. They're set via
+//! `set_custom_segments()`, and extension overrides apply to them as they do to
+//! embedded segments.
+//!
+//! # User Overrides
+//! The user can override the schema for their site (renaming a field, making it
+//! required, changing its allowed values) without writing an extension. User
+//! overrides are applied via `set_user_overrides()`, over the version's schema
+//! and beneath extension overrides, so an extension still has the final say.
+//!
 //! # Extension Overrides
 //! Extensions can still provide runtime schema overrides that merge with the base schemas.
 //! These are applied via `set_extension_overrides()` and affect all subsequent
 //! `get_segment()` calls.
 
+use bincode::Options;
 use color_eyre::{eyre::Context, Result};
-use std::{collections::HashMap, sync::RwLock};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path, sync::RwLock};
 
-use super::{message::MessagesSchema, segment::Field};
+use super::{
+    custom::{CustomSegment, CUSTOM_SEGMENTS_DIR},
+    external::ExternalSchema,
+    message::MessagesSchema,
+    segment::Field,
+    table::Table,
+    version::{normalise_version, DEFAULT_VERSION, SUPPORTED_VERSIONS},
+};
 use crate::extensions::types::SchemaOverride;
 
 // include the generated embedded schemas module
 include!(concat!(env!("OUT_DIR"), "/embedded_schemas.rs"));
 
+/// Name of the binary cache file in the data folder.
+pub const BINARY_CACHE_FILE: &str = "schema-cache.bin";
+
+/// Largest binary cache that will be read, to guard against corrupt files.
+const MAX_BINARY_CACHE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Segment schemas parsed so far.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ParsedSegments {
+    /// Base segment schemas, keyed by segment name.
+    base: HashMap<String, Vec<Field>>,
+
+    /// Segments changed by each version's overlay, merged over the base, keyed by
+    /// version then segment name. A version is present once its overlay has been
+    /// applied; segments it doesn't change come from `base`.
+    versions: HashMap<String, HashMap<String, Vec<Field>>>,
+}
+
+/// Stamp at the start of the binary cache file.
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct BinaryCacheHeader {
+    /// Hermes version that wrote the file.
+    hermes_version: String,
+
+    /// Hash of the embedded schema content the file was built from.
+    schema_hash: u64,
+}
+
+impl BinaryCacheHeader {
+    fn current() -> Self {
+        Self {
+            hermes_version: env!("CARGO_PKG_VERSION").to_string(),
+            schema_hash: SCHEMA_HASH,
+        }
+    }
+}
+
 /// Thread-safe cache for HL7 schema data with extension override support.
 ///
-/// Base schemas are parsed once from compile-time embedded TOML content.
+/// Base schemas are parsed from compile-time embedded TOML content on first access.
 /// Extension overrides can be applied at runtime to customise field definitions.
 pub struct SchemaCache {
     /// Parsed messages schema (message types and segment mappings)
     messages: MessagesSchema,
 
-    /// Parsed segment schemas keyed by segment name (e.g., "PID", "MSH")
-    segments: HashMap<String, Vec<Field>>,
+    /// HL7 tables fields can be bound to, in file order
+    tables: Vec<Table>,
 
-    /// Extension schema overrides to apply on top of base schemas.
+    /// Segment schemas parsed so far, filled in on first access.
+    parsed: RwLock<ParsedSegments>,
+
+    /// Schema files loaded from a folder, replacing the embedded ones.
+    external: RwLock<Option<ExternalSchema>>,
+
+    /// Version explicitly selected by the user, overriding MSH.12 detection.
+    selected_version: RwLock<Option<String>>,
+
+    /// Segments defined by the user, keyed by segment name.
+    custom_segments: RwLock<HashMap<String, CustomSegment>>,
+
+    /// User schema overrides to apply on top of base schemas.
+    user_overrides: RwLock<Option<SchemaOverride>>,
+
+    /// Extension schema overrides to apply on top of user overrides.
     extension_overrides: RwLock<Option<SchemaOverride>>,
 }
 
 impl SchemaCache {
-    /// Create a new schema cache by parsing embedded schema data.
+    /// Create a new schema cache.
     ///
-    /// All schemas are parsed once at initialisation. Extension overrides
-    /// are applied later via `set_extension_overrides()`.
+    /// Only the messages schema is parsed here; segment schemas and version
+    /// overlays are parsed on first access, or loaded from a binary cache with
+    /// `load_binary_cache()`. Extension overrides are applied later via
+    /// `set_extension_overrides()`.
     ///
     /// # Returns
-    /// * `Ok(SchemaCache)` - Initialized cache with all schemas loaded
-    /// * `Err` - Failed to parse embedded schema content
+    /// * `Ok(SchemaCache)` - Initialized cache
+    /// * `Err` - Failed to parse embedded messages.toml or tables.toml
     pub fn new() -> Result<Self> {
         let messages = MessagesSchema::parse(MESSAGES_TOML)
             .wrap_err("failed to parse embedded messages.toml")?;
-
-        let mut segments = HashMap::new();
-        for (segment_name, toml_content) in SEGMENT_SCHEMAS {
-            let fields = Field::parse(toml_content)
-                .wrap_err_with(|| format!("failed to parse embedded schema for {segment_name}"))?;
-            segments.insert((*segment_name).to_string(), fields);
-        }
+        let tables = Table::parse(TABLES_TOML).wrap_err("failed to parse embedded tables.toml")?;
 
         Ok(Self {
             messages,
-            segments,
+            tables,
+            parsed: RwLock::new(ParsedSegments::default()),
+            external: RwLock::new(None),
+            selected_version: RwLock::new(None),
+            custom_segments: RwLock::new(HashMap::new()),
+            user_overrides: RwLock::new(None),
             extension_overrides: RwLock::new(None),
         })
     }
 
-    /// Get a segment schema with extension overrides applied.
+    /// Get a segment schema for the active version with extension overrides applied.
     ///
-    /// Retrieves the base segment schema and applies any extension overrides
-    /// that have been set. If no overrides are present or the segment has no
-    /// overrides, the base schema is returned unchanged.
+    /// The active version is the user-selected version, or the default version
+    /// if none has been selected. Use `get_segment_for_version()` when the
+    /// message being worked on declares its own version.
     ///
     /// # Arguments
     /// * `segment` - Segment name to retrieve (e.g., "PID", "MSH")
@@ -76,31 +184,164 @@ impl SchemaCache {
     /// * `Ok(Vec<Field>)` - Field definitions for the segment with overrides applied
     /// * `Err` - Segment not found in schema
     pub fn get_segment(&self, segment: &str) -> Result<Vec<Field>> {
-        let base_fields = self
-            .segments
-            .get(segment)
+        self.get_segment_for_version(segment, &self.resolve_version(None))
+    }
+
+    /// Get a segment schema for a specific HL7 version with extension overrides applied.
+    ///
+    /// Retrieves the segment schema for the version and applies any user
+    /// overrides, then any extension overrides, that have been set. If no
+    /// overrides are present or the segment has no overrides, the version's
+    /// schema is returned unchanged. Unsupported versions fall back to the
+    /// default version.
+    ///
+    /// # Arguments
+    /// * `segment` - Segment name to retrieve (e.g., "PID", "MSH")
+    /// * `version` - Supported HL7 version (e.g., "2.3.1")
+    ///
+    /// # Returns
+    /// * `Ok(Vec<Field>)` - Field definitions for the segment with overrides applied
+    /// * `Err` - Segment not found in schema
+    pub fn get_segment_for_version(&self, segment: &str, version: &str) -> Result<Vec<Field>> {
+        let version = if has_schema_for(version) {
+            version
+        } else {
+            DEFAULT_VERSION
+        };
+        self.ensure_version(version)?;
+
+        let versioned = self
+            .parsed
+            .read()
+            .expect("can read parsed schemas")
+            .versions
+            .get(version)
+            .and_then(|segments| segments.get(segment))
+            .cloned();
+        let base_fields = match versioned {
+            Some(fields) => fields,
+            None => match self.base_segment(segment)? {
+                Some(fields) => fields,
+                None => self
+                    .custom_segment(segment)
+                    .map(|custom| custom.fields)
+                    .ok_or_else(|| {
+                        color_eyre::eyre::eyre!("segment {segment} not found in schema")
+                    })?,
+            },
+        };
+
+        let fields = apply_overrides(
+            base_fields,
+            &self.user_overrides.read().expect("can read user overrides"),
+            segment,
+        );
+        let mut fields = apply_overrides(
+            fields,
+            &self
+                .extension_overrides
+                .read()
+                .expect("can read extension overrides"),
+            segment,
+        );
+
+        self.fill_table_values(&mut fields);
+        Ok(fields)
+    }
+
+    /// Get every HL7 table, in file order.
+    ///
+    /// The tables of the external schema folder are used if it has any.
+    #[must_use]
+    pub fn get_tables(&self) -> Vec<Table> {
+        self.external
+            .read()
+            .expect("can read external schema")
+            .as_ref()
+            .and_then(|external| external.tables.clone())
+            .unwrap_or_else(|| self.tables.clone())
+    }
+
+    /// Get an HL7 table by number.
+    ///
+    /// # Arguments
+    /// * `id` - Table number, with or without leading zeros (e.g., "0001" or "1")
+    ///
+    /// # Returns
+    /// The table, or None if there's no table with that number
+    #[must_use]
+    pub fn get_table(&self, id: &str) -> Option<Table> {
+        let id = format!("{:0>4}", id.trim());
+        let external = self.external.read().expect("can read external schema");
+        external
+            .as_ref()
+            .and_then(|external| external.tables.as_ref())
+            .unwrap_or(&self.tables)
+            .iter()
+            .find(|table| table.id == id)
             .cloned()
-            .ok_or_else(|| color_eyre::eyre::eyre!("segment {segment} not found in schema"))?;
+    }
 
-        let overrides = self
-            .extension_overrides
+    /// Load schema files from a folder to use in place of the embedded ones,
+    /// replacing any loaded before.
+    ///
+    /// If the folder can't be loaded, the schema in use is left as it was.
+    ///
+    /// # Arguments
+    /// * `directory` - Folder of schema files, or None to go back to the embedded schemas
+    ///
+    /// # Returns
+    /// * `Ok(files)` - The files loaded, relative to the folder
+    /// * `Err` - The folder doesn't exist, or a file in it couldn't be read or parsed
+    pub fn load_external_schema(&self, directory: Option<&Path>) -> Result<Vec<String>> {
+        let external = directory
+            .map(|directory| ExternalSchema::load(directory, &self.messages.segments))
+            .transpose()?;
+        let files = external
+            .as_ref()
+            .map(|external| external.files.clone())
+            .unwrap_or_default();
+
+        *self.external.write().expect("can write external schema") = external;
+        // version schemas were merged over the old base, so rebuild them
+        self.parsed
+            .write()
+            .expect("can write parsed schemas")
+            .versions
+            .clear();
+        Ok(files)
+    }
+
+    /// Get the folder the external schema files were loaded from, if any.
+    pub fn external_schema_directory(&self) -> Option<std::path::PathBuf> {
+        self.external
             .read()
-            .expect("can read extension overrides");
-
-        if let Some(ref schema_override) = *overrides {
-            if let Some(ref segments) = schema_override.segments {
-                if let Some(segment_override) = segments.get(segment) {
-                    if let Some(ref field_overrides) = segment_override.fields {
-                        return Ok(crate::schema::merge::merge_segment_fields(
-                            &base_fields,
-                            field_overrides,
-                        ));
-                    }
-                }
-            }
-        }
+            .expect("can read external schema")
+            .as_ref()
+            .map(|external| external.directory.clone())
+    }
+
+    /// Set the user schema overrides.
+    ///
+    /// The overrides apply beneath extension overrides to all subsequent calls
+    /// to `get_segment()`.
+    ///
+    /// # Arguments
+    /// * `overrides` - The user's schema override, or None to clear it
+    pub fn set_user_overrides(&self, overrides: Option<SchemaOverride>) {
+        let mut user_overrides = self
+            .user_overrides
+            .write()
+            .expect("can write user overrides");
+        *user_overrides = overrides;
+    }
 
-        Ok(base_fields)
+    /// Get the user schema overrides, if any have been set.
+    pub fn user_overrides(&self) -> Option<SchemaOverride> {
+        self.user_overrides
+            .read()
+            .expect("can read user overrides")
+            .clone()
     }
 
     /// Set the extension schema overrides.
@@ -118,22 +359,378 @@ impl SchemaCache {
         *ext_overrides = overrides;
     }
 
+    /// Set the segments defined by the user, replacing any set before.
+    ///
+    /// # Arguments
+    /// * `segments` - The custom segments, normally loaded from the data folder
+    pub fn set_custom_segments(&self, segments: Vec<CustomSegment>) {
+        let mut custom = self
+            .custom_segments
+            .write()
+            .expect("can write custom segments");
+        *custom = segments
+            .into_iter()
+            .map(|segment| (segment.name.clone(), segment))
+            .collect();
+    }
+
+    /// Get a segment defined by the user.
+    ///
+    /// # Returns
+    /// The custom segment, or None if the user hasn't defined one by that name
+    pub fn custom_segment(&self, segment: &str) -> Option<CustomSegment> {
+        self.custom_segments
+            .read()
+            .expect("can read custom segments")
+            .get(segment)
+            .cloned()
+    }
+
+    /// Get every segment defined by the user, sorted by name.
+    pub fn custom_segments(&self) -> Vec<CustomSegment> {
+        let mut segments: Vec<CustomSegment> = self
+            .custom_segments
+            .read()
+            .expect("can read custom segments")
+            .values()
+            .cloned()
+            .collect();
+        segments.sort_by(|a, b| a.name.cmp(&b.name));
+        segments
+    }
+
     /// Get the messages schema.
     ///
     /// Returns the parsed messages schema containing message type definitions
-    /// and segment path mappings.
+    /// and segment path mappings, from the external schema folder if it has one.
+    /// Custom segments are listed alongside the embedded ones, with the path of
+    /// their file in the data folder.
     ///
     /// # Returns
     /// The messages schema (cloned for thread safety)
     pub fn get_messages(&self) -> MessagesSchema {
-        self.messages.clone()
+        let mut messages = self
+            .external
+            .read()
+            .expect("can read external schema")
+            .as_ref()
+            .and_then(|external| external.messages.clone())
+            .unwrap_or_else(|| self.messages.clone());
+        for name in self
+            .custom_segments
+            .read()
+            .expect("can read custom segments")
+            .keys()
+        {
+            messages.segments.entry(name.clone()).or_insert_with(|| {
+                format!("{CUSTOM_SEGMENTS_DIR}/{}.toml", name.to_ascii_lowercase())
+            });
+        }
+        messages
+    }
+
+    /// Select the HL7 version to use regardless of what messages declare.
+    ///
+    /// # Arguments
+    /// * `version` - Version to use, or None to auto-detect from MSH.12
+    ///
+    /// # Returns
+    /// * `Ok(())` - Version selected
+    /// * `Err` - Version is not supported
+    pub fn set_selected_version(&self, version: Option<String>) -> Result<()> {
+        if let Some(ref v) = version {
+            if !SUPPORTED_VERSIONS.contains(&v.as_str()) {
+                return Err(color_eyre::eyre::eyre!("unsupported HL7 version {v}"));
+            }
+        }
+
+        let mut selected = self
+            .selected_version
+            .write()
+            .expect("can write selected version");
+        *selected = version;
+        Ok(())
+    }
+
+    /// Get the version explicitly selected by the user, if any.
+    pub fn selected_version(&self) -> Option<String> {
+        self.selected_version
+            .read()
+            .expect("can read selected version")
+            .clone()
+    }
+
+    /// Resolve which version's schema should be used.
+    ///
+    /// A user-selected version always wins. Otherwise the version detected from
+    /// the message is used if it's supported, falling back to the default.
+    ///
+    /// # Arguments
+    /// * `detected` - Version declared by the message being worked on, if known
+    pub fn resolve_version(&self, detected: Option<&str>) -> String {
+        if let Some(selected) = self.selected_version() {
+            return selected;
+        }
+
+        detected
+            .and_then(normalise_version)
+            .unwrap_or(DEFAULT_VERSION)
+            .to_string()
+    }
+
+    /// Fill in the values of fields bound to a table that don't list their own.
+    fn fill_table_values(&self, fields: &mut [Field]) {
+        for field in fields.iter_mut().filter(|field| field.values.is_none()) {
+            let Some(id) = field.table.as_deref() else {
+                continue;
+            };
+            match self.get_table(id) {
+                Some(table) => field.values = Some(table.values.clone().into_iter().collect()),
+                None => log::warn!("field {} is bound to unknown table {id}", field.field),
+            }
+        }
+    }
+
+    /// Get a base segment schema, parsing it on first access.
+    ///
+    /// The external schema folder's version of the segment is used if it has one.
+    ///
+    /// # Returns
+    /// * `Ok(Some(fields))` - The segment's base field definitions
+    /// * `Ok(None)` - No base schema is embedded for the segment
+    /// * `Err` - The embedded schema failed to parse
+    fn base_segment(&self, segment: &str) -> Result<Option<Vec<Field>>> {
+        if let Some(fields) = self
+            .external
+            .read()
+            .expect("can read external schema")
+            .as_ref()
+            .and_then(|external| external.segments.get(segment))
+        {
+            return Ok(Some(fields.clone()));
+        }
+
+        if let Some(fields) = self
+            .parsed
+            .read()
+            .expect("can read parsed schemas")
+            .base
+            .get(segment)
+        {
+            return Ok(Some(fields.clone()));
+        }
+
+        let Some((_, toml_content)) = SEGMENT_SCHEMAS.iter().find(|(name, _)| *name == segment)
+        else {
+            return Ok(None);
+        };
+        let fields = Field::parse(toml_content)
+            .wrap_err_with(|| format!("failed to parse embedded schema for {segment}"))?;
+
+        self.parsed
+            .write()
+            .expect("can write parsed schemas")
+            .base
+            .insert(segment.to_string(), fields.clone());
+        Ok(Some(fields))
+    }
+
+    /// Apply a version's overlay to the base schemas, if that hasn't happened yet.
+    ///
+    /// Only the segments the overlay changes are merged and stored; the rest of
+    /// the version's schema is the base. The external schema folder's overlay
+    /// for the version is used in place of the embedded one if it has one.
+    fn ensure_version(&self, version: &str) -> Result<()> {
+        if self
+            .parsed
+            .read()
+            .expect("can read parsed schemas")
+            .versions
+            .contains_key(version)
+        {
+            return Ok(());
+        }
+
+        let external_overlay = self
+            .external
+            .read()
+            .expect("can read external schema")
+            .as_ref()
+            .and_then(|external| external.overlays.get(version).cloned());
+        let overlay = match external_overlay {
+            Some(overlay) => Some(overlay),
+            None => VERSION_OVERLAYS
+                .iter()
+                .find(|(v, _)| *v == version)
+                .map(|(_, toml_content)| {
+                    toml::from_str::<SchemaOverride>(toml_content).wrap_err_with(|| {
+                        format!("failed to parse embedded overlay for v{version}")
+                    })
+                })
+                .transpose()?,
+        };
+
+        let mut merged = HashMap::new();
+        if let Some(overlay) = overlay {
+            for (segment_name, segment_override) in overlay.segments.iter().flatten() {
+                let Some(ref field_overrides) = segment_override.fields else {
+                    continue;
+                };
+                let base_fields = self.base_segment(segment_name)?.unwrap_or_default();
+                merged.insert(
+                    segment_name.clone(),
+                    crate::schema::merge::merge_segment_fields(&base_fields, field_overrides),
+                );
+            }
+        }
+
+        self.parsed
+            .write()
+            .expect("can write parsed schemas")
+            .versions
+            .insert(version.to_string(), merged);
+        Ok(())
+    }
+
+    /// Parse every embedded segment schema and version overlay.
+    ///
+    /// Used before saving a binary cache, so the cache holds everything and
+    /// later launches never need to parse TOML.
+    ///
+    /// # Returns
+    /// * `Ok(())` - All schemas parsed
+    /// * `Err` - An embedded schema failed to parse
+    pub fn parse_all(&self) -> Result<()> {
+        for (segment, _) in SEGMENT_SCHEMAS {
+            self.base_segment(segment)?;
+        }
+        for version in SUPPORTED_VERSIONS {
+            self.ensure_version(version)?;
+        }
+        Ok(())
+    }
+
+    /// Load parsed schemas from a binary cache file.
+    ///
+    /// The file is only used if it was written by this Hermes version from the
+    /// same embedded schema content. A missing, stale, or unreadable file is not
+    /// an error; the schemas are simply parsed on demand instead.
+    ///
+    /// # Arguments
+    /// * `path` - Binary cache file, normally `BINARY_CACHE_FILE` in the data folder
+    ///
+    /// # Returns
+    /// `true` if the cache was loaded, `false` if it should be rebuilt
+    pub fn load_binary_cache(&self, path: &Path) -> bool {
+        let Ok(bytes) = std::fs::read(path) else {
+            return false;
+        };
+
+        let mut reader = bytes.as_slice();
+        let options = binary_cache_options();
+        match options.deserialize_from::<_, BinaryCacheHeader>(&mut reader) {
+            Ok(header) if header == BinaryCacheHeader::current() => {}
+            Ok(_) => {
+                log::info!("schema cache {} is stale, ignoring it", path.display());
+                return false;
+            }
+            Err(e) => {
+                log::warn!("failed to read schema cache {}: {e}", path.display());
+                return false;
+            }
+        }
+
+        match options.deserialize_from::<_, ParsedSegments>(&mut reader) {
+            Ok(parsed) => {
+                *self.parsed.write().expect("can write parsed schemas") = parsed;
+                true
+            }
+            Err(e) => {
+                log::warn!("failed to read schema cache {}: {e}", path.display());
+                false
+            }
+        }
+    }
+
+    /// Save the schemas parsed so far to a binary cache file.
+    ///
+    /// The file is written alongside and then renamed into place, so a crash
+    /// part way through never leaves a truncated cache behind. Call
+    /// `parse_all()` first for a complete cache. Nothing is saved while an
+    /// external schema folder is in use, since the version schemas were built
+    /// from its files rather than the embedded ones.
+    ///
+    /// # Arguments
+    /// * `path` - Binary cache file, normally `BINARY_CACHE_FILE` in the data folder
+    ///
+    /// # Returns
+    /// * `Ok(())` - Cache written, or skipped for an external schema folder
+    /// * `Err` - The file couldn't be written
+    pub fn save_binary_cache(&self, path: &Path) -> Result<()> {
+        if self.external_schema_directory().is_some() {
+            log::debug!("not saving the schema cache while an external schema folder is in use");
+            return Ok(());
+        }
+
+        let mut bytes = Vec::new();
+        let options = binary_cache_options();
+        options
+            .serialize_into(&mut bytes, &BinaryCacheHeader::current())
+            .wrap_err("failed to encode schema cache header")?;
+        options
+            .serialize_into(
+                &mut bytes,
+                &*self.parsed.read().expect("can read parsed schemas"),
+            )
+            .wrap_err("failed to encode schema cache")?;
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .wrap_err_with(|| format!("failed to create {}", dir.display()))?;
+        }
+        let partial = path.with_extension("partial");
+        std::fs::write(&partial, &bytes)
+            .wrap_err_with(|| format!("failed to write {}", partial.display()))?;
+        std::fs::rename(&partial, path)
+            .wrap_err_with(|| format!("failed to replace {}", path.display()))?;
+        Ok(())
+    }
+}
+
+/// Merge a schema override's fields for a segment over the segment's fields.
+fn apply_overrides(
+    fields: Vec<Field>,
+    overrides: &Option<SchemaOverride>,
+    segment: &str,
+) -> Vec<Field> {
+    let field_overrides = overrides
+        .as_ref()
+        .and_then(|schema_override| schema_override.segments.as_ref())
+        .and_then(|segments| segments.get(segment))
+        .and_then(|segment_override| segment_override.fields.as_ref());
+    match field_overrides {
+        Some(field_overrides) => {
+            crate::schema::merge::merge_segment_fields(&fields, field_overrides)
+        }
+        None => fields,
     }
 }
 
+/// Whether schemas are embedded for a version (the default or one with an overlay).
+fn has_schema_for(version: &str) -> bool {
+    version == DEFAULT_VERSION || VERSION_OVERLAYS.iter().any(|(v, _)| *v == version)
+}
+
+/// Encoding options for the binary cache.
+fn binary_cache_options() -> impl Options + Copy {
+    bincode::DefaultOptions::new().with_limit(MAX_BINARY_CACHE_BYTES)
+}
+
 #[cfg(test)]
 #[allow(clippy::unwrap_used, clippy::indexing_slicing)]
 mod tests {
     use super::*;
+    use crate::commands::temp_dir;
     use crate::extensions::types::{FieldOverride, Nullable, SchemaOverride, SegmentOverride};
     use indexmap::IndexMap;
 
@@ -191,7 +788,9 @@ mod tests {
                     datatype: None,
                     placeholder: None,
                     values: None,
+                    table: None,
                     template: None,
+                    classification: None,
                 }]),
             },
         );
@@ -233,4 +832,297 @@ mod tests {
         assert_eq!(field_3_restored.name, original_name);
         assert_eq!(field_3_restored.note, original_note);
     }
+
+    #[test]
+    fn custom_segments_are_served_with_overrides() {
+        let cache = SchemaCache::new().expect("can create cache");
+        assert!(cache.get_segment("ZPI").is_err());
+
+        let custom: CustomSegment = toml::from_str(
+            r#"
+            name = "ZPI"
+            description = "Patient Insurance Extension"
+
+            [[fields]]
+            field = 1
+            name = "Plan Code"
+            "#,
+        )
+        .unwrap();
+        cache.set_custom_segments(vec![custom]);
+        assert_eq!(cache.get_segment("ZPI").unwrap()[0].name, "Plan Code");
+        assert_eq!(
+            cache.get_segment_for_version("ZPI", "2.3").unwrap()[0].name,
+            "Plan Code"
+        );
+        assert!(cache.get_messages().segments.contains_key("ZPI"));
+
+        let mut segments = IndexMap::new();
+        segments.insert(
+            "ZPI".to_string(),
+            SegmentOverride {
+                fields: Some(vec![FieldOverride {
+                    field: 1,
+                    component: None,
+                    name: None,
+                    group: None,
+                    note: Some(Nullable::Value("From the extension".to_string())),
+                    required: None,
+                    minlength: None,
+                    maxlength: None,
+                    pattern: None,
+                    datatype: None,
+                    placeholder: None,
+                    values: None,
+                    table: None,
+                    template: None,
+                    classification: None,
+                }]),
+            },
+        );
+        cache.set_extension_overrides(Some(SchemaOverride {
+            segments: Some(segments),
+        }));
+        let fields = cache.get_segment("ZPI").unwrap();
+        assert_eq!(fields[0].name, "Plan Code");
+        assert_eq!(fields[0].note.as_deref(), Some("From the extension"));
+
+        cache.set_custom_segments(Vec::new());
+        assert!(cache.get_segment("ZPI").is_err());
+    }
+
+    #[test]
+    fn user_overrides_apply_beneath_extension_overrides() {
+        let cache = SchemaCache::new().expect("can create cache");
+        let pid = |field: u8| {
+            cache
+                .get_segment("PID")
+                .unwrap()
+                .into_iter()
+                .find(|f| f.field == field && f.component.is_none())
+                .unwrap()
+        };
+        let original_note = pid(19).note;
+
+        let user: SchemaOverride = serde_json::from_value(serde_json::json!({
+            "segments": { "PID": { "fields": [
+                { "field": 19, "name": "Site Member Number", "required": true, "note": null },
+                { "field": 18, "name": "Account" }
+            ] } }
+        }))
+        .unwrap();
+        cache.set_user_overrides(Some(user));
+        assert_eq!(pid(19).name, "Site Member Number");
+        assert_eq!(pid(19).required, Some(true));
+        assert_eq!(pid(19).note, None);
+
+        let extension: SchemaOverride = serde_json::from_value(serde_json::json!({
+            "segments": { "PID": { "fields": [ { "field": 18, "name": "Extension Account" } ] } }
+        }))
+        .unwrap();
+        cache.set_extension_overrides(Some(extension));
+        assert_eq!(pid(18).name, "Extension Account");
+        assert_eq!(pid(19).name, "Site Member Number");
+
+        cache.set_user_overrides(None);
+        assert_ne!(pid(19).name, "Site Member Number");
+        assert_eq!(pid(19).note, original_note);
+    }
+
+    #[test]
+    fn external_schema_replaces_embedded_files() {
+        let dir = temp_dir("schema");
+        std::fs::create_dir_all(dir.join("versions")).unwrap();
+        std::fs::write(
+            dir.join("pid.toml"),
+            "[[fields]]\nfield = 3\nname = \"Site MRN\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("versions").join("2.3.toml"),
+            "[segments.PID]\nfields = [{ field = 3, name = \"Old Site MRN\" }]\n",
+        )
+        .unwrap();
+
+        let cache = SchemaCache::new().expect("can create cache");
+        let original = cache.get_segment("PID").unwrap();
+        let files = cache.load_external_schema(Some(&dir)).unwrap();
+        assert_eq!(files, ["pid.toml", "versions/2.3.toml"]);
+
+        let pid = cache.get_segment("PID").unwrap();
+        assert_eq!(pid.len(), 1);
+        assert_eq!(pid[0].name, "Site MRN");
+        assert_eq!(
+            cache.get_segment_for_version("PID", "2.3").unwrap()[0].name,
+            "Old Site MRN"
+        );
+        assert!(!cache.get_segment("MSH").unwrap().is_empty());
+        assert!(cache.get_table("1").is_some());
+
+        // a bad folder leaves the loaded schema in place
+        std::fs::write(dir.join("pid.toml"), "not toml [").unwrap();
+        assert!(cache.load_external_schema(Some(&dir)).is_err());
+        assert_eq!(cache.get_segment("PID").unwrap()[0].name, "Site MRN");
+
+        cache.load_external_schema(None).unwrap();
+        assert_eq!(cache.get_segment("PID").unwrap().len(), original.len());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_schema_cache_has_all_supported_versions() {
+        let cache = SchemaCache::new().expect("can create cache");
+
+        for version in SUPPORTED_VERSIONS {
+            let msh = cache
+                .get_segment_for_version("MSH", version)
+                .expect("can get MSH segment");
+            let version_field = msh
+                .iter()
+                .find(|f| f.field == 12 && f.component.is_none())
+                .expect("can find MSH.12");
+            assert_eq!(version_field.template.as_deref(), Some(*version));
+        }
+    }
+
+    #[test]
+    fn test_schema_cache_parses_segments_on_demand() {
+        let cache = SchemaCache::new().expect("can create cache");
+        assert!(cache.parsed.read().unwrap().base.is_empty());
+
+        cache.get_segment("PID").expect("can get PID segment");
+        let parsed = cache.parsed.read().unwrap();
+        assert!(parsed.base.contains_key("PID"));
+        assert!(!parsed.base.contains_key("PV1"));
+        assert!(parsed.versions.contains_key(DEFAULT_VERSION));
+    }
+
+    #[test]
+    fn test_schema_cache_binary_round_trip() {
+        let dir = temp_dir("schema");
+        let path = dir.join(BINARY_CACHE_FILE);
+
+        let cache = SchemaCache::new().expect("can create cache");
+        assert!(
+            !cache.load_binary_cache(&path),
+            "missing cache isn't loaded"
+        );
+        cache.parse_all().expect("can parse all schemas");
+        cache.save_binary_cache(&path).expect("can save cache");
+
+        let loaded = SchemaCache::new().expect("can create cache");
+        assert!(loaded.load_binary_cache(&path));
+        assert_eq!(
+            loaded.parsed.read().unwrap().base.len(),
+            SEGMENT_SCHEMAS.len()
+        );
+        for version in SUPPORTED_VERSIONS {
+            let expected = cache.get_segment_for_version("MSH", version).unwrap();
+            let actual = loaded.get_segment_for_version("MSH", version).unwrap();
+            assert_eq!(
+                serde_json::to_value(&expected).unwrap(),
+                serde_json::to_value(&actual).unwrap()
+            );
+        }
+
+        // a cache built from different schema content is ignored
+        let mut bytes = Vec::new();
+        let stale = BinaryCacheHeader {
+            schema_hash: SCHEMA_HASH.wrapping_add(1),
+            ..BinaryCacheHeader::current()
+        };
+        binary_cache_options()
+            .serialize_into(&mut bytes, &stale)
+            .unwrap();
+        std::fs::write(&path, bytes).unwrap();
+        assert!(!SchemaCache::new().unwrap().load_binary_cache(&path));
+
+        // as is a corrupt one
+        std::fs::write(&path, b"not a cache").unwrap();
+        assert!(!SchemaCache::new().unwrap().load_binary_cache(&path));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_schema_cache_resolves_versions() {
+        let cache = SchemaCache::new().expect("can create cache");
+
+        assert_eq!(cache.resolve_version(None), DEFAULT_VERSION);
+        assert_eq!(cache.resolve_version(Some("2.7.1")), "2.7");
+        assert_eq!(cache.resolve_version(Some("9.9")), DEFAULT_VERSION);
+
+        cache
+            .set_selected_version(Some("2.3".to_string()))
+            .expect("can select version");
+        assert_eq!(cache.resolve_version(Some("2.7.1")), "2.3");
+
+        assert!(cache.set_selected_version(Some("9.9".to_string())).is_err());
+
+        cache.set_selected_version(None).expect("can clear version");
+        assert_eq!(cache.resolve_version(Some("2.4")), "2.4");
+    }
+
+    #[test]
+    fn test_schema_cache_fills_values_from_tables() {
+        let cache = SchemaCache::new().expect("can create cache");
+
+        let pid_fields = cache.get_segment("PID").expect("can get PID segment");
+        let sex = pid_fields
+            .iter()
+            .find(|f| f.field == 8 && f.component.is_none())
+            .expect("can find PID.8");
+        assert_eq!(sex.table.as_deref(), Some("0001"));
+        let values = sex.values.as_ref().expect("PID.8 has values");
+        assert_eq!(values.get("U").map(String::as_str), Some("Unknown"));
+
+        assert_eq!(cache.get_table("1").unwrap().name, "Administrative Sex");
+        assert!(cache.get_table("9999").is_none());
+    }
+
+    #[test]
+    fn test_schema_cache_message_segments_have_schemas() {
+        let cache = SchemaCache::new().expect("can create cache");
+        let messages = cache.get_messages();
+
+        for (message, segments) in &messages.message {
+            for segment in segments {
+                assert!(
+                    messages.segments.contains_key(&segment.name),
+                    "{message} lists {} without a schema",
+                    segment.name
+                );
+            }
+        }
+        for message in [
+            "siu_s12", "siu_s26", "vxu_v04", "qbp_q22", "rsp_k22", "qry_a19", "adr_a19",
+        ] {
+            assert!(messages.message.contains_key(message), "{message} missing");
+        }
+    }
+
+    #[test]
+    fn test_schema_cache_tables_are_bound_and_exist() {
+        let cache = SchemaCache::new().expect("can create cache");
+
+        let mut bound = std::collections::HashSet::new();
+        for (segment, _) in SEGMENT_SCHEMAS {
+            for field in cache.get_segment(segment).unwrap() {
+                if let Some(id) = field.table {
+                    assert!(
+                        cache.get_table(&id).is_some(),
+                        "{segment}.{} is bound to unknown table {id}",
+                        field.field
+                    );
+                    bound.insert(id);
+                }
+            }
+        }
+
+        for table in cache.get_tables() {
+            assert_eq!(table.id.len(), 4, "table {} isn't zero-padded", table.id);
+            assert!(!table.values.is_empty(), "table {} has no values", table.id);
+            assert!(bound.contains(&table.id), "table {} isn't used", table.id);
+        }
+    }
 }
@@ -0,0 +1,247 @@
+//! Custom segment (Z-segment) schemas defined by the user.
+//!
+//! Sites add their own segments, named starting with `Z` (e.g., ZPI, ZIN), for
+//! data the standard doesn't cover. Hermes has no embedded schema for these, so
+//! without a custom schema they show as unknown segments with unnamed fields.
+//!
+//! # Storage
+//!
+//! Custom segments are kept in the `custom-segments` folder of the data root,
+//! one TOML file per segment (`ZPI` is saved as `zpi.toml`). The file has the
+//! same `[[fields]]` layout as the embedded segment schemas in `data/`, plus
+//! the segment's name and description, so a schema can be written by hand or
+//! copied to another machine.
+//!
+//! # Merging
+//!
+//! `SchemaCache` serves a custom segment's fields wherever it has no embedded
+//! schema for the segment, for every HL7 version. Extension overrides are then
+//! applied on top, the same as for embedded segments.
+
+use color_eyre::{eyre::Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, path::Path};
+
+use super::segment::Field;
+
+/// Folder under the data root that holds custom segment schemas.
+pub const CUSTOM_SEGMENTS_DIR: &str = "custom-segments";
+
+/// Extension of custom segment schema files.
+const CUSTOM_SEGMENT_EXTENSION: &str = "toml";
+
+/// Schema of a segment defined by the user.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomSegment {
+    /// Segment name (e.g., "ZPI")
+    pub name: String,
+    /// What the segment holds (e.g., "Patient Insurance Extension")
+    pub description: String,
+    /// Field and component definitions, in the same form as embedded schemas
+    #[serde(default)]
+    pub fields: Vec<Field>,
+}
+
+impl CustomSegment {
+    /// Check that the segment can be used as a schema.
+    ///
+    /// # Returns
+    /// * `Ok(())` - The name is a Z-segment name and the fields are well formed
+    /// * `Err` - What's wrong with the segment
+    pub fn validate(&self) -> Result<()> {
+        if !is_custom_segment_name(&self.name) {
+            color_eyre::eyre::bail!(
+                "{} isn't a custom segment name; custom segments are Z followed by two letters or digits (e.g., ZPI)",
+                self.name
+            );
+        }
+        if self.description.trim().is_empty() {
+            color_eyre::eyre::bail!("{} needs a description", self.name);
+        }
+
+        let mut seen = HashSet::new();
+        for field in &self.fields {
+            let path = match field.component {
+                Some(component) => format!("{}.{}.{component}", self.name, field.field),
+                None => format!("{}.{}", self.name, field.field),
+            };
+            if field.field == 0 || field.component == Some(0) {
+                color_eyre::eyre::bail!("{path}: field and component numbers start at 1");
+            }
+            if field.name.trim().is_empty() {
+                color_eyre::eyre::bail!("{path} needs a name");
+            }
+            if !seen.insert((field.field, field.component)) {
+                color_eyre::eyre::bail!("{path} is defined more than once");
+            }
+            if let (Some(min), Some(max)) = (field.minlength, field.maxlength) {
+                if min > max {
+                    color_eyre::eyre::bail!(
+                        "{path}: minimum length {min} is over maximum length {max}"
+                    );
+                }
+            }
+            if let Some(pattern) = &field.pattern {
+                regex::Regex::new(pattern)
+                    .wrap_err_with(|| format!("{path}: invalid pattern {pattern}"))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read every custom segment schema in a folder, sorted by name.
+    ///
+    /// A folder that doesn't exist yet holds no schemas. Files that can't be
+    /// read or aren't valid schemas are logged and skipped, so one bad file
+    /// doesn't hide the rest.
+    #[must_use]
+    pub fn load_all(directory: &Path) -> Vec<Self> {
+        let Ok(entries) = std::fs::read_dir(directory) else {
+            return Vec::new();
+        };
+
+        let mut segments: Vec<Self> = entries
+            .filter_map(std::result::Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|ext| ext == CUSTOM_SEGMENT_EXTENSION)
+            })
+            .filter_map(|path| {
+                let segment = std::fs::read_to_string(&path)
+                    .wrap_err("failed to read file")
+                    .and_then(|content| {
+                        toml::from_str::<Self>(&content).wrap_err("failed to parse schema")
+                    })
+                    .and_then(|segment| segment.validate().map(|()| segment));
+                match segment {
+                    Ok(segment) => Some(segment),
+                    Err(e) => {
+                        log::warn!("skipping custom segment {}: {e:#}", path.display());
+                        None
+                    }
+                }
+            })
+            .collect();
+        segments.sort_by(|a, b| a.name.cmp(&b.name));
+        segments.dedup_by(|a, b| a.name == b.name);
+        segments
+    }
+
+    /// Write the schema to its file in a folder, replacing any earlier version.
+    ///
+    /// # Returns
+    /// * `Ok(())` - Schema written
+    /// * `Err` - The segment isn't valid or the file couldn't be written
+    pub fn save(&self, directory: &Path) -> Result<()> {
+        self.validate()?;
+        let content = toml::to_string_pretty(self).wrap_err("failed to encode schema")?;
+        std::fs::create_dir_all(directory)
+            .wrap_err_with(|| format!("failed to create {}", directory.display()))?;
+        let path = Self::path(directory, &self.name);
+        std::fs::write(&path, content)
+            .wrap_err_with(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Delete a segment's schema file from a folder.
+    ///
+    /// # Returns
+    /// * `Ok(())` - Schema deleted
+    /// * `Err` - There's no schema for the segment, or it couldn't be deleted
+    pub fn delete(directory: &Path, name: &str) -> Result<()> {
+        let path = Self::path(directory, name);
+        std::fs::remove_file(&path).wrap_err_with(|| format!("failed to delete {}", path.display()))
+    }
+
+    /// Path of a segment's schema file.
+    fn path(directory: &Path, name: &str) -> std::path::PathBuf {
+        directory.join(format!(
+            "{}.{CUSTOM_SEGMENT_EXTENSION}",
+            name.to_ascii_lowercase()
+        ))
+    }
+}
+
+/// Whether a name can be used for a custom segment: `Z` followed by two
+/// uppercase letters or digits.
+#[must_use]
+pub fn is_custom_segment_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next() == Some('Z')
+        && name.len() == 3
+        && chars.all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use crate::commands::temp_dir;
+
+    const ZPI: &str = r#"
+name = "ZPI"
+description = "Patient Insurance Extension"
+
+[[fields]]
+field = 1
+name = "Plan Code"
+required = true
+maxlength = 10
+
+[[fields]]
+field = 2
+component = 1
+name = "Member ID"
+pattern = "^[0-9]+$"
+"#;
+
+    #[test]
+    fn saved_segments_are_loaded_back() {
+        let dir = temp_dir("custom-segments");
+        let segment: CustomSegment = toml::from_str(ZPI).unwrap();
+        segment.save(&dir).unwrap();
+        assert!(dir.join("zpi.toml").exists());
+
+        let loaded = CustomSegment::load_all(&dir);
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].description, "Patient Insurance Extension");
+        assert_eq!(loaded[0].fields[1].component, Some(1));
+
+        CustomSegment::delete(&dir, "ZPI").unwrap();
+        assert!(CustomSegment::load_all(&dir).is_empty());
+        assert!(CustomSegment::delete(&dir, "ZPI").is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_segments_are_rejected() {
+        let mut segment: CustomSegment = toml::from_str(ZPI).unwrap();
+        assert!(segment.validate().is_ok());
+
+        segment.name = "PID".to_string();
+        assert!(segment.validate().is_err());
+        segment.name = "ZPI".to_string();
+
+        let duplicate = segment.fields[0].clone();
+        segment.fields.push(duplicate);
+        assert!(segment.validate().is_err());
+        segment.fields.pop();
+
+        segment.fields[1].pattern = Some("[".to_string());
+        assert!(segment.validate().is_err());
+    }
+
+    #[test]
+    fn bad_files_are_skipped() {
+        let dir = temp_dir("custom-segments");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("zpi.toml"), ZPI).unwrap();
+        std::fs::write(dir.join("zin.toml"), "name = 'ZIN'").unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a schema").unwrap();
+
+        let loaded = CustomSegment::load_all(&dir);
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].name, "ZPI");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,231 @@
+//! Schema files loaded from a folder at runtime.
+//!
+//! The embedded schemas can only change with a new release. To fix or extend a
+//! schema in the meantime, Hermes can be pointed at a folder of schema files
+//! that replace the embedded ones.
+//!
+//! # Layout
+//!
+//! The folder has the same layout as `data/`, and any file can be left out to
+//! keep the embedded version of it:
+//!
+//! ```text
+//! schema/
+//!   messages.toml       # message structures and segment file names
+//!   tables.toml         # HL7 tables
+//!   pid.toml            # one file per segment, named as in messages.toml
+//!   versions/2.3.toml   # version overlays
+//! ```
+//!
+//! Segment files are found by the names `messages.toml` gives them, using the
+//! folder's `messages.toml` if it has one, so a segment Hermes doesn't embed
+//! needs a `messages.toml` that lists it.
+//!
+//! # Loading
+//!
+//! Every file is parsed when the folder is loaded, so a mistake is reported
+//! straight away rather than the first time the segment is used, and a folder
+//! with a bad file is rejected as a whole.
+
+use color_eyre::{eyre::Context, Result};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use super::{
+    message::{MessagesSchema, SegmentPaths},
+    segment::Field,
+    table::Table,
+    version::SUPPORTED_VERSIONS,
+};
+use crate::extensions::types::SchemaOverride;
+
+/// Name of the messages schema file.
+const MESSAGES_FILE: &str = "messages.toml";
+
+/// Name of the tables file.
+const TABLES_FILE: &str = "tables.toml";
+
+/// Folder of version overlays.
+const VERSIONS_DIR: &str = "versions";
+
+/// Schema files loaded from a folder, replacing the embedded ones.
+#[derive(Debug, Clone, Default)]
+pub struct ExternalSchema {
+    /// Folder the files were loaded from.
+    pub directory: PathBuf,
+
+    /// Messages schema, if the folder has a `messages.toml`.
+    pub messages: Option<MessagesSchema>,
+
+    /// HL7 tables, if the folder has a `tables.toml`.
+    pub tables: Option<Vec<Table>>,
+
+    /// Segment schemas the folder has, keyed by segment name.
+    pub segments: HashMap<String, Vec<Field>>,
+
+    /// Version overlays the folder has, keyed by version.
+    pub overlays: HashMap<String, SchemaOverride>,
+
+    /// Files loaded, relative to the folder, sorted.
+    pub files: Vec<String>,
+}
+
+impl ExternalSchema {
+    /// Load the schema files in a folder.
+    ///
+    /// # Arguments
+    /// * `directory` - Folder of schema files
+    /// * `embedded_segments` - Segment file names from the embedded messages schema,
+    ///   used when the folder has no `messages.toml`
+    ///
+    /// # Returns
+    /// * `Ok(ExternalSchema)` - The files found (possibly none)
+    /// * `Err` - The folder doesn't exist, or a file in it couldn't be read or parsed
+    pub fn load(directory: &Path, embedded_segments: &SegmentPaths) -> Result<Self> {
+        if !directory.is_dir() {
+            color_eyre::eyre::bail!("{} isn't a folder", directory.display());
+        }
+        let mut schema = Self {
+            directory: directory.to_path_buf(),
+            ..Self::default()
+        };
+
+        if let Some(content) = schema.read(MESSAGES_FILE)? {
+            schema.messages = Some(
+                MessagesSchema::parse(&content).wrap_err_with(|| format!("in {MESSAGES_FILE}"))?,
+            );
+        }
+        if let Some(content) = schema.read(TABLES_FILE)? {
+            schema.tables =
+                Some(Table::parse(&content).wrap_err_with(|| format!("in {TABLES_FILE}"))?);
+        }
+
+        let segment_paths = schema
+            .messages
+            .as_ref()
+            .map_or(embedded_segments, |messages| &messages.segments)
+            .clone();
+        for (segment, file) in segment_paths {
+            if let Some(content) = schema.read(&file)? {
+                let fields = Field::parse(&content).wrap_err_with(|| format!("in {file}"))?;
+                schema.segments.insert(segment, fields);
+            }
+        }
+
+        let versions_dir = directory.join(VERSIONS_DIR);
+        for version in SUPPORTED_VERSIONS {
+            let file = format!("{VERSIONS_DIR}/{version}.toml");
+            if let Some(content) = schema.read(&file)? {
+                let overlay: SchemaOverride =
+                    toml::from_str(&content).wrap_err_with(|| format!("in {file}"))?;
+                schema.overlays.insert((*version).to_string(), overlay);
+            }
+        }
+        if let Ok(entries) = std::fs::read_dir(&versions_dir) {
+            for path in entries
+                .filter_map(std::result::Result::ok)
+                .map(|e| e.path())
+            {
+                let version = path.file_stem().and_then(|stem| stem.to_str());
+                if path.extension().is_some_and(|ext| ext == "toml")
+                    && !version.is_some_and(|v| SUPPORTED_VERSIONS.contains(&v))
+                {
+                    log::warn!(
+                        "ignoring schema overlay {} for an unsupported HL7 version",
+                        path.display()
+                    );
+                }
+            }
+        }
+
+        schema.files.sort();
+        Ok(schema)
+    }
+
+    /// Read a file of the folder, if it exists, noting that it was loaded.
+    fn read(&mut self, file: &str) -> Result<Option<String>> {
+        let path = self.directory.join(file);
+        if !path.is_file() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .wrap_err_with(|| format!("failed to read {}", path.display()))?;
+        self.files.push(file.to_string());
+        Ok(Some(content))
+    }
+}
+
+/// Modification times of the schema files in a folder, to tell when they change.
+///
+/// # Returns
+/// Each TOML file in the folder and its `versions` folder with its modification
+/// time, sorted by path; empty if the folder can't be read
+#[must_use]
+pub fn snapshot(directory: &Path) -> Vec<(PathBuf, SystemTime)> {
+    let mut files: Vec<(PathBuf, SystemTime)> =
+        [directory.to_path_buf(), directory.join(VERSIONS_DIR)]
+            .iter()
+            .filter_map(|dir| std::fs::read_dir(dir).ok())
+            .flatten()
+            .filter_map(std::result::Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+            .filter_map(|path| {
+                let modified = std::fs::metadata(&path).ok()?.modified().ok()?;
+                Some((path, modified))
+            })
+            .collect();
+    files.sort();
+    files
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use crate::commands::temp_dir;
+
+    fn embedded_segments() -> SegmentPaths {
+        [("PID".to_string(), "pid.toml".to_string())].into()
+    }
+
+    #[test]
+    fn loads_the_files_present() {
+        let dir = temp_dir("schema-dir");
+        std::fs::create_dir_all(dir.join(VERSIONS_DIR)).unwrap();
+        std::fs::write(
+            dir.join("pid.toml"),
+            "[[fields]]\nfield = 3\nname = \"Site MRN\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join(VERSIONS_DIR).join("2.3.toml"),
+            "[segments.PID]\nfields = [{ field = 3, name = \"Old MRN\" }]\n",
+        )
+        .unwrap();
+
+        let schema = ExternalSchema::load(&dir, &embedded_segments()).unwrap();
+        assert_eq!(schema.files, ["pid.toml", "versions/2.3.toml"]);
+        assert!(schema.messages.is_none());
+        assert!(schema.tables.is_none());
+        assert_eq!(schema.segments["PID"][0].name, "Site MRN");
+        assert!(schema.overlays.contains_key("2.3"));
+        assert_eq!(snapshot(&dir).len(), 2);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn a_bad_file_rejects_the_folder() {
+        let dir = temp_dir("schema-dir");
+        std::fs::create_dir_all(dir.join(VERSIONS_DIR)).unwrap();
+        std::fs::write(dir.join("pid.toml"), "[[fields]]\nfield = \"three\"\n").unwrap();
+        let error = ExternalSchema::load(&dir, &embedded_segments()).unwrap_err();
+        assert!(format!("{error:#}").contains("pid.toml"));
+
+        assert!(ExternalSchema::load(&dir.join("missing"), &embedded_segments()).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
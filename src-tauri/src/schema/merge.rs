@@ -1,7 +1,7 @@
 //! Schema merging for extension overrides.
 
 use crate::extensions::types::{FieldOverride, Nullable, SchemaOverride, SegmentOverride};
-use crate::schema::segment::{DataType, Field};
+use crate::schema::segment::{Classification, DataType, Field};
 use indexmap::IndexMap;
 use std::collections::HashMap;
 
@@ -9,6 +9,8 @@ use std::collections::HashMap;
 ///
 /// # Merge Logic
 /// - Field and component are preserved from base (override provides match info only)
+/// - Conditional rules (`required_if`, `forbidden_unless`) are preserved from base,
+///   as overrides can't express them
 /// - If override has `Nullable::Value(v)`, use v
 /// - If override has `Nullable::Null`, set to None (unset)
 /// - If override has `None` (absent), keep base value
@@ -29,11 +31,15 @@ pub fn merge_field(base: &Field, override_field: &FieldOverride) -> Field {
         maxlength: merge_minmax_length(&base.maxlength, &override_field.maxlength),
         placeholder: merge_option_nullable(&base.placeholder, &override_field.placeholder),
         required: merge_option_nullable(&base.required, &override_field.required),
+        required_if: base.required_if.clone(),
+        forbidden_unless: base.forbidden_unless.clone(),
         datatype: merge_datatype(&base.datatype, &override_field.datatype),
         pattern: merge_option_nullable(&base.pattern, &override_field.pattern),
         note: merge_option_nullable(&base.note, &override_field.note),
         values: merge_values(&base.values, &override_field.values),
+        table: merge_option_nullable(&base.table, &override_field.table),
         template: merge_option_nullable(&base.template, &override_field.template),
+        classification: merge_classification(&base.classification, &override_field.classification),
     }
 }
 
@@ -85,6 +91,33 @@ fn merge_datatype(
     }
 }
 
+/// Merge classification field, converting from string to Classification enum.
+fn merge_classification(
+    base: &Option<Classification>,
+    override_value: &Option<Nullable<String>>,
+) -> Option<Classification> {
+    match override_value {
+        None => *base,
+        Some(Nullable::Value(s)) => parse_classification_str(s).or_else(|| {
+            log::warn!(
+                "invalid classification '{}' in schema override, keeping base value",
+                s
+            );
+            *base
+        }),
+        Some(Nullable::Null) => None,
+    }
+}
+
+/// Parse a classification string ("phi" | "not-phi").
+fn parse_classification_str(s: &str) -> Option<Classification> {
+    match s {
+        "phi" => Some(Classification::Phi),
+        "not-phi" => Some(Classification::NotPhi),
+        _ => None,
+    }
+}
+
 /// Merge values field, converting from IndexMap to HashMap.
 ///
 /// Note: This performs a **full replacement**, not a key-by-key merge. When an
@@ -217,11 +250,17 @@ fn field_from_override(override_field: &FieldOverride) -> Field {
         maxlength: unwrap_nullable_u16(&override_field.maxlength),
         placeholder: unwrap_nullable(&override_field.placeholder),
         required: unwrap_nullable(&override_field.required),
+        required_if: None,
+        forbidden_unless: None,
         datatype: parse_datatype(&override_field.datatype),
         pattern: unwrap_nullable(&override_field.pattern),
         note: unwrap_nullable(&override_field.note),
         values: unwrap_values(&override_field.values),
+        table: unwrap_nullable(&override_field.table),
         template: unwrap_nullable(&override_field.template),
+        classification: unwrap_nullable(&override_field.classification)
+            .as_deref()
+            .and_then(parse_classification_str),
     }
 }
 
@@ -296,7 +335,14 @@ fn field_to_override(field: &Field) -> FieldOverride {
             }
             Nullable::Value(index_map)
         }),
+        table: option_to_nullable(&field.table),
         template: option_to_nullable(&field.template),
+        classification: field.classification.map(|classification| {
+            Nullable::Value(match classification {
+                Classification::Phi => "phi".to_string(),
+                Classification::NotPhi => "not-phi".to_string(),
+            })
+        }),
     }
 }
 
@@ -374,11 +420,15 @@ mod tests {
             maxlength: None,
             placeholder: None,
             required: None,
+            required_if: None,
+            forbidden_unless: None,
             datatype: None,
             pattern: None,
             note: None,
             values: None,
+            table: None,
             template: None,
+            classification: None,
         }
     }
 
@@ -399,7 +449,9 @@ mod tests {
             datatype: None,
             placeholder: None,
             values: None,
+            table: None,
             template: None,
+            classification: None,
         };
 
         let merged = merge_field(&base, &override_);
@@ -419,11 +471,15 @@ mod tests {
             maxlength: Some(100),
             placeholder: None,
             required: Some(true),
+            required_if: None,
+            forbidden_unless: None,
             datatype: None,
             pattern: None,
             note: None,
             values: None,
+            table: None,
             template: None,
+            classification: None,
         };
 
         let override_ = FieldOverride {
@@ -439,7 +495,9 @@ mod tests {
             datatype: None,
             placeholder: None,
             values: None,
+            table: None,
             template: None,
+            classification: None,
         };
 
         let merged = merge_field(&base, &override_);
@@ -461,11 +519,15 @@ mod tests {
             maxlength: Some(100),
             placeholder: Some("Enter name".to_string()),
             required: Some(true),
+            required_if: None,
+            forbidden_unless: None,
             datatype: Some(DataType::Date),
             pattern: Some("[A-Z]+".to_string()),
             note: Some("Important field".to_string()),
             values: None,
+            table: None,
             template: Some("DOE".to_string()),
+            classification: None,
         };
 
         let override_ = FieldOverride {
@@ -481,7 +543,9 @@ mod tests {
             datatype: None,    // absent, should preserve base
             placeholder: None, // absent, should preserve base
             values: None,      // absent, should preserve base
+            table: None,       // absent, should preserve base
             template: None,    // absent, should preserve base
+            classification: None,
         };
 
         let merged = merge_field(&base, &override_);
@@ -513,11 +577,15 @@ mod tests {
             maxlength: Some(1),
             placeholder: Some("M/F".to_string()),
             required: Some(true),
+            required_if: None,
+            forbidden_unless: None,
             datatype: None,
             pattern: Some("[MF]".to_string()),
             note: Some("Patient gender".to_string()),
             values: Some(base_values),
+            table: Some("0001".to_string()),
             template: Some("M".to_string()),
+            classification: None,
         };
 
         let override_ = FieldOverride {
@@ -533,7 +601,9 @@ mod tests {
             datatype: None,
             placeholder: Some(Nullable::Null), // unset placeholder
             values: Some(Nullable::Null),      // unset values
+            table: Some(Nullable::Null),       // unset table
             template: Some(Nullable::Null),    // unset template
+            classification: None,
         };
 
         let merged = merge_field(&base, &override_);
@@ -546,6 +616,7 @@ mod tests {
         assert_eq!(merged.pattern, None);
         assert_eq!(merged.placeholder, None);
         assert_eq!(merged.values, None);
+        assert_eq!(merged.table, None);
         assert_eq!(merged.template, None);
     }
 
@@ -569,7 +640,9 @@ mod tests {
             datatype: None,
             placeholder: None,
             values: None,
+            table: None,
             template: None,
+            classification: None,
         }];
 
         let merged = merge_segment_fields(&base, &overrides);
@@ -601,7 +674,9 @@ mod tests {
             datatype: None,
             placeholder: None,
             values: None,
+            table: None,
             template: None,
+            classification: None,
         }];
 
         let merged = merge_segment_fields(&base, &overrides);
@@ -633,7 +708,9 @@ mod tests {
                             datatype: None,
                             placeholder: None,
                             values: None,
+                            table: None,
                             template: None,
+                            classification: None,
                         }]),
                     },
                 );
@@ -660,7 +737,9 @@ mod tests {
                             datatype: None,
                             placeholder: None,
                             values: None,
+                            table: None,
                             template: None,
+                            classification: None,
                         }]),
                     },
                 );
@@ -702,7 +781,9 @@ mod tests {
             datatype: Some(Nullable::Value("date".to_string())),
             placeholder: None,
             values: None,
+            table: None,
             template: None,
+            classification: None,
         };
 
         let merged = merge_field(&base, &override_);
@@ -721,7 +802,9 @@ mod tests {
             datatype: Some(Nullable::Value("datetime".to_string())),
             placeholder: None,
             values: None,
+            table: None,
             template: None,
+            classification: None,
         };
 
         let merged_datetime = merge_field(&base, &override_datetime);
@@ -750,7 +833,9 @@ mod tests {
             datatype: None,
             placeholder: None,
             values: Some(Nullable::Value(override_values.clone())),
+            table: None,
             template: None,
+            classification: None,
         };
 
         let merged = merge_field(&base, &override_);
@@ -780,7 +865,9 @@ mod tests {
             datatype: None,
             placeholder: None,
             values: None,
+            table: None,
             template: None,
+            classification: None,
         }];
 
         let merged = merge_segment_fields(&base, &overrides);
@@ -815,7 +902,9 @@ mod tests {
             datatype: None,
             placeholder: None,
             values: None,
+            table: None,
             template: None,
+            classification: None,
         }];
 
         let merged = merge_segment_fields(&base, &overrides);
@@ -19,7 +19,19 @@
 //! [[message.ADT_A01]]
 //! name = "PID"
 //! required = true
+//!
+//! [[message.ADT_A01]]
+//! name = "NK1"
+//! repeating = true
 //! ```
+//!
+//! # Segment Order and Groups
+//! Segments are listed in the order they appear in the message. Consecutive
+//! segments sharing a `group` name repeat together as a unit, like the
+//! ORC/OBR/OBX groups of an order or result message, so a second OBR may follow
+//! the first order's OBX segments. Nested groups are written as a path, e.g.
+//! `"order/observation"` for the OBX/NTE pairs within an order. Within a group,
+//! `required` applies to every occurrence of the group.
 
 use color_eyre::{eyre::Context, Result};
 use serde::{Deserialize, Serialize};
@@ -38,6 +50,12 @@ pub struct SegmentMetadata {
     pub name: String,
     /// Whether this segment is required in the message type
     pub required: Option<bool>,
+    /// Whether this segment may appear several times in a row
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub repeating: Option<bool>,
+    /// Repeating group this segment belongs to, if any (e.g., "order/observation")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
 }
 
 /// Top-level messages schema loaded from messages.toml.
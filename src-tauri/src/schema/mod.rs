@@ -10,6 +10,36 @@
 //! `build.rs`. This enables single-binary distribution without external data files.
 //! The trade-off is that schema changes require recompilation.
 //!
+//! # Tables
+//!
+//! HL7 tables in `data/tables.toml` hold coded value sets shared by several fields.
+//! Fields are bound to a table by number and take their allowed values from it.
+//! See `table.rs` for details.
+//!
+//! # HL7 Versions
+//!
+//! The base schemas describe a single HL7 version. Overlays in `data/versions/`
+//! describe how other supported versions differ, and `SchemaCache` builds each
+//! version's schema from them. See `version.rs` for how a message's version is resolved.
+//!
+//! # Startup
+//!
+//! Segment schemas are parsed lazily on first access, and the parsed result is
+//! persisted to a binary cache in the data folder so later launches can skip TOML
+//! parsing entirely. See `cache.rs` for details.
+//!
+//! # External Schema Folder
+//!
+//! The embedded files can be replaced at runtime by those in a folder with the
+//! same layout as `data/`, which is reloaded whenever it changes. See
+//! `external.rs` for details.
+//!
+//! # Custom Segments
+//!
+//! Users can define schemas for their site's own Z-segments, kept as TOML files
+//! in the data folder and served by `SchemaCache` like embedded schemas. See
+//! `custom.rs` for details.
+//!
 //! # Extension Overrides
 //!
 //! Extensions can provide runtime schema overrides via the extension API. These are
@@ -17,6 +47,10 @@
 //! See `merge.rs` for the merging semantics.
 
 pub mod cache;
+pub mod custom;
+pub mod external;
 pub mod merge;
 pub mod message;
 pub mod segment;
+pub mod table;
+pub mod version;
@@ -14,6 +14,7 @@
 //! [[fields]]
 //! field = 3
 //! name = "Patient ID"
+//! classification = "phi"
 //! required = true
 //! maxlength = 20
 //! template = "MRN123456"
@@ -31,8 +32,52 @@
 //! **File > New from Template**. Special values:
 //!
 //! * `{auto}` - Placeholder for values generated at send time (timestamps, control IDs)
+//! * `{now-2h}`, `{uuid}`, `{seq}`, ... - Placeholders filled in when the message is
+//!   generated (see `commands/editor/placeholders.rs`)
 //! * Empty string - Field left blank intentionally
 //! * Regular value - Used directly in the generated message
+//!
+//! ## Tables
+//!
+//! Coded fields are bound to an HL7 table with `table = "0001"` rather than
+//! listing the table's values on every field that uses it. The schema cache
+//! fills in `values` from the table, unless the field lists its own, which
+//! take precedence. See `table.rs` for the table definitions.
+//!
+//! ## Classification
+//!
+//! `classification` marks whether a field carries protected health information
+//! (`"phi"`) or has been reviewed and doesn't (`"not-phi"`). The PHI exposure
+//! report lists populated PHI fields, and flags populated fields with no
+//! classification so gaps in the schema show up rather than passing silently.
+//!
+//! ## Conditional Rules
+//!
+//! Many conformance statements only require a field in some messages: units
+//! are only needed for numeric observations, and a parent document number only
+//! makes sense for addenda, edits, and replacements. `required_if` makes a field required when
+//! a condition holds, and `forbidden_unless` requires it to be empty unless one
+//! does.
+//!
+//! ```toml
+//! [[fields]]
+//! field = 6
+//! component = 1
+//! name = "Identifier"
+//! required_if = { path = "OBX.2", one_of = ["NM", "SN"] }
+//!
+//! [[fields]]
+//! field = 13
+//! component = 1
+//! name = "Parent Document Number"
+//! forbidden_unless = { path = "MSH.9.2", one_of = ["T05", "T06", "T07", "T08", "T09", "T10"] }
+//! ```
+//!
+//! A condition with neither `equals` nor `one_of` holds when its field is
+//! populated. Paths into the same segment refer to the occurrence being
+//! checked, so each OBX is judged by its own value type; paths into other
+//! segments refer to their first occurrence. `required = true` takes precedence
+//! over `required_if`.
 
 use color_eyre::{eyre::Context, Result};
 use serde::{Deserialize, Serialize};
@@ -51,6 +96,28 @@ pub enum DataType {
     DateTime,
 }
 
+/// Privacy classification of a field.
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Classification {
+    /// Protected health information: identifies the patient or describes their care
+    Phi,
+    /// Reviewed and not considered protected health information
+    NotPhi,
+}
+
+/// Condition on another field that a conditional rule depends on.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct FieldCondition {
+    /// Field path to test (e.g., "OBX.2", "PID.30", "MSH.9.2")
+    pub path: String,
+    /// Value the field must equal
+    pub equals: Option<String>,
+    /// Values the field must be one of
+    pub one_of: Option<Vec<String>>,
+}
+
 /// Definition of a field or component within an HL7 segment.
 ///
 /// Fields can be either:
@@ -79,6 +146,10 @@ pub struct Field {
     pub placeholder: Option<String>,
     /// Whether this field is required (for validation)
     pub required: Option<bool>,
+    /// Condition under which the field is required, if it's only sometimes required
+    pub required_if: Option<FieldCondition>,
+    /// Condition without which the field must be left empty
+    pub forbidden_unless: Option<FieldCondition>,
     /// Special data type for custom rendering/validation
     pub datatype: Option<DataType>,
     /// Regex pattern for validation
@@ -87,8 +158,12 @@ pub struct Field {
     pub note: Option<String>,
     /// Map of valid values (code → description) for enumerated fields
     pub values: Option<HashMap<String, String>>,
+    /// HL7 table the field's values come from (e.g., "0001"), if it's bound to one
+    pub table: Option<String>,
     /// Template/example value used when generating message templates
     pub template: Option<String>,
+    /// Privacy classification, if the field has been classified
+    pub classification: Option<Classification>,
 }
 
 /// Wrapper for deserializing TOML segment files.
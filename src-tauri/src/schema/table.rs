@@ -0,0 +1,77 @@
+//! HL7 tables loaded from TOML.
+//!
+//! Coded fields in HL7 draw their values from numbered tables (0001 for
+//! administrative sex, 0004 for patient class, and so on), and the same table
+//! is often used by several fields. Tables are defined once in `tables.toml`
+//! and fields are bound to one by number, rather than each field listing its
+//! own copy of the values.
+//!
+//! # Table File Format
+//!
+//! ```toml
+//! [[tables]]
+//! id = "0001"
+//! name = "Administrative Sex"
+//! kind = "user"
+//!
+//! [tables.values]
+//! F = "Female"
+//! M = "Male"
+//! ```
+//!
+//! # Binding Fields
+//!
+//! A segment field names its table with `table = "0001"`. When a field is
+//! bound to a table and has no `values` of its own, the schema cache fills its
+//! values in from the table, so validation and dropdowns treat them exactly as
+//! if they'd been listed on the field. A field that only accepts part of a
+//! table can list `values` directly; they take precedence over the table.
+
+use color_eyre::{eyre::Context, Result};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+
+/// Who defines a table's values.
+#[derive(Debug, Deserialize, Serialize, Copy, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TableKind {
+    /// Defined by the HL7 standard
+    Hl7,
+    /// User-defined: the standard suggests values, and sites add their own
+    User,
+}
+
+/// An HL7 table of coded values.
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct Table {
+    /// Table number, zero-padded to four digits (e.g., "0001")
+    pub id: String,
+    /// Table name (e.g., "Administrative Sex")
+    pub name: String,
+    /// Who defines the table's values
+    pub kind: TableKind,
+    /// Codes and their descriptions, in table order
+    pub values: IndexMap<String, String>,
+}
+
+/// Wrapper for deserializing the tables file.
+#[derive(Debug, Deserialize)]
+struct Tables {
+    tables: Vec<Table>,
+}
+
+impl Table {
+    /// Parse table definitions from TOML content.
+    ///
+    /// # Arguments
+    /// * `contents` - TOML string content
+    ///
+    /// # Returns
+    /// * `Ok(Vec<Table>)` - Parsed tables, in file order
+    /// * `Err` - Failed to parse the TOML content
+    pub fn parse(contents: &str) -> Result<Vec<Self>> {
+        let tables: Tables = toml::from_str(contents).wrap_err("failed to parse tables")?;
+        Ok(tables.tables)
+    }
+}
@@ -0,0 +1,111 @@
+//! HL7 version selection and detection.
+//!
+//! Hermes ships schema data for a fixed set of HL7 v2 versions. The base schema
+//! in `data/` describes [`DEFAULT_VERSION`]; every other supported version is
+//! an overlay in `data/versions/` that only lists where it differs.
+//!
+//! # Resolution
+//!
+//! The version used for a message is resolved in order:
+//! 1. The version explicitly selected by the user (if any)
+//! 2. The version declared in the message's MSH.12, if it maps to a supported version
+//! 3. [`DEFAULT_VERSION`]
+//!
+//! # Why Map Versions?
+//!
+//! Messages in the wild declare all sorts of versions ("2.5", "2.7.1", ...) that
+//! differ from their nearest supported neighbour only in errata. Mapping them
+//! onto the closest supported schema is more useful than falling back to the
+//! default.
+
+/// HL7 versions that have schema data, oldest first.
+pub const SUPPORTED_VERSIONS: &[&str] = &["2.3", "2.3.1", "2.4", "2.5.1", "2.7"];
+
+/// Version described by the base schema in `data/`.
+pub const DEFAULT_VERSION: &str = "2.5.1";
+
+/// Map a declared HL7 version onto a supported schema version.
+///
+/// # Arguments
+/// * `version` - Version string as it appears in MSH.12 (e.g., "2.5", "2.7.1")
+///
+/// # Returns
+/// The supported version to use, or `None` if the version isn't recognised
+pub fn normalise_version(version: &str) -> Option<&'static str> {
+    match version.trim() {
+        "2.3" => Some("2.3"),
+        "2.3.1" => Some("2.3.1"),
+        "2.4" => Some("2.4"),
+        "2.5" | "2.5.1" => Some("2.5.1"),
+        "2.6" | "2.7" | "2.7.1" => Some("2.7"),
+        _ => None,
+    }
+}
+
+/// Detect the HL7 version declared in a message's MSH.12.
+///
+/// This is a cheap scan of the MSH segment rather than a full parse, since it
+/// runs on every cursor movement when looking up field descriptions.
+///
+/// # Arguments
+/// * `message` - Raw HL7 message text
+///
+/// # Returns
+/// The supported version matching MSH.12, or `None` if the message has no MSH
+/// segment or declares an unrecognised version
+pub fn detect_version(message: &str) -> Option<&'static str> {
+    let msh = message
+        .split(['\r', '\n'])
+        .find(|line| line.starts_with("MSH"))?;
+
+    let mut chars = msh.chars().skip(3);
+    let field_separator = chars.next()?;
+    let component_separator = chars.next()?;
+
+    // MSH.1 is the separator itself, so MSH.12 is the 11th split after the name
+    let version_field = msh.split(field_separator).nth(11)?;
+    let version = version_field.split(component_separator).next()?;
+
+    normalise_version(version)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_normalise_versions() {
+        assert_eq!(normalise_version("2.3"), Some("2.3"));
+        assert_eq!(normalise_version("2.5"), Some("2.5.1"));
+        assert_eq!(normalise_version("2.7.1"), Some("2.7"));
+        assert_eq!(normalise_version("3.0"), None);
+    }
+
+    #[test]
+    fn can_detect_version_from_msh() {
+        let message = "MSH|^~\\&|APP|FAC|||20250101||ADT^A01|123|P|2.3.1\rPID|||12345";
+        assert_eq!(detect_version(message), Some("2.3.1"));
+    }
+
+    #[test]
+    fn can_detect_version_with_components() {
+        let message = "MSH|^~\\&|APP|FAC|||20250101||ADT^A01|123|P|2.4^CAN\nPID|||12345";
+        assert_eq!(detect_version(message), Some("2.4"));
+    }
+
+    #[test]
+    fn detect_version_handles_missing_msh12() {
+        assert_eq!(detect_version("MSH|^~\\&|APP|FAC"), None);
+        assert_eq!(detect_version("PID|||12345"), None);
+        assert_eq!(detect_version(""), None);
+    }
+
+    #[test]
+    fn all_normalised_versions_are_supported() {
+        for version in ["2.3", "2.3.1", "2.4", "2.5", "2.5.1", "2.6", "2.7", "2.7.1"] {
+            let normalised = normalise_version(version).unwrap();
+            assert!(SUPPORTED_VERSIONS.contains(&normalised));
+        }
+    }
+}
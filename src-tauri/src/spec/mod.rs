@@ -1 +1,2 @@
 pub mod std_spec;
+pub mod supplement;
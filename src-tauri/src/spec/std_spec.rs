@@ -2,6 +2,59 @@
 /// https://github.com/hamaluik/hl7-ls/blob/main/src/spec.rs
 use hl7_parser::Message;
 
+use super::supplement;
+
+/// A field of a segment, as the HL7 definitions or the supplement describe it.
+struct FieldDefinition<'a> {
+    description: &'a str,
+    datatype: &'a str,
+    max_length: Option<String>,
+    optionality: &'a hl7_definitions::FieldOptionality,
+    repeatability: &'a hl7_definitions::FieldRepeatability,
+}
+
+/// The fields of a segment, from the HL7 definitions or, for segments they
+/// leave out, the supplement (see [`supplement`]).
+fn segment_fields<'a>(version: &'a str, segment: &'a str) -> Option<Vec<FieldDefinition<'a>>> {
+    if let Some(s) = hl7_definitions::get_segment(version, segment) {
+        return Some(
+            s.fields
+                .iter()
+                .map(|f| FieldDefinition {
+                    description: f.description,
+                    datatype: f.datatype,
+                    max_length: f.max_length.map(|l| l.to_string()),
+                    optionality: &f.optionality,
+                    repeatability: &f.repeatability,
+                })
+                .collect(),
+        );
+    }
+    supplement::segment(segment).map(|s| {
+        s.fields
+            .iter()
+            .map(|f| FieldDefinition {
+                description: &f.description,
+                datatype: &f.datatype,
+                max_length: None,
+                optionality: &f.optionality,
+                repeatability: &f.repeatability,
+            })
+            .collect()
+    })
+}
+
+/// A single field of a segment, from the HL7 definitions or the supplement.
+fn field_definition<'a>(
+    version: &'a str,
+    segment: &'a str,
+    field: usize,
+) -> Option<FieldDefinition<'a>> {
+    segment_fields(version, segment)?
+        .into_iter()
+        .nth(field.checked_sub(1)?)
+}
+
 pub fn is_valid_version(version: &str) -> bool {
     hl7_definitions::VERSIONS.contains(&version)
 }
@@ -21,9 +74,19 @@ pub fn get_version_with_fallback<'m>(message: &'m Message) -> &'m str {
 }
 
 pub fn segment_description(version: &str, segment: &str) -> String {
+    known_segment_description(version, segment).unwrap_or_else(|| "Unknown segment".to_string())
+}
+
+/// Describe a segment, if the spec or the supplement defines it
+///
+/// # Arguments
+///
+/// * `version` - The HL7 version
+/// * `segment` - The segment name
+pub fn known_segment_description(version: &str, segment: &str) -> Option<String> {
     hl7_definitions::get_segment(version, segment)
         .map(|s| s.description.to_string())
-        .unwrap_or_else(|| "Unknown segment".to_string())
+        .or_else(|| supplement::segment(segment).map(|s| s.description.clone()))
 }
 
 /// Check if a field is a timestamp
@@ -34,8 +97,7 @@ pub fn segment_description(version: &str, segment: &str) -> String {
 /// * `segment` - The segment name
 /// * `field` - The field number (1-indexed)
 pub fn is_field_a_timestamp(version: &str, segment: &str, field: usize) -> bool {
-    hl7_definitions::get_segment(version, segment)
-        .and_then(|s| s.fields.get(field - 1))
+    field_definition(version, segment, field)
         .map(|f| f.datatype == "TS" || f.datatype == "DTM")
         .unwrap_or(false)
 }
@@ -54,10 +116,9 @@ pub fn is_component_a_timestamp(
     field: usize,
     component: usize,
 ) -> bool {
-    hl7_definitions::get_segment(version, segment)
-        .and_then(|s| s.fields.get(field - 1))
+    field_definition(version, segment, field)
         .and_then(|f| hl7_definitions::get_field(version, f.datatype))
-        .and_then(|f| f.subfields.get(component - 1))
+        .and_then(|f| f.subfields.get(component.wrapping_sub(1)))
         .map(|c| c.datatype == "TS" || c.datatype == "DTM")
         .unwrap_or(false)
 }
@@ -71,37 +132,19 @@ pub fn is_component_a_timestamp(
 /// * `segment` - The segment name
 /// * `field` - The field number (1-indexed)
 pub fn describe_field(version: &str, segment: &str, field: usize) -> String {
-    hl7_definitions::get_segment(version, segment)
-        .map(|s| {
-            s.fields
-                .get(field - 1)
+    segment_fields(version, segment)
+        .map(|fields| {
+            fields
+                .into_iter()
+                .nth(field.wrapping_sub(1))
                 .map(|f| {
-                    let datatype = hl7_definitions::get_field(version, f.datatype)
-                        .map(|d| d.description)
-                        .unwrap_or_else(|| "Unknown datatype");
-
-                    let repeat = match f.repeatability {
-                        hl7_definitions::FieldRepeatability::Unbounded => "∞",
-                        hl7_definitions::FieldRepeatability::Single => "1",
-                        hl7_definitions::FieldRepeatability::Bounded(n) => &n.to_string(),
-                    };
-
-                    let optional = match f.optionality {
-                        hl7_definitions::FieldOptionality::Required => "*required*",
-                        hl7_definitions::FieldOptionality::Optional => "*optional*",
-                        hl7_definitions::FieldOptionality::Conditional => "*conditional*",
-                        hl7_definitions::FieldOptionality::BackwardCompatibility => {
-                            "*backwards compatibility*"
-                        }
-                    };
-
-                    format!(
-                        "{description}, len: {len} ({datatype}) [{optional}/{repeat}]",
-                        description = f.description,
-                        len = f
-                            .max_length
-                            .map(|l| l.to_string())
-                            .unwrap_or_else(|| "∞".to_string()),
+                    describe_element(
+                        version,
+                        f.description,
+                        f.datatype,
+                        f.max_length,
+                        f.optionality,
+                        f.repeatability,
                     )
                 })
                 .unwrap_or_else(|| "Unknown field".to_string())
@@ -119,41 +162,22 @@ pub fn describe_field(version: &str, segment: &str, field: usize) -> String {
 /// * `field` - The field number (1-indexed)
 /// * `component` - The component number (1-indexed)
 pub fn describe_component(version: &str, segment: &str, field: usize, component: usize) -> String {
-    hl7_definitions::get_segment(version, segment)
-        .map(|s| {
-            s.fields
-                .get(field - 1)
+    segment_fields(version, segment)
+        .map(|fields| {
+            fields
+                .into_iter()
+                .nth(field.wrapping_sub(1))
                 .map(|f| {
                     hl7_definitions::get_field(version, f.datatype)
-                        .and_then(|f| f.subfields.get(component - 1))
+                        .and_then(|f| f.subfields.get(component.wrapping_sub(1)))
                         .map(|c| {
-                            let datatype = hl7_definitions::get_field(version, c.datatype)
-                                .map(|d| d.description)
-                                .unwrap_or_else(|| "Unknown datatype");
-
-                            let repeat = match c.repeatability {
-                                hl7_definitions::FieldRepeatability::Unbounded => "∞",
-                                hl7_definitions::FieldRepeatability::Single => "1",
-                                hl7_definitions::FieldRepeatability::Bounded(n) => &n.to_string(),
-                            };
-
-                            let optional = match c.optionality {
-                                hl7_definitions::FieldOptionality::Required => "*required*",
-                                hl7_definitions::FieldOptionality::Optional => "*optional*",
-                                hl7_definitions::FieldOptionality::Conditional => "*conditional*",
-                                hl7_definitions::FieldOptionality::BackwardCompatibility => {
-                                    "*backwards compatibility*"
-                                }
-                            };
-
-                            format!(
-                                "{field_description} / {component_description}, len: {len} ({datatype}) [{optional}/{repeat}]",
-                                field_description = f.description,
-                                component_description = c.description,
-                                len = c
-                                    .max_length
-                                    .map(|l| l.to_string())
-                                    .unwrap_or_else(|| "∞".to_string()),
+                            describe_element(
+                                version,
+                                &format!("{} / {}", f.description, c.description),
+                                c.datatype,
+                                c.max_length.map(|l| l.to_string()),
+                                &c.optionality,
+                                &c.repeatability,
                             )
                         })
                         .unwrap_or_else(|| "Unknown component".to_string())
@@ -162,3 +186,196 @@ pub fn describe_component(version: &str, segment: &str, field: usize, component:
         })
         .unwrap_or_else(|| "Unknown segment".to_string())
 }
+
+/// Describe a subcomponent of a component, including its datatype, length,
+/// repeatability, and optionality.
+///
+/// # Arguments
+///
+/// * `version` - The HL7 version
+/// * `segment` - The segment name
+/// * `field` - The field number (1-indexed)
+/// * `component` - The component number (1-indexed)
+/// * `subcomponent` - The subcomponent number (1-indexed)
+pub fn describe_subcomponent(
+    version: &str,
+    segment: &str,
+    field: usize,
+    component: usize,
+    subcomponent: usize,
+) -> String {
+    let Some(fields) = segment_fields(version, segment) else {
+        return "Unknown segment".to_string();
+    };
+    let Some(f) = fields.into_iter().nth(field.wrapping_sub(1)) else {
+        return "Unknown field".to_string();
+    };
+    let Some(c) = hl7_definitions::get_field(version, f.datatype)
+        .and_then(|d| d.subfields.get(component.wrapping_sub(1)))
+    else {
+        return "Unknown component".to_string();
+    };
+    hl7_definitions::get_field(version, c.datatype)
+        .and_then(|d| d.subfields.get(subcomponent.wrapping_sub(1)))
+        .map(|sc| {
+            describe_element(
+                version,
+                &format!("{} / {} / {}", f.description, c.description, sc.description),
+                sc.datatype,
+                sc.max_length.map(|l| l.to_string()),
+                &sc.optionality,
+                &sc.repeatability,
+            )
+        })
+        .unwrap_or_else(|| "Unknown subcomponent".to_string())
+}
+
+/// Describe a data type, listing the components of a composite type.
+///
+/// # Arguments
+///
+/// * `version` - The HL7 version
+/// * `datatype` - The data type code (e.g., "XPN", "CX", "ST")
+///
+/// # Returns
+///
+/// The data type's description, followed by its components separated by `^`
+/// for composite types (e.g., "Hierarchic Designator: Namespace ID ^ Universal
+/// ID ^ Universal ID Type"), or None if the spec doesn't define the type
+pub fn describe_datatype(version: &str, datatype: &str) -> Option<String> {
+    let definition = hl7_definitions::get_field(version, datatype)?;
+    if definition.subfields.is_empty() {
+        return Some(definition.description.to_string());
+    }
+    let components = definition
+        .subfields
+        .iter()
+        .map(|c| c.description)
+        .collect::<Vec<_>>()
+        .join(" ^ ");
+    Some(format!("{}: {components}", definition.description))
+}
+
+/// Describe a field, component, or subcomponent from its definition.
+fn describe_element(
+    version: &str,
+    description: &str,
+    datatype: &str,
+    max_length: Option<String>,
+    optionality: &hl7_definitions::FieldOptionality,
+    repeatability: &hl7_definitions::FieldRepeatability,
+) -> String {
+    let datatype_description = hl7_definitions::get_field(version, datatype)
+        .map(|d| d.description)
+        .unwrap_or_else(|| "Unknown datatype");
+
+    let repeat = match repeatability {
+        hl7_definitions::FieldRepeatability::Unbounded => "∞".to_string(),
+        hl7_definitions::FieldRepeatability::Single => "1".to_string(),
+        hl7_definitions::FieldRepeatability::Bounded(n) => n.to_string(),
+    };
+
+    let optional = match optionality {
+        hl7_definitions::FieldOptionality::Required => "*required*",
+        hl7_definitions::FieldOptionality::Optional => "*optional*",
+        hl7_definitions::FieldOptionality::Conditional => "*conditional*",
+        hl7_definitions::FieldOptionality::BackwardCompatibility => "*backwards compatibility*",
+    };
+
+    format!(
+        "{description}, len: {len} ({datatype} - {datatype_description}) [{optional}/{repeat}]",
+        len = max_length.unwrap_or_else(|| "∞".to_string()),
+    )
+}
+
+/// The description of every field in a segment, in field order.
+///
+/// # Arguments
+///
+/// * `version` - The HL7 version
+/// * `segment` - The segment name
+pub fn field_descriptions(version: &str, segment: &str) -> Vec<String> {
+    segment_fields(version, segment)
+        .map(|fields| fields.iter().map(|f| f.description.to_string()).collect())
+        .unwrap_or_default()
+}
+
+/// The description of every component of a field, in component order.
+///
+/// # Arguments
+///
+/// * `version` - The HL7 version
+/// * `segment` - The segment name
+/// * `field` - The field number (1-indexed)
+pub fn component_descriptions(version: &str, segment: &str, field: usize) -> Vec<String> {
+    field_definition(version, segment, field)
+        .and_then(|f| hl7_definitions::get_field(version, f.datatype))
+        .map(|f| {
+            f.subfields
+                .iter()
+                .map(|c| c.description.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The description of every subcomponent of a component, in subcomponent order.
+///
+/// # Arguments
+///
+/// * `version` - The HL7 version
+/// * `segment` - The segment name
+/// * `field` - The field number (1-indexed)
+/// * `component` - The component number (1-indexed)
+pub fn subcomponent_descriptions(
+    version: &str,
+    segment: &str,
+    field: usize,
+    component: usize,
+) -> Vec<String> {
+    field_datatype(version, segment, field)
+        .and_then(|datatype| component_datatype(version, &datatype, component))
+        .and_then(|datatype| hl7_definitions::get_field(version, &datatype))
+        .map(|c| {
+            c.subfields
+                .iter()
+                .map(|sc| sc.description.to_string())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The data type of a field, if the spec defines it (e.g., "XPN" for PID.5).
+///
+/// # Arguments
+///
+/// * `version` - The HL7 version
+/// * `segment` - The segment name
+/// * `field` - The field number (1-indexed)
+pub fn field_datatype(version: &str, segment: &str, field: usize) -> Option<String> {
+    field_definition(version, segment, field).map(|f| f.datatype.to_string())
+}
+
+/// The data type of a component of a composite data type, if the spec defines
+/// it (e.g., "FN" for XPN.1).
+///
+/// # Arguments
+///
+/// * `version` - The HL7 version
+/// * `datatype` - The composite data type code
+/// * `component` - The component number (1-indexed)
+pub fn component_datatype(version: &str, datatype: &str, component: usize) -> Option<String> {
+    hl7_definitions::get_field(version, datatype)
+        .and_then(|d| d.subfields.get(component.checked_sub(1)?))
+        .map(|c| c.datatype.to_string())
+}
+
+/// Check if a data type is a composite of components (e.g., "XPN", but not "ST")
+///
+/// # Arguments
+///
+/// * `version` - The HL7 version
+/// * `datatype` - The data type code
+pub fn is_composite_datatype(version: &str, datatype: &str) -> bool {
+    hl7_definitions::get_field(version, datatype).is_some_and(|d| !d.subfields.is_empty())
+}
@@ -0,0 +1,182 @@
+//! Descriptions of segments the HL7 definitions leave out.
+//!
+//! The definitions behind [`super::std_spec`] have nothing for several of the
+//! financial, insurance, scheduling, and immunization segments (IN1, GT1, FT1,
+//! SCH, the AI* resources, RXA, ...), so tooltips were blank for much of DFT,
+//! SIU, and VXU traffic. Those segments are described in
+//! `data/spec_supplement.toml` instead, and [`super::std_spec`] falls back to
+//! this module for segments the definitions don't have.
+//!
+//! The supplement describes a segment once, for every version.
+
+use hl7_definitions::{FieldOptionality, FieldRepeatability};
+use serde::Deserialize;
+use std::sync::LazyLock;
+
+/// Embedded supplement.
+const SUPPLEMENT_TOML: &str = include_str!("../../data/spec_supplement.toml");
+
+/// The supplement, parsed on first use.
+static SUPPLEMENT: LazyLock<Vec<SupplementSegment>> = LazyLock::new(|| {
+    let file: SupplementFile =
+        toml::from_str(SUPPLEMENT_TOML).expect("embedded spec_supplement.toml is valid");
+    file.segments
+        .into_iter()
+        .map(SupplementSegment::from)
+        .collect()
+});
+
+/// A segment described by the supplement.
+pub struct SupplementSegment {
+    /// Segment name (e.g., "IN1")
+    pub name: String,
+    /// What the segment represents (e.g., "Insurance")
+    pub description: String,
+    /// The segment's fields, in order from field 1
+    pub fields: Vec<SupplementField>,
+}
+
+/// A field of a segment described by the supplement.
+pub struct SupplementField {
+    /// What the field represents (e.g., "Insurance Plan ID")
+    pub description: String,
+    /// The field's data type (e.g., "CE")
+    pub datatype: String,
+    /// Whether the field is required
+    pub optionality: FieldOptionality,
+    /// Whether the field repeats
+    pub repeatability: FieldRepeatability,
+}
+
+/// Wrapper for deserializing the supplement file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SupplementFile {
+    segments: Vec<RawSegment>,
+}
+
+/// A segment as written in the supplement file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawSegment {
+    name: String,
+    description: String,
+    fields: Vec<RawField>,
+}
+
+/// A field as written in the supplement file.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawField {
+    description: String,
+    datatype: String,
+    optionality: RawOptionality,
+    #[serde(default)]
+    repeating: bool,
+}
+
+/// Optionality codes as the standard writes them.
+#[derive(Deserialize)]
+enum RawOptionality {
+    R,
+    O,
+    C,
+    B,
+}
+
+impl From<RawSegment> for SupplementSegment {
+    fn from(raw: RawSegment) -> Self {
+        SupplementSegment {
+            name: raw.name,
+            description: raw.description,
+            fields: raw
+                .fields
+                .into_iter()
+                .map(|field| SupplementField {
+                    description: field.description,
+                    datatype: field.datatype,
+                    optionality: match field.optionality {
+                        RawOptionality::R => FieldOptionality::Required,
+                        RawOptionality::O => FieldOptionality::Optional,
+                        RawOptionality::C => FieldOptionality::Conditional,
+                        RawOptionality::B => FieldOptionality::BackwardCompatibility,
+                    },
+                    repeatability: if field.repeating {
+                        FieldRepeatability::Unbounded
+                    } else {
+                        FieldRepeatability::Single
+                    },
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Look up a segment in the supplement.
+///
+/// # Arguments
+///
+/// * `segment` - The segment name
+pub fn segment(segment: &str) -> Option<&'static SupplementSegment> {
+    SUPPLEMENT.iter().find(|s| s.name == segment)
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used, clippy::indexing_slicing)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn supplement_lists_every_field_once_per_segment() {
+        let expected = [
+            ("IN1", 53),
+            ("IN2", 72),
+            ("GT1", 57),
+            ("FT1", 31),
+            ("SCH", 27),
+            ("AIS", 12),
+            ("AIG", 14),
+            ("AIL", 12),
+            ("AIP", 12),
+            ("RXA", 26),
+            ("RXR", 6),
+        ];
+        for (name, fields) in expected {
+            let segment = segment(name).unwrap();
+            assert_eq!(segment.fields.len(), fields, "{name}");
+            assert!(!segment.description.is_empty());
+        }
+
+        let mut names = HashSet::new();
+        assert!(SUPPLEMENT.iter().all(|s| names.insert(s.name.as_str())));
+        assert_eq!(names.len(), expected.len());
+    }
+
+    #[test]
+    fn supplement_fields_carry_their_definitions() {
+        let in1 = segment("IN1").unwrap();
+        assert_eq!(in1.fields[2].description, "Insurance Company ID");
+        assert_eq!(in1.fields[2].datatype, "CX");
+        assert!(matches!(
+            in1.fields[2].optionality,
+            FieldOptionality::Required
+        ));
+        assert!(matches!(
+            in1.fields[2].repeatability,
+            FieldRepeatability::Unbounded
+        ));
+
+        let sch = segment("SCH").unwrap();
+        assert!(matches!(
+            sch.fields[0].optionality,
+            FieldOptionality::Conditional
+        ));
+        assert!(matches!(
+            sch.fields[0].repeatability,
+            FieldRepeatability::Single
+        ));
+
+        assert!(segment("PID").is_none());
+    }
+}
@@ -0,0 +1,76 @@
+/**
+ * Bridge module for exporting communication audit events.
+ *
+ * With an exporter configured, the backend records every send, receive, and
+ * acknowledgement (peer, control ID, message type, ACK code, never message
+ * content) and forwards them in batches to a syslog or OTLP collector, retrying
+ * failed exports with backoff.
+ *
+ * The settings are stored with the other app settings; `+page.svelte` passes
+ * them to the backend when they load and whenever they change.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+/** Collector protocol: RFC 5424 syslog, or OpenTelemetry logs over HTTP/JSON. */
+export type AuditProtocol = "syslog" | "otlp";
+
+/** Transport for syslog messages. */
+export type SyslogTransport = "udp" | "tcp";
+
+/**
+ * Exporter configuration.
+ *
+ * Mirrors the Rust `AuditExportConfig` struct from `communication/audit.rs`.
+ */
+export interface AuditExportConfig {
+  protocol: AuditProtocol;
+  /** `host:port` for syslog, or an `http://` URL for OTLP */
+  endpoint: string;
+  /** Transport for syslog (ignored for OTLP) */
+  syslogTransport: SyslogTransport;
+  /** Maximum number of events per export */
+  batchSize: number;
+  /** Longest an event waits before its batch is exported */
+  flushIntervalSeconds: number;
+  /** Retries of a failed export before its batch is dropped */
+  maxRetries: number;
+}
+
+/** Audit export settings as stored, including whether exporting is on. */
+export interface AuditExportSettings extends AuditExportConfig {
+  enabled: boolean;
+}
+
+/** Settings used until the user configures audit export (off). */
+export const DEFAULT_AUDIT_EXPORT: AuditExportSettings = {
+  enabled: false,
+  protocol: "syslog",
+  endpoint: "",
+  syslogTransport: "udp",
+  batchSize: 50,
+  flushIntervalSeconds: 5,
+  maxRetries: 3,
+};
+
+/**
+ * Starts, replaces, or stops the audit exporter.
+ *
+ * @param settings - Stored settings; exporting stops if they're disabled
+ * @throws Error string if the endpoint isn't valid for the protocol
+ */
+export async function configureAuditExport(
+  settings: AuditExportSettings,
+): Promise<void> {
+  const config: AuditExportConfig | null = settings.enabled
+    ? {
+        protocol: settings.protocol,
+        endpoint: settings.endpoint,
+        syslogTransport: settings.syslogTransport,
+        batchSize: settings.batchSize,
+        flushIntervalSeconds: settings.flushIntervalSeconds,
+        maxRetries: settings.maxRetries,
+      }
+    : null;
+  await invoke("configure_audit_export", { config });
+}
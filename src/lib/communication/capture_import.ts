@@ -0,0 +1,63 @@
+/**
+ * Bridge module for importing messages from network captures.
+ *
+ * When all there is to debug a production interface with is a packet capture,
+ * this reads the pcap or pcapng file, reassembles its TCP streams, and returns
+ * every MLLP-framed message in them with when it was sent and between which
+ * endpoints.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import type { Framing, MessageEncoding } from "./wire_format";
+
+/**
+ * A message found in a capture.
+ *
+ * Mirrors the Rust `CapturedMessage` struct from `communication/capture.rs`.
+ */
+export interface CapturedMessage {
+  /** The message, decoded and without its MLLP framing */
+  message: string;
+  /** When the packet completing the message was captured (RFC 3339) */
+  timestamp: string;
+  /** Address and port the message was sent from */
+  source: string;
+  /** Address and port the message was sent to */
+  destination: string;
+  /** MSH.9, if present */
+  messageType: string | null;
+  /** MSH.10, if present */
+  controlId: string | null;
+}
+
+/**
+ * The messages found in a capture.
+ *
+ * Mirrors the Rust `CaptureImport` struct.
+ */
+export interface CaptureImport {
+  /** The messages, in the order they were completed */
+  messages: CapturedMessage[];
+  /** Number of TCP streams carrying data */
+  streams: number;
+  /** Problems that didn't stop the import, such as missing data */
+  warnings: string[];
+}
+
+/**
+ * Extracts the HL7 messages from a pcap or pcapng capture.
+ *
+ * @param path - Path to the capture file
+ * @param encoding - Character encoding of the messages (UTF-8 if not given)
+ * @param framing - MLLP framing of the messages (standard if not given)
+ * @returns The messages found, and any warnings
+ * @throws Error string if the file can't be read or isn't a capture, or the
+ *   framing is invalid
+ */
+export async function importCapture(
+  path: string,
+  encoding?: MessageEncoding,
+  framing?: Framing,
+): Promise<CaptureImport> {
+  return invoke<CaptureImport>("import_capture", { path, encoding, framing });
+}
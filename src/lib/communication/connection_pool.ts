@@ -0,0 +1,64 @@
+/**
+ * Bridge module for MLLP connections kept open across sends.
+ *
+ * Some receivers treat each new connection as a new session, resetting
+ * sequence number expectations, so sends can go over a named connection that
+ * stays open instead of connecting each time. A connection is opened here and
+ * named in `SendRequest.connection`; the backend drops it from the pool if a
+ * send over it fails or the receiver closes it.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import type { Framing } from "./wire_format";
+
+/**
+ * A named open connection.
+ *
+ * Mirrors the Rust `ConnectionInfo` struct from `communication/pool.rs`.
+ */
+export interface ConnectionInfo {
+  /** Name the connection was opened under */
+  name: string;
+  /** Address it's connected to */
+  addr: string;
+  /** When it was opened (RFC 3339) */
+  opened: string;
+  /** Messages sent over it so far, or null while a send is using it */
+  sends: number | null;
+}
+
+/**
+ * Opens a connection and keeps it open under a name, replacing any connection
+ * already open under that name.
+ *
+ * @param name - Name to send over the connection with
+ * @param host - Host to connect to
+ * @param port - Port to connect to
+ * @param framing - MLLP start and end blocks for every send over it
+ * @returns The opened connection
+ * @throws Error string if the address can't be resolved or connected to
+ */
+export async function openConnection(
+  name: string,
+  host: string,
+  port: number,
+  framing?: Framing,
+): Promise<ConnectionInfo> {
+  return invoke("open_connection", { name, host, port, framing });
+}
+
+/**
+ * Closes a named connection, after any send using it finishes.
+ *
+ * @param name - Name the connection was opened under
+ */
+export async function closeConnection(name: string): Promise<void> {
+  return invoke("close_connection", { name });
+}
+
+/**
+ * Lists the open connections, sorted by name.
+ */
+export async function listConnections(): Promise<ConnectionInfo[]> {
+  return invoke("list_connections");
+}
@@ -1,21 +1,154 @@
+import type { Framing, MessageEncoding } from "./wire_format";
+
+/** Environment classification for a connection preset. */
+export type PresetEnvironment = "dev" | "test" | "prod";
+
 /** A saved host/port combination for quick switching between environments. */
 export interface ConnectionPreset {
   id: string;
   name: string;
   hostname: string;
   port: number;
+  /** Freeform notes, e.g. who owns the endpoint or what it expects. */
+  notes?: string;
+  /** Short label shown alongside the preset name. */
+  tag?: string;
+  /** CSS colour of the tag badge. */
+  tagColour?: string;
+  /** Which environment the endpoint belongs to. */
+  environment?: PresetEnvironment;
+  /** MLLP framing bytes, if the endpoint doesn't use standard MLLP. */
+  framing?: Framing;
+  /** Character encoding the endpoint expects, if not UTF-8. */
+  encoding?: MessageEncoding;
+  /** Extension transport to send over instead of MLLP. */
+  transport?: PresetTransport;
+  /** HTTP(S) settings, to POST messages instead of sending over MLLP. */
+  http?: PresetHttp;
+  /** File-drop settings, to write messages to a directory instead of sending over MLLP. */
+  fileDrop?: PresetFileDrop;
+}
+
+/**
+ * A transport provided by an extension, chosen in place of MLLP.
+ *
+ * Mirrors the Rust `ExtensionTransport` from
+ * `src-tauri/src/commands/communication/transport.rs`.
+ */
+export interface PresetTransport {
+  /** Name the extension registered the transport under. */
+  name: string;
+  /** Transport-specific settings passed to the extension (e.g. queue name). */
+  options?: Record<string, string>;
+}
+
+/**
+ * How requests to an HTTP endpoint authenticate.
+ *
+ * Mirrors the Rust `HttpAuth` enum from
+ * `src-tauri/src/commands/communication/http.rs`.
+ */
+export type HttpAuth =
+  | { type: "none" }
+  | { type: "basic"; username: string; password: string }
+  | { type: "bearer"; token: string };
+
+/**
+ * Settings for POSTing messages over HTTP(S) instead of MLLP. The URL is built
+ * from the preset's host and port with this scheme and path.
+ *
+ * Mirrors the Rust `HttpTransport` from
+ * `src-tauri/src/commands/communication/http.rs`.
+ */
+export interface PresetHttp {
+  /** Use HTTPS rather than plain HTTP. */
+  https: boolean;
+  /** Path to POST to, e.g. `/hl7`. */
+  path: string;
+  /** Content-Type of the request (`x-application/hl7-v2+er7` if empty). */
+  contentType: string;
+  /** Extra request headers, by name. */
+  headers: Record<string, string>;
+  /** How the request authenticates. */
+  auth: HttpAuth;
 }
 
+/** Content type sent when a preset doesn't name one. */
+export const DEFAULT_HTTP_CONTENT_TYPE = "x-application/hl7-v2+er7";
+
+/**
+ * Settings for writing messages as files into a directory instead of sending
+ * over MLLP. With an SFTP login, the directory is on the preset's host and
+ * port; otherwise it's local.
+ *
+ * Mirrors the Rust `FileDropTransport` from
+ * `src-tauri/src/commands/communication/file_drop.rs`.
+ */
+export interface PresetFileDrop {
+  /** Directory to write messages into. */
+  directory: string;
+  /**
+   * Name of each file, with `{controlId}`, `{messageType}`, `{timestamp}`,
+   * and `{random}` placeholders (`{controlId}.hl7` if empty).
+   */
+  filenamePattern: string;
+  /** Login for writing over SFTP, authenticating with an SSH key. */
+  sftp?: {
+    username: string;
+    /** Private key file, if not one from the SSH agent. */
+    identityFile?: string;
+  };
+}
+
+/** Filename pattern used when a file-drop preset doesn't give one. */
+export const DEFAULT_FILENAME_PATTERN = "{controlId}.hl7";
+
+/** Colours offered for preset tags, matching the theme palette. */
+export const TAG_COLOURS: string[] = [
+  "var(--col-pine)",
+  "var(--col-foam)",
+  "var(--col-iris)",
+  "var(--col-gold)",
+  "var(--col-rose)",
+  "var(--col-love)",
+];
+
 /** Creates a new preset with a unique ID. */
 export function createPreset(
   name: string,
   hostname: string,
   port: number,
+  details: Pick<
+    ConnectionPreset,
+    | "notes"
+    | "tag"
+    | "tagColour"
+    | "environment"
+    | "framing"
+    | "encoding"
+    | "transport"
+    | "http"
+    | "fileDrop"
+  > = {},
 ): ConnectionPreset {
   return {
     id: crypto.randomUUID(),
     name,
     hostname,
     port,
+    ...details,
   };
 }
+
+/**
+ * Whether sending to a preset should require explicit confirmation.
+ *
+ * @param preset - The preset being sent to, or null for a custom host/port
+ * @param confirmProdSends - Whether the production send guard is enabled
+ */
+export function requiresSendConfirmation(
+  preset: ConnectionPreset | null,
+  confirmProdSends: boolean,
+): boolean {
+  return confirmProdSends && preset?.environment === "prod";
+}
@@ -0,0 +1,83 @@
+/**
+ * Bridge module for connection profiles stored by the backend.
+ *
+ * A profile names a host, port, TLS settings, framing, and encoding once, so
+ * sends and the listener can refer to it by name instead of each repeating the
+ * address. Unlike connection presets, which live in the settings store, the
+ * backend owns the profiles: it validates every change and keeps them in their
+ * own store under the data folder.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import type { Framing, MessageEncoding } from "./wire_format";
+
+/**
+ * How a connection is wrapped in TLS.
+ *
+ * Mirrors the Rust `TlsSettings` struct from `communication/connection_profiles.rs`.
+ */
+export interface TlsSettings {
+  /** Name to verify the server's certificate against, if not the host */
+  serverName?: string | null;
+  /** Accept certificates that don't verify, such as self-signed ones */
+  acceptInvalidCerts: boolean;
+}
+
+/**
+ * A named connection to a remote system.
+ *
+ * Mirrors the Rust `ConnectionProfile` struct from `communication/connection_profiles.rs`.
+ */
+export interface ConnectionProfile {
+  /** Name the profile is referred to by */
+  name: string;
+  /** Hostname or IP address to send to */
+  host: string;
+  /** Port to send to, or listen on */
+  port: number;
+  /** TLS settings, if the connection is wrapped in TLS */
+  tls?: TlsSettings | null;
+  /** MLLP start and end blocks (standard MLLP if omitted) */
+  framing?: Framing;
+  /** Encoding messages are sent and received in (UTF-8 if omitted) */
+  encoding?: MessageEncoding;
+}
+
+/**
+ * Lists the saved profiles, in the order they were added.
+ *
+ * @throws Error string if the profiles store can't be read
+ */
+export async function listConnectionProfiles(): Promise<ConnectionProfile[]> {
+  return invoke("list_connection_profiles");
+}
+
+/**
+ * Saves a profile, adding it or replacing the one it was edited from.
+ *
+ * @param profile - The profile to save
+ * @param replacing - Name of the profile being edited, if it isn't new
+ * @returns The profiles after saving
+ * @throws Error string if the profile isn't valid or its name is taken
+ */
+export async function saveConnectionProfile(
+  profile: ConnectionProfile,
+  replacing?: string,
+): Promise<ConnectionProfile[]> {
+  return invoke("save_connection_profile", {
+    profile,
+    replacing: replacing ?? null,
+  });
+}
+
+/**
+ * Deletes a profile.
+ *
+ * @param name - Name of the profile to delete
+ * @returns The profiles left
+ */
+export async function deleteConnectionProfile(
+  name: string,
+): Promise<ConnectionProfile[]> {
+  return invoke("delete_connection_profile", { name });
+}
@@ -0,0 +1,19 @@
+/**
+ * Bridge module for the history of control IDs already sent or received.
+ *
+ * The backend remembers the control ID (MSH.10) of every message sent or
+ * received, and validation warns when the message in the editor reuses one,
+ * as receivers often drop such messages as resends without saying so.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * Forgets every control ID sent or received, so none are reported as
+ * duplicates.
+ *
+ * @throws Error string if the history can't be saved
+ */
+export async function clearControlIdHistory(): Promise<void> {
+  await invoke("clear_control_id_history");
+}
@@ -0,0 +1,74 @@
+/**
+ * Bridge module for importing endpoint settings from integration engine exports.
+ *
+ * Reads Mirth Connect channel/server exports and Rhapsody communication point
+ * exports, and turns each TCP connector into a connection preset so Hermes can
+ * target (or stand in for) an existing interface without transcribing its
+ * host, port, framing, and encoding by hand.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import { createPreset, type ConnectionPreset } from "./connection_preset";
+import type { Framing, MessageEncoding } from "./wire_format";
+
+/**
+ * What an endpoint does in the engine.
+ *
+ * - `listener`: the engine accepts messages on this port
+ * - `sender`: the engine delivers messages to this remote host and port
+ */
+export type EndpointRole = "listener" | "sender";
+
+/**
+ * A TCP endpoint found in an engine export.
+ *
+ * Mirrors the Rust `ImportedEndpoint` struct from `communication/engine_import.rs`.
+ */
+export interface ImportedEndpoint {
+  name: string;
+  hostname: string;
+  port: number;
+  role: EndpointRole;
+  /** Framing bytes, if not standard MLLP */
+  framing: Framing | null;
+  /** Character encoding, if not UTF-8 */
+  encoding: MessageEncoding | null;
+  notes: string;
+}
+
+/**
+ * Result of importing an engine export.
+ */
+export interface ImportedEndpoints {
+  engine: "mirth" | "rhapsody";
+  endpoints: ImportedEndpoint[];
+  /** Settings that couldn't be imported, one per affected endpoint */
+  warnings: string[];
+}
+
+/**
+ * Reads an engine export file and extracts its TCP endpoints.
+ *
+ * @param path - Path to a Mirth Connect or Rhapsody XML export
+ * @returns The endpoints found, and warnings for anything that couldn't be mapped
+ * @throws Error if the file can't be read or isn't a recognised export
+ */
+export async function importEngineConfig(
+  path: string,
+): Promise<ImportedEndpoints> {
+  return invoke<ImportedEndpoints>("import_engine_config", { path });
+}
+
+/**
+ * Converts an imported endpoint into a connection preset.
+ *
+ * @param endpoint - Endpoint from `importEngineConfig`
+ * @returns A new preset, noting where the endpoint came from
+ */
+export function presetFromEndpoint(endpoint: ImportedEndpoint): ConnectionPreset {
+  return createPreset(endpoint.name, endpoint.hostname, endpoint.port, {
+    notes: endpoint.notes,
+    framing: endpoint.framing ?? undefined,
+    encoding: endpoint.encoding ?? undefined,
+  });
+}
@@ -11,16 +11,45 @@
  * 2. Frontend sets up event listener for "received-message" events
  * 3. Frontend invokes Tauri command "start_listening" with host and port
  * 4. Rust backend:
- *    - Starts a Tokio async TCP listener on the specified host:port
+ *    - Binds the specified host:port, failing with a readable error if the
+ *      port is in use, then starts a Tokio async TCP listener on it
+ *    - Emits "listener-started" with the bound address and the addresses
+ *      peers can send to
  *    - Stores the server's JoinHandle in app state
- *    - For each incoming connection:
- *      - Reads MLLP-framed message
- *      - Emits "received-message" event with the message text
- *      - Sends ACK response back to sender
+ *    - Serves each incoming connection concurrently, keeping it open until the
+ *      client closes it, and for each MLLP-framed message on it:
+ *      - Saves the message to a directory, if the listener saves received messages
+ *      - Emits "received-message" event with the message text and saved path
+ *      - Sends ACK response back to sender, or the response of the first
+ *        matching reply rule
+ *    - Emits "listener-connection" events as connections open, receive, and close
  * 5. Frontend receives "received-message" events and adds to Svelte store
  * 6. UI displays received messages with unread indicators
  * 7. When user clicks "Stop", `stopListening()` cancels the server task
  *
+ * The listener can take messages as HTTP POSTs instead of MLLP, answering each
+ * with its ACK; the events are the same either way.
+ *
+ * ## Saving Received Messages
+ *
+ * With `autoSave` set, the listener writes each message it receives to a
+ * directory, exactly as it arrived, named by a filename pattern. The saved path
+ * comes with the "received-message" event so it can be shown alongside the
+ * message.
+ *
+ * ## Reply Rules
+ *
+ * With `replies` set, messages matching a reply rule are answered with the
+ * rule's templated response instead of an ACK, so the listener can stand in
+ * for a system answering queries. The rule that answered a message comes with
+ * its "received-message" event.
+ *
+ * ## Drop Watches
+ *
+ * Alongside (or instead of) the listener, a drop watch picks up message files
+ * dropped into a local directory, for file-based interfaces. Each message in a
+ * picked-up file arrives as a "received-message" event like any other.
+ *
  * ## Why Svelte Store Integration?
  *
  * The `messages` store is passed directly to the event handler so that received
@@ -29,8 +58,198 @@
  */
 
 import { invoke } from "@tauri-apps/api/core";
-import { listen, type UnlistenFn } from "@tauri-apps/api/event";
-import type { Writable } from "svelte/store";
+import type { UnlistenFn } from "@tauri-apps/api/event";
+import { listenEvent } from "$lib/shared/events";
+import { writable, type Writable } from "svelte/store";
+import type { Framing, MessageEncoding } from "./wire_format";
+
+/**
+ * Protocol the listener receives messages over.
+ *
+ * Mirrors the Rust `ListenProtocol` enum from `communication/listen.rs`.
+ */
+export type ListenProtocol = "mllp" | "http";
+
+/**
+ * Payload of the "listener-connection" event.
+ *
+ * Mirrors the Rust `ListenerConnectionEvent` struct from
+ * `src-tauri/src/commands/communication/listen.rs`.
+ */
+export interface ListenerConnectionEvent {
+  /** Identifies the connection among those accepted since the listener started */
+  connection: number;
+  /** Address of the client, e.g. "10.0.0.5:51234" */
+  peer: string;
+  kind: "connected" | "message" | "disconnected";
+  /** Messages received on the connection so far */
+  messages: number;
+  /** MSH-9 of the message, for "message" events */
+  messageType: string | null;
+  /** Why the connection closed, if it was an error */
+  error: string | null;
+}
+
+/**
+ * Payload of the "listener-started" event.
+ *
+ * Mirrors the Rust `ListenerStarted` struct from
+ * `src-tauri/src/commands/communication/listen.rs`.
+ */
+export interface ListenerStarted {
+  /** Address and port the listener is bound to, e.g. "0.0.0.0:2575" */
+  address: string;
+  protocol: ListenProtocol;
+  /**
+   * Addresses and ports peers can send to: the bound address, or for a
+   * listener on every interface, each interface's address
+   */
+  reachable: string[];
+}
+
+/**
+ * A network interface address the listener can bind to.
+ *
+ * Mirrors the Rust `ListenInterface` struct from
+ * `src-tauri/src/commands/communication/listen.rs`.
+ */
+export interface ListenInterface {
+  /** Name of the interface, e.g. "eth0" */
+  name: string;
+  address: string;
+  ipv6: boolean;
+  /** Whether the interface is only reachable from this computer */
+  loopback: boolean;
+}
+
+/**
+ * Lists the network interface addresses the listener can bind to, IPv4 first.
+ *
+ * @throws If the interfaces can't be read
+ */
+export async function listListenInterfaces(): Promise<ListenInterface[]> {
+  return invoke<ListenInterface[]>("list_listen_interfaces");
+}
+
+/**
+ * Payload of the "received-message" event.
+ *
+ * Mirrors the Rust `ReceivedMessage` struct from
+ * `src-tauri/src/commands/communication/listen.rs`.
+ */
+export interface ReceivedMessage {
+  /** The message, with segments separated by `\n` */
+  message: string;
+  /** Where the message was saved, if the listener saves received messages */
+  savedPath: string | null;
+  /** Name of the reply rule that answered the message, if one did */
+  replyRule: string | null;
+}
+
+/**
+ * Where the listener saves received messages.
+ *
+ * Mirrors the Rust `ListenAutoSave` struct from
+ * `src-tauri/src/commands/communication/listen.rs`.
+ */
+export interface ListenAutoSave {
+  /** Directory to save messages in, created if it doesn't exist */
+  directory: string;
+  /** Pattern for file names; `{controlId}.hl7` if empty */
+  filenamePattern: string;
+}
+
+/**
+ * A field value a message must have for a reply rule to match.
+ *
+ * Mirrors the Rust `ReplyCondition` struct from
+ * `src-tauri/src/commands/communication/reply_rules.rs`.
+ */
+export interface ReplyCondition {
+  /** Path of the field, e.g. "QPD.1.1" */
+  path: string;
+  /** Value the field must have, after unescaping */
+  value: string;
+}
+
+/**
+ * A rule answering matching messages with a templated response instead of an
+ * ACK.
+ *
+ * Mirrors the Rust `ReplyRule` struct from
+ * `src-tauri/src/commands/communication/reply_rules.rs`.
+ */
+export interface ReplyRule {
+  /** Name the rule is shown and logged with */
+  name: string;
+  /** MSH-9 to match by component, e.g. "QBP" or "QBP^Q22"; empty matches any */
+  messageType: string;
+  /** Other field values the message must have */
+  conditions: ReplyCondition[];
+  /**
+   * The response, with `{request.PATH}` for values from the received message,
+   * placeholders, and formulas filled in
+   */
+  template: string;
+}
+
+/** Auto-save settings as kept in the settings store. */
+export interface ListenAutoSaveSettings extends ListenAutoSave {
+  /** Whether received messages are saved */
+  enabled: boolean;
+}
+
+/** Auto-save settings before any are chosen. */
+export const DEFAULT_LISTEN_AUTO_SAVE: ListenAutoSaveSettings = {
+  enabled: false,
+  directory: "",
+  filenamePattern: "",
+};
+
+/** A client connected to the listener. */
+export interface ListenerConnection {
+  connection: number;
+  peer: string;
+  messages: number;
+  connectedAt: Date;
+  /** When the last message arrived */
+  lastMessageAt: Date | null;
+}
+
+/** Connections currently open to the listener, oldest first. */
+export const connections = writable<ListenerConnection[]>([]);
+
+/** Where the running listener is bound, or null when it isn't running. */
+export const listenerStarted = writable<ListenerStarted | null>(null);
+
+let unlistenConnections: UnlistenFn | undefined;
+let unlistenStarted: UnlistenFn | undefined;
+
+function applyConnectionEvent(event: ListenerConnectionEvent) {
+  connections.update((current) => {
+    switch (event.kind) {
+      case "connected":
+        return [
+          ...current,
+          {
+            connection: event.connection,
+            peer: event.peer,
+            messages: 0,
+            connectedAt: new Date(),
+            lastMessageAt: null,
+          },
+        ];
+      case "message":
+        return current.map((c) =>
+          c.connection === event.connection
+            ? { ...c, messages: event.messages, lastMessageAt: new Date() }
+            : c,
+        );
+      case "disconnected":
+        return current.filter((c) => c.connection !== event.connection);
+    }
+  });
+}
 
 /**
  * Sets up a listener for incoming HL7 messages and adds them to the messages store.
@@ -43,16 +262,25 @@ import type { Writable } from "svelte/store";
  * @returns Function to call to stop listening (should be called on app unmount)
  */
 export async function listenToListenResponse(
-  messages: Writable<{ message: string; unread: boolean }[]>,
+  messages: Writable<
+    {
+      message: string;
+      unread: boolean;
+      savedPath?: string;
+      replyRule?: string;
+    }[]
+  >,
 ): Promise<UnlistenFn> {
   console.log("listenToListenResponse");
-  return listen<string>("received-message", (event) => {
+  return listenEvent("received-message", (event) => {
     console.log("received-message", event);
     if (event.payload) {
       messages.update((currentMessages) => {
         const newMessage = {
-          message: event.payload,
+          message: event.payload.message,
           unread: true,
+          savedPath: event.payload.savedPath ?? undefined,
+          replyRule: event.payload.replyRule ?? undefined,
         };
         return [...currentMessages, newMessage];
       });
@@ -67,23 +295,54 @@ export async function listenToListenResponse(
  * the server fails (e.g., port already in use). Only after the server successfully
  * starts is the store set to true, ensuring the UI accurately reflects server state.
  *
- * @param host - Hostname/IP to bind to (null means all interfaces: 0.0.0.0)
+ * @param host - IPv4 or IPv6 address, host name, or interface name to bind to (null
+ *   means all IPv4 interfaces: 0.0.0.0; "::" means all interfaces)
  * @param port - Port number to listen on (typically 2575 for HL7)
  * @param listening - Svelte writable store tracking whether server is running
- * @throws Error if server fails to start (port in use, permission denied, etc.)
+ * @param options - Framing bytes, character encoding, and protocol (standard MLLP and UTF-8 if omitted),
+ *   or a connection profile supplying the port, framing, and encoding; where to save
+ *   received messages, if they should be saved; and reply rules, with the variables
+ *   their templates may use
+ * @throws Error if server fails to start (port in use, permission denied, save directory
+ *   can't be created, etc.)
  */
 export async function startListening(
   host: string | null,
   port: number,
   listening: Writable<boolean>,
+  options: {
+    framing?: Framing;
+    encoding?: MessageEncoding;
+    protocol?: ListenProtocol;
+    profile?: string;
+    autoSave?: ListenAutoSave;
+    replies?: { rules: ReplyRule[]; variables: Record<string, string> };
+  } = {},
 ): Promise<void> {
   host = host || null;
   console.info("startListening", host, port);
   // Set to false first in case start fails
   listening.set(false);
+  // a new listener starts with no connections; track them before any arrive
+  unlistenConnections?.();
+  connections.set([]);
+  unlistenConnections = await listenEvent("listener-connection", (event) =>
+    applyConnectionEvent(event.payload),
+  );
+  unlistenStarted?.();
+  listenerStarted.set(null);
+  unlistenStarted = await listenEvent("listener-started", (event) =>
+    listenerStarted.set(event.payload),
+  );
   await invoke("start_listening", {
     host,
     port,
+    framing: options.framing ?? null,
+    encoding: options.encoding ?? null,
+    protocol: options.protocol ?? null,
+    autoSave: options.autoSave ?? null,
+    replies: options.replies ?? null,
+    profile: options.profile ?? null,
   });
   // Only set to true after successful start
   listening.set(true);
@@ -103,5 +362,128 @@ export async function stopListening(
 ): Promise<void> {
   console.info("stopListening");
   await invoke("stop_listening");
+  // aborted connections don't report closing
+  unlistenConnections?.();
+  unlistenConnections = undefined;
+  connections.set([]);
+  unlistenStarted?.();
+  unlistenStarted = undefined;
+  listenerStarted.set(null);
   listening.set(false);
 }
+
+/**
+ * Settings the listener is started with, as shared in a configuration file.
+ *
+ * Mirrors the Rust `ListenerConfig` struct from
+ * `src-tauri/src/commands/communication/listener_config.rs`.
+ */
+export interface ListenerConfig {
+  /** Port to listen on */
+  port: number;
+  /** Encoding of received messages and sent ACKs */
+  encoding: MessageEncoding;
+  /** MLLP start and end blocks */
+  framing: Framing;
+}
+
+/**
+ * A listener configuration read from a file.
+ */
+export interface ImportedListenerConfig {
+  /** Description given when the configuration was exported, if any */
+  name: string | null;
+  /** Format version the file was written in */
+  version: number;
+  /** The listener settings */
+  config: ListenerConfig;
+}
+
+/**
+ * Writes the listener's settings to a file that can be imported elsewhere.
+ *
+ * @param path - Where to write the file
+ * @param config - The listener settings
+ * @param name - Optional description, shown when the file is imported
+ * @throws If the settings are invalid or the file can't be written
+ */
+export async function exportListenerConfig(
+  path: string,
+  config: ListenerConfig,
+  name: string | null = null,
+): Promise<void> {
+  await invoke("export_listener_config", { path, config, name });
+}
+
+/**
+ * Reads listener settings from a file written by `exportListenerConfig`.
+ *
+ * @param path - The file to read
+ * @returns The settings, and the file's name and format version
+ * @throws If the file isn't a listener configuration, was written by a newer
+ *   version of Hermes, or holds invalid settings
+ */
+export async function importListenerConfig(
+  path: string,
+): Promise<ImportedListenerConfig> {
+  return invoke<ImportedListenerConfig>("import_listener_config", { path });
+}
+
+/**
+ * What a drop watch does with a file once it's been picked up.
+ *
+ * Mirrors the Rust `AfterPickup` enum from
+ * `src-tauri/src/commands/communication/file_drop.rs`.
+ */
+export type AfterPickup =
+  | { action: "leave" }
+  | { action: "delete" }
+  | { action: "move"; directory: string };
+
+/** Settings a drop watch is started with. */
+export interface DropWatchSettings {
+  /** Directory to pick files up from */
+  directory: string;
+  /** Only pick up files with this extension, if not empty (e.g. "hl7") */
+  extension: string;
+  /** What to do with files once picked up */
+  afterPickup: AfterPickup;
+}
+
+/** Settings a drop watch starts with before any are chosen. */
+export const DEFAULT_DROP_WATCH: DropWatchSettings = {
+  directory: "",
+  extension: "hl7",
+  afterPickup: { action: "leave" },
+};
+
+/** Whether a drop watch is running. */
+export const dropWatching = writable(false);
+
+/**
+ * Starts picking up message files dropped into a directory, replacing any drop
+ * watch already running.
+ *
+ * @param settings - Directory to watch, extension filter, and what to do with picked-up files
+ * @param encoding - Encoding to decode the files with (UTF-8 if omitted)
+ * @throws If the directory, or the directory to move files to, doesn't exist
+ */
+export async function startDropWatch(
+  settings: DropWatchSettings,
+  encoding?: MessageEncoding,
+): Promise<void> {
+  dropWatching.set(false);
+  await invoke("start_drop_watch", {
+    directory: settings.directory,
+    extension: settings.extension || null,
+    encoding: encoding ?? null,
+    afterPickup: settings.afterPickup,
+  });
+  dropWatching.set(true);
+}
+
+/** Stops the running drop watch, if any. */
+export async function stopDropWatch(): Promise<void> {
+  await invoke("stop_drop_watch");
+  dropWatching.set(false);
+}
@@ -0,0 +1,197 @@
+/**
+ * Bridge module for the MLLP relay, a debugging proxy between two systems.
+ *
+ * The backend accepts connections on a port and relays each one to a target
+ * host and port, emitting a "relay-traffic" event as connections open and
+ * close and for every message relayed in either direction. Field edits can be
+ * applied to messages on their way to the target and to responses on their
+ * way back.
+ *
+ * ## Why a Store?
+ *
+ * Like a schedule, a relay keeps running while the Relay tab isn't shown. The
+ * event listener lives here and writes into the `relay` store, so the tab
+ * shows the traffic so far whenever it's shown again, and keeps the settings
+ * it was started with.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import type { UnlistenFn } from "@tauri-apps/api/event";
+import { listenEvent } from "$lib/shared/events";
+import { writable } from "svelte/store";
+import type { Framing, MessageEncoding } from "./wire_format";
+
+/** A value written to a field of each relayed message. */
+export interface FieldEdit {
+  /** Path in query syntax, e.g. "MSH.5" or "MSA.1" */
+  path: string;
+  value: string;
+}
+
+/**
+ * Settings the relay is started with.
+ *
+ * Mirrors the Rust `RelayConfig` struct from `communication/relay.rs`.
+ */
+export interface RelayConfig {
+  /** Port to accept connections on */
+  listenPort: number;
+  /** Host and port to relay connections to */
+  targetHost: string;
+  targetPort: number;
+  framing?: Framing;
+  encoding?: MessageEncoding;
+  /** Edits applied to messages on their way to the target */
+  messageEdits: FieldEdit[];
+  /** Edits applied to responses on their way back from the target */
+  responseEdits: FieldEdit[];
+}
+
+/** Which way a relayed message was going. */
+export type RelayDirection = "toTarget" | "toClient";
+
+/**
+ * Payload of the "relay-traffic" event.
+ *
+ * Mirrors the Rust `RelayTraffic` enum from `communication/relay.rs`.
+ */
+export type RelayTraffic =
+  | { kind: "connected"; connection: number; peer: string }
+  | {
+      kind: "message";
+      connection: number;
+      direction: RelayDirection;
+      /** The message as relayed, with segments separated by `\n` */
+      message: string;
+      /** The message before it was edited, if the edits changed it */
+      original: string | null;
+      /** Why the edits couldn't be applied, if they couldn't */
+      error: string | null;
+    }
+  | { kind: "disconnected"; connection: number; error: string | null };
+
+/** A relayed message, as listed in the Relay tab. */
+export type RelayedMessage = Extract<RelayTraffic, { kind: "message" }> & {
+  receivedAt: Date;
+};
+
+/** State of the relay, for display. */
+export interface RelayState {
+  running: boolean;
+  /** Settings the relay was last started with */
+  config: RelayConfig | null;
+  /** Open connections, by id, with the client's address */
+  connections: Map<number, string>;
+  /** Relayed messages, oldest first */
+  messages: RelayedMessage[];
+  /** Why the last connection to fail closed, if one did */
+  lastError: string | null;
+}
+
+/** How many relayed messages to keep for display. */
+const MESSAGE_LIMIT = 500;
+
+/** State of the relay. */
+export const relay = writable<RelayState>({
+  running: false,
+  config: null,
+  connections: new Map(),
+  messages: [],
+  lastError: null,
+});
+
+let unlistenTraffic: UnlistenFn | undefined;
+
+function applyTraffic(traffic: RelayTraffic) {
+  relay.update((state) => {
+    switch (traffic.kind) {
+      case "connected": {
+        const connections = new Map(state.connections);
+        connections.set(traffic.connection, traffic.peer);
+        return { ...state, connections };
+      }
+      case "message":
+        return {
+          ...state,
+          messages: [
+            ...state.messages,
+            { ...traffic, receivedAt: new Date() },
+          ].slice(-MESSAGE_LIMIT),
+        };
+      case "disconnected": {
+        const connections = new Map(state.connections);
+        connections.delete(traffic.connection);
+        return {
+          ...state,
+          connections,
+          lastError: traffic.error ?? state.lastError,
+        };
+      }
+    }
+  });
+}
+
+/**
+ * Parses `PATH=value` lines into field edits, skipping blank lines.
+ *
+ * @param text - One edit per line, e.g. `MSH.5=TEST`
+ */
+export function parseFieldEdits(text: string): FieldEdit[] {
+  const edits: FieldEdit[] = [];
+  for (const line of text.split("\n")) {
+    const separator = line.indexOf("=");
+    if (separator <= 0) continue;
+    edits.push({
+      path: line.slice(0, separator).trim(),
+      value: line.slice(separator + 1),
+    });
+  }
+  return edits;
+}
+
+/** Formats field edits as `PATH=value` lines. */
+export function formatFieldEdits(edits: FieldEdit[]): string {
+  return edits.map((edit) => `${edit.path}=${edit.value}`).join("\n");
+}
+
+/**
+ * Starts relaying connections, replacing any relay already running.
+ *
+ * @param config - Port to listen on, target, and edits
+ * @throws Error string if the target can't be resolved or the port is in use
+ */
+export async function startRelay(config: RelayConfig): Promise<void> {
+  unlistenTraffic?.();
+  relay.set({
+    running: false,
+    config,
+    connections: new Map(),
+    messages: [],
+    lastError: null,
+  });
+  unlistenTraffic = await listenEvent("relay-traffic", (event) =>
+    applyTraffic(event.payload),
+  );
+
+  try {
+    await invoke("start_relay", { config });
+  } catch (error) {
+    unlistenTraffic();
+    unlistenTraffic = undefined;
+    throw error;
+  }
+  relay.update((state) => ({ ...state, running: true }));
+}
+
+/** Stops the relay, closing every connection through it. */
+export async function stopRelay(): Promise<void> {
+  await invoke("stop_relay");
+  // aborted connections don't report closing
+  unlistenTraffic?.();
+  unlistenTraffic = undefined;
+  relay.update((state) => ({
+    ...state,
+    running: false,
+    connections: new Map(),
+  }));
+}
@@ -0,0 +1,179 @@
+/**
+ * Bridge module for scheduled, repeated sends (the load generator).
+ *
+ * The backend sends the same message over and over from a background task, at
+ * a fixed interval with optional jitter, and emits a "schedule-progress" event
+ * after every send and a "schedule-finished" event when it's done or stopped.
+ *
+ * ## Why a Store?
+ *
+ * A schedule can run for hours, long after the Repeat tab that started it has
+ * been switched away from. The event listeners live here rather than in the
+ * component, and write into the `schedule` store, so the tab picks up the
+ * current totals whenever it's shown again.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import type { UnlistenFn } from "@tauri-apps/api/event";
+import { listenEvent } from "$lib/shared/events";
+import { writable } from "svelte/store";
+import type { EndpointLabel } from "./send_receive";
+import type { Framing, MessageEncoding } from "./wire_format";
+
+/**
+ * Configuration for a scheduled send.
+ *
+ * Mirrors the Rust `ScheduleRequest` struct from `communication/schedule.rs`.
+ */
+export interface ScheduleRequest {
+  host: string;
+  port: number;
+  /** Message to send on every iteration (may contain placeholders) */
+  message: string;
+  /** Time between the starts of consecutive sends */
+  intervalMs: number;
+  /** Maximum random variation of each interval, either way */
+  jitterMs: number;
+  /** Number of messages to send, or null to send until stopped */
+  count: number | null;
+  /** Seconds to wait for each acknowledgement */
+  waitTimeoutSeconds: number;
+  /** Replace MSH-7 and MSH-10 on every send, even without placeholders */
+  regenerateIds: boolean;
+  endpoint?: EndpointLabel;
+  framing?: Framing;
+  encoding?: MessageEncoding;
+  /** Values of user-defined `{name}` placeholders */
+  variables?: Record<string, string>;
+}
+
+/** Outcome of a single scheduled send. */
+export type SendOutcome = "accepted" | "rejected" | "timedout" | "failed";
+
+/** Running totals for a schedule. */
+export interface ScheduleStats {
+  sent: number;
+  accepted: number;
+  rejected: number;
+  timedOut: number;
+  failed: number;
+  averageLatencyMs: number | null;
+  maxLatencyMs: number | null;
+}
+
+/** Payload of the "schedule-progress" event. */
+export interface ScheduleProgress {
+  /** Number of this send (1-based) */
+  iteration: number;
+  /** Number of sends the schedule will make, if limited */
+  total: number | null;
+  controlId: string | null;
+  outcome: SendOutcome;
+  /** MSA-1 of the acknowledgement, if one was received */
+  ackCode: string | null;
+  error: string | null;
+  stats: ScheduleStats;
+}
+
+/** Payload of the "schedule-finished" event. */
+export interface ScheduleFinished {
+  stats: ScheduleStats;
+  /** Whether the schedule was stopped before sending every message */
+  cancelled: boolean;
+}
+
+/** State of the current (or last) schedule, for display. */
+export interface ScheduleState {
+  running: boolean;
+  total: number | null;
+  stats: ScheduleStats | null;
+  /** Most recent sends, newest first */
+  recent: ScheduleProgress[];
+  cancelled: boolean;
+}
+
+/** How many recent sends to keep for display. */
+const RECENT_LIMIT = 100;
+
+/** State of the current (or last) schedule. */
+export const schedule = writable<ScheduleState>({
+  running: false,
+  total: null,
+  stats: null,
+  recent: [],
+  cancelled: false,
+});
+
+let unlistenProgress: UnlistenFn | undefined;
+let unlistenFinished: UnlistenFn | undefined;
+
+function stopListening() {
+  unlistenProgress?.();
+  unlistenFinished?.();
+  unlistenProgress = undefined;
+  unlistenFinished = undefined;
+}
+
+/**
+ * Starts sending a message repeatedly, replacing any schedule already running.
+ *
+ * Event listeners are set up before invoking the backend so no progress events
+ * are missed, and are removed once the schedule finishes.
+ *
+ * @param request - Target, message, and timing of the schedule
+ * @param confirmedProd - Whether the user confirmed sending to a production
+ *   endpoint; the backend refuses unconfirmed production sends
+ * @throws Error string if the request is invalid (e.g. unresolvable host or unparseable message)
+ */
+export async function startScheduledSend(
+  request: ScheduleRequest,
+  confirmedProd: boolean,
+): Promise<void> {
+  stopListening();
+  schedule.set({
+    running: true,
+    total: request.count,
+    stats: null,
+    recent: [],
+    cancelled: false,
+  });
+
+  unlistenProgress = await listenEvent(
+    "schedule-progress",
+    (event) => {
+      schedule.update((state) => ({
+        ...state,
+        stats: event.payload.stats,
+        recent: [event.payload, ...state.recent].slice(0, RECENT_LIMIT),
+      }));
+    },
+  );
+  unlistenFinished = await listenEvent(
+    "schedule-finished",
+    (event) => {
+      schedule.update((state) => ({
+        ...state,
+        running: false,
+        stats: event.payload.stats,
+        cancelled: event.payload.cancelled,
+      }));
+      stopListening();
+    },
+  );
+
+  try {
+    await invoke("start_scheduled_send", { request, confirmedProd });
+  } catch (error) {
+    stopListening();
+    schedule.update((state) => ({ ...state, running: false }));
+    throw error;
+  }
+}
+
+/**
+ * Stops the running schedule. The "schedule-finished" event still arrives with
+ * the totals so far.
+ */
+export async function stopScheduledSend(): Promise<void> {
+  await invoke("stop_scheduled_send");
+}
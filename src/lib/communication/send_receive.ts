@@ -29,11 +29,87 @@
  */
 
 import { invoke } from "@tauri-apps/api/core";
-import {
-  listen,
-  type Event as ListenEvent,
-  type UnlistenFn,
-} from "@tauri-apps/api/event";
+import type { Event as ListenEvent, UnlistenFn } from "@tauri-apps/api/event";
+import { listenEvent } from "$lib/shared/events";
+import type {
+  PresetEnvironment,
+  PresetFileDrop,
+  PresetHttp,
+  PresetTransport,
+} from "./connection_preset";
+import type { Framing, MessageEncoding } from "./wire_format";
+
+/**
+ * Identifies the connection preset a message is being sent to.
+ *
+ * Included in the backend's log lines and `sent` confirmation event so it's
+ * clear which environment a message went to.
+ */
+export interface EndpointLabel {
+  /** Preset name */
+  name: string;
+  /** Environment the endpoint belongs to */
+  environment?: PresetEnvironment;
+  /** Freeform tag shown alongside the name */
+  tag?: string;
+}
+
+/**
+ * Confirmation that a message was handed off to the remote server.
+ */
+export interface SendConfirmation {
+  /** Resolved address the message was sent to */
+  addr: string;
+  /** MSH.10 of the sent message, after placeholder transformations */
+  controlId: string | null;
+  /** Label of the connection preset, if the send used one */
+  endpoint: EndpointLabel | null;
+}
+
+/**
+ * How an MLLP send is retried when an attempt fails.
+ *
+ * Mirrors the Rust `RetryPolicy` struct from `communication/retry.rs`.
+ */
+export interface RetryPolicy {
+  /** Number of attempts after the first */
+  retries: number;
+  /** Delay before the first retry, in milliseconds */
+  backoffMs: number;
+  /** Factor the delay is multiplied by after each retry (1 for a fixed delay) */
+  backoffMultiplier: number;
+  /** Retry when no response arrives within the wait timeout */
+  onTimeout: boolean;
+  /** Retry when the connection is refused */
+  onRefused: boolean;
+  /** Retry when the response is an AE, AR, CE, or CR acknowledgement */
+  onNak: boolean;
+}
+
+/** A single attempt, with no retries. */
+export const DEFAULT_RETRY_POLICY: RetryPolicy = {
+  retries: 0,
+  backoffMs: 1000,
+  backoffMultiplier: 1,
+  onTimeout: true,
+  onRefused: true,
+  onNak: false,
+};
+
+/**
+ * An attempt at an MLLP send that was refused, timed out, or negatively
+ * acknowledged.
+ */
+export interface AttemptFailure {
+  /** Which attempt this was, counting from 1 */
+  attempt: number;
+  /** Total number of attempts the retry policy allows */
+  attempts: number;
+  /** Delay before the next attempt, or null if there isn't one */
+  retryInMs: number | null;
+  /** The acknowledgement code, for a negative acknowledgement */
+  detail: string | null;
+}
 
 /**
  * Configuration for sending an HL7 message over MLLP.
@@ -47,6 +123,32 @@ export interface SendRequest {
   wait_timeout_seconds: number;
   /** Raw HL7 message string to send */
   message: string;
+  /** Label of the connection preset being sent to, if any */
+  endpoint?: EndpointLabel;
+  /** MLLP start and end blocks (standard MLLP if omitted) */
+  framing?: Framing;
+  /** Encoding to send in and decode the response with (UTF-8 if omitted) */
+  encoding?: MessageEncoding;
+  /** Extension transport to send over instead of MLLP, if any */
+  transport?: PresetTransport;
+  /** HTTP(S) settings, to POST the message instead of sending over MLLP */
+  http?: PresetHttp;
+  /** Values of user-defined `{name}` placeholders */
+  variables?: Record<string, string>;
+  /**
+   * Name of an open connection to send over instead of connecting, if any.
+   * The connection's address and framing are used in place of the request's.
+   */
+  connection?: string;
+  /** File-drop settings, to write the message to a directory instead of sending over MLLP */
+  file_drop?: PresetFileDrop;
+  /** How to retry an MLLP send (a single attempt if omitted) */
+  retry?: RetryPolicy;
+  /**
+   * Name of a connection profile, whose host, port, TLS settings, framing,
+   * and encoding are used in place of the request's
+   */
+  profile?: string;
 }
 
 /**
@@ -57,7 +159,12 @@ export interface SendRequest {
  * log messages during the send/receive process and emits a final response event when done.
  *
  * @param request - Send configuration including host, port, timeout, and message
+ * @param confirmedProd - Whether the user confirmed sending to a production
+ *   endpoint; the backend refuses unconfirmed production sends
  * @param onSendLog - Optional callback for real-time log updates during the operation
+ * @param onSent - Optional callback invoked each time the message has been sent
+ * @param onAttemptFailed - Optional callback invoked when an attempt is
+ *   refused, times out, or is negatively acknowledged
  * @returns The response message text, or null if no response was received
  * @throws Error string if the send/receive operation fails at any stage
  *
@@ -65,24 +172,43 @@ export interface SendRequest {
  * ```ts
  * const response = await sendMessage(
  *   { host: "127.0.0.1", port: 2575, wait_timeout_seconds: 5, message: "MSH|..." },
+ *   false,
  *   (log) => console.log("Progress:", log)
  * );
  * ```
  */
 export async function sendMessage(
   request: SendRequest,
+  confirmedProd: boolean,
   onSendLog?: (log: string) => void,
+  onSent?: (confirmation: SendConfirmation) => void,
+  onAttemptFailed?: (
+    kind: "refused" | "timedOut" | "nak",
+    failure: AttemptFailure,
+  ) => void,
 ): Promise<string | null> {
   // Set up response listener before invoking to prevent race condition
   let unlistenResponse: UnlistenFn | undefined;
   const responsePromise = new Promise<string | null>((resolve, reject) => {
-    listen<SendResponse>("send-response", (event) => {
+    listenEvent("send-response", (event) => {
       const responseError = getResponseError(event.payload);
       if (responseError) {
         unlistenResponse?.();
         reject(responseError);
         return;
       }
+      if (event.payload.event === "sent") {
+        onSent?.(event.payload.data);
+        return;
+      }
+      if (
+        event.payload.event === "refused" ||
+        event.payload.event === "timedOut" ||
+        event.payload.event === "nak"
+      ) {
+        onAttemptFailed?.(event.payload.event, event.payload.data);
+        return;
+      }
       if (event.payload.event === "final") {
         unlistenResponse?.();
         resolve(event.payload.data);
@@ -98,7 +224,7 @@ export async function sendMessage(
   // Set up log listener before invoking to capture all log messages
   let unlistenLog: UnlistenFn | undefined;
   let logPromise = new Promise<void>((resolve) => {
-    listen<string>("send-log", (event) => {
+    listenEvent("send-log", (event) => {
       onSendLog?.(event.payload);
     }).then((unlistenFn) => {
       unlistenLog = unlistenFn;
@@ -111,6 +237,7 @@ export async function sendMessage(
   try {
     await invoke("send_message", {
       request,
+      confirmedProd,
     });
   } finally {
     // Ensure listeners are cleaned up even if invoke fails
@@ -127,7 +254,9 @@ export async function sendMessage(
  * Discriminated union of all possible response events from the backend.
  *
  * The backend emits different event types to indicate failures at various stages
- * of the send/receive process. The "final" event indicates success and contains
+ * of the send/receive process. The "sent" event confirms the message went out
+ * and identifies where it went. The "refused", "timedOut", and "nak" events
+ * report a failed attempt at an MLLP send, which may be retried. The "final" event indicates success and contains
  * the response message (or null if no response was received but the operation
  * completed successfully).
  */
@@ -137,12 +266,17 @@ export type SendResponse =
   | { event: "failedToReceive"; data: string }
   | { event: "failedToDecode"; data: string }
   | { event: "failedToParse"; data: { message: string; error: string } }
+  | { event: "sent"; data: SendConfirmation }
+  | { event: "refused"; data: AttemptFailure }
+  | { event: "timedOut"; data: AttemptFailure }
+  | { event: "nak"; data: AttemptFailure }
   | { event: "final"; data: string | null };
 
 /**
  * Extracts a human-readable error message from a SendResponse event.
  *
- * Returns null for the "final" event (success case), allowing callers to easily
+ * Returns null for the "sent" and "final" events (success cases), and for the
+ * per-attempt "refused", "timedOut", and "nak" events, allowing callers to easily
  * distinguish between error and success responses.
  *
  * @param response - The response event from the backend
@@ -167,6 +301,21 @@ export function getResponseError(response: SendResponse): string | null {
   return null;
 }
 
+/**
+ * Formats an endpoint label for display, e.g. "Lab Interface [prod, lab]".
+ *
+ * @param endpoint - The endpoint label
+ * @returns The name followed by its environment and tag, if any
+ */
+export function formatEndpointLabel(endpoint: EndpointLabel): string {
+  const details = [endpoint.environment, endpoint.tag].filter(
+    (detail): detail is string => !!detail,
+  );
+  return details.length > 0
+    ? `${endpoint.name} [${details.join(", ")}]`
+    : endpoint.name;
+}
+
 /**
  * Listens to the "send-response" event and invokes the provided handler.
  *
@@ -175,7 +324,7 @@ export function getResponseError(response: SendResponse): string | null {
 export async function listenToSendResponse(
   handler: (event: ListenEvent<SendResponse>) => void,
 ): Promise<UnlistenFn> {
-  return listen<SendResponse>("send-response", handler);
+  return listenEvent("send-response", handler);
 }
 
 /**
@@ -186,5 +335,5 @@ export async function listenToSendResponse(
 export async function listenToSendLog(
   handler: (event: ListenEvent<string>) => void,
 ): Promise<UnlistenFn> {
-  return listen<string>("send-log", handler);
+  return listenEvent("send-log", handler);
 }
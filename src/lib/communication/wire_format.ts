@@ -0,0 +1,86 @@
+/**
+ * MLLP framing and character encoding options for sending and listening.
+ *
+ * Standard MLLP frames messages with `0B` before and `1C 0D` after, and most
+ * systems use UTF-8. Legacy systems sometimes use other framing bytes or send
+ * ISO-8859-1 / Windows-1252, so both can be configured per preset and for the
+ * listener.
+ *
+ * Framing bytes are edited as space-separated hex (e.g. `1C 0D`) and passed to
+ * the backend as byte arrays.
+ */
+
+/**
+ * Bytes marking the start and end of each message.
+ *
+ * Mirrors the Rust `Framing` struct from `communication/framing.rs`.
+ */
+export interface Framing {
+  startBlock: number[];
+  endBlock: number[];
+}
+
+/**
+ * Character encoding used on the wire.
+ *
+ * Mirrors the Rust `MessageEncoding` enum from `communication/encoding.rs`.
+ * `msh18` uses the character set named in each message's MSH-18.
+ */
+export type MessageEncoding = "utf8" | "latin1" | "windows1252" | "msh18";
+
+/** Standard MLLP framing. */
+export const STANDARD_FRAMING: Framing = {
+  startBlock: [0x0b],
+  endBlock: [0x1c, 0x0d],
+};
+
+/** Encodings offered in the UI, with display labels. */
+export const ENCODINGS: { value: MessageEncoding; label: string }[] = [
+  { value: "utf8", label: "UTF-8" },
+  { value: "latin1", label: "ISO-8859-1 (Latin-1)" },
+  { value: "windows1252", label: "Windows-1252" },
+  { value: "msh18", label: "From MSH-18" },
+];
+
+/**
+ * Formats bytes as space-separated uppercase hex.
+ *
+ * @param bytes - Bytes to format
+ * @returns e.g. "1C 0D"
+ */
+export function formatHexBytes(bytes: number[]): string {
+  return bytes
+    .map((b) => b.toString(16).toUpperCase().padStart(2, "0"))
+    .join(" ");
+}
+
+/**
+ * Parses hex bytes, with or without separating spaces.
+ *
+ * @param text - Hex text such as "1C 0D", "1c0d", or "0x1C 0x0D"
+ * @returns The bytes, or null if the text is empty or not valid hex
+ */
+export function parseHexBytes(text: string): number[] | null {
+  const hex = text.replace(/0x/gi, "").replace(/[\s,]/g, "");
+  if (hex.length === 0 || hex.length % 2 !== 0 || !/^[0-9a-f]+$/i.test(hex)) {
+    return null;
+  }
+  const bytes: number[] = [];
+  for (let i = 0; i < hex.length; i += 2) {
+    bytes.push(parseInt(hex.slice(i, i + 2), 16));
+  }
+  return bytes;
+}
+
+/**
+ * Whether framing differs from standard MLLP.
+ */
+export function isCustomFraming(framing: Framing | undefined): boolean {
+  return (
+    framing !== undefined &&
+    (formatHexBytes(framing.startBlock) !==
+      formatHexBytes(STANDARD_FRAMING.startBlock) ||
+      formatHexBytes(framing.endBlock) !==
+        formatHexBytes(STANDARD_FRAMING.endBlock))
+  );
+}
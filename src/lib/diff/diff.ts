@@ -2,7 +2,8 @@
  * Bridge module for comparing HL7 messages.
  *
  * Provides functions to compare two HL7 messages and identify differences
- * at the segment, field, component, and subcomponent levels.
+ * at the segment, field, component, and subcomponent levels, and to compare
+ * two occurrences of a segment within one message.
  */
 
 import { invoke } from "@tauri-apps/api/core";
@@ -74,6 +75,18 @@ export interface MessageDiff {
   summary: DiffSummary;
 }
 
+/**
+ * How occurrences of a repeating segment are paired up between two messages.
+ *
+ * Mirrors the Rust `SegmentMatching` enum from
+ * `src-tauri/src/commands/validation/diff.rs`.
+ *
+ * - `content`: pair each occurrence with the most alike one on the other side,
+ *   so an inserted or reordered OBX doesn't show every later OBX as modified
+ * - `occurrence`: pair the first with the first, the second with the second
+ */
+export type SegmentMatching = "content" | "occurrence";
+
 /**
  * Compares two HL7 messages and returns structured differences.
  *
@@ -82,12 +95,14 @@ export interface MessageDiff {
  * - Field level: within each segment, identifies field changes
  * - Component/subcomponent level: tracks changes at the finest granularity
  *
- * Segments are matched by name and occurrence index. For example, if both
- * messages have two PID segments, PID[0] is compared to PID[0] and PID[1]
- * to PID[1].
+ * Segments are matched by name, and repeating segments are paired up by
+ * `matching`. By content, an OBX inserted early shows as one added OBX; by
+ * occurrence, OBX[0] is compared to OBX[0], OBX[1] to OBX[1], and so on.
+ * For added segments, `occurrence` is that in the right message.
  *
  * @param left - The "original" or "before" message
  * @param right - The "new" or "after" message
+ * @param matching - How to pair repeating segments (defaults to content)
  * @returns Structured diff result with all differences
  * @throws If either message cannot be parsed
  *
@@ -104,6 +119,168 @@ export interface MessageDiff {
 export async function compareMessages(
   left: string,
   right: string,
+  matching: SegmentMatching = "content",
 ): Promise<MessageDiff> {
-  return await invoke("compare_messages", { left, right });
+  return await invoke("compare_messages", { left, right, matching });
+}
+
+/**
+ * How files in two folders are paired up for a bulk comparison.
+ *
+ * Mirrors the Rust `DirectoryPairing` enum from
+ * `src-tauri/src/commands/validation/dir_compare.rs`.
+ *
+ * - `file_name`: compare files with the same name
+ * - `control_id`: compare files whose messages share a control ID (MSH.10)
+ */
+export type DirectoryPairing = "file_name" | "control_id";
+
+/**
+ * Outcome of comparing one pair of files.
+ */
+export type FileStatus =
+  | "identical"
+  | "changed"
+  | "only_left"
+  | "only_right"
+  | "error";
+
+/**
+ * Comparison of one pair of files.
+ */
+export interface FileComparison {
+  /** What the files were paired by: the file name or the control ID */
+  key: string;
+  /** Name of the file in the left folder, if there is one */
+  left_file: string | null;
+  /** Name of the file in the right folder, if there is one */
+  right_file: string | null;
+  /** Outcome of the comparison */
+  status: FileStatus;
+  /** Summary of the differences, if both files were compared */
+  summary: DiffSummary | null;
+  /** Paths of the fields that differ, e.g. "PID.5.1" */
+  changed_fields: string[];
+  /** Why the files couldn't be compared, if they couldn't */
+  error: string | null;
+}
+
+/**
+ * Result of comparing two folders.
+ */
+export interface DirectoryComparison {
+  /** Comparison of each pair of files, ordered by key */
+  files: FileComparison[];
+  /** Number of files with each outcome */
+  summary: {
+    identical: number;
+    changed: number;
+    only_left: number;
+    only_right: number;
+    errors: number;
+  };
+}
+
+/**
+ * Compares every `.hl7` file in one folder with its partner in another.
+ *
+ * Meant for regression-testing engine upgrades: re-run a test corpus and
+ * compare the old outputs with the new to see exactly which messages changed.
+ *
+ * @param leftDir - Folder of the "original" or "before" messages
+ * @param rightDir - Folder of the "new" or "after" messages
+ * @param pairBy - How to pair files up (defaults to file name)
+ * @param matching - How to pair repeating segments (defaults to content)
+ * @param ignoreFields - Field paths whose changes don't count, e.g. ["MSH.7"]
+ * @returns A comparison per file, and the totals
+ * @throws If either folder can't be read
+ */
+export async function compareDirectories(
+  leftDir: string,
+  rightDir: string,
+  pairBy: DirectoryPairing = "file_name",
+  matching: SegmentMatching = "content",
+  ignoreFields: string[] = [],
+): Promise<DirectoryComparison> {
+  return await invoke("compare_directories", {
+    leftDir,
+    rightDir,
+    pairBy,
+    matching,
+    ignoreFields,
+  });
+}
+
+/**
+ * Output format for an exported comparison.
+ *
+ * Mirrors the Rust `DiffExportFormat` enum from
+ * `src-tauri/src/commands/validation/diff_export.rs`.
+ *
+ * - `html`: standalone page with changes colour-coded
+ * - `markdown`: tables with the kind of change spelled out
+ */
+export type DiffExportFormat = "html" | "markdown";
+
+/**
+ * Renders a comparison as a side-by-side report for change-control records.
+ *
+ * @param diff - Result of `compareMessages`
+ * @param format - Report format
+ * @param leftLabel - Name for the left message (defaults to "Original")
+ * @param rightLabel - Name for the right message (defaults to "Modified")
+ * @returns The report's content, ready to be written to a file
+ */
+export async function exportDiff(
+  diff: MessageDiff,
+  format: DiffExportFormat,
+  leftLabel: string | null,
+  rightLabel: string | null,
+): Promise<string> {
+  return await invoke("export_diff", { diff, format, leftLabel, rightLabel });
+}
+
+/**
+ * Compares two occurrences of a segment within the same message.
+ *
+ * Uses the same field-level comparison as `compareMessages`, so repeated
+ * segments (e.g. two OBX rows or IN1 insurance entries) can be reviewed
+ * without copying them into separate documents. Both ranges in the result
+ * refer to `message`.
+ *
+ * @param message - The HL7 message containing both segments
+ * @param pathA - Path of the "left" segment, e.g. "OBX" or "OBX[2]" (1-based)
+ * @param pathB - Path of the "right" segment
+ * @returns Field-level differences between the two segments
+ * @throws If the message cannot be parsed, a segment isn't found, or the
+ *   segments have different names
+ */
+export async function compareSegments(
+  message: string,
+  pathA: string,
+  pathB: string,
+): Promise<SegmentDiff> {
+  return await invoke("compare_segments", { message, pathA, pathB });
+}
+
+/**
+ * Lists the path of every segment occurrence in a message, in order.
+ *
+ * Occurrences are numbered per segment name, so a message with two OBX
+ * segments yields "OBX[1]" and "OBX[2]".
+ *
+ * @param message - Raw HL7 message
+ * @returns Segment paths, e.g. ["MSH[1]", "PID[1]", "OBX[1]", "OBX[2]"]
+ */
+export function listSegmentPaths(message: string): string[] {
+  const counts = new Map<string, number>();
+  return message
+    .split(/\r\n|\r|\n/)
+    .map((line) => line.slice(0, 3))
+    .filter((name) => /^[A-Z0-9]{3}$/.test(name))
+    .map((name) => {
+      const occurrence = (counts.get(name) ?? 0) + 1;
+      counts.set(name, occurrence);
+      return `${name}[${occurrence}]`;
+    });
 }
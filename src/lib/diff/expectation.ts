@@ -0,0 +1,58 @@
+/**
+ * Bridge module for matching messages against "golden" expectations.
+ *
+ * An expectation is the message as it should look, with placeholders where
+ * values legitimately vary between runs (timestamps, control IDs, counters):
+ *
+ * - `{*}` - anything, including nothing
+ * - `{any}` - any value that isn't empty
+ * - `{date}` / `{datetime}` - a valid HL7 date / timestamp
+ * - `{number}` - a decimal number
+ * - `{/regex/}` - text matching the regular expression
+ *
+ * Unlike `compareMessages`, only true mismatches are reported.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * A part of the message that doesn't meet the expectation.
+ *
+ * Mirrors the Rust `ExpectationMismatch` struct from
+ * `src-tauri/src/commands/validation/expectation.rs`.
+ */
+export interface ExpectationMismatch {
+  /** Path of the part (e.g., "PID.5.1", "OBX[2].5", "PID.3[2]") */
+  path: string;
+  /** What the expectation holds there, as written (null if nothing) */
+  expected: string | null;
+  /** What the message holds there (null if nothing) */
+  actual: string | null;
+  /** Character range in the message for highlighting, in UTF-16 code units */
+  range: [number, number] | null;
+}
+
+/**
+ * The outcome of matching a message against an expectation.
+ */
+export interface ExpectationResult {
+  /** Whether the message meets the expectation */
+  matched: boolean;
+  /** Every part that doesn't, in message order */
+  mismatches: ExpectationMismatch[];
+}
+
+/**
+ * Match a message against an expectation that may contain placeholders.
+ *
+ * @param actual - The message to check, such as an engine's output
+ * @param expected - The expected message, with placeholders where values vary
+ * @returns The mismatches, if any
+ * @throws If either message can't be parsed, or a regular expression is invalid
+ */
+export async function matchExpectation(
+  actual: string,
+  expected: string,
+): Promise<ExpectationResult> {
+  return invoke<ExpectationResult>("match_expectation", { actual, expected });
+}
@@ -0,0 +1,97 @@
+/**
+ * Bridge module for generating ADT encounters.
+ *
+ * Builds the messages for a whole visit (register, admit, transfer, discharge
+ * by default) from one set of demographics, so they share the MRN and visit
+ * number, move the patient from location to location, and carry timestamps in
+ * a sensible order.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+/** An ADT event a scenario can include, by trigger event code. */
+export type AdtEvent = "A01" | "A02" | "A03" | "A04" | "A08";
+
+/**
+ * The patient the encounter is for.
+ *
+ * Mirrors the Rust `WorkflowPatient` struct from
+ * `src-tauri/src/commands/editor/adt_workflow.rs`.
+ */
+export interface WorkflowPatient {
+  /** Family name */
+  familyName: string;
+  /** Given name */
+  givenName: string;
+  /** Date of birth (YYYYMMDD), or empty */
+  birthDate?: string;
+  /** Administrative sex (e.g., "F", "M", "U"), or empty */
+  sex?: string;
+  /** Medical record number; generated if empty */
+  mrn?: string;
+}
+
+/**
+ * What happens during the encounter.
+ */
+export interface VisitScenario {
+  /** Events to generate, in order (register, admit, transfer, discharge if empty) */
+  events?: AdtEvent[];
+  /** Time of the first event as an HL7 timestamp (now if empty) */
+  start?: string;
+  /** Minutes between events, replacing the defaults for each event */
+  intervalMinutes?: number | null;
+  /** PV1-2 once the patient is admitted ("I" if empty) */
+  patientClass?: string;
+  /** Locations the patient moves through (e.g., "MED^201^A"), in order */
+  locations?: string[];
+  /** Attending doctor for PV1-7, as written (e.g., "1234^WELBY^MARCUS") */
+  attending?: string;
+  /** Visit number for PV1-19; generated if empty */
+  visitNumber?: string;
+  /** Sending facility for MSH-4 and the MRN's assigning facility ("HOSP" if empty) */
+  facility?: string;
+}
+
+/**
+ * One message of a generated encounter.
+ */
+export interface WorkflowMessage {
+  /** The event the message is for */
+  event: AdtEvent;
+  /** When the event happened, as an HL7 timestamp */
+  timestamp: string;
+  /** The message */
+  message: string;
+}
+
+/**
+ * A generated encounter.
+ */
+export interface AdtWorkflow {
+  /** The messages, in event order */
+  messages: WorkflowMessage[];
+  /** The medical record number used */
+  mrn: string;
+  /** The visit number used */
+  visitNumber: string;
+  /** Every message, separated by blank lines */
+  content: string;
+}
+
+/**
+ * Generates the ADT messages for an encounter.
+ *
+ * @param patient - The patient's demographics
+ * @param scenario - The events to generate and details of the visit
+ * @returns The messages, in event order
+ * @throws Error string if the patient has no name, the start time isn't a
+ *   timestamp, or the events don't make sense in order (a transfer before any
+ *   admission, say)
+ */
+export async function generateAdtWorkflow(
+  patient: WorkflowPatient,
+  scenario: VisitScenario,
+): Promise<AdtWorkflow> {
+  return await invoke<AdtWorkflow>("generate_adt_workflow", { patient, scenario });
+}
@@ -0,0 +1,130 @@
+/**
+ * Bridge module for notes and bookmarks on parts of a message file.
+ *
+ * Annotations are kept by the backend in a sidecar file next to the message
+ * file (`sample.hl7.notes.json`), anchored by query path so they follow their
+ * field as the message is edited. Each command returns the file's annotations
+ * with the current range of every annotated part, or `null` for parts the
+ * message no longer has.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import type { CursorRange } from "$lib/shared/data";
+
+/** What an annotation is for. */
+export type AnnotationKind = "note" | "bookmark";
+
+/**
+ * A note or bookmark and where its part is in the message.
+ *
+ * Mirrors the Rust `LocatedAnnotation` struct from
+ * `src-tauri/src/commands/editor/annotations.rs`.
+ */
+export interface Annotation {
+  /** Identifier of the annotation, unique within its file */
+  id: string;
+  /** Whether this is a note or a bookmark */
+  kind: AnnotationKind;
+  /** Query path of the part it's attached to (e.g., "PID.5.1", "OBX[2]") */
+  path: string;
+  /** Text of the note, or the bookmark's label */
+  text: string;
+  /** Who added it, if known */
+  author: string | null;
+  /** When it was added (ISO 8601) */
+  created: string;
+  /** Whether it has been dealt with */
+  resolved: boolean;
+  /** Range of the annotated part, or null if the message no longer has it */
+  range: CursorRange | null;
+}
+
+/** The part of the message to annotate: a query path or a selection. */
+export type AnnotationTarget = { path: string } | { range: CursorRange };
+
+/**
+ * Lists the annotations of a message file.
+ *
+ * @param filePath - Path of the message file
+ * @param message - The message in the editor
+ * @returns The annotations, oldest first
+ * @throws Error string if the sidecar exists but can't be read
+ */
+export async function listAnnotations(
+  filePath: string,
+  message: string,
+): Promise<Annotation[]> {
+  return invoke<Annotation[]>("list_annotations", { filePath, message });
+}
+
+/**
+ * Attaches a note or bookmark to part of a message file.
+ *
+ * A selection is anchored to the innermost part holding all of it.
+ *
+ * @param filePath - Path of the message file
+ * @param message - The message in the editor
+ * @param kind - Whether to add a note or a bookmark
+ * @param text - Text of the note, or the bookmark's label
+ * @param target - Query path or selection to annotate
+ * @param author - Who is adding it, if known
+ * @returns The file's annotations, with the new one last
+ * @throws Error string if the path is invalid or the selection spans segments
+ */
+export async function addAnnotation(
+  filePath: string,
+  message: string,
+  kind: AnnotationKind,
+  text: string,
+  target: AnnotationTarget,
+  author: string | null = null,
+): Promise<Annotation[]> {
+  return invoke<Annotation[]>("add_annotation", {
+    filePath,
+    message,
+    kind,
+    text,
+    author,
+    path: "path" in target ? target.path : null,
+    range: "range" in target ? target.range : null,
+  });
+}
+
+/**
+ * Marks an annotation as resolved, or reopens it.
+ *
+ * @param filePath - Path of the message file
+ * @param message - The message in the editor
+ * @param id - Identifier of the annotation
+ * @param resolved - Whether the annotation has been dealt with
+ * @returns The file's annotations after the change
+ */
+export async function resolveAnnotation(
+  filePath: string,
+  message: string,
+  id: string,
+  resolved: boolean = true,
+): Promise<Annotation[]> {
+  return invoke<Annotation[]>("resolve_annotation", {
+    filePath,
+    message,
+    id,
+    resolved,
+  });
+}
+
+/**
+ * Deletes an annotation.
+ *
+ * @param filePath - Path of the message file
+ * @param message - The message in the editor
+ * @param id - Identifier of the annotation
+ * @returns The file's annotations left
+ */
+export async function deleteAnnotation(
+  filePath: string,
+  message: string,
+  id: string,
+): Promise<Annotation[]> {
+  return invoke<Annotation[]>("delete_annotation", { filePath, message, id });
+}
@@ -0,0 +1,64 @@
+/**
+ * Bridge module for completion candidates at the cursor.
+ *
+ * Offers segment names at the start of a line, the allowed values of coded
+ * fields (from the schema and HL7 tables), and common dates and timestamps in
+ * date fields, so users can pick from a dropdown rather than remembering code
+ * tables.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import type { CursorRange } from "$lib/shared/data";
+
+/**
+ * What a completion candidate is.
+ *
+ * Mirrors the Rust `CompletionKind` enum from
+ * `src-tauri/src/commands/editor/completions.rs`.
+ */
+export type CompletionKind = "segment" | "value" | "timestamp";
+
+/**
+ * A candidate for the text under the cursor.
+ *
+ * Mirrors the Rust `Completion` struct from
+ * `src-tauri/src/commands/editor/completions.rs`.
+ */
+export interface Completion {
+  /** Text to insert in place of the range */
+  value: string;
+  /** What the text means (e.g., the code's description), if known */
+  detail: string | null;
+  /** What the candidate is */
+  kind: CompletionKind;
+}
+
+/**
+ * Completion candidates for the cursor position, and the text they replace.
+ *
+ * Mirrors the Rust `Completions` struct from
+ * `src-tauri/src/commands/editor/completions.rs`.
+ */
+export interface Completions {
+  /** Range a chosen candidate replaces: the segment name or the whole cell */
+  range: CursorRange;
+  /** Text already typed before the cursor within the range */
+  prefix: string;
+  /** Candidates, in the order to offer them */
+  items: Completion[];
+}
+
+/**
+ * Gets completion candidates for the cursor position.
+ *
+ * @param message - Raw HL7 message
+ * @param cursor - Cursor position in the message
+ * @returns Candidates and the range they replace, or null if there's nothing
+ *   to offer (e.g., in a free-text field)
+ */
+export async function getCompletions(
+  message: string,
+  cursor: number,
+): Promise<Completions | null> {
+  return invoke("get_completions", { message, cursor });
+}
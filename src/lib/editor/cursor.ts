@@ -27,6 +27,26 @@
 
 import { invoke } from "@tauri-apps/api/core";
 
+/** A level of the hierarchy in a cursor's breadcrumb. */
+export type BreadcrumbLevel = "segment" | "field" | "repeat" | "component" | "subcomponent";
+
+/**
+ * One level of a cursor's breadcrumb.
+ *
+ * Mirrors the Rust `BreadcrumbPart` struct from `commands/editor/cursor.rs`.
+ */
+export interface BreadcrumbPart {
+  /** Which level this is */
+  level: BreadcrumbLevel;
+  /**
+   * The segment identifier, or the (1-based) number of the field, repeat,
+   * component, or subcomponent
+   */
+  label: string;
+  /** What the level is called (e.g., "Patient Name"), if known; repeats have no name */
+  name: string | null;
+}
+
 /**
  * Hierarchical position of the cursor within an HL7 message structure.
  *
@@ -37,25 +57,32 @@ export interface LocatedCursor {
   /** Segment name (e.g., "MSH", "PID") */
   segment?: string;
   /** Which occurrence of the segment (0-indexed, for repeating segments) */
-  segment_n?: number;
+  segment_number?: number;
   /** Field number within the segment */
   field?: number;
-  /** Repeat number if the field is repeating */
+  /** Repeat index if the field is repeating (0-indexed) */
   repeat?: number;
   /** Component number within the field */
   component?: number;
   /** Subcomponent number within the component */
   subcomponent?: number;
+  /** Each level of the location, from the segment down, with its name */
+  breadcrumb: BreadcrumbPart[];
+  /** Description of the innermost level from the HL7 specification */
+  description: string | null;
 }
 
 /**
  * Determines the HL7 structural location of the cursor.
  *
  * Returns null if the cursor is not within a valid field (e.g., in whitespace
- * between segments or in an unparseable message).
+ * between segments or in an unparseable message). The location comes with a
+ * named breadcrumb and the spec's description of the innermost level, so
+ * callers don't need to look them up separately.
  *
  * @param message - Raw HL7 message string
- * @param cursor - Character offset of the cursor (0 = start of message)
+ * @param cursor - Offset of the cursor in UTF-16 code units, as the textarea
+ *   counts it (0 = start of message)
  * @returns Location information or null if cursor is not in a valid field
  */
 export async function locateCursor(
@@ -68,6 +95,36 @@ export async function locateCursor(
   });
 }
 
+/**
+ * How far a single navigation step moves.
+ *
+ * - `cell`: the smallest part that exists (field, repeat, component, or subcomponent)
+ * - `component`: components, treating subcomponents as part of their component
+ * - `field`: whole fields, including all of their repeats
+ * - `segment`: whole segments
+ */
+export type NavigationGranularity = "cell" | "component" | "field" | "segment";
+
+/**
+ * Options for field navigation. Omitted options are off, and granularity
+ * defaults to `cell`, which matches plain Tab/Shift+Tab.
+ *
+ * Mirrors the Rust `NavigationOptions` struct from `commands/editor/cursor.rs`.
+ */
+export interface NavigationOptions {
+  /** Skip cells with no content */
+  skip_empty?: boolean;
+  /** Continue from the other end of the message instead of stopping */
+  wrap?: boolean;
+  /** How far a single step moves */
+  granularity?: NavigationGranularity;
+  /**
+   * Move to the same field in the next/previous occurrence of the current
+   * segment (e.g. OBX-5 to the next OBX-5) instead of the adjacent cell
+   */
+  same_path?: boolean;
+}
+
 /**
  * Finds the character range of the field immediately before the cursor.
  *
@@ -76,15 +133,18 @@ export async function locateCursor(
  *
  * @param message - Raw HL7 message string
  * @param cursor - Current cursor position
+ * @param options - Navigation options (skip empty, wrap, granularity, same path)
  * @returns Start and end offsets of the previous field, or null if no previous field
  */
 export async function getRangeOfPreviousField(
   message: string,
   cursor: number,
+  options?: NavigationOptions,
 ): Promise<{ start: number; end: number } | null> {
   return invoke("get_range_of_previous_field", {
     message,
     cursor,
+    options,
   });
 }
 
@@ -97,15 +157,18 @@ export async function getRangeOfPreviousField(
  *
  * @param message - Raw HL7 message string
  * @param cursor - Current cursor position
+ * @param options - Navigation options (skip empty, wrap, granularity, same path)
  * @returns Start and end offsets of the next field, or null if no next field
  */
 export async function getRangeOfNextField(
   message: string,
   cursor: number,
+  options?: NavigationOptions,
 ): Promise<{ start: number; end: number } | null> {
   return invoke("get_range_of_next_field", {
     message,
     cursor,
+    options,
   });
 }
 
@@ -0,0 +1,34 @@
+/**
+ * Bridge module for rewriting a message from one set of delimiters to another.
+ *
+ * Messages from systems using non-standard delimiters, or whose MSH-2 doesn't
+ * match the delimiters they actually use, are split in the wrong places.
+ * Rewriting them for the standard `|^~\&` (or any other set) converts every
+ * delimiter and escape sequence, and escapes text that would otherwise be
+ * read as a delimiter. Returns a new message to pass to `updateMessage` so
+ * it's a single undo entry.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+/** The standard HL7 delimiters, MSH-1 followed by MSH-2. */
+export const STANDARD_DELIMITERS = "|^~\\&";
+
+/**
+ * Rewrites a message for a different set of delimiters.
+ *
+ * @param message - The HL7 message
+ * @param from - The delimiters the message actually uses (e.g., `|*~\&`), or
+ *   null to take them from its MSH header
+ * @param to - The delimiters to rewrite the message with
+ * @returns The rewritten message
+ * @throws Error if a set of delimiters is malformed, or `from` is null and the
+ *   message doesn't start with a header declaring its delimiters
+ */
+export async function remapDelimiters(
+  message: string,
+  from: string | null,
+  to: string = STANDARD_DELIMITERS,
+): Promise<string> {
+  return invoke<string>("remap_delimiters", { message, from, to });
+}
@@ -25,32 +25,75 @@
 import { invoke } from "@tauri-apps/api/core";
 
 /**
- * Loads the standard HL7 specification description for a field or component.
+ * Loads the standard HL7 specification description for a field, component,
+ * or subcomponent.
  *
  * Pass null for field to get the segment description. Pass null for component
- * to get the field description without drilling down to a specific component.
+ * to get the field description without drilling down to a specific component,
+ * and null for subcomponent to stop at the component. Descriptions include the
+ * data type and, for fields bound to one, the HL7 table.
  *
  * @param segment - Segment name (e.g., "MSH", "PID")
  * @param field - Field number (null for segment-level description)
  * @param component - Component number (null for field-level description)
+ * @param subcomponent - Subcomponent number (null for component-level description)
+ * @param message - Message being edited, used to detect the HL7 version from MSH.12
  * @returns Description text from HL7 standard, or null if not found
  *
  * @example
  * ```ts
  * // Get description of PID segment
- * const segDesc = await loadSpec("PID", null, null);
+ * const segDesc = await loadSpec("PID", null, null, null);
  *
  * // Get description of PID.5 (patient name)
- * const fieldDesc = await loadSpec("PID", 5, null);
+ * const fieldDesc = await loadSpec("PID", 5, null, null);
  *
  * // Get description of PID.5.1 (patient last name)
- * const compDesc = await loadSpec("PID", 5, 1);
+ * const compDesc = await loadSpec("PID", 5, 1, null);
+ *
+ * // Get description of PID.3.4.1 (assigning authority namespace ID)
+ * const subDesc = await loadSpec("PID", 3, 4, 1);
  * ```
  */
 export async function loadSpec(
   segment: string,
   field: number | null,
   component: number | null,
+  subcomponent: number | null,
+  message?: string,
 ): Promise<string | null> {
-  return invoke("get_std_description", { segment, field, component });
+  return invoke("get_std_description", { segment, field, component, subcomponent, message });
+}
+
+/**
+ * Loads the standard HL7 specification description of a data type.
+ *
+ * Composite types list their components, separated by `^`.
+ *
+ * @param datatype - Data type code (e.g., "XPN", "CX")
+ * @param message - Message being edited, used to detect the HL7 version from MSH.12
+ * @returns Description text, or an empty string if the type isn't known
+ *
+ * @example
+ * ```ts
+ * // "Hierarchic Designator: Namespace ID ^ Universal ID ^ Universal ID Type"
+ * const hd = await loadDatatypeSpec("HD");
+ * ```
+ */
+export async function loadDatatypeSpec(datatype: string, message?: string): Promise<string> {
+  return invoke("get_datatype_description", { datatype, message });
+}
+
+/**
+ * Lists the segments in a message that have no description.
+ *
+ * These are segments neither the HL7 specification (with its supplement of
+ * financial, insurance, scheduling, and immunization segments) nor a custom
+ * segment schema describes, so their fields show as "Unknown segment".
+ *
+ * @param message - The HL7 message; its MSH.12 picks the version
+ * @returns Segment names, each once, in the order they first appear
+ */
+export async function getUndescribedSegments(message: string): Promise<string[]> {
+  return invoke("get_undescribed_segments", { message });
 }
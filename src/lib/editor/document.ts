@@ -0,0 +1,54 @@
+/**
+ * Bridge module for attaching documents to HL7 messages.
+ *
+ * MDM document messages and ORU results with attached reports carry their
+ * content in OBX segments, either embedded as base64 encapsulated data (ED) or
+ * as a reference pointer (RP) to where the document is stored. These functions
+ * append a correctly formed OBX to the end of the message.
+ *
+ * ## Usage
+ *
+ * ```typescript
+ * const path = await openDialog({ multiple: false });
+ * if (path) {
+ *   const result = await attachDocument(message, path);
+ *   updateMessage(result.message);
+ *   editor.setSelectionRange(result.cursor, result.cursor);
+ * }
+ * ```
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import type { SegmentOperationResult } from "./segment";
+
+/**
+ * Embeds a file in the message as an ED observation.
+ *
+ * @param message - The HL7 message to attach the document to
+ * @param path - Path of the document to embed
+ * @returns The modified message, with the cursor at the start of the new OBX
+ * @throws Error string if the file can't be read or the message can't be parsed
+ */
+export async function attachDocument(
+  message: string,
+  path: string,
+): Promise<SegmentOperationResult> {
+  return invoke("attach_document", { message, path });
+}
+
+/**
+ * References an externally stored document as an RP observation.
+ *
+ * @param message - The HL7 message to attach the reference to
+ * @param pointer - Where the document can be found (e.g. a URL or document ID)
+ * @param application - Application that can resolve the pointer
+ * @returns The modified message, with the cursor at the start of the new OBX
+ * @throws Error string if the pointer is empty or the message can't be parsed
+ */
+export async function attachDocumentReference(
+  message: string,
+  pointer: string,
+  application: string,
+): Promise<SegmentOperationResult> {
+  return invoke("attach_document_reference", { message, pointer, application });
+}
@@ -0,0 +1,72 @@
+/**
+ * Bridge module for the documents open in the editor.
+ *
+ * The backend keeps each message open in the editor as a document with its own
+ * contents, file path, undo history, and file watch, identified by the ID
+ * returned when it's created. Commands that keep per-message state take the ID
+ * of the document they act on. One document is active; extensions and the
+ * Undo/Redo menu items follow it.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * A document open in the editor.
+ *
+ * Mirrors the Rust `DocumentInfo` struct from
+ * `src-tauri/src/commands/editor/documents.rs`.
+ */
+export interface DocumentInfo {
+  /** Identifier of the document */
+  id: number;
+  /** Path of its file, if it has one */
+  filePath: string | null;
+  /** Whether it's the active document */
+  active: boolean;
+}
+
+/**
+ * Opens a document and makes it the active one.
+ *
+ * @param message - The document's contents
+ * @param filePath - Path of the file it was opened from, if any
+ * @returns The new document's identifier
+ */
+export async function createDocument(
+  message: string,
+  filePath: string | null,
+): Promise<number> {
+  return invoke<number>("create_document", { message, filePath });
+}
+
+/**
+ * Closes a document, forgetting its undo history and stopping its file watch.
+ *
+ * @param documentId - The document to close
+ * @returns The active document afterwards, or null if none are open
+ * @throws Error string if the document isn't open
+ */
+export async function closeDocument(
+  documentId: number,
+): Promise<number | null> {
+  return invoke<number | null>("close_document", { documentId });
+}
+
+/**
+ * Makes a document the active one.
+ *
+ * @param documentId - The document to bring to the front
+ * @throws Error string if the document isn't open
+ */
+export async function switchDocument(documentId: number): Promise<void> {
+  return invoke("switch_document", { documentId });
+}
+
+/**
+ * Lists the documents open in the editor.
+ *
+ * @returns The documents, in the order they were opened
+ */
+export async function listDocuments(): Promise<DocumentInfo[]> {
+  return invoke<DocumentInfo[]>("list_documents");
+}
@@ -0,0 +1,58 @@
+/**
+ * Bridge module for encoding and decoding HL7 escape sequences.
+ *
+ * A value containing one of the message's delimiters has to be escaped, or the
+ * delimiter splits it: pasting "Smith & Jones" into a field turns it into two
+ * subcomponents. These commands escape (`\F\`, `\S\`, `\T\`, `\R\`, `\E\`,
+ * `\.br\`, `\Xdd\`) or decode the selected text, or the whole field at the
+ * cursor when nothing is selected, returning a new message to pass to
+ * `updateMessage` so it's a single undo entry.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import type { CursorRange } from "$lib/shared/data";
+
+/**
+ * A message after escaping or decoding part of it.
+ *
+ * Mirrors the Rust `EscapeEdit` struct from
+ * `src-tauri/src/commands/editor/escape.rs`.
+ */
+export interface EscapeEdit {
+  /** The whole message, with the range replaced */
+  message: string;
+  /** Where the replaced text is in the new message, for selecting it */
+  range: CursorRange;
+}
+
+/**
+ * Escapes the delimiters, line breaks, and control characters in a range.
+ *
+ * @param message - The HL7 message
+ * @param range - Text to escape; an empty range escapes the field it's in
+ * @returns The message with the text escaped
+ * @throws Error if the message can't be parsed, or the range is empty and not in a field
+ */
+export async function encodeEscapes(
+  message: string,
+  range: CursorRange,
+): Promise<EscapeEdit> {
+  return invoke<EscapeEdit>("encode_escapes", { message, range });
+}
+
+/**
+ * Replaces the escape sequences in a range with the text they stand for.
+ *
+ * Decoded delimiters split the value again until it's escaped once more.
+ *
+ * @param message - The HL7 message
+ * @param range - Text to decode; an empty range decodes the field it's in
+ * @returns The message with the text decoded
+ * @throws Error if the message can't be parsed, or the range is empty and not in a field
+ */
+export async function decodeEscapes(
+  message: string,
+  range: CursorRange,
+): Promise<EscapeEdit> {
+  return invoke<EscapeEdit>("decode_escapes", { message, range });
+}
@@ -70,3 +70,30 @@ export async function exportToYaml(message: string): Promise<string> {
 export async function exportToToml(message: string): Promise<string> {
   return invoke<string>("export_to_toml", { message });
 }
+
+/**
+ * Exports an HL7 message to HL7 v2.xml.
+ *
+ * Elements are named after the message structure, fields, and data types
+ * (`<ADT_A01>`, `<PID.5>`, `<XPN.1>`), as defined by the v2.xml schemas.
+ *
+ * @param message - The raw HL7 message text
+ * @returns The message as a v2.xml document
+ */
+export async function exportToXml(message: string): Promise<string> {
+  return invoke<string>("export_to_xml", { message });
+}
+
+/**
+ * Exports an HL7 message to a FHIR R4 bundle in JSON format.
+ *
+ * Unlike the other exporters this is a semantic mapping (PID → Patient,
+ * PV1 → Encounter, OBR → DiagnosticReport, OBX → Observation), so only
+ * commonly used fields are carried across.
+ *
+ * @param message - The raw HL7 message text
+ * @returns The FHIR bundle serialized as pretty-printed JSON
+ */
+export async function exportToFhir(message: string): Promise<string> {
+  return invoke<string>("export_to_fhir", { message });
+}
@@ -0,0 +1,65 @@
+/**
+ * Bridge module for measuring field values against their schema length limits.
+ *
+ * Drives the live "23/50" counter in the status bar, so an over-long value is
+ * noticed while typing rather than when validation is next run.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import type { LocatedCursor } from "./cursor";
+
+/**
+ * Length and size of a field's value, and the limits the schema sets on it.
+ *
+ * Mirrors the Rust `FieldMetrics` struct from
+ * `src-tauri/src/commands/editor/field_metrics.rs`.
+ */
+export interface FieldMetrics {
+  /** Number of characters in the decoded value */
+  length: number;
+  /** Number of bytes in the decoded value, encoded as UTF-8 */
+  bytes: number;
+  /** Minimum length from the schema, if the field has one */
+  min_length: number | null;
+  /** Maximum length from the schema, if the field has one */
+  max_length: number | null;
+  /** Characters left before the maximum length, negative once past it */
+  remaining: number | null;
+}
+
+/**
+ * Measures the value at a path against the schema's length limits.
+ *
+ * @param message - Raw HL7 message
+ * @param path - Query path of the field, e.g. "PID.5.1" or "OBX[2].5"
+ * @returns The value's length and size and its limits, or null if the path
+ *   doesn't name a field in the message
+ */
+export async function getFieldMetrics(
+  message: string,
+  path: string,
+): Promise<FieldMetrics | null> {
+  return invoke("get_field_metrics", { message, path });
+}
+
+/**
+ * Builds the query path of the field or component the cursor is in.
+ *
+ * Unlike the path shown in the status bar, occurrences and repeats are
+ * 1-based, as queries expect, e.g. "OBX[2].5" for OBX-5 in the second OBX.
+ *
+ * @param location - Cursor location from `locateCursor`
+ * @returns The query path, or null if the cursor isn't in a field
+ */
+export function queryPathOf(location: LocatedCursor): string | null {
+  const { segment, segment_number, field, repeat, component, subcomponent } = location;
+  if (!segment || field == null) return null;
+
+  let path = segment;
+  if (segment_number) path += `[${segment_number + 1}]`;
+  path += `.${field}`;
+  if (repeat != null) path += `[${repeat + 1}]`;
+  if (component != null) path += `.${component}`;
+  if (subcomponent != null) path += `.${subcomponent}`;
+  return path;
+}
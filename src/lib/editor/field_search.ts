@@ -0,0 +1,42 @@
+/**
+ * Bridge module for finding fields by name.
+ *
+ * Matches a search against the schema's field names and the HL7
+ * specification's field descriptions, so "Jump to Field" can take "attending"
+ * as well as "PV1.7". A word of the search naming a segment in the message
+ * narrows the search to that segment.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * A field whose name or description matches a search.
+ *
+ * Mirrors the Rust `FieldMatch` struct from
+ * `src-tauri/src/commands/editor/field_search.rs`.
+ */
+export interface FieldMatch {
+  /** Query path of the field (e.g., "PV1.7", "OBX[2].5", "PID.5.1") */
+  path: string;
+  /** Name of the field from the schema, or the spec description */
+  name: string;
+  /** Description from the HL7 specification, if it differs from the name */
+  description: string | null;
+  /** Where the field is in the message, or null if the segment is too short */
+  range: { start: number; end: number } | null;
+}
+
+/**
+ * Searches the fields of a message by name and description.
+ *
+ * @param message - Raw HL7 message string
+ * @param query - Text to search for (e.g., "attending", "pid birth")
+ * @returns The best matches, best first (empty for an empty search)
+ * @throws If the message can't be parsed
+ */
+export async function findFieldsByName(
+  message: string,
+  query: string,
+): Promise<FieldMatch[]> {
+  return invoke<FieldMatch[]>("find_fields_by_name", { message, query });
+}
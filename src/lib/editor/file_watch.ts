@@ -0,0 +1,176 @@
+/**
+ * Bridge module for detecting external changes to open files.
+ *
+ * The backend polls each document's file and emits a `file-changed-externally` event
+ * when another program changes it. The change can then be resolved by
+ * reloading the file, overwriting it with the editor contents, or merging the
+ * two versions.
+ *
+ * ## Usage
+ *
+ * ```typescript
+ * await watchFile(documentId, path, savedContents);
+ * const unlisten = await onExternalChange(async (change) => {
+ *   const resolved = await resolveExternalChange(
+ *     change.documentId,
+ *     "merge",
+ *     message,
+ *   );
+ *   message = resolved.message;
+ * });
+ * ```
+ *
+ * `watchFile` must be called again after every save so the watcher knows what
+ * the file is expected to contain.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import type { UnlistenFn } from "@tauri-apps/api/event";
+import { listenEvent } from "$lib/shared/events";
+import type { MessageDiff, SegmentMatching } from "$lib/diff/diff";
+
+/**
+ * An external change to the watched file.
+ */
+export interface ExternalChange {
+  /** Document whose file changed. */
+  documentId: number;
+
+  /** Path of the file that changed. */
+  path: string;
+
+  /** New contents of the file on disk. */
+  disk: string;
+
+  /**
+   * Differences from the editor contents (left) to the disk contents (right),
+   * or null if either version can't be parsed.
+   */
+  diff: MessageDiff | null;
+}
+
+/**
+ * How to resolve an external change.
+ *
+ * - `reload`: discard the editor contents and use the file on disk
+ * - `overwrite`: write the editor contents over the file on disk
+ * - `merge`: merge the editor and disk versions, segment by segment
+ */
+export type ChangeResolution = "reload" | "overwrite" | "merge";
+
+/**
+ * Result of resolving an external change.
+ */
+export interface ResolvedChange {
+  /** New editor contents. */
+  message: string;
+
+  /** Contents of the file on disk after resolving. */
+  disk: string;
+
+  /**
+   * Number of segments changed differently in both versions, for which the
+   * editor's version was kept.
+   */
+  conflicts: number;
+}
+
+/**
+ * A document compared with its file on disk.
+ *
+ * Mirrors the Rust `DiskComparison` struct from
+ * `src-tauri/src/commands/editor/file_watch.rs`.
+ */
+export interface DiskComparison {
+  /** Path of the document's file. */
+  path: string;
+
+  /** Contents of the file on disk. */
+  disk: string;
+
+  /**
+   * Whether the file changed since it was last read or written. Always false
+   * if the file isn't being watched.
+   */
+  changedExternally: boolean;
+
+  /**
+   * Differences from the editor contents (left) to the disk contents (right),
+   * or null if either version can't be parsed.
+   */
+  diff: MessageDiff | null;
+}
+
+/**
+ * Starts watching a document's file for external changes, replacing any
+ * existing watch of that document.
+ *
+ * @param documentId - Document the file is open in
+ * @param path - Path of the file to watch
+ * @param content - Contents of the file as last read or written
+ */
+export async function watchFile(
+  documentId: number,
+  path: string,
+  content: string,
+): Promise<void> {
+  return invoke("watch_file", { documentId, path, content });
+}
+
+/**
+ * Stops watching a document's file.
+ *
+ * @param documentId - Document to stop watching
+ */
+export async function unwatchFile(documentId: number): Promise<void> {
+  return invoke("unwatch_file", { documentId });
+}
+
+/**
+ * Resolves an external change to a document's file.
+ *
+ * @param documentId - Document whose file changed
+ * @param resolution - How to resolve the change
+ * @param message - Current editor contents
+ * @returns The new editor and disk contents
+ */
+export async function resolveExternalChange(
+  documentId: number,
+  resolution: ChangeResolution,
+  message: string,
+): Promise<ResolvedChange> {
+  return invoke<ResolvedChange>("resolve_external_change", {
+    documentId,
+    resolution,
+    message,
+  });
+}
+
+/**
+ * Compares a document with its file on disk.
+ *
+ * @param documentId - Document to compare
+ * @param matching - How to pair repeating segments
+ * @returns The disk contents and their differences from the editor
+ * @throws Error string if the document has no file or it can't be read
+ */
+export async function compareWithDisk(
+  documentId: number,
+  matching: SegmentMatching = "content",
+): Promise<DiskComparison> {
+  return invoke<DiskComparison>("compare_with_disk", { documentId, matching });
+}
+
+/**
+ * Listens for external changes to watched files.
+ *
+ * @param handler - Called with each external change
+ * @returns Function to stop listening
+ */
+export async function onExternalChange(
+  handler: (change: ExternalChange) => void,
+): Promise<UnlistenFn> {
+  return listenEvent("file-changed-externally", (event) =>
+    handler(event.payload),
+  );
+}
@@ -7,6 +7,15 @@
  * raw text typing, form field changes, and segment additions. A centralised
  * history manager ensures consistent behaviour regardless of how the message is modified.
  *
+ * ## Backend Document Model
+ *
+ * The undo history itself lives in the backend (`commands/editor/undo.rs`),
+ * one per open document, so every call names the editor's document. Each change is sent there as the whole new message; the backend records only
+ * the range that changed, groups changes into steps, keeps the Undo/Redo menu
+ * items enabled and named after the step they'd undo, and saves a file's history
+ * when it's saved so it's still there when the file is reopened. This module
+ * mirrors the backend's state so the toolbar can react to it.
+ *
  * ## Design Decision: Function vs Effect
  *
  * We use an explicit `updateMessage()` function pattern rather than a Svelte `$effect`
@@ -20,14 +29,13 @@
  *    distinguish between these.
  *
  * 3. **File operations need to skip history** - Opening a file or creating a new
- *    message should clear history, not record the change.
+ *    message should start a new history, not record the change.
  *
  * ## Coalescing Behavior
  *
- * When `coalesce: true` is passed, rapid changes within 500ms are merged into a
- * single history entry. This prevents each keystroke from being a separate undo step.
- * The saved state is from BEFORE the typing session started, so undoing restores
- * to the pre-typing state.
+ * When `coalesce: true` is passed, changes following each other within 500ms are
+ * merged into a single undo step, so each keystroke isn't a separate step.
+ * Undoing restores the message from before the typing session started.
  *
  * Coalescing is used for:
  * - Regular keyboard typing in the message editor
@@ -40,137 +48,215 @@
  * ## Usage
  *
  * ```typescript
- * const history = createHistoryManager();
+ * const history = createHistoryManager(() => documentId);
  *
- * // In updateMessage function (called before changing message state):
- * history.push(currentMessage, coalesce);
+ * // In updateMessage function (called after changing message state):
  * message = newMessage;
+ * history.record(message, { coalesce });
  *
  * // Undo (pass current state, receive previous state):
- * const previous = history.undo(message);
+ * const previous = await history.undo(message);
  * if (previous !== null) message = previous;
  *
  * // Redo (pass current state, receive next state):
- * const next = history.redo(message);
+ * const next = await history.redo(message);
  * if (next !== null) message = next;
  *
- * // Clear on file new/open:
- * history.clear();
+ * // Start over on file new/open, restoring a saved history if there is one:
+ * history.reset(message, filePath);
+ *
+ * // Keep the history with the file on save:
+ * history.save(filePath, message);
  * ```
  *
  * ## Reactive State
  *
  * `canUndo` and `canRedo` are Svelte 5 derived values that automatically update
- * when the stacks change, enabling reactive UI binding for toolbar buttons and
- * menu item states.
+ * when the backend reports a change, enabling reactive UI binding for toolbar buttons.
  */
 
-const COALESCE_DELAY_MS = 500;
+import { invoke } from "@tauri-apps/api/core";
 
-export function createHistoryManager() {
-  let undoStack: string[] = $state([]);
-  let redoStack: string[] = $state([]);
-  let coalesceTimer: ReturnType<typeof setTimeout> | null = null;
-  let pendingState: string | null = null;
+/**
+ * What can be undone and redone.
+ *
+ * Mirrors the Rust `HistoryState` struct from
+ * `src-tauri/src/commands/editor/undo.rs`.
+ */
+export interface HistoryState {
+  /** Steps that can be undone */
+  undoDepth: number;
+  /** Steps that can be redone */
+  redoDepth: number;
+  /** What the next undo would undo, if the step has a label */
+  undoLabel: string | null;
+  /** What the next redo would redo, if the step has a label */
+  redoLabel: string | null;
+  /** Names of the checkpoints that can be returned to, oldest first */
+  checkpoints: string[];
+}
 
-  const canUndo = $derived(undoStack.length > 0);
-  const canRedo = $derived(redoStack.length > 0);
+/**
+ * The result of undoing, redoing, or returning to a checkpoint.
+ *
+ * Mirrors the Rust `HistoryChange` struct.
+ */
+interface HistoryChange {
+  /** The message to show, or null if there was nothing to do */
+  message: string | null;
+  /** What can be undone and redone now */
+  state: HistoryState;
+}
+
+const EMPTY_STATE: HistoryState = {
+  undoDepth: 0,
+  redoDepth: 0,
+  undoLabel: null,
+  redoLabel: null,
+  checkpoints: [],
+};
+
+/**
+ * @param getDocumentId - The editor's document, or null before it's created;
+ *                        history calls are skipped until there is one
+ */
+export function createHistoryManager(getDocumentId: () => number | null) {
+  let state: HistoryState = $state(EMPTY_STATE);
+
+  const canUndo = $derived(state.undoDepth > 0);
+  const canRedo = $derived(state.redoDepth > 0);
 
   /**
-   * Flush any pending coalesced state to the undo stack.
-   * Called when a non-coalesced change occurs or before undo/redo.
+   * Record a change to the message, after making it.
+   *
+   * @param message - The message after the change
+   * @param options.coalesce - If true, changes following each other within 500ms
+   *                           are merged into one step. Use for typing; false for discrete changes.
+   * @param options.label - What the change did, shown in the Undo menu item (e.g. "Typing")
    */
-  function flushPending() {
-    if (coalesceTimer !== null) {
-      clearTimeout(coalesceTimer);
-      coalesceTimer = null;
-    }
-    if (pendingState !== null) {
-      undoStack.push(pendingState);
-      pendingState = null;
-    }
+  function record(
+    message: string,
+    options?: { coalesce?: boolean; label?: string },
+  ) {
+    const documentId = getDocumentId();
+    if (documentId === null) return;
+    invoke<HistoryState>("record_edit", {
+      documentId,
+      message,
+      label: options?.label ?? null,
+      coalesce: options?.coalesce ?? false,
+    })
+      .then((updated) => {
+        state = updated;
+      })
+      .catch((e) => console.error("failed to record edit:", e));
   }
 
   /**
-   * Push the current message state to history before making a change.
-   *
-   * @param currentMessage - The current message state (before the change)
-   * @param coalesce - If true, rapid calls within COALESCE_DELAY_MS will be merged
-   *                   into a single history entry. Use for typing; false for discrete changes.
+   * Run an undo, redo, or revert, returning the message it produced.
    */
-  function push(currentMessage: string, coalesce: boolean = false) {
-    // Clear redo stack on any new change
-    redoStack = [];
-
-    if (coalesce) {
-      // For coalesced changes, we only want to save the state from BEFORE
-      // the typing session started. If there's no pending state, this is
-      // the start of a new typing session.
-      if (pendingState === null) {
-        pendingState = currentMessage;
-      }
-
-      // Reset the timer - if user keeps typing, we keep delaying
-      if (coalesceTimer !== null) {
-        clearTimeout(coalesceTimer);
-      }
-
-      coalesceTimer = setTimeout(() => {
-        flushPending();
-      }, COALESCE_DELAY_MS);
-    } else {
-      // Non-coalesced change: flush any pending state first, then push current
-      flushPending();
-      undoStack.push(currentMessage);
-    }
+  async function change(
+    command: string,
+    args: Record<string, unknown>,
+  ): Promise<string | null> {
+    const documentId = getDocumentId();
+    if (documentId === null) return null;
+    const result = await invoke<HistoryChange>(command, {
+      documentId,
+      ...args,
+    });
+    state = result.state;
+    return result.message;
   }
 
   /**
    * Undo the last change.
    *
-   * @param currentMessage - The current message state (will be pushed to redo stack)
+   * @param currentMessage - The current message state
    * @returns The previous message state to restore, or null if nothing to undo
+   * @throws Error string if the history no longer fits the message (it's cleared)
    */
-  function undo(currentMessage: string): string | null {
-    // Flush any pending coalesced state before undoing
-    flushPending();
-
-    if (undoStack.length === 0) {
-      return null;
-    }
-
-    const previous = undoStack.pop()!;
-    redoStack.push(currentMessage);
-    return previous;
+  function undo(currentMessage: string): Promise<string | null> {
+    return change("undo_edit", { message: currentMessage });
   }
 
   /**
    * Redo a previously undone change.
    *
-   * @param currentMessage - The current message state (will be pushed to undo stack)
+   * @param currentMessage - The current message state
    * @returns The next message state to restore, or null if nothing to redo
+   * @throws Error string if the history no longer fits the message (it's cleared)
+   */
+  function redo(currentMessage: string): Promise<string | null> {
+    return change("redo_edit", { message: currentMessage });
+  }
+
+  /**
+   * Remember the message under a name, such as the last version that passed
+   * validation, to return to later.
+   *
+   * @param name - Name of the checkpoint; an earlier one of the same name is replaced
+   * @param currentMessage - The current message state
+   */
+  async function checkpoint(name: string, currentMessage: string) {
+    const documentId = getDocumentId();
+    if (documentId === null) return;
+    state = await invoke<HistoryState>("checkpoint_history", {
+      documentId,
+      name,
+      message: currentMessage,
+    });
+  }
+
+  /**
+   * Return to a checkpoint, as a step that can be undone.
+   *
+   * @param name - Name of the checkpoint
+   * @param currentMessage - The current message state
+   * @returns The message at the checkpoint
+   * @throws Error string if there's no checkpoint of that name
    */
-  function redo(currentMessage: string): string | null {
-    if (redoStack.length === 0) {
-      return null;
-    }
-
-    const next = redoStack.pop()!;
-    undoStack.push(currentMessage);
-    return next;
+  function revertTo(
+    name: string,
+    currentMessage: string,
+  ): Promise<string | null> {
+    return change("revert_to_checkpoint", { name, message: currentMessage });
   }
 
   /**
-   * Clear all history. Call when opening a new file or creating a new message.
+   * Start a new history. Call after opening a file or creating a new message.
+   *
+   * @param message - The message now in the editor
+   * @param filePath - Path of the file it was opened from, to restore the
+   *                   history saved with it if the file hasn't changed since
    */
-  function clear() {
-    if (coalesceTimer !== null) {
-      clearTimeout(coalesceTimer);
-      coalesceTimer = null;
-    }
-    pendingState = null;
-    undoStack = [];
-    redoStack = [];
+  function reset(message: string, filePath?: string) {
+    state = EMPTY_STATE;
+    const documentId = getDocumentId();
+    if (documentId === null) return;
+    invoke<HistoryState>("reset_undo_history", {
+      documentId,
+      message,
+      filePath: filePath ?? null,
+    })
+      .then((updated) => {
+        state = updated;
+      })
+      .catch((e) => console.error("failed to reset undo history:", e));
+  }
+
+  /**
+   * Save the history with a file just saved, to restore when it's reopened.
+   *
+   * @param filePath - Path the message was saved to
+   * @param message - The message as saved
+   */
+  function save(filePath: string, message: string) {
+    const documentId = getDocumentId();
+    if (documentId === null) return;
+    invoke("save_undo_history", { documentId, filePath, message }).catch((e) =>
+      console.error("failed to save undo history:", e),
+    );
   }
 
   return {
@@ -180,10 +266,16 @@ export function createHistoryManager() {
     get canRedo() {
       return canRedo;
     },
-    push,
+    get state() {
+      return state;
+    },
+    record,
     undo,
     redo,
-    clear,
+    checkpoint,
+    revertTo,
+    reset,
+    save,
   };
 }
 
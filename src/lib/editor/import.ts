@@ -70,3 +70,29 @@ export async function importFromYaml(content: string): Promise<string> {
 export async function importFromToml(content: string): Promise<string> {
   return invoke<string>("import_from_toml", { content });
 }
+
+/**
+ * Imports an HL7 message from HL7 v2.xml.
+ *
+ * Segment groups and namespace prefixes are accepted; segments are taken in
+ * document order.
+ *
+ * @param content - The v2.xml document
+ * @returns The message as pipe-delimited HL7 text
+ */
+export async function importFromXml(content: string): Promise<string> {
+  return invoke<string>("import_from_xml", { content });
+}
+
+/**
+ * Imports an HL7 message from a FHIR R4 bundle in JSON format.
+ *
+ * Bundles with DiagnosticReport or Observation resources become ORU^R01
+ * messages; otherwise an ADT message is built from the Patient and Encounter.
+ *
+ * @param content - The FHIR bundle as JSON
+ * @returns The message as pipe-delimited HL7 text
+ */
+export async function importFromFhir(content: string): Promise<string> {
+  return invoke<string>("import_from_fhir", { content });
+}
@@ -0,0 +1,54 @@
+/**
+ * Bridge module for extracting messages from logs.
+ *
+ * Interface engine logs bury messages among timestamps and log chatter. These
+ * commands find every embedded message (from `MSH` up to the first line that
+ * isn't a segment) in pasted text or a log file, so one can be opened in the
+ * editor without carving it out by hand.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * A message found in a log.
+ *
+ * Mirrors the Rust `ExtractedMessage` struct from
+ * `src-tauri/src/commands/editor/log_extract.rs`.
+ */
+export interface ExtractedMessage {
+  /** The message, with segments separated by `\r` */
+  message: string;
+  /** Byte offset of the start of `MSH` in the text */
+  start: number;
+  /** Byte offset of the end of the last segment in the text */
+  end: number;
+  /** Line the message starts on (1-based) */
+  line: number;
+  /** Number of segments in the message */
+  segments: number;
+  /** MSH.9, if present */
+  messageType: string | null;
+  /** MSH.10, if present */
+  controlId: string | null;
+}
+
+/**
+ * Extract the HL7 messages embedded in some text.
+ *
+ * @param text - Log output or other text holding messages
+ * @returns The messages found, in the order they appear
+ */
+export async function extractMessages(text: string): Promise<ExtractedMessage[]> {
+  return invoke<ExtractedMessage[]>("extract_messages", { text });
+}
+
+/**
+ * Extract the HL7 messages embedded in a log file.
+ *
+ * @param path - Path to the log file
+ * @returns The messages found, in the order they appear
+ * @throws Error if the file can't be read
+ */
+export async function extractMessagesFromFile(path: string): Promise<ExtractedMessage[]> {
+  return invoke<ExtractedMessage[]>("extract_messages_from_file", { path });
+}
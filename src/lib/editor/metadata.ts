@@ -0,0 +1,71 @@
+/**
+ * Bridge module for metadata headers on message files.
+ *
+ * Message files can start with `#` comment lines carrying a title, an author,
+ * and notes on the scenario the message exercises. The header is split off
+ * when a file is opened, so the editor only ever holds the message, and
+ * written back on save. Validation and sending strip a header left in a
+ * message, so a pasted file still parses.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * Metadata carried in a message file's comment header.
+ *
+ * Mirrors the Rust `DocumentMetadata` struct from
+ * `src-tauri/src/commands/editor/metadata.rs`.
+ */
+export interface DocumentMetadata {
+  /** Short title of the message */
+  title: string | null;
+  /** Who wrote or maintains the message */
+  author: string | null;
+  /** Any other `key: value` properties, in header order */
+  properties: Record<string, string>;
+  /** Free-form notes, one line per comment line */
+  notes: string | null;
+}
+
+/**
+ * A message file split into its metadata header and the message itself.
+ *
+ * Mirrors the Rust `DocumentContent` struct from
+ * `src-tauri/src/commands/editor/metadata.rs`.
+ */
+export interface DocumentContent {
+  /** Metadata from the header (empty if the file has no header) */
+  metadata: DocumentMetadata;
+  /** The message, without the header */
+  message: string;
+  /** Length of the header, i.e. the offset of the message in the file */
+  header_length: number;
+}
+
+/**
+ * Splits a message file into its metadata header and message.
+ *
+ * @param content - Raw file content
+ * @returns The metadata, the message, and where the message starts in the file
+ */
+export async function getDocumentMetadata(
+  content: string,
+): Promise<DocumentContent> {
+  return invoke<DocumentContent>("get_document_metadata", { content });
+}
+
+/**
+ * Writes a metadata header onto a message, replacing any header it has.
+ *
+ * Empty metadata removes the header altogether.
+ *
+ * @param message - The message, with or without a header
+ * @param metadata - Metadata to write
+ * @returns The file content: the header followed by the message
+ */
+export async function setDocumentMetadata(
+  message: string,
+  metadata: DocumentMetadata,
+): Promise<string> {
+  return invoke<string>("set_document_metadata", { message, metadata });
+}
@@ -0,0 +1,62 @@
+/**
+ * Bridge module for edits at several places in a message at once.
+ *
+ * Multi-select workflows (filling the same field in every OBX, deleting all
+ * the segments in a selection) would otherwise need one backend call per
+ * place, each producing an intermediate message and its own undo entry. These
+ * functions send the whole batch and get back a single message, so passing it
+ * to `updateMessage` records one undo entry.
+ *
+ * Batches are atomic: if any path or index is invalid, nothing is changed.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import type { SegmentOperationResult } from "./segment";
+
+/**
+ * A value to write to a field, repeat, or component.
+ *
+ * Mirrors the Rust `FieldEdit` struct from
+ * `src-tauri/src/commands/editor/multi_edit.rs`.
+ */
+export interface FieldEdit {
+  /** Path in query syntax (e.g., "PID.5.1", "OBX[2].5", "PID.3[2].1") */
+  path: string;
+  /** Value to write, with any delimiters already escaped */
+  value: string;
+}
+
+/**
+ * Sets several fields at once, creating any that don't exist yet.
+ *
+ * Edits are applied in order, so a later edit to the same place wins.
+ *
+ * @param message - Raw HL7 message string
+ * @param edits - Values to write, with their paths
+ * @returns The message with every edit applied
+ * @throws Error string if the message can't be parsed, or an edit's path is
+ *   invalid or names a segment the message doesn't have
+ */
+export async function setFields(
+  message: string,
+  edits: FieldEdit[],
+): Promise<string> {
+  return invoke<string>("set_fields", { message, edits });
+}
+
+/**
+ * Deletes several segments at once.
+ *
+ * The cursor is positioned where the first deleted segment was, or at the
+ * start of the last remaining segment if nothing followed the deleted ones.
+ *
+ * @param message - Raw HL7 message string
+ * @param indices - Indices of the segments to delete (cannot include 0/MSH)
+ * @returns Modified message and cursor position, or null if any index is invalid
+ */
+export async function deleteSegments(
+  message: string,
+  indices: number[],
+): Promise<SegmentOperationResult | null> {
+  return invoke("delete_segments", { message, indices });
+}
@@ -0,0 +1,98 @@
+/**
+ * Bridge module for files containing more than one HL7 message.
+ *
+ * Batch exports from integration engines put many messages in one file, either
+ * back to back, separated by blank lines, wrapped in an FHS/BHS envelope, or
+ * still carrying MLLP framing bytes. These functions split such a file into
+ * individual messages for editing and join them back together on save.
+ *
+ * ## Usage
+ *
+ * ```typescript
+ * const file = await splitMessages(content);
+ * if (file.messages.length > 1) {
+ *   // edit one message at a time
+ *   file.messages[index].text = editedMessage;
+ *   const saved = await joinMessages(file);
+ * }
+ * ```
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * How messages were separated in a file.
+ *
+ * Mirrors the Rust `MessageFraming` enum from `editor/multi_message.rs`.
+ */
+export type MessageFraming = "newline" | "blank" | "batch" | "mllp";
+
+/**
+ * A single message within a multi-message file.
+ */
+export interface FileMessage {
+  /** Message text, without surrounding blank lines or framing bytes. */
+  text: string;
+
+  /** Byte offset of the start of the message in the file. */
+  start: number;
+
+  /** Byte offset of the end of the message in the file. */
+  end: number;
+}
+
+/**
+ * A file split into individual messages.
+ */
+export interface MessageFile {
+  /** Messages in file order. */
+  messages: FileMessage[];
+
+  /** How the messages were separated. */
+  framing: MessageFraming;
+
+  /** Line ending used between segments. */
+  lineEnding: string;
+
+  /** Batch header segments (FHS, BHS). */
+  header: string[];
+
+  /** Batch trailer segments (BTS, FTS). */
+  trailer: string[];
+}
+
+/**
+ * Splits file content into individual messages.
+ *
+ * @param content - Raw file content
+ * @returns The messages in the file and the framing needed to rejoin them
+ */
+export async function splitMessages(content: string): Promise<MessageFile> {
+  return invoke<MessageFile>("split_messages", { content });
+}
+
+/**
+ * Finds which message in a file contains a cursor position.
+ *
+ * Positions between messages belong to the preceding message.
+ *
+ * @param content - Raw file content
+ * @param cursor - Cursor position (UTF-16 offset, as the textarea counts)
+ * @returns The index of the message, or null if the file has no messages
+ */
+export async function findMessageAt(
+  content: string,
+  cursor: number,
+): Promise<number | null> {
+  return invoke<number | null>("find_message_at", { content, cursor });
+}
+
+/**
+ * Joins messages back into file content using their original framing.
+ *
+ * @param file - Split file with (possibly edited) message text
+ * @returns The file content to save
+ */
+export async function joinMessages(file: MessageFile): Promise<string> {
+  return invoke<string>("join_messages", { file });
+}
@@ -0,0 +1,66 @@
+/**
+ * Bridge module for converting names and addresses between formats.
+ *
+ * A person name (XPN, as in PID-5) starts with the family name, while a
+ * participant (XCN, as in PV1-7) puts an ID first and moves every name
+ * component along by one. Other systems send a whole name as one
+ * "DOE, JOHN" string, or everything in upper case. These conversions do the
+ * rearranging in one step, returning a new message to pass to
+ * `updateMessage` so it's a single undo entry.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * A name or address conversion.
+ *
+ * Mirrors the Rust `NameStyle` enum from
+ * `src-tauri/src/commands/editor/name_format.rs`.
+ *
+ * - `split-name`: "FAMILY, GIVEN MIDDLE" (or "GIVEN MIDDLE FAMILY") into components
+ * - `join-name`: family, given, and middle name components into "FAMILY, GIVEN MIDDLE"
+ * - `upper-case` / `title-case`: the family, given, and middle names
+ * - `person-to-participant`: XPN into XCN
+ * - `participant-to-person`: XCN into XPN, dropping the ID
+ * - `split-address`: "STREET, CITY, STATE ZIP[, COUNTRY]" into XAD components
+ * - `drop-subcomponents`: keep only the first subcomponent of each component
+ */
+export type NameStyle =
+  | "split-name"
+  | "join-name"
+  | "upper-case"
+  | "title-case"
+  | "person-to-participant"
+  | "participant-to-person"
+  | "split-address"
+  | "drop-subcomponents";
+
+/**
+ * Whether a style converts whole fields rather than starting at a component.
+ *
+ * @param style - Conversion to check
+ * @returns True if paths for the style mustn't name a component
+ */
+export function isWholeFieldStyle(style: NameStyle): boolean {
+  return style === "person-to-participant" || style === "participant-to-person";
+}
+
+/**
+ * Converts the name or address at a path to another format.
+ *
+ * @param message - The HL7 message
+ * @param path - Field in query syntax, optionally with a segment occurrence,
+ *   repeat, and the component the name starts at (e.g., "PID.5", "PV1.7.2",
+ *   "NK1[2].2[1]"); without a repeat, every repeat is converted
+ * @param style - Conversion to apply
+ * @returns The message with the field converted
+ * @throws Error if the path is invalid or empty, or the value isn't in the
+ *   form the style expects
+ */
+export async function convertNameFormat(
+  message: string,
+  path: string,
+  style: NameStyle,
+): Promise<string> {
+  return invoke<string>("convert_name_format", { message, path, style });
+}
@@ -0,0 +1,43 @@
+/**
+ * Bridge module for normalizing the delimiters and line endings of a message.
+ *
+ * Engines differ on whether `PID|1|||` equals `PID|1`; normalizing both sides
+ * before a comparison keeps trailing empty fields, empty repetitions, and line
+ * endings out of the diff. MSH-1, MSH-2, and any `#` comment header are never
+ * changed.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+/** Line ending to separate segments with. */
+export type LineEnding = "cr" | "lf" | "crlf";
+
+/**
+ * What to normalize in a message.
+ *
+ * Mirrors the Rust `NormalizeOptions` struct from
+ * `src-tauri/src/commands/editor/normalize.rs`.
+ */
+export interface NormalizeOptions {
+  /** Remove trailing empty fields, repetitions, components, and subcomponents */
+  stripTrailing?: boolean;
+  /** Remove empty repetitions between other repetitions */
+  collapseRepeats?: boolean;
+  /** Line ending to use between segments; the message's own if left out */
+  lineEnding?: LineEnding | null;
+}
+
+/**
+ * Normalizes the delimiters and line endings of a message.
+ *
+ * @param message - The HL7 message
+ * @param options - What to normalize
+ * @returns The normalized message
+ * @throws Error if the message can't be parsed
+ */
+export async function normalizeMessage(
+  message: string,
+  options: NormalizeOptions,
+): Promise<string> {
+  return invoke<string>("normalize_message", { message, options });
+}
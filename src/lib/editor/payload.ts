@@ -0,0 +1,148 @@
+/**
+ * Bridge module for documents embedded in OBX-5.
+ *
+ * MDM and ORU messages carry documents in their observations: base64 PDFs in
+ * encapsulated data (ED) values, RTF reports written into text observations,
+ * CDA documents as XML. These functions find them, open one in the system's
+ * viewer via a temporary file, save one elsewhere, and replace an
+ * observation's value with a file.
+ *
+ * Payloads are identified by which OBX holds them (1 for the first OBX in the
+ * message), not by set ID, since set IDs needn't be unique.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import type { CursorRange } from "$lib/shared/data";
+
+/**
+ * What kind of document a payload is.
+ *
+ * Mirrors the Rust `PayloadFormat` enum from
+ * `src-tauri/src/commands/editor/payload.rs`.
+ */
+export type PayloadFormat =
+  | "pdf"
+  | "rtf"
+  | "xml"
+  | "html"
+  | "text"
+  | "png"
+  | "jpeg"
+  | "gif"
+  | "tiff"
+  | "binary";
+
+/**
+ * How a payload's data is written in the message.
+ *
+ * Mirrors the Rust `PayloadEncoding` enum.
+ */
+export type PayloadEncoding = "base64" | "hex" | "text";
+
+/**
+ * A document embedded in an observation.
+ *
+ * Mirrors the Rust `EmbeddedPayload` struct.
+ */
+export interface EmbeddedPayload {
+  /** Which OBX segment holds it (1 for the first OBX in the message) */
+  occurrence: number;
+  /** Query path of the value (e.g., "OBX[2].5") */
+  path: string;
+  /** OBX-1 set ID */
+  set_id: string;
+  /** OBX-2 value type (e.g., "ED", "TX") */
+  value_type: string;
+  /** What kind of document it is */
+  format: PayloadFormat;
+  /** How the data is written */
+  encoding: PayloadEncoding;
+  /** Size of the decoded document in bytes */
+  size: number;
+  /** Where OBX-5 is in the message */
+  range: CursorRange;
+}
+
+/**
+ * File extensions for each format, for suggesting a file name when saving.
+ */
+export const PAYLOAD_EXTENSIONS: Record<PayloadFormat, string> = {
+  pdf: "pdf",
+  rtf: "rtf",
+  xml: "xml",
+  html: "html",
+  text: "txt",
+  png: "png",
+  jpeg: "jpg",
+  gif: "gif",
+  tiff: "tif",
+  binary: "bin",
+};
+
+/**
+ * Finds the documents embedded in a message's observations.
+ *
+ * @param message - The HL7 message
+ * @returns Each payload, in message order
+ * @throws Error if the message can't be parsed
+ */
+export async function findEmbeddedPayloads(
+  message: string,
+): Promise<EmbeddedPayload[]> {
+  return invoke<EmbeddedPayload[]>("find_embedded_payloads", { message });
+}
+
+/**
+ * Decodes a payload to a temporary file and opens it in the system's viewer.
+ *
+ * @param message - The HL7 message
+ * @param occurrence - Which OBX holds the payload (1 for the first)
+ * @returns Path of the temporary file
+ * @throws Error if there's no payload there or it can't be decoded or opened
+ */
+export async function openEmbeddedPayload(
+  message: string,
+  occurrence: number,
+): Promise<string> {
+  return invoke<string>("open_embedded_payload", { message, occurrence });
+}
+
+/**
+ * Decodes a payload and saves it to a file.
+ *
+ * @param message - The HL7 message
+ * @param occurrence - Which OBX holds the payload (1 for the first)
+ * @param path - Where to save the document
+ * @throws Error if there's no payload there or it can't be decoded or saved
+ */
+export async function saveEmbeddedPayload(
+  message: string,
+  occurrence: number,
+  path: string,
+): Promise<void> {
+  return invoke("save_embedded_payload", { message, occurrence, path });
+}
+
+/**
+ * Replaces an observation's value with a file, base64-encoded as ED.
+ *
+ * OBX-2 becomes `ED`, and OBX-5 the file's type and subtype (from its
+ * extension), `Base64`, and its content.
+ *
+ * @param message - The HL7 message
+ * @param occurrence - Which OBX to replace the value of (1 for the first)
+ * @param path - The file to embed
+ * @returns The message with the new value
+ * @throws Error if the file can't be read or the message has no such OBX
+ */
+export async function replaceEmbeddedPayload(
+  message: string,
+  occurrence: number,
+  path: string,
+): Promise<string> {
+  return invoke<string>("replace_embedded_payload", {
+    message,
+    occurrence,
+    path,
+  });
+}
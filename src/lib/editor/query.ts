@@ -0,0 +1,98 @@
+/**
+ * Bridge module for building and reading queries.
+ *
+ * Builds the QPD segment of a QBP query (and an RCP when the number of records
+ * is limited) from a query name, tag, and `@PATH^value` parameters, and reads
+ * the same back out of a received QBP or legacy QRY query.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * A parameter of a query.
+ *
+ * Mirrors the Rust `QueryParameter` struct from
+ * `src-tauri/src/commands/editor/query.rs`.
+ */
+export interface QueryParameter {
+  /** Path of the field the value is for (e.g., "PID.5.1") */
+  path: string;
+  /** The value, unescaped */
+  value: string;
+}
+
+/**
+ * A query to build a QPD segment for.
+ *
+ * Mirrors the Rust `QueryCriteria` struct.
+ */
+export interface QueryCriteria {
+  /** Identifier of the query for QPD-1 (e.g., "Q22") */
+  queryName: string;
+  /** Text of the query name (e.g., "Find Candidates") */
+  queryText?: string;
+  /** Coding system of the query name ("HL70471" if empty) */
+  codingSystem?: string;
+  /** Query tag for QPD-2 ("{uuid}" if empty) */
+  queryTag?: string;
+  /** Field values to match, written to QPD-3 */
+  parameters: QueryParameter[];
+  /** Most records to return, for RCP-2 (no RCP if empty) */
+  limit?: string;
+}
+
+/**
+ * The parameters of a received query.
+ *
+ * Mirrors the Rust `QueryParameters` struct.
+ */
+export interface QueryParameters {
+  /** Message type of the query (e.g., "QBP^Q22") */
+  messageType: string;
+  /** Identifier of the query name (QPD-1), or the what subject filter (QRD-9) */
+  queryName: string;
+  /** Query tag (QPD-2), or query ID (QRD-4) */
+  queryTag: string;
+  /** Parameters, in the order they appear */
+  parameters: QueryParameter[];
+  /** Most records to return (RCP-2 or QRD-7), if limited */
+  limit: string | null;
+}
+
+/**
+ * Builds the QPD segment, and an RCP if the query is limited, for a query.
+ *
+ * @param criteria - The query name, tag, parameters, and limit
+ * @returns The segments, one per line
+ * @throws Error string if the query has no name, or a parameter's path is
+ *   invalid
+ */
+export async function buildQuerySegment(criteria: QueryCriteria): Promise<string> {
+  return await invoke<string>("build_query_segment", { criteria });
+}
+
+/**
+ * Replaces the QPD and RCP segments of a message with those built for a query,
+ * or adds them after the MSH if the message has none.
+ *
+ * @param message - The HL7 message
+ * @param criteria - The query name, tag, parameters, and limit
+ * @returns The message with the new query
+ * @throws Error string if the message can't be parsed, the query has no name,
+ *   or a parameter's path is invalid
+ */
+export async function applyQuery(message: string, criteria: QueryCriteria): Promise<string> {
+  return await invoke<string>("apply_query", { message, criteria });
+}
+
+/**
+ * Reads the parameters of a QBP or QRY query message.
+ *
+ * @param message - The HL7 message
+ * @returns The query name, tag, parameters, and limit
+ * @throws Error string if the message can't be parsed, or has neither a QPD
+ *   nor a QRD segment
+ */
+export async function parseQueryParameters(message: string): Promise<QueryParameters> {
+  return await invoke<QueryParameters>("parse_query_parameters", { message });
+}
@@ -0,0 +1,88 @@
+/**
+ * Bridge module for building result blocks.
+ *
+ * Builds the OBR/OBX hierarchy of an ORU^R01 from a list of orders and their
+ * observations, numbering the set IDs, picking value types, and flagging
+ * values outside their reference ranges.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * A single observation.
+ *
+ * Mirrors the Rust `ResultObservation` struct from
+ * `src-tauri/src/commands/editor/result_block.rs`.
+ */
+export interface ResultObservation {
+  /** Code of the observation for OBX-3 */
+  code: string;
+  /** Name of the observation */
+  text?: string;
+  /** Coding system of the code ("LN" if empty) */
+  codingSystem?: string;
+  /** The value for OBX-5 */
+  value?: string;
+  /** OBX-2, worked out from the value if empty */
+  valueType?: string;
+  /** Units for OBX-6 */
+  units?: string;
+  /** Reference range for OBX-7 (e.g., "70-99") */
+  referenceRange?: string;
+  /** Abnormal flags for OBX-8, worked out from the range if empty */
+  abnormalFlags?: string;
+  /** Result status for OBX-11 (the order's if empty) */
+  status?: string;
+  /** Comment, written as an NTE after the observation */
+  note?: string;
+}
+
+/**
+ * An order and the observations reported for it.
+ *
+ * Mirrors the Rust `ResultOrder` struct.
+ */
+export interface ResultOrder {
+  /** Code of the ordered service for OBR-4 */
+  code: string;
+  /** Name of the ordered service */
+  text?: string;
+  /** Coding system of the code ("LN" if empty) */
+  codingSystem?: string;
+  /** Placer order number for OBR-2 */
+  placerOrderNumber?: string;
+  /** Filler order number for OBR-3 */
+  fillerOrderNumber?: string;
+  /** When the observations were made, for OBR-7 and OBX-14 */
+  observedAt?: string;
+  /** Result status for OBR-25 and the observations' default ("F" if empty) */
+  status?: string;
+  /** The observations, in order */
+  observations: ResultObservation[];
+}
+
+/**
+ * Builds the OBR, OBX, and NTE segments for a list of orders.
+ *
+ * @param orders - The orders, each with its observations
+ * @returns The segments, one per line
+ * @throws Error string if there are no orders, or an order or observation has
+ *   no code
+ */
+export async function buildResultBlock(orders: ResultOrder[]): Promise<string> {
+  return await invoke<string>("build_result_block", { orders });
+}
+
+/**
+ * Replaces the results in a message with the segments built for a list of
+ * orders, or adds them at the end if the message has none.
+ *
+ * @param message - The HL7 message
+ * @param orders - The orders, each with its observations
+ * @returns The message with the new results
+ * @throws Error string if the message can't be parsed, there are no orders, or
+ *   an order or observation has no code
+ */
+export async function applyResultBlock(message: string, orders: ResultOrder[]): Promise<string> {
+  return await invoke<string>("apply_result_block", { message, orders });
+}
@@ -0,0 +1,41 @@
+/**
+ * Bridge module for the scripting console.
+ *
+ * Runs a short Rhai script against the message in the editor. Scripts read and
+ * write fields by path (`get`, `set`), count and list segments, regenerate the
+ * control ID, and print lines to the console's output; see the module docs in
+ * `src-tauri/src/commands/editor/script.rs` for the full API.
+ *
+ * Scripts run sandboxed in the backend: they can't touch files or the network,
+ * and a script that runs too long is stopped. A failing script leaves the
+ * message as it was.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * The outcome of running a script.
+ *
+ * Mirrors the Rust `ScriptResult` struct from
+ * `src-tauri/src/commands/editor/script.rs`.
+ */
+export interface ScriptResult {
+  /** The message after the script ran */
+  message: string;
+  /** Whether the script changed the message */
+  changed: boolean;
+  /** Lines printed by the script */
+  output: string[];
+}
+
+/**
+ * Runs a script against a message.
+ *
+ * @param message - Raw HL7 message string
+ * @param script - Rhai source of the script
+ * @returns The message as the script left it, with what it printed
+ * @throws Error string if the script doesn't compile, fails, or runs too long
+ */
+export async function runScript(message: string, script: string): Promise<ScriptResult> {
+  return invoke<ScriptResult>("run_script", { message, script });
+}
@@ -24,6 +24,13 @@
  * - Cmd+Shift+↓: Move segment down
  * - Cmd+Shift+D: Duplicate segment
  *
+ * ## Choosing a Segment
+ *
+ * Operations take a `SegmentTarget`: the segment's 0-based index, as found by
+ * `getSegmentIndexAtCursor`, or its name and 1-based occurrence, as in
+ * `"OBX[3]"` (`"OBX"` alone is the first OBX). `moveSegmentTo` moves a segment
+ * to any other position rather than a line at a time.
+ *
  * ## Constraints
  *
  * The MSH segment is protected:
@@ -31,6 +38,12 @@
  * - Cannot be moved (must always be first)
  * - Cannot be duplicated (would create invalid message)
  *
+ * ## Change Descriptions
+ *
+ * Each result lists the segments the operation added, removed, or moved, so
+ * anything tracking segments can update incrementally. The editor forwards them
+ * to extensions with the next `message/changed` notification.
+ *
  * ## Usage
  *
  * ```typescript
@@ -48,19 +61,61 @@
 
 import { invoke } from "@tauri-apps/api/core";
 
+/**
+ * Kind of segment operation that produced a change.
+ */
+export type SegmentOperation = "delete" | "move" | "duplicate" | "insert";
+
+/**
+ * How a single segment changed in a segment operation.
+ *
+ * The "before" side is null for segments that were added, and the "after"
+ * side for segments that were removed. Ranges cover the segment's content,
+ * without its line ending.
+ *
+ * Mirrors the Rust `SegmentChange` from `src-tauri/src/commands/editor/segment.rs`.
+ */
+export interface SegmentChange {
+  operation: SegmentOperation;
+  /** 0-based index of the segment before the operation */
+  indexBefore: number | null;
+  /** 0-based index of the segment after the operation */
+  indexAfter: number | null;
+  /** Range of the segment before the operation */
+  rangeBefore: { start: number; end: number } | null;
+  /** Range of the segment after the operation */
+  rangeAfter: { start: number; end: number } | null;
+}
+
 /**
  * Result of a segment operation.
  *
- * Contains the modified message and where to position the cursor after
- * the operation completes.
+ * Contains the modified message, where to position the cursor after
+ * the operation completes, and the segments that changed.
  */
 export interface SegmentOperationResult {
   /** The modified message content */
   message: string;
   /** Character offset where the cursor should be positioned */
   cursor: number;
+  /** Segments the operation added, removed, or moved */
+  changes: SegmentChange[];
 }
 
+/**
+ * A segment to operate on: its 0-based index, or its name and 1-based
+ * occurrence (e.g., "OBX[3]", or "OBX" for the first).
+ *
+ * Mirrors the Rust `SegmentTarget` enum.
+ */
+export type SegmentTarget = number | string;
+
+/**
+ * Segment a segment menu event acts on; null for the segment at the cursor,
+ * as when the menu item is chosen.
+ */
+export type SegmentEventTarget = SegmentTarget | null;
+
 /**
  * Direction for moving a segment.
  */
@@ -81,53 +136,72 @@ export async function getSegmentIndexAtCursor(
 }
 
 /**
- * Deletes the segment at the given index.
+ * Deletes a segment.
  *
  * The cursor is positioned at the start of the next segment, or the previous
  * segment if deleting the last one.
  *
  * @param message - Raw HL7 message string
- * @param segmentIndex - Index of segment to delete (cannot be 0/MSH)
+ * @param segment - Segment to delete (cannot be 0/MSH)
  * @returns Modified message and cursor position, or null if operation invalid
  */
 export async function deleteSegment(
   message: string,
-  segmentIndex: number,
+  segment: SegmentTarget,
 ): Promise<SegmentOperationResult | null> {
-  return invoke("delete_segment", { message, segmentIndex });
+  return invoke("delete_segment", { message, segment });
 }
 
 /**
- * Moves the segment at the given index up or down.
+ * Moves a segment up or down.
  *
  * The cursor follows the moved segment.
  *
  * @param message - Raw HL7 message string
- * @param segmentIndex - Index of segment to move
+ * @param segment - Segment to move
  * @param direction - "up" or "down"
  * @returns Modified message and cursor position, or null if operation invalid
  */
 export async function moveSegment(
   message: string,
-  segmentIndex: number,
+  segment: SegmentTarget,
   direction: MoveDirection,
 ): Promise<SegmentOperationResult | null> {
-  return invoke("move_segment", { message, segmentIndex, direction });
+  return invoke("move_segment", { message, segment, direction });
+}
+
+/**
+ * Moves a segment to where another segment is now.
+ *
+ * The segments in between shift by one to make room, and the cursor follows
+ * the moved segment.
+ *
+ * @param message - Raw HL7 message string
+ * @param segment - Segment to move (cannot be 0/MSH)
+ * @param position - Segment whose position it takes (cannot be 0/MSH)
+ * @returns Modified message and cursor position, or null if operation invalid
+ */
+export async function moveSegmentTo(
+  message: string,
+  segment: SegmentTarget,
+  position: SegmentTarget,
+): Promise<SegmentOperationResult | null> {
+  return invoke("move_segment_to", { message, segment, position });
 }
 
 /**
- * Duplicates the segment at the given index.
+ * Duplicates a segment.
  *
  * Creates a copy immediately after the original. The cursor is positioned
  * at the start of the new duplicate segment.
  *
  * @param message - Raw HL7 message string
- * @param segmentIndex - Index of segment to duplicate (cannot be 0/MSH)
+ * @param segment - Segment to duplicate (cannot be 0/MSH)
  * @returns Modified message and cursor position, or null if operation invalid
  */
 export async function duplicateSegment(
   message: string,
-  segmentIndex: number,
+  segment: SegmentTarget,
 ): Promise<SegmentOperationResult | null> {
-  return invoke("duplicate_segment", { message, segmentIndex });
+  return invoke("duplicate_segment", { message, segment });
 }
@@ -0,0 +1,91 @@
+/**
+ * Bridge module for reusable segment snippets.
+ *
+ * A snippet is one or more segments saved under a name, such as a fully
+ * populated IN1 or a standard block of OBXs, kept in the `snippets` folder of
+ * the data root. Snippets are inserted after the segment holding the cursor,
+ * using the message's line endings.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import type { SegmentOperationResult } from "./segment";
+
+/**
+ * A saved snippet.
+ *
+ * Mirrors the Rust `SnippetInfo` struct from
+ * `src-tauri/src/commands/editor/snippets.rs`.
+ */
+export interface SnippetInfo {
+  /** Identifier (the file name without extension) */
+  id: string;
+  /** Name the snippet was saved with */
+  name: string;
+  /** What the snippet is for, if given */
+  description: string | null;
+  /** Names of the snippet's segments, in order */
+  segments: string[];
+  /** The snippet's segments, one per line */
+  content: string;
+}
+
+/**
+ * Lists the saved snippets, sorted by name.
+ *
+ * @param query - Only list snippets whose name, description, or segment names
+ *   contain this text, ignoring case
+ * @returns The matching snippets
+ */
+export async function listSnippets(
+  query: string | null = null,
+): Promise<SnippetInfo[]> {
+  return invoke<SnippetInfo[]>("list_snippets", { query });
+}
+
+/**
+ * Saves segments as a snippet, replacing any snippet of the same name.
+ *
+ * @param name - Name of the snippet
+ * @param content - The segments, one per line (MSH isn't allowed)
+ * @param description - Optional description of what the snippet is for
+ * @returns The saved snippet
+ * @throws Error string if the content isn't a list of segments
+ */
+export async function saveSnippet(
+  name: string,
+  content: string,
+  description: string | null,
+): Promise<SnippetInfo> {
+  return invoke<SnippetInfo>("save_snippet", { name, content, description });
+}
+
+/**
+ * Deletes a snippet.
+ *
+ * @param id - Identifier of the snippet
+ */
+export async function deleteSnippet(id: string): Promise<void> {
+  return invoke<void>("delete_snippet", { id });
+}
+
+/**
+ * Inserts a snippet's segments after the segment holding the cursor.
+ *
+ * @param message - The HL7 message to insert into
+ * @param cursor - Cursor position in the message
+ * @param id - Identifier of the snippet
+ * @returns The modified message, with the cursor at the start of the first
+ *   inserted segment
+ * @throws Error string if the snippet can't be read or the message can't be parsed
+ */
+export async function insertSnippet(
+  message: string,
+  cursor: number,
+  id: string,
+): Promise<SegmentOperationResult> {
+  return invoke<SegmentOperationResult>("insert_snippet", {
+    message,
+    cursor,
+    id,
+  });
+}
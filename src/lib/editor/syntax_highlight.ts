@@ -30,9 +30,9 @@ import { invoke } from "@tauri-apps/api/core";
  * Represents a search match range for highlighting in the editor.
  */
 export interface SearchMatch {
-  /** Start position of the match (byte offset) */
+  /** Start position of the match (UTF-16 offset) */
   start: number;
-  /** End position of the match (byte offset, exclusive) */
+  /** End position of the match (UTF-16 offset, exclusive) */
   end: number;
 }
 
@@ -45,9 +45,9 @@ export type DiffHighlightType = "added" | "removed" | "modified";
  * Represents a diff highlight range with its type.
  */
 export interface DiffMatch {
-  /** Start position of the diff (byte offset) */
+  /** Start position of the diff (UTF-16 offset) */
   start: number;
-  /** End position of the diff (byte offset, exclusive) */
+  /** End position of the diff (UTF-16 offset, exclusive) */
   end: number;
   /** Type of difference */
   diff_type: DiffHighlightType;
@@ -62,9 +62,9 @@ export type ValidationSeverity = "error" | "warning" | "info";
  * Represents a validation highlight range with its severity.
  */
 export interface ValidationMatch {
-  /** Start position of the validation issue (byte offset) */
+  /** Start position of the validation issue (UTF-16 offset) */
   start: number;
-  /** End position of the validation issue (byte offset, exclusive) */
+  /** End position of the validation issue (UTF-16 offset, exclusive) */
   end: number;
   /** Severity of the validation issue */
   severity: ValidationSeverity;
@@ -82,6 +82,7 @@ export interface ValidationMatch {
  * @param currentMatchIndex - Optional index of the currently selected match (0-based)
  * @param diffMatches - Optional array of diff highlight ranges with their types
  * @param validationMatches - Optional array of validation highlight ranges with severities
+ * @param highlightEscapes - Whether to mark escape sequences like `\F\` with the "esc" class
  * @returns HTML string with syntax highlighting markup
  *
  * @example
@@ -104,6 +105,7 @@ export async function syntaxHighlight(
   currentMatchIndex?: number,
   diffMatches?: DiffMatch[],
   validationMatches?: ValidationMatch[],
+  highlightEscapes?: boolean,
 ): Promise<string> {
   return invoke("syntax_highlight", {
     message,
@@ -111,6 +113,7 @@ export async function syntaxHighlight(
     currentMatchIndex: currentMatchIndex ?? null,
     diffMatches: diffMatches ?? null,
     validationMatches: validationMatches ?? null,
+    highlightEscapes: highlightEscapes ?? null,
   });
 }
 
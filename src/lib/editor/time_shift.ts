@@ -0,0 +1,55 @@
+/**
+ * Bridge module for shifting every timestamp in a message at once.
+ *
+ * Replaying an old test message often needs current dates. Shifting moves
+ * every field the schema marks as a timestamp by the same amount, keeping the
+ * time between them, either by an offset or so MSH-7 becomes the current time.
+ * Returns a new message to pass to `updateMessage` so it's a single undo entry.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * How far to move the timestamps of a message.
+ *
+ * Mirrors the Rust `TimestampShift` enum from
+ * `src-tauri/src/commands/editor/time_shift.rs`.
+ */
+export type TimestampShift =
+  /** Move every timestamp by an offset such as `+1d` or `-2h30m` */
+  | { kind: "offset"; offset: string }
+  /** Move every timestamp so MSH-7 becomes the current time */
+  | { kind: "rebase" };
+
+/**
+ * A message after its timestamps were shifted.
+ *
+ * Mirrors the Rust `ShiftedTimestamps` struct from
+ * `src-tauri/src/commands/editor/time_shift.rs`.
+ */
+export interface ShiftedTimestamps {
+  /** The message with its timestamps shifted */
+  message: string;
+  /** Number of timestamps shifted */
+  shifted: number;
+  /** Paths of timestamp fields whose values couldn't be read, left as they were */
+  unreadable: string[];
+}
+
+/**
+ * Shifts every timestamp in a message by the same amount.
+ *
+ * @param message - The HL7 message
+ * @param shift - An offset to shift by, or a rebase onto the current time
+ * @param includeDates - Whether to shift date fields (e.g., birth dates) too
+ * @returns The shifted message
+ * @throws Error if the message can't be parsed, the offset is malformed, or
+ *   MSH-7 has no timestamp to rebase from
+ */
+export async function shiftTimestamps(
+  message: string,
+  shift: TimestampShift,
+  includeDates: boolean,
+): Promise<ShiftedTimestamps> {
+  return invoke<ShiftedTimestamps>("shift_timestamps", { message, shift, includeDates });
+}
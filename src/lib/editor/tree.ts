@@ -0,0 +1,68 @@
+/**
+ * Bridge module for the message structure tree.
+ *
+ * The backend parses the message into a tree of segments, fields, repeats,
+ * components, and subcomponents, named from the schema and the HL7 spec and
+ * carrying their ranges, for rendering a collapsible structure view alongside
+ * the editor. Only the levels a value actually uses are present: a field
+ * without repeats has its components directly beneath it, and a plain field
+ * has no children at all.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+/** What part of the message a node is. */
+export type NodeKind =
+  | "segment"
+  | "field"
+  | "repeat"
+  | "component"
+  | "subcomponent";
+
+/**
+ * A part of the message and the parts within it.
+ *
+ * Mirrors the Rust `TreeNode` struct from
+ * `src-tauri/src/commands/editor/tree.rs`.
+ */
+export interface TreeNode {
+  /** What part of the message this is */
+  kind: NodeKind;
+  /** Query path of the part (e.g., "OBX[2]", "PID.5", "PID.3[2].1") */
+  path: string;
+  /** Name of the part from the schema or spec, if known */
+  name: string | null;
+  /** Raw text of the part, delimiters and escape sequences included */
+  value: string;
+  /** Where the part is in the message */
+  range: { start: number; end: number };
+  /** The parts within this one, empty if it has none */
+  children: TreeNode[];
+}
+
+/**
+ * Parses a message into its structure tree.
+ *
+ * @param message - Raw HL7 message string
+ * @returns One node per segment, in message order
+ * @throws If the message can't be parsed
+ */
+export async function parseMessageTree(message: string): Promise<TreeNode[]> {
+  return invoke<TreeNode[]>("parse_message_tree", { message });
+}
+
+/**
+ * Finds the deepest node containing a cursor position, for revealing the
+ * node under the editor's cursor.
+ *
+ * @param nodes - Nodes to search (e.g., the whole tree)
+ * @param cursor - Cursor offset within the message
+ * @returns The path from the outermost to the innermost node, empty if the
+ *   cursor is outside every node
+ */
+export function nodesAtCursor(nodes: TreeNode[], cursor: number): TreeNode[] {
+  const node = nodes.find(
+    (node) => cursor >= node.range.start && cursor <= node.range.end,
+  );
+  return node ? [node, ...nodesAtCursor(node.children, cursor)] : [];
+}
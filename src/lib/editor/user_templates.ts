@@ -0,0 +1,74 @@
+/**
+ * Bridge module for user-defined message templates.
+ *
+ * Any message can be saved as a named template in the `templates` folder of
+ * the data root. Saved templates are listed under "New from Template" > "My
+ * Templates" in the menu, and in the template library modal.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * A saved user template.
+ *
+ * Mirrors the Rust `UserTemplateInfo` struct from
+ * `src-tauri/src/commands/editor/user_templates.rs`.
+ */
+export interface UserTemplateInfo {
+  /** Identifier (the file name without extension) */
+  id: string;
+  /** Name the template was saved with */
+  name: string;
+  /** What the template is for, if given */
+  description: string | null;
+  /** Message type and trigger event (e.g., "ADT^A01"), if the message has them */
+  message_type: string | null;
+}
+
+/**
+ * Lists the saved user templates, sorted by name.
+ *
+ * @returns The templates (empty if none have been saved)
+ */
+export async function listUserTemplates(): Promise<UserTemplateInfo[]> {
+  return invoke<UserTemplateInfo[]>("list_user_templates");
+}
+
+/**
+ * Saves a message as a user template, replacing any template of the same name.
+ *
+ * @param name - Name of the template
+ * @param message - The message, with or without a metadata header
+ * @param description - Optional description of what the template is for
+ * @returns The saved template
+ */
+export async function saveUserTemplate(
+  name: string,
+  message: string,
+  description: string | null,
+): Promise<UserTemplateInfo> {
+  return invoke<UserTemplateInfo>("save_user_template", {
+    name,
+    message,
+    description,
+  });
+}
+
+/**
+ * Reads a user template's message.
+ *
+ * @param id - Identifier of the template
+ * @returns The message, without the template's metadata header
+ */
+export async function loadUserTemplate(id: string): Promise<string> {
+  return invoke<string>("load_user_template", { id });
+}
+
+/**
+ * Deletes a user template.
+ *
+ * @param id - Identifier of the template
+ */
+export async function deleteUserTemplate(id: string): Promise<void> {
+  return invoke<void>("delete_user_template", { id });
+}
@@ -11,6 +11,7 @@
 
 import { invoke } from "@tauri-apps/api/core";
 import type { ExtensionConfig } from "../../settings";
+import type { SegmentChange } from "$lib/editor/segment";
 
 // Re-export types for convenience
 export type { ExtensionConfig } from "../../settings";
@@ -65,6 +66,34 @@ export interface ExtensionStatus {
 
   /** Error message if state is "failed". */
   error?: string;
+
+  /** Settings the extension declares, if any. */
+  settings?: SettingDefinition[];
+}
+
+/**
+ * A setting an extension declares, shown in the extension settings.
+ *
+ * Mirrors the Rust `SettingDefinition` struct from `extensions/types.rs`.
+ */
+export interface SettingDefinition {
+  /** Key the value is stored and sent under. */
+  key: string;
+
+  /** Kind of value the setting takes. */
+  type: "string" | "number" | "boolean" | "choice";
+
+  /** Label shown next to the input. */
+  label?: string;
+
+  /** Longer description of what the setting does. */
+  description?: string;
+
+  /** Value the extension uses when the setting isn't set. */
+  default?: unknown;
+
+  /** Values a `choice` setting can take. */
+  choices?: string[];
 }
 
 /**
@@ -95,20 +124,100 @@ export interface ToolbarButtonInfo {
   };
 }
 
+/**
+ * A transport provided by a running extension.
+ *
+ * Mirrors the Rust `TransportInfo` from `src-tauri/src/extensions/host.rs`.
+ */
+export interface TransportInfo {
+  /** ID of the extension that provides this transport. */
+  extensionId: string;
+
+  /** Transport name that presets refer to. */
+  name: string;
+
+  /** Human-readable name, falling back to the transport name. */
+  label: string;
+}
+
+/**
+ * A template contributed by a running extension.
+ *
+ * Mirrors the Rust `TemplateInfo` from `src-tauri/src/extensions/host.rs`.
+ */
+export interface TemplateInfo {
+  /** ID of the extension that generates this template. */
+  extensionId: string;
+
+  /** Name shown in the menu. */
+  name: string;
+
+  /** Command passed back in the `template/generate` request. */
+  command: string;
+}
 
 /**
- * Log level for extension events.
+ * A status bar item set by an extension with `ui/setStatusBarItem`.
+ *
+ * Mirrors the Rust `StatusBarItemEvent` from `commands/extensions/ui.rs`.
  */
-export type LogLevel = "info" | "warn" | "error";
+export interface ExtensionStatusBarItem {
+  /** ID of the extension that owns the item. */
+  extensionId: string;
+
+  /** Identifier of the item, unique within the extension. */
+  id: string;
+
+  /** Text to show, or null to remove the item. */
+  text: string | null;
+
+  /** Tooltip shown on hover. */
+  tooltip?: string;
+
+  /** Command sent to the extension when the item is clicked. */
+  command?: string;
+}
+
+/**
+ * A progress report from an extension with `ui/progress`.
+ *
+ * Mirrors the Rust `ProgressEvent` from `commands/extensions/ui.rs`.
+ */
+export interface ExtensionProgress {
+  /** ID of the extension running the operation. */
+  extensionId: string;
+
+  /** Identifier of the operation, unique within the extension. */
+  id: string;
+
+  /** Name of the operation, kept from earlier reports if omitted. */
+  title?: string;
+
+  /** What the operation is doing now. */
+  message?: string;
+
+  /** Percentage complete (0-100), if known. */
+  percentage?: number;
+
+  /** Whether the operation has finished. */
+  done: boolean;
+}
+
+/**
+ * Log level for extension events, from least to most severe.
+ */
+export type LogLevel = "debug" | "info" | "warn" | "error";
 
 /**
  * Message event type for sync_editor_message command.
  *
- * Used to inform extensions about message lifecycle events.
+ * Used to inform extensions about message lifecycle events, and about edits
+ * made by segment operations so `message/changed` can describe them.
  */
 export type MessageEvent =
   | { type: "opened"; isNew: boolean }
-  | { type: "saved"; saveAs: boolean };
+  | { type: "saved"; saveAs: boolean }
+  | { type: "segmentsChanged"; changes: SegmentChange[] };
 
 /**
  * Log entry from an extension.
@@ -124,6 +233,115 @@ export interface ExtensionLog {
   message: string;
 }
 
+/**
+ * A page of an extension's log entries, newest first.
+ */
+export interface ExtensionLogPage {
+  /** The entries on this page. */
+  entries: ExtensionLog[];
+
+  /** Number of entries matching the filter, across all pages. */
+  total: number;
+}
+
+/**
+ * Which of an extension's log entries to get.
+ */
+export interface ExtensionLogQuery {
+  /** Least severe level to include; every level if unset. */
+  level?: LogLevel;
+
+  /** Number of the newest matching entries to skip. */
+  offset?: number;
+
+  /** Most entries to return; all of them if unset. */
+  limit?: number;
+}
+
+/**
+ * Which way a traced message went: from the extension to Hermes, or from
+ * Hermes to the extension.
+ */
+export type TraceDirection = "incoming" | "outgoing";
+
+/**
+ * A JSON-RPC message, as exchanged with an extension.
+ */
+export interface JsonRpcMessage {
+  jsonrpc: "2.0";
+  id?: number | string | null;
+  method?: string;
+  params?: unknown;
+  result?: unknown;
+  error?: { code: number; message: string; data?: unknown };
+}
+
+/**
+ * A message exchanged with an extension in development mode.
+ *
+ * Mirrors the Rust `ExtensionTraceEvent` from `extensions/host.rs`.
+ */
+export interface ExtensionTraceEntry {
+  /** ID of the extension the message was exchanged with. */
+  extensionId: string;
+
+  /** When the message was read or written (ISO 8601 format). */
+  timestamp: string;
+
+  /** Which way the message went. */
+  direction: TraceDirection;
+
+  /** The message, as sent over the wire. */
+  message: JsonRpcMessage;
+}
+
+/**
+ * Outcome of a single conformance check.
+ *
+ * Mirrors the Rust `ConformanceStatus` enum from `extensions/conformance.rs`.
+ */
+export type ConformanceStatus = "pass" | "warn" | "fail" | "skipped";
+
+/**
+ * Result of a single conformance check.
+ */
+export interface ConformanceCheck {
+  /** Short name of the check. */
+  name: string;
+
+  /** Outcome of the check. */
+  status: ConformanceStatus;
+
+  /** Explanation of the outcome. */
+  message: string;
+
+  /** Time taken by the check in milliseconds. */
+  durationMs: number;
+}
+
+/**
+ * Report produced by running the conformance suite against an extension.
+ */
+export interface ConformanceReport {
+  /** Extension command that was tested. */
+  path: string;
+
+  /** Extension name from the initialize response, if the handshake succeeded. */
+  extensionName: string | null;
+
+  /** Extension version from the initialize response, if the handshake succeeded. */
+  extensionVersion: string | null;
+
+  /** Individual check results in the order they ran. */
+  checks: ConformanceCheck[];
+
+  /** Whether every check passed or only warned. */
+  passed: boolean;
+
+  /** Log entries captured from the extension during the run. */
+  logs: ExtensionLog[];
+}
+
 // ============================================================================
 // Tauri Command Bridges
 // ============================================================================
@@ -165,6 +383,143 @@ export async function getExtensionToolbarButtons(): Promise<
   return invoke("get_extension_toolbar_buttons");
 }
 
+/**
+ * Get the transports provided by running extensions.
+ *
+ * Used to offer extension transports alongside MLLP in connection presets.
+ */
+export async function getExtensionTransports(): Promise<TransportInfo[]> {
+  return invoke("get_extension_transports");
+}
+
+/**
+ * Generate a message from a template an extension contributes.
+ *
+ * Called when the user chooses an extension template under "New from
+ * Template"; the returned message is opened in the editor.
+ *
+ * @param template - The template chosen
+ * @returns The generated message
+ */
+export async function generateExtensionTemplate(
+  template: TemplateInfo,
+): Promise<string> {
+  return invoke("generate_extension_template", {
+    extensionId: template.extensionId,
+    command: template.command,
+  });
+}
+
+/**
+ * An extension offered by a registry.
+ *
+ * Mirrors the Rust `AvailableExtension` from `commands/extensions/registry.rs`.
+ */
+export interface AvailableExtension {
+  id: string;
+  name: string;
+  version: string;
+  description: string | null;
+  homepage: string | null;
+
+  /** Version installed, if the extension is installed. */
+  installedVersion: string | null;
+
+  /** Whether the registry has a download for this platform. */
+  supported: boolean;
+}
+
+/**
+ * An extension installed from a registry.
+ *
+ * Mirrors the Rust `InstalledExtension` from `commands/extensions/registry.rs`.
+ */
+export interface InstalledExtension {
+  id: string;
+  name: string;
+  version: string;
+
+  /** Index the extension was installed from, for updates. */
+  indexUrl: string;
+
+  /** SHA-256 of the installed file. */
+  sha256: string;
+
+  /** Configuration to add to the settings to run the extension. */
+  config: ExtensionConfig;
+}
+
+/**
+ * List the extensions a registry offers.
+ *
+ * @param indexUrl - HTTPS URL of the registry's index
+ */
+export async function listAvailableExtensions(
+  indexUrl: string,
+): Promise<AvailableExtension[]> {
+  return invoke("list_available_extensions", { indexUrl });
+}
+
+/** List the extensions installed from registries. */
+export async function listInstalledExtensions(): Promise<InstalledExtension[]> {
+  return invoke("list_installed_extensions");
+}
+
+/**
+ * Download, verify, and install an extension from a registry.
+ *
+ * The returned configuration still has to be added to the settings.
+ *
+ * @param indexUrl - HTTPS URL of the registry's index
+ * @param extensionId - ID of the extension in the index
+ */
+export async function installExtension(
+  indexUrl: string,
+  extensionId: string,
+): Promise<InstalledExtension> {
+  return invoke("install_extension", { indexUrl, extensionId });
+}
+
+/**
+ * Update an installed extension from the registry it came from.
+ *
+ * @returns The updated extension, or null if it was already up to date
+ */
+export async function updateExtension(
+  extensionId: string,
+): Promise<InstalledExtension | null> {
+  return invoke("update_extension", { extensionId });
+}
+
+/**
+ * Uninstall an extension installed from a registry, deleting its files.
+ *
+ * @returns The removed extension, whose configuration is to be removed from
+ *   the settings
+ */
+export async function uninstallExtension(
+  extensionId: string,
+): Promise<InstalledExtension> {
+  return invoke("uninstall_extension", { extensionId });
+}
+
+/**
+ * Change the values of an extension's settings.
+ *
+ * The extension is sent the new values in a `configuration/changed`
+ * notification if it's running.
+ *
+ * @param extensionId - ID of the extension
+ * @param settings - Every value set, by key; null clears a setting
+ * @returns The values as stored, to save in the extension's configuration
+ */
+export async function updateExtensionSettings(
+  extensionId: string,
+  settings: Record<string, unknown>,
+): Promise<Record<string, unknown>> {
+  return invoke("update_extension_settings", { extensionId, settings });
+}
+
 /**
  * Send a command to an extension.
  *
@@ -178,14 +533,49 @@ export async function sendExtensionCommand(command: string): Promise<void> {
 /**
  * Get log entries for a specific extension.
  *
- * Returns the recent log entries (up to 100) for the specified extension.
+ * Returns a page of the extension's recent log entries, newest first. The
+ * number of entries kept is set by the extension's `logCapacity`.
  *
  * @param extensionId - ID of the extension to get logs for
+ * @param query - Level to filter by, and the page to get
  */
 export async function getExtensionLogs(
   extensionId: string,
-): Promise<ExtensionLog[]> {
-  return invoke("get_extension_logs", { extensionId });
+  query: ExtensionLogQuery = {},
+): Promise<ExtensionLogPage> {
+  return invoke("get_extension_logs", { extensionId, ...query });
+}
+
+/**
+ * Get the protocol trace of an extension in development mode.
+ *
+ * Returns the latest messages (up to 1000) exchanged with the extension,
+ * oldest first; later messages arrive in `extension-trace` events. Fails if
+ * the extension isn't in development mode.
+ *
+ * @param extensionId - ID of the extension to get the trace of
+ */
+export async function getExtensionTrace(
+  extensionId: string,
+): Promise<ExtensionTraceEntry[]> {
+  const entries: Omit<ExtensionTraceEntry, "extensionId">[] = await invoke(
+    "get_extension_trace",
+    { extensionId },
+  );
+  return entries.map((entry) => ({ ...entry, extensionId }));
+}
+
+/**
+ * Run the conformance suite against an extension executable.
+ *
+ * Spawns the extension separately from the running extensions, checks how it
+ * handles the initialize handshake, malformed params, unknown methods, and
+ * shutdown, and returns a report of each check.
+ *
+ * @param path - Extension command, as entered in the extension settings
+ */
+export async function testExtension(path: string): Promise<ConformanceReport> {
+  return invoke("test_extension", { path });
 }
 
 /**
@@ -194,22 +584,28 @@ export async function getExtensionLogs(
  * Called whenever the message changes to keep the backend in sync for extension
  * access. Optionally includes event information for opened/saved notifications.
  *
+ * @param documentId - The document the message belongs to
  * @param message - Current message content
  * @param filePath - Current file path (null for unsaved messages)
- * @param event - Optional event type (opened or saved)
+ * @param event - Optional event type (opened, saved, or segments changed)
  */
 export async function syncEditorMessage(
+  documentId: number,
   message: string,
   filePath: string | null,
   event?: MessageEvent,
 ): Promise<void> {
   // convert frontend event format to backend format
-  const eventParam = event
-    ? event.type === "opened"
-      ? { opened: { is_new: event.isNew } }
-      : { saved: { save_as: event.saveAs } }
-    : null;
+  let eventParam = null;
+  if (event?.type === "opened") {
+    eventParam = { opened: { is_new: event.isNew } };
+  } else if (event?.type === "saved") {
+    eventParam = { saved: { save_as: event.saveAs } };
+  } else if (event?.type === "segmentsChanged") {
+    eventParam = { segments_changed: { changes: event.changes } };
+  }
   return invoke("sync_editor_message", {
+    documentId,
     message,
     filePath,
     event: eventParam,
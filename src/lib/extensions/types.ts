@@ -82,6 +82,12 @@ export interface Patch {
 
   /** Create a new segment (path must be segment name only, e.g., "NK1"). */
   create?: boolean;
+
+  /** Copy a segment to just after itself (path must be segment name only, e.g., "OBX[2]"). */
+  duplicate?: boolean;
+
+  /** Move a segment to where another segment is now, e.g., "OBX[1]" (path must be segment name only). */
+  moveTo?: string;
 }
 
 /**
@@ -0,0 +1,90 @@
+/**
+ * Bridge module for running declarative batch jobs.
+ *
+ * A job file describes a batch of work (validate a folder of messages, convert
+ * them to another format, write a report). The backend runs the job and emits
+ * `job-progress` events while it works, and a `job-finished` summary once done.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import type { UnlistenFn } from "@tauri-apps/api/event";
+import { listenEvent } from "$lib/shared/events";
+import type { ValidationIssue, ValidationSummary } from "$lib/validation/validate";
+
+/**
+ * Progress payload emitted after each file is processed.
+ */
+export interface JobProgress {
+  /** Number of files processed so far (1-based) */
+  current: number;
+  /** Total number of files the job will process */
+  total: number;
+  /** Path of the file that was just processed */
+  file: string;
+}
+
+/**
+ * Outcome of processing a single message file.
+ */
+export interface JobFileResult {
+  /** Path of the input file */
+  file: string;
+  /** Validation summary counts (null if the file couldn't be read) */
+  summary: ValidationSummary | null;
+  /** Validation issues found in the message */
+  issues: ValidationIssue[];
+  /** Path of the converted output, if the message was converted */
+  output: string | null;
+  /** Error that stopped this file from being fully processed */
+  error: string | null;
+}
+
+/**
+ * Final report for a job run.
+ */
+export interface JobReport {
+  /** Name of the job, from the job file */
+  name: string | null;
+  /** Per-file results, in processing order */
+  files: JobFileResult[];
+  /** Number of files processed */
+  total: number;
+  /** Number of files with no errors */
+  passed: number;
+  /** Number of files with errors */
+  failed: number;
+  /** Number of files converted */
+  converted: number;
+  /** Where the report was written, if the job has a report step */
+  written: string | null;
+}
+
+/**
+ * Summary emitted as `job-finished` once a job has run.
+ *
+ * Mirrors the Rust `JobSummary` struct from
+ * `src-tauri/src/commands/jobs/runner.rs`.
+ */
+export type JobSummary = Omit<JobReport, "files">;
+
+/**
+ * Run a job file.
+ *
+ * @param path - Path to the job file (TOML)
+ * @returns The final report once every file has been processed
+ */
+export async function runJob(path: string): Promise<JobReport> {
+  return await invoke("run_job", { path });
+}
+
+/**
+ * Listen for job progress events.
+ *
+ * @param callback - Called after each file is processed
+ * @returns Function to stop listening
+ */
+export async function listenToJobProgress(
+  callback: (progress: JobProgress) => void,
+): Promise<UnlistenFn> {
+  return await listenEvent("job-progress", (event) => callback(event.payload));
+}
@@ -40,10 +40,20 @@ import type { SegmentSchema } from "./schema";
  * Fields are accessed by their hierarchical path string (e.g., "PID.3" for
  * patient ID, "PID.5.1" for patient last name). This flat structure simplifies
  * form binding in the UI compared to nested objects.
+ *
+ * Repeating fields (e.g. several identifiers in PID.3, separated by `~`) have
+ * one set of paths per repeat: the first repeat uses the plain path, later
+ * repeats include the repeat number ("PID.3[2]", "PID.5[2].1").
  */
 export interface SegmentData {
   /** Map of field paths to values. Null means the field is empty. */
   fields: Record<string, string | null>;
+  /**
+   * Number of repeats of each repeating field, keyed by field number. Fields
+   * not listed have a single repeat; when rendering, repeats beyond the count
+   * are removed and missing ones are added.
+   */
+  repeats?: Record<string, number>;
 }
 
 /**
@@ -191,6 +201,22 @@ export async function generateControlId(
   return await invoke("generate_control_id", { message });
 }
 
+/**
+ * Replaces every `{=expression}` formula in a message with its result.
+ *
+ * Formulas can refer to other fields (`PID.5.1`), join and slice text, shift
+ * timestamps (`MSH.7 - 2h`), and generate check digits. They are also
+ * evaluated automatically when a template is generated and when a message is
+ * sent; this applies them in the editor so the results can be reviewed.
+ *
+ * @param message - Raw HL7 message string
+ * @returns The message with formulas evaluated
+ * @throws If a formula is malformed or can't be evaluated
+ */
+export async function evaluateFormulas(message: string): Promise<string> {
+  return await invoke("evaluate_formulas", { message });
+}
+
 /**
  * Creates a SegmentData structure with all fields set to null.
  *
@@ -239,7 +265,7 @@ export interface CursorRange {
  * between segments, or if the message can't be parsed).
  *
  * @param message - Raw HL7 message string
- * @param cursor - Cursor position (character offset)
+ * @param cursor - Cursor position (UTF-16 offset, as the textarea counts)
  * @returns Range of the cell containing the cursor, or null if not in a valid cell
  */
 export async function getCurrentCellRange(
@@ -249,6 +275,33 @@ export async function getCurrentCellRange(
   return await invoke("get_current_cell_range", { message, cursor });
 }
 
+/**
+ * Gets the path of the cell at the cursor (e.g., "PID.5.1").
+ *
+ * Uses the same cell as getCurrentCellRange. Segment occurrences and field
+ * repeats are only numbered past the first (e.g., "OBX[2].5", "PID.3[2]").
+ *
+ * @param message - Raw HL7 message string
+ * @param cursor - Cursor position (UTF-16 offset, as the textarea counts)
+ * @returns Path of the cell containing the cursor, or null if not in a valid cell
+ */
+export async function getCellPath(message: string, cursor: number): Promise<string | null> {
+  return await invoke("get_cell_path", { message, cursor });
+}
+
+/**
+ * Gets the value of the cell at the cursor, with escape sequences decoded.
+ *
+ * Uses the same cell as getCurrentCellRange.
+ *
+ * @param message - Raw HL7 message string
+ * @param cursor - Cursor position (UTF-16 offset, as the textarea counts)
+ * @returns Decoded value of the cell containing the cursor, or null if not in a valid cell
+ */
+export async function getCellValue(message: string, cursor: number): Promise<string | null> {
+  return await invoke("get_cell_value", { message, cursor });
+}
+
 /**
  * Generates a current timestamp in HL7 DTM format.
  *
@@ -351,18 +404,71 @@ export async function parseHl7Timestamp(
  * - `oru_r01` for Observation Result messages
  * - `orr_o02` for Order Response messages
  * - `dft_p03` for Financial Transaction messages
+ * - `mdm_t01`, `mdm_t02` for Medical Document Management messages
+ * - `siu_s12`, ..., `siu_s26` for Scheduling Information messages
+ * - `vxu_v04` for Vaccination Record Update messages
+ * - `qbp_q22`, `rsp_k22` for Query by Parameter messages and their responses
+ * - `qry_a19`, `adr_a19` for original mode patient queries and their responses
  *
  * The generated message includes:
  * - MSH segment with message type/trigger event pre-filled
  * - All segments defined in the schema for that message type
  * - Empty field structures (minimal placeholders)
  *
+ * Placeholders such as `{now-2h}`, `{seq}`, or `{uuid}` are filled in, along
+ * with any user-defined variables; `{auto}` is left for send time.
+ *
  * @param templateName - Template identifier (e.g., "adt_a01", "orm_o01")
+ * @param variables - Values of user-defined `{name}` placeholders
  * @returns The generated HL7 message string
- * @throws If template not found or schema loading fails
+ * @throws If template not found, schema loading fails, or a placeholder or
+ *   formula can't be evaluated
  */
 export async function generateTemplateMessage(
   templateName: string,
+  variables: Record<string, string> = {},
 ): Promise<string> {
-  return await invoke("generate_template_message", { templateName });
+  return await invoke("generate_template_message", { templateName, variables });
+}
+
+/**
+ * Messages recovered from an HL7 batch file, with any envelope problems found.
+ */
+export interface UnwrappedBatch {
+  /** Messages in file order, without envelope segments */
+  messages: string[];
+  /** Number of BHS/BTS batches in the file */
+  batchCount: number;
+  /** Whether the file has an FHS/FTS file envelope */
+  hasFileHeader: boolean;
+  /** Problems with the envelope (missing trailers, wrong counts, etc.) */
+  issues: string[];
+}
+
+/**
+ * Wraps one or more messages in BHS/BTS (and optionally FHS/FTS) segments.
+ *
+ * Header fields are copied from the first message's MSH and trailer counts are
+ * filled in. Existing envelope segments are replaced.
+ *
+ * @param content - One or more messages
+ * @param fileHeader - Whether to also add FHS/FTS segments
+ * @returns The wrapped batch
+ * @throws If the content contains no messages
+ */
+export async function wrapBatch(
+  content: string,
+  fileHeader: boolean,
+): Promise<string> {
+  return await invoke("wrap_batch", { content, fileHeader });
+}
+
+/**
+ * Unwraps an HL7 batch file into its messages, validating the envelope.
+ *
+ * @param content - Raw batch file content
+ * @returns The messages, envelope details, and any problems found
+ */
+export async function unwrapBatch(content: string): Promise<UnwrappedBatch> {
+  return await invoke("unwrap_batch", { content });
 }
@@ -0,0 +1,149 @@
+/**
+ * Bridge module for the data folder, where all persisted stores live.
+ *
+ * The backend resolves the data folder once at startup: the `HERMES_DATA_ROOT`
+ * environment variable, then a `hermes-data` folder next to the executable
+ * (portable mode), then a folder chosen in the settings, then the platform's
+ * app data directory.
+ *
+ * ## Switching Folders
+ *
+ * `setDataRoot()` records the new folder and emits "data-root-changed". The
+ * Settings object listens for that event and reloads itself from the new
+ * folder, so nothing needs restarting.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import { join } from "@tauri-apps/api/path";
+
+/**
+ * Where the data folder came from.
+ *
+ * - `default`: the platform's app data directory
+ * - `environment`: the `HERMES_DATA_ROOT` environment variable
+ * - `portable`: a `hermes-data` folder next to the executable
+ * - `configured`: a folder chosen in the settings
+ */
+export type DataRootSource = "default" | "environment" | "portable" | "configured";
+
+/**
+ * The folder holding all persisted data.
+ *
+ * Mirrors the Rust `DataRoot` struct from `support/data_root.rs`.
+ */
+export interface DataRoot {
+  path: string;
+  source: DataRootSource;
+}
+
+/**
+ * Gets the current data folder.
+ *
+ * @returns The folder and where it came from
+ */
+export async function getDataRoot(): Promise<DataRoot> {
+  return await invoke<DataRoot>("get_data_root");
+}
+
+/**
+ * Moves persisted data to a different folder, or back to the default.
+ *
+ * Only possible when the folder isn't fixed by the environment variable or
+ * portable mode (see `canChangeDataRoot`).
+ *
+ * @param path - Absolute path of the new folder, or null for the default
+ * @param copyExisting - Copy the current stores into the new folder, keeping any already there
+ * @returns The new data folder
+ * @throws Error string if the folder is fixed or can't be written to
+ */
+export async function setDataRoot(
+  path: string | null,
+  copyExisting: boolean,
+): Promise<DataRoot> {
+  return await invoke<DataRoot>("set_data_root", { path, copyExisting });
+}
+
+/**
+ * Whether the data folder can be changed from inside the app.
+ *
+ * @param root - Current data folder
+ */
+export function canChangeDataRoot(root: DataRoot): boolean {
+  return root.source === "default" || root.source === "configured";
+}
+
+/**
+ * Resolves the path of a store file in the data folder.
+ *
+ * @param root - Data folder to resolve against
+ * @param name - Store file name, e.g. "settings.json"
+ * @returns Absolute path of the store file
+ */
+export async function storePath(root: DataRoot, name: string): Promise<string> {
+  return await join(root.path, name);
+}
+
+/**
+ * What was done about a store that failed the startup integrity check.
+ *
+ * - `restored`: quarantined, and its last-known-good backup put back
+ * - `quarantined`: quarantined with no usable backup, so it starts empty
+ * - `removed`: a partial file from an interrupted write was deleted
+ */
+export type StoreRepair = "restored" | "quarantined" | "removed";
+
+/**
+ * A problem found with a persisted store.
+ *
+ * Mirrors the Rust `StoreIssue` struct from `support/store_health.rs`.
+ */
+export interface StoreIssue {
+  /** Path of the store, relative to the data folder */
+  store: string;
+  /** What was wrong with it */
+  problem: string;
+  repair: StoreRepair;
+  /** Where the corrupt store was moved to, if it was quarantined */
+  quarantined_to: string | null;
+}
+
+/**
+ * Result of the integrity check of the stores in the data folder.
+ *
+ * Mirrors the Rust `StartupHealth` struct from `support/store_health.rs`.
+ */
+export interface StartupHealth {
+  /** Stores checked, relative to the data folder */
+  checked: string[];
+  /** Problems found and repaired */
+  issues: StoreIssue[];
+}
+
+/**
+ * Gets the findings of the integrity check run on the data folder at startup,
+ * or when the folder was last switched.
+ */
+export async function getStartupHealth(): Promise<StartupHealth> {
+  return await invoke<StartupHealth>("get_startup_health");
+}
+
+/**
+ * Describes the repairs made to the stores, for showing to the user.
+ *
+ * @param health - Findings of the integrity check
+ * @returns One line per repaired store
+ */
+export function describeStoreRepairs(health: StartupHealth): string {
+  return health.issues
+    .map((issue) => {
+      switch (issue.repair) {
+        case "restored":
+          return `${issue.store} was damaged (${issue.problem}) and has been restored from its backup.`;
+        case "quarantined":
+          return `${issue.store} was damaged (${issue.problem}) and has been reset. The damaged copy was moved to ${issue.quarantined_to}.`;
+        case "removed":
+          return `${issue.store}: removed a partial file left by an interrupted write.`;
+      }
+    })
+    .join("\n\n");
+}
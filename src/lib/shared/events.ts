@@ -0,0 +1,142 @@
+// Generated from src-tauri/src/events.rs; do not edit by hand.
+// Regenerate with `HERMES_UPDATE_EVENTS=1 cargo test events` in src-tauri.
+
+/**
+ * Names and payload types of the events the backend emits.
+ *
+ * Listen with `listenEvent` rather than `listen`, so a misspelt event name
+ * or a payload of the wrong type is a type error.
+ */
+
+import {
+  listen,
+  type EventCallback,
+  type UnlistenFn,
+} from "@tauri-apps/api/event";
+import type { ListenerConnectionEvent, ListenerStarted, ReceivedMessage } from "$lib/communication/listen";
+import type { RelayTraffic } from "$lib/communication/relay";
+import type { ScheduleFinished, ScheduleProgress } from "$lib/communication/schedule";
+import type { SendResponse } from "$lib/communication/send_receive";
+import type { ExternalChange } from "$lib/editor/file_watch";
+import type { SegmentEventTarget } from "$lib/editor/segment";
+import type { ExtensionProgress, ExtensionStatus, ExtensionStatusBarItem, ExtensionTraceEntry, TemplateInfo } from "$lib/extensions/extensions";
+import type { JobProgress, JobSummary } from "$lib/jobs/jobs";
+import type { DataRoot } from "$lib/shared/data_root";
+import type { SchemaReload } from "$lib/shared/schema";
+import type { ShutdownProgress } from "$lib/shared/shutdown";
+import type { ScheduledValidation, ValidationProgress } from "$lib/validation/validate";
+
+/** Payload of each event the backend emits, by event name. */
+export interface EventPayloads {
+  "received-message": ReceivedMessage;
+  "listener-connection": ListenerConnectionEvent;
+  "listener-started": ListenerStarted;
+  "relay-traffic": RelayTraffic;
+  "send-log": string;
+  "send-response": SendResponse;
+  "schedule-progress": ScheduleProgress;
+  "schedule-finished": ScheduleFinished;
+  "job-progress": JobProgress;
+  "job-finished": JobSummary;
+  "validation-result": ScheduledValidation;
+  "validation-progress": ValidationProgress;
+  "file-changed-externally": ExternalChange;
+  "schema-reloaded": SchemaReload;
+  "data-root-changed": DataRoot;
+  "shutdown-progress": ShutdownProgress;
+  "shutdown-save-session": null;
+  "extensions-changed": null;
+  "extension-status-changed": ExtensionStatus;
+  "extension-set-message": string;
+  "extension-window-closed": [string, { windowId: string; reason: string }];
+  "extension-status-bar-item": ExtensionStatusBarItem;
+  "extension-progress": ExtensionProgress;
+  "extension-trace": ExtensionTraceEntry;
+  "menu-open-recent": string;
+  "menu-new-from-template": string;
+  "menu-new-from-user-template": string;
+  "menu-new-from-extension-template": TemplateInfo;
+  "menu-open-sample": string;
+  "menu-tools-convert-name": string;
+  "menu-edit-delete-segment": SegmentEventTarget;
+  "menu-edit-move-segment-up": SegmentEventTarget;
+  "menu-edit-move-segment-down": SegmentEventTarget;
+  "menu-edit-duplicate-segment": SegmentEventTarget;
+  "menu-file-new": null;
+  "menu-file-open": null;
+  "menu-file-save": null;
+  "menu-file-save-as": null;
+  "menu-file-export-json": null;
+  "menu-file-export-yaml": null;
+  "menu-file-export-toml": null;
+  "menu-file-export-xml": null;
+  "menu-file-export-fhir": null;
+  "menu-file-import-json": null;
+  "menu-file-import-yaml": null;
+  "menu-file-import-toml": null;
+  "menu-file-import-xml": null;
+  "menu-file-import-fhir": null;
+  "menu-file-auto-save": null;
+  "menu-file-template-library": null;
+  "menu-edit-undo": null;
+  "menu-edit-redo": null;
+  "menu-edit-find": null;
+  "menu-edit-find-replace": null;
+  "menu-edit-jump-to-field": null;
+  "menu-edit-insert-snippet": null;
+  "menu-edit-attach-document": null;
+  "menu-edit-paste-escaped": null;
+  "menu-edit-encode-escapes": null;
+  "menu-edit-decode-escapes": null;
+  "menu-edit-copy-field-path": null;
+  "menu-edit-copy-field-value": null;
+  "menu-view-zoom-in": null;
+  "menu-view-zoom-out": null;
+  "menu-view-reset-zoom": null;
+  "menu-view-keyboard-shortcuts": null;
+  "menu-tools-send": null;
+  "menu-tools-listen": null;
+  "menu-tools-connection-profiles": null;
+  "menu-tools-forget-control-ids": null;
+  "menu-tools-validate": null;
+  "menu-tools-compare": null;
+  "menu-tools-compare-segments": null;
+  "menu-tools-match-expectation": null;
+  "menu-tools-phi-report": null;
+  "menu-tools-run-job": null;
+  "menu-tools-script-console": null;
+  "menu-tools-pipelines": null;
+  "menu-tools-mapping-tables": null;
+  "menu-tools-extract-messages": null;
+  "menu-tools-import-capture": null;
+  "menu-tools-test-cases": null;
+  "menu-tools-adt-workflow": null;
+  "menu-tools-result-block": null;
+  "menu-tools-query-builder": null;
+  "menu-tools-generate-control-id": null;
+  "menu-tools-evaluate-formulas": null;
+  "menu-tools-embedded-documents": null;
+  "menu-tools-wrap-batch": null;
+  "menu-tools-unwrap-batch": null;
+  "menu-tools-insert-timestamp-now": null;
+  "menu-tools-insert-timestamp": null;
+  "menu-clear-recent": null;
+  "menu-help": null;
+}
+
+/** Name of an event the backend emits. */
+export type EventName = keyof EventPayloads;
+
+/**
+ * Listens for a backend event, with its payload typed from `EventPayloads`.
+ *
+ * @param event - Name of the event
+ * @param handler - Called with each occurrence of the event
+ * @returns Function to stop listening
+ */
+export function listenEvent<K extends EventName>(
+  event: K,
+  handler: EventCallback<EventPayloads[K]>,
+): Promise<UnlistenFn> {
+  return listen<EventPayloads[K]>(event, handler);
+}
@@ -0,0 +1,63 @@
+/**
+ * Bridge module for the built-in sample gallery.
+ *
+ * The gallery is a set of curated, fictional messages embedded in the app, so
+ * Hermes can be explored without real messages: common message types, edge
+ * cases, and messages with deliberate mistakes for learning validation. It's
+ * also listed in the Help menu, which emits "menu-open-sample" with the id.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * What a sample is meant to show.
+ *
+ * - `example`: a well-formed message of a common type
+ * - `edge-case`: a valid message exercising a less common part of the standard
+ * - `broken`: a message with deliberate mistakes, for learning validation
+ */
+export type SampleCategory = "example" | "edge-case" | "broken";
+
+/**
+ * A sample as listed in the gallery, without its message.
+ *
+ * Mirrors the Rust `SampleInfo` struct from `support/samples.rs`.
+ */
+export interface SampleInfo {
+  /** Identifier used to load the sample (e.g., "adt-a01-admit") */
+  id: string;
+  /** Display name */
+  name: string;
+  /** What the sample is meant to show */
+  category: SampleCategory;
+  /** What the sample contains and what to try with it */
+  description: string;
+}
+
+/**
+ * A sample and its message.
+ *
+ * Mirrors the Rust `Sample` struct from `support/samples.rs`.
+ */
+export interface Sample extends SampleInfo {
+  /** The HL7 message, one segment per line */
+  message: string;
+}
+
+/**
+ * Lists the samples in the gallery, in gallery order.
+ */
+export async function listSamples(): Promise<SampleInfo[]> {
+  return invoke<SampleInfo[]>("list_samples");
+}
+
+/**
+ * Loads a sample from the gallery.
+ *
+ * @param id - Identifier of the sample
+ * @returns The sample and its message
+ * @throws Error string if no sample has that identifier
+ */
+export async function loadSample(id: string): Promise<Sample> {
+  return invoke<Sample>("load_sample", { id });
+}
@@ -45,6 +45,10 @@ export interface SegmentMetadata {
   name: string;
   /** Whether this segment is required for the message type */
   required?: boolean;
+  /** Whether this segment may appear several times in a row */
+  repeating?: boolean;
+  /** Repeating group the segment belongs to (e.g., "order/observation") */
+  group?: string;
 }
 
 /**
@@ -67,6 +71,21 @@ export enum DataType {
   DateTime = "datetime",
 }
 
+/**
+ * Condition on another field that a conditional field rule depends on.
+ *
+ * Holds when the field equals `equals` and is one of `one_of`, or, with
+ * neither set, when the field is populated.
+ */
+export interface FieldCondition {
+  /** Field path to test (e.g., "OBX.2", "MSH.9.2") */
+  path: string;
+  /** Value the field must equal */
+  equals?: string;
+  /** Values the field must be one of */
+  one_of?: string[];
+}
+
 /**
  * Schema definition for a single field or component within a segment.
  *
@@ -92,6 +111,10 @@ export interface Field {
   placeholder?: string;
   /** Whether this field is required (validation and UI indication) */
   required?: boolean;
+  /** Condition under which the field is required, if only sometimes required */
+  required_if?: FieldCondition;
+  /** Condition without which the field must be left empty */
+  forbidden_unless?: FieldCondition;
   /** Special data type requiring custom UI handling */
   datatype?: DataType;
   /** Regex pattern for validation */
@@ -100,6 +123,10 @@ export interface Field {
   note?: string;
   /** Predefined values for dropdown/select UI (value -> display label) */
   values?: Record<string, string>;
+  /** HL7 table the values come from (e.g., "0001"), if the field is bound to one */
+  table?: string;
+  /** Whether the field carries protected health information, if classified */
+  classification?: "phi" | "not-phi";
 }
 
 /**
@@ -119,6 +146,32 @@ export const fieldId = (segment: string, field: Field): string => {
   );
 };
 
+/**
+ * Generates the field path string for one repeat of a field.
+ *
+ * The first repeat uses the plain path from `fieldId`; later repeats put the
+ * repeat number after the field number, matching the query syntax and the
+ * keys the backend uses in SegmentData.fields.
+ *
+ * @param segment - Segment name (e.g., "PID")
+ * @param field - Field schema
+ * @param repeat - Repeat number, starting at 1
+ * @returns Field path string (e.g., "PID.3", "PID.3[2]" or "PID.5[2].1")
+ */
+export const repeatFieldId = (
+  segment: string,
+  field: Field,
+  repeat: number,
+): string => {
+  if (repeat <= 1) {
+    return fieldId(segment, field);
+  }
+  return (
+    `${segment}.${field.field}[${repeat}]` +
+    (Number.isFinite(field.component) ? `.${field.component}` : "")
+  );
+};
+
 /**
  * Array of field schemas defining all fields in a segment.
  */
@@ -153,13 +206,15 @@ export async function getMessagesSchema(): Promise<MessagesSchema> {
  * schemas at startup. This lazy loading reduces initial load time and memory usage.
  *
  * @param segment - Segment name (e.g., "PID", "ORC")
+ * @param version - HL7 version declared by the message (MSH.12), if known
  * @returns Array of field definitions for the segment
  */
 export async function getSegmentSchema(
   segment: string,
+  version?: string,
 ): Promise<SegmentSchema> {
   try {
-    return await invoke<SegmentSchema>("get_segment_schema", { segment });
+    return await invoke<SegmentSchema>("get_segment_schema", { segment, version });
   } catch (error) {
     console.error(`Error getting segment {segment} schema:`, error);
     throw error;
@@ -179,7 +234,7 @@ export async function getAllSegmentSchemas(): Promise<SegmentSchemas> {
   return getMessagesSchema().then(async (schema) => {
     const segments = Object.keys(schema.segments);
     console.debug("Segments to fetch:", segments);
-    const schemas = await Promise.all(segments.map(getSegmentSchema));
+    const schemas = await Promise.all(segments.map((segment) => getSegmentSchema(segment)));
     console.debug("All segment schemas:", schemas);
     // Transform array of schemas into lookup map
     return schemas.reduce((acc, schema, index) => {
@@ -189,3 +244,283 @@ export async function getAllSegmentSchemas(): Promise<SegmentSchemas> {
     }, {} as SegmentSchemas);
   });
 }
+
+/**
+ * Who defines an HL7 table's values: the standard, or each site.
+ */
+export type TableKind = "hl7" | "user";
+
+/**
+ * An HL7 table as listed, without its values.
+ *
+ * Mirrors the Rust `TableSummary` struct from
+ * `src-tauri/src/commands/support/schema.rs`.
+ */
+export interface TableSummary {
+  /** Table number (e.g., "0001") */
+  id: string;
+  /** Table name (e.g., "Administrative Sex") */
+  name: string;
+  /** Who defines the table's values */
+  kind: TableKind;
+}
+
+/**
+ * An HL7 table of coded values.
+ *
+ * Mirrors the Rust `Table` struct from `src-tauri/src/schema/table.rs`.
+ */
+export interface Table extends TableSummary {
+  /** Codes and their descriptions, in table order */
+  values: Record<string, string>;
+}
+
+/**
+ * Lists the HL7 tables fields can be bound to.
+ *
+ * @returns Each table's number, name, and kind
+ */
+export async function listTables(): Promise<TableSummary[]> {
+  return invoke<TableSummary[]>("list_tables");
+}
+
+/**
+ * Retrieves an HL7 table and its values, e.g. to populate a dropdown.
+ *
+ * Fields bound to a table already carry its values in their schema; this is
+ * for fields the schema doesn't cover and for browsing a table.
+ *
+ * @param table - Table number, with or without leading zeros (e.g., "0001" or "1")
+ * @returns The table with its codes and descriptions
+ * @throws Error string if there's no table with that number
+ */
+export async function getTable(table: string): Promise<Table> {
+  return invoke<Table>("get_table", { table });
+}
+
+/**
+ * HL7 versions available for schema selection.
+ */
+export interface SchemaVersions {
+  /** Versions with schema data, oldest first */
+  supported: string[];
+  /** Version used when a message doesn't declare a supported version */
+  default: string;
+  /** Version pinned by the user, or null when auto-detecting from MSH.12 */
+  selected: string | null;
+}
+
+/**
+ * Lists the HL7 versions the backend has schemas for.
+ *
+ * @returns Supported, default, and currently selected versions
+ */
+export async function getSchemaVersions(): Promise<SchemaVersions> {
+  return await invoke("get_schema_versions");
+}
+
+/**
+ * Pins the HL7 version used for schemas, validation, and field descriptions.
+ *
+ * @param version - Version to use, or null to auto-detect from each message's MSH.12
+ */
+export async function setSchemaVersion(version: string | null): Promise<void> {
+  await invoke("set_schema_version", { version });
+}
+
+/**
+ * Detects which supported HL7 version a message declares in MSH.12.
+ *
+ * @param message - Raw HL7 message
+ * @returns The matching supported version, or null if missing or unrecognised
+ */
+export async function detectMessageVersion(message: string): Promise<string | null> {
+  return await invoke("detect_message_version", { message });
+}
+
+/**
+ * Schema of a segment defined by the user, such as a site's Z-segments.
+ *
+ * Mirrors the Rust `CustomSegment` struct from
+ * `src-tauri/src/schema/custom.rs`.
+ */
+export interface CustomSegment {
+  /** Segment name: Z followed by two letters or digits (e.g., "ZPI") */
+  name: string;
+  /** What the segment holds (e.g., "Patient Insurance Extension") */
+  description: string;
+  /** Field and component definitions, as for embedded segments */
+  fields: Field[];
+}
+
+/**
+ * Lists the custom segments, sorted by name.
+ *
+ * @returns The custom segments (empty if none have been defined)
+ */
+export async function listCustomSegments(): Promise<CustomSegment[]> {
+  return await invoke("list_custom_segments");
+}
+
+/**
+ * Defines a custom segment, replacing any earlier definition of it.
+ *
+ * The segment's schema is used straight away for segment tabs, field names,
+ * descriptions, and validation.
+ *
+ * @param segment - The segment's name, description, and fields
+ * @returns The custom segments after the change
+ * @throws Error string if the segment isn't valid or couldn't be saved
+ */
+export async function saveCustomSegment(segment: CustomSegment): Promise<CustomSegment[]> {
+  return await invoke("save_custom_segment", { segment });
+}
+
+/**
+ * Deletes a custom segment.
+ *
+ * @param name - Name of the segment (e.g., "ZPI")
+ * @returns The custom segments left
+ * @throws Error string if the segment isn't defined or couldn't be deleted
+ */
+export async function deleteCustomSegment(name: string): Promise<CustomSegment[]> {
+  return await invoke("delete_custom_segment", { name });
+}
+
+/**
+ * An override of one field or component's schema.
+ *
+ * A property left out keeps the built-in value, `null` removes it, and any
+ * other value replaces it.
+ *
+ * Mirrors the Rust `FieldOverride` struct from
+ * `src-tauri/src/extensions/types.rs`.
+ */
+export interface FieldOverride {
+  /** 1-based field number */
+  field: number;
+  /** 1-based component number, if overriding a component */
+  component?: number;
+  name?: string | null;
+  group?: string | null;
+  note?: string | null;
+  required?: boolean | null;
+  minlength?: number | null;
+  maxlength?: number | null;
+  pattern?: string | null;
+  datatype?: "date" | "datetime" | null;
+  placeholder?: string | null;
+  /** Allowed values (code to description), replacing the built-in ones */
+  values?: Record<string, string> | null;
+  table?: string | null;
+  template?: string | null;
+  classification?: "phi" | "not-phi" | null;
+}
+
+/**
+ * Overrides of the schema, in the format extensions use.
+ *
+ * Mirrors the Rust `SchemaOverride` struct from
+ * `src-tauri/src/extensions/types.rs`.
+ */
+export interface SchemaOverride {
+  /** Field overrides, keyed by segment name */
+  segments?: Record<string, { fields?: FieldOverride[] }>;
+}
+
+/**
+ * Gets the user's own schema overrides.
+ *
+ * These apply over the built-in schema and beneath extension overrides.
+ *
+ * @returns The overrides, with no segments if none have been set
+ */
+export async function getSchemaOverrides(): Promise<SchemaOverride> {
+  return await invoke("get_schema_overrides");
+}
+
+/**
+ * Replaces the user's own schema overrides.
+ *
+ * @param overrides - The new overrides; no segments clears them
+ * @throws Error string if an override isn't valid or couldn't be saved
+ */
+export async function setSchemaOverrides(overrides: SchemaOverride): Promise<void> {
+  await invoke("set_schema_overrides", { overrides });
+}
+
+/**
+ * Adds or replaces the user's override of one field or component.
+ *
+ * @param segment - Segment name (e.g., "PID")
+ * @param fieldOverride - The override, naming the field and component it applies to
+ * @returns The user's overrides after the change
+ * @throws Error string if the override isn't valid or couldn't be saved
+ */
+export async function setFieldOverride(
+  segment: string,
+  fieldOverride: FieldOverride,
+): Promise<SchemaOverride> {
+  return await invoke("set_field_override", { segment, fieldOverride });
+}
+
+/**
+ * Removes the user's override of one field or component.
+ *
+ * @param segment - Segment name (e.g., "PID")
+ * @param field - 1-based field number
+ * @param component - 1-based component number, or null for the whole field
+ * @returns The user's overrides after the change
+ */
+export async function removeFieldOverride(
+  segment: string,
+  field: number,
+  component: number | null = null,
+): Promise<SchemaOverride> {
+  return await invoke("remove_field_override", { segment, field, component });
+}
+
+/**
+ * The schema in use after it was reloaded.
+ *
+ * Mirrors the Rust `SchemaReload` struct from
+ * `src-tauri/src/commands/support/schema_directory.rs`.
+ */
+export interface SchemaReload {
+  /** Folder the schema files were loaded from, or null for the embedded schemas */
+  directory: string | null;
+  /** Files loaded from the folder, relative to it */
+  files: string[];
+}
+
+/**
+ * Gets the folder schema files are loaded from in place of the embedded ones.
+ *
+ * @returns The folder, or null if the embedded schemas are used
+ */
+export async function getSchemaDirectory(): Promise<string | null> {
+  return await invoke("get_schema_directory");
+}
+
+/**
+ * Sets the folder to load schema files from, and loads it.
+ *
+ * The folder is watched, and the schema reloaded whenever a file in it changes.
+ *
+ * @param path - Folder laid out like the app's `data/` folder, or null for the embedded schemas
+ * @returns The schema now in use
+ * @throws Error string if a file in the folder couldn't be loaded; the folder isn't saved
+ */
+export async function setSchemaDirectory(path: string | null): Promise<SchemaReload> {
+  return await invoke("set_schema_directory", { path });
+}
+
+/**
+ * Reloads the schema from the folder set with {@link setSchemaDirectory}.
+ *
+ * @returns The schema now in use
+ * @throws Error string if a file in the folder couldn't be loaded; the previous schema is kept
+ */
+export async function reloadSchema(): Promise<SchemaReload> {
+  return await invoke("reload_schema");
+}
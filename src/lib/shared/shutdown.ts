@@ -0,0 +1,85 @@
+/**
+ * Bridge module for the coordinated shutdown when the app closes.
+ *
+ * Closing the main window or quitting doesn't exit straight away: the backend
+ * stops the listener and scheduled sends, exports queued audit events, shuts
+ * extensions down, and writes settings to disk first, emitting
+ * "shutdown-progress" as it goes. Part way through it emits
+ * "shutdown-save-session" and waits (for up to half the timeout) for the
+ * frontend to answer with `sessionSaved()`.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import type { UnlistenFn } from "@tauri-apps/api/event";
+import { listenEvent } from "./events";
+
+/**
+ * Progress of the shutdown sequence.
+ *
+ * Mirrors the Rust `ShutdownProgress` struct from
+ * `src-tauri/src/commands/support/shutdown.rs`.
+ */
+export interface ShutdownProgress {
+  /** Stage now running, e.g. "flush_audit_events" */
+  stage: string;
+  /** What the stage is doing, e.g. "Exporting queued audit events" */
+  description: string;
+  /** Number of the stage, from 1 */
+  step: number;
+  /** Number of stages */
+  total: number;
+}
+
+/**
+ * Sets how long quitting waits for background work before exiting anyway.
+ *
+ * @param timeoutSeconds - The timeout, greater than 0 and at most 300 seconds
+ * @throws If the timeout is out of range
+ */
+export async function configureShutdown(timeoutSeconds: number): Promise<void> {
+  await invoke("configure_shutdown", { timeoutSeconds });
+}
+
+/**
+ * Tells the backend the session has been saved, so shutdown can carry on.
+ */
+export async function sessionSaved(): Promise<void> {
+  await invoke("session_saved");
+}
+
+/**
+ * Listens for the backend asking the frontend to save its session.
+ *
+ * `sessionSaved()` is called once the handler finishes, whether or not it
+ * succeeded, so a failed save doesn't hold shutdown up until the timeout.
+ *
+ * @param handler - Saves whatever would otherwise be lost on exit
+ * @returns Function to stop listening
+ */
+export async function onSaveSession(
+  handler: () => Promise<void>,
+): Promise<UnlistenFn> {
+  return listenEvent("shutdown-save-session", async () => {
+    try {
+      await handler();
+    } catch (error) {
+      console.error("Failed to save session on shutdown:", error);
+    } finally {
+      await sessionSaved();
+    }
+  });
+}
+
+/**
+ * Listens for progress through the shutdown sequence.
+ *
+ * @param handler - Called as each stage starts
+ * @returns Function to stop listening
+ */
+export async function onShutdownProgress(
+  handler: (progress: ShutdownProgress) => void,
+): Promise<UnlistenFn> {
+  return listenEvent("shutdown-progress", (event) =>
+    handler(event.payload),
+  );
+}
@@ -0,0 +1,113 @@
+/**
+ * Bridge module for interface test cases.
+ *
+ * A test case is a TOML or JSON file in the `test-cases` folder of the data
+ * folder describing an input message (inline, a file, or a user template), an
+ * optional pipeline to run on it, an optional host to send it to, and the
+ * message expected back, with placeholders where values vary. Running a test
+ * case checks the pipeline's output, or the response to the send, against the
+ * expectation (see `matchExpectation` in `$lib/diff/expectation`).
+ *
+ * Test cases are read from disk each time they're listed or run, so they can
+ * be edited alongside the interface they test.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import type { ExpectationMismatch } from "$lib/diff/expectation";
+
+/**
+ * A test case found in the test cases folder.
+ *
+ * Mirrors the Rust `TestCaseInfo` struct from
+ * `src-tauri/src/commands/testing/cases.rs`.
+ */
+export interface TestCaseInfo {
+  /** Identifier used to select the test case (file name without extension) */
+  id: string;
+  /** Name from the test case, or the identifier if it has none */
+  name: string;
+  /** Description from the test case, if any */
+  description: string | null;
+  /** Why the test case couldn't be loaded, if it couldn't */
+  error: string | null;
+}
+
+/**
+ * The test cases folder and the test cases in it.
+ */
+export interface TestCases {
+  /** Absolute path of the test cases folder */
+  directory: string;
+  /** Test cases found in the folder, sorted by identifier */
+  tests: TestCaseInfo[];
+}
+
+/**
+ * Outcome of running a test case.
+ */
+export interface TestCaseResult {
+  /** Identifier of the test case */
+  id: string;
+  /** Name of the test case */
+  name: string;
+  /** Whether the output met the expectation */
+  passed: boolean;
+  /** The message that was sent, if the test case sends one */
+  sent: string | null;
+  /** The message checked against the expectation, if the run got that far */
+  output: string | null;
+  /** The expectation, if it could be read */
+  expected: string | null;
+  /** Parts of the output that don't meet the expectation */
+  mismatches: ExpectationMismatch[];
+  /** Why the test case couldn't be run, if it couldn't */
+  error: string | null;
+  /** How long the run took, in milliseconds */
+  duration: number;
+}
+
+/**
+ * Outcome of running every test case.
+ */
+export interface TestRunReport {
+  /** Per-test results, sorted by identifier */
+  results: TestCaseResult[];
+  /** Number of test cases that passed */
+  passed: number;
+  /** Number of test cases that failed or couldn't be run */
+  failed: number;
+}
+
+/**
+ * Lists the test cases, creating the test cases folder if needed.
+ *
+ * @returns The test cases folder and the test cases in it, including any that
+ *   failed to load (with the reason)
+ * @throws Error string if the folder can't be created or read
+ */
+export async function listTestCases(): Promise<TestCases> {
+  return await invoke<TestCases>("list_test_cases");
+}
+
+/**
+ * Runs a test case.
+ *
+ * A test case that can't be run resolves as failed, with the reason in
+ * `error`, rather than rejecting.
+ *
+ * @param id - Identifier of the test case
+ * @returns The outcome of the run
+ */
+export async function runTestCase(id: string): Promise<TestCaseResult> {
+  return await invoke<TestCaseResult>("run_test_case", { id });
+}
+
+/**
+ * Runs every test case, one after another.
+ *
+ * @returns The outcome of each test case
+ * @throws Error string if the test cases folder can't be created or read
+ */
+export async function runAllTestCases(): Promise<TestRunReport> {
+  return await invoke<TestRunReport>("run_all_test_cases");
+}
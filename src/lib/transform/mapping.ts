@@ -0,0 +1,112 @@
+/**
+ * Bridge module for code mapping tables.
+ *
+ * A mapping table is a crosswalk from a sending system's local codes to the
+ * codes the receiving system expects. Tables are imported from CSV and kept in
+ * the `mapping-tables` folder of the data folder, normalised to `source`,
+ * `target`, and `description` columns. Pipelines translate values through them
+ * (`map` steps with a `table`), and validation profiles check that a value has
+ * an entry in one (rules with a `mapping_table`).
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * A single mapping from a local code to a standard one.
+ *
+ * Mirrors the Rust `MappingEntry` struct from
+ * `src-tauri/src/commands/transform/mapping.rs`.
+ */
+export interface MappingEntry {
+  /** Code as the sending system has it */
+  source: string;
+  /** Code it maps to */
+  target: string;
+  /** What the code means, if the table says */
+  description: string | null;
+}
+
+/**
+ * A mapping table and its entries.
+ */
+export interface MappingTable {
+  /** Identifier of the table (file name without extension) */
+  id: string;
+  /** Entries in the order they appear in the file */
+  entries: MappingEntry[];
+}
+
+/**
+ * A table found in the mapping tables folder.
+ */
+export interface MappingTableInfo {
+  /** Identifier of the table (file name without extension) */
+  id: string;
+  /** Number of entries in the table */
+  entries: number;
+  /** Why the table couldn't be loaded, if it couldn't */
+  error: string | null;
+}
+
+/**
+ * The mapping tables folder and the tables in it.
+ */
+export interface MappingTables {
+  /** Absolute path of the mapping tables folder */
+  directory: string;
+  /** Tables found in the folder, sorted by identifier */
+  tables: MappingTableInfo[];
+}
+
+/**
+ * Lists the mapping tables, creating the folder if needed.
+ *
+ * @returns The folder and the tables in it, including any that failed to load
+ * @throws Error string if the folder can't be created or read
+ */
+export async function listMappingTables(): Promise<MappingTables> {
+  return await invoke<MappingTables>("list_mapping_tables");
+}
+
+/**
+ * Gets a mapping table's entries.
+ *
+ * @param id - Identifier of the table
+ * @returns The table
+ * @throws Error string if the table doesn't exist or can't be loaded
+ */
+export async function getMappingTable(id: string): Promise<MappingTable> {
+  return await invoke<MappingTable>("get_mapping_table", { id });
+}
+
+/**
+ * Imports a mapping table from a CSV file with a header row.
+ *
+ * Columns named `source` and `target` (or `local` and `standard`) are used if
+ * there are any, and the first two columns otherwise. A table with the same
+ * identifier is replaced.
+ *
+ * @param path - CSV file to import
+ * @param id - Identifier to save the table as; the file name if not given
+ * @returns The imported table
+ * @throws Error string if the file can't be read or lists a code twice
+ */
+export async function importMappingTable(
+  path: string,
+  id?: string,
+): Promise<MappingTableInfo> {
+  return await invoke<MappingTableInfo>("import_mapping_table", {
+    path,
+    id: id ?? null,
+  });
+}
+
+/**
+ * Deletes a mapping table.
+ *
+ * @param id - Identifier of the table
+ * @throws Error string if the table doesn't exist or can't be deleted
+ */
+export async function deleteMappingTable(id: string): Promise<void> {
+  await invoke("delete_mapping_table", { id });
+}
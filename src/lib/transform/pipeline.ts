@@ -0,0 +1,119 @@
+/**
+ * Bridge module for transformation pipelines.
+ *
+ * A pipeline is a TOML or JSON file in the `pipelines` folder of the data
+ * folder, listing operations to apply to a message in order: set a field, map
+ * a value through a table (inline, or one of the mapping tables in
+ * `mapping.ts`), copy a field, delete a segment, or replace with a regular
+ * expression. Pipelines run on the message in the editor or on every
+ * message in a folder, the way an interface engine transforms messages on
+ * their way through.
+ *
+ * Pipelines are read from disk each time they're listed or run, so edits to a
+ * pipeline file take effect on the next run.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * A pipeline found in the pipelines folder.
+ *
+ * Mirrors the Rust `PipelineInfo` struct from
+ * `src-tauri/src/commands/transform/pipeline.rs`.
+ */
+export interface PipelineInfo {
+  /** Identifier used to select the pipeline (file name without extension) */
+  id: string;
+  /** Name from the pipeline, or the identifier if it has none */
+  name: string;
+  /** Description from the pipeline, if any */
+  description: string | null;
+  /** Number of steps in the pipeline */
+  steps: number;
+  /** Why the pipeline couldn't be loaded, if it couldn't */
+  error: string | null;
+}
+
+/**
+ * The pipelines folder and the pipelines in it.
+ */
+export interface Pipelines {
+  /** Absolute path of the pipelines folder */
+  directory: string;
+  /** Pipelines found in the folder, sorted by identifier */
+  pipelines: PipelineInfo[];
+}
+
+/**
+ * Outcome of running a pipeline on one file of a folder.
+ */
+export interface PipelineFileResult {
+  /** Path of the input file */
+  file: string;
+  /** Path the transformed message was written to, if it was */
+  output: string | null;
+  /** Whether the pipeline changed the message */
+  changed: boolean;
+  /** Why the file couldn't be transformed, if it couldn't */
+  error: string | null;
+}
+
+/**
+ * Outcome of running a pipeline on a folder.
+ */
+export interface PipelineFolderReport {
+  /** Per-file results, sorted by path */
+  files: PipelineFileResult[];
+  /** Number of files transformed and written */
+  transformed: number;
+  /** Number of files that failed */
+  failed: number;
+}
+
+/**
+ * Lists the pipelines, creating the pipelines folder if needed.
+ *
+ * @returns The pipelines folder and the pipelines in it, including any that
+ *   failed to load (with the reason)
+ * @throws Error string if the folder can't be created or read
+ */
+export async function listPipelines(): Promise<Pipelines> {
+  return await invoke<Pipelines>("list_pipelines");
+}
+
+/**
+ * Runs a pipeline on a message.
+ *
+ * @param message - Raw HL7 message string
+ * @param pipeline - Identifier of the pipeline to run
+ * @returns The transformed message
+ * @throws Error string if the pipeline can't be loaded or one of its steps fails
+ */
+export async function runPipeline(message: string, pipeline: string): Promise<string> {
+  return await invoke<string>("run_pipeline", { message, pipeline });
+}
+
+/**
+ * Runs a pipeline on every `.hl7` and `.txt` file in a folder.
+ *
+ * Transformed messages are written to the output folder under their original
+ * names. Files that fail are reported rather than stopping the run.
+ *
+ * @param pipeline - Identifier of the pipeline to run
+ * @param input - Folder of messages to transform
+ * @param output - Folder to write the transformed messages to
+ * @returns What happened to each file
+ * @throws Error string if the pipeline can't be loaded or a folder can't be
+ *   read or created
+ */
+export async function runPipelineOnFolder(
+  pipeline: string,
+  input: string,
+  output: string,
+): Promise<PipelineFolderReport> {
+  return await invoke<PipelineFolderReport>("run_pipeline_on_folder", {
+    pipeline,
+    input,
+    output,
+  });
+}
@@ -0,0 +1,60 @@
+/**
+ * Bridge module for the PHI exposure report.
+ *
+ * Lists the protected health information a message would disclose, based on
+ * the `classification` of each field in the schema, as a quick privacy check
+ * before sharing a file outside the organisation. Populated fields the schema
+ * hasn't classified are returned separately as coverage warnings, since the
+ * report can't vouch for them either way.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+
+/**
+ * A populated field or component classified as PHI.
+ */
+export interface PhiElement {
+  /** Field path (e.g., "PID.5.1", "PID.3[2]", "NK1[2].2.1") */
+  path: string;
+  /** Schema name of the field */
+  name: string;
+  /** Decoded value found in the message */
+  value: string;
+  /** Character range in the message for highlighting [start, end] */
+  range: [number, number];
+}
+
+/**
+ * A populated field or component with no classification in the schema.
+ */
+export interface UnclassifiedField {
+  /** Field path (e.g., "PID.20", "ZPI.1") */
+  path: string;
+  /** Schema name of the field, if the schema describes it at all */
+  name: string | null;
+  /** Character range in the message for highlighting [start, end] */
+  range: [number, number];
+}
+
+/**
+ * Everything a message would disclose, as far as the schema can tell.
+ */
+export interface PhiExposureReport {
+  /** Populated PHI fields, in message order */
+  phi: PhiElement[];
+  /** Populated fields with no classification, in message order */
+  unclassified: UnclassifiedField[];
+}
+
+/**
+ * Reports which PHI elements a message contains.
+ *
+ * @param message - HL7 message to check
+ * @returns Populated PHI fields and unclassified populated fields
+ * @throws Error string if the message can't be parsed
+ */
+export async function reportPhiExposure(
+  message: string,
+): Promise<PhiExposureReport> {
+  return await invoke<PhiExposureReport>("report_phi_exposure", { message });
+}
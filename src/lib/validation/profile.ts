@@ -0,0 +1,71 @@
+/**
+ * Bridge module for custom validation profiles.
+ *
+ * A validation profile is a TOML or JSON file in the `validation-profiles`
+ * folder of the data folder, holding site-specific rules (required fields,
+ * value sets, conditional rules) from an interface spec that the built-in
+ * schema can't express. Validating with a profile runs the profile's chosen
+ * built-in checks first, then its rules.
+ *
+ * Profiles are read from disk each time they're listed or used, so edits to a
+ * profile file take effect on the next validation.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import type { ValidationResult } from "./validate";
+
+/**
+ * A profile found in the profiles folder.
+ *
+ * Mirrors the Rust `ValidationProfileInfo` struct from
+ * `src-tauri/src/commands/validation/profile.rs`.
+ */
+export interface ValidationProfileInfo {
+  /** Identifier used to select the profile (file name without extension) */
+  id: string;
+  /** Name from the profile, or the identifier if it has none */
+  name: string;
+  /** Description from the profile, if any */
+  description: string | null;
+  /** Why the profile couldn't be loaded, if it couldn't */
+  error: string | null;
+}
+
+/**
+ * The profiles folder and the profiles in it.
+ */
+export interface ValidationProfiles {
+  /** Absolute path of the profiles folder */
+  directory: string;
+  /** Profiles found in the folder, sorted by identifier */
+  profiles: ValidationProfileInfo[];
+}
+
+/**
+ * Lists the validation profiles, creating the profiles folder if needed.
+ *
+ * @returns The profiles folder and the profiles in it, including any that
+ *   failed to load (with the reason)
+ * @throws Error string if the folder can't be created or read
+ */
+export async function listValidationProfiles(): Promise<ValidationProfiles> {
+  return await invoke<ValidationProfiles>("list_validation_profiles");
+}
+
+/**
+ * Validates a message against a validation profile.
+ *
+ * @param message - The HL7 message to validate
+ * @param profile - Identifier of the profile to use
+ * @returns Issues from the profile's built-in checks and its own rules
+ * @throws Error string if the profile doesn't exist or can't be loaded
+ */
+export async function validateWithProfile(
+  message: string,
+  profile: string,
+): Promise<ValidationResult> {
+  return await invoke<ValidationResult>("validate_with_profile", {
+    message,
+    profile,
+  });
+}
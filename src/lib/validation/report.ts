@@ -0,0 +1,46 @@
+/**
+ * Bridge module for exporting validation results as reports.
+ *
+ * Reports identify the message (type, control ID, version, sending and
+ * receiving applications), give the summary counts, and list every issue, so
+ * validation evidence can be attached to a vendor ticket instead of a
+ * screenshot of the issues panel.
+ */
+
+import { invoke } from "@tauri-apps/api/core";
+import type { ValidationResult } from "./validate";
+
+/**
+ * Output format for a validation report.
+ *
+ * Mirrors the Rust `ReportFormat` enum from
+ * `src-tauri/src/commands/validation/report.rs`.
+ *
+ * - `markdown`: document with tables, for tickets and wikis
+ * - `html`: standalone page
+ * - `csv`: one row per issue, each with the message's control ID and type
+ */
+export type ReportFormat = "markdown" | "html" | "csv";
+
+/**
+ * Renders a validation result as a report.
+ *
+ * @param message - The message that was validated
+ * @param result - Result of validating it
+ * @param format - Report format
+ * @param fileName - Name of the message's file, if it has been saved
+ * @returns The report's content, ready to be written to a file
+ */
+export async function exportValidationReport(
+  message: string,
+  result: ValidationResult,
+  format: ReportFormat,
+  fileName: string | null,
+): Promise<string> {
+  return invoke<string>("export_validation_report", {
+    message,
+    result,
+    format,
+    fileName,
+  });
+}
@@ -6,6 +6,7 @@
  */
 
 import { invoke } from "@tauri-apps/api/core";
+import type { CursorRange } from "$lib/shared/data";
 
 /**
  * Severity level for validation issues.
@@ -18,12 +19,20 @@ export type Severity = "error" | "warning" | "info";
 export type ValidationRule =
   | "parse_error"
   | "required_field"
+  | "forbidden_field"
   | "min_length"
   | "max_length"
   | "pattern"
   | "allowed_values"
   | "required_segment"
-  | "invalid_date";
+  | "invalid_date"
+  | "document_consistency"
+  | "segment_order"
+  | "segment_cardinality"
+  | "unexpected_segment"
+  | "duplicate_control_id"
+  | "trailing_delimiters"
+  | "extension";
 
 /**
  * A single validation issue found in the message.
@@ -41,6 +50,34 @@ export interface ValidationIssue {
   rule: ValidationRule;
   /** The actual value that caused the issue (if applicable) */
   actual_value: string | null;
+  /** Machine-applicable fix replacing the text at `range`, if there is one */
+  fix: QuickFix | null;
+}
+
+/**
+ * A fix for a validation issue, replacing the text in the issue's range.
+ *
+ * Mirrors the Rust `QuickFix` struct from `commands/validation/fixes.rs`.
+ */
+export interface QuickFix {
+  /** What the fix does, for display (e.g., "Change to 'F'") */
+  title: string;
+  /** Text to put in place of the issue's range */
+  replacement: string;
+}
+
+/**
+ * A message after fixes were applied to it.
+ *
+ * Mirrors the Rust `FixResult` struct from `commands/validation/fixes.rs`.
+ */
+export interface FixResult {
+  /** The fixed message */
+  message: string;
+  /** Where each replacement now is in the fixed message, in message order */
+  ranges: CursorRange[];
+  /** Fixable issues left unfixed because they overlapped an applied fix */
+  skipped: number;
 }
 
 /**
@@ -87,6 +124,36 @@ export async function validateLight(message: string): Promise<ValidationResult>
   return await invoke("validate_light", { message });
 }
 
+/**
+ * The result of a validation scheduled with `scheduleValidation`, delivered by
+ * the `validation-result` event.
+ *
+ * Mirrors the Rust `ScheduledValidation` struct from
+ * `src-tauri/src/commands/validation/debounce.rs`.
+ */
+export interface ScheduledValidation {
+  /** Ticket `scheduleValidation` returned for the message */
+  ticket: number;
+  /** Light validation result */
+  result: ValidationResult;
+}
+
+/**
+ * Light-validate a message in the background once edits stop for a moment.
+ *
+ * Call on every edit: each call cancels the validation scheduled before, so a
+ * burst of typing is validated once, off the UI thread. The result arrives as
+ * a `validation-result` event carrying the returned ticket; results with any
+ * other ticket are for an earlier message.
+ *
+ * @param message - The HL7 message to validate
+ * @param delayMs - How long to wait for further edits (500ms if not given)
+ * @returns The ticket the result's event will carry
+ */
+export async function scheduleValidation(message: string, delayMs?: number): Promise<number> {
+  return await invoke("schedule_validation", { message, delayMs });
+}
+
 /**
  * Perform full validation (comprehensive, for on-demand checking).
  *
@@ -94,22 +161,93 @@ export async function validateLight(message: string): Promise<ValidationResult>
  * - Length limits (minlength, maxlength)
  * - Pattern matching
  * - Allowed values
- * - Message structure (required segments)
+ * - Message structure (required segments, segment order and cardinality)
  * - Date/datetime format validation
+ * - Checks by extensions registered as validation providers
+ *
+ * Runs in the background, emitting `validation-progress` events as segments
+ * are checked. Stop it with `cancelFullValidation`; starting another full
+ * validation also cancels the one running.
  *
  * @param message - The HL7 message to validate
- * @returns Validation result with issues and summary
+ * @returns Validation result with issues and summary, or null if cancelled
  *
  * @example
  * const result = await validateFull(message);
- * for (const issue of result.issues) {
+ * for (const issue of result?.issues ?? []) {
  *   console.log(`${issue.severity}: ${issue.path} - ${issue.message}`);
  * }
  */
-export async function validateFull(message: string): Promise<ValidationResult> {
+export async function validateFull(message: string): Promise<ValidationResult | null> {
   return await invoke("validate_full", { message });
 }
 
+/**
+ * How far a full validation has got.
+ *
+ * Mirrors the Rust `ValidationProgress` struct.
+ */
+export interface ValidationProgress {
+  /** Segments checked so far */
+  validated: number;
+  /** Segments in the message */
+  total: number;
+}
+
+/**
+ * Stops the full validation running, if any; its `validateFull` returns null.
+ */
+export async function cancelFullValidation(): Promise<void> {
+  await invoke("cancel_full_validation");
+}
+
+/**
+ * Light-validate a message, re-checking only the segments that changed.
+ *
+ * Issues of unchanged segments are carried over from the previous result,
+ * moved to where their segment now starts, so edits made by extensions can
+ * be highlighted at once rather than after the next debounced validation.
+ * Falls back to validating the whole message if segments were added or
+ * removed, or MSH changed.
+ *
+ * @param previousMessage - The message as it was last light-validated
+ * @param previous - The light validation result for `previousMessage`
+ * @param message - The message to validate
+ * @returns The same result `validateLight(message)` would give
+ */
+export async function revalidateLight(
+  previousMessage: string,
+  previous: ValidationResult,
+  message: string,
+): Promise<ValidationResult> {
+  return await invoke("revalidate_light", { previousMessage, previous, message });
+}
+
+/**
+ * Apply the fix of one validation issue.
+ *
+ * @param message - The message the issue was found in
+ * @param issue - The issue, as returned by validation
+ * @returns The fixed message and where the replacement is
+ * @throws If the issue has no fix, or the message changed since it was validated
+ */
+export async function applyFix(message: string, issue: ValidationIssue): Promise<FixResult> {
+  return await invoke("apply_fix", { message, issue });
+}
+
+/**
+ * Apply every fix full validation finds in a message.
+ *
+ * Fixes overlapping one already applied are skipped; validating the result
+ * again finds them.
+ *
+ * @param message - The message to fix
+ * @returns The fixed message and where each replacement is
+ */
+export async function applyAllFixes(message: string): Promise<FixResult> {
+  return await invoke("apply_all_fixes", { message });
+}
+
 /**
  * Get validation highlights for syntax highlighting.
  *
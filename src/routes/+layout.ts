@@ -17,6 +17,8 @@ export const load: LayoutLoad = async () => {
       {
         message: string;
         unread: boolean;
+        savedPath?: string;
+        replyRule?: string;
       }[]
     >([]),
   };
@@ -8,15 +8,24 @@
  * ## Settings Persistence Flow
  *
  * 1. At app startup, Settings instance is created
- * 2. Constructor asynchronously loads settings.json using Tauri store plugin
+ * 2. Constructor asynchronously loads settings.json from the data folder using
+ *    Tauri store plugin
  * 3. Store values override the default values defined in private fields
  * 4. Settings object is made available to components via context or props
  * 5. When user changes a setting (e.g., sets sendPort = 3000):
  *    - Setter updates the private field
  *    - Setter calls store.set() to persist to disk
- *    - Tauri store plugin auto-saves to AppData/settings.json
+ *    - Tauri store plugin auto-saves to <data folder>/settings.json
  * 6. On next app launch, saved values are loaded from disk
  *
+ * ## Data Folder
+ *
+ * The store lives in the data folder resolved by the backend, which is the app
+ * data directory unless portable mode or a chosen folder says otherwise (see
+ * `$lib/shared/data_root`). When the folder is switched, the backend emits
+ * "data-root-changed" and the settings are reloaded from the new folder, with
+ * every change callback fired as if the app had just started.
+ *
  * ## Why Async Constructor?
  *
  * Tauri store loading is asynchronous because it involves IPC with the Rust
@@ -52,7 +61,11 @@
  * - sendHostname: "127.0.0.1" (localhost testing)
  * - sendPort: 2575 (standard HL7 MLLP port)
  * - listenPort: 2575 (standard HL7 MLLP port for listen server)
+ * - listenHost: "" (every IPv4 interface)
  * - tabsFollowCursor: true (better UX for most users)
+ * - tabSkipsEmptyFields: false (Tab visits every field, as before)
+ * - tabWrapsAround: false (Tab stops at the end of the message)
+ * - highlightEscapes: true (escape sequences stand out from the text around them)
  * - autoSaveEnabled: false (explicit opt-in, matches VS Code default)
  * - themeSetting: "auto" (follows system preference, most intuitive default)
  * - editorHeight: 200px (fits typical screen layouts)
@@ -60,11 +73,50 @@
  * - commDrawerHeight: 320px (comfortable height for send/listen tabs)
  * - commDrawerTab: "send" (most common workflow starts with sending)
  * - recentFiles: [] (empty list, populated as user opens files)
+ * - sendConfirmProd: true (sends to production presets must be confirmed)
+ * - sendRetry: a single attempt (retries are opted into per machine)
+ * - listenEncoding: "utf8" (most systems send UTF-8)
+ * - listenFraming: standard MLLP (0B ... 1C 0D)
+ * - listenProtocol: "mllp" (HTTP is for engines that POST v2 messages)
+ * - listenAutoSave: off (received messages are only kept in memory)
+ * - listenReplyRules: [] (every message is answered with an ACK)
+ * - dropWatch: no directory, `.hl7` files, left in place once picked up
+ * - auditExport: disabled (nothing leaves the machine unless configured)
+ * - validationProfile: "" (full validation uses the built-in schema only)
+ * - shutdownTimeoutSeconds: 10 (long enough to flush queues, short enough to quit)
+ * - templateVariables: {} (only the built-in placeholders are filled in)
  */
 
 import { load, type Store } from "@tauri-apps/plugin-store";
+import { listenEvent } from "$lib/shared/events";
 import { error as logError } from "@tauri-apps/plugin-log";
 import type { ConnectionPreset } from "$lib/communication/connection_preset";
+import {
+  DEFAULT_DROP_WATCH,
+  DEFAULT_LISTEN_AUTO_SAVE,
+  type DropWatchSettings,
+  type ListenAutoSaveSettings,
+  type ListenProtocol,
+  type ReplyRule,
+} from "$lib/communication/listen";
+import {
+  DEFAULT_AUDIT_EXPORT,
+  type AuditExportSettings,
+} from "$lib/communication/audit_export";
+import {
+  DEFAULT_RETRY_POLICY,
+  type RetryPolicy,
+} from "$lib/communication/send_receive";
+import {
+  STANDARD_FRAMING,
+  type Framing,
+  type MessageEncoding,
+} from "$lib/communication/wire_format";
+import {
+  getDataRoot,
+  storePath,
+  type DataRoot,
+} from "$lib/shared/data_root";
 
 /**
  * Configuration for a third-party extension.
@@ -87,6 +139,27 @@ export interface ExtensionConfig {
 
   /** Whether the extension is enabled. Disabled extensions are not started. */
   enabled?: boolean;
+
+  /** Values of the settings the extension declares, by key. */
+  settings?: Record<string, unknown>;
+
+  /**
+   * How the extension exchanges messages with Hermes: over stdin and stdout
+   * (the default), or a TCP or WebSocket connection it makes to Hermes.
+   */
+  transport?: "stdio" | "tcp" | "websocket";
+
+  /** Port to listen on for the socket transports; any free port if unset. */
+  port?: number;
+
+  /**
+   * Whether to record every message exchanged with the extension, for
+   * debugging it.
+   */
+  devMode?: boolean;
+
+  /** Number of log entries to keep for the extension; 1000 if unset. */
+  logCapacity?: number;
 }
 
 export class Settings {
@@ -94,6 +167,9 @@ export class Settings {
 
   // Message editor preferences
   private _tabsFollowCursor: boolean = true;
+  private _tabSkipsEmptyFields: boolean = false;
+  private _tabWrapsAround: boolean = false;
+  private _highlightEscapes: boolean = true;
   private _editorHeight: number = 200;
   private _autoSaveEnabled: boolean = false;
 
@@ -103,6 +179,9 @@ export class Settings {
   // Callback to notify when auto-save setting changes (for menu updates)
   onAutoSaveChanged: ((enabled: boolean) => void) | null = null;
 
+  // Callback to notify when escape highlighting changes (for re-highlighting the editor)
+  onHighlightEscapesChanged: ((enabled: boolean) => void) | null = null;
+
   // Callback to notify when zoom level changes (for applying zoom to webview)
   onZoomChanged: ((zoomLevel: number) => void) | null = null;
 
@@ -118,6 +197,8 @@ export class Settings {
   private _sendTransformControlId: boolean = true;
   private _sendTransformTimestamp: boolean = true;
   private _sendWaitTimeoutSeconds: number = 5;
+  private _sendConfirmProd: boolean = true;
+  private _sendRetry: RetryPolicy = DEFAULT_RETRY_POLICY;
 
   // Recent files list (most recent first, max 10 entries)
   private _recentFiles: string[] = [];
@@ -128,8 +209,15 @@ export class Settings {
   // Communication drawer settings
   private _commDrawerVisible: boolean = false;
   private _commDrawerHeight: number = 320;
-  private _commDrawerTab: "send" | "listen" = "send";
+  private _commDrawerTab: "send" | "repeat" | "listen" | "relay" = "send";
   private _listenPort: number = 2575;
+  private _listenHost: string = "";
+  private _listenEncoding: MessageEncoding = "utf8";
+  private _listenFraming: Framing = STANDARD_FRAMING;
+  private _listenProtocol: ListenProtocol = "mllp";
+  private _listenAutoSave: ListenAutoSaveSettings = DEFAULT_LISTEN_AUTO_SAVE;
+  private _listenReplyRules: ReplyRule[] = [];
+  private _dropWatch: DropWatchSettings = DEFAULT_DROP_WATCH;
 
   /**
    * Callback to sync SendTab's local state after settings load from disk.
@@ -158,6 +246,28 @@ export class Settings {
   onConnectionPresetsChanged: ((presets: ConnectionPreset[]) => void) | null =
     null;
 
+  // Export of send/receive/ACK audit events to a syslog or OTLP collector
+  private _auditExport: AuditExportSettings = DEFAULT_AUDIT_EXPORT;
+
+  // Callback to notify when audit export settings change (for reconfiguring the backend)
+  onAuditExportChanged: ((settings: AuditExportSettings) => void) | null =
+    null;
+
+  // Validation profile used by full validation ("" for none)
+  private _validationProfile: string = "";
+
+  // How long quitting waits for background work before exiting anyway
+  private _shutdownTimeoutSeconds: number = 10;
+
+  // Values of user-defined `{name}` placeholders, filled in at generation and send time
+  private _templateVariables: Record<string, string> = {};
+
+  // Callback to notify when the shutdown timeout changes (for reconfiguring the backend)
+  onShutdownTimeoutChanged: ((seconds: number) => void) | null = null;
+
+  // HTTPS URL of the extension registry index to install extensions from ("" for none)
+  private _extensionIndexUrl: string = "";
+
   // Extension configurations for third-party plugins
   // TODO: Phase 4 will add a settings UI for managing extensions
   private _extensions: ExtensionConfig[] = [];
@@ -172,21 +282,46 @@ export class Settings {
   onExtensionsChanged: ((extensions: ExtensionConfig[]) => void) | null = null;
 
   /**
-   * Initializes settings by loading from persistent store.
+   * Initializes settings by loading from persistent store, and reloads them
+   * whenever the data folder is switched.
+   */
+  constructor() {
+    this.reload();
+    listenEvent("data-root-changed", (event) => {
+      this.reload(event.payload);
+    }).catch((error) => {
+      console.error("Error listening for data folder changes:", error);
+    });
+  }
+
+  /**
+   * Loads settings.json from the data folder.
    *
-   * Loads settings.json with autoSave enabled, meaning every store.set() call
+   * Loads the store with autoSave enabled, meaning every store.set() call
    * automatically triggers a save to disk. Falls back to defaults if the store
    * fails to load or if individual settings are not present in the file.
+   *
+   * @param root - Data folder to load from; fetched from the backend if omitted
    */
-  constructor() {
-    load("settings.json", {
-      autoSave: true,
-    })
+  reload(root?: DataRoot): Promise<void> {
+    // stop writing to the old store while the new one loads
+    this.store = null;
+
+    return (root ? Promise.resolve(root) : getDataRoot())
+      .then((root) => storePath(root, "settings.json"))
+      .then((path) =>
+        load(path, {
+          autoSave: true,
+        }),
+      )
       .then((store) => {
         this.store = store;
 
         return Promise.all([
           store.get<boolean>("tabsFollowCursor"),
+          store.get<boolean>("tabSkipsEmptyFields"),
+          store.get<boolean>("tabWrapsAround"),
+          store.get<boolean>("highlightEscapes"),
           store.get<number>("editorHeight"),
           store.get<boolean>("autoSaveEnabled"),
           store.get<"light" | "dark" | "auto">("themeSetting"),
@@ -195,19 +330,36 @@ export class Settings {
           store.get<boolean>("sendTransformControlId"),
           store.get<boolean>("sendTransformTimestamp"),
           store.get<number>("sendWaitTimeoutSeconds"),
+          store.get<boolean>("sendConfirmProd"),
+          store.get<RetryPolicy>("sendRetry"),
           store.get<string[]>("recentFiles"),
           store.get<boolean>("commDrawerVisible"),
           store.get<number>("commDrawerHeight"),
-          store.get<"send" | "listen">("commDrawerTab"),
+          store.get<"send" | "repeat" | "listen" | "relay">("commDrawerTab"),
           store.get<number>("listenPort"),
+          store.get<string>("listenHost"),
+          store.get<MessageEncoding>("listenEncoding"),
+          store.get<Framing>("listenFraming"),
+          store.get<ListenProtocol>("listenProtocol"),
+          store.get<ListenAutoSaveSettings>("listenAutoSave"),
+          store.get<ReplyRule[]>("listenReplyRules"),
+          store.get<DropWatchSettings>("dropWatch"),
           store.get<number>("zoomLevel"),
           store.get<ConnectionPreset[]>("connectionPresets"),
+          store.get<AuditExportSettings>("auditExport"),
+          store.get<string>("validationProfile"),
+          store.get<number>("shutdownTimeoutSeconds"),
+          store.get<Record<string, string>>("templateVariables"),
+          store.get<string>("extensionIndexUrl"),
           store.get<ExtensionConfig[]>("extensions"),
         ]);
       })
       .then(
         ([
           tabsFollowCursor,
+          tabSkipsEmptyFields,
+          tabWrapsAround,
+          highlightEscapes,
           editorHeight,
           autoSaveEnabled,
           themeSetting,
@@ -216,16 +368,33 @@ export class Settings {
           sendTransformControlId,
           sendTransformTimestamp,
           sendWaitTimeoutSeconds,
+          sendConfirmProd,
+          sendRetry,
           recentFiles,
           commDrawerVisible,
           commDrawerHeight,
           commDrawerTab,
           listenPort,
+          listenHost,
+          listenEncoding,
+          listenFraming,
+          listenProtocol,
+          listenAutoSave,
+          listenReplyRules,
+          dropWatch,
           zoomLevel,
           connectionPresets,
+          auditExport,
+          validationProfile,
+          shutdownTimeoutSeconds,
+          templateVariables,
+          extensionIndexUrl,
           extensions,
         ]) => {
           this._tabsFollowCursor = tabsFollowCursor ?? true;
+          this._tabSkipsEmptyFields = tabSkipsEmptyFields ?? false;
+          this._tabWrapsAround = tabWrapsAround ?? false;
+          this._highlightEscapes = highlightEscapes ?? true;
           this._editorHeight = editorHeight ?? 200;
           this._autoSaveEnabled = autoSaveEnabled ?? false;
           this._themeSetting = themeSetting ?? "auto";
@@ -234,13 +403,30 @@ export class Settings {
           this._sendTransformControlId = sendTransformControlId ?? true;
           this._sendTransformTimestamp = sendTransformTimestamp ?? true;
           this._sendWaitTimeoutSeconds = sendWaitTimeoutSeconds ?? 5;
+          this._sendConfirmProd = sendConfirmProd ?? true;
+          this._sendRetry = { ...DEFAULT_RETRY_POLICY, ...sendRetry };
           this._recentFiles = recentFiles ?? [];
           this._commDrawerVisible = commDrawerVisible ?? false;
           this._commDrawerHeight = commDrawerHeight ?? 320;
           this._commDrawerTab = commDrawerTab ?? "send";
           this._listenPort = listenPort ?? 2575;
+          this._listenHost = listenHost ?? "";
+          this._listenEncoding = listenEncoding ?? "utf8";
+          this._listenFraming = listenFraming ?? STANDARD_FRAMING;
+          this._listenProtocol = listenProtocol ?? "mllp";
+          this._listenAutoSave = {
+            ...DEFAULT_LISTEN_AUTO_SAVE,
+            ...listenAutoSave,
+          };
+          this._listenReplyRules = listenReplyRules ?? [];
+          this._dropWatch = { ...DEFAULT_DROP_WATCH, ...dropWatch };
           this._zoomLevel = zoomLevel ?? 1.0;
           this._connectionPresets = connectionPresets ?? [];
+          this._auditExport = { ...DEFAULT_AUDIT_EXPORT, ...auditExport };
+          this._validationProfile = validationProfile ?? "";
+          this._shutdownTimeoutSeconds = shutdownTimeoutSeconds ?? 10;
+          this._templateVariables = templateVariables ?? {};
+          this._extensionIndexUrl = extensionIndexUrl ?? "";
           this._extensions = extensions ?? [];
 
           // Notify listeners that settings are loaded (for initial menu population)
@@ -253,6 +439,9 @@ export class Settings {
           if (this.onThemeChanged) {
             this.onThemeChanged(this._themeSetting);
           }
+          if (this.onHighlightEscapesChanged) {
+            this.onHighlightEscapesChanged(this._highlightEscapes);
+          }
           if (this.onSendSettingsChanged) {
             this.onSendSettingsChanged(
               this._sendHostname,
@@ -269,6 +458,12 @@ export class Settings {
           if (this.onConnectionPresetsChanged) {
             this.onConnectionPresetsChanged(this._connectionPresets);
           }
+          if (this.onAuditExportChanged) {
+            this.onAuditExportChanged(this._auditExport);
+          }
+          if (this.onShutdownTimeoutChanged) {
+            this.onShutdownTimeoutChanged(this._shutdownTimeoutSeconds);
+          }
           if (this.onExtensionsChanged) {
             this.onExtensionsChanged(this._extensions);
           }
@@ -301,6 +496,54 @@ export class Settings {
     }
   }
 
+  /** Whether Tab/Shift+Tab in the message editor skips over empty fields */
+  get tabSkipsEmptyFields(): boolean {
+    return this._tabSkipsEmptyFields;
+  }
+  set tabSkipsEmptyFields(value: boolean) {
+    console.debug("Setting tabSkipsEmptyFields to:", value);
+    this._tabSkipsEmptyFields = value;
+    if (this.store) {
+      this.store.set("tabSkipsEmptyFields", value).catch((error) => {
+        console.error("Error saving tabSkipsEmptyFields setting:", error);
+        logError("Failed to save tabSkipsEmptyFields setting");
+      });
+    }
+  }
+
+  /** Whether Tab/Shift+Tab wraps to the other end of the message */
+  get tabWrapsAround(): boolean {
+    return this._tabWrapsAround;
+  }
+  set tabWrapsAround(value: boolean) {
+    console.debug("Setting tabWrapsAround to:", value);
+    this._tabWrapsAround = value;
+    if (this.store) {
+      this.store.set("tabWrapsAround", value).catch((error) => {
+        console.error("Error saving tabWrapsAround setting:", error);
+        logError("Failed to save tabWrapsAround setting");
+      });
+    }
+  }
+
+  /** Whether the editor marks escape sequences like `\F\` and `\X0D\` */
+  get highlightEscapes(): boolean {
+    return this._highlightEscapes;
+  }
+  set highlightEscapes(value: boolean) {
+    console.debug("Setting highlightEscapes to:", value);
+    this._highlightEscapes = value;
+    if (this.store) {
+      this.store.set("highlightEscapes", value).catch((error) => {
+        console.error("Error saving highlightEscapes setting:", error);
+        logError("Failed to save highlightEscapes setting");
+      });
+    }
+    if (this.onHighlightEscapesChanged) {
+      this.onHighlightEscapesChanged(value);
+    }
+  }
+
   /** Height of the message editor in pixels (user-resizable) */
   get editorHeight(): number {
     return this._editorHeight;
@@ -418,6 +661,24 @@ export class Settings {
     }
   }
 
+  /**
+   * Whether sending to a connection preset classified as production requires
+   * explicit confirmation first.
+   */
+  get sendConfirmProd(): boolean {
+    return this._sendConfirmProd;
+  }
+  set sendConfirmProd(value: boolean) {
+    console.debug("Setting sendConfirmProd to:", value);
+    this._sendConfirmProd = value;
+    if (this.store) {
+      this.store.set("sendConfirmProd", value).catch((error) => {
+        console.error("Error saving sendConfirmProd setting:", error);
+        logError("Failed to save sendConfirmProd setting");
+      });
+    }
+  }
+
   /**
    * Maximum seconds to wait for a response after sending an HL7 message.
    * If no response is received within this time, the send operation fails.
@@ -436,6 +697,21 @@ export class Settings {
     }
   }
 
+  /** How MLLP sends from the Send tab are retried. */
+  get sendRetry(): RetryPolicy {
+    return this._sendRetry;
+  }
+  set sendRetry(value: RetryPolicy) {
+    console.debug("Setting sendRetry to:", value);
+    this._sendRetry = value;
+    if (this.store) {
+      this.store.set("sendRetry", value).catch((error) => {
+        console.error("Error saving sendRetry setting:", error);
+        logError("Failed to save sendRetry setting");
+      });
+    }
+  }
+
   /** List of recently opened file paths (most recent first, max 10) */
   get recentFiles(): string[] {
     return this._recentFiles;
@@ -502,10 +778,10 @@ export class Settings {
   }
 
   /** Active tab in the communication drawer ("send" or "listen") */
-  get commDrawerTab(): "send" | "listen" {
+  get commDrawerTab(): "send" | "repeat" | "listen" | "relay" {
     return this._commDrawerTab;
   }
-  set commDrawerTab(value: "send" | "listen") {
+  set commDrawerTab(value: "send" | "repeat" | "listen" | "relay") {
     console.debug("Setting commDrawerTab to:", value);
     this._commDrawerTab = value;
     if (this.store) {
@@ -531,6 +807,114 @@ export class Settings {
     }
   }
 
+  /** Character encoding of messages received by the listener. */
+  get listenEncoding(): MessageEncoding {
+    return this._listenEncoding;
+  }
+  set listenEncoding(value: MessageEncoding) {
+    console.debug("Setting listenEncoding to:", value);
+    this._listenEncoding = value;
+    if (this.store) {
+      this.store.set("listenEncoding", value).catch((error) => {
+        console.error("Error saving listenEncoding setting:", error);
+        logError("Failed to save listenEncoding setting");
+      });
+    }
+  }
+
+  /** MLLP framing bytes used by the listener. */
+  get listenFraming(): Framing {
+    return this._listenFraming;
+  }
+  set listenFraming(value: Framing) {
+    console.debug("Setting listenFraming to:", value);
+    this._listenFraming = value;
+    if (this.store) {
+      this.store.set("listenFraming", value).catch((error) => {
+        console.error("Error saving listenFraming setting:", error);
+        logError("Failed to save listenFraming setting");
+      });
+    }
+  }
+
+  /**
+   * Address or interface name the listener binds to; empty for every IPv4
+   * interface, "::" for every interface.
+   */
+  get listenHost(): string {
+    return this._listenHost;
+  }
+  set listenHost(value: string) {
+    console.debug("Setting listenHost to:", value);
+    this._listenHost = value;
+    if (this.store) {
+      this.store.set("listenHost", value).catch((error) => {
+        console.error("Error saving listenHost setting:", error);
+        logError("Failed to save listenHost setting");
+      });
+    }
+  }
+
+  /** Protocol the listener receives messages over. */
+  get listenProtocol(): ListenProtocol {
+    return this._listenProtocol;
+  }
+  set listenProtocol(value: ListenProtocol) {
+    console.debug("Setting listenProtocol to:", value);
+    this._listenProtocol = value;
+    if (this.store) {
+      this.store.set("listenProtocol", value).catch((error) => {
+        console.error("Error saving listenProtocol setting:", error);
+        logError("Failed to save listenProtocol setting");
+      });
+    }
+  }
+
+  /** Whether, where, and under what names the listener saves received messages. */
+  get listenAutoSave(): ListenAutoSaveSettings {
+    return this._listenAutoSave;
+  }
+  set listenAutoSave(value: ListenAutoSaveSettings) {
+    console.debug("Setting listenAutoSave to:", value);
+    this._listenAutoSave = value;
+    if (this.store) {
+      this.store.set("listenAutoSave", value).catch((error) => {
+        console.error("Error saving listenAutoSave setting:", error);
+        logError("Failed to save listenAutoSave setting");
+      });
+    }
+  }
+
+  /** Rules answering received messages with templated responses, in order. */
+  get listenReplyRules(): ReplyRule[] {
+    return this._listenReplyRules;
+  }
+  set listenReplyRules(value: ReplyRule[]) {
+    console.debug("Setting listenReplyRules to:", value);
+    this._listenReplyRules = value;
+    if (this.store) {
+      this.store.set("listenReplyRules", value).catch((error) => {
+        console.error("Error saving listenReplyRules setting:", error);
+        logError("Failed to save listenReplyRules setting");
+      });
+    }
+  }
+
+  /** Directory, extension filter, and disposal of the drop watch. */
+  get dropWatch(): DropWatchSettings {
+    return this._dropWatch;
+  }
+  set dropWatch(value: DropWatchSettings) {
+    console.debug("Setting dropWatch to:", value);
+    this._dropWatch = value;
+    if (this.store) {
+      this.store.set("dropWatch", value).catch((error) => {
+        console.error("Error saving dropWatch setting:", error);
+        logError("Failed to save dropWatch setting");
+      });
+    }
+  }
+
   /** Zoom level as a scale factor (1.0 = 100%, 1.5 = 150%, etc.) */
   get zoomLevel(): number {
     return this._zoomLevel;
@@ -567,6 +951,92 @@ export class Settings {
     }
   }
 
+  /** Export of communication audit events to a central collector */
+  get auditExport(): AuditExportSettings {
+    return this._auditExport;
+  }
+  set auditExport(value: AuditExportSettings) {
+    console.debug("Setting auditExport to:", value);
+    this._auditExport = value;
+    if (this.store) {
+      this.store.set("auditExport", value).catch((error) => {
+        console.error("Error saving auditExport setting:", error);
+        logError("Failed to save auditExport setting");
+      });
+    }
+    if (this.onAuditExportChanged) {
+      this.onAuditExportChanged(value);
+    }
+  }
+
+  /** Identifier of the validation profile full validation uses, or "" for none */
+  get validationProfile(): string {
+    return this._validationProfile;
+  }
+  set validationProfile(value: string) {
+    console.debug("Setting validationProfile to:", value);
+    this._validationProfile = value;
+    if (this.store) {
+      this.store.set("validationProfile", value).catch((error) => {
+        console.error("Error saving validationProfile setting:", error);
+        logError("Failed to save validationProfile setting");
+      });
+    }
+  }
+
+  /** Seconds quitting waits for listeners, queues, and extensions to stop */
+  get shutdownTimeoutSeconds(): number {
+    return this._shutdownTimeoutSeconds;
+  }
+  set shutdownTimeoutSeconds(value: number) {
+    console.debug("Setting shutdownTimeoutSeconds to:", value);
+    this._shutdownTimeoutSeconds = value;
+    if (this.store) {
+      this.store.set("shutdownTimeoutSeconds", value).catch((error) => {
+        console.error("Error saving shutdownTimeoutSeconds setting:", error);
+        logError("Failed to save shutdownTimeoutSeconds setting");
+      });
+    }
+    if (this.onShutdownTimeoutChanged) {
+      this.onShutdownTimeoutChanged(value);
+    }
+  }
+
+  /**
+   * Values of user-defined placeholders, by name.
+   *
+   * `{name}` in a template or a sent message is replaced by the value of the
+   * variable called `name`, alongside built-ins like `{now-2h}` and `{seq}`.
+   */
+  get templateVariables(): Record<string, string> {
+    return this._templateVariables;
+  }
+  set templateVariables(value: Record<string, string>) {
+    console.debug("Setting templateVariables to:", value);
+    this._templateVariables = value;
+    if (this.store) {
+      this.store.set("templateVariables", value).catch((error) => {
+        console.error("Error saving templateVariables setting:", error);
+        logError("Failed to save templateVariables setting");
+      });
+    }
+  }
+
+  /** HTTPS URL of the extension registry index ("" for none) */
+  get extensionIndexUrl(): string {
+    return this._extensionIndexUrl;
+  }
+  set extensionIndexUrl(value: string) {
+    console.debug("Setting extensionIndexUrl to:", value);
+    this._extensionIndexUrl = value;
+    if (this.store) {
+      this.store.set("extensionIndexUrl", value).catch((error) => {
+        console.error("Error saving extensionIndexUrl setting:", error);
+        logError("Failed to save extensionIndexUrl setting");
+      });
+    }
+  }
+
   /**
    * Configured third-party extensions.
    *